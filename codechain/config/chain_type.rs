@@ -17,7 +17,7 @@
 use ccore::Scheme;
 use never_type::Never;
 use serde::de::{Error, Visitor};
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::str::FromStr;
 use std::{fmt, fs};
 
@@ -93,6 +93,14 @@ impl<'a> Deserialize<'a> for ChainType {
     }
 }
 
+impl Serialize for ChainType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer, {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
 impl fmt::Display for ChainType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str(match self {
@@ -127,7 +135,7 @@ impl ChainType {
             ChainType::Custom(filename) => {
                 let file = fs::File::open(filename)
                     .map_err(|e| format!("Could not load specification file at {}: {}", filename, e))?;
-                Scheme::load(file)
+                Scheme::load(file).map_err(|e| format!("Could not load specification file at {}: {}", filename, e))
             }
         }
     }
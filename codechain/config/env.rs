@@ -0,0 +1,215 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::str::FromStr;
+
+const PREFIX: &str = "CODECHAIN_";
+
+/// Environment variables that override the settings of the config file.
+///
+/// A setting is named after its TOML section and key: `port` in `[network]` is
+/// overridden by `CODECHAIN_NETWORK_PORT`.
+pub struct EnvOverrides {
+    vars: HashMap<String, String>,
+}
+
+impl EnvOverrides {
+    pub fn from_env() -> Self {
+        Self::new(env::vars())
+    }
+
+    pub fn new<I: IntoIterator<Item = (String, String)>>(vars: I) -> Self {
+        Self {
+            vars: vars.into_iter().filter(|(name, _)| name.starts_with(PREFIX)).collect(),
+        }
+    }
+
+    pub fn section<'a>(&'a self, section: &str) -> EnvSection<'a> {
+        EnvSection {
+            vars: &self.vars,
+            prefix: format!("{}{}_", PREFIX, section.to_uppercase()),
+        }
+    }
+}
+
+/// The environment variables of a TOML section.
+pub struct EnvSection<'a> {
+    vars: &'a HashMap<String, String>,
+    prefix: String,
+}
+
+impl<'a> EnvSection<'a> {
+    fn get(&self, key: &str) -> Option<(String, &'a str)> {
+        let name = format!("{}{}", self.prefix, key.to_uppercase());
+        let value = self.vars.get(&name)?;
+        Some((name, value.trim()))
+    }
+
+    /// Overrides an optional setting. An empty value unsets it.
+    pub fn read<T>(&self, key: &str, target: &mut Option<T>) -> Result<(), String>
+    where
+        T: FromStr,
+        T::Err: Display, {
+        self.read_with(key, target, |value| value.parse().map_err(|err: T::Err| err.to_string()))
+    }
+
+    /// Overrides an optional setting with a custom parser. An empty value unsets it.
+    pub fn read_with<T, F>(&self, key: &str, target: &mut Option<T>, parse: F) -> Result<(), String>
+    where
+        F: FnOnce(&str) -> Result<T, String>, {
+        if let Some((name, value)) = self.get(key) {
+            *target = if value.is_empty() {
+                None
+            } else {
+                Some(parse(value).map_err(|err| format!("Invalid value {:?} for {}: {}", value, name, err))?)
+            };
+        }
+        Ok(())
+    }
+
+    /// Overrides an optional boolean setting. An empty value unsets it.
+    pub fn read_bool(&self, key: &str, target: &mut Option<bool>) -> Result<(), String> {
+        self.read_with(key, target, parse_bool)
+    }
+
+    /// Overrides a boolean setting that always has a value.
+    pub fn read_flag(&self, key: &str, target: &mut bool) -> Result<(), String> {
+        if let Some((name, value)) = self.get(key) {
+            *target = parse_bool(value).map_err(|err| format!("Invalid value {:?} for {}: {}", value, name, err))?;
+        }
+        Ok(())
+    }
+
+    /// Overrides a map setting with a TOML inline table, such as `{ "http://127.0.0.1:8000/" = "secret" }`.
+    /// An empty value unsets it. The value isn't printed on an error, because a map can hold secrets.
+    pub fn read_map(&self, key: &str, target: &mut Option<HashMap<String, String>>) -> Result<(), String> {
+        if let Some((name, value)) = self.get(key) {
+            *target = if value.is_empty() {
+                None
+            } else {
+                let mut table: HashMap<String, HashMap<String, String>> =
+                    toml::from_str(&format!("map = {}", value))
+                        .map_err(|_| format!("Invalid value for {}: expected a TOML inline table of strings", name))?;
+                table.remove("map")
+            };
+        }
+        Ok(())
+    }
+
+    /// Overrides a list setting with comma-separated values. An empty value sets an empty list.
+    pub fn read_list(&self, key: &str, target: &mut Option<Vec<String>>) {
+        if let Some((_, value)) = self.get(key) {
+            *target = Some(
+                value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(ToString::to_string).collect(),
+            );
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" | "yes" => Ok(true),
+        "false" | "0" | "no" => Ok(false),
+        _ => Err("expected true or false".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(vars: &[(&str, &str)]) -> EnvOverrides {
+        EnvOverrides::new(vars.iter().map(|(name, value)| (name.to_string(), value.to_string())))
+    }
+
+    #[test]
+    fn variables_are_named_after_section_and_key() {
+        let env = overrides(&[("CODECHAIN_NETWORK_MAX_PEERS", "7"), ("CODECHAIN_RPC_MAX_PEERS", "9")]);
+        let mut max_peers: Option<usize> = None;
+        env.section("network").read("max_peers", &mut max_peers).unwrap();
+        assert_eq!(Some(7), max_peers);
+    }
+
+    #[test]
+    fn missing_variable_keeps_the_value() {
+        let env = overrides(&[]);
+        let mut port = Some(3485u16);
+        env.section("network").read("port", &mut port).unwrap();
+        assert_eq!(Some(3485), port);
+    }
+
+    #[test]
+    fn empty_value_unsets_an_optional_setting() {
+        let env = overrides(&[("CODECHAIN_NETWORK_WHITELIST_PATH", "")]);
+        let mut whitelist_path = Some("whitelist.txt".to_string());
+        env.section("network").read("whitelist_path", &mut whitelist_path).unwrap();
+        assert_eq!(None, whitelist_path);
+    }
+
+    #[test]
+    fn parse_error_names_the_variable() {
+        let env = overrides(&[("CODECHAIN_NETWORK_PORT", "http")]);
+        let mut port: Option<u16> = None;
+        let err = env.section("network").read("port", &mut port).unwrap_err();
+        assert!(err.contains("CODECHAIN_NETWORK_PORT"), "{}", err);
+    }
+
+    #[test]
+    fn booleans() {
+        let env = overrides(&[
+            ("CODECHAIN_RPC_DISABLE", "TRUE"),
+            ("CODECHAIN_WS_DISABLE", "0"),
+            ("CODECHAIN_IPC_DISABLE", "maybe"),
+        ]);
+        let mut disable = None;
+        env.section("rpc").read_bool("disable", &mut disable).unwrap();
+        assert_eq!(Some(true), disable);
+        env.section("ws").read_bool("disable", &mut disable).unwrap();
+        assert_eq!(Some(false), disable);
+        let mut flag = false;
+        let err = env.section("ipc").read_flag("disable", &mut flag).unwrap_err();
+        assert!(err.contains("CODECHAIN_IPC_DISABLE"), "{}", err);
+    }
+
+    #[test]
+    fn maps_are_inline_tables() {
+        let env = overrides(&[
+            ("CODECHAIN_MINING_NOTIFY_WORK_SECRETS", r#"{ "http://127.0.0.1:8000/" = "s3cr=t" }"#),
+            ("CODECHAIN_RPC_NOTIFY_WORK_SECRETS", "http://127.0.0.1:8000/=s3cr=t"),
+        ]);
+        let mut secrets = None;
+        env.section("mining").read_map("notify_work_secrets", &mut secrets).unwrap();
+        let mut expected = HashMap::new();
+        expected.insert("http://127.0.0.1:8000/".to_string(), "s3cr=t".to_string());
+        assert_eq!(Some(expected), secrets);
+
+        let err = env.section("rpc").read_map("notify_work_secrets", &mut secrets).unwrap_err();
+        assert!(err.contains("CODECHAIN_RPC_NOTIFY_WORK_SECRETS"), "{}", err);
+        assert!(!err.contains("s3cr=t"), "{}", err);
+    }
+
+    #[test]
+    fn lists_are_comma_separated() {
+        let env = overrides(&[("CODECHAIN_NETWORK_BOOTSTRAP_ADDRESSES", "1.2.3.4:3485, 5.6.7.8:3485,")]);
+        let mut addresses = None;
+        env.section("network").read_list("bootstrap_addresses", &mut addresses);
+        assert_eq!(Some(vec!["1.2.3.4:3485".to_string(), "5.6.7.8:3485".to_string()]), addresses);
+    }
+}
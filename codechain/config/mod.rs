@@ -15,21 +15,35 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod chain_type;
-
-use ccore::{MemPoolMinFees, MinerOptions, StratumConfig, TimeGapParams};
+mod env;
+mod validation;
+
+use ccore::{
+    column_by_name, DatabaseColumnConfig, DatabaseCompactionProfile, MemPoolMinFees, MinerOptions, StratumConfig,
+    TimeGapParams, COLUMN_NAMES,
+};
+use cdiscovery::Mode as DiscoveryMode;
 use cidr::IpCidr;
 use ckey::PlatformAddress;
 use clap;
-use cnetwork::{FilterEntry, NetworkConfig, SocketAddr};
+use cnetwork::{FilterEntry, NetworkConfig, NetworkSecret, SocketAddr};
+use cnode::{RpcHttpConfig, RpcIpcConfig, RpcWsConfig};
+use cstate::CacheSizes;
+use csync::SpotCheckConfig;
+use std::collections::HashMap;
 use std::fs;
+use std::path::Path;
 use std::str::{self, FromStr};
 use std::time::Duration;
 use toml;
 
 pub use self::chain_type::ChainType;
-use crate::rpc::{RpcHttpConfig, RpcIpcConfig, RpcWsConfig};
+use self::env::EnvOverrides;
+use crate::metrics::MetricsConfig;
+use crpc::validate_origin;
+use crpc::{ExecutorConfig, RequestLimits};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
     pub ipc: Ipc,
@@ -42,6 +56,8 @@ pub struct Config {
     pub snapshot: Snapshot,
     pub stratum: Stratum,
     #[serde(default)]
+    pub metrics: Metrics,
+    #[serde(default)]
     pub email_alarm: EmailAlarm,
 }
 
@@ -55,6 +71,7 @@ impl Config {
         self.ws.merge(&other.ws);
         self.snapshot.merge(&other.snapshot);
         self.stratum.merge(&other.stratum);
+        self.metrics.merge(&other.metrics);
         self.email_alarm.merge(&other.email_alarm);
     }
 
@@ -89,6 +106,7 @@ impl Config {
             self.mining.min_asset_scheme_change_cost,
             self.mining.min_asset_supply_increase_cost,
             self.mining.min_asset_unwrap_ccc_cost,
+            self.mining.min_pay_memo_byte_cost,
         );
 
         Ok(MinerOptions {
@@ -100,6 +118,7 @@ impl Config {
             mem_pool_fee_bump_shift: self.mining.mem_pool_fee_bump_shift.unwrap(),
             allow_create_shard: self.mining.allow_create_shard.unwrap_or(false),
             new_work_notify: self.mining.notify_work.clone().unwrap(),
+            new_work_notify_secrets: self.mining.notify_work_secrets.clone().unwrap_or_default(),
             force_sealing: self.mining.force_sealing.unwrap(),
             reseal_on_own_transaction,
             reseal_on_external_transaction,
@@ -108,18 +127,77 @@ impl Config {
             no_reseal_timer: self.mining.no_reseal_timer.unwrap(),
             work_queue_size: self.mining.work_queue_size.unwrap(),
             mem_pool_min_fees,
+            external_sealing: self.mining.external_sealing.unwrap_or(false),
+            pending_seal_timeout: Duration::from_millis(self.mining.pending_seal_timeout.unwrap_or(30000)),
         })
     }
 
-    pub fn rpc_http_config(&self) -> RpcHttpConfig {
+    pub fn state_cache_sizes(&self) -> CacheSizes {
+        let default = CacheSizes::default();
+        CacheSizes {
+            account: self.operating.account_cache_size.unwrap_or(default.account),
+            regular_account: self.operating.regular_account_cache_size.unwrap_or(default.regular_account),
+            shard: self.operating.shard_cache_size.unwrap_or(default.shard),
+            text: self.operating.text_cache_size.unwrap_or(default.text),
+            action_data: self.operating.action_data_cache_size.unwrap_or(default.action_data),
+            asset_scheme: self.operating.asset_scheme_cache_size.unwrap_or(default.asset_scheme),
+            asset: self.operating.asset_cache_size.unwrap_or(default.asset),
+            approver_group: self.operating.approver_group_cache_size.unwrap_or(default.approver_group),
+        }
+    }
+
+    pub fn db_compaction(&self) -> Result<DatabaseCompactionProfile, String> {
+        match &self.operating.db_compaction {
+            Some(compaction) => compaction.parse().map_err(|e| format!("codechain.db_compaction: {}", e)),
+            None => Ok(Default::default()),
+        }
+    }
+
+    pub fn db_columns(&self) -> Result<HashMap<Option<u32>, DatabaseColumnConfig>, String> {
+        let mut db_columns = HashMap::new();
+        for (name, column) in self.operating.db_columns.iter().flatten() {
+            let col = column_by_name(name).ok_or_else(|| {
+                format!(
+                    "codechain.db_columns has an unknown column {}. Expected one of {}",
+                    name,
+                    COLUMN_NAMES.join(", ")
+                )
+            })?;
+            let compaction = column
+                .compaction
+                .as_ref()
+                .map(|compaction| compaction.parse::<DatabaseCompactionProfile>())
+                .transpose()
+                .map_err(|e| format!("codechain.db_columns.{}.compaction: {}", name, e))?;
+            db_columns.insert(col, DatabaseColumnConfig {
+                memory_budget: column.memory_budget,
+                cache_index_and_filter_blocks: column.cache_index_and_filter_blocks,
+                compaction,
+            });
+        }
+        Ok(db_columns)
+    }
+
+    pub fn rpc_http_config(&self) -> Result<RpcHttpConfig, String> {
         debug_assert!(!self.rpc.disable.unwrap());
 
-        // FIXME: Add interface, cors and hosts options.
-        RpcHttpConfig {
+        validate_origins("rpc.cors", &self.rpc.cors)?;
+        Ok(RpcHttpConfig {
             interface: self.rpc.interface.clone().unwrap(),
             port: self.rpc.port.unwrap(),
             cors: self.rpc.cors.clone(),
-            hosts: self.rpc.hosts.clone(),
+            hosts: self.rpc.allowed_hosts.clone(),
+            limits: RequestLimits {
+                max_batch_len: self.rpc.max_batch_len,
+                max_execution_time: self.rpc.max_execution_time.map(Duration::from_millis),
+            },
+        })
+    }
+
+    pub fn rpc_executor_config(&self) -> ExecutorConfig {
+        ExecutorConfig {
+            workers: self.rpc.executor_workers.unwrap(),
+            queue_size: self.rpc.executor_queue_size.unwrap(),
         }
     }
 
@@ -128,18 +206,27 @@ impl Config {
 
         RpcIpcConfig {
             socket_addr: self.ipc.path.clone().unwrap(),
+            token_file: self.ipc.token_file.clone(),
+            allowed_methods: self.ipc.allowed_methods.clone(),
+            denied_methods: self.ipc.denied_methods.clone().unwrap_or_default(),
         }
     }
 
-    pub fn rpc_ws_config(&self) -> RpcWsConfig {
+    pub fn rpc_ws_config(&self) -> Result<RpcWsConfig, String> {
         debug_assert!(!self.ws.disable.unwrap());
 
-        // FIXME: Add hosts and origins options.
-        RpcWsConfig {
+        validate_origins("ws.origins", &self.ws.origins)?;
+        Ok(RpcWsConfig {
             interface: self.ws.interface.clone().unwrap(),
             port: self.ws.port.unwrap(),
             max_connections: self.ws.max_connections.unwrap(),
-        }
+            origins: self.ws.origins.clone(),
+            hosts: self.ws.allowed_hosts.clone(),
+            limits: RequestLimits {
+                max_batch_len: self.ws.max_batch_len,
+                max_execution_time: self.ws.max_execution_time.map(Duration::from_millis),
+            },
+        })
     }
 
     pub fn network_config(&self) -> Result<NetworkConfig, String> {
@@ -181,43 +268,146 @@ impl Config {
             .clone()
             .unwrap()
             .iter()
-            .map(|s| SocketAddr::from_str(s).unwrap())
-            .collect::<Vec<_>>();
+            .map(|s| SocketAddr::from_str(s).map_err(|e| format!("Invalid bootstrap address {}: {}", s, e)))
+            .collect::<Result<Vec<_>, _>>()?;
 
         let whitelist = make_ipaddr_list(self.network.whitelist_path.as_ref(), "white")?;
         let blacklist = make_ipaddr_list(self.network.blacklist_path.as_ref(), "black")?;
 
+        // The contents of the file must not be printed since they are the secret.
+        let network_secret = match &self.network.network_secret_path {
+            Some(path) => Some(
+                fs::read_to_string(path)
+                    .map_err(|e| format!("Cannot open the network secret file {:?}: {:?}", path, e))?
+                    .parse::<NetworkSecret>()
+                    .map_err(|e| format!("Invalid network secret file {:?}: {}", path, e))?,
+            ),
+            None => None,
+        };
+
         Ok(NetworkConfig {
             address: self.network.interface.clone().unwrap(),
             port: self.network.port.unwrap(),
             bootstrap_addresses,
             min_peers: self.network.min_peers.unwrap(),
             max_peers: self.network.max_peers.unwrap(),
+            compression_threshold: self.network.compression_threshold.unwrap(),
             whitelist,
             blacklist,
+            network_secret,
         })
     }
 
-    pub fn stratum_config(&self) -> StratumConfig {
+    /// `discovery = false` disables the discovery regardless of `discovery_mode`.
+    pub fn discovery_mode(&self) -> Result<DiscoveryMode, String> {
+        if !self.network.discovery.unwrap() {
+            return Ok(DiscoveryMode::Disabled)
+        }
+        match &self.network.discovery_mode {
+            Some(mode) => mode.parse(),
+            None => Ok(DiscoveryMode::Full),
+        }
+    }
+
+    pub fn spot_check_config(&self) -> SpotCheckConfig {
+        SpotCheckConfig {
+            score_threshold: self.network.sync_spot_check_threshold.unwrap().into(),
+            samples: self.network.sync_spot_check_samples.unwrap(),
+            ..Default::default()
+        }
+    }
+
+    pub fn stratum_config(&self) -> Result<StratumConfig, String> {
         debug_assert!(!self.stratum.disable.unwrap());
 
-        // FIXME: Add listen_addr and secret
-        StratumConfig {
-            listen_addr: "127.0.0.1".to_string(),
+        let secret = match &self.stratum.secret {
+            Some(secret) => Some(secret.parse().map_err(|_| format!("Invalid stratum secret: {}", secret))?),
+            None => None,
+        };
+        Ok(StratumConfig {
+            listen_addr: self.stratum.listen_address.clone().unwrap(),
             port: self.stratum.port.unwrap(),
-            secret: None,
+            secret,
+        })
+    }
+
+    /// Every error that the node finds in the config before it starts, except the ones that depend on the chain.
+    /// It reads the files that the config refers to, but doesn't open the database or bind the ports.
+    pub fn errors(&self) -> Vec<String> {
+        let mut errors = validation::errors(self);
+        let mut check = |result: Result<(), String>| {
+            if let Err(err) = result {
+                errors.push(err);
+            }
+        };
+        check(self.miner_options().map(|_| ()));
+        check(self.db_compaction().map(|_| ()));
+        check(self.db_columns().map(|_| ()));
+        if !self.rpc.disable.unwrap() {
+            check(self.rpc_http_config().map(|_| ()));
+        }
+        if !self.ws.disable.unwrap() {
+            check(self.rpc_ws_config().map(|_| ()));
+        }
+        if !self.network.disable.unwrap() {
+            check(self.network_config().map(|_| ()));
+            check(self.discovery_mode().map(|_| ()));
+        }
+        if !self.stratum.disable.unwrap() {
+            check(self.stratum_config().map(|_| ()));
+        }
+        if !self.email_alarm.disable.unwrap() {
+            if self.email_alarm.to.is_none() {
+                errors.push("email-alarm-to is not specified".to_string());
+            }
+            if self.email_alarm.sendgrid_key.is_none() {
+                errors.push("email-alarm-sendgrid-key is not specified".to_string());
+            }
+        }
+        errors
+    }
+
+    /// Moves the ports and the IPC path apart from the ones of the other instances.
+    /// It's applied to the preset only, so the values configured explicitly are kept as they are.
+    fn offset_for_instance(&mut self, instance_id: usize) -> Result<(), String> {
+        for port in vec![
+            &mut self.network.port,
+            &mut self.rpc.port,
+            &mut self.ws.port,
+            &mut self.stratum.port,
+            &mut self.metrics.port,
+        ] {
+            if let Some(port) = port.as_mut() {
+                *port = instance_port(*port, instance_id)?;
+            }
+        }
+        if let Some(path) = self.ipc.path.as_mut() {
+            *path = instance_ipc_path(path, instance_id);
+        }
+        Ok(())
+    }
+
+    pub fn metrics_config(&self) -> MetricsConfig {
+        debug_assert!(!self.metrics.disable.unwrap());
+
+        MetricsConfig {
+            interface: self.metrics.interface.clone().unwrap(),
+            port: self.metrics.port.unwrap(),
         }
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Ipc {
     pub disable: Option<bool>,
     pub path: Option<String>,
+    pub token_file: Option<String>,
+    pub allowed_methods: Option<Vec<String>>,
+    pub denied_methods: Option<Vec<String>>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Operating {
     pub quiet: Option<bool>,
@@ -227,9 +417,38 @@ pub struct Operating {
     pub keys_path: Option<String>,
     pub password_path: Option<String>,
     pub chain: Option<ChainType>,
+    pub retention_blocks: Option<u64>,
+    pub address_index: Option<bool>,
+    pub asset_index: Option<bool>,
+    pub text_index: Option<bool>,
+    pub asset_log: Option<bool>,
+    pub asset_log_retention: Option<u64>,
+    pub read_only: Option<bool>,
+    pub audit_stake: Option<bool>,
+    pub import_metrics: Option<bool>,
+    pub timer_coalescing_window: Option<u64>,
+    pub db_cache_size: Option<usize>,
+    pub db_compaction: Option<String>,
+    pub db_columns: Option<HashMap<String, DbColumn>>,
+    pub account_cache_size: Option<usize>,
+    pub regular_account_cache_size: Option<usize>,
+    pub shard_cache_size: Option<usize>,
+    pub text_cache_size: Option<usize>,
+    pub action_data_cache_size: Option<usize>,
+    pub asset_scheme_cache_size: Option<usize>,
+    pub asset_cache_size: Option<usize>,
+    pub approver_group_cache_size: Option<usize>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DbColumn {
+    pub memory_budget: Option<usize>,
+    pub cache_index_and_filter_blocks: Option<bool>,
+    pub compaction: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Mining {
     pub author: Option<PlatformAddress>,
@@ -243,12 +462,15 @@ pub struct Mining {
     pub mem_pool_fee_bump_shift: Option<usize>,
     pub allow_create_shard: Option<bool>,
     pub notify_work: Option<Vec<String>>,
+    pub notify_work_secrets: Option<HashMap<String, String>>,
     pub force_sealing: Option<bool>,
     pub reseal_on_txs: Option<String>,
     pub reseal_min_period: Option<u64>,
     pub reseal_max_period: Option<u64>,
     pub no_reseal_timer: Option<bool>,
     pub work_queue_size: Option<usize>,
+    pub external_sealing: Option<bool>,
+    pub pending_seal_timeout: Option<u64>,
     pub allowed_past_gap: Option<u64>,
     pub allowed_future_gap: Option<u64>,
     pub min_pay_transaction_cost: Option<u64>,
@@ -265,9 +487,10 @@ pub struct Mining {
     pub min_asset_scheme_change_cost: Option<u64>,
     pub min_asset_supply_increase_cost: Option<u64>,
     pub min_asset_unwrap_ccc_cost: Option<u64>,
+    pub min_pay_memo_byte_cost: Option<u64>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Network {
     pub interface: Option<String>,
@@ -276,57 +499,94 @@ pub struct Network {
     pub bootstrap_addresses: Option<Vec<String>>,
     pub min_peers: Option<usize>,
     pub max_peers: Option<usize>,
+    pub compression_threshold: Option<usize>,
     pub sync: Option<bool>,
+    pub sync_spot_check_threshold: Option<u64>,
+    pub sync_spot_check_samples: Option<usize>,
     pub transaction_relay: Option<bool>,
     pub discovery: Option<bool>,
+    pub discovery_mode: Option<String>,
     pub discovery_type: Option<String>,
     pub discovery_refresh: Option<u32>,
     pub discovery_bucket_size: Option<u8>,
     pub blacklist_path: Option<String>,
     pub whitelist_path: Option<String>,
+    pub network_secret_path: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Rpc {
     pub disable: Option<bool>,
     pub interface: Option<String>,
-    pub hosts: Option<Vec<String>>,
+    #[serde(alias = "hosts")]
+    pub allowed_hosts: Option<Vec<String>>,
     pub cors: Option<Vec<String>>,
     pub port: Option<u16>,
     #[serde(default = "default_enable_devel_api")]
     pub enable_devel_api: bool,
+    pub max_batch_len: Option<usize>,
+    pub max_execution_time: Option<u64>,
+    pub executor_workers: Option<usize>,
+    pub executor_queue_size: Option<usize>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Ws {
     pub disable: Option<bool>,
     pub interface: Option<String>,
+    pub allowed_hosts: Option<Vec<String>>,
+    pub origins: Option<Vec<String>>,
     pub port: Option<u16>,
     pub max_connections: Option<usize>,
+    pub max_batch_len: Option<usize>,
+    pub max_execution_time: Option<u64>,
+    pub max_subscriptions: Option<usize>,
+}
+
+fn validate_origins(key: &str, origins: &Option<Vec<String>>) -> Result<(), String> {
+    for origin in origins.iter().flatten() {
+        validate_origin(origin).map_err(|err| format!("{}: {}", key, err))?;
+    }
+    Ok(())
 }
 
 fn default_enable_devel_api() -> bool {
     cfg!(debug_assertions)
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Snapshot {
     pub disable: Option<bool>,
     pub path: Option<String>,
+    /// Takes a snapshot every this many blocks. The snapshot period of the chain is used if it's not set.
+    pub period: Option<u64>,
+    /// The number of the latest snapshots to keep.
+    pub keep: Option<usize>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Stratum {
     pub disable: Option<bool>,
+    pub listen_address: Option<String>,
+    pub port: Option<u16>,
+    /// The blake256 hash of the secret that workers must give to `mining.authorize`.
+    pub secret: Option<String>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Metrics {
+    pub disable: Option<bool>,
+    pub interface: Option<String>,
     pub port: Option<u16>,
 }
 
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct EmailAlarm {
     pub disable: Option<bool>,
@@ -342,6 +602,15 @@ impl Ipc {
         if other.path.is_some() {
             self.path = other.path.clone();
         }
+        if other.token_file.is_some() {
+            self.token_file = other.token_file.clone();
+        }
+        if other.allowed_methods.is_some() {
+            self.allowed_methods = other.allowed_methods.clone();
+        }
+        if other.denied_methods.is_some() {
+            self.denied_methods = other.denied_methods.clone();
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -351,11 +620,40 @@ impl Ipc {
         if let Some(path) = matches.value_of("ipc-path") {
             self.path = Some(path.to_string());
         }
+        if let Some(token_file) = matches.value_of("ipc-token-file") {
+            self.token_file = Some(token_file.to_string());
+        }
+        if let Some(allowed_methods) = matches.values_of_lossy("ipc-allowed-methods") {
+            self.allowed_methods = Some(allowed_methods);
+        }
+        if let Some(denied_methods) = matches.values_of_lossy("ipc-denied-methods") {
+            self.denied_methods = Some(denied_methods);
+        }
+        Ok(())
+    }
+
+    pub fn overwrite_with_env(&mut self, env: &EnvOverrides) -> Result<(), String> {
+        let env = env.section("ipc");
+        env.read_bool("disable", &mut self.disable)?;
+        env.read("path", &mut self.path)?;
+        env.read("token_file", &mut self.token_file)?;
+        env.read_list("allowed_methods", &mut self.allowed_methods);
+        env.read_list("denied_methods", &mut self.denied_methods);
         Ok(())
     }
 }
 
 impl Operating {
+    /// The directory that the data of the instance are stored in by default.
+    /// It's the base path for the instance 0 or no instance id, and a subdirectory of it for the others.
+    pub fn instance_path(&self) -> String {
+        let base_path = self.base_path.clone().unwrap();
+        match self.instance_id {
+            Some(instance_id) if instance_id != 0 => format!("{}/instance-{}", base_path, instance_id),
+            _ => base_path,
+        }
+    }
+
     pub fn merge(&mut self, other: &Operating) {
         if other.quiet.is_some() {
             self.quiet = other.quiet;
@@ -378,6 +676,75 @@ impl Operating {
         if other.chain.is_some() {
             self.chain = other.chain.clone();
         }
+        if other.retention_blocks.is_some() {
+            self.retention_blocks = other.retention_blocks;
+        }
+        if other.address_index.is_some() {
+            self.address_index = other.address_index;
+        }
+        if other.asset_index.is_some() {
+            self.asset_index = other.asset_index;
+        }
+        if other.text_index.is_some() {
+            self.text_index = other.text_index;
+        }
+        if other.asset_log.is_some() {
+            self.asset_log = other.asset_log;
+        }
+        if other.asset_log_retention.is_some() {
+            self.asset_log_retention = other.asset_log_retention;
+        }
+        if other.read_only.is_some() {
+            self.read_only = other.read_only;
+        }
+        if other.audit_stake.is_some() {
+            self.audit_stake = other.audit_stake;
+        }
+        if other.import_metrics.is_some() {
+            self.import_metrics = other.import_metrics;
+        }
+        if other.timer_coalescing_window.is_some() {
+            self.timer_coalescing_window = other.timer_coalescing_window;
+        }
+        if other.db_cache_size.is_some() {
+            self.db_cache_size = other.db_cache_size;
+        }
+        if other.db_compaction.is_some() {
+            self.db_compaction = other.db_compaction.clone();
+        }
+        for (name, other_column) in other.db_columns.iter().flatten() {
+            let db_columns = self.db_columns.get_or_insert_with(Default::default);
+            match db_columns.get_mut(name) {
+                Some(column) => column.merge(other_column),
+                None => {
+                    db_columns.insert(name.clone(), other_column.clone());
+                }
+            }
+        }
+        if other.account_cache_size.is_some() {
+            self.account_cache_size = other.account_cache_size;
+        }
+        if other.regular_account_cache_size.is_some() {
+            self.regular_account_cache_size = other.regular_account_cache_size;
+        }
+        if other.shard_cache_size.is_some() {
+            self.shard_cache_size = other.shard_cache_size;
+        }
+        if other.text_cache_size.is_some() {
+            self.text_cache_size = other.text_cache_size;
+        }
+        if other.action_data_cache_size.is_some() {
+            self.action_data_cache_size = other.action_data_cache_size;
+        }
+        if other.asset_scheme_cache_size.is_some() {
+            self.asset_scheme_cache_size = other.asset_scheme_cache_size;
+        }
+        if other.asset_cache_size.is_some() {
+            self.asset_cache_size = other.asset_cache_size;
+        }
+        if other.approver_group_cache_size.is_some() {
+            self.approver_group_cache_size = other.approver_group_cache_size;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -402,10 +769,86 @@ impl Operating {
         if let Some(chain) = matches.value_of("chain") {
             self.chain = Some(chain.parse().unwrap());
         }
+        if let Some(retention_blocks) = matches.value_of("retention-blocks") {
+            self.retention_blocks = Some(retention_blocks.parse().map_err(|_| "Invalid retention blocks")?);
+        }
+        if matches.is_present("address-index") {
+            self.address_index = Some(true);
+        }
+        if matches.is_present("asset-index") {
+            self.asset_index = Some(true);
+        }
+        if matches.is_present("text-index") {
+            self.text_index = Some(true);
+        }
+        if matches.is_present("asset-log") {
+            self.asset_log = Some(true);
+        }
+        if let Some(asset_log_retention) = matches.value_of("asset-log-retention") {
+            self.asset_log_retention = Some(asset_log_retention.parse().map_err(|_| "Invalid asset log retention")?);
+        }
+        if matches.is_present("read-only") {
+            self.read_only = Some(true);
+        }
+        if matches.is_present("audit-stake") {
+            self.audit_stake = Some(true);
+        }
+        if matches.is_present("import-metrics") {
+            self.import_metrics = Some(true);
+        }
+        if let Some(window) = matches.value_of("timer-coalescing-window") {
+            self.timer_coalescing_window = Some(window.parse().map_err(|_| "Invalid timer coalescing window")?);
+        }
+        Ok(())
+    }
+
+    pub fn overwrite_with_env(&mut self, env: &EnvOverrides) -> Result<(), String> {
+        let env = env.section("codechain");
+        env.read_bool("quiet", &mut self.quiet)?;
+        env.read("instance_id", &mut self.instance_id)?;
+        env.read("base_path", &mut self.base_path)?;
+        env.read("db_path", &mut self.db_path)?;
+        env.read("keys_path", &mut self.keys_path)?;
+        env.read("password_path", &mut self.password_path)?;
+        env.read_with("chain", &mut self.chain, |chain| Ok(chain.parse().unwrap()))?;
+        env.read("retention_blocks", &mut self.retention_blocks)?;
+        env.read_bool("address_index", &mut self.address_index)?;
+        env.read_bool("asset_index", &mut self.asset_index)?;
+        env.read_bool("text_index", &mut self.text_index)?;
+        env.read_bool("asset_log", &mut self.asset_log)?;
+        env.read("asset_log_retention", &mut self.asset_log_retention)?;
+        env.read_bool("read_only", &mut self.read_only)?;
+        env.read_bool("audit_stake", &mut self.audit_stake)?;
+        env.read_bool("import_metrics", &mut self.import_metrics)?;
+        env.read("timer_coalescing_window", &mut self.timer_coalescing_window)?;
+        env.read("db_cache_size", &mut self.db_cache_size)?;
+        env.read("db_compaction", &mut self.db_compaction)?;
+        env.read("account_cache_size", &mut self.account_cache_size)?;
+        env.read("regular_account_cache_size", &mut self.regular_account_cache_size)?;
+        env.read("shard_cache_size", &mut self.shard_cache_size)?;
+        env.read("text_cache_size", &mut self.text_cache_size)?;
+        env.read("action_data_cache_size", &mut self.action_data_cache_size)?;
+        env.read("asset_scheme_cache_size", &mut self.asset_scheme_cache_size)?;
+        env.read("asset_cache_size", &mut self.asset_cache_size)?;
+        env.read("approver_group_cache_size", &mut self.approver_group_cache_size)?;
         Ok(())
     }
 }
 
+impl DbColumn {
+    pub fn merge(&mut self, other: &DbColumn) {
+        if other.memory_budget.is_some() {
+            self.memory_budget = other.memory_budget;
+        }
+        if other.cache_index_and_filter_blocks.is_some() {
+            self.cache_index_and_filter_blocks = other.cache_index_and_filter_blocks;
+        }
+        if other.compaction.is_some() {
+            self.compaction = other.compaction.clone();
+        }
+    }
+}
+
 impl Mining {
     #[allow(clippy::cognitive_complexity)]
     pub fn merge(&mut self, other: &Mining) {
@@ -436,6 +879,9 @@ impl Mining {
         if other.notify_work.is_some() {
             self.notify_work = other.notify_work.clone();
         }
+        for (url, secret) in other.notify_work_secrets.iter().flatten() {
+            self.notify_work_secrets.get_or_insert_with(Default::default).insert(url.clone(), secret.clone());
+        }
         if other.force_sealing.is_some() {
             self.force_sealing = other.force_sealing;
         }
@@ -454,6 +900,12 @@ impl Mining {
         if other.work_queue_size.is_some() {
             self.work_queue_size = other.work_queue_size;
         }
+        if other.external_sealing.is_some() {
+            self.external_sealing = other.external_sealing;
+        }
+        if other.pending_seal_timeout.is_some() {
+            self.pending_seal_timeout = other.pending_seal_timeout;
+        }
         if other.min_pay_transaction_cost.is_some() {
             self.min_pay_transaction_cost = other.min_pay_transaction_cost;
         }
@@ -496,6 +948,9 @@ impl Mining {
         if other.min_asset_unwrap_ccc_cost.is_some() {
             self.min_asset_unwrap_ccc_cost = other.min_asset_unwrap_ccc_cost;
         }
+        if other.min_pay_memo_byte_cost.is_some() {
+            self.min_pay_memo_byte_cost = other.min_pay_memo_byte_cost;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -524,7 +979,6 @@ impl Mining {
         if matches.is_present("no-miner") {
             self.author = None;
             self.engine_signer = None;
-            println!("This option was deprecated. PoW type engine with no engine signer and PBFT or PoA type engine with no author implicitly means no-miner.");
         }
         if let Some(mem_pool_fee_bump_shift) = matches.value_of("mem-pool-fee-bump-shift") {
             self.mem_pool_fee_bump_shift =
@@ -560,6 +1014,12 @@ impl Mining {
         if let Some(work_queue_size) = matches.value_of("work-queue-size") {
             self.work_queue_size = Some(work_queue_size.parse().map_err(|_| "Invalid size")?);
         }
+        if matches.is_present("external-sealing") {
+            self.external_sealing = Some(true);
+        }
+        if let Some(pending_seal_timeout) = matches.value_of("pending-seal-timeout") {
+            self.pending_seal_timeout = Some(pending_seal_timeout.parse().map_err(|_| "Invalid timeout")?);
+        }
         if let Some(allowed_past_gap) = matches.value_of("allowed-past-gap") {
             self.allowed_past_gap = Some(allowed_past_gap.parse().map_err(|_| "Invalid time gap")?);
         }
@@ -578,6 +1038,49 @@ impl Mining {
             allowed_future_gap,
         }
     }
+
+    #[allow(clippy::cognitive_complexity)]
+    pub fn overwrite_with_env(&mut self, env: &EnvOverrides) -> Result<(), String> {
+        let env = env.section("mining");
+        env.read("author", &mut self.author)?;
+        env.read("engine_signer", &mut self.engine_signer)?;
+        env.read("mem_pool_size", &mut self.mem_pool_size)?;
+        env.read("mem_pool_mem_limit", &mut self.mem_pool_mem_limit)?;
+        env.read("self_nomination_metadata", &mut self.self_nomination_metadata)?;
+        env.read("self_target_deposit", &mut self.self_target_deposit)?;
+        env.read_flag("self_nomination_enable", &mut self.self_nomination_enable)?;
+        env.read("self_nomination_interval", &mut self.self_nomination_interval)?;
+        env.read("mem_pool_fee_bump_shift", &mut self.mem_pool_fee_bump_shift)?;
+        env.read_bool("allow_create_shard", &mut self.allow_create_shard)?;
+        env.read_list("notify_work", &mut self.notify_work);
+        env.read_map("notify_work_secrets", &mut self.notify_work_secrets)?;
+        env.read_bool("force_sealing", &mut self.force_sealing)?;
+        env.read("reseal_on_txs", &mut self.reseal_on_txs)?;
+        env.read("reseal_min_period", &mut self.reseal_min_period)?;
+        env.read("reseal_max_period", &mut self.reseal_max_period)?;
+        env.read_bool("no_reseal_timer", &mut self.no_reseal_timer)?;
+        env.read("work_queue_size", &mut self.work_queue_size)?;
+        env.read_bool("external_sealing", &mut self.external_sealing)?;
+        env.read("pending_seal_timeout", &mut self.pending_seal_timeout)?;
+        env.read("allowed_past_gap", &mut self.allowed_past_gap)?;
+        env.read("allowed_future_gap", &mut self.allowed_future_gap)?;
+        env.read("min_pay_transaction_cost", &mut self.min_pay_transaction_cost)?;
+        env.read("min_set_regular_key_transaction_cost", &mut self.min_set_regular_key_transaction_cost)?;
+        env.read("min_create_shard_transaction_cost", &mut self.min_create_shard_transaction_cost)?;
+        env.read("min_set_shard_owners_transaction_cost", &mut self.min_set_shard_owners_transaction_cost)?;
+        env.read("min_set_shard_users_transaction_cost", &mut self.min_set_shard_users_transaction_cost)?;
+        env.read("min_wrap_ccc_transaction_cost", &mut self.min_wrap_ccc_transaction_cost)?;
+        env.read("min_custom_transaction_cost", &mut self.min_custom_transaction_cost)?;
+        env.read("min_store_transaction_cost", &mut self.min_store_transaction_cost)?;
+        env.read("min_remove_transaction_cost", &mut self.min_remove_transaction_cost)?;
+        env.read("min_asset_mint_cost", &mut self.min_asset_mint_cost)?;
+        env.read("min_asset_transfer_cost", &mut self.min_asset_transfer_cost)?;
+        env.read("min_asset_scheme_change_cost", &mut self.min_asset_scheme_change_cost)?;
+        env.read("min_asset_supply_increase_cost", &mut self.min_asset_supply_increase_cost)?;
+        env.read("min_asset_unwrap_ccc_cost", &mut self.min_asset_unwrap_ccc_cost)?;
+        env.read("min_pay_memo_byte_cost", &mut self.min_pay_memo_byte_cost)?;
+        Ok(())
+    }
 }
 
 impl Network {
@@ -600,15 +1103,27 @@ impl Network {
         if other.max_peers.is_some() {
             self.max_peers = other.max_peers;
         }
+        if other.compression_threshold.is_some() {
+            self.compression_threshold = other.compression_threshold;
+        }
         if other.sync.is_some() {
             self.sync = other.sync;
         }
+        if other.sync_spot_check_threshold.is_some() {
+            self.sync_spot_check_threshold = other.sync_spot_check_threshold;
+        }
+        if other.sync_spot_check_samples.is_some() {
+            self.sync_spot_check_samples = other.sync_spot_check_samples;
+        }
         if other.transaction_relay.is_some() {
             self.transaction_relay = other.transaction_relay;
         }
         if other.discovery.is_some() {
             self.discovery = other.discovery;
         }
+        if other.discovery_mode.is_some() {
+            self.discovery_mode = other.discovery_mode.clone();
+        }
         if other.discovery_type.is_some() {
             self.discovery_type = other.discovery_type.clone();
         }
@@ -624,6 +1139,9 @@ impl Network {
         if other.whitelist_path.is_some() {
             self.whitelist_path = other.whitelist_path.clone();
         }
+        if other.network_secret_path.is_some() {
+            self.network_secret_path = other.network_secret_path.clone();
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -648,13 +1166,19 @@ impl Network {
         if let Some(max_peers) = matches.value_of("max-peers") {
             self.max_peers = Some(max_peers.parse().map_err(|_| "Invalid max-peers")?);
         }
-        if self.min_peers > self.max_peers {
-            return Err("Invalid min/max peers".to_string())
+        if let Some(threshold) = matches.value_of("compression-threshold") {
+            self.compression_threshold = Some(threshold.parse().map_err(|_| "Invalid compression-threshold")?);
         }
 
         if matches.is_present("no-sync") {
             self.sync = Some(false);
         }
+        if let Some(threshold) = matches.value_of("sync-spot-check-threshold") {
+            self.sync_spot_check_threshold = Some(threshold.parse().map_err(|_| "Invalid sync-spot-check-threshold")?);
+        }
+        if let Some(samples) = matches.value_of("sync-spot-check-samples") {
+            self.sync_spot_check_samples = Some(samples.parse().map_err(|_| "Invalid sync-spot-check-samples")?);
+        }
         if matches.is_present("no-tx-relay") {
             self.transaction_relay = Some(false);
         }
@@ -662,6 +1186,9 @@ impl Network {
         if matches.is_present("no-discovery") {
             self.discovery = Some(false);
         }
+        if let Some(discovery_mode) = matches.value_of("discovery-mode") {
+            self.discovery_mode = Some(discovery_mode.to_string());
+        }
         if let Some(discovery_type) = matches.value_of("discovery") {
             self.discovery_type = Some(discovery_type.to_string());
         }
@@ -678,9 +1205,36 @@ impl Network {
         if let Some(file_path) = matches.value_of("blacklist-path") {
             self.blacklist_path = Some(file_path.to_string());
         }
+        if let Some(file_path) = matches.value_of("network-secret-path") {
+            self.network_secret_path = Some(file_path.to_string());
+        }
 
         Ok(())
     }
+
+    pub fn overwrite_with_env(&mut self, env: &EnvOverrides) -> Result<(), String> {
+        let env = env.section("network");
+        env.read("interface", &mut self.interface)?;
+        env.read_bool("disable", &mut self.disable)?;
+        env.read("port", &mut self.port)?;
+        env.read_list("bootstrap_addresses", &mut self.bootstrap_addresses);
+        env.read("min_peers", &mut self.min_peers)?;
+        env.read("max_peers", &mut self.max_peers)?;
+        env.read("compression_threshold", &mut self.compression_threshold)?;
+        env.read_bool("sync", &mut self.sync)?;
+        env.read("sync_spot_check_threshold", &mut self.sync_spot_check_threshold)?;
+        env.read("sync_spot_check_samples", &mut self.sync_spot_check_samples)?;
+        env.read_bool("transaction_relay", &mut self.transaction_relay)?;
+        env.read_bool("discovery", &mut self.discovery)?;
+        env.read("discovery_mode", &mut self.discovery_mode)?;
+        env.read("discovery_type", &mut self.discovery_type)?;
+        env.read("discovery_refresh", &mut self.discovery_refresh)?;
+        env.read("discovery_bucket_size", &mut self.discovery_bucket_size)?;
+        env.read("blacklist_path", &mut self.blacklist_path)?;
+        env.read("whitelist_path", &mut self.whitelist_path)?;
+        env.read("network_secret_path", &mut self.network_secret_path)?;
+        Ok(())
+    }
 }
 
 impl Rpc {
@@ -691,8 +1245,8 @@ impl Rpc {
         if other.interface.is_some() {
             self.interface = other.interface.clone();
         }
-        if other.hosts.is_some() {
-            self.hosts = other.hosts.clone();
+        if other.allowed_hosts.is_some() {
+            self.allowed_hosts = other.allowed_hosts.clone();
         }
         if other.cors.is_some() {
             self.cors = other.cors.clone();
@@ -700,6 +1254,18 @@ impl Rpc {
         if other.port.is_some() {
             self.port = other.port;
         }
+        if other.max_batch_len.is_some() {
+            self.max_batch_len = other.max_batch_len;
+        }
+        if other.max_execution_time.is_some() {
+            self.max_execution_time = other.max_execution_time;
+        }
+        if other.executor_workers.is_some() {
+            self.executor_workers = other.executor_workers;
+        }
+        if other.executor_queue_size.is_some() {
+            self.executor_queue_size = other.executor_queue_size;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -713,7 +1279,7 @@ impl Rpc {
             self.interface = Some(interface.to_string());
         }
         if let Some(hosts) = matches.values_of_lossy("jsonrpc-hosts") {
-            self.hosts = Some(hosts);
+            self.allowed_hosts = Some(hosts);
         }
         if let Some(cors) = matches.values_of_lossy("jsonrpc-cors") {
             self.cors = Some(cors);
@@ -721,6 +1287,34 @@ impl Rpc {
         if matches.is_present("enable-devel-api") {
             self.enable_devel_api = true;
         }
+        if let Some(max_batch_len) = matches.value_of("jsonrpc-max-batch-len") {
+            self.max_batch_len = Some(max_batch_len.parse().map_err(|_| "Invalid max batch length")?);
+        }
+        if let Some(max_execution_time) = matches.value_of("jsonrpc-max-execution-time") {
+            self.max_execution_time = Some(max_execution_time.parse().map_err(|_| "Invalid max execution time")?);
+        }
+        if let Some(executor_workers) = matches.value_of("jsonrpc-executor-workers") {
+            self.executor_workers = Some(executor_workers.parse().map_err(|_| "Invalid executor workers")?);
+        }
+        if let Some(executor_queue_size) = matches.value_of("jsonrpc-executor-queue-size") {
+            self.executor_queue_size = Some(executor_queue_size.parse().map_err(|_| "Invalid executor queue size")?);
+        }
+        Ok(())
+    }
+
+    pub fn overwrite_with_env(&mut self, env: &EnvOverrides) -> Result<(), String> {
+        let env = env.section("rpc");
+        env.read_bool("disable", &mut self.disable)?;
+        env.read("interface", &mut self.interface)?;
+        env.read_list("hosts", &mut self.allowed_hosts);
+        env.read_list("allowed_hosts", &mut self.allowed_hosts);
+        env.read_list("cors", &mut self.cors);
+        env.read("port", &mut self.port)?;
+        env.read_flag("enable_devel_api", &mut self.enable_devel_api)?;
+        env.read("max_batch_len", &mut self.max_batch_len)?;
+        env.read("max_execution_time", &mut self.max_execution_time)?;
+        env.read("executor_workers", &mut self.executor_workers)?;
+        env.read("executor_queue_size", &mut self.executor_queue_size)?;
         Ok(())
     }
 }
@@ -733,12 +1327,27 @@ impl Ws {
         if other.interface.is_some() {
             self.interface = other.interface.clone();
         }
+        if other.allowed_hosts.is_some() {
+            self.allowed_hosts = other.allowed_hosts.clone();
+        }
+        if other.origins.is_some() {
+            self.origins = other.origins.clone();
+        }
         if other.port.is_some() {
             self.port = other.port;
         }
         if other.max_connections.is_some() {
             self.max_connections = other.max_connections;
         }
+        if other.max_batch_len.is_some() {
+            self.max_batch_len = other.max_batch_len;
+        }
+        if other.max_execution_time.is_some() {
+            self.max_execution_time = other.max_execution_time;
+        }
+        if other.max_subscriptions.is_some() {
+            self.max_subscriptions = other.max_subscriptions;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -749,12 +1358,41 @@ impl Ws {
         if let Some(interface) = matches.value_of("ws-interface") {
             self.interface = Some(interface.to_string());
         }
+        if let Some(hosts) = matches.values_of_lossy("ws-hosts") {
+            self.allowed_hosts = Some(hosts);
+        }
+        if let Some(origins) = matches.values_of_lossy("ws-origins") {
+            self.origins = Some(origins);
+        }
         if let Some(port) = matches.value_of("ws-port") {
             self.port = Some(port.parse().map_err(|_| "Invalid port")?);
         }
         if let Some(max_connections) = matches.value_of("ws-max-connections") {
             self.max_connections = Some(max_connections.parse().map_err(|_| "Invalid max connections")?);
         }
+        if let Some(max_batch_len) = matches.value_of("ws-max-batch-len") {
+            self.max_batch_len = Some(max_batch_len.parse().map_err(|_| "Invalid max batch length")?);
+        }
+        if let Some(max_execution_time) = matches.value_of("ws-max-execution-time") {
+            self.max_execution_time = Some(max_execution_time.parse().map_err(|_| "Invalid max execution time")?);
+        }
+        if let Some(max_subscriptions) = matches.value_of("ws-max-subscriptions") {
+            self.max_subscriptions = Some(max_subscriptions.parse().map_err(|_| "Invalid max subscriptions")?);
+        }
+        Ok(())
+    }
+
+    pub fn overwrite_with_env(&mut self, env: &EnvOverrides) -> Result<(), String> {
+        let env = env.section("ws");
+        env.read_bool("disable", &mut self.disable)?;
+        env.read("interface", &mut self.interface)?;
+        env.read_list("allowed_hosts", &mut self.allowed_hosts);
+        env.read_list("origins", &mut self.origins);
+        env.read("port", &mut self.port)?;
+        env.read("max_connections", &mut self.max_connections)?;
+        env.read("max_batch_len", &mut self.max_batch_len)?;
+        env.read("max_execution_time", &mut self.max_execution_time)?;
+        env.read("max_subscriptions", &mut self.max_subscriptions)?;
         Ok(())
     }
 }
@@ -767,6 +1405,12 @@ impl Snapshot {
         if other.path.is_some() {
             self.path = other.path.clone();
         }
+        if other.period.is_some() {
+            self.period = other.period;
+        }
+        if other.keep.is_some() {
+            self.keep = other.keep;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -777,6 +1421,21 @@ impl Snapshot {
         if let Some(snapshot_path) = matches.value_of("snapshot-path") {
             self.path = Some(snapshot_path.to_string());
         }
+        if let Some(period) = matches.value_of("snapshot-period") {
+            self.period = Some(period.parse().map_err(|_| "Invalid snapshot period")?);
+        }
+        if let Some(keep) = matches.value_of("snapshot-keep") {
+            self.keep = Some(keep.parse().map_err(|_| "Invalid number of snapshots to keep")?);
+        }
+        Ok(())
+    }
+
+    pub fn overwrite_with_env(&mut self, env: &EnvOverrides) -> Result<(), String> {
+        let env = env.section("snapshot");
+        env.read_bool("disable", &mut self.disable)?;
+        env.read("path", &mut self.path)?;
+        env.read("period", &mut self.period)?;
+        env.read("keep", &mut self.keep)?;
         Ok(())
     }
 }
@@ -786,9 +1445,15 @@ impl Stratum {
         if other.disable.is_some() {
             self.disable = other.disable;
         }
+        if other.listen_address.is_some() {
+            self.listen_address = other.listen_address.clone();
+        }
         if other.port.is_some() {
             self.port = other.port;
         }
+        if other.secret.is_some() {
+            self.secret = other.secret.clone();
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -796,9 +1461,63 @@ impl Stratum {
             self.disable = Some(true);
         }
 
+        if let Some(listen_address) = matches.value_of("stratum-listen-address") {
+            self.listen_address = Some(listen_address.to_string());
+        }
         if let Some(port) = matches.value_of("stratum-port") {
             self.port = Some(port.parse().map_err(|_| "Invalid port")?);
         }
+        if let Some(secret) = matches.value_of("stratum-secret") {
+            self.secret = Some(secret.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn overwrite_with_env(&mut self, env: &EnvOverrides) -> Result<(), String> {
+        let env = env.section("stratum");
+        env.read_bool("disable", &mut self.disable)?;
+        env.read("listen_address", &mut self.listen_address)?;
+        env.read("port", &mut self.port)?;
+        env.read("secret", &mut self.secret)?;
+        Ok(())
+    }
+}
+
+impl Metrics {
+    pub fn merge(&mut self, other: &Metrics) {
+        if other.disable.is_some() {
+            self.disable = other.disable;
+        }
+        if other.interface.is_some() {
+            self.interface = other.interface.clone();
+        }
+        if other.port.is_some() {
+            self.port = other.port;
+        }
+    }
+
+    pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
+        if matches.is_present("metrics") {
+            self.disable = Some(false);
+        }
+        if matches.is_present("no-metrics") {
+            self.disable = Some(true);
+        }
+
+        if let Some(interface) = matches.value_of("metrics-interface") {
+            self.interface = Some(interface.to_string());
+        }
+        if let Some(port) = matches.value_of("metrics-port") {
+            self.port = Some(port.parse().map_err(|_| "Invalid port")?);
+        }
+        Ok(())
+    }
+
+    pub fn overwrite_with_env(&mut self, env: &EnvOverrides) -> Result<(), String> {
+        let env = env.section("metrics");
+        env.read_bool("disable", &mut self.disable)?;
+        env.read("interface", &mut self.interface)?;
+        env.read("port", &mut self.port)?;
         Ok(())
     }
 }
@@ -829,6 +1548,14 @@ impl EmailAlarm {
 
         Ok(())
     }
+
+    pub fn overwrite_with_env(&mut self, env: &EnvOverrides) -> Result<(), String> {
+        let env = env.section("email_alarm");
+        env.read_bool("disable", &mut self.disable)?;
+        env.read("to", &mut self.to)?;
+        env.read("sendgrid_key", &mut self.sendgrid_key)?;
+        Ok(())
+    }
 }
 
 impl Default for EmailAlarm {
@@ -853,12 +1580,92 @@ pub fn read_preset_config() -> &'static str {
     str::from_utf8(bytes).expect("The preset config file must be valid")
 }
 
-pub fn load_config(matches: &clap::ArgMatches) -> Result<Config, String> {
-    let mut config: Config = {
-        let toml_string = read_preset_config().to_string();
-        toml::from_str(toml_string.as_ref()).expect("The preset config file must be valid")
+/// The default ports of the instances are this far apart.
+/// The default ports have different last digits, so the ones of different instances never collide.
+const INSTANCE_PORT_STRIDE: usize = 10;
+
+/// The default port of the instance. The instance 0 uses the port as it is.
+fn instance_port(port: u16, instance_id: usize) -> Result<u16, String> {
+    instance_id
+        .checked_mul(INSTANCE_PORT_STRIDE)
+        .and_then(|offset| offset.checked_add(port as usize))
+        .filter(|port| *port <= u16::max_value() as usize)
+        .map(|port| port as u16)
+        .ok_or_else(|| format!("The instance id {} is too large to offset the port {}", instance_id, port))
+}
+
+/// The default IPC path of the instance. The instance id is appended to the file name except for the instance 0.
+fn instance_ipc_path(path: &str, instance_id: usize) -> String {
+    if instance_id == 0 {
+        return path.to_string()
+    }
+    let path = Path::new(path);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, instance_id, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, instance_id),
     };
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
+
+fn preset_config() -> Config {
+    let toml_string = read_preset_config().to_string();
+    toml::from_str(toml_string.as_ref()).expect("The preset config file must be valid")
+}
+
+pub fn load_config(matches: &clap::ArgMatches) -> Result<Config, String> {
+    load_config_with_env(matches, &EnvOverrides::from_env())
+}
+
+/// Resolves the config as `load_config` does without validating it.
+/// It returns the config merged so far with the errors of every section, instead of stopping at the first one.
+/// It fails only if the config file cannot be read.
+pub fn resolve_config(matches: &clap::ArgMatches) -> Result<(Config, Vec<String>), String> {
+    resolve_config_with_env(matches, &EnvOverrides::from_env())
+}
+
+/// The notices of the deprecated options given in the command line.
+pub fn deprecation_notices(matches: &clap::ArgMatches) -> Vec<&'static str> {
+    let mut notices = Vec::new();
+    if matches.is_present("no-miner") {
+        notices.push("This option was deprecated. PoW type engine with no engine signer and PBFT or PoA type engine with no author implicitly means no-miner.");
+    }
+    notices
+}
 
+fn load_config_with_env(matches: &clap::ArgMatches, env: &EnvOverrides) -> Result<Config, String> {
+    let (config, errors) = resolve_config_with_env(matches, env)?;
+    if !errors.is_empty() {
+        return Err(errors.join("; "))
+    }
+    validation::validate(&config)?;
+    Ok(config)
+}
+
+/// Loads the config in the order of precedence: the preset, the config file, the environment variables
+/// and then the command line arguments.
+/// If the instance id is given, the ports and the IPC path of the preset are moved apart from the ones of the other
+/// instances. The values given by the other sources are kept as they are.
+fn resolve_config_with_env(matches: &clap::ArgMatches, env: &EnvOverrides) -> Result<(Config, Vec<String>), String> {
+    let (config, errors) = override_config(preset_config(), matches, env)?;
+    // The instance id can be given by any source, so the preset is offset after all of them are read.
+    if let Some(instance_id) = config.operating.instance_id {
+        let mut preset = preset_config();
+        if let Err(err) = preset.offset_for_instance(instance_id) {
+            let mut errors = errors;
+            errors.push(err);
+            return Ok((config, errors))
+        }
+        return override_config(preset, matches, env)
+    }
+    Ok((config, errors))
+}
+
+fn override_config(
+    mut config: Config,
+    matches: &clap::ArgMatches,
+    env: &EnvOverrides,
+) -> Result<(Config, Vec<String>), String> {
     if let Some(config_path) = matches.value_of("config") {
         let toml_string = fs::read_to_string(config_path).map_err(|e| format!("Fail to read file: {:?}", e))?;
         let extra_config: Config =
@@ -866,14 +1673,297 @@ pub fn load_config(matches: &clap::ArgMatches) -> Result<Config, String> {
         config.merge(&extra_config);
     };
 
-    config.ipc.overwrite_with(&matches)?;
-    config.operating.overwrite_with(&matches)?;
-    config.mining.overwrite_with(&matches)?;
-    config.network.overwrite_with(&matches)?;
-    config.rpc.overwrite_with(&matches)?;
-    config.ws.overwrite_with(&matches)?;
-    config.snapshot.overwrite_with(&matches)?;
-    config.stratum.overwrite_with(&matches)?;
-    config.email_alarm.overwrite_with(&matches)?;
-    Ok(config)
+    let results = vec![
+        config.ipc.overwrite_with_env(env),
+        config.operating.overwrite_with_env(env),
+        config.mining.overwrite_with_env(env),
+        config.network.overwrite_with_env(env),
+        config.rpc.overwrite_with_env(env),
+        config.ws.overwrite_with_env(env),
+        config.snapshot.overwrite_with_env(env),
+        config.stratum.overwrite_with_env(env),
+        config.metrics.overwrite_with_env(env),
+        config.email_alarm.overwrite_with_env(env),
+        config.ipc.overwrite_with(&matches),
+        config.operating.overwrite_with(&matches),
+        config.mining.overwrite_with(&matches),
+        config.network.overwrite_with(&matches),
+        config.rpc.overwrite_with(&matches),
+        config.ws.overwrite_with(&matches),
+        config.snapshot.overwrite_with(&matches),
+        config.stratum.overwrite_with(&matches),
+        config.metrics.overwrite_with(&matches),
+        config.email_alarm.overwrite_with(&matches),
+    ];
+    // Each section stops at its first error, but the errors of the other sections are reported together.
+    let errors = results.into_iter().filter_map(Result::err).collect();
+    Ok((config, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitives::H256;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn matches_from(args: &[&str]) -> clap::ArgMatches<'static> {
+        clap::App::new("codechain")
+            .arg(clap::Arg::with_name("config").long("config").takes_value(true))
+            .arg(clap::Arg::with_name("instance-id").long("instance-id").takes_value(true))
+            .arg(clap::Arg::with_name("port").long("port").takes_value(true))
+            .arg(clap::Arg::with_name("min-peers").long("min-peers").takes_value(true))
+            .arg(clap::Arg::with_name("max-peers").long("max-peers").takes_value(true))
+            .arg(clap::Arg::with_name("stratum-listen-address").long("stratum-listen-address").takes_value(true))
+            .arg(clap::Arg::with_name("stratum-secret").long("stratum-secret").takes_value(true))
+            .get_matches_from(std::iter::once("codechain").chain(args.iter().cloned()))
+    }
+
+    fn env_from(vars: &[(&str, &str)]) -> EnvOverrides {
+        EnvOverrides::new(vars.iter().map(|(name, value)| (name.to_string(), value.to_string())))
+    }
+
+    #[test]
+    fn env_overrides_preset() {
+        let env = env_from(&[
+            ("CODECHAIN_NETWORK_PORT", "4000"),
+            ("CODECHAIN_NETWORK_BOOTSTRAP_ADDRESSES", "1.2.3.4:3485,5.6.7.8:3486"),
+            ("CODECHAIN_MINING_NO_RESEAL_TIMER", "true"),
+            ("CODECHAIN_MINING_RESEAL_MIN_PERIOD", "1500"),
+            ("CODECHAIN_CODECHAIN_DB_PATH", "/var/lib/codechain"),
+            ("CODECHAIN_EMAIL_ALARM_TO", "ops@example.com"),
+        ]);
+        let config = load_config_with_env(&matches_from(&[]), &env).unwrap();
+        assert_eq!(Some("/var/lib/codechain".to_string()), config.operating.db_path);
+        assert_eq!(Some("ops@example.com".to_string()), config.email_alarm.to);
+
+        let network_config = config.network_config().unwrap();
+        assert_eq!(4000, network_config.port);
+        assert_eq!(
+            vec![SocketAddr::from_str("1.2.3.4:3485").unwrap(), SocketAddr::from_str("5.6.7.8:3486").unwrap()],
+            network_config.bootstrap_addresses
+        );
+
+        let miner_options = config.miner_options().unwrap();
+        assert!(miner_options.no_reseal_timer);
+        assert_eq!(Duration::from_millis(1500), miner_options.reseal_min_period);
+    }
+
+    #[test]
+    fn env_overrides_config_file() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(read_preset_config().replace("port = 3485", "port = 3000").as_bytes()).unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let config = load_config_with_env(&matches_from(&["--config", path]), &env_from(&[])).unwrap();
+        assert_eq!(Some(3000), config.network.port);
+
+        let env = env_from(&[("CODECHAIN_NETWORK_PORT", "4000")]);
+        let config = load_config_with_env(&matches_from(&["--config", path]), &env).unwrap();
+        assert_eq!(Some(4000), config.network.port);
+    }
+
+    #[test]
+    fn instance_port_is_offset_by_the_instance_id() {
+        assert_eq!(Ok(8080), instance_port(8080, 0));
+        assert_eq!(Ok(8110), instance_port(8080, 3));
+        assert_eq!(Ok(65535), instance_port(65525, 1));
+        assert!(instance_port(65535, 1).is_err());
+        assert!(instance_port(8080, usize::max_value()).is_err());
+
+        assert_eq!("/tmp/jsonrpc.ipc", instance_ipc_path("/tmp/jsonrpc.ipc", 0));
+        assert_eq!("/tmp/jsonrpc-3.ipc", instance_ipc_path("/tmp/jsonrpc.ipc", 3));
+        assert_eq!("/tmp/jsonrpc-3", instance_ipc_path("/tmp/jsonrpc", 3));
+    }
+
+    #[test]
+    fn instance_id_offsets_the_preset() {
+        let env = env_from(&[("CODECHAIN_CODECHAIN_INSTANCE_ID", "2")]);
+        let config = load_config_with_env(&matches_from(&[]), &env).unwrap();
+        assert_eq!(Some(3505), config.network.port);
+        assert_eq!(Some(8100), config.rpc.port);
+        assert_eq!(Some(8101), config.ws.port);
+        assert_eq!(Some(8028), config.stratum.port);
+        assert_eq!(Some(8102), config.metrics.port);
+        assert_eq!(Some("/tmp/jsonrpc-2.ipc".to_string()), config.ipc.path);
+        assert_eq!("./instance-2", config.operating.instance_path());
+
+        let config = load_config_with_env(&matches_from(&["--instance-id", "0"]), &env_from(&[])).unwrap();
+        assert_eq!(Some(3485), config.network.port);
+        assert_eq!(Some("/tmp/jsonrpc.ipc".to_string()), config.ipc.path);
+        assert_eq!(".", config.operating.instance_path());
+    }
+
+    #[test]
+    fn explicit_values_beat_the_instance_offset() {
+        let env = env_from(&[("CODECHAIN_RPC_PORT", "9000"), ("CODECHAIN_IPC_PATH", "/tmp/node.ipc")]);
+        let config = load_config_with_env(&matches_from(&["--instance-id", "2", "--port", "4000"]), &env).unwrap();
+        assert_eq!(Some(4000), config.network.port);
+        assert_eq!(Some(9000), config.rpc.port);
+        assert_eq!(Some("/tmp/node.ipc".to_string()), config.ipc.path);
+        assert_eq!(Some(8101), config.ws.port);
+
+        // The values in the config file are explicit too.
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(read_preset_config().replace("port = 8081", "port = 7000").as_bytes()).unwrap();
+        let path = file.path().to_str().unwrap();
+        let config =
+            load_config_with_env(&matches_from(&["--config", path, "--instance-id", "2"]), &env_from(&[])).unwrap();
+        assert_eq!(Some(7000), config.ws.port);
+        assert_eq!(Some(8080), config.rpc.port);
+    }
+
+    fn load_config_with_extra(extra: &str) -> Config {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all((read_preset_config().to_string() + extra).as_bytes()).unwrap();
+        let path = file.path().to_str().unwrap();
+        load_config_with_env(&matches_from(&["--config", path]), &env_from(&[])).unwrap()
+    }
+
+    #[test]
+    fn db_columns_are_read_from_config_file() {
+        let config = load_config_with_extra(
+            r#"
+[codechain.db_columns.state]
+memory_budget = 512
+cache_index_and_filter_blocks = true
+
+[codechain.db_columns.bodies]
+compaction = "hdd"
+"#,
+        );
+        let db_columns = config.db_columns().unwrap();
+        assert_eq!(2, db_columns.len());
+        assert_eq!(
+            Some(&DatabaseColumnConfig {
+                memory_budget: Some(512),
+                cache_index_and_filter_blocks: Some(true),
+                compaction: None,
+            }),
+            db_columns.get(&ccore::COL_STATE)
+        );
+        assert_eq!(
+            Some(DatabaseCompactionProfile::HDD),
+            db_columns.get(&column_by_name("bodies").unwrap()).unwrap().compaction
+        );
+    }
+
+    #[test]
+    fn notify_work_secrets_are_read_from_config_file() {
+        let url = "http://127.0.0.1:8000/";
+        let mut file = NamedTempFile::new().unwrap();
+        let toml = read_preset_config().replace("notify_work = []", &format!("notify_work = [\"{}\"]", url))
+            + &format!("\n[mining.notify_work_secrets]\n\"{}\" = \"secret\"\n", url);
+        file.write_all(toml.as_bytes()).unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let config = load_config_with_env(&matches_from(&["--config", path]), &env_from(&[])).unwrap();
+        let miner_options = config.miner_options().unwrap();
+        assert_eq!(vec![url.to_string()], miner_options.new_work_notify);
+        assert_eq!(Some(&"secret".to_string()), miner_options.new_work_notify_secrets.get(url));
+    }
+
+    #[test]
+    fn no_db_columns_keep_database_options() {
+        let config = load_config_with_env(&matches_from(&[]), &env_from(&[])).unwrap();
+        assert!(config.db_columns().unwrap().is_empty());
+        assert_eq!(DatabaseCompactionProfile::Auto, config.db_compaction().unwrap());
+    }
+
+    #[test]
+    fn unknown_db_column_is_rejected() {
+        let config = load_config_with_extra("\n[codechain.db_columns.body]\nmemory_budget = 512\n");
+        let err = config.db_columns().err().unwrap();
+        assert!(err.contains("unknown column body"), "{}", err);
+    }
+
+    #[test]
+    fn invalid_db_column_compaction_is_rejected() {
+        let config = load_config_with_extra("\n[codechain.db_columns.state]\ncompaction = \"fast\"\n");
+        let err = config.db_columns().err().unwrap();
+        assert!(err.contains("codechain.db_columns.state.compaction"), "{}", err);
+    }
+
+    #[test]
+    fn cli_overrides_env() {
+        let env = env_from(&[("CODECHAIN_NETWORK_PORT", "4000")]);
+        let config = load_config_with_env(&matches_from(&["--port", "5000"]), &env).unwrap();
+        assert_eq!(5000, config.network_config().unwrap().port);
+    }
+
+    #[test]
+    fn invalid_env_value_names_the_variable() {
+        let env = env_from(&[("CODECHAIN_MINING_AUTHOR", "not an address")]);
+        let err = load_config_with_env(&matches_from(&[]), &env).err().unwrap();
+        assert!(err.contains("CODECHAIN_MINING_AUTHOR"), "{}", err);
+    }
+
+    #[test]
+    fn load_config_reads_env_overrides() {
+        let config = load_config_with_env(&matches_from(&[]), &env_from(&[("CODECHAIN_STRATUM_PORT", "9008")]));
+        assert_eq!(Some(9008), config.unwrap().stratum.port);
+    }
+
+    // The stratum server rejecting a worker with a wrong secret is tested in the stratum crate.
+    #[test]
+    fn stratum_config_has_the_listen_address_and_the_secret() {
+        let secret = "ab".repeat(32);
+        let matches = matches_from(&["--stratum-listen-address", "127.0.0.1", "--stratum-secret", &secret]);
+        let config = load_config_with_env(&matches, &env_from(&[])).unwrap();
+        let stratum_config = config.stratum_config().unwrap();
+        assert_eq!("127.0.0.1", stratum_config.listen_addr);
+        assert_eq!(Some(H256::from_str(&secret).unwrap()), stratum_config.secret);
+    }
+
+    #[test]
+    fn peer_flags_set_their_own_fields() {
+        let config = load_config_with_env(&matches_from(&["--max-peers", "100"]), &env_from(&[])).unwrap();
+        assert_eq!(Some(100), config.network.max_peers);
+
+        let config =
+            load_config_with_env(&matches_from(&["--min-peers", "20", "--max-peers", "40"]), &env_from(&[])).unwrap();
+        assert_eq!(Some(20), config.network.min_peers);
+        assert_eq!(Some(40), config.network.max_peers);
+    }
+
+    #[test]
+    fn min_peers_flag_is_validated_against_resolved_max_peers() {
+        let env = env_from(&[("CODECHAIN_NETWORK_MAX_PEERS", "50")]);
+        let err = load_config_with_env(&matches_from(&["--min-peers", "60"]), &env).err().unwrap();
+        assert!(err.contains("network.min_peers (60)") && err.contains("network.max_peers (50)"), "{}", err);
+    }
+
+    #[test]
+    fn allowed_origins_and_hosts_are_passed_to_the_rpc_servers() {
+        let config = load_config_with_env(
+            &matches_from(&[
+                "--jsonrpc-cors",
+                "https://example.com",
+                "--jsonrpc-hosts",
+                "localhost:8080",
+                "--ws-origins",
+                "null",
+                "--ws-hosts",
+                "localhost:8081",
+            ]),
+            &env_from(&[]),
+        )
+        .unwrap();
+
+        let http = config.rpc_http_config().unwrap();
+        assert_eq!(Some(vec!["https://example.com".to_string()]), http.cors);
+        assert_eq!(Some(vec!["localhost:8080".to_string()]), http.hosts);
+
+        let ws = config.rpc_ws_config().unwrap();
+        assert_eq!(Some(vec!["null".to_string()]), ws.origins);
+        assert_eq!(Some(vec!["localhost:8081".to_string()]), ws.hosts);
+    }
+
+    #[test]
+    fn invalid_origins_are_rejected() {
+        let env = env_from(&[("CODECHAIN_WS_ORIGINS", "example.com/path")]);
+        let config = load_config_with_env(&matches_from(&[]), &env).unwrap();
+        let err = config.rpc_ws_config().err().unwrap();
+        assert!(err.starts_with("ws.origins"), "{}", err);
+    }
 }
@@ -0,0 +1,337 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use super::Config;
+use primitives::H256;
+use std::net::IpAddr;
+
+/// A constraint between settings. It returns a message that names the offending settings if the constraint is
+/// violated.
+type Rule = fn(&Config) -> Option<String>;
+
+const RULES: [Rule; 11] = [
+    min_peers_not_greater_than_max_peers,
+    rpc_executor_has_workers,
+    reseal_periods_ordered,
+    stratum_listen_address_is_ip,
+    stratum_secret_is_hash,
+    stratum_port_differs_from_rpc_port,
+    ws_port_differs_from_rpc_port,
+    metrics_port_differs_from_rpc_port,
+    snapshot_period_and_keep_are_positive,
+    notify_work_secrets_have_urls,
+    asset_log_retention_is_positive,
+];
+
+/// Checks every rule against the config resolved from the presets, the config file,
+/// the environment variables and the command line arguments.
+pub fn validate(config: &Config) -> Result<(), String> {
+    let errors = errors(config);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Invalid config: {}", errors.join("; ")))
+    }
+}
+
+/// The messages of the violated rules.
+pub fn errors(config: &Config) -> Vec<String> {
+    RULES.iter().filter_map(|rule| rule(config)).collect()
+}
+
+fn is_enabled(disable: Option<bool>) -> bool {
+    !disable.unwrap_or(false)
+}
+
+fn min_peers_not_greater_than_max_peers(config: &Config) -> Option<String> {
+    let min_peers = config.network.min_peers?;
+    let max_peers = config.network.max_peers?;
+    if min_peers > max_peers {
+        return Some(format!("network.min_peers ({}) is greater than network.max_peers ({})", min_peers, max_peers))
+    }
+    None
+}
+
+fn rpc_executor_has_workers(config: &Config) -> Option<String> {
+    if config.rpc.executor_workers? == 0 {
+        return Some("rpc.executor_workers is 0".to_string())
+    }
+    None
+}
+
+fn reseal_periods_ordered(config: &Config) -> Option<String> {
+    let reseal_min_period = config.mining.reseal_min_period?;
+    let reseal_max_period = config.mining.reseal_max_period?;
+    if reseal_min_period > reseal_max_period {
+        return Some(format!(
+            "mining.reseal_min_period ({}) is greater than mining.reseal_max_period ({})",
+            reseal_min_period, reseal_max_period
+        ))
+    }
+    None
+}
+
+fn stratum_listen_address_is_ip(config: &Config) -> Option<String> {
+    let listen_address = config.stratum.listen_address.as_ref()?;
+    if listen_address.parse::<IpAddr>().is_err() {
+        return Some(format!("stratum.listen_address ({}) is not an IP address", listen_address))
+    }
+    None
+}
+
+fn stratum_secret_is_hash(config: &Config) -> Option<String> {
+    let secret = config.stratum.secret.as_ref()?;
+    if secret.len() != 64 || secret.parse::<H256>().is_err() {
+        return Some(format!("stratum.secret ({}) is not a 64-character hex string", secret))
+    }
+    None
+}
+
+fn stratum_port_differs_from_rpc_port(config: &Config) -> Option<String> {
+    if !is_enabled(config.stratum.disable) || !is_enabled(config.rpc.disable) {
+        return None
+    }
+    let port = config.stratum.port?;
+    if Some(port) == config.rpc.port {
+        return Some(format!("stratum.port and rpc.port are the same ({})", port))
+    }
+    None
+}
+
+fn ws_port_differs_from_rpc_port(config: &Config) -> Option<String> {
+    if !is_enabled(config.ws.disable) || !is_enabled(config.rpc.disable) {
+        return None
+    }
+    let port = config.ws.port?;
+    if Some(port) == config.rpc.port {
+        return Some(format!("ws.port and rpc.port are the same ({})", port))
+    }
+    None
+}
+
+fn metrics_port_differs_from_rpc_port(config: &Config) -> Option<String> {
+    if !is_enabled(config.metrics.disable) || !is_enabled(config.rpc.disable) {
+        return None
+    }
+    let port = config.metrics.port?;
+    if Some(port) == config.rpc.port {
+        return Some(format!("metrics.port and rpc.port are the same ({})", port))
+    }
+    None
+}
+
+fn snapshot_period_and_keep_are_positive(config: &Config) -> Option<String> {
+    if !is_enabled(config.snapshot.disable) {
+        return None
+    }
+    if config.snapshot.period == Some(0) {
+        return Some("snapshot.period is 0".to_string())
+    }
+    if config.snapshot.keep == Some(0) {
+        return Some("snapshot.keep is 0".to_string())
+    }
+    None
+}
+
+fn notify_work_secrets_have_urls(config: &Config) -> Option<String> {
+    let urls = config.mining.notify_work.as_ref();
+    let unknown_urls: Vec<&str> = config
+        .mining
+        .notify_work_secrets
+        .iter()
+        .flatten()
+        .map(|(url, _)| url.as_str())
+        .filter(|url| !urls.map_or(false, |urls| urls.iter().any(|u| u == url)))
+        .collect();
+    if !unknown_urls.is_empty() {
+        return Some(format!(
+            "mining.notify_work_secrets has the URLs not in mining.notify_work: {}",
+            unknown_urls.join(", ")
+        ))
+    }
+    None
+}
+
+fn asset_log_retention_is_positive(config: &Config) -> Option<String> {
+    if !config.operating.asset_log.unwrap_or(false) {
+        return None
+    }
+    if config.operating.asset_log_retention == Some(0) {
+        return Some("codechain.asset_log_retention is 0".to_string())
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::read_preset_config;
+    use super::*;
+    use std::collections::HashMap;
+
+    fn preset() -> Config {
+        toml::from_str(read_preset_config()).unwrap()
+    }
+
+    #[test]
+    fn preset_is_valid() {
+        assert_eq!(Ok(()), validate(&preset()));
+    }
+
+    #[test]
+    fn min_peers_greater_than_max_peers() {
+        let mut config = preset();
+        config.network.min_peers = Some(60);
+        config.network.max_peers = Some(30);
+        let err = validate(&config).unwrap_err();
+        assert!(err.contains("network.min_peers (60)") && err.contains("network.max_peers (30)"), "{}", err);
+
+        config.network.max_peers = Some(60);
+        assert_eq!(Ok(()), validate(&config));
+    }
+
+    #[test]
+    fn rpc_executor_without_workers() {
+        let mut config = preset();
+        config.rpc.executor_workers = Some(0);
+        let err = validate(&config).unwrap_err();
+        assert!(err.contains("rpc.executor_workers"), "{}", err);
+    }
+
+    #[test]
+    fn reseal_min_period_greater_than_reseal_max_period() {
+        let mut config = preset();
+        config.mining.reseal_min_period = Some(5000);
+        config.mining.reseal_max_period = Some(4000);
+        let err = validate(&config).unwrap_err();
+        assert!(
+            err.contains("mining.reseal_min_period (5000)") && err.contains("mining.reseal_max_period (4000)"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn stratum_listen_address_not_ip() {
+        let mut config = preset();
+        config.stratum.listen_address = Some("localhost".to_string());
+        let err = validate(&config).unwrap_err();
+        assert!(err.contains("stratum.listen_address (localhost)"), "{}", err);
+
+        config.stratum.listen_address = Some("0.0.0.0".to_string());
+        assert_eq!(Ok(()), validate(&config));
+    }
+
+    #[test]
+    fn stratum_secret_not_hash() {
+        let mut config = preset();
+        config.stratum.secret = Some("secret".to_string());
+        let err = validate(&config).unwrap_err();
+        assert!(err.contains("stratum.secret (secret)"), "{}", err);
+
+        config.stratum.secret = Some("0x".to_string() + &"ab".repeat(32));
+        assert!(validate(&config).is_err());
+
+        config.stratum.secret = Some("ab".repeat(32));
+        assert_eq!(Ok(()), validate(&config));
+    }
+
+    #[test]
+    fn stratum_port_same_as_rpc_port() {
+        let mut config = preset();
+        config.stratum.disable = Some(false);
+        config.stratum.port = config.rpc.port;
+        let err = validate(&config).unwrap_err();
+        assert!(err.contains("stratum.port and rpc.port"), "{}", err);
+
+        config.stratum.disable = Some(true);
+        assert_eq!(Ok(()), validate(&config));
+    }
+
+    #[test]
+    fn ws_port_same_as_rpc_port() {
+        let mut config = preset();
+        config.ws.disable = Some(false);
+        config.ws.port = config.rpc.port;
+        let err = validate(&config).unwrap_err();
+        assert!(err.contains("ws.port and rpc.port"), "{}", err);
+
+        config.rpc.disable = Some(true);
+        assert_eq!(Ok(()), validate(&config));
+    }
+
+    #[test]
+    fn every_violation_is_reported() {
+        let mut config = preset();
+        config.network.min_peers = Some(60);
+        config.network.max_peers = Some(30);
+        config.ws.disable = Some(false);
+        config.ws.port = config.rpc.port;
+        let err = validate(&config).unwrap_err();
+        assert!(err.contains("network.min_peers") && err.contains("ws.port"), "{}", err);
+    }
+
+    #[test]
+    fn metrics_port_same_as_rpc_port() {
+        let mut config = preset();
+        config.metrics.disable = Some(false);
+        config.metrics.port = config.rpc.port;
+        let err = validate(&config).unwrap_err();
+        assert!(err.contains("metrics.port and rpc.port"), "{}", err);
+
+        config.metrics.disable = Some(true);
+        assert_eq!(Ok(()), validate(&config));
+    }
+
+    #[test]
+    fn snapshot_keep_is_zero() {
+        let mut config = preset();
+        config.snapshot.disable = Some(false);
+        config.snapshot.keep = Some(0);
+        let err = validate(&config).unwrap_err();
+        assert!(err.contains("snapshot.keep"), "{}", err);
+
+        config.snapshot.disable = Some(true);
+        assert_eq!(Ok(()), validate(&config));
+    }
+
+    #[test]
+    fn notify_work_secret_without_url() {
+        let mut config = preset();
+        config.mining.notify_work = Some(vec!["http://127.0.0.1:8000/".to_string()]);
+        let mut secrets = HashMap::new();
+        secrets.insert("http://127.0.0.1:8000/".to_string(), "secret".to_string());
+        config.mining.notify_work_secrets = Some(secrets.clone());
+        assert_eq!(Ok(()), validate(&config));
+
+        secrets.insert("http://127.0.0.1:8001/".to_string(), "secret".to_string());
+        config.mining.notify_work_secrets = Some(secrets);
+        let err = validate(&config).unwrap_err();
+        assert!(err.contains("http://127.0.0.1:8001/"), "{}", err);
+        assert!(!err.contains("http://127.0.0.1:8000/"), "{}", err);
+    }
+
+    #[test]
+    fn asset_log_retention_is_zero() {
+        let mut config = preset();
+        config.operating.asset_log_retention = Some(0);
+        assert_eq!(Ok(()), validate(&config));
+
+        config.operating.asset_log = Some(true);
+        let err = validate(&config).unwrap_err();
+        assert!(err.contains("codechain.asset_log_retention"), "{}", err);
+    }
+}
@@ -16,3 +16,4 @@
 
 pub const DEFAULT_KEYS_PATH: &str = "keys";
 pub const DEFAULT_DB_PATH: &str = "db";
+pub const DEFAULT_NODE_KEY_PATH: &str = "node.key";
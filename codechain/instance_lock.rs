@@ -0,0 +1,163 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::APP_INFO;
+use app_dirs::{app_dir, AppDataType};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// The lock file of a running instance. It lists the ports and the paths that the instance uses,
+/// so that another instance using any of them fails to start with a clear error.
+/// The file is removed when the lock is dropped.
+pub struct InstanceLock {
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Writes the lock file of the instance in the application directory.
+    /// `resources` are the names and the values of the ports and the paths the instance uses.
+    pub fn acquire(instance_id: usize, resources: &[(&str, String)]) -> Result<Self, String> {
+        let dir = app_dir(AppDataType::UserCache, &APP_INFO, "instances")
+            .map_err(|e| format!("Cannot create the directory for the instance lock files: {}", e))?;
+        Self::acquire_in(&dir, instance_id, resources)
+    }
+
+    fn acquire_in(dir: &Path, instance_id: usize, resources: &[(&str, String)]) -> Result<Self, String> {
+        let entries =
+            fs::read_dir(dir).map_err(|e| format!("Cannot read the instance lock files in {:?}: {}", dir, e))?;
+        for path in entries.flat_map(Result::ok).map(|entry| entry.path()) {
+            let other_id = match lock_file_instance_id(&path) {
+                Some(other_id) => other_id,
+                None => continue,
+            };
+            // The file may be removed by its instance in the meantime.
+            let (pid, other_resources) = match fs::read_to_string(&path).ok().and_then(|content| parse(&content)) {
+                Some(lock) => lock,
+                None => continue,
+            };
+            if pid == process::id() || !is_running(pid) {
+                continue
+            }
+            if other_id == instance_id {
+                return Err(format!(
+                    "The instance #{} is already running (pid {}, lock file {:?})",
+                    instance_id, pid, path
+                ))
+            }
+            for (name, value) in resources {
+                if let Some((other_name, _)) = other_resources.iter().find(|(_, other_value)| other_value == value) {
+                    return Err(format!(
+                        "The {} {} of the instance #{} is already used as the {} of the instance #{} \
+                         (pid {}, lock file {:?}). Configure another value, \
+                         or remove the lock file if the instance is not running",
+                        name, value, instance_id, other_name, other_id, pid, path
+                    ))
+                }
+            }
+        }
+
+        let path = dir.join(format!("{}.lock", instance_id));
+        let mut content = format!("pid {}\n", process::id());
+        for (name, value) in resources {
+            content.push_str(&format!("{} {}\n", name, value));
+        }
+        fs::write(&path, content).map_err(|e| format!("Cannot write the instance lock file {:?}: {}", path, e))?;
+        Ok(Self {
+            path,
+        })
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn lock_file_instance_id(path: &Path) -> Option<usize> {
+    if path.extension().and_then(OsStr::to_str) != Some("lock") {
+        return None
+    }
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+/// Parses the pid and the resources written by `InstanceLock::acquire_in`.
+fn parse(content: &str) -> Option<(u32, Vec<(String, String)>)> {
+    let mut entries = content.lines().map(|line| {
+        let mut fields = line.splitn(2, ' ');
+        Some((fields.next()?.to_string(), fields.next()?.to_string()))
+    });
+    let (name, pid) = entries.next()??;
+    if name != "pid" {
+        return None
+    }
+    let resources = entries.collect::<Option<_>>()?;
+    Some((pid.parse().ok()?, resources))
+}
+
+/// The processes are looked up in /proc. Without it, every process with a lock file is considered running.
+fn is_running(pid: u32) -> bool {
+    let proc_dir = Path::new("/proc");
+    !proc_dir.is_dir() || proc_dir.join(pid.to_string()).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resources(network_port: u16, rpc_port: u16) -> Vec<(&'static str, String)> {
+        vec![("network.port", network_port.to_string()), ("rpc.port", rpc_port.to_string())]
+    }
+
+    #[test]
+    fn lock_file_is_written_and_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = InstanceLock::acquire_in(dir.path(), 3, &resources(3515, 8110)).unwrap();
+        let content = fs::read_to_string(dir.path().join("3.lock")).unwrap();
+        assert_eq!(
+            Some((process::id(), vec![
+                ("network.port".to_string(), "3515".to_string()),
+                ("rpc.port".to_string(), "8110".to_string())
+            ])),
+            parse(&content)
+        );
+
+        drop(lock);
+        assert!(!dir.path().join("3.lock").exists());
+    }
+
+    #[test]
+    fn resource_used_by_another_running_instance_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        // The process 1 is always running.
+        fs::write(dir.path().join("1.lock"), "pid 1\nnetwork.port 3495\nrpc.port 9000\n").unwrap();
+
+        assert!(InstanceLock::acquire_in(dir.path(), 2, &resources(3505, 8100)).is_ok());
+        let err = InstanceLock::acquire_in(dir.path(), 2, &resources(3505, 9000)).err().unwrap();
+        assert!(err.contains("rpc.port 9000 of the instance #2"), "{}", err);
+        assert!(err.contains("rpc.port of the instance #1"), "{}", err);
+        assert!(InstanceLock::acquire_in(dir.path(), 1, &resources(3505, 8100)).is_err());
+    }
+
+    #[test]
+    fn lock_files_of_this_process_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let _lock = InstanceLock::acquire_in(dir.path(), 1, &resources(3495, 8090)).unwrap();
+        assert!(InstanceLock::acquire_in(dir.path(), 2, &resources(3495, 8090)).is_ok());
+    }
+}
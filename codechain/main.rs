@@ -36,8 +36,10 @@ extern crate codechain_keystore as ckeystore;
 extern crate codechain_logger as clogger;
 extern crate cidr;
 extern crate codechain_network as cnetwork;
+extern crate codechain_node as cnode;
 extern crate codechain_rpc as crpc;
 extern crate codechain_state as cstate;
+extern crate codechain_stratum as cstratum;
 extern crate codechain_sync as csync;
 extern crate codechain_timer as ctimer;
 extern crate codechain_types as ctypes;
@@ -57,10 +59,9 @@ extern crate toml;
 mod auto_self_nominate;
 mod config;
 mod constants;
-mod dummy_network_service;
+mod instance_lock;
 mod json;
-mod rpc;
-mod rpc_apis;
+mod metrics;
 mod run_node;
 mod subcommand;
 
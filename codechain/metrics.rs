@@ -0,0 +1,212 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use clogger::metric::METRICS;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// A scrape that doesn't send its request within this time is dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// The connections beyond this are dropped until the others are answered.
+const MAX_CONNECTIONS: usize = 16;
+
+#[derive(Debug, PartialEq)]
+pub struct MetricsConfig {
+    pub interface: String,
+    pub port: u16,
+}
+
+/// Serves `GET /metrics` from its own thread, so a slow scraper never blocks the IO threads.
+pub struct MetricsServer {
+    address: SocketAddr,
+    closing: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl MetricsServer {
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn close(self) {
+        self.closing.store(true, Ordering::SeqCst);
+        // Wake up the blocking accept.
+        let _ = TcpStream::connect(self.address);
+        if self.handle.join().is_err() {
+            cerror!(METRICS, "The metrics server thread panicked");
+        }
+    }
+}
+
+pub fn metrics_start(cfg: &MetricsConfig) -> Result<MetricsServer, String> {
+    let url = format!("{}:{}", cfg.interface, cfg.port);
+    let addr: SocketAddr = url.parse().map_err(|_| format!("Invalid metrics listen host/port given: {}", url))?;
+    let listener = TcpListener::bind(addr).map_err(|err| match err.kind() {
+        io::ErrorKind::AddrInUse => format!("Metrics address {} is already in use, make sure that another instance of a CodeChain node is not running or change the address using the --metrics-port option.", url),
+        _ => format!("Metrics server error: {}", err),
+    })?;
+    let address = listener.local_addr().map_err(|err| format!("Metrics server error: {}", err))?;
+    let closing = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let closing = Arc::clone(&closing);
+        thread::Builder::new()
+            .name("metrics".to_string())
+            .spawn(move || serve(&listener, &closing))
+            .map_err(|err| format!("Cannot spawn the metrics server thread: {}", err))?
+    };
+    cinfo!(METRICS, "Metrics Listening on {}", address);
+    Ok(MetricsServer {
+        address,
+        closing,
+        handle,
+    })
+}
+
+fn serve(listener: &TcpListener, closing: &AtomicBool) {
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        if closing.load(Ordering::SeqCst) {
+            break
+        }
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                cdebug!(METRICS, "Cannot accept a scrape: {}", err);
+                continue
+            }
+        };
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            cdebug!(METRICS, "Too many scrapes are in progress. Dropping a connection");
+            continue
+        }
+        // Each connection is served from its own thread, so a stalled scraper doesn't block the others.
+        let spawned = {
+            let connections = Arc::clone(&connections);
+            thread::Builder::new().name("metrics connection".to_string()).spawn(move || {
+                if let Err(err) = respond(stream) {
+                    cdebug!(METRICS, "Cannot respond to a scrape: {}", err);
+                }
+                connections.fetch_sub(1, Ordering::SeqCst);
+            })
+        };
+        if let Err(err) = spawned {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            cerror!(METRICS, "Cannot spawn a metrics connection thread: {}", err);
+        }
+    }
+}
+
+fn respond(stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers. The request has no body.
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", METRICS.render()),
+        (Some("GET"), _) => ("404 Not Found", "Not Found\n".to_string()),
+        _ => ("405 Method Not Allowed", "Method Not Allowed\n".to_string()),
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn get(address: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn start() -> MetricsServer {
+        metrics_start(&MetricsConfig {
+            interface: "127.0.0.1".to_string(),
+            port: 0,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn scrape_exposition() {
+        METRICS.gauge("codechain_block_height", "The number of the best block").set(42);
+        let server = start();
+        let response = get(server.address(), "/metrics");
+        server.close();
+
+        let (head, body) = response.split_at(response.find("\r\n\r\n").unwrap() + 4);
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+        assert!(head.contains(&format!("Content-Length: {}\r\n", body.len())), "{}", head);
+        assert!(body.contains("# HELP codechain_block_height The number of the best block\n"), "{}", body);
+        assert!(body.contains("# TYPE codechain_block_height gauge\n"), "{}", body);
+        assert!(body.contains("\ncodechain_block_height 42\n"), "{}", body);
+        for line in body.lines() {
+            if line.starts_with('#') {
+                assert!(line.starts_with("# HELP ") || line.starts_with("# TYPE "), "{}", line);
+            } else {
+                let mut sample = line.split(' ');
+                assert!(sample.next().is_some(), "{}", line);
+                assert!(sample.next().unwrap().parse::<i64>().is_ok(), "{}", line);
+                assert_eq!(None, sample.next(), "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn stalled_scraper_does_not_block_the_others() {
+        let server = start();
+        // Connects without sending a request, so its thread waits until the read timeout.
+        let stalled = TcpStream::connect(server.address()).unwrap();
+        let response = get(server.address(), "/metrics");
+        drop(stalled);
+        server.close();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    }
+
+    #[test]
+    fn unknown_path() {
+        let server = start();
+        let response = get(server.address(), "/");
+        server.close();
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", response);
+    }
+}
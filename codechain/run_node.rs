@@ -16,167 +16,172 @@
 
 use crate::auto_self_nominate::AutoSelfNomination;
 use crate::config::{self, load_config};
-use crate::constants::{DEFAULT_DB_PATH, DEFAULT_KEYS_PATH};
-use crate::dummy_network_service::DummyNetworkService;
+use crate::constants::{DEFAULT_DB_PATH, DEFAULT_KEYS_PATH, DEFAULT_NODE_KEY_PATH};
+use crate::instance_lock::InstanceLock;
 use crate::json::PasswordFile;
-use crate::rpc::{rpc_http_start, rpc_ipc_start, rpc_ws_start};
-use crate::rpc_apis::ApiDependencies;
+use crate::metrics::metrics_start;
 use ccore::{
-    AccountProvider, AccountProviderError, BlockId, ChainNotify, Client, ClientConfig, ClientService, ConsensusClient,
-    EngineClient, EngineInfo, EngineType, Miner, MinerService, PeerDb, Scheme, Stratum, StratumConfig, StratumError,
-    NUM_COLUMNS,
+    migrate_database, pending_migrations, AccountProvider, AccountProviderError, ClientConfig, ConsensusClient,
+    EngineType, MinerService, QueueConfig, ReadOnlyDatabase, TimedDatabase, MIGRATION_BATCH_SIZE,
 };
-use cdiscovery::{Config, Discovery};
-use ckey::{Address, NetworkId, PlatformAddress};
+use cdiscovery::{Config, Mode as DiscoveryMode};
+use ckey::{Address, Generator, KeyPair, PlatformAddress, Private, Random};
 use ckeystore::accounts_dir::RootDiskDirectory;
 use ckeystore::KeyStore;
 use clap::ArgMatches;
 use clogger::{self, EmailAlarm, LoggerConfig};
-use cnetwork::{Filters, ManagingPeerdb, NetworkConfig, NetworkControl, NetworkService, RoutingTable, SocketAddr};
-use csync::{BlockSyncExtension, BlockSyncSender, SnapshotService, TransactionSyncExtension};
+use cnode::{DiscoveryOptions, NetworkOptions, NodeBuilder, NodeError, RpcOptions, SnapshotOptions};
 use ctimer::TimerLoop;
 use ctrlc::CtrlC;
 use fdlimit::raise_fd_limit;
 use kvdb::KeyValueDB;
-use kvdb_rocksdb::{Database, DatabaseConfig};
+use kvdb_rocksdb::Database;
 use parking_lot::{Condvar, Mutex};
 use std::fs;
+use std::io::Write;
 use std::path::Path;
-use std::sync::{Arc, Weak};
-use std::time::{SystemTime, UNIX_EPOCH};
-
-fn network_start(
-    network_id: NetworkId,
-    timer_loop: TimerLoop,
-    cfg: &NetworkConfig,
-    routing_table: Arc<RoutingTable>,
-    peer_db: Box<dyn ManagingPeerdb>,
-) -> Result<Arc<NetworkService>, String> {
-    let addr = cfg.address.parse().map_err(|_| format!("Invalid NETWORK listen host given: {}", cfg.address))?;
-    let sockaddress = SocketAddr::new(addr, cfg.port);
-    let filters = Filters::new(cfg.whitelist.clone(), cfg.blacklist.clone());
-    let service = NetworkService::start(
-        network_id,
-        timer_loop,
-        sockaddress,
-        cfg.bootstrap_addresses.clone(),
-        cfg.min_peers,
-        cfg.max_peers,
-        filters,
-        routing_table,
-        peer_db,
-    )
-    .map_err(|e| format!("Network service error: {:?}", e))?;
-
-    Ok(service)
-}
+use std::process;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The node is killed if it cannot stop the services within this time.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
 
 fn self_nominate_start(c: Arc<dyn ConsensusClient>, matches: &ArgMatches, ap: Arc<AccountProvider>, address: Address) {
     let auto_self_nominate = AutoSelfNomination::new(c, ap, address);
     auto_self_nominate.send_self_nominate_transaction(matches);
 }
 
-fn discovery_start(
-    service: &NetworkService,
-    cfg: &config::Network,
-    routing_table: Arc<RoutingTable>,
-) -> Result<(), String> {
-    let config = Config {
-        bucket_size: cfg.discovery_bucket_size.unwrap(),
-        t_refresh: cfg.discovery_refresh.unwrap(),
+fn discovery_options(config: &config::Config) -> Result<Option<DiscoveryOptions>, String> {
+    let mode = match config.discovery_mode()? {
+        DiscoveryMode::Disabled => {
+            cwarn!(DISCOVERY, "Node runs without discovery extension");
+            return Ok(None)
+        }
+        mode => mode,
     };
+    let cfg = &config.network;
     let use_kademlia = match cfg.discovery_type.as_ref().map(String::as_str) {
         Some("unstructured") => false,
         Some("kademlia") => true,
         Some(discovery_type) => return Err(format!("Unknown discovery {}", discovery_type)),
-        None => return Ok(()),
+        None => return Ok(None),
     };
-    service.register_extension(move |api| Discovery::new(routing_table, config, api, use_kademlia));
-    Ok(())
+    Ok(Some(DiscoveryOptions {
+        config: Config {
+            bucket_size: cfg.discovery_bucket_size.unwrap(),
+            t_refresh: cfg.discovery_refresh.unwrap(),
+            mode,
+        },
+        use_kademlia,
+    }))
 }
 
-fn client_start(
-    client_config: &ClientConfig,
-    timer_loop: &TimerLoop,
-    db: Arc<dyn KeyValueDB>,
-    scheme: &Scheme,
-    miner: Arc<Miner>,
-) -> Result<ClientService, String> {
-    cinfo!(CLIENT, "Starting client");
-    let reseal_timer = timer_loop.new_timer_with_name("Client reseal timer");
-    let service = ClientService::start(client_config, &scheme, db, miner, reseal_timer.clone())
-        .map_err(|e| format!("Client service error: {}", e))?;
-    reseal_timer.set_handler(Arc::downgrade(&service.client()));
-
-    Ok(service)
+fn network_options(config: &config::Config) -> Result<Option<NetworkOptions>, String> {
+    if config.network.disable.unwrap() || config.operating.read_only.unwrap_or(false) {
+        return Ok(None)
+    }
+    Ok(Some(NetworkOptions {
+        config: config.network_config()?,
+        node_key: load_node_key(&Path::new(&config.operating.instance_path()).join(DEFAULT_NODE_KEY_PATH))?,
+        discovery: discovery_options(config)?,
+        sync: if config.network.sync.unwrap() {
+            Some(config.spot_check_config())
+        } else {
+            None
+        },
+        transaction_relay: config.network.transaction_relay.unwrap(),
+    }))
 }
 
-fn stratum_start(cfg: &StratumConfig, miner: &Arc<Miner>, client: Arc<Client>) -> Result<(), String> {
-    match Stratum::start(cfg, Arc::clone(&miner), client) {
-        // FIXME: Add specified condition like AddrInUse
-        Err(StratumError::Service(_)) =>
-            Err(format!("STRATUM address {} is already in use, make sure that another instance of a CodeChain node is not running or change the address using the --stratum-port option.", cfg.port)),
-        Err(e) => Err(format!("STRATUM start error: {:?}", e)),
-        Ok(stratum) => {
-            miner.add_work_listener(Box::new(stratum));
-            cinfo!(STRATUM, "Listening on {}", cfg.port);
-            Ok(())
-        }
+fn snapshot_options(config: &config::Config) -> Option<SnapshotOptions> {
+    if config.snapshot.disable.unwrap() {
+        return None
     }
+    Some(SnapshotOptions {
+        path: config.snapshot.path.clone().unwrap().into(),
+        period: config.snapshot.period,
+        keep: config.snapshot.keep.unwrap(),
+    })
 }
 
-fn new_miner(
-    config: &config::Config,
-    scheme: &Scheme,
-    ap: Arc<AccountProvider>,
-    db: Arc<dyn KeyValueDB>,
-) -> Result<Arc<Miner>, String> {
-    let miner = Miner::new(config.miner_options()?, scheme, ap, db);
+fn rpc_options(config: &config::Config) -> Result<RpcOptions, String> {
+    Ok(RpcOptions {
+        http: if config.rpc.disable.unwrap() {
+            None
+        } else {
+            Some(config.rpc_http_config()?)
+        },
+        ipc: if config.ipc.disable.unwrap() {
+            None
+        } else {
+            Some(config.rpc_ipc_config())
+        },
+        ws: if config.ws.disable.unwrap() {
+            None
+        } else {
+            Some(config.rpc_ws_config()?)
+        },
+        executor: config.rpc_executor_config(),
+        enable_devel_api: config.rpc.enable_devel_api,
+        max_subscriptions: config.ws.max_subscriptions.unwrap(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        commit_hash: env!("VERGEN_SHA").to_string(),
+    })
+}
 
-    match miner.engine_type() {
+/// Returns the address the miner authors the blocks with.
+pub fn mining_author(config: &config::Config, engine_type: EngineType) -> Result<Option<Address>, String> {
+    match engine_type {
         EngineType::PoW => match &config.mining.author {
-            Some(ref author) => {
-                miner.set_author((*author).into_address()).expect("set_author never fails when PoW is used")
-            }
-            None if config.mining.engine_signer.is_some() => return Err("PoW type engine needs not an engine-signer but an author for mining. Specify the author using --author option.".to_string()),
-            None => (),
+            Some(ref author) => Ok(Some((*author).into_address())),
+            None if config.mining.engine_signer.is_some() => Err("PoW type engine needs not an engine-signer but an author for mining. Specify the author using --author option.".to_string()),
+            None => Ok(None),
         },
         EngineType::PBFT | EngineType::PoA => match &config.mining.engine_signer {
-            Some(ref engine_signer) => match miner.set_author((*engine_signer).into_address()) {
-                Err(AccountProviderError::NotUnlocked) => {
-                    return Err(
-                        format!("The account {} is not unlocked. The key file should exist in the keys_path directory, and the account's password should exist in the password_path file.", engine_signer)
-                    )
-                }
-                Err(e) => return Err(format!("{}", e)),
-                _ => (),
-            },
+            Some(ref engine_signer) => Ok(Some((*engine_signer).into_address())),
             None if config.mining.author.is_some() => {
-                return Err("PBFT or PoA type engine needs not an author but an engine signer for mining. Specify the engine signer using --engine-signer option."
+                Err("PBFT or PoA type engine needs not an author but an engine signer for mining. Specify the engine signer using --engine-signer option."
                     .to_string())
             }
-            None => (),
+            None => Ok(None),
         },
-        EngineType::Solo => miner
-            .set_author(config.mining.author.map_or(Address::default(), PlatformAddress::into_address))
-            .expect("set_author never fails when Solo is used"),
+        EngineType::Solo => Ok(Some(config.mining.author.map_or(Address::default(), PlatformAddress::into_address))),
     }
-
-    Ok(miner)
 }
 
 fn wait_for_exit() {
-    let exit = Arc::new((Mutex::new(()), Condvar::new()));
+    let exit = Arc::new((Mutex::new(false), Condvar::new()));
 
-    // Handle possible exits
+    // Handle possible exits. The handler only sets the flag, so signals during the shutdown are harmless.
     let e = exit.clone();
     CtrlC::set_handler(move || {
+        *e.0.lock() = true;
         e.1.notify_all();
     });
 
     // Wait for signal
-    let mut l = exit.0.lock();
-    exit.1.wait(&mut l);
+    let mut exiting = exit.0.lock();
+    while !*exiting {
+        exit.1.wait(&mut exiting);
+    }
+}
+
+/// Kills the process unless the returned sender is dropped within `timeout`.
+fn start_shutdown_watchdog(timeout: Duration) -> mpsc::Sender<()> {
+    let (sender, receiver) = mpsc::channel::<()>();
+    thread::Builder::new()
+        .name("shutdown watchdog".to_string())
+        .spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(timeout) {
+                cerror!(SHUTDOWN, "Cannot stop the node in {:?}", timeout);
+                process::exit(1);
+            }
+        })
+        .expect("Failed to create the shutdown watchdog");
+    sender
 }
 
 fn prepare_account_provider(keys_path: &str) -> Result<Arc<AccountProvider>, String> {
@@ -210,20 +215,88 @@ fn unlock_accounts(ap: &AccountProvider, pf: &PasswordFile) -> Result<(), String
     Ok(())
 }
 
+/// Loads the long-term key of the node, whose public key is the node id signed in the handshakes.
+/// The key is created on the first run and kept in the instance directory, so that the node id survives restarts.
+fn load_node_key(path: &Path) -> Result<KeyPair, String> {
+    if path.exists() {
+        // The contents of the file must not be printed since they are the secret.
+        let private = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot open the node key file {:?}: {:?}", path, e))?
+            .trim()
+            .parse::<Private>()
+            .map_err(|_| format!("Invalid node key file {:?}: The node key must be 32 bytes in hex", path))?;
+        return KeyPair::from_private(private).map_err(|e| format!("Invalid node key file {:?}: {}", path, e))
+    }
+
+    let key_pair = Random.generate().map_err(|e| format!("Cannot generate the node key: {}", e))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create the directory {:?}: {}", dir, e))?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(format!("{:x}", **key_pair.private()).as_bytes()))
+        .map_err(|e| format!("Cannot write the node key file {:?}: {}", path, e))?;
+    cinfo!(NETWORK, "Created the node key in {:?}", path);
+    Ok(key_pair)
+}
+
+pub fn db_path(cfg: &config::Operating) -> String {
+    cfg.db_path.as_ref().map(String::clone).unwrap_or_else(|| cfg.instance_path() + "/" + DEFAULT_DB_PATH)
+}
+
+/// The ports and the paths that the node uses, which no other instance should use at the same time.
+fn instance_resources(config: &config::Config) -> Vec<(&'static str, String)> {
+    let mut resources = vec![("codechain.db_path", db_path(&config.operating))];
+    let ports = [
+        ("network.port", config.network.disable, config.network.port),
+        ("rpc.port", config.rpc.disable, config.rpc.port),
+        ("ws.port", config.ws.disable, config.ws.port),
+        ("stratum.port", config.stratum.disable, config.stratum.port),
+        ("metrics.port", config.metrics.disable, config.metrics.port),
+    ];
+    for (name, disable, port) in ports.iter() {
+        if let (Some(false), Some(port)) = (disable, port) {
+            resources.push((*name, port.to_string()));
+        }
+    }
+    if let (Some(false), Some(path)) = (config.ipc.disable, &config.ipc.path) {
+        resources.push(("ipc.path", path.clone()));
+    }
+    resources
+}
+
 pub fn open_db(cfg: &config::Operating, client_config: &ClientConfig) -> Result<Arc<dyn KeyValueDB>, String> {
-    let base_path = cfg.base_path.as_ref().unwrap().clone();
-    let db_path = cfg.db_path.as_ref().map(String::clone).unwrap_or_else(|| base_path + "/" + DEFAULT_DB_PATH);
+    let db_path = db_path(cfg);
     let client_path = Path::new(&db_path);
-    let mut db_config = DatabaseConfig::with_columns(NUM_COLUMNS);
-
-    db_config.memory_budget = client_config.db_cache_size;
-    db_config.compaction = client_config.db_compaction.compaction_profile(client_path);
+    let db_config = client_config.database_config(client_path)?;
+
+    if client_config.read_only {
+        let db = ReadOnlyDatabase::open(client_path, &db_config)
+            .map_err(|e| format!("Cannot open the database {} in read-only mode: {}", db_path, e))?;
+        let pending = pending_migrations(&db).map_err(|e| e.to_string())?;
+        if !pending.is_empty() {
+            return Err(format!(
+                "The database {} needs {} migrations, which can't run in read-only mode. Run `codechain db migrate`",
+                db_path,
+                pending.len()
+            ))
+        }
+        return Ok(Arc::new(db))
+    }
 
     let db = Arc::new(
         Database::open(&db_config, &client_path.to_str().expect("DB path could not be converted to string."))
             .map_err(|_e| "Low level database error. Some issue with disk?".to_string())?,
     );
-
+    migrate_database(&*db, MIGRATION_BATCH_SIZE)
+        .map_err(|e| format!("Cannot migrate the database {}: {}", db_path, e))?;
     Ok(db)
 }
 
@@ -231,9 +304,10 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
     // increase max number of open files
     raise_fd_limit();
 
-    let timer_loop = TimerLoop::new(2);
-
     let config = load_config(matches)?;
+    for notice in config::deprecation_notices(matches) {
+        println!("{}", notice);
+    }
 
     let time_gap_params = config.mining.create_time_gaps();
     let scheme = match &config.operating.chain {
@@ -261,118 +335,103 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
     };
     clogger::init(&LoggerConfig::new(instance_id), email_alarm.clone())
         .expect("Logger must be successfully initialized");
+    panic_hook::set_instance_id(instance_id);
     if let Some(email_alarm) = email_alarm {
         panic_hook::set_with_email_alarm(email_alarm);
     }
+    // Held until the node stops.
+    let _instance_lock = match config.operating.instance_id {
+        Some(instance_id) => Some(InstanceLock::acquire(instance_id, &instance_resources(&config))?),
+        None => None,
+    };
 
     let pf = load_password_file(&config.operating.password_path)?;
-    let base_path = config.operating.base_path.as_ref().unwrap().clone();
-    let keys_path =
-        config.operating.keys_path.as_ref().map(String::clone).unwrap_or_else(|| base_path + "/" + DEFAULT_KEYS_PATH);
+    let keys_path = config
+        .operating
+        .keys_path
+        .as_ref()
+        .map(String::clone)
+        .unwrap_or_else(|| config.operating.instance_path() + "/" + DEFAULT_KEYS_PATH);
     let ap = prepare_account_provider(&keys_path)?;
     unlock_accounts(&*ap, &pf)?;
 
-    let client_config: ClientConfig = Default::default();
-    let db = open_db(&config.operating, &client_config)?;
-
-    let miner = new_miner(&config, &scheme, ap.clone(), Arc::clone(&db))?;
-    let client = client_start(&client_config, &timer_loop, db, &scheme, miner.clone())?;
-    miner.recover_from_db(client.client().as_ref());
-
-    let mut _maybe_sync = None;
-    let mut maybe_sync_sender = None;
-
-    scheme.engine.register_chain_notify(client.client().as_ref());
-
-    let network_service: Arc<dyn NetworkControl> = {
-        if !config.network.disable.unwrap() {
-            let network_config = config.network_config()?;
-            // XXX: What should we do if the network id has been changed.
-            let c = client.client();
-            let network_id = c.network_id();
-            let peer_db = PeerDb::new(c.get_kvdb());
-            let routing_table = RoutingTable::new();
-            let service = network_start(network_id, timer_loop, &network_config, Arc::clone(&routing_table), peer_db)?;
-
-            if config.network.discovery.unwrap() {
-                discovery_start(&service, &config.network, routing_table)?;
-            } else {
-                cwarn!(DISCOVERY, "Node runs without discovery extension");
-            }
-
-            if config.network.sync.unwrap() {
-                let sync_sender = {
-                    let client = client.client();
-                    service.register_extension(move |api| BlockSyncExtension::new(client, api))
-                };
-                let sync = Arc::new(BlockSyncSender::from(sync_sender.clone()));
-                client.client().add_notify(Arc::downgrade(&sync) as Weak<dyn ChainNotify>);
-                _maybe_sync = Some(sync); // Hold sync to ensure it not to be destroyed.
-                maybe_sync_sender = Some(sync_sender);
-            }
-            if config.network.transaction_relay.unwrap() {
-                let client = client.client();
-                service.register_extension(move |api| TransactionSyncExtension::new(client, api));
-            }
-
-            scheme.engine.register_network_extension_to_service(&service);
-
-            service
-        } else {
-            Arc::new(DummyNetworkService::new())
-        }
+    let client_config = ClientConfig {
+        retention_blocks: config.operating.retention_blocks,
+        address_index: config.operating.address_index.unwrap_or(false),
+        asset_index: config.operating.asset_index.unwrap_or(false),
+        text_index: config.operating.text_index.unwrap_or(false),
+        asset_log: config.operating.asset_log.unwrap_or(false),
+        asset_log_retention: config.operating.asset_log_retention.unwrap(),
+        read_only: config.operating.read_only.unwrap_or(false),
+        audit_stake: config.operating.audit_stake.unwrap_or(false),
+        queue: QueueConfig {
+            enable_metrics: config.operating.import_metrics.unwrap_or(false),
+            ..Default::default()
+        },
+        db_cache_size: config.operating.db_cache_size,
+        db_compaction: config.db_compaction()?,
+        db_columns: config.db_columns()?,
+        state_cache_sizes: config.state_cache_sizes(),
+        ..Default::default()
     };
-    if config.mining.self_nomination_enable {
-        let c = client.client();
-        let address = miner.get_author_address();
-        let accountp = ap.clone();
-        self_nominate_start(c, matches, accountp, address);
-    }
-
-    let rpc_apis_deps = ApiDependencies {
-        client: client.client(),
-        miner: Arc::clone(&miner),
-        network_control: Arc::clone(&network_service),
-        account_provider: ap,
-        block_sync: maybe_sync_sender,
+    let db = open_db(&config.operating, &client_config)?;
+    let db: Arc<dyn KeyValueDB> = if config.metrics.disable.unwrap() {
+        db
+    } else {
+        Arc::new(TimedDatabase::new(db))
     };
+    let read_only = client_config.read_only;
 
-    let rpc_server = {
-        if !config.rpc.disable.unwrap() {
-            Some(rpc_http_start(config.rpc_http_config(), config.rpc.enable_devel_api, &rpc_apis_deps)?)
-        } else {
-            None
-        }
+    if read_only {
+        cinfo!(CLIENT, "The node is in read-only mode");
+    }
+    let engine_type = scheme.engine.engine_type();
+    let author = if read_only {
+        None
+    } else {
+        mining_author(&config, engine_type)?
     };
-
-    let ipc_server = {
-        if !config.ipc.disable.unwrap() {
-            Some(rpc_ipc_start(&config.rpc_ipc_config(), config.rpc.enable_devel_api, &rpc_apis_deps)?)
-        } else {
-            None
-        }
+    let stratum = if !config.stratum.disable.unwrap() && engine_type == EngineType::PoW && !read_only {
+        Some(config.stratum_config()?)
+    } else {
+        None
     };
-
-    let ws_server = {
-        if !config.ws.disable.unwrap() {
-            Some(rpc_ws_start(&config.rpc_ws_config(), config.rpc.enable_devel_api, &rpc_apis_deps)?)
-        } else {
-            None
-        }
+    let timer_loop = match config.operating.timer_coalescing_window {
+        Some(window) => TimerLoop::with_coalescing_window(2, Duration::from_millis(window)),
+        None => TimerLoop::new(2),
     };
-
-    if (!config.stratum.disable.unwrap()) && (miner.engine_type() == EngineType::PoW) {
-        stratum_start(&config.stratum_config(), &miner, client.client())?
+    let node = NodeBuilder::new(&scheme)
+        .db(db)
+        .client_config(client_config)
+        .miner_options(config.miner_options()?)
+        .account_provider(ap.clone())
+        .author(author)
+        .timer_loop(timer_loop)
+        .network(network_options(&config)?)
+        .stratum(stratum)
+        .snapshot(snapshot_options(&config))
+        .rpc(Some(rpc_options(&config)?))
+        .build()
+        .map_err(|err| match (err, &config.mining.engine_signer) {
+            (NodeError::Author(_, AccountProviderError::NotUnlocked), Some(engine_signer)) => format!(
+                "The account {} is not unlocked. The key file should exist in the keys_path directory, and the account's password should exist in the password_path file.",
+                engine_signer
+            ),
+            (err, _) => err.to_string(),
+        })?;
+    let client = node.client();
+    let miner = node.miner();
+
+    if config.mining.self_nomination_enable && !read_only {
+        let c = Arc::clone(&client);
+        let address = miner.get_author_address();
+        let accountp = ap.clone();
+        self_nominate_start(c, matches, accountp, address);
     }
 
-    let _snapshot_service = {
-        if !config.snapshot.disable.unwrap() {
-            // FIXME: Let's make it load snapshot period dynamically to support changing the period.
-            let client = client.client();
-            let snapshot_period = client.common_params(BlockId::Latest).unwrap().snapshot_period();
-            let service = SnapshotService::new(Arc::clone(&client), config.snapshot.path.unwrap(), snapshot_period);
-            client.add_notify(Arc::downgrade(&service) as Weak<dyn ChainNotify>);
-            Some(service)
+    let metrics_server = {
+        if !config.metrics.disable.unwrap() {
+            Some(metrics_start(&config.metrics_config())?)
         } else {
             None
         }
@@ -385,18 +444,15 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
 
     wait_for_exit();
 
-    if let Some(server) = rpc_server {
-        server.close_handle().close();
-        server.wait();
-    }
-    if let Some(server) = ipc_server {
-        server.close_handle().close();
-        server.wait();
-    }
-    if let Some(server) = ws_server {
-        server.close_handle().close();
-        server.wait().map_err(|err| format!("Error while closing jsonrpc ws server: {}", err))?;
+    cinfo!(SHUTDOWN, "Shutting down");
+    let _watchdog = start_shutdown_watchdog(SHUTDOWN_TIMEOUT);
+
+    // Stop the services from the outside in: the metrics server and then the node.
+    if let Some(server) = metrics_server {
+        server.close();
     }
+    node.shutdown().map_err(|err| err.to_string())?;
 
+    cinfo!(SHUTDOWN, "Shutdown complete");
     Ok(())
 }
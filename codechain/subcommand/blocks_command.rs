@@ -0,0 +1,437 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use crate::config::load_config;
+use crate::run_node::open_db;
+use ccore::{
+    AccountProvider, BlockChainClient, BlockChainTrait, BlockId, BlockImportError, BlockStatus, Client, ClientConfig,
+    ClientService, EngineInfo, Error as CoreError, ImportBlock, ImportError, Miner, MinerOptions, Scheme,
+};
+use ckey::NetworkId;
+use clap::ArgMatches;
+use clogger::{self, LoggerConfig};
+use ctimer::TimerLoop;
+use ctypes::{BlockHash, BlockNumber, Header, MAX_BODY_SIZE_LIMIT};
+use kvdb::KeyValueDB;
+use rlp::{DecoderError, Rlp, RlpStream};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The version of the exported file. It is the first field of the file header.
+const FORMAT_VERSION: u8 = 1;
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// An item of the file is a block or the file header. The limit leaves room for the block header.
+const MAX_ITEM_SIZE: usize = MAX_BODY_SIZE_LIMIT + 64 * 1024;
+
+pub fn run_export_command(global: &ArgMatches, matches: &ArgMatches) -> Result<(), String> {
+    let path = matches.value_of("file").expect("Argument 'file' is required");
+    let from = parse_block_number(matches, "from")?.unwrap_or(0);
+    let to = parse_block_number(matches, "to")?;
+
+    clogger::init(&LoggerConfig::new(0), None).expect("Logger must be successfully initialized");
+
+    let (_timer_loop, service) = start_client(global)?;
+    let client = service.client();
+    let to = to.unwrap_or_else(|| client.chain_info().best_block_number);
+    let result = export_blocks(&client, from, to, Path::new(path));
+    service.stop();
+    result
+}
+
+pub fn run_import_command(global: &ArgMatches, matches: &ArgMatches) -> Result<(), String> {
+    let path = matches.value_of("file").expect("Argument 'file' is required");
+
+    clogger::init(&LoggerConfig::new(0), None).expect("Logger must be successfully initialized");
+
+    let (_timer_loop, service) = start_client(global)?;
+    let result = import_blocks(&service.client(), Path::new(path));
+    service.stop();
+    result.map(|_| ())
+}
+
+fn parse_block_number(matches: &ArgMatches, name: &str) -> Result<Option<BlockNumber>, String> {
+    matches
+        .value_of(name)
+        .map(|value| value.parse().map_err(|_| format!("Invalid block number for --{}: {}", name, value)))
+        .transpose()
+}
+
+fn start_client(matches: &ArgMatches) -> Result<(TimerLoop, ClientService), String> {
+    let config = load_config(matches)?;
+    let scheme = match &config.operating.chain {
+        Some(chain) => chain.scheme()?,
+        None => return Err("chain is not specified".to_string()),
+    };
+    let client_config = ClientConfig {
+        retention_blocks: config.operating.retention_blocks,
+        address_index: config.operating.address_index.unwrap_or(false),
+        asset_index: config.operating.asset_index.unwrap_or(false),
+        text_index: config.operating.text_index.unwrap_or(false),
+        asset_log: config.operating.asset_log.unwrap_or(false),
+        asset_log_retention: config.operating.asset_log_retention.unwrap(),
+        db_cache_size: config.operating.db_cache_size,
+        db_compaction: config.db_compaction()?,
+        db_columns: config.db_columns()?,
+        state_cache_sizes: config.state_cache_sizes(),
+        ..Default::default()
+    };
+    let db = open_db(&config.operating, &client_config)?;
+
+    let timer_loop = TimerLoop::new(1);
+    // Neither export nor import produces blocks, so the miner never needs the reseal timer.
+    let miner_options = MinerOptions {
+        no_reseal_timer: true,
+        ..Default::default()
+    };
+    let service = start_client_service(&client_config, &timer_loop, db, &scheme, miner_options)?;
+    Ok((timer_loop, service))
+}
+
+fn start_client_service(
+    client_config: &ClientConfig,
+    timer_loop: &TimerLoop,
+    db: Arc<dyn KeyValueDB>,
+    scheme: &Scheme,
+    miner_options: MinerOptions,
+) -> Result<ClientService, String> {
+    let miner = Miner::new(miner_options, scheme, AccountProvider::transient_provider(), Arc::clone(&db));
+    let reseal_timer = timer_loop.new_timer_with_name("Client reseal timer");
+    let service = ClientService::start(client_config, scheme, db, miner, reseal_timer.clone())
+        .map_err(|e| format!("Client service error: {}", e))?;
+    reseal_timer.set_handler(Arc::downgrade(&service.client()));
+    Ok(service)
+}
+
+/// Writes the blocks from `from` to `to`, inclusive, to `path`.
+///
+/// The file starts with the RLP list `[FORMAT_VERSION, network_id, genesis_hash]`,
+/// followed by the RLP of each block in ascending order.
+fn export_blocks(client: &Client, from: BlockNumber, to: BlockNumber, path: &Path) -> Result<(), String> {
+    let chain_info = client.chain_info();
+    if from > to || to > chain_info.best_block_number {
+        return Err(format!(
+            "Cannot export blocks from #{} to #{}: the best block is #{}",
+            from, to, chain_info.best_block_number
+        ))
+    }
+
+    let write_error = |e: io::Error| format!("Cannot write to {}: {}", path.display(), e);
+    let file = File::create(path).map_err(write_error)?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(&encode_header(client.network_id(), chain_info.genesis_hash)).map_err(write_error)?;
+
+    let total = to - from + 1;
+    let mut progress = ProgressTimer::new();
+    for number in from..=to {
+        let block = client
+            .block(&BlockId::Number(number))
+            .ok_or_else(|| format!("The block #{} is not in the database", number))?;
+        writer.write_all(&block.into_inner()).map_err(write_error)?;
+        if progress.is_due() {
+            eprintln!("Exported {}/{} blocks", number - from + 1, total);
+        }
+    }
+    writer.flush().map_err(write_error)?;
+    eprintln!("Exported {} blocks (#{} to #{}) to {}", total, from, to, path.display());
+    Ok(())
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct ImportSummary {
+    imported: usize,
+    skipped: usize,
+}
+
+/// Feeds the blocks in `path` to the verification queue and waits until they are in the chain.
+///
+/// Blocks that are already in the database or the queue are skipped.
+fn import_blocks(client: &Client, path: &Path) -> Result<ImportSummary, String> {
+    let read_error = |e: io::Error| format!("Cannot read {}: {}", path.display(), e);
+    let file = File::open(path).map_err(read_error)?;
+    let mut reader = BufReader::new(file);
+
+    let header = read_item(&mut reader).map_err(read_error)?.ok_or_else(|| format!("{} is empty", path.display()))?;
+    let (network_id, genesis_hash) = decode_header(&header)?;
+    let expected_genesis_hash = client.chain_info().genesis_hash;
+    if genesis_hash != expected_genesis_hash {
+        return Err(format!(
+            "The genesis block of the file ({}) does not match the genesis block of the chain ({})",
+            genesis_hash, expected_genesis_hash
+        ))
+    }
+    if network_id != client.network_id() {
+        return Err(format!(
+            "The network id of the file ({}) does not match the network id of the chain ({})",
+            network_id,
+            client.network_id()
+        ))
+    }
+
+    let mut summary = ImportSummary::default();
+    let mut last_imported = None;
+    let mut progress = ProgressTimer::new();
+    while let Some(bytes) = read_item(&mut reader).map_err(read_error)? {
+        let block_header: Header =
+            Rlp::new(&bytes).val_at(0).map_err(|e| format!("Invalid block in {}: {:?}", path.display(), e))?;
+        while client.queue_info().is_full() {
+            thread::sleep(QUEUE_POLL_INTERVAL);
+        }
+        match client.import_block(bytes) {
+            Ok(hash) => {
+                summary.imported += 1;
+                last_imported = Some((block_header.number(), hash));
+            }
+            Err(BlockImportError::Import(ImportError::AlreadyInChain))
+            | Err(BlockImportError::Import(ImportError::AlreadyQueued)) => summary.skipped += 1,
+            Err(err) => {
+                return Err(format!("Cannot import the block #{}: {}", block_header.number(), CoreError::from(err)))
+            }
+        }
+        if progress.is_due() {
+            eprintln!(
+                "Queued {} blocks, skipped {} blocks, best block #{}",
+                summary.imported,
+                summary.skipped,
+                client.chain_info().best_block_number
+            );
+        }
+    }
+
+    if let Some((number, hash)) = last_imported {
+        wait_for_block(client, number, hash)?;
+    }
+    eprintln!("Imported {} blocks, skipped {} blocks", summary.imported, summary.skipped);
+    Ok(summary)
+}
+
+/// Waits until the verification queue is done with the given block.
+fn wait_for_block(client: &Client, number: BlockNumber, hash: BlockHash) -> Result<(), String> {
+    loop {
+        match client.block_status(&BlockId::Hash(hash)) {
+            BlockStatus::InChain => return Ok(()),
+            BlockStatus::Queued | BlockStatus::Pending => thread::sleep(QUEUE_POLL_INTERVAL),
+            BlockStatus::Bad | BlockStatus::Unknown => {
+                return Err(format!("The block #{} ({}) failed verification; see the log for details", number, hash))
+            }
+        }
+    }
+}
+
+fn encode_header(network_id: NetworkId, genesis_hash: BlockHash) -> Vec<u8> {
+    let mut s = RlpStream::new_list(3);
+    s.append(&FORMAT_VERSION).append(&network_id).append(&genesis_hash);
+    s.out()
+}
+
+fn decode_header(bytes: &[u8]) -> Result<(NetworkId, BlockHash), String> {
+    let rlp = Rlp::new(bytes);
+    let invalid_header = |e: DecoderError| format!("Invalid file header: {:?}", e);
+    let version: u8 = rlp.val_at(0).map_err(invalid_header)?;
+    if version != FORMAT_VERSION {
+        return Err(format!("Unsupported file format version {}", version))
+    }
+    Ok((rlp.val_at(1).map_err(invalid_header)?, rlp.val_at(2).map_err(invalid_header)?))
+}
+
+/// Reads the next RLP list from `reader`. Returns `None` at the end of the file.
+fn read_item(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut prefix = [0u8; 1];
+    if let Err(err) = reader.read_exact(&mut prefix) {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            return Ok(None)
+        }
+        return Err(err)
+    }
+
+    let mut item = prefix.to_vec();
+    let payload_len = match prefix[0] {
+        0xc0..=0xf7 => u64::from(prefix[0] - 0xc0),
+        0xf8..=0xff => {
+            let len_of_len = (prefix[0] - 0xf7) as usize;
+            let mut len_bytes = [0u8; 8];
+            reader.read_exact(&mut len_bytes[8 - len_of_len..])?;
+            item.extend_from_slice(&len_bytes[8 - len_of_len..]);
+            u64::from_be_bytes(len_bytes)
+        }
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "expected an RLP list")),
+    };
+    // The length comes from the file, so it is checked before allocating the item.
+    if payload_len > MAX_ITEM_SIZE as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the item is {} bytes long, but the limit is {} bytes", payload_len, MAX_ITEM_SIZE),
+        ))
+    }
+    let payload_len = payload_len as usize;
+    let offset = item.len();
+    item.resize(offset + payload_len, 0);
+    reader.read_exact(&mut item[offset..])?;
+    Ok(Some(item))
+}
+
+/// Tells whether `PROGRESS_INTERVAL` has passed since the last report.
+struct ProgressTimer {
+    last_report: Instant,
+}
+
+impl ProgressTimer {
+    fn new() -> Self {
+        Self {
+            last_report: Instant::now(),
+        }
+    }
+
+    fn is_due(&mut self) -> bool {
+        if self.last_report.elapsed() < PROGRESS_INTERVAL {
+            return false
+        }
+        self.last_report = Instant::now();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ccore::NUM_COLUMNS;
+    use kvdb_rocksdb::{Database, DatabaseConfig};
+    use tempfile::{tempdir, TempDir};
+
+    const CHAIN_LENGTH: BlockNumber = 4;
+
+    fn start_service(
+        dir: &TempDir,
+        scheme: &Scheme,
+        timer_loop: &TimerLoop,
+        miner_options: MinerOptions,
+    ) -> ClientService {
+        let path = dir.path().to_str().unwrap();
+        let db = Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), path).unwrap();
+        start_client_service(&ClientConfig::default(), timer_loop, Arc::new(db), scheme, miner_options).unwrap()
+    }
+
+    fn start_solo_chain(dir: &TempDir, timer_loop: &TimerLoop) -> ClientService {
+        let miner_options = MinerOptions {
+            force_sealing: true,
+            no_reseal_timer: true,
+            ..Default::default()
+        };
+        let service = start_service(dir, &Scheme::new_test_solo(), timer_loop, miner_options);
+        let client = service.client();
+        for _ in 0..CHAIN_LENGTH {
+            client.update_sealing(BlockId::Latest, true);
+        }
+        assert_eq!(CHAIN_LENGTH, client.chain_info().best_block_number);
+        service
+    }
+
+    fn block_hashes(client: &Client) -> Vec<BlockHash> {
+        let best_block_number = client.chain_info().best_block_number;
+        (0..=best_block_number).map(|number| client.block_hash(&BlockId::Number(number)).unwrap()).collect()
+    }
+
+    #[test]
+    fn export_and_import_round_trip() {
+        let timer_loop = TimerLoop::new(1);
+        let source_dir = tempdir().unwrap();
+        let source = start_solo_chain(&source_dir, &timer_loop);
+        let file_dir = tempdir().unwrap();
+        let file = file_dir.path().join("chain.rlp");
+        export_blocks(&source.client(), 0, CHAIN_LENGTH, &file).unwrap();
+
+        let target_dir = tempdir().unwrap();
+        let target = start_service(&target_dir, &Scheme::new_test_solo(), &timer_loop, Default::default());
+        let summary = import_blocks(&target.client(), &file).unwrap();
+        // The genesis block is always in the chain.
+        assert_eq!(
+            ImportSummary {
+                imported: CHAIN_LENGTH as usize,
+                skipped: 1,
+            },
+            summary
+        );
+        assert_eq!(block_hashes(&source.client()), block_hashes(&target.client()));
+
+        source.stop();
+        target.stop();
+    }
+
+    #[test]
+    fn import_skips_blocks_already_in_chain() {
+        let timer_loop = TimerLoop::new(1);
+        let source_dir = tempdir().unwrap();
+        let source = start_solo_chain(&source_dir, &timer_loop);
+        let file_dir = tempdir().unwrap();
+        let file = file_dir.path().join("chain.rlp");
+        export_blocks(&source.client(), 1, CHAIN_LENGTH, &file).unwrap();
+
+        let summary = import_blocks(&source.client(), &file).unwrap();
+        assert_eq!(
+            ImportSummary {
+                imported: 0,
+                skipped: CHAIN_LENGTH as usize,
+            },
+            summary
+        );
+
+        source.stop();
+    }
+
+    #[test]
+    fn import_rejects_other_genesis() {
+        let timer_loop = TimerLoop::new(1);
+        let source_dir = tempdir().unwrap();
+        let source = start_solo_chain(&source_dir, &timer_loop);
+        let file_dir = tempdir().unwrap();
+        let file = file_dir.path().join("chain.rlp");
+        export_blocks(&source.client(), 0, CHAIN_LENGTH, &file).unwrap();
+
+        let target_dir = tempdir().unwrap();
+        let target = start_service(&target_dir, &Scheme::new_test(), &timer_loop, Default::default());
+        let err = import_blocks(&target.client(), &file).unwrap_err();
+        assert!(err.starts_with("The genesis block of the file"), "{}", err);
+        assert_eq!(0, target.client().chain_info().best_block_number);
+
+        source.stop();
+        target.stop();
+    }
+
+    #[test]
+    fn read_item_rejects_a_length_beyond_the_limit() {
+        let mut file = vec![0xff];
+        file.extend_from_slice(&u64::max_value().to_be_bytes());
+        let err = read_item(&mut &file[..]).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    }
+
+    #[test]
+    fn export_rejects_blocks_beyond_best_block() {
+        let timer_loop = TimerLoop::new(1);
+        let source_dir = tempdir().unwrap();
+        let source = start_solo_chain(&source_dir, &timer_loop);
+        let file_dir = tempdir().unwrap();
+        let file = file_dir.path().join("chain.rlp");
+        assert!(export_blocks(&source.client(), 0, CHAIN_LENGTH + 1, &file).is_err());
+
+        source.stop();
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::config::{deprecation_notices, resolve_config, Config};
+use crate::run_node::mining_author;
+use clap::ArgMatches;
+use toml::Value;
+
+/// The settings that are not printed, by their sections.
+const SECRETS: [(&str, &str); 3] =
+    [("mining", "notify_work_secrets"), ("stratum", "secret"), ("email_alarm", "sendgrid_key")];
+
+pub fn run_check_config_command(global: &ArgMatches) -> Result<(), String> {
+    for notice in deprecation_notices(global) {
+        println!("warning: {}", notice);
+    }
+    match check(global) {
+        Ok(config) => {
+            println!("OK");
+            print!("{}", effective_config(&config)?);
+            Ok(())
+        }
+        Err(errors) => {
+            for error in &errors {
+                println!("error: {}", error);
+            }
+            Err(format!("The config has {} errors", errors.len()))
+        }
+    }
+}
+
+/// Resolves the config as the node does, and returns every error that would stop the node.
+/// The checks run on the config merged so far even if some options couldn't be parsed.
+/// It doesn't open the database or bind the ports.
+fn check(global: &ArgMatches) -> Result<Config, Vec<String>> {
+    let (config, mut errors) = resolve_config(global).map_err(|err| vec![err])?;
+    errors.extend(config.errors());
+    match &config.operating.chain {
+        Some(chain) => match chain.scheme() {
+            Ok(scheme) => {
+                if !config.operating.read_only.unwrap_or(false) {
+                    if let Err(err) = mining_author(&config, scheme.engine.engine_type()) {
+                        errors.push(err);
+                    }
+                }
+            }
+            Err(err) => errors.push(err),
+        },
+        None => errors.push("chain is not specified".to_string()),
+    }
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
+}
+
+/// The resolved config in TOML. The secrets are redacted.
+fn effective_config(config: &Config) -> Result<String, String> {
+    let mut value = Value::try_from(config).map_err(|e| format!("Cannot print the config: {}", e))?;
+    for (section, key) in SECRETS.iter() {
+        let secret = value
+            .as_table_mut()
+            .and_then(|sections| sections.get_mut(*section))
+            .and_then(Value::as_table_mut)
+            .and_then(|section| section.get_mut(*key));
+        if let Some(secret) = secret {
+            *secret = Value::String("<redacted>".to_string());
+        }
+    }
+    toml::to_string(&value).map_err(|e| format!("Cannot print the config: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::read_preset_config;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    /// The preset edited by `edit`, written to a file.
+    fn fixture<F: FnOnce(&mut Value)>(edit: F) -> NamedTempFile {
+        let mut preset: Value = toml::from_str(read_preset_config()).unwrap();
+        edit(&mut preset);
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(toml::to_string(&preset).unwrap().as_bytes()).unwrap();
+        file
+    }
+
+    fn set(config: &mut Value, section: &str, key: &str, value: Value) {
+        config.as_table_mut().unwrap().get_mut(section).unwrap().as_table_mut().unwrap().insert(key.to_string(), value);
+    }
+
+    fn check_with(file: &NamedTempFile, args: &[&str]) -> Result<Config, Vec<String>> {
+        let yaml = load_yaml!("../codechain.yml");
+        let mut argv = vec!["codechain", "--config", file.path().to_str().unwrap()];
+        argv.extend_from_slice(args);
+        argv.push("check-config");
+        check(&clap::App::from_yaml(yaml).get_matches_from(argv))
+    }
+
+    #[test]
+    fn good_config_is_printed_without_its_secrets() {
+        let secret = "ab".repeat(32);
+        let file = fixture(|config| set(config, "stratum", "secret", Value::String(secret.clone())));
+        let config = check_with(&file, &["--port", "4000"]).unwrap();
+
+        let printed = effective_config(&config).unwrap();
+        assert!(!printed.contains(&secret), "{}", printed);
+        assert!(printed.contains("secret = \"<redacted>\""), "{}", printed);
+        assert!(printed.contains("port = 4000"), "{}", printed);
+    }
+
+    #[test]
+    fn every_error_is_reported() {
+        let file = fixture(|config| {
+            set(config, "network", "min_peers", Value::Integer(100));
+            set(config, "network", "whitelist_path", Value::String("/nonexistent/whitelist.txt".to_string()));
+            set(config, "mining", "reseal_on_txs", Value::String("sometimes".to_string()));
+            set(config, "codechain", "chain", Value::String("/nonexistent/scheme.json".to_string()));
+        });
+        let errors = check_with(&file, &["--author", "not-an-address"]).unwrap_err();
+
+        assert_eq!(5, errors.len(), "{:?}", errors);
+        let expected = [
+            "Invalid address format",
+            "network.min_peers (100) is greater than network.max_peers",
+            "sometimes isn't a valid value for reseal-on-txs",
+            "Cannot open the whitelist file",
+            "Could not load specification file at /nonexistent/scheme.json",
+        ];
+        for message in expected.iter() {
+            assert!(errors.iter().any(|error| error.contains(message)), "{} is not in {:?}", message, errors);
+        }
+    }
+
+    #[test]
+    fn unparsable_file_is_the_only_error() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"[network\nport = 3485\n").unwrap();
+        let errors = check_with(&file, &["--author", "not-an-address"]).unwrap_err();
+        assert_eq!(1, errors.len(), "{:?}", errors);
+        assert!(errors[0].contains("Error while parsing TOML"), "{}", errors[0]);
+    }
+}
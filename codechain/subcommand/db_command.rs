@@ -0,0 +1,122 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use crate::config::load_config;
+use crate::run_node::db_path;
+use ccore::{
+    migrate_database, pending_migrations, DatabaseMaintenance, RocksDbMaintenance, COLUMN_NAMES, MIGRATION_BATCH_SIZE,
+    NUM_COLUMNS, SCHEMA_VERSION,
+};
+use clap::ArgMatches;
+use clogger::{self, LoggerConfig};
+use kvdb::KeyValueDB;
+use kvdb_rocksdb::{Database, DatabaseConfig};
+use std::path::Path;
+use std::time::Instant;
+
+pub fn run_db_command(global: &ArgMatches, matches: &ArgMatches) -> Result<(), String> {
+    if matches.subcommand.is_none() {
+        println!("{}", matches.usage());
+        return Ok(())
+    }
+
+    let config = load_config(global)?;
+    clogger::init(&LoggerConfig::new(config.operating.instance_id.unwrap_or(0)), None)
+        .expect("Logger must be successfully initialized");
+
+    let path = db_path(&config.operating);
+    if let ("migrate", Some(matches)) = matches.subcommand() {
+        if !Path::new(&path).exists() {
+            return Err(format!("The database {} doesn't exist", path))
+        }
+        let db = Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), &path)
+            .map_err(|err| format!("Cannot open the database at {}: {}", path, err))?;
+        return migrate(&db, matches.is_present("dry-run"))
+    }
+
+    let db = RocksDbMaintenance::open(Path::new(&path), NUM_COLUMNS)
+        .map_err(|err| format!("Cannot open the database at {}: {}", path, err))?;
+
+    match matches.subcommand() {
+        ("stats", _) => stats(&db),
+        ("compact", _) => compact(&db),
+        _ => Err("Invalid subcommand".to_string()),
+    }
+}
+
+fn migrate(db: &dyn KeyValueDB, dry_run: bool) -> Result<(), String> {
+    let pending = pending_migrations(db).map_err(|err| err.to_string())?;
+    if pending.is_empty() {
+        println!("The database is at the schema version {}. No migration is pending", SCHEMA_VERSION);
+        return Ok(())
+    }
+    println!("{} migrations are pending", pending.len());
+    for migration in &pending {
+        println!("  {}: {}", migration.version(), migration.description());
+    }
+    if dry_run {
+        return Ok(())
+    }
+    let started = Instant::now();
+    migrate_database(db, MIGRATION_BATCH_SIZE).map_err(|err| format!("Cannot migrate the database: {}", err))?;
+    eprintln!(
+        "Migrated the database to the schema version {} in {:.1}s",
+        SCHEMA_VERSION,
+        started.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+fn stats(db: &dyn DatabaseMaintenance) -> Result<(), String> {
+    println!("{:<18} {:>14} {:>16} {:>8}", "column", "keys", "size (bytes)", "files");
+    for (col, name) in COLUMN_NAMES.iter().enumerate() {
+        match db.column_stats(Some(col as u32)).map_err(|err| err.to_string())? {
+            Some(stats) => println!("{:<18} {:>14} {:>16} {:>8}", name, stats.keys, stats.size, stats.files),
+            None => println!("{:<18} {:>14} {:>16} {:>8}", name, "-", "-", "-"),
+        }
+    }
+    Ok(())
+}
+
+fn compact(db: &dyn DatabaseMaintenance) -> Result<(), String> {
+    let started = Instant::now();
+    for (col, name) in COLUMN_NAMES.iter().enumerate() {
+        eprintln!("Compacting the {} column ({}/{})", name, col + 1, COLUMN_NAMES.len());
+        let column_started = Instant::now();
+        db.compact(Some(col as u32)).map_err(|err| format!("Cannot compact the {} column: {}", name, err))?;
+        eprintln!("Compacted the {} column in {:.1}s", name, column_started.elapsed().as_secs_f64());
+    }
+    eprintln!("Compacted the database in {:.1}s", started.elapsed().as_secs_f64());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn dry_run_leaves_migrations_pending() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), dir.path().to_str().unwrap()).unwrap();
+        migrate(&db, true).unwrap();
+        assert_eq!(SCHEMA_VERSION as usize, pending_migrations(&db).unwrap().len());
+
+        migrate(&db, false).unwrap();
+        assert!(pending_migrations(&db).unwrap().is_empty());
+    }
+}
@@ -15,17 +15,27 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod account_command;
+mod blocks_command;
+mod check_config_command;
 mod convert_command;
+mod db_command;
 
 use self::account_command::run_account_command;
+use self::blocks_command::{run_export_command, run_import_command};
+use self::check_config_command::run_check_config_command;
 use self::convert_command::run_convert_command;
+use self::db_command::run_db_command;
 use clap::ArgMatches;
 
 pub fn run_subcommand(matches: &ArgMatches) -> Result<(), String> {
     let subcommand = matches.subcommand.as_ref().unwrap();
     match subcommand.name.as_str() {
         "account" => run_account_command(&subcommand.matches),
+        "check-config" => run_check_config_command(matches),
         "convert" => run_convert_command(&subcommand.matches),
+        "db" => run_db_command(matches, &subcommand.matches),
+        "export" => run_export_command(matches, &subcommand.matches),
+        "import" => run_import_command(matches, &subcommand.matches),
         "commit-hash" => {
             println!("{}", env!("VERGEN_SHA"));
             Ok(())
@@ -141,6 +141,11 @@ impl AccountProvider {
         Ok(addresses)
     }
 
+    /// Re-read the key files, to see the accounts added by others without waiting for the refresh.
+    pub fn reload(&self) -> Result<(), Error> {
+        Ok(self.keystore.reload()?)
+    }
+
     pub fn import_wallet(&self, json: &[u8], password: &Password) -> Result<Address, Error> {
         Ok(self.keystore.import_wallet(json, password, false)?)
     }
@@ -155,6 +160,47 @@ impl AccountProvider {
         Ok(())
     }
 
+    pub fn create_vault(&self, name: &str, password: &Password) -> Result<(), Error> {
+        self.keystore.create_vault(name, password)?;
+        Ok(())
+    }
+
+    pub fn open_vault(&self, name: &str, password: &Password) -> Result<(), Error> {
+        self.keystore.open_vault(name, password)?;
+        Ok(())
+    }
+
+    /// Closes the vault and locks its accounts.
+    pub fn close_vault(&self, name: &str) -> Result<(), Error> {
+        let accounts = self.keystore.vault_accounts(name)?;
+        self.keystore.close_vault(name)?;
+        let mut unlocked = self.unlocked.write();
+        for address in accounts {
+            if !self.keystore.has_account(&address)? {
+                unlocked.remove(&address);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn list_vaults(&self) -> Result<Vec<String>, Error> {
+        Ok(self.keystore.list_vaults()?)
+    }
+
+    pub fn list_opened_vaults(&self) -> Result<Vec<String>, Error> {
+        Ok(self.keystore.list_opened_vaults()?)
+    }
+
+    /// Moves the account to the vault, or to the root directory if `vault` is `None`.
+    pub fn change_vault(&self, address: Address, vault: Option<&str>) -> Result<(), Error> {
+        self.keystore.change_account_vault(&address, vault)?;
+        Ok(())
+    }
+
+    pub fn vault_accounts(&self, name: &str) -> Result<Vec<Address>, Error> {
+        Ok(self.keystore.vault_accounts(name)?)
+    }
+
     /// Unlocks account permanently.
     pub fn unlock_account_permanently(&self, account: Address, password: Password) -> Result<(), KeystoreError> {
         self.unlock_account(account, password, Unlock::Perm)
@@ -257,6 +303,9 @@ impl<'a> ScopedAccount<'a> {
 #[cfg(test)]
 mod tests {
     use ckey::{Generator, Random};
+    use ckeystore::accounts_dir::RootDiskDirectory;
+    use ckeystore::KeyStore;
+    use tempfile::tempdir;
 
     use super::AccountProvider;
 
@@ -284,4 +333,27 @@ mod tests {
         assert!(ap.get_account(&kp.address(), None).is_ok());
         assert!(ap.get_account(&kp.address(), None).is_ok());
     }
+
+    #[test]
+    fn closing_vault_locks_its_accounts() {
+        let dir = tempdir().unwrap();
+        let keystore =
+            KeyStore::open_with_iterations(Box::new(RootDiskDirectory::create(dir.path()).unwrap()), 1024).unwrap();
+        let ap = AccountProvider::new(keystore);
+        let kp = Random.generate().unwrap();
+        assert!(ap.insert_account(*kp.private(), &"test".into()).is_ok());
+        assert!(ap.create_vault("vault", &"vault password".into()).is_ok());
+        assert!(ap.change_vault(kp.address(), Some("vault")).is_ok());
+        assert!(ap.unlock_account_permanently(kp.address(), "test".into()).is_ok());
+        assert!(ap.get_account(&kp.address(), None).is_ok());
+
+        assert!(ap.close_vault("vault").is_ok());
+        assert!(ap.get_list().unwrap().is_empty());
+        assert!(ap.get_account(&kp.address(), None).is_err());
+        assert!(ap.get_account(&kp.address(), Some(&"test".into())).is_err());
+
+        assert!(ap.open_vault("vault", &"vault password".into()).is_ok());
+        assert!(ap.get_account(&kp.address(), None).is_err(), "The account must be unlocked again");
+        assert!(ap.get_account(&kp.address(), Some(&"test".into())).is_ok());
+    }
 }
@@ -15,7 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::invoice::Invoice;
+use crate::chain_time_cache::ChainTimeCache;
 use crate::client::{EngineInfo, TermInfo};
+use crate::consensus::stake::FeeDistribution;
 use crate::consensus::CodeChainEngine;
 use crate::error::{BlockError, Error};
 use crate::transaction::{SignedTransaction, UnverifiedTransaction};
@@ -90,6 +92,7 @@ pub struct ExecutedBlock {
     transactions: Vec<SignedTransaction>,
     invoices: Vec<Invoice>,
     transactions_set: HashSet<TxHash>,
+    fee_distribution: Option<FeeDistribution>,
 }
 
 impl ExecutedBlock {
@@ -100,6 +103,7 @@ impl ExecutedBlock {
             transactions: Default::default(),
             invoices: Default::default(),
             transactions_set: Default::default(),
+            fee_distribution: None,
         }
     }
 
@@ -115,12 +119,19 @@ impl ExecutedBlock {
     pub fn header(&self) -> &Header {
         &self.header
     }
+
+    /// Records how the engine distributed the fees while closing the block.
+    pub fn set_fee_distribution(&mut self, fee_distribution: FeeDistribution) {
+        self.fee_distribution = Some(fee_distribution);
+    }
 }
 
 /// Block that is ready for transactions to be added.
 pub struct OpenBlock<'x> {
     block: ExecutedBlock,
     engine: &'x dyn CodeChainEngine,
+    /// The ages of the transactions that the inputs of this block refer to.
+    chain_time_cache: ChainTimeCache,
 }
 
 impl<'x> OpenBlock<'x> {
@@ -136,6 +147,7 @@ impl<'x> OpenBlock<'x> {
         let mut r = OpenBlock {
             block: ExecutedBlock::new(state, parent),
             engine,
+            chain_time_cache: Default::default(),
         };
 
         r.block.header.set_author(author);
@@ -166,7 +178,7 @@ impl<'x> OpenBlock<'x> {
             &tx,
             &hash,
             &tx.signer_public(),
-            client,
+            &self.chain_time_cache.with(client),
             parent_block_number,
             parent_block_timestamp,
             self.block.header.timestamp(),
@@ -198,10 +210,23 @@ impl<'x> OpenBlock<'x> {
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> Result<(), Error> {
-        for tx in transactions {
-            self.push_transaction(tx.clone(), client, parent_block_number, parent_block_timestamp)?;
-        }
-        Ok(())
+        self.begin_batch()?;
+        let result = transactions
+            .iter()
+            .try_for_each(|tx| self.push_transaction(tx.clone(), client, parent_block_number, parent_block_timestamp));
+        self.end_batch()?;
+        result
+    }
+
+    /// Start applying the pushed transactions as one batch.
+    /// See `TopLevelState::begin_batch`.
+    pub fn begin_batch(&mut self) -> Result<(), Error> {
+        Ok(self.block.state.begin_batch()?)
+    }
+
+    /// Finish the batch started by `begin_batch`.
+    pub fn end_batch(&mut self) -> Result<(), Error> {
+        Ok(self.block.state.end_batch()?)
     }
 
     /// Populate self from a header.
@@ -329,9 +354,11 @@ impl ClosedBlock {
         // revert rewards (i.e. set state back at last transaction's state).
         let mut block = self.block;
         block.state = self.unclosed_state;
+        block.fee_distribution = None;
         OpenBlock {
             block,
             engine,
+            chain_time_cache: Default::default(),
         }
     }
 }
@@ -431,6 +458,11 @@ pub trait IsBlock {
         &self.block().invoices
     }
 
+    /// Get how the fees of this block are distributed. None if the engine doesn't distribute them.
+    fn fee_distribution(&self) -> Option<&FeeDistribution> {
+        self.block().fee_distribution.as_ref()
+    }
+
     /// Get the final state associated with this object's block.
     fn state(&self) -> &TopLevelState {
         &self.block().state
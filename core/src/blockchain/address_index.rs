@@ -0,0 +1,246 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use super::block_info::BestBlockChanged;
+use super::body_db::BodyProvider;
+use super::headerchain::HeaderProvider;
+use crate::db;
+use crate::{SignedTransaction, UnverifiedTransaction};
+use ckey::{public_to_address, Address};
+use ctypes::transaction::Action;
+use ctypes::{BlockNumber, TxHash};
+use kvdb::{DBTransaction, KeyValueDB};
+use lru_cache::LruCache;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+const START_BLOCK_KEY: &[u8] = b"address-index-start-block";
+
+/// An index key is the address, the block number and the index of the transaction in the block.
+/// The numbers are big endian so that the keys of an address are sorted by their positions.
+const KEY_LENGTH: usize = 20 + 8 + 4;
+
+/// The senders of the recently imported transactions, so that a reorg doesn't recover them again.
+const SENDER_CACHE_SIZE: usize = 16 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AddressIndexError {
+    /// The address index is disabled by `ClientConfig`.
+    Disabled,
+    /// The requested range starts below the first indexed block.
+    BelowStartBlock {
+        start_block: BlockNumber,
+    },
+}
+
+impl fmt::Display for AddressIndexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressIndexError::Disabled => write!(f, "The address index is disabled"),
+            AddressIndexError::BelowStartBlock {
+                start_block,
+            } => write!(f, "The address index starts from block #{}", start_block),
+        }
+    }
+}
+
+/// Maps an address to the canonical transactions that are sent by it or pay to it.
+///
+/// The index is not backfilled, so only the blocks from `start_block` are indexed.
+pub struct AddressIndex {
+    start_block: BlockNumber,
+    db: Arc<dyn KeyValueDB>,
+    senders: Mutex<LruCache<TxHash, Address>>,
+}
+
+impl AddressIndex {
+    /// Opens the index. If it is enabled for the first time, it starts from the block after the best block.
+    /// The genesis block has no transactions, so a fresh database is indexed from the genesis.
+    pub fn new(db: Arc<dyn KeyValueDB>, best_block_number: BlockNumber) -> Self {
+        Self {
+            start_block: open_start_block(&*db, START_BLOCK_KEY, best_block_number),
+            db,
+            senders: Mutex::new(LruCache::new(SENDER_CACHE_SIZE)),
+        }
+    }
+
+    /// Remembers the senders recovered by the verification, so that indexing the block doesn't recover them again.
+    pub fn note_senders(&self, transactions: &[SignedTransaction]) {
+        let mut senders = self.senders.lock();
+        for tx in transactions {
+            senders.insert(tx.hash(), public_to_address(&tx.signer_public()));
+        }
+    }
+
+    /// Forgets the start block of a disabled index.
+    /// The entries written before are left behind, but they are all below the start block
+    /// chosen when the index is enabled again, so they are never served.
+    pub fn reset(db: &dyn KeyValueDB) {
+        reset_start_block(db, START_BLOCK_KEY)
+    }
+
+    /// Whether the database has the index.
+    pub fn is_maintained(db: &dyn KeyValueDB) -> bool {
+        has_start_block(db, START_BLOCK_KEY)
+    }
+
+    /// Adds the transactions of the enacted blocks and removes the ones of the retracted blocks.
+    pub fn update_best_block<P>(&self, batch: &mut DBTransaction, best_block_changed: &BestBlockChanged, chain: &P)
+    where
+        P: HeaderProvider + BodyProvider, {
+        let best_block = match best_block_changed.best_block() {
+            Some(block) => block,
+            None => return,
+        };
+
+        let mut removed = HashSet::new();
+        let mut added = HashSet::new();
+        if let BestBlockChanged::BranchBecomingCanonChain {
+            tree_route,
+            ..
+        } = best_block_changed
+        {
+            for hash in &tree_route.retracted {
+                let number = chain.block_number(hash).expect("Retracted block must be in database.");
+                let body = chain.block_body(hash).expect("Retracted block must be in database.");
+                removed.extend(self.keys(number, &body.transactions()));
+            }
+            for hash in &tree_route.enacted {
+                let number = chain.block_number(hash).expect("Enacted block must be in database.");
+                let body = chain.block_body(hash).expect("Enacted block must be in database.");
+                added.extend(self.keys(number, &body.transactions()));
+            }
+        }
+        added.extend(self.keys(best_block.header_view().number(), &best_block.transactions()));
+
+        // A transaction can stay at the same position in both branches. Don't remove it then.
+        for key in removed.difference(&added) {
+            batch.delete(db::COL_ADDRESS_INDEX, key);
+        }
+        for key in &added {
+            batch.put(db::COL_ADDRESS_INDEX, key, &[]);
+        }
+    }
+
+    /// Returns the positions of the transactions related to the address in the given block range.
+    /// The positions are sorted, and the first `skip` ones are omitted.
+    pub fn transactions(
+        &self,
+        address: &Address,
+        from: BlockNumber,
+        to: BlockNumber,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<(BlockNumber, usize)>, AddressIndexError> {
+        if from < self.start_block {
+            return Err(AddressIndexError::BelowStartBlock {
+                start_block: self.start_block,
+            })
+        }
+
+        let prefix = &address[..];
+        // The RocksDB iterator seeks to the first key of the range, and doesn't stop at the end of the prefix.
+        let start = index_key(address, from, 0);
+        Ok(self
+            .db
+            .iter_from_prefix(db::COL_ADDRESS_INDEX, &start)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, _)| decode_key(&key))
+            .take_while(|(number, _)| *number <= to)
+            .skip(skip)
+            .take(limit)
+            .collect())
+    }
+
+    fn keys(&self, number: BlockNumber, transactions: &[UnverifiedTransaction]) -> Vec<Vec<u8>> {
+        if number < self.start_block {
+            return Vec::new()
+        }
+        transactions
+            .iter()
+            .enumerate()
+            .flat_map(|(index, tx)| {
+                let sender = self.sender(tx);
+                let receiver = match &tx.action {
+                    Action::Pay {
+                        receiver,
+                        ..
+                    } => Some(*receiver),
+                    _ => None,
+                };
+                sender.into_iter().chain(receiver).map(move |address| index_key(&address, number, index))
+            })
+            .collect()
+    }
+
+    fn sender(&self, tx: &UnverifiedTransaction) -> Option<Address> {
+        let hash = tx.hash();
+        if let Some(sender) = self.senders.lock().get_mut(&hash) {
+            return Some(*sender)
+        }
+        let sender = public_to_address(&tx.recover_public().ok()?);
+        self.senders.lock().insert(hash, sender);
+        Some(sender)
+    }
+}
+
+/// Reads the start block of an index stored at `key`, or writes a new one if there is none.
+pub(super) fn open_start_block(db: &dyn KeyValueDB, key: &[u8], best_block_number: BlockNumber) -> BlockNumber {
+    if let Some(bytes) = db.get(db::COL_EXTRA, key).expect("Low level database error. Some issue with disk?") {
+        return rlp::decode(&bytes).expect("The start block of an index must be encoded properly")
+    }
+    let start_block = if best_block_number == 0 {
+        0
+    } else {
+        best_block_number + 1
+    };
+    let mut batch = DBTransaction::new();
+    batch.put(db::COL_EXTRA, key, &rlp::encode(&start_block));
+    db.write(batch).expect("Low level database error. Some issue with disk?");
+    start_block
+}
+
+pub(super) fn has_start_block(db: &dyn KeyValueDB, key: &[u8]) -> bool {
+    db.get(db::COL_EXTRA, key).expect("Low level database error. Some issue with disk?").is_some()
+}
+
+pub(super) fn reset_start_block(db: &dyn KeyValueDB, key: &[u8]) {
+    if has_start_block(db, key) {
+        let mut batch = DBTransaction::new();
+        batch.delete(db::COL_EXTRA, key);
+        db.write(batch).expect("Low level database error. Some issue with disk?");
+    }
+}
+
+fn index_key(address: &Address, number: BlockNumber, index: usize) -> Vec<u8> {
+    let mut key = Vec::with_capacity(KEY_LENGTH);
+    key.extend_from_slice(&address[..]);
+    key.extend_from_slice(&number.to_be_bytes());
+    key.extend_from_slice(&(index as u32).to_be_bytes());
+    key
+}
+
+fn decode_key(key: &[u8]) -> (BlockNumber, usize) {
+    assert_eq!(KEY_LENGTH, key.len(), "Invalid address index key");
+    let mut number = [0u8; 8];
+    number.copy_from_slice(&key[20..28]);
+    let mut index = [0u8; 4];
+    index.copy_from_slice(&key[28..]);
+    (BlockNumber::from_be_bytes(number), u32::from_be_bytes(index) as usize)
+}
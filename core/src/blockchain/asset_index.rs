@@ -0,0 +1,277 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use super::address_index::{has_start_block, open_start_block, reset_start_block};
+use super::block_info::BestBlockChanged;
+use super::body_db::BodyProvider;
+use super::headerchain::HeaderProvider;
+use crate::db;
+use crate::db_version::{load_progress, save_progress, Migration};
+use crate::UnverifiedTransaction;
+use ccrypto::Blake;
+use ctypes::transaction::{Action, AssetTransferInput};
+use ctypes::{BlockNumber, ShardId, Tracker};
+use kvdb::{DBTransaction, KeyValueDB};
+use primitives::{H160, H256};
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::sync::Arc;
+
+const START_BLOCK_KEY: &[u8] = b"asset-index-start-block";
+
+/// An index key is the shard id, the lock script hash, the tracker and the index of the asset.
+/// The keys of a lock script hash share the prefix, so they can be listed by a prefix iteration.
+const PREFIX_LENGTH: usize = 2 + 20;
+const KEY_LENGTH: usize = PREFIX_LENGTH + 32 + 4;
+/// The length of the keys written before the schema version 1, whose indices are 8 bytes.
+const V0_KEY_LENGTH: usize = PREFIX_LENGTH + 32 + 8;
+
+/// Maps a lock script hash to the unspent assets locked by it.
+///
+/// The index is not backfilled, so the assets created before `start_block` are not listed.
+pub struct AssetIndex {
+    start_block: BlockNumber,
+    db: Arc<dyn KeyValueDB>,
+}
+
+impl AssetIndex {
+    /// Opens the index. If it is enabled for the first time, it starts from the block after the best block.
+    pub fn new(db: Arc<dyn KeyValueDB>, best_block_number: BlockNumber) -> Self {
+        if db.get(db::COL_EXTRA, START_BLOCK_KEY).expect("Low level database error. Some issue with disk?").is_none() {
+            // The spends made while the index was disabled are not reflected in the old entries.
+            let mut batch = DBTransaction::new();
+            for (key, _) in db.iter(db::COL_ASSET_INDEX) {
+                batch.delete(db::COL_ASSET_INDEX, &key);
+            }
+            db.write(batch).expect("Low level database error. Some issue with disk?");
+        }
+        Self {
+            start_block: open_start_block(&*db, START_BLOCK_KEY, best_block_number),
+            db,
+        }
+    }
+
+    /// Forgets the start block of a disabled index.
+    /// The entries left behind become stale, so they are removed when the index is enabled again.
+    pub fn reset(db: &dyn KeyValueDB) {
+        reset_start_block(db, START_BLOCK_KEY)
+    }
+
+    /// Whether the database has the index.
+    pub fn is_maintained(db: &dyn KeyValueDB) -> bool {
+        has_start_block(db, START_BLOCK_KEY)
+    }
+
+    /// Undoes the retracted blocks from the newest one, and then redoes the enacted blocks from the oldest one.
+    /// Every transaction in a block has been applied successfully, so all of its outputs and inputs are counted.
+    pub fn update_best_block<P>(&self, batch: &mut DBTransaction, best_block_changed: &BestBlockChanged, chain: &P)
+    where
+        P: HeaderProvider + BodyProvider, {
+        let best_block = match best_block_changed.best_block() {
+            Some(block) => block,
+            None => return,
+        };
+
+        // true if the asset exists after the change.
+        let mut changes = HashMap::new();
+        if let BestBlockChanged::BranchBecomingCanonChain {
+            tree_route,
+            ..
+        } = best_block_changed
+        {
+            for hash in &tree_route.retracted {
+                let number = chain.block_number(hash).expect("Retracted block must be in database.");
+                let body = chain.block_body(hash).expect("Retracted block must be in database.");
+                for tx in body.transactions().iter().rev() {
+                    let (created, consumed) = self.created_and_consumed(number, tx);
+                    changes.extend(created.into_iter().map(|key| (key, false)));
+                    changes.extend(consumed.into_iter().map(|key| (key, true)));
+                }
+            }
+            for hash in &tree_route.enacted {
+                let number = chain.block_number(hash).expect("Enacted block must be in database.");
+                let body = chain.block_body(hash).expect("Enacted block must be in database.");
+                self.enact(&mut changes, number, &body.transactions());
+            }
+        }
+        self.enact(&mut changes, best_block.header_view().number(), &best_block.transactions());
+
+        for (key, exists) in changes {
+            if exists {
+                batch.put(db::COL_ASSET_INDEX, &key, &[]);
+            } else {
+                batch.delete(db::COL_ASSET_INDEX, &key);
+            }
+        }
+    }
+
+    /// Returns the addresses of the assets in the shard that are locked by the lock script hash.
+    /// The addresses are sorted, and the first `skip` ones are omitted.
+    pub fn assets(
+        &self,
+        shard_id: ShardId,
+        lock_script_hash: &H160,
+        skip: usize,
+        limit: usize,
+    ) -> Vec<(Tracker, usize)> {
+        let prefix = key_prefix(shard_id, lock_script_hash);
+        // The RocksDB iterator doesn't stop at the end of the prefix.
+        self.db
+            .iter_from_prefix(db::COL_ASSET_INDEX, &prefix)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .skip(skip)
+            .take(limit)
+            .map(|(key, _)| decode_key(&key))
+            .collect()
+    }
+
+    fn enact(&self, changes: &mut HashMap<Vec<u8>, bool>, number: BlockNumber, transactions: &[UnverifiedTransaction]) {
+        for tx in transactions {
+            let (created, consumed) = self.created_and_consumed(number, tx);
+            changes.extend(consumed.into_iter().map(|key| (key, false)));
+            changes.extend(created.into_iter().map(|key| (key, true)));
+        }
+    }
+
+    /// Returns the keys of the assets created by the transaction and the keys of the assets consumed by it.
+    fn created_and_consumed(&self, number: BlockNumber, tx: &UnverifiedTransaction) -> (Vec<Vec<u8>>, Vec<Vec<u8>>) {
+        let tracker = match tx.tracker() {
+            Some(tracker) if number >= self.start_block => tracker,
+            _ => return (Vec::new(), Vec::new()),
+        };
+        match &tx.action {
+            Action::MintAsset {
+                shard_id,
+                output,
+                ..
+            }
+            | Action::IncreaseAssetSupply {
+                shard_id,
+                output,
+                ..
+            } => (vec![index_key(*shard_id, &output.lock_script_hash, tracker, 0)], Vec::new()),
+            Action::WrapCCC {
+                shard_id,
+                lock_script_hash,
+                ..
+            } => (vec![index_key(*shard_id, lock_script_hash, tracker, 0)], Vec::new()),
+            Action::TransferAsset {
+                burns,
+                inputs,
+                outputs,
+                ..
+            } => {
+                let created = outputs
+                    .iter()
+                    .enumerate()
+                    .map(|(index, output)| index_key(output.shard_id, &output.lock_script_hash, tracker, index))
+                    .collect();
+                (created, inputs.iter().chain(burns).map(input_key).collect())
+            }
+            Action::UnwrapCCC {
+                burn,
+                ..
+            } => (Vec::new(), vec![input_key(burn)]),
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+}
+
+/// The lock script of a successfully spent input is the one the asset is locked by.
+fn input_key(input: &AssetTransferInput) -> Vec<u8> {
+    let prev_out = &input.prev_out;
+    index_key(prev_out.shard_id, &Blake::blake(&input.lock_script), prev_out.tracker, prev_out.index)
+}
+
+fn key_prefix(shard_id: ShardId, lock_script_hash: &H160) -> Vec<u8> {
+    let mut key = Vec::with_capacity(KEY_LENGTH);
+    key.extend_from_slice(&shard_id.to_be_bytes());
+    key.extend_from_slice(&lock_script_hash[..]);
+    key
+}
+
+fn index_key(shard_id: ShardId, lock_script_hash: &H160, tracker: Tracker, index: usize) -> Vec<u8> {
+    let mut key = key_prefix(shard_id, lock_script_hash);
+    key.extend_from_slice(&tracker[..]);
+    key.extend_from_slice(&(index as u32).to_be_bytes());
+    key
+}
+
+fn decode_key(key: &[u8]) -> (Tracker, usize) {
+    assert_eq!(KEY_LENGTH, key.len(), "Invalid asset index key");
+    let tracker = H256::from_slice(&key[PREFIX_LENGTH..PREFIX_LENGTH + 32]).into();
+    let mut index = [0u8; 4];
+    index.copy_from_slice(&key[PREFIX_LENGTH + 32..]);
+    (tracker, u32::from_be_bytes(index) as usize)
+}
+
+/// Shrinks the indices in the asset index keys from 8 bytes to 4 bytes, as wide as the ones in the address index.
+pub struct ShrinkAssetIndexKeys;
+
+impl Migration for ShrinkAssetIndexKeys {
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn description(&self) -> &'static str {
+        "Shrink the indices in the asset index keys to 4 bytes"
+    }
+
+    fn migrate(
+        &self,
+        db: &dyn KeyValueDB,
+        batch_size: usize,
+        on_batch: &mut dyn FnMut(u64) -> io::Result<()>,
+    ) -> io::Result<u64> {
+        let (mut migrated, cursor) = load_progress(db, self.version())?.unwrap_or_default();
+        let mut batch = DBTransaction::new();
+        let mut batched = 0;
+        // The iterator reads the column as it was when the iterator is created, so the keys written in the
+        // pass are not read by it. The keys up to the cursor are migrated, and the new ones are filtered out
+        // by their length.
+        let old_keys = db
+            .iter(db::COL_ASSET_INDEX)
+            .map(|(key, _)| key)
+            .skip_while(|key| key[..] <= cursor[..])
+            .filter(|key| key.len() == V0_KEY_LENGTH);
+        let mut last_key = Default::default();
+        for key in old_keys {
+            debug_assert_eq!(&[0u8; 4], &key[PREFIX_LENGTH + 32..PREFIX_LENGTH + 36], "An index must fit in 4 bytes");
+            let mut new_key = key[..PREFIX_LENGTH + 32].to_vec();
+            new_key.extend_from_slice(&key[PREFIX_LENGTH + 36..]);
+            batch.delete(db::COL_ASSET_INDEX, &key);
+            batch.put(db::COL_ASSET_INDEX, &new_key, &[]);
+            batched += 1;
+            last_key = key;
+            if batched == batch_size {
+                migrated += batched as u64;
+                save_progress(&mut batch, self.version(), migrated, &last_key);
+                db.write(mem::replace(&mut batch, DBTransaction::new()))?;
+                batched = 0;
+                on_batch(migrated)?;
+            }
+        }
+        if batched > 0 {
+            migrated += batched as u64;
+            save_progress(&mut batch, self.version(), migrated, &last_key);
+            db.write(batch)?;
+            on_batch(migrated)?;
+        }
+        Ok(migrated)
+    }
+}
@@ -0,0 +1,580 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::address_index::{has_start_block, open_start_block, reset_start_block};
+use super::block_info::BestBlockChanged;
+use super::body_db::BodyProvider;
+use super::headerchain::HeaderProvider;
+use crate::db;
+use crate::UnverifiedTransaction;
+use ccrypto::{blake256, Blake};
+use ctypes::transaction::{Action, AssetTransferInput};
+use ctypes::{BlockHash, BlockNumber, ShardId, Tracker};
+use kvdb::{DBTransaction, KeyValueDB};
+use primitives::H160;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+const START_BLOCK_KEY: &[u8] = b"asset-log-start-block";
+
+/// The logs of a block are keyed by the prefix and the block number.
+const LOG_PREFIX: u8 = b'l';
+/// The bloom of a block is keyed by the prefix and the block number. The blocks without logs have no bloom.
+const BLOOM_PREFIX: u8 = b'b';
+/// The bloom of a region is keyed by the prefix and the region number. It accrues the blooms of the blocks in it.
+const REGION_PREFIX: u8 = b'r';
+/// The number of blocks in a region.
+const REGION_SIZE: BlockNumber = 256;
+const BLOOM_BYTES: usize = 256;
+/// The maximum number of blocks whose blooms a query reads.
+/// The blocks in the regions whose blooms don't match the filter are not counted.
+const MAX_SCANNED_BLOCKS: BlockNumber = 16 * REGION_SIZE;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AssetLogError {
+    /// The asset log is disabled by `ClientConfig`.
+    Disabled,
+    /// The requested range starts below the first block whose logs are kept.
+    BelowStartBlock {
+        start_block: BlockNumber,
+    },
+    /// The query reads more blocks than it's allowed before it finds enough logs.
+    TooManyBlocksScanned {
+        max_blocks: BlockNumber,
+    },
+}
+
+impl fmt::Display for AssetLogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetLogError::Disabled => write!(f, "The asset log is disabled"),
+            AssetLogError::BelowStartBlock {
+                start_block,
+            } => write!(f, "The asset log starts from block #{}", start_block),
+            AssetLogError::TooManyBlocksScanned {
+                max_blocks,
+            } => write!(f, "The query scans more than {} blocks. Narrow the filter or the block range", max_blocks),
+        }
+    }
+}
+
+/// An asset moved by a transaction. An output of a transfer, a mint or a wrap creates an asset locked by `to`,
+/// and a burn or an unwrap destroys an asset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetLog {
+    pub tracker: Tracker,
+    pub shard_id: ShardId,
+    pub asset_type: H160,
+    /// The lock script hashes of the inputs of the same asset type, sorted. Empty if the asset is created.
+    pub from: Vec<H160>,
+    /// The lock script hash of the output. None if the asset is destroyed.
+    pub to: Option<H160>,
+    pub quantity: u64,
+}
+
+impl Encodable for AssetLog {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(6);
+        s.append(&self.tracker);
+        s.append(&self.shard_id);
+        s.append(&self.asset_type);
+        s.append_list(&self.from);
+        match &self.to {
+            Some(to) => s.begin_list(1).append(to),
+            None => s.begin_list(0),
+        };
+        s.append(&self.quantity);
+    }
+}
+
+impl Decodable for AssetLog {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 6 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: 6,
+                got: item_count,
+            })
+        }
+        let to: Vec<H160> = rlp.list_at(4)?;
+        if to.len() > 1 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: 1,
+                got: to.len(),
+            })
+        }
+        Ok(Self {
+            tracker: rlp.val_at(0)?,
+            shard_id: rlp.val_at(1)?,
+            asset_type: rlp.val_at(2)?,
+            from: rlp.list_at(3)?,
+            to: to.into_iter().next(),
+            quantity: rlp.val_at(5)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalizedAssetLog {
+    pub block_number: BlockNumber,
+    pub block_hash: BlockHash,
+    pub log: AssetLog,
+}
+
+/// Selects the logs in the blocks `from_block..=to_block`. The logs of any asset type or any shard are selected
+/// if the field is None.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetLogFilter {
+    pub asset_type: Option<H160>,
+    pub shard_id: Option<ShardId>,
+    pub from_block: BlockNumber,
+    pub to_block: BlockNumber,
+}
+
+impl AssetLogFilter {
+    fn matches(&self, log: &AssetLog) -> bool {
+        self.asset_type.map_or(true, |asset_type| asset_type == log.asset_type)
+            && self.shard_id.map_or(true, |shard_id| shard_id == log.shard_id)
+    }
+
+    fn may_match(&self, bloom: &Bloom) -> bool {
+        self.asset_type.map_or(true, |asset_type| bloom.contains(&asset_type_item(&asset_type)))
+            && self.shard_id.map_or(true, |shard_id| bloom.contains(&shard_item(shard_id)))
+    }
+}
+
+/// Keeps the asset logs of the canonical blocks and their blooms.
+/// The blooms aren't in the headers, so they are not a part of the consensus.
+///
+/// The log is not backfilled, so only the blocks from `start_block` are logged.
+/// The logs older than `retention` blocks from the best block are removed.
+pub struct AssetLogDB {
+    start_block: BlockNumber,
+    retention: BlockNumber,
+    db: Arc<dyn KeyValueDB>,
+}
+
+impl AssetLogDB {
+    /// Opens the log. If it is enabled for the first time, it starts from the block after the best block.
+    pub fn new(db: Arc<dyn KeyValueDB>, best_block_number: BlockNumber, retention: BlockNumber) -> Self {
+        assert_ne!(0, retention, "The asset log must keep at least one block");
+        if db.get(db::COL_EXTRA, START_BLOCK_KEY).expect("Low level database error. Some issue with disk?").is_none() {
+            // The reorgs made while the log was disabled are not reflected in the old entries.
+            let mut batch = DBTransaction::new();
+            for (key, _) in db.iter(db::COL_ASSET_LOG) {
+                batch.delete(db::COL_ASSET_LOG, &key);
+            }
+            db.write(batch).expect("Low level database error. Some issue with disk?");
+        }
+        Self {
+            start_block: open_start_block(&*db, START_BLOCK_KEY, best_block_number),
+            retention,
+            db,
+        }
+    }
+
+    /// Forgets the start block of a disabled log.
+    /// The entries left behind become stale, so they are removed when the log is enabled again.
+    pub fn reset(db: &dyn KeyValueDB) {
+        reset_start_block(db, START_BLOCK_KEY)
+    }
+
+    /// Whether the database has the log.
+    pub fn is_maintained(db: &dyn KeyValueDB) -> bool {
+        has_start_block(db, START_BLOCK_KEY)
+    }
+
+    /// Removes the logs of the retracted blocks and writes the ones of the enacted blocks.
+    /// The region blooms keep the bits of the retracted blocks, which only makes the false positives.
+    pub fn update_best_block<P>(&self, batch: &mut DBTransaction, best_block_changed: &BestBlockChanged, chain: &P)
+    where
+        P: HeaderProvider + BodyProvider, {
+        let best_block = match best_block_changed.best_block() {
+            Some(block) => block,
+            None => return,
+        };
+
+        // None if the key is deleted.
+        let mut changes = HashMap::new();
+        let mut enacted = Vec::new();
+        if let BestBlockChanged::BranchBecomingCanonChain {
+            tree_route,
+            ..
+        } = best_block_changed
+        {
+            for hash in &tree_route.retracted {
+                let number = chain.block_number(hash).expect("Retracted block must be in database.");
+                changes.insert(log_key(LOG_PREFIX, number), None);
+                changes.insert(log_key(BLOOM_PREFIX, number), None);
+            }
+            for hash in &tree_route.enacted {
+                let number = chain.block_number(hash).expect("Enacted block must be in database.");
+                let body = chain.block_body(hash).expect("Enacted block must be in database.");
+                enacted.push((number, self.logs(number, &body.transactions())));
+            }
+        }
+        let best_block_number = best_block.header_view().number();
+        enacted.push((best_block_number, self.logs(best_block_number, &best_block.transactions())));
+
+        let mut regions: HashMap<BlockNumber, Bloom> = HashMap::new();
+        for (number, logs) in &enacted {
+            if logs.is_empty() {
+                changes.insert(log_key(LOG_PREFIX, *number), None);
+                changes.insert(log_key(BLOOM_PREFIX, *number), None);
+                continue
+            }
+            let mut bloom = Bloom::default();
+            for log in logs {
+                bloom.accrue(&asset_type_item(&log.asset_type));
+                bloom.accrue(&shard_item(log.shard_id));
+            }
+            let region = number / REGION_SIZE;
+            regions
+                .entry(region)
+                .or_insert_with(|| self.bloom(REGION_PREFIX, region).unwrap_or_default())
+                .accrue_bloom(&bloom);
+            let mut s = RlpStream::new();
+            s.append_list(logs);
+            changes.insert(log_key(LOG_PREFIX, *number), Some(s.out()));
+            changes.insert(log_key(BLOOM_PREFIX, *number), Some(bloom.0));
+        }
+        for (region, bloom) in regions {
+            changes.insert(log_key(REGION_PREFIX, region), Some(bloom.0));
+        }
+        for (number, _) in &enacted {
+            if *number < self.retention {
+                continue
+            }
+            let expired = number - self.retention;
+            changes.insert(log_key(LOG_PREFIX, expired), None);
+            changes.insert(log_key(BLOOM_PREFIX, expired), None);
+            if expired % REGION_SIZE == REGION_SIZE - 1 {
+                changes.insert(log_key(REGION_PREFIX, expired / REGION_SIZE), None);
+            }
+        }
+
+        for (key, value) in changes {
+            match value {
+                Some(value) => batch.put(db::COL_ASSET_LOG, &key, &value),
+                None => batch.delete(db::COL_ASSET_LOG, &key),
+            }
+        }
+    }
+
+    /// Returns the logs that match the filter with their block numbers, skipping the first `skip` ones.
+    /// The blocks whose blooms don't match the filter are not read.
+    /// It fails if it reads the blooms of `MAX_SCANNED_BLOCKS` blocks before it finds enough logs,
+    /// so that a query without a filter cannot make the node read every block of the retention.
+    pub fn logs_matching(
+        &self,
+        filter: &AssetLogFilter,
+        best_block_number: BlockNumber,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<(BlockNumber, AssetLog)>, AssetLogError> {
+        self.scan(filter, best_block_number, skip, limit, MAX_SCANNED_BLOCKS)
+    }
+
+    fn scan(
+        &self,
+        filter: &AssetLogFilter,
+        best_block_number: BlockNumber,
+        skip: usize,
+        limit: usize,
+        max_scanned_blocks: BlockNumber,
+    ) -> Result<Vec<(BlockNumber, AssetLog)>, AssetLogError> {
+        let start_block = ::std::cmp::max(self.start_block, (best_block_number + 1).saturating_sub(self.retention));
+        if filter.from_block < start_block {
+            return Err(AssetLogError::BelowStartBlock {
+                start_block,
+            })
+        }
+
+        let to_block = ::std::cmp::min(filter.to_block, best_block_number);
+        let mut skip = skip;
+        let mut matched = Vec::new();
+        let mut scanned_blocks = 0;
+        let mut number = filter.from_block;
+        while number <= to_block && matched.len() < limit {
+            let region = number / REGION_SIZE;
+            let region_end = ::std::cmp::min((region + 1) * REGION_SIZE - 1, to_block);
+            if !self.bloom(REGION_PREFIX, region).map_or(false, |bloom| filter.may_match(&bloom)) {
+                number = region_end + 1;
+                continue
+            }
+            for number in number..=region_end {
+                if matched.len() == limit {
+                    break
+                }
+                if scanned_blocks == max_scanned_blocks {
+                    return Err(AssetLogError::TooManyBlocksScanned {
+                        max_blocks: max_scanned_blocks,
+                    })
+                }
+                scanned_blocks += 1;
+                if !self.bloom(BLOOM_PREFIX, number).map_or(false, |bloom| filter.may_match(&bloom)) {
+                    continue
+                }
+                for log in self.block_logs(number).into_iter().filter(|log| filter.matches(log)) {
+                    if skip > 0 {
+                        skip -= 1;
+                    } else if matched.len() < limit {
+                        matched.push((number, log));
+                    }
+                }
+            }
+            number = region_end + 1;
+        }
+        Ok(matched)
+    }
+
+    fn block_logs(&self, number: BlockNumber) -> Vec<AssetLog> {
+        self.db
+            .get(db::COL_ASSET_LOG, &log_key(LOG_PREFIX, number))
+            .expect("Low level database error. Some issue with disk?")
+            .map(|bytes| rlp::decode_list(&bytes))
+            .unwrap_or_default()
+    }
+
+    fn bloom(&self, prefix: u8, number: BlockNumber) -> Option<Bloom> {
+        let bytes = self
+            .db
+            .get(db::COL_ASSET_LOG, &log_key(prefix, number))
+            .expect("Low level database error. Some issue with disk?")?;
+        assert_eq!(BLOOM_BYTES, bytes.len(), "Invalid asset log bloom");
+        Some(Bloom(bytes.to_vec()))
+    }
+
+    fn logs(&self, number: BlockNumber, transactions: &[UnverifiedTransaction]) -> Vec<AssetLog> {
+        if number < self.start_block {
+            return Vec::new()
+        }
+        transactions
+            .iter()
+            .flat_map(|tx| tx.tracker().map(|tracker| asset_logs(tracker, &tx.action)).unwrap_or_default())
+            .collect()
+    }
+}
+
+/// Every transaction in a block has been applied successfully, so all of its outputs and burns are logged.
+fn asset_logs(tracker: Tracker, action: &Action) -> Vec<AssetLog> {
+    let created = |shard_id: ShardId, asset_type: H160, to: H160, quantity: u64| AssetLog {
+        tracker,
+        shard_id,
+        asset_type,
+        from: Vec::new(),
+        to: Some(to),
+        quantity,
+    };
+    let destroyed = |burn: &AssetTransferInput| AssetLog {
+        tracker,
+        shard_id: burn.prev_out.shard_id,
+        asset_type: burn.prev_out.asset_type,
+        from: vec![Blake::blake(&burn.lock_script)],
+        to: None,
+        quantity: burn.prev_out.quantity,
+    };
+    match action {
+        Action::MintAsset {
+            shard_id,
+            output,
+            ..
+        } => vec![created(*shard_id, Blake::blake(tracker), output.lock_script_hash, output.supply)],
+        Action::IncreaseAssetSupply {
+            shard_id,
+            asset_type,
+            output,
+            ..
+        } => vec![created(*shard_id, *asset_type, output.lock_script_hash, output.supply)],
+        Action::WrapCCC {
+            shard_id,
+            lock_script_hash,
+            quantity,
+            ..
+        } => vec![created(*shard_id, H160::zero(), *lock_script_hash, *quantity)],
+        Action::TransferAsset {
+            burns,
+            inputs,
+            outputs,
+            ..
+        } => {
+            let outputs = outputs.iter().map(|output| {
+                let from: BTreeSet<H160> = inputs
+                    .iter()
+                    .filter(|input| {
+                        input.prev_out.asset_type == output.asset_type && input.prev_out.shard_id == output.shard_id
+                    })
+                    .map(|input| Blake::blake(&input.lock_script))
+                    .collect();
+                AssetLog {
+                    tracker,
+                    shard_id: output.shard_id,
+                    asset_type: output.asset_type,
+                    from: from.into_iter().collect(),
+                    to: Some(output.lock_script_hash),
+                    quantity: output.quantity,
+                }
+            });
+            outputs.chain(burns.iter().map(destroyed)).collect()
+        }
+        Action::UnwrapCCC {
+            burn,
+            ..
+        } => vec![destroyed(burn)],
+        _ => Vec::new(),
+    }
+}
+
+fn log_key(prefix: u8, number: BlockNumber) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 8);
+    key.push(prefix);
+    key.extend_from_slice(&number.to_be_bytes());
+    key
+}
+
+fn asset_type_item(asset_type: &H160) -> Vec<u8> {
+    let mut item = vec![b'a'];
+    item.extend_from_slice(&asset_type[..]);
+    item
+}
+
+fn shard_item(shard_id: ShardId) -> Vec<u8> {
+    let mut item = vec![b's'];
+    item.extend_from_slice(&shard_id.to_be_bytes());
+    item
+}
+
+/// A 2048-bit bloom filter in which an item sets 3 bits.
+#[derive(Clone, Debug, PartialEq)]
+struct Bloom(Vec<u8>);
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom(vec![0; BLOOM_BYTES])
+    }
+}
+
+impl Bloom {
+    fn bits(item: &[u8]) -> Vec<usize> {
+        let hash = blake256(item);
+        (0..3).map(|i| ((usize::from(hash[2 * i]) << 8) | usize::from(hash[2 * i + 1])) % (BLOOM_BYTES * 8)).collect()
+    }
+
+    fn accrue(&mut self, item: &[u8]) {
+        for bit in Self::bits(item) {
+            self.0[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    fn accrue_bloom(&mut self, other: &Bloom) {
+        for (byte, other) in self.0.iter_mut().zip(&other.0) {
+            *byte |= other;
+        }
+    }
+
+    fn contains(&self, item: &[u8]) -> bool {
+        Self::bits(item).into_iter().all(|bit| self.0[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_and_decode_asset_log() {
+        let minted = AssetLog {
+            tracker: Tracker::from(primitives::H256::random()),
+            shard_id: 1,
+            asset_type: H160::random(),
+            from: vec![],
+            to: Some(H160::random()),
+            quantity: 100,
+        };
+        let burned = AssetLog {
+            from: vec![H160::random(), H160::random()],
+            to: None,
+            ..minted.clone()
+        };
+        for log in vec![minted, burned] {
+            assert_eq!(log, rlp::decode(&rlp::encode(&log)).unwrap());
+        }
+    }
+
+    #[test]
+    fn scan_is_bounded() {
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        let asset_log = AssetLogDB::new(Arc::clone(&db), 0, 1000);
+        let log = AssetLog {
+            tracker: Tracker::from(primitives::H256::random()),
+            shard_id: 0,
+            asset_type: H160::random(),
+            from: vec![],
+            to: Some(H160::random()),
+            quantity: 100,
+        };
+        let mut bloom = Bloom::default();
+        bloom.accrue(&asset_type_item(&log.asset_type));
+        bloom.accrue(&shard_item(log.shard_id));
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_ASSET_LOG, &log_key(REGION_PREFIX, 0), &bloom.0);
+        for number in &[2, 4] {
+            let mut s = RlpStream::new();
+            s.append_list(&[log.clone()]);
+            batch.put(db::COL_ASSET_LOG, &log_key(LOG_PREFIX, *number), &s.out());
+            batch.put(db::COL_ASSET_LOG, &log_key(BLOOM_PREFIX, *number), &bloom.0);
+        }
+        db.write(batch).unwrap();
+
+        let filter = AssetLogFilter {
+            asset_type: None,
+            shard_id: None,
+            from_block: 1,
+            to_block: 10,
+        };
+        assert_eq!(Ok(vec![(2, log.clone()), (4, log.clone())]), asset_log.scan(&filter, 10, 0, 10, 10));
+        // Finding enough logs stops the scan.
+        assert_eq!(Ok(vec![(2, log.clone())]), asset_log.scan(&filter, 10, 0, 1, 3));
+        assert_eq!(
+            Err(AssetLogError::TooManyBlocksScanned {
+                max_blocks: 3
+            }),
+            asset_log.scan(&filter, 10, 1, 1, 3)
+        );
+        // The blocks in the regions that don't match are not counted.
+        let other = AssetLogFilter {
+            asset_type: Some(H160::random()),
+            ..filter
+        };
+        assert!(!asset_log.bloom(REGION_PREFIX, 0).unwrap().contains(&asset_type_item(&other.asset_type.unwrap())));
+        assert_eq!(Ok(vec![]), asset_log.scan(&other, 10, 0, 10, 0));
+    }
+
+    #[test]
+    fn bloom_contains_accrued_items() {
+        let asset_type = H160::random();
+        let mut bloom = Bloom::default();
+        assert!(!bloom.contains(&asset_type_item(&asset_type)));
+        bloom.accrue(&asset_type_item(&asset_type));
+        assert!(bloom.contains(&asset_type_item(&asset_type)));
+
+        let mut region = Bloom::default();
+        region.accrue_bloom(&bloom);
+        assert!(region.contains(&asset_type_item(&asset_type)));
+    }
+}
@@ -14,28 +14,36 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::address_index::{AddressIndex, AddressIndexError};
+use super::asset_index::AssetIndex;
+use super::asset_log::{AssetLogDB, AssetLogError, AssetLogFilter, LocalizedAssetLog};
 use super::block_info::BestBlockChanged;
 use super::body_db::{BodyDB, BodyProvider};
 use super::extras::{BlockDetails, TransactionAddress};
 use super::headerchain::{HeaderChain, HeaderProvider};
 use super::invoice_db::{InvoiceDB, InvoiceProvider};
-use super::route::{tree_route, ImportRoute};
+use super::route::{tree_route, ImportRoute, TreeRoute};
+use super::text_index::TextIndex;
 use crate::blockchain_info::BlockChainInfo;
+use crate::consensus::stake::FeeDistribution;
 use crate::consensus::CodeChainEngine;
-use crate::db;
+use crate::db::{self, Key, Readable, Writable};
 use crate::encoded;
-use crate::invoice::Invoice;
-use crate::transaction::LocalizedTransaction;
+use crate::invoice::{Invoice, TransactionAttempt};
+use crate::transaction::{LocalizedTransaction, SignedTransaction};
 use crate::views::{BlockView, HeaderView};
-use ctypes::{BlockHash, BlockNumber, Tracker, TxHash};
+use ckey::Address;
+use ctypes::{BlockHash, BlockNumber, ShardId, Tracker, TxHash};
 use kvdb::{DBTransaction, KeyValueDB};
 use parking_lot::RwLock;
-use primitives::H256;
+use primitives::{H160, H256};
 use rlp::RlpStream;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 const BEST_BLOCK_KEY: &[u8] = b"best-block";
 const BEST_PROPOSAL_BLOCK_KEY: &[u8] = b"best-proposal-block";
+const PRUNED_BODY_NUMBER_KEY: &[u8] = b"pruned-body-number";
 
 /// Structure providing fast access to blockchain data.
 ///
@@ -45,13 +53,23 @@ pub struct BlockChain {
     best_block_hash: RwLock<BlockHash>,
     /// The hash of the block which has the best score among the proposal blocks
     best_proposal_block_hash: RwLock<BlockHash>,
+    /// The number of the highest canonical block whose body has been pruned.
+    /// Zero means nothing is pruned, since the genesis body is always kept.
+    pruned_body_number: RwLock<BlockNumber>,
 
     headerchain: HeaderChain,
     body_db: BodyDB,
     invoice_db: InvoiceDB,
+    address_index: Option<AddressIndex>,
+    asset_index: Option<AssetIndex>,
+    text_index: Option<TextIndex>,
+    asset_log: Option<AssetLogDB>,
 
     pending_best_block_hash: RwLock<Option<BlockHash>>,
     pending_best_proposal_block_hash: RwLock<Option<BlockHash>>,
+    pending_pruned_body_number: RwLock<Option<BlockNumber>>,
+
+    db: Arc<dyn KeyValueDB>,
 }
 
 fn get_or_insert_with<F: FnOnce() -> BlockHash>(db: &dyn KeyValueDB, key: &[u8], default: F) -> BlockHash {
@@ -70,26 +88,98 @@ fn get_or_insert_with<F: FnOnce() -> BlockHash>(db: &dyn KeyValueDB, key: &[u8],
 
 impl BlockChain {
     /// Create new instance of blockchain from given Genesis.
-    pub fn new(genesis: &[u8], db: Arc<dyn KeyValueDB>) -> Self {
+    /// The address index, the asset index and the text index are maintained only if they are enabled.
+    /// The asset log is maintained if its retention, the number of the latest blocks whose logs are kept, is given.
+    pub fn new(
+        genesis: &[u8],
+        db: Arc<dyn KeyValueDB>,
+        address_index: bool,
+        asset_index: bool,
+        text_index: bool,
+        asset_log: Option<BlockNumber>,
+    ) -> Self {
         let genesis_block = BlockView::new(genesis);
 
         // load best block
         let best_block_hash = get_or_insert_with(&*db, BEST_BLOCK_KEY, || genesis_block.hash());
         let best_proposal_block_hash = get_or_insert_with(&*db, BEST_PROPOSAL_BLOCK_KEY, || genesis_block.hash());
+        let pruned_body_number = db
+            .get(db::COL_EXTRA, PRUNED_BODY_NUMBER_KEY)
+            .expect("Low level database error. Some issue with disk?")
+            .map(|bytes| rlp::decode(&bytes).expect("Pruned body number must be encoded properly"))
+            .unwrap_or(0);
+
+        let headerchain = HeaderChain::new(&genesis_block.header_view(), db.clone());
+        let best_block_number = headerchain.block_number(&best_block_hash).expect("Best block always exists");
+        let address_index = if address_index {
+            Some(AddressIndex::new(db.clone(), best_block_number))
+        } else {
+            AddressIndex::reset(&*db);
+            None
+        };
+        let asset_index = if asset_index {
+            Some(AssetIndex::new(db.clone(), best_block_number))
+        } else {
+            AssetIndex::reset(&*db);
+            None
+        };
+        let text_index = if text_index {
+            Some(TextIndex::new(db.clone(), best_block_number))
+        } else {
+            TextIndex::reset(&*db);
+            None
+        };
+        let asset_log = match asset_log {
+            Some(retention) => Some(AssetLogDB::new(db.clone(), best_block_number, retention)),
+            None => {
+                AssetLogDB::reset(&*db);
+                None
+            }
+        };
 
         Self {
             best_block_hash: RwLock::new(best_block_hash),
             best_proposal_block_hash: RwLock::new(best_proposal_block_hash),
+            pruned_body_number: RwLock::new(pruned_body_number),
 
-            headerchain: HeaderChain::new(&genesis_block.header_view(), db.clone()),
+            headerchain,
             body_db: BodyDB::new(&genesis_block, db.clone()),
             invoice_db: InvoiceDB::new(db.clone()),
+            address_index,
+            asset_index,
+            text_index,
+            asset_log,
 
             pending_best_block_hash: RwLock::new(None),
             pending_best_proposal_block_hash: RwLock::new(None),
+            pending_pruned_body_number: RwLock::new(None),
+
+            db,
         }
     }
 
+    /// Returns whether the database has the address index, the asset index, the text index and the asset log.
+    pub fn maintained_indexes(db: &dyn KeyValueDB) -> (bool, bool, bool, bool) {
+        (
+            AddressIndex::is_maintained(db),
+            AssetIndex::is_maintained(db),
+            TextIndex::is_maintained(db),
+            AssetLogDB::is_maintained(db),
+        )
+    }
+
+    /// Returns the hash of the genesis block in the database.
+    pub fn stored_genesis_hash(db: &dyn KeyValueDB) -> Option<BlockHash> {
+        db.read(db::COL_EXTRA, &(0 as BlockNumber))
+    }
+
+    /// Returns the best block hash in the database.
+    pub fn stored_best_block_hash(db: &dyn KeyValueDB) -> Option<BlockHash> {
+        db.get(db::COL_EXTRA, BEST_BLOCK_KEY)
+            .expect("Low level database error. Some issue with disk?")
+            .map(|hash| H256::from_slice(&hash).into())
+    }
+
     pub fn insert_header(
         &self,
         batch: &mut DBTransaction,
@@ -102,6 +192,13 @@ impl BlockChain {
         }
     }
 
+    /// Remembers the senders of the verified transactions for the indices, so they aren't recovered again.
+    pub fn note_senders(&self, transactions: &[SignedTransaction]) {
+        if let Some(address_index) = &self.address_index {
+            address_index.note_senders(transactions);
+        }
+    }
+
     /// Inserts the block into backing cache database.
     /// Expects the block to be valid and already verified.
     /// If the block is already known, does nothing.
@@ -132,9 +229,33 @@ impl BlockChain {
         self.headerchain.insert_header(batch, &new_header, engine);
         self.body_db.insert_body(batch, &new_block);
         self.body_db.update_best_block(batch, &best_block_changed);
+        if let Some(address_index) = &self.address_index {
+            address_index.update_best_block(batch, &best_block_changed, self);
+        }
+        if let Some(asset_index) = &self.asset_index {
+            asset_index.update_best_block(batch, &best_block_changed, self);
+        }
+        if let Some(text_index) = &self.text_index {
+            text_index.update_best_block(batch, &best_block_changed, self);
+        }
+        if let Some(asset_log) = &self.asset_log {
+            asset_log.update_best_block(batch, &best_block_changed, self);
+        }
+        // The per-block hints list the transactions of the body, whatever else the invoices have.
+        let errors: HashMap<TxHash, Option<String>> =
+            invoices.iter().map(|invoice| (invoice.hash, invoice.error.clone())).collect();
+        let block_invoices = new_block
+            .transaction_hashes()
+            .into_iter()
+            .map(|hash| {
+                let error = errors.get(&hash).cloned().unwrap_or_default();
+                (hash, error)
+            })
+            .collect();
         for invoice in invoices {
             self.invoice_db.insert_invoice(batch, invoice.hash, invoice.tracker, invoice.error);
         }
+        self.invoice_db.insert_block_invoices(batch, new_block_hash, block_invoices);
 
         if let Some(best_block_hash) = best_block_changed.new_best_hash() {
             let mut pending_best_block_hash = self.pending_best_block_hash.write();
@@ -149,6 +270,22 @@ impl BlockChain {
         ImportRoute::new(new_block_hash, &best_block_changed)
     }
 
+    /// Writes how the fees of the block are distributed.
+    pub fn insert_fee_distribution(
+        &self,
+        batch: &mut DBTransaction,
+        hash: BlockHash,
+        fee_distribution: &FeeDistribution,
+    ) {
+        batch.write(db::COL_EXTRA, &hash, fee_distribution);
+    }
+
+    /// Returns how the fees of the block are distributed.
+    /// Returns None if the block is unknown, its body has been pruned, or its engine doesn't distribute fees.
+    pub fn fee_distribution(&self, hash: &BlockHash) -> Option<FeeDistribution> {
+        self.db.read(db::COL_EXTRA, hash)
+    }
+
     /// Apply pending insertion updates
     pub fn commit(&self) {
         ctrace!(BLOCKCHAIN, "Committing.");
@@ -169,6 +306,141 @@ impl BlockChain {
         if let Some(hash) = pending_best_proposal_block_hash.take() {
             *best_proposal_block_hash = hash;
         }
+
+        if let Some(number) = self.pending_pruned_body_number.write().take() {
+            *self.pruned_body_number.write() = number;
+        }
+    }
+
+    /// Deletes the bodies, the error hints, and the fee distributions of the canonical blocks that are older than
+    /// `retention_blocks` from the best block. At most `max_blocks` bodies are removed per call,
+    /// so the pruning catches up gradually instead of stalling a single import.
+    /// Returns the number of pruned bodies.
+    pub fn prune_ancient_bodies(
+        &self,
+        batch: &mut DBTransaction,
+        retention_blocks: BlockNumber,
+        max_blocks: BlockNumber,
+    ) -> BlockNumber {
+        let best_block_number = self.best_block_detail().number;
+        if best_block_number <= retention_blocks {
+            return 0
+        }
+        let mut pending_pruned_body_number = self.pending_pruned_body_number.write();
+        let pruned_body_number = pending_pruned_body_number.unwrap_or_else(|| *self.pruned_body_number.read());
+        let from = pruned_body_number + 1;
+        let to = ::std::cmp::min(best_block_number - retention_blocks, pruned_body_number + max_blocks);
+        if from > to {
+            return 0
+        }
+
+        for number in from..=to {
+            let hash = self.block_hash(number).expect("Canonical blocks below the best block must exist");
+            if let Some(body) = self.block_body(&hash) {
+                self.invoice_db
+                    .remove_invoices(batch, body.transactions().into_iter().map(|tx| (tx.hash(), tx.tracker())));
+            }
+            self.invoice_db.remove_block_invoices(batch, &hash);
+            batch.delete(db::COL_EXTRA, &Key::<FeeDistribution>::key(&hash));
+            self.body_db.remove_body(batch, &hash);
+        }
+        ctrace!(BLOCKCHAIN, "Pruned the bodies of blocks #{}..#{}", from, to);
+
+        batch.put(db::COL_EXTRA, PRUNED_BODY_NUMBER_KEY, &rlp::encode(&to));
+        *pending_pruned_body_number = Some(to);
+        to - pruned_body_number
+    }
+
+    /// The number of the highest block whose body has been pruned.
+    pub fn pruned_body_number(&self) -> BlockNumber {
+        *self.pruned_body_number.read()
+    }
+
+    /// Returns the route between the given blocks, which don't need to be canonical.
+    /// Returns None if any block in the route is unknown.
+    pub fn tree_route(&self, from: BlockHash, to: BlockHash) -> Option<TreeRoute> {
+        tree_route(self, from, to)
+    }
+
+    /// Returns true if the body of the given canonical block has been pruned.
+    pub fn is_body_pruned(&self, hash: &BlockHash) -> bool {
+        match self.block_number(hash) {
+            Some(number) => number <= self.pruned_body_number() && self.block_hash(number) == Some(*hash),
+            None => false,
+        }
+    }
+
+    /// Returns the canonical transactions sent by or paying to the address in the given block range.
+    /// Fails if the address index is disabled or the range starts below the first indexed block.
+    pub fn transaction_addresses_by_address(
+        &self,
+        address: &Address,
+        from: BlockNumber,
+        to: BlockNumber,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<TransactionAddress>, AddressIndexError> {
+        let address_index = self.address_index.as_ref().ok_or(AddressIndexError::Disabled)?;
+        let positions = address_index.transactions(address, from, to, skip, limit)?;
+        Ok(positions
+            .into_iter()
+            .filter_map(|(number, index)| {
+                Some(TransactionAddress {
+                    block_hash: self.block_hash(number)?,
+                    index,
+                })
+            })
+            .collect())
+    }
+
+    /// Returns the unspent assets in the shard that are locked by the lock script hash.
+    /// Returns None if the asset index is disabled.
+    pub fn assets_by_lock_script_hash(
+        &self,
+        shard_id: ShardId,
+        lock_script_hash: &H160,
+        skip: usize,
+        limit: usize,
+    ) -> Option<Vec<(Tracker, usize)>> {
+        let asset_index = self.asset_index.as_ref()?;
+        Some(asset_index.assets(shard_id, lock_script_hash, skip, limit))
+    }
+
+    /// Returns the hashes of the Store transactions of the texts certified by the address.
+    /// Returns None if the text index is disabled.
+    pub fn texts_by_certifier(&self, certifier: &Address, skip: usize, limit: usize) -> Option<Vec<TxHash>> {
+        let text_index = self.text_index.as_ref()?;
+        Some(text_index.texts_by_certifier(certifier, skip, limit))
+    }
+
+    /// Returns the hashes of the Store transactions of the texts whose content hash is `content_hash`.
+    /// Returns None if the text index is disabled.
+    pub fn texts_by_content_hash(&self, content_hash: &H256, skip: usize, limit: usize) -> Option<Vec<TxHash>> {
+        let text_index = self.text_index.as_ref()?;
+        Some(text_index.texts_by_content_hash(content_hash, skip, limit))
+    }
+
+    /// Returns the asset logs of the canonical blocks that match the filter.
+    /// Fails if the asset log is disabled or the range starts below the first logged block.
+    pub fn asset_logs(
+        &self,
+        filter: &AssetLogFilter,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<LocalizedAssetLog>, AssetLogError> {
+        let asset_log = self.asset_log.as_ref().ok_or(AssetLogError::Disabled)?;
+        let best_block_number = self.best_block_detail().number;
+        let logs = asset_log.logs_matching(filter, best_block_number, skip, limit)?;
+        Ok(logs
+            .into_iter()
+            .filter_map(|(block_number, log)| {
+                Some(LocalizedAssetLog {
+                    block_number,
+                    block_hash: self.block_hash(block_number)?,
+                    log,
+                })
+            })
+            .collect())
     }
 
     /// Calculate how best block is changed
@@ -267,6 +539,18 @@ impl BlockChain {
 
         self.headerchain.update_best_as_committed(batch, block_hash);
         self.body_db.update_best_block(batch, &best_block_changed);
+        if let Some(address_index) = &self.address_index {
+            address_index.update_best_block(batch, &best_block_changed, self);
+        }
+        if let Some(asset_index) = &self.asset_index {
+            asset_index.update_best_block(batch, &best_block_changed, self);
+        }
+        if let Some(text_index) = &self.text_index {
+            text_index.update_best_block(batch, &best_block_changed, self);
+        }
+        if let Some(asset_log) = &self.asset_log {
+            asset_log.update_best_block(batch, &best_block_changed, self);
+        }
 
         let mut pending_best_block_hash = self.pending_best_block_hash.write();
         batch.put(db::COL_EXTRA, BEST_BLOCK_KEY, &block_hash);
@@ -299,6 +583,9 @@ impl BlockChain {
             best_proposal_block_hash,
             best_block_number: best_block_detail.number,
             best_block_timestamp: best_block_header.timestamp(),
+            // The client replaces them with the block finalized by the engine.
+            finalized_block_number: best_block_detail.number,
+            finalized_block_hash: best_block_header.hash(),
         }
     }
 
@@ -373,6 +660,40 @@ pub trait BlockProvider: HeaderProvider + BodyProvider + InvoiceProvider {
         self.block_body(block_hash)
             .and_then(|body| self.block_number(block_hash).map(|n| body.view().localized_transactions(block_hash, n)))
     }
+
+    /// Get the canonical transactions with the given tracker, ordered by their block numbers.
+    /// The transactions in the blocks whose bodies are pruned are omitted.
+    fn transactions_by_tracker(&self, tracker: &Tracker) -> Vec<LocalizedTransaction> {
+        self.transaction_addresses_by_tracker(tracker)
+            .into_iter()
+            .filter_map(|address| self.transaction(&address))
+            .collect()
+    }
+
+    /// Get the transactions tried with the given tracker in the order they were first tried.
+    /// The failed ones come from the error hints, because they are not included in the blocks.
+    /// The included ones are the canonical transactions, so the ones in the retracted blocks are omitted.
+    fn transaction_attempts_by_tracker(&self, tracker: &Tracker) -> Vec<TransactionAttempt> {
+        let mut included: HashMap<TxHash, LocalizedTransaction> =
+            self.transactions_by_tracker(tracker).into_iter().map(|tx| (tx.hash(), tx)).collect();
+        let mut attempts: Vec<_> = self
+            .error_hints_by_tracker(tracker)
+            .into_iter()
+            .filter_map(|(hash, error_hint)| match (included.remove(&hash), error_hint) {
+                (Some(tx), _) => Some(TransactionAttempt::Included(tx)),
+                (None, Some(error_hint)) => Some(TransactionAttempt::Failed {
+                    hash,
+                    error_hint,
+                }),
+                (None, None) => None,
+            })
+            .collect();
+        // The canonical transactions whose invoices are not known come last, in the chain order.
+        let mut rest: Vec<_> = included.into_iter().map(|(_, tx)| tx).collect();
+        rest.sort_by_key(|tx| (tx.block_number, tx.transaction_index));
+        attempts.extend(rest.into_iter().map(TransactionAttempt::Included));
+        attempts
+    }
 }
 
 impl HeaderProvider for BlockChain {
@@ -411,6 +732,10 @@ impl BodyProvider for BlockChain {
         self.body_db.transaction_address_by_tracker(tracker)
     }
 
+    fn transaction_addresses_by_tracker(&self, tracker: &Tracker) -> Vec<TransactionAddress> {
+        self.body_db.transaction_addresses_by_tracker(tracker)
+    }
+
     fn block_body(&self, hash: &BlockHash) -> Option<encoded::Body> {
         self.body_db.block_body(hash)
     }
@@ -429,6 +754,772 @@ impl InvoiceProvider for BlockChain {
     fn error_hint(&self, hash: &TxHash) -> Option<String> {
         self.invoice_db.error_hint(hash)
     }
+
+    /// Falls back to the error hint of each transaction if the block was imported before
+    /// the per-block error hints were written.
+    fn error_hints_by_block(&self, hash: &BlockHash) -> Option<Vec<(TxHash, Option<String>)>> {
+        if let Some(hints) = self.invoice_db.error_hints_by_block(hash) {
+            return Some(hints)
+        }
+        let body = self.block_body(hash)?;
+        Some(body.transaction_hashes().into_iter().map(|hash| (hash, self.invoice_db.error_hint(&hash))).collect())
+    }
+}
+
+impl BlockProvider for BlockChain {
+    /// A block whose body has been pruned is still regarded as known.
+    fn is_known(&self, hash: &BlockHash) -> bool {
+        self.is_known_header(hash) && (self.is_known_body(hash) || self.is_body_pruned(hash))
+    }
 }
 
-impl BlockProvider for BlockChain {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::AssetLog;
+    use crate::db::NUM_COLUMNS;
+    use crate::scheme::Scheme;
+    use ccrypto::Blake;
+    use ckey::{public_to_address, Generator, KeyPair, NetworkId, Random, Signature};
+    use cstate::Text;
+    use ctypes::transaction::{
+        Action, AssetMintOutput, AssetOutPoint, AssetTransferInput, AssetTransferOutput, Transaction,
+    };
+    use ctypes::Header;
+    use kvdb_rocksdb::{Database, DatabaseConfig};
+    use primitives::{H160, U256};
+    use tempfile::{tempdir, TempDir};
+
+    /// The address index relies on the RocksDB iterator seeking to the start of the range.
+    fn rocksdb(dir: &TempDir) -> Arc<dyn KeyValueDB> {
+        Arc::new(Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), dir.path().to_str().unwrap()).unwrap())
+    }
+
+    #[test]
+    fn prune_ancient_bodies_keeps_headers_and_recent_bodies() {
+        const BLOCKS: BlockNumber = 300;
+        const RETENTION_BLOCKS: BlockNumber = 20;
+        const MAX_PRUNED_PER_IMPORT: BlockNumber = 4;
+
+        let scheme = Scheme::new_test();
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), false, false, false, None);
+
+        let mut block_hashes = vec![chain.genesis_hash()];
+        let mut transactions = vec![];
+        for number in 1..=BLOCKS {
+            let tx = SignedTransaction::new_with_sign(
+                Transaction {
+                    seq: 0,
+                    fee: 10,
+                    network_id: NetworkId::default(),
+                    action: Action::WrapCCC {
+                        shard_id: 0,
+                        lock_script_hash: H160::random(),
+                        parameters: vec![],
+                        quantity: 1,
+                        payer: Address::random(),
+                    },
+                },
+                Random.generate().unwrap().private(),
+            );
+            let invoice = Invoice {
+                tracker: tx.tracker(),
+                hash: tx.hash(),
+                error: Some("error".to_string()),
+            };
+
+            let mut header = Header::new();
+            header.set_parent_hash(*block_hashes.last().unwrap());
+            header.set_number(number);
+            header.set_score(U256::one());
+            let mut block = RlpStream::new_list(2);
+            block.append(&header);
+            block.append_list(&[tx.clone()]);
+
+            let fee_distribution = FeeDistribution {
+                author: Address::random(),
+                author_reward: number,
+                ..Default::default()
+            };
+
+            let mut batch = DBTransaction::new();
+            chain.insert_block(&mut batch, &block.out(), vec![invoice], &*scheme.engine);
+            chain.insert_fee_distribution(&mut batch, header.hash(), &fee_distribution);
+            chain.prune_ancient_bodies(&mut batch, RETENTION_BLOCKS, MAX_PRUNED_PER_IMPORT);
+            db.write(batch).unwrap();
+            chain.commit();
+
+            block_hashes.push(header.hash());
+            transactions.push(tx);
+        }
+
+        // The pruning is done against the best block committed before each import.
+        let pruned_body_number = BLOCKS - 1 - RETENTION_BLOCKS;
+        assert_eq!(pruned_body_number, chain.pruned_body_number());
+
+        assert!(chain.block_body(&block_hashes[0]).is_some());
+        for number in 1..=BLOCKS {
+            let hash = block_hashes[number as usize];
+            let tx = &transactions[number as usize - 1];
+            let tracker = tx.tracker().unwrap();
+
+            assert!(chain.block_header_data(&hash).is_some());
+            assert!(chain.is_known(&hash));
+            if number <= pruned_body_number {
+                assert!(chain.is_body_pruned(&hash));
+                assert!(chain.block_body(&hash).is_none());
+                assert!(chain.block(&hash).is_none());
+                assert!(!chain.is_known_error_hint(&tx.hash()));
+                assert_eq!(Vec::<(TxHash, Option<String>)>::new(), chain.error_hints_by_tracker(&tracker));
+                assert_eq!(None, chain.fee_distribution(&hash));
+            } else {
+                assert!(!chain.is_body_pruned(&hash));
+                assert!(chain.block_body(&hash).is_some());
+                assert_eq!(Some("error".to_string()), chain.error_hint(&tx.hash()));
+                assert_eq!(vec![(tx.hash(), Some("error".to_string()))], chain.error_hints_by_tracker(&tracker));
+                assert_eq!(Some(number), chain.fee_distribution(&hash).map(|distribution| distribution.author_reward));
+            }
+        }
+    }
+
+    #[test]
+    fn error_hints_by_block_matches_error_hint_of_each_transaction() {
+        let scheme = Scheme::new_test();
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), false, false, false, None);
+
+        let transactions: Vec<_> = (0..4).map(|_| pay(&Random.generate().unwrap(), Address::random())).collect();
+        let mut invoices: Vec<_> = transactions
+            .iter()
+            .enumerate()
+            .map(|(i, tx)| Invoice {
+                tracker: tx.tracker(),
+                hash: tx.hash(),
+                error: if i % 2 == 0 {
+                    None
+                } else {
+                    Some(format!("error {}", i))
+                },
+            })
+            .collect();
+        // An invoice of a transaction that isn't in the body isn't listed in the block.
+        invoices.push(Invoice {
+            tracker: None,
+            hash: TxHash::from(H256::random()),
+            error: Some("not in the block".to_string()),
+        });
+
+        let mut header = Header::new();
+        header.set_parent_hash(chain.genesis_hash());
+        header.set_number(1);
+        header.set_score(U256::one());
+        let mut block = RlpStream::new_list(2);
+        block.append(&header);
+        block.append_list(&transactions);
+
+        let mut batch = DBTransaction::new();
+        chain.insert_block(&mut batch, &block.out(), invoices, &*scheme.engine);
+        db.write(batch).unwrap();
+        chain.commit();
+
+        let hash = header.hash();
+        let expected: Vec<_> = transactions.iter().map(|tx| (tx.hash(), chain.error_hint(&tx.hash()))).collect();
+        assert_eq!(Some(None), expected.first().map(|(_, hint)| hint.clone()));
+        assert_eq!(Some(Some("error 1".to_string())), expected.get(1).map(|(_, hint)| hint.clone()));
+        assert_eq!(Some(expected.clone()), chain.error_hints_by_block(&hash));
+
+        // Blocks imported before the per-block error hints were written fall back to the error hint of each transaction.
+        let mut batch = DBTransaction::new();
+        chain.invoice_db.remove_block_invoices(&mut batch, &hash);
+        db.write(batch).unwrap();
+        assert_eq!(None, chain.invoice_db.error_hints_by_block(&hash));
+        assert_eq!(Some(expected), chain.error_hints_by_block(&hash));
+
+        assert_eq!(None, chain.error_hints_by_block(&BlockHash::from(H256::random())));
+    }
+
+    fn pay(sender: &KeyPair, receiver: Address) -> SignedTransaction {
+        SignedTransaction::new_with_sign(
+            Transaction {
+                seq: 0,
+                fee: 10,
+                network_id: NetworkId::default(),
+                action: Action::Pay {
+                    receiver,
+                    quantity: 1,
+                    memo: None,
+                },
+            },
+            sender.private(),
+        )
+    }
+
+    fn insert_block(
+        chain: &BlockChain,
+        db: &Arc<dyn KeyValueDB>,
+        scheme: &Scheme,
+        parent: BlockHash,
+        score: u64,
+        transactions: &[SignedTransaction],
+    ) -> BlockHash {
+        insert_block_with_invoices(chain, db, scheme, parent, score, transactions, vec![])
+    }
+
+    fn insert_block_with_invoices(
+        chain: &BlockChain,
+        db: &Arc<dyn KeyValueDB>,
+        scheme: &Scheme,
+        parent: BlockHash,
+        score: u64,
+        transactions: &[SignedTransaction],
+        invoices: Vec<Invoice>,
+    ) -> BlockHash {
+        let mut header = Header::new();
+        header.set_parent_hash(parent);
+        header.set_number(chain.block_number(&parent).unwrap() + 1);
+        header.set_score(U256::from(score));
+        let mut block = RlpStream::new_list(2);
+        block.append(&header);
+        block.append_list(transactions);
+
+        let mut batch = DBTransaction::new();
+        chain.note_senders(transactions);
+        chain.insert_block(&mut batch, &block.out(), invoices, &*scheme.engine);
+        db.write(batch).unwrap();
+        chain.commit();
+        header.hash()
+    }
+
+    #[test]
+    fn tree_route_between_forked_blocks() {
+        let scheme = Scheme::new_test();
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), false, false, false, None);
+        let genesis = chain.genesis_hash();
+
+        // genesis -> a1 -> a2 -> a3
+        //                -> b2 -> b3
+        let a1 = insert_block(&chain, &db, &scheme, genesis, 1, &[]);
+        let a2 = insert_block(&chain, &db, &scheme, a1, 1, &[]);
+        let a3 = insert_block(&chain, &db, &scheme, a2, 1, &[]);
+        let b2 = insert_block(&chain, &db, &scheme, a1, 2, &[]);
+        let b3 = insert_block(&chain, &db, &scheme, b2, 2, &[]);
+        assert_eq!(b3, chain.best_block_hash());
+
+        assert_eq!(
+            Some(TreeRoute {
+                ancestor: a1,
+                enacted: vec![b2, b3],
+                retracted: vec![a3, a2],
+            }),
+            chain.tree_route(a3, b3)
+        );
+        assert_eq!(
+            Some(TreeRoute {
+                ancestor: a1,
+                enacted: vec![a2, a3],
+                retracted: vec![b3, b2],
+            }),
+            chain.tree_route(b3, a3)
+        );
+        assert_eq!(
+            Some(TreeRoute {
+                ancestor: a1,
+                enacted: vec![a2, a3],
+                retracted: vec![],
+            }),
+            chain.tree_route(a1, a3)
+        );
+        assert_eq!(
+            Some(TreeRoute {
+                ancestor: a1,
+                enacted: vec![],
+                retracted: vec![b3, b2],
+            }),
+            chain.tree_route(b3, a1)
+        );
+        assert_eq!(
+            Some(TreeRoute {
+                ancestor: a3,
+                enacted: vec![],
+                retracted: vec![],
+            }),
+            chain.tree_route(a3, a3)
+        );
+
+        let unknown = BlockHash::from(H256::random());
+        assert_eq!(None, chain.tree_route(a3, unknown));
+        assert_eq!(None, chain.tree_route(unknown, b3));
+    }
+
+    #[test]
+    fn address_index_is_unwound_on_reorg() {
+        let scheme = Scheme::new_test();
+        let dir = tempdir().unwrap();
+        let db = rocksdb(&dir);
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), true, false, false, None);
+        let genesis = chain.genesis_hash();
+
+        let alice = Random.generate().unwrap();
+        let bob = Random.generate().unwrap();
+        let carol = Random.generate().unwrap();
+        let address = |key_pair: &KeyPair| public_to_address(key_pair.public());
+        let lookup = |key_pair: &KeyPair| chain.transaction_addresses_by_address(&address(key_pair), 0, 10, 0, 10);
+
+        let a1 = insert_block(&chain, &db, &scheme, genesis, 1, &[pay(&alice, address(&bob))]);
+        let a2 = insert_block(&chain, &db, &scheme, a1, 1, &[pay(&bob, address(&alice))]);
+        assert_eq!(a2, chain.best_block_hash());
+        let position = |block_hash: BlockHash, index: usize| TransactionAddress {
+            block_hash,
+            index,
+        };
+        assert_eq!(Ok(vec![position(a1, 0), position(a2, 0)]), lookup(&alice));
+        assert_eq!(Ok(vec![position(a1, 0), position(a2, 0)]), lookup(&bob));
+        assert_eq!(Ok(vec![]), lookup(&carol));
+
+        // Bob is paid at the same position in both branches.
+        let b1 = insert_block(&chain, &db, &scheme, genesis, 2, &[pay(&carol, address(&bob))]);
+        assert_eq!(a2, chain.best_block_hash());
+        let b2 = insert_block(&chain, &db, &scheme, b1, 2, &[]);
+        assert_eq!(b2, chain.best_block_hash());
+
+        assert_eq!(Ok(vec![]), lookup(&alice));
+        assert_eq!(Ok(vec![position(b1, 0)]), lookup(&bob));
+        assert_eq!(Ok(vec![position(b1, 0)]), lookup(&carol));
+    }
+
+    #[test]
+    fn address_index_rejects_blocks_below_its_start() {
+        let scheme = Scheme::new_test();
+        let dir = tempdir().unwrap();
+        let db = rocksdb(&dir);
+        let alice = Random.generate().unwrap();
+        let address = public_to_address(alice.public());
+
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), false, false, false, None);
+        let genesis = chain.genesis_hash();
+        let b1 = insert_block(&chain, &db, &scheme, genesis, 1, &[pay(&alice, Address::random())]);
+        assert_eq!(Err(AddressIndexError::Disabled), chain.transaction_addresses_by_address(&address, 0, 10, 0, 10));
+
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), true, false, false, None);
+        let b2 = insert_block(&chain, &db, &scheme, b1, 1, &[pay(&alice, Address::random())]);
+        assert_eq!(
+            Err(AddressIndexError::BelowStartBlock {
+                start_block: 2
+            }),
+            chain.transaction_addresses_by_address(&address, 1, 10, 0, 10)
+        );
+        assert_eq!(
+            Ok(vec![TransactionAddress {
+                block_hash: b2,
+                index: 0,
+            }]),
+            chain.transaction_addresses_by_address(&address, 2, 10, 0, 10)
+        );
+    }
+
+    #[test]
+    fn address_index_serves_the_requested_range() {
+        let scheme = Scheme::new_test();
+        let dir = tempdir().unwrap();
+        let db = rocksdb(&dir);
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), true, false, false, None);
+
+        let alice = Random.generate().unwrap();
+        let bob = Random.generate().unwrap();
+        let address = public_to_address(alice.public());
+        let mut parent = chain.genesis_hash();
+        let mut hashes = Vec::new();
+        for _ in 0..5 {
+            parent = insert_block(&chain, &db, &scheme, parent, 1, &[pay(&alice, public_to_address(bob.public()))]);
+            hashes.push(parent);
+        }
+        let position = |number: usize| TransactionAddress {
+            block_hash: hashes[number - 1],
+            index: 0,
+        };
+        assert_eq!(
+            Ok(vec![position(2), position(3), position(4)]),
+            chain.transaction_addresses_by_address(&address, 2, 4, 0, 10)
+        );
+        assert_eq!(Ok(vec![position(3)]), chain.transaction_addresses_by_address(&address, 2, 4, 1, 1));
+        assert_eq!(Ok(vec![]), chain.transaction_addresses_by_address(&address, 6, 10, 0, 10));
+    }
+
+    #[test]
+    fn transactions_by_tracker_follow_reorg() {
+        let scheme = Scheme::new_test();
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), false, false, false, None);
+        let genesis = chain.genesis_hash();
+
+        // The same mint signed by different people has the same tracker.
+        let lock_script_hash = H160::random();
+        let failed = mint_asset(&Random.generate().unwrap(), 0, 0, lock_script_hash);
+        let succeeded = mint_asset(&Random.generate().unwrap(), 0, 0, lock_script_hash);
+        let tracker = failed.tracker().unwrap();
+        assert_eq!(Some(tracker), succeeded.tracker());
+        assert_ne!(failed.hash(), succeeded.hash());
+        let invoice = |tx: &SignedTransaction, error: Option<&str>| Invoice {
+            tracker: tx.tracker(),
+            hash: tx.hash(),
+            error: error.map(ToString::to_string),
+        };
+        let failure = || vec![invoice(&failed, Some("error"))];
+        let success = || vec![invoice(&succeeded, None)];
+        let listing = |chain: &BlockChain| -> Vec<_> {
+            chain.transactions_by_tracker(&tracker).into_iter().map(|tx| (tx.block_hash, tx.hash())).collect()
+        };
+
+        let attempts = |chain: &BlockChain| -> Vec<_> {
+            chain
+                .transaction_attempts_by_tracker(&tracker)
+                .into_iter()
+                .map(|attempt| match attempt {
+                    TransactionAttempt::Included(tx) => (tx.hash(), Some(tx.block_hash), None),
+                    TransactionAttempt::Failed {
+                        hash,
+                        error_hint,
+                    } => (hash, None, Some(error_hint)),
+                })
+                .collect()
+        };
+
+        // A failed transaction isn't included in the block, only its invoice is.
+        let a1 = insert_block_with_invoices(&chain, &db, &scheme, genesis, 1, &[], failure());
+        let a2 = insert_block_with_invoices(&chain, &db, &scheme, a1, 1, &[succeeded.clone()], success());
+        assert_eq!(a2, chain.best_block_hash());
+        assert_eq!(vec![(a2, succeeded.hash())], listing(&chain));
+        assert_eq!(Some(a2), chain.transaction_address_by_tracker(&tracker).map(|address| address.block_hash));
+        assert_eq!(
+            vec![(failed.hash(), Some("error".to_string())), (succeeded.hash(), None)],
+            chain.error_hints_by_tracker(&tracker)
+        );
+        assert_eq!(
+            vec![(failed.hash(), None, Some("error".to_string())), (succeeded.hash(), Some(a2), None)],
+            attempts(&chain)
+        );
+
+        // The other branch has only the successful one.
+        let b1 = insert_block_with_invoices(&chain, &db, &scheme, genesis, 2, &[succeeded.clone()], success());
+        let b2 = insert_block(&chain, &db, &scheme, b1, 2, &[]);
+        assert_eq!(b2, chain.best_block_hash());
+        assert_eq!(vec![(b1, succeeded.hash())], listing(&chain));
+        // The invoices are kept after the reorg, so the failure is still reported.
+        assert_eq!(
+            vec![(failed.hash(), None, Some("error".to_string())), (succeeded.hash(), Some(b1), None)],
+            attempts(&chain)
+        );
+    }
+
+    fn asset_transaction(sender: &KeyPair, seq: u64, action: Action) -> SignedTransaction {
+        SignedTransaction::new_with_sign(
+            Transaction {
+                seq,
+                fee: 10,
+                network_id: NetworkId::default(),
+                action,
+            },
+            sender.private(),
+        )
+    }
+
+    #[test]
+    fn asset_index_follows_spends_and_reorg() {
+        let scheme = Scheme::new_test();
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), false, true, false, None);
+        let genesis = chain.genesis_hash();
+        let alice = Random.generate().unwrap();
+
+        let lock_script = vec![0x30, 0x01];
+        let lock_a: H160 = Blake::blake(&lock_script);
+        let lock_b = H160::random();
+        let asset_type = H160::random();
+        let assets = |lock_script_hash: &H160| chain.assets_by_lock_script_hash(0, lock_script_hash, 0, 10);
+
+        let mint = asset_transaction(&alice, 0, Action::MintAsset {
+            network_id: NetworkId::default(),
+            shard_id: 0,
+            metadata: "".to_string(),
+            approver: None,
+            registrar: None,
+            allowed_script_hashes: vec![],
+            output: Box::new(AssetMintOutput {
+                lock_script_hash: lock_a,
+                parameters: vec![],
+                supply: 100,
+            }),
+            approvals: vec![],
+        });
+        let minted = mint.tracker().unwrap();
+        let output = |lock_script_hash: H160, quantity: u64| AssetTransferOutput {
+            lock_script_hash,
+            parameters: vec![],
+            asset_type,
+            shard_id: 0,
+            quantity,
+        };
+        let transfer = asset_transaction(&alice, 1, Action::TransferAsset {
+            network_id: NetworkId::default(),
+            burns: vec![],
+            inputs: vec![AssetTransferInput {
+                prev_out: AssetOutPoint {
+                    tracker: minted,
+                    index: 0,
+                    asset_type,
+                    shard_id: 0,
+                    quantity: 100,
+                },
+                timelock: None,
+                lock_script: lock_script.clone(),
+                unlock_script: vec![],
+            }],
+            outputs: vec![output(lock_b, 30), output(lock_a, 70)],
+            metadata: "".to_string(),
+            approvals: vec![],
+            expiration: None,
+        });
+        let transferred = transfer.tracker().unwrap();
+
+        let a1 = insert_block(&chain, &db, &scheme, genesis, 1, &[mint.clone()]);
+        assert_eq!(Some(vec![(minted, 0)]), assets(&lock_a));
+
+        let a2 = insert_block(&chain, &db, &scheme, a1, 1, &[transfer]);
+        assert_eq!(a2, chain.best_block_hash());
+        assert_eq!(Some(vec![(transferred, 1)]), assets(&lock_a));
+        assert_eq!(Some(vec![(transferred, 0)]), assets(&lock_b));
+        assert_eq!(Some(vec![]), chain.assets_by_lock_script_hash(1, &lock_b, 0, 10));
+        assert_eq!(Some(vec![]), chain.assets_by_lock_script_hash(0, &lock_b, 1, 10));
+
+        // The other branch has the mint but not the transfer.
+        let b1 = insert_block(&chain, &db, &scheme, genesis, 2, &[mint]);
+        let b2 = insert_block(&chain, &db, &scheme, b1, 2, &[]);
+        assert_eq!(b2, chain.best_block_hash());
+        assert_eq!(Some(vec![(minted, 0)]), assets(&lock_a));
+        assert_eq!(Some(vec![]), assets(&lock_b));
+
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), false, false, false, None);
+        assert_eq!(None, chain.assets_by_lock_script_hash(0, &lock_a, 0, 10));
+    }
+
+    #[test]
+    fn text_index_follows_removes_and_reorg() {
+        let scheme = Scheme::new_test();
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), false, false, true, None);
+        let genesis = chain.genesis_hash();
+        let alice = Random.generate().unwrap();
+        let certifier = public_to_address(alice.public());
+        let content_hash = |content: &str| Text::new(content, &certifier).content_hash();
+
+        let store = |seq: u64, content: &str| {
+            asset_transaction(&alice, seq, Action::Store {
+                content: content.to_string(),
+                certifier,
+                signature: Signature::default(),
+            })
+        };
+        let hello = store(0, "hello");
+        let world = store(1, "world");
+        let remove = asset_transaction(&alice, 2, Action::Remove {
+            hash: hello.hash(),
+            signature: Signature::default(),
+        });
+
+        let a1 = insert_block(&chain, &db, &scheme, genesis, 1, &[hello.clone(), world.clone()]);
+        let mut both = vec![hello.hash(), world.hash()];
+        both.sort_by_key(|tx_hash| **tx_hash);
+        assert_eq!(Some(both), chain.texts_by_certifier(&certifier, 0, 10));
+        assert_eq!(Some(vec![hello.hash()]), chain.texts_by_content_hash(&content_hash("hello"), 0, 10));
+
+        let a2 = insert_block(&chain, &db, &scheme, a1, 1, &[remove]);
+        assert_eq!(a2, chain.best_block_hash());
+        assert_eq!(Some(vec![world.hash()]), chain.texts_by_certifier(&certifier, 0, 10));
+        assert_eq!(Some(vec![]), chain.texts_by_content_hash(&content_hash("hello"), 0, 10));
+        assert_eq!(Some(vec![world.hash()]), chain.texts_by_content_hash(&content_hash("world"), 0, 10));
+
+        // The other branch stores hello but neither stores world nor removes hello.
+        let b1 = insert_block(&chain, &db, &scheme, genesis, 2, &[hello.clone()]);
+        let b2 = insert_block(&chain, &db, &scheme, b1, 2, &[]);
+        assert_eq!(b2, chain.best_block_hash());
+        assert_eq!(Some(vec![hello.hash()]), chain.texts_by_certifier(&certifier, 0, 10));
+        assert_eq!(Some(vec![hello.hash()]), chain.texts_by_content_hash(&content_hash("hello"), 0, 10));
+        assert_eq!(Some(vec![]), chain.texts_by_content_hash(&content_hash("world"), 0, 10));
+
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), false, false, false, None);
+        assert_eq!(None, chain.texts_by_certifier(&certifier, 0, 10));
+    }
+
+    fn mint_asset(sender: &KeyPair, seq: u64, shard_id: ShardId, lock_script_hash: H160) -> SignedTransaction {
+        asset_transaction(sender, seq, Action::MintAsset {
+            network_id: NetworkId::default(),
+            shard_id,
+            metadata: "".to_string(),
+            approver: None,
+            registrar: None,
+            allowed_script_hashes: vec![],
+            output: Box::new(AssetMintOutput {
+                lock_script_hash,
+                parameters: vec![],
+                supply: 100,
+            }),
+            approvals: vec![],
+        })
+    }
+
+    #[test]
+    fn asset_logs_are_filtered_and_unwound_on_reorg() {
+        let scheme = Scheme::new_test();
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), false, false, false, Some(1000));
+        let genesis = chain.genesis_hash();
+        let alice = Random.generate().unwrap();
+
+        let lock_script = vec![0x30, 0x01];
+        let lock_a: H160 = Blake::blake(&lock_script);
+        let lock_b = H160::random();
+        let mint = mint_asset(&alice, 0, 0, lock_a);
+        let minted = mint.tracker().unwrap();
+        let asset_type: H160 = Blake::blake(minted);
+        let other_mint = mint_asset(&alice, 1, 1, lock_b);
+        let other_asset_type: H160 = Blake::blake(other_mint.tracker().unwrap());
+        let output = |lock_script_hash: H160, quantity: u64| AssetTransferOutput {
+            lock_script_hash,
+            parameters: vec![],
+            asset_type,
+            shard_id: 0,
+            quantity,
+        };
+        let transfer = asset_transaction(&alice, 2, Action::TransferAsset {
+            network_id: NetworkId::default(),
+            burns: vec![],
+            inputs: vec![AssetTransferInput {
+                prev_out: AssetOutPoint {
+                    tracker: minted,
+                    index: 0,
+                    asset_type,
+                    shard_id: 0,
+                    quantity: 100,
+                },
+                timelock: None,
+                lock_script: lock_script.clone(),
+                unlock_script: vec![],
+            }],
+            outputs: vec![output(lock_b, 30), output(lock_a, 70)],
+            metadata: "".to_string(),
+            approvals: vec![],
+            expiration: None,
+        });
+        let transferred = transfer.tracker().unwrap();
+
+        let filter = |asset_type: Option<H160>, shard_id: Option<ShardId>| AssetLogFilter {
+            asset_type,
+            shard_id,
+            from_block: 0,
+            to_block: 10,
+        };
+        let logs = |filter: AssetLogFilter| {
+            chain
+                .asset_logs(&filter, 0, 10)
+                .unwrap()
+                .into_iter()
+                .map(|log| (log.block_number, log.log))
+                .collect::<Vec<_>>()
+        };
+        let minted_log = AssetLog {
+            tracker: minted,
+            shard_id: 0,
+            asset_type,
+            from: vec![],
+            to: Some(lock_a),
+            quantity: 100,
+        };
+        let other_minted_log = AssetLog {
+            tracker: other_mint.tracker().unwrap(),
+            shard_id: 1,
+            asset_type: other_asset_type,
+            from: vec![],
+            to: Some(lock_b),
+            quantity: 100,
+        };
+        let transferred_log = |to: H160, quantity: u64| AssetLog {
+            tracker: transferred,
+            shard_id: 0,
+            asset_type,
+            from: vec![lock_a],
+            to: Some(to),
+            quantity,
+        };
+
+        let a1 = insert_block(&chain, &db, &scheme, genesis, 1, &[mint.clone(), other_mint.clone()]);
+        let a2 = insert_block(&chain, &db, &scheme, a1, 1, &[transfer]);
+        assert_eq!(a2, chain.best_block_hash());
+        let asset_logs =
+            vec![(1, minted_log.clone()), (2, transferred_log(lock_b, 30)), (2, transferred_log(lock_a, 70))];
+        assert_eq!(asset_logs, logs(filter(Some(asset_type), None)));
+        assert_eq!(asset_logs, logs(filter(Some(asset_type), Some(0))));
+        assert_eq!(vec![(1, other_minted_log.clone())], logs(filter(None, Some(1))));
+        assert_eq!(Vec::<(BlockNumber, AssetLog)>::new(), logs(filter(Some(asset_type), Some(1))));
+        assert_eq!(Vec::<(BlockNumber, AssetLog)>::new(), logs(filter(Some(H160::random()), None)));
+        assert_eq!(4, logs(filter(None, None)).len());
+
+        // Paging and the range.
+        let page = chain.asset_logs(&filter(Some(asset_type), None), 1, 1).unwrap();
+        assert_eq!(vec![transferred_log(lock_b, 30)], page.into_iter().map(|log| log.log).collect::<Vec<_>>());
+        let in_range = chain
+            .asset_logs(
+                &AssetLogFilter {
+                    from_block: 2,
+                    ..filter(Some(asset_type), None)
+                },
+                0,
+                10,
+            )
+            .unwrap();
+        assert_eq!(vec![a2, a2], in_range.iter().map(|log| log.block_hash).collect::<Vec<_>>());
+
+        // The other branch has the mints but not the transfer.
+        let b1 = insert_block(&chain, &db, &scheme, genesis, 2, &[mint, other_mint]);
+        let b2 = insert_block(&chain, &db, &scheme, b1, 2, &[]);
+        assert_eq!(b2, chain.best_block_hash());
+        assert_eq!(vec![(1, minted_log)], logs(filter(Some(asset_type), None)));
+        assert_eq!(vec![(1, other_minted_log)], logs(filter(None, Some(1))));
+
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), false, false, false, None);
+        assert_eq!(Err(AssetLogError::Disabled), chain.asset_logs(&filter(None, None), 0, 10));
+    }
+
+    #[test]
+    fn asset_logs_older_than_the_retention_are_removed() {
+        const RETENTION: BlockNumber = 3;
+
+        let scheme = Scheme::new_test();
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let chain = BlockChain::new(&scheme.genesis_block(), db.clone(), false, false, false, Some(RETENTION));
+        let alice = Random.generate().unwrap();
+        let lock_script_hash = H160::random();
+
+        let mut parent = chain.genesis_hash();
+        for seq in 0..5 {
+            parent = insert_block(&chain, &db, &scheme, parent, 1, &[mint_asset(&alice, seq, 0, lock_script_hash)]);
+        }
+        let filter = |from_block: BlockNumber| AssetLogFilter {
+            asset_type: None,
+            shard_id: None,
+            from_block,
+            to_block: 5,
+        };
+        assert_eq!(
+            Err(AssetLogError::BelowStartBlock {
+                start_block: 3
+            }),
+            chain.asset_logs(&filter(2), 0, 10)
+        );
+        let logs = chain.asset_logs(&filter(3), 0, 10).unwrap();
+        assert_eq!(vec![3, 4, 5], logs.iter().map(|log| log.block_number).collect::<Vec<_>>());
+        let stored_logs = db.iter(db::COL_ASSET_LOG).filter(|(key, _)| key[0] == b'l').count();
+        assert_eq!(RETENTION as usize, stored_logs);
+    }
+}
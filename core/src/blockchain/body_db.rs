@@ -31,6 +31,8 @@ use std::mem;
 use std::sync::Arc;
 
 const BODY_CACHE_SIZE: usize = 1000;
+/// The maximum number of the transactions indexed per tracker. The oldest ones are dropped from the index.
+const MAX_TRANSACTIONS_PER_TRACKER: usize = 128;
 
 pub struct BodyDB {
     // block cache
@@ -87,6 +89,13 @@ impl BodyDB {
         batch.put(db::COL_BODIES, &hash, &compressed_body);
     }
 
+    /// Removes the block body from the database.
+    /// The transaction addresses are kept so that the owning block can still be located.
+    pub fn remove_body(&self, batch: &mut DBTransaction, hash: &BlockHash) {
+        self.body_cache.lock().remove(hash);
+        batch.delete(db::COL_BODIES, hash);
+    }
+
     pub fn update_best_block(&self, batch: &mut DBTransaction, best_block_changed: &BestBlockChanged) {
         let mut pending_addresses_by_hash = self.pending_addresses_by_hash.write();
         let mut pending_addresses_by_tracker = self.pending_addresses_by_tracker.lock();
@@ -257,7 +266,8 @@ impl BodyDB {
 
         inserted_address
             .into_iter()
-            .map(|(hash, address)| {
+            .map(|(hash, mut address)| {
+                address.keep_latest(MAX_TRANSACTIONS_PER_TRACKER);
                 if address.is_empty() {
                     (hash, None)
                 } else {
@@ -286,6 +296,10 @@ pub trait BodyProvider {
 
     fn transaction_address_by_tracker(&self, tracker: &Tracker) -> Option<TransactionAddress>;
 
+    /// Get the addresses of the canonical transactions with the given tracker, ordered by their block numbers.
+    /// At most `MAX_TRANSACTIONS_PER_TRACKER` latest ones are kept.
+    fn transaction_addresses_by_tracker(&self, tracker: &Tracker) -> Vec<TransactionAddress>;
+
     /// Get the block body (transactions).
     fn block_body(&self, hash: &BlockHash) -> Option<encoded::Body>;
 }
@@ -307,6 +321,13 @@ impl BodyProvider for BodyDB {
         addresses.into_iter().next()
     }
 
+    fn transaction_addresses_by_tracker(&self, tracker: &Tracker) -> Vec<TransactionAddress> {
+        self.db
+            .read_with_cache(db::COL_EXTRA, &mut *self.addresses_by_tracker_cache.lock(), tracker)
+            .map(|addresses| addresses.into_iter().collect())
+            .unwrap_or_default()
+    }
+
     /// Get block body data
     fn block_body(&self, hash: &BlockHash) -> Option<encoded::Body> {
         // Check cache first
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::consensus::stake::FeeDistribution;
 use crate::db::Key;
 use crate::types::TransactionId;
 use ctypes::{BlockHash, BlockNumber, Tracker, TxHash};
@@ -33,6 +34,8 @@ enum ExtrasIndex {
     TransactionAddresses = 3,
     // (Reserved) = 4,
     // (Reserved) = 5,
+    /// Fee distribution index
+    FeeDistribution = 6,
 }
 
 fn with_index(hash: &H256, i: ExtrasIndex) -> H264 {
@@ -75,6 +78,14 @@ impl Key<BlockDetails> for BlockHash {
     }
 }
 
+impl Key<FeeDistribution> for BlockHash {
+    type Target = H264;
+
+    fn key(&self) -> H264 {
+        with_index(self, ExtrasIndex::FeeDistribution)
+    }
+}
+
 impl Key<TransactionAddress> for TxHash {
     type Target = H264;
 
@@ -118,6 +129,7 @@ impl From<TransactionAddress> for TransactionId {
 }
 
 /// Represents address of certain transaction that has the same tracker
+/// The addresses are ordered by the block numbers, the oldest first.
 #[derive(Debug, Default, PartialEq, Clone, RlpEncodableWrapper, RlpDecodableWrapper)]
 pub struct TransactionAddresses {
     addresses: Vec<TransactionAddress>,
@@ -133,6 +145,14 @@ impl TransactionAddresses {
     pub fn is_empty(&self) -> bool {
         self.addresses.is_empty()
     }
+
+    /// Drops the oldest addresses so that at most `limit` addresses are left.
+    pub fn keep_latest(&mut self, limit: usize) {
+        if self.addresses.len() > limit {
+            let dropped = self.addresses.len() - limit;
+            self.addresses.drain(..dropped);
+        }
+    }
 }
 
 impl IntoIterator for TransactionAddresses {
@@ -219,6 +239,22 @@ mod tests {
         });
     }
 
+    #[test]
+    fn keep_latest_drops_the_oldest_addresses() {
+        let address = |index: usize| TransactionAddress {
+            block_hash: H256::from(index as u64).into(),
+            index,
+        };
+        let mut addresses = TransactionAddresses {
+            addresses: (0..5).map(address).collect(),
+        };
+
+        addresses.keep_latest(5);
+        assert_eq!((0..5).map(address).collect::<Vec<_>>(), addresses.addresses);
+        addresses.keep_latest(2);
+        assert_eq!(vec![address(3), address(4)], addresses.addresses);
+    }
+
     #[test]
     fn add() {
         let t1 = TransactionAddresses {
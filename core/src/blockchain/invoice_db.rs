@@ -15,7 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db::{self, CacheUpdatePolicy, Key, Readable, Writable};
-use ctypes::{Tracker, TxHash};
+use ctypes::{BlockHash, Tracker, TxHash};
 use kvdb::{DBTransaction, KeyValueDB};
 use parking_lot::RwLock;
 use primitives::{H256, H264};
@@ -32,6 +32,8 @@ pub struct InvoiceDB {
     tracker_cache: RwLock<HashMap<Tracker, TrackerInvoices>>,
     // transaction hash -> error hint
     hash_cache: RwLock<HashMap<TxHash, Option<String>>>,
+    // block hash -> transaction hashes + error hints
+    block_cache: RwLock<HashMap<BlockHash, TrackerInvoices>>,
 
     db: Arc<dyn KeyValueDB>,
 }
@@ -42,6 +44,7 @@ impl InvoiceDB {
         Self {
             tracker_cache: Default::default(),
             hash_cache: Default::default(),
+            block_cache: Default::default(),
 
             db,
         }
@@ -73,6 +76,62 @@ impl InvoiceDB {
 
         batch.write_with_cache(db::COL_ERROR_HINT, &mut *hint_cache, hash, error_hint, CacheUpdatePolicy::Remove);
     }
+
+    /// Writes the error hints of all the transactions in the block, in the order of the transactions,
+    /// so that they can be read with a single lookup.
+    pub fn insert_block_invoices(
+        &self,
+        batch: &mut DBTransaction,
+        block_hash: BlockHash,
+        invoices: Vec<(TxHash, Option<String>)>,
+    ) {
+        let mut block_cache = self.block_cache.write();
+        let invoices: TrackerInvoices = invoices.into();
+        batch.write_with_cache(db::COL_ERROR_HINT, &mut *block_cache, block_hash, invoices, CacheUpdatePolicy::Remove);
+    }
+
+    /// Removes the per-block error hints of the given block.
+    pub fn remove_block_invoices(&self, batch: &mut DBTransaction, block_hash: &BlockHash) {
+        self.block_cache.write().remove(block_hash);
+        batch.delete(db::COL_ERROR_HINT, &with_index(block_hash, ErrorHintIndex::BlockToHashes));
+    }
+
+    /// Removes the error hints of the given transactions.
+    pub fn remove_invoices(
+        &self,
+        batch: &mut DBTransaction,
+        transactions: impl IntoIterator<Item = (TxHash, Option<Tracker>)>,
+    ) {
+        let mut hashes_cache = self.tracker_cache.write();
+        let mut hint_cache = self.hash_cache.write();
+
+        let mut removed_by_tracker: HashMap<Tracker, Vec<TxHash>> = HashMap::new();
+        for (hash, tracker) in transactions {
+            hint_cache.remove(&hash);
+            batch.delete(db::COL_ERROR_HINT, &with_index(&hash, ErrorHintIndex::HashToHint));
+            if let Some(tracker) = tracker {
+                removed_by_tracker.entry(tracker).or_default().push(hash);
+            }
+        }
+
+        for (tracker, removed) in removed_by_tracker {
+            let mut hashes: TrackerInvoices =
+                self.db.read_with_cache(db::COL_ERROR_HINT, &mut *hashes_cache, &tracker).unwrap_or_default();
+            hashes.retain(|(hash, _)| !removed.contains(hash));
+            if hashes.is_empty() {
+                hashes_cache.remove(&tracker);
+                batch.delete(db::COL_ERROR_HINT, &with_index(&tracker, ErrorHintIndex::TrackerToHashes));
+            } else {
+                batch.write_with_cache(
+                    db::COL_ERROR_HINT,
+                    &mut *hashes_cache,
+                    tracker,
+                    hashes,
+                    CacheUpdatePolicy::Remove,
+                );
+            }
+        }
+    }
 }
 
 /// Interface for querying invoices.
@@ -85,6 +144,10 @@ pub trait InvoiceProvider {
 
     /// Get error hint
     fn error_hint(&self, hash: &TxHash) -> Option<String>;
+
+    /// Get the error hints of the transactions in the given block.
+    /// Returns None if they are not known.
+    fn error_hints_by_block(&self, hash: &BlockHash) -> Option<Vec<(TxHash, Option<String>)>>;
 }
 
 impl InvoiceProvider for InvoiceDB {
@@ -102,6 +165,12 @@ impl InvoiceProvider for InvoiceDB {
     fn error_hint(&self, hash: &TxHash) -> Option<String> {
         self.db.read_with_cache(db::COL_ERROR_HINT, &mut *self.hash_cache.write(), hash)?
     }
+
+    fn error_hints_by_block(&self, hash: &BlockHash) -> Option<Vec<(TxHash, Option<String>)>> {
+        self.db
+            .read_with_cache(db::COL_ERROR_HINT, &mut *self.block_cache.write(), hash)
+            .map(|hashes| (*hashes).clone())
+    }
 }
 
 #[derive(Clone, Default)]
@@ -158,6 +227,7 @@ impl DerefMut for TrackerInvoices {
 enum ErrorHintIndex {
     TrackerToHashes = 0,
     HashToHint = 1,
+    BlockToHashes = 2,
 }
 
 impl From<ErrorHintIndex> for u8 {
@@ -182,6 +252,14 @@ impl Key<TrackerInvoices> for Tracker {
     }
 }
 
+impl Key<TrackerInvoices> for BlockHash {
+    type Target = H264;
+
+    fn key(&self) -> H264 {
+        with_index(self, ErrorHintIndex::BlockToHashes)
+    }
+}
+
 fn with_index(hash: &H256, i: ErrorHintIndex) -> H264 {
     let mut result = H264::default();
     result[0] = i as u8;
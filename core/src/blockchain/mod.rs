@@ -14,6 +14,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod address_index;
+mod asset_index;
+mod asset_log;
 mod block_info;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod blockchain;
@@ -22,10 +25,14 @@ mod extras;
 mod headerchain;
 mod invoice_db;
 mod route;
+mod text_index;
 
+pub use self::address_index::AddressIndexError;
+pub use self::asset_index::ShrinkAssetIndexKeys;
+pub use self::asset_log::{AssetLog, AssetLogError, AssetLogFilter, LocalizedAssetLog};
 pub use self::blockchain::{BlockChain, BlockProvider};
 pub use self::body_db::BodyProvider;
 pub use self::extras::{BlockDetails, TransactionAddress, TransactionAddresses};
 pub use self::headerchain::HeaderProvider;
 pub use self::invoice_db::InvoiceProvider;
-pub use self::route::ImportRoute;
+pub use self::route::{ImportRoute, TreeRoute};
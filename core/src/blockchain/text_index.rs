@@ -0,0 +1,260 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use super::address_index::{has_start_block, open_start_block, reset_start_block};
+use super::block_info::BestBlockChanged;
+use super::body_db::BodyProvider;
+use super::headerchain::HeaderProvider;
+use crate::db;
+use crate::UnverifiedTransaction;
+use ckey::Address;
+use cstate::Text;
+use ctypes::transaction::Action;
+use ctypes::{BlockNumber, TxHash};
+use kvdb::{DBTransaction, KeyValueDB};
+use primitives::H256;
+use rlp::RlpStream;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const START_BLOCK_KEY: &[u8] = b"text-index-start-block";
+
+/// The keys of the texts certified by an address are the prefix, the certifier and the hash of the Store transaction.
+const CERTIFIER_PREFIX: u8 = b'c';
+/// The keys of the texts having a content are the prefix, the content hash and the hash of the Store transaction.
+const CONTENT_PREFIX: u8 = b'h';
+/// A record maps the hash of a Store transaction to the certifier and the content hash of the text.
+/// It is kept after the text is removed, so that retracting the Remove can restore the other keys.
+const RECORD_PREFIX: u8 = b't';
+
+/// Maps a certifier and a content hash to the texts stored in the state.
+///
+/// The index is not backfilled, so the texts stored before `start_block` are not listed.
+pub struct TextIndex {
+    start_block: BlockNumber,
+    db: Arc<dyn KeyValueDB>,
+}
+
+impl TextIndex {
+    /// Opens the index. If it is enabled for the first time, it starts from the block after the best block.
+    pub fn new(db: Arc<dyn KeyValueDB>, best_block_number: BlockNumber) -> Self {
+        if db.get(db::COL_EXTRA, START_BLOCK_KEY).expect("Low level database error. Some issue with disk?").is_none() {
+            // The removals made while the index was disabled are not reflected in the old entries.
+            let mut batch = DBTransaction::new();
+            for (key, _) in db.iter(db::COL_TEXT_INDEX) {
+                batch.delete(db::COL_TEXT_INDEX, &key);
+            }
+            db.write(batch).expect("Low level database error. Some issue with disk?");
+        }
+        Self {
+            start_block: open_start_block(&*db, START_BLOCK_KEY, best_block_number),
+            db,
+        }
+    }
+
+    /// Forgets the start block of a disabled index.
+    /// The entries left behind become stale, so they are removed when the index is enabled again.
+    pub fn reset(db: &dyn KeyValueDB) {
+        reset_start_block(db, START_BLOCK_KEY)
+    }
+
+    /// Whether the database has the index.
+    pub fn is_maintained(db: &dyn KeyValueDB) -> bool {
+        has_start_block(db, START_BLOCK_KEY)
+    }
+
+    /// Undoes the retracted blocks from the newest one, and then redoes the enacted blocks from the oldest one.
+    pub fn update_best_block<P>(&self, batch: &mut DBTransaction, best_block_changed: &BestBlockChanged, chain: &P)
+    where
+        P: HeaderProvider + BodyProvider, {
+        let best_block = match best_block_changed.best_block() {
+            Some(block) => block,
+            None => return,
+        };
+
+        // None if the key is deleted.
+        let mut changes = HashMap::new();
+        if let BestBlockChanged::BranchBecomingCanonChain {
+            tree_route,
+            ..
+        } = best_block_changed
+        {
+            for hash in &tree_route.retracted {
+                let number = chain.block_number(hash).expect("Retracted block must be in database.");
+                let body = chain.block_body(hash).expect("Retracted block must be in database.");
+                for tx in body.transactions().iter().rev() {
+                    self.retract(&mut changes, number, tx);
+                }
+            }
+            for hash in &tree_route.enacted {
+                let number = chain.block_number(hash).expect("Enacted block must be in database.");
+                let body = chain.block_body(hash).expect("Enacted block must be in database.");
+                for tx in &body.transactions() {
+                    self.enact(&mut changes, number, tx);
+                }
+            }
+        }
+        for tx in &best_block.transactions() {
+            self.enact(&mut changes, best_block.header_view().number(), tx);
+        }
+
+        for (key, value) in changes {
+            match value {
+                Some(value) => batch.put(db::COL_TEXT_INDEX, &key, &value),
+                None => batch.delete(db::COL_TEXT_INDEX, &key),
+            }
+        }
+    }
+
+    /// Returns the hashes of the Store transactions of the texts certified by the address.
+    /// The hashes are sorted, and the first `skip` ones are omitted.
+    pub fn texts_by_certifier(&self, certifier: &Address, skip: usize, limit: usize) -> Vec<TxHash> {
+        self.tx_hashes(&certifier_key(certifier, None), skip, limit)
+    }
+
+    /// Returns the hashes of the Store transactions of the texts whose content hash is `content_hash`.
+    /// The hashes are sorted, and the first `skip` ones are omitted.
+    pub fn texts_by_content_hash(&self, content_hash: &H256, skip: usize, limit: usize) -> Vec<TxHash> {
+        self.tx_hashes(&content_key(content_hash, None), skip, limit)
+    }
+
+    fn tx_hashes(&self, prefix: &[u8], skip: usize, limit: usize) -> Vec<TxHash> {
+        // The RocksDB iterator doesn't stop at the end of the prefix.
+        self.db
+            .iter_from_prefix(db::COL_TEXT_INDEX, prefix)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .skip(skip)
+            .take(limit)
+            .map(|(key, _)| H256::from_slice(&key[key.len() - 32..]).into())
+            .collect()
+    }
+
+    fn enact(&self, changes: &mut HashMap<Vec<u8>, Option<Vec<u8>>>, number: BlockNumber, tx: &UnverifiedTransaction) {
+        if number < self.start_block {
+            return
+        }
+        match &tx.action {
+            Action::Store {
+                content,
+                certifier,
+                ..
+            } => {
+                let tx_hash = tx.hash();
+                let content_hash = Text::new(content, certifier).content_hash();
+                changes.insert(record_key(&tx_hash), Some(encode_record(certifier, &content_hash)));
+                changes.insert(certifier_key(certifier, Some(&tx_hash)), Some(Vec::new()));
+                changes.insert(content_key(&content_hash, Some(&tx_hash)), Some(Vec::new()));
+            }
+            Action::Remove {
+                hash,
+                ..
+            } => {
+                if let Some((certifier, content_hash)) = self.record(changes, hash) {
+                    changes.insert(certifier_key(&certifier, Some(hash)), None);
+                    changes.insert(content_key(&content_hash, Some(hash)), None);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn retract(
+        &self,
+        changes: &mut HashMap<Vec<u8>, Option<Vec<u8>>>,
+        number: BlockNumber,
+        tx: &UnverifiedTransaction,
+    ) {
+        if number < self.start_block {
+            return
+        }
+        match &tx.action {
+            Action::Store {
+                content,
+                certifier,
+                ..
+            } => {
+                let tx_hash = tx.hash();
+                let content_hash = Text::new(content, certifier).content_hash();
+                changes.insert(record_key(&tx_hash), None);
+                changes.insert(certifier_key(certifier, Some(&tx_hash)), None);
+                changes.insert(content_key(&content_hash, Some(&tx_hash)), None);
+            }
+            Action::Remove {
+                hash,
+                ..
+            } => {
+                if let Some((certifier, content_hash)) = self.record(changes, hash) {
+                    changes.insert(certifier_key(&certifier, Some(hash)), Some(Vec::new()));
+                    changes.insert(content_key(&content_hash, Some(hash)), Some(Vec::new()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Reads the record of the Store transaction, including the changes that are not written yet.
+    /// Returns None if the text was stored before the start block.
+    fn record(&self, changes: &HashMap<Vec<u8>, Option<Vec<u8>>>, tx_hash: &TxHash) -> Option<(Address, H256)> {
+        let key = record_key(tx_hash);
+        let bytes = match changes.get(&key) {
+            Some(value) => value.clone()?,
+            None => self
+                .db
+                .get(db::COL_TEXT_INDEX, &key)
+                .expect("Low level database error. Some issue with disk?")?
+                .to_vec(),
+        };
+        let rlp = rlp::Rlp::new(&bytes);
+        let certifier = rlp.val_at(0).expect("A text record must be encoded properly");
+        let content_hash = rlp.val_at(1).expect("A text record must be encoded properly");
+        Some((certifier, content_hash))
+    }
+}
+
+fn encode_record(certifier: &Address, content_hash: &H256) -> Vec<u8> {
+    let mut s = RlpStream::new_list(2);
+    s.append(certifier);
+    s.append(content_hash);
+    s.out()
+}
+
+fn record_key(tx_hash: &TxHash) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 32);
+    key.push(RECORD_PREFIX);
+    key.extend_from_slice(&tx_hash[..]);
+    key
+}
+
+fn certifier_key(certifier: &Address, tx_hash: Option<&TxHash>) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 20 + 32);
+    key.push(CERTIFIER_PREFIX);
+    key.extend_from_slice(&certifier[..]);
+    if let Some(tx_hash) = tx_hash {
+        key.extend_from_slice(&tx_hash[..]);
+    }
+    key
+}
+
+fn content_key(content_hash: &H256, tx_hash: Option<&TxHash>) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 32 + 32);
+    key.push(CONTENT_PREFIX);
+    key.extend_from_slice(&content_hash[..]);
+    if let Some(tx_hash) = tx_hash {
+        key.extend_from_slice(&tx_hash[..]);
+    }
+    key
+}
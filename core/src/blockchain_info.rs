@@ -36,4 +36,8 @@ pub struct BlockChainInfo {
     pub best_block_number: BlockNumber,
     /// Best blockchain block timestamp.
     pub best_block_timestamp: u64,
+    /// The number of the highest block that the engine considers final.
+    pub finalized_block_number: BlockNumber,
+    /// The hash of the highest block that the engine considers final.
+    pub finalized_block_hash: BlockHash,
 }
@@ -0,0 +1,132 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use cstate::{ActionHandler, FindActionHandler};
+use ctypes::{BlockNumber, Tracker};
+use cvm::ChainTimeInfo;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// The ages of the transactions looked up while executing a block.
+///
+/// The timelocked inputs of a block usually refer to a few transactions, and every lookup reads the headers through
+/// the client. It belongs to the block being executed, so that nothing is shared between the blocks.
+#[derive(Default)]
+pub struct ChainTimeCache {
+    block_ages: Mutex<HashMap<(Tracker, BlockNumber), Option<u64>>>,
+    time_ages: Mutex<HashMap<(Tracker, u64), Option<u64>>>,
+}
+
+impl ChainTimeCache {
+    /// Returns the `ChainTimeInfo` that answers from the cache and asks the client only for the missing ages.
+    pub fn with<'a, C>(&'a self, client: &'a C) -> CachedChainTime<'a, C> {
+        CachedChainTime {
+            client,
+            cache: self,
+        }
+    }
+}
+
+pub struct CachedChainTime<'a, C> {
+    client: &'a C,
+    cache: &'a ChainTimeCache,
+}
+
+fn get_or_insert_with<K, F>(map: &Mutex<HashMap<K, Option<u64>>>, key: K, f: F) -> Option<u64>
+where
+    K: Eq + Hash,
+    F: FnOnce() -> Option<u64>, {
+    if let Some(age) = map.lock().get(&key) {
+        return *age
+    }
+    // The lock isn't held while reading the client.
+    let age = f();
+    *map.lock().entry(key).or_insert(age)
+}
+
+impl<'a, C: ChainTimeInfo> ChainTimeInfo for CachedChainTime<'a, C> {
+    fn transaction_block_age(&self, tracker: &Tracker, parent_block_number: BlockNumber) -> Option<u64> {
+        get_or_insert_with(&self.cache.block_ages, (*tracker, parent_block_number), || {
+            self.client.transaction_block_age(tracker, parent_block_number)
+        })
+    }
+
+    fn transaction_time_age(&self, tracker: &Tracker, parent_timestamp: u64) -> Option<u64> {
+        get_or_insert_with(&self.cache.time_ages, (*tracker, parent_timestamp), || {
+            self.client.transaction_time_age(tracker, parent_timestamp)
+        })
+    }
+
+    fn caps_multisig_pubkeys(&self, parent_block_number: BlockNumber) -> bool {
+        self.client.caps_multisig_pubkeys(parent_block_number)
+    }
+}
+
+impl<'a, C: FindActionHandler> FindActionHandler for CachedChainTime<'a, C> {
+    fn find_action_handler_for(&self, id: u64) -> Option<&dyn ActionHandler> {
+        self.client.find_action_handler_for(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitives::H256;
+    use std::cell::Cell;
+
+    #[derive(Default)]
+    struct CountingClient {
+        lookups: Cell<usize>,
+    }
+
+    impl ChainTimeInfo for CountingClient {
+        fn transaction_block_age(&self, _tracker: &Tracker, parent_block_number: BlockNumber) -> Option<u64> {
+            self.lookups.set(self.lookups.get() + 1);
+            Some(parent_block_number)
+        }
+
+        fn transaction_time_age(&self, _tracker: &Tracker, parent_timestamp: u64) -> Option<u64> {
+            self.lookups.set(self.lookups.get() + 1);
+            Some(parent_timestamp)
+        }
+
+        fn caps_multisig_pubkeys(&self, _parent_block_number: BlockNumber) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn each_distinct_age_is_looked_up_once() {
+        let client = CountingClient::default();
+        let cache = ChainTimeCache::default();
+        let trackers: Vec<Tracker> = (0..3).map(|_| H256::random().into()).collect();
+        {
+            let cached = cache.with(&client);
+            for _ in 0..100 {
+                for tracker in &trackers {
+                    assert_eq!(Some(10), cached.transaction_block_age(tracker, 10));
+                    assert_eq!(Some(1000), cached.transaction_time_age(tracker, 1000));
+                }
+            }
+        }
+        assert_eq!(6, client.lookups.get());
+
+        // The ages against another parent are looked up again.
+        assert_eq!(Some(11), cache.with(&client).transaction_block_age(&trackers[0], 11));
+        assert_eq!(7, client.lookups.get());
+    }
+}
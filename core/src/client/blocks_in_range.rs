@@ -0,0 +1,235 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{BlockChainClient, BlockChainTrait};
+use crate::encoded;
+use crate::types::BlockId;
+use ctypes::BlockNumber;
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockRangeError {
+    /// The body of the block has been pruned by the retention policy.
+    BodyPruned {
+        number: BlockNumber,
+    },
+    /// The block is not in the canonical chain.
+    NotFound {
+        number: BlockNumber,
+    },
+}
+
+impl BlockRangeError {
+    pub fn number(&self) -> BlockNumber {
+        match self {
+            BlockRangeError::BodyPruned {
+                number,
+            }
+            | BlockRangeError::NotFound {
+                number,
+            } => *number,
+        }
+    }
+}
+
+impl fmt::Display for BlockRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlockRangeError::BodyPruned {
+                number,
+            } => write!(f, "The body of block #{} has been pruned", number),
+            BlockRangeError::NotFound {
+                number,
+            } => write!(f, "Block #{} is not in the canonical chain", number),
+        }
+    }
+}
+
+/// Iterates the canonical blocks in a range, reading one block at a time.
+/// It yields an error at the first unavailable block and stops there.
+/// The blocks are read as the chain is when each of them is read, so a reorg during the iteration is not detected.
+pub struct BlocksInRange<'a, C: ?Sized> {
+    client: &'a C,
+    range: Range<BlockNumber>,
+    failed: bool,
+}
+
+impl<'a, C> BlocksInRange<'a, C>
+where
+    C: BlockChainClient + ?Sized,
+{
+    pub fn new(client: &'a C, range: Range<BlockNumber>) -> Self {
+        BlocksInRange {
+            client,
+            range,
+            failed: false,
+        }
+    }
+}
+
+impl<'a, C> Iterator for BlocksInRange<'a, C>
+where
+    C: BlockChainClient + ?Sized,
+{
+    type Item = Result<encoded::Block, BlockRangeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None
+        }
+        let number = self.range.next()?;
+        let id = BlockId::Number(number);
+        match self.client.block(&id) {
+            Some(block) => Some(Ok(block)),
+            None => {
+                self.failed = true;
+                if self.client.is_block_body_pruned(&id) {
+                    Some(Err(BlockRangeError::BodyPruned {
+                        number,
+                    }))
+                } else {
+                    Some(Err(BlockRangeError::NotFound {
+                        number,
+                    }))
+                }
+            }
+        }
+    }
+}
+
+/// Reads the headers of up to `count` consecutive blocks from the given block.
+/// It reads the canonical block of each height from the number index, and stops at the first block
+/// that isn't linked to the previous one by its parent hash. It happens when the chain is reorganized
+/// during the walk or when the first block is off the canonical chain.
+pub fn headers_in_range<C>(client: &C, from: &BlockId, count: u64, reverse: bool) -> Vec<encoded::Header>
+where
+    C: BlockChainTrait + ?Sized, {
+    let mut headers: Vec<encoded::Header> = Vec::new();
+    if count == 0 {
+        return headers
+    }
+    let mut next = client.block_header(from);
+    while let Some(header) = next {
+        let (number, hash, parent_hash) = (header.number(), header.hash(), header.parent_hash());
+        headers.push(header);
+        if headers.len() as u64 == count {
+            break
+        }
+        next = if reverse {
+            if number == 0 {
+                break
+            }
+            client.block_header(&BlockId::Number(number - 1)).filter(|parent| parent.hash() == parent_hash)
+        } else {
+            client.block_header(&BlockId::Number(number + 1)).filter(|child| child.parent_hash() == hash)
+        };
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{BlockChainTrait, TestBlockChainClient};
+
+    #[test]
+    fn iterates_blocks_until_the_first_missing_one() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(300, 1);
+
+        let blocks: Vec<_> = client.blocks_in_range(0..310).collect();
+        assert_eq!(302, blocks.len());
+        for (number, block) in blocks[..301].iter().enumerate() {
+            let expected = client.block(&BlockId::Number(number as BlockNumber)).unwrap();
+            assert_eq!(expected.into_inner(), block.clone().unwrap().into_inner());
+        }
+        assert_eq!(
+            Err(BlockRangeError::NotFound {
+                number: 301
+            }),
+            blocks[301].clone().map(|_| ())
+        );
+    }
+
+    #[test]
+    fn empty_range() {
+        let client = TestBlockChainClient::new();
+        assert_eq!(0, client.blocks_in_range(1..1).count());
+    }
+
+    fn numbers(headers: &[encoded::Header]) -> Vec<BlockNumber> {
+        headers.iter().map(encoded::Header::number).collect()
+    }
+
+    #[test]
+    fn headers_in_range_forward() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(10, 0);
+
+        assert_eq!(vec![3, 4, 5, 6], numbers(&client.headers_in_range(&BlockId::Number(3), 4, false)));
+        assert_eq!(vec![8, 9, 10], numbers(&client.headers_in_range(&BlockId::Number(8), 100, false)));
+        assert_eq!(vec![0, 1], numbers(&client.headers_in_range(&BlockId::Earliest, 2, false)));
+        assert_eq!(Vec::<BlockNumber>::new(), numbers(&client.headers_in_range(&BlockId::Number(3), 0, false)));
+        assert_eq!(Vec::<BlockNumber>::new(), numbers(&client.headers_in_range(&BlockId::Number(11), 4, false)));
+    }
+
+    #[test]
+    fn headers_in_range_reverse() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(10, 0);
+
+        let headers = client.headers_in_range(&BlockId::Latest, 4, true);
+        assert_eq!(vec![10, 9, 8, 7], numbers(&headers));
+        for pair in headers.windows(2) {
+            assert_eq!(pair[0].parent_hash(), pair[1].hash());
+        }
+        assert_eq!(vec![2, 1, 0], numbers(&client.headers_in_range(&BlockId::Number(2), 100, true)));
+    }
+
+    #[test]
+    fn headers_in_range_across_a_reorg() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(6, 0);
+        let old_best = client.chain_info().best_block_hash;
+        let fork_point = client.block_hash(&BlockId::Number(3)).unwrap();
+        let side_4 = client.add_block_on(fork_point, None, vec![]);
+        let side_5 = client.add_block_on(side_4, None, vec![]);
+        let side_6 = client.add_block_on(side_5, None, vec![]);
+        let side_7 = client.add_block_on(side_6, None, vec![]);
+        client.set_best_block(side_7);
+
+        // The walk doesn't jump from a reorged-away block to the canonical chain.
+        let headers = client.headers_in_range(&BlockId::Hash(old_best), 5, true);
+        assert_eq!(vec![old_best], headers.iter().map(encoded::Header::hash).collect::<Vec<_>>());
+        let headers = client.headers_in_range(&BlockId::Hash(old_best), 5, false);
+        assert_eq!(vec![old_best], headers.iter().map(encoded::Header::hash).collect::<Vec<_>>());
+
+        let headers = client.headers_in_range(&BlockId::Latest, 6, true);
+        assert_eq!(vec![7, 6, 5, 4, 3, 2], numbers(&headers));
+        assert_eq!(
+            vec![side_7, side_6, side_5, side_4, fork_point],
+            headers[..5].iter().map(encoded::Header::hash).collect::<Vec<_>>()
+        );
+
+        let headers = client.headers_in_range(&BlockId::Number(2), 10, false);
+        assert_eq!(vec![2, 3, 4, 5, 6, 7], numbers(&headers));
+        assert_eq!(
+            vec![side_4, side_5, side_6, side_7],
+            headers[2..].iter().map(encoded::Header::hash).collect::<Vec<_>>()
+        );
+    }
+}
@@ -14,7 +14,15 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crossbeam_channel::{self, Sender};
 use ctypes::BlockHash;
+use parking_lot::RwLock;
+use std::sync::{Arc, Weak};
+use std::thread;
+
+/// The number of the chain events that can wait for the notification thread.
+/// The importer blocks only when this many events are waiting.
+const NOTIFY_QUEUE_SIZE: usize = 128;
 
 /// Represents what has to be handled by actor listening to chain events
 pub trait ChainNotify: Send + Sync {
@@ -43,3 +51,242 @@ pub trait ChainNotify: Send + Sync {
         // does nothing by default
     }
 }
+
+enum ChainEvent {
+    NewHeaders {
+        imported: Vec<BlockHash>,
+        invalid: Vec<BlockHash>,
+        enacted: Vec<BlockHash>,
+        retracted: Vec<BlockHash>,
+        sealed: Vec<BlockHash>,
+        new_best_proposal: Option<BlockHash>,
+    },
+    NewBlocks {
+        imported: Vec<BlockHash>,
+        invalid: Vec<BlockHash>,
+        enacted: Vec<BlockHash>,
+        retracted: Vec<BlockHash>,
+        sealed: Vec<BlockHash>,
+    },
+}
+
+impl ChainEvent {
+    fn dispatch(&self, notify: &dyn ChainNotify) {
+        match self {
+            ChainEvent::NewHeaders {
+                imported,
+                invalid,
+                enacted,
+                retracted,
+                sealed,
+                new_best_proposal,
+            } => notify.new_headers(
+                imported.clone(),
+                invalid.clone(),
+                enacted.clone(),
+                retracted.clone(),
+                sealed.clone(),
+                *new_best_proposal,
+            ),
+            ChainEvent::NewBlocks {
+                imported,
+                invalid,
+                enacted,
+                retracted,
+                sealed,
+            } => {
+                notify.new_blocks(imported.clone(), invalid.clone(), enacted.clone(), retracted.clone(), sealed.clone())
+            }
+        }
+    }
+}
+
+/// Notifies the chain events to the registered actors after the importer committed them.
+/// The actors are notified on a dedicated thread in the order of the events, so a slow one doesn't delay the import.
+/// An actor registered as synchronous is notified before the importer goes on,
+/// which is needed only if it must handle an event before the next block is imported.
+pub struct ChainNotifier {
+    synchronous: RwLock<Vec<Weak<dyn ChainNotify>>>,
+    asynchronous: Arc<RwLock<Vec<Weak<dyn ChainNotify>>>>,
+    sender: Sender<ChainEvent>,
+}
+
+impl ChainNotifier {
+    pub fn new() -> Self {
+        Self::with_queue_size(NOTIFY_QUEUE_SIZE)
+    }
+
+    fn with_queue_size(queue_size: usize) -> Self {
+        let (sender, receiver) = crossbeam_channel::bounded::<ChainEvent>(queue_size);
+        let asynchronous = Arc::new(RwLock::new(Vec::new()));
+        let targets = Arc::clone(&asynchronous);
+        // The thread ends when the notifier is dropped and the remaining events are dispatched.
+        thread::Builder::new()
+            .name("Chain notifier".to_string())
+            .spawn(move || {
+                for event in receiver {
+                    // The targets are copied so that an actor can register another one while it's notified.
+                    let targets = targets.read().clone();
+                    notify_all(&targets, &event);
+                }
+            })
+            .expect("Cannot spawn the chain notifier thread");
+        ChainNotifier {
+            synchronous: RwLock::new(Vec::new()),
+            asynchronous,
+            sender,
+        }
+    }
+
+    pub fn add(&self, target: Weak<dyn ChainNotify>) {
+        self.asynchronous.write().push(target);
+    }
+
+    pub fn add_synchronous(&self, target: Weak<dyn ChainNotify>) {
+        self.synchronous.write().push(target);
+    }
+
+    pub fn new_headers(
+        &self,
+        imported: Vec<BlockHash>,
+        invalid: Vec<BlockHash>,
+        enacted: Vec<BlockHash>,
+        retracted: Vec<BlockHash>,
+        sealed: Vec<BlockHash>,
+        new_best_proposal: Option<BlockHash>,
+    ) {
+        self.dispatch(ChainEvent::NewHeaders {
+            imported,
+            invalid,
+            enacted,
+            retracted,
+            sealed,
+            new_best_proposal,
+        });
+    }
+
+    pub fn new_blocks(
+        &self,
+        imported: Vec<BlockHash>,
+        invalid: Vec<BlockHash>,
+        enacted: Vec<BlockHash>,
+        retracted: Vec<BlockHash>,
+        sealed: Vec<BlockHash>,
+    ) {
+        self.dispatch(ChainEvent::NewBlocks {
+            imported,
+            invalid,
+            enacted,
+            retracted,
+            sealed,
+        });
+    }
+
+    fn dispatch(&self, event: ChainEvent) {
+        let synchronous = self.synchronous.read().clone();
+        notify_all(&synchronous, &event);
+        // It blocks only if the queue is full.
+        if self.sender.send(event).is_err() {
+            cerror!(CLIENT, "The chain notifier thread has stopped");
+        }
+    }
+}
+
+fn notify_all(targets: &[Weak<dyn ChainNotify>], event: &ChainEvent) {
+    for target in targets {
+        if let Some(target) = target.upgrade() {
+            event.dispatch(&*target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::Receiver;
+    use primitives::H256;
+    use std::time::{Duration, Instant};
+
+    /// Sends the imported blocks of each event after waiting for `delay`.
+    struct SlowNotify {
+        delay: Duration,
+        sender: Sender<Vec<BlockHash>>,
+    }
+
+    impl SlowNotify {
+        fn new(delay: Duration) -> (Arc<Self>, Receiver<Vec<BlockHash>>) {
+            let (sender, receiver) = crossbeam_channel::unbounded();
+            let notify = Arc::new(SlowNotify {
+                delay,
+                sender,
+            });
+            (notify, receiver)
+        }
+    }
+
+    impl ChainNotify for SlowNotify {
+        fn new_blocks(
+            &self,
+            imported: Vec<BlockHash>,
+            _invalid: Vec<BlockHash>,
+            _enacted: Vec<BlockHash>,
+            _retracted: Vec<BlockHash>,
+            _sealed: Vec<BlockHash>,
+        ) {
+            thread::sleep(self.delay);
+            self.sender.send(imported).unwrap();
+        }
+    }
+
+    fn new_blocks(notifier: &ChainNotifier, number: u64) {
+        notifier.new_blocks(vec![H256::from(number).into()], vec![], vec![], vec![], vec![]);
+    }
+
+    #[test]
+    fn slow_notify_does_not_delay_the_dispatch() {
+        let notifier = ChainNotifier::new();
+        let (slow, receiver) = SlowNotify::new(Duration::from_millis(50));
+        notifier.add(Arc::downgrade(&slow) as Weak<dyn ChainNotify>);
+
+        let started = Instant::now();
+        for number in 0..20 {
+            new_blocks(&notifier, number);
+        }
+        assert!(started.elapsed() < Duration::from_millis(500), "It took {:?}", started.elapsed());
+
+        for number in 0..20 {
+            let imported = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(vec![BlockHash::from(H256::from(number))], imported);
+        }
+    }
+
+    #[test]
+    fn synchronous_notify_is_notified_before_the_dispatch_returns() {
+        let notifier = ChainNotifier::new();
+        let (notify, receiver) = SlowNotify::new(Duration::from_millis(10));
+        notifier.add_synchronous(Arc::downgrade(&notify) as Weak<dyn ChainNotify>);
+
+        for number in 0..3 {
+            new_blocks(&notifier, number);
+            assert_eq!(Ok(vec![BlockHash::from(H256::from(number))]), receiver.try_recv());
+        }
+    }
+
+    #[test]
+    fn dispatch_blocks_when_the_queue_is_full() {
+        let notifier = Arc::new(ChainNotifier::with_queue_size(1));
+        let (slow, receiver) = SlowNotify::new(Duration::from_millis(300));
+        notifier.add(Arc::downgrade(&slow) as Weak<dyn ChainNotify>);
+
+        let started = Instant::now();
+        // The first one is taken by the thread, and the second one fills the queue.
+        new_blocks(&notifier, 0);
+        new_blocks(&notifier, 1);
+        new_blocks(&notifier, 2);
+        assert!(started.elapsed() >= Duration::from_millis(250), "It took {:?}", started.elapsed());
+
+        for _ in 0..3 {
+            receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+    }
+}
@@ -14,40 +14,56 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::chain_notify::ChainNotifier;
+use super::db_backup::{self, BackupError};
 use super::importer::Importer;
+use super::orphaned_proposals::{OrphanedProposal, OrphanedProposalLog};
 use super::{
-    AccountData, AssetClient, BlockChainClient, BlockChainInfo, BlockChainTrait, BlockProducer, ChainNotify,
-    ClientConfig, DatabaseClient, EngineClient, EngineInfo, ExecuteClient, ImportBlock, ImportResult,
+    AccountData, AssetClient, BackupClient, BlockChainClient, BlockChainInfo, BlockChainTrait, BlockProducer,
+    ChainNotify, ClientConfig, DatabaseClient, EngineClient, EngineInfo, ExecuteClient, ImportBlock, ImportResult,
     MiningBlockChainClient, Shard, StateInfo, StateOrBlock, TextClient,
 };
-use crate::block::{ClosedBlock, IsBlock, OpenBlock, SealedBlock};
-use crate::blockchain::{BlockChain, BlockProvider, BodyProvider, HeaderProvider, InvoiceProvider, TransactionAddress};
+use crate::block::{enact, ClosedBlock, IsBlock, LockedBlock, OpenBlock, SealedBlock};
+use crate::blockchain::{
+    AddressIndexError, AssetLogError, AssetLogFilter, BlockChain, BlockProvider, BodyProvider, HeaderProvider,
+    InvoiceProvider, LocalizedAssetLog, TransactionAddress, TreeRoute,
+};
 use crate::client::{ConsensusClient, TermInfo};
+use crate::consensus::stake::{FeeDistribution, Validator, ValidatorLiveness};
 use crate::consensus::{CodeChainEngine, EngineError};
 use crate::encoded;
-use crate::error::{BlockImportError, Error, ImportError, SchemeError};
-use crate::miner::{Miner, MinerService};
+use crate::error::{BlockError, BlockImportError, Error, ImportError, SchemeError};
+use crate::invoice::TransactionAttempt;
+use crate::miner::{
+    FeeHistogram, MemPoolEntry, Miner, MinerService, ReadyTransactionsCursor, ReadyTransactionsPage, SeqGapInfo,
+};
 use crate::scheme::Scheme;
 use crate::service::ClientIoMessage;
 use crate::transaction::{LocalizedTransaction, PendingSignedTransactions, SignedTransaction, UnverifiedTransaction};
-use crate::types::{BlockId, BlockStatus, TransactionId, VerificationQueueInfo as BlockQueueInfo};
+use crate::types::{BlockId, BlockStatus, ImportMetrics, TransactionId, VerificationQueueInfo as BlockQueueInfo};
+use crate::verification::verify_block_basic;
 use crate::MemPoolMinFees;
 use cdb::{new_journaldb, Algorithm, AsHashDB, DatabaseError};
 use cio::IoChannel;
-use ckey::{Address, NetworkId, PlatformAddress, Public};
+use ckey::{Address, NetworkId, PlatformAddress, Public, Signature};
+use clogger::metric::METRICS;
 use cstate::{
-    ActionHandler, AssetScheme, FindActionHandler, OwnedAsset, StateDB, StateResult, Text, TopLevelState, TopStateView,
+    ActionHandler, AssetScheme, CacheCounts, CacheKind, DryRun, FindActionHandler, InputCheck, OwnedAsset, ShardEntry,
+    StateDB, Text, TopLevelState, TopStateView,
 };
 use ctimer::{TimeoutHandler, TimerApi, TimerScheduleError, TimerToken};
-use ctypes::transaction::{AssetTransferInput, PartialHashing, ShardTransaction};
-use ctypes::{BlockHash, BlockNumber, CommonParams, Header, ShardId, Tracker, TxHash};
+use ctypes::transaction::{AssetTransferInput, PartialHashing, ShardTransaction, Transaction};
+use ctypes::util::unexpected::Mismatch;
+use ctypes::{BlockHash, BlockNumber, CommonParams, Header, ShardId, Tracker, TxHash, MULTISIG_PUBKEY_CAP_ERA};
 use cvm::{decode, execute, ChainTimeInfo, ScriptResult, VMConfig};
 use kvdb::{DBTransaction, KeyValueDB};
 use merkle_trie::Result as TrieResult;
 use parking_lot::{Mutex, RwLock, RwLockReadGuard};
 use primitives::{Bytes, H160, H256, U256};
-use rlp::Rlp;
+use rlp::{DecoderError, Rlp};
+use std::fs;
 use std::ops::Range;
+use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::{Arc, Weak};
 
@@ -65,8 +81,11 @@ pub struct Client {
 
     state_db: RwLock<StateDB>,
 
-    /// List of actors to be notified on certain chain events
-    notify: RwLock<Vec<Weak<dyn ChainNotify>>>,
+    /// Notifies the actors of certain chain events
+    notify: ChainNotifier,
+
+    /// The proposals that the consensus engine has seen but are not committed
+    orphaned_proposals: OrphanedProposalLog,
 
     /// Count of pending transactions in the queue
     queue_transactions: AtomicUsize,
@@ -80,6 +99,18 @@ pub struct Client {
 
     /// Timer for reseal_min_period/reseal_max_period on miner client
     reseal_timer: TimerApi,
+
+    /// Neither blocks nor transactions are imported if it's set.
+    read_only: bool,
+
+    /// The number of recent blocks whose asset logs are kept, if the database has them.
+    asset_log_retention: BlockNumber,
+
+    /// Backups hold it for read, and restores hold it for write.
+    backup_lock: RwLock<()>,
+
+    /// The number of the sealed blocks executed again by `reexecute_block`
+    reexecuted_blocks: AtomicUsize,
 }
 
 impl Client {
@@ -92,11 +123,14 @@ impl Client {
         reseal_timer: TimerApi,
     ) -> Result<Arc<Client>, Error> {
         let journal_db = new_journaldb(Arc::clone(&db), Algorithm::Archive, crate::db::COL_STATE);
-        let mut state_db = StateDB::new(journal_db);
+        let mut state_db = StateDB::new_with_cache_sizes(journal_db, config.state_cache_sizes);
         if !scheme.check_genesis_root(state_db.as_hashdb()) {
             return Err(SchemeError::InvalidState.into())
         }
         if state_db.is_empty() {
+            if config.read_only {
+                return Err(Error::Other("A read-only client cannot open an empty database".to_string()))
+            }
             // Sets the correct state root.
             state_db = scheme.ensure_genesis_state(state_db)?;
             let mut batch = DBTransaction::new();
@@ -105,7 +139,31 @@ impl Client {
         }
 
         let gb = scheme.genesis_block();
-        let chain = BlockChain::new(&gb, db.clone());
+        let (address_index, asset_index, text_index, asset_log) = if config.read_only {
+            // A read-only client cannot build or reset the indexes, so it serves the ones the database has.
+            let maintained = BlockChain::maintained_indexes(&*db);
+            if maintained != (config.address_index, config.asset_index, config.text_index, config.asset_log) {
+                cwarn!(
+                    CLIENT,
+                    "The read-only database has the address index: {}, the asset index: {}, the text index: {}, \
+                     the asset log: {}",
+                    maintained.0,
+                    maintained.1,
+                    maintained.2,
+                    maintained.3
+                );
+            }
+            maintained
+        } else {
+            (config.address_index, config.asset_index, config.text_index, config.asset_log)
+        };
+        let asset_log_retention = config.asset_log_retention;
+        let asset_log = if asset_log {
+            Some(asset_log_retention)
+        } else {
+            None
+        };
+        let chain = BlockChain::new(&gb, db.clone(), address_index, asset_index, text_index, asset_log);
 
         let engine = scheme.engine.clone();
 
@@ -116,14 +174,19 @@ impl Client {
             engine,
             io_channel: Mutex::new(message_channel),
             chain: RwLock::new(chain),
+            orphaned_proposals: OrphanedProposalLog::new(Arc::clone(&db)),
             db,
             state_db: RwLock::new(state_db),
-            notify: RwLock::new(Vec::new()),
+            notify: ChainNotifier::new(),
             queue_transactions: AtomicUsize::new(0),
             genesis_accounts,
             importer,
             miner,
             reseal_timer,
+            read_only: config.read_only,
+            asset_log_retention,
+            backup_lock: RwLock::new(()),
+            reexecuted_blocks: AtomicUsize::new(0),
         });
 
         // ensure buffered changes are flushed.
@@ -131,14 +194,50 @@ impl Client {
         Ok(client)
     }
 
+    /// Stops posting messages to the IO service and cancels the reseal timers.
+    pub(crate) fn stop_accepting_work(&self) {
+        *self.io_channel.lock() = IoChannel::disconnected();
+        for token in &[RESEAL_MAX_TIMER_TOKEN, RESEAL_MIN_TIMER_TOKEN] {
+            if let Err(err) = self.reseal_timer.cancel(*token) {
+                cdebug!(SHUTDOWN, "Cannot cancel the reseal timer {}: {:?}", token, err);
+            }
+        }
+    }
+
+    /// Joins the verifier threads. Blocks imported after it are rejected.
+    pub(crate) fn stop_verification(&self) {
+        self.importer.header_queue.stop();
+        self.importer.block_queue.stop();
+    }
+
+    /// Whether the client serves a database that it doesn't change.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn ensure_writable(&self) -> Result<(), BlockImportError> {
+        if self.read_only {
+            return Err(BlockImportError::Import(ImportError::ReadOnly))
+        }
+        Ok(())
+    }
+
     /// Returns engine reference.
     pub fn engine(&self) -> &dyn CodeChainEngine {
         &*self.engine
     }
 
-    /// Adds an actor to be notified on certain events
+    /// Adds an actor to be notified on certain events.
+    /// It's notified on the notification thread after the import, so it may see a later chain than the event.
     pub fn add_notify(&self, target: Weak<dyn ChainNotify>) {
-        self.notify.write().push(target);
+        self.notify.add(target);
+    }
+
+    /// Adds an actor to be notified on certain events before the importer goes on.
+    /// It delays the import, so use it only for an actor that handles an event cheaply
+    /// or must handle it before the next import.
+    pub fn add_synchronous_notify(&self, target: Weak<dyn ChainNotify>) {
+        self.notify.add_synchronous(target);
     }
 
     pub fn new_blocks(
@@ -149,15 +248,17 @@ impl Client {
         retracted: &[BlockHash],
         sealed: &[BlockHash],
     ) {
-        self.notify(|notify| {
-            notify.new_blocks(
-                imported.to_vec(),
-                invalid.to_vec(),
-                enacted.to_vec(),
-                retracted.to_vec(),
-                sealed.to_vec(),
-            )
-        });
+        METRICS
+            .gauge("codechain_block_height", "The number of the best block")
+            .set(self.best_block_header().number() as i64);
+        report_state_cache_metrics(&self.state_cache_counts());
+        self.notify.new_blocks(
+            imported.to_vec(),
+            invalid.to_vec(),
+            enacted.to_vec(),
+            retracted.to_vec(),
+            sealed.to_vec(),
+        );
     }
 
     pub fn new_headers(
@@ -169,26 +270,14 @@ impl Client {
         sealed: &[BlockHash],
         new_best_proposal: Option<BlockHash>,
     ) {
-        self.notify(|notify| {
-            notify.new_headers(
-                imported.to_vec(),
-                invalid.to_vec(),
-                enacted.to_vec(),
-                retracted.to_vec(),
-                sealed.to_vec(),
-                new_best_proposal,
-            );
-        });
-    }
-
-    fn notify<F>(&self, f: F)
-    where
-        F: Fn(&dyn ChainNotify), {
-        for np in self.notify.read().iter() {
-            if let Some(n) = np.upgrade() {
-                f(&*n);
-            }
-        }
+        self.notify.new_headers(
+            imported.to_vec(),
+            invalid.to_vec(),
+            enacted.to_vec(),
+            retracted.to_vec(),
+            sealed.to_vec(),
+            new_best_proposal,
+        );
     }
 
     /// This is triggered by a message coming from a header queue when the header is ready for insertion
@@ -198,11 +287,40 @@ impl Client {
 
     /// This is triggered by a message coming from a block queue when the block is ready for insertion
     pub fn import_verified_blocks(&self) -> usize {
-        self.importer.import_verified_blocks(self)
+        let imported = self.importer.import_verified_blocks(self);
+        METRICS
+            .counter("codechain_blocks_imported_total", "The number of blocks imported from the verification queue")
+            .inc_by(imported as u64);
+        let queue_info = self.importer.block_queue.queue_info();
+        METRICS
+            .gauge("codechain_block_queue_unverified", "The number of blocks waiting for verification")
+            .set(queue_info.unverified_queue_size as i64);
+        METRICS
+            .gauge("codechain_block_queue_verifying", "The number of blocks being verified")
+            .set(queue_info.verifying_queue_size as i64);
+        METRICS
+            .gauge("codechain_block_queue_verified", "The number of verified blocks waiting for import")
+            .set(queue_info.verified_queue_size as i64);
+        imported
+    }
+
+    /// Executes the transactions of the block on the state of its parent, as the blocks from the network are.
+    fn reexecute_block(&self, block: &SealedBlock) -> Result<LockedBlock, Error> {
+        let header = block.header();
+        let parent = self
+            .block_chain()
+            .block_header(header.parent_hash())
+            .ok_or_else(|| BlockError::UnknownParent(*header.parent_hash()))?;
+        let db = self.state_db().read().clone(&parent.state_root());
+        self.reexecuted_blocks.fetch_add(1, AtomicOrdering::Relaxed);
+        enact(header, block.transactions(), &*self.engine, self, db, &parent)
     }
 
     /// This is triggered by a message coming from a engine when a new block should be created
     pub fn update_sealing(&self, parent_block: BlockId, allow_empty_block: bool) {
+        if self.read_only {
+            return
+        }
         self.miner.update_sealing(self, parent_block, allow_empty_block);
     }
 
@@ -245,6 +363,10 @@ impl Client {
     /// This is triggered by a message coming from the Tendermint engine when a block is committed.
     /// See EngineClient::update_best_as_committed() for details.
     pub fn update_best_as_committed(&self, block_hash: BlockHash) {
+        if self.read_only {
+            cwarn!(CLIENT, "Ignoring the best block update to {} in read-only mode", block_hash);
+            return
+        }
         ctrace!(CLIENT, "Update the best block to the hash({}), as requested", block_hash);
         let route = {
             let _import_lock = self.importer.import_lock.lock();
@@ -258,7 +380,7 @@ impl Client {
 
             // Clear the state DB cache
             let mut state_db = self.state_db().write();
-            state_db.clear_cache();
+            state_db.clear_caches();
 
             route
         };
@@ -306,6 +428,19 @@ impl Client {
     pub fn db(&self) -> &Arc<dyn KeyValueDB> {
         &self.db
     }
+
+    /// Reads the texts stored by the transactions at the best block.
+    fn latest_texts(&self, tx_hashes: Vec<TxHash>) -> TrieResult<Vec<(TxHash, Text)>> {
+        let state = Client::state_at(&self, BlockId::Latest).expect("Latest state MUST exist");
+        let mut texts = Vec::with_capacity(tx_hashes.len());
+        for tx_hash in tx_hashes {
+            // The best block can be changed after reading the index.
+            if let Some(text) = state.text(&tx_hash)? {
+                texts.push((tx_hash, text));
+            }
+        }
+        Ok(texts)
+    }
 }
 
 /// When RESEAL_MAX_TIMER invoked, a block is created although the block is empty.
@@ -340,6 +475,67 @@ impl DatabaseClient for Client {
     }
 }
 
+impl BackupClient for Client {
+    fn create_backup(&self, path: &str) -> Result<BlockHash, BackupError> {
+        let _backup_lock = self.backup_lock.try_read().ok_or(BackupError::InProgress)?;
+        let best_block_hash = if self.read_only {
+            // Nothing is written to a read-only database, so it's copied as it is.
+            db_backup::copy_database(&*self.db, path)?;
+            self.chain_info().best_block_hash
+        } else {
+            // Blocks are not imported while the database is copied, so the backup is consistent.
+            // The copy can be replaced with a RocksDB checkpoint once kvdb-rocksdb exposes it.
+            let _import_lock = self.importer.import_lock.lock();
+            self.db.flush()?;
+            db_backup::copy_database(&*self.db, path)?;
+            self.chain_info().best_block_hash
+        };
+        cinfo!(CLIENT, "Backed up the database to {}. The best block is {}", path, best_block_hash);
+        Ok(best_block_hash)
+    }
+
+    fn restore_from_backup(&self, path: &str) -> Result<BlockHash, BackupError> {
+        if self.read_only {
+            return Err(BackupError::ReadOnly)
+        }
+        let _backup_lock = self.backup_lock.try_write().ok_or(BackupError::InProgress)?;
+        let _import_lock = self.importer.import_lock.lock();
+        let genesis = self.block(&BlockId::Number(0)).expect("Genesis always exists");
+        // The genesis block covers the network ID, which is in its common params.
+        let restoring = db_backup::prepare_restore(path, genesis.hash())?;
+        if let Err(err) = self.db.restore(&restoring) {
+            // The copy is made by this restore, so it's not left behind.
+            if Path::new(&restoring).exists() {
+                fs::remove_dir_all(&restoring)?;
+            }
+            return Err(err.into())
+        }
+
+        // The chain and the state caches have the data of the replaced database.
+        let (address_index, asset_index, text_index, asset_log) = BlockChain::maintained_indexes(&*self.db);
+        let asset_log = if asset_log {
+            Some(self.asset_log_retention)
+        } else {
+            None
+        };
+        *self.chain.write() = BlockChain::new(
+            &genesis.into_inner(),
+            Arc::clone(&self.db),
+            address_index,
+            asset_index,
+            text_index,
+            asset_log,
+        );
+        // The journal is reopened to read the latest era of the restored state.
+        self.state_db.write().reopen(new_journaldb(Arc::clone(&self.db), Algorithm::Archive, crate::db::COL_STATE));
+        // The pending transactions and the sealing work are of the replaced chain.
+        self.miner.reset_to_db(self);
+        let best_block_hash = self.chain_info().best_block_hash;
+        cinfo!(CLIENT, "Restored the database from {}. The best block is {}", path, best_block_hash);
+        Ok(best_block_hash)
+    }
+}
+
 impl AssetClient for Client {
     fn get_asset_scheme(&self, asset_type: H160, shard_id: ShardId, id: BlockId) -> TrieResult<Option<AssetScheme>> {
         if let Some(state) = Client::state_at(&self, id) {
@@ -402,6 +598,54 @@ impl AssetClient for Client {
         let state = Client::state_at(&self, block_id).unwrap();
         Ok(Some(state.asset(shard_id, tracker, index)?.is_none()))
     }
+
+    fn check_transfer_inputs(&self, inputs: &[AssetTransferInput], id: BlockId) -> TrieResult<Option<Vec<InputCheck>>> {
+        if let Some(state) = Client::state_at(&self, id) {
+            Ok(Some(state.check_transfer_inputs(inputs)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn get_shard_entries(
+        &self,
+        shard_id: ShardId,
+        from: &H256,
+        limit: usize,
+        id: BlockId,
+    ) -> TrieResult<Option<(Vec<(H256, ShardEntry)>, Option<H256>)>> {
+        let state = match Client::state_at(&self, id) {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+        let shard_state = match state.shard_state(shard_id)? {
+            Some(shard_state) => shard_state,
+            None => return Ok(None),
+        };
+        Ok(Some(shard_state.entries(from, limit)?))
+    }
+
+    fn owned_assets_by_lock_script_hash(
+        &self,
+        shard_id: ShardId,
+        lock_script_hash: &H160,
+        skip: usize,
+        limit: usize,
+    ) -> TrieResult<Option<Vec<(Tracker, usize, OwnedAsset)>>> {
+        let addresses = match self.block_chain().assets_by_lock_script_hash(shard_id, lock_script_hash, skip, limit) {
+            Some(addresses) => addresses,
+            None => return Ok(None),
+        };
+        let state = Client::state_at(&self, BlockId::Latest).expect("Latest state MUST exist");
+        let mut assets = Vec::with_capacity(addresses.len());
+        for (tracker, index) in addresses {
+            // The best block can be changed after reading the index.
+            if let Some(asset) = state.asset(shard_id, tracker, index)? {
+                assets.push((tracker, index, asset));
+            }
+        }
+        Ok(Some(assets))
+    }
 }
 
 impl TextClient for Client {
@@ -412,19 +656,60 @@ impl TextClient for Client {
             Ok(None)
         }
     }
+
+    fn texts_by_certifier(
+        &self,
+        certifier: &Address,
+        skip: usize,
+        limit: usize,
+    ) -> TrieResult<Option<Vec<(TxHash, Text)>>> {
+        let tx_hashes = match self.block_chain().texts_by_certifier(certifier, skip, limit) {
+            Some(tx_hashes) => tx_hashes,
+            None => return Ok(None),
+        };
+        self.latest_texts(tx_hashes).map(Some)
+    }
+
+    fn texts_by_content_hash(
+        &self,
+        content_hash: &H256,
+        skip: usize,
+        limit: usize,
+    ) -> TrieResult<Option<Vec<(TxHash, Text)>>> {
+        let tx_hashes = match self.block_chain().texts_by_content_hash(content_hash, skip, limit) {
+            Some(tx_hashes) => tx_hashes,
+            None => return Ok(None),
+        };
+        self.latest_texts(tx_hashes).map(Some)
+    }
 }
 
 impl ExecuteClient for Client {
-    fn execute_transaction(&self, transaction: &ShardTransaction, sender: &Address) -> StateResult<()> {
-        let mut state = Client::state_at(&self, BlockId::Latest).expect("Latest state MUST exist");
-        state.apply_shard_transaction(
-            transaction,
-            sender,
-            &[],
+    fn dry_run_transaction(
+        &self,
+        tx: &Transaction,
+        signer_public: &Public,
+        id: BlockId,
+    ) -> Result<Option<DryRun>, Error> {
+        let (mut state, header, common_params) =
+            match (Client::state_at(&self, id), self.block_header(&id), self.common_params(id)) {
+                (Some(state), Some(header), Some(common_params)) => (state, header, common_params),
+                _ => return Ok(None),
+            };
+        let unverified = UnverifiedTransaction::new(tx.clone(), Signature::default());
+        unverified.verify_basic()?;
+        self.engine.verify_transaction_with_params(&unverified, &common_params)?;
+
+        let dry_run = state.dry_run(
+            tx,
+            &tx.hash(),
+            signer_public,
             self,
-            self.best_block_header().number(),
-            self.best_block_header().timestamp(),
-        )
+            header.number(),
+            header.timestamp(),
+            header.timestamp(),
+        )?;
+        Ok(Some(dry_run))
     }
 
     fn execute_vm(
@@ -476,7 +761,11 @@ impl StateInfo for Client {
     fn state_at(&self, id: BlockId) -> Option<TopLevelState> {
         self.block_header(&id).and_then(|header| {
             let root = header.state_root();
-            TopLevelState::from_existing(self.state_db.read().clone(&root), root).ok()
+            let state_db = self.state_db.read();
+            if !state_db.contains_root(&root) {
+                return None
+            }
+            TopLevelState::from_existing(state_db.clone(&root), root).ok()
         })
     }
 }
@@ -533,11 +822,55 @@ impl EngineInfo for Client {
             addresses.into_iter().map(|address| PlatformAddress::new_v1(network_id, address)).collect()
         }))
     }
+
+    fn validators(&self, block_number: Option<u64>) -> Result<Option<Vec<Validator>>, EngineError> {
+        if block_number == Some(0) {
+            // The genesis block is not signed by validators.
+            return Ok(None)
+        }
+        self.engine().validators(block_number)
+    }
+
+    fn proposal_block(&self, height: BlockNumber, view: u64) -> Option<encoded::Block> {
+        self.engine().proposal_block(height, view)
+    }
+
+    fn validator_liveness(&self, term_id: Option<u64>) -> Result<Option<Vec<ValidatorLiveness>>, Error> {
+        let best_block_number = self.chain_info().best_block_number;
+        let current_term_id = match self.current_term_id(BlockId::Latest) {
+            Some(current_term_id) => current_term_id,
+            None => return Ok(None),
+        };
+        let term_id = term_id.unwrap_or(current_term_id);
+        if term_id > current_term_id {
+            return Ok(None)
+        }
+        let (end_of_the_last_term, last_block) = if term_id == current_term_id {
+            (self.last_term_finished_block_num(BlockId::Latest), best_block_number)
+        } else {
+            // The state of the last block of a term has the id of the next term.
+            let (mut low, mut high) = (1, best_block_number);
+            while low < high {
+                let middle = low + (high - low) / 2;
+                match self.current_term_id(middle.into()) {
+                    Some(id) if id > term_id => high = middle,
+                    Some(_) => low = middle + 1,
+                    None => return Ok(None),
+                }
+            }
+            (self.last_term_finished_block_num((low - 1).into()), low)
+        };
+        let start_of_the_term = match end_of_the_last_term {
+            Some(end_of_the_last_term) => end_of_the_last_term + 1,
+            None => return Ok(None),
+        };
+        self.engine().validator_liveness(start_of_the_term, last_block)
+    }
 }
 
 impl EngineClient for Client {
-    /// Make a new block and seal it.
-    fn update_sealing(&self, parent_block: BlockId, allow_empty_block: bool) {
+    /// Queue a request to make a new block and seal it. The IO handler makes the block.
+    fn update_sealing_async(&self, parent_block: BlockId, allow_empty_block: bool) {
         match self.io_channel.lock().send(ClientIoMessage::NewBlockRequired {
             parent_block,
             allow_empty_block,
@@ -577,14 +910,25 @@ impl EngineClient for Client {
     fn get_kvdb(&self) -> Arc<dyn KeyValueDB> {
         self.db.clone()
     }
+
+    fn record_orphaned_proposals(&self, proposals: Vec<OrphanedProposal>) {
+        if self.read_only {
+            return
+        }
+        self.orphaned_proposals.record(proposals);
+    }
 }
 
 impl ConsensusClient for Client {}
 
 impl BlockChainTrait for Client {
     fn chain_info(&self) -> BlockChainInfo {
-        let mut chain_info = self.block_chain().chain_info();
+        let chain = self.block_chain();
+        let mut chain_info = chain.chain_info();
         chain_info.pending_total_score = chain_info.best_score + self.importer.block_queue.total_score();
+        chain_info.finalized_block_number = self.engine().finalized_block_number(chain_info.best_block_number);
+        chain_info.finalized_block_hash =
+            chain.block_hash(chain_info.finalized_block_number).expect("The ancestors of the best block exist");
         chain_info
     }
 
@@ -624,6 +968,10 @@ impl BlockChainTrait for Client {
     fn transaction_header(&self, tracker: &Tracker) -> Option<encoded::Header> {
         self.transaction_addresses(tracker).map(|addr| addr.block_hash).and_then(|hash| self.block_header(&hash.into()))
     }
+
+    fn tree_route(&self, from: BlockHash, to: BlockHash) -> Option<TreeRoute> {
+        self.block_chain().tree_route(from, to)
+    }
 }
 
 impl ImportBlock for Client {
@@ -631,7 +979,8 @@ impl ImportBlock for Client {
         use crate::verification::queue::kind::blocks::Unverified;
         use crate::verification::queue::kind::BlockLike;
 
-        let unverified = Unverified::new(bytes);
+        self.ensure_writable()?;
+        let unverified = Unverified::new_checked(bytes, &*self.engine).map_err(BlockImportError::Block)?;
         {
             if self.block_chain().is_known(&unverified.hash()) {
                 return Err(BlockImportError::Import(ImportError::AlreadyInChain))
@@ -640,7 +989,22 @@ impl ImportBlock for Client {
         Ok(self.importer.block_queue.import(unverified)?)
     }
 
+    fn import_sealed_block(&self, bytes: Bytes) -> Result<BlockHash, BlockImportError> {
+        use crate::verification::queue::kind::blocks::Unverified;
+        use crate::verification::queue::kind::BlockLike;
+
+        self.ensure_writable()?;
+        let unverified = Unverified::new_checked(bytes, &*self.engine).map_err(BlockImportError::Block)?;
+        {
+            if self.block_chain().is_known(&unverified.hash()) {
+                return Err(BlockImportError::Import(ImportError::AlreadyInChain))
+            }
+        }
+        Ok(self.importer.block_queue.import_priority(unverified)?)
+    }
+
     fn import_header(&self, unverified: Header) -> Result<BlockHash, BlockImportError> {
+        self.ensure_writable()?;
         if self.block_chain().is_known_header(&unverified.hash()) {
             return Err(BlockImportError::Import(ImportError::AlreadyInChain))
         }
@@ -648,18 +1012,47 @@ impl ImportBlock for Client {
     }
 
     fn import_generated_block(&self, block: &SealedBlock) -> ImportResult {
+        // A read-only client never seals, so it has no generated blocks.
+        debug_assert!(!self.read_only);
         let h = block.header().hash();
+        let number = block.header().number();
+        let block_data = block.rlp_bytes();
+        let header = block.header();
+        verify_block_basic(header, &block_data)?;
+
+        // The state of the block can be stale if it was changed after the block was closed.
+        let reexecuted = if block.state().root() == *header.state_root() {
+            debug_assert_eq!(
+                Some(*header.state_root()),
+                self.reexecute_block(block).ok().map(|reexecuted| *reexecuted.header().state_root()),
+                "Re-executing the sealed block #{} ({}) must give the same state root",
+                number,
+                h
+            );
+            None
+        } else {
+            cwarn!(CLIENT, "The state of the sealed block #{} ({}) is stale. It's executed again", number, h);
+            let reexecuted = self.reexecute_block(block)?;
+            if reexecuted.header().state_root() != header.state_root() {
+                return Err(BlockError::InvalidStateRoot(Mismatch {
+                    expected: *reexecuted.header().state_root(),
+                    found: *header.state_root(),
+                })
+                .into())
+            }
+            Some(reexecuted)
+        };
+
         let route = {
             // scope for self.import_lock
             let import_lock = self.importer.import_lock.lock();
 
-            let number = block.header().number();
-            let block_data = block.rlp_bytes();
-            let header = block.header();
-
             self.importer.import_verified_headers(vec![header], self, &import_lock);
 
-            let route = self.importer.commit_block(block, header, &block_data, self);
+            let route = match &reexecuted {
+                Some(reexecuted) => self.importer.commit_block(reexecuted, header, &block_data, self),
+                None => self.importer.commit_block(block, header, &block_data, self),
+            };
             cinfo!(CLIENT, "Imported sealed block #{} ({})", number, h);
             route
         };
@@ -699,13 +1092,40 @@ impl BlockChainClient for Client {
         self.importer.block_queue.queue_info()
     }
 
+    fn import_metrics(&self) -> Option<ImportMetrics> {
+        Some(ImportMetrics {
+            headers: self.importer.header_queue.timings()?,
+            blocks: self.importer.block_queue.timings()?,
+        })
+    }
+
+    fn state_cache_counts(&self) -> Vec<(CacheKind, CacheCounts)> {
+        let stats = self.state_db.read().cache_stats();
+        CacheKind::ALL.iter().map(|kind| (*kind, stats.counts(*kind))).collect()
+    }
+
     /// Import own transaction
     fn queue_own_transaction(&self, transaction: SignedTransaction) -> Result<(), Error> {
-        self.miner.import_own_transaction(self, transaction)?;
+        if self.read_only {
+            return Err(ImportError::ReadOnly.into())
+        }
+        self.miner.import_own_transaction(self, transaction, None)?;
+        Ok(())
+    }
+
+    fn queue_own_transaction_with_expiry(&self, transaction: SignedTransaction, expiry: u64) -> Result<(), Error> {
+        if self.read_only {
+            return Err(ImportError::ReadOnly.into())
+        }
+        self.miner.import_own_transaction(self, transaction, Some(expiry))?;
         Ok(())
     }
 
     fn queue_transactions(&self, transactions: Vec<Bytes>) {
+        if self.read_only {
+            cwarn!(EXTERNAL_TX, "Ignoring {} transactions: the client is in read-only mode", transactions.len());
+            return
+        }
         let queue_size = self.queue_transactions.load(AtomicOrdering::Relaxed);
         ctrace!(EXTERNAL_TX, "Queue size: {}", queue_size);
         if queue_size > MAX_MEM_POOL_SIZE {
@@ -727,10 +1147,30 @@ impl BlockChainClient for Client {
         self.miner.delete_all_pending_transactions();
     }
 
+    fn remove_local_transaction(&self, hash: &TxHash) -> Option<bool> {
+        self.miner.remove_local_transaction(self, hash)
+    }
+
+    fn pending_transactions_by_signer(&self, signer: &Address) -> Vec<MemPoolEntry> {
+        self.miner.pending_transactions_by_signer(signer)
+    }
+
+    fn seq_gap_info(&self, signer: &Address) -> SeqGapInfo {
+        self.miner.seq_gap_info(self, signer)
+    }
+
+    fn drop_future_transactions(&self, signer: &Address, from_seq: u64) -> Result<Vec<TxHash>, TxHash> {
+        self.miner.drop_future_transactions(self, signer, from_seq)
+    }
+
     fn ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions {
         self.miner.ready_transactions(range)
     }
 
+    fn pending_fee_histogram(&self) -> FeeHistogram {
+        self.miner.pending_fee_histogram()
+    }
+
     fn count_pending_transactions(&self, range: Range<u64>) -> usize {
         self.miner.count_pending_transactions(range)
     }
@@ -742,6 +1182,15 @@ impl BlockChainClient for Client {
     fn future_ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions {
         self.miner.future_ready_transactions(range)
     }
+
+    fn ready_transactions_limited(
+        &self,
+        max_count: usize,
+        max_encoded_size: usize,
+        cursor: Option<ReadyTransactionsCursor>,
+    ) -> ReadyTransactionsPage {
+        self.miner.ready_transactions_limited(max_count, max_encoded_size, cursor)
+    }
     fn is_pending_queue_empty(&self) -> bool {
         self.miner.status().transactions_in_pending_queue == 0
     }
@@ -756,6 +1205,12 @@ impl BlockChainClient for Client {
         Self::block_hash(&chain, id).and_then(|hash| chain.block_body(&hash))
     }
 
+    fn is_block_body_pruned(&self, id: &BlockId) -> bool {
+        let chain = self.block_chain();
+
+        Self::block_hash(&chain, id).map_or(false, |hash| chain.is_body_pruned(&hash))
+    }
+
     fn block_status(&self, id: &BlockId) -> BlockStatus {
         let chain = self.block_chain();
         match Self::block_hash(&chain, id) {
@@ -783,7 +1238,10 @@ impl BlockChainClient for Client {
 
     fn error_hint(&self, hash: &TxHash) -> Option<String> {
         let chain = self.block_chain();
-        chain.error_hint(hash)
+        if chain.is_known_error_hint(hash) {
+            return chain.error_hint(hash)
+        }
+        self.miner.expired_transaction_hint(hash)
     }
 
     fn transaction_by_tracker(&self, tracker: &Tracker) -> Option<LocalizedTransaction> {
@@ -792,10 +1250,54 @@ impl BlockChainClient for Client {
         address.and_then(|address| chain.transaction(&address))
     }
 
+    fn transactions_by_tracker(&self, tracker: &Tracker) -> Vec<LocalizedTransaction> {
+        self.block_chain().transactions_by_tracker(tracker)
+    }
+
+    fn transaction_attempts_by_tracker(&self, tracker: &Tracker) -> Vec<TransactionAttempt> {
+        self.block_chain().transaction_attempts_by_tracker(tracker)
+    }
+
     fn error_hints_by_tracker(&self, tracker: &Tracker) -> Vec<(TxHash, Option<String>)> {
         let chain = self.block_chain();
         chain.error_hints_by_tracker(tracker)
     }
+
+    fn error_hints_by_block(&self, id: &BlockId) -> Vec<(TxHash, Option<String>)> {
+        let chain = self.block_chain();
+        Self::block_hash(&chain, id).and_then(|hash| chain.error_hints_by_block(&hash)).unwrap_or_default()
+    }
+
+    fn fee_distribution(&self, id: &BlockId) -> Option<FeeDistribution> {
+        let chain = self.block_chain();
+        Self::block_hash(&chain, id).and_then(|hash| chain.fee_distribution(&hash))
+    }
+
+    fn transactions_by_address(
+        &self,
+        address: &Address,
+        from: BlockNumber,
+        to: BlockNumber,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<LocalizedTransaction>, AddressIndexError> {
+        let chain = self.block_chain();
+        let addresses = chain.transaction_addresses_by_address(address, from, to, skip, limit)?;
+        Ok(addresses.iter().filter_map(|address| chain.transaction(address)).collect())
+    }
+
+    fn asset_logs(
+        &self,
+        filter: &AssetLogFilter,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<LocalizedAssetLog>, AssetLogError> {
+        self.block_chain().asset_logs(filter, skip, limit)
+    }
+
+    fn orphaned_proposals(&self, from: BlockNumber, to: BlockNumber) -> Result<Vec<OrphanedProposal>, DecoderError> {
+        self.orphaned_proposals.proposals(from, to)
+    }
 }
 
 impl TermInfo for Client {
@@ -915,6 +1417,10 @@ impl MiningBlockChainClient for Client {
     fn mem_pool_min_fees(&self) -> MemPoolMinFees {
         self.miner.get_options().mem_pool_min_fees
     }
+
+    fn pending_state(&self) -> TopLevelState {
+        self.miner.pending_state_snapshot(self)
+    }
 }
 
 impl ChainTimeInfo for Client {
@@ -925,6 +1431,10 @@ impl ChainTimeInfo for Client {
     fn transaction_time_age(&self, tracker: &Tracker, parent_timestamp: u64) -> Option<u64> {
         self.transaction_block_timestamp(tracker).map(|block_timestamp| parent_timestamp - block_timestamp)
     }
+
+    fn caps_multisig_pubkeys(&self, parent_block_number: BlockNumber) -> bool {
+        self.common_params(parent_block_number.into()).map_or(false, |params| params.era() >= MULTISIG_PUBKEY_CAP_ERA)
+    }
 }
 
 impl FindActionHandler for Client {
@@ -932,3 +1442,370 @@ impl FindActionHandler for Client {
         self.engine.find_action_handler_for(id)
     }
 }
+
+fn report_state_cache_metrics(stats: &[(CacheKind, CacheCounts)]) {
+    macro_rules! names {
+        ($kind:literal) => {
+            (
+                concat!("codechain_state_cache_", $kind, "_hits_total"),
+                concat!("codechain_state_cache_", $kind, "_misses_total"),
+                concat!("codechain_state_cache_", $kind, "_evictions_total"),
+            )
+        };
+    }
+    for (kind, counts) in stats {
+        // The registry needs static names.
+        let (hits, misses, evictions) = match kind {
+            CacheKind::Account => names!("account"),
+            CacheKind::RegularAccount => names!("regular_account"),
+            CacheKind::Metadata => names!("metadata"),
+            CacheKind::Shard => names!("shard"),
+            CacheKind::Text => names!("text"),
+            CacheKind::ActionData => names!("action_data"),
+            CacheKind::AssetScheme => names!("asset_scheme"),
+            CacheKind::Asset => names!("asset"),
+            CacheKind::ApproverGroup => names!("approver_group"),
+        };
+        METRICS.counter(hits, "The number of state reads served by the cache").raise_to(counts.hits as u64);
+        METRICS.counter(misses, "The number of state reads that went to the trie").raise_to(counts.misses as u64);
+        METRICS
+            .counter(evictions, "The number of items evicted from a full state cache")
+            .raise_to(counts.evictions as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account_provider::AccountProvider;
+    use crate::client::BlockRangeError;
+    use crate::db::NUM_COLUMNS;
+    use crate::miner::MinerOptions;
+    use crate::read_only_db::ReadOnlyDatabase;
+    use crate::tests::helpers::get_good_dummy_block;
+    use cio::IoService;
+    use ckey::{sign, KeyPair};
+    use ctimer::TimerLoop;
+    use kvdb_rocksdb::{Database, DatabaseConfig};
+    use tempfile::tempdir;
+
+    const VALIDATOR: &str = "ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd";
+
+    fn open_client(
+        db: Arc<dyn KeyValueDB>,
+        scheme: &Scheme,
+        read_only: bool,
+    ) -> Result<(Arc<Client>, Arc<Miner>), Error> {
+        let client_config = ClientConfig {
+            read_only,
+            ..Default::default()
+        };
+        open_client_with_config(db, scheme, client_config)
+    }
+
+    fn open_client_with_config(
+        db: Arc<dyn KeyValueDB>,
+        scheme: &Scheme,
+        client_config: ClientConfig,
+    ) -> Result<(Arc<Client>, Arc<Miner>), Error> {
+        let options = MinerOptions {
+            external_sealing: true,
+            no_reseal_timer: true,
+            ..Default::default()
+        };
+        let miner = Miner::new(options, scheme, AccountProvider::transient_provider(), Arc::clone(&db));
+        let timer_loop = TimerLoop::new(2);
+        let reseal_timer = timer_loop.new_timer_with_name("Client reseal timer");
+        let io_service = IoService::<ClientIoMessage>::start("Client")?;
+        let client =
+            Client::try_new(&client_config, scheme, db, Arc::clone(&miner), io_service.channel(), reseal_timer)?;
+        Ok((client, miner))
+    }
+
+    fn seal_next_block(client: &Client, miner: &Miner) -> BlockHash {
+        let validator = KeyPair::from_private(VALIDATOR.into()).unwrap();
+        miner.set_author(validator.address()).unwrap();
+        let pending = miner.pending_seal(client).unwrap();
+        let seal = vec![::rlp::encode(&sign(validator.private(), &pending.block_hash).unwrap())];
+        client.submit_seal(pending.block_hash, seal);
+        client.chain_info().best_block_hash
+    }
+
+    #[test]
+    fn read_only_client_serves_queries_and_rejects_writes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let scheme = Scheme::new_test_simple_poa_with_test_validator();
+
+        let first = {
+            let db = Arc::new(Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), path).unwrap());
+            let (client, miner) = open_client(db, &scheme, false).unwrap();
+            seal_next_block(&client, &miner)
+        };
+
+        {
+            let db = Arc::new(Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), path).unwrap());
+            let db: Arc<dyn KeyValueDB> = Arc::new(ReadOnlyDatabase::new(db));
+            let (client, _miner) = open_client(Arc::clone(&db), &scheme, true).unwrap();
+            assert!(client.is_read_only());
+            assert_eq!(1, client.chain_info().best_block_number);
+            assert_eq!(first, client.chain_info().best_block_hash);
+            assert!(client.block(&BlockId::Number(1)).is_some());
+
+            match client.import_block(get_good_dummy_block()) {
+                Err(BlockImportError::Import(ImportError::ReadOnly)) => {}
+                result => panic!("Unexpected import result: {:?}", result),
+            }
+            assert!(db.write(DBTransaction::new()).is_err());
+        }
+
+        let db = Arc::new(Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), path).unwrap());
+        let (client, miner) = open_client(db, &scheme, false).unwrap();
+        assert_eq!(first, client.chain_info().best_block_hash);
+        let second = seal_next_block(&client, &miner);
+        assert_eq!(2, client.chain_info().best_block_number);
+        assert_eq!(Some(first), client.block_header(&BlockId::Hash(second)).map(|header| header.parent_hash()));
+    }
+
+    #[test]
+    fn generated_block_is_imported_without_executing_it_again() {
+        use ctypes::transaction::Action;
+
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let (client, _miner) = open_client(db, &Scheme::new_test_solo(), false).unwrap();
+        let parent = client.block_header(&BlockId::Latest).unwrap().decode();
+
+        let validator = KeyPair::from_private(VALIDATOR.into()).unwrap();
+        let mut block = client.prepare_open_block(BlockId::Latest, validator.address(), vec![]);
+        for seq in 0..100 {
+            let tx = SignedTransaction::new_with_sign(
+                Transaction {
+                    seq,
+                    fee: 10,
+                    network_id: "tc".into(),
+                    action: Action::Pay {
+                        receiver: Address::random(),
+                        quantity: 1,
+                        memo: None,
+                    },
+                },
+                validator.private(),
+            );
+            block.push_transaction(tx, &*client, parent.number(), parent.timestamp()).unwrap();
+        }
+        let term_common_params = client.term_common_params(BlockId::Latest);
+        let sealed =
+            block.close_and_lock(&parent, term_common_params.as_ref()).unwrap().seal(&*client.engine, vec![]).unwrap();
+
+        let reexecuted = client.reexecute_block(&sealed).unwrap();
+        assert_eq!(sealed.header().state_root(), reexecuted.header().state_root());
+        assert_eq!(1, client.reexecuted_blocks.load(AtomicOrdering::Relaxed));
+
+        let hash = client.import_generated_block(&sealed).unwrap();
+        assert_eq!(hash, client.chain_info().best_block_hash);
+        assert_eq!(*sealed.header().state_root(), client.block_header(&BlockId::Latest).unwrap().state_root());
+        // Only the debug builds execute the block again, to check the state root.
+        let checks = if cfg!(debug_assertions) {
+            1
+        } else {
+            0
+        };
+        assert_eq!(1 + checks, client.reexecuted_blocks.load(AtomicOrdering::Relaxed));
+
+        // The executed state is committed as the state of the imported block.
+        assert_eq!(Some(100), client.block_transaction_count(&BlockId::Hash(hash)));
+        assert_eq!(Some(100), client.seq(&validator.address(), BlockId::Hash(hash)));
+    }
+
+    #[test]
+    fn read_only_client_refuses_empty_database() {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let db: Arc<dyn KeyValueDB> = Arc::new(ReadOnlyDatabase::new(db));
+        assert!(open_client(db, &Scheme::new_test_simple_poa_with_test_validator(), true).is_err());
+    }
+
+    #[test]
+    fn blocks_in_range_stops_at_pruned_bodies() {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let client_config = ClientConfig {
+            retention_blocks: Some(100),
+            ..Default::default()
+        };
+        let (client, miner) =
+            open_client_with_config(db, &Scheme::new_test_simple_poa_with_test_validator(), client_config).unwrap();
+        for _ in 0..300 {
+            seal_next_block(&client, &miner);
+        }
+        let pruned_body_number = client.block_chain().pruned_body_number();
+        assert!(pruned_body_number > 0);
+
+        let from = pruned_body_number + 1;
+        let blocks = client.blocks_in_range(from..301).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!((301 - from) as usize, blocks.len());
+        for (number, block) in (from..301).zip(blocks) {
+            let header = client.block_header(&BlockId::Number(number)).unwrap();
+            let body = client.block_body(&BlockId::Number(number)).unwrap();
+            let view = block.view();
+            assert_eq!(header.into_inner(), view.header_rlp().as_raw());
+            assert_eq!(body.rlp().at(0).unwrap().as_raw(), view.rlp().at(1).unwrap().as_raw());
+        }
+
+        let mut blocks = client.blocks_in_range(pruned_body_number..301);
+        assert_eq!(
+            Some(Err(BlockRangeError::BodyPruned {
+                number: pruned_body_number
+            })),
+            blocks.next().map(|block| block.map(|_| ()))
+        );
+        assert!(blocks.next().is_none());
+    }
+
+    #[test]
+    fn restore_brings_back_the_backed_up_chain() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("db");
+        let backup_path = dir.path().join("backup");
+        let backup_path = backup_path.to_str().unwrap();
+        let scheme = Scheme::new_test_simple_poa_with_test_validator();
+        let db = Arc::new(Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), path.to_str().unwrap()).unwrap());
+        let (client, miner) = open_client(db, &scheme, false).unwrap();
+
+        let first = seal_next_block(&client, &miner);
+        assert_eq!(first, client.create_backup(backup_path).unwrap());
+        let second = seal_next_block(&client, &miner);
+        assert_eq!(2, client.chain_info().best_block_number);
+        assert_eq!(Some(2), client.state_db.read().latest_era());
+
+        assert_eq!(first, client.restore_from_backup(backup_path).unwrap());
+        assert_eq!(1, client.chain_info().best_block_number);
+        assert_eq!(first, client.chain_info().best_block_hash);
+        assert!(client.block(&BlockId::Number(1)).is_some());
+        assert!(client.block(&BlockId::Hash(second)).is_none());
+        // The journal and the miner follow the restored chain.
+        assert_eq!(Some(1), client.state_db.read().latest_era());
+        assert!(miner.pending_block_header(0).is_none());
+        assert_eq!(2, client.block_header(&BlockId::Hash(seal_next_block(&client, &miner))).unwrap().number());
+        assert!(!Path::new(&format!("{}.restoring", backup_path)).exists());
+        // The backup is kept after it's restored.
+        let backup = Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), backup_path).unwrap();
+        assert_eq!(Some(first), BlockChain::stored_best_block_hash(&backup));
+    }
+
+    #[test]
+    fn backup_of_a_memory_database_can_be_opened() {
+        let dir = tempdir().unwrap();
+        let backup_path = dir.path().join("backup");
+        let backup_path = backup_path.to_str().unwrap();
+        let scheme = Scheme::new_test_simple_poa_with_test_validator();
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let (client, miner) = open_client(db, &scheme, false).unwrap();
+        let first = seal_next_block(&client, &miner);
+        assert_eq!(first, client.create_backup(backup_path).unwrap());
+        assert!(client.create_backup(backup_path).is_err(), "The backup is not overwritten");
+
+        let db = Arc::new(Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), backup_path).unwrap());
+        let (backup, _miner) = open_client(db, &scheme, false).unwrap();
+        assert_eq!(first, backup.chain_info().best_block_hash);
+        assert_eq!(
+            client.block(&BlockId::Number(1)).unwrap().into_inner(),
+            backup.block(&BlockId::Number(1)).unwrap().into_inner()
+        );
+    }
+
+    #[test]
+    fn backup_of_another_chain_is_not_restored() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("db");
+        let backup_path = dir.path().join("backup");
+        let backup_path = backup_path.to_str().unwrap();
+        let other = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let (other, _miner) = open_client(other, &Scheme::new_test(), false).unwrap();
+        other.create_backup(backup_path).unwrap();
+
+        let db = Arc::new(Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), path.to_str().unwrap()).unwrap());
+        let (client, _miner) = open_client(db, &Scheme::new_test_simple_poa_with_test_validator(), false).unwrap();
+        match client.restore_from_backup(backup_path) {
+            Err(BackupError::GenesisMismatch {
+                found,
+                ..
+            }) => assert_eq!(Some(other.chain_info().genesis_hash), found),
+            result => panic!("Unexpected restore result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn only_the_copy_left_by_a_restore_is_removed() {
+        let dir = tempdir().unwrap();
+        let backup_path = dir.path().join("backup");
+        let backup_path = backup_path.to_str().unwrap();
+        let restoring = dir.path().join("backup.restoring");
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let (client, _miner) = open_client(db, &Scheme::new_test_simple_poa_with_test_validator(), false).unwrap();
+        client.create_backup(backup_path).unwrap();
+        let genesis_hash = client.chain_info().genesis_hash;
+
+        fs::create_dir(&restoring).unwrap();
+        fs::write(restoring.join("data"), b"not a copy of the backup").unwrap();
+        assert!(db_backup::prepare_restore(backup_path, genesis_hash).is_err());
+        assert!(restoring.join("data").exists());
+
+        fs::write(restoring.join("CODECHAIN_RESTORING"), b"").unwrap();
+        let copy = db_backup::prepare_restore(backup_path, genesis_hash).unwrap();
+        assert!(!restoring.join("data").exists());
+        assert!(!restoring.join("CODECHAIN_RESTORING").exists());
+        let copy = Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), &copy).unwrap();
+        assert_eq!(Some(genesis_hash), BlockChain::stored_genesis_hash(&copy));
+    }
+
+    #[test]
+    fn backup_is_refused_while_restoring() {
+        let dir = tempdir().unwrap();
+        let backup_path = dir.path().join("backup");
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let (client, _miner) = open_client(db, &Scheme::new_test_simple_poa_with_test_validator(), false).unwrap();
+
+        let _restoring = client.backup_lock.write();
+        match client.create_backup(backup_path.to_str().unwrap()) {
+            Err(BackupError::InProgress) => {}
+            result => panic!("Unexpected backup result: {:?}", result),
+        }
+        match client.restore_from_backup(backup_path.to_str().unwrap()) {
+            Err(BackupError::InProgress) => {}
+            result => panic!("Unexpected restore result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn block_import_errors_have_stable_codes() {
+        use crate::error::import_error_codes;
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let (client, miner) = open_client(db, &Scheme::new_test_simple_poa_with_test_validator(), false).unwrap();
+        seal_next_block(&client, &miner);
+        let block = client.block(&BlockId::Number(1)).unwrap();
+        let header = block.decode_header();
+
+        let duplicate = client.import_block(block.into_inner()).unwrap_err();
+        assert_eq!(import_error_codes::ALREADY_IN_CHAIN, duplicate.code());
+        assert!(!duplicate.is_invalid_block());
+
+        let mut future = header.clone();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        future.set_timestamp(now + 60);
+        let future = client.import_header(future).unwrap_err();
+        assert_eq!(import_error_codes::TEMPORARILY_INVALID, future.code());
+        assert!(!future.is_invalid_block());
+
+        let mut unsealed = header;
+        unsealed.set_timestamp(unsealed.timestamp() + 1);
+        unsealed.set_seal(vec![]);
+        let bad_seal = client.import_header(unsealed).unwrap_err();
+        assert_eq!(import_error_codes::INVALID_SEAL, bad_seal.code());
+        assert!(bad_seal.is_invalid_block());
+
+        let unknown_parent = BlockImportError::from(Error::from(BlockError::UnknownParent(BlockHash::default())));
+        assert_eq!(import_error_codes::UNKNOWN_PARENT, unknown_parent.code());
+        assert!(!unknown_parent.is_invalid_block());
+    }
+}
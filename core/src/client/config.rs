@@ -14,8 +14,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::db::{COLUMN_NAMES, NUM_COLUMNS};
 use crate::verification::QueueConfig;
-use kvdb_rocksdb::CompactionProfile;
+use cstate::CacheSizes;
+use ctypes::BlockNumber;
+use kvdb_rocksdb::{CompactionProfile, DatabaseConfig};
+use std::collections::HashMap;
 use std::path::Path;
 use std::str::FromStr;
 
@@ -60,6 +64,28 @@ impl FromStr for DatabaseCompactionProfile {
     }
 }
 
+/// RocksDB options of a column. The unset ones follow the options of the whole database.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct DatabaseColumnConfig {
+    /// Memory budget of the column in MiB
+    pub memory_budget: Option<usize>,
+    /// Keep the index and filter blocks in the block cache
+    pub cache_index_and_filter_blocks: Option<bool>,
+    /// Compaction profile of the column
+    pub compaction: Option<DatabaseCompactionProfile>,
+}
+
+/// RocksDB options that a column is opened with
+#[derive(Debug, PartialEq, Clone)]
+pub struct DatabaseColumnOptions {
+    /// Memory budget of the column in MiB. `None` takes an even share of `db_cache_size`.
+    pub memory_budget: Option<usize>,
+    /// Keep the index and filter blocks in the block cache
+    pub cache_index_and_filter_blocks: bool,
+    /// Compaction profile of the column
+    pub compaction: DatabaseCompactionProfile,
+}
+
 /// Client configuration. Includes configs for all sub-systems.
 #[derive(Debug, PartialEq)]
 pub struct ClientConfig {
@@ -69,8 +95,29 @@ pub struct ClientConfig {
     pub db_cache_size: Option<usize>,
     /// State db compaction profile
     pub db_compaction: DatabaseCompactionProfile,
+    /// RocksDB options that override the ones above for each column
+    pub db_columns: HashMap<Option<u32>, DatabaseColumnConfig>,
     /// State db cache-size.
     pub state_cache_size: usize,
+    /// The number of items kept in each state cache.
+    pub state_cache_sizes: CacheSizes,
+    /// The number of recent blocks whose bodies and error hints are kept.
+    /// Older ones are pruned. `None` keeps the full archive.
+    pub retention_blocks: Option<BlockNumber>,
+    /// Maintain the index of the transactions sent by or paying to each address.
+    pub address_index: bool,
+    /// Maintain the index of the unspent assets locked by each lock script hash.
+    pub asset_index: bool,
+    /// Maintain the index of the texts stored by each certifier and with each content.
+    pub text_index: bool,
+    /// Keep the mint, transfer and burn logs of the assets in the recent blocks.
+    pub asset_log: bool,
+    /// The number of recent blocks whose asset logs are kept.
+    pub asset_log_retention: BlockNumber,
+    /// Serve the database without importing blocks or transactions.
+    pub read_only: bool,
+    /// Audit the stake action data after executing each block, and halt if the block doesn't conserve the CCS.
+    pub audit_stake: bool,
 }
 
 impl Default for ClientConfig {
@@ -81,7 +128,179 @@ impl Default for ClientConfig {
             queue: Default::default(),
             db_cache_size: Default::default(),
             db_compaction: Default::default(),
+            db_columns: Default::default(),
             state_cache_size: DEFAULT_STATE_CACHE_SIZE as usize * mb,
+            state_cache_sizes: Default::default(),
+            retention_blocks: None,
+            address_index: false,
+            asset_index: false,
+            text_index: false,
+            asset_log: false,
+            asset_log_retention: 100_000,
+            read_only: false,
+            audit_stake: false,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Returns the options of the column, filling the unset ones with the options of the whole database.
+    pub fn db_column_options(&self, col: Option<u32>) -> DatabaseColumnOptions {
+        let column = self.db_columns.get(&col).cloned().unwrap_or_default();
+        DatabaseColumnOptions {
+            memory_budget: column.memory_budget,
+            // kvdb-rocksdb always caches them.
+            cache_index_and_filter_blocks: column.cache_index_and_filter_blocks.unwrap_or(true),
+            compaction: column.compaction.unwrap_or_else(|| self.db_compaction.clone()),
+        }
+    }
+
+    /// Translates the database options into the config that kvdb-rocksdb opens the database with.
+    ///
+    /// kvdb-rocksdb 0.1 applies the same options to every column, so the columns must resolve to the same options.
+    /// Distinct options need a kvdb-rocksdb release that takes the options of each column.
+    pub fn database_config(&self, db_path: &Path) -> Result<DatabaseConfig, String> {
+        let num_columns = NUM_COLUMNS.unwrap_or(0);
+        if let Some(col) = self.db_columns.keys().find(|col| col.map_or(true, |col| col >= num_columns)) {
+            return Err(format!("The database has no column {:?}", col))
+        }
+
+        let options = self.db_column_options(Some(0));
+        if let Some(col) = (1..num_columns).find(|col| self.db_column_options(Some(*col)) != options) {
+            return Err(format!(
+                "The options of the {} column differ from the ones of the {} column, \
+                 but kvdb-rocksdb opens every column with the same options",
+                COLUMN_NAMES[col as usize], COLUMN_NAMES[0]
+            ))
+        }
+        if !options.cache_index_and_filter_blocks {
+            return Err("kvdb-rocksdb always keeps the index and filter blocks in the block cache".to_string())
+        }
+
+        let mut db_config = DatabaseConfig::with_columns(NUM_COLUMNS);
+        db_config.memory_budget = match options.memory_budget {
+            Some(memory_budget) => Some(memory_budget * num_columns as usize),
+            None => self.db_cache_size,
+        };
+        db_config.compaction = options.compaction.compaction_profile(db_path);
+        Ok(db_config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{column_by_name, COL_BODIES, COL_STATE};
+    use crate::db_maintenance::{DatabaseMaintenance, RocksDbMaintenance};
+    use kvdb::{DBTransaction, KeyValueDB};
+    use kvdb_rocksdb::Database;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    fn every_column(column: DatabaseColumnConfig) -> HashMap<Option<u32>, DatabaseColumnConfig> {
+        (0..NUM_COLUMNS.unwrap()).map(|col| (Some(col), column.clone())).collect()
+    }
+
+    #[test]
+    fn columns_are_found_by_name() {
+        assert_eq!(Some(COL_STATE), column_by_name("state"));
+        assert_eq!(Some(COL_BODIES), column_by_name("bodies"));
+        assert_eq!(None, column_by_name("body"));
+        assert_eq!(NUM_COLUMNS.unwrap() as usize, COLUMN_NAMES.len());
+    }
+
+    #[test]
+    fn default_column_options_follow_database_options() {
+        let config = ClientConfig {
+            db_cache_size: Some(64),
+            db_compaction: DatabaseCompactionProfile::HDD,
+            ..Default::default()
+        };
+        let options = DatabaseColumnOptions {
+            memory_budget: None,
+            cache_index_and_filter_blocks: true,
+            compaction: DatabaseCompactionProfile::HDD,
+        };
+        for col in 0..NUM_COLUMNS.unwrap() {
+            assert_eq!(options, config.db_column_options(Some(col)));
         }
+
+        let db_config = config.database_config(Path::new(".")).unwrap();
+        assert_eq!(Some(64), db_config.memory_budget);
+        assert_eq!(NUM_COLUMNS, db_config.columns);
+    }
+
+    #[test]
+    fn column_options_override_database_options() {
+        let mut config = ClientConfig::default();
+        config.db_columns.insert(COL_STATE, DatabaseColumnConfig {
+            memory_budget: Some(512),
+            cache_index_and_filter_blocks: None,
+            compaction: Some(DatabaseCompactionProfile::SSD),
+        });
+        assert_eq!(
+            DatabaseColumnOptions {
+                memory_budget: Some(512),
+                cache_index_and_filter_blocks: true,
+                compaction: DatabaseCompactionProfile::SSD,
+            },
+            config.db_column_options(COL_STATE)
+        );
+        assert_eq!(DatabaseCompactionProfile::Auto, config.db_column_options(COL_BODIES).compaction);
+    }
+
+    #[test]
+    fn distinct_column_options_are_rejected() {
+        let mut config = ClientConfig::default();
+        config.db_columns.insert(COL_BODIES, DatabaseColumnConfig {
+            compaction: Some(DatabaseCompactionProfile::HDD),
+            ..Default::default()
+        });
+        let err = config.database_config(Path::new(".")).err().unwrap();
+        assert!(err.contains("bodies"), "{}", err);
+    }
+
+    #[test]
+    fn unknown_column_is_rejected() {
+        let mut config = ClientConfig::default();
+        config.db_columns.insert(NUM_COLUMNS, Default::default());
+        assert!(config.database_config(Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn uncached_index_and_filter_blocks_are_rejected() {
+        let config = ClientConfig {
+            db_columns: every_column(DatabaseColumnConfig {
+                cache_index_and_filter_blocks: Some(false),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(config.database_config(Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn database_opens_with_column_options() {
+        let config = ClientConfig {
+            db_columns: every_column(DatabaseColumnConfig {
+                memory_budget: Some(8),
+                cache_index_and_filter_blocks: Some(true),
+                compaction: Some(DatabaseCompactionProfile::SSD),
+            }),
+            ..Default::default()
+        };
+        let dir = tempdir().unwrap();
+        let db_config = config.database_config(dir.path()).unwrap();
+        assert_eq!(Some(8 * NUM_COLUMNS.unwrap() as usize), db_config.memory_budget);
+
+        let db: Arc<dyn KeyValueDB> = Arc::new(Database::open(&db_config, dir.path().to_str().unwrap()).unwrap());
+        let mut batch = DBTransaction::new();
+        batch.put(COL_STATE, b"key", b"value");
+        db.write(batch).unwrap();
+        drop(db);
+
+        let maintenance = RocksDbMaintenance::open(dir.path(), NUM_COLUMNS).unwrap();
+        let stats = maintenance.column_stats(COL_STATE).unwrap().unwrap();
+        assert_eq!(1, stats.keys);
     }
 }
@@ -0,0 +1,138 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::blockchain::BlockChain;
+use crate::db::NUM_COLUMNS;
+use ctypes::BlockHash;
+use kvdb::{DBTransaction, KeyValueDB};
+use kvdb_rocksdb::{Database, DatabaseConfig};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+
+/// The number of the entries written to the backup at once.
+const BACKUP_BATCH_SIZE: usize = 10_000;
+/// The file that marks a directory as the one `prepare_restore` is filling.
+const RESTORING_MARKER: &str = "CODECHAIN_RESTORING";
+
+#[derive(Debug)]
+pub enum BackupError {
+    /// A restore is running, or a backup is running when restoring.
+    InProgress,
+    /// The backup is of another chain.
+    GenesisMismatch {
+        expected: BlockHash,
+        found: Option<BlockHash>,
+    },
+    ReadOnly,
+    Io(io::Error),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BackupError::InProgress => write!(f, "A backup or a restore is in progress"),
+            BackupError::GenesisMismatch {
+                expected,
+                found: Some(found),
+            } => write!(f, "The genesis block of the backup is {}, but {} is expected", found, expected),
+            BackupError::GenesisMismatch {
+                found: None,
+                ..
+            } => write!(f, "The backup has no genesis block"),
+            BackupError::ReadOnly => write!(f, "A read-only database cannot be restored"),
+            BackupError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<io::Error> for BackupError {
+    fn from(err: io::Error) -> Self {
+        BackupError::Io(err)
+    }
+}
+
+/// Copies all the columns of the database into a new RocksDB database at the path.
+/// The caller should keep the database from being written until it returns.
+pub fn copy_database(db: &dyn KeyValueDB, path: &str) -> io::Result<()> {
+    if Path::new(path).exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", path)))
+    }
+    let backup = Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), path)?;
+    for index in 0..NUM_COLUMNS.expect("The database has columns") {
+        let column = Some(index);
+        let mut batch = DBTransaction::new();
+        let mut batch_size = 0;
+        for (key, value) in db.iter(column) {
+            batch.put(column, &key, &value);
+            batch_size += 1;
+            if batch_size == BACKUP_BATCH_SIZE {
+                backup.write(mem::replace(&mut batch, DBTransaction::new()))?;
+                batch_size = 0;
+            }
+        }
+        backup.write(batch)?;
+    }
+    backup.flush()
+}
+
+/// Checks that the backup at the path is of the chain whose genesis block is given,
+/// and returns the path of its copy to be restored.
+/// Restoring moves the files of the database, so the backup itself is kept.
+///
+/// The copy is made in `{path}.restoring`. A directory left there is removed only if it's marked as
+/// the one an earlier call was filling; otherwise it's not touched and the restore fails.
+pub fn prepare_restore(path: &str, genesis_hash: BlockHash) -> Result<String, BackupError> {
+    if !Path::new(path).is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not a directory", path)).into())
+    }
+    {
+        let backup = Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), path)?;
+        let found = BlockChain::stored_genesis_hash(&backup);
+        if found != Some(genesis_hash) {
+            return Err(BackupError::GenesisMismatch {
+                expected: genesis_hash,
+                found,
+            })
+        }
+    }
+
+    let restoring = format!("{}.restoring", path.trim_end_matches('/'));
+    let marker = Path::new(&restoring).join(RESTORING_MARKER);
+    if Path::new(&restoring).exists() {
+        if !marker.is_file() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists, but it's not left by an earlier restore", restoring),
+            )
+            .into())
+        }
+        fs::remove_dir_all(&restoring)?;
+    }
+    fs::create_dir(&restoring)?;
+    fs::File::create(&marker)?;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            fs::copy(entry.path(), Path::new(&restoring).join(entry.file_name()))?;
+        }
+    }
+    // The copy is complete, and it's moved into the database next.
+    fs::remove_file(&marker)?;
+    Ok(restoring)
+}
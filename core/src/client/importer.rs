@@ -17,8 +17,8 @@
 use super::{BlockChainTrait, Client, ClientConfig};
 use crate::block::{enact, IsBlock, LockedBlock};
 use crate::blockchain::{BodyProvider, HeaderProvider, ImportRoute};
-use crate::client::EngineInfo;
-use crate::consensus::CodeChainEngine;
+use crate::client::{EngineInfo, StateInfo};
+use crate::consensus::{stake, CodeChainEngine};
 use crate::error::Error;
 use crate::miner::{Miner, MinerService};
 use crate::service::ClientIoMessage;
@@ -28,7 +28,7 @@ use crate::verification::{self, PreverifiedBlock, Verifier};
 use crate::views::{BlockView, HeaderView};
 use cio::IoChannel;
 use ctypes::header::Header;
-use ctypes::BlockHash;
+use ctypes::{BlockHash, BlockNumber};
 use kvdb::DBTransaction;
 use parking_lot::{Mutex, MutexGuard};
 use rlp::Encodable;
@@ -36,6 +36,9 @@ use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+/// The maximum number of ancient bodies pruned while committing a single block.
+const MAX_PRUNED_BODIES_PER_IMPORT: BlockNumber = 16;
+
 pub struct Importer {
     /// Lock used during block import
     pub import_lock: Mutex<()>, // FIXME Maybe wrap the whole `Importer` instead?
@@ -54,6 +57,12 @@ pub struct Importer {
 
     /// CodeChain engine to be used during import
     pub engine: Arc<dyn CodeChainEngine>,
+
+    /// The number of recent blocks whose bodies are kept. `None` keeps every body.
+    retention_blocks: Option<BlockNumber>,
+
+    /// Check that each block conserves the CCS.
+    audit_stake: bool,
 }
 
 impl Importer {
@@ -74,6 +83,8 @@ impl Importer {
             header_queue,
             miner,
             engine,
+            retention_blocks: config.retention_blocks,
+            audit_stake: config.audit_stake,
         })
     }
 
@@ -119,22 +130,23 @@ impl Importer {
             if !invalid_blocks.is_empty() {
                 self.block_queue.mark_as_bad(&invalid_blocks);
             }
+            self.block_queue.mark_as_inserted(imported_blocks.iter().cloned());
             let is_empty = self.block_queue.mark_as_good(&imported_blocks);
             (imported_blocks, import_results, invalid_blocks, imported, is_empty)
         };
 
-        {
-            if !imported_blocks.is_empty() {
-                if !is_empty {
-                    ctrace!(CLIENT, "Call new_blocks even though block verification queue is not empty");
-                }
-                let (enacted, retracted) = self.calculate_enacted_retracted(&import_results);
-                self.miner.chain_new_blocks(client, &imported_blocks, &invalid_blocks, &enacted, &retracted);
-                client.new_blocks(&imported_blocks, &invalid_blocks, &enacted, &retracted, &[]);
+        client.db().flush().expect("DB flush failed.");
+
+        if !imported_blocks.is_empty() {
+            if !is_empty {
+                ctrace!(CLIENT, "Call new_blocks even though block verification queue is not empty");
             }
+            let (enacted, retracted) = self.calculate_enacted_retracted(&import_results);
+            // The miner is updated synchronously, so the mem pool drops the imported transactions
+            // before the next block is imported or sealed.
+            self.miner.chain_new_blocks(client, &imported_blocks, &invalid_blocks, &enacted, &retracted);
+            client.new_blocks(&imported_blocks, &invalid_blocks, &enacted, &retracted, &[]);
         }
-
-        client.db().flush().expect("DB flush failed.");
         imported
     }
 
@@ -184,7 +196,14 @@ impl Importer {
         let mut batch = DBTransaction::new();
 
         block.state().journal_under(&mut batch, number).expect("DB commit failed");
+        chain.note_senders(block.transactions());
         let route = chain.insert_block(&mut batch, block_data, invoices, self.engine.borrow());
+        if let Some(fee_distribution) = block.fee_distribution() {
+            chain.insert_fee_distribution(&mut batch, hash, fee_distribution);
+        }
+        if let Some(retention_blocks) = self.retention_blocks {
+            chain.prune_ancient_bodies(&mut batch, retention_blocks, MAX_PRUNED_BODIES_PER_IMPORT);
+        }
 
         // Final commit to the DB
         client.db().write_buffered(batch);
@@ -192,6 +211,10 @@ impl Importer {
 
         if hash == chain.best_block_hash() {
             let mut state_db = client.state_db().write();
+            if !route.retracted.is_empty() {
+                // The cached items belong to the retracted branch.
+                state_db.clear_caches();
+            }
             let state = block.state();
             state_db.override_state(&state);
         }
@@ -283,6 +306,10 @@ impl Importer {
             );
         })?;
 
+        if self.audit_stake {
+            audit_stake(client, &parent, &locked_block);
+        }
+
         Ok(locked_block)
     }
 
@@ -291,7 +318,10 @@ impl Importer {
         const MAX_HEADERS_TO_IMPORT: usize = 1_000;
         let lock = self.import_lock.lock();
         let headers = self.header_queue.drain(MAX_HEADERS_TO_IMPORT);
-        self.import_verified_headers(&headers, client, &lock)
+        let imported = self.import_verified_headers(&headers, client, &lock);
+        // The bad headers are already forgotten by the queue.
+        self.header_queue.mark_as_inserted(headers.iter().map(Header::hash));
+        imported
     }
 
     pub fn import_verified_headers<'a>(
@@ -380,3 +410,17 @@ impl Importer {
         route
     }
 }
+
+/// Halts the node if the block creates or loses CCS, or its stake action data is inconsistent.
+fn audit_stake(client: &Client, parent: &Header, block: &LockedBlock) {
+    let header = block.header();
+    let parent_state =
+        client.state_at(parent.hash().into()).expect("The state of the parent is available while importing");
+    match stake::audit_ccs_conservation(&parent_state, block.state()) {
+        Ok(audit) => ctrace!(CLIENT, "Stake audit of #{}: {:?}", header.number(), audit),
+        Err(err) => {
+            cerror!(CLIENT, "Stake audit failed for #{} ({}): {}", header.number(), header.hash(), err);
+            panic!("Stake audit failed for #{}: {}", header.number(), err);
+        }
+    }
+}
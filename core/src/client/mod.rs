@@ -14,36 +14,51 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod blocks_in_range;
 mod chain_notify;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod client;
 mod config;
+mod db_backup;
 mod importer;
+mod orphaned_proposals;
 mod test_client;
 
+pub use self::blocks_in_range::{BlockRangeError, BlocksInRange};
 pub use self::chain_notify::ChainNotify;
 
 pub use self::client::Client;
-pub use self::config::ClientConfig;
+pub use self::config::{ClientConfig, DatabaseColumnConfig, DatabaseColumnOptions, DatabaseCompactionProfile};
+pub use self::db_backup::BackupError;
+pub use self::orphaned_proposals::OrphanedProposal;
 pub use self::test_client::TestBlockChainClient;
 
 use crate::block::{ClosedBlock, OpenBlock, SealedBlock};
+use crate::blockchain::{AddressIndexError, AssetLogError, AssetLogFilter, LocalizedAssetLog, TreeRoute};
 use crate::blockchain_info::BlockChainInfo;
+use crate::consensus::stake::{FeeDistribution, Validator, ValidatorLiveness};
 use crate::consensus::EngineError;
 use crate::encoded;
 use crate::error::{BlockImportError, Error as GenericError};
-use crate::miner::MemPoolMinFees;
+use crate::invoice::TransactionAttempt;
+use crate::miner::{
+    FeeHistogram, MemPoolEntry, MemPoolMinFees, ReadyTransactionsCursor, ReadyTransactionsPage, SeqGapInfo,
+};
 use crate::transaction::{LocalizedTransaction, PendingSignedTransactions, SignedTransaction};
-use crate::types::{BlockId, BlockStatus, TransactionId, VerificationQueueInfo as BlockQueueInfo};
+use crate::types::{BlockId, BlockStatus, ImportMetrics, TransactionId, VerificationQueueInfo as BlockQueueInfo};
 use cdb::DatabaseError;
 use ckey::{Address, NetworkId, PlatformAddress, Public};
-use cstate::{AssetScheme, FindActionHandler, OwnedAsset, StateResult, Text, TopLevelState, TopStateView};
-use ctypes::transaction::{AssetTransferInput, PartialHashing, ShardTransaction};
+use cstate::{
+    AssetScheme, CacheCounts, CacheKind, DryRun, FindActionHandler, InputCheck, OwnedAsset, ShardEntry, Text,
+    TopLevelState, TopStateView,
+};
+use ctypes::transaction::{AssetTransferInput, PartialHashing, Transaction};
 use ctypes::{BlockHash, BlockNumber, CommonParams, Header, ShardId, Tracker, TxHash};
 use cvm::ChainTimeInfo;
 use kvdb::KeyValueDB;
 use merkle_trie::Result as TrieResult;
 use primitives::{Bytes, H160, H256, U256};
+use rlp::DecoderError;
 use std::ops::Range;
 use std::sync::Arc;
 
@@ -81,6 +96,16 @@ pub trait BlockChainTrait {
     fn transaction_block_timestamp(&self, tracker: &Tracker) -> Option<u64> {
         self.transaction_header(tracker).map(|header| header.timestamp())
     }
+
+    /// Get the route from `from` to `to` through their common ancestor.
+    /// The blocks don't need to be canonical. Returns None if any block in the route is unknown.
+    fn tree_route(&self, from: BlockHash, to: BlockHash) -> Option<TreeRoute>;
+
+    /// Get the headers of up to `count` consecutive blocks from the given block, in descending order if `reverse`.
+    /// It stops early at either end of the chain.
+    fn headers_in_range(&self, from: &BlockId, count: u64, reverse: bool) -> Vec<encoded::Header> {
+        blocks_in_range::headers_in_range(self, from, count, reverse)
+    }
 }
 
 pub trait EngineInfo: Send + Sync {
@@ -91,12 +116,22 @@ pub trait EngineInfo: Send + Sync {
     fn mining_reward(&self, block_number: u64) -> Option<u64>;
     fn recommended_confirmation(&self) -> u32;
     fn possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>, EngineError>;
+    /// The validators of the given block with their stakes. The validators of the next block if it's not given.
+    fn validators(&self, block_number: Option<u64>) -> Result<Option<Vec<Validator>>, EngineError>;
+    /// The proposal block of the given height and view that the consensus engine is voting on.
+    fn proposal_block(&self, height: BlockNumber, view: u64) -> Option<encoded::Block>;
+    /// The precommits of the validators counted over the given term, or the current term if it's not given.
+    /// Returns None if the engine doesn't count them, the term is not started,
+    /// or the states to find the term are pruned.
+    fn validator_liveness(&self, term_id: Option<u64>) -> Result<Option<Vec<ValidatorLiveness>>, GenericError>;
 }
 
 /// Client facilities used by internally sealing Engines.
 pub trait EngineClient: Sync + Send + BlockChainTrait + ImportBlock {
-    /// Make a new block and seal it.
-    fn update_sealing(&self, parent_block: BlockId, allow_empty_block: bool);
+    /// Queue a request to make a new block and seal it.
+    /// It returns without waiting for the miner, so the engine can call it anywhere.
+    /// The miner calls back into the engine, so the engines have no way to run it inline.
+    fn update_sealing_async(&self, parent_block: BlockId, allow_empty_block: bool);
 
     /// Submit a seal for a block in the mining queue.
     fn submit_seal(&self, block_hash: BlockHash, seal: Vec<Bytes>);
@@ -110,6 +145,11 @@ pub trait EngineClient: Sync + Send + BlockChainTrait + ImportBlock {
     fn update_best_as_committed(&self, block_hash: BlockHash);
 
     fn get_kvdb(&self) -> Arc<dyn KeyValueDB>;
+
+    /// Log the proposals that are not committed at their heights.
+    ///
+    /// Used in Tendermint. It must not block, so the proposals may be dropped if the log falls behind.
+    fn record_orphaned_proposals(&self, proposals: Vec<OrphanedProposal>);
 }
 
 pub trait ConsensusClient: BlockChainClient + EngineClient + EngineInfo + TermInfo + StateInfo {}
@@ -196,10 +236,16 @@ pub trait ImportBlock {
     /// Import a block into the blockchain.
     fn import_block(&self, bytes: Bytes) -> Result<BlockHash, BlockImportError>;
 
+    /// Import a block sealed locally or received through the consensus engine.
+    /// Unlike `import_block`, it skips the backlog of the verification queue.
+    fn import_sealed_block(&self, bytes: Bytes) -> Result<BlockHash, BlockImportError>;
+
     /// Import a header into the blockchain
     fn import_header(&self, header: Header) -> Result<BlockHash, BlockImportError>;
 
-    /// Import sealed block. Skips all verifications.
+    /// Import a block sealed by this node.
+    /// It reuses the state computed while the block was built instead of executing the block again,
+    /// so only the cheap structural checks are done.
     fn import_generated_block(&self, block: &SealedBlock) -> ImportResult;
 
     /// Set reseal min timer as reseal_min_period, for creating blocks with transactions which are pending because of reseal_min_period
@@ -213,21 +259,63 @@ pub trait BlockChainClient: Sync + Send + AccountData + BlockChainTrait + Import
     /// Get block queue information.
     fn queue_info(&self) -> BlockQueueInfo;
 
+    /// Get the timings of the header and block import stages.
+    /// Returns None if the metrics are disabled by `ClientConfig`.
+    fn import_metrics(&self) -> Option<ImportMetrics>;
+
+    /// Get the hit, miss and eviction counts of the state caches for each item type.
+    fn state_cache_counts(&self) -> Vec<(CacheKind, CacheCounts)>;
+
     /// Queue own transaction for importing
     fn queue_own_transaction(&self, transaction: SignedTransaction) -> Result<(), GenericError>;
 
+    /// Queue own transaction which is dropped if it's not mined until the expiry.
+    /// The expiry is a timestamp compared with the timestamp of the best block.
+    fn queue_own_transaction_with_expiry(
+        &self,
+        transaction: SignedTransaction,
+        expiry: u64,
+    ) -> Result<(), GenericError>;
+
     /// Queue transactions for importing.
     fn queue_transactions(&self, transactions: Vec<Bytes>);
 
     /// Delete all pending transactions.
     fn delete_all_pending_transactions(&self);
 
+    /// Remove a pending transaction which was queued by `queue_own_transaction`.
+    /// Returns `None` if the transaction is not pending and `Some(false)` if it is not a local one.
+    fn remove_local_transaction(&self, hash: &TxHash) -> Option<bool>;
+
+    /// List the pending transactions of the signer in the order of their seqs.
+    fn pending_transactions_by_signer(&self, signer: &Address) -> Vec<MemPoolEntry>;
+
+    /// Find the first seq the pending transactions of the signer are waiting for.
+    fn seq_gap_info(&self, signer: &Address) -> SeqGapInfo;
+
+    /// Remove the future transactions of the signer whose seqs are `from_seq` or more, returning their hashes.
+    /// Returns the hash of a transaction which is not a local one without removing anything.
+    fn drop_future_transactions(&self, signer: &Address, from_seq: u64) -> Result<Vec<TxHash>, TxHash>;
+
     /// List all transactions that are allowed into the next block.
     fn ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions;
 
     /// List all transactions in future block.
     fn future_ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions;
 
+    /// List the transactions that are allowed into the next block after the cursor, up to the given limits.
+    /// They are in the order in which the miner includes them in a block.
+    fn ready_transactions_limited(
+        &self,
+        max_count: usize,
+        max_encoded_size: usize,
+        cursor: Option<ReadyTransactionsCursor>,
+    ) -> ReadyTransactionsPage;
+
+    /// Get the counts and total sizes of the pending transactions bucketed by fee per byte.
+    /// The mem pool keeps it up to date, so it is cheap enough to poll on every new block.
+    fn pending_fee_histogram(&self) -> FeeHistogram;
+
     /// Get the count of all pending transactions currently in the mem_pool.
     fn count_pending_transactions(&self, range: Range<u64>) -> usize;
 
@@ -244,6 +332,21 @@ pub trait BlockChainClient: Sync + Send + AccountData + BlockChainTrait + Import
     /// Block body is an RLP list of one item: transactions.
     fn block_body(&self, id: &BlockId) -> Option<encoded::Body>;
 
+    /// Get the number of transactions in the block without decoding them.
+    fn block_transaction_count(&self, id: &BlockId) -> Option<usize> {
+        self.block_body(id).map(|body| body.transactions_count())
+    }
+
+    /// Returns true if the body of the given block has been pruned by the retention policy.
+    fn is_block_body_pruned(&self, id: &BlockId) -> bool;
+
+    /// Iterate the canonical blocks in the range without loading them all at once.
+    fn blocks_in_range(&self, range: Range<BlockNumber>) -> BlocksInRange<Self>
+    where
+        Self: Sized, {
+        BlocksInRange::new(self, range)
+    }
+
     /// Get block status by block header hash.
     fn block_status(&self, id: &BlockId) -> BlockStatus;
 
@@ -263,11 +366,51 @@ pub trait BlockChainClient: Sync + Send + AccountData + BlockChainTrait + Import
     /// Get the transaction with given tracker.
     fn transaction_by_tracker(&self, tracker: &Tracker) -> Option<LocalizedTransaction>;
 
+    /// Get the canonical transactions with the given tracker, ordered by their block numbers.
+    fn transactions_by_tracker(&self, tracker: &Tracker) -> Vec<LocalizedTransaction>;
+
+    /// Get the transactions tried with the given tracker, including the failed ones, in the order they were tried.
+    fn transaction_attempts_by_tracker(&self, tracker: &Tracker) -> Vec<TransactionAttempt>;
+
+    /// Get the error hints of the transactions tried with the given tracker, in the order they were imported.
+    /// The transactions in the retracted blocks are also included.
     fn error_hints_by_tracker(&self, tracker: &Tracker) -> Vec<(TxHash, Option<String>)>;
+
+    /// Get the error hints of all the transactions in the block, in the order of the transactions.
+    /// Returns an empty list if the block or its error hints are not known.
+    fn error_hints_by_block(&self, id: &BlockId) -> Vec<(TxHash, Option<String>)>;
+
+    /// Get who received how much of the fees when the block was closed.
+    /// Returns None if the block is unknown, its body has been pruned, or its engine doesn't distribute fees.
+    fn fee_distribution(&self, id: &BlockId) -> Option<FeeDistribution>;
+
+    /// List the canonical transactions sent by or paying to the address in the block range `from..=to`,
+    /// skipping the first `skip` ones. The transactions in the pruned blocks are omitted.
+    /// Fails if the address index is disabled by `ClientConfig` or doesn't cover `from`.
+    fn transactions_by_address(
+        &self,
+        address: &Address,
+        from: BlockNumber,
+        to: BlockNumber,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<LocalizedTransaction>, AddressIndexError>;
+
+    /// List the asset logs of the canonical blocks that match the filter, skipping the first `skip` ones.
+    /// Fails if the asset log is disabled by `ClientConfig` or doesn't cover `filter.from_block`.
+    fn asset_logs(
+        &self,
+        filter: &AssetLogFilter,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<LocalizedAssetLog>, AssetLogError>;
+
+    /// List the logged proposals in the heights `from..=to` that are not committed.
+    fn orphaned_proposals(&self, from: BlockNumber, to: BlockNumber) -> Result<Vec<OrphanedProposal>, DecoderError>;
 }
 
 /// Result of import block operation.
-pub type ImportResult = Result<BlockHash, DatabaseError>;
+pub type ImportResult = Result<BlockHash, GenericError>;
 
 /// Provides methods used for sealing new state
 pub trait BlockProducer {
@@ -296,6 +439,19 @@ pub trait MiningBlockChainClient: BlockChainClient + BlockProducer + FindActionH
     fn register_immune_users(&self, immune_user_vec: Vec<Address>);
 
     fn mem_pool_min_fees(&self) -> MemPoolMinFees;
+
+    /// Returns a snapshot of the state which includes the pending transactions.
+    ///
+    /// It is the state of the currently prepared block if there is one,
+    /// or the latest state with the ready transactions in the mem pool applied.
+    fn pending_state(&self) -> TopLevelState;
+
+    /// Get address seq including the pending transactions.
+    fn latest_seq_pending(&self, address: &Address) -> u64 {
+        self.pending_state()
+            .seq(address)
+            .expect("The pending state is derived from the latest state, which always exists")
+    }
 }
 
 /// Provides methods to access database.
@@ -303,6 +459,15 @@ pub trait DatabaseClient {
     fn database(&self) -> Arc<dyn KeyValueDB>;
 }
 
+/// Backs up and restores the whole database while the node runs.
+pub trait BackupClient {
+    /// Copies the database into a new database at the path and returns the best block hash of the copy.
+    fn create_backup(&self, path: &str) -> Result<BlockHash, BackupError>;
+
+    /// Replaces the database with the backup at the path and returns the restored best block hash.
+    fn restore_from_backup(&self, path: &str) -> Result<BlockHash, BackupError>;
+}
+
 /// Provides methods to access asset
 pub trait AssetClient {
     fn get_asset_scheme(&self, asset_type: H160, shard_id: ShardId, id: BlockId) -> TrieResult<Option<AssetScheme>>;
@@ -322,15 +487,67 @@ pub trait AssetClient {
         shard_id: ShardId,
         block_id: BlockId,
     ) -> TrieResult<Option<bool>>;
+
+    /// Resolve the prev-outs of the inputs against the state of the given block, without running the scripts.
+    /// Returns None if the state of the block doesn't exist.
+    fn check_transfer_inputs(&self, inputs: &[AssetTransferInput], id: BlockId) -> TrieResult<Option<Vec<InputCheck>>>;
+
+    /// Get at most `limit` entries of the shard, starting from the trie path `from`.
+    /// The path of the next entry is returned together if there are more.
+    fn get_shard_entries(
+        &self,
+        shard_id: ShardId,
+        from: &H256,
+        limit: usize,
+        id: BlockId,
+    ) -> TrieResult<Option<(Vec<(H256, ShardEntry)>, Option<H256>)>>;
+
+    /// List the unspent assets in the shard that are locked by the lock script hash at the best block,
+    /// skipping the first `skip` ones. The assets are sorted by their trackers and indices.
+    /// Returns None if the asset index is disabled by `ClientConfig`.
+    fn owned_assets_by_lock_script_hash(
+        &self,
+        shard_id: ShardId,
+        lock_script_hash: &H160,
+        skip: usize,
+        limit: usize,
+    ) -> TrieResult<Option<Vec<(Tracker, usize, OwnedAsset)>>>;
 }
 
 /// Provides methods to texts
 pub trait TextClient {
     fn get_text(&self, tx_hash: TxHash, id: BlockId) -> TrieResult<Option<Text>>;
+
+    /// List the texts certified by the address at the best block, skipping the first `skip` ones.
+    /// The texts are sorted by the hashes of their Store transactions.
+    /// Returns None if the text index is disabled by `ClientConfig`.
+    fn texts_by_certifier(
+        &self,
+        certifier: &Address,
+        skip: usize,
+        limit: usize,
+    ) -> TrieResult<Option<Vec<(TxHash, Text)>>>;
+
+    /// List the texts whose content hash is `content_hash` at the best block, skipping the first `skip` ones.
+    /// The texts are sorted by the hashes of their Store transactions.
+    /// Returns None if the text index is disabled by `ClientConfig`.
+    fn texts_by_content_hash(
+        &self,
+        content_hash: &H256,
+        skip: usize,
+        limit: usize,
+    ) -> TrieResult<Option<Vec<(TxHash, Text)>>>;
 }
 
 pub trait ExecuteClient: ChainTimeInfo {
-    fn execute_transaction(&self, transaction: &ShardTransaction, sender: &Address) -> StateResult<()>;
+    /// Execute the transaction on the state of the given block without committing it.
+    /// Returns None if the state of the block doesn't exist.
+    fn dry_run_transaction(
+        &self,
+        tx: &Transaction,
+        signer_public: &Public,
+        id: BlockId,
+    ) -> Result<Option<DryRun>, GenericError>;
 
     fn execute_vm(
         &self,
@@ -0,0 +1,249 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db;
+use ckey::Address;
+use crossbeam_channel::{self, Sender, TrySendError};
+use ctypes::{BlockHash, BlockNumber};
+use kvdb::{DBTransaction, KeyValueDB};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// The number of the batches that can wait for the writer thread.
+/// The batches beyond it are dropped rather than delaying the consensus engine.
+const LOG_QUEUE_SIZE: usize = 64;
+
+/// The orphaned proposals are kept for this many heights below the highest logged one.
+pub const ORPHANED_PROPOSAL_RETENTION: BlockNumber = 100_000;
+
+/// A key is the height and the hash of the proposal.
+/// The height is big endian so that the keys are sorted by the heights.
+const KEY_LENGTH: usize = 8 + 32;
+
+/// A proposal block that the consensus engine has seen but that isn't committed at its height.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrphanedProposal {
+    pub height: BlockNumber,
+    pub view: u64,
+    pub hash: BlockHash,
+    pub author: Address,
+    /// Whether the signer of this node proposed the block.
+    pub is_own: bool,
+}
+
+impl Encodable for OrphanedProposal {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5)
+            .append(&self.height)
+            .append(&self.view)
+            .append(&self.hash)
+            .append(&self.author)
+            .append(&self.is_own);
+    }
+}
+
+impl Decodable for OrphanedProposal {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 5 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: 5,
+                got: item_count,
+            })
+        }
+        Ok(OrphanedProposal {
+            height: rlp.val_at(0)?,
+            view: rlp.val_at(1)?,
+            hash: rlp.val_at(2)?,
+            author: rlp.val_at(3)?,
+            is_own: rlp.val_at(4)?,
+        })
+    }
+}
+
+/// Keeps the orphaned proposals reported by the consensus engine in `COL_ORPHANED_PROPOSALS`.
+/// They are written on a dedicated thread, so reporting them never waits for the disk.
+pub struct OrphanedProposalLog {
+    db: Arc<dyn KeyValueDB>,
+    sender: Option<Sender<Vec<OrphanedProposal>>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl OrphanedProposalLog {
+    pub fn new(db: Arc<dyn KeyValueDB>) -> Self {
+        let (sender, receiver) = crossbeam_channel::bounded::<Vec<OrphanedProposal>>(LOG_QUEUE_SIZE);
+        let writer_db = Arc::clone(&db);
+        // The thread ends when the log is dropped and the remaining batches are written.
+        let writer = thread::Builder::new()
+            .name("Orphaned proposal log".to_string())
+            .spawn(move || {
+                for proposals in receiver {
+                    write(&*writer_db, &proposals);
+                }
+            })
+            .expect("Cannot spawn the orphaned proposal log thread");
+        Self {
+            db,
+            sender: Some(sender),
+            writer: Some(writer),
+        }
+    }
+
+    /// Queues the proposals to be written. They are dropped if the writer thread falls behind.
+    pub fn record(&self, proposals: Vec<OrphanedProposal>) {
+        if proposals.is_empty() {
+            return
+        }
+        let sender = self.sender.as_ref().expect("The sender is taken only when the log is dropped");
+        match sender.try_send(proposals) {
+            Ok(()) => {}
+            Err(TrySendError::Full(proposals)) => {
+                cwarn!(CLIENT, "The orphaned proposal log is full. {} proposals are not logged", proposals.len());
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                cerror!(CLIENT, "The orphaned proposal log thread has stopped");
+            }
+        }
+    }
+
+    /// Returns the logged proposals in the heights `from..=to`, sorted by their heights.
+    pub fn proposals(&self, from: BlockNumber, to: BlockNumber) -> Result<Vec<OrphanedProposal>, DecoderError> {
+        // The RocksDB iterator doesn't stop at the end of the prefix, and the in-memory one doesn't seek.
+        // The column is small enough to be iterated from the beginning.
+        let mut proposals = Vec::new();
+        for (key, value) in self.db.iter(db::COL_ORPHANED_PROPOSALS) {
+            let height = key_height(&key)?;
+            if height < from {
+                continue
+            }
+            if height > to {
+                break
+            }
+            proposals.push(rlp::decode(&value)?);
+        }
+        Ok(proposals)
+    }
+}
+
+impl Drop for OrphanedProposalLog {
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            if writer.join().is_err() {
+                cerror!(CLIENT, "The orphaned proposal log thread panicked");
+            }
+        }
+    }
+}
+
+fn write(db: &dyn KeyValueDB, proposals: &[OrphanedProposal]) {
+    let mut batch = DBTransaction::new();
+    for proposal in proposals {
+        batch.put(db::COL_ORPHANED_PROPOSALS, &log_key(proposal.height, &proposal.hash), &rlp::encode(proposal));
+    }
+    let highest = proposals.iter().map(|proposal| proposal.height).max().unwrap_or_default();
+    let lowest_kept = highest.saturating_sub(ORPHANED_PROPOSAL_RETENTION);
+    for (key, _) in db.iter(db::COL_ORPHANED_PROPOSALS) {
+        match key_height(&key) {
+            Ok(height) if height < lowest_kept => batch.delete(db::COL_ORPHANED_PROPOSALS, &key),
+            Ok(_) => break,
+            Err(err) => {
+                cerror!(CLIENT, "The orphaned proposal log has an invalid key {:?}: {}", key, err);
+                break
+            }
+        }
+    }
+    // Losing the log doesn't harm the chain, so a failed write is not fatal.
+    if let Err(err) = db.write(batch) {
+        cerror!(CLIENT, "Cannot write the orphaned proposals: {}", err);
+    }
+}
+
+fn log_key(height: BlockNumber, hash: &BlockHash) -> Vec<u8> {
+    let mut key = Vec::with_capacity(KEY_LENGTH);
+    key.extend_from_slice(&height.to_be_bytes());
+    key.extend_from_slice(&hash[..]);
+    key
+}
+
+fn key_height(key: &[u8]) -> Result<BlockNumber, DecoderError> {
+    if key.len() != KEY_LENGTH {
+        return Err(DecoderError::Custom("Invalid orphaned proposal key"))
+    }
+    let mut height = [0u8; 8];
+    height.copy_from_slice(&key[..8]);
+    Ok(BlockNumber::from_be_bytes(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::NUM_COLUMNS;
+    use primitives::H256;
+
+    fn proposal(height: BlockNumber, view: u64) -> OrphanedProposal {
+        OrphanedProposal {
+            height,
+            view,
+            hash: H256::from(height * 100 + view).into(),
+            author: Address::from(view),
+            is_own: view == 0,
+        }
+    }
+
+    #[test]
+    fn logged_proposals_are_read_by_height() {
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let log = OrphanedProposalLog::new(Arc::clone(&db));
+        log.record(vec![proposal(3, 0), proposal(3, 1)]);
+        log.record(vec![proposal(1, 2), proposal(5, 0)]);
+        // Dropping the log waits for the writer.
+        drop(log);
+
+        let log = OrphanedProposalLog::new(db);
+        assert_eq!(vec![proposal(3, 0), proposal(3, 1)], log.proposals(2, 4).unwrap());
+        assert_eq!(vec![proposal(1, 2), proposal(3, 0), proposal(3, 1), proposal(5, 0)], log.proposals(0, 10).unwrap());
+        assert_eq!(Vec::<OrphanedProposal>::new(), log.proposals(6, 10).unwrap());
+    }
+
+    #[test]
+    fn old_proposals_are_pruned() {
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let log = OrphanedProposalLog::new(Arc::clone(&db));
+        log.record(vec![proposal(1, 0), proposal(2, 0)]);
+        log.record(vec![proposal(ORPHANED_PROPOSAL_RETENTION + 2, 0)]);
+        drop(log);
+
+        let log = OrphanedProposalLog::new(db);
+        assert_eq!(
+            vec![proposal(2, 0), proposal(ORPHANED_PROPOSAL_RETENTION + 2, 0)],
+            log.proposals(0, ORPHANED_PROPOSAL_RETENTION + 2).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_key_is_an_error() {
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_ORPHANED_PROPOSALS, b"short", &rlp::encode(&proposal(1, 0)));
+        db.write(batch).unwrap();
+
+        let log = OrphanedProposalLog::new(db);
+        assert_eq!(Err(DecoderError::Custom("Invalid orphaned proposal key")), log.proposals(0, 10));
+        log.record(vec![proposal(ORPHANED_PROPOSAL_RETENTION + 2, 0)]);
+    }
+}
@@ -31,26 +31,31 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::block::{ClosedBlock, OpenBlock, SealedBlock};
+use crate::blockchain::{AddressIndexError, AssetLogError, AssetLogFilter, LocalizedAssetLog, TreeRoute};
 use crate::blockchain_info::BlockChainInfo;
 use crate::client::{
-    AccountData, BlockChainClient, BlockChainTrait, BlockProducer, BlockStatus, ConsensusClient, EngineInfo,
-    ImportBlock, ImportResult, MiningBlockChainClient, StateInfo, StateOrBlock, TermInfo,
+    AccountData, BlockChainClient, BlockChainTrait, BlockProducer, BlockStatus, ChainNotify, ConsensusClient,
+    EngineInfo, ImportBlock, ImportResult, MiningBlockChainClient, OrphanedProposal, StateInfo, StateOrBlock, TermInfo,
 };
-use crate::consensus::stake::{Validator, Validators};
+use crate::consensus::stake::{FeeDistribution, Validator, ValidatorLiveness, Validators};
 use crate::consensus::EngineError;
 use crate::db::{COL_STATE, NUM_COLUMNS};
 use crate::encoded;
 use crate::error::{BlockImportError, Error as GenericError};
-use crate::miner::{MemPoolMinFees, Miner, MinerService, TransactionImportResult};
+use crate::invoice::TransactionAttempt;
+use crate::miner::{
+    FeeHistogram, MemPoolEntry, MemPoolMinFees, Miner, MinerService, ReadyTransactionsCursor, ReadyTransactionsPage,
+    SeqGapInfo, TransactionImportResult,
+};
 use crate::scheme::Scheme;
 use crate::transaction::{LocalizedTransaction, PendingSignedTransactions, SignedTransaction};
-use crate::types::{BlockId, TransactionId, VerificationQueueInfo as QueueInfo};
+use crate::types::{BlockId, ImportMetrics, TransactionId, VerificationQueueInfo as QueueInfo};
 use cdb;
 use ckey::{public_to_address, Address, Generator, KeyPair, NetworkId, PlatformAddress, Private, Public, Random};
 use cstate::tests::helpers::empty_top_state;
-use cstate::{FindActionHandler, StateDB, TopLevelState};
+use cstate::{CacheCounts, CacheKind, FindActionHandler, StateDB, TopLevelState};
 use ctimer::{TimeoutHandler, TimerToken};
-use ctypes::transaction::{Action, Transaction};
+use ctypes::transaction::TransactionBuilder;
 use ctypes::{BlockHash, BlockNumber, CommonParams, Header as BlockHeader, Tracker, TxHash};
 use cvm::ChainTimeInfo;
 use kvdb::KeyValueDB;
@@ -62,8 +67,8 @@ use rlp::*;
 use std::collections::HashMap;
 use std::mem;
 use std::ops::Range;
-use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrder};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrder};
+use std::sync::{Arc, Weak};
 
 /// Test client.
 pub struct TestBlockChainClient {
@@ -81,6 +86,8 @@ pub struct TestBlockChainClient {
     pub extra_data: Bytes,
     /// Score.
     pub score: RwLock<U256>,
+    /// Total score of every known block, including the ones on side branches.
+    pub total_scores: RwLock<HashMap<BlockHash, U256>>,
     /// Balances.
     pub balances: RwLock<HashMap<Address, u64>>,
     /// Seqs.
@@ -103,6 +110,16 @@ pub struct TestBlockChainClient {
     pub validator_keys: RwLock<HashMap<Public, Private>>,
     /// Fixed validators
     pub validators: Validators,
+    /// Whether the transactions of retracted blocks go back to the mem pool when the best block changes.
+    pub reinsert_retracted_transactions: AtomicBool,
+    /// Actors notified when the best block changes.
+    pub notify: RwLock<Vec<Weak<dyn ChainNotify>>>,
+    /// The common params changed by each block. The other blocks have the params of their parents.
+    pub common_params: RwLock<HashMap<BlockHash, CommonParams>>,
+    /// The proposals recorded by the consensus engine as orphaned.
+    pub orphaned_proposals: RwLock<Vec<OrphanedProposal>>,
+    /// The sealing requests queued by `update_sealing_async`.
+    pub sealing_requests: RwLock<Vec<(BlockId, bool)>>,
 }
 
 impl Default for TestBlockChainClient {
@@ -146,6 +163,7 @@ impl TestBlockChainClient {
             last_hash: RwLock::new(genesis_hash),
             last_transactions_root: RwLock::new(genesis_transactions_root),
             score: RwLock::new(genesis_score),
+            total_scores: RwLock::new(HashMap::new()),
             balances: RwLock::new(HashMap::new()),
             seqs: RwLock::new(HashMap::new()),
             storage: RwLock::new(HashMap::new()),
@@ -157,14 +175,35 @@ impl TestBlockChainClient {
             term_id: Some(1),
             validator_keys: RwLock::new(HashMap::new()),
             validators: Validators::from_vector_to_test(vec![]),
+            reinsert_retracted_transactions: AtomicBool::new(false),
+            notify: RwLock::new(Vec::new()),
+            common_params: RwLock::new(HashMap::new()),
+            orphaned_proposals: RwLock::new(Vec::new()),
+            sealing_requests: RwLock::new(Vec::new()),
         };
 
         // insert genesis hash.
         client.blocks.get_mut().insert(genesis_hash, genesis_block);
         client.numbers.get_mut().insert(0, genesis_hash);
+        client.total_scores.get_mut().insert(genesis_hash, genesis_score);
         client
     }
 
+    /// Adds an actor to be notified when the best block changes.
+    pub fn add_notify(&self, target: Weak<dyn ChainNotify>) {
+        self.notify.write().push(target);
+    }
+
+    /// Set whether the transactions of retracted blocks go back to the mem pool on a reorg.
+    pub fn set_reinsert_retracted_transactions(&self, reinsert: bool) {
+        self.reinsert_retracted_transactions.store(reinsert, AtomicOrder::Relaxed);
+    }
+
+    /// Makes the params changed by the block, as if it had a ChangeParams transaction.
+    pub fn set_common_params(&self, hash: BlockHash, params: CommonParams) {
+        self.common_params.write().insert(hash, params);
+    }
+
     /// Set the balance of account `address` to `balance`.
     pub fn set_balance(&self, address: Address, balance: u64) {
         self.balances.write().insert(address, balance);
@@ -180,6 +219,14 @@ impl TestBlockChainClient {
         self.storage.write().insert((address, position), value);
     }
 
+    /// Runs the queued sealing requests as the IO handler does.
+    pub fn process_sealing_requests(&self) {
+        let requests = mem::replace(&mut *self.sealing_requests.write(), Vec::new());
+        for (parent_block, allow_empty_block) in requests {
+            self.miner.update_sealing(self, parent_block, allow_empty_block);
+        }
+    }
+
     /// Set block queue size for testing
     pub fn set_queue_size(&self, size: usize) {
         self.queue_size.store(size, AtomicOrder::Relaxed);
@@ -207,23 +254,7 @@ impl TestBlockChainClient {
         if let Some(addr) = author {
             header.set_author(addr);
         }
-        let mut transactions = Vec::with_capacity(transaction_length);
-        for _ in 0..transaction_length {
-            let keypair = Random.generate().unwrap();
-            // Update seqs value
-            self.seqs.write().insert(keypair.address(), 0);
-            let tx = Transaction {
-                seq: 0,
-                fee: 10,
-                network_id: NetworkId::default(),
-                action: Action::Pay {
-                    receiver: Address::random(),
-                    quantity: 0,
-                },
-            };
-            let signed = SignedTransaction::new_with_sign(tx, keypair.private());
-            transactions.push(signed);
-        }
+        let transactions = self.random_transactions(transaction_length);
         header.set_transactions_root(skewed_merkle_root(
             *self.last_transactions_root.read(),
             transactions.iter().map(Encodable::rlp_bytes),
@@ -234,6 +265,102 @@ impl TestBlockChainClient {
         self.import_block(rlp.as_raw().to_vec()).unwrap()
     }
 
+    /// Add a block on top of `parent_hash` without changing the best block.
+    ///
+    /// Use it to grow a competing branch and call `set_best_block` to switch to it.
+    pub fn add_block_on(
+        &self,
+        parent_hash: BlockHash,
+        author: Option<Address>,
+        transactions: Vec<SignedTransaction>,
+    ) -> BlockHash {
+        let parent =
+            self.block_header(&parent_hash.into()).unwrap_or_else(|| panic!("Unknown block parent {:?}", parent_hash));
+        let n = parent.number() + 1;
+        let mut header = BlockHeader::new();
+        header.set_score(From::from(n));
+        header.set_parent_hash(parent_hash);
+        header.set_number(n);
+        // Siblings would share their hash if they had the same author and transactions.
+        header.set_timestamp(self.blocks.read().len() as u64);
+        header.set_extra_data(self.extra_data.clone());
+        if let Some(addr) = author {
+            header.set_author(addr);
+        }
+        header.set_transactions_root(skewed_merkle_root(
+            parent.transactions_root(),
+            transactions.iter().map(Encodable::rlp_bytes),
+        ));
+        let mut rlp = RlpStream::new_list(2);
+        rlp.append(&header);
+        rlp.append_list(&transactions);
+        self.insert_block(rlp.out())
+    }
+
+    /// Make `hash` the best block as if its branch had won, and return the route from the previous best block.
+    pub fn set_best_block(&self, hash: BlockHash) -> TreeRoute {
+        self.change_best_block(hash, &[])
+    }
+
+    fn insert_block(&self, b: Bytes) -> BlockHash {
+        let header = Rlp::new(&b).val_at::<BlockHeader>(0).unwrap();
+        let h = header.hash();
+        let parent_total_score = if header.number() == 0 {
+            U256::zero()
+        } else {
+            self.total_scores.read()[header.parent_hash()]
+        };
+        self.total_scores.write().insert(h, parent_total_score + *header.score());
+        self.blocks.write().insert(h, b);
+        h
+    }
+
+    fn change_best_block(&self, hash: BlockHash, imported: &[BlockHash]) -> TreeRoute {
+        let route = self.tree_route(*self.last_hash.read(), hash).expect("The best block and the new one are known");
+        {
+            let number = self.block_header(&hash.into()).unwrap().number() as usize;
+            let ancestor_number = number - route.enacted.len();
+            let mut numbers = self.numbers.write();
+            numbers.retain(|n, _| *n <= ancestor_number);
+            for (index, enacted) in route.enacted.iter().enumerate() {
+                numbers.insert(ancestor_number + 1 + index, *enacted);
+            }
+        }
+        *self.score.write() = self.total_scores.read()[&hash];
+        *self.last_hash.write() = hash;
+        // FIXME: The transactions root is not related to block hash.
+        *self.last_transactions_root.write() = *hash;
+
+        if self.reinsert_retracted_transactions.load(AtomicOrder::Relaxed) {
+            self.miner.chain_new_blocks(self, imported, &[], &route.enacted, &route.retracted);
+        }
+        for np in self.notify.read().iter() {
+            if let Some(n) = np.upgrade() {
+                n.new_blocks(imported.to_vec(), vec![], route.enacted.clone(), route.retracted.clone(), vec![]);
+            }
+        }
+        route
+    }
+
+    /// Generates transactions signed by fresh accounts.
+    pub fn random_transactions(&self, transaction_length: usize) -> Vec<SignedTransaction> {
+        let mut transactions = Vec::with_capacity(transaction_length);
+        for _ in 0..transaction_length {
+            let keypair = Random.generate().unwrap();
+            // Update seqs value
+            self.seqs.write().insert(keypair.address(), 0);
+            let tx = TransactionBuilder::pay(Address::random(), 0)
+                .seq(0)
+                .fee(10)
+                .network(NetworkId::default())
+                .build()
+                .expect("A valid pay transaction");
+            let signed = SignedTransaction::new_with_sign(tx, keypair.private());
+            transactions.push(signed);
+        }
+        transactions
+    }
+
     /// Make a bad block by setting invalid extra data.
     pub fn corrupt_block(&self, n: BlockNumber) {
         let block_id = n.into();
@@ -288,15 +415,12 @@ impl TestBlockChainClient {
     /// Inserts a transaction to miners mem pool.
     pub fn insert_transaction_to_pool(&self) -> TxHash {
         let keypair = Random.generate().unwrap();
-        let tx = Transaction {
-            seq: 0,
-            fee: 10,
-            network_id: NetworkId::default(),
-            action: Action::Pay {
-                receiver: Address::random(),
-                quantity: 0,
-            },
-        };
+        let tx = TransactionBuilder::pay(Address::random(), 0)
+            .seq(0)
+            .fee(10)
+            .network(NetworkId::default())
+            .build()
+            .expect("A valid pay transaction");
         let signed = SignedTransaction::new_with_sign(tx, keypair.private());
         let sender_address = public_to_address(&signed.signer_public());
         self.set_balance(sender_address, 10_000_000_000_000_000_000);
@@ -381,6 +505,10 @@ impl MiningBlockChainClient for TestBlockChainClient {
     fn mem_pool_min_fees(&self) -> MemPoolMinFees {
         self.miner.get_options().mem_pool_min_fees
     }
+
+    fn pending_state(&self) -> TopLevelState {
+        self.miner.pending_state_snapshot(self)
+    }
 }
 
 impl AccountData for TestBlockChainClient {
@@ -417,7 +545,8 @@ impl AccountData for TestBlockChainClient {
 
 impl BlockChainTrait for TestBlockChainClient {
     fn chain_info(&self) -> BlockChainInfo {
-        let number = self.blocks.read().len() as BlockNumber - 1;
+        let number = self.numbers.read().len() as BlockNumber - 1;
+        let finalized_block_number = self.scheme.engine.finalized_block_number(number);
         BlockChainInfo {
             best_score: *self.score.read(),
             best_proposal_score: *self.score.read(),
@@ -427,6 +556,8 @@ impl BlockChainTrait for TestBlockChainClient {
             best_proposal_block_hash: *self.last_hash.read(),
             best_block_number: number,
             best_block_timestamp: number,
+            finalized_block_number,
+            finalized_block_hash: self.numbers.read()[&(finalized_block_number as usize)],
         }
     }
 
@@ -463,6 +594,39 @@ impl BlockChainTrait for TestBlockChainClient {
     fn transaction_header(&self, _tracker: &Tracker) -> Option<encoded::Header> {
         None
     }
+
+    fn tree_route(&self, from: BlockHash, to: BlockHash) -> Option<TreeRoute> {
+        let mut retracted = vec![];
+        let mut enacted = vec![];
+
+        let mut cur_retract = self.block_header(&from.into())?;
+        let mut cur_enact = self.block_header(&to.into())?;
+
+        while cur_retract.number() != cur_enact.number() {
+            let (header, vec) = if cur_retract.number() > cur_enact.number() {
+                (&mut cur_retract, &mut retracted)
+            } else {
+                (&mut cur_enact, &mut enacted)
+            };
+            vec.push(header.hash());
+            *header = self.block_header(&header.parent_hash().into())?;
+        }
+
+        while cur_retract.hash() != cur_enact.hash() {
+            retracted.push(cur_retract.hash());
+            enacted.push(cur_enact.hash());
+            cur_retract = self.block_header(&cur_retract.parent_hash().into())?;
+            cur_enact = self.block_header(&cur_enact.parent_hash().into())?;
+        }
+
+        enacted.reverse();
+
+        Some(TreeRoute {
+            ancestor: cur_retract.hash(),
+            enacted,
+            retracted,
+        })
+    }
 }
 
 impl ImportBlock for TestBlockChainClient {
@@ -482,32 +646,17 @@ impl ImportBlock for TestBlockChainClient {
             assert_eq!(parent.number(), header.number() - 1, "Unexpected block parent");
         }
         let len = self.numbers.read().len();
+        self.insert_block(b);
         if number == len {
-            {
-                let mut score = self.score.write();
-                *score += *header.score();
-            }
-            mem::replace(&mut *self.last_hash.write(), h);
-            // FIXME: The transactions root is not related to block hash.
-            mem::replace(&mut *self.last_transactions_root.write(), *h);
-            self.blocks.write().insert(h, b);
-            self.numbers.write().insert(number, h);
-            let mut parent_hash = *header.parent_hash();
-            if number > 0 {
-                let mut n = number - 1;
-                while n > 0 && self.numbers.read()[&n] != parent_hash {
-                    *self.numbers.write().get_mut(&n).unwrap() = parent_hash;
-                    n -= 1;
-                    parent_hash =
-                        *Rlp::new(&self.blocks.read()[&parent_hash]).val_at::<BlockHeader>(0).unwrap().parent_hash();
-                }
-            }
-        } else {
-            self.blocks.write().insert(h, b.to_vec());
+            self.change_best_block(h, &[h]);
         }
         Ok(h)
     }
 
+    fn import_sealed_block(&self, b: Bytes) -> Result<BlockHash, BlockImportError> {
+        self.import_block(b)
+    }
+
     fn import_header(&self, _bytes: BlockHeader) -> Result<BlockHash, BlockImportError> {
         unimplemented!()
     }
@@ -534,8 +683,25 @@ impl BlockChainClient for TestBlockChainClient {
         }
     }
 
+    fn import_metrics(&self) -> Option<ImportMetrics> {
+        None
+    }
+
+    fn state_cache_counts(&self) -> Vec<(CacheKind, CacheCounts)> {
+        Vec::new()
+    }
+
     fn queue_own_transaction(&self, transaction: SignedTransaction) -> Result<(), GenericError> {
-        self.miner.import_own_transaction(self, transaction)?;
+        self.miner.import_own_transaction(self, transaction, None)?;
+        Ok(())
+    }
+
+    fn queue_own_transaction_with_expiry(
+        &self,
+        transaction: SignedTransaction,
+        expiry: u64,
+    ) -> Result<(), GenericError> {
+        self.miner.import_own_transaction(self, transaction, Some(expiry))?;
         Ok(())
     }
 
@@ -549,6 +715,22 @@ impl BlockChainClient for TestBlockChainClient {
         self.miner.delete_all_pending_transactions();
     }
 
+    fn remove_local_transaction(&self, hash: &TxHash) -> Option<bool> {
+        self.miner.remove_local_transaction(self, hash)
+    }
+
+    fn pending_transactions_by_signer(&self, signer: &Address) -> Vec<MemPoolEntry> {
+        self.miner.pending_transactions_by_signer(signer)
+    }
+
+    fn seq_gap_info(&self, signer: &Address) -> SeqGapInfo {
+        self.miner.seq_gap_info(self, signer)
+    }
+
+    fn drop_future_transactions(&self, signer: &Address, from_seq: u64) -> Result<Vec<TxHash>, TxHash> {
+        self.miner.drop_future_transactions(self, signer, from_seq)
+    }
+
     fn ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions {
         self.miner.ready_transactions(range)
     }
@@ -557,6 +739,19 @@ impl BlockChainClient for TestBlockChainClient {
         self.miner.future_ready_transactions(range)
     }
 
+    fn ready_transactions_limited(
+        &self,
+        max_count: usize,
+        max_encoded_size: usize,
+        cursor: Option<ReadyTransactionsCursor>,
+    ) -> ReadyTransactionsPage {
+        self.miner.ready_transactions_limited(max_count, max_encoded_size, cursor)
+    }
+
+    fn pending_fee_histogram(&self) -> FeeHistogram {
+        self.miner.pending_fee_histogram()
+    }
+
     fn count_pending_transactions(&self, range: Range<u64>) -> usize {
         self.miner.count_pending_transactions(range)
     }
@@ -569,8 +764,8 @@ impl BlockChainClient for TestBlockChainClient {
         self.miner.status().transactions_in_pending_queue == 0
     }
 
-    fn block_number(&self, _id: &BlockId) -> Option<BlockNumber> {
-        unimplemented!()
+    fn block_number(&self, id: &BlockId) -> Option<BlockNumber> {
+        self.block_header(id).map(|header| header.number())
     }
 
     fn block_body(&self, id: &BlockId) -> Option<encoded::Body> {
@@ -583,9 +778,13 @@ impl BlockChainClient for TestBlockChainClient {
         })
     }
 
+    fn is_block_body_pruned(&self, _id: &BlockId) -> bool {
+        false
+    }
+
     fn block_status(&self, id: &BlockId) -> BlockStatus {
         match id {
-            BlockId::Number(number) if (*number as usize) < self.blocks.read().len() => BlockStatus::InChain,
+            BlockId::Number(number) if self.numbers.read().contains_key(&(*number as usize)) => BlockStatus::InChain,
             BlockId::Hash(ref hash) if self.blocks.read().get(hash).is_some() => BlockStatus::InChain,
             BlockId::Latest | BlockId::Earliest => BlockStatus::InChain,
             BlockId::ParentOfLatest => BlockStatus::InChain,
@@ -593,8 +792,8 @@ impl BlockChainClient for TestBlockChainClient {
         }
     }
 
-    fn block_total_score(&self, _id: &BlockId) -> Option<U256> {
-        Some(U256::zero())
+    fn block_total_score(&self, id: &BlockId) -> Option<U256> {
+        self.block_hash(id).and_then(|hash| self.total_scores.read().get(&hash).cloned())
     }
 
     fn block_hash(&self, id: &BlockId) -> Option<BlockHash> {
@@ -613,9 +812,57 @@ impl BlockChainClient for TestBlockChainClient {
         unimplemented!();
     }
 
+    fn transactions_by_tracker(&self, _: &Tracker) -> Vec<LocalizedTransaction> {
+        unimplemented!();
+    }
+
+    fn transaction_attempts_by_tracker(&self, _: &Tracker) -> Vec<TransactionAttempt> {
+        unimplemented!();
+    }
+
     fn error_hints_by_tracker(&self, _: &Tracker) -> Vec<(TxHash, Option<String>)> {
         unimplemented!();
     }
+
+    fn error_hints_by_block(&self, _id: &BlockId) -> Vec<(TxHash, Option<String>)> {
+        unimplemented!();
+    }
+
+    fn fee_distribution(&self, _id: &BlockId) -> Option<FeeDistribution> {
+        unimplemented!();
+    }
+
+    fn transactions_by_address(
+        &self,
+        _address: &Address,
+        _from: BlockNumber,
+        _to: BlockNumber,
+        _skip: usize,
+        _limit: usize,
+    ) -> Result<Vec<LocalizedTransaction>, AddressIndexError> {
+        Err(AddressIndexError::Disabled)
+    }
+
+    fn asset_logs(
+        &self,
+        _filter: &AssetLogFilter,
+        _skip: usize,
+        _limit: usize,
+    ) -> Result<Vec<LocalizedAssetLog>, AssetLogError> {
+        Err(AssetLogError::Disabled)
+    }
+
+    fn orphaned_proposals(&self, from: BlockNumber, to: BlockNumber) -> Result<Vec<OrphanedProposal>, DecoderError> {
+        let mut proposals: Vec<_> = self
+            .orphaned_proposals
+            .read()
+            .iter()
+            .filter(|proposal| from <= proposal.height && proposal.height <= to)
+            .cloned()
+            .collect();
+        proposals.sort_by_key(|proposal| proposal.height);
+        Ok(proposals)
+    }
 }
 
 impl TimeoutHandler for TestBlockChainClient {
@@ -630,13 +877,17 @@ impl ChainTimeInfo for TestBlockChainClient {
     fn transaction_time_age(&self, _: &Tracker, _parent_timestamp: u64) -> Option<u64> {
         Some(0)
     }
+
+    fn caps_multisig_pubkeys(&self, _parent_block_number: BlockNumber) -> bool {
+        false
+    }
 }
 
 impl FindActionHandler for TestBlockChainClient {}
 
 impl super::EngineClient for TestBlockChainClient {
-    fn update_sealing(&self, parent_block: BlockId, allow_empty_block: bool) {
-        self.miner.update_sealing(self, parent_block, allow_empty_block)
+    fn update_sealing_async(&self, parent_block: BlockId, allow_empty_block: bool) {
+        self.sealing_requests.write().push((parent_block, allow_empty_block));
     }
 
     fn submit_seal(&self, block_hash: BlockHash, seal: Vec<Bytes>) {
@@ -655,6 +906,10 @@ impl super::EngineClient for TestBlockChainClient {
         let db = kvdb_memorydb::create(NUM_COLUMNS.unwrap_or(0));
         Arc::new(db)
     }
+
+    fn record_orphaned_proposals(&self, proposals: Vec<OrphanedProposal>) {
+        self.orphaned_proposals.write().extend(proposals);
+    }
 }
 
 impl EngineInfo for TestBlockChainClient {
@@ -662,8 +917,18 @@ impl EngineInfo for TestBlockChainClient {
         self.scheme.engine.machine().genesis_common_params().network_id()
     }
 
-    fn common_params(&self, _block_id: BlockId) -> Option<CommonParams> {
-        Some(*self.scheme.engine.machine().genesis_common_params())
+    fn common_params(&self, block_id: BlockId) -> Option<CommonParams> {
+        let mut hash = self.block_hash(&block_id)?;
+        let common_params = self.common_params.read();
+        loop {
+            if let Some(params) = common_params.get(&hash) {
+                return Some(*params)
+            }
+            match self.block_header(&hash.into()) {
+                Some(header) if header.number() > 0 => hash = header.parent_hash(),
+                _ => return Some(*self.scheme.engine.machine().genesis_common_params()),
+            }
+        }
     }
 
     fn metadata_seq(&self, _block_id: BlockId) -> Option<u64> {
@@ -685,6 +950,18 @@ impl EngineInfo for TestBlockChainClient {
     fn possible_authors(&self, _block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>, EngineError> {
         unimplemented!()
     }
+
+    fn validators(&self, _block_number: Option<u64>) -> Result<Option<Vec<Validator>>, EngineError> {
+        Ok(Some(self.validators.to_vec()))
+    }
+
+    fn proposal_block(&self, height: BlockNumber, view: u64) -> Option<encoded::Block> {
+        self.scheme.engine.proposal_block(height, view)
+    }
+
+    fn validator_liveness(&self, _term_id: Option<u64>) -> Result<Option<Vec<ValidatorLiveness>>, GenericError> {
+        Ok(None)
+    }
 }
 
 impl ConsensusClient for TestBlockChainClient {}
@@ -712,3 +989,131 @@ impl StateInfo for TestBlockChainClient {
         Some(top_state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::ConsensusEngine;
+    use parking_lot::Mutex;
+
+    #[derive(Default)]
+    struct Routes(Mutex<Vec<(Vec<BlockHash>, Vec<BlockHash>)>>);
+
+    impl ChainNotify for Routes {
+        fn new_blocks(
+            &self,
+            _imported: Vec<BlockHash>,
+            _invalid: Vec<BlockHash>,
+            enacted: Vec<BlockHash>,
+            retracted: Vec<BlockHash>,
+            _sealed: Vec<BlockHash>,
+        ) {
+            self.0.lock().push((enacted, retracted));
+        }
+    }
+
+    fn pay(private: &Private, seq: u64) -> SignedTransaction {
+        let tx = TransactionBuilder::pay(Address::random(), 0)
+            .seq(seq)
+            .fee(10)
+            .network(NetworkId::default())
+            .build()
+            .expect("A valid pay transaction");
+        SignedTransaction::new_with_sign(tx, private)
+    }
+
+    #[test]
+    fn switching_to_heavier_branch_reorganizes_the_chain() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(5, 0);
+        let old_branch: Vec<_> = (3..=5u64).map(|n| client.block_hash(&n.into()).unwrap()).collect();
+
+        let routes = Arc::new(Routes::default());
+        let notify: Arc<dyn ChainNotify> = Arc::clone(&routes) as _;
+        client.add_notify(Arc::downgrade(&notify));
+
+        let mut new_branch = vec![client.block_hash(&2u64.into()).unwrap()];
+        for _ in 0..4 {
+            let parent = *new_branch.last().unwrap();
+            new_branch.push(client.add_block_on(parent, Some(Address::random()), vec![]));
+        }
+        let ancestor = new_branch.remove(0);
+        assert_eq!(client.chain_info().best_block_hash, old_branch[2]);
+
+        let tip = *new_branch.last().unwrap();
+        let route = client.set_best_block(tip);
+        assert_eq!(ancestor, route.ancestor);
+        assert_eq!(new_branch, route.enacted);
+        assert_eq!(old_branch.iter().rev().cloned().collect::<Vec<_>>(), route.retracted);
+
+        let info = client.chain_info();
+        assert_eq!(tip, info.best_block_hash);
+        assert_eq!(6, info.best_block_number);
+        assert_eq!(client.block_total_score(&tip.into()), Some(info.best_score));
+        for (n, hash) in (3..=6u64).zip(new_branch.iter()) {
+            assert_eq!(Some(*hash), client.block_hash(&n.into()));
+        }
+        assert_eq!(Some(tip), client.block_hash(&BlockId::Latest));
+        assert_eq!(vec![(route.enacted, route.retracted)], *routes.0.lock());
+    }
+
+    #[test]
+    fn transactions_of_retracted_blocks_become_pending() {
+        let client = TestBlockChainClient::new();
+        client.set_reinsert_retracted_transactions(true);
+        let keypair = Random.generate().unwrap();
+        client.set_balance(keypair.address(), 1_000_000);
+
+        let tx = pay(keypair.private(), 0);
+        let included = client.add_block_on(client.genesis_hash, None, vec![tx.clone()]);
+        client.set_best_block(included);
+        assert_eq!(0, client.count_pending_transactions(0..u64::max_value()));
+
+        let first = client.add_block_on(client.genesis_hash, Some(Address::random()), vec![]);
+        let second = client.add_block_on(first, Some(Address::random()), vec![]);
+        let route = client.set_best_block(second);
+        assert_eq!(vec![included], route.retracted);
+        assert_eq!(vec![tx], client.ready_transactions(0..u64::max_value()).transactions);
+    }
+
+    #[test]
+    fn transactions_of_retracted_blocks_are_dropped_by_default() {
+        let client = TestBlockChainClient::new();
+        let keypair = Random.generate().unwrap();
+        client.set_balance(keypair.address(), 1_000_000);
+
+        let included = client.add_block_on(client.genesis_hash, None, vec![pay(keypair.private(), 0)]);
+        client.set_best_block(included);
+
+        let first = client.add_block_on(client.genesis_hash, Some(Address::random()), vec![]);
+        let second = client.add_block_on(first, Some(Address::random()), vec![]);
+        client.set_best_block(second);
+        assert_eq!(0, client.count_pending_transactions(0..u64::max_value()));
+    }
+
+    #[test]
+    fn blocks_are_finalized_after_recommended_confirmations() {
+        let client = TestBlockChainClient::new_with_scheme(Scheme::new_test_cuckoo());
+        assert_eq!(6, client.scheme.engine.recommended_confirmation());
+
+        client.add_blocks(3, 0);
+        let info = client.chain_info();
+        assert_eq!(0, info.finalized_block_number);
+        assert_eq!(client.genesis_hash, info.finalized_block_hash);
+
+        client.add_blocks(5, 0);
+        let info = client.chain_info();
+        assert_eq!(8, info.best_block_number);
+        assert_eq!(3, info.finalized_block_number);
+        assert_eq!(client.block_hash(&3u64.into()), Some(info.finalized_block_hash));
+    }
+
+    #[test]
+    fn best_block_is_finalized_under_tendermint() {
+        let client = TestBlockChainClient::new_with_scheme(Scheme::new_test_tendermint());
+        client.add_blocks(4, 0);
+        let info = client.chain_info();
+        assert_eq!(4, info.finalized_block_number);
+        assert_eq!(info.best_block_hash, info.finalized_block_hash);
+    }
+}
@@ -117,105 +117,33 @@ impl CodeChainMachine {
         client: &C,
     ) -> Result<(), Error> {
         for input in inputs {
-            if let Some(timelock) = input.timelock {
-                match timelock {
-                    Timelock::Block(value) if value > header.number() => {
-                        return Err(HistoryError::Timelocked {
-                            timelock,
-                            remaining_time: value - header.number(),
-                        }
-                        .into())
-                    }
-                    Timelock::BlockAge(value) => {
-                        let absolute = client.transaction_block_number(&input.prev_out.tracker).ok_or_else(|| {
-                            Error::History(HistoryError::Timelocked {
-                                timelock,
-                                remaining_time: u64::max_value(),
+            if let Some(timelock) = &input.timelock {
+                let tracker = &input.prev_out.tracker;
+                timelock
+                    .evaluate(&mut |condition: &Timelock| match *condition {
+                        Timelock::Block(value) => value.saturating_sub(header.number()),
+                        Timelock::BlockAge(value) => client
+                            .transaction_block_number(tracker)
+                            .map_or(u64::max_value(), |number| (number + value).saturating_sub(header.number())),
+                        Timelock::Time(value) => value.saturating_sub(header.timestamp()),
+                        Timelock::TimeAge(value) => {
+                            client.transaction_block_timestamp(tracker).map_or(u64::max_value(), |timestamp| {
+                                (timestamp + value).saturating_sub(header.timestamp())
                             })
-                        })? + value;
-                        if absolute > header.number() {
-                            return Err(HistoryError::Timelocked {
-                                timelock,
-                                remaining_time: absolute - header.number(),
-                            }
-                            .into())
-                        }
-                    }
-                    Timelock::Time(value) if value > header.timestamp() => {
-                        return Err(HistoryError::Timelocked {
-                            timelock,
-                            remaining_time: value - header.timestamp(),
                         }
-                        .into())
-                    }
-                    Timelock::TimeAge(value) => {
-                        let absolute =
-                            client.transaction_block_timestamp(&input.prev_out.tracker).ok_or_else(|| {
-                                Error::History(HistoryError::Timelocked {
-                                    timelock,
-                                    remaining_time: u64::max_value(),
-                                })
-                            })? + value;
-                        if absolute > header.timestamp() {
-                            return Err(HistoryError::Timelocked {
-                                timelock,
-                                remaining_time: absolute - header.timestamp(),
-                            }
-                            .into())
-                        }
-                    }
-                    _ => (),
-                }
+                        Timelock::All(_) | Timelock::Any(_) => unreachable!("Only single conditions are evaluated"),
+                    })
+                    .map_err(|(timelock, remaining_time)| HistoryError::Timelocked {
+                        timelock,
+                        remaining_time,
+                    })?;
             }
         }
         Ok(())
     }
 
     pub fn min_cost(params: &CommonParams, action: &Action) -> u64 {
-        match action {
-            Action::MintAsset {
-                ..
-            } => params.min_asset_mint_cost(),
-            Action::TransferAsset {
-                ..
-            } => params.min_asset_transfer_cost(),
-            Action::ChangeAssetScheme {
-                ..
-            } => params.min_asset_scheme_change_cost(),
-            Action::IncreaseAssetSupply {
-                ..
-            } => params.min_asset_supply_increase_cost(),
-            Action::UnwrapCCC {
-                ..
-            } => params.min_asset_unwrap_ccc_cost(),
-            Action::Pay {
-                ..
-            } => params.min_pay_transaction_cost(),
-            Action::SetRegularKey {
-                ..
-            } => params.min_set_regular_key_transaction_cost(),
-            Action::CreateShard {
-                ..
-            } => params.min_create_shard_transaction_cost(),
-            Action::SetShardOwners {
-                ..
-            } => params.min_set_shard_owners_transaction_cost(),
-            Action::SetShardUsers {
-                ..
-            } => params.min_set_shard_users_transaction_cost(),
-            Action::WrapCCC {
-                ..
-            } => params.min_wrap_ccc_transaction_cost(),
-            Action::Custom {
-                ..
-            } => params.min_custom_transaction_cost(),
-            Action::Store {
-                ..
-            } => params.min_store_transaction_cost(),
-            Action::Remove {
-                ..
-            } => params.min_remove_transaction_cost(),
-        }
+        action.min_cost(params)
     }
 
     pub fn balance(&self, live: &ExecutedBlock, address: &Address) -> Result<u64, Error> {
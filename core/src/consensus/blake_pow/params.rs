@@ -15,7 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use cjson;
+use cjson::uint::OverflowError;
 use primitives::U256;
+use std::convert::TryFrom;
 
 pub struct BlakePoWParams {
     pub block_reward: u64,
@@ -24,13 +26,15 @@ pub struct BlakePoWParams {
     pub recommmended_confirmation: u32,
 }
 
-impl From<cjson::scheme::BlakePoWParams> for BlakePoWParams {
-    fn from(p: cjson::scheme::BlakePoWParams) -> Self {
-        BlakePoWParams {
-            block_reward: p.block_reward.map_or(0, Into::into),
-            block_interval: p.block_interval.map_or(120, Into::into),
+impl TryFrom<cjson::scheme::BlakePoWParams> for BlakePoWParams {
+    type Error = OverflowError;
+
+    fn try_from(p: cjson::scheme::BlakePoWParams) -> Result<Self, Self::Error> {
+        Ok(BlakePoWParams {
+            block_reward: p.block_reward.map_or(Ok(0), u64::try_from)?,
+            block_interval: p.block_interval.map_or(Ok(120), u64::try_from)?,
             min_score: p.min_score.map_or(U256::from(0x0002_0000), Into::into),
-            recommmended_confirmation: p.recommended_confirmation.map_or(15, Into::into),
-        }
+            recommmended_confirmation: p.recommended_confirmation.map_or(Ok(15), u32::try_from)?,
+        })
     }
 }
@@ -15,7 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use cjson;
+use cjson::uint::OverflowError;
 use primitives::U256;
+use std::convert::TryFrom;
 
 pub struct CuckooParams {
     pub block_reward: u64,
@@ -27,16 +29,18 @@ pub struct CuckooParams {
     pub recommmended_confirmation: u32,
 }
 
-impl From<cjson::scheme::CuckooParams> for CuckooParams {
-    fn from(p: cjson::scheme::CuckooParams) -> Self {
-        CuckooParams {
-            block_reward: p.block_reward.map_or(0, Into::into),
-            block_interval: p.block_interval.map_or(120, Into::into),
+impl TryFrom<cjson::scheme::CuckooParams> for CuckooParams {
+    type Error = OverflowError;
+
+    fn try_from(p: cjson::scheme::CuckooParams) -> Result<Self, Self::Error> {
+        Ok(CuckooParams {
+            block_reward: p.block_reward.map_or(Ok(0), u64::try_from)?,
+            block_interval: p.block_interval.map_or(Ok(120), u64::try_from)?,
             min_score: p.min_score.map_or(U256::from(0x0002_0000), Into::into),
-            max_vertex: p.max_vertex.map_or(1 << 30, Into::into),
-            max_edge: p.max_edge.map_or(1 << 29, Into::into),
-            cycle_length: p.cycle_length.map_or(42, Into::into),
-            recommmended_confirmation: p.recommended_confirmation.map_or(15, Into::into),
-        }
+            max_vertex: p.max_vertex.map_or(Ok(1 << 30), usize::try_from)?,
+            max_edge: p.max_edge.map_or(Ok(1 << 29), usize::try_from)?,
+            cycle_length: p.cycle_length.map_or(Ok(42), usize::try_from)?,
+            recommmended_confirmation: p.recommended_confirmation.map_or(Ok(15), u32::try_from)?,
+        })
     }
 }
@@ -37,10 +37,12 @@ pub use self::validator_set::validator_list::RoundRobinValidator;
 pub use self::validator_set::{DynamicValidator, ValidatorSet};
 
 use self::bit_set::BitSet;
+use self::stake::{Validator, ValidatorLiveness};
 use crate::account_provider::AccountProvider;
 use crate::block::{ExecutedBlock, SealedBlock};
 use crate::client::ConsensusClient;
 use crate::codechain_machine::CodeChainMachine;
+use crate::encoded;
 use crate::error::Error;
 use crate::transaction::UnverifiedTransaction;
 use crate::views::HeaderView;
@@ -51,7 +53,7 @@ use cstate::ActionHandler;
 use ctypes::errors::SyntaxError;
 use ctypes::transaction::Action;
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
-use ctypes::{BlockHash, CommonParams, Header};
+use ctypes::{BlockHash, BlockNumber, CommonParams, Header};
 use primitives::{Bytes, U256};
 use std::fmt;
 use std::sync::{Arc, Weak};
@@ -153,6 +155,12 @@ pub trait ConsensusEngine: Sync + Send {
         None
     }
 
+    /// Whether the blocks can be sealed by a signer outside of the node.
+    /// The miner gives the prepared blocks to the external signer only if the engine supports it.
+    fn supports_external_sealing(&self) -> bool {
+        false
+    }
+
     /// The type of this engine.
     fn engine_type(&self) -> EngineType;
 
@@ -235,6 +243,12 @@ pub trait ConsensusEngine: Sync + Send {
         false
     }
 
+    /// The proposal block of the given height and view, which may not be inserted into the DB yet.
+    /// Only the proposals of the current height are kept.
+    fn proposal_block(&self, _height: u64, _view: u64) -> Option<encoded::Block> {
+        None
+    }
+
     /// Register an account which signs consensus messages.
     fn set_signer(&self, _ap: Arc<AccountProvider>, _address: Address) {}
 
@@ -254,6 +268,12 @@ pub trait ConsensusEngine: Sync + Send {
 
     fn recommended_confirmation(&self) -> u32;
 
+    /// The number of the highest block that won't be reverted when the best block is `best_block_number`.
+    /// A block is final when it gets `recommended_confirmation` confirmations, counting itself.
+    fn finalized_block_number(&self, best_block_number: BlockNumber) -> BlockNumber {
+        (best_block_number + 1).saturating_sub(self.recommended_confirmation().into())
+    }
+
     fn register_chain_notify(&self, _: &Client) {}
 
     fn get_best_block_from_best_proposal_header(&self, header: &HeaderView) -> BlockHash {
@@ -282,6 +302,22 @@ pub trait ConsensusEngine: Sync + Send {
     }
 
     fn possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<Address>>, EngineError>;
+
+    /// The validators of the given block, or the next block if it's not given.
+    /// Returns None if the engine has no validators.
+    fn validators(&self, _block_number: Option<u64>) -> Result<Option<Vec<Validator>>, EngineError> {
+        Ok(None)
+    }
+
+    /// The precommits of the validators on the blocks from `start_of_the_term` to the parent of `last_block`.
+    /// Returns None if the engine doesn't count the precommits or the block doesn't exist.
+    fn validator_liveness(
+        &self,
+        _start_of_the_term: BlockNumber,
+        _last_block: BlockNumber,
+    ) -> Result<Option<Vec<ValidatorLiveness>>, Error> {
+        Ok(None)
+    }
 }
 
 /// Voting errors.
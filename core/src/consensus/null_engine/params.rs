@@ -15,6 +15,8 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use cjson;
+use cjson::uint::OverflowError;
+use std::convert::TryFrom;
 
 /// Params for a null engine.
 #[derive(Clone, Default)]
@@ -23,10 +25,12 @@ pub struct NullEngineParams {
     pub block_reward: u64,
 }
 
-impl From<cjson::scheme::NullEngineParams> for NullEngineParams {
-    fn from(p: cjson::scheme::NullEngineParams) -> Self {
-        NullEngineParams {
-            block_reward: p.block_reward.map_or_else(Default::default, Into::into),
-        }
+impl TryFrom<cjson::scheme::NullEngineParams> for NullEngineParams {
+    type Error = OverflowError;
+
+    fn try_from(p: cjson::scheme::NullEngineParams) -> Result<Self, Self::Error> {
+        Ok(NullEngineParams {
+            block_reward: p.block_reward.map_or(Ok(0), u64::try_from)?,
+        })
     }
 }
@@ -18,6 +18,7 @@ mod params;
 
 use self::params::SimplePoAParams;
 use super::signer::EngineSigner;
+use super::stake::Validator;
 use super::validator_set::validator_list::RoundRobinValidator;
 use super::validator_set::ValidatorSet;
 use super::{ConsensusEngine, EngineError, Seal};
@@ -27,8 +28,9 @@ use crate::client::ConsensusClient;
 use crate::codechain_machine::CodeChainMachine;
 use crate::consensus::EngineType;
 use crate::error::{BlockError, Error};
+use crate::BlockId;
 use ckey::{public_to_address, recover, Address, Signature};
-use ctypes::{CommonParams, Header};
+use ctypes::{BlockHash, CommonParams, Header};
 use parking_lot::RwLock;
 use primitives::H256;
 use std::sync::{Arc, Weak};
@@ -39,6 +41,7 @@ pub struct SimplePoA {
     validators: Box<dyn ValidatorSet>,
     /// Reward per block, in base units.
     block_reward: u64,
+    client: RwLock<Option<Weak<dyn ConsensusClient>>>,
 }
 
 impl SimplePoA {
@@ -50,8 +53,19 @@ impl SimplePoA {
             // If you want to change the type of validator set, please fix possible_authors first.
             validators: Box::new(RoundRobinValidator::new(params.validators)),
             block_reward: params.block_reward,
+            client: Default::default(),
         }
     }
+
+    /// The hash of the parent of the given block. The latest block is the parent of the next block.
+    fn parent_hash_of(&self, block_number: Option<u64>) -> Result<BlockHash, EngineError> {
+        let client = self.client.read().as_ref().and_then(Weak::upgrade).ok_or(EngineError::CannotOpenBlock)?;
+        let parent = match block_number {
+            None => BlockId::Latest,
+            Some(block_number) => BlockId::Number(block_number.checked_sub(1).ok_or(EngineError::CannotOpenBlock)?),
+        };
+        client.block_header(&parent).map(|header| header.hash()).ok_or(EngineError::CannotOpenBlock)
+    }
 }
 
 fn verify_external(header: &Header, validators: &dyn ValidatorSet) -> Result<(), Error> {
@@ -86,6 +100,10 @@ impl ConsensusEngine for SimplePoA {
         Some(self.signer.read().is_some())
     }
 
+    fn supports_external_sealing(&self) -> bool {
+        true
+    }
+
     fn engine_type(&self) -> EngineType {
         EngineType::PoA
     }
@@ -124,6 +142,7 @@ impl ConsensusEngine for SimplePoA {
     }
 
     fn register_client(&self, client: Weak<dyn ConsensusClient>) {
+        *self.client.write() = Some(Weak::clone(&client));
         self.validators.register_client(client);
     }
 
@@ -145,6 +164,11 @@ impl ConsensusEngine for SimplePoA {
         let parent = H256::from(0).into();
         Ok(Some(self.validators.addresses(&parent)))
     }
+
+    fn validators(&self, block_number: Option<u64>) -> Result<Option<Vec<Validator>>, EngineError> {
+        let parent = self.parent_hash_of(block_number)?;
+        Ok(Some(self.validators.validators(&parent)))
+    }
 }
 
 #[cfg(test)]
@@ -184,4 +208,19 @@ mod tests {
         let engine = Scheme::new_test_simple_poa().engine;
         assert!(!engine.seals_internally().unwrap());
     }
+
+    #[test]
+    fn validators_are_read_at_the_parent_of_the_block() {
+        let engine = Scheme::new_test_simple_poa().engine;
+        assert_eq!(Some(EngineError::CannotOpenBlock), engine.validators(None).err());
+
+        let client = crate::client::TestBlockChainClient::default();
+        client.add_blocks(2, 0);
+        let client: Arc<dyn ConsensusClient> = Arc::new(client);
+        engine.register_client(Arc::downgrade(&client));
+        assert_eq!(1, engine.validators(None).unwrap().unwrap().len());
+        assert_eq!(1, engine.validators(Some(2)).unwrap().unwrap().len());
+        assert_eq!(Some(EngineError::CannotOpenBlock), engine.validators(Some(0)).err());
+        assert_eq!(Some(EngineError::CannotOpenBlock), engine.validators(Some(5)).err());
+    }
 }
@@ -15,7 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use cjson;
+use cjson::uint::OverflowError;
 use ckey::Public;
+use std::convert::TryFrom;
 
 #[derive(Debug, PartialEq)]
 pub struct SimplePoAParams {
@@ -25,11 +27,13 @@ pub struct SimplePoAParams {
     pub block_reward: u64,
 }
 
-impl From<cjson::scheme::SimplePoAParams> for SimplePoAParams {
-    fn from(p: cjson::scheme::SimplePoAParams) -> Self {
-        SimplePoAParams {
+impl TryFrom<cjson::scheme::SimplePoAParams> for SimplePoAParams {
+    type Error = OverflowError;
+
+    fn try_from(p: cjson::scheme::SimplePoAParams) -> Result<Self, Self::Error> {
+        Ok(SimplePoAParams {
             validators: p.validators,
-            block_reward: p.block_reward.map_or_else(Default::default, Into::into),
-        }
+            block_reward: p.block_reward.map_or(Ok(0), u64::try_from)?,
+        })
     }
 }
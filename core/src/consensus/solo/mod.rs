@@ -100,12 +100,20 @@ impl ConsensusEngine for Solo {
         assert!(total_reward >= total_min_fee, "{} >= {}", total_reward, total_min_fee);
         let stakes = stake::get_stakes(block.state()).expect("Cannot get Stake status");
 
-        let mut distributor = stake::fee_distribute(total_min_fee, &stakes);
-        for (address, share) in &mut distributor {
-            self.machine.add_balance(block, &address, share)?
+        let shares = stake::distribute_fee(total_min_fee, &stakes, &parent_common_params);
+        for stakeholder in &shares.stakeholders {
+            self.machine.add_balance(block, &stakeholder.address, stakeholder.share)?
         }
+        let treasury = parent_common_params.fee_treasury();
+        self.machine.add_balance(block, &treasury, shares.treasury)?;
 
-        let block_author_reward = total_reward - total_min_fee + distributor.remaining_fee();
+        let block_author_reward = total_reward - total_min_fee + shares.author;
+        block.set_fee_distribution(stake::FeeDistribution {
+            author,
+            author_reward: block_author_reward,
+            treasury,
+            shares,
+        });
 
         let term_seconds = parent_common_params.term_seconds();
         if term_seconds == 0 {
@@ -181,6 +189,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn closed_block_has_fee_distribution() {
+        let scheme = Scheme::new_test_solo();
+        let client = Arc::new(TestBlockChainClient::new_with_scheme(scheme));
+        let engine = client.scheme.engine.clone();
+        engine.register_client(Arc::downgrade(&(client.clone() as Arc<dyn ConsensusClient>)));
+        let db = client.scheme.ensure_genesis_state(get_temp_state_db()).unwrap();
+        let genesis_header = client.scheme.genesis_header();
+        let b = OpenBlock::try_new(&*engine, db, &genesis_header, Default::default(), vec![]).unwrap();
+        assert_eq!(None, b.fee_distribution());
+
+        let term_common_params = CommonParams::default_for_test();
+        let b = b.close_and_lock(&genesis_header, Some(&term_common_params)).unwrap();
+        let fee_distribution = b.fee_distribution().unwrap();
+        assert_eq!(b.header().author(), &fee_distribution.author);
+        assert_eq!(0, fee_distribution.shares.total_min_fee);
+        assert_eq!(0, fee_distribution.shares.author);
+        assert_eq!(0, fee_distribution.shares.treasury);
+    }
+
     #[test]
     fn fail_to_verify() {
         let engine = Scheme::new_test_solo().engine;
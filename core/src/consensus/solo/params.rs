@@ -15,8 +15,10 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use cjson;
+use cjson::uint::OverflowError;
 use ckey::{Address, PlatformAddress};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 
 /// Params for a null engine.
 #[derive(Clone, Default)]
@@ -27,10 +29,12 @@ pub struct SoloParams {
     pub genesis_stakes: HashMap<Address, u64>,
 }
 
-impl From<cjson::scheme::SoloParams> for SoloParams {
-    fn from(p: cjson::scheme::SoloParams) -> Self {
-        SoloParams {
-            block_reward: p.block_reward.map_or_else(Default::default, Into::into),
+impl TryFrom<cjson::scheme::SoloParams> for SoloParams {
+    type Error = OverflowError;
+
+    fn try_from(p: cjson::scheme::SoloParams) -> Result<Self, Self::Error> {
+        Ok(SoloParams {
+            block_reward: p.block_reward.map_or(Ok(0), u64::try_from)?,
             enable_hit_handler: p.action_handlers.hit.is_some(),
             genesis_stakes: p
                 .action_handlers
@@ -39,6 +43,6 @@ impl From<cjson::scheme::SoloParams> for SoloParams {
                 .into_iter()
                 .map(|(pa, amount)| (PlatformAddress::into_address(pa), amount))
                 .collect(),
-        }
+        })
     }
 }
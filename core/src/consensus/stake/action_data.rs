@@ -26,7 +26,7 @@ use std::collections::btree_set::{self, BTreeSet};
 use std::collections::{btree_map, HashMap, HashSet};
 use std::mem;
 use std::ops::Deref;
-use std::vec;
+use std::{slice, vec};
 
 pub fn get_account_key(address: &Address) -> H256 {
     ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 2).append(&"Account").append(address).into_key()
@@ -42,6 +42,8 @@ lazy_static! {
         ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"Banned").into_key();
     pub static ref VALIDATORS_KEY: H256 =
         ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"Validators").into_key();
+    pub static ref CONSENSUS_KEYS_KEY: H256 =
+        ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"ConsensusKeys").into_key();
 }
 
 pub fn get_delegation_key(address: &Address) -> H256 {
@@ -259,6 +261,11 @@ impl Validator {
         }
     }
 
+    /// A validator that is not elected by staking, e.g. one in the initial validator list.
+    pub fn without_stake(pubkey: Public) -> Self {
+        Self::new(0, 0, pubkey)
+    }
+
     fn reset(&mut self) {
         self.weight = self.delegation;
     }
@@ -270,6 +277,14 @@ impl Validator {
     pub fn delegation(&self) -> StakeQuantity {
         self.delegation
     }
+
+    pub fn weight(&self) -> StakeQuantity {
+        self.weight
+    }
+
+    pub fn deposit(&self) -> Deposit {
+        self.deposit
+    }
 }
 
 #[derive(Debug)]
@@ -488,11 +503,12 @@ impl Candidates {
         delegations: &HashMap<Address, StakeQuantity>,
     ) -> StateResult<Vec<Validator>> {
         let Candidates(candidates) = Self::load_from_state(state)?;
+        let consensus_keys = ConsensusKeys::load_from_state(state)?;
         let mut result = Vec::new();
         for candidate in candidates.into_iter().filter(|c| c.deposit >= min_deposit) {
             let address = public_to_address(&candidate.pubkey);
             if let Some(delegation) = delegations.get(&address).cloned() {
-                result.push(Validator::new(delegation, candidate.deposit, candidate.pubkey));
+                result.push(Validator::new(delegation, candidate.deposit, consensus_keys.consensus_key(&candidate)));
             }
         }
         // Candidates are sorted in low priority: low index, high priority: high index
@@ -506,6 +522,10 @@ impl Candidates {
         self.0.iter().find(|c| public_to_address(&c.pubkey) == *account)
     }
 
+    pub fn iter(&self) -> slice::Iter<Candidate> {
+        self.0.iter()
+    }
+
     #[cfg(test)]
     pub fn len(&self) -> usize {
         self.0.len()
@@ -544,20 +564,23 @@ impl Candidates {
         nomination_ends_at: u64,
         inactive_validators: &[Address],
         banned: &Banned,
+        consensus_keys: &ConsensusKeys,
     ) {
         let to_renew: HashSet<_> = (validators.iter())
             .map(|validator| validator.pubkey)
             .filter(|pubkey| !inactive_validators.contains(&public_to_address(pubkey)))
             .collect();
 
-        for candidate in self.0.iter_mut().filter(|c| to_renew.contains(&c.pubkey)) {
+        for candidate in self.0.iter_mut().filter(|c| to_renew.contains(&consensus_keys.consensus_key(c))) {
             let address = public_to_address(&candidate.pubkey);
             assert!(!banned.is_banned(&address), "{} is banned address", address);
             candidate.nomination_ends_at = nomination_ends_at;
         }
 
-        let to_reprioritize: Vec<_> =
-            self.0.iter().filter(|c| to_renew.contains(&c.pubkey)).map(|c| public_to_address(&c.pubkey)).collect();
+        let to_reprioritize: Vec<_> = (self.0.iter())
+            .filter(|c| to_renew.contains(&consensus_keys.consensus_key(c)))
+            .map(|c| public_to_address(&c.pubkey))
+            .collect();
 
         self.reprioritize(&to_reprioritize);
     }
@@ -627,6 +650,10 @@ impl Jail {
         self.0.get(address)
     }
 
+    pub fn iter(&self) -> btree_map::Values<Address, Prisoner> {
+        self.0.values()
+    }
+
     #[cfg(test)]
     pub fn len(&self) -> usize {
         self.0.len()
@@ -671,6 +698,7 @@ impl Jail {
     }
 }
 
+#[derive(Default)]
 pub struct Banned(BTreeSet<Address>);
 impl Banned {
     pub fn load_from_state(state: &TopLevelState) -> StateResult<Banned> {
@@ -699,6 +727,82 @@ impl Banned {
     }
 }
 
+/// The consensus keys of the candidates that changed them from the keys of their accounts.
+/// A changed key is pending until the current term closes, so the validators of a term sign with the same keys.
+#[derive(Default, Debug, PartialEq)]
+pub struct ConsensusKeys {
+    current: BTreeMap<Address, Public>,
+    pending: BTreeMap<Address, Public>,
+}
+
+impl ConsensusKeys {
+    pub fn load_from_state(state: &TopLevelState) -> StateResult<Self> {
+        let key = *CONSENSUS_KEYS_KEY;
+        let action_data = state.action_data(&key)?;
+        let (current, pending) = decode_map_tuple(action_data.as_ref());
+
+        Ok(Self {
+            current,
+            pending,
+        })
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> StateResult<()> {
+        let key = *CONSENSUS_KEYS_KEY;
+        if self.current.is_empty() && self.pending.is_empty() {
+            state.remove_action_data(&key);
+        } else {
+            let encoded = encode_map_tuple(&self.current, &self.pending);
+            state.update_action_data(&key, encoded)?;
+        }
+        Ok(())
+    }
+
+    /// The key that the candidate signs the consensus messages with.
+    pub fn consensus_key(&self, candidate: &Candidate) -> Public {
+        let account = public_to_address(&candidate.pubkey);
+        self.current.get(&account).cloned().unwrap_or(candidate.pubkey)
+    }
+
+    /// The account of the validator that signs with the key of the given address.
+    pub fn account_of(&self, consensus_address: &Address) -> Address {
+        self.current
+            .iter()
+            .find(|(_, pubkey)| public_to_address(pubkey) == *consensus_address)
+            .map(|(account, _)| *account)
+            .unwrap_or(*consensus_address)
+    }
+
+    /// The address of the key that the account's validator signs with.
+    pub fn consensus_address(&self, account: &Address) -> Address {
+        self.current.get(account).map(public_to_address).unwrap_or(*account)
+    }
+
+    /// The account that uses the key as its current or pending consensus key.
+    pub fn owner(&self, pubkey: &Public) -> Option<Address> {
+        (self.current.iter()).chain(self.pending.iter()).find(|(_, used)| *used == pubkey).map(|(account, _)| *account)
+    }
+
+    pub fn has_pending(&self, account: &Address) -> bool {
+        self.pending.contains_key(account)
+    }
+
+    pub fn add_pending(&mut self, account: Address, pubkey: Public) {
+        let previous = self.pending.insert(account, pubkey);
+        assert_eq!(None, previous, "The consensus key can be changed once in a term");
+    }
+
+    /// Makes the pending keys current. It's called when a term closes.
+    pub fn apply_pending(&mut self) {
+        self.current.append(&mut self.pending);
+    }
+
+    pub fn remove(&mut self, account: &Address) {
+        self.current.remove(account);
+        self.pending.remove(account);
+    }
+}
+
 fn decode_set<V>(data: Option<&ActionData>) -> BTreeSet<V>
 where
     V: Ord + Decodable, {
@@ -1767,7 +1871,7 @@ mod tests {
                 .collect(),
         );
         let dummy_banned = Banned::load_from_state(&state).unwrap();
-        candidates.renew_candidates(&dummy_validators, 0, &[], &dummy_banned);
+        candidates.renew_candidates(&dummy_validators, 0, &[], &dummy_banned, &ConsensusKeys::default());
 
         let indexes: Vec<_> =
             pubkeys.iter().map(|pubkey| candidates.get_index(&public_to_address(pubkey)).unwrap()).collect();
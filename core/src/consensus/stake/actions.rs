@@ -17,9 +17,9 @@
 use crate::client::ConsensusClient;
 use crate::consensus::{ConsensusMessage, ValidatorSet};
 use ccrypto::Blake;
-use ckey::{recover, Address, Signature};
+use ckey::{recover, Address, Public, Signature};
 use ctypes::errors::SyntaxError;
-use ctypes::CommonParams;
+use ctypes::{CommonParams, CHANGE_CONSENSUS_KEY_ERA};
 use primitives::{Bytes, H256};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use std::sync::Arc;
@@ -33,6 +33,7 @@ enum ActionTag {
     SelfNominate = 4,
     ReportDoubleVote = 5,
     Redelegate = 6,
+    ChangeConsensusKey = 7,
     ChangeParams = 0xFF,
 }
 
@@ -52,6 +53,7 @@ impl Decodable for ActionTag {
             4 => Ok(ActionTag::SelfNominate),
             5 => Ok(ActionTag::ReportDoubleVote),
             6 => Ok(ActionTag::Redelegate),
+            7 => Ok(ActionTag::ChangeConsensusKey),
             0xFF => Ok(ActionTag::ChangeParams),
             _ => Err(DecoderError::Custom("Unexpected ActionTag Value")),
         }
@@ -81,6 +83,10 @@ pub enum Action {
         deposit: u64,
         metadata: Bytes,
     },
+    /// Changes the key that the candidate signs the consensus messages with from the next term.
+    ChangeConsensusKey {
+        new_pubkey: Public,
+    },
     ChangeParams {
         metadata_seq: u64,
         params: Box<CommonParams>,
@@ -124,6 +130,16 @@ impl Action {
                     )))
                 }
             }
+            Action::ChangeConsensusKey {
+                ..
+            } => {
+                if current_params.era() < CHANGE_CONSENSUS_KEY_ERA {
+                    return Err(SyntaxError::InvalidCustomAction(format!(
+                        "The consensus key can be changed from the era {}",
+                        CHANGE_CONSENSUS_KEY_ERA
+                    )))
+                }
+            }
             Action::ChangeParams {
                 metadata_seq,
                 params,
@@ -238,6 +254,11 @@ impl Encodable for Action {
             } => {
                 s.begin_list(3).append(&ActionTag::SelfNominate).append(deposit).append(metadata);
             }
+            Action::ChangeConsensusKey {
+                new_pubkey,
+            } => {
+                s.begin_list(2).append(&ActionTag::ChangeConsensusKey).append(new_pubkey);
+            }
             Action::ChangeParams {
                 metadata_seq,
                 params,
@@ -334,6 +355,18 @@ impl Decodable for Action {
                     metadata: rlp.val_at(2)?,
                 })
             }
+            ActionTag::ChangeConsensusKey => {
+                let item_count = rlp.item_count()?;
+                if item_count != 2 {
+                    return Err(DecoderError::RlpInvalidLength {
+                        expected: 2,
+                        got: item_count,
+                    })
+                }
+                Ok(Action::ChangeConsensusKey {
+                    new_pubkey: rlp.val_at(1)?,
+                })
+            }
             ActionTag::ChangeParams => {
                 let item_count = rlp.item_count()?;
                 if item_count < 4 {
@@ -373,7 +406,7 @@ impl Decodable for Action {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::client::TestBlockChainClient;
+    use crate::client::{BlockChainTrait, TestBlockChainClient};
     use crate::consensus::{ConsensusMessage, DynamicValidator, Step, VoteOn, VoteStep};
     use ckey::sign_schnorr;
     use ctypes::BlockHash;
@@ -395,6 +428,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn rlp_of_change_consensus_key() {
+        rlp_encode_and_decode_test!(Action::ChangeConsensusKey {
+            new_pubkey: Public::random(),
+        });
+    }
+
+    #[test]
+    fn change_consensus_key_is_rejected_before_its_era() {
+        let action = Action::ChangeConsensusKey {
+            new_pubkey: Public::random(),
+        };
+        let mut params = CommonParams::default_for_test();
+        params.set_era_for_test(CHANGE_CONSENSUS_KEY_ERA - 1);
+        assert!(action.verify(&params, None, None).is_err());
+
+        params.set_era_for_test(CHANGE_CONSENSUS_KEY_ERA);
+        assert_eq!(Ok(()), action.verify(&params, None, None));
+    }
+
     #[test]
     fn rlp_of_change_params() {
         rlp_encode_and_decode_test!(Action::ChangeParams {
@@ -460,6 +513,12 @@ mod tests {
         G: Fn(Option<BlockHash>) -> Option<BlockHash>, {
         let mut test_client = TestBlockChainClient::default();
         test_client.add_blocks(10, 1);
+        // The reported heights must be looked up on the branch that won the reorg.
+        let mut best = test_client.block_header(&1u64.into()).unwrap().hash();
+        for _ in 0..10 {
+            best = test_client.add_block_on(best, Some(Address::random()), vec![]);
+        }
+        test_client.set_best_block(best);
         test_client.set_random_validators(10);
         let validator_set =
             DynamicValidator::new(test_client.get_validators().iter().map(|val| *val.pubkey()).collect());
@@ -0,0 +1,252 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::action_data::{Candidates, Delegation, Jail, StakeAccount, Stakeholders};
+use ckey::Address;
+use cstate::{StateError, TopLevelState};
+use std::fmt;
+
+/// The stake tokens and the deposits that the stake action data of a state holds.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CcsAudit {
+    /// The sum of the balances of the stakeholders.
+    pub balances: u64,
+    /// The sum of the quantities that the stakeholders delegated.
+    pub delegations: u64,
+    /// The sum of the deposits of the candidates.
+    pub candidate_deposits: u64,
+    /// The sum of the deposits of the prisoners.
+    pub prisoner_deposits: u64,
+}
+
+impl CcsAudit {
+    /// The CCS that the stakeholders own. Delegating and revoking only move CCS between a balance and a delegation,
+    /// so it never changes after the genesis.
+    /// The deposits are CCC taken from the balances of the candidates, so they are not a part of it.
+    pub fn total_ccs(&self) -> u64 {
+        self.balances + self.delegations
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuditError {
+    State(StateError),
+    /// The sum of the named quantities doesn't fit in u64.
+    Overflow(&'static str),
+    /// The stakeholder has neither a balance nor a delegation, so it should have been removed from the list.
+    EmptyStakeholder(Address),
+    /// The total CCS of the later state differs from the one of the earlier state.
+    NotConserved {
+        before: CcsAudit,
+        after: CcsAudit,
+    },
+}
+
+impl From<StateError> for AuditError {
+    fn from(err: StateError) -> Self {
+        AuditError::State(err)
+    }
+}
+
+impl fmt::Display for AuditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuditError::State(err) => write!(f, "Cannot read the stake action data: {}", err),
+            AuditError::Overflow(quantities) => write!(f, "The sum of the {} overflows", quantities),
+            AuditError::EmptyStakeholder(address) => {
+                write!(f, "The stakeholder {} has neither a balance nor a delegation", address)
+            }
+            AuditError::NotConserved {
+                before,
+                after,
+            } => write!(f, "The total CCS changed from {:?} to {:?}", before, after),
+        }
+    }
+}
+
+fn add(sum: u64, quantity: u64, quantities: &'static str) -> Result<u64, AuditError> {
+    sum.checked_add(quantity).ok_or(AuditError::Overflow(quantities))
+}
+
+/// Sums the balances and the delegations of every stakeholder, and the deposits of the candidates and the prisoners.
+pub fn audit_ccs(state: &TopLevelState) -> Result<CcsAudit, AuditError> {
+    let mut audit = CcsAudit::default();
+
+    let stakeholders = Stakeholders::load_from_state(state)?;
+    for stakeholder in stakeholders.iter() {
+        let balance = StakeAccount::load_from_state(state, stakeholder)?.balance;
+        let delegation = Delegation::load_from_state(state, stakeholder)?;
+        let delegated = delegation.iter().try_fold(0, |sum, (_, quantity)| add(sum, *quantity, "delegations"))?;
+        if balance == 0 && delegated == 0 {
+            return Err(AuditError::EmptyStakeholder(*stakeholder))
+        }
+        audit.balances = add(audit.balances, balance, "balances")?;
+        audit.delegations = add(audit.delegations, delegated, "delegations")?;
+    }
+    add(audit.balances, audit.delegations, "balances and delegations")?;
+
+    for candidate in Candidates::load_from_state(state)?.iter() {
+        audit.candidate_deposits = add(audit.candidate_deposits, candidate.deposit, "candidate deposits")?;
+    }
+    for prisoner in Jail::load_from_state(state)?.iter() {
+        audit.prisoner_deposits = add(audit.prisoner_deposits, prisoner.deposit, "prisoner deposits")?;
+    }
+    Ok(audit)
+}
+
+/// Audits both states and checks that no CCS was created or lost between them.
+/// Returns the audit of the later state.
+pub fn audit_ccs_conservation(before: &TopLevelState, after: &TopLevelState) -> Result<CcsAudit, AuditError> {
+    let before = audit_ccs(before)?;
+    let after = audit_ccs(after)?;
+    if before.total_ccs() != after.total_ccs() {
+        return Err(AuditError::NotConserved {
+            before,
+            after,
+        })
+    }
+    Ok(after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{delegate_ccs, jail, revoke, self_nominate, transfer_ccs, Stake};
+    use super::*;
+    use ckey::{public_to_address, Public};
+    use cstate::tests::helpers;
+    use cstate::{ActionHandler, TopState};
+    use std::collections::HashMap;
+
+    struct Fixture {
+        state: TopLevelState,
+        delegator: Address,
+        candidate: Address,
+        prisoner: Address,
+    }
+
+    // The delegator delegates 40 of 100 CCS to the candidate, which deposits 30 CCC.
+    // The prisoner deposited 50 CCC before it is jailed.
+    fn fixture() -> Fixture {
+        let delegator_pubkey = Public::random();
+        let candidate_pubkey = Public::random();
+        let prisoner_pubkey = Public::random();
+        let delegator = public_to_address(&delegator_pubkey);
+        let candidate = public_to_address(&candidate_pubkey);
+        let prisoner = public_to_address(&prisoner_pubkey);
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator, 100);
+            genesis_stakes.insert(candidate, 100);
+            genesis_stakes.insert(prisoner, 20);
+            Stake::new(genesis_stakes)
+        };
+        stake.init(&mut state).unwrap();
+        state.add_balance(&candidate, 1000).unwrap();
+        state.add_balance(&prisoner, 1000).unwrap();
+        self_nominate(&mut state, &candidate, &candidate_pubkey, 30, 0, 10, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &prisoner, &prisoner_pubkey, 50, 0, 10, b"".to_vec()).unwrap();
+        delegate_ccs(&mut state, &delegator, &candidate, 40).unwrap();
+        jail(&mut state, &[prisoner], 2, 3).unwrap();
+
+        Fixture {
+            state,
+            delegator,
+            candidate,
+            prisoner,
+        }
+    }
+
+    #[test]
+    fn audit_sums_every_kind_of_stake_data() {
+        let Fixture {
+            state,
+            ..
+        } = fixture();
+        assert_eq!(
+            Ok(CcsAudit {
+                balances: 180,
+                delegations: 40,
+                candidate_deposits: 30,
+                prisoner_deposits: 50,
+            }),
+            audit_ccs(&state)
+        );
+    }
+
+    #[test]
+    fn transfer_and_revoke_conserve_the_ccs() {
+        let Fixture {
+            mut state,
+            delegator,
+            candidate,
+            prisoner,
+        } = fixture();
+        let before = state.clone();
+        transfer_ccs(&mut state, &candidate, &prisoner, 10).unwrap();
+        revoke(&mut state, &delegator, &candidate, 15).unwrap();
+
+        let audit = audit_ccs_conservation(&before, &state).unwrap();
+        assert_eq!(180 + 15, audit.balances);
+        assert_eq!(40 - 15, audit.delegations);
+    }
+
+    #[test]
+    fn corrupted_balance_is_caught() {
+        let Fixture {
+            mut state,
+            delegator,
+            ..
+        } = fixture();
+        let before = state.clone();
+        StakeAccount {
+            address: &delegator,
+            balance: 61,
+        }
+        .save_to_state(&mut state)
+        .unwrap();
+
+        let before_audit = audit_ccs(&before).unwrap();
+        assert_eq!(
+            Err(AuditError::NotConserved {
+                before: before_audit,
+                after: CcsAudit {
+                    balances: 181,
+                    ..before_audit
+                },
+            }),
+            audit_ccs_conservation(&before, &state)
+        );
+    }
+
+    #[test]
+    fn emptied_stakeholder_is_caught() {
+        let Fixture {
+            mut state,
+            prisoner,
+            ..
+        } = fixture();
+        StakeAccount {
+            address: &prisoner,
+            balance: 0,
+        }
+        .save_to_state(&mut state)
+        .unwrap();
+
+        assert_eq!(Err(AuditError::EmptyStakeholder(prisoner)), audit_ccs(&state));
+    }
+}
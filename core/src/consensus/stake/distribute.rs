@@ -15,29 +15,107 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use ckey::Address;
+use ctypes::{CommonParams, FEE_SHARE_BASIS_POINTS};
 use std::collections::hash_map;
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::hash::BuildHasher;
 
-pub fn fee_distribute<S: ::std::hash::BuildHasher>(
+/// The shares of the minimum fees of a block.
+/// They always add up to `total_min_fee`.
+#[derive(Clone, Debug, Default, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct FeeShares {
+    pub total_min_fee: u64,
+    pub author: u64,
+    pub treasury: u64,
+    /// Sorted by the address. The stakeholders who get nothing are omitted.
+    pub stakeholders: Vec<StakeholderShare>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct StakeholderShare {
+    pub address: Address,
+    pub share: u64,
+}
+
+/// Who received how much when a block was closed.
+#[derive(Clone, Debug, Default, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct FeeDistribution {
+    pub author: Address,
+    /// The block reward and the fees above the minimum, in addition to the author share of the minimum fees.
+    /// It's paid at the end of the term if the term has started.
+    pub author_reward: u64,
+    pub treasury: Address,
+    pub shares: FeeShares,
+}
+
+/// Splits the minimum fees by the shares given in the params.
+/// The author and the treasury take their shares first, and the stakeholders split the rest by their stakes.
+/// Every share is rounded down, and the remainder goes to the author or the treasury as the params say.
+pub fn distribute_fee<S: BuildHasher>(
     total_min_fee: u64,
     stakes: &HashMap<Address, u64, S>,
-) -> FeeDistributeIter {
+    params: &CommonParams,
+) -> FeeShares {
+    let author_share = portion(total_min_fee, params.fee_author_share());
+    let treasury_share = portion(total_min_fee, params.fee_treasury_share());
+    let stakeholders_fee = author_share
+        .checked_add(treasury_share)
+        .and_then(|shares| total_min_fee.checked_sub(shares))
+        .expect("The sum of the author share and the treasury share is verified not to exceed the whole");
+
+    let mut distributor = fee_distribute(stakeholders_fee, stakes);
+    let mut stakeholders: Vec<_> = (&mut distributor)
+        .filter(|(_, share)| *share != 0)
+        .map(|(address, share)| StakeholderShare {
+            address: *address,
+            share,
+        })
+        .collect();
+    stakeholders.sort_unstable_by_key(|stakeholder| stakeholder.address);
+
+    let remaining_fee = distributor.remaining_fee();
+    let (author, treasury) = if params.fee_remainder_to_treasury() {
+        (author_share, treasury_share + remaining_fee)
+    } else {
+        (author_share + remaining_fee, treasury_share)
+    };
+    FeeShares {
+        total_min_fee,
+        author,
+        treasury,
+        stakeholders,
+    }
+}
+
+fn portion(fee: u64, basis_points: u64) -> u64 {
+    fraction(fee, basis_points, u128::from(FEE_SHARE_BASIS_POINTS))
+}
+
+/// Returns `value * numerator / denominator` rounded down.
+/// It is computed in u128, so the product doesn't overflow.
+fn fraction(value: u64, numerator: u64, denominator: u128) -> u64 {
+    assert!(u128::from(numerator) <= denominator, "{} / {}", numerator, denominator);
+    u64::try_from(u128::from(value) * u128::from(numerator) / denominator)
+        .expect("The fraction of a value is not larger than the value")
+}
+
+pub fn fee_distribute<S: BuildHasher>(total_min_fee: u64, stakes: &HashMap<Address, u64, S>) -> FeeDistributeIter {
     FeeDistributeIter {
-        total_stakes: stakes.values().sum(),
+        // The sum of the stakes can exceed u64 even though every stake fits in it.
+        total_stakes: stakes.values().map(|stake| u128::from(*stake)).sum(),
         total_min_fee,
         remaining_fee: total_min_fee,
         stake_holdings: stakes.iter(),
     }
 }
 
-fn share(total_stakes: u64, stake: u64, total_min_fee: u64) -> u64 {
-    assert!(total_stakes >= stake);
-    u64::try_from((u128::from(total_min_fee) * u128::from(stake)) / u128::from(total_stakes)).unwrap()
+fn share(total_stakes: u128, stake: u64, total_min_fee: u64) -> u64 {
+    fraction(total_min_fee, stake, total_stakes)
 }
 
 pub struct FeeDistributeIter<'a> {
-    total_stakes: u64,
+    total_stakes: u128,
     total_min_fee: u64,
     remaining_fee: u64,
     stake_holdings: hash_map::Iter<'a, Address, u64>,
@@ -65,6 +143,19 @@ impl<'a> Iterator for FeeDistributeIter<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+    use rlp::rlp_encode_and_decode_test;
+
+    fn rng() -> XorShiftRng {
+        let seed: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 0, 1, 2, 3, 4, 5, 6, 7];
+        XorShiftRng::from_seed(seed)
+    }
+
+    fn sum_of(shares: &FeeShares) -> u128 {
+        let stakeholders: u128 = shares.stakeholders.iter().map(|stakeholder| u128::from(stakeholder.share)).sum();
+        u128::from(shares.author) + u128::from(shares.treasury) + stakeholders
+    }
 
     #[test]
     fn distribute_even() {
@@ -83,6 +174,25 @@ mod tests {
         });
     }
 
+    #[test]
+    fn stakes_whose_sum_exceeds_u64_are_distributed() {
+        let address1 = Address::random();
+        let address2 = Address::random();
+        let mut stakes = HashMap::new();
+        stakes.insert(address1, u64::max_value());
+        stakes.insert(address2, u64::max_value());
+
+        let mut iter = fee_distribute(u64::max_value(), &stakes);
+        let shares: HashMap<Address, u64> = (&mut iter).map(|(k, v)| (*k, v)).collect();
+        assert_eq!(shares, {
+            let mut expected = HashMap::with_capacity(stakes.len());
+            expected.insert(address1, u64::max_value() / 2);
+            expected.insert(address2, u64::max_value() / 2);
+            expected
+        });
+        assert_eq!(1, iter.remaining_fee());
+    }
+
     #[test]
     fn distribute_and_changes() {
         let addresses: Vec<_> = (0..51).map(|_| Address::random()).collect();
@@ -106,4 +216,119 @@ mod tests {
             expected
         });
     }
+
+    #[test]
+    fn default_params_give_the_remainder_to_the_author() {
+        let addresses: Vec<_> = (0..51).map(|_| Address::random()).collect();
+        let mut stakes = HashMap::with_capacity(addresses.len());
+        for address in &addresses {
+            stakes.insert(*address, 10);
+        }
+
+        let shares = distribute_fee(100, &stakes, &CommonParams::default_for_test());
+        assert_eq!(49, shares.author);
+        assert_eq!(0, shares.treasury);
+        assert_eq!(51, shares.stakeholders.len());
+        assert!(shares.stakeholders.iter().all(|stakeholder| stakeholder.share == 1));
+    }
+
+    #[test]
+    fn distribute_with_author_and_treasury_shares() {
+        let address1 = Address::random();
+        let address2 = Address::random();
+        let mut stakes = HashMap::new();
+        stakes.insert(address1, 10);
+        stakes.insert(address2, 20);
+
+        let mut params = CommonParams::default_for_test();
+        params.set_fee_distribution_params_for_test(1_000, 2_500, Address::random(), true);
+        let shares = distribute_fee(1_001, &stakes, &params);
+        assert_eq!(100, shares.author);
+        // 1001 * 0.25 = 250, and 1001 - 100 - 250 = 651 is split into 217 and 434 without the remainder.
+        assert_eq!(250, shares.treasury);
+        let mut expected = vec![
+            StakeholderShare {
+                address: address1,
+                share: 217,
+            },
+            StakeholderShare {
+                address: address2,
+                share: 434,
+            },
+        ];
+        expected.sort_unstable_by_key(|stakeholder| stakeholder.address);
+        assert_eq!(expected, shares.stakeholders);
+        assert_eq!(1_001, sum_of(&shares));
+
+        params.set_fee_distribution_params_for_test(1_000, 2_500, Address::random(), false);
+        let shares = distribute_fee(1_000, &stakes, &params);
+        assert_eq!(100 + 1, shares.author);
+        assert_eq!(250, shares.treasury);
+    }
+
+    #[test]
+    fn stakeholders_share_nothing_if_the_author_and_the_treasury_take_the_whole() {
+        let mut stakes = HashMap::new();
+        stakes.insert(Address::random(), 10);
+
+        let mut params = CommonParams::default_for_test();
+        params.set_fee_distribution_params_for_test(3_333, 6_667, Address::random(), true);
+        let shares = distribute_fee(1_000_000, &stakes, &params);
+        assert_eq!(333_300, shares.author);
+        assert_eq!(666_700, shares.treasury);
+        assert_eq!(Vec::<StakeholderShare>::new(), shares.stakeholders);
+    }
+
+    #[test]
+    fn shares_always_add_up_to_the_fee() {
+        let mut rng = rng();
+        for _ in 0..1_000 {
+            let total_min_fee = match rng.gen_range(0, 3) {
+                0 => rng.gen_range(0, 1_000),
+                1 => rng.gen(),
+                _ => u64::max_value() - rng.gen_range(0, 1_000),
+            };
+            let number_of_stakeholders = rng.gen_range(0, 30);
+            let stakes: HashMap<Address, u64> = (0..number_of_stakeholders)
+                .map(|_| (Address::random(), rng.gen_range(1, u64::max_value() / 32)))
+                .collect();
+            let author_share = rng.gen_range(0, FEE_SHARE_BASIS_POINTS + 1);
+            let treasury_share = rng.gen_range(0, FEE_SHARE_BASIS_POINTS - author_share + 1);
+            let mut params = CommonParams::default_for_test();
+            params.set_fee_distribution_params_for_test(author_share, treasury_share, Address::random(), rng.gen());
+            assert_eq!(Ok(()), params.verify());
+
+            let shares = distribute_fee(total_min_fee, &stakes, &params);
+            assert_eq!(u128::from(total_min_fee), sum_of(&shares), "{:?} {:?}", stakes, params);
+            assert!(shares.author >= portion(total_min_fee, author_share));
+            assert!(shares.treasury >= portion(total_min_fee, treasury_share));
+            assert!(shares.stakeholders.windows(2).all(|pair| pair[0].address < pair[1].address));
+        }
+    }
+
+    #[test]
+    fn distribution_does_not_depend_on_the_order_of_the_stakes() {
+        let mut rng = rng();
+        for _ in 0..100 {
+            let total_min_fee = rng.gen();
+            let holdings: Vec<(Address, u64)> =
+                (0..rng.gen_range(1, 30)).map(|_| (Address::random(), rng.gen_range(1, 1_000_000))).collect();
+            let mut params = CommonParams::default_for_test();
+            params.set_fee_distribution_params_for_test(rng.gen_range(0, 5_000), 1, Address::random(), rng.gen());
+
+            let forward: HashMap<_, _> = holdings.iter().cloned().collect();
+            let mut backward = HashMap::with_capacity(holdings.len());
+            for (address, stake) in holdings.iter().rev() {
+                backward.insert(*address, *stake);
+            }
+            let shares = distribute_fee(total_min_fee, &forward, &params);
+            assert_eq!(shares, distribute_fee(total_min_fee, &backward, &params));
+            rlp_encode_and_decode_test!(FeeDistribution {
+                author: Address::random(),
+                author_reward: rng.gen(),
+                treasury: params.fee_treasury(),
+                shares,
+            });
+        }
+    }
 }
@@ -0,0 +1,193 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::consensus::EngineError;
+use ckey::Address;
+use ctypes::util::unexpected::OutOfBounds;
+use std::collections::btree_map::{BTreeMap, Entry};
+
+/// The precommits of a validator counted over a term.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ValidatorLiveness {
+    pub address: Address,
+    /// The number of the counted blocks in which the validator was in the validator set.
+    /// A validator who joined in the middle of the term is expected to sign only the blocks after it joined.
+    pub expected: u64,
+    /// The number of the precommits of the validator included in the seals.
+    pub signed: u64,
+}
+
+impl ValidatorLiveness {
+    pub fn missed(&self) -> u64 {
+        self.expected - self.signed
+    }
+
+    /// Whether the validator missed more than `max_missed_percentage` percent of the precommits it was expected to sign.
+    /// A validator who was not expected to sign any block is never inactive.
+    pub fn is_inactive(&self, max_missed_percentage: u64) -> bool {
+        self.missed() * 100 > max_missed_percentage * self.expected
+    }
+}
+
+/// The running tally of the precommits of the validators.
+#[derive(Clone, Debug, Default)]
+pub struct Liveness {
+    tally: BTreeMap<Address, ValidatorLiveness>,
+}
+
+impl Liveness {
+    /// Counts the precommits on a block.
+    /// `validators` is the validator set of the block, and `signers` are the indices of the validators who signed it.
+    /// Nothing is counted if a signer is not in the validator set.
+    pub fn record(
+        &mut self,
+        validators: &[Address],
+        signers: impl IntoIterator<Item = usize>,
+    ) -> Result<(), EngineError> {
+        let signers = signers
+            .into_iter()
+            .map(|index| {
+                validators.get(index).ok_or_else(|| {
+                    EngineError::BadSealFieldSize(OutOfBounds {
+                        min: None,
+                        max: Some(validators.len()),
+                        found: index + 1,
+                    })
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for address in validators {
+            self.entry(*address).expected += 1;
+        }
+        for address in signers {
+            self.entry(*address).signed += 1;
+        }
+        Ok(())
+    }
+
+    /// Adds the tally of the later blocks.
+    pub fn merge(&mut self, other: Liveness) {
+        for (address, liveness) in other.tally {
+            let entry = self.entry(address);
+            entry.expected += liveness.expected;
+            entry.signed += liveness.signed;
+        }
+    }
+
+    fn entry(&mut self, address: Address) -> &mut ValidatorLiveness {
+        match self.tally.entry(address) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(ValidatorLiveness {
+                address,
+                expected: 0,
+                signed: 0,
+            }),
+        }
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&ValidatorLiveness> {
+        self.tally.get(address)
+    }
+
+    /// The tallies sorted by the address.
+    pub fn to_vec(&self) -> Vec<ValidatorLiveness> {
+        self.tally.values().cloned().collect()
+    }
+
+    /// The validators who missed more than `max_missed_percentage` percent of their precommits.
+    pub fn inactive_validators(
+        &self,
+        validators: impl IntoIterator<Item = Address>,
+        max_missed_percentage: u64,
+    ) -> Vec<Address> {
+        validators
+            .into_iter()
+            .filter(|address| self.get(address).map_or(false, |liveness| liveness.is_inactive(max_missed_percentage)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validator_who_joined_in_the_middle_is_prorated() {
+        let (a, b, c) = (Address::random(), Address::random(), Address::random());
+        let mut liveness = Liveness::default();
+        for _ in 0..6 {
+            liveness.record(&[a, b], vec![0]).unwrap();
+        }
+        for _ in 0..4 {
+            liveness.record(&[a, b, c], vec![0, 2]).unwrap();
+        }
+
+        assert_eq!(Some(10), liveness.get(&a).map(|liveness| liveness.signed));
+        assert_eq!(Some(0), liveness.get(&b).map(|liveness| liveness.signed));
+        assert_eq!(
+            Some(&ValidatorLiveness {
+                address: c,
+                expected: 4,
+                signed: 4,
+            }),
+            liveness.get(&c)
+        );
+        assert_eq!(vec![b], liveness.inactive_validators(vec![a, b, c], 50));
+    }
+
+    #[test]
+    fn signer_out_of_the_validator_set_is_rejected() {
+        let (a, b) = (Address::random(), Address::random());
+        let mut liveness = Liveness::default();
+        liveness.record(&[a, b], vec![0, 1]).unwrap();
+
+        assert!(liveness.record(&[a, b], vec![0, 2]).is_err());
+        assert_eq!(Some((1, 1)), liveness.get(&a).map(|liveness| (liveness.expected, liveness.signed)));
+        assert_eq!(Some((1, 1)), liveness.get(&b).map(|liveness| (liveness.expected, liveness.signed)));
+    }
+
+    #[test]
+    fn merged_tallies_are_added() {
+        let (a, b, c) = (Address::random(), Address::random(), Address::random());
+        let mut earlier = Liveness::default();
+        earlier.record(&[a, b], vec![0]).unwrap();
+        let mut later = Liveness::default();
+        later.record(&[a, b, c], vec![1, 2]).unwrap();
+
+        earlier.merge(later);
+        let tally = |address| earlier.get(&address).map(|liveness| (liveness.expected, liveness.signed));
+        assert_eq!(Some((2, 1)), tally(a));
+        assert_eq!(Some((2, 1)), tally(b));
+        assert_eq!(Some((1, 1)), tally(c));
+    }
+
+    #[test]
+    fn threshold_is_exclusive() {
+        let liveness = ValidatorLiveness {
+            address: Address::random(),
+            expected: 10,
+            signed: 7,
+        };
+        assert!(!liveness.is_inactive(30));
+        assert!(liveness.is_inactive(29));
+        assert!(!ValidatorLiveness {
+            expected: 0,
+            signed: 0,
+            ..liveness
+        }
+        .is_inactive(0));
+    }
+}
@@ -16,12 +16,14 @@
 
 mod action_data;
 mod actions;
+mod audit;
 mod distribute;
+mod liveness;
 
 use crate::client::ConsensusClient;
 use ccrypto::Blake;
 use ckey::{public_to_address, recover, Address, Public, Signature};
-use cstate::{ActionHandler, StateResult, TopLevelState, TopState, TopStateView};
+use cstate::{ActionHandler, CheckpointId, StateResult, StateWithCheckpoint, TopLevelState, TopState, TopStateView};
 use ctypes::errors::{RuntimeError, SyntaxError};
 use ctypes::util::unexpected::Mismatch;
 use ctypes::{CommonParams, Header};
@@ -32,14 +34,22 @@ use std::collections::btree_map::BTreeMap;
 use std::collections::HashMap;
 use std::sync::{Arc, Weak};
 
-pub use self::action_data::{Banned, Candidates, Jail, Validator, Validators};
-use self::action_data::{Delegation, IntermediateRewards, ReleaseResult, StakeAccount, Stakeholders};
+pub use self::action_data::{
+    Banned, Candidate, Candidates, ConsensusKeys, Delegation, Jail, Prisoner, StakeAccount, Stakeholders, Validator,
+    Validators,
+};
+use self::action_data::{IntermediateRewards, ReleaseResult};
 pub use self::actions::Action;
-pub use self::distribute::fee_distribute;
+pub use self::audit::{audit_ccs, audit_ccs_conservation, AuditError, CcsAudit};
+pub use self::distribute::{distribute_fee, FeeDistribution, FeeShares, StakeholderShare};
+pub use self::liveness::{Liveness, ValidatorLiveness};
 use super::ValidatorSet;
 
 pub const CUSTOM_ACTION_HANDLER_ID: u64 = 2;
 
+/// The changes of a failed stake action are reverted even if the caller doesn't isolate the action.
+const STAKE_ACTION_CHECKPOINT: CheckpointId = 200;
+
 pub struct Stake {
     genesis_stakes: HashMap<Address, u64>,
     client: RwLock<Option<Weak<dyn ConsensusClient>>>,
@@ -58,39 +68,14 @@ impl Stake {
         *self.client.write() = Some(Weak::clone(&client));
         *self.validators.write() = Some(Weak::clone(&validators));
     }
-}
-
-impl ActionHandler for Stake {
-    fn name(&self) -> &'static str {
-        "stake handler"
-    }
-
-    fn handler_id(&self) -> u64 {
-        CUSTOM_ACTION_HANDLER_ID
-    }
 
-    fn init(&self, state: &mut TopLevelState) -> StateResult<()> {
-        let mut stakeholders = Stakeholders::load_from_state(state)?;
-        for (address, amount) in self.genesis_stakes.iter() {
-            let account = StakeAccount {
-                address,
-                balance: *amount,
-            };
-            account.save_to_state(state)?;
-            stakeholders.update_by_increased_balance(&account);
-        }
-        stakeholders.save_to_state(state)?;
-        Ok(())
-    }
-
-    fn execute(
+    fn execute_action(
         &self,
-        bytes: &[u8],
+        action: Action,
         state: &mut TopLevelState,
         fee_payer: &Address,
         sender_public: &Public,
     ) -> StateResult<()> {
-        let action = Action::decode(&Rlp::new(bytes)).expect("Verification passed");
         match action {
             Action::TransferCCS {
                 address,
@@ -126,6 +111,9 @@ impl ActionHandler for Stake {
                 };
                 self_nominate(state, fee_payer, sender_public, deposit, current_term, nomination_ends_at, metadata)
             }
+            Action::ChangeConsensusKey {
+                new_pubkey,
+            } => change_consensus_key(state, sender_public, &new_pubkey),
             Action::ChangeParams {
                 metadata_seq,
                 params,
@@ -146,6 +134,44 @@ impl ActionHandler for Stake {
             }
         }
     }
+}
+
+impl ActionHandler for Stake {
+    fn name(&self) -> &'static str {
+        "stake handler"
+    }
+
+    fn handler_id(&self) -> u64 {
+        CUSTOM_ACTION_HANDLER_ID
+    }
+
+    fn init(&self, state: &mut TopLevelState) -> StateResult<()> {
+        let mut stakeholders = Stakeholders::load_from_state(state)?;
+        for (address, amount) in self.genesis_stakes.iter() {
+            let account = StakeAccount {
+                address,
+                balance: *amount,
+            };
+            account.save_to_state(state)?;
+            stakeholders.update_by_increased_balance(&account);
+        }
+        stakeholders.save_to_state(state)?;
+        Ok(())
+    }
+
+    fn execute(
+        &self,
+        bytes: &[u8],
+        state: &mut TopLevelState,
+        fee_payer: &Address,
+        sender_public: &Public,
+    ) -> StateResult<()> {
+        let action = Action::decode(&Rlp::new(bytes)).expect("Verification passed");
+        let mut guard = state.checkpoint_guard(STAKE_ACTION_CHECKPOINT)?;
+        self.execute_action(action, &mut guard, fee_payer, sender_public)?;
+        guard.commit()?;
+        Ok(())
+    }
 
     fn verify(&self, bytes: &[u8], current_params: &CommonParams) -> Result<(), SyntaxError> {
         let action =
@@ -275,6 +301,14 @@ fn self_nominate(
         return Err(RuntimeError::FailedToHandleCustomAction("Account is blacklisted".to_string()).into())
     }
 
+    let consensus_keys = ConsensusKeys::load_from_state(state)?;
+    if consensus_keys.owner(sender_public).map_or(false, |owner| owner != *fee_payer) {
+        return Err(RuntimeError::FailedToHandleCustomAction(
+            "The key is a consensus key of another candidate".to_string(),
+        )
+        .into())
+    }
+
     let mut jail = Jail::load_from_state(&state)?;
     let total_deposit = match jail.try_release(fee_payer, current_term) {
         ReleaseResult::InCustody => {
@@ -305,6 +339,46 @@ fn self_nominate(
     Ok(())
 }
 
+fn change_consensus_key(state: &mut TopLevelState, sender_public: &Public, new_pubkey: &Public) -> StateResult<()> {
+    let mut consensus_keys = ConsensusKeys::load_from_state(state)?;
+    // Either the account of the candidate or its current consensus key can change the key.
+    let account = consensus_keys.account_of(&public_to_address(sender_public));
+    let candidates = Candidates::load_from_state(state)?;
+    let candidate = match candidates.get_candidate(&account) {
+        Some(candidate) => candidate,
+        None => {
+            return Err(RuntimeError::FailedToHandleCustomAction(
+                "Only a candidate can change the consensus key".to_string(),
+            )
+            .into())
+        }
+    };
+    if consensus_keys.has_pending(&account) {
+        return Err(RuntimeError::FailedToHandleCustomAction(
+            "The consensus key has already been changed in this term".to_string(),
+        )
+        .into())
+    }
+    if *new_pubkey == consensus_keys.consensus_key(candidate) {
+        return Err(RuntimeError::FailedToHandleCustomAction("The key is the current consensus key".to_string()).into())
+    }
+
+    // The validators are found by the addresses of their consensus keys, so the key must not be of anyone else.
+    let new_address = public_to_address(new_pubkey);
+    let is_other_candidate = new_address != account && candidates.get_candidate(&new_address).is_some();
+    let is_jailed = Jail::load_from_state(state)?.get_prisoner(&new_address).is_some();
+    let is_banned = Banned::load_from_state(state)?.is_banned(&new_address);
+    if is_other_candidate || is_jailed || is_banned || consensus_keys.owner(new_pubkey).is_some() {
+        return Err(RuntimeError::FailedToHandleCustomAction("The key is used by another account".to_string()).into())
+    }
+
+    consensus_keys.add_pending(account, *new_pubkey);
+    consensus_keys.save_to_state(state)?;
+
+    ctrace!(ENGINE, "Changed the consensus key. candidate: {}, new key: {}", account, new_address);
+    Ok(())
+}
+
 pub fn get_stakes(state: &TopLevelState) -> StateResult<HashMap<Address, u64>> {
     let stakeholders = Stakeholders::load_from_state(state)?;
     let mut result = HashMap::new();
@@ -405,13 +479,25 @@ pub fn on_term_close(
         (nomination_expiration, current_term + custody_period, current_term + release_period)
     };
 
-    let expired = update_candidates(state, current_term, nomination_expiration, inactive_validators)?;
+    let mut consensus_keys = ConsensusKeys::load_from_state(state)?;
+    // The inactive validators are the addresses of their consensus keys.
+    let inactive_accounts: Vec<_> =
+        inactive_validators.iter().map(|address| consensus_keys.account_of(address)).collect();
+
+    let expired = update_candidates(state, current_term, nomination_expiration, inactive_validators, &consensus_keys)?;
     let released = release_jailed_prisoners(state, current_term)?;
 
     let reverted: Vec<_> = expired.into_iter().chain(released).collect();
     revert_delegations(state, &reverted)?;
 
-    jail(state, inactive_validators, custody_until, kick_at)?;
+    jail(state, &inactive_accounts, custody_until, kick_at)?;
+
+    // The consensus keys changed in this term are used from the validators of the next term.
+    for account in &reverted {
+        consensus_keys.remove(account);
+    }
+    consensus_keys.apply_pending();
+    consensus_keys.save_to_state(state)?;
 
     let validators = Validators::elect(state)?;
     validators.save_to_state(state)?;
@@ -425,6 +511,7 @@ fn update_candidates(
     current_term: u64,
     nomination_expiration: u64,
     inactive_validators: &[Address],
+    consensus_keys: &ConsensusKeys,
 ) -> StateResult<Vec<Address>> {
     let banned = Banned::load_from_state(state)?;
 
@@ -432,7 +519,7 @@ fn update_candidates(
     let nomination_ends_at = current_term + nomination_expiration;
 
     let current_validators = Validators::load_from_state(state)?;
-    candidates.renew_candidates(&current_validators, nomination_ends_at, &inactive_validators, &banned);
+    candidates.renew_candidates(&current_validators, nomination_ends_at, &inactive_validators, &banned, consensus_keys);
 
     let expired = candidates.drain_expired_candidates(current_term);
     for candidate in &expired {
@@ -474,6 +561,9 @@ pub fn jail(state: &mut TopLevelState, addresses: &[Address], custody_until: u64
 }
 
 pub fn ban(state: &mut TopLevelState, informant: &Public, criminal: Address) -> StateResult<()> {
+    let mut consensus_keys = ConsensusKeys::load_from_state(state)?;
+    // The criminal can be given by the address of its consensus key.
+    let criminal = consensus_keys.account_of(&criminal);
     let mut banned = Banned::load_from_state(state)?;
     if banned.is_banned(&criminal) {
         return Err(RuntimeError::FailedToHandleCustomAction("Account is already banned".to_string()).into())
@@ -494,12 +584,14 @@ pub fn ban(state: &mut TopLevelState, informant: &Public, criminal: Address) ->
 
     jailed.remove(&criminal);
     banned.add(criminal);
-    validators.remove(&criminal);
+    validators.remove(&consensus_keys.consensus_address(&criminal));
+    consensus_keys.remove(&criminal);
 
     jailed.save_to_state(state)?;
     banned.save_to_state(state)?;
     candidates.save_to_state(state)?;
     validators.save_to_state(state)?;
+    consensus_keys.save_to_state(state)?;
 
     // Revert delegations
     revert_delegations(state, &[criminal])?;
@@ -1754,7 +1846,176 @@ mod tests {
         assert_eq!(jail.get_prisoner(&criminal), None, "Should be removed from the jail");
     }
 
+    #[test]
+    fn validators_who_missed_too_many_precommits_are_jailed() {
+        let pubkeys = [Public::random(), Public::random(), Public::random()];
+        let (active, inactive, latecomer) =
+            (public_to_address(&pubkeys[0]), public_to_address(&pubkeys[1]), public_to_address(&pubkeys[2]));
+
+        let mut state = metadata_for_election();
+        for (pubkey, address) in pubkeys.iter().zip(&[active, inactive, latecomer]) {
+            state.add_balance(address, 1000).unwrap();
+            self_nominate(&mut state, address, pubkey, 200, 0, 10, b"".to_vec()).unwrap();
+        }
+
+        // The latecomer joined for the last 4 blocks of the term and signed all of them.
+        let mut liveness = Liveness::default();
+        for _ in 0..6 {
+            liveness.record(&[active, inactive], vec![0]).unwrap();
+        }
+        for _ in 0..4 {
+            liveness.record(&[active, inactive, latecomer], vec![0, 1, 2]).unwrap();
+        }
+        assert_eq!(Some(4), liveness.get(&inactive).map(|liveness| liveness.signed));
+        let inactive_validators = liveness.inactive_validators(vec![active, inactive, latecomer], 50);
+        assert_eq!(vec![inactive], inactive_validators);
+
+        on_term_close(&mut state, pseudo_term_to_block_num_calculator(1), &inactive_validators).unwrap();
+
+        let jail = Jail::load_from_state(&state).unwrap();
+        assert_ne!(None, jail.get_prisoner(&inactive));
+        assert_eq!(None, jail.get_prisoner(&active));
+        assert_eq!(None, jail.get_prisoner(&latecomer));
+        let candidates = Candidates::load_from_state(&state).unwrap();
+        assert!(candidates.get_candidate(&inactive).is_none());
+        assert!(candidates.get_candidate(&latecomer).is_some());
+    }
+
     fn pseudo_term_to_block_num_calculator(term_id: u64) -> u64 {
         term_id * 10 + 1
     }
+
+    fn validator_keys(state: &TopLevelState) -> Vec<Public> {
+        Validators::load_from_state(state).unwrap().iter().map(|validator| *validator.pubkey()).collect()
+    }
+
+    /// Elects a validator of the account in term 1, and returns the stake handler with the state in term 2.
+    fn elect_validator(account_pubkey: &Public) -> (Stake, TopLevelState) {
+        let account = public_to_address(account_pubkey);
+        let delegator_pubkey = Public::random();
+        let delegator = public_to_address(&delegator_pubkey);
+
+        let mut state = metadata_for_election();
+        increase_term_id_until(&mut state, 1);
+        state.add_balance(&account, 20000).unwrap();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator, 100);
+            Stake::new(genesis_stakes)
+        };
+        stake.init(&mut state).unwrap();
+
+        self_nominate(&mut state, &account, account_pubkey, 10000, 1, 30, b"".to_vec()).unwrap();
+        let action = Action::DelegateCCS {
+            address: account,
+            quantity: 40,
+        };
+        stake.execute(&action.rlp_bytes(), &mut state, &delegator, &delegator_pubkey).unwrap();
+        assert_eq!(Ok(()), on_term_close(&mut state, pseudo_term_to_block_num_calculator(1), &[]));
+        assert_eq!(vec![*account_pubkey], validator_keys(&state));
+        (stake, state)
+    }
+
+    #[test]
+    fn change_consensus_key_of_validator_from_next_term() {
+        use crate::client::TestBlockChainClient;
+        use crate::consensus::DynamicValidator;
+        use ckey::{sign_schnorr, verify_schnorr, Generator, Random};
+
+        let account_key = Random.generate().unwrap();
+        let account = account_key.address();
+        let new_key = Random.generate().unwrap();
+        let (stake, mut state) = elect_validator(account_key.public());
+
+        let action = Action::ChangeConsensusKey {
+            new_pubkey: *new_key.public(),
+        };
+        assert_eq!(Ok(()), stake.execute(&action.rlp_bytes(), &mut state, &account, account_key.public()));
+        assert_eq!(vec![*account_key.public()], validator_keys(&state), "The key is changed from the next term");
+        let validators_before = Validators::load_from_state(&state).unwrap();
+
+        let action = Action::ChangeConsensusKey {
+            new_pubkey: Public::random(),
+        };
+        assert!(
+            stake.execute(&action.rlp_bytes(), &mut state, &account, account_key.public()).is_err(),
+            "The key can be changed once in a term"
+        );
+
+        assert_eq!(Ok(()), on_term_close(&mut state, pseudo_term_to_block_num_calculator(2), &[]));
+        assert_eq!(vec![*new_key.public()], validator_keys(&state));
+        let validators_after = Validators::load_from_state(&state).unwrap();
+        let candidates = Candidates::load_from_state(&state).unwrap();
+        assert_eq!(Some(account_key.public()), candidates.get_candidate(&account).map(|candidate| &candidate.pubkey));
+
+        // The blocks are verified with the validators in the state of their parents.
+        let message = H256::random();
+        for (validators, signer, other) in
+            vec![(validators_before, &account_key, &new_key), (validators_after, &new_key, &account_key)]
+        {
+            let validator_set = DynamicValidator::new(vec![]);
+            let client: Arc<dyn ConsensusClient> = Arc::new({
+                let mut client = TestBlockChainClient::new();
+                client.validators = validators;
+                client
+            });
+            validator_set.register_client(Arc::downgrade(&client));
+            let expected_signer = validator_set.get(&Default::default(), 0);
+            let signature = sign_schnorr(signer.private(), &message).unwrap();
+            assert_eq!(Ok(true), verify_schnorr(&expected_signer, &signature, &message));
+            let signature = sign_schnorr(other.private(), &message).unwrap();
+            assert_eq!(Ok(false), verify_schnorr(&expected_signer, &signature, &message));
+        }
+
+        // The current consensus key can change the key, and the inactive validator is jailed by its account.
+        let action = Action::ChangeConsensusKey {
+            new_pubkey: Public::random(),
+        };
+        assert_eq!(Ok(()), stake.execute(&action.rlp_bytes(), &mut state, &new_key.address(), new_key.public()));
+        assert_eq!(Ok(()), on_term_close(&mut state, pseudo_term_to_block_num_calculator(3), &[new_key.address()]));
+        assert!(Jail::load_from_state(&state).unwrap().get_prisoner(&account).is_some());
+    }
+
+    #[test]
+    fn change_consensus_key_requires_unused_key_of_candidate() {
+        let account_pubkey = Public::random();
+        let account = public_to_address(&account_pubkey);
+        let (stake, mut state) = elect_validator(&account_pubkey);
+
+        let other_pubkey = Public::random();
+        let other = public_to_address(&other_pubkey);
+        state.add_balance(&other, 1000).unwrap();
+        self_nominate(&mut state, &other, &other_pubkey, 0, 2, 30, b"".to_vec()).unwrap();
+
+        let to_new_key = Action::ChangeConsensusKey {
+            new_pubkey: Public::random(),
+        };
+        let non_candidate = Public::random();
+        assert!(
+            stake
+                .execute(&to_new_key.rlp_bytes(), &mut state, &public_to_address(&non_candidate), &non_candidate)
+                .is_err(),
+            "Only a candidate can change its key"
+        );
+        assert!(
+            stake.execute(&to_new_key.rlp_bytes(), &mut state, &account, &non_candidate).is_err(),
+            "A regular key cannot change the consensus key"
+        );
+
+        let to_other_candidate = Action::ChangeConsensusKey {
+            new_pubkey: other_pubkey,
+        };
+        assert!(stake.execute(&to_other_candidate.rlp_bytes(), &mut state, &account, &account_pubkey).is_err());
+
+        let new_pubkey = Public::random();
+        let to_used_key = Action::ChangeConsensusKey {
+            new_pubkey,
+        };
+        assert_eq!(Ok(()), stake.execute(&to_used_key.rlp_bytes(), &mut state, &account, &account_pubkey));
+        assert!(stake.execute(&to_used_key.rlp_bytes(), &mut state, &other, &other_pubkey).is_err());
+        assert!(
+            self_nominate(&mut state, &public_to_address(&new_pubkey), &new_pubkey, 0, 2, 30, b"".to_vec()).is_err(),
+            "A consensus key cannot be a candidate"
+        );
+    }
 }
@@ -30,13 +30,13 @@ use crate::consensus::{EngineType, ValidatorSet};
 use crate::encoded;
 use crate::error::Error;
 use crate::views::HeaderView;
-use crate::BlockId;
 use ckey::{public_to_address, Address};
 use cnetwork::NetworkService;
 use crossbeam_channel as crossbeam;
 use cstate::{ActionHandler, TopStateView};
-use ctypes::{BlockHash, CommonParams, Header};
+use ctypes::{BlockHash, BlockNumber, CommonParams, Header};
 use num_rational::Ratio;
+use primitives::Bytes;
 use rlp::Encodable;
 use std::collections::btree_map::BTreeMap;
 use std::collections::{HashMap, HashSet};
@@ -91,6 +91,10 @@ impl ConsensusEngine for Tendermint {
     /// Called when the node is the leader and a proposal block is generated from the miner.
     /// This writes the proposal information and go to the prevote step.
     fn proposal_generated(&self, sealed_block: &SealedBlock) {
+        let header = sealed_block.header();
+        if let Ok(view) = TendermintSealView::new(header.seal()).author_view() {
+            self.proposal_blocks.write().insert(header.number(), view, encoded::Block::new(sealed_block.rlp_bytes()));
+        }
         self.inner.send(worker::Event::ProposalGenerated(Box::from(sealed_block.clone()))).unwrap();
     }
 
@@ -158,12 +162,20 @@ impl ConsensusEngine for Tendermint {
         assert!(total_reward >= total_min_fee, "{} >= {}", total_reward, total_min_fee);
         let stakes = stake::get_stakes(block.state()).expect("Cannot get Stake status");
 
-        let mut distributor = stake::fee_distribute(total_min_fee, &stakes);
-        for (address, share) in &mut distributor {
-            self.machine.add_balance(block, &address, share)?
+        let shares = stake::distribute_fee(total_min_fee, &stakes, &parent_common_params);
+        for stakeholder in &shares.stakeholders {
+            self.machine.add_balance(block, &stakeholder.address, stakeholder.share)?
         }
-
-        let block_author_reward = total_reward - total_min_fee + distributor.remaining_fee();
+        let treasury = parent_common_params.fee_treasury();
+        self.machine.add_balance(block, &treasury, shares.treasury)?;
+
+        let block_author_reward = total_reward - total_min_fee + shares.author;
+        block.set_fee_distribution(stake::FeeDistribution {
+            author,
+            author_reward: block_author_reward,
+            treasury,
+            shares,
+        });
 
         let metadata = block.state().metadata()?.expect("Metadata must exist");
         let term = metadata.current_term_id();
@@ -200,12 +212,14 @@ impl ConsensusEngine for Tendermint {
                         client.block_header(&start_of_the_current_term.into()).unwrap()
                     };
 
+                    let consensus_keys = consensus_keys_of_the_previous_term(&*client, start_of_the_current_term)?;
                     let pending_rewards = calculate_pending_rewards_of_the_term(
                         &*client,
                         &*self.validators,
                         rewards,
                         start_of_the_current_term_header,
                         &banned,
+                        &consensus_keys,
                     )?;
 
                     for (address, reward) in pending_rewards {
@@ -217,9 +231,18 @@ impl ConsensusEngine for Tendermint {
 
                 let validators = stake::Validators::load_from_state(block.state())?
                     .into_iter()
-                    .map(|val| public_to_address(val.pubkey()))
-                    .collect();
-                inactive_validators(&*client, start_of_the_current_term, block.header(), validators)
+                    .map(|val| public_to_address(val.pubkey()));
+                match term_common_params.and_then(CommonParams::max_missed_precommit_percentage) {
+                    Some(max_missed_percentage) => {
+                        // The precommits on the closing block are not sealed yet.
+                        let parent_header = client.block_header(&parent_hash.into()).expect("Parent header must exist");
+                        let liveness = self.tally_liveness(&*client, start_of_the_current_term, parent_header)?;
+                        liveness.inactive_validators(validators, max_missed_percentage)
+                    }
+                    None => {
+                        inactive_validators(&*client, start_of_the_current_term, block.header(), validators.collect())
+                    }
+                }
             }
         };
 
@@ -245,6 +268,10 @@ impl ConsensusEngine for Tendermint {
         receiver.recv().unwrap()
     }
 
+    fn proposal_block(&self, height: u64, view: u64) -> Option<encoded::Block> {
+        self.proposal_blocks.read().get(height, view)
+    }
+
     fn set_signer(&self, ap: Arc<AccountProvider>, address: Address) {
         self.has_signer.store(true, AtomicOrdering::SeqCst);
         self.inner
@@ -280,8 +307,15 @@ impl ConsensusEngine for Tendermint {
         1
     }
 
+    /// The best block is committed: either the worker committed it or its child carries the precommits for it.
+    fn finalized_block_number(&self, best_block_number: BlockNumber) -> BlockNumber {
+        best_block_number
+    }
+
     fn register_chain_notify(&self, client: &Client) {
-        client.add_notify(Arc::downgrade(&self.chain_notify) as Weak<dyn ChainNotify>);
+        // It only forwards the events to the worker, so it costs the importer nothing and the worker is not kept
+        // waiting behind slow actors.
+        client.add_synchronous_notify(Arc::downgrade(&self.chain_notify) as Weak<dyn ChainNotify>);
     }
 
     fn get_best_block_from_best_proposal_header(&self, header: &HeaderView) -> BlockHash {
@@ -304,17 +338,84 @@ impl ConsensusEngine for Tendermint {
     }
 
     fn possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<Address>>, EngineError> {
+        let block_hash = self.parent_hash_of(block_number)?;
+        Ok(Some(self.validators.addresses(&block_hash)))
+    }
+
+    fn validators(&self, block_number: Option<u64>) -> Result<Option<Vec<stake::Validator>>, EngineError> {
+        let block_hash = self.parent_hash_of(block_number)?;
+        Ok(Some(self.validators.validators(&block_hash)))
+    }
+
+    fn validator_liveness(
+        &self,
+        start_of_the_term: BlockNumber,
+        last_block: BlockNumber,
+    ) -> Result<Option<Vec<stake::ValidatorLiveness>>, Error> {
         let client = self.client().ok_or(EngineError::CannotOpenBlock)?;
-        let block_hash = match block_number {
-            None => {
-                client.block_header(&BlockId::Latest).expect("latest block must exist").hash() // the latest block
-            }
-            Some(block_number) => {
-                assert_ne!(0, block_number);
-                client.block_header(&(block_number - 1).into()).ok_or(EngineError::CannotOpenBlock)?.hash() // the parent of the given block number
+        let last_header = match client.block_header(&last_block.into()) {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let liveness = self.tally_liveness(&*client, start_of_the_term, last_header)?;
+        Ok(Some(liveness.to_vec()))
+    }
+}
+
+/// The tally of the precommits from the start of a term to a block.
+pub(super) struct TermLiveness {
+    start_of_the_term: BlockNumber,
+    last_hash: BlockHash,
+    liveness: stake::Liveness,
+}
+
+impl Tendermint {
+    /// Counts the precommits on the blocks from `start_of_the_term` to the parent of `last_header`.
+    /// The precommits on a block are in the seal of its child, so the ones on `last_header` are not counted.
+    ///
+    /// The last tally is cached, and a tally that reaches its block reads only the blocks after it.
+    /// So the term close and `chain_getValidatorLiveness` don't read the whole term on every call.
+    fn tally_liveness(
+        &self,
+        chain: &dyn ConsensusClient,
+        start_of_the_term: BlockNumber,
+        last_header: encoded::Header,
+    ) -> Result<stake::Liveness, Error> {
+        let last_hash = last_header.hash();
+        let (cached_hash, cached) = match &*self.liveness_cache.lock() {
+            Some(cache) if cache.start_of_the_term == start_of_the_term => {
+                (Some(cache.last_hash), Some(cache.liveness.clone()))
             }
+            _ => (None, None),
         };
-        Ok(Some(self.validators.addresses(&block_hash)))
+
+        let mut later = stake::Liveness::default();
+        let mut header = last_header;
+        let mut earlier = None;
+        while start_of_the_term < header.number() {
+            if Some(header.hash()) == cached_hash {
+                earlier = cached;
+                break
+            }
+            let parent = chain.block_header(&header.parent_hash().into()).expect("Header of the parent must exist");
+            // The precommits on the parent are signed by the validators of the parent.
+            let parent_validators = self.validators.addresses(&parent.parent_hash());
+            record_precommits(&mut later, &parent_validators, &header.seal())?;
+            header = parent;
+        }
+        let mut liveness = earlier.unwrap_or_default();
+        liveness.merge(later);
+
+        let mut cache = self.liveness_cache.lock();
+        // The tally of an earlier term doesn't replace the one of the current term.
+        if cache.as_ref().map_or(true, |cache| cache.start_of_the_term <= start_of_the_term) {
+            *cache = Some(TermLiveness {
+                start_of_the_term,
+                last_hash,
+                liveness: liveness.clone(),
+            });
+        }
+        Ok(liveness)
     }
 }
 
@@ -346,6 +447,12 @@ fn inactive_validators(
     validators.into_iter().collect()
 }
 
+fn record_precommits(liveness: &mut stake::Liveness, validators: &[Address], seal: &[Bytes]) -> Result<(), Error> {
+    let precommits = TendermintSealView::new(seal).bitset()?;
+    liveness.record(validators, precommits.true_index_iter())?;
+    Ok(())
+}
+
 // Aggregate the validators' work info of a term
 fn aggregate_work_info(
     chain: &dyn ConsensusClient,
@@ -388,17 +495,41 @@ fn aggregate_work_info(
     Ok(work_info)
 }
 
+/// The consensus keys that the validators signed the blocks of the term before the current one with.
+/// They are the keys applied when the term before it closed.
+fn consensus_keys_of_the_previous_term(
+    chain: &dyn ConsensusClient,
+    start_of_the_current_term: BlockNumber,
+) -> Result<stake::ConsensusKeys, Error> {
+    let end_of_the_term_before_the_previous_one =
+        chain.last_term_finished_block_num((start_of_the_current_term - 2).into()).unwrap();
+    let state = chain.state_at(end_of_the_term_before_the_previous_one.into()).expect("The state must exist");
+    Ok(stake::ConsensusKeys::load_from_state(&state)?)
+}
+
 fn calculate_pending_rewards_of_the_term(
     chain: &dyn ConsensusClient,
     validators: &dyn ValidatorSet,
     rewards: BTreeMap<Address, u64>,
     start_of_the_next_term_header: encoded::Header,
     banned: &stake::Banned,
+    consensus_keys: &stake::ConsensusKeys,
+) -> Result<HashMap<Address, u64>, Error> {
+    let work_info = aggregate_work_info(chain, validators, start_of_the_next_term_header)?;
+    pending_rewards(rewards, work_info, banned, consensus_keys)
+}
+
+/// The rewards and the work info are of the addresses of the consensus keys,
+/// and the pending rewards are of the accounts of the validators.
+fn pending_rewards(
+    rewards: BTreeMap<Address, u64>,
+    work_info: HashMap<Address, WorkInfo>,
+    banned: &stake::Banned,
+    consensus_keys: &stake::ConsensusKeys,
 ) -> Result<HashMap<Address, u64>, Error> {
     // XXX: It's okay because we don't have a plan to increasing the maximum number of validators.
     //      However, it's better to use the correct number.
     const MAX_NUM_OF_VALIDATORS: usize = 30;
-    let work_info = aggregate_work_info(chain, validators, start_of_the_next_term_header)?;
     let mut pending_rewards = HashMap::<Address, u64>::with_capacity(MAX_NUM_OF_VALIDATORS);
 
     let mut reduced_rewards = 0;
@@ -406,7 +537,8 @@ fn calculate_pending_rewards_of_the_term(
     // Penalty disloyal validators
     let number_of_blocks_in_term: usize = work_info.values().map(|info| info.proposed).sum();
     for (address, intermediate_reward) in rewards {
-        if banned.is_banned(&address) {
+        let account = consensus_keys.account_of(&address);
+        if banned.is_banned(&account) {
             reduced_rewards += intermediate_reward;
             continue
         }
@@ -414,12 +546,12 @@ fn calculate_pending_rewards_of_the_term(
         let final_block_rewards =
             final_rewards(intermediate_reward, number_of_signatures, u64::try_from(number_of_blocks_in_term).unwrap());
         reduced_rewards += intermediate_reward - final_block_rewards;
-        pending_rewards.insert(address, final_block_rewards);
+        *pending_rewards.entry(account).or_default() += final_block_rewards;
     }
 
     // Give additional rewards
     give_additional_rewards(reduced_rewards, work_info, |address, reward| {
-        let prev = pending_rewards.entry(*address).or_default();
+        let prev = pending_rewards.entry(consensus_keys.account_of(address)).or_default();
         *prev += reward;
         Ok(())
     })?;
@@ -496,6 +628,8 @@ mod tests {
     use std::iter::FromIterator;
 
     use super::*;
+    use crate::consensus::BitSet;
+    use ckey::{Generator, Random};
 
     #[test]
     fn test_final_rewards() {
@@ -617,6 +751,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn precommits_in_the_seals_are_tallied() {
+        let (a, b, c) = (Address::random(), Address::random(), Address::random());
+        let mut liveness = stake::Liveness::default();
+        // B signs the first 3 blocks only, and C joins the validator set at the 7th block.
+        for i in 0..10 {
+            let (validators, signers) = match i {
+                0..=2 => (vec![a, b], vec![0, 1]),
+                3..=5 => (vec![a, b], vec![0]),
+                _ => (vec![a, b, c], vec![0, 2]),
+            };
+            let seal = Seal::Tendermint {
+                prev_view: 0,
+                cur_view: 0,
+                precommits: vec![],
+                precommit_bitset: BitSet::new_with_indices(&signers),
+            };
+            let mut header = Header::default();
+            header.set_number(i + 2);
+            header.set_seal(seal.seal_fields().unwrap());
+            record_precommits(&mut liveness, &validators, header.seal()).unwrap();
+        }
+
+        let tally = |address| liveness.get(&address).map(|liveness| (liveness.expected, liveness.signed));
+        assert_eq!(Some((10, 10)), tally(a));
+        assert_eq!(Some((10, 3)), tally(b));
+        assert_eq!(Some((4, 4)), tally(c));
+        assert_eq!(vec![b], liveness.inactive_validators(vec![a, b, c], 50));
+        assert_eq!(Vec::<Address>::new(), liveness.inactive_validators(vec![a, b, c], 70));
+    }
+
     #[test]
     fn test_additional_rewards() {
         let reduced_rewards = 100;
@@ -675,4 +840,38 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn rewards_of_a_rotated_key_are_paid_to_its_account() {
+        let (account, new_key) = (Random.generate().unwrap(), Random.generate().unwrap());
+        let (banned_account, banned_key) = (Random.generate().unwrap(), Random.generate().unwrap());
+        let mut consensus_keys = stake::ConsensusKeys::default();
+        consensus_keys.add_pending(account.address(), *new_key.public());
+        consensus_keys.add_pending(banned_account.address(), *banned_key.public());
+        consensus_keys.apply_pending();
+        let mut banned = stake::Banned::default();
+        banned.add(banned_account.address());
+
+        // The blocks are authored and signed with the new keys.
+        let rewards = BTreeMap::from_iter(vec![(new_key.address(), 1000), (banned_key.address(), 1000)]);
+        let work_info = HashMap::from_iter(vec![
+            (new_key.address(), WorkInfo {
+                proposed: 10,
+                missed: 0,
+                signed: 10,
+            }),
+            (banned_key.address(), WorkInfo {
+                proposed: 0,
+                missed: 0,
+                signed: 10,
+            }),
+        ]);
+
+        let pending_rewards = pending_rewards(rewards, work_info, &banned, &consensus_keys).unwrap();
+        assert_eq!(None, pending_rewards.get(&new_key.address()));
+        assert_eq!(None, pending_rewards.get(&banned_key.address()));
+        assert_eq!(None, pending_rewards.get(&banned_account.address()));
+        // The reward of the banned validator is reduced, and the half of it goes to the other validator.
+        assert_eq!(Some(&1500), pending_rewards.get(&account.address()));
+    }
 }
@@ -22,21 +22,25 @@ mod network;
 mod params;
 pub mod types;
 pub mod vote_collector;
+mod vote_journal;
 mod vote_regression_checker;
 mod worker;
 
 use self::chain_notify::TendermintChainNotify;
 pub use self::message::{ConsensusMessage, VoteOn, VoteStep};
 pub use self::params::{TendermintParams, TimeGapParams, TimeoutParams};
+use self::types::ProposalBlocks;
 pub use self::types::{Height, Step, View};
+use super::EngineError;
 pub use super::{stake, ValidatorSet};
 use crate::client::ConsensusClient;
 use crate::codechain_machine::CodeChainMachine;
-use crate::ChainNotify;
+use crate::{BlockId, ChainNotify};
 use crossbeam_channel as crossbeam;
 use cstate::ActionHandler;
 use ctimer::TimerToken;
-use parking_lot::RwLock;
+use ctypes::BlockHash;
+use parking_lot::{Mutex, RwLock};
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Weak};
 use std::thread::JoinHandle;
@@ -72,6 +76,10 @@ pub struct Tendermint {
     /// Chain notify
     chain_notify: Arc<TendermintChainNotify>,
     has_signer: AtomicBool,
+    /// The proposal blocks of the current height, shared with the worker
+    proposal_blocks: Arc<RwLock<ProposalBlocks>>,
+    /// The last tally of the precommits, which the next tally of the term continues
+    liveness_cache: Mutex<Option<engine::TermLiveness>>,
 }
 
 impl Drop for Tendermint {
@@ -90,9 +98,10 @@ impl Tendermint {
         let stake = Arc::new(stake::Stake::new(our_params.genesis_stakes));
         let timeouts = our_params.timeouts;
         let machine = Arc::new(machine);
+        let proposal_blocks = Arc::new(RwLock::new(ProposalBlocks::default()));
 
         let (join, external_params_initializer, extension_initializer, inner, quit_tendermint) =
-            worker::spawn(our_params.validators);
+            worker::spawn(our_params.validators, Arc::clone(&proposal_blocks));
         let action_handlers: Vec<Arc<dyn ActionHandler>> = vec![stake.clone()];
         let chain_notify = Arc::new(TendermintChainNotify::new(inner.clone()));
 
@@ -111,12 +120,29 @@ impl Tendermint {
             stake,
             chain_notify,
             has_signer: false.into(),
+            proposal_blocks,
+            liveness_cache: Default::default(),
         })
     }
 
     fn client(&self) -> Option<Arc<dyn ConsensusClient>> {
         self.client.read().as_ref()?.upgrade()
     }
+
+    /// The hash of the parent of the given block. The latest block is the parent of the next block.
+    fn parent_hash_of(&self, block_number: Option<u64>) -> Result<BlockHash, EngineError> {
+        let client = self.client().ok_or(EngineError::CannotOpenBlock)?;
+        let block_hash = match block_number {
+            None => {
+                client.block_header(&BlockId::Latest).expect("latest block must exist").hash() // the latest block
+            }
+            Some(block_number) => {
+                assert_ne!(0, block_number);
+                client.block_header(&(block_number - 1).into()).ok_or(EngineError::CannotOpenBlock)?.hash() // the parent of the given block number
+            }
+        };
+        Ok(block_hash)
+    }
 }
 
 const SEAL_FIELDS: usize = 4;
@@ -131,10 +157,12 @@ mod tests {
 
     use super::super::BitSet;
     use super::message::VoteStep;
+    use super::types::SeenProposals;
     use crate::account_provider::AccountProvider;
     use crate::block::{ClosedBlock, OpenBlock};
-    use crate::client::TestBlockChainClient;
+    use crate::client::{BlockChainClient, EngineClient, OrphanedProposal, TestBlockChainClient};
     use crate::consensus::{CodeChainEngine, Seal};
+    use crate::encoded;
     use crate::error::BlockError;
     use crate::error::Error;
     use crate::scheme::Scheme;
@@ -279,4 +307,64 @@ mod tests {
         println!(".....");
         assert!(engine.verify_block_external(&header).is_err());
     }
+
+    #[test]
+    fn generated_proposal_is_given_through_client() {
+        let client = TestBlockChainClient::new_with_scheme(Scheme::new_test_tendermint());
+        let engine = client.scheme.engine.as_ref();
+
+        // The block is built by another engine, because the tendermint worker isn't running in this test.
+        let solo = Scheme::new_test_solo();
+        let db = solo.ensure_genesis_state(get_temp_state_db()).unwrap();
+        let genesis_header = solo.genesis_header();
+        let block = OpenBlock::try_new(solo.engine.as_ref(), db, &genesis_header, Address::default(), vec![]).unwrap();
+        let term_common_params = CommonParams::default_for_test();
+        let seal = Seal::Tendermint {
+            prev_view: 0,
+            cur_view: 1,
+            precommits: vec![],
+            precommit_bitset: BitSet::new(),
+        };
+        let sealed_block = block
+            .close(&genesis_header, Some(&term_common_params))
+            .unwrap()
+            .lock()
+            .seal(engine, seal.seal_fields().unwrap())
+            .unwrap();
+        engine.proposal_generated(&sealed_block);
+
+        let client: &dyn ConsensusClient = &client;
+        assert_eq!(Some(encoded::Block::new(sealed_block.rlp_bytes())), client.proposal_block(1, 1));
+        assert_eq!(None, client.proposal_block(1, 0));
+        assert_eq!(None, client.proposal_block(2, 1));
+    }
+
+    #[test]
+    fn proposal_not_committed_is_logged_as_orphaned() {
+        let client = TestBlockChainClient::new_with_scheme(Scheme::new_test_tendermint());
+        let ours = Address::from(1);
+        let theirs = Address::from(2);
+        // Two proposals of height 1 are imported, and the one of view 1 becomes the best block.
+        let not_committed = client.add_block_on(client.genesis_hash, Some(ours), vec![]);
+        let committed = client.add_block_with_author(Some(theirs), 1, 0);
+        let proposal = |view, hash, author| OrphanedProposal {
+            height: 1,
+            view,
+            hash,
+            author,
+            is_own: author == ours,
+        };
+        let canonical_hash = |height| client.block_hash(&BlockId::Number(height));
+
+        let mut seen = SeenProposals::default();
+        seen.insert(proposal(0, not_committed, ours));
+        seen.insert(proposal(1, committed, theirs));
+        assert_eq!(Vec::<OrphanedProposal>::new(), seen.take_orphaned(1, canonical_hash), "Height 1 is not committed");
+
+        client.record_orphaned_proposals(seen.take_orphaned(2, canonical_hash));
+        assert_eq!(Some(committed), canonical_hash(1));
+        assert_eq!(vec![proposal(0, not_committed, ours)], client.orphaned_proposals(1, 1).unwrap());
+        assert!(client.orphaned_proposals(1, 1).unwrap()[0].is_own);
+        assert_eq!(Vec::<OrphanedProposal>::new(), seen.take_orphaned(3, canonical_hash), "Reported only once");
+    }
 }
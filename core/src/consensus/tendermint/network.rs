@@ -25,14 +25,14 @@ use super::{
 };
 use crate::consensus::EngineError;
 use ckey::SchnorrSignature;
-use cnetwork::{Api, NetworkExtension, NodeId};
+use cnetwork::{decode_message, Api, NetworkExtension, NodeId};
 use crossbeam_channel as crossbeam;
 use ctimer::TimerToken;
 use ctypes::BlockHash;
 use primitives::Bytes;
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
-use rlp::{Encodable, Rlp};
+use rlp::Encodable;
 use std::cmp;
 use std::collections::HashMap;
 use std::iter::Iterator;
@@ -239,8 +239,7 @@ impl NetworkExtension<Event> for TendermintExtension {
     }
 
     fn on_message(&mut self, token: &NodeId, data: &[u8]) {
-        let m = Rlp::new(data);
-        match m.as_val() {
+        match decode_message(data) {
             Ok(TendermintMessage::ConsensusMessage(ref messages)) => {
                 ctrace!(ENGINE, "Received messages({})", messages.len());
                 let (result, receiver) = crossbeam::bounded(messages.len());
@@ -285,7 +284,7 @@ impl NetworkExtension<Event> for TendermintExtension {
                     })
                     .unwrap();
                 if let Some(c) = receiver.recv().unwrap() {
-                    if let Err(e) = c.import_block(message) {
+                    if let Err(e) = c.import_sealed_block(message) {
                         cinfo!(ENGINE, "Failed to import proposal block {:?}", e);
                     }
                 }
@@ -390,7 +389,7 @@ impl NetworkExtension<Event> for TendermintExtension {
                     .unwrap();
 
                 if let Some(c) = receiver.recv().unwrap() {
-                    if let Err(e) = c.import_block(block) {
+                    if let Err(e) = c.import_sealed_block(block) {
                         cinfo!(ENGINE, "Failed to import committed block {:?}", e);
                     }
                 }
@@ -18,8 +18,10 @@ use super::super::validator_set::DynamicValidator;
 use super::types::View;
 use super::Step;
 use cjson;
+use cjson::uint::{OverflowError, Uint};
 use ckey::{Address, PlatformAddress};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -35,34 +37,35 @@ pub struct TendermintParams {
     pub genesis_stakes: HashMap<Address, u64>,
 }
 
-impl From<cjson::scheme::TendermintParams> for TendermintParams {
-    fn from(p: cjson::scheme::TendermintParams) -> Self {
+impl TryFrom<cjson::scheme::TendermintParams> for TendermintParams {
+    type Error = OverflowError;
+
+    fn try_from(p: cjson::scheme::TendermintParams) -> Result<Self, Self::Error> {
         let dt = TimeoutParams::default();
-        TendermintParams {
+        Ok(TendermintParams {
             validators: Arc::new(DynamicValidator::new(p.validators)),
             timeouts: TimeoutParams {
-                propose: p.timeout_propose.map_or(dt.propose, to_duration),
-                propose_delta: p.timeout_propose_delta.map_or(dt.propose_delta, to_duration),
-                prevote: p.timeout_prevote.map_or(dt.prevote, to_duration),
-                prevote_delta: p.timeout_prevote_delta.map_or(dt.prevote_delta, to_duration),
-                precommit: p.timeout_precommit.map_or(dt.precommit, to_duration),
-                precommit_delta: p.timeout_precommit_delta.map_or(dt.precommit_delta, to_duration),
-                commit: p.timeout_commit.map_or(dt.commit, to_duration),
+                propose: p.timeout_propose.map_or(Ok(dt.propose), to_duration)?,
+                propose_delta: p.timeout_propose_delta.map_or(Ok(dt.propose_delta), to_duration)?,
+                prevote: p.timeout_prevote.map_or(Ok(dt.prevote), to_duration)?,
+                prevote_delta: p.timeout_prevote_delta.map_or(Ok(dt.prevote_delta), to_duration)?,
+                precommit: p.timeout_precommit.map_or(Ok(dt.precommit), to_duration)?,
+                precommit_delta: p.timeout_precommit_delta.map_or(Ok(dt.precommit_delta), to_duration)?,
+                commit: p.timeout_commit.map_or(Ok(dt.commit), to_duration)?,
             },
-            block_reward: p.block_reward.map_or(0, Into::into),
+            block_reward: p.block_reward.map_or(Ok(0), u64::try_from)?,
             genesis_stakes: p
                 .genesis_stakes
                 .unwrap_or_default()
                 .into_iter()
                 .map(|(pa, amount)| (PlatformAddress::into_address(pa), amount))
                 .collect(),
-        }
+        })
     }
 }
 
-fn to_duration(ms: cjson::uint::Uint) -> Duration {
-    let ms: usize = ms.into();
-    Duration::from_millis(ms as u64)
+fn to_duration(ms: Uint) -> Result<Duration, OverflowError> {
+    Ok(Duration::from_millis(u64::try_from(ms)?))
 }
 
 pub struct TimeGapParams {
@@ -17,10 +17,13 @@
 use super::super::BitSet;
 use super::message::VoteStep;
 use crate::block::{IsBlock, SealedBlock};
+use crate::client::OrphanedProposal;
+use crate::encoded;
 use ckey::SchnorrSignature;
 use ctypes::BlockHash;
 use primitives::Bytes;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::collections::HashMap;
 use std::fmt;
 
 pub type Height = u64;
@@ -336,3 +339,68 @@ impl Proposal {
         }
     }
 }
+
+/// The proposal blocks of the current height.
+/// A proposal is not inserted into the DB until it's committed, so they are kept here for the observers.
+#[derive(Default)]
+pub struct ProposalBlocks {
+    height: Height,
+    blocks: HashMap<View, encoded::Block>,
+}
+
+impl ProposalBlocks {
+    pub fn get(&self, height: Height, view: View) -> Option<encoded::Block> {
+        if height != self.height {
+            return None
+        }
+        self.blocks.get(&view).cloned()
+    }
+
+    /// Keeps the proposal unless it's for a height lower than the current one.
+    pub fn insert(&mut self, height: Height, view: View, block: encoded::Block) {
+        self.move_to_height(height);
+        if height == self.height {
+            self.blocks.insert(view, block);
+        }
+    }
+
+    /// Drops the proposals of the previous heights.
+    pub fn move_to_height(&mut self, height: Height) {
+        if height > self.height {
+            self.height = height;
+            self.blocks.clear();
+        }
+    }
+}
+
+/// The imported proposals whose heights are not committed yet.
+/// When their heights are committed, the ones that didn't become the canonical blocks are orphaned.
+#[derive(Default)]
+pub struct SeenProposals {
+    proposals: Vec<OrphanedProposal>,
+}
+
+impl SeenProposals {
+    pub fn insert(&mut self, proposal: OrphanedProposal) {
+        if !self.proposals.iter().any(|seen| seen.hash == proposal.hash) {
+            self.proposals.push(proposal);
+        }
+    }
+
+    /// Takes the proposals below the height and returns the ones that are not canonical.
+    /// A proposal is dropped if its height has no canonical block, since it can't be told whether it's orphaned.
+    pub fn take_orphaned<F>(&mut self, height: Height, canonical_hash: F) -> Vec<OrphanedProposal>
+    where
+        F: Fn(Height) -> Option<BlockHash>, {
+        let (committed, pending): (Vec<_>, Vec<_>) =
+            self.proposals.drain(..).partition(|proposal| proposal.height < height);
+        self.proposals = pending;
+        committed
+            .into_iter()
+            .filter(|proposal| match canonical_hash(proposal.height) {
+                Some(hash) => hash != proposal.hash,
+                None => false,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::message::{VoteOn, VoteStep};
+use super::types::{Height, Step};
+use crate::db;
+use ctypes::BlockHash;
+use kvdb::{DBTransaction, KeyValueDB};
+use rlp::{Decodable, Encodable, Rlp};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// The write-ahead journal of the votes signed by this node.
+///
+/// A vote is written before it's signed, so a node that restarts in the middle of a round
+/// remembers what it voted and never signs a different block at the same step.
+pub struct VoteJournal {
+    db: Arc<dyn KeyValueDB>,
+    votes: BTreeMap<VoteStep, Option<BlockHash>>,
+}
+
+/// The result of writing a vote to the journal.
+#[derive(Debug, PartialEq)]
+pub enum JournalResult {
+    /// The vote is recorded, or the same vote was recorded before.
+    Recorded,
+    /// Another block was voted at the step. It must not be signed.
+    Conflict(Option<BlockHash>),
+}
+
+impl VoteJournal {
+    /// Loads the votes recorded before the last shutdown.
+    pub fn load(db: Arc<dyn KeyValueDB>) -> Self {
+        let mut votes = BTreeMap::new();
+        for (key, value) in db.iter(db::COL_VOTE_JOURNAL) {
+            let step = VoteStep::decode(&Rlp::new(&key));
+            let block_hash = Rlp::new(&value).as_val::<Option<BlockHash>>();
+            match (step, block_hash) {
+                (Ok(step), Ok(block_hash)) => {
+                    votes.insert(step, block_hash);
+                }
+                _ => cerror!(ENGINE, "The vote journal has a broken entry {:?}", key),
+            }
+        }
+        VoteJournal {
+            db,
+            votes,
+        }
+    }
+
+    /// Writes the vote and flushes it to the disk before the caller signs it.
+    pub fn record(&mut self, on: &VoteOn) -> JournalResult {
+        if let Some(recorded) = self.votes.get(&on.step) {
+            if *recorded == on.block_hash {
+                return JournalResult::Recorded
+            }
+            return JournalResult::Conflict(*recorded)
+        }
+
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_VOTE_JOURNAL, &on.step.rlp_bytes(), &on.block_hash.rlp_bytes());
+        self.db.write(batch).expect("Low level database error. Some issue with disk?");
+        self.db.flush().expect("Low level database error. Some issue with disk?");
+        self.votes.insert(on.step, on.block_hash);
+        JournalResult::Recorded
+    }
+
+    /// Removes the votes below the height, which is already finalized.
+    pub fn prune(&mut self, height: Height) {
+        let kept = self.votes.split_off(&VoteStep::new(height, 0, Step::Propose));
+        let pruned = std::mem::replace(&mut self.votes, kept);
+        if pruned.is_empty() {
+            return
+        }
+
+        let mut batch = DBTransaction::new();
+        for step in pruned.keys() {
+            batch.delete(db::COL_VOTE_JOURNAL, &step.rlp_bytes());
+        }
+        self.db.write(batch).expect("Low level database error. Some issue with disk?");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use primitives::H256;
+
+    fn vote(height: Height, view: u64, step: Step, block_hash: Option<BlockHash>) -> VoteOn {
+        VoteOn {
+            step: VoteStep::new(height, view, step),
+            block_hash,
+        }
+    }
+
+    #[test]
+    fn conflicting_vote_is_refused_after_restart() {
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        let voted = Some(H256::random().into());
+        let other = Some(H256::random().into());
+
+        let mut journal = VoteJournal::load(Arc::clone(&db));
+        assert_eq!(JournalResult::Recorded, journal.record(&vote(3, 1, Step::Propose, voted)));
+        assert_eq!(JournalResult::Recorded, journal.record(&vote(3, 1, Step::Prevote, voted)));
+        drop(journal);
+
+        let mut restarted = VoteJournal::load(Arc::clone(&db));
+        assert_eq!(JournalResult::Conflict(voted), restarted.record(&vote(3, 1, Step::Propose, other)));
+        assert_eq!(JournalResult::Conflict(voted), restarted.record(&vote(3, 1, Step::Prevote, None)));
+        assert_eq!(JournalResult::Recorded, restarted.record(&vote(3, 1, Step::Prevote, voted)), "The same vote");
+        assert_eq!(JournalResult::Recorded, restarted.record(&vote(3, 1, Step::Precommit, other)));
+        assert_eq!(JournalResult::Recorded, restarted.record(&vote(3, 2, Step::Prevote, other)));
+    }
+
+    #[test]
+    fn next_height_is_voted_after_restart() {
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap()));
+        let voted = Some(H256::random().into());
+        let next = Some(H256::random().into());
+
+        let mut journal = VoteJournal::load(Arc::clone(&db));
+        assert_eq!(JournalResult::Recorded, journal.record(&vote(3, 0, Step::Precommit, voted)));
+        drop(journal);
+
+        let mut restarted = VoteJournal::load(Arc::clone(&db));
+        restarted.prune(4);
+        assert_eq!(JournalResult::Recorded, restarted.record(&vote(4, 0, Step::Prevote, next)));
+        drop(restarted);
+
+        let restarted = VoteJournal::load(db);
+        assert_eq!(vec![VoteStep::new(4, 0, Step::Prevote)], restarted.votes.keys().cloned().collect::<Vec<_>>());
+    }
+}
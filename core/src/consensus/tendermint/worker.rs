@@ -20,20 +20,24 @@ use super::message::*;
 use super::network;
 use super::params::TimeGapParams;
 use super::stake::CUSTOM_ACTION_HANDLER_ID;
-use super::types::{Height, Proposal, Step, TendermintSealView, TendermintState, TwoThirdsMajority, View};
+use super::types::{
+    Height, Proposal, ProposalBlocks, SeenProposals, Step, TendermintSealView, TendermintState, TwoThirdsMajority, View,
+};
 use super::vote_collector::{DoubleVote, VoteCollector};
+use super::vote_journal::{JournalResult, VoteJournal};
 use super::vote_regression_checker::VoteRegressionChecker;
 use super::{
     ENGINE_TIMEOUT_BROADCAST_STEP_STATE, ENGINE_TIMEOUT_EMPTY_PROPOSAL, ENGINE_TIMEOUT_TOKEN_NONCE_BASE, SEAL_FIELDS,
 };
 use crate::account_provider::AccountProvider;
 use crate::block::*;
-use crate::client::ConsensusClient;
+use crate::client::{ConsensusClient, OrphanedProposal};
 use crate::consensus::signer::EngineSigner;
 use crate::consensus::validator_set::{DynamicValidator, ValidatorSet};
 use crate::consensus::{EngineError, Seal};
 use crate::encoded;
 use crate::error::{BlockError, Error};
+use crate::lock_order::{self, LockClass};
 use crate::transaction::{SignedTransaction, UnverifiedTransaction};
 use crate::views::BlockView;
 use crate::BlockId;
@@ -43,6 +47,7 @@ use crossbeam_channel as crossbeam;
 use ctypes::transaction::{Action, Transaction};
 use ctypes::util::unexpected::Mismatch;
 use ctypes::{BlockHash, BlockNumber, Header};
+use parking_lot::RwLock;
 use primitives::{u256_from_u128, Bytes, U256};
 use rlp::{Encodable, Rlp};
 use std::cell::Cell;
@@ -61,8 +66,8 @@ type SpawnResult = (
     crossbeam::Sender<()>,
 );
 
-pub fn spawn(validators: Arc<DynamicValidator>) -> SpawnResult {
-    Worker::spawn(validators)
+pub fn spawn(validators: Arc<DynamicValidator>, proposal_blocks: Arc<RwLock<ProposalBlocks>>) -> SpawnResult {
+    Worker::spawn(validators, proposal_blocks)
 }
 
 struct Worker {
@@ -83,6 +88,10 @@ struct Worker {
     last_two_thirds_majority: TwoThirdsMajority,
     /// hash of the proposed block, used for seal submission.
     proposal: Proposal,
+    /// The proposal blocks of the current height, shared with the engine.
+    proposal_blocks: Arc<RwLock<ProposalBlocks>>,
+    /// The imported proposals that are reported when they are orphaned.
+    seen_proposals: SeenProposals,
     /// The finalized view of the previous height's block.
     /// The signatures for the previous block is signed for the view below.
     finalized_view_of_previous_block: View,
@@ -95,6 +104,8 @@ struct Worker {
     time_gap_params: TimeGapParams,
     timeout_token_nonce: usize,
     vote_regression_checker: VoteRegressionChecker,
+    /// The votes signed before, loaded from the database on the first use.
+    vote_journal: Option<VoteJournal>,
 }
 
 pub enum Event {
@@ -178,6 +189,7 @@ impl Worker {
         extension: EventSender<network::Event>,
         client: Weak<dyn ConsensusClient>,
         time_gap_params: TimeGapParams,
+        proposal_blocks: Arc<RwLock<ProposalBlocks>>,
     ) -> Self {
         Worker {
             client,
@@ -188,6 +200,8 @@ impl Worker {
             signer: Default::default(),
             last_two_thirds_majority: TwoThirdsMajority::Empty,
             proposal: Proposal::None,
+            proposal_blocks,
+            seen_proposals: SeenProposals::default(),
             finalized_view_of_previous_block: 0,
             finalized_view_of_current_block: None,
             validators,
@@ -196,10 +210,11 @@ impl Worker {
             time_gap_params,
             timeout_token_nonce: ENGINE_TIMEOUT_TOKEN_NONCE_BASE,
             vote_regression_checker: VoteRegressionChecker::new(),
+            vote_journal: None,
         }
     }
 
-    fn spawn(validators: Arc<DynamicValidator>) -> SpawnResult {
+    fn spawn(validators: Arc<DynamicValidator>, proposal_blocks: Arc<RwLock<ProposalBlocks>>) -> SpawnResult {
         let (sender, receiver) = crossbeam::unbounded();
         let (quit, quit_receiver) = crossbeam::bounded(1);
         let (external_params_initializer, external_params_receiver) = crossbeam::bounded(1);
@@ -248,7 +263,7 @@ impl Worker {
                 }
                 };
                 validators.register_client(Weak::clone(&client));
-                let mut inner = Self::new(validators, extension, client, time_gap_params);
+                let mut inner = Self::new(validators, extension, client, time_gap_params, proposal_blocks);
                 loop {
                     crossbeam::select! {
                     recv(receiver) -> msg => {
@@ -605,8 +620,9 @@ impl Worker {
             .unwrap();
     }
 
+    /// The miner calls back into this worker to seal the block, so the request is only queued.
     fn update_sealing(&self, parent_block_hash: BlockHash) {
-        self.client().update_sealing(BlockId::Hash(parent_block_hash), true);
+        self.client().update_sealing_async(BlockId::Hash(parent_block_hash), true);
     }
 
     /// Do we need this function?
@@ -639,10 +655,13 @@ impl Worker {
         self.height += 1;
         self.view = 0;
         self.proposal = Proposal::None;
+        self.proposal_blocks.write().move_to_height(self.height);
         self.votes_received = MutTrigger::new(BitSet::new());
         self.finalized_view_of_previous_block =
             self.finalized_view_of_current_block.expect("self.step == Step::Commit");
         self.finalized_view_of_current_block = None;
+        self.report_orphaned_proposals();
+        self.vote_journal().prune(self.height);
     }
 
     /// Jump to the height.
@@ -657,13 +676,54 @@ impl Worker {
         self.height = height;
         self.view = 0;
         self.proposal = Proposal::None;
+        self.proposal_blocks.write().move_to_height(self.height);
         self.votes_received = MutTrigger::new(BitSet::new());
         self.finalized_view_of_previous_block = finalized_view_of_previous_height;
         self.finalized_view_of_current_block = None;
+        self.report_orphaned_proposals();
+        self.vote_journal().prune(self.height);
+    }
+
+    fn vote_journal(&mut self) -> &mut VoteJournal {
+        if self.vote_journal.is_none() {
+            self.vote_journal = Some(VoteJournal::load(self.client().get_kvdb()));
+        }
+        self.vote_journal.as_mut().expect("The journal is loaded above")
+    }
+
+    /// Writes the vote to the journal before it's signed.
+    /// Returns false if a different block was voted at the same step, even before a restart.
+    fn journal_vote(&mut self, on: &VoteOn) -> bool {
+        match self.vote_journal().record(on) {
+            JournalResult::Recorded => true,
+            JournalResult::Conflict(recorded) => {
+                cerror!(
+                    ENGINE,
+                    "Refused to sign {:?} on {:?}, because {:?} was voted at the step. Did the node restart?",
+                    on.step,
+                    on.block_hash,
+                    recorded
+                );
+                false
+            }
+        }
+    }
+
+    /// Reports the proposals of the committed heights that didn't become the canonical blocks.
+    /// The client only queues them, so it doesn't delay the consensus.
+    fn report_orphaned_proposals(&mut self) {
+        let client = self.client();
+        let orphaned =
+            self.seen_proposals.take_orphaned(self.height, |height| client.block_hash(&BlockId::Number(height)));
+        if !orphaned.is_empty() {
+            cinfo!(ENGINE, "{} proposals are orphaned below height {}", orphaned.len(), self.height);
+            client.record_orphaned_proposals(orphaned);
+        }
     }
 
     #[allow(clippy::cognitive_complexity)]
     fn move_to_step(&mut self, state: TendermintState, is_restoring: bool) {
+        let _lock_order = lock_order::acquire(LockClass::ConsensusStep);
         ctrace!(ENGINE, "Transition to {:?} triggered from {:?}.", state, self.step);
         let prev_step = mem::replace(&mut self.step, state.clone());
         if !is_restoring {
@@ -943,6 +1003,13 @@ impl Worker {
         let height = proposal.number() as Height;
         let seal_view = TendermintSealView::new(proposal.seal());
         let parent_block_finalized_view = seal_view.parent_block_finalized_view().expect("The proposal is verified");
+        self.seen_proposals.insert(OrphanedProposal {
+            height,
+            view: seal_view.author_view().expect("The proposal is verified"),
+            hash: proposal.hash(),
+            author: *proposal.author(),
+            is_own: self.signer.is_address(proposal.author()),
+        });
         let on = VoteOn {
             step: VoteStep::new(height - 1, parent_block_finalized_view, Step::Precommit),
             block_hash: Some(*proposal.parent_hash()),
@@ -1147,7 +1214,9 @@ impl Worker {
         }
         debug_assert_eq!(Ok(self.view), TendermintSealView::new(header.seal()).author_view());
 
-        self.vote_on_header_for_proposal(&header).expect("I'm a proposer");
+        if self.vote_on_header_for_proposal(&header).expect("I'm a proposer").is_none() {
+            return
+        }
 
         self.step = TendermintState::ProposeWaitImported {
             block: Box::new(sealed_block.clone()),
@@ -1500,7 +1569,9 @@ impl Worker {
 
     fn repropose_block(&mut self, block: encoded::Block) {
         let header = block.decode_header();
-        self.vote_on_header_for_proposal(&header).expect("I am proposer");
+        if self.vote_on_header_for_proposal(&header).expect("I am proposer").is_none() {
+            return
+        }
         self.proposal = Proposal::new_imported(header.hash());
         self.broadcast_proposal_block(self.view, block);
     }
@@ -1541,6 +1612,9 @@ impl Worker {
             block_hash,
         };
         assert!(self.vote_regression_checker.check(&on), "Vote should not regress");
+        if !self.journal_vote(&on) {
+            return Ok(None)
+        }
 
         let signature = self.signer.sign(on.hash())?;
 
@@ -1556,7 +1630,7 @@ impl Worker {
         Ok(Some(vote))
     }
 
-    fn vote_on_header_for_proposal(&mut self, header: &Header) -> Result<ConsensusMessage, Error> {
+    fn vote_on_header_for_proposal(&mut self, header: &Header) -> Result<Option<ConsensusMessage>, Error> {
         assert_eq!(header.number(), self.height);
 
         let parent_hash = header.parent_hash();
@@ -1568,6 +1642,9 @@ impl Worker {
             block_hash: Some(header.hash()),
         };
         assert!(self.vote_regression_checker.check(&on), "Vote should not regress");
+        if !self.journal_vote(&on) {
+            return Ok(None)
+        }
 
         let signature = self.signer.sign(on.hash())?;
 
@@ -1579,7 +1656,7 @@ impl Worker {
 
         self.votes.collect(vote.clone()).expect("Must not attempt double vote on proposal");
         cinfo!(ENGINE, "Voted {:?} as {}th proposer.", vote, signer_index);
-        Ok(vote)
+        Ok(Some(vote))
     }
 
     fn recover_proposal_vote(
@@ -1740,7 +1817,13 @@ impl Worker {
 
         // This block borrows bytes
         {
-            let block_view = BlockView::new(&bytes);
+            let block_view = match BlockView::new_checked(&bytes, SEAL_FIELDS) {
+                Ok(block_view) => block_view,
+                Err(err) => {
+                    cwarn!(ENGINE, "Malformed proposal block: {:?}", err);
+                    return None
+                }
+            };
             let header_view = block_view.header();
             let number = header_view.number();
             cinfo!(ENGINE, "Proposal received for {}-{:?}", number, header_view.hash());
@@ -1827,6 +1910,10 @@ impl Worker {
                 self.report_double_vote(&double_vote);
                 return None
             }
+
+            if number == self.height as u64 {
+                self.proposal_blocks.write().insert(self.height, proposed_view, encoded::Block::new(bytes.clone()));
+            }
         }
 
         Some(c)
@@ -2028,9 +2115,12 @@ impl Worker {
         if self.step.is_commit() {
             return None
         }
-        let block_hash = {
-            let block_view = BlockView::new(&block);
-            block_view.hash()
+        let block_hash = match BlockView::new_checked(&block, SEAL_FIELDS) {
+            Ok(block_view) => block_view.hash(),
+            Err(err) => {
+                cwarn!(ENGINE, "Malformed commit block: {:?}", err);
+                return None
+            }
         };
 
         if votes.is_empty() {
@@ -2245,3 +2335,40 @@ impl<T> std::ops::DerefMut for MutTrigger<T> {
         &mut self.target
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::TestBlockChainClient;
+    use crate::scheme::Scheme;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    /// Moving to the propose step after a commit used to run the miner inline.
+    /// The miner waits for this worker to generate the seal, so the worker never returned.
+    #[test]
+    fn sealing_requested_during_commit_does_not_wait_for_the_miner() {
+        let client = Arc::new(TestBlockChainClient::new_with_scheme(Scheme::new_test_tendermint()));
+        let consensus_client = Arc::clone(&client) as Arc<dyn ConsensusClient>;
+        let weak_client = Arc::downgrade(&consensus_client);
+        let parent = client.genesis_hash;
+
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let (extension, _) = crossbeam::unbounded();
+            let time_gap_params = TimeGapParams {
+                allowed_past_gap: Duration::from_secs(30),
+                allowed_future_gap: Duration::from_secs(5),
+            };
+            let validators = Arc::new(DynamicValidator::new(Vec::new()));
+            let worker = Worker::new(validators, extension, weak_client, time_gap_params, Default::default());
+
+            // The worker holds the step while it commits and moves to the next height.
+            let _lock_order = lock_order::acquire(LockClass::ConsensusStep);
+            worker.update_sealing(parent);
+            sender.send(()).unwrap();
+        });
+        receiver.recv_timeout(Duration::from_secs(5)).expect("The worker must not wait for the miner");
+        assert_eq!(vec![(BlockId::Hash(parent), true)], *client.sealing_requests.read());
+    }
+}
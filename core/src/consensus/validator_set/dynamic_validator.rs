@@ -39,7 +39,7 @@ impl DynamicValidator {
         }
     }
 
-    fn validators(&self, parent: BlockHash) -> Option<Vec<Validator>> {
+    fn elected_validators(&self, parent: BlockHash) -> Option<Vec<Validator>> {
         let client: Arc<dyn ConsensusClient> =
             self.client.read().as_ref().and_then(Weak::upgrade).expect("Client is not initialized");
         let block_id = parent.into();
@@ -63,11 +63,11 @@ impl DynamicValidator {
     }
 
     fn validators_pubkey(&self, parent: BlockHash) -> Option<Vec<Public>> {
-        self.validators(parent).map(|validators| validators.into_iter().map(|val| *val.pubkey()).collect())
+        self.elected_validators(parent).map(|validators| validators.into_iter().map(|val| *val.pubkey()).collect())
     }
 
     pub fn proposer_index(&self, parent: BlockHash, prev_proposer_index: usize, proposed_view: usize) -> usize {
-        if let Some(validators) = self.validators(parent) {
+        if let Some(validators) = self.elected_validators(parent) {
             let num_validators = validators.len();
             proposed_view % num_validators
         } else {
@@ -134,7 +134,7 @@ impl ValidatorSet for DynamicValidator {
     }
 
     fn count(&self, parent: &BlockHash) -> usize {
-        if let Some(validators) = self.validators(*parent) {
+        if let Some(validators) = self.elected_validators(*parent) {
             validators.len()
         } else {
             self.initial_list.count(parent)
@@ -142,7 +142,7 @@ impl ValidatorSet for DynamicValidator {
     }
 
     fn check_enough_votes(&self, parent: &BlockHash, votes: &BitSet) -> Result<(), EngineError> {
-        if let Some(validators) = self.validators(*parent) {
+        if let Some(validators) = self.elected_validators(*parent) {
             let mut voted_delegation = 0u64;
             let n_validators = validators.len();
             for index in votes.true_index_iter() {
@@ -186,6 +186,10 @@ impl ValidatorSet for DynamicValidator {
             self.initial_list.addresses(parent)
         }
     }
+
+    fn validators(&self, parent: &BlockHash) -> Vec<Validator> {
+        self.elected_validators(*parent).unwrap_or_else(|| self.initial_list.validators(parent))
+    }
 }
 
 #[cfg(test)]
@@ -17,6 +17,7 @@
 use self::validator_list::RoundRobinValidator;
 use super::BitSet;
 use crate::client::ConsensusClient;
+use crate::consensus::stake::Validator;
 use crate::consensus::EngineError;
 use ckey::{Address, Public};
 use ctypes::BlockHash;
@@ -56,4 +57,8 @@ pub trait ValidatorSet: Send + Sync {
     fn register_client(&self, _client: Weak<dyn ConsensusClient>) {}
 
     fn addresses(&self, _parent: &BlockHash) -> Vec<Address>;
+
+    /// Returns the validators in the order of their indices.
+    /// The validators that are not elected by staking have no stake.
+    fn validators(&self, parent: &BlockHash) -> Vec<Validator>;
 }
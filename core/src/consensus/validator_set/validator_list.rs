@@ -17,6 +17,7 @@
 use super::super::BitSet;
 use super::ValidatorSet;
 use crate::client::ConsensusClient;
+use crate::consensus::stake::Validator;
 use crate::consensus::EngineError;
 use crate::types::BlockId;
 use ckey::{public_to_address, Address, Public};
@@ -106,6 +107,10 @@ impl ValidatorSet for RoundRobinValidator {
     fn addresses(&self, _parent: &BlockHash) -> Vec<Address> {
         self.validators.iter().map(public_to_address).collect()
     }
+
+    fn validators(&self, _parent: &BlockHash) -> Vec<Validator> {
+        self.validators.iter().cloned().map(Validator::without_stake).collect()
+    }
 }
 
 #[cfg(test)]
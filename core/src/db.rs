@@ -34,8 +34,40 @@ pub const COL_EXTRA: Option<u32> = Some(3);
 pub const COL_MEMPOOL: Option<u32> = Some(4);
 /// Column for Transaction error hints
 pub const COL_ERROR_HINT: Option<u32> = Some(5);
+/// Column for the address index
+pub const COL_ADDRESS_INDEX: Option<u32> = Some(6);
+/// Column for the asset index
+pub const COL_ASSET_INDEX: Option<u32> = Some(7);
+/// Column for the proposals that are not committed
+pub const COL_ORPHANED_PROPOSALS: Option<u32> = Some(8);
+/// Column for the text index
+pub const COL_TEXT_INDEX: Option<u32> = Some(9);
+/// Column for the votes signed by the consensus engine
+pub const COL_VOTE_JOURNAL: Option<u32> = Some(10);
+/// Column for the asset logs and their blooms
+pub const COL_ASSET_LOG: Option<u32> = Some(11);
 /// Number of columns in DB
-pub const NUM_COLUMNS: Option<u32> = Some(6);
+pub const NUM_COLUMNS: Option<u32> = Some(12);
+/// Names of the columns in the order of their indices
+pub const COLUMN_NAMES: [&str; 12] = [
+    "state",
+    "headers",
+    "bodies",
+    "extra",
+    "mempool",
+    "error_hint",
+    "address_index",
+    "asset_index",
+    "orphaned_proposals",
+    "text_index",
+    "vote_journal",
+    "asset_log",
+];
+
+/// Returns the column named `name`.
+pub fn column_by_name(name: &str) -> Option<Option<u32>> {
+    COLUMN_NAMES.iter().position(|column| *column == name).map(|index| Some(index as u32))
+}
 
 /// Modes for updating caches.
 #[derive(Clone, Copy)]
@@ -0,0 +1,186 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use kvdb_memorydb::InMemory;
+use rocksdb::{Column, Options, DB};
+use std::io;
+use std::path::Path;
+
+/// The number of levels RocksDB uses by default.
+const NUM_LEVELS: u32 = 7;
+
+/// Statistics of a database column.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnStats {
+    /// The estimated number of keys.
+    pub keys: u64,
+    /// The approximate size of the column on disk in bytes.
+    pub size: u64,
+    /// The number of files that store the column.
+    pub files: u64,
+}
+
+/// Maintenance operations that `KeyValueDB` doesn't provide.
+///
+/// The defaults suit databases that have nothing to compact and nothing to report.
+pub trait DatabaseMaintenance {
+    /// The statistics of the column, or `None` if the database doesn't track them.
+    fn column_stats(&self, _col: Option<u32>) -> io::Result<Option<ColumnStats>> {
+        Ok(None)
+    }
+
+    /// Compacts the whole column.
+    fn compact(&self, _col: Option<u32>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl DatabaseMaintenance for InMemory {}
+
+/// A RocksDB database opened for maintenance.
+///
+/// It holds the database lock, so it must not be used while a node is running on the same database.
+pub struct RocksDbMaintenance {
+    db: DB,
+    columns: Vec<Column>,
+}
+
+impl RocksDbMaintenance {
+    /// Opens the database at `path`, which has `num_columns` columns besides the default one.
+    pub fn open(path: &Path, num_columns: Option<u32>) -> io::Result<Self> {
+        let path = path.to_str().ok_or_else(|| invalid_input("The database path is not valid UTF-8"))?;
+        let names: Vec<String> = (0..num_columns.unwrap_or(0)).map(|col| format!("col{}", col)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let options: Vec<Options> = names.iter().map(|_| Options::new()).collect();
+
+        let mut db_options = Options::new();
+        db_options.create_if_missing(false);
+        let db = DB::open_cf(&db_options, path, &names, &options).map_err(|err| {
+            if is_lock_error(&err, path) {
+                io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    format!("The database at {} is in use by another process", path),
+                )
+            } else {
+                other(err)
+            }
+        })?;
+        let columns =
+            names.iter().map(|name| db.cf_handle(name).expect("open_cf opens a handle for each column")).collect();
+        Ok(Self {
+            db,
+            columns,
+        })
+    }
+
+    fn column(&self, col: u32) -> io::Result<Column> {
+        self.columns.get(col as usize).cloned().ok_or_else(|| invalid_input(format!("No such column: {}", col)))
+    }
+
+    fn property(&self, col: Option<u32>, name: &str) -> io::Result<u64> {
+        let value = match col {
+            Some(col) => self.db.property_int_value_cf(self.column(col)?, name),
+            None => self.db.property_int_value(name),
+        };
+        Ok(value.map_err(other)?.unwrap_or(0))
+    }
+}
+
+impl DatabaseMaintenance for RocksDbMaintenance {
+    fn column_stats(&self, col: Option<u32>) -> io::Result<Option<ColumnStats>> {
+        let mut files = 0;
+        for level in 0..NUM_LEVELS {
+            files += self.property(col, &format!("rocksdb.num-files-at-level{}", level))?;
+        }
+        Ok(Some(ColumnStats {
+            keys: self.property(col, "rocksdb.estimate-num-keys")?,
+            size: self.property(col, "rocksdb.total-sst-files-size")?,
+            files,
+        }))
+    }
+
+    fn compact(&self, col: Option<u32>) -> io::Result<()> {
+        match col {
+            Some(col) => self.db.compact_range_cf(self.column(col)?, None, None),
+            None => self.db.compact_range(None, None),
+        }
+        Ok(())
+    }
+}
+
+/// Tells whether RocksDB failed to lock the database at `path`.
+///
+/// parity-rocksdb reports the status of RocksDB only as a string, so the failure is recognized by
+/// the status code of an I/O error and the lock file, which the other I/O errors don't name.
+fn is_lock_error(err: &str, path: &str) -> bool {
+    let lock_file = format!("{}/LOCK:", path.trim_end_matches('/'));
+    err.starts_with("IO error: ") && err.contains(&lock_file)
+}
+
+fn invalid_input<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err)
+}
+
+fn other<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::NUM_COLUMNS;
+    use kvdb_rocksdb::{Database, DatabaseConfig};
+    use tempfile::tempdir;
+
+    #[test]
+    fn in_memory_database_has_nothing_to_report_or_compact() {
+        let db = kvdb_memorydb::create(NUM_COLUMNS.unwrap());
+        for col in 0..NUM_COLUMNS.unwrap() {
+            assert_eq!(None, db.column_stats(Some(col)).unwrap());
+            db.compact(Some(col)).unwrap();
+        }
+    }
+
+    #[test]
+    fn only_the_lock_failures_are_lock_errors() {
+        let path = "/data/db";
+        assert!(is_lock_error("IO error: While lock file: /data/db/LOCK: Resource temporarily unavailable", path));
+        assert!(is_lock_error("IO error: lock /data/db/LOCK: No locks available", path));
+        assert!(is_lock_error("IO error: lock /data/db/LOCK: No locks available", "/data/db/"));
+        assert!(!is_lock_error("IO error: While open a file for random read: /data/db/000012.sst: Blocked", path));
+        assert!(!is_lock_error("IO error: While opening /data/db/LOCKED: No such file or directory", path));
+        assert!(!is_lock_error("Corruption: /data/db/LOCK: bad", path));
+        assert!(!is_lock_error("IO error: lock /other/db/LOCK: No locks available", path));
+    }
+
+    #[test]
+    fn refuse_to_open_database_in_use() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let db = Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), path).unwrap();
+
+        let err = RocksDbMaintenance::open(dir.path(), NUM_COLUMNS).err().expect("The database is locked");
+        assert_eq!(io::ErrorKind::WouldBlock, err.kind());
+
+        drop(db);
+        let maintenance = RocksDbMaintenance::open(dir.path(), NUM_COLUMNS).unwrap();
+        for col in 0..NUM_COLUMNS.unwrap() {
+            maintenance.compact(Some(col)).unwrap();
+            assert!(maintenance.column_stats(Some(col)).unwrap().is_some());
+        }
+    }
+}
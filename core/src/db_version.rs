@@ -13,12 +13,29 @@
 //
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::blockchain::ShrinkAssetIndexKeys;
 use crate::db;
 use kvdb::{DBTransaction, KeyValueDB};
+use rlp::{Rlp, RlpStream};
+use std::fmt;
+use std::io;
 
 pub const VERSION_KEY_PREFIX: &[u8] = b"version_";
 /// Save the version of Tendermint backup where the key below is pointing
 pub const VERSION_KEY_TENDERMINT_BACKUP: &[u8] = b"version_tendermint-backup";
+/// Save the version of the layout of the columns, which is the version of the last migration applied
+pub const VERSION_KEY_SCHEMA: &[u8] = b"version_schema";
+
+const MIGRATION_PROGRESS_KEY_PREFIX: &[u8] = b"migration-progress-";
+
+/// The migrations in the order of their versions. The n-th migration upgrades the schema to the version n.
+const MIGRATIONS: &[&dyn Migration] = &[&ShrinkAssetIndexKeys];
+
+/// The schema version this binary reads and writes
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// The number of the entries a migration writes in a batch
+pub const MIGRATION_BATCH_SIZE: usize = 10_000;
 
 /// To support data values that are saved before the version scheme return 0 if the version does not exist
 pub fn get_version(db: &dyn KeyValueDB, key: &[u8]) -> u32 {
@@ -37,3 +54,233 @@ pub fn set_version(batch: &mut DBTransaction, key: &[u8], value: u32) {
     );
     batch.put(db::COL_EXTRA, key, &rlp::encode(&value));
 }
+
+/// A change of the layout of the columns.
+///
+/// A migration reads the entries in a single pass, and writes every `batch_size` of them together with its
+/// progress by `save_progress`. So a migration interrupted by a crash resumes from the last batch written.
+pub trait Migration {
+    /// The schema version that the migration upgrades the database to
+    fn version(&self) -> u32;
+
+    fn description(&self) -> &'static str;
+
+    /// Migrates the entries left, and returns the number of the entries migrated in total.
+    /// `on_batch` is called with the number after each batch is written. An error from it stops the migration.
+    fn migrate(
+        &self,
+        db: &dyn KeyValueDB,
+        batch_size: usize,
+        on_batch: &mut dyn FnMut(u64) -> io::Result<()>,
+    ) -> io::Result<u64>;
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The database is written by a binary newer than this one.
+    NewerSchema {
+        found: u32,
+        supported: u32,
+    },
+    Io(io::Error),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MigrationError::NewerSchema {
+                found,
+                supported,
+            } => write!(
+                f,
+                "The database schema version {} is newer than {}, the latest one this binary supports",
+                found, supported
+            ),
+            MigrationError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<io::Error> for MigrationError {
+    fn from(err: io::Error) -> Self {
+        MigrationError::Io(err)
+    }
+}
+
+/// Returns the migrations that the database needs, in the order they should run.
+pub fn pending_migrations(db: &dyn KeyValueDB) -> Result<Vec<&'static dyn Migration>, MigrationError> {
+    let version = get_version(db, VERSION_KEY_SCHEMA);
+    if version > SCHEMA_VERSION {
+        return Err(MigrationError::NewerSchema {
+            found: version,
+            supported: SCHEMA_VERSION,
+        })
+    }
+    Ok(MIGRATIONS[version as usize..].to_vec())
+}
+
+/// Runs the pending migrations, resuming the one that was interrupted.
+pub fn migrate_database(db: &dyn KeyValueDB, batch_size: usize) -> Result<(), MigrationError> {
+    for migration in pending_migrations(db)? {
+        let version = migration.version();
+        cinfo!(CLIENT, "Migrating the database to the schema version {}: {}", version, migration.description());
+        let migrated = migration.migrate(db, batch_size, &mut |migrated| {
+            cinfo!(CLIENT, "Migrated {} entries to the schema version {}", migrated, version);
+            Ok(())
+        })?;
+        let mut batch = DBTransaction::new();
+        set_version(&mut batch, VERSION_KEY_SCHEMA, version);
+        batch.delete(db::COL_EXTRA, &progress_key(version));
+        db.write(batch)?;
+        cinfo!(CLIENT, "Migrated the database to the schema version {} ({} entries)", version, migrated);
+    }
+    Ok(())
+}
+
+/// Returns the number of the entries migrated and the cursor that the migration saved with its last batch.
+pub fn load_progress(db: &dyn KeyValueDB, version: u32) -> io::Result<Option<(u64, Vec<u8>)>> {
+    let bytes = match db.get(db::COL_EXTRA, &progress_key(version))? {
+        Some(bytes) => bytes,
+        None => return Ok(None),
+    };
+    let rlp = Rlp::new(&bytes);
+    let decode_error =
+        |err| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid migration progress: {:?}", err));
+    let migrated = rlp.val_at(0).map_err(decode_error)?;
+    let cursor = rlp.val_at(1).map_err(decode_error)?;
+    Ok(Some((migrated, cursor)))
+}
+
+pub fn save_progress(batch: &mut DBTransaction, version: u32, migrated: u64, cursor: &[u8]) {
+    let mut s = RlpStream::new_list(2);
+    s.append(&migrated).append(&cursor.to_vec());
+    batch.put(db::COL_EXTRA, &progress_key(version), &s.out());
+}
+
+fn progress_key(version: u32) -> Vec<u8> {
+    let mut key = MIGRATION_PROGRESS_KEY_PREFIX.to_vec();
+    key.extend_from_slice(&version.to_be_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{COL_ASSET_INDEX, NUM_COLUMNS};
+
+    const OLD_KEY_LENGTH: usize = 2 + 20 + 32 + 8;
+
+    fn old_key(lock_script_hash: u8, index: u64) -> Vec<u8> {
+        let mut key = vec![0, 0];
+        key.extend_from_slice(&[lock_script_hash; 20]);
+        key.extend_from_slice(&[0xab; 32]);
+        key.extend_from_slice(&index.to_be_bytes());
+        key
+    }
+
+    fn new_key(lock_script_hash: u8, index: u32) -> Vec<u8> {
+        let mut key = vec![0, 0];
+        key.extend_from_slice(&[lock_script_hash; 20]);
+        key.extend_from_slice(&[0xab; 32]);
+        key.extend_from_slice(&index.to_be_bytes());
+        key
+    }
+
+    fn db_with_old_asset_index() -> kvdb_memorydb::InMemory {
+        let db = kvdb_memorydb::create(NUM_COLUMNS.unwrap());
+        let mut batch = DBTransaction::new();
+        for lock_script_hash in 1..=3 {
+            for index in 0..2 {
+                batch.put(COL_ASSET_INDEX, &old_key(lock_script_hash, index), &[]);
+            }
+        }
+        db.write(batch).unwrap();
+        db
+    }
+
+    fn asset_index_keys(db: &dyn KeyValueDB) -> Vec<Vec<u8>> {
+        db.iter(COL_ASSET_INDEX).map(|(key, _)| key.to_vec()).collect()
+    }
+
+    #[test]
+    fn migrations_are_numbered_in_order() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(index as u32 + 1, migration.version());
+        }
+    }
+
+    #[test]
+    fn fresh_database_is_migrated_to_the_latest_version() {
+        let db = kvdb_memorydb::create(NUM_COLUMNS.unwrap());
+        assert_eq!(MIGRATIONS.len(), pending_migrations(&db).unwrap().len());
+
+        migrate_database(&db, MIGRATION_BATCH_SIZE).unwrap();
+        assert_eq!(SCHEMA_VERSION, get_version(&db, VERSION_KEY_SCHEMA));
+        assert!(pending_migrations(&db).unwrap().is_empty());
+    }
+
+    #[test]
+    fn asset_index_keys_are_shrunk() {
+        let db = db_with_old_asset_index();
+        migrate_database(&db, MIGRATION_BATCH_SIZE).unwrap();
+
+        let mut expected: Vec<_> =
+            (1..=3).flat_map(|lock_script_hash| (0..2).map(move |index| new_key(lock_script_hash, index))).collect();
+        expected.sort();
+        assert_eq!(expected, asset_index_keys(&db));
+        assert_eq!(None, load_progress(&db, ShrinkAssetIndexKeys.version()).unwrap());
+    }
+
+    #[test]
+    fn interrupted_migration_resumes_from_saved_progress() {
+        let db = db_with_old_asset_index();
+        let mut batches = Vec::new();
+        let result = ShrinkAssetIndexKeys.migrate(&db, 4, &mut |migrated| {
+            batches.push(migrated);
+            // The node stops here, before the migration is done.
+            Err(io::Error::new(io::ErrorKind::Interrupted, "Stopped"))
+        });
+        assert_eq!(io::ErrorKind::Interrupted, result.unwrap_err().kind());
+        assert_eq!(vec![4], batches);
+        let (migrated, cursor) = load_progress(&db, ShrinkAssetIndexKeys.version()).unwrap().unwrap();
+        assert_eq!(4, migrated);
+        assert_eq!(old_key(2, 1), cursor);
+        let keys = asset_index_keys(&db);
+        assert_eq!(2, keys.iter().filter(|key| key.len() == OLD_KEY_LENGTH).count());
+        assert_eq!(0, get_version(&db, VERSION_KEY_SCHEMA));
+
+        let mut batches = Vec::new();
+        let migrated = ShrinkAssetIndexKeys
+            .migrate(&db, 1, &mut |migrated| {
+                batches.push(migrated);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(6, migrated);
+        // The entries migrated before the stop are not read again.
+        assert_eq!(vec![5, 6], batches);
+        migrate_database(&db, 1).unwrap();
+        assert!(asset_index_keys(&db).iter().all(|key| key.len() == OLD_KEY_LENGTH - 4));
+        assert_eq!(6, asset_index_keys(&db).len());
+        assert_eq!(SCHEMA_VERSION, get_version(&db, VERSION_KEY_SCHEMA));
+    }
+
+    #[test]
+    fn newer_schema_is_refused() {
+        let db = kvdb_memorydb::create(NUM_COLUMNS.unwrap());
+        let mut batch = DBTransaction::new();
+        set_version(&mut batch, VERSION_KEY_SCHEMA, SCHEMA_VERSION + 1);
+        db.write(batch).unwrap();
+
+        match migrate_database(&db, MIGRATION_BATCH_SIZE) {
+            Err(MigrationError::NewerSchema {
+                found,
+                supported,
+            }) => {
+                assert_eq!(SCHEMA_VERSION + 1, found);
+                assert_eq!(SCHEMA_VERSION, supported);
+            }
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+}
@@ -16,7 +16,8 @@
 
 //! Lazily-decoded owning views of RLP-encoded blockchain objects.
 //! These views are meant to contain _trusted_ data -- without encoding
-//! errors or inconsistencies.
+//! errors or inconsistencies. Data from untrusted sources must be wrapped
+//! with the `new_checked` constructors.
 //!
 //! In general these views are useful when only a few fields of an object
 //! are relevant. In these cases it's more efficient to decode the object piecemeal.
@@ -30,7 +31,9 @@ use ccrypto::blake256;
 use ckey::Address;
 use ctypes::{BlockHash, BlockNumber, Header as FullHeader, TxHash};
 use primitives::{H256, U256};
-use rlp::Rlp;
+use rlp::{DecoderError, Rlp};
+
+pub use crate::views::HeaderFields;
 
 /// Owning header view.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -44,6 +47,13 @@ impl Header {
         Header(encoded)
     }
 
+    /// Create a new owning header view from untrusted data, e.g. the bytes received from peers.
+    /// Fails unless the header has exactly `seal_fields` seal fields and every other field is decodable.
+    pub fn new_checked(encoded: Vec<u8>, seal_fields: usize) -> Result<Self, DecoderError> {
+        views::HeaderView::new_checked(&encoded, seal_fields)?;
+        Ok(Header(encoded))
+    }
+
     /// Upgrade this encoded view to a fully owned `Header` object.
     pub fn decode(&self) -> FullHeader {
         ::rlp::decode(&self.0).unwrap()
@@ -118,6 +128,39 @@ impl Header {
     pub fn seal(&self) -> Vec<Vec<u8>> {
         self.view().seal()
     }
+
+    /// The fixed-size fields, decoded in a single pass.
+    pub fn decode_fields(&self) -> HeaderFields {
+        self.view().decode_fields()
+    }
+}
+
+// borrowed accessors that don't copy the fields out of the encoded header.
+impl Header {
+    /// Returns the parent hash.
+    pub fn parent_hash_ref(&self) -> &[u8] {
+        self.view().parent_hash_ref()
+    }
+
+    /// Returns the author.
+    pub fn author_ref(&self) -> &[u8] {
+        self.view().author_ref()
+    }
+
+    /// Returns the state root.
+    pub fn state_root_ref(&self) -> &[u8] {
+        self.view().state_root_ref()
+    }
+
+    /// Returns the transaction trie root.
+    pub fn transactions_root_ref(&self) -> &[u8] {
+        self.view().transactions_root_ref()
+    }
+
+    /// Block extra data.
+    pub fn extra_data_ref(&self) -> &[u8] {
+        self.view().extra_data_ref()
+    }
 }
 
 /// Owning block body view.
@@ -131,6 +174,13 @@ impl Body {
         Body(raw)
     }
 
+    /// Create a new owning block body view from untrusted data.
+    /// Fails unless the data is an rlp-encoded block body.
+    pub fn new_checked(raw: Vec<u8>) -> Result<Self, DecoderError> {
+        views::BodyView::new_checked(&raw)?;
+        Ok(Body(raw))
+    }
+
     /// Get a borrowed view of the data within.
     #[inline]
     pub fn view(&self) -> views::BodyView {
@@ -187,6 +237,13 @@ impl Block {
         Block(raw)
     }
 
+    /// Create a new owning block view from untrusted data.
+    /// Fails unless the data is an rlp-encoded block whose header has exactly `seal_fields` seal fields.
+    pub fn new_checked(raw: Vec<u8>, seal_fields: usize) -> Result<Self, DecoderError> {
+        views::BlockView::new_checked(&raw, seal_fields)?;
+        Ok(Block(raw))
+    }
+
     /// Get a borrowed view of the whole block.
     #[inline]
     pub fn view(&self) -> views::BlockView {
@@ -301,3 +358,47 @@ impl Block {
         self.view().transaction_hashes()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::Encodable;
+
+    fn header() -> Header {
+        let mut header = FullHeader::default();
+        header.set_parent_hash(H256::from(1).into());
+        header.set_author(Address::from(2));
+        header.set_state_root(H256::from(3));
+        header.set_transactions_root(H256::from(4));
+        header.set_score(U256::from(5));
+        header.set_number(6);
+        header.set_timestamp(7);
+        header.set_extra_data(b"extra data".to_vec());
+        header.set_seal(vec![::rlp::encode(&8u64), ::rlp::encode(&9u64)]);
+        Header::new(header.rlp_bytes())
+    }
+
+    #[test]
+    fn borrowed_accessors_match_the_owned_ones() {
+        let header = header();
+        assert_eq!(&header.parent_hash()[..], header.parent_hash_ref());
+        assert_eq!(&header.author()[..], header.author_ref());
+        assert_eq!(&header.state_root()[..], header.state_root_ref());
+        assert_eq!(&header.transactions_root()[..], header.transactions_root_ref());
+        assert_eq!(&header.extra_data()[..], header.extra_data_ref());
+    }
+
+    #[test]
+    fn decoded_fields_match_the_owned_accessors() {
+        let header = header();
+        let fields = header.decode_fields();
+        assert_eq!(header.parent_hash(), fields.parent_hash);
+        assert_eq!(header.author(), fields.author);
+        assert_eq!(header.state_root(), fields.state_root);
+        assert_eq!(header.transactions_root(), fields.transactions_root);
+        assert_eq!(header.score(), fields.score);
+        assert_eq!(header.number(), fields.number);
+        assert_eq!(header.timestamp(), fields.timestamp);
+        assert_eq!(HeaderFields::from(&header.decode()), fields);
+    }
+}
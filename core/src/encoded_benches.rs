@@ -0,0 +1,135 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate test;
+
+use self::test::{black_box, Bencher};
+use crate::encoded;
+use ckey::Address;
+use ctypes::Header;
+use primitives::{H256, U256};
+use rlp::Encodable;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+const NUM_HEADERS: usize = 1000;
+
+/// Counts the allocations of each thread, so that the parallel tests don't disturb the counts.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = Cell::new(0);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // It fails while the thread is being destroyed.
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+/// Headers that look like the ones of Tendermint chains.
+fn headers() -> Vec<encoded::Header> {
+    (0..NUM_HEADERS)
+        .map(|number| {
+            let mut header = Header::default();
+            header.set_parent_hash(H256::random().into());
+            header.set_author(Address::random());
+            header.set_state_root(H256::random());
+            header.set_transactions_root(H256::random());
+            header.set_score(U256::from(number));
+            header.set_number(number as u64);
+            header.set_timestamp(number as u64);
+            header.set_extra_data(vec![0; 32]);
+            let signatures = vec![0u8; 64];
+            let bitset = vec![0u8; 8];
+            header.set_seal(vec![
+                ::rlp::encode(&0u64),
+                ::rlp::encode(&1u64),
+                ::rlp::encode(&signatures),
+                ::rlp::encode(&bitset),
+            ]);
+            encoded::Header::new(header.rlp_bytes())
+        })
+        .collect()
+}
+
+/// What the sync extension reads to decide whether a body has to be downloaded.
+fn with_decoded_headers(headers: &[encoded::Header]) -> usize {
+    headers
+        .windows(2)
+        .filter(|pair| pair[0].decode().transactions_root() == pair[1].decode().transactions_root())
+        .count()
+}
+
+fn with_borrowed_accessors(headers: &[encoded::Header]) -> usize {
+    headers.windows(2).filter(|pair| pair[0].transactions_root_ref() == pair[1].transactions_root_ref()).count()
+}
+
+#[test]
+fn borrowed_accessors_do_not_allocate() {
+    let headers = headers();
+    let decoded = count_allocations(|| {
+        black_box(with_decoded_headers(&headers));
+    });
+    let borrowed = count_allocations(|| {
+        black_box(with_borrowed_accessors(&headers));
+    });
+    let fields = count_allocations(|| {
+        for header in &headers {
+            black_box(header.decode_fields());
+        }
+    });
+    assert!(decoded >= NUM_HEADERS, "{} allocations", decoded);
+    assert_eq!(0, borrowed);
+    assert_eq!(0, fields);
+}
+
+#[bench]
+pub fn compare_decoded_headers(bencher: &mut Bencher) {
+    let headers = headers();
+    bencher.iter(|| black_box(with_decoded_headers(&headers)));
+}
+
+#[bench]
+pub fn compare_borrowed_fields(bencher: &mut Bencher) {
+    let headers = headers();
+    bencher.iter(|| black_box(with_borrowed_accessors(&headers)));
+}
+
+#[bench]
+pub fn decode_fields_in_a_single_pass(bencher: &mut Bencher) {
+    let headers = headers();
+    bencher.iter(|| {
+        for header in &headers {
+            black_box(header.decode_fields());
+        }
+    });
+}
@@ -18,8 +18,9 @@ use crate::account_provider::Error as AccountProviderError;
 use crate::consensus::EngineError;
 use cdb::DatabaseError;
 use cio::IoError;
+use cjson::uint::OverflowError;
 use ckey::{Address, Error as KeyError};
-use cstate::StateError;
+use cstate::{CheckpointError, StateError};
 use ctypes::errors::{HistoryError, RuntimeError, SyntaxError};
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
 use ctypes::{BlockHash, BlockNumber};
@@ -38,6 +39,10 @@ pub enum ImportError {
     AlreadyQueued,
     /// Already marked as bad from a previous import (could mean parent is bad).
     KnownBad,
+    /// The queue is stopped because the client is shutting down.
+    Stopped,
+    /// The client doesn't import blocks because it's opened in read-only mode.
+    ReadOnly,
 }
 
 impl fmt::Display for ImportError {
@@ -46,6 +51,8 @@ impl fmt::Display for ImportError {
             ImportError::AlreadyInChain => "block already in chain",
             ImportError::AlreadyQueued => "block already in the block queue",
             ImportError::KnownBad => "block known to be bad",
+            ImportError::Stopped => "the block queue is stopped",
+            ImportError::ReadOnly => "the client is in read-only mode",
         };
 
         f.write_fmt(format_args!("Block import error ({})", msg))
@@ -112,13 +119,17 @@ pub enum BlockError {
     /// Parent given is unknown.
     UnknownParent(BlockHash),
     /// Body size limit is exceeded.
-    BodySizeIsTooBig,
+    BodySizeIsTooBig(OutOfBounds<usize>),
+    /// The block is not an RLP-encoded block of the current engine.
+    MalformedBlock,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SchemeError {
     InvalidCommonParams,
     InvalidState,
+    /// A value doesn't fit in the type of the field.
+    IntegerOverflow(OverflowError),
 }
 
 impl fmt::Display for SchemeError {
@@ -127,6 +138,7 @@ impl fmt::Display for SchemeError {
         let msg: String = match self {
             InvalidCommonParams => "Common params are not matched with gensis block".into(),
             InvalidState => "Genesis state is not same with spec".into(),
+            IntegerOverflow(err) => err.to_string(),
         };
         f.write_fmt(format_args!("Scheme file error ({})", msg))
     }
@@ -153,13 +165,91 @@ impl fmt::Display for BlockError {
             RidiculousNumber(oob) => format!("Implausible block number. {}", oob),
             UnknownParent(hash) => format!("Unknown parent: {}", hash),
             TooManyTransactions(address) => format!("Too many transactions from: {}", address),
-            BodySizeIsTooBig => "Block's body size is too big".to_string(),
+            BodySizeIsTooBig(oob) => format!("Block's body size is too big: {}", oob),
+            MalformedBlock => "Block is malformed".to_string(),
         };
 
         f.write_fmt(format_args!("Block error ({})", msg))
     }
 }
 
+/// The codes of the block import failures.
+///
+/// They are sent to the RPC clients, so a code must never be changed or reused.
+pub mod import_error_codes {
+    pub const ALREADY_IN_CHAIN: i64 = -32070;
+    pub const ALREADY_QUEUED: i64 = -32071;
+    pub const KNOWN_BAD: i64 = -32072;
+    pub const QUEUE_STOPPED: i64 = -32073;
+    /// The same code as the RPC gives to the other writes refused in read-only mode.
+    pub const READ_ONLY: i64 = -32059;
+    pub const UNKNOWN_PARENT: i64 = -32074;
+    pub const INVALID_SEAL: i64 = -32075;
+    pub const TEMPORARILY_INVALID: i64 = -32076;
+    pub const INVALID_HEADER: i64 = -32077;
+    pub const INVALID_BODY: i64 = -32078;
+    pub const INVALID_STATE_ROOT: i64 = -32079;
+    pub const OTHER: i64 = -32080;
+}
+
+impl ImportError {
+    pub fn code(&self) -> i64 {
+        use self::import_error_codes::*;
+        match self {
+            ImportError::AlreadyInChain => ALREADY_IN_CHAIN,
+            ImportError::AlreadyQueued => ALREADY_QUEUED,
+            ImportError::KnownBad => KNOWN_BAD,
+            ImportError::Stopped => QUEUE_STOPPED,
+            ImportError::ReadOnly => READ_ONLY,
+        }
+    }
+}
+
+impl BlockError {
+    pub fn code(&self) -> i64 {
+        use self::import_error_codes::*;
+        use self::BlockError::*;
+        match self {
+            UnknownParent(_) => UNKNOWN_PARENT,
+            InvalidSealArity(_) | InvalidProofOfWork | PowOutOfBounds(_) | InvalidSeal => INVALID_SEAL,
+            TemporarilyInvalid(_) => TEMPORARILY_INVALID,
+            ExtraDataOutOfBounds(_)
+            | ScoreOutOfBounds(_)
+            | InvalidScore(_)
+            | InvalidTimestamp(_)
+            | InvalidParentHash(_)
+            | InvalidNumber(_)
+            | RidiculousNumber(_) => INVALID_HEADER,
+            InvalidTransactionsRoot(_) | TooManyTransactions(_) | BodySizeIsTooBig(_) | MalformedBlock => INVALID_BODY,
+            InvalidStateRoot(_) => INVALID_STATE_ROOT,
+        }
+    }
+}
+
+impl BlockImportError {
+    /// The code of the failure class, one of `import_error_codes`.
+    pub fn code(&self) -> i64 {
+        match self {
+            BlockImportError::Import(err) => err.code(),
+            BlockImportError::Block(err) => err.code(),
+            BlockImportError::Other(_) => import_error_codes::OTHER,
+        }
+    }
+
+    /// Returns true if the block is wrong in itself, so the peer that sent it is to blame.
+    /// Duplicates, blocks from the future and blocks whose parents are not known yet can be sent by honest peers.
+    pub fn is_invalid_block(&self) -> bool {
+        match self {
+            BlockImportError::Import(ImportError::KnownBad) => true,
+            BlockImportError::Import(_) => false,
+            BlockImportError::Block(BlockError::TemporarilyInvalid(_)) => false,
+            BlockImportError::Block(BlockError::UnknownParent(_)) => false,
+            BlockImportError::Block(_) => true,
+            BlockImportError::Other(_) => false,
+        }
+    }
+}
+
 #[derive(Debug)]
 /// General error type which should be capable of representing all errors in codechain
 pub enum Error {
@@ -187,6 +277,7 @@ pub enum Error {
     /// Error concerning a database.
     Database(DatabaseError),
     Rlp(DecoderError),
+    Checkpoint(CheckpointError),
     Other(String),
 }
 
@@ -208,6 +299,7 @@ impl fmt::Display for Error {
             Error::Syntax(err) => err.fmt(f),
             Error::Database(err) => err.fmt(f),
             Error::Rlp(err) => err.fmt(f),
+            Error::Checkpoint(err) => err.fmt(f),
             Error::Other(s) => write!(f, "{}", s),
         }
     }
@@ -237,6 +329,12 @@ impl From<SchemeError> for Error {
     }
 }
 
+impl From<OverflowError> for Error {
+    fn from(err: OverflowError) -> Error {
+        Error::Scheme(SchemeError::IntegerOverflow(err))
+    }
+}
+
 impl From<EngineError> for Error {
     fn from(err: EngineError) -> Error {
         Error::Engine(err)
@@ -282,10 +380,17 @@ impl From<StateError> for Error {
         match err {
             StateError::Trie(err) => Error::Trie(err),
             StateError::Runtime(err) => Error::Runtime(err),
+            StateError::Checkpoint(err) => Error::Checkpoint(err),
         }
     }
 }
 
+impl From<CheckpointError> for Error {
+    fn from(err: CheckpointError) -> Self {
+        Error::Checkpoint(err)
+    }
+}
+
 impl From<RuntimeError> for Error {
     fn from(err: RuntimeError) -> Self {
         Error::Runtime(err)
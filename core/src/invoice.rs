@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::transaction::LocalizedTransaction;
 use ctypes::{Tracker, TxHash};
 
 #[derive(Clone, Debug, PartialEq)]
@@ -22,3 +23,14 @@ pub struct Invoice {
     pub hash: TxHash,
     pub error: Option<String>,
 }
+
+/// A transaction tried with a tracker.
+/// A transaction that failed isn't included in the block, so only its hash and error hint are known.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransactionAttempt {
+    Included(LocalizedTransaction),
+    Failed {
+        hash: TxHash,
+        error_hint: String,
+    },
+}
@@ -31,6 +31,7 @@ extern crate codechain_timer as ctimer;
 extern crate codechain_types as ctypes;
 extern crate codechain_vm as cvm;
 extern crate crossbeam_channel;
+extern crate crossbeam_utils;
 extern crate cuckoo;
 extern crate kvdb;
 extern crate kvdb_memorydb;
@@ -38,6 +39,7 @@ extern crate kvdb_rocksdb;
 extern crate linked_hash_map;
 extern crate lru_cache;
 extern crate num_rational;
+extern crate parity_rocksdb as rocksdb;
 extern crate primitives;
 extern crate rand;
 #[cfg(test)]
@@ -49,6 +51,8 @@ extern crate rlp_derive;
 extern crate parking_lot;
 extern crate snap;
 extern crate table;
+#[cfg(test)]
+extern crate tempfile;
 
 #[macro_use]
 extern crate log;
@@ -59,19 +63,28 @@ mod account_provider;
 pub mod block;
 mod blockchain;
 mod blockchain_info;
+mod chain_time_cache;
 mod client;
 mod codechain_machine;
 mod consensus;
 mod db;
+mod db_maintenance;
 mod db_version;
 pub mod encoded;
+#[cfg(all(feature = "nightly", test))]
+mod encoded_benches;
 mod error;
 mod invoice;
+mod lock_order;
 mod miner;
 mod peer_db;
+mod read_only_db;
 mod scheme;
 mod service;
+mod timed_db;
 mod transaction;
+#[cfg(all(feature = "nightly", test))]
+mod transaction_benches;
 mod types;
 mod verification;
 mod views;
@@ -81,20 +94,36 @@ mod tests;
 
 pub use crate::account_provider::{AccountProvider, Error as AccountProviderError};
 pub use crate::block::Block;
+pub use crate::blockchain::{AddressIndexError, AssetLog, AssetLogError, AssetLogFilter, LocalizedAssetLog, TreeRoute};
 pub use crate::client::{
-    AccountData, AssetClient, BlockChainClient, BlockChainTrait, ChainNotify, Client, ClientConfig, ConsensusClient,
-    DatabaseClient, EngineClient, EngineInfo, ExecuteClient, ImportBlock, MiningBlockChainClient, Shard, StateInfo,
-    TermInfo, TestBlockChainClient, TextClient,
+    AccountData, AssetClient, BackupClient, BackupError, BlockChainClient, BlockChainTrait, BlockRangeError,
+    BlocksInRange, ChainNotify, Client, ClientConfig, ConsensusClient, DatabaseClient, DatabaseColumnConfig,
+    DatabaseColumnOptions, DatabaseCompactionProfile, EngineClient, EngineInfo, ExecuteClient, ImportBlock,
+    MiningBlockChainClient, OrphanedProposal, Shard, StateInfo, TermInfo, TestBlockChainClient, TextClient,
 };
 pub use crate::consensus::stake;
 pub use crate::consensus::{EngineType, TimeGapParams};
-pub use crate::db::{COL_STATE, NUM_COLUMNS};
-pub use crate::error::{BlockImportError, Error, ImportError};
-pub use crate::miner::{MemPoolMinFees, Miner, MinerOptions, MinerService, Stratum, StratumConfig, StratumError};
+pub use crate::db::{column_by_name, COLUMN_NAMES, COL_STATE, NUM_COLUMNS};
+pub use crate::db_maintenance::{ColumnStats, DatabaseMaintenance, RocksDbMaintenance};
+pub use crate::db_version::{
+    migrate_database, pending_migrations, Migration, MigrationError, MIGRATION_BATCH_SIZE, SCHEMA_VERSION,
+};
+pub use crate::error::{import_error_codes, BlockError, BlockImportError, Error, ImportError};
+pub use crate::invoice::TransactionAttempt;
+pub use crate::miner::{
+    FeeBucket, FeeHistogram, FutureReason, MemPoolEntry, MemPoolMinFees, Miner, MinerOptions, MinerService,
+    MinerStatus, PendingSeal, ReadyTransactionsCursor, ReadyTransactionsPage, ResealTimers, SeqGapInfo, Stratum,
+    StratumConfig, StratumError, TxOrigin,
+};
 pub use crate::peer_db::PeerDb;
+pub use crate::read_only_db::ReadOnlyDatabase;
 pub use crate::scheme::Scheme;
 pub use crate::service::ClientService;
+pub use crate::timed_db::TimedDatabase;
 pub use crate::transaction::{
     LocalizedTransaction, PendingSignedTransactions, SignedTransaction, UnverifiedTransaction,
 };
-pub use crate::types::{BlockId, TransactionId};
+pub use crate::types::{
+    BlockId, BlockStatus, ImportMetrics, QueueTimings, StageTimings, TransactionId, HISTOGRAM_BOUNDS_MICROS,
+};
+pub use crate::verification::QueueConfig;
@@ -0,0 +1,116 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Debug-only checks of the order in which the miner and the consensus engine are entered.
+//! The miner calls back into the engine while it seals a block, so entering the miner
+//! from the engine on the same thread deadlocks.
+//! The checks are compiled out in release builds.
+
+#[cfg(debug_assertions)]
+use std::cell::RefCell;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockClass {
+    /// The miner preparing and sealing a block.
+    Miner,
+    /// The consensus engine handling an event or moving to another step.
+    ConsensusStep,
+}
+
+/// The pairs `(first, second)` where `second` must not be entered while `first` is held.
+#[cfg(debug_assertions)]
+const FORBIDDEN: &[(LockClass, LockClass)] = &[(LockClass::ConsensusStep, LockClass::Miner)];
+
+#[cfg(debug_assertions)]
+thread_local! {
+    static HELD: RefCell<Vec<LockClass>> = RefCell::new(Vec::new());
+}
+
+/// Marks that the current thread holds the class until the guard is dropped.
+#[must_use]
+pub struct LockOrderGuard {
+    #[cfg(debug_assertions)]
+    class: LockClass,
+}
+
+/// Records that the current thread enters the class.
+/// It panics in debug builds if the class must not be entered under one of the classes held by this thread.
+pub fn acquire(class: LockClass) -> LockOrderGuard {
+    #[cfg(debug_assertions)]
+    {
+        HELD.with(|held| {
+            let mut held = held.borrow_mut();
+            for &holding in held.iter() {
+                if FORBIDDEN.contains(&(holding, class)) {
+                    panic!("{:?} is acquired while {:?} is held", class, holding);
+                }
+            }
+            held.push(class);
+        });
+        LockOrderGuard {
+            class,
+        }
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        let _ = class;
+        LockOrderGuard {}
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Drop for LockOrderGuard {
+    fn drop(&mut self) {
+        let class = self.class;
+        // It fails while the thread is being destroyed.
+        let _ = HELD.try_with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(index) = held.iter().rposition(|holding| *holding == class) {
+                held.remove(index);
+            }
+        });
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consensus_step_can_be_entered_from_the_miner() {
+        let _miner = acquire(LockClass::Miner);
+        let _step = acquire(LockClass::ConsensusStep);
+    }
+
+    #[test]
+    fn same_class_can_be_entered_again() {
+        let _outer = acquire(LockClass::ConsensusStep);
+        let _inner = acquire(LockClass::ConsensusStep);
+    }
+
+    #[test]
+    fn miner_can_be_entered_after_the_consensus_step_is_released() {
+        drop(acquire(LockClass::ConsensusStep));
+        let _miner = acquire(LockClass::Miner);
+    }
+
+    #[test]
+    #[should_panic(expected = "Miner is acquired while ConsensusStep is held")]
+    fn miner_cannot_be_entered_from_the_consensus_step() {
+        let _step = acquire(LockClass::ConsensusStep);
+        let _miner = acquire(LockClass::Miner);
+    }
+}
@@ -16,26 +16,29 @@
 
 use super::backup;
 use super::mem_pool_types::{
-    AccountDetails, CurrentQueue, FutureQueue, MemPoolInput, MemPoolItem, MemPoolMinFees, MemPoolStatus,
-    PoolingInstant, QueueTag, TransactionOrder, TransactionOrderWithTag, TxOrigin, TxTimelock,
+    AccountDetails, CurrentQueue, FeeHistogram, FutureQueue, FutureReason, MemPoolEntry, MemPoolInput, MemPoolItem,
+    MemPoolMinFees, MemPoolStatus, PoolingInstant, QueueTag, ReadyTransactionsCursor, ReadyTransactionsPage,
+    SeqGapInfo, TransactionOrder, TransactionOrderWithTag, TxOrigin, TxTimelock,
 };
 use super::TransactionImportResult;
 use crate::client::{AccountData, BlockChainTrait};
 use crate::miner::fetch_account_creator;
 use crate::transaction::{PendingSignedTransactions, SignedTransaction};
 use crate::{BlockId, Error as CoreError};
-use ckey::{public_to_address, Public};
+use ckey::{public_to_address, Address, Public};
 use ctypes::errors::{HistoryError, RuntimeError, SyntaxError};
 use ctypes::{BlockNumber, TxHash};
 use kvdb::{DBTransaction, KeyValueDB};
 use rlp;
 use std::cmp::max;
 use std::collections::{BTreeSet, HashMap, HashSet};
-use std::ops::Range;
+use std::ops::{Bound, Range};
 use std::sync::Arc;
 use table::Table;
 
 const DEFAULT_POOLING_PERIOD: BlockNumber = 128;
+/// How long the errors of the expired transactions are kept, in seconds.
+const EXPIRED_HINT_PERIOD: u64 = 60 * 60;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Error {
@@ -110,6 +113,8 @@ pub struct MemPool {
     next_transaction_id: u64,
     /// Arc of KeyValueDB in which the backup information is stored.
     db: Arc<dyn KeyValueDB>,
+    /// The expiries and the drop timestamps of the transactions dropped because their expiries have passed
+    expired: HashMap<TxHash, (u64, u64)>,
 }
 
 impl MemPool {
@@ -138,6 +143,7 @@ impl MemPool {
             last_timestamp: 0,
             next_transaction_id: 0,
             db,
+            expired: HashMap::new(),
         }
     }
 
@@ -222,9 +228,15 @@ impl MemPool {
         MemPoolStatus {
             pending: self.current.len(),
             future: self.future.len(),
+            local: self.current.local_count + self.future.local_count,
         }
     }
 
+    /// Returns the histogram of the pending transactions by fee per byte.
+    pub fn fee_histogram(&self) -> FeeHistogram {
+        self.current.fee_histogram.clone()
+    }
+
     /// Add signed transaction to pool to be verified and imported.
     ///
     /// NOTE details_provider methods should be cheap to compute
@@ -261,6 +273,17 @@ impl MemPool {
                 input.origin
             };
 
+            if let Some(expiry) = input.expiry {
+                if expiry < inserted_timestamp {
+                    insert_results.push(Err(HistoryError::Expired {
+                        expiry,
+                        timestamp: inserted_timestamp,
+                    }
+                    .into()));
+                    continue
+                }
+            }
+
             let client_account = fetch_account(&signer_public);
             if let Err(e) = self.verify_transaction(&tx, origin, &client_account) {
                 insert_results.push(Err(e));
@@ -269,7 +292,9 @@ impl MemPool {
 
             let id = self.next_transaction_id;
             self.next_transaction_id += 1;
-            let item = MemPoolItem::new(tx, origin, inserted_block_number, inserted_timestamp, id, timelock);
+            self.expired.remove(&hash);
+            let item =
+                MemPoolItem::new(tx, origin, inserted_block_number, inserted_timestamp, id, timelock, input.expiry);
             let order = TransactionOrder::for_transaction(&item, client_account.seq);
             let order_with_tag = TransactionOrderWithTag::new(order, QueueTag::New);
 
@@ -377,6 +402,22 @@ impl MemPool {
             .collect()
     }
 
+    /// Drops every transaction and what the pool knows about the signers, keeping the limits.
+    /// The backups of the transactions in the database are kept.
+    pub fn clear(&mut self) {
+        self.current = CurrentQueue::new();
+        self.future = FutureQueue::new();
+        self.by_signer_public = Table::new();
+        self.by_hash.clear();
+        self.first_seqs.clear();
+        self.next_seqs.clear();
+        self.is_local_account.clear();
+        self.last_block_number = 0;
+        self.last_timestamp = 0;
+        self.next_transaction_id = 0;
+        self.expired.clear();
+    }
+
     /// Clear both current and future.
     pub fn remove_all(&mut self) {
         self.current.clear();
@@ -385,6 +426,7 @@ impl MemPool {
 
     /// Checks the current seq for all transactions' senders in the pool and removes the old transactions.
     /// Expired transactions are removed by this function only.
+    /// The expiry given to the pool is applied to the local transactions too.
     pub fn remove_old<F>(&mut self, fetch_account: &F, current_block_number: PoolingInstant, current_timestamp: u64)
     where
         F: Fn(&Public) -> AccountDetails, {
@@ -394,8 +436,21 @@ impl MemPool {
         let max_block_number = self.max_block_number_period_in_pool;
         let balance_check = max_block_number >> 3;
 
+        let expired: Vec<_> = self
+            .by_hash
+            .iter()
+            .filter_map(|(hash, item)| match item.expiry {
+                Some(expiry) if expiry < current_timestamp => Some((*hash, expiry, item.origin)),
+                _ => None,
+            })
+            .collect();
+        self.expired.retain(|_, (_, dropped_at)| *dropped_at + EXPIRED_HINT_PERIOD >= current_timestamp);
+        for (hash, expiry, _) in &expired {
+            self.expired.insert(*hash, (*expiry, current_timestamp));
+        }
+
         // Clear transactions occupying the pool too long, or expired
-        let invalid = self
+        let mut invalid = self
             .by_hash
             .iter()
             .filter(|&(_, ref item)| !item.origin.is_local())
@@ -423,6 +478,7 @@ impl MemPool {
                 None
             })
             .collect::<Vec<_>>();
+        invalid.extend(expired.into_iter().filter(|(_, _, origin)| origin.is_local()).map(|(hash, ..)| hash));
         let fetch_seq =
             |a: &Public| signers.get(a).expect("We fetch details for all signers from both current and future").seq;
         self.remove(&invalid, &fetch_seq, current_block_number, current_timestamp);
@@ -897,10 +953,9 @@ impl MemPool {
             })
             .filter(|t| range.contains(&t.inserted_timestamp))
             .take_while(|t| {
-                let encoded_byte_array = rlp::encode(&t.tx);
-                let size_in_byte = encoded_byte_array.len();
-                current_size += size_in_byte;
-                current_size < size_limit
+                current_size += rlp::encode(&t.tx).len();
+                // The body is the RLP list of the transactions, so its size includes the list header.
+                encoded_list_size(current_size) <= size_limit
             })
             .collect();
 
@@ -913,6 +968,67 @@ impl MemPool {
         }
     }
 
+    /// Returns the transactions after the cursor in the order of `top_transactions`.
+    /// The page ends before the first transaction that exceeds the limits.
+    pub fn ready_transactions_page(
+        &self,
+        max_count: usize,
+        max_encoded_size: usize,
+        cursor: Option<ReadyTransactionsCursor>,
+    ) -> ReadyTransactionsPage {
+        let start = match cursor {
+            Some(cursor) => Bound::Excluded(self.order_of(&cursor.hash).unwrap_or_else(|| cursor.order())),
+            None => Bound::Unbounded,
+        };
+        let mut remaining = self.current.queue.range((start, Bound::Unbounded)).peekable();
+
+        let mut transactions = Vec::new();
+        let mut last = None;
+        let mut current_size: usize = 0;
+        while transactions.len() < max_count {
+            let order = match remaining.peek() {
+                Some(order) => *order,
+                None => break,
+            };
+            // The mem usage is the RLP byte length of the transaction.
+            if current_size + order.mem_usage > max_encoded_size {
+                break
+            }
+            current_size += order.mem_usage;
+            let item = self
+                .by_hash
+                .get(&order.hash)
+                .expect("All transactions in `current` and `future` are always included in `by_hash`");
+            transactions.push(item.tx.clone());
+            last = Some(order);
+            remaining.next();
+        }
+
+        ReadyTransactionsPage {
+            transactions,
+            cursor: last.map(|order| ReadyTransactionsCursor::new(&order)).or(cursor),
+            has_more: remaining.peek().is_some(),
+        }
+    }
+
+    /// The seq height of a transaction decreases when the preceding transactions are mined,
+    /// so the current order is preferred to the order in the cursor.
+    fn order_of(&self, hash: &TxHash) -> Option<TransactionOrder> {
+        let item = self.by_hash.get(hash)?;
+        self.by_signer_public.get(&item.signer_public(), &item.seq()).map(|order_with_tag| order_with_tag.order)
+    }
+
+    /// Returns the error of the transaction if it was dropped because its expiry has passed.
+    pub fn expired_transaction_hint(&self, hash: &TxHash) -> Option<String> {
+        self.expired.get(hash).map(|(expiry, dropped_at)| {
+            HistoryError::Expired {
+                expiry: *expiry,
+                timestamp: *dropped_at,
+            }
+            .to_string()
+        })
+    }
+
     /// Return all transactions whose timestamp are in the given range in the memory pool.
     pub fn count_pending_transactions(&self, range: Range<u64>) -> usize {
         self.current
@@ -1038,6 +1154,96 @@ impl MemPool {
             .collect()
     }
 
+    fn signer_public(&self, signer: &Address) -> Option<&Public> {
+        self.by_signer_public.keys().find(|public| public_to_address(public) == *signer)
+    }
+
+    /// Returns the transactions of the signer in the pool in the order of their seqs.
+    fn signer_orders(&self, signer: &Address) -> Vec<(u64, &TransactionOrderWithTag)> {
+        let mut orders: Vec<_> = match self.signer_public(signer).and_then(|public| self.by_signer_public.row(public)) {
+            Some(row) => row.iter().map(|(seq, order_with_tag)| (*seq, order_with_tag)).collect(),
+            None => return Vec::new(),
+        };
+        orders.sort_unstable_by_key(|(seq, _)| *seq);
+        orders
+    }
+
+    /// Finds where the seqs of the signer's transactions stop following `chain_seq`.
+    pub fn seq_gap_info(&self, signer: &Address, chain_seq: u64) -> SeqGapInfo {
+        let mut ready_end = chain_seq;
+        let mut orphans = Vec::new();
+        for (seq, order_with_tag) in self.signer_orders(signer) {
+            if seq < chain_seq {
+                continue
+            }
+            if seq == ready_end && orphans.is_empty() {
+                ready_end += 1;
+            } else {
+                orphans.push((seq, order_with_tag.order.hash));
+            }
+        }
+        SeqGapInfo {
+            chain_seq,
+            ready: chain_seq..ready_end,
+            first_gap: if orphans.is_empty() {
+                None
+            } else {
+                Some(ready_end)
+            },
+            orphans,
+        }
+    }
+
+    /// Returns the hashes of the signer's future transactions whose seqs are `from_seq` or more,
+    /// with whether each of them is a local one.
+    pub fn future_transactions_from(&self, signer: &Address, from_seq: u64) -> Vec<(TxHash, bool)> {
+        self.signer_orders(signer)
+            .into_iter()
+            .filter(|(seq, order_with_tag)| *seq >= from_seq && order_with_tag.tag == QueueTag::Future)
+            .map(|(_, order_with_tag)| (order_with_tag.order.hash, order_with_tag.order.origin.is_local()))
+            .collect()
+    }
+
+    /// Returns the transactions of the signer in the order of their seqs.
+    pub fn transactions_by_signer(&self, signer: &Address) -> Vec<MemPoolEntry> {
+        let public = match self.signer_public(signer) {
+            Some(public) => public,
+            None => return Vec::new(),
+        };
+        let row = self.by_signer_public.row(public).expect("The key is taken from the table");
+        let next_seq = self.next_seqs.get(public).or_else(|| self.first_seqs.get(public)).cloned().unwrap_or(0);
+        let fee_floor = self.effective_minimum_fee();
+
+        let mut entries: Vec<_> = row
+            .iter()
+            .map(|(seq, order_with_tag)| {
+                let order = &order_with_tag.order;
+                let item = self.by_hash.get(&order.hash).expect("`by_hash` and `by_signer_public` must be synced");
+                let future_reason = match order_with_tag.tag {
+                    QueueTag::Current => None,
+                    _ if Self::should_wait_timelock(&order.timelock, self.last_block_number, self.last_timestamp) => {
+                        Some(FutureReason::Timelock)
+                    }
+                    _ => Some(FutureReason::SeqGap {
+                        expected_seq: next_seq,
+                    }),
+                };
+                MemPoolEntry {
+                    hash: order.hash,
+                    seq: *seq,
+                    fee: order.fee,
+                    origin: order.origin,
+                    inserted_block_number: item.inserted_block_number,
+                    inserted_timestamp: item.inserted_timestamp,
+                    future_reason,
+                    below_fee_floor: order.fee < fee_floor,
+                }
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.seq);
+        entries
+    }
+
     /// Returns true if there is at least one local transaction pending
     pub fn has_local_pending_transactions(&self) -> bool {
         self.current.queue.iter().any(|tx| tx.origin.is_local())
@@ -1065,17 +1271,29 @@ impl MemPool {
 }
 
 
+/// The size of the RLP list whose items are encoded in `payload_size` bytes.
+fn encoded_list_size(payload_size: usize) -> usize {
+    let header_size = if payload_size < 56 {
+        1
+    } else {
+        let length_size = (::std::mem::size_of::<usize>() * 8 - payload_size.leading_zeros() as usize + 7) / 8;
+        1 + length_size
+    };
+    payload_size.saturating_add(header_size)
+}
+
 #[cfg(test)]
 pub mod test {
     use std::cmp::Ordering;
 
     use crate::client::{AccountData, TestBlockChainClient};
+    use crate::miner::FeeBucket;
     use ckey::{Generator, KeyPair, Random};
     use ctypes::transaction::{Action, AssetMintOutput, Transaction};
     use primitives::H160;
 
     use super::*;
-    use rlp::rlp_encode_and_decode_test;
+    use rlp::{rlp_encode_and_decode_test, Rlp, RlpStream};
 
     #[test]
     fn origin_ordering() {
@@ -1346,7 +1564,7 @@ pub mod test {
         };
         let keypair = Random.generate().unwrap();
         let signed = SignedTransaction::new_with_sign(tx, keypair.private());
-        let item = MemPoolItem::new(signed, TxOrigin::Local, 0, 0, 0, timelock);
+        let item = MemPoolItem::new(signed, TxOrigin::Local, 0, 0, 0, timelock, None);
 
         assert_eq!(fee, item.cost());
     }
@@ -1374,7 +1592,7 @@ pub mod test {
         };
         let keypair = Random.generate().unwrap();
         let signed = SignedTransaction::new_with_sign(tx, keypair.private());
-        let item = MemPoolItem::new(signed, TxOrigin::Local, 0, 0, 0, timelock);
+        let item = MemPoolItem::new(signed, TxOrigin::Local, 0, 0, 0, timelock, None);
 
         assert_eq!(fee, item.cost());
     }
@@ -1392,6 +1610,7 @@ pub mod test {
             action: Action::Pay {
                 receiver,
                 quantity,
+                memo: None,
             },
         };
         let timelock = TxTimelock {
@@ -1399,7 +1618,7 @@ pub mod test {
             timestamp: None,
         };
         let signed = SignedTransaction::new_with_sign(tx, keypair.private());
-        let item = MemPoolItem::new(signed, TxOrigin::Local, 0, 0, 0, timelock);
+        let item = MemPoolItem::new(signed, TxOrigin::Local, 0, 0, 0, timelock, None);
 
         assert_eq!(fee + quantity, item.cost());
     }
@@ -1467,6 +1686,7 @@ pub mod test {
             action: Action::Pay {
                 receiver,
                 quantity: 100_000,
+                memo: None,
             },
         };
         let signed = SignedTransaction::new_with_sign(tx, keypair.private());
@@ -1501,9 +1721,15 @@ pub mod test {
             timestamp: None,
         };
         let signed = SignedTransaction::new_with_sign(tx, keypair.private());
-        let item = MemPoolItem::new(signed, TxOrigin::Local, 0, 0, 0, timelock);
+        let item = MemPoolItem::new(signed, TxOrigin::Local, 0, 0, 0, timelock, None);
 
         rlp_encode_and_decode_test!(item);
+
+        let item_with_expiry = MemPoolItem {
+            expiry: Some(100),
+            ..item
+        };
+        rlp_encode_and_decode_test!(item_with_expiry);
     }
 
     #[test]
@@ -1581,6 +1807,7 @@ pub mod test {
             action: Action::Pay {
                 receiver,
                 quantity: 100_000,
+                memo: None,
             },
         };
         SignedTransaction::new_with_sign(tx, keypair.private())
@@ -1595,6 +1822,7 @@ pub mod test {
             action: Action::Pay {
                 receiver,
                 quantity: 100_000,
+                memo: None,
             },
         };
         SignedTransaction::new_with_sign(tx, keypair.private())
@@ -1631,7 +1859,7 @@ pub mod test {
             timestamp: None,
         };
         let signed = SignedTransaction::new_with_sign(tx, keypair.private());
-        let item = MemPoolItem::new(signed, TxOrigin::Local, 0, 0, 0, timelock);
+        let item = MemPoolItem::new(signed, TxOrigin::Local, 0, 0, 0, timelock, None);
         TransactionOrder::for_transaction(&item, 0)
     }
 
@@ -1673,6 +1901,7 @@ pub mod test {
             None,
             None,
             None,
+            None,
         );
 
         let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
@@ -1728,6 +1957,7 @@ pub mod test {
             None,
             None,
             None,
+            None,
         );
 
         let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
@@ -1768,6 +1998,62 @@ pub mod test {
         assert_eq!(Vec::<SignedTransaction>::default(), mem_pool.future_transactions());
     }
 
+    #[test]
+    fn external_pay_transactions_should_pay_the_fee_for_the_memo() {
+        let test_client = TestBlockChainClient::new();
+        // Set the pay transaction minimum fee and the fee for each byte of the memo
+        let fees = MemPoolMinFees::create_from_options(
+            Some(150),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(10),
+        );
+
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 3, db, fees);
+        let keypair = Random.generate().unwrap();
+        let address = public_to_address(keypair.public());
+
+        test_client.set_balance(address, 1_000_000_000_000);
+
+        let create_signed_pay_with_memo = |seq: u64, fee: u64| {
+            let tx = Transaction {
+                seq,
+                fee,
+                network_id: "tc".into(),
+                action: Action::Pay {
+                    receiver: 1u64.into(),
+                    quantity: 100_000,
+                    memo: Some(b"1234".to_vec()),
+                },
+            };
+            SignedTransaction::new_with_sign(tx, keypair.private())
+        };
+        let txs = vec![create_signed_pay_with_memo(0, 189), create_signed_pay_with_memo(0, 190)];
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, txs, TxOrigin::External);
+        assert_eq!(
+            vec![
+                Err(Error::Syntax(SyntaxError::InsufficientFee {
+                    minimal: 190,
+                    got: 189,
+                })),
+                Ok(TransactionImportResult::Current),
+            ],
+            result
+        );
+    }
+
     #[test]
     fn transactions_are_moved_to_future_queue_if_the_preceding_one_removed() {
         //setup test_client
@@ -1827,4 +2113,303 @@ pub mod test {
 
         assert_eq!(vec![create_signed_pay(2, keypair),], mem_pool.future_transactions());
     }
+
+    fn mem_pool_with_pays(test_client: &TestBlockChainClient, fees: &[u64]) -> MemPool {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 3, db, Default::default());
+        let mut txs = Vec::new();
+        for fee in fees {
+            let keypair = Random.generate().unwrap();
+            test_client.set_balance(public_to_address(keypair.public()), 1_000_000_000_000);
+            txs.push(create_signed_pay_with_fee(0, *fee, keypair));
+            txs.push(create_signed_pay_with_fee(1, *fee, keypair));
+        }
+        let result = abbreviated_mempool_add(test_client, &mut mem_pool, txs, TxOrigin::External);
+        assert!(result.iter().all(|result| result == &Ok(TransactionImportResult::Current)));
+        mem_pool
+    }
+
+    fn walk_ready_transactions(
+        mem_pool: &MemPool,
+        max_count: usize,
+        max_encoded_size: usize,
+    ) -> Vec<SignedTransaction> {
+        let mut transactions = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = mem_pool.ready_transactions_page(max_count, max_encoded_size, cursor);
+            assert!(!page.transactions.is_empty());
+            assert!(page.transactions.len() <= max_count);
+            assert!(page.transactions.iter().map(|tx| rlp::encode(tx).len()).sum::<usize>() <= max_encoded_size);
+            transactions.extend(page.transactions);
+            if !page.has_more {
+                return transactions
+            }
+            cursor = page.cursor;
+        }
+    }
+
+    #[test]
+    fn ready_transactions_pages_are_in_the_order_of_top_transactions() {
+        let test_client = TestBlockChainClient::new();
+        let mem_pool = mem_pool_with_pays(&test_client, &[300, 100, 500, 100, 200, 400, 700]);
+        let all = mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions;
+        assert_eq!(14, all.len());
+
+        for max_count in 1..=all.len() {
+            assert_eq!(all, walk_ready_transactions(&mem_pool, max_count, std::usize::MAX));
+        }
+        let tx_size = rlp::encode(&all[0]).len();
+        assert_eq!(all, walk_ready_transactions(&mem_pool, std::usize::MAX, tx_size * 3 + 1));
+    }
+
+    #[test]
+    fn top_transactions_fit_in_the_body_size_limit() {
+        let test_client = TestBlockChainClient::new();
+        let mem_pool = mem_pool_with_pays(&test_client, &[300, 100, 500]);
+        let all = mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions;
+        let body_size = |transactions: &[SignedTransaction]| {
+            let mut body = RlpStream::new();
+            body.append_list(transactions);
+            body.out().len()
+        };
+
+        let limit = body_size(&all[..3]);
+        assert_eq!(all[..3], mem_pool.top_transactions(limit, None, 0..std::u64::MAX).transactions[..]);
+        assert_eq!(all[..2], mem_pool.top_transactions(limit - 1, None, 0..std::u64::MAX).transactions[..]);
+        assert!(mem_pool.top_transactions(0, None, 0..std::u64::MAX).transactions.is_empty());
+    }
+
+    #[test]
+    fn encoded_list_size_matches_the_rlp_list() {
+        for item_size in &[0, 53, 54, 250, 251, 65_530, 65_531] {
+            let mut list = RlpStream::new_list(1);
+            list.append(&vec![0u8; *item_size]);
+            let encoded = list.out();
+            let payload_size = Rlp::new(&encoded).payload_info().unwrap().value_len;
+            assert_eq!(encoded.len(), encoded_list_size(payload_size));
+        }
+    }
+
+    #[test]
+    fn ready_transactions_page_skips_the_removed_transaction_at_the_cursor() {
+        let test_client = TestBlockChainClient::new();
+        let mut mem_pool = mem_pool_with_pays(&test_client, &[300, 100, 500, 200]);
+        let all = mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions;
+
+        let first_page = mem_pool.ready_transactions_page(3, std::usize::MAX, None);
+        assert_eq!(all[..3], first_page.transactions[..]);
+        assert!(first_page.has_more);
+
+        // The transactions of the signer after the removed one move to the future queue.
+        let removed = all[2].hash();
+        let fetch_seq = |_: &Public| -> u64 { 0 };
+        let chain_info = test_client.chain_info();
+        mem_pool.remove(&[removed], &fetch_seq, chain_info.best_block_number, chain_info.best_block_timestamp);
+        let remaining = mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions;
+        assert!(!remaining.contains(&all[2]));
+
+        let mut cursor = first_page.cursor;
+        let mut rest = Vec::new();
+        loop {
+            let page = mem_pool.ready_transactions_page(2, std::usize::MAX, cursor);
+            rest.extend(page.transactions);
+            if !page.has_more {
+                break
+            }
+            cursor = page.cursor;
+        }
+        let expected: Vec<_> = all[3..].iter().filter(|tx| remaining.contains(tx)).cloned().collect();
+        assert_eq!(expected, rest);
+    }
+
+    #[test]
+    fn ready_transactions_page_is_empty_if_the_next_transaction_is_too_big() {
+        let test_client = TestBlockChainClient::new();
+        let mem_pool = mem_pool_with_pays(&test_client, &[300, 100]);
+        let first_page = mem_pool.ready_transactions_page(1, std::usize::MAX, None);
+        assert_eq!(1, first_page.transactions.len());
+
+        let tx_size = rlp::encode(&first_page.transactions[0]).len();
+        let page = mem_pool.ready_transactions_page(10, tx_size - 1, first_page.cursor);
+        assert_eq!(Vec::<SignedTransaction>::new(), page.transactions);
+        assert_eq!(first_page.cursor, page.cursor);
+        assert!(page.has_more);
+
+        let page = mem_pool.ready_transactions_page(10, tx_size - 1, None);
+        assert_eq!(Vec::<SignedTransaction>::new(), page.transactions);
+        assert_eq!(None, page.cursor);
+        assert!(page.has_more);
+    }
+
+    #[test]
+    fn transactions_are_dropped_after_their_expiries() {
+        let test_client = TestBlockChainClient::new();
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 3, db, Default::default());
+        let fetch_account = fetch_account_creator(&test_client, BlockId::Latest);
+
+        let keypair = Random.generate().unwrap();
+        let other_keypair = Random.generate().unwrap();
+        test_client.set_balance(public_to_address(keypair.public()), 1_000_000_000_000);
+        test_client.set_balance(public_to_address(other_keypair.public()), 1_000_000_000_000);
+        let no_timelock = TxTimelock {
+            block: None,
+            timestamp: None,
+        };
+
+        let expiring = create_signed_pay(0, keypair);
+        let other = create_signed_pay(0, other_keypair);
+        let inputs = vec![
+            MemPoolInput::new(expiring.clone(), TxOrigin::Local, no_timelock).with_expiry(Some(110)),
+            MemPoolInput::new(other.clone(), TxOrigin::Local, no_timelock),
+        ];
+        let result = mem_pool.add(inputs, 1, 100, &fetch_account);
+        assert_eq!(vec![Ok(TransactionImportResult::Current), Ok(TransactionImportResult::Current)], result);
+        assert_eq!(2, mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions.len());
+        assert_eq!(None, mem_pool.expired_transaction_hint(&expiring.hash()));
+
+        // Blocks after the expiry don't include it even before it's dropped.
+        assert_eq!(
+            vec![other.clone()],
+            mem_pool.top_transactions(std::usize::MAX, Some(111), 0..std::u64::MAX).transactions
+        );
+
+        mem_pool.remove_old(&fetch_account, 2, 111);
+        assert_eq!(
+            vec![other.clone()],
+            mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions
+        );
+        assert_eq!(
+            Some(
+                HistoryError::Expired {
+                    expiry: 110,
+                    timestamp: 111,
+                }
+                .to_string()
+            ),
+            mem_pool.expired_transaction_hint(&expiring.hash())
+        );
+        assert_eq!(None, mem_pool.expired_transaction_hint(&other.hash()));
+
+        let late =
+            MemPoolInput::new(create_signed_pay(1, other_keypair), TxOrigin::Local, no_timelock).with_expiry(Some(110));
+        assert_eq!(
+            vec![Err(Error::History(HistoryError::Expired {
+                expiry: 110,
+                timestamp: 111,
+            }))],
+            mem_pool.add(vec![late], 2, 111, &fetch_account)
+        );
+    }
+
+    #[test]
+    fn fee_histogram_follows_the_current_queue() {
+        let test_client = TestBlockChainClient::new();
+        // The pays are about 110 bytes long, so the fees per byte are around 90, 11_500 and 740_000.
+        let mut mem_pool =
+            mem_pool_with_pays(&test_client, &[10_000, 1_270_000, 10_000, 81_300_000, 1_270_000, 10_000]);
+        let all = mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions;
+        let size_of = |fee: u64| all.iter().filter(|tx| tx.fee == fee).map(|tx| rlp::encode(tx).len()).sum::<usize>();
+
+        assert_eq!(
+            vec![
+                FeeBucket {
+                    min_fee_per_byte: 64,
+                    count: 6,
+                    size: size_of(10_000),
+                },
+                FeeBucket {
+                    min_fee_per_byte: 8192,
+                    count: 4,
+                    size: size_of(1_270_000),
+                },
+                FeeBucket {
+                    min_fee_per_byte: 524_288,
+                    count: 2,
+                    size: size_of(81_300_000),
+                },
+            ],
+            mem_pool.fee_histogram().buckets()
+        );
+
+        let removed: Vec<_> = all.iter().filter(|tx| tx.fee == 81_300_000).map(|tx| tx.hash()).collect();
+        let fetch_seq = |_: &Public| -> u64 { 0 };
+        let chain_info = test_client.chain_info();
+        mem_pool.remove(&removed, &fetch_seq, chain_info.best_block_number, chain_info.best_block_timestamp);
+        let buckets = mem_pool.fee_histogram().buckets();
+        assert_eq!(vec![64, 8192], buckets.iter().map(|bucket| bucket.min_fee_per_byte).collect::<Vec<_>>());
+        assert_eq!(vec![6, 4], buckets.iter().map(|bucket| bucket.count).collect::<Vec<_>>());
+
+        mem_pool.remove_all();
+        assert_eq!(Vec::<FeeBucket>::new(), mem_pool.fee_histogram().buckets());
+    }
+
+    #[test]
+    fn estimated_fee_clears_the_top_transactions() {
+        let test_client = TestBlockChainClient::new();
+        let mem_pool = mem_pool_with_pays(&test_client, &[10_000, 1_270_000, 10_000, 81_300_000, 1_270_000, 10_000]);
+        let all = mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions;
+        let fee_per_byte = |tx: &SignedTransaction| tx.fee / rlp::encode(tx).len() as u64;
+        let size_of = |fee: u64| all.iter().filter(|tx| tx.fee == fee).map(|tx| rlp::encode(tx).len()).sum::<usize>();
+        let top_size = size_of(81_300_000);
+        let middle_size = size_of(1_270_000);
+        let histogram = mem_pool.fee_histogram();
+
+        assert_eq!(1_048_576, histogram.estimate_fee_per_byte(0));
+        assert_eq!(1_048_576, histogram.estimate_fee_per_byte(top_size - 1));
+        assert_eq!(16_384, histogram.estimate_fee_per_byte(top_size));
+        assert_eq!(16_384, histogram.estimate_fee_per_byte(top_size + middle_size - 1));
+        assert_eq!(128, histogram.estimate_fee_per_byte(top_size + middle_size));
+        assert_eq!(0, histogram.estimate_fee_per_byte(top_size + middle_size + size_of(10_000)));
+
+        for capacity in (0..=top_size + middle_size + size_of(10_000)).step_by(37) {
+            let suggested = histogram.estimate_fee_per_byte(capacity);
+            let size_paying = |min: u64| {
+                all.iter().filter(|tx| fee_per_byte(tx) >= min).map(|tx| rlp::encode(*tx).len()).sum::<usize>()
+            };
+            // A transaction paying the suggestion only waits for the top `capacity` bytes,
+            // and the next lower bucket boundary would put it behind more than that.
+            assert!(size_paying(suggested) <= capacity);
+            assert!(suggested == 0 || size_paying(suggested / 2) > capacity);
+        }
+    }
+
+    #[test]
+    fn local_count_follows_the_queues() {
+        let test_client = TestBlockChainClient::new();
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 3, db, Default::default());
+        let local = Random.generate().unwrap();
+        let external = Random.generate().unwrap();
+        test_client.set_balance(public_to_address(local.public()), 1_000_000_000_000);
+        test_client.set_balance(public_to_address(external.public()), 1_000_000_000_000);
+
+        // The transaction with seq 2 waits in the future queue for the one with seq 1.
+        let local_txs = vec![create_signed_pay_with_fee(0, 200, local), create_signed_pay_with_fee(2, 200, local)];
+        abbreviated_mempool_add(&test_client, &mut mem_pool, local_txs.clone(), TxOrigin::Local);
+        abbreviated_mempool_add(
+            &test_client,
+            &mut mem_pool,
+            vec![create_signed_pay_with_fee(0, 200, external)],
+            TxOrigin::External,
+        );
+        let status = mem_pool.status();
+        assert_eq!(2, status.pending);
+        assert_eq!(1, status.future);
+        assert_eq!(2, status.local);
+
+        let fetch_seq = |_: &Public| -> u64 { 0 };
+        let chain_info = test_client.chain_info();
+        mem_pool.remove(
+            &[local_txs[0].hash()],
+            &fetch_seq,
+            chain_info.best_block_number,
+            chain_info.best_block_timestamp,
+        );
+        assert_eq!(1, mem_pool.status().local);
+
+        mem_pool.clear();
+        assert_eq!(0, mem_pool.status().local);
+    }
 }
@@ -36,6 +36,7 @@ fn create_input(keypair: &KeyPair, seq: u64, block: Option<PoolingInstant>, time
         action: Action::Pay {
             receiver: 0.into(),
             quantity: 100,
+            memo: None,
         },
     };
     let timelock = TxTimelock {
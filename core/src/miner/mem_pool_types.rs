@@ -20,8 +20,9 @@ use ctypes::transaction::Action;
 use ctypes::{BlockNumber, TxHash};
 use rlp;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
-use std::cmp::Ordering;
+use std::cmp::{min, Ordering};
 use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
 
 /// Point in time when transaction was inserted.
 pub type PoolingInstant = BlockNumber;
@@ -54,7 +55,7 @@ impl Encodable for TxOrigin {
 
 impl Decodable for TxOrigin {
     fn decode(d: &Rlp) -> Result<Self, DecoderError> {
-        match d.as_val().expect("rlp decode Error") {
+        match d.as_val()? {
             LOCAL => Ok(TxOrigin::Local),
             EXTERNAL => Ok(TxOrigin::External),
             RETRACTEDBLOCK => Ok(TxOrigin::RetractedBlock),
@@ -199,8 +200,58 @@ impl Ord for TransactionOrder {
     }
 }
 
+/// Points right after the last transaction of a page of the ready transactions.
+/// It keeps the ordering key of the transaction, so the next page can be found even if the transaction is removed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct ReadyTransactionsCursor {
+    pub hash: TxHash,
+    origin: TxOrigin,
+    seq_height: u64,
+    fee_per_byte: u64,
+    fee: u64,
+    timelock: TxTimelock,
+    insertion_id: u64,
+}
+
+impl ReadyTransactionsCursor {
+    pub fn new(order: &TransactionOrder) -> Self {
+        Self {
+            hash: order.hash,
+            origin: order.origin,
+            seq_height: order.seq_height,
+            fee_per_byte: order.fee_per_byte,
+            fee: order.fee,
+            timelock: order.timelock,
+            insertion_id: order.insertion_id,
+        }
+    }
+
+    /// The order of the transaction when the cursor was made.
+    pub fn order(&self) -> TransactionOrder {
+        TransactionOrder {
+            seq_height: self.seq_height,
+            fee: self.fee,
+            fee_per_byte: self.fee_per_byte,
+            mem_usage: 0,
+            hash: self.hash,
+            insertion_id: self.insertion_id,
+            origin: self.origin,
+            timelock: self.timelock,
+        }
+    }
+}
+
+/// A page of the ready transactions in the order in which the miner includes them in a block.
+pub struct ReadyTransactionsPage {
+    pub transactions: Vec<SignedTransaction>,
+    /// The cursor to get the next page. It's the given cursor if the page is empty.
+    pub cursor: Option<ReadyTransactionsCursor>,
+    /// Whether there are transactions after this page.
+    pub has_more: bool,
+}
+
 /// Transaction item in the mem pool.
-#[derive(Clone, Eq, PartialEq, Debug, RlpEncodable, RlpDecodable)]
+#[derive(Clone, Eq, PartialEq, Debug)]
 pub struct MemPoolItem {
     /// Transaction.
     pub tx: SignedTransaction,
@@ -214,6 +265,9 @@ pub struct MemPoolItem {
     pub insertion_id: u64,
     /// A timelock.
     pub timelock: TxTimelock,
+    /// The timestamp after which the transaction is dropped from the pool.
+    /// It's not a part of the transaction, so it's not checked by the other nodes.
+    pub expiry: Option<u64>,
 }
 
 impl MemPoolItem {
@@ -224,6 +278,7 @@ impl MemPoolItem {
         inserted_timestamp: u64,
         insertion_id: u64,
         timelock: TxTimelock,
+        expiry: Option<u64>,
     ) -> Self {
         MemPoolItem {
             tx,
@@ -232,6 +287,7 @@ impl MemPoolItem {
             inserted_timestamp,
             insertion_id,
             timelock,
+            expiry,
         }
     }
 
@@ -261,17 +317,67 @@ impl MemPoolItem {
         }
     }
 
+    /// Returns the earlier of the expiration of the transaction and the expiry given to the pool.
     pub fn expiration(&self) -> Option<u64> {
-        match &self.tx.action {
+        let expiration = match &self.tx.action {
             Action::TransferAsset {
                 expiration,
                 ..
             } => *expiration,
             _ => None,
+        };
+        match (expiration, self.expiry) {
+            (Some(expiration), Some(expiry)) => Some(min(expiration, expiry)),
+            (expiration, expiry) => expiration.or(expiry),
         }
     }
 }
 
+// The expiry is appended only if it's given, so the items backed up before it was added can be decoded.
+impl Encodable for MemPoolItem {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(if self.expiry.is_some() {
+            7
+        } else {
+            6
+        });
+        s.append(&self.tx);
+        s.append(&self.origin);
+        s.append(&self.inserted_block_number);
+        s.append(&self.inserted_timestamp);
+        s.append(&self.insertion_id);
+        s.append(&self.timelock);
+        if let Some(expiry) = self.expiry {
+            s.append(&expiry);
+        }
+    }
+}
+
+impl Decodable for MemPoolItem {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        let expiry = match item_count {
+            6 => None,
+            7 => Some(rlp.val_at(6)?),
+            _ => {
+                return Err(DecoderError::RlpIncorrectListLen {
+                    got: item_count,
+                    expected: 6,
+                })
+            }
+        };
+        Ok(Self {
+            tx: rlp.val_at(0)?,
+            origin: rlp.val_at(1)?,
+            inserted_block_number: rlp.val_at(2)?,
+            inserted_timestamp: rlp.val_at(3)?,
+            insertion_id: rlp.val_at(4)?,
+            timelock: rlp.val_at(5)?,
+            expiry,
+        })
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum QueueTag {
     Current,
@@ -294,16 +400,114 @@ impl TransactionOrderWithTag {
     }
 }
 
+/// The number of buckets in `FeeHistogram`.
+/// The bucket 0 holds the fee per byte 0 and the bucket `i` holds the fees per byte in `[2^(i-1), 2^i)`.
+const FEE_HISTOGRAM_BUCKETS: usize = 65;
+
+/// The pending transactions whose fees per byte fall in the same log-scale range.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FeeBucket {
+    /// The lowest fee per byte of the range.
+    pub min_fee_per_byte: u64,
+    /// The number of transactions in the bucket.
+    pub count: usize,
+    /// The total RLP byte length of the transactions in the bucket.
+    pub size: usize,
+}
+
+/// Counts and total sizes of transactions bucketed by fee per byte.
+/// It is updated whenever a transaction enters or leaves the current queue.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeeHistogram {
+    buckets: Vec<FeeBucket>,
+}
+
+impl Default for FeeHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FeeHistogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: (0..FEE_HISTOGRAM_BUCKETS)
+                .map(|index| FeeBucket {
+                    min_fee_per_byte: Self::min_fee_per_byte_of(index),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn index_of(fee_per_byte: u64) -> usize {
+        64 - fee_per_byte.leading_zeros() as usize
+    }
+
+    fn min_fee_per_byte_of(index: usize) -> u64 {
+        if index == 0 {
+            0
+        } else {
+            1 << (index - 1)
+        }
+    }
+
+    pub fn insert(&mut self, fee_per_byte: u64, size: usize) {
+        let bucket = &mut self.buckets[Self::index_of(fee_per_byte)];
+        bucket.count += 1;
+        bucket.size += size;
+    }
+
+    pub fn remove(&mut self, fee_per_byte: u64, size: usize) {
+        let bucket = &mut self.buckets[Self::index_of(fee_per_byte)];
+        bucket.count -= 1;
+        bucket.size -= size;
+    }
+
+    pub fn clear(&mut self) {
+        for bucket in self.buckets.iter_mut() {
+            bucket.count = 0;
+            bucket.size = 0;
+        }
+    }
+
+    /// Returns the non-empty buckets in the ascending order of the fee per byte.
+    pub fn buckets(&self) -> Vec<FeeBucket> {
+        self.buckets.iter().filter(|bucket| bucket.count != 0).cloned().collect()
+    }
+
+    /// Returns the lowest fee per byte that outbids all transactions but those in the top `capacity` bytes.
+    /// The transactions paying it or more never take more than `capacity` bytes.
+    pub fn estimate_fee_per_byte(&self, capacity: usize) -> u64 {
+        let mut size = 0usize;
+        for (index, bucket) in self.buckets.iter().enumerate().rev() {
+            size = size.saturating_add(bucket.size);
+            if size > capacity {
+                return if index + 1 == FEE_HISTOGRAM_BUCKETS {
+                    u64::max_value()
+                } else {
+                    Self::min_fee_per_byte_of(index + 1)
+                }
+            }
+        }
+        0
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct CurrentQueue {
     /// Priority queue for transactions
     pub queue: BTreeSet<TransactionOrder>,
     /// Counter on fees of transactions in the current queue
     pub fee_counter: BTreeMap<u64, usize>,
+    /// Counts and sizes of transactions in the current queue bucketed by fee per byte
+    pub fee_histogram: FeeHistogram,
     /// Memory usage of the external transactions in the queue
     pub mem_usage: usize,
     /// Count of the external transactions in the queue
     pub count: usize,
+    /// Count of the local transactions in the queue
+    pub local_count: usize,
 }
 
 impl CurrentQueue {
@@ -311,16 +515,20 @@ impl CurrentQueue {
         Self {
             queue: BTreeSet::new(),
             fee_counter: BTreeMap::new(),
+            fee_histogram: FeeHistogram::new(),
             mem_usage: 0,
             count: 0,
+            local_count: 0,
         }
     }
 
     pub fn clear(&mut self) {
         self.queue.clear();
         self.fee_counter.clear();
+        self.fee_histogram.clear();
         self.mem_usage = 0;
         self.count = 0;
+        self.local_count = 0;
     }
 
     pub fn len(&self) -> usize {
@@ -333,7 +541,11 @@ impl CurrentQueue {
             self.mem_usage += order.mem_usage;
             self.count += 1;
         }
+        if order.origin.is_local() {
+            self.local_count += 1;
+        }
         *self.fee_counter.entry(order.fee).or_default() += 1;
+        self.fee_histogram.insert(order.fee_per_byte, order.mem_usage);
     }
 
     pub fn remove(&mut self, order: &TransactionOrder) {
@@ -342,6 +554,10 @@ impl CurrentQueue {
             self.mem_usage -= order.mem_usage;
             self.count -= 1;
         }
+        if order.origin.is_local() {
+            self.local_count -= 1;
+        }
+        self.fee_histogram.remove(order.fee_per_byte, order.mem_usage);
         {
             let counter = self.fee_counter.get_mut(&order.fee).unwrap();
             *counter -= 1;
@@ -365,6 +581,8 @@ pub struct FutureQueue {
     pub mem_usage: usize,
     /// Count of the external transactions in the queue
     pub count: usize,
+    /// Count of the local transactions in the queue
+    pub local_count: usize,
 }
 
 impl FutureQueue {
@@ -373,6 +591,7 @@ impl FutureQueue {
             queue: BTreeSet::new(),
             mem_usage: 0,
             count: 0,
+            local_count: 0,
         }
     }
 
@@ -380,6 +599,7 @@ impl FutureQueue {
         self.queue.clear();
         self.mem_usage = 0;
         self.count = 0;
+        self.local_count = 0;
     }
 
     pub fn len(&self) -> usize {
@@ -392,6 +612,9 @@ impl FutureQueue {
             self.mem_usage += order.mem_usage;
             self.count += 1;
         }
+        if order.origin.is_local() {
+            self.local_count += 1;
+        }
     }
 
     pub fn remove(&mut self, order: &TransactionOrder) {
@@ -400,6 +623,9 @@ impl FutureQueue {
             self.mem_usage -= order.mem_usage;
             self.count -= 1;
         }
+        if order.origin.is_local() {
+            self.local_count -= 1;
+        }
     }
 }
 
@@ -408,6 +634,7 @@ pub struct MemPoolInput {
     pub transaction: SignedTransaction,
     pub origin: TxOrigin,
     pub timelock: TxTimelock,
+    pub expiry: Option<u64>,
 }
 
 impl MemPoolInput {
@@ -416,8 +643,14 @@ impl MemPoolInput {
             transaction,
             origin,
             timelock,
+            expiry: None,
         }
     }
+
+    pub fn with_expiry(mut self, expiry: Option<u64>) -> Self {
+        self.expiry = expiry;
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -427,6 +660,49 @@ pub struct MemPoolStatus {
     pub pending: usize,
     /// Number of future transactions (waiting for transactions with lower seqs first)
     pub future: usize,
+    /// Number of pending and future transactions that were submitted to this node
+    pub local: usize,
+}
+
+/// The reason why a transaction in the future queue cannot go to a block yet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FutureReason {
+    /// The transaction with `expected_seq` must go to a block first.
+    SeqGap {
+        expected_seq: u64,
+    },
+    /// The timelock of the transaction has not expired.
+    Timelock,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A transaction in the pool and the state of it
+pub struct MemPoolEntry {
+    pub hash: TxHash,
+    pub seq: u64,
+    pub fee: u64,
+    pub origin: TxOrigin,
+    /// The best block number when the transaction was inserted
+    pub inserted_block_number: PoolingInstant,
+    /// The best block timestamp when the transaction was inserted
+    pub inserted_timestamp: u64,
+    /// `None` if the transaction is in the current queue
+    pub future_reason: Option<FutureReason>,
+    /// True if the pool is full and the fee is lower than the one a new transaction needs to replace it
+    pub below_fee_floor: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Where the transactions of a signer stop being mineable because of a missing seq
+pub struct SeqGapInfo {
+    /// The seq of the signer in the best block
+    pub chain_seq: u64,
+    /// The seqs of the pool that follow `chain_seq` without a gap
+    pub ready: Range<u64>,
+    /// The first missing seq, if some transactions wait for it
+    pub first_gap: Option<u64>,
+    /// The seqs and the hashes of the transactions after the gap, in the order of their seqs
+    pub orphans: Vec<(u64, TxHash)>,
 }
 
 #[derive(Debug)]
@@ -455,6 +731,8 @@ pub struct MemPoolMinFees {
     pub min_asset_scheme_change_cost: u64,
     pub min_asset_supply_increase_cost: u64,
     pub min_asset_unwrap_ccc_cost: u64,
+    /// Additional cost of Pay for each byte of the memo.
+    pub min_pay_memo_byte_cost: u64,
 }
 
 impl MemPoolMinFees {
@@ -474,6 +752,7 @@ impl MemPoolMinFees {
         min_asset_scheme_change_cost_option: Option<u64>,
         min_asset_supply_increase_cost_option: Option<u64>,
         min_asset_unwrap_ccc_cost_option: Option<u64>,
+        min_pay_memo_byte_cost_option: Option<u64>,
     ) -> Self {
         MemPoolMinFees {
             min_pay_transaction_cost: min_pay_cost_option.unwrap_or_default(),
@@ -490,6 +769,7 @@ impl MemPoolMinFees {
             min_asset_scheme_change_cost: min_asset_scheme_change_cost_option.unwrap_or_default(),
             min_asset_supply_increase_cost: min_asset_supply_increase_cost_option.unwrap_or_default(),
             min_asset_unwrap_ccc_cost: min_asset_unwrap_ccc_cost_option.unwrap_or_default(),
+            min_pay_memo_byte_cost: min_pay_memo_byte_cost_option.unwrap_or_default(),
         }
     }
     pub fn min_cost(&self, action: &Action) -> u64 {
@@ -502,6 +782,9 @@ impl MemPoolMinFees {
             } => self.min_asset_transfer_cost,
             Action::ChangeAssetScheme {
                 ..
+            }
+            | Action::CreateApproverGroup {
+                ..
             } => self.min_asset_scheme_change_cost,
             Action::IncreaseAssetSupply {
                 ..
@@ -510,16 +793,30 @@ impl MemPoolMinFees {
                 ..
             } => self.min_asset_unwrap_ccc_cost,
             Action::Pay {
+                memo,
                 ..
-            } => self.min_pay_transaction_cost,
+            } => {
+                let memo_len = memo.as_ref().map_or(0, Vec::len) as u64;
+                self.min_pay_transaction_cost.saturating_add(memo_len.saturating_mul(self.min_pay_memo_byte_cost))
+            }
             Action::SetRegularKey {
                 ..
-            } => self.min_set_regular_key_transaction_cost,
+            }
+            | Action::RevokeRegularKey => self.min_set_regular_key_transaction_cost,
             Action::CreateShard {
                 ..
             } => self.min_create_shard_transaction_cost,
             Action::SetShardOwners {
                 ..
+            }
+            | Action::OfferShardOwnership {
+                ..
+            }
+            | Action::AcceptShardOwnership {
+                ..
+            }
+            | Action::CancelShardOwnershipOffer {
+                ..
             } => self.min_set_shard_owners_transaction_cost,
             Action::SetShardUsers {
                 ..
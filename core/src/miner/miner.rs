@@ -16,32 +16,39 @@
 
 use super::mem_pool::{Error as MemPoolError, MemPool};
 pub use super::mem_pool_types::MemPoolMinFees;
-use super::mem_pool_types::{MemPoolInput, TxOrigin, TxTimelock};
+use super::mem_pool_types::{
+    FeeHistogram, MemPoolEntry, MemPoolInput, ReadyTransactionsCursor, ReadyTransactionsPage, SeqGapInfo, TxOrigin,
+    TxTimelock,
+};
 use super::sealing_queue::SealingQueue;
 use super::work_notify::{NotifyWork, WorkPoster};
-use super::{fetch_account_creator, MinerService, MinerStatus, TransactionImportResult};
+use super::{fetch_account_creator, MinerService, MinerStatus, PendingSeal, ResealTimers, TransactionImportResult};
 use crate::account_provider::{AccountProvider, Error as AccountProviderError};
 use crate::block::{Block, ClosedBlock, IsBlock};
 use crate::client::{
-    AccountData, BlockChainTrait, BlockProducer, Client, EngineInfo, ImportBlock, MiningBlockChainClient, TermInfo,
+    AccountData, BlockChainTrait, BlockProducer, Client, EngineInfo, ImportBlock, MiningBlockChainClient, StateInfo,
+    TermInfo,
 };
 use crate::codechain_machine::CodeChainMachine;
 use crate::consensus::{CodeChainEngine, EngineType};
-use crate::error::Error;
+use crate::error::{BlockError, Error};
+use crate::lock_order::{self, LockClass};
 use crate::scheme::Scheme;
 use crate::transaction::{PendingSignedTransactions, SignedTransaction, UnverifiedTransaction};
 use crate::types::{BlockId, TransactionId};
 use ckey::{public_to_address, Address, Password, PlatformAddress, Public};
+use clogger::metric::METRICS;
 use cstate::{FindActionHandler, TopLevelState};
 use ctypes::errors::{HistoryError, RuntimeError};
 use ctypes::transaction::{Action, IncompleteTransaction, Timelock};
-use ctypes::{BlockHash, BlockNumber, Header, TxHash};
+use ctypes::util::unexpected::Mismatch;
+use ctypes::{BlockHash, BlockNumber, Header, Tracker, TxHash};
 use cvm::ChainTimeInfo;
 use kvdb::KeyValueDB;
 use parking_lot::{Mutex, RwLock};
 use primitives::{Bytes, H256, U256};
 use std::borrow::Borrow;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::iter::once;
 use std::ops::Range;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -53,6 +60,8 @@ use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 pub struct MinerOptions {
     /// URLs to notify when there is new work.
     pub new_work_notify: Vec<String>,
+    /// The secrets with which the notifications to the URLs are signed.
+    pub new_work_notify_secrets: HashMap<String, String>,
     /// Force the miner to reseal, even when nobody has asked for work.
     pub force_sealing: bool,
     /// Reseal on receipt of new external transactions.
@@ -79,12 +88,18 @@ pub struct MinerOptions {
     pub work_queue_size: usize,
     /// Minimum fees configured by the machine.
     pub mem_pool_min_fees: MemPoolMinFees,
+    /// Give the prepared blocks to an external signer instead of sealing them internally.
+    /// It works only if the engine supports external sealing.
+    pub external_sealing: bool,
+    /// How long the prepared blocks wait for the seal from an external signer.
+    pub pending_seal_timeout: Duration,
 }
 
 impl Default for MinerOptions {
     fn default() -> Self {
         MinerOptions {
             new_work_notify: vec![],
+            new_work_notify_secrets: Default::default(),
             force_sealing: false,
             reseal_on_external_transaction: true,
             reseal_on_own_transaction: true,
@@ -97,6 +112,8 @@ impl Default for MinerOptions {
             allow_create_shard: false,
             work_queue_size: 20,
             mem_pool_min_fees: Default::default(),
+            external_sealing: false,
+            pending_seal_timeout: Duration::from_secs(30),
         }
     }
 }
@@ -110,6 +127,10 @@ pub struct AuthoringParams {
 struct SealingWork {
     queue: SealingQueue,
     enabled: bool,
+    /// When the last block was pushed to the queue.
+    prepared_at: Instant,
+    /// When this node sealed a block the last time.
+    last_sealed_at: Option<SystemTime>,
 }
 
 pub struct Miner {
@@ -126,6 +147,7 @@ pub struct Miner {
 
     accounts: Arc<AccountProvider>,
     notifiers: Notifiers,
+    transaction_listeners: RwLock<Vec<Box<dyn Fn(&[TxHash]) + Send + Sync>>>,
     malicious_users: Users,
     immune_users: Users,
 }
@@ -180,10 +202,10 @@ impl Notifiers {
         self.notifiers.read().is_empty()
     }
 
-    pub fn notify(&self, pow_hash: H256, target: U256) {
+    pub fn notify(&self, pow_hash: H256, target: U256, number: BlockNumber, timestamp: u64) {
         // FIXME: Calling callbacks inside of lock lifetime may cause a deadlock.
         for notifier in self.notifiers.read().iter() {
-            notifier.notify(pow_hash, target)
+            notifier.notify(pow_hash, target, number, timestamp)
         }
     }
 }
@@ -263,12 +285,18 @@ impl Miner {
         self.notifiers.push(notifier);
     }
 
+    /// Push listener that will be called with the hashes of transactions added to the mem pool
+    pub fn add_transactions_listener(&self, listener: Box<dyn Fn(&[TxHash]) + Send + Sync>) {
+        self.transaction_listeners.write().push(listener);
+    }
+
     pub fn new(
         options: MinerOptions,
         scheme: &Scheme,
         accounts: Arc<AccountProvider>,
         db: Arc<dyn KeyValueDB>,
     ) -> Arc<Self> {
+        record_sealing_enabled(true);
         Arc::new(Self::new_raw(options, scheme, accounts, db))
     }
 
@@ -294,7 +322,7 @@ impl Miner {
         let notifiers: Vec<Box<dyn NotifyWork>> = if options.new_work_notify.is_empty() {
             Vec::new()
         } else {
-            vec![Box::new(WorkPoster::new(&options.new_work_notify))]
+            vec![Box::new(WorkPoster::new(&options.new_work_notify, &options.new_work_notify_secrets))]
         };
 
         Self {
@@ -306,12 +334,15 @@ impl Miner {
             sealing_work: Mutex::new(SealingWork {
                 queue: SealingQueue::new(options.work_queue_size),
                 enabled: options.force_sealing || scheme.engine.seals_internally().is_some(),
+                prepared_at: Instant::now(),
+                last_sealed_at: None,
             }),
             engine: scheme.engine.clone(),
             options,
             sealing_enabled: AtomicBool::new(true),
             accounts,
             notifiers: Notifiers::new(notifiers),
+            transaction_listeners: RwLock::new(Vec::new()),
             malicious_users: Users::new(),
             immune_users: Users::new(),
         }
@@ -321,11 +352,58 @@ impl Miner {
         self.mem_pool.write().recover_from_db(client);
     }
 
+    /// Discards the sealing work and the pending transactions, and recovers the mem pool from the database.
+    /// It's called when the database is replaced by a restore.
+    pub fn reset_to_db(&self, client: &Client) {
+        self.sealing_work.lock().queue.reset();
+        let mut mem_pool = self.mem_pool.write();
+        mem_pool.clear();
+        mem_pool.recover_from_db(client);
+    }
+
     /// Get `Some` `clone()` of the current pending block's state or `None` if we're not sealing.
     pub fn pending_state(&self, latest_block_number: BlockNumber) -> Option<TopLevelState> {
         self.map_pending_block(|b| b.state().clone(), latest_block_number)
     }
 
+    /// Get a snapshot of the state which includes the pending transactions.
+    ///
+    /// Returns the state of the current pending block if there is one.
+    /// Otherwise, the ready transactions in the mem pool are applied to the state of the best block.
+    pub fn pending_state_snapshot<C>(&self, chain: &C) -> TopLevelState
+    where
+        C: BlockChainTrait + ChainTimeInfo + EngineInfo + FindActionHandler + StateInfo, {
+        let best_header = chain.best_block_header();
+        if let Some(state) = self.pending_state(best_header.number()) {
+            return state
+        }
+
+        let mut state = chain.state_at(BlockId::Hash(best_header.hash())).expect("The state of the best block exists");
+        let fake_header = best_header.decode().generate_child();
+        let max_body_size = chain.common_params(best_header.hash().into()).unwrap().max_body_size();
+        const DEFAULT_RANGE: Range<u64> = 0..::std::u64::MAX;
+
+        // Hold the lock until all transactions are applied, so the snapshot corresponds to a single pool state.
+        let mem_pool = self.mem_pool.read();
+        let transactions =
+            mem_pool.top_transactions(max_body_size, Some(fake_header.timestamp()), DEFAULT_RANGE).transactions;
+        for tx in transactions {
+            let hash = tx.hash();
+            if let Err(err) = state.apply(
+                &tx,
+                &hash,
+                &tx.signer_public(),
+                chain,
+                best_header.number(),
+                best_header.timestamp(),
+                fake_header.timestamp(),
+            ) {
+                ctrace!(MINER, "Cannot apply the pending transaction {:?} to the pending state: {:?}", hash, err);
+            }
+        }
+        state
+    }
+
     /// Get `Some` `clone()` of the current pending block or `None` if we're not sealing.
     pub fn pending_block(&self, latest_block_number: BlockNumber) -> Option<Block> {
         self.map_pending_block(IsBlock::to_base, latest_block_number)
@@ -340,6 +418,31 @@ impl Miner {
         &self.options
     }
 
+    /// Returns when this node sealed a block the last time.
+    pub fn last_sealed_at(&self) -> Option<SystemTime> {
+        self.sealing_work.lock().last_sealed_at
+    }
+
+    /// Returns false after `stop_sealing` is called until `start_sealing` is called.
+    pub fn is_sealing_enabled(&self) -> bool {
+        self.sealing_enabled.load(Ordering::Relaxed)
+    }
+
+    /// Returns how long the miner waits until it may, or must, reseal.
+    pub fn reseal_timers(&self) -> ResealTimers {
+        let now = Instant::now();
+        ResealTimers {
+            min_period_left: self.next_allowed_reseal.get().saturating_duration_since(now),
+            max_period_left: self.next_mandatory_reseal.get().saturating_duration_since(now),
+        }
+    }
+
+    fn on_block_sealed(&self) {
+        let now = SystemTime::now();
+        self.sealing_work.lock().last_sealed_at = Some(now);
+        record_last_sealed_at(now);
+    }
+
     /// Check is reseal is allowed and necessary.
     fn requires_reseal(&self, best_block: BlockNumber) -> bool {
         let has_local_transactions = self.mem_pool.read().has_local_pending_transactions();
@@ -380,6 +483,7 @@ impl Miner {
         client: &C,
         transactions: Vec<UnverifiedTransaction>,
         default_origin: TxOrigin,
+        expiry: Option<u64>,
         mem_pool: &mut MemPool,
     ) -> Vec<Result<TransactionImportResult, Error>> {
         let best_header = client.best_block_header().decode();
@@ -392,10 +496,10 @@ impl Miner {
 
         let intermediate_results: Vec<Result<(), Error>> = transactions
             .into_iter()
-            .map(|tx| {
+            .map(|mut tx| {
                 let hash = tx.hash();
-                // FIXME: Refactoring is needed. recover_public is calling in verify_transaction_unordered.
-                let signer_public = tx.recover_public()?;
+                // The public is cached, so verifying the seal below doesn't recover it again.
+                let signer_public = tx.cache_signer_public()?;
                 let signer_address = public_to_address(&signer_public);
                 if default_origin.is_local() {
                     self.immune_users.insert(signer_address);
@@ -451,7 +555,7 @@ impl Miner {
                 let timelock = self.calculate_timelock(&tx, client)?;
                 let tx_hash = tx.hash();
 
-                to_insert.push(MemPoolInput::new(tx, origin, timelock));
+                to_insert.push(MemPoolInput::new(tx, origin, timelock).with_expiry(expiry));
                 tx_hashes.push(tx_hash);
                 Ok(())
             })
@@ -464,7 +568,7 @@ impl Miner {
 
         debug_assert_eq!(insertion_results.len(), intermediate_results.iter().filter(|r| r.is_ok()).count());
         let mut insertion_results_index = 0;
-        intermediate_results
+        let results = intermediate_results
             .into_iter()
             .map(|res| match res {
                 Err(e) => Err(e),
@@ -476,60 +580,171 @@ impl Miner {
                     Ok(result)
                 }
             })
-            .collect()
+            .collect();
+
+        if !inserted.is_empty() {
+            for listener in self.transaction_listeners.read().iter() {
+                listener(&inserted);
+            }
+        }
+        record_mem_pool_size(mem_pool);
+        results
     }
 
     pub fn delete_all_pending_transactions(&self) {
         let mut mem_pool = self.mem_pool.write();
         mem_pool.remove_all();
+        record_mem_pool_size(&mem_pool);
+    }
+
+    pub fn pending_transactions_by_signer(&self, signer: &Address) -> Vec<MemPoolEntry> {
+        self.mem_pool.read().transactions_by_signer(signer)
+    }
+
+    pub fn seq_gap_info<C: AccountData>(&self, chain: &C, signer: &Address) -> SeqGapInfo {
+        let chain_seq = chain.latest_seq(signer);
+        self.mem_pool.read().seq_gap_info(signer, chain_seq)
+    }
+
+    /// Removes the future transactions of the signer whose seqs are `from_seq` or more.
+    ///
+    /// If one of them came from the network, nothing is removed and its hash is returned as the error.
+    pub fn drop_future_transactions<C: AccountData + BlockChainTrait>(
+        &self,
+        chain: &C,
+        signer: &Address,
+        from_seq: u64,
+    ) -> Result<Vec<TxHash>, TxHash> {
+        let chain_info = chain.chain_info();
+        let fetch_account = fetch_account_creator(chain, BlockId::Hash(chain_info.best_block_hash));
+        let fetch_seq = |public: &Public| fetch_account(public).seq;
+
+        let mut mem_pool = self.mem_pool.write();
+        let targets = mem_pool.future_transactions_from(signer, from_seq);
+        if let Some((external, _)) = targets.iter().find(|(_, is_local)| !is_local) {
+            return Err(*external)
+        }
+        let hashes: Vec<_> = targets.into_iter().map(|(hash, _)| hash).collect();
+        mem_pool.remove(&hashes, &fetch_seq, chain_info.best_block_number, chain_info.best_block_timestamp);
+        record_mem_pool_size(&mem_pool);
+        Ok(hashes)
+    }
+
+    /// Removes the transaction only if it was submitted to this node.
+    ///
+    /// Returns `None` if the transaction is not in the pool and `Some(false)` if it came from the network.
+    pub fn remove_local_transaction<C: AccountData + BlockChainTrait>(&self, chain: &C, hash: &TxHash) -> Option<bool> {
+        let chain_info = chain.chain_info();
+        let fetch_account = fetch_account_creator(chain, BlockId::Hash(chain_info.best_block_hash));
+        let fetch_seq = |public: &Public| fetch_account(public).seq;
+
+        let mut mem_pool = self.mem_pool.write();
+        if !mem_pool.is_local_transaction(*hash)? {
+            return Some(false)
+        }
+        mem_pool.remove(&[*hash], &fetch_seq, chain_info.best_block_number, chain_info.best_block_timestamp);
+        record_mem_pool_size(&mem_pool);
+        Some(true)
     }
 
     fn calculate_timelock<C: BlockChainTrait>(&self, tx: &SignedTransaction, client: &C) -> Result<TxTimelock, Error> {
-        let mut max_block = None;
-        let mut max_timestamp = None;
+        let mut result = TxTimelock {
+            block: None,
+            timestamp: None,
+        };
         if let Action::TransferAsset {
             inputs,
             ..
         } = &tx.action
         {
             for input in inputs {
-                if let Some(timelock) = input.timelock {
-                    let (is_block_number, value) = match timelock {
-                        Timelock::Block(value) => (true, value),
-                        Timelock::BlockAge(value) => (
-                            true,
-                            client.transaction_block_number(&input.prev_out.tracker).ok_or_else(|| {
-                                Error::History(HistoryError::Timelocked {
-                                    timelock,
-                                    remaining_time: u64::max_value(),
-                                })
-                            })? + value,
-                        ),
-                        Timelock::Time(value) => (false, value),
-                        Timelock::TimeAge(value) => (
-                            false,
-                            client.transaction_block_timestamp(&input.prev_out.tracker).ok_or_else(|| {
-                                Error::History(HistoryError::Timelocked {
-                                    timelock,
-                                    remaining_time: u64::max_value(),
-                                })
-                            })? + value,
-                        ),
+                if let Some(timelock) = &input.timelock {
+                    Self::add_timelock_requirement(timelock, &input.prev_out.tracker, client, &mut result)?;
+                }
+            }
+        };
+        Ok(result)
+    }
+
+    /// Raises `result` so that `timelock` is satisfied once the best block reaches it.
+    /// For `Any`, the alternative that can be satisfied the earliest is waited for:
+    /// one that the best block already satisfies, or else one that no other alternative precedes
+    /// in both the block number and the timestamp.
+    fn add_timelock_requirement<C: BlockChainTrait>(
+        timelock: &Timelock,
+        tracker: &Tracker,
+        client: &C,
+        result: &mut TxTimelock,
+    ) -> Result<(), Error> {
+        let origin_missing = || {
+            Error::History(HistoryError::Timelocked {
+                timelock: timelock.clone(),
+                remaining_time: u64::max_value(),
+            })
+        };
+        let (is_block_number, value) = match timelock {
+            Timelock::All(timelocks) => {
+                for timelock in timelocks {
+                    Self::add_timelock_requirement(timelock, tracker, client, result)?;
+                }
+                return Ok(())
+            }
+            Timelock::Any(timelocks) => {
+                let mut alternatives = Vec::with_capacity(timelocks.len());
+                let mut first_error = None;
+                for timelock in timelocks {
+                    let mut requirement = TxTimelock {
+                        block: None,
+                        timestamp: None,
                     };
-                    if is_block_number {
-                        if max_block.is_none() || max_block.expect("The previous guard ensures") < value {
-                            max_block = Some(value);
+                    match Self::add_timelock_requirement(timelock, tracker, client, &mut requirement) {
+                        Ok(()) => alternatives.push(requirement),
+                        Err(err) => {
+                            first_error.get_or_insert(err);
                         }
-                    } else if max_timestamp.is_none() || max_timestamp.expect("The previous guard ensures") < value {
-                        max_timestamp = Some(value);
                     }
                 }
+                let chain_info = client.chain_info();
+                let is_satisfied = |requirement: &TxTimelock| {
+                    requirement.block.map_or(true, |block| block <= chain_info.best_block_number)
+                        && requirement.timestamp.map_or(true, |timestamp| timestamp <= chain_info.best_block_timestamp)
+                };
+                // `None` means no requirement, so it precedes any value.
+                let precedes =
+                    |a: &TxTimelock, b: &TxTimelock| a != b && a.block <= b.block && a.timestamp <= b.timestamp;
+                let earliest = alternatives.iter().find(|requirement| is_satisfied(requirement)).or_else(|| {
+                    alternatives
+                        .iter()
+                        .find(|requirement| !alternatives.iter().any(|other| precedes(other, requirement)))
+                });
+                return match (earliest, first_error) {
+                    (Some(requirement), _) => {
+                        result.block = result.block.max(requirement.block);
+                        result.timestamp = result.timestamp.max(requirement.timestamp);
+                        Ok(())
+                    }
+                    (None, Some(err)) => Err(err),
+                    (None, None) => Ok(()),
+                }
+            }
+            Timelock::Block(value) => (true, *value),
+            Timelock::BlockAge(value) => {
+                (true, client.transaction_block_number(tracker).ok_or_else(origin_missing)? + value)
+            }
+            Timelock::Time(value) => (false, *value),
+            Timelock::TimeAge(value) => {
+                (false, client.transaction_block_timestamp(tracker).ok_or_else(origin_missing)? + value)
             }
         };
-        Ok(TxTimelock {
-            block: max_block,
-            timestamp: max_timestamp,
-        })
+        let max = if is_block_number {
+            &mut result.block
+        } else {
+            &mut result.timestamp
+        };
+        if max.map_or(true, |max| max < value) {
+            *max = Some(value);
+        }
+        Ok(())
     }
 
     /// Prepares work which has to be done to seal.
@@ -552,14 +767,16 @@ impl Miner {
                 );
                 let pow_hash = *block.block().header().hash();
                 let number = block.block().header().number();
+                let timestamp = block.block().header().timestamp();
                 let score = *block.block().header().score();
                 let is_new = original_work_hash.map_or(true, |h| *block.block().header().hash() != h);
                 sealing_work.queue.push(block);
+                sealing_work.prepared_at = Instant::now();
                 // If push notifications are enabled we assume all work items are used.
                 if !self.notifiers.is_empty() && is_new {
                     sealing_work.queue.use_last_ref();
                 }
-                (Some((pow_hash, score, number)), is_new)
+                (Some((pow_hash, score, number, timestamp)), is_new)
             } else {
                 (None, false)
             };
@@ -571,9 +788,9 @@ impl Miner {
             (work, is_new)
         };
         if is_new {
-            if let Some((pow_hash, score, _number)) = work {
+            if let Some((pow_hash, score, number, timestamp)) = work {
                 let target = self.engine.score_to_target(&score);
-                self.notifiers.notify(pow_hash, target);
+                self.notifiers.notify(pow_hash, target, number, timestamp);
             }
         }
     }
@@ -635,6 +852,7 @@ impl Miner {
         let tx_total = transactions.len();
         let mut invalid_tx_users = HashSet::new();
 
+        open_block.begin_batch()?;
         for tx in transactions {
             let signer_public = tx.signer_public();
             let signer_address = public_to_address(&signer_public);
@@ -696,6 +914,7 @@ impl Miner {
                 } // imported ok
             }
         }
+        open_block.end_batch()?;
         cdebug!(MINER, "Pushed {}/{} transactions", tx_count, tx_total);
 
         let (parent_header, parent_hash) = {
@@ -773,12 +992,16 @@ impl Miner {
             self.engine.proposal_generated(&sealed);
         }
 
-        chain.import_generated_block(&sealed).is_ok()
+        if chain.import_generated_block(&sealed).is_err() {
+            return false
+        }
+        self.on_block_sealed();
+        true
     }
 
     /// Are we allowed to do a non-mandatory reseal?
     fn transaction_reseal_allowed(&self) -> bool {
-        self.sealing_enabled.load(Ordering::Relaxed) && (Instant::now() > self.next_allowed_reseal.get())
+        self.is_sealing_enabled() && (Instant::now() > self.next_allowed_reseal.get())
     }
 
     fn map_pending_block<F, T>(&self, f: F, latest_block_number: BlockNumber) -> Option<T>
@@ -794,6 +1017,14 @@ impl Miner {
         })
     }
 
+    /// Discards the blocks waiting for an external seal if the last one has waited longer than the timeout.
+    fn discard_stale_seal_work(&self, sealing_work: &mut SealingWork) {
+        if sealing_work.prepared_at.elapsed() > self.options.pending_seal_timeout {
+            cdebug!(MINER, "Discard the blocks waiting for an external seal");
+            sealing_work.queue.reset();
+        }
+    }
+
     fn is_allowed_transaction(&self, action: &Action) -> bool {
         if let Action::CreateShard {
             ..
@@ -813,12 +1044,20 @@ impl MinerService for Miner {
     type State = TopLevelState;
 
     fn status(&self) -> MinerStatus {
-        let status = self.mem_pool.read().status();
+        // Keep the lock order of prepare_block: sealing_work first, then mem_pool.
         let sealing_work = self.sealing_work.lock();
+        let mem_pool = self.mem_pool.read();
+        let status = mem_pool.status();
         MinerStatus {
+            sealing_enabled: self.is_sealing_enabled(),
+            author: self.params.get().author,
+            last_sealed_at: sealing_work.last_sealed_at,
             transactions_in_pending_queue: status.pending,
             transactions_in_future_queue: status.future,
+            local_transactions_in_queue: status.local,
             tranasction_in_pending_block: sealing_work.queue.peek_last_ref().map_or(0, |b| b.transactions().len()),
+            effective_minimum_fee: mem_pool.effective_minimum_fee(),
+            reseal_timers: self.reseal_timers(),
         }
     }
 
@@ -829,7 +1068,10 @@ impl MinerService for Miner {
     fn set_author(&self, address: Address) -> Result<(), AccountProviderError> {
         self.params.apply(|params| params.author = address);
 
-        if self.engine_type().need_signer_key() && self.engine.seals_internally().is_some() {
+        if self.engine_type().need_signer_key()
+            && self.engine.seals_internally().is_some()
+            && !self.can_seal_externally()
+        {
             ctrace!(MINER, "Set author to {:?}", address);
             // Sign test message
             self.accounts.get_unlocked_account(&address)?.sign(&Default::default())?;
@@ -878,7 +1120,8 @@ impl MinerService for Miner {
                     "Client is sending message after commit to db and inserting to chain; the block is available; qed",
                 );
                 let transactions = block.transactions();
-                let _ = self.add_transactions_to_pool(chain, transactions, TxOrigin::RetractedBlock, &mut mem_pool);
+                let _ =
+                    self.add_transactions_to_pool(chain, transactions, TxOrigin::RetractedBlock, None, &mut mem_pool);
             }
         }
 
@@ -893,6 +1136,7 @@ impl MinerService for Miner {
             let current_timestamp = chain.chain_info().best_block_timestamp;
             let mut mem_pool = self.mem_pool.write();
             mem_pool.remove_old(&fetch_account, current_block_number, current_timestamp);
+            record_mem_pool_size(&mem_pool);
         }
 
         if !self.options.no_reseal_timer {
@@ -904,6 +1148,10 @@ impl MinerService for Miner {
         self.engine.seals_internally().is_none()
     }
 
+    fn can_seal_externally(&self) -> bool {
+        self.options.external_sealing && self.engine.supports_external_sealing()
+    }
+
     fn engine_type(&self) -> EngineType {
         self.engine.engine_type()
     }
@@ -969,6 +1217,7 @@ impl MinerService for Miner {
             + ChainTimeInfo
             + FindActionHandler
             + TermInfo, {
+        let _lock_order = lock_order::acquire(LockClass::Miner);
         ctrace!(MINER, "update_sealing: preparing a block");
 
         let parent_block_number = chain.block_header(&parent_block).expect("Parent is always exist").number();
@@ -1006,6 +1255,13 @@ impl MinerService for Miner {
                         ctrace!(MINER, "update_sealing: imported internally sealed block");
                     }
                 }
+                Some(false) if self.can_seal_externally() => {
+                    ctrace!(MINER, "update_sealing: waiting for an external seal");
+                    self.prepare_work(block, original_work_hash);
+                    if !self.options.no_reseal_timer {
+                        chain.set_max_timer();
+                    }
+                }
                 Some(false) => {
                     ctrace!(MINER, "update_sealing: engine is not keen to seal internally right now");
                     return
@@ -1052,6 +1308,7 @@ impl MinerService for Miner {
             let h = sealed.header().hash();
             chain.import_generated_block(&sealed)?;
             cinfo!(MINER, "Submitted block imported OK. #{}: {}", n, h);
+            self.on_block_sealed();
             Ok(())
         })
     }
@@ -1069,6 +1326,47 @@ impl MinerService for Miner {
         ret.map(f)
     }
 
+    fn pending_seal<C>(&self, client: &C) -> Option<PendingSeal>
+    where
+        C: AccountData + BlockChainTrait + BlockProducer + ChainTimeInfo + EngineInfo + FindActionHandler + TermInfo,
+    {
+        if !self.can_seal_externally() {
+            return None
+        }
+        self.discard_stale_seal_work(&mut self.sealing_work.lock());
+        self.map_sealing_work(client, |b| {
+            let header = b.header();
+            PendingSeal {
+                block_hash: b.hash().into(),
+                number: header.number(),
+                parent_hash: *header.parent_hash(),
+                author: *header.author(),
+            }
+        })
+    }
+
+    fn verify_external_seal(&self, block_hash: BlockHash, seal: &[Bytes]) -> Result<(), Error> {
+        let mut sealing_work = self.sealing_work.lock();
+        self.discard_stale_seal_work(&mut sealing_work);
+        let block = sealing_work.queue.find_used(|b| b.hash() == *block_hash).ok_or_else(|| {
+            cwarn!(MINER, "Submitted seal rejected: Block unknown or out of date.");
+            Error::PowHashInvalid
+        })?;
+
+        let mut header = block.header().clone();
+        let expected_seal_fields = self.engine.seal_fields(&header);
+        if seal.len() != expected_seal_fields {
+            return Err(BlockError::InvalidSealArity(Mismatch {
+                expected: expected_seal_fields,
+                found: seal.len(),
+            })
+            .into())
+        }
+        header.set_seal(seal.to_vec());
+        self.engine.verify_block_seal(&header)?;
+        self.engine.verify_block_external(&header)
+    }
+
     fn import_external_transactions<C: MiningBlockChainClient + EngineInfo + TermInfo>(
         &self,
         client: &C,
@@ -1077,7 +1375,7 @@ impl MinerService for Miner {
         ctrace!(EXTERNAL_TX, "Importing external transactions");
         let results = {
             let mut mem_pool = self.mem_pool.write();
-            self.add_transactions_to_pool(client, transactions, TxOrigin::External, &mut mem_pool)
+            self.add_transactions_to_pool(client, transactions, TxOrigin::External, None, &mut mem_pool)
         };
 
         if !results.is_empty()
@@ -1098,6 +1396,7 @@ impl MinerService for Miner {
         &self,
         chain: &C,
         tx: SignedTransaction,
+        expiry: Option<u64>,
     ) -> Result<TransactionImportResult, Error> {
         ctrace!(OWN_TX, "Importing transaction: {:?}", tx);
 
@@ -1106,7 +1405,7 @@ impl MinerService for Miner {
             let mut mem_pool = self.mem_pool.write();
             // We need to re-validate transactions
             let import = self
-                .add_transactions_to_pool(chain, vec![tx.into()], TxOrigin::Local, &mut mem_pool)
+                .add_transactions_to_pool(chain, vec![tx.into()], TxOrigin::Local, expiry, &mut mem_pool)
                 .pop()
                 .expect("one result returned per added transaction; one added => one result; qed");
 
@@ -1179,17 +1478,25 @@ impl MinerService for Miner {
         let unverified = UnverifiedTransaction::new(tx, sig);
         let signed = SignedTransaction::try_new(unverified)?;
         let hash = signed.hash();
-        self.import_own_transaction(client, signed)?;
+        self.import_own_transaction(client, signed, None)?;
 
         Ok((hash, seq))
     }
 
+    fn expired_transaction_hint(&self, hash: &TxHash) -> Option<String> {
+        self.mem_pool.read().expired_transaction_hint(hash)
+    }
+
     fn ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions {
         // FIXME: Update the body size when the common params are updated
         let max_body_size = self.engine.machine().genesis_common_params().max_body_size();
         self.mem_pool.read().top_transactions(max_body_size, None, range)
     }
 
+    fn pending_fee_histogram(&self) -> FeeHistogram {
+        self.mem_pool.read().fee_histogram()
+    }
+
     fn count_pending_transactions(&self, range: Range<u64>) -> usize {
         self.mem_pool.read().count_pending_transactions(range)
     }
@@ -1202,6 +1509,16 @@ impl MinerService for Miner {
         let max_body_size = self.engine.machine().genesis_common_params().max_body_size();
         self.mem_pool.read().get_future_pending_transactions(max_body_size, None, range)
     }
+
+    fn ready_transactions_limited(
+        &self,
+        max_count: usize,
+        max_encoded_size: usize,
+        cursor: Option<ReadyTransactionsCursor>,
+    ) -> ReadyTransactionsPage {
+        self.mem_pool.read().ready_transactions_page(max_count, max_encoded_size, cursor)
+    }
+
     /// Get a list of all future transactions.
     fn future_transactions(&self) -> Vec<SignedTransaction> {
         self.mem_pool.read().future_transactions()
@@ -1210,6 +1527,7 @@ impl MinerService for Miner {
     fn start_sealing<C: MiningBlockChainClient + EngineInfo + TermInfo>(&self, client: &C) {
         cdebug!(MINER, "Start sealing");
         self.sealing_enabled.store(true, Ordering::Relaxed);
+        record_sealing_enabled(true);
         // ------------------------------------------------------------------
         // | NOTE Code below requires mem_pool and sealing_queue locks.     |
         // | Make sure to release the locks before calling that method.     |
@@ -1223,6 +1541,7 @@ impl MinerService for Miner {
     fn stop_sealing(&self) {
         cdebug!(MINER, "Stop sealing");
         self.sealing_enabled.store(false, Ordering::Relaxed);
+        record_sealing_enabled(false);
     }
 
     fn get_malicious_users(&self) -> Vec<Address> {
@@ -1246,6 +1565,36 @@ impl MinerService for Miner {
     }
 }
 
+fn record_mem_pool_size(mem_pool: &MemPool) {
+    let status = mem_pool.status();
+    METRICS
+        .gauge(
+            "codechain_mem_pool_pending",
+            "The number of transactions in the mem pool that can be included in a block",
+        )
+        .set(status.pending as i64);
+    METRICS
+        .gauge("codechain_mem_pool_future", "The number of transactions in the mem pool waiting for their turn")
+        .set(status.future as i64);
+    METRICS
+        .gauge("codechain_mem_pool_local", "The number of transactions in the mem pool submitted to this node")
+        .set(status.local as i64);
+    METRICS
+        .gauge("codechain_mem_pool_effective_min_fee", "The fee a transaction should pay to enter the mem pool")
+        .set(mem_pool.effective_minimum_fee() as i64);
+}
+
+fn record_sealing_enabled(enabled: bool) {
+    METRICS.gauge("codechain_miner_sealing_enabled", "1 if the miner is allowed to seal blocks").set(enabled as i64);
+}
+
+fn record_last_sealed_at(at: SystemTime) {
+    let timestamp = at.duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
+    METRICS
+        .gauge("codechain_miner_last_sealed_at", "The UNIX timestamp when this node sealed a block the last time")
+        .set(timestamp as i64);
+}
+
 fn get_next_seq(transactions: impl IntoIterator<Item = SignedTransaction>, addresses: &[Address]) -> Option<u64> {
     let mut txes = transactions
         .into_iter()
@@ -1261,7 +1610,7 @@ fn get_next_seq(transactions: impl IntoIterator<Item = SignedTransaction>, addre
 #[cfg(test)]
 pub mod test {
     use cio::IoService;
-    use ckey::{Private, Signature};
+    use ckey::{sign, KeyPair, Private, Signature};
     use ctimer::TimerLoop;
     use ctypes::transaction::Transaction;
     use primitives::H512;
@@ -1270,9 +1619,44 @@ pub mod test {
     use super::super::super::service::ClientIoMessage;
     use super::super::super::transaction::{SignedTransaction, UnverifiedTransaction};
     use super::*;
-    use crate::client::Client;
+    use crate::client::{Client, EngineClient, TestBlockChainClient};
     use crate::db::NUM_COLUMNS;
 
+    #[test]
+    fn any_timelock_waits_for_the_earliest_alternative() {
+        let client = TestBlockChainClient::default();
+        client.add_blocks(10, 0);
+        let tracker = H256::random().into();
+        let requirement = |timelock: Timelock| {
+            let mut result = TxTimelock {
+                block: None,
+                timestamp: None,
+            };
+            Miner::add_timelock_requirement(&timelock, &tracker, &client, &mut result).unwrap();
+            result
+        };
+        let requires = |block, timestamp| TxTimelock {
+            block,
+            timestamp,
+        };
+
+        assert_eq!(
+            requires(Some(20), None),
+            requirement(Timelock::Any(vec![Timelock::Block(30), Timelock::Block(20)]))
+        );
+        // The best block satisfies the second alternative.
+        assert_eq!(requires(None, Some(5)), requirement(Timelock::Any(vec![Timelock::Block(30), Timelock::Time(5)])));
+        // Neither precedes the other, so the first one is waited for.
+        assert_eq!(requires(Some(30), None), requirement(Timelock::Any(vec![Timelock::Block(30), Timelock::Time(20)])));
+        assert_eq!(
+            requires(Some(30), Some(20)),
+            requirement(Timelock::Any(vec![
+                Timelock::All(vec![Timelock::Block(40), Timelock::Time(20)]),
+                Timelock::All(vec![Timelock::Block(30), Timelock::Time(20)]),
+            ]))
+        );
+    }
+
     #[test]
     fn check_add_transactions_result_idx() {
         let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
@@ -1310,7 +1694,149 @@ pub mod test {
         );
 
         let transactions = vec![transaction1.clone(), transaction2, transaction1];
-        miner.add_transactions_to_pool(client.as_ref(), transactions, TxOrigin::Local, &mut mem_pool);
+        miner.add_transactions_to_pool(client.as_ref(), transactions, TxOrigin::Local, None, &mut mem_pool);
+    }
+
+    #[test]
+    fn pending_seq_reflects_chained_transactions() {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let scheme = Scheme::new_test();
+        let miner = Arc::new(Miner::with_scheme_for_test(&scheme, db.clone()));
+        let client = generate_test_client(db, Arc::clone(&miner), &scheme).unwrap();
+
+        let private: Private = "ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd".into();
+        let address = KeyPair::from_private(private).unwrap().address();
+        let transactions: Vec<UnverifiedTransaction> = (0..2)
+            .map(|seq| {
+                SignedTransaction::new_with_sign(
+                    Transaction {
+                        seq,
+                        fee: 10,
+                        network_id: "tc".into(),
+                        action: Action::Pay {
+                            receiver: Address::random(),
+                            quantity: 1,
+                            memo: None,
+                        },
+                    },
+                    &private,
+                )
+                .into()
+            })
+            .collect();
+
+        let results = {
+            let mut mem_pool = miner.mem_pool.write();
+            miner.add_transactions_to_pool(client.as_ref(), transactions, TxOrigin::Local, None, &mut mem_pool)
+        };
+        assert!(results.iter().all(Result::is_ok));
+
+        assert_eq!(0, client.latest_seq(&address));
+        assert_eq!(2, client.latest_seq_pending(&address));
+    }
+
+    #[test]
+    fn external_signer_seals_the_prepared_block() {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let scheme = Scheme::new_test_simple_poa_with_test_validator();
+        let options = MinerOptions {
+            external_sealing: true,
+            no_reseal_timer: true,
+            ..Default::default()
+        };
+        // The key of the validator is not in the account provider.
+        let miner = Miner::new(options, &scheme, AccountProvider::transient_provider(), db.clone());
+        let client = generate_test_client(db, Arc::clone(&miner), &scheme).unwrap();
+
+        let validator =
+            KeyPair::from_private("ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd".into()).unwrap();
+        miner.set_author(validator.address()).unwrap();
+        assert!(miner.can_seal_externally());
+
+        let pending = miner.pending_seal(client.as_ref()).unwrap();
+        assert_eq!(1, pending.number);
+        assert_eq!(validator.address(), pending.author);
+
+        let other: Private = H256::random().into();
+        let invalid_seal = vec![::rlp::encode(&sign(&other, &pending.block_hash).unwrap())];
+        assert!(miner.verify_external_seal(pending.block_hash, &invalid_seal).is_err());
+        assert!(miner.verify_external_seal(pending.block_hash, &[]).is_err());
+
+        let seal = vec![::rlp::encode(&sign(validator.private(), &pending.block_hash).unwrap())];
+        assert!(miner.verify_external_seal(pending.block_hash, &seal).is_ok());
+        client.submit_seal(pending.block_hash, seal);
+        assert_eq!(1, client.chain_info().best_block_number);
+    }
+
+    #[test]
+    fn pending_seal_is_not_given_without_external_sealing() {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let scheme = Scheme::new_test_simple_poa_with_test_validator();
+        let miner = Arc::new(Miner::with_scheme_for_test(&scheme, db.clone()));
+        let client = generate_test_client(db, Arc::clone(&miner), &scheme).unwrap();
+
+        assert!(!miner.can_seal_externally());
+        assert!(miner.pending_seal(client.as_ref()).is_none());
+    }
+
+    #[test]
+    fn status_follows_queued_transactions_and_sealed_blocks() {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let scheme = Scheme::new_test_solo();
+        let miner = Arc::new(Miner::with_scheme_for_test(&scheme, db.clone()));
+        let client = generate_test_client(db, Arc::clone(&miner), &scheme).unwrap();
+
+        let status = miner.status();
+        assert!(status.sealing_enabled);
+        assert_eq!(None, status.last_sealed_at);
+        assert_eq!(0, status.transactions_in_pending_queue);
+        assert_eq!(0, status.transactions_in_future_queue);
+        assert_eq!(0, status.local_transactions_in_queue);
+
+        let private: Private = "ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd".into();
+        // The transaction with seq 2 waits for the one with seq 1, which is never sent.
+        let transactions: Vec<UnverifiedTransaction> = [0, 2]
+            .iter()
+            .map(|&seq| {
+                SignedTransaction::new_with_sign(
+                    Transaction {
+                        seq,
+                        fee: 10,
+                        network_id: "tc".into(),
+                        action: Action::Pay {
+                            receiver: Address::random(),
+                            quantity: 1,
+                            memo: None,
+                        },
+                    },
+                    &private,
+                )
+                .into()
+            })
+            .collect();
+        let results = {
+            let mut mem_pool = miner.mem_pool.write();
+            miner.add_transactions_to_pool(client.as_ref(), transactions, TxOrigin::Local, None, &mut mem_pool)
+        };
+        assert!(results.iter().all(Result::is_ok));
+
+        let status = miner.status();
+        assert_eq!(1, status.transactions_in_pending_queue);
+        assert_eq!(1, status.transactions_in_future_queue);
+        assert_eq!(2, status.local_transactions_in_queue);
+
+        miner.update_sealing(client.as_ref(), BlockId::Latest, false);
+        assert_eq!(1, client.chain_info().best_block_number);
+
+        let status = miner.status();
+        assert_eq!(0, status.transactions_in_pending_queue);
+        assert_eq!(1, status.transactions_in_future_queue);
+        assert_eq!(1, status.local_transactions_in_queue);
+        assert!(status.last_sealed_at.is_some());
+        assert_eq!(miner.last_sealed_at(), status.last_sealed_at);
+
+        miner.stop_sealing();
+        assert!(!miner.status().sealing_enabled);
     }
 
     fn generate_test_client(db: Arc<dyn KeyValueDB>, miner: Arc<Miner>, scheme: &Scheme) -> Result<Arc<Client>, Error> {
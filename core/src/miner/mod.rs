@@ -24,7 +24,10 @@ mod stratum;
 mod work_notify;
 
 use self::mem_pool_types::AccountDetails;
-pub use self::mem_pool_types::MemPoolMinFees;
+pub use self::mem_pool_types::{
+    FeeBucket, FeeHistogram, FutureReason, MemPoolEntry, MemPoolMinFees, ReadyTransactionsCursor,
+    ReadyTransactionsPage, SeqGapInfo, TxOrigin,
+};
 pub use self::miner::{AuthoringParams, Miner, MinerOptions};
 pub use self::stratum::{Config as StratumConfig, Error as StratumError, Stratum};
 use crate::account_provider::{AccountProvider, Error as AccountProviderError};
@@ -39,10 +42,11 @@ use crate::BlockId;
 use ckey::{public_to_address, Address, Password, PlatformAddress, Public};
 use cstate::{FindActionHandler, TopStateView};
 use ctypes::transaction::IncompleteTransaction;
-use ctypes::{BlockHash, TxHash};
+use ctypes::{BlockHash, BlockNumber, TxHash};
 use cvm::ChainTimeInfo;
 use primitives::Bytes;
 use std::ops::Range;
+use std::time::{Duration, SystemTime};
 
 /// Miner client API
 pub trait MinerService: Send + Sync {
@@ -115,6 +119,19 @@ pub trait MinerService: Send + Sync {
         F: FnOnce(&ClosedBlock) -> T,
         Self: Sized;
 
+    /// Whether the blocks are given to an external signer instead of being sealed internally.
+    fn can_seal_externally(&self) -> bool;
+
+    /// Get the block waiting for a seal from an external signer.
+    /// The waiting blocks are discarded and a new block is prepared if they have waited too long.
+    fn pending_seal<C>(&self, client: &C) -> Option<PendingSeal>
+    where
+        C: AccountData + BlockChainTrait + BlockProducer + ChainTimeInfo + EngineInfo + FindActionHandler + TermInfo;
+
+    /// Verify the seal from an external signer for the block of `block_hash` against the engine.
+    /// It doesn't import the block. Submit the seal with `submit_seal` to import it.
+    fn verify_external_seal(&self, block_hash: BlockHash, seal: &[Bytes]) -> Result<(), Error>;
+
     /// Imports transactions to mem pool.
     fn import_external_transactions<C: MiningBlockChainClient + EngineInfo + TermInfo>(
         &self,
@@ -123,10 +140,12 @@ pub trait MinerService: Send + Sync {
     ) -> Vec<Result<TransactionImportResult, Error>>;
 
     /// Imports own (node owner) transaction to mem pool.
+    /// The transaction is dropped from the mem pool if it's not mined until the expiry.
     fn import_own_transaction<C: MiningBlockChainClient + EngineInfo + TermInfo>(
         &self,
         chain: &C,
         tx: SignedTransaction,
+        expiry: Option<u64>,
     ) -> Result<TransactionImportResult, Error>;
 
     /// Imports incomplete (node owner) transaction to mem pool.
@@ -140,12 +159,26 @@ pub trait MinerService: Send + Sync {
         seq: Option<u64>,
     ) -> Result<(TxHash, u64), Error>;
 
+    /// Get the error of the transaction dropped from the mem pool because its expiry has passed.
+    fn expired_transaction_hint(&self, hash: &TxHash) -> Option<String>;
+
     /// Get a list of all pending transactions in the mem pool.
     fn ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions;
 
     /// Get list of all future transaction in the mem pool.
     fn future_ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions;
 
+    /// Get a page of the pending transactions after the cursor in the order of block building.
+    fn ready_transactions_limited(
+        &self,
+        max_count: usize,
+        max_encoded_size: usize,
+        cursor: Option<ReadyTransactionsCursor>,
+    ) -> ReadyTransactionsPage;
+
+    /// Get the counts and sizes of the pending transactions bucketed by fee per byte.
+    fn pending_fee_histogram(&self) -> FeeHistogram;
+
     /// Get a count of all pending transactions in the mem pool.
     fn count_pending_transactions(&self, range: Range<u64>) -> usize;
 
@@ -180,12 +213,44 @@ pub trait MinerService: Send + Sync {
 /// Mining status
 #[derive(Debug)]
 pub struct MinerStatus {
+    /// Whether the miner is allowed to seal blocks
+    pub sealing_enabled: bool,
+    /// The author of the blocks sealed by this node
+    pub author: Address,
+    /// When this node sealed a block the last time
+    pub last_sealed_at: Option<SystemTime>,
     /// Number of transactions in queue with state `pending` (ready to be included in block)
     pub transactions_in_pending_queue: usize,
     /// Number of transactions in queue with state `future` (not yet ready to be included in block)
     pub transactions_in_future_queue: usize,
+    /// Number of transactions in queue which were submitted to this node
+    pub local_transactions_in_queue: usize,
     /// Number of transactions included in currently mined block
     pub tranasction_in_pending_block: usize,
+    /// The fee a new transaction should pay to enter the full queue
+    pub effective_minimum_fee: u64,
+    /// The state of the reseal timers
+    pub reseal_timers: ResealTimers,
+}
+
+/// The time left until the miner may, or must, seal a new block.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResealTimers {
+    /// New transactions don't trigger a reseal until it becomes zero.
+    pub min_period_left: Duration,
+    /// A block is sealed even without transactions when it becomes zero.
+    pub max_period_left: Duration,
+}
+
+/// A block prepared by the miner, waiting for a seal from an external signer.
+#[derive(Debug)]
+pub struct PendingSeal {
+    /// The hash of the header without the seal, which the seal signs.
+    /// The seal is submitted with this hash.
+    pub block_hash: BlockHash,
+    pub number: BlockNumber,
+    pub parent_hash: BlockHash,
+    pub author: Address,
 }
 
 /// Represents the result of importing tranasction.
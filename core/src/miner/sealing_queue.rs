@@ -63,6 +63,13 @@ impl SealingQueue {
         self.in_use.clear();
     }
 
+    /// Return a reference to the used item that satisfies the predicate without taking it.
+    pub fn find_used<P>(&self, predicate: P) -> Option<&ClosedBlock>
+    where
+        P: Fn(&ClosedBlock) -> bool, {
+        self.in_use.iter().find(|r| predicate(r))
+    }
+
     pub fn take_used_if<P>(&mut self, predicate: P) -> Option<ClosedBlock>
     where
         P: Fn(&ClosedBlock) -> bool, {
@@ -21,6 +21,7 @@ use crate::error::Error as MinerError;
 use crate::miner::work_notify::NotifyWork;
 use crate::miner::{Miner, MinerService};
 use cstratum::{Error as StratumServiceError, JobDispatcher, PushWorkHandler, Stratum as StratumService};
+use ctypes::BlockNumber;
 use primitives::{Bytes, H256, U256};
 use std::net::{AddrParseError, SocketAddr};
 use std::sync::Arc;
@@ -120,7 +121,7 @@ impl From<AddrParseError> for Error {
 }
 
 impl NotifyWork for Stratum {
-    fn notify(&self, pow_hash: H256, target: U256) {
+    fn notify(&self, pow_hash: H256, target: U256, _number: BlockNumber, _timestamp: u64) {
         ctrace!(STRATUM, "Notify work");
 
         self.service
@@ -17,80 +17,344 @@
 extern crate hyper;
 
 use self::hyper::client::{Client, Request, Response};
-use self::hyper::header::ContentType;
+use self::hyper::header::{ContentLength, ContentType};
 use self::hyper::method::Method;
 use self::hyper::net::HttpStream;
 use self::hyper::{Next, Url};
-use parking_lot::Mutex;
+use ccrypto::sha256;
+use crossbeam_channel as crossbeam;
+use ctypes::BlockNumber;
 use primitives::{H256, U256};
+use std::collections::HashMap;
 use std::io::Write;
+use std::thread::{Builder, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The header that carries the HMAC-SHA256 of the body, keyed by the secret of the URL.
+const SIGNATURE_HEADER: &str = "X-CodeChain-Signature";
+/// The number of consecutive failed deliveries after which a URL is paused.
+const FAILURES_TO_PAUSE: u32 = 5;
+/// How long a URL is paused before the next delivery is tried.
+const PAUSE_DURATION: Duration = Duration::from_secs(60);
 
 /// Trait for notifying about new mining work
 pub trait NotifyWork: Send + Sync {
     /// Fired when new mining job available
-    fn notify(&self, pow_hash: H256, target: U256);
+    fn notify(&self, pow_hash: H256, target: U256, number: BlockNumber, timestamp: u64);
 }
 
 /// POSTs info about new work to given urls.
+///
+/// The deliveries run on a separate thread. The failures of the connections and the 5xx responses are retried
+/// with an exponential backoff. Every URL has its own retries, so a failing URL doesn't delay the others.
+/// A newer work replaces the ones being retried.
 pub struct WorkPoster {
-    urls: Vec<Url>,
-    client: Mutex<Client<PostHandler>>,
+    sender: Option<crossbeam::Sender<Work>>,
+    worker: Option<JoinHandle<()>>,
 }
 
 impl WorkPoster {
-    /// Create new `WorkPoster`.
-    pub fn new(urls: &[String]) -> Self {
-        let urls = urls
+    /// Create new `WorkPoster`. The body sent to a URL is signed if the URL has a secret.
+    pub fn new(urls: &[String], secrets: &HashMap<String, String>) -> Self {
+        Self::with_policy(urls, secrets, Default::default())
+    }
+
+    fn with_policy(urls: &[String], secrets: &HashMap<String, String>, policy: RetryPolicy) -> Self {
+        let endpoints = urls
             .iter()
             .filter_map(|u| match Url::parse(u) {
-                Ok(url) => Some(url),
+                Ok(url) => Some(Endpoint::new(url, secrets.get(u).cloned())),
                 Err(e) => {
                     cwarn!(MINER, "Error parsing URL {} : {}", u, e);
                     None
                 }
             })
             .collect();
-        let client = WorkPoster::create_client();
+        let (sender, receiver) = crossbeam::unbounded();
+        let mut worker = Worker {
+            receiver,
+            client: Worker::create_client(),
+            endpoints,
+            policy,
+        };
+        let worker = Builder::new()
+            .name("work-notify".to_string())
+            .spawn(move || worker.run())
+            .expect("Error creating the work notify thread");
         WorkPoster {
-            client: Mutex::new(client),
-            urls,
+            sender: Some(sender),
+            worker: Some(worker),
+        }
+    }
+}
+
+impl NotifyWork for WorkPoster {
+    fn notify(&self, pow_hash: H256, target: U256, number: BlockNumber, timestamp: u64) {
+        let work = Work {
+            pow_hash,
+            target,
+            number,
+            timestamp,
+        };
+        if let Some(sender) = &self.sender {
+            if sender.send(work).is_err() {
+                cwarn!(MINER, "The work notify thread is stopped");
+            }
+        }
+    }
+}
+
+impl Drop for WorkPoster {
+    fn drop(&mut self) {
+        // Closing the channel stops the worker.
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                cerror!(MINER, "The work notify thread panicked");
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Work {
+    pow_hash: H256,
+    target: U256,
+    number: BlockNumber,
+    timestamp: u64,
+}
+
+impl Work {
+    /// The `result` field is kept for the proxies that read the work in the format of `getWork`.
+    fn to_json(&self) -> String {
+        format!(
+            r#"{{"result":["0x{:x}","0x{:x}"],"powHash":"0x{:x}","target":"0x{:x}","number":{},"timestamp":{}}}"#,
+            self.pow_hash, self.target, self.pow_hash, self.target, self.number, self.timestamp
+        )
+    }
+}
+
+struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    response_timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            response_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// The work being delivered to an endpoint.
+struct Pending {
+    body: String,
+    /// The number of the failed attempts.
+    failures: u32,
+    next_attempt: Instant,
+    backoff: Duration,
+}
+
+/// A URL to notify, its retries and its circuit breaker.
+struct Endpoint {
+    url: Url,
+    secret: Option<String>,
+    pending: Option<Pending>,
+    consecutive_failures: u32,
+    paused_until: Option<Instant>,
+}
+
+impl Endpoint {
+    fn new(url: Url, secret: Option<String>) -> Self {
+        Self {
+            url,
+            secret,
+            pending: None,
+            consecutive_failures: 0,
+            paused_until: None,
         }
     }
 
+    /// Replaces the pending work. A work superseded after a failed attempt counts as a failed delivery,
+    /// so an endpoint that keeps stalling is paused even if the works come faster than its retries.
+    fn start(&mut self, body: &str, policy: &RetryPolicy, now: Instant) {
+        if self.pending.as_ref().map_or(false, |pending| pending.failures > 0) {
+            cdebug!(MINER, "The work notification to {} is superseded before it's delivered", self.url);
+            self.on_failed(now);
+        }
+        self.pending = if self.is_paused(now) {
+            None
+        } else {
+            Some(Pending {
+                body: body.to_string(),
+                failures: 0,
+                next_attempt: now,
+                backoff: policy.initial_backoff,
+            })
+        };
+    }
+
+    fn is_due(&self, now: Instant) -> bool {
+        self.pending.as_ref().map_or(false, |pending| pending.next_attempt <= now)
+    }
+
+    fn is_paused(&self, now: Instant) -> bool {
+        self.paused_until.map_or(false, |until| now < until)
+    }
+
+    fn on_delivered(&mut self) {
+        if self.paused_until.take().is_some() {
+            cinfo!(MINER, "Resume the work notification to {}", self.url);
+        }
+        self.consecutive_failures = 0;
+    }
+
+    fn on_failed(&mut self, now: Instant) {
+        self.consecutive_failures += 1;
+        if self.consecutive_failures >= FAILURES_TO_PAUSE {
+            cwarn!(
+                MINER,
+                "The work notification to {} failed {} times in a row. Pause it for {:?}",
+                self.url,
+                self.consecutive_failures,
+                PAUSE_DURATION
+            );
+            self.paused_until = Some(now + PAUSE_DURATION);
+            // Another failure after the pause pauses it again.
+            self.consecutive_failures = FAILURES_TO_PAUSE - 1;
+        }
+    }
+}
+
+enum Outcome {
+    Delivered,
+    Rejected(u16),
+    Retryable(String),
+}
+
+struct Worker {
+    receiver: crossbeam::Receiver<Work>,
+    client: Client<PostHandler>,
+    endpoints: Vec<Endpoint>,
+    policy: RetryPolicy,
+}
+
+impl Worker {
     fn create_client() -> Client<PostHandler> {
         Client::<PostHandler>::configure().keep_alive(true).build().expect("Error creating HTTP client")
     }
-}
 
-impl NotifyWork for WorkPoster {
-    fn notify(&self, pow_hash: H256, target: U256) {
-        let body = format!(r#"{{ "result": ["0x{:x}","0x{:x}"] }}"#, pow_hash, target);
-        let mut client = self.client.lock();
-        for u in &self.urls {
-            if let Err(e) = client.request(u.clone(), PostHandler {
-                body: body.clone(),
-            }) {
-                cwarn!(MINER, "Error sending HTTP notification to {} : {}, retrying", u, e);
-                // TODO: remove this once https://github.com/hyperium/hyper/issues/848 is fixed
-                *client = WorkPoster::create_client();
-                if let Err(e) = client.request(u.clone(), PostHandler {
-                    body: body.clone(),
-                }) {
-                    cwarn!(MINER, "Error sending HTTP notification to {} : {}", u, e);
+    fn run(&mut self) {
+        loop {
+            let next_attempt = self
+                .endpoints
+                .iter()
+                .filter_map(|endpoint| endpoint.pending.as_ref())
+                .map(|pending| pending.next_attempt)
+                .min();
+            let received = match next_attempt {
+                Some(next_attempt) => {
+                    match self.receiver.recv_timeout(next_attempt.saturating_duration_since(Instant::now())) {
+                        Ok(work) => Some(work),
+                        Err(crossbeam::RecvTimeoutError::Timeout) => None,
+                        Err(crossbeam::RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                None => match self.receiver.recv() {
+                    Ok(work) => Some(work),
+                    Err(_) => return,
+                },
+            };
+            if let Some(work) = received {
+                // Only the latest work is worth notifying.
+                let work = self.receiver.try_iter().last().unwrap_or(work);
+                let body = work.to_json();
+                let now = Instant::now();
+                for endpoint in &mut self.endpoints {
+                    endpoint.start(&body, &self.policy, now);
+                }
+            }
+            for index in 0..self.endpoints.len() {
+                if self.endpoints[index].is_due(Instant::now()) {
+                    self.deliver(index);
+                }
+            }
+        }
+    }
+
+    /// Makes an attempt to deliver the pending work of the endpoint, and schedules the retry if it failed.
+    fn deliver(&mut self, index: usize) {
+        let mut pending = self.endpoints[index].pending.take().expect("Only the pending work is delivered");
+        let outcome = self.post(index, &pending.body);
+        let endpoint = &mut self.endpoints[index];
+        match outcome {
+            Outcome::Delivered => endpoint.on_delivered(),
+            Outcome::Rejected(status) => {
+                cwarn!(MINER, "{} rejected the work notification with {}", endpoint.url, status);
+                endpoint.on_failed(Instant::now());
+            }
+            Outcome::Retryable(reason) => {
+                cdebug!(MINER, "Error sending HTTP notification to {} : {}", endpoint.url, reason);
+                if pending.failures == self.policy.max_retries {
+                    endpoint.on_failed(Instant::now());
+                } else {
+                    pending.failures += 1;
+                    pending.next_attempt = Instant::now() + pending.backoff;
+                    pending.backoff *= 2;
+                    endpoint.pending = Some(pending);
                 }
             }
         }
     }
+
+    fn post(&mut self, index: usize, body: &str) -> Outcome {
+        let endpoint = &self.endpoints[index];
+        let (sender, receiver) = crossbeam::bounded(1);
+        let handler = PostHandler {
+            body: body.to_string(),
+            signature: endpoint.secret.as_ref().map(|secret| hmac_sha256(secret.as_bytes(), body.as_bytes())),
+            outcome: Some(sender),
+        };
+        if let Err(e) = self.client.request(endpoint.url.clone(), handler) {
+            // TODO: remove this once https://github.com/hyperium/hyper/issues/848 is fixed
+            self.client = Worker::create_client();
+            return Outcome::Retryable(e.to_string())
+        }
+        match receiver.recv_timeout(self.policy.response_timeout) {
+            Ok(outcome) => outcome,
+            Err(_) => Outcome::Retryable("No response".to_string()),
+        }
+    }
 }
 
 struct PostHandler {
     body: String,
+    signature: Option<H256>,
+    outcome: Option<crossbeam::Sender<Outcome>>,
+}
+
+impl PostHandler {
+    fn report(&mut self, outcome: Outcome) {
+        if let Some(sender) = self.outcome.take() {
+            // The worker may not wait for the outcome anymore.
+            let _ = sender.send(outcome);
+        }
+    }
 }
 
 impl hyper::client::Handler<HttpStream> for PostHandler {
     fn on_request(&mut self, request: &mut Request) -> Next {
         request.set_method(Method::Post);
-        request.headers_mut().set(ContentType::json());
+        let headers = request.headers_mut();
+        headers.set(ContentType::json());
+        headers.set(ContentLength(self.body.len() as u64));
+        if let Some(signature) = self.signature {
+            headers.set_raw(SIGNATURE_HEADER, vec![format!("sha256={:x}", signature).into_bytes()]);
+        }
         Next::write()
     }
 
@@ -102,7 +366,13 @@ impl hyper::client::Handler<HttpStream> for PostHandler {
         Next::read()
     }
 
-    fn on_response(&mut self, _response: Response) -> Next {
+    fn on_response(&mut self, response: Response) -> Next {
+        let status = response.status().to_u16();
+        self.report(match status {
+            200..=299 => Outcome::Delivered,
+            500..=599 => Outcome::Retryable(format!("HTTP {}", status)),
+            _ => Outcome::Rejected(status),
+        });
         Next::end()
     }
 
@@ -112,6 +382,186 @@ impl hyper::client::Handler<HttpStream> for PostHandler {
 
     fn on_error(&mut self, err: hyper::Error) -> Next {
         ctrace!(MINER, "Error posting work data: {}", err);
+        self.report(Outcome::Retryable(err.to_string()));
         Next::end()
     }
 }
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> H256 {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner: Vec<u8> = block.iter().map(|b| b ^ 0x36).chain(message.iter().cloned()).collect();
+    let outer: Vec<u8> = block.iter().map(|b| b ^ 0x5c).chain(sha256(inner).iter().cloned()).collect();
+    sha256(outer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+    use std::thread;
+
+    struct ReceivedRequest {
+        headers: Vec<String>,
+        body: String,
+    }
+
+    /// Answers the requests with the given statuses in order.
+    fn run_server(statuses: Vec<u16>) -> (String, crossbeam::Receiver<ReceivedRequest>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let (sender, receiver) = crossbeam::unbounded();
+        thread::spawn(move || {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end().to_string();
+                    if line.is_empty() {
+                        break
+                    }
+                    headers.push(line);
+                }
+                let content_length = headers
+                    .iter()
+                    .filter_map(|header| header.splitn(2, ": ").nth(1).filter(|_| header.starts_with("Content-Length")))
+                    .next()
+                    .map_or(0, |length| length.parse().unwrap());
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                write!(stream, "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).unwrap();
+                sender
+                    .send(ReceivedRequest {
+                        headers,
+                        body: String::from_utf8(body).unwrap(),
+                    })
+                    .unwrap();
+            }
+        });
+        (url, receiver)
+    }
+
+    fn policy_for_test() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(10),
+            response_timeout: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn hmac_sha256_matches_rfc4231() {
+        assert_eq!(
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843".parse::<H256>().unwrap(),
+            hmac_sha256(b"Jefe", b"what do ya want for nothing?")
+        );
+        // The key longer than the block size is hashed.
+        assert_eq!(
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54".parse::<H256>().unwrap(),
+            hmac_sha256(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First")
+        );
+    }
+
+    #[test]
+    fn failed_notification_is_retried() {
+        let (url, requests) = run_server(vec![500, 200]);
+        let mut secrets = HashMap::new();
+        secrets.insert(url.clone(), "secret".to_string());
+        let poster = WorkPoster::with_policy(&[url], &secrets, policy_for_test());
+        poster.notify(H256::random(), 1000.into(), 3, 1_500_000_000);
+
+        let first = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+        let second = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(first.body, second.body);
+        assert!(second.body.contains(r#""number":3"#), "{}", second.body);
+        assert!(second.body.contains(r#""timestamp":1500000000"#), "{}", second.body);
+
+        let signature = format!("{}: sha256={:x}", SIGNATURE_HEADER, hmac_sha256(b"secret", second.body.as_bytes()));
+        assert!(second.headers.iter().any(|header| header.eq_ignore_ascii_case(&signature)), "{:?}", second.headers);
+    }
+
+    #[test]
+    fn notification_without_secret_is_not_signed() {
+        let (url, requests) = run_server(vec![200]);
+        let poster = WorkPoster::with_policy(&[url], &HashMap::new(), policy_for_test());
+        poster.notify(H256::random(), 1000.into(), 3, 1_500_000_000);
+
+        let request = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+        let signature_header = SIGNATURE_HEADER.to_lowercase();
+        assert!(!request.headers.iter().any(|header| header.to_lowercase().starts_with(&signature_header)));
+    }
+
+    #[test]
+    fn failing_url_does_not_delay_the_others() {
+        let (failing_url, failing_requests) = run_server(vec![500, 500]);
+        let (url, requests) = run_server(vec![200, 200]);
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(60),
+            ..policy_for_test()
+        };
+        let poster = WorkPoster::with_policy(&[failing_url, url], &HashMap::new(), policy);
+        poster.notify(H256::random(), 1000.into(), 3, 1_500_000_000);
+        failing_requests.recv_timeout(Duration::from_secs(10)).unwrap();
+        let first = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(first.body.contains(r#""number":3"#), "{}", first.body);
+
+        // The newer work replaces the retry to the failing URL.
+        poster.notify(H256::random(), 1000.into(), 4, 1_500_000_001);
+        let second = failing_requests.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(second.body.contains(r#""number":4"#), "{}", second.body);
+        let second = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(second.body.contains(r#""number":4"#), "{}", second.body);
+    }
+
+    #[test]
+    fn superseded_retry_counts_as_failure() {
+        let policy = policy_for_test();
+        let mut endpoint = Endpoint::new(Url::parse("http://127.0.0.1:1/").unwrap(), None);
+        let now = Instant::now();
+        endpoint.start("first", &policy, now);
+        endpoint.start("second", &policy, now);
+        assert_eq!(0, endpoint.consecutive_failures);
+
+        endpoint.pending.as_mut().unwrap().failures = 1;
+        endpoint.start("third", &policy, now);
+        assert_eq!(1, endpoint.consecutive_failures);
+        assert_eq!("third", endpoint.pending.as_ref().unwrap().body);
+        assert!(endpoint.is_due(now));
+
+        for _ in 1..FAILURES_TO_PAUSE {
+            endpoint.pending.as_mut().unwrap().failures = 1;
+            endpoint.start("fourth", &policy, now);
+        }
+        assert!(endpoint.is_paused(now));
+        assert!(endpoint.pending.is_none());
+    }
+
+    #[test]
+    fn repeatedly_failing_url_is_paused() {
+        let mut endpoint = Endpoint::new(Url::parse("http://127.0.0.1:1/").unwrap(), None);
+        let now = Instant::now();
+        for _ in 0..FAILURES_TO_PAUSE - 1 {
+            endpoint.on_failed(now);
+        }
+        assert!(!endpoint.is_paused(now));
+        endpoint.on_failed(now);
+        assert!(endpoint.is_paused(now));
+        assert!(!endpoint.is_paused(now + PAUSE_DURATION));
+
+        // A failure after the pause pauses it again, and a delivery resets it.
+        endpoint.on_failed(now + PAUSE_DURATION);
+        assert!(endpoint.is_paused(now + PAUSE_DURATION));
+        endpoint.on_delivered();
+        assert!(!endpoint.is_paused(now + PAUSE_DURATION));
+        assert_eq!(0, endpoint.consecutive_failures);
+    }
+}
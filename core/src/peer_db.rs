@@ -17,23 +17,48 @@ use crate::db::COL_EXTRA;
 use cnetwork::{ManagingPeerdb, SocketAddr};
 use kvdb::{DBTransaction, KeyValueDB};
 use parking_lot::Mutex;
-use rlp::RlpStream;
-use std::collections::HashMap;
+use primitives::Bytes;
+use rlp::{Rlp, RlpStream};
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub struct PeerDb {
     db: Arc<dyn KeyValueDB>,
     peers_and_count: Mutex<(HashMap<SocketAddr, u64>, usize)>,
+    trusted_peers: Mutex<BTreeSet<SocketAddr>>,
 }
 
+const TRUSTED_PEER_DB_KEY: &[u8] = b"trusted-peer-list";
+const ROUTING_STATE_DB_KEY: &[u8] = b"routing-state";
+
 impl PeerDb {
     pub fn new(database: Arc<dyn KeyValueDB>) -> Box<Self> {
+        let trusted_peers = database
+            .get(COL_EXTRA, TRUSTED_PEER_DB_KEY)
+            .expect("The DB must alive")
+            .map(|encoded| {
+                let trusted_peers: Vec<SocketAddr> =
+                    Rlp::new(&encoded).as_list().expect("The trusted peers must be encoded correctly");
+                trusted_peers.into_iter().collect()
+            })
+            .unwrap_or_default();
         Box::new(Self {
             db: database,
             peers_and_count: Default::default(),
+            trusted_peers: Mutex::new(trusted_peers),
         })
     }
+
+    fn write_trusted_peers(&self, trusted_peers: &BTreeSet<SocketAddr>) {
+        let mut s = RlpStream::new_list(trusted_peers.len());
+        for address in trusted_peers {
+            s.append(address);
+        }
+        let mut batch = DBTransaction::new();
+        batch.put(COL_EXTRA, TRUSTED_PEER_DB_KEY, &s.drain());
+        self.db.write(batch).expect("The DB must alive");
+    }
 }
 
 impl ManagingPeerdb for PeerDb {
@@ -59,6 +84,36 @@ impl ManagingPeerdb for PeerDb {
             self.db.write(batch).expect("The DB must alive");
         }
     }
+
+    // The trusted peers are written immediately because they are rarely changed.
+    fn insert_trusted(&self, key: SocketAddr) {
+        let mut trusted_peers = self.trusted_peers.lock();
+        if trusted_peers.insert(key) {
+            self.write_trusted_peers(&trusted_peers);
+        }
+    }
+
+    fn delete_trusted(&self, key: &SocketAddr) {
+        let mut trusted_peers = self.trusted_peers.lock();
+        if trusted_peers.remove(key) {
+            self.write_trusted_peers(&trusted_peers);
+        }
+    }
+
+    fn trusted_peers(&self) -> Vec<SocketAddr> {
+        self.trusted_peers.lock().iter().cloned().collect()
+    }
+
+    fn routing_state(&self) -> Option<Bytes> {
+        self.db.get(COL_EXTRA, ROUTING_STATE_DB_KEY).expect("The DB must alive").map(|state| state.to_vec())
+    }
+
+    // The state is written immediately because a consumed resumption ticket must not be restored.
+    fn save_routing_state(&self, state: Bytes) {
+        let mut batch = DBTransaction::new();
+        batch.put(COL_EXTRA, ROUTING_STATE_DB_KEY, &state);
+        self.db.write(batch).expect("The DB must alive");
+    }
 }
 
 // XXX: It may not be needed. Generally, in the p2p networks, the old node lives longer.
@@ -97,3 +152,43 @@ fn get_db_transaction(peers: &HashMap<SocketAddr, u64>) -> DBTransaction {
     batch.put(COLUMN_TO_WRITE, PEER_DB_KEY, &encoded);
     batch
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::NUM_COLUMNS;
+    use std::str::FromStr;
+
+    #[test]
+    fn trusted_peers_survive_restarts() {
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let first = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+        let second = SocketAddr::from_str("127.0.0.2:3485").unwrap();
+        let third = SocketAddr::from_str("127.0.0.3:3485").unwrap();
+        {
+            let peer_db = PeerDb::new(Arc::clone(&db));
+            assert_eq!(Vec::<SocketAddr>::new(), peer_db.trusted_peers());
+            peer_db.insert_trusted(first);
+            peer_db.insert_trusted(second);
+            peer_db.insert_trusted(third);
+            peer_db.delete_trusted(&second);
+        }
+
+        let peer_db = PeerDb::new(db);
+        assert_eq!(vec![first, third], peer_db.trusted_peers());
+    }
+
+    #[test]
+    fn routing_state_survives_restarts() {
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        {
+            let peer_db = PeerDb::new(Arc::clone(&db));
+            assert_eq!(None, peer_db.routing_state());
+            peer_db.save_routing_state(vec![1, 2, 3]);
+            peer_db.save_routing_state(vec![4, 5]);
+        }
+
+        let peer_db = PeerDb::new(db);
+        assert_eq!(Some(vec![4, 5]), peer_db.routing_state());
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use kvdb::{DBTransaction, DBValue, KeyValueDB};
+use kvdb_rocksdb::{Database, DatabaseConfig};
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A database that can be read but not written.
+///
+/// `write` and `restore` fail, and the buffered writes are dropped.
+/// It's used to serve a copy of another node's database without changing it.
+pub struct ReadOnlyDatabase {
+    db: Arc<dyn KeyValueDB>,
+    /// Whether the dropped writes are reported already.
+    dropped_writes_reported: AtomicBool,
+}
+
+impl ReadOnlyDatabase {
+    pub fn new(db: Arc<dyn KeyValueDB>) -> Self {
+        Self {
+            db,
+            dropped_writes_reported: AtomicBool::new(false),
+        }
+    }
+
+    /// Opens the RocksDB database at `path`, which must exist already.
+    /// kvdb-rocksdb has no read-only open, so the database is opened for writing and only the writes
+    /// above it are dropped. It takes the lock of the database until kvdb-rocksdb adds one.
+    pub fn open(path: &Path, db_config: &DatabaseConfig) -> io::Result<Self> {
+        if !path.exists() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{:?} doesn't exist", path)))
+        }
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid database path {:?}", path)))?;
+        Ok(Self::new(Arc::new(Database::open(db_config, path_str)?)))
+    }
+}
+
+fn read_only_error() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, "The database is opened in read-only mode")
+}
+
+impl KeyValueDB for ReadOnlyDatabase {
+    fn get(&self, col: Option<u32>, key: &[u8]) -> io::Result<Option<DBValue>> {
+        self.db.get(col, key)
+    }
+
+    fn get_by_prefix(&self, col: Option<u32>, prefix: &[u8]) -> Option<Box<[u8]>> {
+        self.db.get_by_prefix(col, prefix)
+    }
+
+    fn write_buffered(&self, transaction: DBTransaction) {
+        if !self.dropped_writes_reported.swap(true, Ordering::Relaxed) {
+            cwarn!(
+                CLIENT,
+                "{} changes to the read-only database are dropped. The later changes are dropped silently",
+                transaction.ops.len()
+            );
+        }
+    }
+
+    fn write(&self, _transaction: DBTransaction) -> io::Result<()> {
+        Err(read_only_error())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn iter<'a>(&'a self, col: Option<u32>) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.db.iter(col)
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: Option<u32>,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.db.iter_from_prefix(col, prefix)
+    }
+
+    fn restore(&self, _new_db: &str) -> io::Result<()> {
+        Err(read_only_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::NUM_COLUMNS;
+    use tempfile::tempdir;
+
+    #[test]
+    fn writes_are_rejected() {
+        let memory = Arc::new(kvdb_memorydb::create(1));
+        let mut batch = DBTransaction::new();
+        batch.put(Some(0), b"key", b"value");
+        memory.write(batch).unwrap();
+
+        let db = ReadOnlyDatabase::new(Arc::clone(&memory) as Arc<dyn KeyValueDB>);
+        assert_eq!(Some(&b"value"[..]), db.get(Some(0), b"key").unwrap().as_deref());
+
+        let mut batch = DBTransaction::new();
+        batch.put(Some(0), b"key", b"changed");
+        batch.delete(Some(0), b"other");
+        assert_eq!(io::ErrorKind::PermissionDenied, db.write(batch.clone()).unwrap_err().kind());
+        db.write_buffered(batch.clone());
+        assert!(db.dropped_writes_reported.load(Ordering::Relaxed));
+        db.write_buffered(batch);
+        db.flush().unwrap();
+        assert_eq!(Some(&b"value"[..]), memory.get(Some(0), b"key").unwrap().as_deref());
+    }
+
+    #[test]
+    fn rocksdb_is_served_without_the_writes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        {
+            let writable = Database::open(&DatabaseConfig::with_columns(NUM_COLUMNS), path).unwrap();
+            let mut batch = DBTransaction::new();
+            batch.put(None, b"version", b"1");
+            batch.put(Some(0), b"key1", b"value1");
+            batch.put(Some(0), b"key2", b"value2");
+            writable.write(batch).unwrap();
+        }
+
+        let db = ReadOnlyDatabase::open(dir.path(), &DatabaseConfig::with_columns(NUM_COLUMNS)).unwrap();
+        assert_eq!(Some(&b"1"[..]), db.get(None, b"version").unwrap().as_deref());
+        assert_eq!(Some(&b"value2"[..]), db.get(Some(0), b"key2").unwrap().as_deref());
+        assert_eq!(None, db.get(Some(1), b"key1").unwrap());
+        assert_eq!(Some(b"value1".to_vec().into_boxed_slice()), db.get_by_prefix(Some(0), b"key"));
+        let keys: Vec<_> = db.iter(Some(0)).map(|(key, _)| key.into_vec()).collect();
+        assert_eq!(vec![b"key1".to_vec(), b"key2".to_vec()], keys);
+
+        let mut batch = DBTransaction::new();
+        batch.put(Some(0), b"key3", b"value3");
+        assert_eq!(io::ErrorKind::PermissionDenied, db.write(batch).unwrap_err().kind());
+        assert_eq!(None, db.get(Some(0), b"key3").unwrap());
+    }
+
+    #[test]
+    fn missing_rocksdb_is_not_created() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("db");
+        assert!(ReadOnlyDatabase::open(&path, &DatabaseConfig::with_columns(NUM_COLUMNS)).is_err());
+        assert!(!path.exists());
+    }
+}
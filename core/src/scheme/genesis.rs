@@ -17,9 +17,11 @@
 use super::seal::Seal;
 use ccrypto::BLAKE_NULL_RLP;
 use cjson;
+use cjson::uint::OverflowError;
 use ckey::{Address, PlatformAddress};
 use ctypes::BlockHash;
 use primitives::{Bytes, H256, U256};
+use std::convert::TryFrom;
 
 /// Genesis components.
 pub struct Genesis {
@@ -41,17 +43,19 @@ pub struct Genesis {
     pub extra_data: Bytes,
 }
 
-impl From<cjson::scheme::Genesis> for Genesis {
-    fn from(g: cjson::scheme::Genesis) -> Self {
-        Genesis {
-            seal: From::from(g.seal),
+impl TryFrom<cjson::scheme::Genesis> for Genesis {
+    type Error = OverflowError;
+
+    fn try_from(g: cjson::scheme::Genesis) -> Result<Self, Self::Error> {
+        Ok(Genesis {
+            seal: Seal::try_from(g.seal)?,
             score: g.score.into(),
             author: g.author.map_or_else(Address::default, PlatformAddress::into_address),
-            timestamp: g.timestamp.map_or(0, Into::into),
+            timestamp: g.timestamp.map_or(Ok(0), u64::try_from)?,
             parent_hash: g.parent_hash.map_or_else(H256::zero, Into::into).into(),
             transactions_root: g.transactions_root.map_or_else(|| BLAKE_NULL_RLP, Into::into),
             state_root: g.state_root.map(Into::into),
             extra_data: g.extra_data.map_or_else(Vec::new, Into::into),
-        }
+        })
     }
 }
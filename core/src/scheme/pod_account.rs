@@ -15,9 +15,11 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use cjson;
+use cjson::uint::OverflowError;
 use ckey::Public;
 use cstate::Account;
 use rlp::{Encodable, RlpStream};
+use std::convert::TryFrom;
 use std::fmt;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -45,13 +47,15 @@ impl Encodable for PodAccount {
     }
 }
 
-impl From<cjson::scheme::Account> for PodAccount {
-    fn from(a: cjson::scheme::Account) -> Self {
-        PodAccount {
-            balance: a.balance.map_or(0, Into::into),
-            seq: a.seq.map_or(0, Into::into),
+impl TryFrom<cjson::scheme::Account> for PodAccount {
+    type Error = OverflowError;
+
+    fn try_from(a: cjson::scheme::Account) -> Result<Self, Self::Error> {
+        Ok(PodAccount {
+            balance: a.balance.map_or(Ok(0), u64::try_from)?,
+            seq: a.seq.map_or(Ok(0), u64::try_from)?,
             regular_key: None,
-        }
+        })
     }
 }
 
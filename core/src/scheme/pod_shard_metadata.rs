@@ -15,7 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use cjson;
+use cjson::uint::OverflowError;
 use ckey::{Address, PlatformAddress};
+use std::convert::TryFrom;
 use std::fmt;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -25,13 +27,15 @@ pub struct PodShardMetadata {
     pub seq: u64,
 }
 
-impl From<cjson::scheme::Shard> for PodShardMetadata {
-    fn from(s: cjson::scheme::Shard) -> Self {
-        Self {
-            seq: s.seq.map(Into::into).unwrap_or(0),
+impl TryFrom<cjson::scheme::Shard> for PodShardMetadata {
+    type Error = OverflowError;
+
+    fn try_from(s: cjson::scheme::Shard) -> Result<Self, Self::Error> {
+        Ok(Self {
+            seq: s.seq.map_or(Ok(0), u64::try_from)?,
             owners: s.owners.into_iter().map(PlatformAddress::into_address).collect(),
             users: s.users.unwrap_or_else(Vec::new).into_iter().map(PlatformAddress::into_address).collect(),
-        }
+        })
     }
 }
 
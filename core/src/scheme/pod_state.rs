@@ -17,9 +17,11 @@
 use super::pod_account::PodAccount;
 use super::pod_shard_metadata::PodShardMetadata;
 use cjson;
+use cjson::uint::OverflowError;
 use ckey::Address;
 use ctypes::ShardId;
 use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::fmt;
 use std::ops::Deref;
 
@@ -35,14 +37,16 @@ impl Deref for PodAccounts {
     }
 }
 
-impl From<cjson::scheme::Accounts> for PodAccounts {
-    fn from(s: cjson::scheme::Accounts) -> PodAccounts {
+impl TryFrom<cjson::scheme::Accounts> for PodAccounts {
+    type Error = OverflowError;
+
+    fn try_from(s: cjson::scheme::Accounts) -> Result<Self, Self::Error> {
         let accounts = s
             .into_iter()
             .filter(|(_, acc)| !acc.is_empty())
-            .map(|(addr, acc)| (addr.into_address(), acc.into()))
-            .collect();
-        PodAccounts(accounts)
+            .map(|(addr, acc)| Ok((addr.into_address(), PodAccount::try_from(acc)?)))
+            .collect::<Result<_, OverflowError>>()?;
+        Ok(PodAccounts(accounts))
     }
 }
 
@@ -68,10 +72,15 @@ impl Deref for PodShards {
     }
 }
 
-impl From<cjson::scheme::Shards> for PodShards {
-    fn from(s: cjson::scheme::Shards) -> PodShards {
-        let shards = s.into_iter().map(|(shard_id, shard)| (shard_id, shard.into())).collect();
-        PodShards(shards)
+impl TryFrom<cjson::scheme::Shards> for PodShards {
+    type Error = OverflowError;
+
+    fn try_from(s: cjson::scheme::Shards) -> Result<Self, Self::Error> {
+        let shards = s
+            .into_iter()
+            .map(|(shard_id, shard)| Ok((shard_id, PodShardMetadata::try_from(shard)?)))
+            .collect::<Result<_, OverflowError>>()?;
+        Ok(PodShards(shards))
     }
 }
 
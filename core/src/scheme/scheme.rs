@@ -24,13 +24,18 @@ use ccrypto::BLAKE_NULL_RLP;
 use cdb::{AsHashDB, HashDB};
 use cjson;
 use ckey::Address;
-use cstate::{Metadata, MetadataAddress, Shard, ShardAddress, StateDB, StateResult, StateWithCache, TopLevelState};
+use cstate::{
+    ApproverGroup, ApproverGroupAddress, Metadata, MetadataAddress, Shard, ShardAddress, StateDB, StateResult,
+    StateWithCache, TopLevelState,
+};
 use ctypes::errors::SyntaxError;
+use ctypes::transaction::Action;
 use ctypes::{BlockHash, CommonParams, Header, ShardId};
 use merkle_trie::{TrieFactory, TrieMut};
 use parking_lot::RwLock;
 use primitives::{Bytes, H256, U256};
 use rlp::{Encodable, Rlp, RlpStream};
+use std::convert::{TryFrom, TryInto};
 use std::io::Read;
 use std::sync::Arc;
 
@@ -68,6 +73,7 @@ pub struct Scheme {
     /// Genesis state as plain old data.
     genesis_accounts: PodAccounts,
     genesis_shards: PodShards,
+    genesis_approver_groups: Vec<ApproverGroup>,
 }
 
 // helper for formatting errors.
@@ -93,23 +99,28 @@ impl Scheme {
 
     /// Convert engine scheme into a arc'd Engine of the right underlying type.
     /// TODO avoid this hard-coded nastiness - use dynamic-linked plugin framework instead.
-    fn engine(engine_scheme: cjson::scheme::Engine, params: CommonParams) -> Arc<dyn CodeChainEngine> {
+    fn engine(engine_scheme: cjson::scheme::Engine, params: CommonParams) -> Result<Arc<dyn CodeChainEngine>, Error> {
         let machine = Self::machine(&engine_scheme, params);
 
-        match engine_scheme {
-            cjson::scheme::Engine::Null(null) => Arc::new(NullEngine::new(null.params.into(), machine)),
-            cjson::scheme::Engine::Solo(solo) => Arc::new(Solo::new(solo.params.into(), machine)),
-            cjson::scheme::Engine::SimplePoA(simple_poa) => Arc::new(SimplePoA::new(simple_poa.params.into(), machine)),
-            cjson::scheme::Engine::Tendermint(tendermint) => Tendermint::new(tendermint.params.into(), machine),
-            cjson::scheme::Engine::Cuckoo(cuckoo) => Arc::new(Cuckoo::new(cuckoo.params.into(), machine)),
-            cjson::scheme::Engine::BlakePoW(blake_pow) => Arc::new(BlakePoW::new(blake_pow.params.into(), machine)),
-        }
+        Ok(match engine_scheme {
+            cjson::scheme::Engine::Null(null) => Arc::new(NullEngine::new(null.params.try_into()?, machine)),
+            cjson::scheme::Engine::Solo(solo) => Arc::new(Solo::new(solo.params.try_into()?, machine)),
+            cjson::scheme::Engine::SimplePoA(simple_poa) => {
+                Arc::new(SimplePoA::new(simple_poa.params.try_into()?, machine))
+            }
+            cjson::scheme::Engine::Tendermint(tendermint) => Tendermint::new(tendermint.params.try_into()?, machine),
+            cjson::scheme::Engine::Cuckoo(cuckoo) => Arc::new(Cuckoo::new(cuckoo.params.try_into()?, machine)),
+            cjson::scheme::Engine::BlakePoW(blake_pow) => {
+                Arc::new(BlakePoW::new(blake_pow.params.try_into()?, machine))
+            }
+        })
     }
 
     fn initialize_state(&self, db: StateDB) -> Result<StateDB, Error> {
         let root = BLAKE_NULL_RLP;
         let (db, root) = self.initialize_accounts(db, root)?;
         let (db, root) = self.initialize_shards(db, root)?;
+        let (db, root) = self.initialize_approver_groups(db, root)?;
         let (db, root) = self.initialize_action_handlers(db, root)?;
 
         *self.state_root_memo.write() = root;
@@ -169,6 +180,21 @@ impl Scheme {
         Ok((db, root))
     }
 
+    fn initialize_approver_groups<DB: AsHashDB>(&self, mut db: DB, mut root: H256) -> StateResult<(DB, H256)> {
+        {
+            let mut t = TrieFactory::from_existing(db.as_hashdb_mut(), &mut root)?;
+
+            for group in &self.genesis_approver_groups {
+                let address = ApproverGroupAddress::new(&group.address());
+                let r = t.insert(&*address, &group.rlp_bytes());
+                debug_assert_eq!(Ok(None), r);
+                r?;
+            }
+        }
+
+        Ok((db, root))
+    }
+
     fn initialize_action_handlers(&self, db: StateDB, root: H256) -> StateResult<(StateDB, H256)> {
         // basic accounts in scheme.
         let mut top_level = TopLevelState::from_existing(db, root)?;
@@ -228,6 +254,13 @@ impl Scheme {
         load_bundled!("simple_poa")
     }
 
+    /// Create a new Scheme with SimplePoA consensus whose validator is the key used in the tests.
+    /// It must not be used outside the tests because the private key of the validator is public.
+    #[cfg(test)]
+    pub fn new_test_simple_poa_with_test_validator() -> Self {
+        load_bundled!("simple_poa_test_validator")
+    }
+
     /// Create a new Scheme with Tendermint consensus which does internal sealing (not requiring
     /// work).
     pub fn new_test_tendermint() -> Self {
@@ -306,11 +339,28 @@ impl Scheme {
 
 /// Load from JSON object.
 fn load_from(s: cjson::scheme::Scheme) -> Result<Scheme, Error> {
-    let g = Genesis::from(s.genesis);
+    let g = Genesis::try_from(s.genesis)?;
     let GenericSeal(seal_rlp) = g.seal.into();
-    let params = CommonParams::from(s.params);
+    let params = CommonParams::try_from(s.params)?;
     params.verify().map_err(|reason| Error::Syntax(SyntaxError::InvalidCustomAction(reason)))?;
-    let engine = Scheme::engine(s.engine, params);
+    let genesis_approver_groups = s
+        .approver_groups
+        .unwrap_or_default()
+        .into_iter()
+        .map(|group| {
+            let members: Vec<Address> = group.members.into_iter().map(|member| member.into_address()).collect();
+            let threshold = u8::try_from(group.threshold)?;
+            // The genesis groups obey the same rules as the groups created by transactions.
+            let action = Action::CreateApproverGroup {
+                members: members.clone(),
+                threshold,
+            };
+            action.verify()?;
+            action.verify_with_params(&params)?;
+            Ok(ApproverGroup::new(members, threshold))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let engine = Scheme::engine(s.engine, params)?;
 
     let mut s = Scheme {
         name: s.name.clone(),
@@ -325,8 +375,9 @@ fn load_from(s: cjson::scheme::Scheme) -> Result<Scheme, Error> {
         extra_data: g.extra_data,
         seal_rlp,
         state_root_memo: RwLock::new(Default::default()), // will be overwritten right after.
-        genesis_accounts: s.accounts.into(),
-        genesis_shards: s.shards.into(),
+        genesis_accounts: PodAccounts::try_from(s.accounts)?,
+        genesis_shards: PodShards::try_from(s.shards)?,
+        genesis_approver_groups,
     };
 
     // use memoized state root if provided.
@@ -340,3 +391,17 @@ fn load_from(s: cjson::scheme::Scheme) -> Result<Scheme, Error> {
 
     Ok(s)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn param_overflowing_its_type_is_rejected() {
+        let solo = include_str!("../../res/solo.json");
+        let scheme = solo.replace("\"maxBodySize\": 4194304", "\"maxBodySize\": \"0x10000000000000000\"");
+        assert_ne!(solo, scheme);
+        let err = Scheme::load(scheme.as_bytes()).err().expect("The scheme must be rejected");
+        assert!(err.contains("Integer overflow when casting 18446744073709551616 to usize"), "{}", err);
+    }
+}
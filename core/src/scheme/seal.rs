@@ -15,8 +15,10 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use cjson;
+use cjson::uint::OverflowError;
 use primitives::H520;
 use rlp::RlpStream;
+use std::convert::{TryFrom, TryInto};
 
 /// Tendermint seal.
 pub struct Tendermint {
@@ -46,16 +48,18 @@ pub enum Seal {
     Generic(Generic),
 }
 
-impl From<cjson::scheme::Seal> for Seal {
-    fn from(s: cjson::scheme::Seal) -> Self {
-        match s {
+impl TryFrom<cjson::scheme::Seal> for Seal {
+    type Error = OverflowError;
+
+    fn try_from(s: cjson::scheme::Seal) -> Result<Self, Self::Error> {
+        Ok(match s {
             cjson::scheme::Seal::Tendermint(tender) => Seal::Tendermint(Tendermint {
-                prev_view: tender.prev_view.into(),
-                cur_view: tender.cur_view.into(),
+                prev_view: tender.prev_view.try_into()?,
+                cur_view: tender.cur_view.try_into()?,
                 precommits: tender.precommits.into_iter().map(Into::into).collect(),
             }),
             cjson::scheme::Seal::Generic(g) => Seal::Generic(Generic(g.into())),
-        }
+        })
     }
 }
 
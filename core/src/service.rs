@@ -24,12 +24,14 @@ use ctimer::TimerApi;
 use ctypes::BlockHash;
 use kvdb::KeyValueDB;
 use primitives::Bytes;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 /// Client service setup.
 pub struct ClientService {
-    _io_service: IoService<ClientIoMessage>,
+    io_service: IoService<ClientIoMessage>,
     client: Arc<Client>,
+    stopped: AtomicBool,
 }
 
 impl ClientService {
@@ -52,14 +54,38 @@ impl ClientService {
         scheme.engine.register_client(Arc::downgrade(&client) as _);
 
         Ok(ClientService {
-            _io_service: io_service,
+            io_service,
             client,
+            stopped: AtomicBool::new(false),
         })
     }
 
     pub fn client(&self) -> Arc<Client> {
         Arc::clone(&self.client)
     }
+
+    /// Stops the client in order: new work is refused, the buffered writes are flushed,
+    /// the verifier threads are joined and then the IO service is stopped.
+    /// The client can still be read after it. Calling it again does nothing.
+    pub fn stop(&self) {
+        if self.stopped.swap(true, Ordering::SeqCst) {
+            return
+        }
+        cinfo!(SHUTDOWN, "Stopping the client");
+        self.client.stop_accepting_work();
+        if let Err(err) = self.client.db().flush() {
+            cerror!(SHUTDOWN, "Cannot flush the database: {}", err);
+        }
+        self.client.stop_verification();
+        self.io_service.stop();
+        cinfo!(SHUTDOWN, "The client is stopped");
+    }
+}
+
+impl Drop for ClientService {
+    fn drop(&mut self) {
+        self.stop();
+    }
 }
 
 /// Message type for external and internal events
@@ -111,3 +137,68 @@ impl IoHandler<ClientIoMessage> for ClientIoHandler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{BlockChainClient, EngineClient};
+    use crate::db::NUM_COLUMNS;
+    use crate::lock_order::{self, LockClass};
+    use ctimer::TimerLoop;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn start_and_stop_repeatedly() {
+        let scheme = Scheme::new_test();
+        let timer_loop = TimerLoop::new(1);
+        for _ in 0..5 {
+            let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+            let miner = Arc::new(Miner::with_scheme_for_test(&scheme, Arc::clone(&db)));
+            let reseal_timer = timer_loop.new_timer_with_name("Client reseal timer");
+            let service =
+                ClientService::start(&ClientConfig::default(), &scheme, db, miner, reseal_timer.clone()).unwrap();
+            let client = service.client();
+            reseal_timer.set_handler(Arc::downgrade(&client));
+
+            service.stop();
+            // Others holding the client must not panic after the service is stopped.
+            client.update_sealing(BlockId::Latest, false);
+            client.queue_transactions(vec![]);
+            service.stop();
+            drop(service);
+
+            // No thread of the service is left holding the client.
+            let weak = Arc::downgrade(&client);
+            drop(client);
+            assert!(weak.upgrade().is_none());
+        }
+    }
+
+    /// The Tendermint worker requests a block while it holds the consensus step.
+    /// The real client must queue the request instead of entering the miner, which calls back into the engine.
+    #[test]
+    fn sealing_requested_under_the_consensus_step_is_made_by_the_io_handler() {
+        let scheme = Scheme::new_test_solo();
+        let timer_loop = TimerLoop::new(1);
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let miner = Arc::new(Miner::with_scheme_for_test(&scheme, Arc::clone(&db)));
+        let reseal_timer = timer_loop.new_timer_with_name("Client reseal timer");
+        let service = ClientService::start(&ClientConfig::default(), &scheme, db, miner, reseal_timer.clone()).unwrap();
+        let client = service.client();
+        reseal_timer.set_handler(Arc::downgrade(&client));
+
+        {
+            // The lock order check panics in debug builds if the miner runs on this thread.
+            let _lock_order = lock_order::acquire(LockClass::ConsensusStep);
+            EngineClient::update_sealing_async(&*client, BlockId::Latest, true);
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while client.chain_info().best_block_number == 0 {
+            assert!(Instant::now() < deadline, "The IO handler must make the requested block");
+            thread::sleep(Duration::from_millis(10));
+        }
+        service.stop();
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use clogger::metric::{Metric, METRICS};
+use kvdb::{DBTransaction, DBValue, KeyValueDB};
+use std::io;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A database that exports the number and the total duration of its point reads.
+///
+/// The iterators aren't timed, since they are consumed lazily by the callers.
+pub struct TimedDatabase {
+    db: Arc<dyn KeyValueDB>,
+    reads: Arc<Metric>,
+    read_micros: Arc<Metric>,
+}
+
+impl TimedDatabase {
+    pub fn new(db: Arc<dyn KeyValueDB>) -> Self {
+        Self {
+            db,
+            reads: METRICS.counter("codechain_db_reads_total", "The number of database reads"),
+            read_micros: METRICS
+                .counter("codechain_db_read_duration_microseconds_total", "The time spent in database reads"),
+        }
+    }
+
+    fn timed<T>(&self, read: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = read();
+        self.reads.inc_by(1);
+        self.read_micros.inc_by(started.elapsed().as_micros() as u64);
+        result
+    }
+}
+
+impl KeyValueDB for TimedDatabase {
+    fn get(&self, col: Option<u32>, key: &[u8]) -> io::Result<Option<DBValue>> {
+        self.timed(|| self.db.get(col, key))
+    }
+
+    fn get_by_prefix(&self, col: Option<u32>, prefix: &[u8]) -> Option<Box<[u8]>> {
+        self.timed(|| self.db.get_by_prefix(col, prefix))
+    }
+
+    fn write_buffered(&self, transaction: DBTransaction) {
+        self.db.write_buffered(transaction)
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        self.db.write(transaction)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.db.flush()
+    }
+
+    fn iter<'a>(&'a self, col: Option<u32>) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.db.iter(col)
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: Option<u32>,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.db.iter_from_prefix(col, prefix)
+    }
+
+    fn restore(&self, new_db: &str) -> io::Result<()> {
+        self.db.restore(new_db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_are_counted() {
+        let memory = Arc::new(kvdb_memorydb::create(1));
+        let mut batch = DBTransaction::new();
+        batch.put(Some(0), b"key", b"value");
+        memory.write(batch).unwrap();
+
+        let db = TimedDatabase::new(memory);
+        // The registry is global, so the other tests may read concurrently.
+        let before = db.reads.get();
+        assert_eq!(Some(&b"value"[..]), db.get(Some(0), b"key").unwrap().as_deref());
+        assert_eq!(None, db.get(Some(0), b"other").unwrap());
+        assert!(db.get_by_prefix(Some(0), b"ke").is_some());
+        assert!(db.reads.get() - before >= 3);
+        assert!(METRICS.render().contains("# TYPE codechain_db_read_duration_microseconds_total counter\n"));
+    }
+}
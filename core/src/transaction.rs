@@ -17,14 +17,21 @@
 use crate::error::Error;
 use ccrypto::blake256;
 use ckey::{self, public_to_address, recover, sign, Private, Public, Signature};
+use crossbeam_utils::thread;
 use ctypes::errors::SyntaxError;
 use ctypes::transaction::Transaction;
 use ctypes::{BlockHash, BlockNumber, CommonParams, TxHash};
 use rlp::{self, DecoderError, Encodable, Rlp, RlpStream};
+use std::cmp;
 use std::ops::Deref;
 
+/// The signatures are recovered in parallel only if each thread gets at least this many transactions,
+/// since spawning a thread costs about as much as recovering a few signatures.
+const MIN_TRANSACTIONS_PER_RECOVERY_THREAD: usize = 64;
+const MAX_RECOVERY_THREADS: usize = 4;
+
 /// Signed transaction information without verified signature.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct UnverifiedTransaction {
     /// Plain Transaction.
     unsigned: Transaction,
@@ -32,8 +39,18 @@ pub struct UnverifiedTransaction {
     sig: Signature,
     /// Hash of the transaction
     hash: TxHash,
+    /// The public key recovered from the signature, if it has been recovered.
+    signer_public: Option<Public>,
 }
 
+impl PartialEq for UnverifiedTransaction {
+    fn eq(&self, other: &Self) -> bool {
+        self.unsigned == other.unsigned && self.sig == other.sig && self.hash == other.hash
+    }
+}
+
+impl Eq for UnverifiedTransaction {}
+
 impl Deref for UnverifiedTransaction {
     type Target = Transaction;
 
@@ -67,6 +84,7 @@ impl rlp::Decodable for UnverifiedTransaction {
             },
             sig: d.val_at(4)?,
             hash,
+            signer_public: None,
         })
     }
 }
@@ -83,6 +101,7 @@ impl UnverifiedTransaction {
             unsigned,
             sig,
             hash: Default::default(),
+            signer_public: None,
         }
         .compute_hash()
     }
@@ -115,10 +134,21 @@ impl UnverifiedTransaction {
     }
 
     /// Recovers the public key of the signature.
+    /// It doesn't recover again if the public key has been cached.
     pub fn recover_public(&self) -> Result<Public, ckey::Error> {
+        if let Some(public) = self.signer_public {
+            return Ok(public)
+        }
         Ok(recover(&self.signature(), &self.unsigned.hash())?)
     }
 
+    /// Recovers the public key of the signature and caches it.
+    pub fn cache_signer_public(&mut self) -> Result<Public, ckey::Error> {
+        let public = self.recover_public()?;
+        self.signer_public = Some(public);
+        Ok(public)
+    }
+
     /// Checks whether the signature has a low 's' value.
     pub fn check_low_s(&self) -> Result<(), ckey::Error> {
         if !self.signature().is_low_s() {
@@ -187,12 +217,15 @@ impl Deref for SignedTransaction {
 
 impl From<SignedTransaction> for UnverifiedTransaction {
     fn from(tx: SignedTransaction) -> Self {
-        tx.tx
+        let (mut tx, signer_public) = tx.deconstruct();
+        tx.signer_public = Some(signer_public);
+        tx
     }
 }
 
 impl SignedTransaction {
     /// Try to verify transaction and recover public.
+    /// The cached public of `tx` is used if it has been recovered.
     pub fn try_new(tx: UnverifiedTransaction) -> Result<Self, Error> {
         let signer_public = tx.recover_public()?;
         let signer = public_to_address(&signer_public);
@@ -220,6 +253,31 @@ impl SignedTransaction {
     }
 }
 
+/// Recovers and caches the signer publics of the transactions, using a few threads if there are many of them.
+/// A transaction whose public can't be recovered is left as it is,
+/// so verifying the transactions in order fails at the same transaction with the same error as without this.
+pub fn recover_signer_publics(transactions: &mut [UnverifiedTransaction]) {
+    let threads = cmp::min(MAX_RECOVERY_THREADS, transactions.len() / MIN_TRANSACTIONS_PER_RECOVERY_THREAD);
+    if threads <= 1 {
+        for tx in transactions {
+            let _ = tx.cache_signer_public();
+        }
+        return
+    }
+
+    let chunk_size = (transactions.len() + threads - 1) / threads;
+    thread::scope(|scope| {
+        for chunk in transactions.chunks_mut(chunk_size) {
+            scope.spawn(move |_| {
+                for tx in chunk {
+                    let _ = tx.cache_signer_public();
+                }
+            });
+        }
+    })
+    .expect("Recovering signatures doesn't panic");
+}
+
 /// Signed Transaction that is a part of canon blockchain.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LocalizedTransaction {
@@ -266,7 +324,7 @@ impl From<LocalizedTransaction> for Transaction {
 
 #[cfg(test)]
 mod tests {
-    use ckey::{Address, Public, Signature};
+    use ckey::{Address, Generator, KeyPair, Public, Random, Signature};
     use ctypes::transaction::Action;
     use primitives::H256;
     use rlp::rlp_encode_and_decode_test;
@@ -286,6 +344,7 @@ mod tests {
             },
             sig: Signature::default(),
             hash: H256::default().into(),
+            signer_public: None,
         }
         .compute_hash());
     }
@@ -300,10 +359,12 @@ mod tests {
                 action: Action::Pay {
                     receiver: Address::random(),
                     quantity: 300,
+                    memo: None,
                 },
             },
             sig: Signature::default(),
             hash: H256::default().into(),
+            signer_public: None,
         }
         .compute_hash());
     }
@@ -321,6 +382,7 @@ mod tests {
             },
             sig: Signature::default(),
             hash: H256::default().into(),
+            signer_public: None,
         }
         .compute_hash());
     }
@@ -338,7 +400,69 @@ mod tests {
             },
             sig: Signature::default(),
             hash: H256::default().into(),
+            signer_public: None,
         }
         .compute_hash());
     }
+
+    fn signed_transactions(count: usize) -> Vec<UnverifiedTransaction> {
+        let keypair: KeyPair = Random.generate().unwrap();
+        (0..count)
+            .map(|seq| {
+                let tx = Transaction {
+                    seq: seq as u64,
+                    fee: 10,
+                    network_id: "tc".into(),
+                    action: Action::Pay {
+                        receiver: Address::random(),
+                        quantity: 100,
+                        memo: None,
+                    },
+                };
+                SignedTransaction::new_with_sign(tx, keypair.private()).deconstruct().0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cached_public_is_used_without_recovering_again() {
+        let mut tx = signed_transactions(1).pop().unwrap();
+        let public = tx.cache_signer_public().unwrap();
+        assert_eq!(Some(public), tx.signer_public);
+
+        // A wrong public is set to see that the cache is used instead of the signature.
+        let other = Public::random();
+        tx.signer_public = Some(other);
+        assert_eq!(Ok(other), tx.recover_public());
+        assert_eq!(other, SignedTransaction::try_new(tx.clone()).unwrap().signer_public());
+
+        let mut uncached = tx.clone();
+        uncached.signer_public = None;
+        assert_eq!(tx, uncached, "The cache doesn't affect the equality");
+    }
+
+    #[test]
+    fn public_is_cached_when_signed_transaction_is_converted() {
+        let mut tx = signed_transactions(1).pop().unwrap();
+        let signed = SignedTransaction::try_new(tx.clone()).unwrap();
+        let public = signed.signer_public();
+        let unverified: UnverifiedTransaction = signed.into();
+        assert_eq!(Some(public), unverified.signer_public);
+        assert_eq!(Ok(public), tx.cache_signer_public());
+    }
+
+    #[test]
+    fn parallel_recovery_fails_at_the_same_transaction() {
+        let mut transactions = signed_transactions(300);
+        transactions[150] = UnverifiedTransaction::new(transactions[150].unsigned.clone(), Signature::default());
+        let expected: Vec<_> = transactions.iter().map(|tx| tx.recover_public()).collect();
+
+        recover_signer_publics(&mut transactions);
+        for (i, (tx, expected)) in transactions.iter().zip(expected).enumerate() {
+            assert_eq!(expected.as_ref().ok().cloned(), tx.signer_public, "#{}", i);
+            assert_eq!(expected, tx.recover_public(), "#{}", i);
+        }
+        let first_error = transactions.into_iter().map(SignedTransaction::try_new).position(|result| result.is_err());
+        assert_eq!(Some(150), first_error);
+    }
 }
@@ -0,0 +1,67 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+extern crate test;
+
+use self::test::{black_box, Bencher};
+use crate::transaction::{recover_signer_publics, SignedTransaction, UnverifiedTransaction};
+use ckey::{Address, Generator, KeyPair, Random};
+use ctypes::transaction::{Action, Transaction};
+
+/// The number of transactions in a large block.
+const NUM_TRANSACTIONS: usize = 1000;
+
+fn signed_transactions(count: usize) -> Vec<UnverifiedTransaction> {
+    let keypair: KeyPair = Random.generate().unwrap();
+    (0..count)
+        .map(|seq| {
+            let tx = Transaction {
+                seq: seq as u64,
+                fee: 10,
+                network_id: "tc".into(),
+                action: Action::Pay {
+                    receiver: Address::random(),
+                    quantity: 100,
+                    memo: None,
+                },
+            };
+            SignedTransaction::new_with_sign(tx, keypair.private()).deconstruct().0
+        })
+        .collect()
+}
+
+#[bench]
+pub fn recover_signers_sequentially(bencher: &mut Bencher) {
+    let transactions = signed_transactions(NUM_TRANSACTIONS);
+    bencher.iter(|| {
+        for tx in transactions.clone() {
+            black_box(SignedTransaction::try_new(tx).unwrap());
+        }
+    });
+}
+
+#[bench]
+pub fn recover_signers_in_parallel(bencher: &mut Bencher) {
+    let transactions = signed_transactions(NUM_TRANSACTIONS);
+    bencher.iter(|| {
+        let mut transactions = transactions.clone();
+        recover_signer_publics(&mut transactions);
+        for tx in transactions {
+            black_box(SignedTransaction::try_new(tx).unwrap());
+        }
+    });
+}
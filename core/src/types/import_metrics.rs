@@ -0,0 +1,54 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+/// The upper bounds of the histogram buckets in microseconds.
+/// The last bucket counts the durations longer than every bound.
+pub const HISTOGRAM_BOUNDS_MICROS: [u64; 5] = [100, 1_000, 10_000, 100_000, 1_000_000];
+
+/// Timing statistics of a stage of the import pipeline.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StageTimings {
+    /// Number of the measured items since the start
+    pub count: u64,
+    /// Sum of the durations of the measured items
+    pub total: Duration,
+    /// Histogram of the recent durations, bucketed by `HISTOGRAM_BOUNDS_MICROS`
+    pub histogram: Vec<u64>,
+}
+
+/// Timings of the items that went through a verification queue and were inserted into the chain.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueueTimings {
+    /// From the enqueue to the start of the verification
+    pub queued: StageTimings,
+    /// The verification itself
+    pub verification: StageTimings,
+    /// From the end of the verification to the drain
+    pub verified: StageTimings,
+    /// From the drain to the insertion into the chain
+    pub commit: StageTimings,
+    /// From the enqueue to the insertion into the chain
+    pub total: StageTimings,
+}
+
+/// Timings of the header and block queues.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportMetrics {
+    pub headers: QueueTimings,
+    pub blocks: QueueTimings,
+}
@@ -16,8 +16,10 @@
 
 mod block_status;
 mod ids;
+mod import_metrics;
 mod verification_queue_info;
 
 pub use self::block_status::BlockStatus;
 pub use self::ids::{BlockId, TransactionId};
+pub use self::import_metrics::{ImportMetrics, QueueTimings, StageTimings, HISTOGRAM_BOUNDS_MICROS};
 pub use self::verification_queue_info::VerificationQueueInfo;
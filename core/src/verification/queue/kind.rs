@@ -156,8 +156,10 @@ pub mod blocks {
     };
     use super::{BlockLike, Kind, MemUsage};
     use crate::consensus::CodeChainEngine;
-    use crate::error::Error;
+    use crate::encoded;
+    use crate::error::{BlockError, Error};
     use crate::service::ClientIoMessage;
+    use rlp::Rlp;
 
     /// A mode for verifying blocks.
     pub struct Blocks;
@@ -215,14 +217,30 @@ pub mod blocks {
         pub fn new(bytes: Bytes) -> Self {
             use crate::views::BlockView;
 
-            let header = BlockView::new(&bytes).header();
-            let mem_usage = header.mem_usage() + bytes.len();
+            let view = BlockView::new(&bytes);
+            let header = view.header();
+            // The same as `header.mem_usage()`, but without encoding the header again.
+            let mem_usage = view.header_rlp().as_raw().len() + bytes.len();
             Unverified {
                 header,
                 bytes,
                 mem_usage,
             }
         }
+
+        /// Create an `Unverified` from untrusted bytes.
+        /// Unlike `new`, a malformed block is rejected instead of causing a panic.
+        pub fn new_checked(bytes: Bytes, engine: &dyn CodeChainEngine) -> Result<Self, BlockError> {
+            let header = Rlp::new(&bytes).val_at::<Header>(0);
+            let checked = header.and_then(|header| encoded::Block::new_checked(bytes, engine.seal_fields(&header)));
+            match checked {
+                Ok(block) => Ok(Self::new(block.into_inner())),
+                Err(err) => {
+                    cdebug!(CLIENT, "Malformed block: {:?}", err);
+                    Err(BlockError::MalformedBlock)
+                }
+            }
+        }
     }
 
     impl BlockLike for Unverified {
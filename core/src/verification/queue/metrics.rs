@@ -0,0 +1,164 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::{QueueTimings, StageTimings, HISTOGRAM_BOUNDS_MICROS};
+use ctypes::BlockHash;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// The number of recent durations kept for the histogram of each stage.
+const HISTOGRAM_WINDOW: usize = 1024;
+
+#[derive(Default)]
+struct RollingHistogram {
+    count: u64,
+    total: Duration,
+    recent: VecDeque<Duration>,
+}
+
+impl RollingHistogram {
+    fn record(&mut self, duration: Duration) {
+        self.count += 1;
+        self.total += duration;
+        if self.recent.len() == HISTOGRAM_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(duration);
+    }
+
+    fn timings(&self) -> StageTimings {
+        let mut histogram = vec![0; HISTOGRAM_BOUNDS_MICROS.len() + 1];
+        for duration in &self.recent {
+            let micros = duration.as_micros();
+            let bucket = HISTOGRAM_BOUNDS_MICROS
+                .iter()
+                .position(|bound| micros < u128::from(*bound))
+                .unwrap_or(HISTOGRAM_BOUNDS_MICROS.len());
+            histogram[bucket] += 1;
+        }
+        StageTimings {
+            count: self.count,
+            total: self.total,
+            histogram,
+        }
+    }
+}
+
+struct ItemTiming {
+    enqueued: Instant,
+    verify_started: Option<Instant>,
+    verified: Option<Instant>,
+    drained: Option<Instant>,
+}
+
+/// Measures how long the items stay in each stage of the queue.
+/// The stages of an item are recorded together when it is inserted into the chain,
+/// so the sum of the stages always matches the total.
+#[derive(Default)]
+pub struct QueueMetrics {
+    items: HashMap<BlockHash, ItemTiming>,
+    queued: RollingHistogram,
+    verification: RollingHistogram,
+    verified: RollingHistogram,
+    commit: RollingHistogram,
+    total: RollingHistogram,
+}
+
+impl QueueMetrics {
+    pub fn enqueued(&mut self, hash: BlockHash) {
+        self.items.insert(hash, ItemTiming {
+            enqueued: Instant::now(),
+            verify_started: None,
+            verified: None,
+            drained: None,
+        });
+    }
+
+    pub fn verify_started(&mut self, hash: &BlockHash) {
+        if let Some(item) = self.items.get_mut(hash) {
+            item.verify_started = Some(Instant::now());
+        }
+    }
+
+    pub fn verified(&mut self, hash: &BlockHash) {
+        if let Some(item) = self.items.get_mut(hash) {
+            item.verified = Some(Instant::now());
+        }
+    }
+
+    pub fn drained(&mut self, hash: &BlockHash) {
+        if let Some(item) = self.items.get_mut(hash) {
+            item.drained = Some(Instant::now());
+        }
+    }
+
+    pub fn inserted(&mut self, hash: &BlockHash) {
+        let item = match self.items.remove(hash) {
+            Some(item) => item,
+            None => return,
+        };
+        if let (Some(verify_started), Some(verified), Some(drained)) =
+            (item.verify_started, item.verified, item.drained)
+        {
+            let inserted = Instant::now();
+            self.queued.record(verify_started - item.enqueued);
+            self.verification.record(verified - verify_started);
+            self.verified.record(drained - verified);
+            self.commit.record(inserted - drained);
+            self.total.record(inserted - item.enqueued);
+        }
+    }
+
+    /// Forgets the item which will never be inserted.
+    pub fn removed(&mut self, hash: &BlockHash) {
+        self.items.remove(hash);
+    }
+
+    pub fn timings(&self) -> QueueTimings {
+        QueueTimings {
+            queued: self.queued.timings(),
+            verification: self.verification.timings(),
+            verified: self.verified.timings(),
+            commit: self.commit.timings(),
+            total: self.total.timings(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_recent_durations() {
+        let mut histogram = RollingHistogram::default();
+        histogram.record(Duration::from_micros(10));
+        histogram.record(Duration::from_micros(500));
+        histogram.record(Duration::from_secs(2));
+
+        let timings = histogram.timings();
+        assert_eq!(3, timings.count);
+        assert_eq!(Duration::from_micros(2_000_510), timings.total);
+        assert_eq!(vec![1, 1, 0, 0, 0, 1], timings.histogram);
+
+        for _ in 0..HISTOGRAM_WINDOW {
+            histogram.record(Duration::from_millis(50));
+        }
+        let timings = histogram.timings();
+        assert_eq!(3 + HISTOGRAM_WINDOW as u64, timings.count);
+        assert_eq!(vec![0, 0, 0, HISTOGRAM_WINDOW as u64, 0, 0], timings.histogram);
+    }
+}
@@ -15,12 +15,14 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 pub mod kind;
+mod metrics;
 
 use self::kind::{BlockLike, Kind, MemUsage};
+use self::metrics::QueueMetrics;
 use crate::consensus::CodeChainEngine;
 use crate::error::{BlockError, Error, ImportError};
 use crate::service::ClientIoMessage;
-use crate::types::{BlockStatus as Status, VerificationQueueInfo as QueueInfo};
+use crate::types::{BlockStatus as Status, QueueTimings, VerificationQueueInfo as QueueInfo};
 use cio::IoChannel;
 use ctypes::BlockHash;
 use parking_lot::{Mutex, RwLock};
@@ -50,6 +52,8 @@ pub struct Config {
     /// Maximum heap memory to use.
     /// When the limit is reached, is_full returns true.
     pub max_mem_use: usize,
+    /// Measure the time spent in each stage of the queue.
+    pub enable_metrics: bool,
 }
 
 impl Default for Config {
@@ -57,6 +61,7 @@ impl Default for Config {
         Config {
             max_queue_size: 30000,
             max_mem_use: 50 * 1024 * 1024,
+            enable_metrics: false,
         }
     }
 }
@@ -69,11 +74,17 @@ pub struct VerificationQueue<K: Kind> {
     ready_signal: Arc<QueueSignal>,
     total_score: RwLock<U256>,
     more_to_verify: Arc<SCondvar>,
-    verifier_handles: Vec<JoinHandle<()>>,
+    verifier_handles: Mutex<Vec<JoinHandle<()>>>,
     max_queue_size: usize,
     max_mem_use: usize,
 }
 
+/// Sends the ready message to the client.
+///
+/// `signalled` is true while a sent message is not handled by `drain` yet.
+/// It is changed only while the verified queues are locked,
+/// so an item entering the verified queue is either taken by the ongoing `drain`
+/// or produces exactly one new message.
 struct QueueSignal {
     deleting: Arc<AtomicBool>,
     signalled: AtomicBool,
@@ -82,36 +93,37 @@ struct QueueSignal {
 }
 
 impl QueueSignal {
-    fn set_sync(&self) {
+    /// Returns true if the caller is responsible for sending the message.
+    fn claim(&self) -> bool {
+        !self.signalled.swap(true, AtomicOrdering::SeqCst)
+    }
+
+    fn send_sync(&self) {
         // Do not signal when we are about to close
-        if self.deleting.load(AtomicOrdering::Relaxed) {
+        if self.deleting.load(AtomicOrdering::SeqCst) {
             return
         }
 
-        if !self.signalled.compare_and_swap(false, true, AtomicOrdering::Relaxed) {
-            let channel = self.message_channel.lock().clone();
-            if let Err(e) = channel.send_sync(self.message.clone()) {
-                cwarn!(ENGINE, "Error sending verified message: {:?}", e);
-            }
+        let channel = self.message_channel.lock().clone();
+        if let Err(e) = channel.send_sync(self.message.clone()) {
+            cwarn!(ENGINE, "Error sending verified message: {:?}", e);
         }
     }
 
-    fn set_async(&self) {
+    fn send_async(&self) {
         // Do not signal when we are about to close
-        if self.deleting.load(AtomicOrdering::Relaxed) {
+        if self.deleting.load(AtomicOrdering::SeqCst) {
             return
         }
 
-        if !self.signalled.compare_and_swap(false, true, AtomicOrdering::Relaxed) {
-            let channel = self.message_channel.lock().clone();
-            if let Err(e) = channel.send(self.message.clone()) {
-                cwarn!(ENGINE, "Error sending verified message: {:?}", e);
-            }
+        let channel = self.message_channel.lock().clone();
+        if let Err(e) = channel.send(self.message.clone()) {
+            cwarn!(ENGINE, "Error sending verified message: {:?}", e);
         }
     }
 
     fn reset(&self) {
-        self.signalled.store(false, AtomicOrdering::Relaxed);
+        self.signalled.store(false, AtomicOrdering::SeqCst);
     }
 }
 
@@ -123,9 +135,8 @@ impl<K: Kind> VerificationQueue<K> {
         check_seal: bool,
     ) -> Self {
         let verification = Arc::new(Verification {
-            unverified: Mutex::new(VecDeque::new()),
-            verifying: Mutex::new(VecDeque::new()),
-            verified: Mutex::new(VecDeque::new()),
+            priority: Lane::new(),
+            backlog: Lane::new(),
             bad: Mutex::new(HashSet::new()),
             sizes: Sizes {
                 unverified: AtomicUsize::new(0),
@@ -134,6 +145,11 @@ impl<K: Kind> VerificationQueue<K> {
             },
             check_seal,
             more_to_verify_mutex: SMutex::new(()),
+            metrics: if config.enable_metrics {
+                Some(Mutex::new(QueueMetrics::default()))
+            } else {
+                None
+            },
         });
         let deleting = Arc::new(AtomicBool::new(false));
         let ready_signal = Arc::new(QueueSignal {
@@ -180,7 +196,7 @@ impl<K: Kind> VerificationQueue<K> {
             ready_signal,
             total_score: RwLock::new(0.into()),
             more_to_verify,
-            verifier_handles,
+            verifier_handles: Mutex::new(verifier_handles),
             max_queue_size: cmp::max(config.max_queue_size, MIN_QUEUE_LIMIT),
             max_mem_use: cmp::max(config.max_mem_use, MIN_MEM_LIMIT),
         }
@@ -200,11 +216,11 @@ impl<K: Kind> VerificationQueue<K> {
             {
                 let mut more_to_verify_mutex = verification.more_to_verify_mutex.lock().unwrap();
 
-                if verification.unverified.lock().is_empty() && verification.verifying.lock().is_empty() {
+                if verification.is_empty() {
                     empty.notify_all();
                 }
 
-                while verification.unverified.lock().is_empty() {
+                while verification.is_unverified_empty() {
                     if deleting.load(AtomicOrdering::SeqCst) {
                         return
                     }
@@ -215,11 +231,17 @@ impl<K: Kind> VerificationQueue<K> {
                 return
             }
 
-            // do work.
+            // do work. The priority lane is always served first.
+            let is_priority = !verification.priority.unverified.lock().is_empty();
+            let lane = if is_priority {
+                &verification.priority
+            } else {
+                &verification.backlog
+            };
             let item = {
                 // acquire these locks before getting the item to verify.
-                let mut unverified = verification.unverified.lock();
-                let mut verifying = verification.verifying.lock();
+                let mut unverified = lane.unverified.lock();
+                let mut verifying = lane.verifying.lock();
 
                 let item = match unverified.pop_front() {
                     Some(item) => item,
@@ -227,6 +249,7 @@ impl<K: Kind> VerificationQueue<K> {
                 };
 
                 verification.sizes.unverified.fetch_sub(item.mem_usage(), AtomicOrdering::SeqCst);
+                verification.record(|metrics| metrics.verify_started(&item.hash()));
                 verifying.push_back(Verifying {
                     hash: item.hash(),
                     output: None,
@@ -235,9 +258,10 @@ impl<K: Kind> VerificationQueue<K> {
             };
 
             let hash = item.hash();
-            let is_ready = match K::verify(item, engine, verification.check_seal) {
+            let must_signal = match K::verify(item, engine, verification.check_seal) {
                 Ok(verified) => {
-                    let mut verifying = verification.verifying.lock();
+                    verification.record(|metrics| metrics.verified(&hash));
+                    let mut verifying = lane.verifying.lock();
                     let mut idx = None;
                     for (i, e) in verifying.iter_mut().enumerate() {
                         if e.hash == hash {
@@ -251,55 +275,52 @@ impl<K: Kind> VerificationQueue<K> {
 
                     if idx == Some(0) {
                         // we're next!
-                        let mut verified = verification.verified.lock();
+                        let mut verified = lane.verified.lock();
                         let mut bad = verification.bad.lock();
-                        VerificationQueue::drain_verifying(
-                            &mut verifying,
-                            &mut verified,
-                            &mut bad,
-                            &verification.sizes,
-                        );
-                        true
+                        let is_inserted =
+                            VerificationQueue::drain_verifying(&mut verifying, &mut verified, &mut bad, verification);
+                        // A priority item is signalled even if a message is pending.
+                        is_inserted && (ready_signal.claim() || is_priority)
                     } else {
                         false
                     }
                 }
                 Err(_) => {
-                    let mut verifying = verification.verifying.lock();
-                    let mut verified = verification.verified.lock();
+                    verification.record(|metrics| metrics.removed(&hash));
+                    let mut verifying = lane.verifying.lock();
+                    let mut verified = lane.verified.lock();
                     let mut bad = verification.bad.lock();
 
                     bad.insert(hash);
                     verifying.retain(|e| e.hash != hash);
 
                     if verifying.front().map_or(false, |x| x.output.is_some()) {
-                        VerificationQueue::drain_verifying(
-                            &mut verifying,
-                            &mut verified,
-                            &mut bad,
-                            &verification.sizes,
-                        );
-                        true
+                        let is_inserted =
+                            VerificationQueue::drain_verifying(&mut verifying, &mut verified, &mut bad, verification);
+                        is_inserted && (ready_signal.claim() || is_priority)
                     } else {
                         false
                     }
                 }
             };
-            if is_ready {
+            if must_signal {
                 // Import the block immediately
-                ready_signal.set_sync();
+                ready_signal.send_sync();
             }
         }
     }
 
+    /// Moves the verified items at the front of `verifying` into `verified`.
+    /// Returns true if any item is inserted into `verified`.
     fn drain_verifying(
         verifying: &mut VecDeque<Verifying<K>>,
         verified: &mut VecDeque<K::Verified>,
         bad: &mut HashSet<BlockHash>,
-        sizes: &Sizes,
-    ) {
+        verification: &Verification<K>,
+    ) -> bool {
         let mut removed_size = 0;
         let mut inserted_size = 0;
+        let mut is_inserted = false;
 
         while let Some(output) = verifying.front_mut().and_then(|x| x.output.take()) {
             assert!(verifying.pop_front().is_some());
@@ -307,15 +328,18 @@ impl<K: Kind> VerificationQueue<K> {
             removed_size += size;
 
             if bad.contains(&output.parent_hash()) {
+                verification.record(|metrics| metrics.removed(&output.hash()));
                 bad.insert(output.hash());
             } else {
                 inserted_size += size;
+                is_inserted = true;
                 verified.push_back(output);
             }
         }
 
-        sizes.verifying.fetch_sub(removed_size, AtomicOrdering::SeqCst);
-        sizes.verified.fetch_add(inserted_size, AtomicOrdering::SeqCst);
+        verification.sizes.verifying.fetch_sub(removed_size, AtomicOrdering::SeqCst);
+        verification.sizes.verified.fetch_add(inserted_size, AtomicOrdering::SeqCst);
+        is_inserted
     }
 
     /// Check if the item is currently in the queue
@@ -331,6 +355,19 @@ impl<K: Kind> VerificationQueue<K> {
 
     /// Add a block to the queue.
     pub fn import(&self, input: K::Input) -> Result<BlockHash, Error> {
+        self.push(input, &self.verification.backlog)
+    }
+
+    /// Add a block to the priority lane of the queue.
+    /// It is verified and drained before the items added by `import`.
+    pub fn import_priority(&self, input: K::Input) -> Result<BlockHash, Error> {
+        self.push(input, &self.verification.priority)
+    }
+
+    fn push(&self, input: K::Input, lane: &Lane<K>) -> Result<BlockHash, Error> {
+        if self.deleting.load(AtomicOrdering::SeqCst) {
+            return Err(ImportError::Stopped.into())
+        }
         let h = input.hash();
         {
             if self.processing.read().contains_key(&h) {
@@ -352,13 +389,18 @@ impl<K: Kind> VerificationQueue<K> {
                 self.verification.sizes.unverified.fetch_add(item.mem_usage(), AtomicOrdering::SeqCst);
 
                 self.processing.write().insert(h, item.score());
+                self.verification.record(|metrics| metrics.enqueued(h));
                 {
                     let mut ts = self.total_score.write();
                     *ts += item.score();
                 }
 
-                self.verification.unverified.lock().push_back(item);
-                self.more_to_verify.notify_all();
+                lane.unverified.lock().push_back(item);
+                {
+                    // Notify while holding the mutex, so that a verifier about to wait doesn't miss it.
+                    let _more_to_verify_mutex = self.verification.more_to_verify_mutex.lock().unwrap();
+                    self.more_to_verify.notify_all();
+                }
                 Ok(h)
             }
             Err(err) => {
@@ -374,18 +416,54 @@ impl<K: Kind> VerificationQueue<K> {
         }
     }
 
-    /// Removes up to `max` verified items from the queue
+    /// Removes up to `max` verified items from the queue.
+    /// The items in the priority lane are returned before the others,
+    /// but a priority item whose parent is in the backlog is returned after the parent.
     pub fn drain(&self, max: usize) -> Vec<K::Verified> {
-        let mut verified = self.verification.verified.lock();
-        let count = cmp::min(max, verified.len());
-        let result = verified.drain(..count).collect::<Vec<_>>();
+        let mut priority = self.verification.priority.verified.lock();
+        // The backlog is locked in the same order as the verifiers lock it.
+        let backlog_unverified = self.verification.backlog.unverified.lock();
+        let backlog_verifying = self.verification.backlog.verifying.lock();
+        let mut backlog = self.verification.backlog.verified.lock();
+
+        let mut result = Vec::with_capacity(cmp::min(max, priority.len() + backlog.len()));
+        let mut is_priority_blocked = false;
+        while result.len() < max {
+            let parent = match priority.front() {
+                Some(item) => item.parent_hash(),
+                None => break,
+            };
+            if let Some(index) = backlog.iter().position(|item| item.hash() == parent) {
+                let count = cmp::min(index + 1, max - result.len());
+                result.extend(backlog.drain(..count));
+                continue
+            }
+            // The parent is not verified yet, so the priority lane waits for it.
+            if backlog_unverified.iter().any(|item| item.hash() == parent)
+                || backlog_verifying.iter().any(|item| item.hash == parent)
+            {
+                is_priority_blocked = true;
+                break
+            }
+            result.extend(priority.pop_front());
+        }
+        let backlog_count = cmp::min(max - result.len(), backlog.len());
+        result.extend(backlog.drain(..backlog_count));
+        self.verification.record(|metrics| {
+            for item in &result {
+                metrics.drained(&item.hash());
+            }
+        });
 
         let drained_size = result.iter().map(MemUsage::mem_usage).sum::<usize>();
         self.verification.sizes.verified.fetch_sub(drained_size, AtomicOrdering::SeqCst);
 
+        // The signal is reset while the verified queues are locked, so the verifiers cannot miss it.
+        // A blocked priority lane is signalled again when the verifiers finish its parent.
         self.ready_signal.reset();
-        if !verified.is_empty() {
-            self.ready_signal.set_async();
+        let has_more = !backlog.is_empty() || (!priority.is_empty() && !is_priority_blocked);
+        if has_more && self.ready_signal.claim() {
+            self.ready_signal.send_async();
         }
         result
     }
@@ -413,37 +491,62 @@ impl<K: Kind> VerificationQueue<K> {
         if hashes.is_empty() {
             return
         }
-        let mut verified_lock = self.verification.verified.lock();
-        let verified = &mut *verified_lock;
+        let mut priority = self.verification.priority.verified.lock();
+        let mut backlog = self.verification.backlog.verified.lock();
         let mut bad = self.verification.bad.lock();
         let mut processing = self.processing.write();
         bad.reserve(hashes.len());
         for hash in hashes {
             bad.insert(*hash);
+            self.verification.record(|metrics| metrics.removed(hash));
             if let Some(score) = processing.remove(hash) {
                 let mut td = self.total_score.write();
                 *td -= score;
             }
         }
 
-        let mut new_verified = VecDeque::new();
         let mut removed_size = 0;
-        for output in verified.drain(..) {
-            if bad.contains(&output.parent_hash()) {
-                removed_size += output.mem_usage();
-                bad.insert(output.hash());
-                if let Some(score) = processing.remove(&output.hash()) {
-                    let mut td = self.total_score.write();
-                    *td -= score;
+        // The parent of an item may be in the other lane, so the lanes are scanned until no more items are removed.
+        let mut is_removed = true;
+        while is_removed {
+            is_removed = false;
+            for verified in [&mut *priority, &mut *backlog].iter_mut() {
+                let mut new_verified = VecDeque::new();
+                for output in verified.drain(..) {
+                    if bad.contains(&output.parent_hash()) {
+                        is_removed = true;
+                        removed_size += output.mem_usage();
+                        self.verification.record(|metrics| metrics.removed(&output.hash()));
+                        bad.insert(output.hash());
+                        if let Some(score) = processing.remove(&output.hash()) {
+                            let mut td = self.total_score.write();
+                            *td -= score;
+                        }
+                    } else {
+                        new_verified.push_back(output);
+                    }
                 }
-            } else {
-                new_verified.push_back(output);
+                **verified = new_verified;
             }
         }
         processing.shrink_to_fit();
 
         self.verification.sizes.verified.fetch_sub(removed_size, AtomicOrdering::SeqCst);
-        *verified = new_verified;
+    }
+
+    /// Record that the given items are inserted into the chain.
+    /// It does nothing if the metrics are disabled.
+    pub fn mark_as_inserted(&self, hashes: impl IntoIterator<Item = BlockHash>) {
+        self.verification.record(|metrics| {
+            for hash in hashes {
+                metrics.inserted(&hash);
+            }
+        });
+    }
+
+    /// Get the timings of the inserted items, if the metrics are enabled.
+    pub fn timings(&self) -> Option<QueueTimings> {
+        self.verification.metrics.as_ref().map(|metrics| metrics.lock().timings())
     }
 
     /// Get queue status.
@@ -451,18 +554,18 @@ impl<K: Kind> VerificationQueue<K> {
         use std::mem::size_of;
 
         let (unverified_len, unverified_bytes) = {
-            let len = self.verification.unverified.lock().len();
+            let len = self.verification.lanes().iter().map(|lane| lane.unverified.lock().len()).sum::<usize>();
             let size = self.verification.sizes.unverified.load(AtomicOrdering::Acquire);
 
             (len, size + len * size_of::<K::Unverified>())
         };
         let (verifying_len, verifying_bytes) = {
-            let len = self.verification.verifying.lock().len();
+            let len = self.verification.lanes().iter().map(|lane| lane.verifying.lock().len()).sum::<usize>();
             let size = self.verification.sizes.verifying.load(AtomicOrdering::Acquire);
             (len, size + len * size_of::<Verifying<K>>())
         };
         let (verified_len, verified_bytes) = {
-            let len = self.verification.verified.lock().len();
+            let len = self.verification.lanes().iter().map(|lane| lane.verified.lock().len()).sum::<usize>();
             let size = self.verification.sizes.verified.load(AtomicOrdering::Acquire);
             (len, size + len * size_of::<K::Verified>())
         };
@@ -481,18 +584,27 @@ impl<K: Kind> VerificationQueue<K> {
     pub fn total_score(&self) -> U256 {
         *self.total_score.read()
     }
-}
 
-impl<K: Kind> Drop for VerificationQueue<K> {
-    fn drop(&mut self) {
+    /// Rejects new items and joins the verifier threads.
+    /// The items already verified can still be drained.
+    pub fn stop(&self) {
         self.deleting.store(true, AtomicOrdering::SeqCst);
-        self.more_to_verify.notify_all();
-        for handle in self.verifier_handles.drain(0..) {
+        {
+            let _more_to_verify_mutex = self.verification.more_to_verify_mutex.lock().unwrap();
+            self.more_to_verify.notify_all();
+        }
+        for handle in self.verifier_handles.lock().drain(..) {
             handle.join().unwrap();
         }
     }
 }
 
+impl<K: Kind> Drop for VerificationQueue<K> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
 // the internal queue sizes.
 struct Sizes {
     unverified: AtomicUsize,
@@ -501,13 +613,53 @@ struct Sizes {
 }
 
 struct Verification<K: Kind> {
-    unverified: Mutex<VecDeque<K::Unverified>>,
-    verifying: Mutex<VecDeque<Verifying<K>>>,
-    verified: Mutex<VecDeque<K::Verified>>,
+    // Items which skip the backlog, e.g. the blocks sealed locally or received by the consensus engine.
+    priority: Lane<K>,
+    backlog: Lane<K>,
     bad: Mutex<HashSet<BlockHash>>,
     sizes: Sizes,
     check_seal: bool,
     more_to_verify_mutex: SMutex<()>,
+    metrics: Option<Mutex<QueueMetrics>>,
+}
+
+impl<K: Kind> Verification<K> {
+    fn record<F>(&self, f: F)
+    where
+        F: FnOnce(&mut QueueMetrics), {
+        if let Some(metrics) = &self.metrics {
+            f(&mut metrics.lock());
+        }
+    }
+
+    fn lanes(&self) -> [&Lane<K>; 2] {
+        [&self.priority, &self.backlog]
+    }
+
+    fn is_unverified_empty(&self) -> bool {
+        self.lanes().iter().all(|lane| lane.unverified.lock().is_empty())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lanes().iter().all(|lane| lane.unverified.lock().is_empty() && lane.verifying.lock().is_empty())
+    }
+}
+
+// the stages of the verification. Each lane keeps its own order.
+struct Lane<K: Kind> {
+    unverified: Mutex<VecDeque<K::Unverified>>,
+    verifying: Mutex<VecDeque<Verifying<K>>>,
+    verified: Mutex<VecDeque<K::Verified>>,
+}
+
+impl<K: Kind> Lane<K> {
+    fn new() -> Self {
+        Self {
+            unverified: Mutex::new(VecDeque::new()),
+            verifying: Mutex::new(VecDeque::new()),
+            verified: Mutex::new(VecDeque::new()),
+        }
+    }
 }
 
 /// An item which is in the process of being verified.
@@ -520,11 +672,24 @@ pub struct Verifying<K: Kind> {
 mod tests {
     use cio::IoChannel;
 
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use cio::{IoContext, IoHandler, IoHandlerResult, IoService};
+    use parking_lot::RwLock;
+
     use super::kind::blocks::Unverified;
-    use super::{BlockQueue, Config};
-    use crate::error::{Error, ImportError};
+    use super::kind::BlockLike;
+    use super::{BlockQueue, Config, Status};
+    use crate::error::{BlockError, Error, ImportError};
     use crate::scheme::Scheme;
-    use crate::tests::helpers::get_good_dummy_block;
+    use crate::service::ClientIoMessage;
+    use crate::tests::helpers::{create_test_block, get_good_dummy_block};
+    use ctypes::{BlockHash, Header};
+    use primitives::{Bytes, H256, U256};
+    use rlp::{Rlp, RlpStream};
 
     // create a test block queue.
     // auto_scaling enables verifier adjustment.
@@ -536,6 +701,22 @@ mod tests {
         BlockQueue::new(&config, engine, IoChannel::disconnected(), true)
     }
 
+    fn get_dummy_block(timestamp: u64) -> (BlockHash, Bytes) {
+        let mut header = Header::new();
+        header.set_score(U256::from(0x20000));
+        header.set_timestamp(timestamp);
+        header.set_number(1);
+        header.set_parent_hash(Scheme::new_test().genesis_header().hash());
+
+        (header.hash(), create_test_block(&header))
+    }
+
+    fn wait_for_verification(queue: &BlockQueue) {
+        while queue.queue_info().incomplete_queue_size() != 0 {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
     #[test]
     fn create() {
         // TODO better test
@@ -574,4 +755,247 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn stopped_queue_rejects_new_blocks() {
+        let queue = get_test_queue();
+        let (hash, bytes) = get_dummy_block(40);
+        queue.import(Unverified::new(bytes)).unwrap();
+        wait_for_verification(&queue);
+
+        queue.stop();
+        queue.stop();
+        assert!(queue.verifier_handles.lock().is_empty());
+        match queue.import(Unverified::new(get_dummy_block(41).1)) {
+            Err(Error::Import(ImportError::Stopped)) => {}
+            result => panic!("must return Stopped error: {:?}", result),
+        }
+        let drained: Vec<_> = queue.drain(10).iter().map(BlockLike::hash).collect();
+        assert_eq!(vec![hash], drained);
+    }
+
+    #[test]
+    fn priority_block_is_drained_first() {
+        let queue = get_test_queue();
+        let backlog: Vec<_> = (0..100).map(|i| get_dummy_block(40 + i)).collect();
+        for (_, bytes) in &backlog {
+            queue.import(Unverified::new(bytes.clone())).unwrap();
+        }
+        let (priority_hash, priority_bytes) = get_dummy_block(1_000);
+        queue.import_priority(Unverified::new(priority_bytes)).unwrap();
+
+        wait_for_verification(&queue);
+        assert_eq!(backlog.len() + 1, queue.queue_info().verified_queue_size);
+
+        let drained = queue.drain(10);
+        assert_eq!(10, drained.len());
+        assert_eq!(priority_hash, drained[0].hash());
+        let backlog_hashes: Vec<_> = backlog.iter().map(|(hash, _)| *hash).collect();
+        let drained_hashes: Vec<_> = drained[1..].iter().map(BlockLike::hash).collect();
+        assert_eq!(&backlog_hashes[..9], &drained_hashes[..]);
+
+        assert_eq!(backlog.len() - 9, queue.drain(1_000).len());
+        assert!(queue.queue_info().is_empty());
+    }
+
+    fn get_dummy_child(parent: BlockHash, timestamp: u64) -> (BlockHash, Bytes) {
+        let mut header = Header::new();
+        header.set_score(U256::from(0x20000));
+        header.set_timestamp(timestamp);
+        header.set_number(2);
+        header.set_parent_hash(parent);
+
+        (header.hash(), create_test_block(&header))
+    }
+
+    #[test]
+    fn priority_block_is_drained_after_its_parent_in_the_backlog() {
+        let queue = get_test_queue();
+        let backlog: Vec<_> = (0..20).map(|i| get_dummy_block(40 + i)).collect();
+        for (_, bytes) in &backlog {
+            queue.import(Unverified::new(bytes.clone())).unwrap();
+        }
+        let (child_hash, child_bytes) = get_dummy_child(backlog[5].0, 1_000);
+        queue.import_priority(Unverified::new(child_bytes)).unwrap();
+        wait_for_verification(&queue);
+
+        let drained: Vec<_> = queue.drain(1_000).iter().map(BlockLike::hash).collect();
+        let mut expected: Vec<_> = backlog.iter().map(|(hash, _)| *hash).collect();
+        expected.insert(6, child_hash);
+        assert_eq!(expected, drained);
+    }
+
+    #[test]
+    fn mark_as_bad_removes_the_descendants_in_both_lanes() {
+        let queue = get_test_queue();
+        let bad_parent = BlockHash::from(H256::random());
+        let (child_hash, child_bytes) = get_dummy_child(bad_parent, 1_000);
+        queue.import(Unverified::new(child_bytes)).unwrap();
+        wait_for_verification(&queue);
+        let (grandchild_hash, grandchild_bytes) = get_dummy_child(child_hash, 1_001);
+        queue.import_priority(Unverified::new(grandchild_bytes)).unwrap();
+        wait_for_verification(&queue);
+        assert_eq!(2, queue.queue_info().verified_queue_size);
+
+        queue.mark_as_bad(&[bad_parent]);
+        assert!(queue.queue_info().is_empty());
+        assert_eq!(Status::Bad, queue.status(&child_hash));
+        assert_eq!(Status::Bad, queue.status(&grandchild_hash));
+    }
+
+    // Drains a few items whenever the queue signals, like the client does.
+    #[derive(Default)]
+    struct DrainHandler {
+        queue: RwLock<Option<Arc<BlockQueue>>>,
+        drained: AtomicUsize,
+    }
+
+    impl IoHandler<ClientIoMessage> for DrainHandler {
+        fn message(&self, _io: &IoContext<ClientIoMessage>, message: ClientIoMessage) -> IoHandlerResult<()> {
+            if message == ClientIoMessage::BlockVerified {
+                if let Some(queue) = &*self.queue.read() {
+                    self.drained.fetch_add(queue.drain(3).len(), AtomicOrdering::SeqCst);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn drain_does_not_lose_the_ready_signal() {
+        const IMPORTERS: u64 = 4;
+        const BLOCKS_PER_IMPORTER: u64 = 50;
+
+        let service = IoService::<ClientIoMessage>::start("Test").unwrap();
+        let handler = Arc::new(DrainHandler::default());
+        service.register_handler(handler.clone()).unwrap();
+
+        let scheme = Scheme::new_test();
+        let queue = Arc::new(BlockQueue::new(&Config::default(), scheme.engine, service.channel(), true));
+        *handler.queue.write() = Some(Arc::clone(&queue));
+
+        let expected = (IMPORTERS * BLOCKS_PER_IMPORTER) as usize;
+        let imported = Arc::new(AtomicUsize::new(0));
+        let importers: Vec<_> = (0..IMPORTERS)
+            .map(|importer| {
+                let queue = Arc::clone(&queue);
+                let imported = Arc::clone(&imported);
+                thread::spawn(move || {
+                    for i in 0..BLOCKS_PER_IMPORTER {
+                        let (_, bytes) = get_dummy_block(40 + importer * BLOCKS_PER_IMPORTER + i);
+                        queue.import(Unverified::new(bytes)).unwrap();
+                        imported.fetch_add(1, AtomicOrdering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+        // Race with the verifiers and the handler while importing.
+        let mut drained_here = 0;
+        while imported.load(AtomicOrdering::SeqCst) != expected {
+            drained_here += queue.drain(1).len();
+        }
+        for importer in importers {
+            importer.join().unwrap();
+        }
+
+        // The rest must be drained by the signals only.
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while drained_here + handler.drained.load(AtomicOrdering::SeqCst) != expected {
+            assert!(Instant::now() < deadline, "The verification queue stalled: {:?}", queue.queue_info());
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(queue.queue_info().is_empty());
+
+        // The verifier threads must exit when the queue is dropped.
+        *handler.queue.write() = None;
+        assert_eq!(1, Arc::strong_count(&queue));
+        drop(queue);
+        service.stop();
+    }
+
+    #[test]
+    fn metrics_are_recorded_for_inserted_items() {
+        let scheme = Scheme::new_test();
+        let config = Config {
+            enable_metrics: true,
+            ..Config::default()
+        };
+        let queue = BlockQueue::new(&config, scheme.engine, IoChannel::disconnected(), true);
+        for i in 0..10 {
+            queue.import(Unverified::new(get_dummy_block(40 + i).1)).unwrap();
+        }
+        wait_for_verification(&queue);
+
+        let drained = queue.drain(10);
+        assert_eq!(0, queue.timings().unwrap().total.count);
+        queue.mark_as_inserted(drained.iter().map(BlockLike::hash));
+
+        let timings = queue.timings().unwrap();
+        for stage in &[&timings.queued, &timings.verification, &timings.verified, &timings.commit, &timings.total] {
+            assert_eq!(10, stage.count);
+            assert_eq!(10, stage.histogram.iter().sum::<u64>());
+        }
+        assert_eq!(
+            timings.total.total,
+            timings.queued.total + timings.verification.total + timings.verified.total + timings.commit.total
+        );
+    }
+
+    #[test]
+    fn metrics_are_disabled_by_default() {
+        let queue = get_test_queue();
+        queue.import(Unverified::new(get_good_dummy_block())).unwrap();
+        wait_for_verification(&queue);
+        queue.mark_as_inserted(queue.drain(1).iter().map(BlockLike::hash));
+        assert_eq!(None, queue.timings());
+    }
+
+    fn block_with_header_fields(fields: &[&[u8]]) -> Bytes {
+        let mut header = RlpStream::new_list(fields.len());
+        for field in fields {
+            header.append_raw(field, 1);
+        }
+        let mut block = RlpStream::new_list(2);
+        block.append_raw(&header.out(), 1);
+        block.begin_list(0);
+        block.out()
+    }
+
+    #[test]
+    fn malformed_blocks_are_rejected_without_panic() {
+        let engine = Scheme::new_test().engine;
+        let check = |bytes: Bytes| Unverified::new_checked(bytes, &*engine).map(|unverified| unverified.hash());
+
+        let (hash, block) = get_dummy_block(40);
+        assert_eq!(Ok(hash), check(block.clone()));
+
+        for len in 0..block.len() {
+            assert_eq!(Err(BlockError::MalformedBlock), check(block[..len].to_vec()), "truncated to {} bytes", len);
+        }
+
+        let header_rlp = Rlp::new(&block).at(0).unwrap();
+        let fields: Vec<&[u8]> = header_rlp.iter().map(|field| field.as_raw()).collect();
+        assert_eq!(Ok(hash), check(block_with_header_fields(&fields)));
+        for count in 0..fields.len() {
+            let truncated = block_with_header_fields(&fields[..count]);
+            assert_eq!(Err(BlockError::MalformedBlock), check(truncated), "{} header fields", count);
+        }
+
+        // The test engine doesn't have any seal field.
+        let mut list_field = RlpStream::new_list(1);
+        list_field.append(&1u64);
+        let list_field = list_field.out();
+        let mut over_long = fields.clone();
+        over_long.push(&list_field[..]);
+        assert_eq!(Err(BlockError::MalformedBlock), check(block_with_header_fields(&over_long)));
+
+        // The block number is not a list.
+        let mut wrong_type = fields.clone();
+        wrong_type[5] = &list_field[..];
+        assert_eq!(Err(BlockError::MalformedBlock), check(block_with_header_fields(&wrong_type)));
+
+        let mut without_body = RlpStream::new_list(1);
+        without_body.append_raw(header_rlp.as_raw(), 1);
+        assert_eq!(Err(BlockError::MalformedBlock), check(without_body.out()));
+    }
 }
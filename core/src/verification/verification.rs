@@ -19,7 +19,7 @@ use crate::client::BlockChainTrait;
 use crate::codechain_machine::CodeChainMachine;
 use crate::consensus::CodeChainEngine;
 use crate::error::{BlockError, Error};
-use crate::transaction::{SignedTransaction, UnverifiedTransaction};
+use crate::transaction::{recover_signer_publics, SignedTransaction, UnverifiedTransaction};
 use crate::views::BlockView;
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
 use ctypes::{BlockNumber, CommonParams, Header};
@@ -72,8 +72,14 @@ pub fn verify_block_with_params(
     verify_header_with_params(&header, common_params)?;
 
     let body_rlp = Rlp::new(bytes).at(1).expect("verify_block_basic already checked it");
-    if body_rlp.as_raw().len() > common_params.max_body_size() {
-        return Err(BlockError::BodySizeIsTooBig.into())
+    let body_size = body_rlp.as_raw().len();
+    if body_size > common_params.max_body_size() {
+        return Err(BlockError::BodySizeIsTooBig(OutOfBounds {
+            min: None,
+            max: Some(common_params.max_body_size()),
+            found: body_size,
+        })
+        .into())
     }
 
     for t in body_rlp.iter().map(|rlp| rlp.as_val().expect("verify_block_basic already checked it")) {
@@ -162,13 +168,12 @@ pub fn verify_block_seal(
         engine.verify_block_seal(&header)?;
     }
     // Verify transactions.
-    let mut transactions = Vec::new();
-    {
-        let v = BlockView::new(&bytes);
-        for t in v.transactions() {
-            let signed = CodeChainMachine::verify_transaction_seal(t, &header)?;
-            transactions.push(signed);
-        }
+    let mut unverified = BlockView::new(&bytes).transactions();
+    recover_signer_publics(&mut unverified);
+    let mut transactions = Vec::with_capacity(unverified.len());
+    for t in unverified {
+        let signed = CodeChainMachine::verify_transaction_seal(t, &header)?;
+        transactions.push(signed);
     }
     Ok(PreverifiedBlock {
         header,
@@ -264,3 +269,59 @@ pub fn verify_block_final(expected: &Header, got: &Header) -> Result<(), Error>
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheme::Scheme;
+    use cjson::scheme::Params;
+    use ckey::{Address, Generator, NetworkId, Random};
+    use ctypes::transaction::{Action, Transaction};
+    use rlp::RlpStream;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn body_size_limit_is_inclusive() {
+        let scheme = Scheme::new_test();
+        let sender = Random.generate().unwrap();
+        let transactions: Vec<_> = (0..3)
+            .map(|seq| {
+                SignedTransaction::new_with_sign(
+                    Transaction {
+                        seq,
+                        fee: 10,
+                        network_id: NetworkId::default(),
+                        action: Action::Pay {
+                            receiver: Address::random(),
+                            quantity: 1,
+                            memo: None,
+                        },
+                    },
+                    sender.private(),
+                )
+            })
+            .collect();
+        let header = Header::default();
+        let mut block = RlpStream::new_list(2);
+        block.append(&header);
+        block.append_list(&transactions);
+        let bytes = block.out();
+        let body_size = Rlp::new(&bytes).at(1).unwrap().as_raw().len();
+
+        let params = |max_body_size: usize| {
+            CommonParams::try_from(Params {
+                max_body_size: max_body_size.into(),
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        assert!(verify_block_with_params(&header, &bytes, &*scheme.engine, &params(body_size)).is_ok());
+        match verify_block_with_params(&header, &bytes, &*scheme.engine, &params(body_size - 1)) {
+            Err(Error::Block(BlockError::BodySizeIsTooBig(oob))) => {
+                assert_eq!(Some(body_size - 1), oob.max);
+                assert_eq!(body_size, oob.found);
+            }
+            result => panic!("The oversized body is accepted: {:?}", result),
+        }
+    }
+}
@@ -18,7 +18,7 @@ use super::{HeaderView, TransactionView};
 use crate::transaction::{LocalizedTransaction, UnverifiedTransaction};
 use ccrypto::blake256;
 use ctypes::{BlockHash, Header, TxHash};
-use rlp::Rlp;
+use rlp::{DecoderError, Rlp};
 
 /// View onto block rlp.
 pub struct BlockView<'a> {
@@ -40,6 +40,24 @@ impl<'a> BlockView<'a> {
         }
     }
 
+    /// Creates new view onto untrusted block bytes.
+    /// Fails unless the block is a checked header with `seal_fields` seal fields and a list of transactions.
+    pub fn new_checked(bytes: &'a [u8], seal_fields: usize) -> Result<BlockView<'a>, DecoderError> {
+        let rlp = Rlp::new(bytes);
+        let got = rlp.item_count()?;
+        if got != 2 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: 2,
+                got,
+            })
+        }
+        HeaderView::new_from_rlp_checked(rlp.at(0)?, seal_fields)?;
+        rlp.list_at::<UnverifiedTransaction>(1)?;
+        Ok(Self {
+            rlp,
+        })
+    }
+
     /// Block header hash.
     pub fn hash(&self) -> BlockHash {
         self.header_view().hash()
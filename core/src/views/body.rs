@@ -18,7 +18,7 @@ use super::TransactionView;
 use crate::transaction::{LocalizedTransaction, UnverifiedTransaction};
 use ccrypto::blake256;
 use ctypes::{BlockHash, BlockNumber, TxHash};
-use rlp::Rlp;
+use rlp::{DecoderError, Rlp};
 
 /// View onto block rlp.
 pub struct BodyView<'a> {
@@ -40,6 +40,23 @@ impl<'a> BodyView<'a> {
         }
     }
 
+    /// Creates new view onto untrusted body bytes.
+    /// Fails unless the body is a list of one item: the list of transactions.
+    pub fn new_checked(bytes: &'a [u8]) -> Result<BodyView<'a>, DecoderError> {
+        let rlp = Rlp::new(bytes);
+        let got = rlp.item_count()?;
+        if got != 1 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: 1,
+                got,
+            })
+        }
+        rlp.list_at::<UnverifiedTransaction>(0)?;
+        Ok(BodyView {
+            rlp,
+        })
+    }
+
     /// Return reference to underlaying rlp.
     pub fn rlp(&self) -> &Rlp<'a> {
         &self.rlp
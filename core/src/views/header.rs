@@ -16,9 +16,38 @@
 
 use ccrypto::blake256;
 use ckey::Address;
-use ctypes::{BlockHash, BlockNumber};
+use ctypes::{BlockHash, BlockNumber, Header};
 use primitives::{Bytes, H256, U256};
-use rlp::{self, Rlp};
+use rlp::{self, DecoderError, Rlp};
+
+/// The number of header fields that precede the seal fields.
+pub const HEADER_SIZE_WITHOUT_SEAL: usize = 8;
+
+/// The fixed-size fields of a header. See `HeaderView::decode_fields`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeaderFields {
+    pub parent_hash: BlockHash,
+    pub author: Address,
+    pub state_root: H256,
+    pub transactions_root: H256,
+    pub score: U256,
+    pub number: BlockNumber,
+    pub timestamp: u64,
+}
+
+impl<'a> From<&'a Header> for HeaderFields {
+    fn from(header: &'a Header) -> Self {
+        HeaderFields {
+            parent_hash: *header.parent_hash(),
+            author: *header.author(),
+            state_root: *header.state_root(),
+            transactions_root: *header.transactions_root(),
+            score: *header.score(),
+            number: header.number(),
+            timestamp: header.timestamp(),
+        }
+    }
+}
 
 /// View onto block header rlp.
 pub struct HeaderView<'a> {
@@ -40,6 +69,29 @@ impl<'a> HeaderView<'a> {
         }
     }
 
+    /// Creates new view onto untrusted header bytes.
+    /// Fails unless the header has exactly `seal_fields` seal fields and every other field is decodable,
+    /// so the accessors of the view never panic.
+    pub fn new_checked(bytes: &'a [u8], seal_fields: usize) -> Result<HeaderView<'a>, DecoderError> {
+        Self::new_from_rlp_checked(Rlp::new(bytes), seal_fields)
+    }
+
+    /// Creates new view onto untrusted header rlp. See `new_checked`.
+    pub fn new_from_rlp_checked(rlp: Rlp<'a>, seal_fields: usize) -> Result<HeaderView<'a>, DecoderError> {
+        let expected = HEADER_SIZE_WITHOUT_SEAL + seal_fields;
+        let got = rlp.item_count()?;
+        if got != expected {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected,
+                got,
+            })
+        }
+        rlp.as_val::<Header>()?;
+        Ok(HeaderView {
+            rlp,
+        })
+    }
+
     /// Returns header hash.
     pub fn hash(&self) -> BlockHash {
         blake256(self.rlp.as_raw()).into()
@@ -90,13 +142,57 @@ impl<'a> HeaderView<'a> {
         self.rlp.val_at(7).unwrap()
     }
 
+    /// Returns the parent hash as a slice of the header bytes.
+    pub fn parent_hash_ref(&self) -> &'a [u8] {
+        self.field_ref(0)
+    }
+
+    /// Returns the author as a slice of the header bytes.
+    pub fn author_ref(&self) -> &'a [u8] {
+        self.field_ref(1)
+    }
+
+    /// Returns the state root as a slice of the header bytes.
+    pub fn state_root_ref(&self) -> &'a [u8] {
+        self.field_ref(2)
+    }
+
+    /// Returns the transactions root as a slice of the header bytes.
+    pub fn transactions_root_ref(&self) -> &'a [u8] {
+        self.field_ref(3)
+    }
+
+    /// Returns the extra data as a slice of the header bytes.
+    pub fn extra_data_ref(&self) -> &'a [u8] {
+        self.field_ref(7)
+    }
+
+    fn field_ref(&self, index: usize) -> &'a [u8] {
+        self.rlp.at(index).unwrap().data().unwrap()
+    }
+
+    /// Decodes the fixed-size fields in a single pass.
+    /// Use it instead of the accessors when several fields are needed.
+    pub fn decode_fields(&self) -> HeaderFields {
+        let mut items = self.rlp.iter();
+        let mut next = || items.next().expect("A header has all the fields");
+        // The fields of a struct expression are evaluated in the order they are written.
+        HeaderFields {
+            parent_hash: next().as_val().unwrap(),
+            author: next().as_val().unwrap(),
+            state_root: next().as_val().unwrap(),
+            transactions_root: next().as_val().unwrap(),
+            score: next().as_val().unwrap(),
+            number: next().as_val().unwrap(),
+            timestamp: next().as_val().unwrap(),
+        }
+    }
+
     /// Returns a vector of post-RLP-encoded seal fields.
     pub fn seal(&self) -> Vec<Bytes> {
-        const SIZE_WITHOUT_SEAL: usize = 8;
-
         let item_count = self.rlp.item_count().unwrap();
-        let mut seal = Vec::with_capacity(item_count - SIZE_WITHOUT_SEAL);
-        for i in SIZE_WITHOUT_SEAL..item_count {
+        let mut seal = Vec::with_capacity(item_count - HEADER_SIZE_WITHOUT_SEAL);
+        for i in HEADER_SIZE_WITHOUT_SEAL..item_count {
             seal.push(self.rlp.at(i).unwrap().as_raw().to_vec());
         }
         seal
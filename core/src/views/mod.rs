@@ -21,5 +21,5 @@ mod transaction;
 
 pub use self::block::BlockView;
 pub use self::body::BodyView;
-pub use self::header::HeaderView;
+pub use self::header::{HeaderFields, HeaderView};
 pub use self::transaction::TransactionView;
@@ -17,16 +17,54 @@
 pub struct Config {
     pub bucket_size: u8,
     pub t_refresh: u32,
+    pub mode: Mode,
 }
 
 use super::K;
 use super::T_REFRESH;
+use std::fmt;
+use std::str::FromStr;
 
 impl Config {
-    pub fn new(bucket_size: Option<u8>, t_refresh: Option<u32>) -> Self {
+    pub fn new(bucket_size: Option<u8>, t_refresh: Option<u32>, mode: Option<Mode>) -> Self {
         Self {
             bucket_size: bucket_size.unwrap_or(K),
             t_refresh: t_refresh.unwrap_or(T_REFRESH),
+            mode: mode.unwrap_or(Mode::Full),
+        }
+    }
+}
+
+/// How much the node takes part in the discovery.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// Queries the peers for their neighbors periodically and answers their queries.
+    Full,
+    /// Answers the queries of the peers, but never queries them nor sets the refresh timer.
+    Passive,
+    /// The extension is not registered. The node connects to the bootstrap addresses and the ones added manually.
+    Disabled,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Mode::Full),
+            "passive" => Ok(Mode::Passive),
+            "disabled" => Ok(Mode::Disabled),
+            _ => Err(format!("Unknown discovery mode {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mode::Full => write!(f, "full"),
+            Mode::Passive => write!(f, "passive"),
+            Mode::Disabled => write!(f, "disabled"),
         }
     }
 }
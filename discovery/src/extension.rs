@@ -16,14 +16,16 @@
 
 use super::message::Message;
 use super::node_id::{address_to_hash, KademliaId};
-use super::Config;
-use cnetwork::{Api, IntoSocketAddr, NetworkExtension, NodeId, RoutingTable};
+use super::{Config, Mode};
+use cnetwork::{
+    decode_message, Api, IntoSocketAddr, NetworkExtension, NodeId, RequestError, RequestId, RoutingTable, SocketAddr,
+};
 use ctimer::TimerToken;
 use never_type::Never;
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
-use rlp::{Decodable, Encodable, Rlp};
-use std::collections::HashSet;
+use rlp::Encodable;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -31,19 +33,22 @@ pub struct Extension {
     config: Config,
     routing_table: Arc<RoutingTable>,
     api: Box<dyn Api>,
-    nodes: HashSet<NodeId>, // FIXME: Find the optimized data structure for it
+    /// The connected nodes and their versions.
+    nodes: HashMap<NodeId, u64>, // FIXME: Find the optimized data structure for it
     use_kademlia: bool,
 }
 
 impl Extension {
     pub fn new(routing_table: Arc<RoutingTable>, config: Config, api: Box<dyn Api>, use_kademlia: bool) -> Self {
         if use_kademlia {
-            cinfo!(DISCOVERY, "Discovery starts with kademlia option");
+            cinfo!(DISCOVERY, "Discovery starts with kademlia option in {} mode", config.mode);
         } else {
-            cinfo!(DISCOVERY, "Discovery starts with unstructured option");
+            cinfo!(DISCOVERY, "Discovery starts with unstructured option in {} mode", config.mode);
+        }
+        if config.mode == Mode::Full {
+            api.set_timer(REFRESH_TOKEN, Duration::from_millis(u64::from(config.t_refresh)))
+                .expect("Refresh must be registered");
         }
-        api.set_timer(REFRESH_TOKEN, Duration::from_millis(u64::from(config.t_refresh)))
-            .expect("Refresh must be registered");
         Self {
             config,
             routing_table,
@@ -52,10 +57,56 @@ impl Extension {
             use_kademlia,
         }
     }
+
+    fn request_addresses(&self, node: &NodeId, version: u64) {
+        if self.config.mode != Mode::Full {
+            return
+        }
+        let request = Arc::new(Message::Request(self.config.bucket_size).rlp_bytes());
+        if version >= REQUEST_VERSION {
+            self.api.request(node, request, REQUEST_TIMEOUT);
+        } else {
+            self.api.send(node, request);
+        }
+    }
+
+    fn addresses_for(&self, node: &NodeId, len: u8) -> Vec<SocketAddr> {
+        if self.use_kademlia {
+            let datum = address_to_hash(&node.into_addr());
+            let mut addresses = self
+                .routing_table
+                .reachable_addresses(&node.into_addr())
+                .into_iter()
+                .map(|address| KademliaId::new(address, &datum))
+                .collect::<Vec<_>>();
+
+            addresses.sort_unstable();
+
+            addresses.into_iter().map(From::from).take(::std::cmp::min(self.config.bucket_size, len) as usize).collect()
+        } else {
+            let mut addresses = self.routing_table.reachable_addresses(&node.into_addr());
+            addresses.shuffle(&mut thread_rng());
+            addresses.into_iter().take(::std::cmp::min(self.config.bucket_size, len) as usize).collect()
+        }
+    }
+}
+
+fn decode(node: &NodeId, message: &[u8]) -> Option<Message> {
+    match decode_message::<Message>(message) {
+        Ok(message) => Some(message),
+        Err(err) => {
+            cwarn!(DISCOVERY, "Invalid message from {} : {:?}", node, err);
+            None
+        }
+    }
 }
 
 const REFRESH_TOKEN: TimerToken = 0;
 
+/// The peers of this version query the addresses with `Api::request`.
+const REQUEST_VERSION: u64 = 1;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
 impl NetworkExtension<Never> for Extension {
     fn name() -> &'static str {
         "discovery"
@@ -66,13 +117,17 @@ impl NetworkExtension<Never> for Extension {
     }
 
     fn versions() -> &'static [u64] {
-        const VERSIONS: &[u64] = &[0];
+        const VERSIONS: &[u64] = &[0, REQUEST_VERSION];
         &VERSIONS
     }
 
-    fn on_node_added(&mut self, node: &NodeId, _version: u64) {
-        self.nodes.insert(*node);
-        self.api.send(&node, Arc::new(Message::Request(self.config.bucket_size).rlp_bytes()));
+    fn request_version() -> Option<u64> {
+        Some(REQUEST_VERSION)
+    }
+
+    fn on_node_added(&mut self, node: &NodeId, version: u64) {
+        self.nodes.insert(*node, version);
+        self.request_addresses(node, version);
     }
 
     fn on_node_removed(&mut self, node: &NodeId) {
@@ -80,54 +135,163 @@ impl NetworkExtension<Never> for Extension {
     }
 
     fn on_message(&mut self, node: &NodeId, message: &[u8]) {
-        let message = match Message::decode(&Rlp::new(message)) {
+        match decode(node, message) {
+            Some(Message::Request(len)) => {
+                let response = Arc::new(Message::Response(self.addresses_for(node, len)).rlp_bytes());
+                self.api.send(&node, response);
+            }
+            Some(Message::Response(addresses)) => {
+                if self.config.mode != Mode::Full {
+                    cdebug!(DISCOVERY, "{} sent the addresses that are not requested", node);
+                    return
+                }
+                self.routing_table.touch_addresses(addresses);
+            }
+            None => {}
+        }
+    }
+
+    fn on_request(&mut self, node: &NodeId, request: RequestId, message: &[u8]) {
+        match decode(node, message) {
+            Some(Message::Request(len)) => {
+                let response = Arc::new(Message::Response(self.addresses_for(node, len)).rlp_bytes());
+                self.api.respond(&node, request, response);
+            }
+            Some(Message::Response(_)) => {
+                cwarn!(DISCOVERY, "{} sent a response as a request", node);
+            }
+            None => {}
+        }
+    }
+
+    fn on_response(&mut self, node: &NodeId, _request: RequestId, result: Result<&[u8], RequestError>) {
+        let message = match result {
             Ok(message) => message,
             Err(err) => {
-                cwarn!(DISCOVERY, "Invalid message from {} : {:?}", node, err);
+                cdebug!(DISCOVERY, "Cannot get the addresses from {}: {}", node, err);
                 return
             }
         };
-        match message {
-            Message::Request(len) => {
-                let addresses = if self.use_kademlia {
-                    let datum = address_to_hash(&node.into_addr());
-                    let mut addresses = self
-                        .routing_table
-                        .reachable_addresses(&node.into_addr())
-                        .into_iter()
-                        .map(|address| KademliaId::new(address, &datum))
-                        .collect::<Vec<_>>();
-
-                    addresses.sort_unstable();
-
-                    addresses
-                        .into_iter()
-                        .map(From::from)
-                        .take(::std::cmp::min(self.config.bucket_size, len) as usize)
-                        .collect()
-                } else {
-                    let mut addresses = self.routing_table.reachable_addresses(&node.into_addr());
-                    addresses.shuffle(&mut thread_rng());
-                    addresses.into_iter().take(::std::cmp::min(self.config.bucket_size, len) as usize).collect()
-                };
-                let response = Arc::new(Message::Response(addresses).rlp_bytes());
-                self.api.send(&node, response);
-            }
-            Message::Response(addresses) => {
+        match decode(node, message) {
+            Some(Message::Response(addresses)) => {
                 self.routing_table.touch_addresses(addresses);
             }
+            Some(Message::Request(_)) => {
+                cwarn!(DISCOVERY, "{} responded with a request", node);
+            }
+            None => {}
         }
     }
 
     fn on_timeout(&mut self, timer: TimerToken) {
         match timer {
             REFRESH_TOKEN => {
-                let request = Arc::new(Message::Request(self.config.bucket_size).rlp_bytes());
-                for node in &self.nodes {
-                    self.api.send(node, Arc::clone(&request));
+                for (node, version) in &self.nodes {
+                    self.request_addresses(node, *version);
                 }
             }
             _ => unreachable!(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::K;
+    use cnetwork::NetworkExtensionResult;
+    use parking_lot::Mutex;
+    use primitives::Bytes;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[derive(Debug, PartialEq)]
+    enum Call {
+        Send(NodeId, Message),
+        Request(NodeId, Message),
+        Respond(NodeId, RequestId, Message),
+        SetTimer(TimerToken),
+    }
+
+    #[derive(Clone, Default)]
+    struct TestApi {
+        calls: Arc<Mutex<Vec<Call>>>,
+    }
+
+    impl TestApi {
+        fn take_calls(&self) -> Vec<Call> {
+            ::std::mem::replace(&mut *self.calls.lock(), Vec::new())
+        }
+    }
+
+    fn message(bytes: &[u8]) -> Message {
+        rlp::decode(bytes).unwrap()
+    }
+
+    impl Api for TestApi {
+        fn send(&self, node: &NodeId, message_bytes: Arc<Bytes>) {
+            self.calls.lock().push(Call::Send(*node, message(&message_bytes)));
+        }
+
+        fn request(&self, node: &NodeId, message_bytes: Arc<Bytes>, _timeout: Duration) -> RequestId {
+            self.calls.lock().push(Call::Request(*node, message(&message_bytes)));
+            0
+        }
+
+        fn respond(&self, node: &NodeId, request: RequestId, message_bytes: Arc<Bytes>) {
+            self.calls.lock().push(Call::Respond(*node, request, message(&message_bytes)));
+        }
+
+        fn set_timer(&self, timer: TimerToken, _duration: Duration) -> NetworkExtensionResult<()> {
+            self.calls.lock().push(Call::SetTimer(timer));
+            Ok(())
+        }
+
+        fn set_timer_once(&self, _timer: TimerToken, _duration: Duration) -> NetworkExtensionResult<()> {
+            unimplemented!()
+        }
+
+        fn clear_timer(&self, _timer: TimerToken) -> NetworkExtensionResult<()> {
+            unimplemented!()
+        }
+    }
+
+    fn extension(mode: Mode) -> (Extension, TestApi) {
+        let api = TestApi::default();
+        let extension =
+            Extension::new(RoutingTable::new(), Config::new(None, None, Some(mode)), Box::new(api.clone()), false);
+        (extension, api)
+    }
+
+    fn node() -> NodeId {
+        NodeId::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 3485)
+    }
+
+    #[test]
+    fn full_mode_queries_the_added_nodes() {
+        let (mut extension, api) = extension(Mode::Full);
+        assert_eq!(vec![Call::SetTimer(REFRESH_TOKEN)], api.take_calls());
+
+        extension.on_node_added(&node(), REQUEST_VERSION);
+        extension.on_timeout(REFRESH_TOKEN);
+        assert_eq!(
+            vec![Call::Request(node(), Message::Request(K)), Call::Request(node(), Message::Request(K))],
+            api.take_calls()
+        );
+    }
+
+    #[test]
+    fn passive_mode_only_answers_the_queries() {
+        let (mut extension, api) = extension(Mode::Passive);
+        extension.on_node_added(&node(), REQUEST_VERSION);
+        extension.on_node_added(&NodeId::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 3486), 0);
+        extension.on_message(&node(), &Message::Response(vec![]).rlp_bytes());
+        assert_eq!(Vec::<Call>::new(), api.take_calls());
+
+        extension.on_request(&node(), 7, &Message::Request(3).rlp_bytes());
+        extension.on_message(&node(), &Message::Request(3).rlp_bytes());
+        assert_eq!(
+            vec![Call::Respond(node(), 7, Message::Response(vec![])), Call::Send(node(), Message::Response(vec![]))],
+            api.take_calls()
+        );
+    }
+}
@@ -34,11 +34,14 @@ extern crate codechain_timer as ctimer;
 
 mod config;
 mod extension;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
 mod message;
 mod node_id;
 
 const K: u8 = 16;
 const T_REFRESH: u32 = 60_000;
 
-pub use crate::config::Config;
+pub use crate::config::{Config, Mode};
 pub use crate::extension::Extension as Discovery;
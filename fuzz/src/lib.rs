@@ -0,0 +1,57 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The bodies of the fuzz targets.
+//! They are plain functions so that `tests/corpus.rs` can run them without libFuzzer.
+
+extern crate codechain_core as ccore;
+extern crate codechain_discovery as cdiscovery;
+extern crate codechain_network as cnetwork;
+extern crate rlp;
+
+use ccore::{encoded, UnverifiedTransaction};
+use rlp::Rlp;
+
+pub use cdiscovery::fuzzing::discovery_message;
+pub use cnetwork::fuzzing::p2p_message;
+
+/// The number of the seal fields of a Tendermint header.
+const SEAL_FIELDS: usize = 4;
+
+pub fn unverified_transaction(data: &[u8]) {
+    if let Ok(tx) = Rlp::new(data).as_val::<UnverifiedTransaction>() {
+        tx.hash();
+        let _ = tx.verify_basic();
+    }
+}
+
+/// A header accepted by `new_checked` must not panic later.
+pub fn encoded_header(data: &[u8]) {
+    if let Ok(header) = encoded::Header::new_checked(data.to_vec(), SEAL_FIELDS) {
+        header.decode();
+        header.number();
+        header.seal();
+    }
+}
+
+/// A block accepted by `new_checked` must not panic later.
+pub fn encoded_block(data: &[u8]) {
+    if let Ok(block) = encoded::Block::new_checked(data.to_vec(), SEAL_FIELDS) {
+        block.decode();
+        block.transaction_hashes();
+        block.seal();
+    }
+}
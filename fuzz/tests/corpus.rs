@@ -0,0 +1,87 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Runs every fuzz target over its seed corpus and simple mutations of the seeds.
+//! It doesn't need libFuzzer, so `cargo test` runs it on the stable compiler.
+
+extern crate codechain_core as ccore;
+extern crate codechain_fuzz;
+extern crate rlp;
+
+use ccore::{encoded, UnverifiedTransaction};
+use std::fs;
+use std::path::Path;
+
+fn seeds(target: &str) -> Vec<Vec<u8>> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus").join(target);
+    let seeds: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("Cannot read {}: {}", dir.display(), err))
+        .map(|entry| fs::read(entry.unwrap().path()).unwrap())
+        .collect();
+    assert!(!seeds.is_empty(), "The corpus of {} is empty", target);
+    seeds
+}
+
+/// Runs the target over the seeds, their truncations and the seeds with a byte flipped.
+fn run(target: &str, fuzz: fn(&[u8])) {
+    for seed in seeds(target) {
+        fuzz(&seed);
+        for len in 0..seed.len() {
+            fuzz(&seed[..len]);
+        }
+        for i in 0..seed.len() {
+            for &mask in &[0x01, 0x80, 0xff] {
+                let mut mutated = seed.clone();
+                mutated[i] ^= mask;
+                fuzz(&mutated);
+            }
+        }
+    }
+}
+
+#[test]
+fn unverified_transaction() {
+    for seed in seeds("unverified_transaction") {
+        rlp::decode::<UnverifiedTransaction>(&seed).unwrap();
+    }
+    run("unverified_transaction", codechain_fuzz::unverified_transaction);
+}
+
+#[test]
+fn encoded_header() {
+    for seed in seeds("encoded_header") {
+        encoded::Header::new_checked(seed, 4).unwrap();
+    }
+    run("encoded_header", codechain_fuzz::encoded_header);
+}
+
+#[test]
+fn encoded_block() {
+    for seed in seeds("encoded_block") {
+        encoded::Block::new_checked(seed, 4).unwrap();
+    }
+    run("encoded_block", codechain_fuzz::encoded_block);
+}
+
+#[test]
+fn discovery_message() {
+    run("discovery_message", codechain_fuzz::discovery_message);
+}
+
+#[test]
+fn p2p_message() {
+    run("p2p_message", codechain_fuzz::p2p_message);
+}
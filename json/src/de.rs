@@ -0,0 +1,687 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! JSON deserialization that reports where the input was rejected.
+//!
+//! serde_json reports the line and the column of an error, which is hard to map to a field.
+//! The functions in this module parse the input first and then deserialize the parsed value,
+//! so an error carries the JSON pointer of the rejected value and a snippet of it.
+
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{
+    self, Deserialize, DeserializeOwned, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, Unexpected, VariantAccess,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+use serde_json::{self, Value};
+use std::io::Read;
+use std::{fmt, iter, slice};
+
+/// The maximum number of characters of the rejected value shown in an error.
+const SNIPPET_LENGTH: usize = 40;
+
+/// How the fields of a JSON object are matched to the fields of a struct.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Field names must match exactly.
+    /// Unknown fields are rejected by the types that deny them.
+    Strict,
+    /// Field names are matched case-insensitively and unknown fields are ignored.
+    /// Use this for files written by other programs.
+    Lenient,
+}
+
+/// Error returned when a JSON cannot be deserialized.
+#[derive(Debug)]
+pub enum Error {
+    /// The input is not a well-formed JSON.
+    Syntax(serde_json::Error),
+    /// The value at `pointer` cannot be deserialized.
+    Data {
+        /// The JSON pointer of the rejected value.
+        pointer: String,
+        /// The rejected value, truncated to a few characters.
+        snippet: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Syntax(err) => write!(f, "Invalid JSON: {}", err),
+            Error::Data {
+                pointer,
+                snippet,
+                message,
+            } => write!(f, "{} at \"{}\" (found {})", message, pointer, snippet),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub fn from_str<T>(s: &str, mode: Mode) -> Result<T, Error>
+where
+    T: DeserializeOwned, {
+    let value: Value = serde_json::from_str(s).map_err(Error::Syntax)?;
+    from_value(&value, mode)
+}
+
+pub fn from_slice<T>(v: &[u8], mode: Mode) -> Result<T, Error>
+where
+    T: DeserializeOwned, {
+    let value: Value = serde_json::from_slice(v).map_err(Error::Syntax)?;
+    from_value(&value, mode)
+}
+
+pub fn from_reader<R, T>(reader: R, mode: Mode) -> Result<T, Error>
+where
+    R: Read,
+    T: DeserializeOwned, {
+    let value: Value = serde_json::from_reader(reader).map_err(Error::Syntax)?;
+    from_value(&value, mode)
+}
+
+pub fn from_value<'de, T>(value: &'de Value, mode: Mode) -> Result<T, Error>
+where
+    T: Deserialize<'de>, {
+    let deserializer = ValueDeserializer {
+        value,
+        path: &Path::Root,
+        mode,
+    };
+    T::deserialize(deserializer).map_err(|err| {
+        let err = err.locate(&Path::Root, value);
+        let (pointer, snippet) = err.location.expect("The error is located");
+        Error::Data {
+            pointer,
+            snippet,
+            message: err.message,
+        }
+    })
+}
+
+/// The position of a value in the document, from which the JSON pointer is built.
+#[derive(Clone, Copy)]
+enum Path<'a> {
+    Root,
+    Key(&'a Path<'a>, &'a str),
+    Index(&'a Path<'a>, usize),
+}
+
+impl<'a> fmt::Display for Path<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Path::Root => Ok(()),
+            Path::Key(parent, key) => write!(f, "{}/{}", parent, key.replace('~', "~0").replace('/', "~1")),
+            Path::Index(parent, index) => write!(f, "{}/{}", parent, index),
+        }
+    }
+}
+
+fn snippet(value: &Value) -> String {
+    let json = value.to_string();
+    if json.chars().count() <= SNIPPET_LENGTH {
+        return json
+    }
+    let mut snippet: String = json.chars().take(SNIPPET_LENGTH).collect();
+    snippet.push_str("...");
+    snippet
+}
+
+fn unexpected(value: &Value) -> Unexpected {
+    match value {
+        Value::Null => Unexpected::Unit,
+        Value::Bool(b) => Unexpected::Bool(*b),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => Unexpected::Unsigned(u),
+            (None, Some(i)) => Unexpected::Signed(i),
+            (None, None) => Unexpected::Float(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => Unexpected::Str(s),
+        Value::Array(_) => Unexpected::Seq,
+        Value::Object(_) => Unexpected::Map,
+    }
+}
+
+/// The error of the internal deserializers.
+///
+/// It is located by the deserializer of the value that caused it. The deserializers of the
+/// enclosing values don't overwrite the location, so the innermost value is reported.
+#[derive(Debug)]
+struct DataError {
+    message: String,
+    location: Option<(String, String)>,
+}
+
+impl DataError {
+    fn locate(mut self, path: &Path, value: &Value) -> Self {
+        if self.location.is_none() {
+            self.location = Some((path.to_string(), snippet(value)));
+        }
+        self
+    }
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for DataError {}
+
+impl de::Error for DataError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DataError {
+            message: msg.to_string(),
+            location: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ValueDeserializer<'de, 'p> {
+    value: &'de Value,
+    path: &'p Path<'p>,
+    mode: Mode,
+}
+
+impl<'de, 'p> ValueDeserializer<'de, 'p> {
+    fn locate<T>(self, result: Result<T, DataError>) -> Result<T, DataError> {
+        result.map_err(|err| err.locate(self.path, self.value))
+    }
+
+    fn visit_array<V>(self, array: &'de [Value], visitor: V) -> Result<V::Value, DataError>
+    where
+        V: Visitor<'de>, {
+        let mut seq = SeqDeserializer {
+            iter: array.iter().enumerate(),
+            path: self.path,
+            mode: self.mode,
+        };
+        let value = visitor.visit_seq(&mut seq)?;
+        if seq.iter.len() != 0 {
+            return Err(de::Error::invalid_length(array.len(), &"fewer elements in array"))
+        }
+        Ok(value)
+    }
+}
+
+impl<'de, 'p> de::Deserializer<'de> for ValueDeserializer<'de, 'p> {
+    type Error = DataError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, DataError>
+    where
+        V: Visitor<'de>, {
+        let result = match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Number(n) => match (n.as_u64(), n.as_i64(), n.as_f64()) {
+                (Some(u), ..) => visitor.visit_u64(u),
+                (None, Some(i), _) => visitor.visit_i64(i),
+                (None, None, Some(f)) => visitor.visit_f64(f),
+                (None, None, None) => Err(de::Error::custom("unrepresentable number")),
+            },
+            Value::String(s) => visitor.visit_borrowed_str(s),
+            Value::Array(array) => self.visit_array(array, visitor),
+            Value::Object(map) => visitor.visit_map(MapDeserializer {
+                iter: map.iter(),
+                fields: None,
+                pending: None,
+                path: self.path,
+                mode: self.mode,
+            }),
+        };
+        self.locate(result)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, DataError>
+    where
+        V: Visitor<'de>, {
+        match self.value {
+            Value::Null => self.locate(visitor.visit_none()),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, DataError>
+    where
+        V: Visitor<'de>, {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DataError>
+    where
+        V: Visitor<'de>, {
+        let result = match self.value {
+            Value::Object(map) => visitor.visit_map(MapDeserializer {
+                iter: map.iter(),
+                fields: Some(fields),
+                pending: None,
+                path: self.path,
+                mode: self.mode,
+            }),
+            Value::Array(array) => self.visit_array(array, visitor),
+            _ => Err(de::Error::invalid_type(unexpected(self.value), &visitor)),
+        };
+        self.locate(result)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DataError>
+    where
+        V: Visitor<'de>, {
+        let result = match self.value {
+            Value::String(variant) => visitor.visit_enum(EnumDeserializer {
+                variant: variant.as_str(),
+                value: None,
+                path: self.path,
+                mode: self.mode,
+            }),
+            Value::Object(map) if map.len() == 1 => {
+                let (variant, value) = map.iter().next().expect("The map has one entry");
+                visitor.visit_enum(EnumDeserializer {
+                    variant: variant.as_str(),
+                    value: Some(value),
+                    path: self.path,
+                    mode: self.mode,
+                })
+            }
+            Value::Object(_) => Err(de::Error::invalid_value(Unexpected::Map, &"a map with a single key")),
+            _ => Err(de::Error::invalid_type(unexpected(self.value), &"a string or a map")),
+        };
+        self.locate(result)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map identifier ignored_any
+    }
+}
+
+struct SeqDeserializer<'de, 'p> {
+    iter: iter::Enumerate<slice::Iter<'de, Value>>,
+    path: &'p Path<'p>,
+    mode: Mode,
+}
+
+impl<'de, 'p> SeqAccess<'de> for SeqDeserializer<'de, 'p> {
+    type Error = DataError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, DataError>
+    where
+        T: DeserializeSeed<'de>, {
+        match self.iter.next() {
+            Some((index, value)) => {
+                let path = Path::Index(self.path, index);
+                seed.deserialize(ValueDeserializer {
+                    value,
+                    path: &path,
+                    mode: self.mode,
+                })
+                .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapDeserializer<'de, 'p> {
+    iter: serde_json::map::Iter<'de>,
+    /// The fields of the struct being deserialized, or `None` for a map.
+    fields: Option<&'static [&'static str]>,
+    /// The entry whose key is deserialized but whose value is not yet.
+    pending: Option<(&'de str, &'de Value)>,
+    path: &'p Path<'p>,
+    mode: Mode,
+}
+
+/// Returns the field to which `key` is deserialized, or `None` if the entry is skipped.
+fn matching_field<'a>(mode: Mode, fields: Option<&'static [&'static str]>, key: &'a str) -> Option<&'a str> {
+    let fields = match (mode, fields) {
+        (Mode::Lenient, Some(fields)) => fields,
+        _ => return Some(key),
+    };
+    if fields.iter().any(|field| *field == key) {
+        return Some(key)
+    }
+    fields.iter().find(|field| field.eq_ignore_ascii_case(key)).cloned()
+}
+
+impl<'de, 'p> MapAccess<'de> for MapDeserializer<'de, 'p> {
+    type Error = DataError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, DataError>
+    where
+        K: DeserializeSeed<'de>, {
+        for (key, value) in &mut self.iter {
+            let key = key.as_str();
+            let field = match matching_field(self.mode, self.fields, key) {
+                Some(field) => field,
+                None => continue,
+            };
+            self.pending = Some((key, value));
+            let path = Path::Key(self.path, key);
+            return seed
+                .deserialize(KeyDeserializer {
+                    key: field,
+                })
+                .map(Some)
+                .map_err(|err| err.locate(&path, value))
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, DataError>
+    where
+        V: DeserializeSeed<'de>, {
+        let (key, value) = self.pending.take().ok_or_else(|| de::Error::custom("value is missing"))?;
+        let path = Path::Key(self.path, key);
+        seed.deserialize(ValueDeserializer {
+            value,
+            path: &path,
+            mode: self.mode,
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match (self.mode, self.fields) {
+            (Mode::Lenient, Some(_)) => None,
+            _ => Some(self.iter.len()),
+        }
+    }
+}
+
+/// Deserializes an object key, which is always a string in JSON.
+///
+/// Integers are parsed from the key like serde_json does, so maps with integer keys work.
+struct KeyDeserializer<'de> {
+    key: &'de str,
+}
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, DataError>
+            where
+                V: Visitor<'de>, {
+                match self.key.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(self.key), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = DataError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, DataError>
+    where
+        V: Visitor<'de>, {
+        visitor.visit_borrowed_str(self.key)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, DataError>
+    where
+        V: Visitor<'de>, {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DataError>
+    where
+        V: Visitor<'de>, {
+        de::Deserializer::deserialize_enum(BorrowedStrDeserializer::new(self.key), name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool f32 f64 char str string bytes byte_buf option unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct EnumDeserializer<'de, 'p> {
+    variant: &'de str,
+    /// The content of the variant, or `None` for a unit variant written as a string.
+    value: Option<&'de Value>,
+    path: &'p Path<'p>,
+    mode: Mode,
+}
+
+impl<'de, 'p> EnumAccess<'de> for EnumDeserializer<'de, 'p> {
+    type Error = DataError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), DataError>
+    where
+        V: DeserializeSeed<'de>, {
+        let variant = seed.deserialize(KeyDeserializer {
+            key: self.variant,
+        })?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'p> EnumDeserializer<'de, 'p> {
+    fn content(&self, expected: &str) -> Result<&'de Value, DataError> {
+        self.value.ok_or_else(|| de::Error::invalid_type(Unexpected::UnitVariant, &expected))
+    }
+}
+
+impl<'de, 'p> VariantAccess<'de> for EnumDeserializer<'de, 'p> {
+    type Error = DataError;
+
+    fn unit_variant(self) -> Result<(), DataError> {
+        let value = match self.value {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+        let path = Path::Key(self.path, self.variant);
+        Deserialize::deserialize(ValueDeserializer {
+            value,
+            path: &path,
+            mode: self.mode,
+        })
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, DataError>
+    where
+        T: DeserializeSeed<'de>, {
+        let value = self.content("newtype variant")?;
+        let path = Path::Key(self.path, self.variant);
+        seed.deserialize(ValueDeserializer {
+            value,
+            path: &path,
+            mode: self.mode,
+        })
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, DataError>
+    where
+        V: Visitor<'de>, {
+        let value = self.content("tuple variant")?;
+        let path = Path::Key(self.path, self.variant);
+        de::Deserializer::deserialize_tuple(
+            ValueDeserializer {
+                value,
+                path: &path,
+                mode: self.mode,
+            },
+            len,
+            visitor,
+        )
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, DataError>
+    where
+        V: Visitor<'de>, {
+        let value = self.content("struct variant")?;
+        let path = Path::Key(self.path, self.variant);
+        de::Deserializer::deserialize_struct(
+            ValueDeserializer {
+                value,
+                path: &path,
+                mode: self.mode,
+            },
+            "",
+            fields,
+            visitor,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_str, Error, Mode};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(deny_unknown_fields, rename_all = "camelCase")]
+    struct Outer {
+        inner_values: Vec<Inner>,
+        #[serde(default)]
+        named: BTreeMap<String, u8>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Inner {
+        value: u8,
+    }
+
+    fn pointer_of(err: Error) -> String {
+        match err {
+            Error::Data {
+                pointer,
+                ..
+            } => pointer,
+            Error::Syntax(err) => panic!("Unexpected syntax error: {}", err),
+        }
+    }
+
+    #[test]
+    fn innermost_pointer_is_reported() {
+        let err = from_str::<Outer>(r#"{"innerValues": [{"value": 1}, {"value": 256}]}"#, Mode::Strict).unwrap_err();
+        assert_eq!("/innerValues/1/value", pointer_of(err));
+    }
+
+    #[test]
+    fn unknown_field_is_reported_at_the_field() {
+        let err = from_str::<Outer>(r#"{"innerValues": [{"value": 1, "extra": 2}]}"#, Mode::Strict).unwrap_err();
+        assert_eq!("/innerValues/0/extra", pointer_of(err));
+    }
+
+    #[test]
+    fn missing_field_is_reported_at_the_object() {
+        let err = from_str::<Outer>(r#"{"innerValues": [{}]}"#, Mode::Strict).unwrap_err();
+        assert_eq!("/innerValues/0", pointer_of(err));
+    }
+
+    #[test]
+    fn keys_are_escaped_in_the_pointer() {
+        let err = from_str::<Outer>(r#"{"innerValues": [], "named": {"a/b~c": -1}}"#, Mode::Strict).unwrap_err();
+        assert_eq!("/named/a~1b~0c", pointer_of(err));
+    }
+
+    #[test]
+    fn snippet_is_truncated() {
+        let long = "f".repeat(100);
+        let json = format!(r#"{{"innerValues": [{{"value": "{}"}}]}}"#, long);
+        match from_str::<Outer>(&json, Mode::Strict).unwrap_err() {
+            Error::Data {
+                snippet,
+                ..
+            } => assert_eq!(format!("\"{}...", "f".repeat(39)), snippet),
+            Error::Syntax(err) => panic!("Unexpected syntax error: {}", err),
+        }
+    }
+
+    #[test]
+    fn syntax_error() {
+        match from_str::<Outer>(r#"{"innerValues": "#, Mode::Strict).unwrap_err() {
+            Error::Syntax(_) => {}
+            err => panic!("Unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn lenient_mode_ignores_unknown_fields_and_case() {
+        let json = r#"{"InnerValues": [{"VALUE": 1, "extra": 2}], "comment": "written by another program"}"#;
+        assert!(from_str::<Outer>(json, Mode::Strict).is_err());
+        assert_eq!(
+            Outer {
+                inner_values: vec![Inner {
+                    value: 1
+                }],
+                named: Default::default(),
+            },
+            from_str::<Outer>(json, Mode::Lenient).unwrap()
+        );
+    }
+
+    #[test]
+    fn lenient_mode_keeps_map_keys() {
+        let json = r#"{"innerValues": [], "named": {"A": 1, "a": 2}}"#;
+        let outer = from_str::<Outer>(json, Mode::Lenient).unwrap();
+        assert_eq!(2, outer.named.len());
+    }
+
+    #[test]
+    fn integer_keys() {
+        let map: BTreeMap<u16, u8> = from_str(r#"{"0": 1, "65535": 2}"#, Mode::Strict).unwrap();
+        assert_eq!(Some(&2), map.get(&65535));
+        let err = from_str::<BTreeMap<u16, u8>>(r#"{"65536": 1}"#, Mode::Strict).unwrap_err();
+        assert_eq!("/65536", pointer_of(err));
+    }
+
+    #[test]
+    fn extra_array_elements_are_rejected() {
+        let err = from_str::<(u8, u8)>("[1, 2, 3]", Mode::Strict).unwrap_err();
+        assert_eq!("", pointer_of(err));
+    }
+}
@@ -22,6 +22,7 @@ use rustc_hex::ToHex;
 use serde::de::{Error, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::mem;
 use std::str::FromStr;
 
 macro_rules! impl_hash {
@@ -52,22 +53,39 @@ macro_rules! impl_hash {
                     type Value = $name;
 
                     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                        write!(formatter, "a 0x-prefixed hex-encoded hash")
+                        write!(formatter, "a hex-encoded hash with or without the 0x prefix")
                     }
 
                     fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
                     where
                         E: Error, {
-                        let value = match value.len() {
-                            0 => $inner::from(0),
-                            2 if value == "0x" => $inner::from(0),
-                            _ if value.starts_with("0x") => $inner::from_str(&value[2..])
-                                .map_err(|e| Error::custom(format!("Invalid hex value {}: {}", value, e).as_str()))?,
-                            _ => $inner::from_str(value)
-                                .map_err(|e| Error::custom(format!("Invalid hex value {}: {}", value, e).as_str()))?,
+                        let digits = if value.starts_with("0x") {
+                            &value[2..]
+                        } else {
+                            value
                         };
-
-                        Ok($name(value))
+                        if digits.is_empty() {
+                            return Ok($name($inner::from(0)))
+                        }
+                        if digits.len() % 2 != 0 {
+                            return Err(Error::custom(format!(
+                                "Invalid hex value {}: expected an even number of hex digits",
+                                value
+                            )))
+                        }
+                        let len = mem::size_of::<$inner>();
+                        if digits.len() != len * 2 {
+                            return Err(Error::custom(format!(
+                                "Invalid hex value {}: expected {} bytes ({} hex digits), found {} hex digits",
+                                value,
+                                len,
+                                len * 2,
+                                digits.len()
+                            )))
+                        }
+                        let hash = $inner::from_str(digits)
+                            .map_err(|e| Error::custom(format!("Invalid hex value {}: {}", value, e)))?;
+                        Ok($name(hash))
                     }
 
                     fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
@@ -114,6 +132,29 @@ mod test {
         ]);
     }
 
+    #[test]
+    fn hash_deserialization_with_and_without_prefix() {
+        let s = r#"["0x", "0x5a39ed1020c04d4d84539975b893a4e7c53eab6c2965db8bc3468093a31bc5ae"]"#;
+        let deserialized: Vec<H256> = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized, vec![
+            H256(primitives::H256::from(0)),
+            H256(primitives::H256::from("5a39ed1020c04d4d84539975b893a4e7c53eab6c2965db8bc3468093a31bc5ae")),
+        ]);
+    }
+
+    #[test]
+    fn odd_number_of_hex_digits() {
+        let s = r#""0x5a39ed1020c04d4d84539975b893a4e7c53eab6c2965db8bc3468093a31bc5a""#;
+        let err = serde_json::from_str::<H256>(s).unwrap_err();
+        assert!(err.to_string().contains("expected an even number of hex digits"), "{}", err);
+    }
+
+    #[test]
+    fn wrong_number_of_bytes() {
+        let err = serde_json::from_str::<H256>(r#""5a39ed""#).unwrap_err();
+        assert!(err.to_string().contains("expected 32 bytes (64 hex digits), found 6 hex digits"), "{}", err);
+    }
+
     #[test]
     fn hash_into() {
         assert_eq!(primitives::H256::from(0), H256(primitives::H256::from(0)).into());
@@ -23,6 +23,7 @@ extern crate serde_json;
 extern crate serde_derive;
 
 pub mod bytes;
+pub mod de;
 pub mod hash;
 pub mod scheme;
 pub mod uint;
@@ -18,6 +18,7 @@ use crate::uint::Uint;
 
 /// Scheme account.
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Account {
     /// Balance.
     pub balance: Option<Uint>,
@@ -0,0 +1,55 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::uint::Uint;
+use ckey::PlatformAddress;
+
+/// An approver group created in the genesis state.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApproverGroup {
+    pub members: Vec<PlatformAddress>,
+    pub threshold: Uint,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ckey::PlatformAddress;
+    use serde_json;
+
+    use super::*;
+
+    #[test]
+    fn approver_group_deserialization() {
+        let s = r#"{
+            "members": ["tccq8vapdlstar6ghmqgczp6j2e83njsqq0tsvaxm9u", "tccq8txq9uafdg8y2de9m2tdkhsfsj3m9nluq94hyan"],
+            "threshold": 2
+        }"#;
+        let group: ApproverGroup = serde_json::from_str(s).unwrap();
+        assert_eq!(
+            ApproverGroup {
+                members: vec![
+                    PlatformAddress::from_str("tccq8vapdlstar6ghmqgczp6j2e83njsqq0tsvaxm9u").unwrap(),
+                    PlatformAddress::from_str("tccq8txq9uafdg8y2de9m2tdkhsfsj3m9nluq94hyan").unwrap(),
+                ],
+                threshold: 2.into(),
+            },
+            group
+        );
+    }
+}
@@ -17,6 +17,7 @@
 use crate::uint::Uint;
 
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 pub struct BlakePoWParams {
     /// Block reward.
@@ -27,6 +28,7 @@ pub struct BlakePoWParams {
 }
 
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct BlakePoW {
     pub params: BlakePoWParams,
 }
@@ -17,6 +17,7 @@
 use crate::uint::Uint;
 
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 pub struct CuckooParams {
     /// Block reward.
@@ -30,6 +31,7 @@ pub struct CuckooParams {
 }
 
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Cuckoo {
     pub params: CuckooParams,
 }
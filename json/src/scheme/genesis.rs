@@ -22,6 +22,7 @@ use ckey::PlatformAddress;
 
 /// Scheme genesis.
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 pub struct Genesis {
     /// Seal.
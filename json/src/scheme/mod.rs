@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod account;
+mod approver_group;
 mod blake_pow;
 mod cuckoo;
 mod engine;
@@ -29,18 +30,21 @@ mod simple_poa;
 mod solo;
 mod state;
 mod tendermint;
+mod validation;
 
 pub use self::account::Account;
+pub use self::approver_group::ApproverGroup;
 pub use self::blake_pow::{BlakePoW, BlakePoWParams};
 pub use self::cuckoo::{Cuckoo, CuckooParams};
 pub use self::engine::Engine;
 pub use self::genesis::Genesis;
 pub use self::null_engine::{NullEngine, NullEngineParams};
 pub use self::params::Params;
-pub use self::scheme::Scheme;
+pub use self::scheme::{LoadError, Scheme};
 pub use self::seal::{Seal, TendermintSeal};
 pub use self::shard::Shard;
 pub use self::simple_poa::{SimplePoA, SimplePoAParams};
 pub use self::solo::{Solo, SoloParams};
 pub use self::state::{Accounts, Shards};
 pub use self::tendermint::{Tendermint, TendermintParams};
+pub use self::validation::{validate, Violation};
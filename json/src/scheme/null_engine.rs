@@ -18,6 +18,7 @@ use crate::uint::Uint;
 
 /// Authority params deserialization.
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 pub struct NullEngineParams {
     /// Block reward.
@@ -26,6 +27,7 @@ pub struct NullEngineParams {
 
 /// Null engine descriptor
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct NullEngine {
     pub params: NullEngineParams,
 }
@@ -15,10 +15,11 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::uint::Uint;
-use ckey::NetworkId;
+use ckey::{NetworkId, PlatformAddress};
 
 /// Scheme params.
 #[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 pub struct Params {
     /// Maximum size of extra data.
@@ -69,6 +70,36 @@ pub struct Params {
     /// A monotonically increasing number to denote the consensus version.
     /// It is increased when we fork.
     pub era: Option<Uint>,
+
+    pub min_revoke_regular_key_cost: Option<Uint>,
+
+    /// Maximum size of the memo of Pay.
+    pub max_pay_memo_size: Option<Uint>,
+    /// Additional cost of Pay for each byte of the memo.
+    pub min_pay_memo_byte_cost: Option<Uint>,
+
+    /// The costs of the shard ownership offer. They fall back to minSetShardOwnersCost.
+    pub min_offer_shard_ownership_cost: Option<Uint>,
+    pub min_accept_shard_ownership_cost: Option<Uint>,
+    pub min_cancel_shard_ownership_offer_cost: Option<Uint>,
+    /// Maximum number of blocks that a shard ownership offer stays open.
+    pub max_shard_ownership_offer_period: Option<Uint>,
+
+    /// The shares of the minimum fees that go to the block author and the treasury, in basis points.
+    /// The rest is distributed to the stakeholders.
+    pub fee_author_share: Option<Uint>,
+    pub fee_treasury_share: Option<Uint>,
+    pub fee_treasury: Option<PlatformAddress>,
+    /// Who takes the fee left over by rounding: 0 for the block author, 1 for the treasury.
+    pub fee_remainder_rule: Option<Uint>,
+
+    /// Falls back to minChangeAssetSchemeCost.
+    pub min_create_approver_group_cost: Option<Uint>,
+    /// Maximum number of the members of an approver group.
+    pub max_approver_group_size: Option<Uint>,
+
+    /// The validators who missed more than this percentage of their precommits in a term are jailed.
+    pub max_missed_precommit_percentage: Option<Uint>,
 }
 
 #[cfg(test)]
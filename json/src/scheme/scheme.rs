@@ -14,13 +14,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::{Accounts, Engine, Genesis, Params, Shards};
+use super::validation::{validate, Violation};
+use super::{Accounts, ApproverGroup, Engine, Genesis, Params, Shards};
+use crate::de::{self, Mode};
 use serde_json;
-use serde_json::Error;
+use std::fmt;
 use std::io::Read;
 
 /// Scheme deserialization.
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 pub struct Scheme {
     /// Scheme name.
@@ -36,23 +39,148 @@ pub struct Scheme {
     /// Genesis state.
     pub accounts: Accounts,
     pub shards: Shards,
+    /// Approver groups created in the genesis state.
+    pub approver_groups: Option<Vec<ApproverGroup>>,
     /// Boot nodes.
     pub nodes: Option<Vec<String>>,
 }
 
+/// Error returned when a scheme cannot be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The input is not a well-formed JSON.
+    Json(serde_json::Error),
+    /// The JSON does not describe a valid scheme.
+    Invalid(Vec<Violation>),
+    /// The JSON passed the validation but cannot be deserialized into a scheme.
+    Data(de::Error),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Json(err) => write!(f, "Invalid JSON: {}", err),
+            LoadError::Invalid(violations) => {
+                write!(f, "Invalid scheme ({} errors):", violations.len())?;
+                for violation in violations {
+                    write!(f, "\n  {}", violation)?;
+                }
+                Ok(())
+            }
+            LoadError::Data(err) => write!(f, "Invalid scheme: {}", err),
+        }
+    }
+}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(err: serde_json::Error) -> Self {
+        LoadError::Json(err)
+    }
+}
+
 impl Scheme {
     /// Loads test from json.
-    pub fn load<R>(reader: R) -> Result<Self, Error>
+    ///
+    /// The JSON is validated before deserialization so that every violation is reported at once.
+    /// Unknown fields are rejected.
+    pub fn load<R>(reader: R) -> Result<Self, LoadError>
     where
         R: Read, {
-        serde_json::from_reader(reader)
+        let value: serde_json::Value = serde_json::from_reader(reader)?;
+        let violations = validate(&value);
+        if !violations.is_empty() {
+            return Err(LoadError::Invalid(violations))
+        }
+        de::from_value(&value, Mode::Strict).map_err(LoadError::Data)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::Scheme;
-    use serde_json;
+    use crate::de::{self, Mode};
+    use serde_json::{self, json, Value};
+
+    const SOLO: &str = include_str!("../../../core/res/solo.json");
+
+    /// Returns the solo scheme with `value` inserted at `pointer`.
+    fn fixture(pointer: &str, value: Value) -> Value {
+        let mut scheme: Value = serde_json::from_str(SOLO).unwrap();
+        let (parent, key) = pointer.split_at(pointer.rfind('/').unwrap());
+        scheme.pointer_mut(parent).unwrap().as_object_mut().unwrap().insert(key[1..].to_string(), value);
+        scheme
+    }
+
+    fn rejected_at(scheme: &Value) -> String {
+        match de::from_value::<Scheme>(scheme, Mode::Strict).unwrap_err() {
+            de::Error::Data {
+                pointer,
+                ..
+            } => pointer,
+            err => panic!("Unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn bundled_schemes_are_loaded() {
+        let schemes = [
+            include_str!("../../../core/res/beagle.json"),
+            include_str!("../../../core/res/blake_pow.json"),
+            include_str!("../../../core/res/corgi.json"),
+            include_str!("../../../core/res/cuckoo.json"),
+            include_str!("../../../core/res/husky.json"),
+            include_str!("../../../core/res/mainnet.json"),
+            include_str!("../../../core/res/null.json"),
+            include_str!("../../../core/res/saluki.json"),
+            include_str!("../../../core/res/simple_poa.json"),
+            include_str!("../../../core/res/solo.json"),
+            include_str!("../../../core/res/tendermint.json"),
+        ];
+        for scheme in schemes.iter() {
+            if let Err(err) = Scheme::load(scheme.as_bytes()) {
+                panic!("{}", err);
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_param_is_rejected() {
+        let scheme = fixture("/params/minPayCostt", json!(10));
+        assert_eq!("/params/minPayCostt", rejected_at(&scheme));
+    }
+
+    #[test]
+    fn unknown_account_field_is_rejected() {
+        let scheme = fixture("/accounts/tccqyqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqyca3rwt/nonce", json!("0"));
+        assert_eq!("/accounts/tccqyqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqyca3rwt/nonce", rejected_at(&scheme));
+    }
+
+    #[test]
+    fn odd_length_hash_is_rejected() {
+        let scheme = fixture("/genesis/parentHash", json!("0x000"));
+        assert_eq!("/genesis/parentHash", rejected_at(&scheme));
+        let err = de::from_value::<Scheme>(&scheme, Mode::Strict).unwrap_err();
+        assert!(err.to_string().contains("expected an even number of hex digits"), "{}", err);
+    }
+
+    #[test]
+    fn invalid_shard_owner_is_rejected() {
+        let scheme = fixture("/shards/0/owners", json!(["tccq8vapdlstar6ghmqgczp6j2e83njsqq0tsvaxm9"]));
+        assert_eq!("/shards/0/owners/0", rejected_at(&scheme));
+    }
+
+    #[test]
+    fn shard_id_out_of_range_is_rejected() {
+        let scheme = fixture("/shards/65536", json!({ "owners": [] }));
+        assert_eq!("/shards/65536", rejected_at(&scheme));
+    }
+
+    #[test]
+    fn missing_genesis_score_is_reported_at_the_genesis() {
+        let mut scheme: Value = serde_json::from_str(SOLO).unwrap();
+        scheme["genesis"].as_object_mut().unwrap().remove("score");
+        assert_eq!("/genesis", rejected_at(&scheme));
+    }
 
     #[test]
     fn spec_deserialization() {
@@ -20,6 +20,7 @@ use crate::uint::Uint;
 
 /// Tendermint seal.
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct TendermintSeal {
     /// Seal round.
     pub prev_view: Uint,
@@ -18,6 +18,7 @@ use crate::uint::Uint;
 use ckey::PlatformAddress;
 
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Shard {
     pub seq: Option<Uint>,
     pub owners: Vec<PlatformAddress>,
@@ -19,6 +19,7 @@ use ckey::Public;
 
 /// Authority params deserialization.
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 pub struct SimplePoAParams {
     /// Valid authorities
@@ -29,6 +30,7 @@ pub struct SimplePoAParams {
 
 /// Authority engine deserialization.
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SimplePoA {
     pub params: SimplePoAParams,
 }
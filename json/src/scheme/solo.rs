@@ -19,6 +19,9 @@ use ckey::PlatformAddress;
 use std::collections::HashMap;
 
 /// Solo params deserialization.
+///
+/// serde doesn't support `deny_unknown_fields` together with `flatten`,
+/// so unknown fields of the solo params are only rejected by the scheme validation.
 #[derive(Debug, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SoloParams {
@@ -37,6 +40,7 @@ pub struct SoloActionHandlersParams {
 
 /// Solo engine deserialization.
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Solo {
     pub params: SoloParams,
 }
@@ -20,6 +20,7 @@ use std::collections::HashMap;
 
 /// Tendermint params deserialization.
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 pub struct TendermintParams {
     /// Valid validators.
@@ -50,6 +51,7 @@ pub struct TendermintParams {
 
 /// Tendermint engine deserialization.
 #[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Tendermint {
     pub params: TendermintParams,
 }
@@ -0,0 +1,753 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{BlakePoWParams, CuckooParams, NullEngineParams, Params, SimplePoAParams, TendermintParams};
+use ckey::PlatformAddress;
+use primitives::U256;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+const ENGINES: [&str; 6] = ["null", "solo", "simplePoA", "tendermint", "cuckoo", "blakePoW"];
+
+/// The fields of `Params` that are not optional.
+const REQUIRED_PARAMS: [&str; 23] = [
+    "maxExtraDataSize",
+    "maxAssetSchemeMetadataSize",
+    "maxTransferMetadataSize",
+    "maxTextContentSize",
+    "networkID",
+    "minPayCost",
+    "minSetRegularKeyCost",
+    "minCreateShardCost",
+    "minSetShardOwnersCost",
+    "minSetShardUsersCost",
+    "minWrapCccCost",
+    "minCustomCost",
+    "minStoreCost",
+    "minRemoveCost",
+    "minMintAssetCost",
+    "minTransferAssetCost",
+    "minChangeAssetSchemeCost",
+    "minIncreaseAssetSupplyCost",
+    "minComposeAssetCost",
+    "minDecomposeAssetCost",
+    "minUnwrapCccCost",
+    "maxBodySize",
+    "snapshotPeriod",
+];
+
+/// The names of the fields that `T` is deserialized from.
+///
+/// The derived `Deserialize` passes them to `deserialize_struct`,
+/// so the struct stays the only list of the fields that a scheme can have.
+fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    struct FieldCollector<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de, 'a> Deserializer<'de> for FieldCollector<'a> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("the fields are collected"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf option unit unit_struct
+            newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldCollector(&mut fields));
+    fields
+}
+
+/// The fields of `T` except the required ones.
+fn optional_fields<'de, T: Deserialize<'de>>(required: &[&str]) -> Vec<&'static str> {
+    struct_fields::<T>().iter().filter(|field| !required.contains(field)).cloned().collect()
+}
+
+/// A constraint of the scheme violated at `path`, such as `engine.tendermint.params.validators[0]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Checks the structure and the values of a scheme JSON and returns every violation.
+///
+/// Serde stops at the first error and silently ignores unknown fields,
+/// so this runs on the raw JSON before it is deserialized into `Scheme`.
+pub fn validate(scheme: &Value) -> Vec<Violation> {
+    let mut validator = Validator::default();
+    validator.scheme(scheme);
+    validator.violations
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn index(path: &str, index: usize) -> String {
+    format!("{}[{}]", path, index)
+}
+
+#[derive(Default)]
+struct Validator {
+    violations: Vec<Violation>,
+}
+
+impl Validator {
+    fn report<S: Into<String>>(&mut self, path: &str, message: S) {
+        self.violations.push(Violation {
+            path: path.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn map<'a>(&mut self, path: &str, value: &'a Value) -> Option<&'a Map<String, Value>> {
+        let map = value.as_object();
+        if map.is_none() {
+            self.report(path, "expected an object");
+        }
+        map
+    }
+
+    /// Checks that the object has every required field and no unknown field.
+    fn object<'a>(
+        &mut self,
+        path: &str,
+        value: &'a Value,
+        required: &[&str],
+        optional: &[&str],
+    ) -> Option<&'a Map<String, Value>> {
+        let object = self.map(path, value)?;
+        for key in required {
+            if !object.contains_key(*key) {
+                self.report(&join(path, key), "missing field");
+            }
+        }
+        for key in object.keys() {
+            if !required.contains(&key.as_str()) && !optional.contains(&key.as_str()) {
+                self.report(&join(path, key), "unknown field");
+            }
+        }
+        Some(object)
+    }
+
+    fn array<'a>(&mut self, path: &str, value: &'a Value) -> Option<&'a Vec<Value>> {
+        let array = value.as_array();
+        if array.is_none() {
+            self.report(path, "expected an array");
+        }
+        array
+    }
+
+    fn string<'a>(&mut self, path: &str, value: &'a Value) -> Option<&'a str> {
+        let string = value.as_str();
+        if string.is_none() {
+            self.report(path, "expected a string");
+        }
+        string
+    }
+
+    fn uint(&mut self, path: &str, value: &Value) -> Option<U256> {
+        let uint = match value {
+            Value::Number(number) => number.as_u64().map(U256::from),
+            Value::String(s) if s.is_empty() || s == "0x" => Some(U256::zero()),
+            Value::String(s) if s.starts_with("0x") => U256::from_str(&s[2..]).ok(),
+            Value::String(s) => U256::from_dec_str(s).ok(),
+            _ => None,
+        };
+        if uint.is_none() {
+            self.report(path, format!("expected a hex encoded or decimal uint, found {}", value));
+        }
+        uint
+    }
+
+    fn u64(&mut self, path: &str, value: &Value) -> Option<u64> {
+        let uint = self.uint(path, value)?;
+        if uint > U256::from(u64::max_value()) {
+            self.report(path, format!("{} does not fit in 64 bits", uint));
+            return None
+        }
+        Some(uint.low_u64())
+    }
+
+    fn non_zero(&mut self, path: &str, value: &Value) {
+        if let Some(0) = self.u64(path, value) {
+            self.report(path, "must not be zero");
+        }
+    }
+
+    /// Checks a hex string of `len` bytes. Any length is allowed if `len` is `None`.
+    fn hex(&mut self, path: &str, value: &Value, len: Option<usize>) {
+        let s = match self.string(path, value) {
+            Some(s) => s,
+            None => return,
+        };
+        let digits = if s.starts_with("0x") {
+            &s[2..]
+        } else {
+            s
+        };
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            self.report(path, format!("{:?} is not hex encoded", s));
+            return
+        }
+        // An odd number of digits is zero-padded by `Bytes`, so it is only an error for a fixed length.
+        if let Some(len) = len {
+            if digits.len() != len * 2 {
+                self.report(
+                    path,
+                    format!("expected {} bytes ({} hex digits), found {} hex digits", len, len * 2, digits.len()),
+                );
+            }
+        }
+    }
+
+    fn address(&mut self, path: &str, address: &str) {
+        if let Err(err) = PlatformAddress::from_str(address) {
+            self.report(path, format!("invalid address {:?}: {}", address, err));
+        }
+    }
+
+    fn address_value(&mut self, path: &str, value: &Value) {
+        if let Some(address) = self.string(path, value) {
+            self.address(path, address);
+        }
+    }
+
+    fn scheme(&mut self, value: &Value) {
+        let required = ["name", "engine", "params", "genesis", "accounts", "shards"];
+        let scheme = match self.object("", value, &required, &["dataDir", "nodes", "approverGroups"]) {
+            Some(scheme) => scheme,
+            None => return,
+        };
+        for (key, value) in scheme {
+            match key.as_str() {
+                "name" | "dataDir" => {
+                    self.string(key, value);
+                }
+                "engine" => self.engine(key, value),
+                "params" => self.params(key, value),
+                "genesis" => self.genesis(key, value),
+                "accounts" => self.accounts(key, value),
+                "shards" => self.shards(key, value),
+                "approverGroups" => self.approver_groups(key, value),
+                "nodes" => {
+                    if let Some(nodes) = self.array(key, value) {
+                        for (i, node) in nodes.iter().enumerate() {
+                            self.string(&index(key, i), node);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn engine(&mut self, path: &str, value: &Value) {
+        let engine = match self.map(path, value) {
+            Some(engine) => engine,
+            None => return,
+        };
+        if engine.len() != 1 {
+            self.report(path, format!("expected exactly one of {}", ENGINES.join(", ")));
+            return
+        }
+        let (name, value) = engine.iter().next().expect("The engine has one field");
+        let path = join(path, name);
+        let (required, optional): (&[&str], Vec<&str>) = match name.as_str() {
+            "null" => (&[], optional_fields::<NullEngineParams>(&[])),
+            // The solo params flatten the action handler params, so their fields are not listed by serde.
+            "solo" => (&[], vec!["blockReward", "hit", "genesisStakes"]),
+            "simplePoA" => (&["validators"], optional_fields::<SimplePoAParams>(&["validators"])),
+            "tendermint" => (&["validators"], optional_fields::<TendermintParams>(&["validators"])),
+            "cuckoo" => (&[], optional_fields::<CuckooParams>(&[])),
+            "blakePoW" => (&[], optional_fields::<BlakePoWParams>(&[])),
+            _ => {
+                self.report(&path, format!("unknown engine; expected one of {}", ENGINES.join(", ")));
+                return
+            }
+        };
+
+        let params = match self.object(&path, value, &["params"], &[]).and_then(|engine| engine.get("params")) {
+            Some(params) => params,
+            None => return,
+        };
+        let path = join(&path, "params");
+        let params = match self.object(&path, params, required, &optional) {
+            Some(params) => params,
+            None => return,
+        };
+        for (key, value) in params {
+            let path = join(&path, key);
+            match key.as_str() {
+                "validators" => self.validators(&path, value),
+                "genesisStakes" => self.genesis_stakes(&path, value),
+                "hit" => {
+                    self.map(&path, value);
+                }
+                key if key.starts_with("timeout") => self.non_zero(&path, value),
+                key if optional.contains(&key) => {
+                    self.uint(&path, value);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn validators(&mut self, path: &str, value: &Value) {
+        let validators = match self.array(path, value) {
+            Some(validators) => validators,
+            None => return,
+        };
+        if validators.is_empty() {
+            self.report(path, "expected at least one validator");
+        }
+        for (i, validator) in validators.iter().enumerate() {
+            // A validator is identified by its public key.
+            self.hex(&index(path, i), validator, Some(64));
+        }
+    }
+
+    fn genesis_stakes(&mut self, path: &str, value: &Value) {
+        let stakes = match self.map(path, value) {
+            Some(stakes) => stakes,
+            None => return,
+        };
+        for (address, stake) in stakes {
+            let path = join(path, address);
+            self.address(&path, address);
+            if stake.as_u64().is_none() {
+                self.report(&path, format!("expected a 64 bit unsigned integer, found {}", stake));
+            }
+        }
+    }
+
+    fn params(&mut self, path: &str, value: &Value) {
+        let optional = optional_fields::<Params>(&REQUIRED_PARAMS);
+        let params = match self.object(path, value, &REQUIRED_PARAMS, &optional) {
+            Some(params) => params,
+            None => return,
+        };
+        for (key, value) in params {
+            let path = join(path, key);
+            if key == "networkID" {
+                if let Some(network_id) = self.string(&path, value) {
+                    if network_id.len() != 2 {
+                        self.report(&path, format!("expected 2 characters, found {:?}", network_id));
+                    }
+                }
+            } else if key == "feeTreasury" {
+                self.address_value(&path, value);
+            } else if REQUIRED_PARAMS.contains(&key.as_str()) || optional.contains(&key.as_str()) {
+                self.uint(&path, value);
+            }
+        }
+    }
+
+    fn genesis(&mut self, path: &str, value: &Value) {
+        let optional = ["author", "timestamp", "parentHash", "transactionsRoot", "stateRoot", "extraData"];
+        let genesis = match self.object(path, value, &["seal", "score"], &optional) {
+            Some(genesis) => genesis,
+            None => return,
+        };
+        for (key, value) in genesis {
+            let path = join(path, key);
+            match key.as_str() {
+                "seal" => self.seal(&path, value),
+                "score" => {
+                    self.uint(&path, value);
+                }
+                "author" => self.address_value(&path, value),
+                "timestamp" => {
+                    self.u64(&path, value);
+                }
+                "parentHash" | "transactionsRoot" | "stateRoot" => self.hex(&path, value, Some(32)),
+                "extraData" => self.hex(&path, value, None),
+                _ => {}
+            }
+        }
+    }
+
+    fn seal(&mut self, path: &str, value: &Value) {
+        let seal = match self.map(path, value) {
+            Some(seal) => seal,
+            None => return,
+        };
+        if seal.len() != 1 {
+            self.report(path, "expected exactly one of generic, tendermint");
+            return
+        }
+        let (kind, value) = seal.iter().next().expect("The seal has one field");
+        let path = join(path, kind);
+        match kind.as_str() {
+            "generic" => self.hex(&path, value, None),
+            "tendermint" => {
+                let seal = match self.object(&path, value, &["prev_view", "cur_view", "precommits"], &[]) {
+                    Some(seal) => seal,
+                    None => return,
+                };
+                for view in &["prev_view", "cur_view"] {
+                    if let Some(value) = seal.get(*view) {
+                        self.uint(&join(&path, view), value);
+                    }
+                }
+                if let Some(precommits) = seal.get("precommits") {
+                    let path = join(&path, "precommits");
+                    if let Some(precommits) = self.array(&path, precommits) {
+                        for (i, precommit) in precommits.iter().enumerate() {
+                            self.hex(&index(&path, i), precommit, Some(65));
+                        }
+                    }
+                }
+            }
+            _ => self.report(&path, "unknown seal; expected one of generic, tendermint"),
+        }
+    }
+
+    fn accounts(&mut self, path: &str, value: &Value) {
+        let accounts = match self.map(path, value) {
+            Some(accounts) => accounts,
+            None => return,
+        };
+        for (address, account) in accounts {
+            let path = join(path, address);
+            self.address(&path, address);
+            if let Some(account) = self.object(&path, account, &[], &["balance", "seq"]) {
+                for (key, value) in account {
+                    if key == "balance" || key == "seq" {
+                        self.u64(&join(&path, key), value);
+                    }
+                }
+            }
+        }
+    }
+
+    fn shards(&mut self, path: &str, value: &Value) {
+        let shards = match self.map(path, value) {
+            Some(shards) => shards,
+            None => return,
+        };
+        for (shard_id, shard) in shards {
+            let path = join(path, shard_id);
+            if shard_id.parse::<u16>().is_err() {
+                self.report(&path, format!("{:?} is not a 16 bit shard id", shard_id));
+            }
+            let shard = match self.object(&path, shard, &["owners"], &["seq", "users"]) {
+                Some(shard) => shard,
+                None => continue,
+            };
+            for (key, value) in shard {
+                let path = join(&path, key);
+                match key.as_str() {
+                    "seq" => {
+                        self.uint(&path, value);
+                    }
+                    "owners" | "users" => {
+                        if let Some(addresses) = self.array(&path, value) {
+                            for (i, address) in addresses.iter().enumerate() {
+                                self.address_value(&index(&path, i), address);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn approver_groups(&mut self, path: &str, value: &Value) {
+        let groups = match self.array(path, value) {
+            Some(groups) => groups,
+            None => return,
+        };
+        for (i, group) in groups.iter().enumerate() {
+            let path = index(path, i);
+            let group = match self.object(&path, group, &["members", "threshold"], &[]) {
+                Some(group) => group,
+                None => continue,
+            };
+            for (key, value) in group {
+                let path = join(&path, key);
+                match key.as_str() {
+                    "members" => {
+                        if let Some(addresses) = self.array(&path, value) {
+                            for (i, address) in addresses.iter().enumerate() {
+                                self.address_value(&index(&path, i), address);
+                            }
+                        }
+                    }
+                    "threshold" => {
+                        if let Some(threshold) = self.u64(&path, value) {
+                            if threshold > u64::from(u8::max_value()) {
+                                self.report(&path, format!("{} does not fit in 8 bits", threshold));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    const TENDERMINT: &str = r#"{
+        "name": "Tendermint",
+        "engine": {
+            "tendermint": {
+                "params": {
+                    "validators": [
+                        "0x4f1541fc6bdec60bf0ac6380a8e3914a469fe6cd4fa817c890d5823cfdda83932f61dc083e1b6736dadeceb5afd3fcfbac915e5fa2c9c20acf1c30b080114d7f"
+                    ],
+                    "timeoutPropose": 1000,
+                    "timeoutCommit": 1000,
+                    "blockReward": 0
+                }
+            }
+        },
+        "params": {
+            "maxExtraDataSize": "0x20",
+            "maxAssetSchemeMetadataSize": "0x0400",
+            "maxTransferMetadataSize": "0x0100",
+            "maxTextContentSize": "0x0200",
+            "networkID": "tc",
+            "minPayCost": 10,
+            "minSetRegularKeyCost": 10,
+            "minCreateShardCost": 10,
+            "minSetShardOwnersCost": 10,
+            "minSetShardUsersCost": 10,
+            "minWrapCccCost": 10,
+            "minCustomCost": 10,
+            "minStoreCost": 10,
+            "minRemoveCost": 10,
+            "minMintAssetCost": 10,
+            "minTransferAssetCost": 10,
+            "minChangeAssetSchemeCost": 10,
+            "minIncreaseAssetSupplyCost": 10,
+            "minComposeAssetCost": 10,
+            "minDecomposeAssetCost": 10,
+            "minUnwrapCccCost": 10,
+            "maxBodySize": 4194304,
+            "snapshotPeriod": 16384
+        },
+        "genesis": {
+            "seal": {
+                "tendermint": {
+                    "prev_view": "0x0",
+                    "cur_view": "0x0",
+                    "precommits": [
+                        "0x0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000"
+                    ]
+                }
+            },
+            "score": "0x20000",
+            "author": "tccqyqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqhhn9p3",
+            "timestamp": "0x00",
+            "parentHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "extraData": "0x"
+        },
+        "accounts": {
+            "tccqyqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqyca3rwt": { "balance": "1", "seq": "0" }
+        },
+        "shards": {
+            "0": { "seq": 0, "owners": ["tccqyqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqhhn9p3"] }
+        }
+    }"#;
+
+    fn fixture(pointer: &str, value: Option<Value>) -> Value {
+        let mut scheme: Value = serde_json::from_str(TENDERMINT).unwrap();
+        let (parent, key) = pointer.split_at(pointer.rfind('/').unwrap());
+        let parent = scheme.pointer_mut(parent).unwrap().as_object_mut().unwrap();
+        match value {
+            Some(value) => parent.insert(key[1..].to_string(), value),
+            None => parent.remove(&key[1..]),
+        };
+        scheme
+    }
+
+    fn paths(scheme: &Value) -> Vec<String> {
+        validate(scheme).into_iter().map(|violation| violation.path).collect()
+    }
+
+    #[test]
+    fn required_params_are_the_ones_without_defaults() {
+        let scheme: Value = serde_json::from_str(TENDERMINT).unwrap();
+        let params = scheme["params"].as_object().unwrap();
+        let mut keys: Vec<_> = params.keys().map(String::as_str).collect();
+        let mut required = REQUIRED_PARAMS.to_vec();
+        keys.sort();
+        required.sort();
+        assert_eq!(required, keys);
+        assert!(serde_json::from_value::<Params>(scheme["params"].clone()).is_ok());
+
+        for key in REQUIRED_PARAMS.iter() {
+            let mut params = params.clone();
+            params.remove(*key);
+            assert!(serde_json::from_value::<Params>(Value::Object(params)).is_err(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn struct_fields_are_the_serialized_names() {
+        let fields = struct_fields::<Params>();
+        assert!(fields.contains(&"networkID"));
+        assert!(fields.contains(&"maxMissedPrecommitPercentage"));
+        assert_eq!(vec!["timeoutPropose"], optional_fields::<TendermintParams>(&["validators"])[..1].to_vec());
+    }
+
+    #[test]
+    fn valid_scheme() {
+        let scheme: Value = serde_json::from_str(TENDERMINT).unwrap();
+        assert_eq!(Vec::<Violation>::new(), validate(&scheme));
+    }
+
+    #[test]
+    fn bundled_schemes_are_valid() {
+        let schemes = [
+            include_str!("../../../core/res/beagle.json"),
+            include_str!("../../../core/res/blake_pow.json"),
+            include_str!("../../../core/res/corgi.json"),
+            include_str!("../../../core/res/cuckoo.json"),
+            include_str!("../../../core/res/husky.json"),
+            include_str!("../../../core/res/mainnet.json"),
+            include_str!("../../../core/res/null.json"),
+            include_str!("../../../core/res/saluki.json"),
+            include_str!("../../../core/res/simple_poa.json"),
+            include_str!("../../../core/res/solo.json"),
+            include_str!("../../../core/res/tendermint.json"),
+        ];
+        for scheme in schemes.iter() {
+            let scheme: Value = serde_json::from_str(scheme).unwrap();
+            assert_eq!(Vec::<Violation>::new(), validate(&scheme), "{}", scheme["name"]);
+        }
+    }
+
+    #[test]
+    fn short_validator_public_key() {
+        let public = "0x4f1541fc6bdec60bf0ac6380a8e3914a469fe6cd4fa817c890d5823cfdda83932f61dc083e1b6736dadeceb5afd3fcfbac915e5fa2c9c20acf1c30b080114d7";
+        let scheme = fixture("/engine/tendermint/params/validators", Some(serde_json::json!([public])));
+        assert_eq!(
+            vec![Violation {
+                path: "engine.tendermint.params.validators[0]".to_string(),
+                message: "expected 64 bytes (128 hex digits), found 127 hex digits".to_string(),
+            }],
+            validate(&scheme)
+        );
+    }
+
+    #[test]
+    fn missing_validators() {
+        let scheme = fixture("/engine/tendermint/params/validators", None);
+        assert_eq!(vec!["engine.tendermint.params.validators"], paths(&scheme));
+    }
+
+    #[test]
+    fn zero_tendermint_timeout() {
+        let scheme = fixture("/engine/tendermint/params/timeoutCommit", Some(serde_json::json!("0x0")));
+        assert_eq!(vec!["engine.tendermint.params.timeoutCommit"], paths(&scheme));
+    }
+
+    #[test]
+    fn non_numeric_block_reward() {
+        let scheme = fixture("/engine/tendermint/params/blockReward", Some(serde_json::json!("ten")));
+        assert_eq!(vec!["engine.tendermint.params.blockReward"], paths(&scheme));
+    }
+
+    #[test]
+    fn balance_overflowing_u64() {
+        let scheme = fixture(
+            "/accounts/tccqyqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqyca3rwt/balance",
+            Some(serde_json::json!("18446744073709551616")),
+        );
+        assert_eq!(vec!["accounts.tccqyqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqyca3rwt.balance"], paths(&scheme));
+    }
+
+    #[test]
+    fn fee_treasury_must_be_an_address() {
+        let scheme =
+            fixture("/params/feeTreasury", Some(serde_json::json!("tccqyqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqyca3rwt")));
+        assert_eq!(Vec::<String>::new(), paths(&scheme));
+
+        let scheme = fixture("/params/feeTreasury", Some(serde_json::json!(1)));
+        assert_eq!(vec!["params.feeTreasury"], paths(&scheme));
+    }
+
+    #[test]
+    fn approver_groups() {
+        let member = "tccq8vapdlstar6ghmqgczp6j2e83njsqq0tsvaxm9u";
+        let scheme = fixture("/approverGroups", Some(serde_json::json!([{ "members": [member], "threshold": 1 }])));
+        assert_eq!(Vec::<String>::new(), paths(&scheme));
+
+        let scheme = fixture(
+            "/approverGroups",
+            Some(serde_json::json!([{ "members": [member], "threshold": 256 }, { "members": ["tcc"] }])),
+        );
+        assert_eq!(
+            vec!["approverGroups[0].threshold", "approverGroups[1].threshold", "approverGroups[1].members[0]"],
+            paths(&scheme)
+        );
+    }
+
+    #[test]
+    fn unknown_engine_field() {
+        let scheme = fixture("/engine/tendermint/params/timeoutPropse", Some(serde_json::json!(1000)));
+        let violations = validate(&scheme);
+        assert_eq!(1, violations.len());
+        assert_eq!("engine.tendermint.params.timeoutPropse: unknown field", violations[0].to_string());
+    }
+
+    #[test]
+    fn every_violation_is_reported() {
+        let mut scheme = fixture("/params/networkID", Some(serde_json::json!("tcc")));
+        scheme["genesis"]["parentHash"] = serde_json::json!("0x00");
+        scheme["shards"]["65536"] = scheme["shards"]["0"].clone();
+        scheme["genesis"]["seal"]["tendermint"]["precommits"][0] = serde_json::json!("0xzz");
+        assert_eq!(
+            vec!["genesis.parentHash", "genesis.seal.tendermint.precommits[0]", "params.networkID", "shards.65536"],
+            paths(&scheme)
+        );
+    }
+}
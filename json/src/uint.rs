@@ -17,6 +17,7 @@
 use primitives::U256;
 use serde::de::{Error, Unexpected, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
 use std::fmt;
 use std::str::FromStr;
 
@@ -30,35 +31,96 @@ impl From<Uint> for U256 {
     }
 }
 
-impl From<Uint> for u64 {
-    fn from(f: Uint) -> Self {
-        Self::from(f.0)
+impl TryFrom<Uint> for u64 {
+    type Error = OverflowError;
+
+    fn try_from(f: Uint) -> Result<Self, Self::Error> {
+        narrow(f.0, "u64")
     }
 }
 
-impl From<Uint> for u32 {
-    fn from(f: Uint) -> Self {
-        Self::from(f.0)
+impl TryFrom<Uint> for u32 {
+    type Error = OverflowError;
+
+    fn try_from(f: Uint) -> Result<Self, Self::Error> {
+        narrow(f.0, "u32")
     }
 }
 
-impl From<Uint> for u16 {
-    fn from(f: Uint) -> Self {
-        u64::from(f.0) as u16
+impl TryFrom<Uint> for u16 {
+    type Error = OverflowError;
+
+    fn try_from(f: Uint) -> Result<Self, Self::Error> {
+        narrow(f.0, "u16")
     }
 }
 
-impl From<Uint> for u8 {
-    fn from(f: Uint) -> Self {
-        u64::from(f.0) as u8
+impl TryFrom<Uint> for u8 {
+    type Error = OverflowError;
+
+    fn try_from(f: Uint) -> Result<Self, Self::Error> {
+        narrow(f.0, "u8")
     }
 }
 
-impl From<Uint> for usize {
-    fn from(f: Uint) -> Self {
-        // TODO: clean it after util conversions refactored.
-        u64::from(f.0) as usize
+impl TryFrom<Uint> for usize {
+    type Error = OverflowError;
+
+    fn try_from(f: Uint) -> Result<Self, Self::Error> {
+        narrow(f.0, "usize")
+    }
+}
+
+fn narrow<T: TryFrom<u64>>(value: U256, type_name: &'static str) -> Result<T, OverflowError> {
+    u256_to_u64_checked(value).and_then(|narrowed| T::try_from(narrowed).ok()).ok_or(OverflowError {
+        value,
+        type_name,
+    })
+}
+
+/// Error returned when a `Uint` doesn't fit in the type it is converted into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OverflowError {
+    pub value: U256,
+    pub type_name: &'static str,
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Integer overflow when casting {} to {}", self.value, self.type_name)
+    }
+}
+
+impl std::error::Error for OverflowError {}
+
+/// Returns `None` if the value doesn't fit in 64 bits.
+pub fn u256_to_u64_checked(value: U256) -> Option<u64> {
+    if value > U256::from(u64::max_value()) {
+        return None
+    }
+    Some(value.low_u64())
+}
+
+/// Returns `None` if the value doesn't fit in 128 bits.
+pub fn u256_to_u128_checked(value: U256) -> Option<u128> {
+    if value.bits() > 128 {
+        return None
     }
+    let high = (value >> 64).low_u64();
+    let low = value.low_u64();
+    Some((u128::from(high) << 64) | u128::from(low))
+}
+
+/// Returns `u64::max_value()` if the value doesn't fit in 64 bits.
+/// Don't use it where the clamped value would change the consensus result.
+pub fn saturating_into_u64(value: U256) -> u64 {
+    u256_to_u64_checked(value).unwrap_or_else(u64::max_value)
+}
+
+pub fn u256_from_u128(value: u128) -> U256 {
+    let high = u64::try_from(value >> 64).expect("The high 64 bits fit in u64");
+    let low = u64::try_from(value & u128::from(u64::max_value())).expect("The low 64 bits fit in u64");
+    (U256::from(high) << 64) | U256::from(low)
 }
 
 impl From<u64> for Uint {
@@ -157,7 +219,7 @@ where
     let value = Uint::deserialize(d)?;
 
     if value == Uint(U256::from(0)) {
-        return Err(Error::invalid_value(Unexpected::Unsigned(value.into()), &"a non-zero value"))
+        return Err(Error::invalid_value(Unexpected::Unsigned(0), &"a non-zero value"))
     }
 
     Ok(value)
@@ -170,7 +232,7 @@ where
 
     if let Some(value) = value {
         if value == Uint(U256::from(0)) {
-            return Err(Error::invalid_value(Unexpected::Unsigned(value.into()), &"a non-zero value"))
+            return Err(Error::invalid_value(Unexpected::Unsigned(0), &"a non-zero value"))
         }
     }
 
@@ -179,8 +241,9 @@ where
 
 #[cfg(test)]
 mod test {
-    use super::Uint;
-    use primitives::U256;
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
     use serde_json;
 
     #[test]
@@ -202,6 +265,73 @@ mod test {
         assert_eq!(U256::from(10), Uint(U256::from(10)).into());
     }
 
+    #[test]
+    fn uint_into_narrower_types() {
+        assert_eq!(Ok(::std::u16::MAX), u16::try_from(Uint(U256::from(::std::u16::MAX))));
+        assert_eq!(Ok(::std::u8::MAX), u8::try_from(Uint(U256::from(::std::u8::MAX))));
+        assert_eq!(Ok(::std::u32::MAX), u32::try_from(Uint(U256::from(::std::u32::MAX))));
+        assert_eq!(Ok(::std::u64::MAX), u64::try_from(Uint(U256::from(::std::u64::MAX))));
+    }
+
+    #[test]
+    fn uint_into_narrower_types_does_not_truncate() {
+        let err = u16::try_from(Uint(U256::from(::std::u16::MAX) + 1)).unwrap_err();
+        assert_eq!("Integer overflow when casting 65536 to u16", err.to_string());
+        let err = u8::try_from(Uint(U256::from(::std::u8::MAX) + 1)).unwrap_err();
+        assert_eq!("Integer overflow when casting 256 to u8", err.to_string());
+        let err = u32::try_from(Uint(U256::from(::std::u32::MAX) + 1)).unwrap_err();
+        assert_eq!("Integer overflow when casting 4294967296 to u32", err.to_string());
+        let err = u64::try_from(Uint(U256::from(::std::u64::MAX) + 1)).unwrap_err();
+        assert_eq!("Integer overflow when casting 18446744073709551616 to u64", err.to_string());
+        let err = usize::try_from(Uint(U256::from(::std::u64::MAX) + 1)).unwrap_err();
+        assert_eq!("Integer overflow when casting 18446744073709551616 to usize", err.to_string());
+    }
+
+    #[test]
+    fn u64_boundary() {
+        let max = U256::from(::std::u64::MAX);
+        assert_eq!(Some(::std::u64::MAX), u256_to_u64_checked(max));
+        assert_eq!(None, u256_to_u64_checked(max + 1));
+        assert_eq!(::std::u64::MAX, saturating_into_u64(max));
+        assert_eq!(::std::u64::MAX, saturating_into_u64(max + 1));
+        assert_eq!(::std::u64::MAX, saturating_into_u64(U256::max_value()));
+    }
+
+    #[test]
+    fn u128_boundary() {
+        let max = u256_from_u128(::std::u128::MAX);
+        assert_eq!(max, (U256::one() << 128) - 1);
+        assert_eq!(Some(::std::u128::MAX), u256_to_u128_checked(max));
+        assert_eq!(None, u256_to_u128_checked(max + 1));
+        assert_eq!(Some(u128::from(::std::u64::MAX) + 1), u256_to_u128_checked(U256::from(::std::u64::MAX) + 1));
+    }
+
+    #[test]
+    fn random_values_round_trip() {
+        let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 0, 1, 2, 3, 4, 5, 6, 7]);
+        for _ in 0..1000 {
+            let value = U256([rng.gen(), rng.gen(), rng.gen(), rng.gen()]);
+            // Shifted so that every bit length is sampled equally often.
+            let shift: usize = rng.gen_range(0, 256);
+            let value = value >> shift;
+
+            let fits_in_u64 = value <= U256::from(::std::u64::MAX);
+            match u256_to_u64_checked(value) {
+                Some(narrowed) => assert_eq!(value, U256::from(narrowed)),
+                None => assert!(!fits_in_u64, "{}", value),
+            }
+            assert_eq!(fits_in_u64, u64::try_from(Uint(value)).is_ok(), "{}", value);
+            if !fits_in_u64 {
+                assert_eq!(::std::u64::MAX, saturating_into_u64(value));
+            }
+
+            match u256_to_u128_checked(value) {
+                Some(narrowed) => assert_eq!(value, u256_from_u128(narrowed)),
+                None => assert!(value.bits() > 128, "{}", value),
+            }
+        }
+    }
+
     #[test]
     fn uint_serialization() {
         let v: Vec<Uint> = vec![0.into(), 1.into(), 100.into(), ::std::u64::MAX.into()];
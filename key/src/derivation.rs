@@ -0,0 +1,354 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Hierarchical deterministic key derivation.
+//!
+//! The scheme is BIP32 with keyed BLAKE2b-512 in place of HMAC-SHA512,
+//! so the derived keys are not the same as the keys of BIP32 wallets.
+//!
+//! - The master key of a seed: `I = BLAKE2b-512(key: "CodeChain seed", data: seed)`.
+//!   The secret is `I[0..32]` and the chain code is `I[32..64]`.
+//! - The child `i` of a key with the secret `k` and the chain code `c`:
+//!   `I = BLAKE2b-512(key: c, data: 0x00 || k || i)` if the index is hardened, i.e. `i >= 2^31`,
+//!   and `I = BLAKE2b-512(key: c, data: compressed public key of k || i)` otherwise.
+//!   `i` is serialized as a 4-byte big-endian integer.
+//!   The secret of the child is `I[0..32] + k (mod n)` and the chain code is `I[32..64]`.
+//! - The derivation fails if `I[0..32] >= n` or the secret of the child is zero.
+//!   It happens with a probability lower than 2^-127, and the index should be skipped then.
+//! - A key pair that doesn't come from a seed has no chain code, so `KeyPair::derive_child` uses
+//!   `BLAKE2b-256(key: "CodeChain chain code", data: k)` as the chain code of the secret `k`.
+
+use crate::{Error, KeyPair, Private, SECP256K1};
+use crypto::Blake;
+use primitives::{H256, H512};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use secp256k1::key;
+use std::str::FromStr;
+use std::{fmt, ptr};
+
+/// Indexes from `HARDENED_INDEX` are hardened.
+/// The public key of the parent is not enough to derive their public keys.
+pub const HARDENED_INDEX: u32 = 0x8000_0000;
+
+const MASTER_KEY: &[u8] = b"CodeChain seed";
+const CHAIN_CODE_KEY: &[u8] = b"CodeChain chain code";
+const MIN_SEED_LENGTH: usize = 16;
+const MAX_SEED_LENGTH: usize = 64;
+const RANDOM_SEED_LENGTH: usize = 32;
+
+/// The seed of a master key
+#[derive(Clone, PartialEq)]
+pub struct Seed(Vec<u8>);
+
+impl Seed {
+    /// The seed should be 16 to 64 bytes long.
+    pub fn from_slice(seed: &[u8]) -> Result<Self, Error> {
+        if seed.len() < MIN_SEED_LENGTH || seed.len() > MAX_SEED_LENGTH {
+            return Err(Error::InvalidSeed)
+        }
+        Ok(Seed(seed.to_vec()))
+    }
+
+    pub fn random() -> Result<Self, Error> {
+        let mut seed = vec![0u8; RANDOM_SEED_LENGTH];
+        OsRng::new().map_err(|_| Error::FailedKeyGeneration)?.fill_bytes(&mut seed);
+        Ok(Seed(seed))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Seed(******)")
+    }
+}
+
+// Custom drop impl to zero out memory.
+impl Drop for Seed {
+    fn drop(&mut self) {
+        for byte_ref in self.0.iter_mut() {
+            unsafe { ptr::write_volatile(byte_ref, 0) }
+        }
+    }
+}
+
+/// A key pair with the chain code to derive its children
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExtendedKeyPair {
+    keypair: KeyPair,
+    chain_code: H256,
+}
+
+impl ExtendedKeyPair {
+    pub fn new(private: Private, chain_code: H256) -> Result<Self, Error> {
+        Ok(ExtendedKeyPair {
+            keypair: KeyPair::from_private(private)?,
+            chain_code,
+        })
+    }
+
+    /// Create the master key of the seed
+    pub fn from_seed(seed: &Seed) -> Result<Self, Error> {
+        let i = H512::blake_with_key(seed.as_bytes(), MASTER_KEY);
+        let secret = key::SecretKey::from_slice(&SECP256K1, &i[0..32])?;
+        Self::new(secret.into(), H256::from(&i[32..64]))
+    }
+
+    pub fn keypair(&self) -> &KeyPair {
+        &self.keypair
+    }
+
+    pub fn chain_code(&self) -> &H256 {
+        &self.chain_code
+    }
+
+    /// Derive the descendant at the path
+    pub fn derive(&self, path: &DerivationPath) -> Result<Self, Error> {
+        path.indexes().iter().try_fold(*self, |key, index| {
+            let (keypair, chain_code) = derive_child(&key.keypair, &key.chain_code, *index)?;
+            Ok(ExtendedKeyPair {
+                keypair,
+                chain_code,
+            })
+        })
+    }
+}
+
+impl KeyPair {
+    /// Derive the child at the index, with the chain code made from the secret
+    pub fn derive_child(&self, index: u32) -> Result<KeyPair, Error> {
+        let chain_code = H256::blake_with_key(&self.private()[..], CHAIN_CODE_KEY);
+        Ok(derive_child(self, &chain_code, index)?.0)
+    }
+}
+
+/// Derive the child of the key pair and the chain code at the index
+fn derive_child(keypair: &KeyPair, chain_code: &H256, index: u32) -> Result<(KeyPair, H256), Error> {
+    let context = &SECP256K1;
+    let parent = key::SecretKey::from_slice(context, &keypair.private()[..])?;
+
+    let mut data = Vec::with_capacity(37);
+    if index >= HARDENED_INDEX {
+        data.push(0);
+        data.extend_from_slice(&keypair.private()[..]);
+    } else {
+        let public = key::PublicKey::from_secret_key(context, &parent)?;
+        data.extend_from_slice(&public.serialize_vec(context, true)[..]);
+    }
+    data.extend_from_slice(&index.to_be_bytes());
+
+    let i = H512::blake_with_key(&data, chain_code);
+    let mut secret = key::SecretKey::from_slice(context, &i[0..32])?;
+    secret.add_assign(context, &parent)?;
+    let public = key::PublicKey::from_secret_key(context, &secret)?;
+    Ok((KeyPair::from_keypair(secret, public), H256::from(&i[32..64])))
+}
+
+/// A path from a master key, such as "m/0'/5".
+/// The indexes with `'` or `h` are hardened.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    pub fn new(indexes: Vec<u32>) -> Self {
+        DerivationPath(indexes)
+    }
+
+    pub fn indexes(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(Error::InvalidDerivationPath)
+        }
+        let indexes = parts
+            .map(|part| {
+                let (number, hardened) = if part.ends_with('\'') || part.ends_with('h') {
+                    (&part[..part.len() - 1], true)
+                } else {
+                    (part, false)
+                };
+                // u32::from_str accepts a leading '+'.
+                if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(Error::InvalidDerivationPath)
+                }
+                let index: u32 = number.parse().map_err(|_| Error::InvalidDerivationPath)?;
+                match (index < HARDENED_INDEX, hardened) {
+                    (true, true) => Ok(index + HARDENED_INDEX),
+                    (true, false) => Ok(index),
+                    (false, _) => Err(Error::InvalidDerivationPath),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(DerivationPath(indexes))
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            if *index >= HARDENED_INDEX {
+                write!(f, "/{}'", index - HARDENED_INDEX)?;
+            } else {
+                write!(f, "/{}", index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Public;
+    use rustc_hex::FromHex;
+
+    fn test_seed() -> Seed {
+        Seed::from_slice(&"000102030405060708090a0b0c0d0e0f".from_hex().unwrap()).unwrap()
+    }
+
+    /// Other implementations should derive the same keys from the seed.
+    #[test]
+    fn test_vectors() {
+        let vectors = [
+            (
+                "m",
+                "bf3e9ed0c4fb51e7a672e94b80c9ae9e0bb8bcfccf87b2022c92dd163c5dbbf2",
+                "7328adb45ed6aa7511bed7af74349ff7034d7b420e770ab5e6c1208b5c26ade9\
+                 9c0c934a7dc5fb2e0c33228fc18de752a1f42a4daa984f9f607c994b1a5b07c6",
+            ),
+            (
+                "m/0'",
+                "660c6215e2ca15eaf621047075d8b247a7e56384a511fff05199e2aae8b43d4f",
+                "66652cdc869ca68e533eb5e60640d26cc4c7aa2be6ba4977b27cd2057d2ecb98\
+                 7c5dc90c4abf511e928a4de711f63524f7d07b940c1d9e35570149ad871fc309",
+            ),
+            (
+                "m/0'/1",
+                "c946e99a2549e4bbeb5d73930808fb712e609cf2688f3cfbb01c64bb71cc5fed",
+                "5ec571fa9cb164d99f83b2681a515bf1fa3225ffeffbb5e2625c15949c4f72ba\
+                 3976393bf357d64695a285ec0e8820fefe887680466710d9588011fda327a88e",
+            ),
+            (
+                "m/0'/1/2'",
+                "9c36f5f7724f1e8edc7e2cb342189ff6b0173c5bb46721d87c4277b2c4e6b7bc",
+                "d7a079b6b14fc8e3fd82c842f31cf1ab195c16f3a87a47f890f2b71e6e1a4225\
+                 2195055e0315430b2ee408c4d33590e50b1b5b89ccc0ea182079e7b16ad54c52",
+            ),
+            (
+                "m/0'/5",
+                "57fab9ec1183f5c31e40016554bb498f605fc3543d47f37ccb8b9cf94f625dfa",
+                "165a6bb698456ec7a074b28be286be226f10c24e24b212d245a4aea945710d6a\
+                 041ef149bf86ac015ec9a0f301060f7864847edd9f2818a5931620e8cc89532b",
+            ),
+            (
+                "m/44'/2147483647'/0/1",
+                "fd8d6215e0643b149646c30f2a8328cc8524c8ee3dc57a053db6ee9af1b54094",
+                "02de24dfc194d600f248c9484c9d9e54d44bb32faa23d009f3b810f15177da84\
+                 6855e30ec197e6e3d3c5aced6c070cd7c6016c0b8243bbc3de740a2cf9e436b8",
+            ),
+        ];
+
+        let master = ExtendedKeyPair::from_seed(&test_seed()).unwrap();
+        for (path, chain_code, public) in vectors.iter() {
+            let derived = master.derive(&path.parse().unwrap()).unwrap();
+            assert_eq!(&chain_code.parse::<H256>().unwrap(), derived.chain_code(), "{}", path);
+            assert_eq!(&public.parse::<Public>().unwrap(), derived.keypair().public(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn derive_step_by_step() {
+        let master = ExtendedKeyPair::from_seed(&test_seed()).unwrap();
+        let child = master.derive(&DerivationPath::new(vec![HARDENED_INDEX])).unwrap();
+        let derived = child.derive(&DerivationPath::new(vec![5])).unwrap();
+        assert_eq!(master.derive(&"m/0'/5".parse().unwrap()).unwrap(), derived);
+        assert_eq!(
+            "7c93f3062f16b6d47e79c2b8d68510f0b3ad46eeedecac9778a447116ac86eec".parse::<H256>().unwrap(),
+            **derived.keypair().private()
+        );
+    }
+
+    /// Other implementations should derive the same children from the key pair.
+    #[test]
+    fn derive_child_of_key_pair() {
+        let vectors = [
+            (
+                0,
+                "adee43a2b274d1e0417604e2f0a444526520436a79c152dc21c83e62f4e788a6\
+                 cd6c703ead0698e9cefc00c1f39cf7707e74c1befe59040160a9a37d43d2de27",
+            ),
+            (
+                HARDENED_INDEX,
+                "1251ce635f0af8d6e93160d0f31ca656e4d421eea366e30a22135a78199df9b3\
+                 bb7f19f3b7caf6898040ed9f212d61c3144d304a6f81da1b2e071237c0265ce9",
+            ),
+            (
+                HARDENED_INDEX + 5,
+                "af7187b00eb45e4591dae548ac53a8961dd0c74f0e775d5df3e6fda682992aca\
+                 7837fa0ddd9a9ba7bf7fda86ca3fdcdabc81762bc8364bcc1f7efa69bd9b0556",
+            ),
+        ];
+
+        let keypair = ExtendedKeyPair::from_seed(&test_seed()).unwrap().keypair;
+        assert_eq!(
+            "29095de1082b29899013eda5abc8edcfd1d719b53ac0f6f54a65ec9224b2266c".parse::<H256>().unwrap(),
+            **keypair.private()
+        );
+        for (index, public) in vectors.iter() {
+            let child = keypair.derive_child(*index).unwrap();
+            assert_eq!(&public.parse::<Public>().unwrap(), child.public(), "{}", index);
+        }
+    }
+
+    #[test]
+    fn hardened_and_normal_children_differ() {
+        let keypair = ExtendedKeyPair::from_seed(&test_seed()).unwrap().keypair;
+        assert_ne!(keypair.derive_child(0).unwrap(), keypair.derive_child(HARDENED_INDEX).unwrap());
+    }
+
+    #[test]
+    fn parse_path() {
+        assert_eq!(DerivationPath::new(vec![]), "m".parse().unwrap());
+        assert_eq!(DerivationPath::new(vec![HARDENED_INDEX, 5]), "m/0'/5".parse().unwrap());
+        assert_eq!(DerivationPath::new(vec![HARDENED_INDEX + 1, 2]), "m/1h/2".parse().unwrap());
+        assert_eq!("m/44'/0/2147483647'", "m/44h/0/2147483647'".parse::<DerivationPath>().unwrap().to_string());
+
+        for invalid in &["", "0/1", "m/", "m/a", "m/+1", "m/1''", "m/2147483648", "m/-1", "M/0"] {
+            assert_eq!(Err(Error::InvalidDerivationPath), invalid.parse::<DerivationPath>(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn seed_length_is_checked() {
+        assert_eq!(Err(Error::InvalidSeed), Seed::from_slice(&[0; 15]));
+        assert!(Seed::from_slice(&[0; 16]).is_ok());
+        assert!(Seed::from_slice(&[0; 64]).is_ok());
+        assert_eq!(Err(Error::InvalidSeed), Seed::from_slice(&[0; 65]));
+        assert_eq!(RANDOM_SEED_LENGTH, Seed::random().unwrap().as_bytes().len());
+    }
+}
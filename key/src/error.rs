@@ -31,6 +31,8 @@ pub enum Error {
     InvalidPrivate,
     InvalidAddress,
     FailedKeyGeneration,
+    InvalidSeed,
+    InvalidDerivationPath,
     Bech32MissingSeparator,
     Bech32InvalidChecksum,
     Bech32InvalidLength,
@@ -56,6 +58,8 @@ impl fmt::Display for Error {
             Error::InvalidPrivate => "Invalid Private".into(),
             Error::InvalidAddress => "Invalid Address".into(),
             Error::FailedKeyGeneration => "Key generation failed".into(),
+            Error::InvalidSeed => "Invalid Seed".into(),
+            Error::InvalidDerivationPath => "Invalid Derivation Path".into(),
             Error::Bech32MissingSeparator => "Missing human-readable separator".into(),
             Error::Bech32InvalidChecksum => "Invalid checksum".into(),
             Error::Bech32InvalidLength => "Invalid Length".into(),
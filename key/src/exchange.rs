@@ -14,7 +14,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{Error, Private, Public, Secret, SECP256K1};
+use crate::{sign_schnorr, verify_schnorr, Error, KeyPair, Private, Public, SchnorrSignature, Secret, SECP256K1};
+use crypto::Blake;
+use primitives::H256;
 use secp256k1::{ecdh, key};
 use std::result;
 
@@ -32,10 +34,78 @@ pub fn exchange(public: &Public, private: &Private) -> result::Result<Secret, Er
     Ok(Secret::from(&shared[0..32]))
 }
 
+/// The keys that both sides of a handshake exchanged.
+/// The node ids are the long-term public keys of the nodes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HandshakeTranscript {
+    pub initiator_ephemeral: Public,
+    pub recipient_ephemeral: Public,
+    pub initiator_id: Public,
+    pub recipient_id: Public,
+}
+
+impl HandshakeTranscript {
+    pub fn hash(&self) -> H256 {
+        let mut bytes = Vec::with_capacity(4 * 64);
+        bytes.extend_from_slice(&self.initiator_ephemeral);
+        bytes.extend_from_slice(&self.recipient_ephemeral);
+        bytes.extend_from_slice(&self.initiator_id);
+        bytes.extend_from_slice(&self.recipient_id);
+        H256::blake(&bytes)
+    }
+
+    /// Returns the ephemeral key of the other side if the given one is in the transcript.
+    fn remote_ephemeral(&self, local_ephemeral: &Public) -> Result<&Public, Error> {
+        if *local_ephemeral == self.initiator_ephemeral {
+            Ok(&self.recipient_ephemeral)
+        } else if *local_ephemeral == self.recipient_ephemeral {
+            Ok(&self.initiator_ephemeral)
+        } else {
+            Err(Error::Custom("The ephemeral key is not in the handshake transcript".to_string()))
+        }
+    }
+}
+
+/// Exchanges the ephemeral keys of the transcript and binds the shared secret to the transcript,
+/// so that the sides derive different secrets unless they saw the same keys and node ids.
+/// The ephemeral key must be used for one handshake only. The caller, which keeps the state of the handshake, checks it.
+pub fn exchange_with_transcript(ephemeral: &KeyPair, transcript: &HandshakeTranscript) -> Result<Secret, Error> {
+    let remote_ephemeral = transcript.remote_ephemeral(ephemeral.public())?;
+    let shared = exchange(remote_ephemeral, ephemeral.private())?;
+    Ok(Secret::blake_with_key(&transcript.hash(), &shared))
+}
+
+/// Does `exchange_with_transcript` and signs the transcript with the long-term key of the node,
+/// which is the node id of its side.
+pub fn authenticated_exchange(
+    ephemeral: &KeyPair,
+    identity: &KeyPair,
+    transcript: &HandshakeTranscript,
+) -> Result<(Secret, SchnorrSignature), Error> {
+    if *identity.public() != transcript.initiator_id && *identity.public() != transcript.recipient_id {
+        return Err(Error::Custom("The node id is not in the handshake transcript".to_string()))
+    }
+    let secret = exchange_with_transcript(ephemeral, transcript)?;
+    let signature = sign_schnorr(identity.private(), &transcript.hash())?;
+    Ok((secret, signature))
+}
+
+/// Verifies the transcript signature of the other side, whose node id must be in the transcript.
+pub fn verify_handshake(
+    remote_id: &Public,
+    signature: &SchnorrSignature,
+    transcript: &HandshakeTranscript,
+) -> Result<bool, Error> {
+    if *remote_id != transcript.initiator_id && *remote_id != transcript.recipient_id {
+        return Ok(false)
+    }
+    verify_schnorr(remote_id, signature, &transcript.hash())
+}
+
 #[cfg(test)]
 mod tests {
-    use super::exchange;
-    use crate::{Generator, KeyPair, Random};
+    use super::*;
+    use crate::{Generator, Random};
 
     #[test]
     fn exchange_makes_same_private_key() {
@@ -53,4 +123,49 @@ mod tests {
         let s2 = exchange(&k1.public(), &k2.private()).unwrap();
         assert_eq!(s1, s2);
     }
+
+    fn transcript(
+        initiator: &KeyPair,
+        recipient: &KeyPair,
+        initiator_id: &KeyPair,
+        recipient_id: &KeyPair,
+    ) -> HandshakeTranscript {
+        HandshakeTranscript {
+            initiator_ephemeral: *initiator.public(),
+            recipient_ephemeral: *recipient.public(),
+            initiator_id: *initiator_id.public(),
+            recipient_id: *recipient_id.public(),
+        }
+    }
+
+    #[test]
+    fn authenticated_exchange_makes_same_secret_and_verifiable_signature() {
+        let (initiator, recipient): (KeyPair, KeyPair) = (Random.generate().unwrap(), Random.generate().unwrap());
+        let (initiator_id, recipient_id): (KeyPair, KeyPair) = (Random.generate().unwrap(), Random.generate().unwrap());
+        let transcript = transcript(&initiator, &recipient, &initiator_id, &recipient_id);
+
+        let (recipient_secret, signature) = authenticated_exchange(&recipient, &recipient_id, &transcript).unwrap();
+        assert_eq!(Ok(true), verify_handshake(recipient_id.public(), &signature, &transcript));
+        let initiator_secret = exchange_with_transcript(&initiator, &transcript).unwrap();
+        assert_eq!(initiator_secret, recipient_secret);
+        assert_ne!(exchange(recipient.public(), initiator.private()).unwrap(), initiator_secret);
+    }
+
+    #[test]
+    fn tampered_transcript_fails_verification() {
+        let (initiator, recipient): (KeyPair, KeyPair) = (Random.generate().unwrap(), Random.generate().unwrap());
+        let (initiator_id, recipient_id): (KeyPair, KeyPair) = (Random.generate().unwrap(), Random.generate().unwrap());
+        let transcript = transcript(&initiator, &recipient, &initiator_id, &recipient_id);
+        let (_, signature) = authenticated_exchange(&recipient, &recipient_id, &transcript).unwrap();
+
+        let attacker: KeyPair = Random.generate().unwrap();
+        let tampered = HandshakeTranscript {
+            recipient_ephemeral: *attacker.public(),
+            ..transcript
+        };
+        assert_eq!(Ok(false), verify_handshake(recipient_id.public(), &signature, &tampered));
+        // The signer must be the node of the transcript.
+        assert_eq!(Ok(false), verify_handshake(attacker.public(), &signature, &transcript));
+        assert!(authenticated_exchange(&attacker, &recipient_id, &transcript).is_err());
+    }
 }
@@ -33,6 +33,7 @@ extern crate serde_derive;
 extern crate serde_json;
 
 mod address;
+mod derivation;
 mod ecdsa;
 mod error;
 mod exchange;
@@ -45,12 +46,15 @@ mod random;
 mod schnorr;
 
 pub use crate::address::Address;
+pub use crate::derivation::{DerivationPath, ExtendedKeyPair, Seed, HARDENED_INDEX};
 pub use crate::ecdsa::{
     recover_ecdsa as recover, sign_ecdsa as sign, verify_ecdsa as verify, verify_ecdsa_address as verify_address,
     ECDSASignature as Signature, ECDSA_SIGNATURE_LENGTH as SIGNATURE_LENGTH,
 };
 pub use crate::error::Error;
-pub use crate::exchange::exchange;
+pub use crate::exchange::{
+    authenticated_exchange, exchange, exchange_with_transcript, verify_handshake, HandshakeTranscript,
+};
 pub use crate::keypair::{public_to_address, KeyPair};
 pub use crate::network::NetworkId;
 pub use crate::password::Password;
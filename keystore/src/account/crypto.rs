@@ -18,11 +18,14 @@ use crate::account::{Aes128Ctr, Cipher, Kdf, Pbkdf2, Prf};
 use crate::random::Random;
 use crate::{json, Error};
 use ccrypto;
-use ckey::{public_to_address, Address, KeyPair, Password, Private, Secret};
+use ckey::{public_to_address, Address, ExtendedKeyPair, KeyPair, Password, Private, Secret};
 use smallvec::SmallVec;
 use std::num::NonZeroU32;
 use std::str;
 
+/// The plain text of a master key is its secret followed by its chain code.
+const MASTER_KEY_LENGTH: usize = 64;
+
 /// Encrypted data
 #[derive(Debug, PartialEq, Clone)]
 pub struct Crypto {
@@ -111,14 +114,42 @@ impl Crypto {
         }
     }
 
+    /// Encrypt master key
+    pub fn with_master_key(
+        master_key: &ExtendedKeyPair,
+        password: &Password,
+        iterations: u32,
+    ) -> Result<Self, ccrypto::Error> {
+        let mut plain = Vec::with_capacity(MASTER_KEY_LENGTH);
+        plain.extend_from_slice(&master_key.keypair().private()[..]);
+        plain.extend_from_slice(&master_key.chain_code()[..]);
+        Crypto::with_plain(&plain, password, iterations)
+    }
+
     /// Try to decrypt and convert result to account secret
     pub fn secret(&self, password: &Password) -> Result<Secret, Error> {
-        if self.ciphertext.len() > 32 {
-            return Err(Error::InvalidSecret)
+        let secret = match self.ciphertext.len() {
+            MASTER_KEY_LENGTH => self.do_decrypt(password, MASTER_KEY_LENGTH)?,
+            len if len <= 32 => self.do_decrypt(password, 32)?,
+            _ => return Err(Error::InvalidSecret),
+        };
+        Ok(*Private::from_slice(&secret[0..32]))
+    }
+
+    /// Check if the encrypted data is a master key
+    pub fn is_master_key(&self) -> bool {
+        self.ciphertext.len() == MASTER_KEY_LENGTH
+    }
+
+    /// Try to decrypt and convert result to master key
+    pub fn master_key(&self, password: &Password) -> Result<ExtendedKeyPair, Error> {
+        if !self.is_master_key() {
+            return Err(Error::NotMasterKey)
         }
 
-        let secret = self.do_decrypt(password, 32)?;
-        Ok(*Private::from_slice(&secret))
+        let plain = self.do_decrypt(password, MASTER_KEY_LENGTH)?;
+        let chain_code = Secret::from(&plain[32..64]);
+        Ok(ExtendedKeyPair::new(Private::from_slice(&plain[0..32]), chain_code)?)
     }
 
     pub fn address(&self, password: &Password) -> Result<Address, Error> {
@@ -18,7 +18,7 @@ use super::crypto::Crypto;
 use crate::account::Version;
 use crate::{json, DecryptedAccount, Error};
 use ccrypto;
-use ckey::{Address, KeyPair, Password};
+use ckey::{Address, ExtendedKeyPair, KeyPair, Password};
 
 /// Account representation.
 #[derive(Debug, PartialEq, Clone)]
@@ -35,6 +35,8 @@ pub struct SafeAccount {
     pub filename: Option<String>,
     /// Account metadata
     pub meta: String,
+    /// The name of the vault that holds the account, or `None` if it's in the root directory
+    pub vault: Option<String>,
 }
 
 impl From<SafeAccount> for json::KeyFile {
@@ -65,6 +67,26 @@ impl SafeAccount {
             address: keypair.address(),
             filename: None,
             meta,
+            vault: None,
+        })
+    }
+
+    /// Create a new account that holds a master key
+    pub fn create_master(
+        master_key: &ExtendedKeyPair,
+        id: [u8; 16],
+        password: &Password,
+        iterations: u32,
+        meta: String,
+    ) -> Result<Self, ccrypto::Error> {
+        Ok(SafeAccount {
+            id,
+            version: Version::V3,
+            crypto: Crypto::with_master_key(master_key, password, iterations)?,
+            address: master_key.keypair().address(),
+            filename: None,
+            meta,
+            vault: None,
         })
     }
 
@@ -99,6 +121,7 @@ impl SafeAccount {
             crypto,
             filename,
             meta: json.meta.unwrap_or_else(|| "{}".to_string()),
+            vault: None,
         })
     }
 
@@ -117,6 +140,7 @@ impl SafeAccount {
             address: self.address,
             filename: self.filename.clone(),
             meta: self.meta.clone(),
+            vault: self.vault.clone(),
         };
         Ok(result)
     }
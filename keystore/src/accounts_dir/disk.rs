@@ -14,17 +14,20 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::KeyDirectory;
+use super::{KeyDirectory, VaultDiskDirectory, VaultKey, VaultKeyDirectory, VaultKeyDirectoryProvider};
 use crate::json::Uuid;
 use crate::{json, Error, SafeAccount};
+use cjson::de::Mode;
+use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use std::{fs, io};
 use time;
 
-const IGNORED_FILES: &[&str] = &["thumbs.db"];
+const IGNORED_FILES: &[&str] = &["thumbs.db", super::vault::VAULT_FILE_NAME];
 
 #[cfg(not(windows))]
 fn restrict_permissions_to_owner(file_path: &Path) -> Result<(), i32> {
@@ -48,8 +51,8 @@ pub type RootDiskDirectory = DiskDirectory<DiskKeyFileManager>;
 
 /// Disk directory key file manager
 pub trait KeyFileManager: Send + Sync {
-    /// Read `SafeAccount` from given key file stream
-    fn read<T>(&self, filename: Option<String>, reader: T) -> Result<SafeAccount, Error>
+    /// Read `SafeAccount` from given key file stream, matching its fields as `mode` says
+    fn read<T>(&self, filename: Option<String>, reader: T, mode: Mode) -> Result<SafeAccount, Error>
     where
         T: io::Read;
     /// Write `SafeAccount` to given key file stream
@@ -59,11 +62,16 @@ pub trait KeyFileManager: Send + Sync {
 }
 
 /// Disk-based keys directory implementation
+///
+/// It keeps the accounts read from the key files with the modification times of the files,
+/// so only the new or modified files are read when the directory is loaded again.
+/// A file rewritten within the resolution of the modification time is not read again.
 pub struct DiskDirectory<T>
 where
     T: KeyFileManager, {
     path: PathBuf,
     key_manager: T,
+    index: RwLock<HashMap<PathBuf, (SystemTime, SafeAccount)>>,
 }
 
 /// Keys file manager for root keys directory
@@ -95,25 +103,29 @@ where
         DiskDirectory {
             path: path.as_ref().to_path_buf(),
             key_manager,
+            index: Default::default(),
         }
     }
 
     fn files(&self) -> Result<Vec<PathBuf>, Error> {
+        Ok(self.files_with_modified_time()?.into_iter().map(|(path, _)| path).collect())
+    }
+
+    /// The key files with their modification times. Listing them doesn't read the files.
+    fn files_with_modified_time(&self) -> Result<Vec<(PathBuf, SystemTime)>, Error> {
         Ok(fs::read_dir(&self.path)?
             .flat_map(Result::ok)
-            .filter(|entry| {
-                let metadata = entry.metadata().ok();
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
                 let file_name = entry.file_name();
                 let name = file_name.to_string_lossy();
-                // filter directories
-                metadata.map_or(false, |m| !m.is_dir()) &&
-					// hidden files
-					!name.starts_with('.') &&
-					// other ignored files
-					!IGNORED_FILES.contains(&&*name)
+                // filter directories, hidden files and other ignored files
+                if metadata.is_dir() || name.starts_with('.') || IGNORED_FILES.contains(&&*name) {
+                    return None
+                }
+                Some((entry.path(), metadata.modified().ok()?))
             })
-            .map(|entry| entry.path())
-            .collect::<Vec<PathBuf>>())
+            .collect())
     }
 
     pub fn files_hash(&self) -> Result<u64, Error> {
@@ -137,29 +149,36 @@ where
     }
 
     /// all accounts found in keys directory
+    /// Only the files that are new or modified since the last call are read.
     fn files_content(&self) -> Result<HashMap<PathBuf, SafeAccount>, Error> {
-        // it's not done using one iterator cause
-        // there is an issue with rustc and it takes tooo much time to compile
-        let paths = self.files()?;
-        Ok(paths
-            .into_iter()
-            .filter_map(|path| {
-                let filename = Some(
-                    path.file_name().and_then(OsStr::to_str).expect("Keys have valid UTF8 names only.").to_owned(),
-                );
-                fs::File::open(path.clone())
-                    .map_err(Into::into)
-                    .and_then(|file| self.key_manager.read(filename, file))
-                    .map_err(|e| {
+        let files = self.files_with_modified_time()?;
+        let mut index = self.index.write();
+        let mut accounts = HashMap::with_capacity(files.len());
+        let mut new_index = HashMap::with_capacity(files.len());
+        for (path, modified) in files {
+            let account = match index.remove(&path) {
+                Some((indexed, account)) if indexed == modified => account,
+                _ => match self.read_file(&path) {
+                    Ok(account) => account,
+                    Err(e) => {
                         warn!("Invalid key file: {:?} ({})", path, e);
-                        e
-                    })
-                    .map(|account| (path, account))
-                    .ok()
-            })
-            .collect())
+                        continue
+                    }
+                },
+            };
+            accounts.insert(path.clone(), account.clone());
+            new_index.insert(path, (modified, account));
+        }
+        *index = new_index;
+        Ok(accounts)
     }
 
+    fn read_file(&self, path: &Path) -> Result<SafeAccount, Error> {
+        let filename =
+            Some(path.file_name().and_then(OsStr::to_str).expect("Keys have valid UTF8 names only.").to_owned());
+        let file = fs::File::open(path)?;
+        self.key_manager.read(filename, file, Mode::Strict)
+    }
 
     /// insert account with given filename. if the filename is a duplicate of any stored account and dedup is set to
     /// true, a random suffix is appended to the filename.
@@ -198,6 +217,8 @@ where
             file.sync_all()?;
         }
 
+        let modified = fs::metadata(&keyfile_path)?.modified()?;
+        self.index.write().insert(keyfile_path, (modified, account.clone()));
         Ok(account)
     }
 
@@ -238,7 +259,11 @@ where
         // remove it
         match to_remove {
             None => Err(Error::InvalidAccount),
-            Some((path, _)) => fs::remove_file(path).map_err(From::from),
+            Some((path, _)) => {
+                fs::remove_file(&path)?;
+                self.index.write().remove(&path);
+                Ok(())
+            }
         }
     }
 
@@ -249,13 +274,36 @@ where
     fn unique_repr(&self) -> Result<u64, Error> {
         self.last_modification_date()
     }
+
+    fn as_vault_provider(&self) -> Option<&dyn VaultKeyDirectoryProvider> {
+        Some(self)
+    }
+}
+
+impl<T> VaultKeyDirectoryProvider for DiskDirectory<T>
+where
+    T: KeyFileManager,
+{
+    fn create(&self, name: &str, key: VaultKey) -> Result<Box<dyn VaultKeyDirectory>, Error> {
+        let vault = VaultDiskDirectory::create(&self.path, name, key)?;
+        Ok(Box::new(vault))
+    }
+
+    fn open(&self, name: &str, key: VaultKey) -> Result<Box<dyn VaultKeyDirectory>, Error> {
+        let vault = VaultDiskDirectory::at(&self.path, name, key)?;
+        Ok(Box::new(vault))
+    }
+
+    fn list_vaults(&self) -> Result<Vec<String>, Error> {
+        VaultDiskDirectory::list(&self.path)
+    }
 }
 
 impl KeyFileManager for DiskKeyFileManager {
-    fn read<T>(&self, filename: Option<String>, reader: T) -> Result<SafeAccount, Error>
+    fn read<T>(&self, filename: Option<String>, reader: T, mode: Mode) -> Result<SafeAccount, Error>
     where
         T: io::Read, {
-        let key_file = json::KeyFile::load(reader).map_err(|e| Error::Custom(format!("{:?}", e)))?;
+        let key_file = json::KeyFile::load(reader, mode).map_err(|e| Error::Custom(e.to_string()))?;
         SafeAccount::from_file(key_file, filename, None)
     }
 
@@ -279,6 +327,7 @@ fn account_filename(account: &SafeAccount) -> String {
 mod test {
     extern crate tempdir;
 
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::{env, fs};
 
     use ckey::{Generator, Random};
@@ -359,4 +408,64 @@ mod test {
 
         assert!(new_hash != hash, "hash of the file list should change once directory content changed");
     }
+
+    #[derive(Default)]
+    struct CountingKeyFileManager {
+        reads: AtomicUsize,
+    }
+
+    impl KeyFileManager for CountingKeyFileManager {
+        fn read<T>(&self, filename: Option<String>, reader: T, mode: Mode) -> Result<SafeAccount, Error>
+        where
+            T: io::Read, {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            DiskKeyFileManager.read(filename, reader, mode)
+        }
+
+        fn write<T>(&self, account: SafeAccount, writer: &mut T) -> Result<(), Error>
+        where
+            T: io::Write, {
+            DiskKeyFileManager.write(account, writer)
+        }
+    }
+
+    fn new_account() -> SafeAccount {
+        let keypair = Random.generate().unwrap();
+        let password = &"test pass".into();
+        SafeAccount::create(&keypair, [0u8; 16], password, 1024, "{\"name\":\"Test\"}".to_string()).unwrap()
+    }
+
+    #[test]
+    fn load_reads_only_the_new_files() {
+        let temp_path = TempDir::new("").unwrap();
+        let directory = DiskDirectory::new(&temp_path, CountingKeyFileManager::default());
+        // Another directory writes the files, as another process would do.
+        let other = RootDiskDirectory::at(&temp_path);
+
+        other.insert(new_account()).unwrap();
+        other.insert(new_account()).unwrap();
+        assert_eq!(2, directory.load().unwrap().len());
+        assert_eq!(2, directory.key_manager().reads.load(Ordering::SeqCst));
+
+        assert_eq!(2, directory.load().unwrap().len());
+        assert_eq!(2, directory.key_manager().reads.load(Ordering::SeqCst), "Unchanged files are not read again");
+
+        other.insert(new_account()).unwrap();
+        assert_eq!(3, directory.load().unwrap().len());
+        assert_eq!(3, directory.key_manager().reads.load(Ordering::SeqCst), "Only the new file is read");
+    }
+
+    #[test]
+    fn own_writes_are_indexed() {
+        let temp_path = TempDir::new("").unwrap();
+        let directory = DiskDirectory::new(&temp_path, CountingKeyFileManager::default());
+
+        let account = directory.insert(new_account()).unwrap();
+        assert_eq!(vec![account.clone()], directory.load().unwrap());
+        assert_eq!(0, directory.key_manager().reads.load(Ordering::SeqCst));
+
+        directory.remove(&account).unwrap();
+        assert_eq!(Vec::<SafeAccount>::new(), directory.load().unwrap());
+        assert_eq!(0, directory.key_manager().reads.load(Ordering::SeqCst));
+    }
 }
@@ -17,10 +17,12 @@
 //! Accounts Directory
 
 use crate::{Error, SafeAccount};
+use ckey::Password;
 use std::path::PathBuf;
 
 mod disk;
 mod memory;
+mod vault;
 
 /// `VaultKeyDirectory::set_key` error
 #[derive(Debug)]
@@ -33,6 +35,25 @@ pub enum SetKeyError {
     NonFatalNew(Error),
 }
 
+/// Vault key
+#[derive(Clone, PartialEq)]
+pub struct VaultKey {
+    /// Vault password
+    pub password: Password,
+    /// Number of iterations to derive the key from the password
+    pub iterations: u32,
+}
+
+impl VaultKey {
+    /// Create a new vault key
+    pub fn new(password: &Password, iterations: u32) -> Self {
+        VaultKey {
+            password: password.clone(),
+            iterations,
+        }
+    }
+}
+
 /// Keys directory
 pub trait KeyDirectory: Send + Sync {
     /// Read keys from directory
@@ -49,7 +70,32 @@ pub trait KeyDirectory: Send + Sync {
     }
     /// Unique representation of directory account collection
     fn unique_repr(&self) -> Result<u64, Error>;
+    /// Return the vault provider, if the directory supports vaults
+    fn as_vault_provider(&self) -> Option<&dyn VaultKeyDirectoryProvider> {
+        None
+    }
+}
+
+/// Keys directory protected by a vault password
+pub trait VaultKeyDirectory: KeyDirectory {
+    /// Cast to `KeyDirectory`
+    fn as_key_directory(&self) -> &dyn KeyDirectory;
+    /// Vault name
+    fn name(&self) -> &str;
+    /// Vault key
+    fn key(&self) -> VaultKey;
+}
+
+/// Directory that can create and open vaults
+pub trait VaultKeyDirectoryProvider {
+    /// Create a new vault with the given key
+    fn create(&self, name: &str, key: VaultKey) -> Result<Box<dyn VaultKeyDirectory>, Error>;
+    /// Open an existing vault with the given key
+    fn open(&self, name: &str, key: VaultKey) -> Result<Box<dyn VaultKeyDirectory>, Error>;
+    /// List the names of all vaults
+    fn list_vaults(&self) -> Result<Vec<String>, Error>;
 }
 
 pub use self::disk::{DiskKeyFileManager, KeyFileManager, RootDiskDirectory};
 pub use self::memory::MemoryDirectory;
+pub use self::vault::VaultDiskDirectory;
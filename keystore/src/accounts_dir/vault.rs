@@ -0,0 +1,225 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{KeyDirectory, RootDiskDirectory, VaultKey, VaultKeyDirectory};
+use crate::random::Random;
+use crate::{json, Crypto, Error, SafeAccount};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Name of the directory that holds the vaults, relative to the root keys directory
+pub const VAULTS_DIR_NAME: &str = "vaults";
+/// Name of the vault metadata file
+pub const VAULT_FILE_NAME: &str = "vault.json";
+
+/// Keys directory at `<root>/vaults/<name>/`.
+/// Its `vault.json` holds a blob encrypted with the vault password, which is used to verify the password.
+pub struct VaultDiskDirectory {
+    name: String,
+    key: VaultKey,
+    dir: RootDiskDirectory,
+}
+
+impl VaultDiskDirectory {
+    /// Create a new vault under the given root keys directory
+    pub fn create<P>(root: P, name: &str, key: VaultKey) -> Result<Self, Error>
+    where
+        P: AsRef<Path>, {
+        check_vault_name(name)?;
+        let path = vault_path(root, name);
+        if path.exists() {
+            return Err(Error::AlreadyExists)
+        }
+
+        fs::create_dir_all(&path)?;
+        if let Err(err) = create_vault_file(&path, &key) {
+            let _ = fs::remove_dir_all(&path);
+            return Err(err)
+        }
+        Ok(VaultDiskDirectory {
+            name: name.to_string(),
+            key,
+            dir: RootDiskDirectory::at(path),
+        })
+    }
+
+    /// Open an existing vault under the given root keys directory
+    pub fn at<P>(root: P, name: &str, key: VaultKey) -> Result<Self, Error>
+    where
+        P: AsRef<Path>, {
+        check_vault_name(name)?;
+        let path = vault_path(root, name);
+        if !path.join(VAULT_FILE_NAME).is_file() {
+            return Err(Error::VaultNotFound)
+        }
+
+        verify_vault_file(&path, &key)?;
+        Ok(VaultDiskDirectory {
+            name: name.to_string(),
+            key,
+            dir: RootDiskDirectory::at(path),
+        })
+    }
+
+    /// List the names of the vaults under the given root keys directory
+    pub fn list<P>(root: P) -> Result<Vec<String>, Error>
+    where
+        P: AsRef<Path>, {
+        let vaults_path = root.as_ref().join(VAULTS_DIR_NAME);
+        if !vaults_path.exists() {
+            return Ok(Vec::new())
+        }
+
+        let mut names: Vec<String> = fs::read_dir(vaults_path)?
+            .flat_map(Result::ok)
+            .filter(|entry| entry.path().join(VAULT_FILE_NAME).is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| check_vault_name(name).is_ok())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+}
+
+impl KeyDirectory for VaultDiskDirectory {
+    fn load(&self) -> Result<Vec<SafeAccount>, Error> {
+        self.dir.load()
+    }
+
+    fn update(&self, account: SafeAccount) -> Result<SafeAccount, Error> {
+        self.dir.update(account)
+    }
+
+    fn insert(&self, account: SafeAccount) -> Result<SafeAccount, Error> {
+        self.dir.insert(account)
+    }
+
+    fn remove(&self, account: &SafeAccount) -> Result<(), Error> {
+        self.dir.remove(account)
+    }
+
+    fn path(&self) -> Option<&PathBuf> {
+        self.dir.path()
+    }
+
+    fn unique_repr(&self) -> Result<u64, Error> {
+        self.dir.unique_repr()
+    }
+}
+
+impl VaultKeyDirectory for VaultDiskDirectory {
+    fn as_key_directory(&self) -> &dyn KeyDirectory {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn key(&self) -> VaultKey {
+        self.key.clone()
+    }
+}
+
+fn vault_path<P>(root: P, name: &str) -> PathBuf
+where
+    P: AsRef<Path>, {
+    root.as_ref().join(VAULTS_DIR_NAME).join(name)
+}
+
+/// Vault names are used as directory names, so only alphanumerics, `-` and `_` are allowed.
+fn check_vault_name(name: &str) -> Result<(), Error> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(Error::InvalidVaultName)
+    }
+    Ok(())
+}
+
+fn create_vault_file(path: &Path, key: &VaultKey) -> Result<(), Error> {
+    let blob: [u8; 32] = Random::random();
+    let crypto = Crypto::with_plain(&blob, &key.password, key.iterations)?;
+    let vault_file = json::VaultFile {
+        crypto: crypto.into(),
+        meta: None,
+    };
+
+    let mut file = fs::File::create(path.join(VAULT_FILE_NAME))?;
+    vault_file.write(&mut file).map_err(|e| Error::Custom(format!("{:?}", e)))?;
+    file.flush()?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn verify_vault_file(path: &Path, key: &VaultKey) -> Result<(), Error> {
+    let file = fs::File::open(path.join(VAULT_FILE_NAME))?;
+    let vault_file = json::VaultFile::load(file).map_err(|e| Error::Custom(format!("{:?}", e)))?;
+    // The MAC doesn't match if the password is wrong.
+    Crypto::from(vault_file.crypto).decrypt(&key.password)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempdir;
+
+    use self::tempdir::TempDir;
+    use super::*;
+
+    fn key(password: &str) -> VaultKey {
+        VaultKey::new(&password.into(), 1024)
+    }
+
+    #[test]
+    fn create_and_open_vault() {
+        let root = TempDir::new("").unwrap();
+
+        let vault = VaultDiskDirectory::create(root.path(), "vault", key("password")).unwrap();
+        assert_eq!("vault", vault.name());
+        assert!(root.path().join("vaults").join("vault").join("vault.json").is_file());
+        assert_eq!(0, vault.load().unwrap().len(), "The vault file is not a key file");
+
+        assert!(VaultDiskDirectory::at(root.path(), "vault", key("password")).is_ok());
+        assert_eq!(vec!["vault".to_string()], VaultDiskDirectory::list(root.path()).unwrap());
+    }
+
+    #[test]
+    fn open_vault_with_wrong_password() {
+        let root = TempDir::new("").unwrap();
+        VaultDiskDirectory::create(root.path(), "vault", key("password")).unwrap();
+
+        match VaultDiskDirectory::at(root.path(), "vault", key("wrong")) {
+            Err(Error::InvalidPassword) => {}
+            Err(err) => panic!("Unexpected error: {}", err),
+            Ok(_) => panic!("The vault must not be opened with a wrong password"),
+        }
+    }
+
+    #[test]
+    fn vault_name_is_checked() {
+        let root = TempDir::new("").unwrap();
+        for name in &["", "..", "a/b", "a b"] {
+            match VaultDiskDirectory::create(root.path(), name, key("password")) {
+                Err(Error::InvalidVaultName) => {}
+                _ => panic!("{:?} is not a valid vault name", name),
+            }
+        }
+        match VaultDiskDirectory::at(root.path(), "not-created", key("password")) {
+            Err(Error::VaultNotFound) => {}
+            _ => panic!("The vault doesn't exist"),
+        }
+    }
+}
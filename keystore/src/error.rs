@@ -38,6 +38,14 @@ pub enum Error {
     CreationFailed,
     /// Account already exists.
     AlreadyExists,
+    /// Vault not found, or not opened.
+    VaultNotFound,
+    /// Vault name is invalid.
+    InvalidVaultName,
+    /// The key directory doesn't support vaults.
+    VaultsAreNotSupported,
+    /// Account is not a master key, so no account can be derived from it.
+    NotMasterKey,
     /// `ckeys` error
     CKey(CKeyError),
     /// `CCrypto` error
@@ -57,6 +65,10 @@ impl fmt::Display for Error {
             Error::InvalidKeyFile(ref reason) => format!("Invalid key file: {}", reason),
             Error::CreationFailed => "Account creation failed".into(),
             Error::AlreadyExists => "Account already exists".into(),
+            Error::VaultNotFound => "Vault not found".into(),
+            Error::InvalidVaultName => "Invalid vault name".into(),
+            Error::VaultsAreNotSupported => "Vaults are not supported".into(),
+            Error::NotMasterKey => "Account is not a master key".into(),
             Error::CKey(ref err) => err.to_string(),
             Error::CCrypto(ref err) => err.to_string(),
             Error::Custom(ref s) => s.clone(),
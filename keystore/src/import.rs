@@ -16,6 +16,7 @@
 
 use crate::accounts_dir::{DiskKeyFileManager, KeyDirectory, KeyFileManager};
 use crate::Error;
+use cjson::de::Mode;
 use ckey::Address;
 use std::collections::HashSet;
 use std::ffi::OsStr;
@@ -27,7 +28,9 @@ pub fn import_account(path: &Path, dst: &dyn KeyDirectory) -> Result<Address, Er
     let key_manager = DiskKeyFileManager;
     let existing_accounts = dst.load()?.into_iter().map(|a| a.address).collect::<HashSet<_>>();
     let filename = path.file_name().and_then(OsStr::to_str).map(ToOwned::to_owned);
-    let account = fs::File::open(&path).map_err(Into::into).and_then(|file| key_manager.read(filename, file))?;
+    // The file may be written by another wallet.
+    let account =
+        fs::File::open(&path).map_err(Into::into).and_then(|file| key_manager.read(filename, file, Mode::Lenient))?;
 
     let address = account.address;
     if !existing_accounts.contains(&address) {
@@ -85,7 +85,7 @@ impl<'a> Deserialize<'a> for Crypto {
     fn deserialize<D>(deserializer: D) -> Result<Crypto, D::Error>
     where
         D: Deserializer<'a>, {
-        static FIELDS: &[&str] = &["id", "version", "crypto", "Crypto", "address"];
+        static FIELDS: &[&str] = &["cipher", "cipherparams", "ciphertext", "kdf", "kdfparams", "mac"];
         deserializer.deserialize_struct("Crypto", FIELDS, CryptoVisitor)
     }
 }
@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::{Crypto, Uuid, Version, H160};
+use cjson::de::{self, Mode};
 use serde::{Serialize, Serializer};
 use serde_json;
 use std::io::{Read, Write};
@@ -54,10 +55,14 @@ pub struct KeyFile {
 }
 
 impl KeyFile {
-    pub fn load<R>(reader: R) -> Result<Self, serde_json::Error>
+    /// Loads a key file.
+    ///
+    /// Key files written by other wallets may use different letter cases and extra fields,
+    /// so they should be loaded in `Mode::Lenient`.
+    pub fn load<R>(reader: R, mode: Mode) -> Result<Self, de::Error>
     where
         R: Read, {
-        serde_json::from_reader(reader)
+        de::from_reader(reader, mode)
     }
 
     pub fn write<W>(&self, writer: &mut W) -> Result<(), serde_json::Error>
@@ -71,10 +76,21 @@ impl KeyFile {
 mod tests {
     use std::str::FromStr;
 
+    use cjson::de::{Error, Mode};
     use serde_json;
 
     use crate::json::{Aes128Ctr, Cipher, Crypto, Kdf, KeyFile, Scrypt, Uuid, Version};
 
+    fn rejected_at(json: &str, mode: Mode) -> String {
+        match KeyFile::load(json.as_bytes(), mode).unwrap_err() {
+            Error::Data {
+                pointer,
+                ..
+            } => pointer,
+            Error::Syntax(err) => panic!("Unexpected syntax error: {}", err),
+        }
+    }
+
     #[test]
     fn basic_keyfile() {
         let json = r#"
@@ -289,4 +305,51 @@ mod tests {
 
         assert_eq!(file, deserialized);
     }
+
+    #[test]
+    fn foreign_keyfile_is_loaded_in_lenient_mode() {
+        let json = include_str!("../../tests/res/keyfile/foreign.json");
+        let expected = KeyFile {
+            id: Uuid::from_str("8777d9f6-7860-4b9b-88b7-0b57ee6b3a73").unwrap(),
+            version: Version::V3,
+            address: Some("6edddfc6349aff20bc6467ccf276c5b52487f7a8".into()),
+            crypto: Crypto {
+                cipher: Cipher::Aes128Ctr(Aes128Ctr {
+                    iv: "b5a7ec855ec9e2c405371356855fec83".into(),
+                }),
+                ciphertext: "7203da0676d141b138cd7f8e1a4365f59cc1aa6978dc5443f364ca943d7cb4bc".into(),
+                kdf: Kdf::Scrypt(Scrypt {
+                    n: 262_144,
+                    dklen: 32,
+                    p: 1,
+                    r: 8,
+                    salt: "1e8642fdf1f87172492c1412fc62f8db75d796cdfa9c53c3f2b11e44a2a1b209".into(),
+                }),
+                mac: "46325c5d4e8c991ad2683d525c7854da387138b6ca45068985aa4959fa2b8c8f".into(),
+            },
+            meta: None,
+        };
+
+        assert_eq!(expected, KeyFile::load(json.as_bytes(), Mode::Lenient).unwrap());
+        assert_eq!("", rejected_at(json, Mode::Strict));
+    }
+
+    #[test]
+    fn short_mac_is_rejected() {
+        let json = include_str!("../../tests/res/keyfile/short_mac.json");
+        assert_eq!("/crypto/mac", rejected_at(json, Mode::Strict));
+        assert_eq!("/crypto/mac", rejected_at(json, Mode::Lenient));
+    }
+
+    #[test]
+    fn unknown_kdf_params_are_rejected() {
+        let json = include_str!("../../tests/res/keyfile/unknown_kdf_params.json");
+        assert_eq!("/crypto/kdfparams", rejected_at(json, Mode::Strict));
+    }
+
+    #[test]
+    fn unknown_crypto_field_is_rejected_in_strict_mode() {
+        let json = include_str!("../../tests/res/keyfile/foreign.json").replace("\"Crypto\"", "\"crypto\"");
+        assert_eq!("/crypto/CipherText", rejected_at(&json, Mode::Strict));
+    }
 }
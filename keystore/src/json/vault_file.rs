@@ -0,0 +1,41 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::Crypto;
+use serde_json;
+use std::io::{Read, Write};
+
+/// The metadata of a vault.
+/// The crypto is used to verify the vault password.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct VaultFile {
+    pub crypto: Crypto,
+    pub meta: Option<String>,
+}
+
+impl VaultFile {
+    pub fn load<R>(reader: R) -> Result<Self, serde_json::Error>
+    where
+        R: Read, {
+        serde_json::from_reader(reader)
+    }
+
+    pub fn write<W>(&self, writer: &mut W) -> Result<(), serde_json::Error>
+    where
+        W: Write, {
+        serde_json::to_writer(writer, self)
+    }
+}
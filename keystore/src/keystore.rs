@@ -15,18 +15,22 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::account::{DecryptedAccount, SafeAccount};
-use crate::accounts_dir::KeyDirectory;
+use crate::accounts_dir::{KeyDirectory, VaultKey, VaultKeyDirectory};
 use crate::json::{self, OpaqueKeyFile, Uuid};
 use crate::random::Random;
 use crate::{Error, SecretStore, SimpleSecretStore};
 use ccrypto::KEY_ITERATIONS;
-use ckey::{Address, KeyPair, Password, Secret};
+use cjson::de::Mode;
+use ckey::{Address, DerivationPath, ExtendedKeyPair, KeyPair, Password, Secret, Seed};
 use parking_lot::{Mutex, RwLock};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::mem;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
+/// How often the key directory is checked for the accounts added by others by default.
+pub const DEFAULT_REFRESH_TIME: Duration = Duration::from_secs(1);
+
 /// Accounts store.
 pub struct KeyStore {
     store: KeyMultiStore,
@@ -45,15 +49,23 @@ impl KeyStore {
         })
     }
 
-    /// Modify account refresh timeout - how often they are re-read from `KeyDirectory`.
+    /// Modify account refresh timeout - how often the modification time of `KeyDirectory` is checked.
     ///
-    /// Setting this to low values (or 0) will cause new accounts to be picked up quickly,
-    /// although it may induce heavy disk reads and is not recommended if you manage many keys (say over 10k).
+    /// The accounts are kept in memory and looking up a known account never touches `KeyDirectory`.
+    /// The directory is checked at most once per the refresh time, when an account is not found or the accounts
+    /// are enumerated, and it's re-read only if it has changed since the last check.
+    /// So the accounts added by others are eventually seen, after at most the refresh time.
+    /// Call `reload` to see them immediately.
     ///
-    /// By default refreshing is disabled, so only accounts created using this instance of `KeyStore` are taken into account.
+    /// The default is `DEFAULT_REFRESH_TIME`.
     pub fn set_refresh_time(&self, time: Duration) {
         self.store.set_refresh_time(time)
     }
+
+    /// Re-read the accounts from `KeyDirectory`, to see the modifications made by others.
+    pub fn reload(&self) -> Result<(), Error> {
+        self.store.reload()
+    }
 }
 
 impl SimpleSecretStore for KeyStore {
@@ -99,7 +111,7 @@ impl SimpleSecretStore for KeyStore {
 impl SecretStore for KeyStore {
     fn import_wallet(&self, json: &[u8], password: &Password, gen_id: bool) -> Result<Address, Error> {
         let json_keyfile =
-            json::KeyFile::load(json).map_err(|err| Error::InvalidKeyFile(format!("Invalid JSON format: {}", err)))?;
+            json::KeyFile::load(json, Mode::Lenient).map_err(|err| Error::InvalidKeyFile(err.to_string()))?;
         let mut safe_account = SafeAccount::from_file(json_keyfile, None, Some(password))?;
 
         if gen_id {
@@ -112,7 +124,7 @@ impl SecretStore for KeyStore {
     }
 
     fn test_password(&self, account: &Address, password: &Password) -> Result<bool, Error> {
-        match self.store.get_verified_account(account, password) {
+        match self.store.get_secret(account, password) {
             Ok(_) => Ok(true),
             Err(Error::InvalidPassword) => Ok(false),
             Err(err) => Err(err),
@@ -126,7 +138,7 @@ impl SecretStore for KeyStore {
         password: &Password,
         new_password: &Password,
     ) -> Result<(), Error> {
-        let secret = self.store.get_verified_account(account, password)?.secret;
+        let secret = self.store.get_secret(account, password)?;
         new_store.insert_account(secret, new_password)?;
         Ok(())
     }
@@ -153,18 +165,69 @@ impl SecretStore for KeyStore {
     fn local_path(&self) -> PathBuf {
         self.store.dir.path().cloned().unwrap_or_else(PathBuf::new)
     }
+
+    fn create_vault(&self, name: &str, password: &Password) -> Result<(), Error> {
+        self.store.create_vault(name, password)
+    }
+
+    fn open_vault(&self, name: &str, password: &Password) -> Result<(), Error> {
+        self.store.open_vault(name, password)
+    }
+
+    fn close_vault(&self, name: &str) -> Result<(), Error> {
+        self.store.close_vault(name)
+    }
+
+    fn list_vaults(&self) -> Result<Vec<String>, Error> {
+        self.store.list_vaults()
+    }
+
+    fn list_opened_vaults(&self) -> Result<Vec<String>, Error> {
+        Ok(self.store.list_opened_vaults())
+    }
+
+    fn change_account_vault(&self, account: &Address, vault: Option<&str>) -> Result<(), Error> {
+        self.store.change_account_vault(account, vault)
+    }
+
+    fn vault_accounts(&self, name: &str) -> Result<Vec<Address>, Error> {
+        self.store.vault_accounts(name)
+    }
+
+    fn insert_master_seed(&self, seed: &Seed, password: &Password) -> Result<Address, Error> {
+        let master_key = ExtendedKeyPair::from_seed(seed)?;
+        if self.has_account(&master_key.keypair().address())? {
+            Err(Error::AlreadyExists)
+        } else {
+            self.store.insert_master_key(&master_key, password)
+        }
+    }
+
+    fn derive_account(&self, master: &Address, path: &DerivationPath, password: &Password) -> Result<Address, Error> {
+        self.store.derive_account(master, path, password)
+    }
 }
 
 /// Similar to `KeyStore` but may store many accounts (with different passwords) for the same `Address`
 pub struct KeyMultiStore {
     dir: Box<dyn KeyDirectory>,
     iterations: u32,
-    // order lock: cache
+    // order lock: cache, vaults
     cache: RwLock<BTreeMap<Address, Vec<SafeAccount>>>,
+    vaults: Mutex<HashMap<String, Box<dyn VaultKeyDirectory>>>,
+    derived: RwLock<HashMap<Address, DerivedAccount>>,
     timestamp: Mutex<Timestamp>,
 }
 
+/// Account derived from a master key account. Its secret is not stored.
+#[derive(Clone)]
+struct DerivedAccount {
+    master: Address,
+    path: DerivationPath,
+}
+
 struct Timestamp {
+    /// `unique_repr` of the directory when the accounts were loaded. `None` if it should be loaded again.
     dir_hash: Option<u64>,
     last_checked: Instant,
     refresh_time: Duration,
@@ -182,27 +245,41 @@ impl KeyMultiStore {
             dir: directory,
             iterations,
             cache: Default::default(),
+            vaults: Default::default(),
+            derived: Default::default(),
             timestamp: Mutex::new(Timestamp {
                 dir_hash: None,
                 last_checked: Instant::now(),
-                // by default we never refresh accounts
-                refresh_time: Duration::from_secs(u64::max_value()),
+                refresh_time: DEFAULT_REFRESH_TIME,
             }),
         };
-        store.reload_accounts()?;
+        store.reload()?;
         Ok(store)
     }
 
-    /// Modify account refresh timeout - how often they are re-read from `KeyDirectory`.
-    ///
-    /// Setting this to low values (or 0) will cause new accounts to be picked up quickly,
-    /// although it may induce heavy disk reads and is not recommended if you manage many keys (say over 10k).
+    /// Modify account refresh timeout - how often the modification time of `KeyDirectory` is checked.
     ///
-    /// By default refreshing is disabled, so only accounts created using this instance of `KeyStore` are taken into account.
+    /// See `KeyStore::set_refresh_time`.
     pub fn set_refresh_time(&self, time: Duration) {
         self.timestamp.lock().refresh_time = time;
     }
 
+    /// Re-read the accounts from `KeyDirectory`, to see the modifications made by others.
+    pub fn reload(&self) -> Result<(), Error> {
+        let mut last_timestamp = self.timestamp.lock();
+        let dir_hash = Some(self.dir.unique_repr()?);
+        last_timestamp.last_checked = Instant::now();
+        self.reload_accounts()?;
+        last_timestamp.dir_hash = dir_hash;
+        Ok(())
+    }
+
+    /// Our own writes are already in the cache, but the next check should re-read the directory,
+    /// because the modification time of the directory may not change within its resolution.
+    fn invalidate_dir_hash(&self) {
+        self.timestamp.lock().dir_hash = None;
+    }
+
     fn reload_if_changed(&self) -> Result<(), Error> {
         let mut last_timestamp = self.timestamp.lock();
         let now = Instant::now();
@@ -226,6 +303,13 @@ impl KeyMultiStore {
             let account_ref = account.address;
             new_accounts.entry(account_ref).or_insert_with(Vec::new).push(account);
         }
+        for (name, vault) in self.vaults.lock().iter() {
+            for mut account in vault.load()? {
+                account.vault = Some(name.clone());
+                let account_ref = account.address;
+                new_accounts.entry(account_ref).or_insert_with(Vec::new).push(account);
+            }
+        }
 
         mem::replace(&mut *cache, new_accounts);
         Ok(())
@@ -278,9 +362,67 @@ impl KeyMultiStore {
         Err(Error::InvalidPassword)
     }
 
+    /// Get the secret of a stored account, or of an account derived from a master key.
+    /// The secret of a derived account is decrypted with the password of its master key.
+    fn get_secret(&self, account: &Address, password: &Password) -> Result<Secret, Error> {
+        match self.get_verified_account(account, password) {
+            Err(Error::InvalidAccount) => {}
+            result => return result.map(|verified| verified.secret),
+        }
+
+        let derived = self.derived.read().get(account).cloned().ok_or(Error::InvalidAccount)?;
+        let key = self.derive_key(&derived.master, &derived.path, password)?;
+        Ok(**key.keypair().private())
+    }
+
+    fn derive_key(
+        &self,
+        master: &Address,
+        path: &DerivationPath,
+        password: &Password,
+    ) -> Result<ExtendedKeyPair, Error> {
+        let masters: Vec<_> =
+            self.get_safe_accounts(master)?.into_iter().filter(|account| account.crypto.is_master_key()).collect();
+        if masters.is_empty() {
+            return Err(Error::NotMasterKey)
+        }
+        for account in masters {
+            match account.crypto.master_key(password) {
+                Ok(master_key) => return Ok(master_key.derive(path)?),
+                Err(Error::InvalidPassword) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Err(Error::InvalidPassword)
+    }
+
+    /// Insert an account that holds the master key.
+    pub fn insert_master_key(&self, master_key: &ExtendedKeyPair, password: &Password) -> Result<Address, Error> {
+        let id: [u8; 16] = Random::random();
+        let account = SafeAccount::create_master(master_key, id, password, self.iterations, "{}".to_string())?;
+        self.import(account)
+    }
+
+    /// Derive an account from the master key account.
+    /// The derived account is usable until the store is closed, while only the master key is stored.
+    pub fn derive_account(
+        &self,
+        master: &Address,
+        path: &DerivationPath,
+        password: &Password,
+    ) -> Result<Address, Error> {
+        let address = self.derive_key(master, path, password)?.keypair().address();
+        self.derived.write().insert(address, DerivedAccount {
+            master: *master,
+            path: path.clone(),
+        });
+        Ok(address)
+    }
+
     fn import(&self, account: SafeAccount) -> Result<Address, Error> {
         // save to file
         let account = self.dir.insert(account)?;
+        self.invalidate_dir_hash();
 
         // update cache
         let account_ref = account.address;
@@ -292,21 +434,28 @@ impl KeyMultiStore {
 
     fn update(&self, account_ref: &Address, old: &SafeAccount, new: SafeAccount) -> Result<(), Error> {
         // save to file
-        let account = self.dir.update(new)?;
+        let vault = new.vault.clone();
+        let account = self.with_dir(&vault, |dir| dir.update(new))?;
+        self.invalidate_dir_hash();
 
         // update cache
+        self.replace_cached_account(account_ref, old, account);
+        Ok(())
+    }
+
+    fn replace_cached_account(&self, account_ref: &Address, old: &SafeAccount, new: SafeAccount) {
         let mut cache = self.cache.write();
         let accounts = cache.entry(*account_ref).or_insert_with(Vec::new);
         // Remove old account
         accounts.retain(|acc| acc != old);
         // And push updated to the end
-        accounts.push(account);
-        Ok(())
+        accounts.push(new);
     }
 
     fn remove_safe_account(&self, account_ref: &Address, account: &SafeAccount) -> Result<(), Error> {
         // Remove from dir
-        self.dir.remove(&account)?;
+        self.with_dir(&account.vault, |dir| dir.remove(&account))?;
+        self.invalidate_dir_hash();
 
         // Remove from cache
         let mut cache = self.cache.write();
@@ -327,6 +476,125 @@ impl KeyMultiStore {
 
         Ok(())
     }
+
+    /// Run `f` with the root directory, or with the given vault if it's opened.
+    fn with_dir<F, R>(&self, vault: &Option<String>, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&dyn KeyDirectory) -> Result<R, Error>, {
+        match vault {
+            None => f(&*self.dir),
+            Some(name) => {
+                let vaults = self.vaults.lock();
+                let vault = vaults.get(name).ok_or(Error::VaultNotFound)?;
+                f(vault.as_key_directory())
+            }
+        }
+    }
+
+    /// Create a new vault and open it.
+    pub fn create_vault(&self, name: &str, password: &Password) -> Result<(), Error> {
+        let provider = self.dir.as_vault_provider().ok_or(Error::VaultsAreNotSupported)?;
+        let vault = provider.create(name, VaultKey::new(password, self.iterations))?;
+        self.vaults.lock().insert(name.to_string(), vault);
+        Ok(())
+    }
+
+    /// Open a vault, so its accounts are listed and usable.
+    ///
+    /// The opened vaults are kept in memory with their keys, and nothing on disk records them.
+    /// So every vault is closed when the store is opened, and the vaults must be opened again after a restart.
+    pub fn open_vault(&self, name: &str, password: &Password) -> Result<(), Error> {
+        if let Some(vault) = self.vaults.lock().get(name) {
+            return if vault.key().password == *password {
+                Ok(())
+            } else {
+                Err(Error::InvalidPassword)
+            }
+        }
+
+        let provider = self.dir.as_vault_provider().ok_or(Error::VaultsAreNotSupported)?;
+        let vault = provider.open(name, VaultKey::new(password, self.iterations))?;
+        let accounts = vault.load()?;
+
+        let mut cache = self.cache.write();
+        self.vaults.lock().insert(name.to_string(), vault);
+        for mut account in accounts {
+            account.vault = Some(name.to_string());
+            cache.entry(account.address).or_insert_with(Vec::new).push(account);
+        }
+        Ok(())
+    }
+
+    /// Close a vault. Its accounts are not listed nor usable until it's opened again.
+    pub fn close_vault(&self, name: &str) -> Result<(), Error> {
+        let mut cache = self.cache.write();
+        if self.vaults.lock().remove(name).is_none() {
+            return Err(Error::VaultNotFound)
+        }
+
+        for accounts in cache.values_mut() {
+            accounts.retain(|account| account.vault.as_ref().map_or(true, |vault| vault != name));
+        }
+        let emptied: Vec<_> =
+            cache.iter().filter(|(_, accounts)| accounts.is_empty()).map(|(account_ref, _)| *account_ref).collect();
+        for account_ref in emptied {
+            cache.remove(&account_ref);
+        }
+        Ok(())
+    }
+
+    /// List the names of all vaults, including the closed ones.
+    pub fn list_vaults(&self) -> Result<Vec<String>, Error> {
+        let provider = self.dir.as_vault_provider().ok_or(Error::VaultsAreNotSupported)?;
+        provider.list_vaults()
+    }
+
+    /// List the names of the opened vaults.
+    pub fn list_opened_vaults(&self) -> Vec<String> {
+        let mut names: Vec<_> = self.vaults.lock().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Move an account to the given vault, or to the root directory if `vault` is `None`.
+    /// The account keeps its password.
+    pub fn change_account_vault(&self, account_ref: &Address, vault: Option<&str>) -> Result<(), Error> {
+        let vault = vault.map(ToString::to_string);
+        if let Some(name) = &vault {
+            if !self.vaults.lock().contains_key(name) {
+                return Err(Error::VaultNotFound)
+            }
+        }
+
+        for account in self.get_safe_accounts(account_ref)? {
+            if account.vault == vault {
+                continue
+            }
+            let mut moved = account.clone();
+            moved.vault = vault.clone();
+            let moved = self.with_dir(&vault, |dir| dir.insert(moved))?;
+            self.with_dir(&account.vault, |dir| dir.remove(&account))?;
+            self.replace_cached_account(account_ref, &account, moved);
+        }
+        Ok(())
+    }
+
+    /// List the accounts in an opened vault.
+    pub fn vault_accounts(&self, name: &str) -> Result<Vec<Address>, Error> {
+        if !self.vaults.lock().contains_key(name) {
+            return Err(Error::VaultNotFound)
+        }
+        self.reload_if_changed()?;
+        Ok(self
+            .cache
+            .read()
+            .iter()
+            .filter(|(_, accounts)| {
+                accounts.iter().any(|account| account.vault.as_ref().map_or(false, |vault| vault == name))
+            })
+            .map(|(account_ref, _)| *account_ref)
+            .collect())
+    }
 }
 
 impl SimpleSecretStore for KeyMultiStore {
@@ -339,19 +607,26 @@ impl SimpleSecretStore for KeyMultiStore {
 
     fn accounts(&self) -> Result<Vec<Address>, Error> {
         self.reload_if_changed()?;
-        Ok(self.cache.read().keys().cloned().collect())
+        let cache = self.cache.read();
+        let mut accounts: Vec<_> = cache.keys().cloned().collect();
+        accounts.extend(self.derived.read().keys().filter(|account| !cache.contains_key(account)));
+        Ok(accounts)
     }
 
     fn has_account(&self, account: &Address) -> Result<bool, Error> {
         match self.get_safe_accounts(account) {
             Ok(_) => Ok(true),
-            Err(Error::InvalidAccount) => Ok(false),
+            Err(Error::InvalidAccount) => Ok(self.derived.read().contains_key(account)),
             Err(e) => Err(e),
         }
     }
 
     fn remove_account(&self, account_ref: &Address) -> Result<(), Error> {
-        let accounts = self.get_safe_accounts(account_ref)?;
+        let is_derived = self.derived.write().remove(account_ref).is_some();
+        let accounts = match self.get_safe_accounts(account_ref) {
+            Err(Error::InvalidAccount) if is_derived => return Ok(()),
+            result => result?,
+        };
 
         for account in accounts {
             self.remove_safe_account(account_ref, &account)?;
@@ -389,7 +664,7 @@ impl SimpleSecretStore for KeyMultiStore {
     }
 
     fn decrypt_account(&self, account: &Address, password: &Password) -> Result<DecryptedAccount, Error> {
-        Ok(DecryptedAccount::new(self.get_verified_account(account, password)?.secret))
+        Ok(DecryptedAccount::new(self.get_secret(account, password)?))
     }
 }
 
@@ -402,10 +677,14 @@ struct VerifiedAccount {
 mod tests {
     extern crate tempdir;
 
-    use ckey::{Generator, Random};
+    use ckey::{verify, Generator, Random};
 
+    use self::tempdir::TempDir;
     use super::*;
-    use crate::accounts_dir::MemoryDirectory;
+    use crate::accounts_dir::{MemoryDirectory, RootDiskDirectory};
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
     fn keypair() -> KeyPair {
         Random.generate().unwrap()
@@ -419,6 +698,10 @@ mod tests {
         KeyMultiStore::open(Box::new(MemoryDirectory::default())).expect("MemoryDirectory always load successfuly; qed")
     }
 
+    fn disk_store(dir: &TempDir) -> KeyStore {
+        KeyStore::open_with_iterations(Box::new(RootDiskDirectory::create(dir.path()).unwrap()), 1024).unwrap()
+    }
+
     #[test]
     fn insert_account_successfully() {
         // given
@@ -538,4 +821,248 @@ mod tests {
         // then
         assert!(exported.is_ok(), "Should export single account: {:?}", exported);
     }
+
+    #[test]
+    fn create_open_and_close_vault() {
+        // given
+        let dir = TempDir::new("").unwrap();
+        let store = disk_store(&dir);
+        let keypair = keypair();
+        let address = store.insert_account(**keypair.private(), &"test".into()).unwrap();
+
+        // when
+        store.create_vault("vault", &"vault password".into()).unwrap();
+        store.change_account_vault(&address, Some("vault")).unwrap();
+
+        // then
+        assert_eq!(store.list_vaults().unwrap(), vec!["vault".to_string()]);
+        assert_eq!(store.list_opened_vaults().unwrap(), vec!["vault".to_string()]);
+        assert_eq!(store.vault_accounts("vault").unwrap(), vec![address]);
+
+        // when
+        store.close_vault("vault").unwrap();
+
+        // then
+        assert_eq!(store.list_opened_vaults().unwrap(), Vec::<String>::new());
+        assert_eq!(store.accounts().unwrap(), vec![], "The accounts in a closed vault are not listed.");
+        assert_matches!(store.decrypt_account(&address, &"test".into()), Err(Error::InvalidAccount));
+        assert_matches!(store.vault_accounts("vault"), Err(Error::VaultNotFound));
+
+        // when
+        let reopened = disk_store(&dir);
+
+        // then
+        assert_eq!(reopened.accounts().unwrap(), vec![], "Vaults are closed when the store is opened.");
+        reopened.open_vault("vault", &"vault password".into()).unwrap();
+        assert_eq!(reopened.accounts().unwrap(), vec![address]);
+        assert!(reopened.decrypt_account(&address, &"test".into()).is_ok());
+    }
+
+    #[test]
+    fn open_vault_with_wrong_password() {
+        // given
+        let dir = TempDir::new("").unwrap();
+        let store = disk_store(&dir);
+        store.create_vault("vault", &"vault password".into()).unwrap();
+
+        // then
+        assert_matches!(store.open_vault("vault", &"wrong".into()), Err(Error::InvalidPassword));
+        store.close_vault("vault").unwrap();
+        assert_matches!(store.open_vault("vault", &"wrong".into()), Err(Error::InvalidPassword));
+        assert_eq!(store.list_opened_vaults().unwrap(), Vec::<String>::new());
+        assert_matches!(store.open_vault("unknown", &"vault password".into()), Err(Error::VaultNotFound));
+    }
+
+    #[test]
+    fn move_account_into_and_out_of_vault() {
+        // given
+        let dir = TempDir::new("").unwrap();
+        let store = disk_store(&dir);
+        let keypair = keypair();
+        let address = store.insert_account(**keypair.private(), &"test".into()).unwrap();
+        store.create_vault("vault", &"vault password".into()).unwrap();
+        assert_matches!(store.change_account_vault(&address, Some("unknown")), Err(Error::VaultNotFound));
+
+        // when
+        store.change_account_vault(&address, Some("vault")).unwrap();
+
+        // then
+        let vault_path = dir.path().join("vaults").join("vault");
+        assert_eq!(fs::read_dir(&vault_path).unwrap().count(), 2, "The vault holds vault.json and the key file.");
+        assert!(store.test_password(&address, &"test".into()).unwrap(), "The account keeps its password.");
+
+        // when
+        store.change_account_vault(&address, None).unwrap();
+        store.close_vault("vault").unwrap();
+
+        // then
+        assert_eq!(fs::read_dir(&vault_path).unwrap().count(), 1);
+        assert_eq!(store.accounts().unwrap(), vec![address]);
+        assert!(store.decrypt_account(&address, &"test".into()).is_ok());
+    }
+
+    #[test]
+    fn memory_directory_does_not_support_vaults() {
+        let store = store();
+        assert_matches!(store.create_vault("vault", &"vault password".into()), Err(Error::VaultsAreNotSupported));
+        assert_matches!(store.list_vaults(), Err(Error::VaultsAreNotSupported));
+    }
+
+    #[test]
+    fn derive_account_from_master_seed() {
+        // given
+        let store = store();
+        let seed = Seed::random().unwrap();
+        let master = store.insert_master_seed(&seed, &"test".into()).unwrap();
+        let path: DerivationPath = "m/0'/5".parse().unwrap();
+
+        // when
+        let derived = store.derive_account(&master, &path, &"test".into()).unwrap();
+
+        // then
+        let expected = ExtendedKeyPair::from_seed(&seed).unwrap().derive(&path).unwrap();
+        assert_eq!(derived, expected.keypair().address());
+        assert_eq!(store.accounts().unwrap().len(), 2);
+        assert!(store.has_account(&derived).unwrap());
+        assert!(store.test_password(&derived, &"test".into()).unwrap());
+        assert!(!store.test_password(&derived, &"x".into()).unwrap());
+
+        let message = Default::default();
+        let signature = store.decrypt_account(&derived, &"test".into()).unwrap().sign(&message).unwrap();
+        assert!(verify(expected.keypair().public(), &signature, &message).unwrap());
+        assert_matches!(store.export_account(&derived, &"test".into()), Err(Error::InvalidAccount));
+    }
+
+    #[test]
+    fn master_key_account_can_sign() {
+        // given
+        let store = store();
+        let seed = Seed::random().unwrap();
+        let master = store.insert_master_seed(&seed, &"test".into()).unwrap();
+        assert_matches!(store.insert_master_seed(&seed, &"test".into()), Err(Error::AlreadyExists));
+
+        // when
+        let message = Default::default();
+        let signature = store.decrypt_account(&master, &"test".into()).unwrap().sign(&message).unwrap();
+
+        // then
+        let master_key = ExtendedKeyPair::from_seed(&seed).unwrap();
+        assert!(verify(master_key.keypair().public(), &signature, &message).unwrap());
+    }
+
+    #[test]
+    fn derive_account_only_from_master_key() {
+        // given
+        let store = store();
+        let keypair = keypair();
+        let address = store.insert_account(**keypair.private(), &"test".into()).unwrap();
+        let master = store.insert_master_seed(&Seed::random().unwrap(), &"test".into()).unwrap();
+        let path = "m/0'".parse().unwrap();
+
+        // then
+        assert_matches!(store.derive_account(&address, &path, &"test".into()), Err(Error::NotMasterKey));
+        assert_matches!(store.derive_account(&master, &path, &"x".into()), Err(Error::InvalidPassword));
+
+        // when
+        let derived = store.derive_account(&master, &path, &"test".into()).unwrap();
+        store.remove_account(&derived).unwrap();
+
+        // then
+        assert!(!store.has_account(&derived).unwrap());
+        assert!(store.has_account(&master).unwrap());
+    }
+
+    /// Counts how many times the directory is walked or its modification time is read.
+    struct CountingDirectory {
+        dir: Box<dyn KeyDirectory>,
+        loads: Arc<AtomicUsize>,
+        unique_reprs: Arc<AtomicUsize>,
+    }
+
+    impl KeyDirectory for CountingDirectory {
+        fn load(&self) -> Result<Vec<SafeAccount>, Error> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            self.dir.load()
+        }
+
+        fn update(&self, account: SafeAccount) -> Result<SafeAccount, Error> {
+            self.dir.update(account)
+        }
+
+        fn insert(&self, account: SafeAccount) -> Result<SafeAccount, Error> {
+            self.dir.insert(account)
+        }
+
+        fn remove(&self, account: &SafeAccount) -> Result<(), Error> {
+            self.dir.remove(account)
+        }
+
+        fn unique_repr(&self) -> Result<u64, Error> {
+            self.unique_reprs.fetch_add(1, Ordering::SeqCst);
+            self.dir.unique_repr()
+        }
+    }
+
+    #[test]
+    fn lookups_of_known_accounts_do_not_hit_the_directory() {
+        // given
+        let dir = TempDir::new("").unwrap();
+        let loads = Arc::new(AtomicUsize::new(0));
+        let unique_reprs = Arc::new(AtomicUsize::new(0));
+        let store = KeyStore::open_with_iterations(
+            Box::new(CountingDirectory {
+                dir: Box::new(RootDiskDirectory::create(dir.path()).unwrap()),
+                loads: Arc::clone(&loads),
+                unique_reprs: Arc::clone(&unique_reprs),
+            }),
+            1024,
+        )
+        .unwrap();
+        store.set_refresh_time(Duration::from_secs(0));
+        let addresses: Vec<_> =
+            (0..32).map(|_| store.insert_account(**keypair().private(), &"test".into()).unwrap()).collect();
+        loads.store(0, Ordering::SeqCst);
+        unique_reprs.store(0, Ordering::SeqCst);
+
+        // when
+        for address in &addresses {
+            assert!(store.has_account(address).unwrap());
+            assert!(store.test_password(address, &"test".into()).unwrap());
+        }
+
+        // then
+        assert_eq!(0, loads.load(Ordering::SeqCst));
+        assert_eq!(0, unique_reprs.load(Ordering::SeqCst));
+
+        // An unknown account checks the directory, which is re-read once after our own writes.
+        assert!(!store.has_account(&keypair().address()).unwrap());
+        assert!(!store.has_account(&keypair().address()).unwrap());
+        assert_eq!(1, loads.load(Ordering::SeqCst));
+        assert_eq!(2, unique_reprs.load(Ordering::SeqCst));
+        assert_eq!(addresses.len(), store.accounts().unwrap().len());
+    }
+
+    #[test]
+    fn accounts_added_by_others_are_seen_after_reload() {
+        // given
+        let dir = TempDir::new("").unwrap();
+        let store = disk_store(&dir);
+        store.set_refresh_time(Duration::from_secs(3600));
+        let other = disk_store(&dir);
+
+        // when
+        let address = other.insert_account(**keypair().private(), &"test".into()).unwrap();
+
+        // then
+        assert!(!store.has_account(&address).unwrap());
+        assert!(store.accounts().unwrap().is_empty());
+
+        // when
+        store.reload().unwrap();
+
+        // then
+        assert!(store.has_account(&address).unwrap());
+        assert_eq!(vec![address], store.accounts().unwrap());
+        assert!(store.test_password(&address, &"test".into()).unwrap());
+    }
 }
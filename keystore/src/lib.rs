@@ -74,6 +74,6 @@ pub use crate::account::{Crypto, DecryptedAccount, SafeAccount};
 pub use crate::error::Error;
 pub use crate::import::{import_account, import_accounts};
 pub use crate::json::OpaqueKeyFile as KeyFile;
-pub use crate::keystore::{KeyMultiStore, KeyStore};
+pub use crate::keystore::{KeyMultiStore, KeyStore, DEFAULT_REFRESH_TIME};
 pub use crate::random::random_string;
 pub use crate::secret_store::{SecretStore, SimpleSecretStore};
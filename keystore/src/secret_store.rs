@@ -32,7 +32,7 @@
 
 use crate::json::{OpaqueKeyFile, Uuid};
 use crate::{DecryptedAccount, Error};
-use ckey::{Address, Password, Secret};
+use ckey::{Address, DerivationPath, Password, Secret, Seed};
 use std::path::PathBuf;
 
 
@@ -82,4 +82,27 @@ pub trait SecretStore: SimpleSecretStore {
 
     /// Returns local path of the store.
     fn local_path(&self) -> PathBuf;
+
+    /// Creates a new vault and opens it.
+    fn create_vault(&self, name: &str, password: &Password) -> Result<(), Error>;
+    /// Opens a vault, so its accounts are listed and usable.
+    ///
+    /// Only the running store remembers it, because the vault password is never written to disk.
+    /// Every vault is closed when the store is opened again.
+    fn open_vault(&self, name: &str, password: &Password) -> Result<(), Error>;
+    /// Closes a vault. It stays closed after a restart, since no vault is opened at startup.
+    fn close_vault(&self, name: &str) -> Result<(), Error>;
+    /// Returns the names of all vaults.
+    fn list_vaults(&self) -> Result<Vec<String>, Error>;
+    /// Returns the names of the opened vaults.
+    fn list_opened_vaults(&self) -> Result<Vec<String>, Error>;
+    /// Moves an account to the vault, or to the root directory if `vault` is `None`.
+    fn change_account_vault(&self, account: &Address, vault: Option<&str>) -> Result<(), Error>;
+    /// Returns the accounts in an opened vault.
+    fn vault_accounts(&self, name: &str) -> Result<Vec<Address>, Error>;
+
+    /// Inserts the master key of the seed as an account.
+    fn insert_master_seed(&self, seed: &Seed, password: &Password) -> Result<Address, Error>;
+    /// Derives an account from the master key account, which signs with the password of the master key.
+    fn derive_account(&self, master: &Address, path: &DerivationPath, password: &Password) -> Result<Address, Error>;
 }
@@ -198,7 +198,7 @@ impl Decodable for SocketAddr {
                 let port = rlp.val_at(4)?;
                 Ok(SocketAddr::v4(ip0, ip1, ip2, ip3, port))
             }
-            17 => unimplemented!(),
+            17 => Err(DecoderError::Custom("IPv6 addresses are not supported")),
             got => Err(DecoderError::RlpIncorrectListLen {
                 expected: 5,
                 got,
@@ -310,4 +310,17 @@ mod tests {
     fn encode_and_decode_nonce() {
         rlp_encode_and_decode_test!(SocketAddr::v4(1, 2, 3, 4, 5678));
     }
+
+    #[test]
+    fn decoding_ipv6_address_fails() {
+        let mut s = RlpStream::new_list(17);
+        for _ in 0..16 {
+            s.append(&0u8);
+        }
+        s.append(&3485u16);
+        assert_eq!(
+            Err(DecoderError::Custom("IPv6 addresses are not supported")),
+            Rlp::new(&s.out()).as_val::<SocketAddr>()
+        );
+    }
 }
@@ -14,14 +14,20 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::control::ExtensionState;
 use crate::p2p::Message as P2pMessage;
-use crate::{Api, IntoSocketAddr, NetworkExtension, NetworkExtensionResult, NodeId};
+use crate::request::{Frame, Requests};
+use crate::{
+    decode_message, Api, IntoSocketAddr, NetworkExtension, NetworkExtensionResult, NodeId, RequestError, RequestId,
+};
 use cio::IoChannel;
 use crossbeam_channel as crossbeam;
 use ctimer::{TimeoutHandler, TimerApi, TimerLoop, TimerToken};
 use parking_lot::{Mutex, RwLock};
 use primitives::Bytes;
+use rlp::Encodable;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread::{Builder, JoinHandle};
 use std::time::Duration;
@@ -31,10 +37,17 @@ struct ClientApi {
     timer: TimerApi,
     name: &'static str,
     need_encryption: bool,
+    requests: Arc<Mutex<Requests>>,
+    /// Delivers the requests failed before they are sent.
+    sender: crossbeam::Sender<ExtensionMessage>,
 }
 
-impl Api for ClientApi {
-    fn send(&self, id: &NodeId, data: Arc<Bytes>) {
+impl ClientApi {
+    fn send_frame(&self, id: &NodeId, frame: Frame) {
+        self.send_data(id, Arc::new(frame.rlp_bytes()));
+    }
+
+    fn send_data(&self, id: &NodeId, data: Arc<Bytes>) {
         let need_encryption = self.need_encryption;
         let extension_name = self.name;
         let node_id = *id;
@@ -57,9 +70,46 @@ impl Api for ClientApi {
             cdebug!(NETAPI, "`{}` sends {} bytes to {}", extension_name, bytes, id.into_addr());
         }
     }
+}
+
+impl Api for ClientApi {
+    fn send(&self, id: &NodeId, data: Arc<Bytes>) {
+        let is_framed = self.requests.lock().is_framed(id);
+        if is_framed {
+            self.send_frame(id, Frame::Message(data.to_vec()));
+        } else {
+            self.send_data(id, data);
+        }
+    }
+
+    fn request(&self, id: &NodeId, data: Arc<Bytes>, timeout: Duration) -> RequestId {
+        let started = self.requests.lock().start(id, timeout);
+        match started {
+            Ok(request) => {
+                self.send_frame(id, Frame::Request(request, data.to_vec()));
+                request
+            }
+            Err((request, err)) => {
+                if let Err(send_err) = self.sender.send(ExtensionMessage::RequestFailed(*id, request, err)) {
+                    cwarn!(NETAPI, "`{}` cannot fail request {}: {:?}", self.name, request, send_err);
+                }
+                request
+            }
+        }
+    }
+
+    fn respond(&self, id: &NodeId, request: RequestId, data: Arc<Bytes>) {
+        let is_framed = self.requests.lock().is_framed(id);
+        if is_framed {
+            self.send_frame(id, Frame::Response(request, data.to_vec()));
+        } else {
+            cwarn!(NETAPI, "`{}` cannot respond to {} that doesn't support requests", self.name, id.into_addr());
+        }
+    }
 
     fn set_timer(&self, token: TimerToken, duration: Duration) -> NetworkExtensionResult<()> {
-        self.timer.schedule_repeat(duration, token)?;
+        // The timeouts are handed over to the thread of the extension, so a slow extension doesn't hold the timer.
+        self.timer.schedule_periodic(self.name, duration, Duration::default(), token)?;
         Ok(())
     }
 
@@ -80,25 +130,54 @@ struct Extension {
     sender: Mutex<crossbeam::Sender<ExtensionMessage>>,
     quit: Mutex<crossbeam::Sender<()>>,
     join: Mutex<Option<JoinHandle<()>>>,
+    enabled: AtomicBool,
+    dropped_messages: AtomicUsize,
+    /// The versions negotiated with the nodes. They are kept while the extension is disabled.
+    nodes: Mutex<HashMap<NodeId, u64>>,
 }
 
 impl TimeoutHandler for Extension {
     fn on_timeout(&self, token: TimerToken) {
+        if !self.enabled.load(Ordering::SeqCst) {
+            return
+        }
         if let Err(err) = self.sender.lock().send(ExtensionMessage::Timeout(token)) {
             cwarn!(NETAPI, "{} cannot timeout {}: {:?}", self.name, token, err);
         }
     }
 }
 
-impl Drop for Extension {
-    fn drop(&mut self) {
-        let _ = self.quit.lock().send(());
+impl Extension {
+    /// Returns false if the extension is already in the state.
+    fn set_enabled(&self, enabled: bool) -> bool {
+        if self.enabled.swap(enabled, Ordering::SeqCst) == enabled {
+            return false
+        }
+        let message = if enabled {
+            ExtensionMessage::Enabled
+        } else {
+            ExtensionMessage::Disabled
+        };
+        if let Err(err) = self.sender.lock().send(message) {
+            cwarn!(NETAPI, "{} cannot be enabled({}): {:?}", self.name, enabled, err);
+        }
+        true
+    }
+
+    fn stop(&self) {
         if let Some(join) = self.join.lock().take() {
+            let _ = self.quit.lock().send(());
             join.join().unwrap();
         }
     }
 }
 
+impl Drop for Extension {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
 pub struct Client {
     extensions: RwLock<HashMap<&'static str, Arc<Extension>>>,
     p2p_channel: IoChannel<P2pMessage>,
@@ -117,7 +196,9 @@ impl Client {
         let cloned_timer = timer.clone();
         let p2p_channel = self.p2p_channel.clone();
         let (channel, rx) = crossbeam::unbounded();
+        let api_sender = channel.clone();
         let sender = channel.into();
+        let requests = Arc::new(Mutex::new(Requests::new(name, T::request_version(), timer.clone())));
 
         let (quit_sender, quit_receiver) = crossbeam::bounded(1);
         let (init_sender, init_receiver) = crossbeam::bounded(1);
@@ -133,6 +214,8 @@ impl Client {
                         need_encryption: T::need_encryption(),
                         p2p_channel,
                         timer,
+                        requests: Arc::clone(&requests),
+                        sender: api_sender,
                     };
                     let mut extension = factory(Box::from(api));
 
@@ -140,6 +223,8 @@ impl Client {
                     let rx_index = s.recv(&rx);
                     let quit_index = s.recv(&quit_receiver);
                     let mut event_closed = false;
+                    // The messages sent before the extension is disabled can arrive after it.
+                    let mut enabled = true;
                     loop {
                         let mut s = s.clone();
                         // Not all extension uses event channel, so closing the event channel is natural thing.
@@ -153,17 +238,19 @@ impl Client {
                         };
                         match s.ready() {
                             index if index == rx_index => match rx.try_recv() {
-                                Ok(ExtensionMessage::NodeAdded(id, version)) => {
-                                    extension.on_node_added(&id, version);
+                                Ok(ExtensionMessage::Disabled) => {
+                                    enabled = false;
+                                    requests.lock().clear();
+                                    extension.on_disabled();
                                 }
-                                Ok(ExtensionMessage::NodeRemoved(id)) => {
-                                    extension.on_node_removed(&id);
+                                Ok(ExtensionMessage::Enabled) => {
+                                    enabled = true;
+                                    extension.on_enabled();
                                 }
-                                Ok(ExtensionMessage::Timeout(token)) => {
-                                    extension.on_timeout(token);
-                                }
-                                Ok(ExtensionMessage::Message(id, message)) => {
-                                    extension.on_message(&id, message.as_ref());
+                                Ok(message) => {
+                                    if enabled {
+                                        handle_extension_message(&mut extension, &requests, message);
+                                    }
                                 }
                                 Err(crossbeam::TryRecvError::Empty) => continue, // Handle a spuriously wake-up
                                 Err(crossbeam::TryRecvError::Disconnected) => {
@@ -183,7 +270,9 @@ impl Client {
                                 assert!(!event_closed);
                                 match event_receiver.try_recv() {
                                     Ok(event) => {
-                                        extension.on_event(event);
+                                        if enabled {
+                                            extension.on_event(event);
+                                        }
                                     }
                                     Err(crossbeam::TryRecvError::Empty) => continue, // Handle a spuriously wake-up
                                     Err(crossbeam::TryRecvError::Disconnected) => {
@@ -209,6 +298,9 @@ impl Client {
             sender,
             quit: quit_sender.into(),
             join,
+            enabled: AtomicBool::new(true),
+            dropped_messages: AtomicUsize::new(0),
+            nodes: Default::default(),
         });
         cloned_timer.set_handler(Arc::downgrade(&extension));
         if extensions.insert(name, extension).is_some() {
@@ -226,23 +318,103 @@ impl Client {
         })
     }
 
+    /// Stops the extension threads. The messages for them are dropped after it.
+    pub fn stop_extensions(&self) {
+        let extensions: Vec<_> = self.extensions.write().drain().collect();
+        for (name, extension) in extensions {
+            cinfo!(NETAPI, "Stopping {}", name);
+            extension.stop();
+        }
+    }
+
+    /// The versions of the enabled extensions, which are negotiated with the peers.
     pub fn extension_versions(&self) -> Vec<(String, Vec<u64>)> {
         let extensions = self.extensions.read();
-        extensions.iter().map(|(name, extension)| ((*name).to_string(), extension.versions.clone())).collect()
+        extensions
+            .iter()
+            .filter(|(_, extension)| extension.enabled.load(Ordering::SeqCst))
+            .map(|(name, extension)| ((*name).to_string(), extension.versions.clone()))
+            .collect()
+    }
+
+    pub fn extension_states(&self) -> Vec<ExtensionState> {
+        let extensions = self.extensions.read();
+        let mut states: Vec<_> = extensions
+            .iter()
+            .map(|(name, extension)| ExtensionState {
+                name: (*name).to_string(),
+                enabled: extension.enabled.load(Ordering::SeqCst),
+                dropped_messages: extension.dropped_messages.load(Ordering::SeqCst),
+            })
+            .collect();
+        states.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        states
+    }
+
+    /// Returns None if there is no such extension, and Some(false) if it's already disabled.
+    pub fn disable_extension(&self, name: &str) -> Option<bool> {
+        let extensions = self.extensions.read();
+        let extension = extensions.get(name)?;
+        let disabled = extension.set_enabled(false);
+        if disabled {
+            cinfo!(NETAPI, "{} is disabled", name);
+        }
+        Some(disabled)
+    }
+
+    /// Returns None if there is no such extension, and Some(false) if it's already enabled.
+    pub fn enable_extension(&self, name: &str) -> Option<bool> {
+        let extensions = self.extensions.read();
+        let extension = extensions.get(name)?;
+        let enabled = extension.set_enabled(true);
+        if enabled {
+            cinfo!(NETAPI, "{} is enabled", name);
+        }
+        Some(enabled)
+    }
+
+    /// The nodes that have negotiated the extension, and the versions.
+    pub fn negotiated_versions(&self, name: &str) -> HashMap<NodeId, u64> {
+        let extensions = self.extensions.read();
+        extensions.get(name).map(|extension| extension.nodes.lock().clone()).unwrap_or_default()
     }
 
     pub fn on_node_removed(&self, id: &NodeId) {
         let extensions = self.extensions.read();
         for (name, extension) in extensions.iter() {
+            extension.nodes.lock().remove(id);
+            if !extension.enabled.load(Ordering::SeqCst) {
+                continue
+            }
             if let Err(err) = extension.sender.lock().send(ExtensionMessage::NodeRemoved(*id)) {
                 cwarn!(NETAPI, "{} cannot remove {}: {:?}", name, id, err);
             }
         }
     }
 
+    /// The peer has disabled the extension, so it's removed from the nodes of the extension.
+    pub fn on_extension_disabled_by(&self, name: &str, id: &NodeId) {
+        let extensions = self.extensions.read();
+        if let Some(extension) = extensions.get(name) {
+            let was_added = extension.nodes.lock().remove(id).is_some();
+            if !was_added || !extension.enabled.load(Ordering::SeqCst) {
+                return
+            }
+            if let Err(err) = extension.sender.lock().send(ExtensionMessage::NodeRemoved(*id)) {
+                cwarn!(NETAPI, "{} cannot remove {}: {:?}", name, id, err);
+            }
+        } else {
+            cdebug!(NETAPI, "{} doesn't exist.", name);
+        }
+    }
+
     pub fn on_node_added(&self, name: &str, id: &NodeId, version: u64) {
         let extensions = self.extensions.read();
         if let Some(extension) = extensions.get(name) {
+            extension.nodes.lock().insert(*id, version);
+            if !extension.enabled.load(Ordering::SeqCst) {
+                return
+            }
             if let Err(err) = extension.sender.lock().send(ExtensionMessage::NodeAdded(*id, version)) {
                 cwarn!(NETAPI, "{} cannot add {}:{}: {:?}", name, id, version, err);
             }
@@ -254,6 +426,11 @@ impl Client {
     pub fn on_message(&self, name: &str, id: &NodeId, data: Arc<Bytes>) {
         let extensions = self.extensions.read();
         if let Some(extension) = extensions.get(name) {
+            if !extension.enabled.load(Ordering::SeqCst) {
+                extension.dropped_messages.fetch_add(1, Ordering::SeqCst);
+                cdebug!(NETAPI, "`{}` is disabled, so it drops {} bytes from {}", name, data.len(), id.into_addr());
+                return
+            }
             cdebug!(NETAPI, "`{}` receives {} bytes from {}", name, data.len(), id.into_addr());
             if let Err(err) = extension.sender.lock().send(ExtensionMessage::Message(*id, data)) {
                 cwarn!(NETAPI, "{} cannot message {}: {:?}", name, id, err);
@@ -269,6 +446,71 @@ enum ExtensionMessage {
     NodeAdded(NodeId, u64),
     NodeRemoved(NodeId),
     Timeout(TimerToken),
+    RequestFailed(NodeId, RequestId, RequestError),
+    Disabled,
+    Enabled,
+}
+
+/// Calls the callbacks of the extension on its thread.
+/// The lock of the requests is released before the callbacks, since they may send requests.
+fn handle_extension_message<T, E>(extension: &mut T, requests: &Mutex<Requests>, message: ExtensionMessage)
+where
+    T: NetworkExtension<E>,
+    E: Send, {
+    match message {
+        ExtensionMessage::NodeAdded(id, version) => {
+            requests.lock().on_node_added(id, version);
+            extension.on_node_added(&id, version);
+        }
+        ExtensionMessage::NodeRemoved(id) => {
+            let failed = requests.lock().on_node_removed(&id);
+            for request in failed {
+                extension.on_response(&id, request, Err(RequestError::Disconnected));
+            }
+            extension.on_node_removed(&id);
+        }
+        ExtensionMessage::Timeout(token) if Requests::is_request_timer(token) => {
+            let timed_out = requests.lock().on_timeout(token);
+            if let Some((id, request)) = timed_out {
+                extension.on_response(&id, request, Err(RequestError::Timeout));
+            }
+        }
+        ExtensionMessage::Timeout(token) => {
+            extension.on_timeout(token);
+        }
+        ExtensionMessage::Message(id, message) => {
+            let is_framed = requests.lock().is_framed(&id);
+            if !is_framed {
+                extension.on_message(&id, message.as_ref());
+                return
+            }
+            match decode_message(&message) {
+                Ok(Frame::Message(message)) => {
+                    extension.on_message(&id, &message);
+                }
+                Ok(Frame::Request(request, message)) => {
+                    extension.on_request(&id, request, &message);
+                }
+                Ok(Frame::Response(request, message)) => {
+                    let is_outstanding = requests.lock().on_response(&id, request);
+                    if is_outstanding {
+                        extension.on_response(&id, request, Ok(&message));
+                    } else {
+                        cdebug!(NETAPI, "`{}` ignores the response of finished request {}", T::name(), request);
+                    }
+                }
+                Err(err) => {
+                    cwarn!(NETAPI, "`{}` receives an invalid frame from {}: {:?}", T::name(), id.into_addr(), err);
+                }
+            }
+        }
+        ExtensionMessage::RequestFailed(id, request, err) => {
+            extension.on_response(&id, request, Err(err));
+        }
+        ExtensionMessage::Disabled | ExtensionMessage::Enabled => {
+            unreachable!("The extension thread handles {} itself", T::name())
+        }
+    }
 }
 
 #[cfg(test)]
@@ -287,6 +529,14 @@ mod tests {
             unimplemented!()
         }
 
+        fn request(&self, _id: &NodeId, _message: Arc<Bytes>, _timeout: Duration) -> RequestId {
+            unimplemented!()
+        }
+
+        fn respond(&self, _id: &NodeId, _request: RequestId, _message: Arc<Bytes>) {
+            unimplemented!()
+        }
+
         fn set_timer(&self, _timer_id: usize, _duration: Duration) -> NetworkExtensionResult<()> {
             unimplemented!()
         }
@@ -384,4 +634,138 @@ mod tests {
         client.on_message(&"e2".to_string(), &node_id5, Default::default());
         client.on_message(&"e2".to_string(), &node_id1, Default::default());
     }
+
+    #[test]
+    fn stop_extensions_twice() {
+        let p2p_service = IoService::start("P2P").unwrap();
+        let timer_loop = TimerLoop::new(2);
+
+        let client = Client::new(p2p_service.channel(), timer_loop);
+        let _e1 = client.register_extension(|_| TestExtension1::new());
+        let _e2 = client.register_extension(|_| TestExtension2::new());
+        assert_eq!(2, client.extension_versions().len());
+
+        client.stop_extensions();
+        client.stop_extensions();
+        assert!(client.extension_versions().is_empty());
+
+        // Messages to the stopped extensions are ignored.
+        let node_id = SocketAddr::v4(127, 0, 0, 1, 8081).into();
+        client.on_message(&"e1".to_string(), &node_id, Default::default());
+    }
+
+    const CALLBACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[derive(Debug, Eq, PartialEq)]
+    enum RequestCallback {
+        NodeAdded,
+        Requested(RequestId),
+        Response(RequestId, Result<Bytes, RequestError>),
+    }
+
+    /// Sends a request to the node of each event.
+    struct RequestingExtension {
+        api: Box<dyn Api>,
+        callbacks: crossbeam::Sender<RequestCallback>,
+    }
+
+    impl NetworkExtension<(NodeId, Duration)> for RequestingExtension {
+        fn name() -> &'static str {
+            "requesting"
+        }
+
+        fn need_encryption() -> bool {
+            false
+        }
+
+        fn versions() -> &'static [u64] {
+            const VERSIONS: &[u64] = &[0, 1];
+            &VERSIONS
+        }
+
+        fn request_version() -> Option<u64> {
+            Some(1)
+        }
+
+        fn on_node_added(&mut self, _id: &NodeId, _version: u64) {
+            self.callbacks.send(RequestCallback::NodeAdded).unwrap();
+        }
+
+        fn on_response(&mut self, _id: &NodeId, request: RequestId, result: Result<&[u8], RequestError>) {
+            self.callbacks.send(RequestCallback::Response(request, result.map(<[u8]>::to_vec))).unwrap();
+        }
+
+        fn on_event(&mut self, (id, timeout): (NodeId, Duration)) {
+            let request = self.api.request(&id, Arc::new(b"ping".to_vec()), timeout);
+            self.callbacks.send(RequestCallback::Requested(request)).unwrap();
+        }
+    }
+
+    /// Sends a request to a node after the node is added.
+    fn request(client: &Client, timeout: Duration) -> (crossbeam::Receiver<RequestCallback>, NodeId, RequestId) {
+        let (callbacks, receiver) = crossbeam::unbounded();
+        let events = client.register_extension(move |api| RequestingExtension {
+            api,
+            callbacks,
+        });
+        let node_id = SocketAddr::v4(127, 0, 0, 1, 8081).into();
+        client.on_node_added("requesting", &node_id, 1);
+        assert_eq!(RequestCallback::NodeAdded, receiver.recv_timeout(CALLBACK_TIMEOUT).unwrap());
+
+        events.send((node_id, timeout)).unwrap();
+        let request = match receiver.recv_timeout(CALLBACK_TIMEOUT).unwrap() {
+            RequestCallback::Requested(request) => request,
+            callback => panic!("Unexpected callback {:?}", callback),
+        };
+        (receiver, node_id, request)
+    }
+
+    fn response(request: RequestId, message: &[u8]) -> Arc<Bytes> {
+        Arc::new(Frame::Response(request, message.to_vec()).rlp_bytes())
+    }
+
+    #[test]
+    fn response_is_delivered_once() {
+        let p2p_service = IoService::start("P2P").unwrap();
+        let client = Client::new(p2p_service.channel(), TimerLoop::new(2));
+        let (receiver, node_id, request) = request(&client, Duration::from_secs(60));
+
+        client.on_message("requesting", &node_id, response(request, b"pong"));
+        assert_eq!(
+            RequestCallback::Response(request, Ok(b"pong".to_vec())),
+            receiver.recv_timeout(CALLBACK_TIMEOUT).unwrap()
+        );
+
+        client.on_message("requesting", &node_id, response(request, b"pong"));
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn request_times_out() {
+        let p2p_service = IoService::start("P2P").unwrap();
+        let client = Client::new(p2p_service.channel(), TimerLoop::new(2));
+        let (receiver, node_id, request) = request(&client, Duration::from_millis(10));
+
+        assert_eq!(
+            RequestCallback::Response(request, Err(RequestError::Timeout)),
+            receiver.recv_timeout(CALLBACK_TIMEOUT).unwrap()
+        );
+
+        // The late response is ignored.
+        client.on_message("requesting", &node_id, response(request, b"pong"));
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn request_fails_when_the_node_is_disconnected() {
+        let p2p_service = IoService::start("P2P").unwrap();
+        let client = Client::new(p2p_service.channel(), TimerLoop::new(2));
+        let (receiver, node_id, request) = request(&client, Duration::from_secs(60));
+
+        client.on_node_removed(&node_id);
+        assert_eq!(
+            RequestCallback::Response(request, Err(RequestError::Disconnected)),
+            receiver.recv_timeout(Duration::from_secs(1)).unwrap()
+        );
+    }
 }
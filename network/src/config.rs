@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::filters::FilterEntry;
+use crate::session::NetworkSecret;
 use crate::SocketAddr;
 
 pub struct Config {
@@ -23,6 +24,9 @@ pub struct Config {
     pub bootstrap_addresses: Vec<SocketAddr>,
     pub min_peers: usize,
     pub max_peers: usize,
+    pub compression_threshold: usize,
     pub whitelist: Vec<FilterEntry>,
     pub blacklist: Vec<FilterEntry>,
+    /// Only the nodes that have the same secret can connect to each other if it is set.
+    pub network_secret: Option<NetworkSecret>,
 }
@@ -32,6 +32,11 @@ pub trait Control: Send + Sync {
     fn get_peer_count(&self) -> Result<usize, Error>;
     fn established_peers(&self) -> Result<Vec<SocketAddr>, Error>;
 
+    fn add_trusted_peer(&self, addr: SocketAddr) -> Result<(), Error>;
+    /// Returns false if the peer is not trusted.
+    fn remove_trusted_peer(&self, addr: &SocketAddr) -> Result<bool, Error>;
+    fn trusted_peers(&self) -> Result<Vec<SocketAddr>, Error>;
+
     fn add_to_whitelist(&self, addr: IpCidr, tag: Option<String>) -> Result<(), Error>;
     fn remove_from_whitelist(&self, addr: &IpCidr) -> Result<(), Error>;
 
@@ -48,10 +53,25 @@ pub trait Control: Send + Sync {
     fn get_blacklist(&self) -> Result<(Vec<FilterEntry>, bool), Error>;
 
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>, Error>;
+
+    /// Stops delivering messages and timers to the extension, and tells the peers that it's disabled.
+    fn disable_extension(&self, name: &str) -> Result<(), Error>;
+    /// Negotiates the extension with the connected peers again.
+    fn enable_extension(&self, name: &str) -> Result<(), Error>;
+    fn extension_states(&self) -> Result<Vec<ExtensionState>, Error>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtensionState {
+    pub name: String,
+    pub enabled: bool,
+    /// The number of the messages dropped because the extension was disabled.
+    pub dropped_messages: usize,
 }
 
 #[derive(Clone, Debug)]
 pub enum Error {
     Disabled,
     NotConnected,
+    NoSuchExtension(String),
 }
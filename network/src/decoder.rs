@@ -0,0 +1,69 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use panic_hook::catch_panic;
+use rlp::{Decodable, DecoderError, Rlp};
+
+/// Returned by `decode_message` when decoding the message panicked.
+/// A peer that sends such a message is banned, since it is either malicious or broken.
+pub const DECODER_PANICKED: DecoderError = DecoderError::Custom("Panicked while decoding");
+
+/// Decodes a message received from a peer.
+/// A panic in a `Decodable` implementation is turned into `DECODER_PANICKED` instead of crashing the node.
+pub fn decode_message<M>(bytes: &[u8]) -> Result<M, DecoderError>
+where
+    M: Decodable, {
+    match catch_panic(|| Rlp::new(bytes).as_val::<M>()) {
+        Ok(result) => result,
+        Err(message) => {
+            cwarn!(NETWORK, "Decoding a message panicked: {}", message);
+            Err(DECODER_PANICKED)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::{Encodable, RlpStream};
+
+    #[derive(Debug, PartialEq)]
+    struct Panicking;
+
+    impl Decodable for Panicking {
+        fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+            let value: u8 = rlp.as_val()?;
+            assert_ne!(0, value, "Zero is not allowed");
+            Ok(Panicking)
+        }
+    }
+
+    #[test]
+    fn panic_is_turned_into_an_error() {
+        assert_eq!(Err(DECODER_PANICKED), decode_message::<Panicking>(&0u8.rlp_bytes()));
+    }
+
+    #[test]
+    fn decode_valid_message() {
+        assert_eq!(Ok(Panicking), decode_message::<Panicking>(&1u8.rlp_bytes()));
+    }
+
+    #[test]
+    fn decoder_error_is_returned_as_is() {
+        let empty_list = RlpStream::new_list(0).out();
+        assert_eq!(Err(DecoderError::RlpExpectedToBeData), decode_message::<Panicking>(&empty_list));
+    }
+}
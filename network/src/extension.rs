@@ -19,6 +19,7 @@ use cio::IoError;
 use ctimer::{TimerScheduleError, TimerToken};
 use primitives::Bytes;
 use std::convert::From;
+use std::fmt;
 use std::result;
 use std::sync::Arc;
 use std::time::Duration;
@@ -46,9 +47,40 @@ impl From<TimerScheduleError> for Error {
 
 pub type Result<T> = result::Result<T, Error>;
 
+pub type RequestId = u64;
+
+/// Why a request is finished without a response.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RequestError {
+    /// No response came in the timeout.
+    Timeout,
+    /// The node was disconnected before it responded.
+    Disconnected,
+    /// The version negotiated with the node doesn't frame the requests.
+    NotSupported,
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::Timeout => write!(f, "The request timed out"),
+            RequestError::Disconnected => write!(f, "The node is disconnected"),
+            RequestError::NotSupported => write!(f, "The node doesn't support requests"),
+        }
+    }
+}
+
 pub trait Api {
     fn send(&self, node: &NodeId, message: Arc<Bytes>);
 
+    /// Sends a request whose result is delivered to `Extension::on_response` once.
+    /// It fails with `RequestError::Timeout` if the node doesn't respond in the timeout.
+    fn request(&self, node: &NodeId, message: Arc<Bytes>, timeout: Duration) -> RequestId;
+    /// Responds to the request delivered to `Extension::on_request`.
+    fn respond(&self, node: &NodeId, request: RequestId, message: Arc<Bytes>);
+
+    /// Fires the timer every `d`, without drifting by the time the extension takes to handle it.
+    /// A timeout is skipped while the previous one is being delivered.
     fn set_timer(&self, timer: TimerToken, d: Duration) -> Result<()>;
     fn set_timer_once(&self, timer: TimerToken, d: Duration) -> Result<()>;
     fn clear_timer(&self, timer: TimerToken) -> Result<()>;
@@ -58,13 +90,25 @@ pub trait Extension<Event: Send> {
     fn name() -> &'static str;
     fn need_encryption() -> bool;
     fn versions() -> &'static [u64];
+    /// The first version that frames the messages to match the responses with the requests.
+    /// The extensions that don't use `Api::request` don't need it.
+    fn request_version() -> Option<u64> {
+        None
+    }
 
     fn on_node_added(&mut self, _node: &NodeId, _version: u64) {}
     fn on_node_removed(&mut self, _node: &NodeId) {}
 
     fn on_message(&mut self, _node: &NodeId, _message: &[u8]) {}
+    fn on_request(&mut self, _node: &NodeId, _request: RequestId, _message: &[u8]) {}
+    fn on_response(&mut self, _node: &NodeId, _request: RequestId, _result: result::Result<&[u8], RequestError>) {}
 
     fn on_timeout(&mut self, _token: TimerToken) {}
 
     fn on_event(&mut self, _event: Event) {}
+
+    /// Called when the extension is disabled at runtime.
+    /// No callbacks are called until `on_enabled`, and the nodes are added again after it.
+    fn on_disabled(&mut self) {}
+    fn on_enabled(&mut self) {}
 }
@@ -38,6 +38,8 @@ extern crate rlp;
 extern crate rlp_derive;
 extern crate kvdb;
 extern crate never_type;
+extern crate panic_hook;
+extern crate snap;
 extern crate table as ctable;
 extern crate time;
 extern crate token_generator;
@@ -45,9 +47,12 @@ extern crate token_generator;
 mod addr;
 mod client;
 mod config;
+mod decoder;
 mod extension;
 mod filters;
 mod node_id;
+mod request;
+mod resumption;
 mod routing_table;
 mod service;
 mod stream;
@@ -58,16 +63,22 @@ pub mod session;
 
 pub use crate::addr::SocketAddr;
 pub use crate::config::Config as NetworkConfig;
-pub use crate::control::{Control as NetworkControl, Error as NetworkControlError};
+pub use crate::control::{Control as NetworkControl, Error as NetworkControlError, ExtensionState};
+pub use crate::decoder::{decode_message, DECODER_PANICKED};
 pub use crate::extension::{
-    Api, Error as NetworkExtensionError, Extension as NetworkExtension, Result as NetworkExtensionResult,
+    Api, Error as NetworkExtensionError, Extension as NetworkExtension, RequestError, RequestId,
+    Result as NetworkExtensionResult,
 };
 pub use crate::node_id::{IntoSocketAddr, NodeId};
 pub use crate::service::{Error as NetworkServiceError, Service as NetworkService};
 
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub use self::p2p::fuzzing;
 pub use self::p2p::{Handler, ManagingPeerdb};
 pub use crate::filters::{FilterEntry, Filters, FiltersControl};
 pub use crate::routing_table::RoutingTable;
+pub use crate::session::NetworkSecret;
 
 pub type EventSender<E> = crossbeam_channel::Sender<E>;
 pub type EventReceiver<E> = crossbeam_channel::Receiver<E>;
@@ -32,6 +32,10 @@ use std::sync::Arc;
 pub struct EstablishedConnection {
     stream: SignedStream,
     peer_addr: SocketAddr,
+    // Extension messages larger than it are compressed. None if the peer can't receive compressed messages.
+    compression_threshold: Option<usize>,
+    // False until the first message is received.
+    received_any: bool,
 }
 
 impl EstablishedConnection {
@@ -39,9 +43,16 @@ impl EstablishedConnection {
         Self {
             stream: SignedStream::new(stream, session),
             peer_addr,
+            compression_threshold: None,
+            received_any: false,
         }
     }
 
+    /// Called after the peer has agreed to receive compressed extension messages.
+    pub fn enable_compression(&mut self, threshold: usize) {
+        self.compression_threshold = Some(threshold);
+    }
+
     fn write(&mut self, message: &Message) -> usize {
         self.stream.write(message)
     }
@@ -60,10 +71,17 @@ impl EstablishedConnection {
         need_encryption: bool,
         message: Arc<Bytes>,
     ) -> Result<usize> {
-        let message = if need_encryption {
-            ExtensionMessage::encrypted_from_unencrypted_data(extension_name, &message, self.stream.session())?
-        } else {
-            ExtensionMessage::unencrypted(extension_name, message)
+        let message = match self.compression_threshold {
+            Some(threshold) if message.len() > threshold => ExtensionMessage::compressed_from_unencrypted_data(
+                extension_name,
+                &message,
+                need_encryption,
+                self.stream.session(),
+            )?,
+            _ if need_encryption => {
+                ExtensionMessage::encrypted_from_unencrypted_data(extension_name, &message, self.stream.session())?
+            }
+            _ => ExtensionMessage::unencrypted(extension_name, message),
         };
 
         Ok(self.write(&Message::Extension(message)))
@@ -83,7 +101,15 @@ impl EstablishedConnection {
     }
 
     pub fn receive(&mut self) -> Result<Option<Message>> {
-        Ok(self.stream.read()?)
+        let message = self.stream.read()?;
+        if message.is_some() {
+            self.received_any = true;
+        }
+        Ok(message)
+    }
+
+    pub fn has_received_any(&self) -> bool {
+        self.received_any
     }
 
     pub fn session(&self) -> &Session {
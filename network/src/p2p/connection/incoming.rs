@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::{EstablishedConnection, IncomingMessage, OutgoingMessage, Result};
+use crate::routing_table::NodeAuthentication;
 use crate::session::Session;
 use crate::stream::Stream;
 use crate::SocketAddr;
@@ -28,15 +29,22 @@ use std::io;
 
 pub struct IncomingConnection {
     stream: Stream,
+    // True if it's accepted beyond the max peers. It's kept only if the initiator turns out to be a trusted peer.
+    beyond_max_peers: bool,
 }
 
 impl IncomingConnection {
-    pub fn new(stream: Stream) -> Self {
+    pub fn new(stream: Stream, beyond_max_peers: bool) -> Self {
         Self {
             stream,
+            beyond_max_peers,
         }
     }
 
+    pub fn is_beyond_max_peers(&self) -> bool {
+        self.beyond_max_peers
+    }
+
     pub fn establish(self, session: Session, port: u16) -> Result<EstablishedConnection> {
         let peer_addr = SocketAddr::new(self.stream.peer_addr()?.ip(), port);
         Ok(EstablishedConnection::new(self.stream, session, peer_addr))
@@ -46,10 +54,16 @@ impl IncomingConnection {
         Ready::writable() | Ready::readable() | UnixReady::hup()
     }
 
-    pub fn send_ack(&mut self, recipient_pub_key: Public, encrypted_nonce: Bytes) -> usize {
+    pub fn send_ack(
+        &mut self,
+        recipient_pub_key: Public,
+        encrypted_nonce: Bytes,
+        authentication: Option<NodeAuthentication>,
+    ) -> usize {
         self.stream.write(&IncomingMessage::Ack {
             recipient_pub_key,
             encrypted_nonce,
+            authentication,
         })
     }
 
@@ -57,6 +71,13 @@ impl IncomingConnection {
         self.stream.write(&IncomingMessage::Nack)
     }
 
+    pub fn send_resumed(&mut self, recipient_pub_key: Public, encrypted_nonce: Bytes) -> usize {
+        self.stream.write(&IncomingMessage::Resumed {
+            recipient_pub_key,
+            encrypted_nonce,
+        })
+    }
+
     pub fn flush(&mut self) -> Result<()> {
         self.stream.flush()?;
         Ok(())
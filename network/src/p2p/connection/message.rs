@@ -14,22 +14,41 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use ckey::{NetworkId, Public};
+use crate::resumption::ResumptionRequest;
+use ckey::{NetworkId, Public, SchnorrSignature};
 use primitives::Bytes;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
+/// The node ids and the signature are appended from the version 2 of the handshake.
+/// The nodes that don't send them use the version 1, which doesn't authenticate the keys.
 #[derive(Debug, PartialEq)]
 pub enum OutgoingMessage {
     Sync1 {
         initiator_pub_key: Public,
         network_id: NetworkId,
         initiator_port: u16,
+        initiator_id: Option<Public>,
     },
     Sync2 {
         initiator_pub_key: Public,
         recipient_pub_key: Public,
         network_id: NetworkId,
         initiator_port: u16,
+        initiator_id: Option<Public>,
+    },
+    /// Sync1 with a ticket of the previous session. The recipient answers Resumed if the ticket is valid,
+    /// or answers it as Sync1 otherwise.
+    Resume {
+        initiator_pub_key: Public,
+        network_id: NetworkId,
+        initiator_port: u16,
+        initiator_id: Public,
+        request: ResumptionRequest,
+    },
+    /// The signature of the initiator on the handshake transcript, sent after the authenticated Ack.
+    /// The recipient uses the session only after it verifies the signature.
+    Finish {
+        signature: SchnorrSignature,
     },
 }
 
@@ -38,14 +57,24 @@ pub enum IncomingMessage {
     Ack {
         recipient_pub_key: Public,
         encrypted_nonce: Bytes,
+        /// The node id of the recipient and its signature on the handshake transcript.
+        authentication: Option<(Public, SchnorrSignature)>,
     },
     Nack,
+    /// The session is resumed without the key exchange.
+    Resumed {
+        recipient_pub_key: Public,
+        encrypted_nonce: Bytes,
+    },
 }
 
 const SYNC1_ID: u8 = 0x01;
 const SYNC2_ID: u8 = 0x02;
 const ACK_ID: u8 = 0x03;
 const NACK_ID: u8 = 0x04;
+const RESUME_ID: u8 = 0x05;
+const RESUMED_ID: u8 = 0x06;
+const FINISH_ID: u8 = 0x07;
 
 impl Encodable for OutgoingMessage {
     fn rlp_append(&self, s: &mut RlpStream) {
@@ -54,21 +83,55 @@ impl Encodable for OutgoingMessage {
                 initiator_pub_key,
                 network_id,
                 initiator_port,
+                initiator_id,
             } => {
-                s.begin_list(4).append(&SYNC1_ID).append(initiator_pub_key).append(network_id).append(initiator_port);
+                s.begin_list(4 + initiator_id.iter().count())
+                    .append(&SYNC1_ID)
+                    .append(initiator_pub_key)
+                    .append(network_id)
+                    .append(initiator_port);
+                if let Some(initiator_id) = initiator_id {
+                    s.append(initiator_id);
+                }
             }
             OutgoingMessage::Sync2 {
                 initiator_pub_key,
                 recipient_pub_key,
                 network_id,
                 initiator_port,
+                initiator_id,
             } => {
-                s.begin_list(5)
+                s.begin_list(5 + initiator_id.iter().count())
                     .append(&SYNC2_ID)
                     .append(initiator_pub_key)
                     .append(recipient_pub_key)
                     .append(network_id)
                     .append(initiator_port);
+                if let Some(initiator_id) = initiator_id {
+                    s.append(initiator_id);
+                }
+            }
+            OutgoingMessage::Resume {
+                initiator_pub_key,
+                network_id,
+                initiator_port,
+                initiator_id,
+                request,
+            } => {
+                s.begin_list(8)
+                    .append(&RESUME_ID)
+                    .append(initiator_pub_key)
+                    .append(network_id)
+                    .append(initiator_port)
+                    .append(initiator_id)
+                    .append(&request.ticket_id)
+                    .append(&request.initiator_random)
+                    .append(&request.binder);
+            }
+            OutgoingMessage::Finish {
+                signature,
+            } => {
+                s.begin_list(2).append(&FINISH_ID).append(signature);
             }
         }
     }
@@ -79,9 +142,9 @@ impl Decodable for OutgoingMessage {
         match rlp.val_at(0)? {
             SYNC1_ID => {
                 let item_count = rlp.item_count()?;
-                if item_count != 4 {
+                if item_count != 4 && item_count != 5 {
                     return Err(DecoderError::RlpInvalidLength {
-                        expected: 4,
+                        expected: 5,
                         got: item_count,
                     })
                 }
@@ -89,13 +152,18 @@ impl Decodable for OutgoingMessage {
                     initiator_pub_key: rlp.val_at(1)?,
                     network_id: rlp.val_at(2)?,
                     initiator_port: rlp.val_at(3)?,
+                    initiator_id: if item_count == 5 {
+                        Some(rlp.val_at(4)?)
+                    } else {
+                        None
+                    },
                 })
             }
             SYNC2_ID => {
                 let item_count = rlp.item_count()?;
-                if item_count != 5 {
+                if item_count != 5 && item_count != 6 {
                     return Err(DecoderError::RlpInvalidLength {
-                        expected: 5,
+                        expected: 6,
                         got: item_count,
                     })
                 }
@@ -104,6 +172,43 @@ impl Decodable for OutgoingMessage {
                     recipient_pub_key: rlp.val_at(2)?,
                     network_id: rlp.val_at(3)?,
                     initiator_port: rlp.val_at(4)?,
+                    initiator_id: if item_count == 6 {
+                        Some(rlp.val_at(5)?)
+                    } else {
+                        None
+                    },
+                })
+            }
+            RESUME_ID => {
+                let item_count = rlp.item_count()?;
+                if item_count != 8 {
+                    return Err(DecoderError::RlpInvalidLength {
+                        expected: 8,
+                        got: item_count,
+                    })
+                }
+                Ok(OutgoingMessage::Resume {
+                    initiator_pub_key: rlp.val_at(1)?,
+                    network_id: rlp.val_at(2)?,
+                    initiator_port: rlp.val_at(3)?,
+                    initiator_id: rlp.val_at(4)?,
+                    request: ResumptionRequest {
+                        ticket_id: rlp.val_at(5)?,
+                        initiator_random: rlp.val_at(6)?,
+                        binder: rlp.val_at(7)?,
+                    },
+                })
+            }
+            FINISH_ID => {
+                let item_count = rlp.item_count()?;
+                if item_count != 2 {
+                    return Err(DecoderError::RlpInvalidLength {
+                        expected: 2,
+                        got: item_count,
+                    })
+                }
+                Ok(OutgoingMessage::Finish {
+                    signature: rlp.val_at(1)?,
                 })
             }
             _ => Err(DecoderError::Custom("Invalid id")),
@@ -117,12 +222,25 @@ impl Encodable for IncomingMessage {
             IncomingMessage::Ack {
                 recipient_pub_key,
                 encrypted_nonce,
+                authentication,
             } => {
-                s.begin_list(3).append(&ACK_ID).append(recipient_pub_key).append(encrypted_nonce);
+                s.begin_list(3 + 2 * authentication.iter().count())
+                    .append(&ACK_ID)
+                    .append(recipient_pub_key)
+                    .append(encrypted_nonce);
+                if let Some((recipient_id, signature)) = authentication {
+                    s.append(recipient_id).append(signature);
+                }
             }
             IncomingMessage::Nack => {
                 s.begin_list(1).append(&NACK_ID);
             }
+            IncomingMessage::Resumed {
+                recipient_pub_key,
+                encrypted_nonce,
+            } => {
+                s.begin_list(3).append(&RESUMED_ID).append(recipient_pub_key).append(encrypted_nonce);
+            }
         }
     }
 }
@@ -132,15 +250,20 @@ impl Decodable for IncomingMessage {
         match rlp.val_at(0)? {
             ACK_ID => {
                 let item_count = rlp.item_count()?;
-                if item_count != 3 {
+                if item_count != 3 && item_count != 5 {
                     return Err(DecoderError::RlpInvalidLength {
-                        expected: 3,
+                        expected: 5,
                         got: item_count,
                     })
                 }
                 Ok(IncomingMessage::Ack {
                     recipient_pub_key: rlp.val_at(1)?,
                     encrypted_nonce: rlp.val_at(2)?,
+                    authentication: if item_count == 5 {
+                        Some((rlp.val_at(3)?, rlp.val_at(4)?))
+                    } else {
+                        None
+                    },
                 })
             }
             NACK_ID => {
@@ -153,6 +276,19 @@ impl Decodable for IncomingMessage {
                 }
                 Ok(IncomingMessage::Nack)
             }
+            RESUMED_ID => {
+                let item_count = rlp.item_count()?;
+                if item_count != 3 {
+                    return Err(DecoderError::RlpInvalidLength {
+                        expected: 3,
+                        got: item_count,
+                    })
+                }
+                Ok(IncomingMessage::Resumed {
+                    recipient_pub_key: rlp.val_at(1)?,
+                    encrypted_nonce: rlp.val_at(2)?,
+                })
+            }
             _ => Err(DecoderError::Custom("Invalid id")),
         }
     }
@@ -160,6 +296,7 @@ impl Decodable for IncomingMessage {
 
 #[cfg(test)]
 mod tests {
+    use primitives::H256;
     use rlp::rlp_encode_and_decode_test;
 
     use super::*;
@@ -169,7 +306,14 @@ mod tests {
         rlp_encode_and_decode_test!(OutgoingMessage::Sync1 {
             initiator_pub_key: Public::random(),
             network_id: "ab".into(),
-            initiator_port: 3100
+            initiator_port: 3100,
+            initiator_id: None,
+        });
+        rlp_encode_and_decode_test!(OutgoingMessage::Sync1 {
+            initiator_pub_key: Public::random(),
+            network_id: "ab".into(),
+            initiator_port: 3100,
+            initiator_id: Some(Public::random()),
         });
     }
 
@@ -179,7 +323,37 @@ mod tests {
             initiator_pub_key: Public::random(),
             recipient_pub_key: Public::random(),
             network_id: "ab".into(),
-            initiator_port: 3100
+            initiator_port: 3100,
+            initiator_id: None,
+        });
+        rlp_encode_and_decode_test!(OutgoingMessage::Sync2 {
+            initiator_pub_key: Public::random(),
+            recipient_pub_key: Public::random(),
+            network_id: "ab".into(),
+            initiator_port: 3100,
+            initiator_id: Some(Public::random()),
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_resume() {
+        rlp_encode_and_decode_test!(OutgoingMessage::Resume {
+            initiator_pub_key: Public::random(),
+            network_id: "ab".into(),
+            initiator_port: 3100,
+            initiator_id: Public::random(),
+            request: ResumptionRequest {
+                ticket_id: H256::random(),
+                initiator_random: H256::random(),
+                binder: H256::random(),
+            },
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_finish() {
+        rlp_encode_and_decode_test!(OutgoingMessage::Finish {
+            signature: SchnorrSignature::default(),
         });
     }
 
@@ -188,6 +362,12 @@ mod tests {
         rlp_encode_and_decode_test!(IncomingMessage::Ack {
             recipient_pub_key: Public::random(),
             encrypted_nonce: vec![1, 23, 4, 5, 6],
+            authentication: None,
+        });
+        rlp_encode_and_decode_test!(IncomingMessage::Ack {
+            recipient_pub_key: Public::random(),
+            encrypted_nonce: vec![1, 23, 4, 5, 6],
+            authentication: Some((Public::random(), SchnorrSignature::default())),
         });
     }
 
@@ -195,4 +375,12 @@ mod tests {
     fn encode_and_decode_nack() {
         rlp_encode_and_decode_test!(IncomingMessage::Nack);
     }
+
+    #[test]
+    fn encode_and_decode_resumed() {
+        rlp_encode_and_decode_test!(IncomingMessage::Resumed {
+            recipient_pub_key: Public::random(),
+            encrypted_nonce: vec![1, 23, 4, 5, 6],
+        });
+    }
 }
@@ -31,12 +31,14 @@ pub use self::message::{IncomingMessage, OutgoingMessage};
 pub use self::outgoing::OutgoingConnection;
 
 use super::super::stream::Error as StreamError;
+use super::message::ExtensionMessageError;
 use super::stream::Error as P2pStreamError;
 
 
 #[derive(Debug)]
 pub enum Error {
     SymmetricCipher(SymmError),
+    ExtensionMessage(ExtensionMessageError),
     IoError(io::Error),
     Decoder(DecoderError),
     InvalidSign,
@@ -46,6 +48,7 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Error::SymmetricCipher(err) => write!(f, "{:?}", err),
+            Error::ExtensionMessage(err) => err.fmt(f),
             Error::Decoder(err) => err.fmt(f),
             Error::IoError(err) => err.fmt(f),
             Error::InvalidSign => write!(f, "Invalid signature"),
@@ -84,4 +87,10 @@ impl From<SymmError> for Error {
     }
 }
 
+impl From<ExtensionMessageError> for Error {
+    fn from(err: ExtensionMessageError) -> Self {
+        Error::ExtensionMessage(err)
+    }
+}
+
 pub type Result<T> = result::Result<T, Error>;
@@ -15,11 +15,12 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::{EstablishedConnection, IncomingMessage, OutgoingMessage, Result};
+use crate::resumption::ResumptionRequest;
 use crate::session::Session;
 use crate::stream::Stream;
 use crate::SocketAddr;
 use cio::IoManager;
-use ckey::{NetworkId, Public};
+use ckey::{NetworkId, Public, SchnorrSignature};
 use mio::deprecated::EventLoop;
 use mio::unix::UnixReady;
 use mio::{PollOpt, Ready, Token};
@@ -54,23 +55,41 @@ impl OutgoingConnection {
         Ready::writable() | Ready::readable() | UnixReady::hup()
     }
 
-    pub fn send_sync(&mut self, recipient_pub_key: Option<Public>) -> usize {
+    pub fn send_sync(&mut self, recipient_pub_key: Option<Public>, initiator_id: Option<Public>) -> usize {
         if let Some(recipient_pub_key) = recipient_pub_key {
             self.stream.write(&OutgoingMessage::Sync2 {
                 initiator_pub_key: self.initiator_pub_key,
                 network_id: self.network_id,
                 initiator_port: self.initiator_port,
                 recipient_pub_key,
+                initiator_id,
             })
         } else {
             self.stream.write(&OutgoingMessage::Sync1 {
                 initiator_pub_key: self.initiator_pub_key,
                 network_id: self.network_id,
                 initiator_port: self.initiator_port,
+                initiator_id,
             })
         }
     }
 
+    pub fn send_resume(&mut self, initiator_id: Public, request: ResumptionRequest) -> usize {
+        self.stream.write(&OutgoingMessage::Resume {
+            initiator_pub_key: self.initiator_pub_key,
+            network_id: self.network_id,
+            initiator_port: self.initiator_port,
+            initiator_id,
+            request,
+        })
+    }
+
+    pub fn send_finish(&mut self, signature: SchnorrSignature) -> usize {
+        self.stream.write(&OutgoingMessage::Finish {
+            signature,
+        })
+    }
+
     pub fn flush(&mut self) -> Result<()> {
         self.stream.flush()?;
         Ok(())
@@ -0,0 +1,38 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Entry points for the fuzz targets in `fuzz/`.
+//! They decode without `decode_message`, so that a panic reaches the fuzzer instead of being caught.
+
+use super::connection::{IncomingMessage, OutgoingMessage};
+use super::handler::MAX_INFLATED_SIZE;
+use super::{NetworkMessage, SignedMessage};
+use crate::session::Session;
+use ckey::Secret;
+use rlp::Rlp;
+
+/// Decodes `data` as every message a peer can send on a p2p connection.
+/// The payload of an extension message is also unwrapped, as the handler does before passing it to the extension.
+pub fn p2p_message(data: &[u8]) {
+    let rlp = Rlp::new(data);
+    let _ = rlp.as_val::<OutgoingMessage>();
+    let _ = rlp.as_val::<IncomingMessage>();
+    let _ = rlp.as_val::<SignedMessage>();
+    if let Ok(NetworkMessage::Extension(message)) = rlp.as_val::<NetworkMessage>() {
+        let session = Session::new_with_zero_nonce(Secret::from([0x11; 32]));
+        let _ = message.unencrypted_data(&session, MAX_INFLATED_SIZE);
+    }
+}
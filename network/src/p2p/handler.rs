@@ -18,15 +18,20 @@ use super::connection::{
     EstablishedConnection, IncomingConnection, IncomingMessage, OutgoingConnection, OutgoingMessage,
 };
 use super::listener::Listener;
-use super::{NegotiationMessage, NetworkMessage};
+use super::message::{
+    disabled_extension, ExtensionMessageError, COMPRESSION_NEGOTIATION_NAME, COMPRESSION_VERSION,
+    DISABLED_EXTENSION_PREFIX,
+};
+use super::{ExtensionMessage, NegotiationMessage, NetworkMessage};
 use crate::client::Client;
 use crate::p2p::connection::Error as P2PConnectionError;
 use crate::session::Session;
 use crate::stream::Stream;
-use crate::{FiltersControl, NodeId, RoutingTable, SocketAddr};
+use crate::{FiltersControl, NodeId, RoutingTable, SocketAddr, DECODER_PANICKED};
 use ccrypto::error::SymmError;
 use cio::{IoChannel, IoContext, IoHandler, IoHandlerResult, IoManager, StreamToken, TimerToken};
 use ckey::NetworkId;
+use clogger::metric::METRICS;
 use finally_block::finally;
 use mio::deprecated::EventLoop;
 use mio::{PollOpt, Ready, Token};
@@ -37,6 +42,7 @@ use rand::rngs::OsRng;
 use rand::Rng;
 use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::iter::FromIterator;
+use std::net::IpAddr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -63,6 +69,7 @@ const LAST_OUTGOING: StreamToken = FIRST_OUTGOING + MAX_OUTGOING_CONNECTIONS - 1
 
 const CREATE_CONNECTIONS: TimerToken = 0;
 const CONNECT_TO_BOOTSTRAP: TimerToken = CREATE_CONNECTIONS + 1;
+const REDIAL_TRUSTED_PEERS: TimerToken = CONNECT_TO_BOOTSTRAP + 1;
 
 const FIRST_WAIT_SYNC: TimerToken = FIRST_INCOMING;
 const LAST_WAIT_SYNC: TimerToken = LAST_INCOMING;
@@ -73,7 +80,13 @@ const LAST_WAIT_ACK: TimerToken = LAST_OUTGOING;
 const FIRST_TRY_SYNC: TimerToken = FIRST_OUTGOING + 1000;
 const LAST_TRY_SYNC: TimerToken = LAST_OUTGOING + 1000;
 
+/// The limit on the size of an inflated extension message to prevent a peer from exhausting the memory.
+pub(crate) const MAX_INFLATED_SIZE: usize = 64 * 1024 * 1024;
+
 const CREATE_CONNECTION_INTERVAL: Duration = Duration::from_secs(3);
+const REDIAL_TRUSTED_PEERS_INTERVAL: Duration = Duration::from_secs(1);
+const MIN_REDIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_REDIAL_BACKOFF: Duration = Duration::from_secs(60);
 
 const RETRY_SYNC_MAX: Duration = Duration::from_secs(10); // T1
 const RTT: Duration = Duration::from_secs(10); // T2
@@ -82,6 +95,34 @@ const WAIT_SYNC: Duration = Duration::from_secs(30); // T3 >> T1 + RTT
 pub trait ManagingPeerdb: Send + Sync {
     fn insert(&self, key: SocketAddr);
     fn delete(&self, key: &SocketAddr);
+
+    fn insert_trusted(&self, key: SocketAddr);
+    fn delete_trusted(&self, key: &SocketAddr);
+    fn trusted_peers(&self) -> Vec<SocketAddr>;
+
+    /// The resumption tickets and the pinned peers encoded by `RoutingTable::persistent_state`.
+    fn routing_state(&self) -> Option<Bytes>;
+    fn save_routing_state(&self, state: Bytes);
+}
+
+/// The schedule to redial a trusted peer. The backoff doubles on every dial until the connection is established.
+struct Redial {
+    next: Instant,
+    backoff: Duration,
+}
+
+impl Redial {
+    fn new() -> Self {
+        Self {
+            next: Instant::now(),
+            backoff: MIN_REDIAL_BACKOFF,
+        }
+    }
+
+    fn postpone(&mut self, now: Instant) {
+        self.next = now + self.backoff;
+        self.backoff = std::cmp::min(self.backoff * 2, MAX_REDIAL_BACKOFF);
+    }
 }
 
 pub struct Handler {
@@ -103,6 +144,8 @@ pub struct Handler {
     outgoing_tokens: Mutex<TokenGenerator>,
 
     establishing_incoming_session: Mutex<HashMap<StreamToken, (u16, Session)>>,
+    // The incoming sessions of the authenticated handshakes that wait for the signatures of the initiators.
+    unfinished_incoming_session: Mutex<HashMap<StreamToken, (u16, Session)>>,
     establishing_outgoing_session: Mutex<HashMap<StreamToken, Session>>,
 
     routing_table: Arc<RoutingTable>,
@@ -114,16 +157,19 @@ pub struct Handler {
     client: Arc<Client>,
 
     bootstrap_addresses: Vec<SocketAddr>,
+    trusted_peers: RwLock<HashMap<SocketAddr, Redial>>,
 
     network_usage_in_10_seconds: Mutex<HashMap<String, VecDeque<(Instant, usize)>>>,
 
     min_peers: usize,
     max_peers: usize,
+    compression_threshold: usize,
     peer_db: Box<dyn (ManagingPeerdb)>,
     rng: Mutex<OsRng>,
 }
 
 impl Handler {
+    #[allow(clippy::too_many_arguments)]
     pub fn try_new(
         channel: IoChannel<Message>,
         network_id: NetworkId,
@@ -134,11 +180,18 @@ impl Handler {
         bootstrap_addresses: Vec<SocketAddr>,
         min_peers: usize,
         max_peers: usize,
+        compression_threshold: usize,
         peer_db: Box<dyn ManagingPeerdb>,
     ) -> ::std::result::Result<Self, String> {
         if MAX_INBOUND_CONNECTIONS + MAX_OUTBOUND_CONNECTIONS < max_peers {
             return Err(format!("Max peers must be less than {}", MAX_INBOUND_CONNECTIONS + MAX_OUTBOUND_CONNECTIONS))
         }
+        let trusted_peers = peer_db.trusted_peers().into_iter().map(|addr| (addr, Redial::new())).collect();
+        if let Some(state) = peer_db.routing_state() {
+            if let Err(err) = routing_table.restore_persistent_state(&state) {
+                cwarn!(NETWORK, "Cannot restore the resumption tickets: {:?}", err);
+            }
+        }
         Ok(Self {
             connecting_lock: Default::default(),
             channel,
@@ -158,6 +211,7 @@ impl Handler {
             outgoing_tokens: Mutex::new(TokenGenerator::new(FIRST_OUTGOING, MAX_OUTGOING_CONNECTIONS)),
 
             establishing_incoming_session: Default::default(),
+            unfinished_incoming_session: Default::default(),
             establishing_outgoing_session: Default::default(),
 
             routing_table,
@@ -171,8 +225,10 @@ impl Handler {
             network_usage_in_10_seconds: Default::default(),
 
             bootstrap_addresses,
+            trusted_peers: RwLock::new(trusted_peers),
             min_peers,
             max_peers,
+            compression_threshold,
             peer_db,
             rng: Mutex::new(OsRng::new().unwrap()),
         })
@@ -200,6 +256,78 @@ impl Handler {
         self.routing_table.established_addresses()
     }
 
+    /// The trusted peers are always redialed when they are disconnected, and don't count toward `max_peers`.
+    /// The filters take precedence over them.
+    pub fn add_trusted_peer(&self, addr: SocketAddr) {
+        if !self.filters.is_allowed(&addr.ip()) {
+            cwarn!(NETWORK, "The trusted peer {} is not connected while it's not allowed by the filters", addr);
+        }
+        self.trusted_peers.write().entry(addr).or_insert_with(Redial::new);
+        self.peer_db.insert_trusted(addr);
+    }
+
+    /// Returns false if the peer is not trusted.
+    pub fn remove_trusted_peer(&self, addr: &SocketAddr) -> bool {
+        if self.trusted_peers.write().remove(addr).is_none() {
+            return false
+        }
+        self.peer_db.delete_trusted(addr);
+        true
+    }
+
+    pub fn trusted_peers(&self) -> Vec<SocketAddr> {
+        let mut trusted_peers: Vec<_> = self.trusted_peers.read().keys().cloned().collect();
+        trusted_peers.sort();
+        trusted_peers
+    }
+
+    // The port of an incoming connection is not the one the peer listens on.
+    // So only the IP is compared on accept, and the port is checked after the sync message.
+    fn is_trusted_ip(&self, ip: &IpAddr) -> bool {
+        self.trusted_peers.read().keys().any(|addr| addr.ip() == *ip)
+    }
+
+    fn is_trusted_peer(&self, addr: &SocketAddr) -> bool {
+        self.trusted_peers.read().contains_key(addr)
+    }
+
+    /// An incoming connection accepted beyond the max peers is allowed only if it's from a trusted peer.
+    fn check_max_peers(&self, con: &IncomingConnection, from: &SocketAddr) -> Result<(), String> {
+        if con.is_beyond_max_peers() && !self.is_trusted_peer(from) {
+            return Err(format!("Connection from {} is dropped because there are too many connections", from))
+        }
+        Ok(())
+    }
+
+    fn established_trusted_peer_count(&self) -> usize {
+        self.trusted_peers.read().keys().filter(|addr| self.routing_table.is_established(addr)).count()
+    }
+
+    fn reset_redial(&self, addr: &SocketAddr) {
+        if let Some(redial) = self.trusted_peers.write().get_mut(addr) {
+            *redial = Redial::new();
+        }
+    }
+
+    /// Drops all connections. Call it after the event loop is stopped.
+    pub fn close_streams(&self) {
+        self.establishing_incoming_session.lock().clear();
+        self.unfinished_incoming_session.lock().clear();
+        self.establishing_outgoing_session.lock().clear();
+        self.incoming_connections.write().clear();
+        self.outgoing_connections.write().clear();
+        self.inbound_connections.write().clear();
+        self.outbound_connections.write().clear();
+        record_inbound_peers(0);
+        record_outbound_peers(0);
+    }
+
+    // The state is written whenever a ticket is issued or consumed, before the session is used.
+    // Otherwise, a consumed ticket could be accepted again after a crash.
+    fn save_routing_state(&self) {
+        self.peer_db.save_routing_state(self.routing_table.persistent_state());
+    }
+
     fn connect(&self, io: &IoContext<Message>, socket_address: SocketAddr) -> IoHandlerResult<()> {
         let ip = socket_address.ip();
         if !self.filters.is_allowed(&ip) {
@@ -217,6 +345,11 @@ impl Handler {
             return Ok(())
         }
 
+        if self.routing_table.is_retry_delayed(&socket_address) {
+            cdebug!(NETWORK, "The handshake with {} failed recently. Retry later", socket_address);
+            return Ok(())
+        }
+
         if let Some(stream) = Stream::connect(&socket_address)? {
             let mut outgoing_connections = self.outgoing_connections.write();
             // Please make sure there is no early return after it.
@@ -253,6 +386,40 @@ impl Handler {
     }
 }
 
+/// Bans the peer if it sent a compressed message that inflates beyond `max_inflated_size`.
+fn extension_data(
+    msg: &ExtensionMessage,
+    con: &EstablishedConnection,
+    routing_table: &RoutingTable,
+    max_inflated_size: usize,
+) -> Result<Arc<Bytes>, ExtensionMessageError> {
+    let result = msg.unencrypted_data(con.session(), max_inflated_size);
+    if let Err(ExtensionMessageError::TooLargeInflation {
+        ..
+    }) = &result
+    {
+        cwarn!(NETWORK, "{} sent a message that inflates too much. Ban it", con.peer_addr());
+        routing_table.ban(*con.peer_addr());
+    }
+    result
+}
+
+/// Bans the peer if decoding its message panicked.
+fn ban_if_decoder_panicked<T>(
+    received: &Result<T, P2PConnectionError>,
+    peer_addr: &SocketAddr,
+    routing_table: &RoutingTable,
+) -> bool {
+    match received {
+        Err(P2PConnectionError::Decoder(err)) if *err == DECODER_PANICKED => {
+            cwarn!(NETWORK, "{} sent a message that panicked the decoder. Ban it", peer_addr);
+            routing_table.ban(*peer_addr);
+            true
+        }
+        _ => false,
+    }
+}
+
 fn retry_sync_timer(stream: StreamToken) -> TimerToken {
     assert!(FIRST_OUTGOING <= stream && stream <= LAST_OUTGOING, "{} < {} < {}", FIRST_OUTGOING, stream, LAST_OUTGOING);
     stream - FIRST_OUTGOING + FIRST_TRY_SYNC
@@ -294,6 +461,7 @@ impl IoHandler<Message> for Handler {
         io.register_stream(ACCEPT);
         io.register_timer_once(CREATE_CONNECTIONS, CREATE_CONNECTION_INTERVAL);
         io.register_timer_once(CONNECT_TO_BOOTSTRAP, Duration::default());
+        io.register_timer(REDIAL_TRUSTED_PEERS, REDIAL_TRUSTED_PEERS_INTERVAL);
         Ok(())
     }
 
@@ -368,6 +536,38 @@ impl IoHandler<Message> for Handler {
                 const CHECK_BOOTSTRAP_INTERVAL: Duration = Duration::from_secs(15);
                 io.register_timer_once(CONNECT_TO_BOOTSTRAP, CHECK_BOOTSTRAP_INTERVAL);
             }
+            REDIAL_TRUSTED_PEERS => {
+                let _l = self.connecting_lock.lock();
+                let now = Instant::now();
+                // The lock on the trusted peers is released before connecting to keep the lock order.
+                let targets: Vec<_> = self
+                    .trusted_peers
+                    .write()
+                    .iter_mut()
+                    .filter(|(_, redial)| redial.next <= now)
+                    .filter(|(addr, _)| !self.routing_table.is_establishing_or_established(addr))
+                    .filter(|(addr, _)| !self.routing_table.is_banned(addr))
+                    .map(|(addr, redial)| {
+                        redial.postpone(now);
+                        *addr
+                    })
+                    .collect();
+                for addr in targets {
+                    if !self.filters.is_allowed(&addr.ip()) {
+                        cwarn!(
+                            NETWORK,
+                            "Cannot redial the trusted peer {} because it's not allowed by the filters",
+                            addr
+                        );
+                        continue
+                    }
+                    cdebug!(NETWORK, "Redial the trusted peer {}", addr);
+                    if let Err(err) = self.connect(io, addr) {
+                        self.routing_table.remove(&addr);
+                        cwarn!(NETWORK, "Cannot connect to the trusted peer {}: {:?}", addr, err);
+                    }
+                }
+            }
             FIRST_WAIT_SYNC..=LAST_WAIT_SYNC => {
                 cwarn!(NETWORK, "No sync message from {}", timer);
                 io.deregister_stream(wait_sync_stream(timer));
@@ -388,7 +588,15 @@ impl IoHandler<Message> for Handler {
                             return Err(err.into())
                         }
                     };
-                    let network_message_size = con.send_sync(maybe_remote_public);
+                    let initiator_id = self.routing_table.handshake_node_id(&target);
+                    let network_message_size = match (initiator_id, self.routing_table.resumption_request(target)) {
+                        (Some(initiator_id), Some(request)) => {
+                            cinfo!(NETWORK, "Resume the session with {}", target);
+                            self.save_routing_state();
+                            con.send_resume(initiator_id, request)
+                        }
+                        _ => con.send_sync(maybe_remote_public, initiator_id),
+                    };
                     {
                         let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
                         insert_network_usage(
@@ -493,6 +701,47 @@ impl IoHandler<Message> for Handler {
                     }
                 }
             }
+            Message::ExtensionDisabled(name) => {
+                // Only the outbound side can send requests without being disconnected.
+                let disabled_name = format!("{}{}", DISABLED_EXTENSION_PREFIX, name);
+                for (stream, con) in self.outbound_connections.write().iter_mut() {
+                    con.enqueue_negotiation_request(disabled_name.clone(), Vec::new());
+                    io.update_registration(*stream);
+                }
+                for (stream, con) in self.inbound_connections.write().iter_mut() {
+                    con.enqueue_negotiation_response(disabled_name.clone(), 0);
+                    io.update_registration(*stream);
+                }
+            }
+            Message::ExtensionEnabled(name) => {
+                let versions = match self.client.extension_versions().into_iter().find(|(n, _)| *n == name) {
+                    Some((_, versions)) => versions,
+                    None => {
+                        cdebug!(NETWORK, "{} is disabled again before it's negotiated", name);
+                        return Ok(())
+                    }
+                };
+                // The outbound peers answer the requests as they do for new connections.
+                for (stream, con) in self.outbound_connections.write().iter_mut() {
+                    con.enqueue_negotiation_request(name.clone(), versions.clone());
+                    io.update_registration(*stream);
+                }
+                // The inbound peers cannot be asked, so the versions negotiated before are reused.
+                let negotiated = self.client.negotiated_versions(&name);
+                for (stream, con) in self.inbound_connections.write().iter_mut() {
+                    let remote_node_id = match self.remote_node_ids.read().get(stream) {
+                        Some(remote_node_id) => *remote_node_id,
+                        None => continue,
+                    };
+                    if let Some(version) = negotiated.get(&remote_node_id) {
+                        con.enqueue_negotiation_response(name.clone(), *version);
+                        io.update_registration(*stream);
+                        self.client.on_node_added(&name, &remote_node_id, *version);
+                    } else {
+                        cdebug!(NETWORK, "{} has never negotiated {}", con.peer_addr(), name);
+                    }
+                }
+            }
             Message::Established {
                 connection,
                 is_inbound: true,
@@ -500,6 +749,7 @@ impl IoHandler<Message> for Handler {
                 let mut inbound_connections = self.inbound_connections.write();
                 let target = connection.peer_addr();
                 self.peer_db.insert(*target);
+                self.reset_redial(target);
                 if let Some(token) = self.inbound_tokens.lock().gen() {
                     let remote_node_id = connection.peer_addr().into();
                     assert_eq!(
@@ -519,6 +769,7 @@ impl IoHandler<Message> for Handler {
 
                     let t = inbound_connections.insert(token, connection);
                     assert!(t.is_none());
+                    record_inbound_peers(inbound_connections.len());
                     io.register_stream(token);
                 } else {
                     cwarn!(NETWORK, "Cannot establish an inbound connection");
@@ -531,6 +782,7 @@ impl IoHandler<Message> for Handler {
                 let mut outbound_connections = self.outbound_connections.write();
                 if let Some(token) = self.outbound_tokens.lock().gen() {
                     let peer_addr = *connection.peer_addr();
+                    self.reset_redial(&peer_addr);
                     let remote_node_id = peer_addr.into();
                     assert_eq!(
                         None,
@@ -551,6 +803,9 @@ impl IoHandler<Message> for Handler {
                     for (name, versions) in self.client.extension_versions() {
                         network_message_size += connection.enqueue_negotiation_request(name.clone(), versions);
                     }
+                    let compression_versions = vec![COMPRESSION_VERSION];
+                    network_message_size += connection
+                        .enqueue_negotiation_request(COMPRESSION_NEGOTIATION_NAME.to_string(), compression_versions);
                     {
                         let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
                         insert_network_usage(
@@ -560,6 +815,7 @@ impl IoHandler<Message> for Handler {
                     }
                     let t = outbound_connections.insert(token, connection);
                     assert!(t.is_none());
+                    record_outbound_peers(outbound_connections.len());
                     io.register_stream(token);
                 } else {
                     cwarn!(NETWORK, "Cannot establish an outbound connection");
@@ -613,18 +869,23 @@ impl IoHandler<Message> for Handler {
                     io.update_registration(stream_token);
                 });
                 while let Some((stream, socket_address)) = self.listener.accept()? {
-                    let mut incoming_connections = {
+                    let is_trusted = self.is_trusted_ip(&socket_address.ip());
+                    let trusted_connections = self.established_trusted_peer_count();
+                    let (mut incoming_connections, beyond_max_peers) = {
                         let inbound_connections = self.inbound_connections.read();
                         let outbound_connections = self.outbound_connections.read();
                         let incoming_connections = self.incoming_connections.write();
                         let outgoing_connections = self.outgoing_connections.read();
 
-                        let current_connections = outbound_connections.len()
+                        // The trusted peers don't count toward the max peers.
+                        let current_connections = (outbound_connections.len()
                             + inbound_connections.len()
                             + incoming_connections.len()
-                            + outgoing_connections.len();
+                            + outgoing_connections.len())
+                        .saturating_sub(trusted_connections);
 
-                        if self.max_peers < current_connections {
+                        let beyond_max_peers = self.max_peers < current_connections;
+                        if !is_trusted && beyond_max_peers {
                             cinfo!(
                                 NETWORK,
                                 "New connection from {} is dropped because there are too many connections({} < {})",
@@ -634,7 +895,7 @@ impl IoHandler<Message> for Handler {
                             );
                             return Ok(())
                         }
-                        incoming_connections
+                        (incoming_connections, beyond_max_peers)
                     };
                     let ip = socket_address.ip();
                     if !self.filters.is_allowed(&ip) {
@@ -647,7 +908,7 @@ impl IoHandler<Message> for Handler {
                         .gen()
                         .ok_or_else(|| format!("Too many incoming connections: {}", incoming_connections.len()))?;
                     // Please make sure there is no early return after it.
-                    let t = incoming_connections.insert(token, IncomingConnection::new(stream));
+                    let t = incoming_connections.insert(token, IncomingConnection::new(stream, beyond_max_peers));
                     assert!(t.is_none());
                     cinfo!(NETWORK, "New connection from {}({})", socket_address, token);
                     io.register_stream(token);
@@ -669,14 +930,55 @@ impl IoHandler<Message> for Handler {
                             io.deregister_stream(stream_token);
                         }
                     };
+                    if ban_if_decoder_panicked(&received, con.peer_addr(), &self.routing_table) {
+                        should_update.store(false, Ordering::SeqCst);
+                        io.deregister_stream(stream_token);
+                    }
                     match received? {
                         Some(NetworkMessage::Extension(msg)) => {
                             let remote_node_id = *self.remote_node_ids.read().get(&stream_token).unwrap_or_else(|| {
                                 unreachable!("Node id for {}:{} must exist", stream_token, con.peer_addr())
                             });
-                            let unencrypted = msg.unencrypted_data(con.session()).map_err(|e| format!("{:?}", e))?;
+                            let unencrypted = match extension_data(&msg, con, &self.routing_table, MAX_INFLATED_SIZE) {
+                                Ok(unencrypted) => unencrypted,
+                                Err(err) => {
+                                    if self.routing_table.is_banned(con.peer_addr()) {
+                                        should_update.store(false, Ordering::SeqCst);
+                                        io.deregister_stream(stream_token);
+                                    }
+                                    return Err(format!("{}", err).into())
+                                }
+                            };
                             self.client.on_message(msg.extension_name(), &remote_node_id, unencrypted);
                         }
+                        Some(NetworkMessage::Negotiation(NegotiationMessage::Request {
+                            extension_name,
+                            extension_versions,
+                        })) if extension_name == COMPRESSION_NEGOTIATION_NAME => {
+                            if !extension_versions.contains(&COMPRESSION_VERSION) {
+                                return Err(format!("There is no valid version for {}", extension_name).into())
+                            }
+                            con.enable_compression(self.compression_threshold);
+                            let network_message_size =
+                                con.enqueue_negotiation_response(extension_name, COMPRESSION_VERSION);
+                            let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
+                            insert_network_usage(
+                                network_usage_in_10_seconds
+                                    .entry(format!("negotiation@{}", con.peer_addr()))
+                                    .or_default(),
+                                network_message_size,
+                            );
+                        }
+                        Some(NetworkMessage::Negotiation(NegotiationMessage::Request {
+                            extension_name,
+                            ..
+                        })) if disabled_extension(&extension_name).is_some() => {
+                            let remote_node_id = *self.remote_node_ids.read().get(&stream_token).unwrap_or_else(|| {
+                                unreachable!("Node id for {}:{} must exist", stream_token, con.peer_addr())
+                            });
+                            let name = disabled_extension(&extension_name).expect("Checked by the guard");
+                            self.client.on_extension_disabled_by(name, &remote_node_id);
+                        }
                         Some(NetworkMessage::Negotiation(NegotiationMessage::Request {
                             extension_name,
                             extension_versions,
@@ -744,12 +1046,25 @@ impl IoHandler<Message> for Handler {
                             io.deregister_stream(stream_token);
                         }
                     };
+                    if ban_if_decoder_panicked(&received, con.peer_addr(), &self.routing_table) {
+                        should_update.store(false, Ordering::SeqCst);
+                        io.deregister_stream(stream_token);
+                    }
                     match received? {
                         Some(NetworkMessage::Extension(msg)) => {
                             let remote_node_id = *self.remote_node_ids.read().get(&stream_token).unwrap_or_else(|| {
                                 unreachable!("Node id for {}:{} must exist", stream_token, con.peer_addr())
                             });
-                            let unencrypted = msg.unencrypted_data(con.session()).map_err(|e| format!("{:?}", e))?;
+                            let unencrypted = match extension_data(&msg, con, &self.routing_table, MAX_INFLATED_SIZE) {
+                                Ok(unencrypted) => unencrypted,
+                                Err(err) => {
+                                    if self.routing_table.is_banned(con.peer_addr()) {
+                                        should_update.store(false, Ordering::SeqCst);
+                                        io.deregister_stream(stream_token);
+                                    }
+                                    return Err(format!("{}", err).into())
+                                }
+                            };
                             self.client.on_message(msg.extension_name(), &remote_node_id, unencrypted);
                         }
                         Some(NetworkMessage::Negotiation(NegotiationMessage::Request {
@@ -763,6 +1078,22 @@ impl IoHandler<Message> for Handler {
                             )
                             .into())
                         }
+                        Some(NetworkMessage::Negotiation(NegotiationMessage::Response {
+                            extension_name,
+                            ..
+                        })) if extension_name == COMPRESSION_NEGOTIATION_NAME => {
+                            con.enable_compression(self.compression_threshold);
+                        }
+                        Some(NetworkMessage::Negotiation(NegotiationMessage::Response {
+                            extension_name,
+                            ..
+                        })) if disabled_extension(&extension_name).is_some() => {
+                            let remote_node_id = *self.remote_node_ids.read().get(&stream_token).unwrap_or_else(|| {
+                                unreachable!("Node id for {}:{} must exist", stream_token, con.peer_addr())
+                            });
+                            let name = disabled_extension(&extension_name).expect("Checked by the guard");
+                            self.client.on_extension_disabled_by(name, &remote_node_id);
+                        }
                         Some(NetworkMessage::Negotiation(NegotiationMessage::Response {
                             extension_name,
                             allowed_version,
@@ -800,6 +1131,7 @@ impl IoHandler<Message> for Handler {
                             initiator_pub_key,
                             network_id,
                             initiator_port,
+                            initiator_id,
                         }) => {
                             let from = con.remote_addr(initiator_port)?;
                             if network_id != self.network_id {
@@ -807,26 +1139,44 @@ impl IoHandler<Message> for Handler {
                                 should_update.store(false, Ordering::SeqCst);
                                 return Err(format!("An invalid network id({}) from {}", network_id, from).into())
                             }
-                            let network_message_size = if let Some((encrypted_nonce, local_public, session)) =
-                                self.routing_table.set_recipient_establish1(from, initiator_pub_key)?
-                            {
-                                cinfo!(NETWORK, "Send ack to {}", from);
-                                let network_message_size = con.send_ack(local_public, encrypted_nonce);
-                                let t = self
-                                    .establishing_incoming_session
-                                    .lock()
-                                    .insert(stream_token, (initiator_port, session));
-                                assert_eq!(None, t, "Cannot establish {}", initiator_port);
-                                io.clear_timer(wait_sync_timer(stream_token));
-                                should_update.store(false, Ordering::SeqCst);
+                            if let Err(err) = self.check_max_peers(con, &from) {
                                 io.deregister_stream(stream_token);
-                                network_message_size
-                            } else {
-                                cinfo!(NETWORK, "Send nack to {}", from);
-                                let network_message_size = con.send_nack();
-                                io.register_timer_once(wait_sync_timer(stream_token), WAIT_SYNC);
-                                network_message_size
-                            };
+                                should_update.store(false, Ordering::SeqCst);
+                                return Err(err.into())
+                            }
+                            let network_message_size =
+                                if let Some((encrypted_nonce, local_public, session, authentication)) = self
+                                    .routing_table
+                                    .set_recipient_establish1(from, initiator_pub_key, initiator_id)?
+                                {
+                                    cinfo!(NETWORK, "Send ack to {}", from);
+                                    let waits_for_finish = authentication.is_some();
+                                    let network_message_size =
+                                        con.send_ack(local_public, encrypted_nonce, authentication);
+                                    if waits_for_finish {
+                                        // The session is established after the initiator signs the transcript.
+                                        let t = self
+                                            .unfinished_incoming_session
+                                            .lock()
+                                            .insert(stream_token, (initiator_port, session));
+                                        assert_eq!(None, t, "Cannot establish {}", initiator_port);
+                                    } else {
+                                        let t = self
+                                            .establishing_incoming_session
+                                            .lock()
+                                            .insert(stream_token, (initiator_port, session));
+                                        assert_eq!(None, t, "Cannot establish {}", initiator_port);
+                                        io.clear_timer(wait_sync_timer(stream_token));
+                                        should_update.store(false, Ordering::SeqCst);
+                                        io.deregister_stream(stream_token);
+                                    }
+                                    network_message_size
+                                } else {
+                                    cinfo!(NETWORK, "Send nack to {}", from);
+                                    let network_message_size = con.send_nack();
+                                    io.register_timer_once(wait_sync_timer(stream_token), WAIT_SYNC);
+                                    network_message_size
+                                };
                             let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
                             insert_network_usage(
                                 network_usage_in_10_seconds.entry(format!("handshake@{}", from)).or_default(),
@@ -838,6 +1188,7 @@ impl IoHandler<Message> for Handler {
                             recipient_pub_key,
                             network_id,
                             initiator_port,
+                            initiator_id,
                         }) => {
                             let from = con.remote_addr(initiator_port)?;
                             if network_id != self.network_id {
@@ -845,12 +1196,83 @@ impl IoHandler<Message> for Handler {
                                 io.deregister_stream(stream_token);
                                 return Err(format!("An invalid network id({}) from {}", network_id, from).into())
                             }
-                            let network_message_size = if let Some((encrypted_nonce, local_public, session)) = self
-                                .routing_table
-                                .set_recipient_establish2(from, recipient_pub_key, initiator_pub_key)?
-                            {
-                                cinfo!(NETWORK, "Send ack to {}", from);
-                                let network_message_size = con.send_ack(local_public, encrypted_nonce);
+                            if let Err(err) = self.check_max_peers(con, &from) {
+                                io.deregister_stream(stream_token);
+                                should_update.store(false, Ordering::SeqCst);
+                                return Err(err.into())
+                            }
+                            let network_message_size =
+                                if let Some((encrypted_nonce, local_public, session, authentication)) =
+                                    self.routing_table.set_recipient_establish2(
+                                        from,
+                                        recipient_pub_key,
+                                        initiator_pub_key,
+                                        initiator_id,
+                                    )?
+                                {
+                                    cinfo!(NETWORK, "Send ack to {}", from);
+                                    let waits_for_finish = authentication.is_some();
+                                    let network_message_size =
+                                        con.send_ack(local_public, encrypted_nonce, authentication);
+                                    if waits_for_finish {
+                                        // The session is established after the initiator signs the transcript.
+                                        let t = self
+                                            .unfinished_incoming_session
+                                            .lock()
+                                            .insert(stream_token, (initiator_port, session));
+                                        assert_eq!(None, t, "Cannot establish {}", initiator_port);
+                                    } else {
+                                        let t = self
+                                            .establishing_incoming_session
+                                            .lock()
+                                            .insert(stream_token, (initiator_port, session));
+                                        assert_eq!(None, t, "Cannot establish {}", initiator_port);
+                                        io.clear_timer(wait_sync_timer(stream_token));
+                                        should_update.store(false, Ordering::SeqCst);
+                                        io.deregister_stream(stream_token);
+                                    }
+                                    network_message_size
+                                } else {
+                                    cinfo!(NETWORK, "Send nack to {}", from);
+                                    let network_message_size = con.send_nack();
+                                    io.register_timer_once(wait_sync_timer(stream_token), WAIT_SYNC);
+                                    network_message_size
+                                };
+                            let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
+                            insert_network_usage(
+                                network_usage_in_10_seconds.entry(format!("handshake@{}", from)).or_default(),
+                                network_message_size,
+                            );
+                        }
+                        Some(OutgoingMessage::Resume {
+                            initiator_pub_key,
+                            network_id,
+                            initiator_port,
+                            initiator_id,
+                            request,
+                        }) => {
+                            let from = con.remote_addr(initiator_port)?;
+                            if network_id != self.network_id {
+                                io.deregister_stream(stream_token);
+                                should_update.store(false, Ordering::SeqCst);
+                                return Err(format!("An invalid network id({}) from {}", network_id, from).into())
+                            }
+                            if let Err(err) = self.check_max_peers(con, &from) {
+                                io.deregister_stream(stream_token);
+                                should_update.store(false, Ordering::SeqCst);
+                                return Err(err.into())
+                            }
+                            let resumed = self.routing_table.set_recipient_resume(
+                                from,
+                                initiator_pub_key,
+                                initiator_id,
+                                &request,
+                            )?;
+                            let network_message_size = if let Some((encrypted_nonce, local_public, session)) = resumed {
+                                self.save_routing_state();
+                                record_resumed_session();
+                                cinfo!(NETWORK, "Send resumed to {}", from);
+                                let network_message_size = con.send_resumed(local_public, encrypted_nonce);
                                 let t = self
                                     .establishing_incoming_session
                                     .lock()
@@ -860,6 +1282,20 @@ impl IoHandler<Message> for Handler {
                                 should_update.store(false, Ordering::SeqCst);
                                 io.deregister_stream(stream_token);
                                 network_message_size
+                            } else if let Some((encrypted_nonce, local_public, session, authentication)) = self
+                                .routing_table
+                                .set_recipient_establish1(from, initiator_pub_key, Some(initiator_id))?
+                            {
+                                // The ticket is rejected. The key exchange is done as if Sync1 were received.
+                                // The node id was sent, so the session is established after the initiator signs it.
+                                cinfo!(NETWORK, "Cannot resume the session with {}. Send ack", from);
+                                let network_message_size = con.send_ack(local_public, encrypted_nonce, authentication);
+                                let t = self
+                                    .unfinished_incoming_session
+                                    .lock()
+                                    .insert(stream_token, (initiator_port, session));
+                                assert_eq!(None, t, "Cannot establish {}", initiator_port);
+                                network_message_size
                             } else {
                                 cinfo!(NETWORK, "Send nack to {}", from);
                                 let network_message_size = con.send_nack();
@@ -872,6 +1308,36 @@ impl IoHandler<Message> for Handler {
                                 network_message_size,
                             );
                         }
+                        Some(OutgoingMessage::Finish {
+                            signature,
+                        }) => {
+                            let unfinished = self.unfinished_incoming_session.lock().remove(&stream_token);
+                            let (initiator_port, session) = match unfinished {
+                                Some(unfinished) => unfinished,
+                                None => {
+                                    should_update.store(false, Ordering::SeqCst);
+                                    io.deregister_stream(stream_token);
+                                    return Err(format!("Unexpected finish on {}", stream_token).into())
+                                }
+                            };
+                            let from = con.remote_addr(initiator_port)?;
+                            if let Err(err) = self.routing_table.set_recipient_finish(from, &signature) {
+                                self.routing_table.remove(&from);
+                                should_update.store(false, Ordering::SeqCst);
+                                io.deregister_stream(stream_token);
+                                return Err(err.into())
+                            }
+                            self.save_routing_state();
+                            cinfo!(NETWORK, "{} signed the handshake", from);
+                            let t = self
+                                .establishing_incoming_session
+                                .lock()
+                                .insert(stream_token, (initiator_port, session));
+                            assert_eq!(None, t, "Cannot establish {}", initiator_port);
+                            io.clear_timer(wait_sync_timer(stream_token));
+                            should_update.store(false, Ordering::SeqCst);
+                            io.deregister_stream(stream_token);
+                        }
                         None => {
                             should_update.store(false, Ordering::SeqCst);
                         }
@@ -896,16 +1362,61 @@ impl IoHandler<Message> for Handler {
                             io.deregister_stream(stream_token);
                         }
                     };
+                    if ban_if_decoder_panicked(&received, &from, &self.routing_table) {
+                        should_update.store(false, Ordering::SeqCst);
+                        io.deregister_stream(stream_token);
+                    }
                     match received? {
                         Some(IncomingMessage::Ack {
                             recipient_pub_key,
                             encrypted_nonce,
+                            authentication,
                         }) => {
-                            let session = self.routing_table.set_initiator_establish(
+                            let (session, signature) = match self.routing_table.set_initiator_establish(
                                 from,
                                 recipient_pub_key,
                                 &encrypted_nonce,
-                            )?;
+                                authentication,
+                            ) {
+                                Ok(established) => established,
+                                Err(err) => {
+                                    io.clear_timer(wait_ack_timer(stream_token));
+                                    should_update.store(false, Ordering::SeqCst);
+                                    io.deregister_stream(stream_token);
+                                    return Err(err.into())
+                                }
+                            };
+                            if let Some(signature) = signature {
+                                self.save_routing_state();
+                                // The recipient uses the session after it verifies the signature of this node.
+                                con.send_finish(signature);
+                            }
+                            let t = self.establishing_outgoing_session.lock().insert(stream_token, session);
+                            assert_eq!(None, t);
+                            io.clear_timer(wait_ack_timer(stream_token));
+                            io.clear_timer(retry_sync_timer(stream_token));
+                            should_update.store(false, Ordering::SeqCst);
+                            io.deregister_stream(stream_token);
+                        }
+                        Some(IncomingMessage::Resumed {
+                            recipient_pub_key,
+                            encrypted_nonce,
+                        }) => {
+                            let session = match self.routing_table.set_initiator_resume(
+                                from,
+                                recipient_pub_key,
+                                &encrypted_nonce,
+                            ) {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    io.clear_timer(wait_ack_timer(stream_token));
+                                    should_update.store(false, Ordering::SeqCst);
+                                    io.deregister_stream(stream_token);
+                                    return Err(err.into())
+                                }
+                            };
+                            self.save_routing_state();
+                            cinfo!(NETWORK, "The session with {} is resumed", from);
                             let t = self.establishing_outgoing_session.lock().insert(stream_token, session);
                             assert_eq!(None, t);
                             io.clear_timer(wait_ack_timer(stream_token));
@@ -1109,8 +1620,13 @@ impl IoHandler<Message> for Handler {
                         unreachable!("{} has no node id", stream);
                     }
                     con.deregister(event_loop)?;
+                    if !con.has_received_any() {
+                        // The initiator sends the negotiation requests as soon as the session is established.
+                        self.routing_table.note_silent_inbound_session(*con.peer_addr());
+                    }
                     self.routing_table.remove(con.peer_addr());
                     self.inbound_tokens.lock().restore(stream);
+                    record_inbound_peers(inbound_connections.len());
                     ctrace!(NETWORK, "Inbound connect({}) removed", stream);
                     let remove_target = con.peer_addr();
                     self.peer_db.delete(&remove_target);
@@ -1132,6 +1648,7 @@ impl IoHandler<Message> for Handler {
                     self.peer_db.delete(&remove_target);
                     self.routing_table.remove(con.peer_addr());
                     self.outbound_tokens.lock().restore(stream);
+                    record_outbound_peers(outbound_connections.len());
                     ctrace!(NETWORK, "Outbound connect({}) removed", stream);
                 } else {
                     cdebug!(NETWORK, "Invalid outbound token({}) on deregister", stream);
@@ -1160,6 +1677,10 @@ impl IoHandler<Message> for Handler {
                         })?;
                         ctrace!(NETWORK, "Incoming connect({}) established", stream);
                     } else {
+                        if let Some((port, _)) = self.unfinished_incoming_session.lock().remove(&stream) {
+                            // The initiator didn't sign the handshake. The session acked to it is dropped.
+                            self.routing_table.remove(&con.remote_addr(port)?);
+                        }
                         ctrace!(NETWORK, "Incoming connect({}) removed", stream);
                     }
                 } else {
@@ -1189,6 +1710,11 @@ impl IoHandler<Message> for Handler {
                         })?;
                         ctrace!(NETWORK, "Outgoing connect({}) established", stream);
                     } else {
+                        // The nodes that don't know the resumption drop it. The key exchange is done on the next try.
+                        // Otherwise, the nodes of the version 1 drop the sync messages that carry a node id.
+                        if !self.routing_table.abandon_resumption(con.peer_addr()) {
+                            self.routing_table.fall_back_to_legacy_handshake(*con.peer_addr());
+                        }
                         self.routing_table.remove(con.peer_addr());
                         ctrace!(NETWORK, "Outgoing connect({}) removed", stream);
                     }
@@ -1220,6 +1746,10 @@ pub enum Message {
     },
     Disconnect(SocketAddr),
     ApplyFilters,
+    /// Tells the peers that the extension is disabled.
+    ExtensionDisabled(String),
+    /// Negotiates the extension with the peers again.
+    ExtensionEnabled(String),
     Established {
         connection: EstablishedConnection,
         is_inbound: bool,
@@ -1261,3 +1791,148 @@ fn insert_network_usage(usage_per_extension: &mut VecDeque<(Instant, usize)>, ne
     remove_outdated_network_usage(usage_per_extension, now);
     usage_per_extension.push_back((now + Duration::from_secs(10), network_message_size));
 }
+
+fn record_inbound_peers(count: usize) {
+    METRICS.gauge("codechain_peers_inbound", "The number of connected inbound peers").set(count as i64);
+}
+
+fn record_outbound_peers(count: usize) {
+    METRICS.gauge("codechain_peers_outbound", "The number of connected outbound peers").set(count as i64);
+}
+
+fn record_resumed_session() {
+    METRICS.counter("codechain_resumed_sessions", "The number of inbound sessions resumed with tickets").inc_by(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckey::Secret;
+    use rlp::{DecoderError, Encodable};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    fn connected_pair() -> (EstablishedConnection, EstablishedConnection) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let sender = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (receiver, _) = listener.accept().unwrap();
+
+        let secret = Secret::random();
+        let establish = |stream: TcpStream| {
+            let peer_addr = stream.peer_addr().unwrap().into();
+            let stream = mio::net::TcpStream::from_stream(stream).unwrap();
+            EstablishedConnection::new(stream.into(), Session::new(secret.clone(), 0), peer_addr)
+        };
+        (establish(sender), establish(receiver))
+    }
+
+    fn send(
+        sender: &mut EstablishedConnection,
+        receiver: &mut EstablishedConnection,
+        need_encryption: bool,
+        data: Bytes,
+    ) -> ExtensionMessage {
+        sender.enqueue_extension_message("extension".to_string(), need_encryption, Arc::new(data)).unwrap();
+        for _ in 0..100 {
+            sender.flush().unwrap();
+            if let Some(NetworkMessage::Extension(message)) = receiver.receive().unwrap() {
+                return message
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("The message is not delivered");
+    }
+
+    fn is_compressed(message: &ExtensionMessage) -> bool {
+        match message {
+            ExtensionMessage::Compressed {
+                ..
+            } => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn compressed_messages_round_trip_between_connections() {
+        let routing_table = RoutingTable::new();
+        let (mut sender, mut receiver) = connected_pair();
+        sender.enable_compression(64);
+        receiver.enable_compression(64);
+
+        let small = vec![1; 64];
+        let message = send(&mut sender, &mut receiver, false, small.clone());
+        assert!(!is_compressed(&message));
+        assert_eq!(small, *extension_data(&message, &receiver, &routing_table, MAX_INFLATED_SIZE).unwrap());
+
+        for need_encryption in &[false, true] {
+            let large = b"block body ".iter().cycle().take(4096).cloned().collect::<Bytes>();
+            let message = send(&mut sender, &mut receiver, *need_encryption, large.clone());
+            assert!(is_compressed(&message));
+            assert!(message.rlp_bytes().len() < large.len());
+            assert_eq!(large, *extension_data(&message, &receiver, &routing_table, MAX_INFLATED_SIZE).unwrap());
+
+            let message = send(&mut receiver, &mut sender, *need_encryption, large.clone());
+            assert!(is_compressed(&message));
+            assert_eq!(large, *extension_data(&message, &sender, &routing_table, MAX_INFLATED_SIZE).unwrap());
+        }
+        assert!(!routing_table.is_banned(sender.peer_addr()));
+        assert!(!routing_table.is_banned(receiver.peer_addr()));
+    }
+
+    #[test]
+    fn messages_are_not_compressed_unless_the_peer_agreed() {
+        let routing_table = RoutingTable::new();
+        let (mut sender, mut receiver) = connected_pair();
+
+        let large = vec![0; 4096];
+        let message = send(&mut sender, &mut receiver, false, large.clone());
+        assert!(!is_compressed(&message));
+        assert_eq!(large, *extension_data(&message, &receiver, &routing_table, MAX_INFLATED_SIZE).unwrap());
+    }
+
+    #[test]
+    fn peer_sending_a_message_inflating_too_much_is_banned() {
+        let routing_table = RoutingTable::new();
+        let (mut sender, mut receiver) = connected_pair();
+        sender.enable_compression(64);
+
+        let message = send(&mut sender, &mut receiver, true, vec![0; 4096]);
+        assert!(is_compressed(&message));
+        assert!(!routing_table.is_banned(receiver.peer_addr()));
+        match extension_data(&message, &receiver, &routing_table, 1024) {
+            Err(ExtensionMessageError::TooLargeInflation {
+                max: 1024,
+                size: 4096,
+            }) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        assert!(routing_table.is_banned(receiver.peer_addr()));
+    }
+
+    #[test]
+    fn redial_backoff_doubles_up_to_the_max() {
+        let mut redial = Redial::new();
+        let now = redial.next;
+        let mut expected = MIN_REDIAL_BACKOFF;
+        for _ in 0..10 {
+            redial.postpone(now);
+            assert_eq!(now + expected, redial.next);
+            expected = std::cmp::min(expected * 2, MAX_REDIAL_BACKOFF);
+        }
+        assert_eq!(now + MAX_REDIAL_BACKOFF, redial.next);
+    }
+
+    #[test]
+    fn peer_sending_a_message_panicking_the_decoder_is_banned() {
+        let routing_table = RoutingTable::new();
+        let peer_addr = SocketAddr::v4(127, 0, 0, 1, 3485);
+
+        let received: Result<Option<NetworkMessage>, _> = Err(P2PConnectionError::Decoder(DecoderError::RlpIsTooShort));
+        assert!(!ban_if_decoder_panicked(&received, &peer_addr, &routing_table));
+        assert!(!routing_table.is_banned(&peer_addr));
+
+        let received: Result<Option<NetworkMessage>, _> = Err(P2PConnectionError::Decoder(DECODER_PANICKED));
+        assert!(ban_if_decoder_panicked(&received, &peer_addr, &routing_table));
+        assert!(routing_table.is_banned(&peer_addr));
+    }
+}
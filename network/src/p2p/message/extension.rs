@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::COMPRESSED_ID;
 use super::ENCRYPTED_ID;
 use super::UNENCRYPTED_ID;
 use crate::session::Session;
@@ -21,8 +22,44 @@ use ccrypto::aes;
 use ccrypto::error::SymmError;
 use primitives::Bytes;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::fmt;
 use std::sync::Arc;
 
+#[derive(Debug)]
+pub enum Error {
+    SymmetricCipher(SymmError),
+    Compression(snap::Error),
+    TooLargeInflation {
+        max: usize,
+        size: usize,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::SymmetricCipher(err) => write!(f, "{:?}", err),
+            Error::Compression(err) => err.fmt(f),
+            Error::TooLargeInflation {
+                max,
+                size,
+            } => write!(f, "The message inflates to {} bytes, which exceeds {} bytes", size, max),
+        }
+    }
+}
+
+impl From<SymmError> for Error {
+    fn from(err: SymmError) -> Self {
+        Error::SymmetricCipher(err)
+    }
+}
+
+impl From<snap::Error> for Error {
+    fn from(err: snap::Error) -> Self {
+        Error::Compression(err)
+    }
+}
+
 
 #[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub enum Message {
@@ -34,6 +71,12 @@ pub enum Message {
         extension_name: String,
         data: Arc<Bytes>,
     },
+    /// The data is compressed before it's encrypted.
+    Compressed {
+        extension_name: String,
+        encrypted: bool,
+        compressed: Vec<u8>,
+    },
 }
 
 impl Message {
@@ -60,6 +103,25 @@ impl Message {
         }
     }
 
+    pub fn compressed_from_unencrypted_data(
+        extension_name: String,
+        unencrypted_data: &[u8],
+        need_encryption: bool,
+        session: &Session,
+    ) -> Result<Self, Error> {
+        let compressed = snap::Encoder::new().compress_vec(unencrypted_data)?;
+        let compressed = if need_encryption {
+            aes::encrypt(&compressed, session.secret(), &session.nonce())?
+        } else {
+            compressed
+        };
+        Ok(Message::Compressed {
+            extension_name,
+            encrypted: need_encryption,
+            compressed,
+        })
+    }
+
     #[cfg(test)]
     fn data(&self) -> &[u8] {
         match self {
@@ -71,10 +133,15 @@ impl Message {
                 data,
                 ..
             } => &data,
+            Message::Compressed {
+                compressed,
+                ..
+            } => &compressed,
         }
     }
 
-    pub fn unencrypted_data(&self, session: &Session) -> Result<Arc<Bytes>, SymmError> {
+    /// Fails without inflating a compressed message if it inflates to more than `max_inflated_size` bytes.
+    pub fn unencrypted_data(&self, session: &Session, max_inflated_size: usize) -> Result<Arc<Bytes>, Error> {
         match self {
             Message::Encrypted {
                 encrypted,
@@ -84,6 +151,27 @@ impl Message {
                 data,
                 ..
             } => Ok(Arc::clone(data)),
+            Message::Compressed {
+                encrypted,
+                compressed,
+                ..
+            } => {
+                let decrypted;
+                let compressed = if *encrypted {
+                    decrypted = aes::decrypt(compressed, session.secret(), &session.nonce())?;
+                    &decrypted
+                } else {
+                    compressed
+                };
+                let size = snap::decompress_len(compressed)?;
+                if size > max_inflated_size {
+                    return Err(Error::TooLargeInflation {
+                        max: max_inflated_size,
+                        size,
+                    })
+                }
+                Ok(Arc::new(snap::Decoder::new().decompress_vec(compressed)?))
+            }
         }
     }
 
@@ -97,6 +185,10 @@ impl Message {
                 extension_name,
                 ..
             } => &extension_name,
+            Message::Compressed {
+                extension_name,
+                ..
+            } => &extension_name,
         }
     }
 }
@@ -116,20 +208,33 @@ impl Encodable for Message {
             } => {
                 s.begin_list(3).append(&UNENCRYPTED_ID).append(extension_name).append(data.as_ref());
             }
+            Message::Compressed {
+                extension_name,
+                encrypted,
+                compressed,
+            } => {
+                s.begin_list(4).append(&COMPRESSED_ID).append(extension_name).append(encrypted).append(compressed);
+            }
         }
     }
 }
 
 impl Decodable for Message {
     fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let id = rlp.val_at(0)?;
+        let expected = if id == COMPRESSED_ID {
+            4
+        } else {
+            3
+        };
         let item_count = rlp.item_count()?;
-        if item_count != 3 {
+        if item_count != expected {
             return Err(DecoderError::RlpInvalidLength {
-                expected: 3,
+                expected,
                 got: item_count,
             })
         }
-        match rlp.val_at(0)? {
+        match id {
             ENCRYPTED_ID => Ok(Message::Encrypted {
                 extension_name: rlp.val_at(1)?,
                 encrypted: rlp.val_at(2)?,
@@ -138,6 +243,11 @@ impl Decodable for Message {
                 extension_name: rlp.val_at(1)?,
                 data: Arc::new(rlp.val_at(2)?),
             }),
+            COMPRESSED_ID => Ok(Message::Compressed {
+                extension_name: rlp.val_at(1)?,
+                encrypted: rlp.val_at(2)?,
+                compressed: rlp.val_at(3)?,
+            }),
             _ => Err(DecoderError::Custom("Invalid id in extension message")),
         }
     }
@@ -165,7 +275,45 @@ mod tests {
         let session = Session::new(shared_secret, nonce);
         let encrypted = Message::encrypted_from_unencrypted_data(extension_name, unencrypted_data, &session).unwrap();
         assert_ne!(unencrypted_data, encrypted.data());
-        assert_eq!(unencrypted_data, encrypted.unencrypted_data(&session).unwrap().as_slice());
+        assert_eq!(unencrypted_data, encrypted.unencrypted_data(&session, unencrypted_data.len()).unwrap().as_slice());
+    }
+
+    #[test]
+    fn compressed_with_unencrypted_data_function_internally_compresses() {
+        let extension_name = "compress".to_string();
+        let unencrypted_data = vec![7; 1000];
+        let mut rng = OsRng::new().expect("Cannot generate random number");
+        let session = Session::new(Secret::random(), rng.gen());
+
+        for need_encryption in &[false, true] {
+            let compressed = Message::compressed_from_unencrypted_data(
+                extension_name.clone(),
+                &unencrypted_data,
+                *need_encryption,
+                &session,
+            )
+            .unwrap();
+            assert!(compressed.data().len() < unencrypted_data.len());
+            assert_eq!(unencrypted_data, *compressed.unencrypted_data(&session, unencrypted_data.len()).unwrap());
+        }
+    }
+
+    #[test]
+    fn compressed_data_inflating_over_the_limit_is_rejected() {
+        let mut rng = OsRng::new().expect("Cannot generate random number");
+        let session = Session::new(Secret::random(), rng.gen());
+        let unencrypted_data = vec![0; 1000];
+        let compressed =
+            Message::compressed_from_unencrypted_data("compress".to_string(), &unencrypted_data, true, &session)
+                .unwrap();
+
+        match compressed.unencrypted_data(&session, 999) {
+            Err(Error::TooLargeInflation {
+                max: 999,
+                size: 1000,
+            }) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
     }
 
     #[test]
@@ -177,4 +325,13 @@ mod tests {
     fn encode_and_decode_unencrypted() {
         rlp_encode_and_decode_test!(Message::unencrypted("a".to_string(), Arc::new(vec![1, 2, 3, 4])));
     }
+
+    #[test]
+    fn encode_and_decode_compressed() {
+        rlp_encode_and_decode_test!(Message::Compressed {
+            extension_name: "a".to_string(),
+            encrypted: true,
+            compressed: vec![1, 2, 3, 4],
+        });
+    }
 }
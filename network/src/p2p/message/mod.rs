@@ -22,7 +22,7 @@ mod signed_message;
 
 use primitives::H256;
 
-pub use self::extension::Message as ExtensionMessage;
+pub use self::extension::{Error as ExtensionMessageError, Message as ExtensionMessage};
 pub use self::message::Message;
 pub use self::negotiation::Message as NegotiationMessage;
 pub use self::signed_message::SignedMessage;
@@ -35,9 +35,29 @@ pub const REQUEST_ID: u8 = 0x05;
 pub const RESPONSE_ID: u8 = 0x06;
 pub const ENCRYPTED_ID: u8 = 0x07;
 pub const UNENCRYPTED_ID: u8 = 0x08;
+pub const COMPRESSED_ID: u8 = 0x09;
+
+/// The pseudo extension negotiated to tell that the peer can receive compressed extension messages.
+/// Peers that don't know it never answer, so they never receive compressed messages.
+pub const COMPRESSION_NEGOTIATION_NAME: &str = "compression";
+pub const COMPRESSION_VERSION: Version = 1;
+
+/// The prefix of the pseudo extensions negotiated to tell that the extensions are disabled.
+/// Peers that don't know it ignore the negotiation, and the messages they keep sending are dropped.
+pub const DISABLED_EXTENSION_PREFIX: &str = "disabled/";
+
+/// Returns the name of the disabled extension if the negotiated name is the pseudo extension for it.
+pub fn disabled_extension(negotiated_name: &str) -> Option<&str> {
+    if negotiated_name.starts_with(DISABLED_EXTENSION_PREFIX) {
+        Some(&negotiated_name[DISABLED_EXTENSION_PREFIX.len()..])
+    } else {
+        None
+    }
+}
 
 #[cfg(test)]
 mod tests {
+    use super::COMPRESSED_ID;
     use super::ENCRYPTED_ID;
     use super::REQUEST_ID;
     use super::RESPONSE_ID;
@@ -48,6 +68,7 @@ mod tests {
         assert_ne!(REQUEST_ID, RESPONSE_ID);
         assert_ne!(REQUEST_ID, ENCRYPTED_ID);
         assert_ne!(REQUEST_ID, UNENCRYPTED_ID);
+        assert_ne!(REQUEST_ID, COMPRESSED_ID);
     }
 
     #[test]
@@ -55,6 +76,7 @@ mod tests {
         assert_ne!(RESPONSE_ID, REQUEST_ID);
         assert_ne!(RESPONSE_ID, ENCRYPTED_ID);
         assert_ne!(RESPONSE_ID, UNENCRYPTED_ID);
+        assert_ne!(RESPONSE_ID, COMPRESSED_ID);
     }
 
     #[test]
@@ -62,6 +84,7 @@ mod tests {
         assert_ne!(ENCRYPTED_ID, REQUEST_ID);
         assert_ne!(ENCRYPTED_ID, RESPONSE_ID);
         assert_ne!(ENCRYPTED_ID, UNENCRYPTED_ID);
+        assert_ne!(ENCRYPTED_ID, COMPRESSED_ID);
     }
 
     #[test]
@@ -69,5 +92,14 @@ mod tests {
         assert_ne!(UNENCRYPTED_ID, REQUEST_ID);
         assert_ne!(UNENCRYPTED_ID, RESPONSE_ID);
         assert_ne!(UNENCRYPTED_ID, ENCRYPTED_ID);
+        assert_ne!(UNENCRYPTED_ID, COMPRESSED_ID);
+    }
+
+    #[test]
+    fn compressed_id_is_a_unique() {
+        assert_ne!(COMPRESSED_ID, REQUEST_ID);
+        assert_ne!(COMPRESSED_ID, RESPONSE_ID);
+        assert_ne!(COMPRESSED_ID, ENCRYPTED_ID);
+        assert_ne!(COMPRESSED_ID, UNENCRYPTED_ID);
     }
 }
@@ -15,12 +15,13 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::SignedMessage;
+use crate::decoder::decode_message;
 use crate::session::Session;
 use crate::stream::{Error as StreamError, Stream};
 use crate::SocketAddr;
 use mio::event::Evented;
 use mio::{Poll, PollOpt, Ready, Token};
-use rlp::{Decodable, DecoderError, Encodable, Rlp};
+use rlp::{Decodable, DecoderError, Encodable};
 use std::fmt;
 use std::io;
 
@@ -84,8 +85,7 @@ impl SignedStream {
             if !signed.is_valid(&self.session) {
                 return Err(Error::InvalidSign)
             }
-            let rlp = Rlp::new(&signed.message);
-            Ok(Some(rlp.as_val::<M>()?))
+            Ok(Some(decode_message::<M>(&signed.message)?))
         } else {
             Ok(None)
         }
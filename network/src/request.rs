@@ -0,0 +1,220 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::extension::{RequestError, RequestId};
+use crate::{IntoSocketAddr, NodeId};
+use ctimer::{TimerApi, TimerToken};
+use primitives::Bytes;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The timer tokens of the requests start from it, so they don't collide with the tokens of the extensions.
+const REQUEST_TIMER_TOKEN_BASE: TimerToken = ::std::usize::MAX / 2 + 1;
+
+const MESSAGE_FRAME_ID: u8 = 0x00;
+const REQUEST_FRAME_ID: u8 = 0x01;
+const RESPONSE_FRAME_ID: u8 = 0x02;
+
+/// The messages of an extension are framed when the negotiated version is its `request_version` or later.
+#[derive(Debug, PartialEq)]
+pub enum Frame {
+    Message(Bytes),
+    Request(RequestId, Bytes),
+    Response(RequestId, Bytes),
+}
+
+impl Encodable for Frame {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            Frame::Message(message) => {
+                s.begin_list(2).append(&MESSAGE_FRAME_ID).append(message);
+            }
+            Frame::Request(request, message) => {
+                s.begin_list(3).append(&REQUEST_FRAME_ID).append(request).append(message);
+            }
+            Frame::Response(request, message) => {
+                s.begin_list(3).append(&RESPONSE_FRAME_ID).append(request).append(message);
+            }
+        }
+    }
+}
+
+impl Decodable for Frame {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        let id: u8 = rlp.val_at(0)?;
+        let expected = match id {
+            MESSAGE_FRAME_ID => 2,
+            REQUEST_FRAME_ID | RESPONSE_FRAME_ID => 3,
+            _ => return Err(DecoderError::Custom("Unexpected frame id")),
+        };
+        if item_count != expected {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected,
+                got: item_count,
+            })
+        }
+        match id {
+            MESSAGE_FRAME_ID => Ok(Frame::Message(rlp.val_at(1)?)),
+            REQUEST_FRAME_ID => Ok(Frame::Request(rlp.val_at(1)?, rlp.val_at(2)?)),
+            RESPONSE_FRAME_ID => Ok(Frame::Response(rlp.val_at(1)?, rlp.val_at(2)?)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Tracks the outstanding requests of an extension.
+/// Each request has its own timer in the timer of the extension.
+pub struct Requests {
+    name: &'static str,
+    request_version: Option<u64>,
+    timer: TimerApi,
+    /// The versions negotiated with the connected nodes.
+    versions: HashMap<NodeId, u64>,
+    next_id: RequestId,
+    outstanding: HashMap<RequestId, NodeId>,
+}
+
+impl Requests {
+    pub fn new(name: &'static str, request_version: Option<u64>, timer: TimerApi) -> Self {
+        Self {
+            name,
+            request_version,
+            timer,
+            versions: HashMap::new(),
+            next_id: 0,
+            outstanding: HashMap::new(),
+        }
+    }
+
+    pub fn is_request_timer(token: TimerToken) -> bool {
+        token >= REQUEST_TIMER_TOKEN_BASE
+    }
+
+    pub fn on_node_added(&mut self, node: NodeId, version: u64) {
+        self.versions.insert(node, version);
+    }
+
+    /// Returns the requests to the node, which are failed by the disconnection.
+    pub fn on_node_removed(&mut self, node: &NodeId) -> Vec<RequestId> {
+        self.versions.remove(node);
+        let mut failed: Vec<_> =
+            self.outstanding.iter().filter(|(_, requested)| *requested == node).map(|(request, _)| *request).collect();
+        failed.sort_unstable();
+        for request in &failed {
+            self.outstanding.remove(request);
+            self.cancel_timer(*request);
+        }
+        failed
+    }
+
+    /// Forgets all the nodes and the requests without failing them.
+    pub fn clear(&mut self) {
+        self.versions.clear();
+        let outstanding: Vec<_> = self.outstanding.drain().map(|(request, _)| request).collect();
+        for request in outstanding {
+            self.cancel_timer(request);
+        }
+    }
+
+    /// Whether the messages to and from the node are framed.
+    pub fn is_framed(&self, node: &NodeId) -> bool {
+        match (self.request_version, self.versions.get(node)) {
+            (Some(request_version), Some(version)) => *version >= request_version,
+            _ => false,
+        }
+    }
+
+    /// Returns the id of the new request. The error should be delivered with the id.
+    pub fn start(&mut self, node: &NodeId, timeout: Duration) -> Result<RequestId, (RequestId, RequestError)> {
+        let request = self.next_id;
+        self.next_id += 1;
+        if !self.versions.contains_key(node) {
+            return Err((request, RequestError::Disconnected))
+        }
+        if !self.is_framed(node) {
+            return Err((request, RequestError::NotSupported))
+        }
+        if let Err(err) = self.timer.schedule_once(timeout, timer_token(request)) {
+            cwarn!(NETAPI, "`{}` cannot schedule the timeout of request {}: {:?}", self.name, request, err);
+        }
+        self.outstanding.insert(request, *node);
+        Ok(request)
+    }
+
+    /// Returns false if the request to the node is already finished.
+    pub fn on_response(&mut self, node: &NodeId, request: RequestId) -> bool {
+        if self.outstanding.get(&request) != Some(node) {
+            return false
+        }
+        self.outstanding.remove(&request);
+        self.cancel_timer(request);
+        true
+    }
+
+    /// Returns the timed out request and its node if it's not finished yet.
+    pub fn on_timeout(&mut self, token: TimerToken) -> Option<(NodeId, RequestId)> {
+        let request = (token - REQUEST_TIMER_TOKEN_BASE) as RequestId;
+        let node = self.outstanding.remove(&request)?;
+        cdebug!(NETAPI, "`{}` request {} to {} timed out", self.name, request, node.into_addr());
+        Some((node, request))
+    }
+
+    fn cancel_timer(&self, request: RequestId) {
+        if let Err(err) = self.timer.cancel(timer_token(request)) {
+            cwarn!(NETAPI, "`{}` cannot cancel the timeout of request {}: {:?}", self.name, request, err);
+        }
+    }
+}
+
+fn timer_token(request: RequestId) -> TimerToken {
+    REQUEST_TIMER_TOKEN_BASE + request as TimerToken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::rlp_encode_and_decode_test;
+
+    #[test]
+    fn encode_and_decode_message() {
+        rlp_encode_and_decode_test!(Frame::Message(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn encode_and_decode_request() {
+        rlp_encode_and_decode_test!(Frame::Request(3, vec![]));
+    }
+
+    #[test]
+    fn encode_and_decode_response() {
+        rlp_encode_and_decode_test!(Frame::Response(::std::u64::MAX, vec![0; 100]));
+    }
+
+    #[test]
+    fn frame_with_an_unexpected_length() {
+        let mut s = RlpStream::new_list(2);
+        s.append(&RESPONSE_FRAME_ID).append(&3u64);
+        assert_eq!(
+            Err(DecoderError::RlpIncorrectListLen {
+                expected: 3,
+                got: 2
+            }),
+            rlp::decode::<Frame>(&s.out())
+        );
+    }
+}
@@ -0,0 +1,330 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::SocketAddr;
+use ccrypto::Blake;
+use ckey::{Public, Secret};
+use primitives::H256;
+use rlp::{DecoderError, Rlp, RlpStream};
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The time a ticket can be used after the handshake that derived it.
+pub const TICKET_LIFETIME: Duration = Duration::from_secs(10 * 60);
+/// The number of the tickets kept for each side of the handshake.
+const MAX_TICKETS: usize = 1024;
+
+/// A ticket derived by both sides of an authenticated handshake.
+/// Presenting it on the next connection skips the key exchange. It can be used only once.
+#[derive(Clone, Debug, PartialEq)]
+struct Ticket {
+    id: H256,
+    secret: Secret,
+    expires_at: Instant,
+}
+
+impl Ticket {
+    fn derive(shared_secret: &Secret, now: Instant) -> Self {
+        Self {
+            id: Blake::blake_with_key(b"resumption ticket id", shared_secret),
+            secret: Blake::blake_with_key(b"resumption ticket secret", shared_secret),
+            expires_at: now + TICKET_LIFETIME,
+        }
+    }
+
+    /// Proves that the initiator knows the secret of the ticket.
+    fn binder(&self, initiator_random: &H256) -> H256 {
+        let mut data = self.id.to_vec();
+        data.extend_from_slice(initiator_random);
+        Blake::blake_with_key(&data, &self.secret)
+    }
+
+    fn resumed_secret(&self, initiator_random: &H256) -> Secret {
+        Blake::blake_with_key(initiator_random, &self.secret)
+    }
+
+    // The expiry time is written in the wall-clock time because an instant is meaningless in another process.
+    fn rlp_append(&self, s: &mut RlpStream, node_id: &Public, now: Instant, system_now: SystemTime) {
+        let expires_at = system_now + self.expires_at.saturating_duration_since(now);
+        let expires_at = expires_at.duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
+        s.begin_list(4).append(node_id).append(&self.id).append(&self.secret).append(&expires_at);
+    }
+
+    /// Returns None if the ticket expired while it was written.
+    fn decode(rlp: &Rlp, now: Instant, system_now: SystemTime) -> Result<Option<(Public, Self)>, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 4 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 4,
+            })
+        }
+        let expires_at = UNIX_EPOCH + Duration::from_secs(rlp.val_at(3)?);
+        let remaining = match expires_at.duration_since(system_now) {
+            // A ticket never lives longer than its lifetime even if the clock went back.
+            Ok(remaining) if remaining > Duration::from_secs(0) => remaining.min(TICKET_LIFETIME),
+            _ => return Ok(None),
+        };
+        let ticket = Self {
+            id: rlp.val_at(1)?,
+            secret: rlp.val_at(2)?,
+            expires_at: now + remaining,
+        };
+        Ok(Some((rlp.val_at(0)?, ticket)))
+    }
+}
+
+/// What the initiator sends instead of the key exchange to resume a session.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResumptionRequest {
+    pub ticket_id: H256,
+    pub initiator_random: H256,
+    pub binder: H256,
+}
+
+#[derive(Default)]
+pub struct ResumptionTickets {
+    // The tickets to accept from the initiators, keyed by their node ids.
+    issued: HashMap<Public, Ticket>,
+    // The tickets to present to the recipients, keyed by their node ids.
+    received: HashMap<Public, Ticket>,
+    // The node ids of the recipients that signed the handshakes.
+    recipient_ids: HashMap<SocketAddr, Public>,
+    // The secrets of the resumptions that wait for the answers of the recipients.
+    pending: HashMap<SocketAddr, Secret>,
+}
+
+impl ResumptionTickets {
+    /// Called by the recipient after the handshake with `initiator_id`.
+    pub fn issue(&mut self, initiator_id: Public, shared_secret: &Secret, now: Instant) {
+        insert(&mut self.issued, initiator_id, Ticket::derive(shared_secret, now), now);
+    }
+
+    /// Called by the initiator after the handshake with `recipient_id` at `target`.
+    pub fn receive(&mut self, target: SocketAddr, recipient_id: Public, shared_secret: &Secret, now: Instant) {
+        insert(&mut self.received, recipient_id, Ticket::derive(shared_secret, now), now);
+        self.recipient_ids.insert(target, recipient_id);
+        let received = &self.received;
+        let pending = &self.pending;
+        self.recipient_ids.retain(|addr, id| received.contains_key(id) || pending.contains_key(addr));
+    }
+
+    /// Called by the initiator after the session with the target is resumed. The resumed secret derives the next ticket.
+    pub fn renew(&mut self, target: SocketAddr, resumed_secret: &Secret, now: Instant) {
+        if let Some(recipient_id) = self.recipient_ids.get(&target).cloned() {
+            self.receive(target, recipient_id, resumed_secret, now);
+        }
+    }
+
+    /// Takes the ticket for the target out of the store, so that it's never presented twice.
+    pub fn request(&mut self, target: SocketAddr, initiator_random: H256, now: Instant) -> Option<ResumptionRequest> {
+        let recipient_id = self.recipient_ids.get(&target)?;
+        let ticket = self.received.remove(recipient_id)?;
+        if ticket.expires_at <= now {
+            return None
+        }
+        self.pending.insert(target, ticket.resumed_secret(&initiator_random));
+        Some(ResumptionRequest {
+            ticket_id: ticket.id,
+            initiator_random,
+            binder: ticket.binder(&initiator_random),
+        })
+    }
+
+    /// The secret of the resumption requested to the target, which is forgotten after it's taken.
+    pub fn take_pending(&mut self, target: &SocketAddr) -> Option<Secret> {
+        self.pending.remove(target)
+    }
+
+    /// Returns the secret of the resumed session if the request has a valid ticket.
+    /// The ticket is consumed, so the same request is rejected if it's replayed.
+    pub fn accept(&mut self, initiator_id: &Public, request: &ResumptionRequest, now: Instant) -> Option<Secret> {
+        let ticket = self.issued.get(initiator_id)?;
+        if ticket.id != request.ticket_id || ticket.binder(&request.initiator_random) != request.binder {
+            // Someone who doesn't know the secret cannot burn the ticket.
+            return None
+        }
+        let ticket = self.issued.remove(initiator_id).expect("The ticket exists");
+        if ticket.expires_at <= now {
+            return None
+        }
+        Some(ticket.resumed_secret(&request.initiator_random))
+    }
+
+    /// Writes the tickets to restore them after a restart.
+    /// The pending resumptions aren't written because their connections don't survive the restart.
+    pub fn rlp_append(&self, s: &mut RlpStream, now: Instant, system_now: SystemTime) {
+        s.begin_list(3);
+        s.begin_list(self.issued.len());
+        for (initiator_id, ticket) in &self.issued {
+            ticket.rlp_append(s, initiator_id, now, system_now);
+        }
+        s.begin_list(self.received.len());
+        for (recipient_id, ticket) in &self.received {
+            ticket.rlp_append(s, recipient_id, now, system_now);
+        }
+        let recipient_ids: Vec<_> =
+            self.recipient_ids.iter().filter(|(_, id)| self.received.contains_key(*id)).collect();
+        s.begin_list(recipient_ids.len());
+        for (target, recipient_id) in recipient_ids {
+            s.begin_list(2).append(target).append(recipient_id);
+        }
+    }
+
+    /// Reads the tickets written by `rlp_append`. The tickets that expired in the meantime are dropped.
+    pub fn decode(rlp: &Rlp, now: Instant, system_now: SystemTime) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 3 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 3,
+            })
+        }
+        let decode_tickets = |rlp: Rlp| -> Result<HashMap<Public, Ticket>, DecoderError> {
+            let mut tickets = HashMap::new();
+            for ticket in rlp.iter() {
+                if let Some((node_id, ticket)) = Ticket::decode(&ticket, now, system_now)? {
+                    tickets.insert(node_id, ticket);
+                }
+            }
+            Ok(tickets)
+        };
+        let issued = decode_tickets(rlp.at(0)?)?;
+        let received = decode_tickets(rlp.at(1)?)?;
+        let mut recipient_ids = HashMap::new();
+        for pair in rlp.at(2)?.iter() {
+            let recipient_id: Public = pair.val_at(1)?;
+            if received.contains_key(&recipient_id) {
+                recipient_ids.insert(pair.val_at(0)?, recipient_id);
+            }
+        }
+        Ok(Self {
+            issued,
+            received,
+            recipient_ids,
+            pending: HashMap::new(),
+        })
+    }
+}
+
+fn insert(tickets: &mut HashMap<Public, Ticket>, node_id: Public, ticket: Ticket, now: Instant) {
+    tickets.retain(|_, ticket| now < ticket.expires_at);
+    if tickets.len() >= MAX_TICKETS && !tickets.contains_key(&node_id) {
+        let oldest = *tickets.iter().min_by_key(|(_, ticket)| ticket.expires_at).expect("Tickets are full").0;
+        tickets.remove(&oldest);
+    }
+    tickets.insert(node_id, ticket);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn tickets_of_a_handshake(now: Instant) -> (ResumptionTickets, ResumptionTickets, Public, SocketAddr) {
+        let shared_secret = Secret::random();
+        let initiator_id = Public::random();
+        let recipient_id = Public::random();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+
+        let mut initiator = ResumptionTickets::default();
+        let mut recipient = ResumptionTickets::default();
+        initiator.receive(recipient_addr, recipient_id, &shared_secret, now);
+        recipient.issue(initiator_id, &shared_secret, now);
+        (initiator, recipient, initiator_id, recipient_addr)
+    }
+
+    #[test]
+    fn both_sides_derive_the_same_resumed_secret() {
+        let now = Instant::now();
+        let (mut initiator, mut recipient, initiator_id, recipient_addr) = tickets_of_a_handshake(now);
+
+        let request = initiator.request(recipient_addr, H256::random(), now).unwrap();
+        let initiator_secret = initiator.take_pending(&recipient_addr).unwrap();
+        assert_eq!(Some(initiator_secret), recipient.accept(&initiator_id, &request, now));
+        // The initiator presents a ticket only once.
+        assert_eq!(None, initiator.request(recipient_addr, H256::random(), now));
+    }
+
+    #[test]
+    fn forged_binder_does_not_consume_the_ticket() {
+        let now = Instant::now();
+        let (mut initiator, mut recipient, initiator_id, recipient_addr) = tickets_of_a_handshake(now);
+
+        let request = initiator.request(recipient_addr, H256::random(), now).unwrap();
+        let forged = ResumptionRequest {
+            binder: H256::random(),
+            ..request
+        };
+        assert_eq!(None, recipient.accept(&initiator_id, &forged, now));
+        assert!(recipient.accept(&initiator_id, &request, now).is_some());
+    }
+
+    #[test]
+    fn expired_ticket_is_rejected() {
+        let now = Instant::now();
+        let (mut initiator, mut recipient, initiator_id, recipient_addr) = tickets_of_a_handshake(now);
+
+        let later = now + TICKET_LIFETIME;
+        let request = initiator.request(recipient_addr, H256::random(), now).unwrap();
+        assert_eq!(None, recipient.accept(&initiator_id, &request, later));
+
+        let (mut initiator, _, _, recipient_addr) = tickets_of_a_handshake(now);
+        assert_eq!(None, initiator.request(recipient_addr, H256::random(), later));
+    }
+
+    fn restart(
+        tickets: &ResumptionTickets,
+        now: Instant,
+        system_now: SystemTime,
+        later: Duration,
+    ) -> ResumptionTickets {
+        let mut s = RlpStream::new();
+        tickets.rlp_append(&mut s, now, system_now);
+        let encoded = s.out();
+        // The instants of the restarted process have nothing to do with the previous ones.
+        ResumptionTickets::decode(&Rlp::new(&encoded), Instant::now(), system_now + later).unwrap()
+    }
+
+    #[test]
+    fn tickets_survive_restarts() {
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let (initiator, recipient, initiator_id, recipient_addr) = tickets_of_a_handshake(now);
+
+        let mut initiator = restart(&initiator, now, system_now, Duration::from_secs(60));
+        let mut recipient = restart(&recipient, now, system_now, Duration::from_secs(60));
+        let request = initiator.request(recipient_addr, H256::random(), Instant::now()).unwrap();
+        let initiator_secret = initiator.take_pending(&recipient_addr).unwrap();
+        assert_eq!(Some(initiator_secret), recipient.accept(&initiator_id, &request, Instant::now()));
+
+        // The consumed ticket is not restored.
+        let mut recipient = restart(&recipient, Instant::now(), system_now, Duration::from_secs(0));
+        assert_eq!(None, recipient.accept(&initiator_id, &request, Instant::now()));
+    }
+
+    #[test]
+    fn tickets_expired_during_restarts_are_dropped() {
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let (initiator, recipient, initiator_id, recipient_addr) = tickets_of_a_handshake(now);
+
+        let mut initiator = restart(&initiator, now, system_now, TICKET_LIFETIME);
+        let recipient = restart(&recipient, now, system_now, TICKET_LIFETIME);
+        assert_eq!(None, initiator.request(recipient_addr, H256::random(), Instant::now()));
+        assert!(recipient.issued.get(&initiator_id).is_none());
+    }
+}
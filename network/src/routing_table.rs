@@ -14,17 +14,35 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::session::{Nonce, Session};
+use crate::resumption::{ResumptionRequest, ResumptionTickets};
+use crate::session::{NetworkSecret, Nonce, Session};
 use crate::SocketAddr;
 use ccrypto::aes;
 use ccrypto::error::SymmError;
-use ckey::{exchange, Generator, KeyPair, Public, Random, Secret};
+use ccrypto::Blake;
+use ckey::{
+    authenticated_exchange, exchange, verify_handshake, Generator, HandshakeTranscript, KeyPair, Private, Public,
+    Random, SchnorrSignature, Secret,
+};
 use parking_lot::{Mutex, RwLock};
-use primitives::Bytes;
+use primitives::{Bytes, H256};
 use rand::rngs::OsRng;
 use rand::Rng;
-use std::collections::HashMap;
+use rlp::{DecoderError, Rlp, RlpStream};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The time to wait before retrying the handshake with a node that failed it.
+const HANDSHAKE_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// The time to use the version 1 of the handshake with a node that didn't answer the version 2.
+/// The node is tried with the version 2 again after it, so that a dropped handshake doesn't downgrade it forever.
+/// The nodes that completed the version 2 before are never downgraded.
+const LEGACY_HANDSHAKE_DURATION: Duration = Duration::from_secs(10 * 60);
+
+/// The node id of the recipient and its signature on the handshake transcript.
+pub type NodeAuthentication = (Public, SchnorrSignature);
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 enum SecretOrigin {
@@ -122,18 +140,280 @@ impl Default for State {
 
 pub struct RoutingTable {
     entries: RwLock<HashMap<SocketAddr, State>>,
+    network_secret: Option<NetworkSecret>,
+    // The nodes that failed the handshake and the time until which the handshake is not retried.
+    // It's kept apart from the entries because the entries of the failed nodes are removed.
+    delayed_retries: Mutex<HashMap<SocketAddr, Instant>>,
+    /// The long-term key of this node. Its public key is the node id signed in the handshakes.
+    identity: KeyPair,
+    // The nodes that didn't answer the authenticated handshake and the time until which the version 1 is used.
+    legacy_handshakes: Mutex<HashMap<SocketAddr, Instant>>,
+    // The nodes that completed the authenticated handshake. The handshakes with them are pinned to the version 2,
+    // so that a man in the middle cannot downgrade them by dropping or stripping the messages.
+    authenticated_peers: Mutex<HashSet<SocketAddr>>,
+    // The transcripts and the secrets of the authenticated handshakes that wait for the signature of the initiator.
+    pending_initiator_signatures: Mutex<HashMap<SocketAddr, (HandshakeTranscript, Secret)>>,
+    // The tickets to resume the sessions without the key exchange.
+    // They are kept apart from the entries because the entries of the disconnected nodes are removed.
+    resumption_tickets: Mutex<ResumptionTickets>,
 
     rng: Mutex<OsRng>,
 }
 
 impl RoutingTable {
     pub fn new() -> Arc<Self> {
+        Self::new_with_network_secret(None)
+    }
+
+    /// Only the nodes that have the same network secret can establish sessions with this node.
+    pub fn new_with_network_secret(network_secret: Option<NetworkSecret>) -> Arc<Self> {
+        Self::new_with_identity(network_secret, Random.generate().unwrap())
+    }
+
+    /// The public key of the identity is the node id signed in the handshakes.
+    pub fn new_with_identity(network_secret: Option<NetworkSecret>, identity: KeyPair) -> Arc<Self> {
         Arc::new(Self {
             entries: RwLock::new(HashMap::new()),
+            network_secret,
+            delayed_retries: Default::default(),
+            identity,
+            legacy_handshakes: Default::default(),
+            authenticated_peers: Default::default(),
+            pending_initiator_signatures: Default::default(),
+            resumption_tickets: Default::default(),
             rng: Mutex::new(OsRng::new().unwrap()),
         })
     }
 
+    /// Encodes the resumption tickets and the pinned peers, which are restored after a restart.
+    pub fn persistent_state(&self) -> Bytes {
+        let mut s = RlpStream::new_list(2);
+        self.resumption_tickets.lock().rlp_append(&mut s, Instant::now(), SystemTime::now());
+        let authenticated_peers: Vec<_> = self.authenticated_peers.lock().iter().cloned().collect();
+        s.append_list(&authenticated_peers);
+        s.out()
+    }
+
+    /// Restores the state encoded by `persistent_state`.
+    pub fn restore_persistent_state(&self, encoded: &[u8]) -> Result<(), DecoderError> {
+        let rlp = Rlp::new(encoded);
+        let item_count = rlp.item_count()?;
+        if item_count != 2 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 2,
+            })
+        }
+        let resumption_tickets = ResumptionTickets::decode(&rlp.at(0)?, Instant::now(), SystemTime::now())?;
+        let authenticated_peers: Vec<SocketAddr> = rlp.list_at(1)?;
+        *self.resumption_tickets.lock() = resumption_tickets;
+        self.authenticated_peers.lock().extend(authenticated_peers);
+        Ok(())
+    }
+
+    fn exchange(&self, remote_public: &Public, local_private: &Private) -> Result<Secret, String> {
+        let shared_secret =
+            exchange(remote_public, local_private).map_err(|e| format!("Cannot exchange key: {:?}", e))?;
+        Ok(self.mix_network_secret(shared_secret))
+    }
+
+    fn mix_network_secret(&self, shared_secret: Secret) -> Secret {
+        match &self.network_secret {
+            Some(network_secret) => network_secret.mix(&shared_secret),
+            None => shared_secret,
+        }
+    }
+
+    /// Signs the transcript if the initiator sent its node id. Otherwise, the handshake is the version 1,
+    /// which is rejected if the initiator completed the version 2 before.
+    /// The ticket is issued after the initiator signs the transcript, too.
+    fn recipient_secret(
+        &self,
+        target: SocketAddr,
+        local_key_pair: &KeyPair,
+        remote_public: &Public,
+        initiator_id: Option<Public>,
+    ) -> Result<(Secret, Option<NodeAuthentication>), String> {
+        let initiator_id = match initiator_id {
+            Some(initiator_id) => initiator_id,
+            None if self.is_authenticated_peer(&target) => {
+                return Err(format!(
+                    "{} completed the authenticated handshake before. Cannot downgrade it to the version 1",
+                    target
+                ))
+            }
+            None => return Ok((self.exchange(remote_public, local_key_pair.private())?, None)),
+        };
+        let transcript = HandshakeTranscript {
+            initiator_ephemeral: *remote_public,
+            recipient_ephemeral: *local_key_pair.public(),
+            initiator_id,
+            recipient_id: *self.identity.public(),
+        };
+        let (shared_secret, signature) = authenticated_exchange(local_key_pair, &self.identity, &transcript)
+            .map_err(|e| format!("Cannot exchange key: {:?}", e))?;
+        let shared_secret = self.mix_network_secret(shared_secret);
+        self.pending_initiator_signatures.lock().insert(target, (transcript, shared_secret));
+        Ok((shared_secret, Some((*self.identity.public(), signature))))
+    }
+
+    /// Verifies the signature of the recipient unless the target falls back to the version 1.
+    /// Returns the signature of this node on the transcript, which is sent to the recipient.
+    fn initiator_secret(
+        &self,
+        target: SocketAddr,
+        local_key_pair: &KeyPair,
+        remote_public: &Public,
+        authentication: Option<NodeAuthentication>,
+    ) -> Result<(Secret, Option<SchnorrSignature>), String> {
+        let (recipient_id, signature) = match (self.uses_legacy_handshake(&target), authentication) {
+            (true, _) => return Ok((self.exchange(remote_public, local_key_pair.private())?, None)),
+            (false, Some(authentication)) => authentication,
+            (false, None) => return Err(format!("{} didn't sign the handshake", target)),
+        };
+        let transcript = HandshakeTranscript {
+            initiator_ephemeral: *local_key_pair.public(),
+            recipient_ephemeral: *remote_public,
+            initiator_id: *self.identity.public(),
+            recipient_id,
+        };
+        if verify_handshake(&recipient_id, &signature, &transcript) != Ok(true) {
+            return Err(format!("The handshake signature of {} is invalid", target))
+        }
+        let (shared_secret, initiator_signature) = authenticated_exchange(local_key_pair, &self.identity, &transcript)
+            .map_err(|e| format!("Cannot exchange key: {:?}", e))?;
+        let shared_secret = self.mix_network_secret(shared_secret);
+        self.resumption_tickets.lock().receive(target, recipient_id, &shared_secret, Instant::now());
+        self.authenticated_peers.lock().insert(target);
+        Ok((shared_secret, Some(initiator_signature)))
+    }
+
+    /// Verifies the signature of the initiator on the transcript of the authenticated handshake.
+    /// The ticket of the session is issued only after it, because the node id of the initiator isn't proven before.
+    pub fn set_recipient_finish(&self, target: SocketAddr, signature: &SchnorrSignature) -> Result<(), String> {
+        let (transcript, shared_secret) = self
+            .pending_initiator_signatures
+            .lock()
+            .remove(&target)
+            .ok_or_else(|| format!("{} didn't take the authenticated handshake", target))?;
+        if verify_handshake(&transcript.initiator_id, signature, &transcript) != Ok(true) {
+            return Err(format!("The handshake signature of {} is invalid", target))
+        }
+        self.resumption_tickets.lock().issue(transcript.initiator_id, &shared_secret, Instant::now());
+        self.authenticated_peers.lock().insert(target);
+        Ok(())
+    }
+
+    /// True if the recipient waits for the signature of the initiator before the session is used.
+    pub fn waits_for_initiator_signature(&self, target: &SocketAddr) -> bool {
+        self.pending_initiator_signatures.lock().contains_key(target)
+    }
+
+    fn is_authenticated_peer(&self, target: &SocketAddr) -> bool {
+        self.authenticated_peers.lock().contains(target)
+    }
+
+    /// The node id to send in the sync message, or None if the target falls back to the version 1 of the handshake.
+    pub fn handshake_node_id(&self, target: &SocketAddr) -> Option<Public> {
+        if self.uses_legacy_handshake(target) {
+            None
+        } else {
+            Some(*self.identity.public())
+        }
+    }
+
+    /// Takes the ticket of the previous session with the target, which is sent instead of the key exchange.
+    /// Returns None if there's no valid ticket or if the target isn't in the state to send Sync1.
+    pub fn resumption_request(&self, target: SocketAddr) -> Option<ResumptionRequest> {
+        if self.uses_legacy_handshake(&target) {
+            return None
+        }
+        let entries = self.entries.read();
+        match entries.get(&target) {
+            Some(State::Establishing1(_)) => {}
+            _ => return None,
+        }
+        let initiator_random = self.rng.lock().gen::<[u8; 32]>().into();
+        self.resumption_tickets.lock().request(target, initiator_random, Instant::now())
+    }
+
+    /// Forgets the requested resumption. True if it was requested.
+    pub fn abandon_resumption(&self, target: &SocketAddr) -> bool {
+        self.resumption_tickets.lock().take_pending(target).is_some()
+    }
+
+    fn uses_legacy_handshake(&self, target: &SocketAddr) -> bool {
+        let mut legacy_handshakes = self.legacy_handshakes.lock();
+        match legacy_handshakes.get(target) {
+            Some(until) if Instant::now() < *until => true,
+            Some(_) => {
+                legacy_handshakes.remove(target);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Falls back to the version 1 of the handshake for a while if the target dropped the version 2 unanswered.
+    /// The targets that completed the version 2 before are not downgraded. Returns true if it falls back.
+    pub fn fall_back_to_legacy_handshake(&self, target: SocketAddr) -> bool {
+        if !self.is_establishing(&target) || self.uses_legacy_handshake(&target) {
+            return false
+        }
+        if self.is_authenticated_peer(&target) {
+            cwarn!(
+                NETWORK,
+                "{} didn't answer the authenticated handshake though it completed one before. It's not downgraded",
+                target
+            );
+            return false
+        }
+        self.legacy_handshakes.lock().insert(target, Instant::now() + LEGACY_HANDSHAKE_DURATION);
+        cwarn!(
+            NETWORK,
+            "{} didn't answer the authenticated handshake. The unauthenticated version 1 will be used for a while",
+            target
+        );
+        true
+    }
+
+    /// True if the handshake with the target failed recently.
+    pub fn is_retry_delayed(&self, target: &SocketAddr) -> bool {
+        let mut delayed_retries = self.delayed_retries.lock();
+        match delayed_retries.get(target) {
+            Some(until) if Instant::now() < *until => true,
+            Some(_) => {
+                delayed_retries.remove(target);
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn delay_retry(&self, target: SocketAddr) {
+        self.delayed_retries.lock().insert(target, Instant::now() + HANDSHAKE_RETRY_DELAY);
+    }
+
+    /// Delays the handshakes from an initiator that closed the inbound session without sending anything.
+    /// The initiator does it when it cannot decrypt the nonce, i.e. it doesn't have the same network secret.
+    pub fn note_silent_inbound_session(&self, initiator: SocketAddr) {
+        if self.network_secret.is_some() {
+            cinfo!(
+                NETWORK,
+                "{} closed the session without a message. It may not have the same network secret",
+                initiator
+            );
+            self.delay_retry(initiator);
+        }
+    }
+
+    fn check_inbound_retry(&self, initiator: &SocketAddr) -> Result<(), String> {
+        if self.is_retry_delayed(initiator) {
+            return Err(format!("Cannot accept the handshake. the handshake with {} failed recently", initiator))
+        }
+        Ok(())
+    }
+
     pub fn is_banned(&self, target: &SocketAddr) -> bool {
         let entries = self.entries.read();
         match entries.get(target) {
@@ -282,6 +562,9 @@ impl RoutingTable {
     }
 
     pub fn try_establish(&self, target: SocketAddr) -> Result<Option<Public>, String> {
+        if self.is_retry_delayed(&target) {
+            return Err(format!("Cannot try establish. the handshake with {} failed recently", target))
+        }
         let mut entries = self.entries.write();
         let entry = entries.entry(target).or_default();
         let new_state = match entry {
@@ -291,8 +574,7 @@ impl RoutingTable {
                 remote_public,
                 secret_origin,
             } => {
-                let shared_secret = exchange(remote_public, local_key_pair.private())
-                    .map_err(|e| format!("Cannot exchange key: {:?}", e))?;
+                let shared_secret = self.exchange(remote_public, local_key_pair.private())?;
                 State::Establishing2 {
                     local_key_pair: *local_key_pair,
                     remote_public: *remote_public,
@@ -319,15 +601,17 @@ impl RoutingTable {
         &self,
         target: SocketAddr,
         received_remote_public: Public,
-    ) -> Result<Option<(Bytes, Public, Session)>, String> {
+        initiator_id: Option<Public>,
+    ) -> Result<Option<(Bytes, Public, Session, Option<NodeAuthentication>)>, String> {
+        self.check_inbound_retry(&target)?;
         let mut entries = self.entries.write();
         let mut rng = self.rng.lock();
         let entry = entries.entry(target).or_default();
-        let (new_state, shared_secret, nonce, local_public) = match entry {
+        let (new_state, shared_secret, nonce, local_public, authentication) = match entry {
             State::Candidate(local_key_pair) => {
                 let nonce = rng.gen();
-                let shared_secret = exchange(&received_remote_public, local_key_pair.private())
-                    .map_err(|e| format!("Cannot exchange key: {:?}", e))?;
+                let (shared_secret, authentication) =
+                    self.recipient_secret(target, local_key_pair, &received_remote_public, initiator_id)?;
                 (
                     State::Established {
                         local_key_pair: *local_key_pair,
@@ -339,6 +623,7 @@ impl RoutingTable {
                     shared_secret,
                     nonce,
                     *local_key_pair.public(),
+                    authentication,
                 )
             }
             State::Registered {
@@ -353,8 +638,8 @@ impl RoutingTable {
                     ))
                 }
                 let nonce = rng.gen();
-                let shared_secret = exchange(remote_public, local_key_pair.private())
-                    .map_err(|e| format!("Cannot exchange key: {:?}", e))?;
+                let (shared_secret, authentication) =
+                    self.recipient_secret(target, local_key_pair, remote_public, initiator_id)?;
                 (
                     State::Established {
                         local_key_pair: *local_key_pair,
@@ -366,6 +651,7 @@ impl RoutingTable {
                     shared_secret,
                     nonce,
                     *local_key_pair.public(),
+                    authentication,
                 )
             }
             State::Establishing1(_) => return Ok(None),
@@ -383,10 +669,51 @@ impl RoutingTable {
             }
             _ => return Err("Cannot establish a connection for Recipient".to_string()),
         };
-        let encrypted_nonce =
-            encrypt_nonce(nonce, &shared_secret).map_err(|e| format!("Cannot encrypt nonce: {:?}", e))?;
+        let encrypted_nonce = encrypt_nonce(nonce, &shared_secret, self.network_secret.is_some())
+            .map_err(|e| format!("Cannot encrypt nonce: {:?}", e))?;
         *entry = new_state;
-        Ok(Some((encrypted_nonce, local_public, entry.session().expect("Established connection must have a session"))))
+        let session = entry.session().expect("Established connection must have a session");
+        debug_assert_ephemeral_used_once(&entries, &local_public);
+        Ok(Some((encrypted_nonce, local_public, session, authentication)))
+    }
+
+    /// Resumes the session if the initiator presents a valid ticket. Returns None if it doesn't,
+    /// then the message should be handled as Sync1.
+    pub fn set_recipient_resume(
+        &self,
+        target: SocketAddr,
+        received_remote_public: Public,
+        initiator_id: Public,
+        request: &ResumptionRequest,
+    ) -> Result<Option<(Bytes, Public, Session)>, String> {
+        self.check_inbound_retry(&target)?;
+        let mut entries = self.entries.write();
+        let mut rng = self.rng.lock();
+        let entry = entries.entry(target).or_default();
+        let local_key_pair = match entry {
+            State::Candidate(local_key_pair) => *local_key_pair,
+            _ => return Ok(None),
+        };
+        let mut resumption_tickets = self.resumption_tickets.lock();
+        let now = Instant::now();
+        let resumed_secret = match resumption_tickets.accept(&initiator_id, request, now) {
+            Some(resumed_secret) => resumed_secret,
+            None => return Ok(None),
+        };
+        let nonce = rng.gen();
+        // The tag confirms that the recipient knows the secret of the ticket.
+        let encrypted_nonce =
+            encrypt_nonce(nonce, &resumed_secret, true).map_err(|e| format!("Cannot encrypt nonce: {:?}", e))?;
+        resumption_tickets.issue(initiator_id, &resumed_secret, now);
+        *entry = State::Established {
+            local_key_pair,
+            remote_public: received_remote_public,
+            shared_secret: resumed_secret,
+            secret_origin: SecretOrigin::Shared,
+            nonce,
+        };
+        let session = entry.session().expect("Established connection must have a session");
+        Ok(Some((encrypted_nonce, *local_key_pair.public(), session)))
     }
 
     pub fn set_recipient_establish2(
@@ -394,11 +721,13 @@ impl RoutingTable {
         target: SocketAddr,
         received_local_public: Public,
         received_remote_public: Public,
-    ) -> Result<Option<(Bytes, Public, Session)>, String> {
+        initiator_id: Option<Public>,
+    ) -> Result<Option<(Bytes, Public, Session, Option<NodeAuthentication>)>, String> {
+        self.check_inbound_retry(&target)?;
         let mut entries = self.entries.write();
         let mut rng = self.rng.lock();
         let entry = entries.entry(target).or_default();
-        let (new_state, shared_secret, nonce, local_public) = match entry {
+        let (new_state, shared_secret, nonce, local_public, authentication) = match entry {
             State::Candidate(local_key_pair) => {
                 if received_local_public != *local_key_pair.public() {
                     return Err(format!(
@@ -408,8 +737,8 @@ impl RoutingTable {
                     ))
                 }
                 let nonce = rng.gen();
-                let shared_secret = exchange(&received_remote_public, local_key_pair.private())
-                    .map_err(|e| format!("Cannot exchange key: {:?}", e))?;
+                let (shared_secret, authentication) =
+                    self.recipient_secret(target, local_key_pair, &received_remote_public, initiator_id)?;
                 (
                     State::Established {
                         local_key_pair: *local_key_pair,
@@ -421,6 +750,7 @@ impl RoutingTable {
                     shared_secret,
                     nonce,
                     *local_key_pair.public(),
+                    authentication,
                 )
             }
             State::Registered {
@@ -442,8 +772,8 @@ impl RoutingTable {
                     ))
                 }
                 let nonce = rng.gen();
-                let shared_secret = exchange(remote_public, local_key_pair.private())
-                    .map_err(|e| format!("Cannot exchange key: {:?}", e))?;
+                let (shared_secret, authentication) =
+                    self.recipient_secret(target, local_key_pair, remote_public, initiator_id)?;
                 (
                     State::Established {
                         local_key_pair: *local_key_pair,
@@ -455,6 +785,7 @@ impl RoutingTable {
                     shared_secret,
                     nonce,
                     *local_key_pair.public(),
+                    authentication,
                 )
             }
             State::Establishing1(local_key_pair) => {
@@ -489,10 +820,12 @@ impl RoutingTable {
             }
             _ => return Err("Cannot establish a connection for Recipient".to_string()),
         };
-        let encrypted_nonce =
-            encrypt_nonce(nonce, &shared_secret).map_err(|e| format!("Cannot encrypt nonce: {:?}", e))?;
+        let encrypted_nonce = encrypt_nonce(nonce, &shared_secret, self.network_secret.is_some())
+            .map_err(|e| format!("Cannot encrypt nonce: {:?}", e))?;
         *entry = new_state;
-        Ok(Some((encrypted_nonce, local_public, entry.session().expect("Established connection must have a session"))))
+        let session = entry.session().expect("Established connection must have a session");
+        debug_assert_ephemeral_used_once(&entries, &local_public);
+        Ok(Some((encrypted_nonce, local_public, session, authentication)))
     }
 
     pub fn set_initiator_establish(
@@ -500,27 +833,33 @@ impl RoutingTable {
         target: SocketAddr,
         remote_public: Public,
         encrypted_nonce: &[u8],
-    ) -> Result<Session, String> {
+        authentication: Option<NodeAuthentication>,
+    ) -> Result<(Session, Option<SchnorrSignature>), String> {
+        // The recipient rejected the ticket and answered with the key exchange.
+        self.abandon_resumption(&target);
         let mut entries = self.entries.write();
         let entry = entries.entry(target).or_default();
-        let new_state = match entry {
+        let (new_state, signature) = match entry {
             State::Establishing1(local_key_pair) => {
-                let shared_secret = exchange(&remote_public, local_key_pair.private())
-                    .map_err(|e| format!("Cannot exchange key: {:?}", e))?;
-                let nonce = decrypt_nonce(encrypted_nonce, &shared_secret)?;
-                State::Established {
-                    local_key_pair: *local_key_pair,
-                    remote_public,
-                    shared_secret,
-                    secret_origin: SecretOrigin::Shared,
-                    nonce,
-                }
+                let (shared_secret, signature) =
+                    self.initiator_secret(target, local_key_pair, &remote_public, authentication)?;
+                let nonce = self.decrypt_received_nonce(target, encrypted_nonce, &shared_secret)?;
+                (
+                    State::Established {
+                        local_key_pair: *local_key_pair,
+                        remote_public,
+                        shared_secret,
+                        secret_origin: SecretOrigin::Shared,
+                        nonce,
+                    },
+                    signature,
+                )
             }
             State::Establishing2 {
                 local_key_pair,
                 remote_public: reserved_remote_public,
-                shared_secret,
                 secret_origin,
+                ..
             } => {
                 if remote_public != *reserved_remote_public {
                     return Err(format!(
@@ -528,23 +867,74 @@ impl RoutingTable {
                         reserved_remote_public, remote_public
                     ))
                 }
-                debug_assert_eq!(*shared_secret, exchange(&remote_public, local_key_pair.private()).unwrap());
-                let nonce = decrypt_nonce(encrypted_nonce, &shared_secret)?;
-                State::Established {
-                    local_key_pair: *local_key_pair,
-                    remote_public,
-                    shared_secret: *shared_secret,
-                    secret_origin: *secret_origin,
-                    nonce,
-                }
+                // The secret derived in try_establish is of the version 1, which isn't bound to the node ids.
+                let (shared_secret, signature) =
+                    self.initiator_secret(target, local_key_pair, &remote_public, authentication)?;
+                let nonce = self.decrypt_received_nonce(target, encrypted_nonce, &shared_secret)?;
+                (
+                    State::Established {
+                        local_key_pair: *local_key_pair,
+                        remote_public,
+                        shared_secret,
+                        secret_origin: *secret_origin,
+                        nonce,
+                    },
+                    signature,
+                )
             }
             _ => return Err("Initiator is not Establishing1".to_string()),
         };
+        let local_public = *new_state.local_public().expect("Established connection must have a local key");
         *entry = new_state;
+        let session = entry.session().expect("Established connection must have a session");
+        debug_assert_ephemeral_used_once(&entries, &local_public);
+        Ok((session, signature))
+    }
+
+    pub fn set_initiator_resume(
+        &self,
+        target: SocketAddr,
+        remote_public: Public,
+        encrypted_nonce: &[u8],
+    ) -> Result<Session, String> {
+        let mut entries = self.entries.write();
+        let entry = entries.entry(target).or_default();
+        let local_key_pair = match entry {
+            State::Establishing1(local_key_pair) => *local_key_pair,
+            _ => return Err("Initiator is not Establishing1".to_string()),
+        };
+        let mut resumption_tickets = self.resumption_tickets.lock();
+        let resumed_secret = resumption_tickets
+            .take_pending(&target)
+            .ok_or_else(|| format!("No resumption was requested to {}", target))?;
+        let nonce = decrypt_nonce(encrypted_nonce, &resumed_secret, true)?;
+        resumption_tickets.renew(target, &resumed_secret, Instant::now());
+        *entry = State::Established {
+            local_key_pair,
+            remote_public,
+            shared_secret: resumed_secret,
+            secret_origin: SecretOrigin::Shared,
+            nonce,
+        };
         Ok(entry.session().expect("Established connection must have a session"))
     }
 
+    /// Delays the retries to the target if the nonce cannot be decrypted,
+    /// which happens when the target doesn't have the same network secret.
+    fn decrypt_received_nonce(
+        &self,
+        target: SocketAddr,
+        encrypted_nonce: &[u8],
+        shared_secret: &Secret,
+    ) -> Result<Nonce, String> {
+        decrypt_nonce(encrypted_nonce, shared_secret, self.network_secret.is_some()).map_err(|err| {
+            self.delay_retry(target);
+            format!("{}. {} may not have the same network secret", err, target)
+        })
+    }
+
     pub fn reset_initiator_establish(&self, target: SocketAddr) -> Result<(), String> {
+        self.abandon_resumption(&target);
         let mut entries = self.entries.write();
         let entry = entries.entry(target).or_default();
         let new_state = match entry {
@@ -607,6 +997,7 @@ impl RoutingTable {
             return false
         }
         entries.remove(target);
+        self.pending_initiator_signatures.lock().remove(target);
         true
     }
 
@@ -617,35 +1008,67 @@ impl RoutingTable {
     }
 }
 
-fn decrypt_nonce(encrypted_bytes: &[u8], shared_secret: &Secret) -> Result<Nonce, String> {
+fn decrypt_nonce(encrypted_bytes: &[u8], shared_secret: &Secret, with_confirmation: bool) -> Result<Nonce, String> {
     let iv = 0; // FIXME: Use proper iv
     let unecrypted =
         aes::decrypt(encrypted_bytes, shared_secret, &iv).map_err(|e| format!("Cannot decrypt nonce: {:?}", e))?;
     debug_assert_eq!(std::mem::size_of::<Nonce>(), 16);
-    if unecrypted.len() != 16 {
+    let expected_len = if with_confirmation {
+        32
+    } else {
+        16
+    };
+    if unecrypted.len() != expected_len {
         return Err(format!(
-            "Cannot decrpyt nonce: 16 length bytes expected but, {} length bytes received",
+            "Cannot decrpyt nonce: {} length bytes expected but, {} length bytes received",
+            expected_len,
             unecrypted.len()
         )) // FIXME
     }
     let mut nonce_bytes = [0u8; 16];
-    nonce_bytes.copy_from_slice(&unecrypted);
+    nonce_bytes.copy_from_slice(&unecrypted[..16]);
+    if with_confirmation && unecrypted[16..] != confirmation_tag(&nonce_bytes, shared_secret)[..] {
+        return Err("Cannot decrypt nonce: the confirmation tag doesn't match".to_string())
+    }
     Ok(Nonce::from_be_bytes(nonce_bytes))
 }
 
-fn encrypt_nonce(nonce: Nonce, shared_secret: &Secret) -> Result<Bytes, SymmError> {
+/// When the network secret is used, the nonce is sent with a tag to confirm that both nodes derived the same secret.
+/// An ephemeral key is made for an entry and must be used for one handshake only, so no two entries may hold it.
+/// A reuse is a bug of the routing table, so it's checked in the debug builds only.
+fn debug_assert_ephemeral_used_once(entries: &HashMap<SocketAddr, State>, local_public: &Public) {
+    debug_assert!(
+        entries.values().filter(|state| state.local_public() == Some(local_public)).count() <= 1,
+        "The ephemeral key {} is used for two handshakes",
+        local_public
+    );
+}
+
+fn encrypt_nonce(nonce: Nonce, shared_secret: &Secret, with_confirmation: bool) -> Result<Bytes, SymmError> {
     let iv = 0; // FIXME: Use proper iv
-    Ok(aes::encrypt(&nonce.to_be_bytes(), shared_secret, &iv)?)
+    let nonce_bytes = nonce.to_be_bytes();
+    let mut unencrypted = nonce_bytes.to_vec();
+    if with_confirmation {
+        unencrypted.extend_from_slice(&confirmation_tag(&nonce_bytes, shared_secret)[..]);
+    }
+    Ok(aes::encrypt(&unencrypted, shared_secret, &iv)?)
+}
+
+fn confirmation_tag(nonce_bytes: &[u8; 16], shared_secret: &Secret) -> Vec<u8> {
+    let tag: H256 = Blake::blake_with_key(nonce_bytes, shared_secret);
+    tag[..16].to_vec()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ckey::sign_schnorr;
+    use std::str::FromStr;
 
     fn encrypt_and_decrypt(secret: Secret, nonce: Nonce) {
         assert_eq!(
             nonce,
-            decrypt_nonce(&encrypt_nonce(nonce, &secret).unwrap(), &secret).unwrap(),
+            decrypt_nonce(&encrypt_nonce(nonce, &secret, false).unwrap(), &secret, false).unwrap(),
             "nonce: {}, secret: {}",
             nonce,
             secret
@@ -672,4 +1095,392 @@ mod tests {
         let nonce = ::std::u128::MAX;
         encrypt_and_decrypt(secret, nonce);
     }
+
+    #[test]
+    fn encrypt_and_decrypt_with_confirmation() {
+        let secret = Secret::random();
+        let nonce = 1000;
+        let encrypted = encrypt_nonce(nonce, &secret, true).unwrap();
+        assert_eq!(Ok(nonce), decrypt_nonce(&encrypted, &secret, true));
+        assert!(decrypt_nonce(&encrypted, &secret, false).is_err());
+    }
+
+    /// Runs the handshake of an initiator that doesn't know the recipient's key in advance.
+    fn handshake(initiator: &RoutingTable, recipient: &RoutingTable) -> Result<(Session, Session), String> {
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+
+        let initiator_public = initiator.local_public(recipient_addr).unwrap();
+        assert_eq!(None, initiator.try_establish(recipient_addr)?);
+        let initiator_id = initiator.handshake_node_id(&recipient_addr);
+        let (encrypted_nonce, recipient_public, recipient_session, authentication) =
+            recipient.set_recipient_establish1(initiator_addr, initiator_public, initiator_id)?.unwrap();
+        let (initiator_session, signature) =
+            initiator.set_initiator_establish(recipient_addr, recipient_public, &encrypted_nonce, authentication)?;
+        if let Some(signature) = signature {
+            recipient.set_recipient_finish(initiator_addr, &signature)?;
+        }
+        Ok((initiator_session, recipient_session))
+    }
+
+    #[test]
+    fn nodes_with_the_same_network_secret_establish_a_session() {
+        let network_secret = NetworkSecret::new(H256::random());
+        let initiator = RoutingTable::new_with_network_secret(Some(network_secret));
+        let recipient = RoutingTable::new_with_network_secret(Some(network_secret));
+
+        let (initiator_session, recipient_session) = handshake(&initiator, &recipient).unwrap();
+        assert_eq!(initiator_session, recipient_session);
+    }
+
+    #[test]
+    fn nodes_without_network_secrets_establish_a_session() {
+        let (initiator_session, recipient_session) = handshake(&RoutingTable::new(), &RoutingTable::new()).unwrap();
+        assert_eq!(initiator_session, recipient_session);
+    }
+
+    #[test]
+    fn nodes_with_different_network_secrets_fail_the_handshake() {
+        let pairs = vec![
+            (Some(NetworkSecret::new(H256::random())), Some(NetworkSecret::new(H256::random()))),
+            (Some(NetworkSecret::new(H256::random())), None),
+            (None, Some(NetworkSecret::new(H256::random()))),
+        ];
+        for (initiator_secret, recipient_secret) in pairs {
+            let initiator = RoutingTable::new_with_network_secret(initiator_secret);
+            let recipient = RoutingTable::new_with_network_secret(recipient_secret);
+            let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+
+            let err = handshake(&initiator, &recipient).unwrap_err();
+            assert!(err.contains("may not have the same network secret"), "{}", err);
+            assert!(!initiator.is_established(&recipient_addr));
+
+            // The retries are delayed even after the failed entry is removed.
+            initiator.remove(&recipient_addr);
+            assert!(initiator.is_retry_delayed(&recipient_addr));
+            let err = initiator.try_establish(recipient_addr).unwrap_err();
+            assert!(err.contains("failed recently"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn handshakes_from_initiator_closed_silently_are_delayed() {
+        let initiator = RoutingTable::new_with_network_secret(Some(NetworkSecret::new(H256::random())));
+        let recipient = RoutingTable::new_with_network_secret(Some(NetworkSecret::new(H256::random())));
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+
+        handshake(&initiator, &recipient).unwrap_err();
+        // The recipient sees the initiator closing the session without a message.
+        recipient.note_silent_inbound_session(initiator_addr);
+        recipient.remove(&initiator_addr);
+
+        let initiator_public = initiator.local_public(SocketAddr::from_str("127.0.0.1:3486").unwrap()).unwrap();
+        let err = recipient.set_recipient_establish1(initiator_addr, initiator_public, None).unwrap_err();
+        assert!(err.contains("failed recently"), "{}", err);
+    }
+
+    #[test]
+    fn silent_inbound_session_is_not_delayed_without_network_secret() {
+        let recipient = RoutingTable::new();
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+
+        recipient.note_silent_inbound_session(initiator_addr);
+        assert!(!recipient.is_retry_delayed(&initiator_addr));
+    }
+
+    #[test]
+    fn authenticated_handshake_establishes_a_session() {
+        let initiator = RoutingTable::new();
+        let recipient = RoutingTable::new();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+        assert_eq!(Some(*initiator.identity.public()), initiator.handshake_node_id(&recipient_addr));
+
+        let (initiator_session, recipient_session) = handshake(&initiator, &recipient).unwrap();
+        assert_eq!(initiator_session, recipient_session);
+    }
+
+    #[test]
+    fn falls_back_to_the_legacy_handshake_if_the_handshake_is_dropped() {
+        let initiator = RoutingTable::new();
+        let recipient = RoutingTable::new();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+
+        // Nothing to fall back from before the handshake starts.
+        initiator.fall_back_to_legacy_handshake(recipient_addr);
+        assert!(initiator.handshake_node_id(&recipient_addr).is_some());
+
+        assert_eq!(None, initiator.try_establish(recipient_addr).unwrap());
+        initiator.fall_back_to_legacy_handshake(recipient_addr);
+        assert_eq!(None, initiator.handshake_node_id(&recipient_addr));
+        initiator.remove(&recipient_addr);
+
+        let (initiator_session, recipient_session) = handshake(&initiator, &recipient).unwrap();
+        assert_eq!(initiator_session, recipient_session);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "is used for two handshakes")]
+    fn reusing_an_ephemeral_key_panics_in_debug_builds() {
+        let initiator = RoutingTable::new();
+        let recipient = RoutingTable::new();
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+        let other_addr = SocketAddr::from_str("127.0.0.1:3487").unwrap();
+        let recipient_public = recipient.local_public(initiator_addr).unwrap();
+        let recipient_key_pair = match &recipient.entries.read()[&initiator_addr] {
+            State::Candidate(key_pair) => *key_pair,
+            state => panic!("Unexpected state {:?}", state),
+        };
+        recipient.entries.write().insert(other_addr, State::Candidate(recipient_key_pair));
+        assert_eq!(Some(&recipient_public), recipient.entries.read()[&other_addr].local_public());
+
+        let initiator_public = initiator.local_public(SocketAddr::from_str("127.0.0.1:3486").unwrap()).unwrap();
+        recipient.set_recipient_establish1(initiator_addr, initiator_public, None).unwrap();
+    }
+
+    #[test]
+    fn legacy_handshake_expires() {
+        let initiator = RoutingTable::new();
+        let recipient = RoutingTable::new();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+
+        assert_eq!(None, initiator.try_establish(recipient_addr).unwrap());
+        initiator.fall_back_to_legacy_handshake(recipient_addr);
+        assert_eq!(None, initiator.handshake_node_id(&recipient_addr));
+        initiator.remove(&recipient_addr);
+
+        initiator.legacy_handshakes.lock().insert(recipient_addr, Instant::now());
+        assert_eq!(Some(*initiator.identity.public()), initiator.handshake_node_id(&recipient_addr));
+        assert!(initiator.legacy_handshakes.lock().is_empty());
+        let (initiator_session, recipient_session) = handshake(&initiator, &recipient).unwrap();
+        assert_eq!(initiator_session, recipient_session);
+    }
+
+    #[test]
+    fn unsigned_ack_to_the_authenticated_handshake_is_rejected() {
+        let initiator = RoutingTable::new();
+        let recipient = RoutingTable::new();
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+
+        let initiator_public = initiator.local_public(recipient_addr).unwrap();
+        assert_eq!(None, initiator.try_establish(recipient_addr).unwrap());
+        // The recipient acts as a version 1 node, as if the node id was stripped.
+        let (encrypted_nonce, recipient_public, _, authentication) =
+            recipient.set_recipient_establish1(initiator_addr, initiator_public, None).unwrap().unwrap();
+        assert_eq!(None, authentication);
+
+        let err =
+            initiator.set_initiator_establish(recipient_addr, recipient_public, &encrypted_nonce, None).unwrap_err();
+        assert!(err.contains("didn't sign the handshake"), "{}", err);
+        assert!(!initiator.is_established(&recipient_addr));
+    }
+
+    #[test]
+    fn tampered_handshake_is_rejected() {
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+        let attacker = Random.generate().unwrap();
+        // (Replaces the initiator id sent to the recipient, Replaces the authentication sent to the initiator)
+        let tamperings: Vec<(bool, Option<NodeAuthentication>)> = vec![
+            (true, None),
+            (false, Some((*attacker.public(), sign_schnorr(attacker.private(), &H256::random()).unwrap()))),
+        ];
+        for (replaces_initiator_id, replaced_authentication) in tamperings {
+            let initiator = RoutingTable::new();
+            let recipient = RoutingTable::new();
+
+            let initiator_public = initiator.local_public(recipient_addr).unwrap();
+            assert_eq!(None, initiator.try_establish(recipient_addr).unwrap());
+            let initiator_id = if replaces_initiator_id {
+                Some(*attacker.public())
+            } else {
+                initiator.handshake_node_id(&recipient_addr)
+            };
+            let (encrypted_nonce, recipient_public, _, authentication) =
+                recipient.set_recipient_establish1(initiator_addr, initiator_public, initiator_id).unwrap().unwrap();
+            let authentication = replaced_authentication.or(authentication);
+
+            let err = initiator
+                .set_initiator_establish(recipient_addr, recipient_public, &encrypted_nonce, authentication)
+                .unwrap_err();
+            assert!(err.contains("signature"), "{}", err);
+            assert!(!initiator.is_established(&recipient_addr));
+        }
+    }
+
+    /// Reconnects the nodes that had a session, presenting the ticket of the previous session.
+    /// Returns the request and the sessions if the recipient resumed the session without the key exchange.
+    #[allow(clippy::type_complexity)]
+    fn reconnect(
+        initiator: &RoutingTable,
+        recipient: &RoutingTable,
+    ) -> Result<(ResumptionRequest, Option<(Session, Session)>), String> {
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+        initiator.remove(&recipient_addr);
+        recipient.remove(&initiator_addr);
+
+        let initiator_public = initiator.local_public(recipient_addr).unwrap();
+        assert_eq!(None, initiator.try_establish(recipient_addr)?);
+        let initiator_id = initiator.handshake_node_id(&recipient_addr).unwrap();
+        let request = initiator.resumption_request(recipient_addr).ok_or("No ticket")?;
+        let resumed = match recipient.set_recipient_resume(initiator_addr, initiator_public, initiator_id, &request)? {
+            Some((encrypted_nonce, recipient_public, recipient_session)) => {
+                let initiator_session =
+                    initiator.set_initiator_resume(recipient_addr, recipient_public, &encrypted_nonce)?;
+                Some((initiator_session, recipient_session))
+            }
+            None => None,
+        };
+        Ok((request, resumed))
+    }
+
+    #[test]
+    fn reconnect_resumes_the_session_without_the_key_exchange() {
+        let network_secret = NetworkSecret::new(H256::random());
+        let initiator = RoutingTable::new_with_network_secret(Some(network_secret));
+        let recipient = RoutingTable::new_with_network_secret(Some(network_secret));
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+
+        let (session, _) = handshake(&initiator, &recipient).unwrap();
+        let (_, resumed) = reconnect(&initiator, &recipient).unwrap();
+        let (initiator_session, recipient_session) = resumed.expect("The abbreviated handshake is taken");
+        assert_eq!(initiator_session, recipient_session);
+        assert_ne!(session.secret(), initiator_session.secret());
+        assert!(initiator.is_established(&recipient_addr));
+
+        // The resumed session derives the ticket of the next reconnect.
+        let (_, resumed) = reconnect(&initiator, &recipient).unwrap();
+        let (next_initiator_session, next_recipient_session) = resumed.expect("The abbreviated handshake is taken");
+        assert_eq!(next_initiator_session, next_recipient_session);
+        assert_ne!(initiator_session.secret(), next_initiator_session.secret());
+    }
+
+    #[test]
+    fn replayed_ticket_is_rejected() {
+        let initiator = RoutingTable::new();
+        let recipient = RoutingTable::new();
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+        let initiator_id = *initiator.identity.public();
+
+        handshake(&initiator, &recipient).unwrap();
+        let (request, resumed) = reconnect(&initiator, &recipient).unwrap();
+        assert!(resumed.is_some());
+
+        recipient.remove(&initiator_addr);
+        let replayed =
+            recipient.set_recipient_resume(initiator_addr, Public::random(), initiator_id, &request).unwrap();
+        assert_eq!(None, replayed);
+        assert!(!recipient.is_established(&initiator_addr));
+    }
+
+    #[test]
+    fn rejected_ticket_falls_back_to_the_key_exchange() {
+        let initiator = RoutingTable::new();
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+
+        handshake(&initiator, &RoutingTable::new()).unwrap();
+        // The recipient restarted and forgot the ticket.
+        let recipient = RoutingTable::new();
+        let (_, resumed) = reconnect(&initiator, &recipient).unwrap();
+        assert_eq!(None, resumed);
+
+        // The recipient answers the resumption as Sync1.
+        let initiator_public = initiator.local_public(recipient_addr).unwrap();
+        let initiator_id = initiator.handshake_node_id(&recipient_addr);
+        let (encrypted_nonce, recipient_public, recipient_session, authentication) =
+            recipient.set_recipient_establish1(initiator_addr, initiator_public, initiator_id).unwrap().unwrap();
+        let (initiator_session, signature) = initiator
+            .set_initiator_establish(recipient_addr, recipient_public, &encrypted_nonce, authentication)
+            .unwrap();
+        recipient.set_recipient_finish(initiator_addr, &signature.unwrap()).unwrap();
+        assert_eq!(initiator_session, recipient_session);
+        assert!(!initiator.abandon_resumption(&recipient_addr));
+    }
+
+    #[test]
+    fn authenticated_peer_is_not_downgraded() {
+        let initiator = RoutingTable::new();
+        let recipient = RoutingTable::new();
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+        handshake(&initiator, &recipient).unwrap();
+        initiator.remove(&recipient_addr);
+        recipient.remove(&initiator_addr);
+
+        // A man in the middle drops the handshake.
+        assert_eq!(None, initiator.try_establish(recipient_addr).unwrap());
+        assert!(!initiator.fall_back_to_legacy_handshake(recipient_addr));
+        assert_eq!(Some(*initiator.identity.public()), initiator.handshake_node_id(&recipient_addr));
+
+        // A man in the middle strips the node id.
+        let initiator_public = initiator.local_public(recipient_addr).unwrap();
+        let err = recipient.set_recipient_establish1(initiator_addr, initiator_public, None).unwrap_err();
+        assert!(err.contains("Cannot downgrade"), "{}", err);
+        assert!(!recipient.is_established(&initiator_addr));
+    }
+
+    #[test]
+    fn ticket_is_issued_after_the_initiator_signs_the_transcript() {
+        let initiator = RoutingTable::new();
+        let recipient = RoutingTable::new();
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+
+        let initiator_public = initiator.local_public(recipient_addr).unwrap();
+        assert_eq!(None, initiator.try_establish(recipient_addr).unwrap());
+        let initiator_id = initiator.handshake_node_id(&recipient_addr);
+        let (encrypted_nonce, recipient_public, _, authentication) =
+            recipient.set_recipient_establish1(initiator_addr, initiator_public, initiator_id).unwrap().unwrap();
+        assert!(recipient.waits_for_initiator_signature(&initiator_addr));
+        let (_, signature) = initiator
+            .set_initiator_establish(recipient_addr, recipient_public, &encrypted_nonce, authentication)
+            .unwrap();
+        recipient.set_recipient_finish(initiator_addr, &signature.unwrap()).unwrap();
+        assert!(!recipient.waits_for_initiator_signature(&initiator_addr));
+
+        let (_, resumed) = reconnect(&initiator, &recipient).unwrap();
+        assert!(resumed.is_some());
+    }
+
+    #[test]
+    fn ticket_is_not_issued_without_the_signature_of_the_initiator() {
+        let initiator = RoutingTable::new();
+        let recipient = RoutingTable::new();
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+
+        let initiator_public = initiator.local_public(recipient_addr).unwrap();
+        assert_eq!(None, initiator.try_establish(recipient_addr).unwrap());
+        let initiator_id = initiator.handshake_node_id(&recipient_addr);
+        let (encrypted_nonce, recipient_public, _, authentication) =
+            recipient.set_recipient_establish1(initiator_addr, initiator_public, initiator_id).unwrap().unwrap();
+        initiator.set_initiator_establish(recipient_addr, recipient_public, &encrypted_nonce, authentication).unwrap();
+        // The signature of the initiator is dropped and the session is closed.
+        recipient.remove(&initiator_addr);
+        assert!(!recipient.waits_for_initiator_signature(&initiator_addr));
+
+        let (_, resumed) = reconnect(&initiator, &recipient).unwrap();
+        assert_eq!(None, resumed);
+    }
+
+    #[test]
+    fn initiator_claiming_a_node_id_without_its_key_is_rejected() {
+        let initiator = RoutingTable::new();
+        let recipient = RoutingTable::new();
+        let initiator_addr = SocketAddr::from_str("127.0.0.1:3485").unwrap();
+        let recipient_addr = SocketAddr::from_str("127.0.0.1:3486").unwrap();
+        let attacker = Random.generate().unwrap();
+
+        let initiator_public = initiator.local_public(recipient_addr).unwrap();
+        assert_eq!(None, initiator.try_establish(recipient_addr).unwrap());
+        recipient.set_recipient_establish1(initiator_addr, initiator_public, Some(*attacker.public())).unwrap();
+
+        let forged = sign_schnorr(initiator.identity.private(), &H256::random()).unwrap();
+        let err = recipient.set_recipient_finish(initiator_addr, &forged).unwrap_err();
+        assert!(err.contains("signature"), "{}", err);
+        assert!(!recipient.is_authenticated_peer(&initiator_addr));
+    }
 }
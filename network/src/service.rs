@@ -15,7 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::client::Client;
-use crate::control::{Control, Error as ControlError};
+use crate::control::{Control, Error as ControlError, ExtensionState};
 use crate::filters::{FilterEntry, FiltersControl};
 use crate::routing_table::RoutingTable;
 use crate::{p2p, Api, ManagingPeerdb, NetworkExtension, SocketAddr};
@@ -44,6 +44,7 @@ impl Service {
         bootstrap_addresses: Vec<SocketAddr>,
         min_peers: usize,
         max_peers: usize,
+        compression_threshold: usize,
         filters_control: Arc<dyn FiltersControl>,
         routing_table: Arc<RoutingTable>,
         peer_db: Box<dyn ManagingPeerdb>,
@@ -62,6 +63,7 @@ impl Service {
             bootstrap_addresses,
             min_peers,
             max_peers,
+            compression_threshold,
             peer_db,
         )?);
         p2p.register_handler(p2p_handler.clone())?;
@@ -83,6 +85,14 @@ impl Service {
         self.client.register_extension(factory)
     }
 
+    /// Stops the extensions first so that they don't send messages to closed streams.
+    pub fn stop(&self) {
+        cinfo!(NETWORK, "Stopping the network service");
+        self.client.stop_extensions();
+        self.p2p.stop();
+        self.p2p_handler.close_streams();
+    }
+
     pub fn connect_to(&self, address: SocketAddr) -> Result<(), String> {
         self.p2p.send_message(p2p::Message::RequestConnection(address)).map_err(|e| format!("{:?}", e))?;
         Ok(())
@@ -138,6 +148,19 @@ impl Control for Service {
         Ok(self.p2p_handler.established_peers())
     }
 
+    fn add_trusted_peer(&self, addr: SocketAddr) -> Result<(), ControlError> {
+        self.p2p_handler.add_trusted_peer(addr);
+        Ok(())
+    }
+
+    fn remove_trusted_peer(&self, addr: &SocketAddr) -> Result<bool, ControlError> {
+        Ok(self.p2p_handler.remove_trusted_peer(addr))
+    }
+
+    fn trusted_peers(&self) -> Result<Vec<SocketAddr>, ControlError> {
+        Ok(self.p2p_handler.trusted_peers())
+    }
+
     fn add_to_whitelist(&self, addr: IpCidr, tag: Option<String>) -> Result<(), ControlError> {
         self.filters_control.add_to_whitelist(addr, tag);
         Ok(())
@@ -201,6 +224,32 @@ impl Control for Service {
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>, ControlError> {
         Ok(self.p2p_handler.recent_network_usage())
     }
+
+    fn disable_extension(&self, name: &str) -> Result<(), ControlError> {
+        let disabled =
+            self.client.disable_extension(name).ok_or_else(|| ControlError::NoSuchExtension(name.to_string()))?;
+        if disabled {
+            if let Err(err) = self.p2p.send_message(p2p::Message::ExtensionDisabled(name.to_string())) {
+                cerror!(NETWORK, "Error occurred while disabling {}: {:?}", name, err);
+            }
+        }
+        Ok(())
+    }
+
+    fn enable_extension(&self, name: &str) -> Result<(), ControlError> {
+        let enabled =
+            self.client.enable_extension(name).ok_or_else(|| ControlError::NoSuchExtension(name.to_string()))?;
+        if enabled {
+            if let Err(err) = self.p2p.send_message(p2p::Message::ExtensionEnabled(name.to_string())) {
+                cerror!(NETWORK, "Error occurred while enabling {}: {:?}", name, err);
+            }
+        }
+        Ok(())
+    }
+
+    fn extension_states(&self) -> Result<Vec<ExtensionState>, ControlError> {
+        Ok(self.client.extension_states())
+    }
 }
 
 #[derive(Debug)]
@@ -220,3 +269,285 @@ impl From<String> for Error {
         Error::General(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::Filters;
+    use crate::NodeId;
+    use ckey::{Generator, KeyPair, Random};
+    use clogger::metric::METRICS;
+    use parking_lot::Mutex;
+    use primitives::Bytes;
+    use std::collections::BTreeSet;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// The clones share the storage, so that a restarted service reads what the previous one wrote.
+    #[derive(Clone, Default)]
+    struct MemoryPeerDb {
+        trusted_peers: Arc<Mutex<BTreeSet<SocketAddr>>>,
+        routing_state: Arc<Mutex<Option<Bytes>>>,
+    }
+
+    impl ManagingPeerdb for MemoryPeerDb {
+        fn insert(&self, _key: SocketAddr) {}
+
+        fn delete(&self, _key: &SocketAddr) {}
+
+        fn insert_trusted(&self, key: SocketAddr) {
+            self.trusted_peers.lock().insert(key);
+        }
+
+        fn delete_trusted(&self, key: &SocketAddr) {
+            self.trusted_peers.lock().remove(key);
+        }
+
+        fn trusted_peers(&self) -> Vec<SocketAddr> {
+            self.trusted_peers.lock().iter().cloned().collect()
+        }
+
+        fn routing_state(&self) -> Option<Bytes> {
+            self.routing_state.lock().clone()
+        }
+
+        fn save_routing_state(&self, state: Bytes) {
+            *self.routing_state.lock() = Some(state);
+        }
+    }
+
+    fn unused_address() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().into()
+    }
+
+    fn start(address: SocketAddr) -> Arc<Service> {
+        start_with(address, Random.generate().unwrap(), MemoryPeerDb::default())
+    }
+
+    fn start_with(address: SocketAddr, identity: KeyPair, peer_db: MemoryPeerDb) -> Arc<Service> {
+        Service::start(
+            NetworkId::default(),
+            TimerLoop::new(1),
+            address,
+            Vec::new(),
+            0,
+            10,
+            1024,
+            Filters::new(Vec::new(), Vec::new()),
+            RoutingTable::new_with_identity(None, identity),
+            Box::new(peer_db),
+        )
+        .unwrap()
+    }
+
+    // Dropping the service closes its listener, so that the restarted one can bind the same address.
+    fn restart(service: Arc<Service>, address: SocketAddr, identity: KeyPair, peer_db: MemoryPeerDb) -> Arc<Service> {
+        service.stop();
+        drop(service);
+        start_with(address, identity, peer_db)
+    }
+
+    fn resumed_sessions() -> i64 {
+        METRICS.counter("codechain_resumed_sessions", "The number of inbound sessions resumed with tickets").get()
+    }
+
+    fn wait_until<F: Fn() -> bool>(condition: F) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while Instant::now() < deadline {
+            if condition() {
+                return true
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        false
+    }
+
+    // The backoff of the failed dials is tested with the redial schedule in the handler.
+    #[test]
+    fn trusted_peer_is_dialed() {
+        let b_address = unused_address();
+        let b = start(b_address);
+        let a = start(unused_address());
+        a.add_trusted_peer(b_address).unwrap();
+        assert_eq!(vec![b_address], a.trusted_peers().unwrap());
+
+        assert!(wait_until(|| a.is_connected(&b_address).unwrap()));
+
+        assert!(a.remove_trusted_peer(&b_address).unwrap());
+        assert!(!a.remove_trusted_peer(&b_address).unwrap());
+        assert!(a.trusted_peers().unwrap().is_empty());
+
+        a.stop();
+        b.stop();
+    }
+
+    #[test]
+    fn sessions_are_resumed_after_restarts_but_tickets_are_not_replayed() {
+        let a_identity = Random.generate().unwrap();
+        let b_identity = Random.generate().unwrap();
+        let a_peer_db = MemoryPeerDb::default();
+        let b_peer_db = MemoryPeerDb::default();
+        let b_address = unused_address();
+        let b = start_with(b_address, b_identity, b_peer_db.clone());
+        let a = start_with(unused_address(), a_identity, a_peer_db.clone());
+
+        a.connect_to(b_address).unwrap();
+        assert!(wait_until(|| a.is_connected(&b_address).unwrap()));
+        assert!(wait_until(|| a_peer_db.routing_state().is_some() && b_peer_db.routing_state().is_some()));
+        // The ticket that will be consumed by the next connection.
+        let consumed_ticket = a_peer_db.routing_state().unwrap();
+
+        let resumed = resumed_sessions();
+        let b = restart(b, b_address, b_identity, b_peer_db.clone());
+        let a = restart(a, unused_address(), a_identity, a_peer_db.clone());
+        a.connect_to(b_address).unwrap();
+        assert!(wait_until(|| a.is_connected(&b_address).unwrap()));
+        assert!(wait_until(|| resumed_sessions() > resumed));
+
+        // The initiator that presents the consumed ticket again falls back to the full handshake.
+        let resumed = resumed_sessions();
+        a.stop();
+        drop(a);
+        let replaying_peer_db = MemoryPeerDb::default();
+        replaying_peer_db.save_routing_state(consumed_ticket);
+        let a = start_with(unused_address(), a_identity, replaying_peer_db);
+        a.connect_to(b_address).unwrap();
+        assert!(wait_until(|| a.is_connected(&b_address).unwrap()));
+        assert_eq!(resumed, resumed_sessions());
+
+        a.stop();
+        b.stop();
+    }
+
+    #[derive(Debug, Eq, PartialEq)]
+    enum ToyCallback {
+        NodeAdded,
+        NodeRemoved,
+        Message(Bytes),
+        Disabled,
+        Enabled,
+    }
+
+    /// Sends each event to the node added last, even after the node is removed.
+    struct ToyExtension {
+        api: Box<dyn Api>,
+        node: Option<NodeId>,
+        callbacks: crossbeam_channel::Sender<ToyCallback>,
+    }
+
+    impl NetworkExtension<Bytes> for ToyExtension {
+        fn name() -> &'static str {
+            "toy"
+        }
+
+        fn need_encryption() -> bool {
+            false
+        }
+
+        fn versions() -> &'static [u64] {
+            const VERSIONS: &[u64] = &[0];
+            &VERSIONS
+        }
+
+        fn on_node_added(&mut self, node: &NodeId, _version: u64) {
+            self.node = Some(*node);
+            self.callbacks.send(ToyCallback::NodeAdded).unwrap();
+        }
+
+        fn on_node_removed(&mut self, _node: &NodeId) {
+            self.callbacks.send(ToyCallback::NodeRemoved).unwrap();
+        }
+
+        fn on_message(&mut self, _node: &NodeId, message: &[u8]) {
+            self.callbacks.send(ToyCallback::Message(message.to_vec())).unwrap();
+        }
+
+        fn on_event(&mut self, message: Bytes) {
+            if let Some(node) = &self.node {
+                self.api.send(node, Arc::new(message));
+            }
+        }
+
+        fn on_disabled(&mut self) {
+            self.callbacks.send(ToyCallback::Disabled).unwrap();
+        }
+
+        fn on_enabled(&mut self) {
+            self.callbacks.send(ToyCallback::Enabled).unwrap();
+        }
+    }
+
+    fn register_toy(service: &Service) -> (Sender<Bytes>, crossbeam_channel::Receiver<ToyCallback>) {
+        let (callbacks, receiver) = crossbeam_channel::unbounded();
+        let events = service.register_extension(move |api| ToyExtension {
+            api,
+            node: None,
+            callbacks,
+        });
+        (events, receiver)
+    }
+
+    fn next(callbacks: &crossbeam_channel::Receiver<ToyCallback>) -> ToyCallback {
+        callbacks.recv_timeout(Duration::from_secs(30)).unwrap()
+    }
+
+    fn dropped_messages(service: &Service) -> usize {
+        service.extension_states().unwrap()[0].dropped_messages
+    }
+
+    #[test]
+    fn disabled_extension_drops_messages_without_disconnection() {
+        let a = start(unused_address());
+        let b_address = unused_address();
+        let b = start(b_address);
+        let (a_events, a_callbacks) = register_toy(&a);
+        let (b_events, b_callbacks) = register_toy(&b);
+        a.connect(b_address).unwrap();
+        assert_eq!(ToyCallback::NodeAdded, next(&a_callbacks));
+        assert_eq!(ToyCallback::NodeAdded, next(&b_callbacks));
+        a_events.send(b"1".to_vec()).unwrap();
+        assert_eq!(ToyCallback::Message(b"1".to_vec()), next(&b_callbacks));
+
+        // b is the inbound side of the connection.
+        b.disable_extension("toy").unwrap();
+        assert_eq!(ToyCallback::Disabled, next(&b_callbacks));
+        assert_eq!(ToyCallback::NodeRemoved, next(&a_callbacks));
+        assert!(!b.extension_states().unwrap()[0].enabled);
+        a_events.send(b"2".to_vec()).unwrap();
+        assert!(wait_until(|| dropped_messages(&b) == 1));
+
+        b.enable_extension("toy").unwrap();
+        assert_eq!(ToyCallback::Enabled, next(&b_callbacks));
+        assert_eq!(ToyCallback::NodeAdded, next(&b_callbacks));
+        assert_eq!(ToyCallback::NodeAdded, next(&a_callbacks));
+        a_events.send(b"3".to_vec()).unwrap();
+        assert_eq!(ToyCallback::Message(b"3".to_vec()), next(&b_callbacks));
+
+        // a is the outbound side of the connection.
+        a.disable_extension("toy").unwrap();
+        assert_eq!(ToyCallback::Disabled, next(&a_callbacks));
+        assert_eq!(ToyCallback::NodeRemoved, next(&b_callbacks));
+        b_events.send(b"4".to_vec()).unwrap();
+        assert!(wait_until(|| dropped_messages(&a) == 1));
+
+        a.enable_extension("toy").unwrap();
+        assert_eq!(ToyCallback::Enabled, next(&a_callbacks));
+        assert_eq!(ToyCallback::NodeAdded, next(&a_callbacks));
+        assert_eq!(ToyCallback::NodeAdded, next(&b_callbacks));
+        b_events.send(b"5".to_vec()).unwrap();
+        assert_eq!(ToyCallback::Message(b"5".to_vec()), next(&a_callbacks));
+
+        assert!(a.is_connected(&b_address).unwrap());
+        assert_eq!(1, b.get_peer_count().unwrap());
+        assert!(a_callbacks.is_empty());
+        assert!(b_callbacks.is_empty());
+        match a.disable_extension("unknown") {
+            Err(ControlError::NoSuchExtension(name)) => assert_eq!("unknown", name),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        a.stop();
+        b.stop();
+    }
+}
@@ -14,8 +14,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod network_secret;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod session;
 
 pub type Nonce = u128;
+pub use self::network_secret::NetworkSecret;
 pub use self::session::Session;
@@ -0,0 +1,91 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ccrypto::Blake;
+use ckey::Secret;
+use primitives::H256;
+use std::fmt;
+use std::str::FromStr;
+
+/// The secret shared by all the nodes of a private network.
+/// Only the nodes that have the same secret can establish sessions with each other.
+///
+/// It's never printed, so that it doesn't leak into the logs.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct NetworkSecret(H256);
+
+impl NetworkSecret {
+    pub fn new(secret: H256) -> Self {
+        NetworkSecret(secret)
+    }
+
+    /// Binds the secret agreed by the ephemeral key exchange to the network secret.
+    pub fn mix(&self, shared_secret: &Secret) -> Secret {
+        Blake::blake_with_key(shared_secret, &self.0)
+    }
+}
+
+impl fmt::Debug for NetworkSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NetworkSecret(..)")
+    }
+}
+
+impl FromStr for NetworkSecret {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = if s.starts_with("0x") {
+            &s[2..]
+        } else {
+            s
+        };
+        // The error of H256 is not returned because it may contain a part of the secret.
+        H256::from_str(s).map(NetworkSecret).map_err(|_| "The network secret must be 32 bytes in hex".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_does_not_print_the_secret() {
+        let secret = H256::random();
+        let network_secret = NetworkSecret::new(secret);
+        assert_eq!("NetworkSecret(..)", format!("{:?}", network_secret));
+    }
+
+    #[test]
+    fn parse_with_and_without_prefix() {
+        let secret = H256::random();
+        assert_eq!(Ok(NetworkSecret::new(secret)), format!("{:x}", secret).parse());
+        assert_eq!(Ok(NetworkSecret::new(secret)), format!("0x{:x}\n", secret).parse());
+        assert!("0x1234".parse::<NetworkSecret>().is_err());
+    }
+
+    #[test]
+    fn different_network_secrets_give_different_session_secrets() {
+        let shared_secret = Secret::random();
+        let secret1 = NetworkSecret::new(H256::random());
+        let secret2 = NetworkSecret::new(H256::random());
+        assert_eq!(secret1.mix(&shared_secret), secret1.mix(&shared_secret));
+        assert_ne!(secret1.mix(&shared_secret), secret2.mix(&shared_secret));
+        assert_ne!(shared_secret, secret1.mix(&shared_secret));
+    }
+}
@@ -14,12 +14,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::decoder::decode_message;
 use crate::SocketAddr;
 use mio::deprecated::{TryRead, TryWrite};
 use mio::event::Evented;
 use mio::net::TcpStream;
 use mio::{Poll, PollOpt, Ready, Token};
-use rlp::{Decodable, DecoderError, Encodable, Rlp};
+use rlp::{Decodable, DecoderError, Encodable};
 use std::collections::VecDeque;
 use std::{fmt, io, net};
 
@@ -180,7 +181,14 @@ impl<Stream: TryRead + TryWrite + PeerAddr + Shutdown> TryStream<Stream> {
         if total_length == 0 {
             return Ok(None)
         }
-        let mut remain_length = total_length + len_of_len - result.len();
+        let mut remain_length = match total_length.checked_add(len_of_len) {
+            Some(length) => length - result.len(),
+            None => {
+                cwarn!(NETWORK, "Too long message({} bytes) from {}", total_length, from_socket);
+                self.shutdown()?;
+                return Ok(None)
+            }
+        };
         let mut bytes: [u8; 1024] = [0; 1024];
 
         ctrace!(NETWORK, "Read {} bytes from {}", total_length, from_socket);
@@ -297,10 +305,7 @@ impl Stream {
         match self.read_bytes()? {
             None => Ok(None),
             Some(ref bytes) if bytes.is_empty() => Ok(None),
-            Some(bytes) => {
-                let rlp = Rlp::new(&bytes);
-                Ok(Some(rlp.as_val::<M>()?))
-            }
+            Some(bytes) => Ok(Some(decode_message::<M>(&bytes)?)),
         }
     }
 
@@ -0,0 +1,500 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::dummy_network_service::DummyNetworkService;
+use crate::rpc::{RpcOptions, RpcServers};
+use crate::rpc_apis::ApiDependencies;
+use ccore::{
+    migrate_database, AccountProvider, AccountProviderError, BlockId, ChainNotify, Client, ClientConfig, ClientService,
+    EngineClient, EngineInfo, EngineType, Error, Miner, MinerOptions, MinerService, PeerDb, Scheme, Stratum,
+    StratumConfig, StratumError, MIGRATION_BATCH_SIZE, NUM_COLUMNS,
+};
+use cdiscovery::{Config as DiscoveryConfig, Discovery};
+use ckey::{Address, KeyPair};
+use cnetwork::{EventSender, Filters, NetworkConfig, NetworkControl, NetworkService, RoutingTable, SocketAddr};
+use crpc::v1::Subscriptions;
+use crpc::Executor;
+use csync::{
+    BlockSyncEvent, BlockSyncExtension, BlockSyncSender, SnapshotService, SpotCheckConfig, SyncStatus,
+    TransactionSyncExtension,
+};
+use ctimer::TimerLoop;
+use kvdb::KeyValueDB;
+use kvdb_rocksdb::Database;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+
+enum NodeDatabase {
+    Memory,
+    Path(PathBuf),
+    Opened(Arc<dyn KeyValueDB>),
+}
+
+/// The network service and the extensions registered on it.
+pub struct NetworkOptions {
+    pub config: NetworkConfig,
+    /// The long-term key of the node, whose public key is the node id signed in the handshakes.
+    pub node_key: KeyPair,
+    pub discovery: Option<DiscoveryOptions>,
+    /// The block sync extension is registered if it's given.
+    pub sync: Option<SpotCheckConfig>,
+    pub transaction_relay: bool,
+}
+
+pub struct DiscoveryOptions {
+    pub config: DiscoveryConfig,
+    /// Uses the kademlia discovery instead of the unstructured one.
+    pub use_kademlia: bool,
+}
+
+pub struct SnapshotOptions {
+    pub path: PathBuf,
+    /// The period of the common params is used if it's not given.
+    pub period: Option<u64>,
+    pub keep: usize,
+}
+
+/// Starts a node in the current process.
+///
+/// The client and the miner are always started. The network, the stratum, the snapshot and the RPC servers
+/// are started only if their options are given, so a node without them is what `NodeBuilder::new` makes.
+pub struct NodeBuilder<'a> {
+    scheme: &'a Scheme,
+    db: NodeDatabase,
+    client_config: ClientConfig,
+    miner_options: MinerOptions,
+    account_provider: Option<Arc<AccountProvider>>,
+    author: Option<Address>,
+    timer_loop: Option<TimerLoop>,
+    network: Option<NetworkOptions>,
+    stratum: Option<StratumConfig>,
+    snapshot: Option<SnapshotOptions>,
+    rpc: Option<RpcOptions>,
+}
+
+impl<'a> NodeBuilder<'a> {
+    pub fn new(scheme: &'a Scheme) -> Self {
+        Self {
+            scheme,
+            db: NodeDatabase::Memory,
+            client_config: Default::default(),
+            miner_options: Default::default(),
+            account_provider: None,
+            author: None,
+            timer_loop: None,
+            network: None,
+            stratum: None,
+            snapshot: None,
+            rpc: None,
+        }
+    }
+
+    /// Opens a RocksDB database at the path and migrates it. The database is kept in memory by default.
+    pub fn db_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.db = NodeDatabase::Path(path.into());
+        self
+    }
+
+    /// Uses the database opened by the caller as it is.
+    pub fn db(mut self, db: Arc<dyn KeyValueDB>) -> Self {
+        self.db = NodeDatabase::Opened(db);
+        self
+    }
+
+    pub fn client_config(mut self, client_config: ClientConfig) -> Self {
+        self.client_config = client_config;
+        self
+    }
+
+    pub fn miner_options(mut self, miner_options: MinerOptions) -> Self {
+        self.miner_options = miner_options;
+        self
+    }
+
+    /// A transient account provider is used by default.
+    pub fn account_provider(mut self, account_provider: Arc<AccountProvider>) -> Self {
+        self.account_provider = Some(account_provider);
+        self
+    }
+
+    /// The author of the blocks, or the signer if the engine needs one.
+    pub fn author(mut self, author: Option<Address>) -> Self {
+        self.author = author;
+        self
+    }
+
+    /// Creates the timers of the client and the network on the given loop. The node creates one otherwise.
+    pub fn timer_loop(mut self, timer_loop: TimerLoop) -> Self {
+        self.timer_loop = Some(timer_loop);
+        self
+    }
+
+    /// The network service is started only when the node is not read-only.
+    pub fn network(mut self, network: Option<NetworkOptions>) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// The stratum service is started only when the engine is PoW and the node is not read-only.
+    pub fn stratum(mut self, stratum: Option<StratumConfig>) -> Self {
+        self.stratum = stratum;
+        self
+    }
+
+    pub fn snapshot(mut self, snapshot: Option<SnapshotOptions>) -> Self {
+        self.snapshot = snapshot;
+        self
+    }
+
+    /// The RPC servers whose configs are given in the options are started.
+    pub fn rpc(mut self, rpc: Option<RpcOptions>) -> Self {
+        self.rpc = rpc;
+        self
+    }
+
+    pub fn build(self) -> Result<RunningNode, NodeError> {
+        let db = match self.db {
+            NodeDatabase::Memory => Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap())),
+            NodeDatabase::Path(path) => open_db(&self.client_config, path)?,
+            NodeDatabase::Opened(db) => db,
+        };
+        let read_only = self.client_config.read_only;
+        let account_provider = self.account_provider.unwrap_or_else(AccountProvider::transient_provider);
+        let miner = Miner::new(self.miner_options, self.scheme, Arc::clone(&account_provider), Arc::clone(&db));
+        if let Some(author) = self.author {
+            miner.set_author(author).map_err(|err| NodeError::Author(author, err))?;
+        }
+
+        let timer_loop = self.timer_loop.unwrap_or_else(|| TimerLoop::new(2));
+        let timer_monitor = timer_loop.monitor();
+        cinfo!(CLIENT, "Starting client");
+        let reseal_timer = timer_loop.new_timer_with_name("Client reseal timer");
+        let service = ClientService::start(
+            &self.client_config,
+            self.scheme,
+            Arc::clone(&db),
+            Arc::clone(&miner),
+            reseal_timer.clone(),
+        )
+        .map_err(NodeError::Client)?;
+        let client = service.client();
+        reseal_timer.set_handler(Arc::downgrade(&client));
+        miner.recover_from_db(&client);
+        self.scheme.engine.register_chain_notify(client.as_ref());
+
+        if let Some(stratum) = self.stratum {
+            if miner.engine_type() == EngineType::PoW && !read_only {
+                stratum_start(&stratum, &miner, Arc::clone(&client))?;
+            }
+        }
+
+        let sync_status = Arc::new(SyncStatus::default());
+        let (network, timer_loop) = match self.network {
+            Some(network) if !read_only => {
+                (Some(network_start(network, self.scheme, &client, &sync_status, timer_loop)?), None)
+            }
+            _ => (None, Some(timer_loop)),
+        };
+
+        let snapshot = self.snapshot.map(|snapshot| {
+            // FIXME: Let's make it load snapshot period dynamically to support changing the period.
+            let period =
+                snapshot.period.unwrap_or_else(|| client.common_params(BlockId::Latest).unwrap().snapshot_period());
+            let service = SnapshotService::new(Arc::clone(&client), snapshot.path, period, snapshot.keep);
+            client.add_notify(Arc::downgrade(&service) as Weak<dyn ChainNotify>);
+            service
+        });
+
+        let rpc = match self.rpc {
+            Some(rpc) => {
+                let subscriptions = Arc::new(Subscriptions::new(Arc::clone(&client), rpc.max_subscriptions));
+                client.add_notify(Arc::downgrade(&subscriptions) as Weak<dyn ChainNotify>);
+                {
+                    let subscriptions = Arc::downgrade(&subscriptions);
+                    miner.add_transactions_listener(Box::new(move |hashes| {
+                        if let Some(subscriptions) = subscriptions.upgrade() {
+                            subscriptions.notify_pending_transactions(hashes);
+                        }
+                    }));
+                }
+                let executor = Executor::start(rpc.executor)
+                    .map_err(|err| NodeError::Rpc(format!("Cannot start the rpc executor: {}", err)))?;
+                let network_control: Arc<dyn NetworkControl> = match &network {
+                    Some(network) => Arc::clone(&network.service) as Arc<dyn NetworkControl>,
+                    None => Arc::new(DummyNetworkService::new()),
+                };
+                let deps = ApiDependencies {
+                    client: Arc::clone(&client),
+                    miner: Arc::clone(&miner),
+                    network_control,
+                    account_provider,
+                    block_sync: network.as_ref().and_then(|network| network.block_sync.clone()),
+                    sync_status,
+                    snapshot_service: snapshot.clone(),
+                    subscriptions,
+                    timer_monitor,
+                    executor: executor.handle(),
+                    read_only,
+                    version: rpc.version.clone(),
+                    commit_hash: rpc.commit_hash.clone(),
+                };
+                Some(RpcServers::start(&rpc, executor, deps).map_err(NodeError::Rpc)?)
+            }
+            None => None,
+        };
+
+        Ok(RunningNode {
+            rpc,
+            network,
+            snapshot,
+            service,
+            miner,
+            db,
+            _timer_loop: timer_loop,
+        })
+    }
+}
+
+fn open_db(client_config: &ClientConfig, path: PathBuf) -> Result<Arc<dyn KeyValueDB>, NodeError> {
+    let db_config = client_config.database_config(&path).map_err(NodeError::Database)?;
+    let path = path.to_str().ok_or_else(|| NodeError::Database(format!("Invalid database path {:?}", path)))?;
+    let db = Database::open(&db_config, path)
+        .map_err(|err| NodeError::Database(format!("Cannot open the database {}: {}", path, err)))?;
+    migrate_database(&db, MIGRATION_BATCH_SIZE)
+        .map_err(|err| NodeError::Database(format!("Cannot migrate the database {}: {}", path, err)))?;
+    Ok(Arc::new(db))
+}
+
+fn stratum_start(cfg: &StratumConfig, miner: &Arc<Miner>, client: Arc<Client>) -> Result<(), NodeError> {
+    match Stratum::start(cfg, Arc::clone(&miner), client) {
+        // FIXME: Add specified condition like AddrInUse
+        Err(StratumError::Service(_)) => Err(NodeError::Stratum(format!(
+            "STRATUM address {}:{} is already in use, make sure that another instance of a CodeChain node is not running or change the address using the --stratum-port option.",
+            cfg.listen_addr, cfg.port
+        ))),
+        Err(e) => Err(NodeError::Stratum(format!("STRATUM start error: {:?}", e))),
+        Ok(stratum) => {
+            miner.add_work_listener(Box::new(stratum));
+            cinfo!(STRATUM, "Listening on {}:{}", cfg.listen_addr, cfg.port);
+            Ok(())
+        }
+    }
+}
+
+struct RunningNetwork {
+    service: Arc<NetworkService>,
+    block_sync: Option<EventSender<BlockSyncEvent>>,
+    // Held to keep the sync extension notified of the new blocks.
+    _sync: Option<Arc<BlockSyncSender>>,
+}
+
+fn network_start(
+    options: NetworkOptions,
+    scheme: &Scheme,
+    client: &Arc<Client>,
+    sync_status: &Arc<SyncStatus>,
+    timer_loop: TimerLoop,
+) -> Result<RunningNetwork, NodeError> {
+    let cfg = &options.config;
+    let addr = cfg
+        .address
+        .parse()
+        .map_err(|_| NodeError::Network(format!("Invalid NETWORK listen host given: {}", cfg.address)))?;
+    let sockaddress = SocketAddr::new(addr, cfg.port);
+    let filters = Filters::new(cfg.whitelist.clone(), cfg.blacklist.clone());
+    // XXX: What should we do if the network id has been changed.
+    let network_id = client.network_id();
+    let peer_db = PeerDb::new(client.get_kvdb());
+    let routing_table = RoutingTable::new_with_identity(cfg.network_secret, options.node_key);
+    let service = NetworkService::start(
+        network_id,
+        timer_loop,
+        sockaddress,
+        cfg.bootstrap_addresses.clone(),
+        cfg.min_peers,
+        cfg.max_peers,
+        cfg.compression_threshold,
+        filters,
+        Arc::clone(&routing_table),
+        peer_db,
+    )
+    .map_err(|e| NodeError::Network(format!("Network service error: {:?}", e)))?;
+
+    if let Some(DiscoveryOptions {
+        config,
+        use_kademlia,
+    }) = options.discovery
+    {
+        service.register_extension(move |api| Discovery::new(routing_table, config, api, use_kademlia));
+    }
+
+    let (sync, block_sync) = match options.sync {
+        Some(spot_check_config) => {
+            let sync_sender = {
+                let client = Arc::clone(client);
+                let sync_status = Arc::clone(sync_status);
+                service
+                    .register_extension(move |api| BlockSyncExtension::new(client, api, sync_status, spot_check_config))
+            };
+            let sync = Arc::new(BlockSyncSender::from(sync_sender.clone()));
+            client.add_notify(Arc::downgrade(&sync) as Weak<dyn ChainNotify>);
+            (Some(sync), Some(sync_sender))
+        }
+        None => (None, None),
+    };
+    if options.transaction_relay {
+        let client = Arc::clone(client);
+        service.register_extension(move |api| TransactionSyncExtension::new(client, api));
+    }
+
+    scheme.engine.register_network_extension_to_service(&service);
+
+    Ok(RunningNetwork {
+        service,
+        block_sync,
+        _sync: sync,
+    })
+}
+
+/// The services started by `NodeBuilder`.
+pub struct RunningNode {
+    rpc: Option<RpcServers>,
+    network: Option<RunningNetwork>,
+    snapshot: Option<Arc<SnapshotService>>,
+    service: ClientService,
+    miner: Arc<Miner>,
+    db: Arc<dyn KeyValueDB>,
+    // Dropped after the client, whose reseal timer runs on it. The network service owns it if it's started.
+    _timer_loop: Option<TimerLoop>,
+}
+
+impl RunningNode {
+    pub fn client(&self) -> Arc<Client> {
+        self.service.client()
+    }
+
+    pub fn miner(&self) -> Arc<Miner> {
+        Arc::clone(&self.miner)
+    }
+
+    pub fn db(&self) -> Arc<dyn KeyValueDB> {
+        Arc::clone(&self.db)
+    }
+
+    pub fn network(&self) -> Option<Arc<NetworkService>> {
+        self.network.as_ref().map(|network| Arc::clone(&network.service))
+    }
+
+    /// Stops the services from the outside in: the RPC servers, the network, the miner, the client and then
+    /// flushes the database. The services built on top of the node should be stopped before it.
+    pub fn shutdown(self) -> Result<(), NodeError> {
+        if let Some(rpc) = self.rpc {
+            rpc.stop();
+        }
+        if let Some(network) = self.network {
+            network.service.stop();
+        }
+        // The snapshot service waits for the snapshot being written when the last handle is dropped.
+        drop(self.snapshot);
+        self.miner.stop_sealing();
+        self.service.stop();
+        self.db.flush().map_err(|err| NodeError::Database(format!("Error while flushing the database: {}", err)))
+    }
+}
+
+#[derive(Debug)]
+pub enum NodeError {
+    /// The database cannot be opened, migrated or flushed.
+    Database(String),
+    /// The miner refused the author.
+    Author(Address, AccountProviderError),
+    /// The client service cannot be started.
+    Client(Error),
+    /// The stratum service cannot be started.
+    Stratum(String),
+    /// The network service cannot be started.
+    Network(String),
+    /// The RPC servers cannot be started.
+    Rpc(String),
+}
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NodeError::Database(err) => f.write_str(err),
+            NodeError::Author(author, err) => write!(f, "Cannot set the author to {}: {}", author, err),
+            NodeError::Client(err) => write!(f, "Client service error: {}", err),
+            NodeError::Stratum(err) => f.write_str(err),
+            NodeError::Network(err) => f.write_str(err),
+            NodeError::Rpc(err) => f.write_str(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ccore::{BlockChainClient, BlockChainTrait, SignedTransaction};
+    use ctypes::transaction::{Action, Transaction};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use tempfile::tempdir;
+
+    #[test]
+    fn embedded_solo_node_mines_a_transaction() {
+        let dir = tempdir().unwrap();
+        let scheme = Scheme::new_test_solo();
+        let node = NodeBuilder::new(&scheme).db_path(dir.path()).author(Some(Address::default())).build().unwrap();
+        let client = node.client();
+        assert_eq!(0, client.chain_info().best_block_number);
+        assert!(node.network().is_none());
+
+        let sender =
+            KeyPair::from_private("ede1d4ccb4ec9a8bbbae9a13db3f4a7b56ea04189be86ac3a6a439d9a0a1addd".into()).unwrap();
+        let tx = SignedTransaction::new_with_sign(
+            Transaction {
+                seq: 0,
+                fee: 10,
+                network_id: "tc".into(),
+                action: Action::Pay {
+                    receiver: Address::random(),
+                    quantity: 1,
+                    memo: None,
+                },
+            },
+            sender.private(),
+        );
+        let tx_hash = tx.hash();
+        client.queue_own_transaction(tx).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mined = loop {
+            if let Some(mined) = client.transaction(&tx_hash.into()) {
+                break mined
+            }
+            assert!(Instant::now() < deadline, "The transaction is not mined");
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(Some(mined.block_hash), client.block_hash(&BlockId::Number(mined.block_number)));
+        node.shutdown().unwrap();
+
+        // The chain is kept in the directory.
+        let node = NodeBuilder::new(&scheme).db_path(dir.path()).build().unwrap();
+        assert!(node.client().transaction(&tx_hash.into()).is_some());
+        node.shutdown().unwrap();
+    }
+}
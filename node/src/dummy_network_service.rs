@@ -16,7 +16,7 @@
 
 use cidr::IpCidr;
 use ckey::Public;
-use cnetwork::{FilterEntry, NetworkControl, NetworkControlError, SocketAddr};
+use cnetwork::{ExtensionState, FilterEntry, NetworkControl, NetworkControlError, SocketAddr};
 use std::collections::HashMap;
 use std::net::IpAddr;
 
@@ -66,6 +66,18 @@ impl NetworkControl for DummyNetworkService {
         Err(NetworkControlError::Disabled)
     }
 
+    fn add_trusted_peer(&self, _addr: SocketAddr) -> Result<(), NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn remove_trusted_peer(&self, _addr: &SocketAddr) -> Result<bool, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn trusted_peers(&self) -> Result<Vec<SocketAddr>, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
     fn add_to_whitelist(&self, _addr: IpCidr, _tag: Option<String>) -> Result<(), NetworkControlError> {
         Err(NetworkControlError::Disabled)
     }
@@ -109,4 +121,16 @@ impl NetworkControl for DummyNetworkService {
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>, NetworkControlError> {
         Err(NetworkControlError::Disabled)
     }
+
+    fn disable_extension(&self, _name: &str) -> Result<(), NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn enable_extension(&self, _name: &str) -> Result<(), NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn extension_states(&self) -> Result<Vec<ExtensionState>, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
 }
@@ -0,0 +1,43 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate cidr;
+extern crate codechain_core as ccore;
+extern crate codechain_discovery as cdiscovery;
+extern crate codechain_key as ckey;
+#[macro_use]
+extern crate codechain_logger as clogger;
+extern crate codechain_network as cnetwork;
+extern crate codechain_rpc as crpc;
+extern crate codechain_sync as csync;
+extern crate codechain_timer as ctimer;
+#[cfg(test)]
+extern crate codechain_types as ctypes;
+extern crate kvdb;
+extern crate kvdb_memorydb;
+extern crate kvdb_rocksdb;
+#[macro_use]
+extern crate log;
+#[cfg(test)]
+extern crate tempfile;
+
+mod builder;
+mod dummy_network_service;
+mod rpc;
+mod rpc_apis;
+
+pub use crate::builder::{DiscoveryOptions, NetworkOptions, NodeBuilder, NodeError, RunningNode, SnapshotOptions};
+pub use crate::rpc::{RpcHttpConfig, RpcIpcConfig, RpcOptions, RpcWsConfig};
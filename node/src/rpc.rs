@@ -0,0 +1,241 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::rpc_apis;
+use crpc::v1::Metadata;
+use crpc::{
+    start_http, start_ipc, start_ws, AccessControl, Executor, ExecutorConfig, HttpServer, IpcServer, MetaIoHandler,
+    Middleware, RequestLimits, RpcMiddleware, WsError, WsServer,
+};
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+
+#[derive(Debug, PartialEq)]
+pub struct RpcHttpConfig {
+    pub interface: String,
+    pub port: u16,
+    pub cors: Option<Vec<String>>,
+    pub hosts: Option<Vec<String>>,
+    pub limits: RequestLimits,
+}
+
+pub fn rpc_http_start(
+    cfg: &RpcHttpConfig,
+    enable_devel_api: bool,
+    deps: &rpc_apis::ApiDependencies,
+) -> Result<HttpServer, String> {
+    let url = format!("{}:{}", cfg.interface, cfg.port);
+    let addr = url.parse().map_err(|_| format!("Invalid JSONRPC listen host/port given: {}", url))?;
+    let server = setup_http_rpc_server(&addr, cfg.cors.clone(), cfg.hosts.clone(), cfg.limits, enable_devel_api, deps)?;
+    cinfo!(RPC, "RPC Listening on {}", url);
+    if let Some(hosts) = &cfg.hosts {
+        cinfo!(RPC, "Allowed hosts are {:?}", hosts);
+    }
+    if let Some(cors) = &cfg.cors {
+        cinfo!(RPC, "CORS domains are {:?}", cors);
+    }
+    Ok(server)
+}
+
+fn setup_http_rpc_server(
+    url: &SocketAddr,
+    cors_domains: Option<Vec<String>>,
+    allowed_hosts: Option<Vec<String>>,
+    limits: RequestLimits,
+    enable_devel_api: bool,
+    deps: &rpc_apis::ApiDependencies,
+) -> Result<HttpServer, String> {
+    let server = setup_rpc_server(limits, AccessControl::default(), enable_devel_api, deps);
+    let start_result = start_http(url, cors_domains, allowed_hosts, server);
+    match start_result {
+        Err(ref err) if err.kind() == io::ErrorKind::AddrInUse => {
+            Err(format!("RPC address {} is already in use, make sure that another instance of a CodeChain node is not running or change the address using the --jsonrpc-port option.", url))
+        },
+        Err(e) => Err(format!("RPC error: {:?}", e)),
+        Ok(server) => Ok(server),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RpcIpcConfig {
+    pub socket_addr: String,
+    /// The file that contains the token that unlocks every method.
+    pub token_file: Option<String>,
+    pub allowed_methods: Option<Vec<String>>,
+    pub denied_methods: Vec<String>,
+}
+
+impl RpcIpcConfig {
+    fn access_control(&self) -> Result<AccessControl, String> {
+        let token = match &self.token_file {
+            Some(path) => {
+                let token = fs::read_to_string(path)
+                    .map_err(|e| format!("Cannot read the IPC token file {:?}: {:?}", path, e))?
+                    .trim()
+                    .to_string();
+                if token.is_empty() {
+                    return Err(format!("The IPC token file {:?} is empty", path))
+                }
+                Some(token)
+            }
+            None => None,
+        };
+        Ok(AccessControl {
+            token,
+            allowed_methods: self.allowed_methods.clone(),
+            denied_methods: self.denied_methods.clone(),
+        })
+    }
+}
+
+pub fn rpc_ipc_start(
+    cfg: &RpcIpcConfig,
+    enable_devel_api: bool,
+    deps: &rpc_apis::ApiDependencies,
+) -> Result<IpcServer, String> {
+    let access = cfg.access_control()?;
+    if let Some(allowed_methods) = &access.allowed_methods {
+        cinfo!(RPC, "IPC methods callable without authentication are {:?}", allowed_methods);
+    }
+    let server = setup_rpc_server(RequestLimits::default(), access, enable_devel_api, deps);
+    let start_result = start_ipc(&cfg.socket_addr, server);
+    match start_result {
+        Err(ref err) if err.kind() == io::ErrorKind::AddrInUse => {
+            Err(format!("IPC address {} is already in use, make sure that another instance of a Codechain node is not running or change the address using the --ipc-path options.", cfg.socket_addr))
+            },
+        Err(e) => Err(format!("IPC error: {:?}", e)),
+        Ok(server) =>  {
+            cinfo!(RPC, "IPC Listening on {}", cfg.socket_addr);
+            Ok(server)
+        },
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RpcWsConfig {
+    pub interface: String,
+    pub port: u16,
+    pub max_connections: usize,
+    pub origins: Option<Vec<String>>,
+    pub hosts: Option<Vec<String>>,
+    pub limits: RequestLimits,
+}
+
+pub fn rpc_ws_start(
+    cfg: &RpcWsConfig,
+    enable_devel_api: bool,
+    deps: &rpc_apis::ApiDependencies,
+) -> Result<WsServer, String> {
+    let server = setup_rpc_server(cfg.limits, AccessControl::default(), enable_devel_api, deps);
+    let url = format!("{}:{}", cfg.interface, cfg.port);
+    let addr = url.parse().map_err(|_| format!("Invalid WebSockets listen host/port given: {}", url))?;
+    let start_result = start_ws(&addr, server, cfg.max_connections, cfg.origins.clone(), cfg.hosts.clone());
+    match start_result {
+        Err(WsError::Io(ref err)) if err.kind() == io::ErrorKind::AddrInUse => {
+            Err(format!("WebSockets address {} is already in use, make sure that another instance of a Codechain node is not running or change the address using the --ws-port options.", addr))
+        },
+        Err(e) => Err(format!("WebSockets error: {:?}", e)),
+        Ok(server) => {
+            cinfo!(RPC, "WebSockets Listening on {}", addr);
+            if let Some(hosts) = &cfg.hosts {
+                cinfo!(RPC, "Allowed WebSockets hosts are {:?}", hosts);
+            }
+            if let Some(origins) = &cfg.origins {
+                cinfo!(RPC, "Allowed WebSockets origins are {:?}", origins);
+            }
+            Ok(server)
+        },
+    }
+}
+
+fn setup_rpc_server(
+    limits: RequestLimits,
+    access: AccessControl,
+    enable_devel_api: bool,
+    deps: &rpc_apis::ApiDependencies,
+) -> MetaIoHandler<Metadata, impl Middleware<Metadata>> {
+    let mut handler = MetaIoHandler::with_middleware(RpcMiddleware::new(limits, access));
+    deps.extend_api(enable_devel_api, &mut handler);
+    rpc_apis::setup_rpc(handler, deps)
+}
+
+/// The RPC servers to start. A server is started only if its config is given.
+pub struct RpcOptions {
+    pub http: Option<RpcHttpConfig>,
+    pub ipc: Option<RpcIpcConfig>,
+    pub ws: Option<RpcWsConfig>,
+    pub executor: ExecutorConfig,
+    pub enable_devel_api: bool,
+    pub max_subscriptions: usize,
+    /// Answered by the `version` method.
+    pub version: String,
+    /// Answered by the `commitHash` method.
+    pub commit_hash: String,
+}
+
+pub struct RpcServers {
+    http: Option<HttpServer>,
+    ipc: Option<IpcServer>,
+    ws: Option<WsServer>,
+    executor: Executor,
+    // The snapshot service in it waits for the snapshot being written when the last handle is dropped.
+    _deps: rpc_apis::ApiDependencies,
+}
+
+impl RpcServers {
+    pub fn start(options: &RpcOptions, executor: Executor, deps: rpc_apis::ApiDependencies) -> Result<Self, String> {
+        let http = match &options.http {
+            Some(cfg) => Some(rpc_http_start(cfg, options.enable_devel_api, &deps)?),
+            None => None,
+        };
+        let ipc = match &options.ipc {
+            Some(cfg) => Some(rpc_ipc_start(cfg, options.enable_devel_api, &deps)?),
+            None => None,
+        };
+        let ws = match &options.ws {
+            Some(cfg) => Some(rpc_ws_start(cfg, options.enable_devel_api, &deps)?),
+            None => None,
+        };
+        Ok(Self {
+            http,
+            ipc,
+            ws,
+            executor,
+            _deps: deps,
+        })
+    }
+
+    pub fn stop(self) {
+        if let Some(server) = self.http {
+            server.close_handle().close();
+            server.wait();
+        }
+        if let Some(server) = self.ipc {
+            server.close_handle().close();
+            server.wait();
+        }
+        if let Some(server) = self.ws {
+            server.close_handle().close();
+            if let Err(err) = server.wait() {
+                cerror!(SHUTDOWN, "Error while closing jsonrpc ws server: {}", err);
+            }
+        }
+        if let Err(err) = self.executor.shutdown() {
+            cerror!(SHUTDOWN, "Error while stopping the rpc executor: {}", err);
+        }
+    }
+}
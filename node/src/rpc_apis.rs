@@ -17,8 +17,10 @@
 use ccore::{AccountProvider, Client, Miner};
 use clogger::SLOGGER;
 use cnetwork::{EventSender, NetworkControl};
-use crpc::{MetaIoHandler, Middleware, Params, Value};
-use csync::BlockSyncEvent;
+use crpc::v1::{Metadata, Subscriptions};
+use crpc::{ExecutorHandle, MetaIoHandler, Middleware, Params, Value};
+use csync::{BlockSyncEvent, SnapshotService, SyncStatus};
+use ctimer::TimerMonitor;
 use std::sync::Arc;
 
 pub struct ApiDependencies {
@@ -27,18 +29,42 @@ pub struct ApiDependencies {
     pub network_control: Arc<dyn NetworkControl>,
     pub account_provider: Arc<AccountProvider>,
     pub block_sync: Option<EventSender<BlockSyncEvent>>,
+    pub sync_status: Arc<SyncStatus>,
+    pub snapshot_service: Option<Arc<SnapshotService>>,
+    pub subscriptions: Arc<Subscriptions<Client>>,
+    pub timer_monitor: TimerMonitor,
+    pub executor: ExecutorHandle,
+    pub read_only: bool,
+    pub version: String,
+    pub commit_hash: String,
 }
 
 impl ApiDependencies {
-    pub fn extend_api(&self, enable_devel_api: bool, handler: &mut MetaIoHandler<(), impl Middleware<()>>) {
+    pub fn extend_api(&self, enable_devel_api: bool, handler: &mut MetaIoHandler<Metadata, impl Middleware<Metadata>>) {
         use crpc::v1::*;
-        handler.extend_with(ChainClient::new(Arc::clone(&self.client)).to_delegate());
+        handler.extend_with(
+            ChainClient::new(
+                Arc::clone(&self.client),
+                enable_devel_api,
+                self.executor.clone(),
+                Arc::clone(&self.sync_status),
+            )
+            .to_delegate(),
+        );
         handler.extend_with(MempoolClient::new(Arc::clone(&self.client)).to_delegate());
         if enable_devel_api {
             handler.extend_with(
-                DevelClient::new(Arc::clone(&self.client), Arc::clone(&self.miner), self.block_sync.clone())
-                    .to_delegate(),
+                DevelClient::new(
+                    Arc::clone(&self.client),
+                    Arc::clone(&self.miner),
+                    self.block_sync.clone(),
+                    self.snapshot_service.clone(),
+                    self.timer_monitor.clone(),
+                    self.executor.clone(),
+                )
+                .to_delegate(),
             );
+            handler.extend_with(BlockStreamClient::new(Arc::clone(&self.client)).to_delegate());
         }
         handler.extend_with(EngineClient::new(Arc::clone(&self.client), Arc::clone(&self.miner)).to_delegate());
         handler.extend_with(MinerClient::new(Arc::clone(&self.client), Arc::clone(&self.miner)).to_delegate());
@@ -47,13 +73,22 @@ impl ApiDependencies {
             AccountClient::new(Arc::clone(&self.account_provider), Arc::clone(&self.client), Arc::clone(&self.miner))
                 .to_delegate(),
         );
+        handler.extend_with(SubscriptionClient::new(Arc::clone(&self.subscriptions)).to_delegate());
+        if self.read_only {
+            reject_writes(handler, enable_devel_api);
+        }
     }
 }
 
-pub fn setup_rpc<M: Middleware<()>>(mut handler: MetaIoHandler<(), M>) -> MetaIoHandler<(), M> {
+pub fn setup_rpc<M: Middleware<Metadata>>(
+    mut handler: MetaIoHandler<Metadata, M>,
+    deps: &ApiDependencies,
+) -> MetaIoHandler<Metadata, M> {
     handler.add_method("ping", |_params: Params| Ok(Value::String("pong".to_string())));
-    handler.add_method("version", |_params: Params| Ok(Value::String(env!("CARGO_PKG_VERSION").to_string())));
-    handler.add_method("commitHash", |_params: Params| Ok(Value::String(env!("VERGEN_SHA").to_string())));
+    let version = deps.version.clone();
+    handler.add_method("version", move |_params: Params| Ok(Value::String(version.clone())));
+    let commit_hash = deps.commit_hash.clone();
+    handler.add_method("commitHash", move |_params: Params| Ok(Value::String(commit_hash.clone())));
 
     handler.add_method("slog", |_params: Params| {
         let logs = SLOGGER.get_logs();
@@ -0,0 +1,217 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::v1::errors;
+use jsonrpc_core::futures::sync::oneshot;
+use jsonrpc_core::futures::{future, Future};
+use jsonrpc_core::{BoxFuture, Result};
+use parking_lot::Mutex;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExecutorConfig {
+    /// The number of threads that run the tasks.
+    pub workers: usize,
+    /// The maximum number of tasks waiting for a thread.
+    pub queue_size: usize,
+}
+
+enum Message {
+    Run(Box<dyn FnOnce() + Send>),
+    Stop,
+}
+
+/// Runs the slow calls off the threads of the JSON-RPC servers.
+/// The tasks beyond the queue bound are rejected with the server busy error.
+pub struct Executor {
+    handle: ExecutorHandle,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Executor {
+    pub fn start(config: ExecutorConfig) -> io::Result<Self> {
+        let (sender, receiver) = sync_channel(config.queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..config.workers)
+            .map(|index| {
+                let receiver = Arc::clone(&receiver);
+                thread::Builder::new().name(format!("rpc executor #{}", index)).spawn(move || run(&receiver))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Executor {
+            handle: ExecutorHandle {
+                sender,
+                closed: Default::default(),
+            },
+            workers,
+        })
+    }
+
+    pub fn handle(&self) -> ExecutorHandle {
+        self.handle.clone()
+    }
+
+    /// Rejects the new tasks and waits until the queued and running tasks finish.
+    pub fn shutdown(self) -> std::result::Result<(), String> {
+        self.handle.closed.store(true, Ordering::SeqCst);
+        let mut result = Ok(());
+        for _ in &self.workers {
+            // The workers stop after running the tasks queued before the stop messages.
+            if self.handle.sender.send(Message::Stop).is_err() {
+                result = Err("The rpc executor threads stopped before the shutdown".to_string());
+                break
+            }
+        }
+        for worker in self.workers {
+            if worker.join().is_err() {
+                result = Err("An rpc executor thread panicked".to_string());
+            }
+        }
+        result
+    }
+}
+
+fn run(receiver: &Mutex<Receiver<Message>>) {
+    loop {
+        let message = receiver.lock().recv();
+        match message {
+            Ok(Message::Run(task)) => {
+                // A panicking task must not take the thread down with it.
+                if panic::catch_unwind(AssertUnwindSafe(task)).is_err() {
+                    cerror!(RPC, "An rpc task panicked");
+                }
+            }
+            Ok(Message::Stop) | Err(_) => return,
+        }
+    }
+}
+
+/// Spawns the tasks on an `Executor`. It can be cloned into the RPC handlers.
+#[derive(Clone)]
+pub struct ExecutorHandle {
+    sender: SyncSender<Message>,
+    closed: Arc<AtomicBool>,
+}
+
+impl ExecutorHandle {
+    /// Runs the task on the executor and resolves to its result.
+    /// It fails with the server busy error if the queue is full or the executor is shut down.
+    pub fn spawn<T, F>(&self, task: F) -> BoxFuture<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T> + Send + 'static, {
+        if self.closed.load(Ordering::SeqCst) {
+            return Box::new(future::err(errors::server_busy()))
+        }
+        let (result_sender, result_receiver) = oneshot::channel();
+        let message = Message::Run(Box::new(move || {
+            // The caller doesn't wait for the result if the connection is closed.
+            let _ = result_sender.send(task());
+        }));
+        match self.sender.try_send(message) {
+            // The result is dropped without being sent only if the task panicked.
+            Ok(()) => Box::new(
+                result_receiver
+                    .then(|result| result.unwrap_or_else(|_| Err(errors::internal("The task panicked", ())))),
+            ),
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                Box::new(future::err(errors::server_busy()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_core::ErrorCode;
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    #[test]
+    fn rejects_tasks_when_queue_is_full() {
+        let executor = Executor::start(ExecutorConfig {
+            workers: 1,
+            queue_size: 1,
+        })
+        .unwrap();
+        let handle = executor.handle();
+
+        let (started_sender, started_receiver) = channel();
+        let (release_sender, release_receiver) = channel::<()>();
+        let running = handle.spawn(move || {
+            started_sender.send(()).unwrap();
+            release_receiver.recv().unwrap();
+            Ok(1)
+        });
+        started_receiver.recv().unwrap();
+        let queued = handle.spawn(|| Ok(2));
+
+        let err = handle.spawn(|| Ok(3)).wait().unwrap_err();
+        assert_eq!(ErrorCode::ServerError(-32058), err.code);
+
+        release_sender.send(()).unwrap();
+        assert_eq!(Ok(1), running.wait());
+        assert_eq!(Ok(2), queued.wait());
+        executor.shutdown().unwrap();
+    }
+
+    #[test]
+    fn shutdown_waits_for_tasks_in_flight() {
+        let executor = Executor::start(ExecutorConfig {
+            workers: 1,
+            queue_size: 2,
+        })
+        .unwrap();
+        let handle = executor.handle();
+
+        let (started_sender, started_receiver) = channel();
+        let running = handle.spawn(move || {
+            started_sender.send(()).unwrap();
+            thread::sleep(Duration::from_millis(100));
+            Ok(1)
+        });
+        let queued = handle.spawn(|| Ok(2));
+        started_receiver.recv().unwrap();
+
+        executor.shutdown().unwrap();
+        assert_eq!(Ok(1), running.wait());
+        assert_eq!(Ok(2), queued.wait());
+
+        let err = handle.spawn(|| Ok(3)).wait().unwrap_err();
+        assert_eq!(ErrorCode::ServerError(-32058), err.code);
+    }
+
+    #[test]
+    fn panicking_task_does_not_stop_the_worker() {
+        let executor = Executor::start(ExecutorConfig {
+            workers: 1,
+            queue_size: 2,
+        })
+        .unwrap();
+        let handle = executor.handle();
+
+        let err = handle.spawn(|| -> Result<u32> { panic!("The task panics") }).wait().unwrap_err();
+        assert_eq!(ErrorCode::InternalError, err.code);
+        assert_eq!(Ok(1), handle.spawn(|| Ok(1)).wait());
+        executor.shutdown().unwrap();
+    }
+}
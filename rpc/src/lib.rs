@@ -24,11 +24,13 @@ extern crate codechain_keystore as ckeystore;
 extern crate codechain_network as cnetwork;
 extern crate codechain_state as cstate;
 extern crate codechain_sync as csync;
+extern crate codechain_timer as ctimer;
 extern crate codechain_types as ctypes;
 extern crate codechain_vm as cvm;
 pub extern crate jsonrpc_core;
 extern crate jsonrpc_http_server;
 extern crate jsonrpc_ipc_server;
+extern crate jsonrpc_pubsub;
 extern crate jsonrpc_ws_server;
 extern crate kvdb;
 extern crate kvdb_rocksdb as rocksdb;
@@ -49,10 +51,13 @@ extern crate cidr;
 extern crate serde_json;
 extern crate time;
 extern crate tokio_core;
+#[cfg(test)]
+extern crate ws;
 
 #[macro_use]
 extern crate jsonrpc_derive;
 
+pub mod executor;
 pub mod rpc_server;
 pub mod v1;
 
@@ -68,3 +73,6 @@ pub use rpc_server::start_ipc;
 
 pub use jsonrpc_ws_server::{Error as WsError, Server as WsServer};
 pub use rpc_server::start_ws;
+
+pub use executor::{Executor, ExecutorConfig, ExecutorHandle};
+pub use rpc_server::{validate_origin, AccessControl, RequestLimits, RpcMiddleware};
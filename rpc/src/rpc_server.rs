@@ -15,15 +15,228 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 // TODO: panic handler
-use jsonrpc_core;
-use jsonrpc_http_server::{self, Host, Server as HttpServer, ServerBuilder as HttpServerBuilder};
-use jsonrpc_ipc_server::{Server as IpcServer, ServerBuilder as IpcServerBuilder};
-use jsonrpc_ws_server::{Error as WsError, Server as WsServer, ServerBuilder as WsServerBuilder};
+use crate::v1::{errors, Metadata};
+use jsonrpc_core::futures::future::{self, Either};
+use jsonrpc_core::futures::Future;
+use jsonrpc_core::{self, Call, FutureOutput, FutureResponse, MethodCall, Output, Request, Response, Value, Version};
+use jsonrpc_http_server::{self, DomainsValidation, Host, Server as HttpServer, ServerBuilder as HttpServerBuilder};
+use jsonrpc_ipc_server::{RequestContext as IpcRequestContext, Server as IpcServer, ServerBuilder as IpcServerBuilder};
+use jsonrpc_pubsub::Session;
+use jsonrpc_ws_server::{
+    Error as WsError, Origin as WsOrigin, RequestContext, Server as WsServer, ServerBuilder as WsServerBuilder,
+};
+use serde_json;
+use std::cell::Cell;
 use std::default::Default;
 use std::io;
 use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// Limits applied to each request a JSON-RPC server receives.
+/// `None` means unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RequestLimits {
+    /// The maximum number of calls in a batch.
+    pub max_batch_len: Option<usize>,
+    /// The maximum time spent on the calls of a single request.
+    pub max_execution_time: Option<Duration>,
+}
+
+/// The method that authenticates a client with `AccessControl::token`.
+const AUTH_METHOD: &str = "auth";
+
+/// Restricts the methods that a client can call.
+/// The default allows every method.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccessControl {
+    /// A client that called `auth` with this token can call every method.
+    pub token: Option<String>,
+    /// The methods callable without authentication. `None` allows every method.
+    pub allowed_methods: Option<Vec<String>>,
+    /// The methods not callable without authentication even if they are allowed.
+    pub denied_methods: Vec<String>,
+}
+
+impl AccessControl {
+    fn is_permitted(&self, method: &str, meta: &Metadata) -> bool {
+        if meta.is_authenticated() {
+            return true
+        }
+        let matches = |pattern: &String| match pattern.as_str() {
+            "*" => true,
+            pattern if pattern.ends_with('*') => method.starts_with(&pattern[..pattern.len() - 1]),
+            pattern => method == pattern,
+        };
+        let allowed = self.allowed_methods.as_ref().map_or(true, |patterns| patterns.iter().any(matches));
+        allowed && !self.denied_methods.iter().any(matches)
+    }
+
+    fn authenticate(&self, token: &str, meta: &Metadata) -> jsonrpc_core::Result<bool> {
+        match &self.token {
+            Some(expected) if is_same_token(expected, token) => {
+                meta.authenticate();
+                Ok(true)
+            }
+            _ => Err(errors::invalid_auth_token()),
+        }
+    }
+}
+
+/// Compares the tokens in a time that doesn't depend on the position of the first different byte.
+fn is_same_token(expected: &str, given: &str) -> bool {
+    let (expected, given) = (expected.as_bytes(), given.as_bytes());
+    if expected.len() != given.len() {
+        return false
+    }
+    expected.iter().zip(given).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+thread_local! {
+    // The calls of a request run on the thread that handles the request,
+    // so the deadline of the current request is kept per thread.
+    static DEADLINE: Cell<Option<Instant>> = Cell::new(None);
+}
+
+/// Logs the calls with their execution times and enforces `RequestLimits` and `AccessControl`.
+pub struct RpcMiddleware {
+    limits: RequestLimits,
+    access: AccessControl,
+}
+
+impl RpcMiddleware {
+    pub fn new(limits: RequestLimits, access: AccessControl) -> Self {
+        RpcMiddleware {
+            limits,
+            access,
+        }
+    }
+
+    fn print_call(method_call: &MethodCall) {
+        cinfo!(RPC, "RPC call({}({}))", method_call.method, serde_json::to_string(&method_call.params).unwrap());
+    }
+
+    fn print_timing(method: &str, duration: Duration, output: &Option<Output>) {
+        let success = match output {
+            Some(Output::Failure(_)) => false,
+            _ => true,
+        };
+        cinfo!(
+            RPC_TIMING,
+            "{}",
+            serde_json::json!({
+                "method": method,
+                "durationMicros": duration.as_micros() as u64,
+                "success": success,
+            })
+        );
+    }
+}
+
+impl jsonrpc_core::Middleware<Metadata> for RpcMiddleware {
+    type Future = FutureResponse;
+    type CallFuture = FutureOutput;
+
+    fn on_request<F, X>(&self, request: Request, meta: Metadata, next: F) -> Either<Self::Future, X>
+    where
+        F: FnOnce(Request, Metadata) -> X + Send,
+        X: Future<Item = Option<Response>, Error = ()> + Send + 'static, {
+        if let (Request::Batch(calls), Some(max_batch_len)) = (&request, self.limits.max_batch_len) {
+            if calls.len() > max_batch_len {
+                cwarn!(RPC, "Rejected a batch of {} calls", calls.len());
+                let response = Response::from(errors::batch_too_large(max_batch_len), Some(Version::V2));
+                return Either::A(Box::new(future::ok(Some(response))))
+            }
+        }
+
+        let deadline = self.limits.max_execution_time.map(|budget| Instant::now() + budget);
+        let previous = DEADLINE.with(|cell| cell.replace(deadline));
+        let response = next(request, meta);
+        DEADLINE.with(|cell| cell.set(previous));
+        Either::B(response)
+    }
+
+    fn on_call<F, X>(&self, call: Call, meta: Metadata, next: F) -> Either<Self::CallFuture, X>
+    where
+        F: FnOnce(Call, Metadata) -> X + Send,
+        X: Future<Item = Option<Output>, Error = ()> + Send + 'static, {
+        let (method, id, jsonrpc) = match &call {
+            Call::MethodCall(method_call) if self.access.token.is_some() && method_call.method == AUTH_METHOD => {
+                // Handled here without being logged because the parameter is the token.
+                let result = method_call
+                    .params
+                    .clone()
+                    .parse::<(String,)>()
+                    .and_then(|(token,)| self.access.authenticate(&token, &meta))
+                    .map(Value::Bool);
+                let output = Output::from(result, method_call.id.clone(), method_call.jsonrpc);
+                return Either::A(Box::new(future::ok(Some(output))))
+            }
+            Call::MethodCall(method_call) => {
+                Self::print_call(method_call);
+                (method_call.method.clone(), method_call.id.clone(), method_call.jsonrpc)
+            }
+            Call::Notification(notification) if !self.access.is_permitted(&notification.method, &meta) => {
+                cwarn!(RPC, "Dropped an unauthorized notification {}", notification.method);
+                return Either::A(Box::new(future::ok(None)))
+            }
+            _ => return Either::B(next(call, meta)),
+        };
+
+        if !self.access.is_permitted(&method, &meta) {
+            cwarn!(RPC, "Rejected an unauthorized call to {}", method);
+            let output = Output::from(Err(errors::unauthorized(&method)), id, jsonrpc);
+            return Either::A(Box::new(future::ok(Some(output))))
+        }
+
+        let exhausted = DEADLINE.with(Cell::get).map_or(false, |deadline| deadline <= Instant::now());
+        if exhausted {
+            cwarn!(RPC, "Skipped {}: the execution time budget of the request is exhausted", method);
+            let output = Output::from(Err(errors::execution_budget_exceeded()), id, jsonrpc);
+            return Either::A(Box::new(future::ok(Some(output))))
+        }
+
+        let started = Instant::now();
+        Either::A(Box::new(next(call, meta).map(move |output| {
+            Self::print_timing(&method, started.elapsed(), &output);
+            output
+        })))
+    }
+}
+
+/// Checks an origin allowed to call the servers from browsers.
+/// It should be "*", "null", or a scheme and a host with an optional port, e.g. "https://example.com:8080".
+pub fn validate_origin(origin: &str) -> Result<(), String> {
+    if origin == "*" || origin == "null" {
+        return Ok(())
+    }
+    let invalid = || {
+        format!(
+            "Invalid origin {:?}: it should be \"*\", \"null\" or a scheme and a host like \"https://example.com\"",
+            origin
+        )
+    };
+    let (scheme, authority) = match origin.find("://") {
+        Some(index) => (&origin[..index], &origin[index + 3..]),
+        None => return Err(invalid()),
+    };
+    if scheme.is_empty() || !scheme.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.') {
+        return Err(invalid())
+    }
+    let (host, port) = match authority.rfind(':') {
+        Some(index) if !authority.ends_with(']') => (&authority[..index], Some(&authority[index + 1..])),
+        _ => (authority, None),
+    };
+    if host.is_empty() || host.contains(|c: char| c.is_whitespace() || "/?#@".contains(c)) {
+        return Err(invalid())
+    }
+    if let Some(port) = port {
+        port.parse::<u16>().map_err(|_| invalid())?;
+    }
+    Ok(())
+}
 
 /// Start http server asynchronously and returns result with `Server` handle on success or an error.
+/// Browsers can't call the server from other origins unless `cors_domains` allows them.
 pub fn start_http<M: jsonrpc_core::Metadata>(
     addr: &SocketAddr,
     cors_domains: Option<Vec<String>>,
@@ -32,41 +245,235 @@ pub fn start_http<M: jsonrpc_core::Metadata>(
 ) -> Result<HttpServer, io::Error>
 where
     M: Default, {
-    let cors_domains = cors_domains.map(|domains| {
-        domains
-            .into_iter()
-            .map(|v| match v.as_str() {
-                "*" => jsonrpc_http_server::AccessControlAllowOrigin::Any,
-                "null" => jsonrpc_http_server::AccessControlAllowOrigin::Null,
-                v => jsonrpc_http_server::AccessControlAllowOrigin::Value(v.into()),
-            })
-            .collect()
-    });
+    // jsonrpc-http-server allows every origin if the validation is disabled.
+    let cors_domains = cors_domains
+        .unwrap_or_default()
+        .into_iter()
+        .map(|v| match v.as_str() {
+            "*" => jsonrpc_http_server::AccessControlAllowOrigin::Any,
+            "null" => jsonrpc_http_server::AccessControlAllowOrigin::Null,
+            v => jsonrpc_http_server::AccessControlAllowOrigin::Value(v.into()),
+        })
+        .collect();
 
     HttpServerBuilder::new(handler)
-        .cors(cors_domains.into())
+        .cors(DomainsValidation::AllowOnly(cors_domains))
         .allowed_hosts(allowed_hosts.map(|hosts| hosts.into_iter().map(Host::from).collect()).into())
         .start_http(addr)
 }
 
 /// Start ipc server asynchronously and returns result with `Server` handle on success or an error.
-pub fn start_ipc<M: jsonrpc_core::Metadata>(
+/// Each connection has its own session so that it can subscribe to events.
+pub fn start_ipc(
     addr: &str,
-    handler: jsonrpc_core::MetaIoHandler<M, impl jsonrpc_core::Middleware<M>>,
-) -> Result<IpcServer, io::Error>
-where
-    M: Default, {
-    IpcServerBuilder::new(handler).start(addr)
+    handler: jsonrpc_core::MetaIoHandler<Metadata, impl jsonrpc_core::Middleware<Metadata>>,
+) -> Result<IpcServer, io::Error> {
+    IpcServerBuilder::with_meta_extractor(handler, |context: &IpcRequestContext| {
+        Metadata::new(Session::new(context.sender.clone()))
+    })
+    .start(addr)
 }
 
 /// Start WS server and return `Server` handle.
-pub fn start_ws<M: jsonrpc_core::Metadata>(
+/// Each connection has its own session so that it can subscribe to events.
+/// Browsers can't connect to the server unless `allowed_origins` allows their origins.
+pub fn start_ws(
     addr: &SocketAddr,
-    handler: jsonrpc_core::MetaIoHandler<M, impl jsonrpc_core::Middleware<M>>,
+    handler: jsonrpc_core::MetaIoHandler<Metadata, impl jsonrpc_core::Middleware<Metadata>>,
     max_connections: usize,
-) -> Result<WsServer, WsError>
-where
-    M: Default, {
-    // FIXME: Add Hosts, Origins and Session States
-    WsServerBuilder::new(handler).max_connections(max_connections).start(addr)
+    allowed_origins: Option<Vec<String>>,
+    allowed_hosts: Option<Vec<String>>,
+) -> Result<WsServer, WsError> {
+    let allowed_origins = allowed_origins.unwrap_or_default().into_iter().map(WsOrigin::from).collect();
+    WsServerBuilder::with_meta_extractor(handler, |context: &RequestContext| {
+        Metadata::new(Session::new(context.sender()))
+    })
+    .max_connections(max_connections)
+    .allowed_origins(DomainsValidation::AllowOnly(allowed_origins))
+    .allowed_hosts(allowed_hosts.map(|hosts| hosts.into_iter().map(Host::from).collect()).into())
+    .start(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_core::{MetaIoHandler, Params, Value};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+
+    fn handler_with_access(limits: RequestLimits, access: AccessControl) -> MetaIoHandler<Metadata, RpcMiddleware> {
+        let mut handler = MetaIoHandler::with_middleware(RpcMiddleware::new(limits, access));
+        handler.add_method("ping", |_params: Params| Ok(Value::String("pong".to_string())));
+        handler.add_method("sleep", |_params: Params| {
+            thread::sleep(Duration::from_millis(20));
+            Ok(Value::Null)
+        });
+        handler.add_method("chain_getBestBlockNumber", |_params: Params| Ok(Value::from(1)));
+        handler.add_method("account_sign", |_params: Params| Ok(Value::String("0x1234".to_string())));
+        handler
+    }
+
+    fn handler(limits: RequestLimits) -> MetaIoHandler<Metadata, RpcMiddleware> {
+        handler_with_access(limits, AccessControl::default())
+    }
+
+    fn batch(method: &str, len: usize) -> String {
+        let calls: Vec<_> = (0..len)
+            .map(|id| format!(r#"{{"jsonrpc":"2.0","method":"{}","params":[],"id":{}}}"#, method, id))
+            .collect();
+        format!("[{}]", calls.join(","))
+    }
+
+    fn send_with_meta(handler: &MetaIoHandler<Metadata, RpcMiddleware>, request: &str, meta: Metadata) -> Value {
+        serde_json::from_str(&handler.handle_request_sync(request, meta).unwrap()).unwrap()
+    }
+
+    fn send(handler: &MetaIoHandler<Metadata, RpcMiddleware>, request: &str) -> Value {
+        send_with_meta(handler, request, Metadata::default())
+    }
+
+    fn call(method: &str, params: &str) -> String {
+        format!(r#"{{"jsonrpc":"2.0","method":"{}","params":{},"id":0}}"#, method, params)
+    }
+
+    fn ipc_access() -> AccessControl {
+        AccessControl {
+            token: Some("secret".to_string()),
+            allowed_methods: Some(vec!["chain_*".to_string(), "net_*".to_string(), "ping".to_string()]),
+            denied_methods: vec!["net_shareSecret".to_string()],
+        }
+    }
+
+    #[test]
+    fn batch_over_the_limit_is_rejected() {
+        let handler = handler(RequestLimits {
+            max_batch_len: Some(2),
+            max_execution_time: None,
+        });
+        let response = send(&handler, &batch("ping", 3));
+        assert_eq!(response["error"]["code"], -32050);
+    }
+
+    #[test]
+    fn batch_within_the_limit_is_processed() {
+        let handler = handler(RequestLimits {
+            max_batch_len: Some(2),
+            max_execution_time: None,
+        });
+        let response = send(&handler, &batch("ping", 2));
+        let outputs = response.as_array().unwrap();
+        assert_eq!(2, outputs.len());
+        assert!(outputs.iter().all(|output| output["result"] == "pong"));
+    }
+
+    #[test]
+    fn calls_after_the_budget_is_exhausted_fail() {
+        let handler = handler(RequestLimits {
+            max_batch_len: None,
+            max_execution_time: Some(Duration::from_millis(10)),
+        });
+        let response = send(&handler, &batch("sleep", 3));
+        let outputs = response.as_array().unwrap();
+        assert_eq!(Value::Null, outputs[0]["result"]);
+        assert!(outputs[0]["error"].is_null());
+        assert_eq!(outputs[1]["error"]["code"], -32051);
+        assert_eq!(outputs[2]["error"]["code"], -32051);
+
+        // The budget is per request.
+        let response = send(&handler, r#"{"jsonrpc":"2.0","method":"ping","params":[],"id":0}"#);
+        assert_eq!(response["result"], "pong");
+    }
+
+    #[test]
+    fn requests_are_unlimited_by_default() {
+        let handler = handler(RequestLimits::default());
+        let response = send(&handler, &batch("ping", 100));
+        assert_eq!(100, response.as_array().unwrap().len());
+    }
+
+    #[test]
+    fn allowed_methods_are_callable_without_auth() {
+        let handler = handler_with_access(RequestLimits::default(), ipc_access());
+        let response = send(&handler, &call("chain_getBestBlockNumber", "[]"));
+        assert_eq!(response["result"], 1);
+        let response = send(&handler, &call("ping", "[]"));
+        assert_eq!(response["result"], "pong");
+    }
+
+    #[test]
+    fn methods_not_allowed_are_denied_without_auth() {
+        let handler = handler_with_access(RequestLimits::default(), ipc_access());
+        let response = send(&handler, &call("account_sign", r#"["0x00"]"#));
+        assert_eq!(response["error"]["code"], -32053);
+        let response = send(&handler, &call("net_shareSecret", "[]"));
+        assert_eq!(response["error"]["code"], -32053);
+        let response = send(&handler, &call("auth", r#"["wrong"]"#));
+        assert_eq!(response["error"]["code"], -32053);
+    }
+
+    fn post_from(addr: &SocketAddr, origin: &str) -> String {
+        let body = call("ping", "[]");
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: {}\r\nOrigin: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+            addr,
+            origin,
+            body.len()
+        );
+        write!(stream, "{}Connection: close\r\n\r\n{}", request, body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response.to_lowercase()
+    }
+
+    #[test]
+    fn cors_header_is_sent_only_to_the_configured_origins() {
+        let any_port = "127.0.0.1:0".parse().unwrap();
+        let wallet = "https://wallet.example.com";
+        let server =
+            start_http(&any_port, Some(vec![wallet.to_string()]), None, handler(RequestLimits::default())).unwrap();
+        let response = post_from(server.address(), wallet);
+        assert!(response.contains(&format!("access-control-allow-origin: {}", wallet)), "{}", response);
+        let response = post_from(server.address(), "https://evil.example.com");
+        assert!(!response.contains("access-control-allow-origin"), "{}", response);
+        server.close();
+
+        let server = start_http(&any_port, None, None, handler(RequestLimits::default())).unwrap();
+        let response = post_from(server.address(), wallet);
+        assert!(!response.contains("access-control-allow-origin"), "{}", response);
+        server.close();
+    }
+
+    #[test]
+    fn origins_are_validated() {
+        for origin in &["*", "null", "http://localhost:8080", "https://wallet.example.com", "http://[::1]:3000"] {
+            assert_eq!(Ok(()), validate_origin(origin), "{}", origin);
+        }
+        for origin in &["", "wallet.example.com", "https://", "https://example.com/path", "http://host:port", "://a"] {
+            assert!(validate_origin(origin).is_err(), "{}", origin);
+        }
+    }
+
+    #[test]
+    fn authenticated_client_can_sign() {
+        let handler = handler_with_access(RequestLimits::default(), ipc_access());
+        let meta = Metadata::default();
+        let response = send_with_meta(&handler, &call("auth", r#"["secret"]"#), meta.clone());
+        assert_eq!(response["result"], true);
+        let response = send_with_meta(&handler, &call("account_sign", r#"["0x00"]"#), meta);
+        assert_eq!(response["result"], "0x1234");
+
+        // The authentication doesn't leak to the other clients.
+        let response = send(&handler, &call("account_sign", r#"["0x00"]"#));
+        assert_eq!(response["error"]["code"], -32053);
+    }
+
+    #[test]
+    fn only_the_same_token_matches() {
+        assert!(is_same_token("secret", "secret"));
+        assert!(!is_same_token("secret", "secreT"));
+        assert!(!is_same_token("secret", "secret2"));
+        assert!(!is_same_token("secret", ""));
+    }
 }
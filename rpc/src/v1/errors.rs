@@ -14,21 +14,30 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use ccore::stake::AuditError;
 use ccore::AccountProviderError;
+use ccore::AddressIndexError;
+use ccore::AssetLogError;
+use ccore::BackupError;
 use ccore::Error as CoreError;
+use ccore::{BlockError, BlockImportError};
+use cjson::uint::OverflowError;
 use ckey::Error as KeyError;
 use ckeystore::Error as KeystoreError;
 use cnetwork::control::Error as NetworkControlError;
 use cstate::StateError;
 use ctypes::errors::{HistoryError, RuntimeError, SyntaxError};
+use ctypes::util::unexpected::{Mismatch, OutOfBounds};
 use jsonrpc_core::{Error, ErrorCode, Value};
 use rlp::DecoderError;
 use rustc_serialize::hex::FromHexError as HexError;
+use serde::Serialize;
 use std::fmt;
 
 pub enum ConversionError {
     Key(KeyError),
     Hex(HexError),
+    Overflow(OverflowError),
 }
 
 impl From<KeyError> for ConversionError {
@@ -43,12 +52,19 @@ impl From<HexError> for ConversionError {
     }
 }
 
+impl From<OverflowError> for ConversionError {
+    fn from(err: OverflowError) -> ConversionError {
+        ConversionError::Overflow(err)
+    }
+}
+
 mod codes {
     pub const NO_AUTHOR: i64 = -32002;
     pub const NO_WORK_REQUIRED: i64 = -32004;
     pub const HEX_ERROR: i64 = -32007;
     pub const RLP_ERROR: i64 = -32009;
     pub const CORE_ERROR: i64 = -32010;
+    pub const KVDB_ERROR: i64 = -32011;
     pub const RUNTIME_ERROR: i64 = -32012;
     pub const NETWORK_DISABLED: i64 = -32014;
     pub const NETWORK_CANNOT_DISCONNECT_NOT_CONNECTED_ERROR: i64 = -32015;
@@ -66,22 +82,124 @@ mod codes {
     pub const WRONG_PASSWORD: i64 = -32043;
     pub const NO_SUCH_ACCOUNT: i64 = -32044;
     pub const NOT_UNLOCKED: i64 = -32045;
-    pub const TRANSFER_ONLY_IN_EXECUTE_VM: i64 = -32046;
-    pub const ASSET_TRANSACTION_ONLY_IN_EXECUTE_TRANSACITON: i64 = -32047;
+    pub const TRANSFER_ONLY: i64 = -32046;
     pub const STATE_NOT_EXIST: i64 = -32048;
     pub const ACTION_DATA_HANDLER_NOT_FOUND: i64 = -32049;
+    pub const BATCH_TOO_LARGE: i64 = -32050;
+    pub const EXECUTION_BUDGET_EXCEEDED: i64 = -32051;
+    pub const TOO_MANY_SUBSCRIPTIONS: i64 = -32052;
+    pub const UNAUTHORIZED: i64 = -32053;
+    pub const NOT_LOCAL_TRANSACTION: i64 = -32054;
+    pub const ADDRESS_INDEX_ERROR: i64 = -32055;
+    pub const ASSET_INDEX_DISABLED: i64 = -32056;
+    pub const EXTERNAL_SEALING_DISABLED: i64 = -32057;
+    pub const SERVER_BUSY: i64 = -32058;
+    pub const READ_ONLY: i64 = -32059;
+    pub const INVALID_BLOCK_RANGE: i64 = -32060;
+    pub const BACKUP_ERROR: i64 = -32061;
+    pub const TOO_MANY_INPUTS: i64 = -32062;
+    pub const NO_SUCH_EXTENSION: i64 = -32063;
+    pub const NOT_YET_SYNCED: i64 = -32064;
+    pub const TEXT_INDEX_DISABLED: i64 = -32065;
+    pub const ASSET_LOG_ERROR: i64 = -32066;
+    pub const INVALID_FEE_TARGET: i64 = -32067;
+    pub const STAKE_AUDIT_FAILED: i64 = -32068;
+    pub const SUBSCRIPTIONS_UNAVAILABLE: i64 = -32069;
+    // -32070 to -32080 are the block import errors in ccore::import_error_codes.
+    pub const TREE_ROUTE_TOO_LONG: i64 = -32081;
+    pub const BLOCK_RANGE_TOO_LARGE: i64 = -32082;
     pub const UNKNOWN_ERROR: i64 = -32099;
 }
 
 pub fn core<T: Into<CoreError>>(error: T) -> Error {
+    let error = error.into();
+    match error {
+        CoreError::Block(_) | CoreError::Import(_) => block_import(error),
+        _ => Error {
+            code: ErrorCode::ServerError(codes::CORE_ERROR),
+            message: format!("{}", error),
+            data: Some(Value::String(format!("{:?}", error))),
+        },
+    }
+}
+
+/// True if the error is a failure to access the database, not an error of the request.
+pub fn is_kvdb_error(error: &CoreError) -> bool {
+    match error {
+        CoreError::Trie(_) | CoreError::Database(_) | CoreError::Checkpoint(_) => true,
+        _ => false,
+    }
+}
+
+pub fn kvdb<T: Into<CoreError>>(error: T) -> Error {
     let error = error.into();
     Error {
-        code: ErrorCode::ServerError(codes::CORE_ERROR),
-        message: format!("{}", error),
+        code: ErrorCode::ServerError(codes::KVDB_ERROR),
+        message: format!("Failed to access the state: {}", error),
         data: Some(Value::String(format!("{:?}", error))),
     }
 }
 
+/// Each failure class of the block import has its own code in `ccore::import_error_codes`.
+pub fn block_import<T: Into<BlockImportError>>(error: T) -> Error {
+    let error = error.into();
+    let data = match &error {
+        BlockImportError::Block(error) => block_error_data(error),
+        _ => None,
+    };
+    Error {
+        code: ErrorCode::ServerError(error.code()),
+        message: match &error {
+            BlockImportError::Import(error) => error.to_string(),
+            BlockImportError::Block(error) => error.to_string(),
+            BlockImportError::Other(error) => error.clone(),
+        },
+        data: Some(data.unwrap_or_else(|| Value::String(format!("{:?}", error)))),
+    }
+}
+
+fn block_error_data(error: &BlockError) -> Option<Value> {
+    fn mismatch<T: Serialize>(field: &str, mismatch: &Mismatch<T>) -> Value {
+        serde_json::json!({
+            "field": field,
+            "expected": mismatch.expected,
+            "found": mismatch.found,
+        })
+    }
+
+    fn out_of_bounds<T: Serialize>(field: &str, out_of_bounds: &OutOfBounds<T>) -> Value {
+        serde_json::json!({
+            "field": field,
+            "min": out_of_bounds.min,
+            "max": out_of_bounds.max,
+            "found": out_of_bounds.found,
+        })
+    }
+
+    Some(match error {
+        BlockError::ExtraDataOutOfBounds(oob) => out_of_bounds("extraData", oob),
+        BlockError::InvalidSealArity(mis) => mismatch("seal", mis),
+        BlockError::InvalidStateRoot(mis) => mismatch("stateRoot", mis),
+        BlockError::InvalidTransactionsRoot(mis) => mismatch("transactionsRoot", mis),
+        BlockError::ScoreOutOfBounds(oob) => out_of_bounds("score", oob),
+        BlockError::InvalidScore(mis) => mismatch("score", mis),
+        BlockError::PowOutOfBounds(oob) => out_of_bounds("seal", oob),
+        BlockError::InvalidTimestamp(oob) | BlockError::TemporarilyInvalid(oob) => out_of_bounds("timestamp", oob),
+        BlockError::InvalidParentHash(mis) => mismatch("parentHash", mis),
+        BlockError::InvalidNumber(mis) => mismatch("number", mis),
+        BlockError::RidiculousNumber(oob) => out_of_bounds("number", oob),
+        BlockError::UnknownParent(parent_hash) => serde_json::json!({
+            "field": "parentHash",
+            "found": parent_hash,
+        }),
+        BlockError::InvalidProofOfWork
+        | BlockError::InvalidSeal
+        | BlockError::TooManyTransactions(_)
+        | BlockError::BodySizeIsTooBig(_)
+        | BlockError::MalformedBlock => return None,
+    })
+}
+
 pub fn conversion<T: Into<ConversionError>>(error: T) -> Error {
     let error = error.into();
     match error {
@@ -91,6 +209,11 @@ pub fn conversion<T: Into<ConversionError>>(error: T) -> Error {
             message: error.to_string(),
             data: Some(Value::String(format!("{:?}", error))),
         },
+        ConversionError::Overflow(error) => Error {
+            code: ErrorCode::InvalidParams,
+            message: error.to_string(),
+            data: None,
+        },
     }
 }
 
@@ -257,21 +380,18 @@ pub fn network_control(error: &NetworkControlError) -> Error {
             message: "Network is diabled.".into(),
             data: None,
         },
+        NetworkControlError::NoSuchExtension(name) => Error {
+            code: ErrorCode::ServerError(codes::NO_SUCH_EXTENSION),
+            message: format!("There is no extension named {}", name),
+            data: None,
+        },
     }
 }
 
-pub fn transfer_only() -> Error {
-    Error {
-        code: ErrorCode::ServerError(codes::TRANSFER_ONLY_IN_EXECUTE_VM),
-        message: "chain_executeVM() only accepts AssetTransfer transactions.".into(),
-        data: None,
-    }
-}
-
-pub fn asset_transaction_only() -> Error {
+pub fn transfer_only(method: &str) -> Error {
     Error {
-        code: ErrorCode::ServerError(codes::ASSET_TRANSACTION_ONLY_IN_EXECUTE_TRANSACITON),
-        message: "chain_executeTransaction() only accepts asset transactions.".into(),
+        code: ErrorCode::ServerError(codes::TRANSFER_ONLY),
+        message: format!("{}() only accepts AssetTransfer transactions.", method),
         data: None,
     }
 }
@@ -292,6 +412,166 @@ pub fn invalid_custom_action(err: String) -> Error {
     }
 }
 
+pub fn batch_too_large(max_batch_len: usize) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::BATCH_TOO_LARGE),
+        message: format!("A batch cannot have more than {} calls", max_batch_len),
+        data: None,
+    }
+}
+
+pub fn execution_budget_exceeded() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::EXECUTION_BUDGET_EXCEEDED),
+        message: "The execution time budget of the request is exhausted".into(),
+        data: None,
+    }
+}
+
+pub fn too_many_subscriptions(max_subscriptions: usize) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::TOO_MANY_SUBSCRIPTIONS),
+        message: format!("A connection cannot have more than {} subscriptions", max_subscriptions),
+        data: None,
+    }
+}
+
+pub fn subscriptions_unavailable() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::SUBSCRIPTIONS_UNAVAILABLE),
+        message: "Subscriptions are only available over WebSockets and IPC".into(),
+        data: None,
+    }
+}
+
+pub fn unauthorized(method: &str) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::UNAUTHORIZED),
+        message: format!("Not authorized to call {}", method),
+        data: None,
+    }
+}
+
+pub fn invalid_auth_token() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::UNAUTHORIZED),
+        message: "The authentication token is invalid".into(),
+        data: None,
+    }
+}
+
+pub fn not_local_transaction() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::NOT_LOCAL_TRANSACTION),
+        message: "Only the transactions submitted to this node can be removed".into(),
+        data: None,
+    }
+}
+
+pub fn address_index(error: AddressIndexError) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::ADDRESS_INDEX_ERROR),
+        message: format!("{}", error),
+        data: None,
+    }
+}
+
+pub fn asset_index_disabled() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::ASSET_INDEX_DISABLED),
+        message: "The asset index is disabled".into(),
+        data: None,
+    }
+}
+
+pub fn text_index_disabled() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::TEXT_INDEX_DISABLED),
+        message: "The text index is disabled".into(),
+        data: None,
+    }
+}
+
+pub fn asset_log(error: AssetLogError) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::ASSET_LOG_ERROR),
+        message: format!("{}", error),
+        data: None,
+    }
+}
+
+pub fn external_sealing_disabled() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::EXTERNAL_SEALING_DISABLED),
+        message: "External sealing is disabled or not supported by the engine".into(),
+        data: None,
+    }
+}
+
+pub fn server_busy() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::SERVER_BUSY),
+        message: "The server is too busy to handle the request".into(),
+        data: None,
+    }
+}
+
+pub fn read_only() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::READ_ONLY),
+        message: "Unsupported in read-only mode".into(),
+        data: None,
+    }
+}
+
+pub fn invalid_block_range(from: u64, to: u64) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::INVALID_BLOCK_RANGE),
+        message: format!("The block range {}..={} is empty", from, to),
+        data: None,
+    }
+}
+
+pub fn tree_route_too_long(max_length: usize) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::TREE_ROUTE_TOO_LONG),
+        message: format!("The route has more than {} blocks", max_length),
+        data: None,
+    }
+}
+
+pub fn block_range_too_large(from: u64, to: u64, max_blocks: u64) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::BLOCK_RANGE_TOO_LARGE),
+        message: format!("The block range {}..={} has more than {} blocks", from, to, max_blocks),
+        data: None,
+    }
+}
+
+pub fn invalid_fee_target() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::INVALID_FEE_TARGET),
+        message: "The target number of blocks must be positive".into(),
+        data: None,
+    }
+}
+
+pub fn stake_audit(error: AuditError) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::STAKE_AUDIT_FAILED),
+        message: format!("{}", error),
+        data: None,
+    }
+}
+
+pub fn backup(error: BackupError) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::BACKUP_ERROR),
+        message: format!("{}", error),
+        data: None,
+    }
+}
+
 /// Internal error signifying a logic error in code.
 /// Should not be used when function can just fail
 /// because of invalid parameters or incomplete node state.
@@ -302,3 +582,71 @@ pub fn internal<T: fmt::Debug>(error: &str, data: T) -> Error {
         data: Some(Value::String(format!("{:?}", data))),
     }
 }
+
+pub fn too_many_inputs(max_inputs: usize) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::TOO_MANY_INPUTS),
+        message: format!("A transaction cannot be checked if it has more than {} inputs and burns", max_inputs),
+        data: None,
+    }
+}
+
+pub fn not_yet_synced(best_block_number: u64, best_known_number: u64) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::NOT_YET_SYNCED),
+        message: format!("The block is not imported yet. The best block is #{}", best_block_number),
+        data: Some(serde_json::json!({
+            "bestBlockNumber": best_block_number,
+            "bestKnownNumber": best_known_number,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ccore::{import_error_codes, ImportError};
+    use primitives::H256;
+
+    #[test]
+    fn block_errors_keep_their_codes_and_fields() {
+        let expected = H256::random();
+        let found = H256::random();
+        let error = core(BlockError::InvalidStateRoot(Mismatch {
+            expected,
+            found,
+        }));
+        assert_eq!(ErrorCode::ServerError(import_error_codes::INVALID_STATE_ROOT), error.code);
+        assert_eq!(
+            Some(serde_json::json!({
+                "field": "stateRoot",
+                "expected": expected,
+                "found": found,
+            })),
+            error.data
+        );
+
+        let error = core(BlockError::TemporarilyInvalid(OutOfBounds {
+            min: None,
+            max: Some(10),
+            found: 20,
+        }));
+        assert_eq!(ErrorCode::ServerError(import_error_codes::TEMPORARILY_INVALID), error.code);
+        assert_eq!(
+            Some(serde_json::json!({
+                "field": "timestamp",
+                "min": null,
+                "max": 10,
+                "found": 20,
+            })),
+            error.data
+        );
+
+        let error = block_import(BlockImportError::Import(ImportError::AlreadyInChain));
+        assert_eq!(ErrorCode::ServerError(import_error_codes::ALREADY_IN_CHAIN), error.code);
+        assert_eq!(
+            ErrorCode::ServerError(codes::READ_ONLY),
+            block_import(CoreError::Import(ImportError::ReadOnly)).code
+        );
+    }
+}
@@ -0,0 +1,332 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::super::errors;
+use super::super::traits::BlockStream;
+use super::super::types::BlockStreamItem;
+use super::super::Metadata;
+use ccore::BlockChainClient;
+use jsonrpc_core::futures::Future;
+use jsonrpc_core::Result;
+use jsonrpc_pubsub::typed::{Sink, Subscriber};
+use jsonrpc_pubsub::SubscriptionId;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// The blocks are sent in notifications of about this size.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Streams are served by their own threads, so only a few of them run at once.
+const MAX_STREAMS: usize = 4;
+
+/// A running stream.
+struct ActiveStream {
+    /// The connection that started the stream. Only it can stop the stream.
+    connection_id: u64,
+    cancelled: Arc<AtomicBool>,
+}
+
+pub struct BlockStreamClient<C> {
+    client: Arc<C>,
+    next_id: Mutex<u64>,
+    active: Arc<Mutex<HashMap<SubscriptionId, ActiveStream>>>,
+}
+
+impl<C> BlockStreamClient<C> {
+    pub fn new(client: Arc<C>) -> Self {
+        BlockStreamClient {
+            client,
+            next_id: Mutex::new(0),
+            active: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<C> BlockStream for BlockStreamClient<C>
+where
+    C: BlockChainClient + Send + Sync + 'static,
+{
+    type Metadata = Metadata;
+
+    fn stream_blocks(&self, meta: Metadata, subscriber: Subscriber<BlockStreamItem>, from: u64, to: u64) {
+        let connection_id = match meta.connection_id() {
+            Some(connection_id) => connection_id,
+            None => {
+                let _ = subscriber.reject(errors::subscriptions_unavailable());
+                return
+            }
+        };
+        if from > to {
+            let _ = subscriber.reject(errors::invalid_block_range(from, to));
+            return
+        }
+        let mut active = self.active.lock();
+        if active.len() >= MAX_STREAMS {
+            let _ = subscriber.reject(errors::server_busy());
+            return
+        }
+
+        let id = {
+            let mut next_id = self.next_id.lock();
+            *next_id += 1;
+            SubscriptionId::Number(*next_id)
+        };
+        let sink = match subscriber.assign_id(id.clone()) {
+            Ok(sink) => sink,
+            Err(()) => return,
+        };
+        let cancelled = Arc::new(AtomicBool::new(false));
+        active.insert(id.clone(), ActiveStream {
+            connection_id,
+            cancelled: Arc::clone(&cancelled),
+        });
+
+        let client = Arc::clone(&self.client);
+        let active = Arc::clone(&self.active);
+        thread::Builder::new()
+            .name("RPC block stream".to_string())
+            .spawn(move || {
+                stream(&*client, &sink, from, to, &cancelled);
+                active.lock().remove(&id);
+            })
+            .expect("Cannot spawn the block stream thread");
+    }
+
+    fn stop_stream_blocks(&self, meta: Option<Metadata>, id: SubscriptionId) -> Result<bool> {
+        let connection_id = match meta.map(|meta| meta.connection_id()) {
+            // jsonrpc-pubsub unsubscribes without the metadata when the session is dropped.
+            None => None,
+            Some(Some(connection_id)) => Some(connection_id),
+            Some(None) => return Err(errors::subscriptions_unavailable()),
+        };
+        Ok(stop(&mut self.active.lock(), &id, connection_id))
+    }
+}
+
+/// Stops the stream if it belongs to the connection.
+/// `None` is given when the session of the stream is dropped.
+fn stop(active: &mut HashMap<SubscriptionId, ActiveStream>, id: &SubscriptionId, connection_id: Option<u64>) -> bool {
+    match (active.get(id), connection_id) {
+        (Some(stream), Some(connection_id)) if stream.connection_id != connection_id => false,
+        (Some(_), _) => {
+            let stream = active.remove(id).expect("The stream exists");
+            stream.cancelled.store(true, Ordering::SeqCst);
+            true
+        }
+        (None, _) => false,
+    }
+}
+
+/// Sends the blocks `from..=to` until the stream ends or is cancelled.
+/// Waiting for the connection to take each notification keeps a slow reader from piling up the blocks in memory.
+fn stream<C>(client: &C, sink: &Sink<BlockStreamItem>, from: u64, to: u64, cancelled: &AtomicBool)
+where
+    C: BlockChainClient, {
+    let notify = |item: BlockStreamItem| -> bool {
+        if cancelled.load(Ordering::SeqCst) {
+            return false
+        }
+        if sink.notify(Ok(item)).wait().is_err() {
+            cdebug!(RPC, "Stopped streaming blocks to a closed connection");
+            return false
+        }
+        true
+    };
+
+    let mut chunk_from = from;
+    let mut count = 0;
+    let mut data = Vec::with_capacity(CHUNK_SIZE);
+    for block in client.blocks_in_range(from..to.saturating_add(1)) {
+        match block {
+            Ok(block) => {
+                let bytes = block.into_inner();
+                data.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+                data.extend_from_slice(&bytes);
+                count += 1;
+            }
+            Err(err) => {
+                if count != 0
+                    && !notify(BlockStreamItem::Blocks {
+                        from: chunk_from,
+                        count,
+                        data: data.into(),
+                    })
+                {
+                    return
+                }
+                notify(err.into());
+                return
+            }
+        }
+        if data.len() >= CHUNK_SIZE {
+            let chunk = BlockStreamItem::Blocks {
+                from: chunk_from,
+                count,
+                data: mem::replace(&mut data, Vec::with_capacity(CHUNK_SIZE)).into(),
+            };
+            if !notify(chunk) {
+                return
+            }
+            chunk_from += count as u64;
+            count = 0;
+        }
+    }
+    if count != 0
+        && !notify(BlockStreamItem::Blocks {
+            from: chunk_from,
+            count,
+            data: data.into(),
+        })
+    {
+        return
+    }
+    notify(BlockStreamItem::End);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc_server::start_ws;
+    use ccore::{BlockChainTrait, BlockId, TestBlockChainClient};
+    use jsonrpc_core::MetaIoHandler;
+    use rustc_hex::FromHex;
+    use serde_json::Value;
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+    use ws;
+
+    /// Streams the blocks over WS and returns the decoded blocks and the last item.
+    fn stream_over_ws(client: Arc<TestBlockChainClient>, from: u64, to: u64) -> (Vec<Vec<u8>>, Value) {
+        let mut handler: MetaIoHandler<Metadata> = MetaIoHandler::default();
+        handler.extend_with(BlockStreamClient::new(client).to_delegate());
+        let server = start_ws(&"127.0.0.1:0".parse().unwrap(), handler, 10, None, None).unwrap();
+        let url = format!("ws://{}", server.addr());
+
+        let (sender, receiver) = channel();
+        let ws_client = thread::spawn(move || {
+            ws::connect(url, |out| {
+                let request =
+                    format!(r#"{{"jsonrpc":"2.0","method":"devel_streamBlocks","params":[{},{}],"id":1}}"#, from, to);
+                out.send(request).unwrap();
+                let sender = sender.clone();
+                move |message: ws::Message| {
+                    let message: Value = serde_json::from_str(message.as_text()?).unwrap();
+                    let item_type = message["params"]["result"]["type"].clone();
+                    sender.send(message).unwrap();
+                    if item_type == "end" || item_type == "error" {
+                        out.close(ws::CloseCode::Normal)
+                    } else {
+                        Ok(())
+                    }
+                }
+            })
+            .unwrap();
+        });
+
+        let response = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(response["result"].is_number(), "{}", response);
+
+        let mut blocks = Vec::new();
+        let last = loop {
+            let notification = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!("devel_blocks", notification["method"]);
+            let item = notification["params"]["result"].clone();
+            if item["type"] != "blocks" {
+                break item
+            }
+            assert_eq!(blocks.len() as u64 + from, item["from"].as_u64().unwrap());
+            let data: Vec<u8> = item["data"].as_str().unwrap()[2..].from_hex().unwrap();
+            let mut rest = &data[..];
+            let mut count = 0;
+            while !rest.is_empty() {
+                let mut len = [0u8; 4];
+                len.copy_from_slice(&rest[..4]);
+                let len = u32::from_be_bytes(len) as usize;
+                blocks.push(rest[4..4 + len].to_vec());
+                rest = &rest[4 + len..];
+                count += 1;
+            }
+            assert_eq!(item["count"].as_u64().unwrap(), count);
+        };
+
+        ws_client.join().unwrap();
+        server.close_handle().close();
+        (blocks, last)
+    }
+
+    #[test]
+    fn streamed_blocks_are_the_same_as_the_stored_ones() {
+        let client = Arc::new(TestBlockChainClient::new());
+        client.add_blocks(300, 1);
+
+        let (blocks, last) = stream_over_ws(Arc::clone(&client), 0, 300);
+        assert_eq!("end", last["type"], "{}", last);
+        assert_eq!(301, blocks.len());
+        for (number, block) in blocks.into_iter().enumerate() {
+            assert_eq!(client.block(&BlockId::Number(number as u64)).unwrap().into_inner(), block);
+        }
+    }
+
+    #[test]
+    fn stream_ends_with_an_error_at_a_missing_block() {
+        let client = Arc::new(TestBlockChainClient::new());
+        client.add_blocks(10, 1);
+
+        let (blocks, last) = stream_over_ws(Arc::clone(&client), 5, 20);
+        assert_eq!(6, blocks.len());
+        assert_eq!("error", last["type"], "{}", last);
+        assert_eq!("notFound", last["reason"]);
+        assert_eq!(11, last["number"]);
+    }
+
+    #[test]
+    fn only_the_connection_that_started_a_stream_can_stop_it() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let id = SubscriptionId::Number(1);
+        let mut active = HashMap::new();
+        active.insert(id.clone(), ActiveStream {
+            connection_id: 7,
+            cancelled: Arc::clone(&cancelled),
+        });
+
+        assert!(!stop(&mut active, &id, Some(8)));
+        assert!(!cancelled.load(Ordering::SeqCst));
+        assert!(!stop(&mut active, &SubscriptionId::Number(2), Some(7)));
+
+        assert!(stop(&mut active, &id, Some(7)));
+        assert!(cancelled.load(Ordering::SeqCst));
+        assert!(active.is_empty());
+        assert!(!stop(&mut active, &id, Some(7)));
+    }
+
+    #[test]
+    fn stream_is_stopped_when_its_session_is_dropped() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let id = SubscriptionId::Number(1);
+        let mut active = HashMap::new();
+        active.insert(id.clone(), ActiveStream {
+            connection_id: 7,
+            cancelled: Arc::clone(&cancelled),
+        });
+
+        assert!(stop(&mut active, &id, None));
+        assert!(cancelled.load(Ordering::SeqCst));
+    }
+}
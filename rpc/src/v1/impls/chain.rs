@@ -16,35 +16,75 @@
 
 use super::super::errors;
 use super::super::traits::Chain;
-use super::super::types::{AssetScheme, Block, BlockNumberAndHash, OwnedAsset, Text, Transaction, UnsignedTransaction};
+use super::super::types::{
+    AssetLog, AssetLogFilter, AssetScheme, Block, BlockIdOrTag, BlockNumberAndHash, BlockNumberOrTag, DryRun,
+    FeeDistribution, Finalization, Header, IndexedText, OrphanedProposal, OwnedAsset, SyncStatus, Text, Transaction,
+    TransactionAttempt, TransferInputCheck, TreeRoute, UnsignedTransaction, UnspentAsset, Validator, ValidatorLiveness,
+};
+use crate::executor::ExecutorHandle;
 use ccore::{
-    AccountData, AssetClient, BlockId, EngineInfo, ExecuteClient, MiningBlockChainClient, Shard, TermInfo, TextClient,
+    AccountData, AssetClient, BlockChainClient, BlockId, EngineInfo, ExecuteClient, MiningBlockChainClient, Shard,
+    StateInfo, TermInfo, TextClient,
 };
 use ccrypto::Blake;
 use cjson::scheme::Params;
 use cjson::uint::Uint;
 use ckey::{public_to_address, NetworkId, PlatformAddress, Public};
-use cstate::FindActionHandler;
-use ctypes::transaction::{Action, ShardTransaction as ShardTransactionType};
-use ctypes::{BlockHash, BlockNumber, ShardId, Tracker, TxHash};
-use jsonrpc_core::Result;
+use cstate::{FindActionHandler, TopStateView};
+use csync::SyncStatus as BlockSyncStatus;
+use ctypes::transaction::{Action, ActionType, IncompleteTransaction, ShardTransaction as ShardTransactionType};
+use ctypes::{BlockHash, BlockNumber, CommonParams, ShardId, Tracker, TxHash};
+use jsonrpc_core::futures::future;
+use jsonrpc_core::{BoxFuture, Result};
 use primitives::{Bytes as BytesArray, H160, H256};
-use std::convert::{TryFrom, TryInto};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::io;
 use std::sync::Arc;
 
+/// The maximum number of headers that chain_getHeadersInRange returns.
+const MAX_HEADERS_IN_RANGE: u64 = 1024;
+/// The maximum total size of the JSON headers that chain_getHeadersInRange returns.
+const MAX_HEADERS_IN_RANGE_SIZE: usize = 1024 * 1024;
+/// The number of recent blocks whose body sizes chain_estimateFee averages.
+const FEE_ESTIMATE_SAMPLE_BLOCKS: u64 = 16;
+/// The maximum number of blocks in the route that chain_getTreeRoute returns.
+const MAX_TREE_ROUTE_LENGTH: usize = 1024;
+/// The maximum number of heights that chain_getOrphanedProposals reads at once.
+const MAX_ORPHANED_PROPOSAL_HEIGHTS: u64 = 1024;
+
 pub struct ChainClient<C>
 where
     C: AssetClient + MiningBlockChainClient + Shard + ExecuteClient + EngineInfo, {
     client: Arc<C>,
+    enable_dry_run: bool,
+    executor: ExecutorHandle,
+    sync_status: Arc<BlockSyncStatus>,
 }
 
 impl<C> ChainClient<C>
 where
     C: AssetClient + MiningBlockChainClient + Shard + AccountData + ExecuteClient + EngineInfo + TextClient,
 {
-    pub fn new(client: Arc<C>) -> Self {
+    pub fn new(
+        client: Arc<C>,
+        enable_dry_run: bool,
+        executor: ExecutorHandle,
+        sync_status: Arc<BlockSyncStatus>,
+    ) -> Self {
         ChainClient {
             client,
+            enable_dry_run,
+            executor,
+            sync_status,
+        }
+    }
+
+    fn check_synced(&self, block_number: Option<BlockNumber>) -> Result<()> {
+        match block_number {
+            Some(block_number) => check_synced(&*self.client, &self.sync_status, block_number),
+            None => Ok(()),
         }
     }
 }
@@ -60,6 +100,7 @@ where
         + FindActionHandler
         + TextClient
         + TermInfo
+        + StateInfo
         + 'static,
 {
     fn get_transaction(&self, transaction_hash: TxHash) -> Result<Option<Transaction>> {
@@ -87,6 +128,46 @@ where
         Ok(self.client.transaction_by_tracker(&tracker).map(From::from))
     }
 
+    fn get_transactions_by_tracker(&self, tracker: Tracker) -> Result<Vec<TransactionAttempt>> {
+        Ok(self.client.transaction_attempts_by_tracker(&tracker).into_iter().map(From::from).collect())
+    }
+
+    fn get_transactions_by_address(
+        &self,
+        address: PlatformAddress,
+        from_block: u64,
+        to_block: Option<u64>,
+        skip: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Transaction>> {
+        const DEFAULT_LIMIT: usize = 100;
+        const MAX_LIMIT: usize = 1000;
+
+        let address = address.try_address().map_err(errors::core)?;
+        let to_block = to_block.unwrap_or_else(|| self.client.chain_info().best_block_number);
+        let limit = ::std::cmp::min(limit.unwrap_or(DEFAULT_LIMIT), MAX_LIMIT);
+        let transactions = self
+            .client
+            .transactions_by_address(address, from_block, to_block, skip.unwrap_or(0), limit)
+            .map_err(errors::address_index)?;
+        Ok(transactions.into_iter().map(From::from).collect())
+    }
+
+    fn get_asset_logs(
+        &self,
+        filter: AssetLogFilter,
+        skip: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<Vec<AssetLog>> {
+        const DEFAULT_LIMIT: usize = 100;
+        const MAX_LIMIT: usize = 1000;
+
+        let filter = filter.into_filter(self.client.chain_info().best_block_number);
+        let limit = ::std::cmp::min(limit.unwrap_or(DEFAULT_LIMIT), MAX_LIMIT);
+        let logs = self.client.asset_logs(&filter, skip.unwrap_or(0), limit).map_err(errors::asset_log)?;
+        Ok(logs.into_iter().map(From::from).collect())
+    }
+
     fn get_asset_scheme_by_tracker(
         &self,
         tracker: Tracker,
@@ -103,6 +184,7 @@ where
         shard_id: ShardId,
         block_number: Option<u64>,
     ) -> Result<Option<AssetScheme>> {
+        self.check_synced(block_number)?;
         if block_number == Some(0) {
             return Ok(None)
         }
@@ -121,6 +203,7 @@ where
     }
 
     fn get_text(&self, transaction_hash: TxHash, block_number: Option<u64>) -> Result<Option<Text>> {
+        self.check_synced(block_number)?;
         if block_number == Some(0) {
             return Ok(None)
         }
@@ -132,6 +215,39 @@ where
             .map(|text| Text::from_core(text, self.client.network_id())))
     }
 
+    fn get_texts_by_certifier(
+        &self,
+        certifier: PlatformAddress,
+        skip: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<Vec<IndexedText>> {
+        const DEFAULT_LIMIT: usize = 100;
+        const MAX_LIMIT: usize = 1000;
+
+        let certifier = certifier.try_address().map_err(errors::core)?;
+        let limit = ::std::cmp::min(limit.unwrap_or(DEFAULT_LIMIT), MAX_LIMIT);
+        let texts = self
+            .client
+            .texts_by_certifier(certifier, skip.unwrap_or(0), limit)
+            .map_err(errors::transaction_state)?
+            .ok_or_else(errors::text_index_disabled)?;
+        let network_id = self.client.network_id();
+        Ok(texts.into_iter().map(|(tx_hash, text)| IndexedText::from_core(tx_hash, text, network_id)).collect())
+    }
+
+    fn get_text_by_content_hash(&self, content_hash: H256) -> Result<Option<IndexedText>> {
+        // Some texts of the index may have been removed after reading it, so it reads a few of them.
+        const LIMIT: usize = 10;
+
+        let texts = self
+            .client
+            .texts_by_content_hash(&content_hash, 0, LIMIT)
+            .map_err(errors::transaction_state)?
+            .ok_or_else(errors::text_index_disabled)?;
+        let network_id = self.client.network_id();
+        Ok(texts.into_iter().next().map(|(tx_hash, text)| IndexedText::from_core(tx_hash, text, network_id)))
+    }
+
     fn get_asset(
         &self,
         tracker: Tracker,
@@ -139,11 +255,31 @@ where
         shard_id: ShardId,
         block_number: Option<u64>,
     ) -> Result<Option<OwnedAsset>> {
+        self.check_synced(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         let asset = self.client.get_asset(tracker, index, shard_id, block_id).map_err(errors::transaction_state)?;
         Ok(asset.map(From::from))
     }
 
+    fn get_owned_assets_by_lock_script_hash(
+        &self,
+        shard_id: ShardId,
+        lock_script_hash: H160,
+        skip: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<Vec<UnspentAsset>> {
+        const DEFAULT_LIMIT: usize = 100;
+        const MAX_LIMIT: usize = 1000;
+
+        let limit = ::std::cmp::min(limit.unwrap_or(DEFAULT_LIMIT), MAX_LIMIT);
+        let assets = self
+            .client
+            .owned_assets_by_lock_script_hash(shard_id, &lock_script_hash, skip.unwrap_or(0), limit)
+            .map_err(errors::transaction_state)?
+            .ok_or_else(errors::asset_index_disabled)?;
+        Ok(assets.into_iter().map(From::from).collect())
+    }
+
     fn is_asset_spent(
         &self,
         tracker: Tracker,
@@ -151,29 +287,48 @@ where
         shard_id: ShardId,
         block_number: Option<u64>,
     ) -> Result<Option<bool>> {
+        self.check_synced(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         self.client.is_asset_spent(tracker, index, shard_id, block_id).map_err(errors::transaction_state)
     }
 
-    fn get_seq(&self, address: PlatformAddress, block_number: Option<u64>) -> Result<Option<u64>> {
-        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+    fn get_seq(&self, address: PlatformAddress, block_number: Option<BlockNumberOrTag>) -> Result<Option<u64>> {
+        if let Some(BlockNumberOrTag::Number(number)) = &block_number {
+            self.check_synced(Some(*number))?;
+        }
         let address = address.try_address().map_err(errors::core)?;
-        Ok(self.client.seq(address, block_id))
+        match block_number {
+            Some(BlockNumberOrTag::Number(number)) => Ok(self.client.seq(address, BlockId::Number(number))),
+            Some(BlockNumberOrTag::Latest) | None => Ok(self.client.seq(address, BlockId::Latest)),
+            Some(BlockNumberOrTag::Pending) => Ok(Some(self.client.latest_seq_pending(address))),
+        }
     }
 
-    fn get_balance(&self, aaddress: PlatformAddress, block_number: Option<u64>) -> Result<Option<Uint>> {
-        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+    fn get_balance(&self, aaddress: PlatformAddress, block_number: Option<BlockNumberOrTag>) -> Result<Option<Uint>> {
+        if let Some(BlockNumberOrTag::Number(number)) = &block_number {
+            self.check_synced(Some(*number))?;
+        }
         let address = aaddress.try_address().map_err(errors::core)?;
-        Ok(self.client.balance(address, block_id.into()).map(Into::into))
+        let state = match block_number {
+            Some(BlockNumberOrTag::Number(number)) => BlockId::Number(number).into(),
+            Some(BlockNumberOrTag::Latest) | None => BlockId::Latest.into(),
+            Some(BlockNumberOrTag::Pending) => {
+                let pending_state: Box<dyn TopStateView> = Box::new(self.client.pending_state());
+                pending_state.into()
+            }
+        };
+        Ok(self.client.balance(address, state).map(Into::into))
     }
 
     fn get_regular_key(&self, address: PlatformAddress, block_number: Option<u64>) -> Result<Option<Public>> {
+        self.check_synced(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         let address = address.try_address().map_err(errors::core)?;
         Ok(self.client.regular_key(address, block_id.into()))
     }
 
     fn get_regular_key_owner(&self, public: Public, block_number: Option<u64>) -> Result<Option<PlatformAddress>> {
+        self.check_synced(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.regular_key_owner(&public_to_address(&public), block_id.into()).and_then(|address| {
             let network_id = self.client.network_id();
@@ -186,21 +341,25 @@ where
     }
 
     fn get_number_of_shards(&self, block_number: Option<u64>) -> Result<Option<ShardId>> {
+        self.check_synced(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.number_of_shards(block_id.into()))
     }
 
     fn get_shard_id_by_hash(&self, create_shard_tx_hash: TxHash, block_number: Option<u64>) -> Result<Option<ShardId>> {
+        self.check_synced(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.shard_id_by_hash(&create_shard_tx_hash, block_id.into()))
     }
 
     fn get_shard_root(&self, shard_id: ShardId, block_number: Option<u64>) -> Result<Option<H256>> {
+        self.check_synced(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.shard_root(shard_id, block_id.into()))
     }
 
     fn get_shard_owners(&self, shard_id: ShardId, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>> {
+        self.check_synced(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.shard_owners(shard_id, block_id.into()).map(|owners| {
             let network_id = self.client.network_id();
@@ -209,6 +368,7 @@ where
     }
 
     fn get_shard_users(&self, shard_id: ShardId, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>> {
+        self.check_synced(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.shard_users(shard_id, block_id.into()).map(|users| {
             let network_id = self.client.network_id();
@@ -220,6 +380,11 @@ where
         Ok(self.client.chain_info().best_block_number)
     }
 
+    fn get_sync_status(&self) -> Result<SyncStatus> {
+        let best_block_number = self.client.chain_info().best_block_number;
+        Ok(SyncStatus::new(best_block_number, self.sync_status.best_known_number()))
+    }
+
     fn get_best_block_id(&self) -> Result<BlockNumberAndHash> {
         let chain_info = self.client.chain_info();
         Ok(BlockNumberAndHash {
@@ -228,55 +393,98 @@ where
         })
     }
 
+    fn get_finalized_block_id(&self) -> Result<BlockNumberAndHash> {
+        let chain_info = self.client.chain_info();
+        Ok(BlockNumberAndHash {
+            number: chain_info.finalized_block_number,
+            hash: chain_info.finalized_block_hash,
+        })
+    }
+
+    fn is_finalized(&self, block_hash: BlockHash) -> Result<Option<Finalization>> {
+        Ok(finalization(&*self.client, block_hash))
+    }
+
     fn get_block_hash(&self, block_number: u64) -> Result<Option<BlockHash>> {
+        self.check_synced(Some(block_number))?;
         Ok(self.client.block_hash(&BlockId::Number(block_number)))
     }
 
     fn get_block_by_number(&self, block_number: u64) -> Result<Option<Block>> {
+        self.check_synced(Some(block_number))?;
         let id = BlockId::Number(block_number);
-        Ok(self.client.block(&id).map(|block| Block::from_core(block.decode(), self.client.network_id())))
+        Ok(self.client.block(&id).map(|block| Block::from_encoded(block, self.client.network_id())))
     }
 
     fn get_block_by_hash(&self, block_hash: BlockHash) -> Result<Option<Block>> {
         let id = BlockId::Hash(block_hash);
-        Ok(self.client.block(&id).map(|block| {
-            let block = block.decode();
-            Block::from_core(block, self.client.network_id())
-        }))
+        Ok(self.client.block(&id).map(|block| Block::from_encoded(block, self.client.network_id())))
+    }
+
+    fn get_block_transaction_count_by_number(&self, block_number: u64) -> Result<Option<usize>> {
+        self.check_synced(Some(block_number))?;
+        Ok(self.client.block_transaction_count(&BlockId::Number(block_number)))
     }
 
     fn get_block_transaction_count_by_hash(&self, block_hash: BlockHash) -> Result<Option<usize>> {
-        Ok(self.client.block(&BlockId::Hash(block_hash)).map(|block| block.transactions_count()))
+        Ok(self.client.block_transaction_count(&BlockId::Hash(block_hash)))
     }
 
-    fn get_min_transaction_fee(&self, action_type: String, block_number: Option<u64>) -> Result<Option<u64>> {
-        if block_number == Some(0) {
+    fn get_block_error_hints(&self, block_hash: BlockHash) -> Result<Option<Vec<(TxHash, Option<String>)>>> {
+        let id = BlockId::Hash(block_hash);
+        if self.client.block_header(&id).is_none() {
             return Ok(None)
         }
-        // Unlike other RPCs, use the latest parameters if the block number is `null`.
-        let block_id = block_number.map(|n| (n - 1).into()).unwrap_or(BlockId::Latest);
-        if let Some(common_parameters) = self.client.common_params(block_id) {
-            Ok(match action_type.as_str() {
-                "mintAsset" => Some(common_parameters.min_asset_mint_cost()),
-                "transferAsset" => Some(common_parameters.min_asset_transfer_cost()),
-                "changeAssetScheme" => Some(common_parameters.min_asset_scheme_change_cost()),
-                "increaseAssetSupply" => Some(common_parameters.min_asset_supply_increase_cost()),
-                "unwrapCCC" => Some(common_parameters.min_asset_unwrap_ccc_cost()),
-                "pay" => Some(common_parameters.min_pay_transaction_cost()),
-                "setRegularKey" => Some(common_parameters.min_set_regular_key_transaction_cost()),
-                "createShard" => Some(common_parameters.min_create_shard_transaction_cost()),
-                "setShardOwners" => Some(common_parameters.min_set_shard_owners_transaction_cost()),
-                "setShardUsers" => Some(common_parameters.min_set_shard_users_transaction_cost()),
-                "wrapCCC" => Some(common_parameters.min_wrap_ccc_transaction_cost()),
-                "store" => Some(common_parameters.min_store_transaction_cost()),
-                "remove" => Some(common_parameters.min_remove_transaction_cost()),
-                "custom" => Some(common_parameters.min_custom_transaction_cost()),
-
-                _ => None,
-            })
-        } else {
-            Ok(None)
+        Ok(Some(self.client.error_hints_by_block(&id)))
+    }
+
+    fn get_block_fee_distribution(&self, block_hash: BlockHash) -> Result<Option<FeeDistribution>> {
+        let network_id = self.client.network_id();
+        Ok(self
+            .client
+            .fee_distribution(&BlockId::Hash(block_hash))
+            .map(|distribution| FeeDistribution::from_core(distribution, network_id)))
+    }
+
+    fn get_tree_route(&self, from: BlockHash, to: BlockHash) -> Result<Option<TreeRoute>> {
+        tree_route(&*self.client, from, to, MAX_TREE_ROUTE_LENGTH)
+    }
+
+    fn get_headers_in_range(&self, from: BlockIdOrTag, count: u64, reverse: bool) -> Result<Vec<Value>> {
+        Ok(headers_in_range(&*self.client, from.into(), count, reverse, MAX_HEADERS_IN_RANGE_SIZE))
+    }
+
+    fn get_proposal_block(&self, block_number: u64, view: u64) -> Result<Option<Block>> {
+        Ok(self
+            .client
+            .proposal_block(block_number, view)
+            .map(|block| Block::from_encoded(block, self.client.network_id())))
+    }
+
+    fn get_min_transaction_fee(&self, action_type: String, block_number: Option<u64>) -> Result<Option<u64>> {
+        let action_type = match action_type.parse::<ActionType>() {
+            Ok(action_type) => action_type,
+            Err(_) => return Ok(None),
+        };
+        Ok(params_for_fees(&*self.client, block_number).map(|params| params.min_transaction_cost(action_type)))
+    }
+
+    fn get_min_transaction_fees(&self, block_number: Option<u64>) -> Result<Option<BTreeMap<String, u64>>> {
+        Ok(params_for_fees(&*self.client, block_number).map(|params| min_transaction_fees(&params)))
+    }
+
+    fn estimate_fee(&self, target_blocks: u64) -> Result<u64> {
+        if target_blocks == 0 {
+            return Err(errors::invalid_fee_target())
         }
+        let max_body_size = self.client.common_params(BlockId::Latest).unwrap().max_body_size();
+        let best_block_number = self.client.chain_info().best_block_number;
+        let average_body_size = average_body_size(&*self.client, best_block_number);
+        // The recent blocks were filled by the transactions that kept arriving.
+        // Only the room they left is counted for the transactions already pending.
+        let spare_room = max_body_size.saturating_sub(average_body_size);
+        let capacity = (target_blocks as usize).saturating_mul(spare_room);
+        Ok(self.client.pending_fee_histogram().estimate_fee_per_byte(capacity))
     }
 
     fn get_mining_reward(&self, block_number: u64) -> Result<Option<u64>> {
@@ -288,11 +496,13 @@ where
     }
 
     fn get_common_params(&self, block_number: Option<u64>) -> Result<Option<Params>> {
+        self.check_synced(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.common_params(block_id).map(Params::from))
     }
 
     fn get_term_metadata(&self, block_number: Option<u64>) -> Result<Option<(u64, u64)>> {
+        self.check_synced(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         let last_term_finished_block_num = self.client.last_term_finished_block_num(block_id);
         let current_term_id = self.client.current_term_id(block_id);
@@ -306,6 +516,7 @@ where
     }
 
     fn get_metadata_seq(&self, block_number: Option<u64>) -> Result<Option<u64>> {
+        self.check_synced(block_number)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         Ok(self.client.metadata_seq(block_id))
     }
@@ -314,18 +525,78 @@ where
         Ok(self.client.possible_authors(block_number).map_err(errors::core)?)
     }
 
-    fn execute_transaction(&self, tx: UnsignedTransaction, sender: PlatformAddress) -> Result<Option<String>> {
-        let sender_address = sender.try_address().map_err(errors::core)?;
-        let action = Action::try_from(tx.action).map_err(errors::conversion)?;
-        if let Some(transaction) = action.asset_transaction() {
-            let result = self.client.execute_transaction(&transaction, sender_address);
-            match result {
-                Ok(()) => Ok(None),
-                Err(err) => Ok(Some(err.to_string())),
-            }
-        } else {
-            Err(errors::asset_transaction_only())
+    fn get_validator_set(&self, block_number: Option<u64>) -> Result<Option<Vec<Validator>>> {
+        let parent = match block_number {
+            Some(0) => return Ok(None),
+            Some(block_number) => BlockId::Number(block_number - 1),
+            None => BlockId::Latest,
+        };
+        // The validators are read from the state of the parent, which may be pruned.
+        if self.client.state_at(parent).is_none() {
+            return Err(errors::state_not_exist())
+        }
+        let network_id = self.client.network_id();
+        let validators = self.client.validators(block_number).map_err(errors::core)?;
+        Ok(validators
+            .map(|validators| validators.iter().map(|validator| Validator::from_core(validator, network_id)).collect()))
+    }
+
+    fn get_validator_liveness(&self, term_id: Option<u64>) -> Result<Option<Vec<ValidatorLiveness>>> {
+        if self.client.state_at(BlockId::Latest).is_none() {
+            return Err(errors::state_not_exist())
         }
+        let network_id = self.client.network_id();
+        let liveness = self.client.validator_liveness(term_id).map_err(errors::core)?;
+        Ok(liveness.map(|liveness| {
+            liveness.iter().map(|liveness| ValidatorLiveness::from_core(liveness, network_id)).collect()
+        }))
+    }
+
+    fn get_orphaned_proposals(&self, from_height: u64, to_height: u64) -> Result<Vec<OrphanedProposal>> {
+        if from_height > to_height {
+            return Err(errors::invalid_block_range(from_height, to_height))
+        }
+        if to_height - from_height >= MAX_ORPHANED_PROPOSAL_HEIGHTS {
+            return Err(errors::block_range_too_large(from_height, to_height, MAX_ORPHANED_PROPOSAL_HEIGHTS))
+        }
+        let network_id = self.client.network_id();
+        Ok(self
+            .client
+            .orphaned_proposals(from_height, to_height)
+            .map_err(|err| errors::rlp(&err))?
+            .into_iter()
+            .map(|proposal| OrphanedProposal::from_core(proposal, network_id))
+            .collect())
+    }
+
+    fn execute_transaction(
+        &self,
+        tx: UnsignedTransaction,
+        sender: Public,
+        block_number: Option<u64>,
+    ) -> BoxFuture<DryRun> {
+        if !self.enable_dry_run {
+            return Box::new(future::err(errors::unauthorized("chain_executeTransaction")))
+        }
+        let client = Arc::clone(&self.client);
+        self.executor.spawn(move || {
+            let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+            let (tx, seq): (IncompleteTransaction, Option<u64>) = tx.try_into()?;
+            let seq = match seq {
+                Some(seq) => seq,
+                None => {
+                    let sender_address = public_to_address(&sender);
+                    let payer = client.regular_key_owner(&sender_address, block_id.into()).unwrap_or(sender_address);
+                    client.seq(&payer, block_id).ok_or_else(errors::state_not_exist)?
+                }
+            };
+            match client.dry_run_transaction(&tx.complete(seq), &sender, block_id) {
+                Ok(Some(dry_run)) => Ok(DryRun::from_core(dry_run, client.network_id())),
+                Ok(None) => Err(errors::state_not_exist()),
+                Err(err) if errors::is_kvdb_error(&err) => Err(errors::kvdb(err)),
+                Err(err) => Ok(DryRun::rejected(err.to_string())),
+            }
+        })
     }
 
     fn execute_vm(
@@ -343,7 +614,418 @@ where
             let transaction = Option::<ShardTransactionType>::from(action.clone()).unwrap();
             Ok(self.client.execute_vm(&transaction, inputs, &params, &indices).map_err(errors::core)?)
         } else {
-            Err(errors::transfer_only())
+            Err(errors::transfer_only("chain_executeVM"))
+        }
+    }
+
+    fn compute_tracker(&self, tx: UnsignedTransaction) -> Result<Option<Tracker>> {
+        let (tx, seq): (IncompleteTransaction, Option<u64>) = tx.try_into()?;
+        Ok(compute_tracker(tx, seq))
+    }
+
+    fn check_transfer_inputs(
+        &self,
+        tx: UnsignedTransaction,
+        block_number: Option<u64>,
+    ) -> BoxFuture<Vec<TransferInputCheck>> {
+        const MAX_INPUTS: usize = 256;
+
+        let client = Arc::clone(&self.client);
+        self.executor.spawn(move || {
+            let (burns, inputs) = match tx.action.try_into().map_err(errors::conversion)? {
+                Action::TransferAsset {
+                    burns,
+                    inputs,
+                    ..
+                } => (burns, inputs),
+                _ => return Err(errors::transfer_only("chain_checkTransferInputs")),
+            };
+            if inputs.len() + burns.len() > MAX_INPUTS {
+                return Err(errors::too_many_inputs(MAX_INPUTS))
+            }
+            let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+            let all_inputs = [inputs.as_slice(), burns.as_slice()].concat();
+            let checks = client
+                .check_transfer_inputs(&all_inputs, block_id)
+                .map_err(errors::transaction_state)?
+                .ok_or_else(errors::state_not_exist)?;
+            Ok(all_inputs
+                .iter()
+                .zip(checks)
+                .enumerate()
+                .map(|(i, (input, check))| TransferInputCheck::from_core(&input.prev_out, i >= inputs.len(), check))
+                .collect())
+        })
+    }
+}
+
+/// The tracker of the transaction if it's a shard transaction.
+/// The tracker of WrapCCC is the hash of the whole transaction, so it's None if the seq is not given.
+fn compute_tracker(tx: IncompleteTransaction, seq: Option<u64>) -> Option<Tracker> {
+    let seq = match tx.action {
+        Action::WrapCCC {
+            ..
+        } => seq?,
+        // The trackers of the other transactions don't depend on the seq.
+        _ => seq.unwrap_or(0),
+    };
+    tx.complete(seq).tracker()
+}
+
+/// The params that the block with given number is verified with.
+/// Unlike other RPCs, use the latest parameters if the block number is `null`.
+/// Fails if the block is not imported yet but its header is already downloaded from the peers,
+/// so that the caller can tell it from a block that doesn't exist.
+fn check_synced<C: BlockChainClient>(
+    client: &C,
+    sync_status: &BlockSyncStatus,
+    block_number: BlockNumber,
+) -> Result<()> {
+    let best_block_number = client.chain_info().best_block_number;
+    let best_known_number = sync_status.best_known_number();
+    if best_block_number < block_number && block_number <= best_known_number {
+        return Err(errors::not_yet_synced(best_block_number, best_known_number))
+    }
+    Ok(())
+}
+
+fn params_for_fees<C: EngineInfo>(client: &C, block_number: Option<u64>) -> Option<CommonParams> {
+    if block_number == Some(0) {
+        return None
+    }
+    let block_id = block_number.map(|n| (n - 1).into()).unwrap_or(BlockId::Latest);
+    client.common_params(block_id)
+}
+
+/// Returns the average body size of the recent blocks except the genesis.
+fn average_body_size<C: BlockChainClient>(client: &C, best_block_number: BlockNumber) -> usize {
+    let from = best_block_number.saturating_sub(FEE_ESTIMATE_SAMPLE_BLOCKS - 1).max(1);
+    let sizes: Vec<usize> = (from..=best_block_number)
+        .filter_map(|number| client.block_body(&number.into()))
+        .filter_map(|body| body.rlp().at(0).ok().map(|transactions| transactions.as_raw().len()))
+        .collect();
+    if sizes.is_empty() {
+        return 0
+    }
+    sizes.iter().sum::<usize>() / sizes.len()
+}
+
+fn min_transaction_fees(params: &CommonParams) -> BTreeMap<String, u64> {
+    ActionType::ALL
+        .iter()
+        .map(|action_type| (action_type.name().to_string(), params.min_transaction_cost(*action_type)))
+        .collect()
+}
+
+/// Reads at most `MAX_HEADERS_IN_RANGE` headers, and stops before their total JSON size exceeds `max_size`.
+/// The first header is always included so that the caller can make progress.
+/// Each header is converted to its JSON value once, and the value is measured and returned as it is.
+fn headers_in_range<C: BlockChainClient + EngineInfo>(
+    client: &C,
+    from: BlockId,
+    count: u64,
+    reverse: bool,
+    max_size: usize,
+) -> Vec<Value> {
+    let network_id = client.network_id();
+    let headers = client.headers_in_range(&from, count.min(MAX_HEADERS_IN_RANGE), reverse);
+    let mut size = ByteCounter::default();
+    let mut result = Vec::with_capacity(headers.len());
+    for header in headers {
+        let header = serde_json::to_value(Header::from_core(header.decode(), network_id))
+            .expect("A header is always serialized");
+        serde_json::to_writer(&mut size, &header).expect("The counter never fails");
+        if !result.is_empty() && size.0 > max_size {
+            break
         }
+        result.push(header);
+    }
+    result
+}
+
+/// Counts the bytes written without keeping them.
+#[derive(Default)]
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn tree_route<C: BlockChainClient>(
+    client: &C,
+    from: BlockHash,
+    to: BlockHash,
+    max_length: usize,
+) -> Result<Option<TreeRoute>> {
+    let (from_number, to_number) = match (client.block_number(&from.into()), client.block_number(&to.into())) {
+        (Some(from_number), Some(to_number)) => (from_number, to_number),
+        _ => return Ok(None),
+    };
+    // The route is at least as long as the distance between the heights, so it's checked before walking the route.
+    let distance = if from_number > to_number {
+        from_number - to_number
+    } else {
+        to_number - from_number
+    };
+    if distance > max_length as u64 {
+        return Err(errors::tree_route_too_long(max_length))
+    }
+    let route = match client.tree_route(from, to) {
+        Some(route) => route,
+        None => return Ok(None),
+    };
+    if route.enacted.len() + route.retracted.len() > max_length {
+        return Err(errors::tree_route_too_long(max_length))
+    }
+    // The canonical bodies are pruned from the lowest, so checking the children of the ancestor is enough.
+    let crosses_pruned_boundary = route
+        .enacted
+        .first()
+        .into_iter()
+        .chain(route.retracted.last())
+        .any(|hash| client.is_block_body_pruned(&BlockId::Hash(*hash)));
+    Ok(Some(TreeRoute::from_core(route, crosses_pruned_boundary)))
+}
+
+fn finalization<C: BlockChainClient>(client: &C, block_hash: BlockHash) -> Option<Finalization> {
+    let number = client.block_number(&block_hash.into())?;
+    let chain_info = client.chain_info();
+    let is_canonical = client.block_hash(&number.into()) == Some(block_hash);
+    if !is_canonical || number > chain_info.best_block_number {
+        return Some(Finalization {
+            finalized: false,
+            confirmations: 0,
+        })
+    }
+    Some(Finalization {
+        finalized: number <= chain_info.finalized_block_number,
+        confirmations: chain_info.best_block_number - number + 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ccore::{BlockChainClient, BlockChainTrait, EngineInfo, Scheme, TestBlockChainClient};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn transaction_count_of_genesis_block() {
+        let client = TestBlockChainClient::new();
+        assert_eq!(Some(0), client.block_transaction_count(&BlockId::Number(0)));
+        assert_eq!(Some(0), client.block_transaction_count(&BlockId::Hash(client.genesis_hash)));
+    }
+
+    #[test]
+    fn transaction_count_of_populated_block() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(1, 3);
+        let hash = client.chain_info().best_block_hash;
+        assert_eq!(Some(3), client.block_transaction_count(&BlockId::Number(1)));
+        assert_eq!(Some(3), client.block_transaction_count(&BlockId::Hash(hash)));
+    }
+
+    #[test]
+    fn transaction_count_of_missing_block_is_null() {
+        let client = TestBlockChainClient::new();
+        assert_eq!(None, client.block_transaction_count(&BlockId::Number(1)));
+        assert_eq!(None, client.block_transaction_count(&BlockId::Hash(H256::random().into())));
+    }
+
+    #[test]
+    fn block_size_is_the_length_of_the_stored_rlp() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(1, 3);
+        let encoded = client.block(&BlockId::Number(1)).unwrap();
+        let size = encoded.rlp().as_raw().len();
+        let block = Block::from_encoded(encoded, client.network_id());
+        assert_eq!(serde_json::to_value(&block).unwrap()["size"], size);
+    }
+
+    #[test]
+    fn finalization_counts_confirmations_of_canonical_blocks() {
+        let client = TestBlockChainClient::new_with_scheme(Scheme::new_test_cuckoo());
+        client.add_blocks(8, 0);
+        let finalized = client.block_hash(&BlockId::Number(3)).unwrap();
+        let pending = client.block_hash(&BlockId::Number(4)).unwrap();
+        assert_eq!(
+            Some(Finalization {
+                finalized: true,
+                confirmations: 6,
+            }),
+            finalization(&client, finalized)
+        );
+        assert_eq!(
+            Some(Finalization {
+                finalized: false,
+                confirmations: 5,
+            }),
+            finalization(&client, pending)
+        );
+
+        let side = client.add_block_on(client.genesis_hash, None, vec![]);
+        assert_eq!(
+            Some(Finalization {
+                finalized: false,
+                confirmations: 0,
+            }),
+            finalization(&client, side)
+        );
+        assert_eq!(None, finalization(&client, H256::random().into()));
+    }
+
+    #[test]
+    fn min_transaction_fees_follow_the_changed_params() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(3, 0);
+        let genesis_params = client.common_params(BlockId::Number(0)).unwrap();
+        let mut params: Params = genesis_params.into();
+        params.min_pay_cost = (genesis_params.min_pay_transaction_cost() + 10).into();
+        let changed_params = CommonParams::try_from(params).unwrap();
+        client.set_common_params(client.block_hash(&BlockId::Number(2)).unwrap(), changed_params);
+
+        // The params changed in block 2 are applied from block 3.
+        let before = min_transaction_fees(&params_for_fees(&client, Some(2)).unwrap());
+        let after = min_transaction_fees(&params_for_fees(&client, Some(3)).unwrap());
+        assert_eq!(Some(&genesis_params.min_pay_transaction_cost()), before.get("pay"));
+        assert_eq!(Some(&changed_params.min_pay_transaction_cost()), after.get("pay"));
+        assert_eq!(before.get("transferAsset"), after.get("transferAsset"));
+        assert_eq!(after, min_transaction_fees(&params_for_fees(&client, None).unwrap()));
+        assert_eq!(None, params_for_fees(&client, Some(0)));
+        assert_eq!(ActionType::ALL.len(), after.len());
+    }
+
+    #[test]
+    fn headers_in_range_is_capped_by_count() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(MAX_HEADERS_IN_RANGE as usize + 10, 0);
+
+        let headers = headers_in_range(&client, BlockId::Earliest, u64::max_value(), false, usize::max_value());
+        assert_eq!(MAX_HEADERS_IN_RANGE as usize, headers.len());
+        let headers = headers_in_range(&client, BlockId::Latest, 3, true, usize::max_value());
+        let numbers: Vec<_> = headers.iter().map(|header| header["number"].as_u64().unwrap()).collect();
+        let best = client.chain_info().best_block_number;
+        assert_eq!(vec![best, best - 1, best - 2], numbers);
+    }
+
+    #[test]
+    fn headers_in_range_is_capped_by_size() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(10, 0);
+        let header_size =
+            serde_json::to_vec(&headers_in_range(&client, BlockId::Number(1), 1, false, usize::max_value())[0])
+                .unwrap()
+                .len();
+
+        assert_eq!(3, headers_in_range(&client, BlockId::Number(1), 5, false, header_size * 3).len());
+        assert_eq!(1, headers_in_range(&client, BlockId::Number(1), 5, false, 0).len());
+        assert_eq!(0, headers_in_range(&client, BlockId::Number(11), 5, false, usize::max_value()).len());
+    }
+
+    #[test]
+    fn tree_route_is_capped() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(10, 0);
+        let genesis = client.genesis_hash;
+        let fifth = client.block_hash(&BlockId::Number(5)).unwrap();
+        let best = client.chain_info().best_block_hash;
+        // A side block on the genesis makes a route that is longer than the distance between the heights.
+        let side = client.add_block_on(genesis, None, vec![]);
+
+        let route = tree_route(&client, genesis, fifth, 5).unwrap().unwrap();
+        assert_eq!(5, serde_json::to_value(&route).unwrap()["enacted"].as_array().unwrap().len());
+        assert!(tree_route(&client, genesis, best, 5).is_err());
+        assert!(tree_route(&client, best, genesis, 5).is_err());
+        assert!(tree_route(&client, side, fifth, 5).is_err());
+        assert!(tree_route(&client, side, fifth, 6).unwrap().is_some());
+        assert!(tree_route(&client, genesis, H256::random().into(), 5).unwrap().is_none());
+    }
+
+    #[test]
+    fn compute_tracker_needs_seq_only_for_wrap_ccc() {
+        let incomplete = |action| IncompleteTransaction {
+            fee: 10,
+            network_id: "tc".into(),
+            action,
+        };
+        let transfer = Action::TransferAsset {
+            network_id: "tc".into(),
+            burns: vec![],
+            inputs: vec![],
+            outputs: vec![],
+            metadata: "".into(),
+            approvals: vec![],
+            expiration: None,
+        };
+        let tracker = Option::<ShardTransactionType>::from(transfer.clone()).unwrap().tracker();
+        assert_eq!(Some(tracker), compute_tracker(incomplete(transfer.clone()), None));
+        assert_eq!(Some(tracker), compute_tracker(incomplete(transfer), Some(3)));
+
+        let wrap_ccc = Action::WrapCCC {
+            shard_id: 0,
+            lock_script_hash: H160::random(),
+            parameters: vec![],
+            quantity: 30,
+            payer: Default::default(),
+        };
+        assert_eq!(None, compute_tracker(incomplete(wrap_ccc.clone()), None));
+        let tx = incomplete(wrap_ccc.clone()).complete(3);
+        assert_eq!(Some((*tx.hash()).into()), compute_tracker(incomplete(wrap_ccc), Some(3)));
+
+        let pay = Action::Pay {
+            receiver: Default::default(),
+            quantity: 10,
+            memo: None,
+        };
+        assert_eq!(None, compute_tracker(incomplete(pay), Some(3)));
+    }
+
+    #[test]
+    fn block_beyond_the_best_is_not_yet_synced_if_the_peers_have_it() {
+        let client = TestBlockChainClient::new();
+        client.add_blocks(3, 0);
+        let sync_status = BlockSyncStatus::default();
+        sync_status.set_best_known_number(10);
+
+        let error = check_synced(&client, &sync_status, 5).unwrap_err();
+        assert_eq!(jsonrpc_core::ErrorCode::ServerError(-32064), error.code);
+        assert_eq!(
+            Some(serde_json::json!({
+                "bestBlockNumber": 3,
+                "bestKnownNumber": 10,
+            })),
+            error.data
+        );
+        assert!(check_synced(&client, &sync_status, 10).is_err());
+        assert_eq!(None, client.block_hash(&BlockId::Number(5)));
+
+        assert!(check_synced(&client, &sync_status, 3).is_ok());
+        // No peer has it either, so it doesn't exist.
+        assert!(check_synced(&client, &sync_status, 11).is_ok());
+        assert_eq!(None, client.block(&BlockId::Hash(H256::random().into())));
+    }
+
+    #[test]
+    fn sync_status_of_lagging_client() {
+        let status = serde_json::to_value(&SyncStatus::new(3, 10)).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "bestBlockNumber": 3,
+                "bestKnownNumber": 10,
+                "isSyncing": true,
+                "estimatedRemainingBlocks": 7,
+            }),
+            status
+        );
+        // A node without peers doesn't know any block beyond its best.
+        assert_eq!(SyncStatus::new(3, 3), SyncStatus::new(3, 0));
+        assert_eq!(serde_json::to_value(&SyncStatus::new(3, 0)).unwrap()["isSyncing"], false);
     }
 }
@@ -16,21 +16,25 @@
 
 use super::super::errors;
 use super::super::traits::Devel;
-use super::super::types::{TPSTestOption, TPSTestSetting};
+use super::super::types::{
+    ImportMetrics, ShardEntries, Snapshot, StakeAudit, StateCacheStats, TPSTestOption, TPSTestSetting, Timer,
+};
+use crate::executor::ExecutorHandle;
 use ccore::{
-    BlockId, DatabaseClient, EngineClient, EngineInfo, MinerService, MiningBlockChainClient, SignedTransaction,
-    TermInfo, COL_STATE,
+    stake, AssetClient, BackupClient, BlockId, DatabaseClient, EngineClient, EngineInfo, MinerService,
+    MiningBlockChainClient, SignedTransaction, StateInfo, TermInfo, COL_STATE,
 };
 use ccrypto::Blake;
 use cjson::bytes::Bytes;
 use ckey::{Address, KeyPair, Private};
 use cnetwork::{unbounded_event_callback, EventSender, IntoSocketAddr};
-use csync::BlockSyncEvent;
+use csync::{BlockSyncEvent, SnapshotService};
+use ctimer::TimerMonitor;
 use ctypes::transaction::{
     Action, AssetMintOutput, AssetOutPoint, AssetTransferInput, AssetTransferOutput, Transaction,
 };
-use ctypes::{BlockHash, Tracker, TxHash};
-use jsonrpc_core::Result;
+use ctypes::{BlockHash, ShardId, Tracker, TxHash};
+use jsonrpc_core::{BoxFuture, Result};
 use kvdb::KeyValueDB;
 use primitives::{H160, H256};
 use rand::rngs::SmallRng;
@@ -44,36 +48,63 @@ use std::time::{Duration, Instant};
 use std::vec::Vec;
 use time::PreciseTime;
 
+/// The maximum number of entries returned by `devel_getShardStateEntries` at once.
+const MAX_SHARD_STATE_ENTRIES: usize = 1024;
+
 pub struct DevelClient<C, M> {
     client: Arc<C>,
     db: Arc<dyn KeyValueDB>,
     miner: Arc<M>,
     block_sync: Option<EventSender<BlockSyncEvent>>,
+    snapshot_service: Option<Arc<SnapshotService>>,
+    timer_monitor: TimerMonitor,
+    executor: ExecutorHandle,
 }
 
 impl<C, M> DevelClient<C, M>
 where
     C: DatabaseClient,
 {
-    pub fn new(client: Arc<C>, miner: Arc<M>, block_sync: Option<EventSender<BlockSyncEvent>>) -> Self {
+    pub fn new(
+        client: Arc<C>,
+        miner: Arc<M>,
+        block_sync: Option<EventSender<BlockSyncEvent>>,
+        snapshot_service: Option<Arc<SnapshotService>>,
+        timer_monitor: TimerMonitor,
+        executor: ExecutorHandle,
+    ) -> Self {
         let db = client.database();
         Self {
             client,
             db,
             miner,
             block_sync,
+            snapshot_service,
+            timer_monitor,
+            executor,
         }
     }
 }
 
 impl<C, M> Devel for DevelClient<C, M>
 where
-    C: DatabaseClient + EngineInfo + EngineClient + MiningBlockChainClient + TermInfo + 'static,
+    C: DatabaseClient
+        + BackupClient
+        + EngineInfo
+        + EngineClient
+        + MiningBlockChainClient
+        + TermInfo
+        + AssetClient
+        + StateInfo
+        + 'static,
     M: MinerService + 'static,
 {
-    fn get_state_trie_keys(&self, offset: usize, limit: usize) -> Result<Vec<H256>> {
-        let iter = self.db.iter(COL_STATE);
-        Ok(iter.skip(offset).take(limit).map(|val| H256::from(val.0.deref())).collect())
+    fn get_state_trie_keys(&self, offset: usize, limit: usize) -> BoxFuture<Vec<H256>> {
+        let db = Arc::clone(&self.db);
+        self.executor.spawn(move || {
+            let iter = db.iter(COL_STATE);
+            Ok(iter.skip(offset).take(limit).map(|val| H256::from(val.0.deref())).collect())
+        })
     }
 
     fn get_state_trie_value(&self, key: H256) -> Result<Vec<Bytes>> {
@@ -86,6 +117,22 @@ where
         }
     }
 
+    fn get_shard_state_entries(
+        &self,
+        shard_id: ShardId,
+        from_key: Option<H256>,
+        limit: usize,
+    ) -> Result<Option<ShardEntries>> {
+        let from_key = from_key.unwrap_or_else(H256::zero);
+        let limit = limit.min(MAX_SHARD_STATE_ENTRIES);
+        let network_id = self.client.network_id();
+        Ok(self
+            .client
+            .get_shard_entries(shard_id, &from_key, limit, BlockId::Latest)
+            .map_err(errors::transaction_state)?
+            .map(|(entries, next_key)| ShardEntries::from_core(entries, next_key, network_id)))
+    }
+
     fn start_sealing(&self) -> Result<()> {
         self.miner.start_sealing(&*self.client);
         Ok(())
@@ -126,6 +173,57 @@ where
         }
     }
 
+    fn get_import_metrics(&self) -> Result<Option<ImportMetrics>> {
+        Ok(self.client.import_metrics().map(ImportMetrics::from))
+    }
+
+    fn get_timers(&self) -> Result<Vec<Timer>> {
+        let now = Instant::now();
+        Ok(self.timer_monitor.snapshot().into_iter().map(|timer| Timer::new(timer, now)).collect())
+    }
+
+    fn get_state_cache_stats(&self) -> Result<Vec<StateCacheStats>> {
+        Ok(self.client.state_cache_counts().into_iter().map(StateCacheStats::from).collect())
+    }
+
+    fn audit_stake(&self, block_number: Option<u64>) -> Result<Option<StakeAudit>> {
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        let number = match self.client.block_number(&block_id) {
+            Some(number) => number,
+            None => return Ok(None),
+        };
+        let state = match self.client.state_at(number.into()) {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+        let audit = if number == 0 {
+            stake::audit_ccs(&state)
+        } else {
+            match self.client.state_at((number - 1).into()) {
+                Some(parent_state) => stake::audit_ccs_conservation(&parent_state, &state),
+                None => stake::audit_ccs(&state),
+            }
+        };
+        audit.map(|audit| Some(audit.into())).map_err(errors::stake_audit)
+    }
+
+    fn get_snapshot_list(&self) -> Result<Vec<Snapshot>> {
+        match self.snapshot_service.as_ref() {
+            Some(service) => Ok(service.snapshots().into_iter().map(Snapshot::from).collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn create_backup(&self, path: String) -> BoxFuture<BlockHash> {
+        let client = Arc::clone(&self.client);
+        self.executor.spawn(move || client.create_backup(&path).map_err(errors::backup))
+    }
+
+    fn restore_from_backup(&self, path: String) -> BoxFuture<BlockHash> {
+        let client = Arc::clone(&self.client);
+        self.executor.spawn(move || client.restore_from_backup(&path).map_err(errors::backup))
+    }
+
     fn test_tps(&self, setting: TPSTestSetting) -> Result<f64> {
         let common_params = self.client.common_params(BlockId::Latest).unwrap();
         let mint_fee = common_params.min_asset_mint_cost();
@@ -153,6 +251,7 @@ where
                     action: Action::Pay {
                         receiver: $address,
                         quantity: $quantity,
+                        memo: None,
                     },
                 }
             };
@@ -16,8 +16,8 @@
 
 use super::super::errors;
 use super::super::traits::Mempool;
-use super::super::types::{MemPoolMinFees, PendingTransactions};
-use ccore::{BlockChainClient, EngineInfo, MiningBlockChainClient, SignedTransaction};
+use super::super::types::{MemPoolMinFees, PendingBySender, PendingTransactions, ReadyTransactions, SeqGapInfo};
+use ccore::{BlockChainClient, BlockId, EngineInfo, MiningBlockChainClient, SignedTransaction, TransactionAttempt};
 use cjson::bytes::Bytes;
 use ckey::{Address, PlatformAddress};
 use ctypes::{Tracker, TxHash};
@@ -55,12 +55,25 @@ where
             .map(Into::into)
     }
 
+    fn send_signed_transaction_with_expiry(&self, raw: Bytes, expiry: u64) -> Result<TxHash> {
+        let tx = Rlp::new(&raw.into_vec()).as_val().map_err(|e| errors::rlp(&e))?;
+        let signed = SignedTransaction::try_new(tx).map_err(errors::transaction_core)?;
+        let hash = signed.hash();
+        self.client.queue_own_transaction_with_expiry(signed, expiry).map_err(errors::transaction_core)?;
+        Ok(hash)
+    }
+
     fn get_transaction_results_by_tracker(&self, tracker: Tracker) -> Result<Vec<bool>> {
         Ok(self
             .client
-            .error_hints_by_tracker(&tracker)
+            .transaction_attempts_by_tracker(&tracker)
             .into_iter()
-            .map(|(_hash, error_hint)| error_hint.is_none())
+            .map(|attempt| match attempt {
+                TransactionAttempt::Included(_) => true,
+                TransactionAttempt::Failed {
+                    ..
+                } => false,
+            })
             .collect())
     }
 
@@ -86,6 +99,23 @@ where
         }
     }
 
+    fn get_ready_transactions(
+        &self,
+        max_count: Option<usize>,
+        max_size: Option<usize>,
+        cursor: Option<Bytes>,
+    ) -> Result<ReadyTransactions> {
+        let cursor = match cursor {
+            Some(cursor) => Some(Rlp::new(&cursor.into_vec()).as_val().map_err(|e| errors::rlp(&e))?),
+            None => None,
+        };
+        let max_size = match max_size {
+            Some(max_size) => max_size,
+            None => self.client.common_params(BlockId::Latest).unwrap().max_body_size(),
+        };
+        Ok(self.client.ready_transactions_limited(max_count.unwrap_or(::std::usize::MAX), max_size, cursor).into())
+    }
+
     fn get_pending_transactions_count(
         &self,
         from: Option<u64>,
@@ -99,6 +129,28 @@ where
         }
     }
 
+    fn get_pending_by_sender(&self, address: PlatformAddress) -> Result<PendingBySender> {
+        let address = address.try_address().map_err(errors::core)?;
+        Ok(self.client.pending_transactions_by_signer(address).into())
+    }
+
+    fn remove_transaction(&self, transaction_hash: TxHash) -> Result<bool> {
+        match self.client.remove_local_transaction(&transaction_hash) {
+            Some(true) => Ok(true),
+            Some(false) => Err(errors::not_local_transaction()),
+            None => Ok(false),
+        }
+    }
+
+    fn get_seq_gap_info(&self, address: PlatformAddress) -> Result<SeqGapInfo> {
+        let address = address.try_address().map_err(errors::core)?;
+        Ok(self.client.seq_gap_info(address).into())
+    }
+
+    fn drop_future_transactions(&self, address: PlatformAddress, from_seq: u64) -> Result<Vec<TxHash>> {
+        let address = address.try_address().map_err(errors::core)?;
+        self.client.drop_future_transactions(address, from_seq).map_err(|_| errors::not_local_transaction())
+    }
 
     fn get_banned_accounts(&self) -> Result<Vec<PlatformAddress>> {
         let malicious_user_vec = self.client.get_malicious_users();
@@ -137,3 +189,144 @@ where
         Ok(MemPoolMinFees::from(self.client.mem_pool_min_fees()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ccore::{MinerService, TestBlockChainClient};
+    use ckey::{Generator, KeyPair, Random};
+    use ctypes::transaction::{Action, Transaction};
+
+    fn pay(keypair: &KeyPair, seq: u64) -> SignedTransaction {
+        let tx = Transaction {
+            seq,
+            fee: 10,
+            network_id: Default::default(),
+            action: Action::Pay {
+                receiver: Address::random(),
+                quantity: 0,
+                memo: None,
+            },
+        };
+        SignedTransaction::new_with_sign(tx, keypair.private())
+    }
+
+    fn queue_gapped_transactions(client: &TestBlockChainClient, keypair: &KeyPair) -> Vec<TxHash> {
+        // Blocks opened by the test client don't have the balance, so sealing would drop the transactions.
+        client.miner.stop_sealing();
+        client.set_balance(keypair.address(), 1_000_000);
+        [0, 1, 3, 4]
+            .iter()
+            .map(|seq| {
+                let tx = pay(keypair, *seq);
+                let hash = tx.hash();
+                client.queue_own_transaction(tx).unwrap();
+                hash
+            })
+            .collect()
+    }
+
+    #[test]
+    fn transactions_after_seq_gap_are_labeled_future() {
+        let client = Arc::new(TestBlockChainClient::new());
+        let keypair = Random.generate().unwrap();
+        let hashes = queue_gapped_transactions(&client, &keypair);
+        let mempool = MempoolClient::new(Arc::clone(&client));
+
+        let address = PlatformAddress::new_v1(client.network_id(), keypair.address());
+        let pending = serde_json::to_value(mempool.get_pending_by_sender(address).unwrap()).unwrap();
+        let current = pending["current"].as_array().unwrap();
+        let future = pending["future"].as_array().unwrap();
+
+        assert_eq!(2, current.len());
+        for (entry, (seq, hash)) in current.iter().zip([0, 1].iter().zip(&hashes[..2])) {
+            assert_eq!(*seq, entry["seq"]);
+            assert_eq!(serde_json::to_value(hash).unwrap(), entry["hash"]);
+            assert_eq!("local", entry["origin"]);
+            assert_eq!(None, entry.get("reason"));
+        }
+        assert_eq!(2, future.len());
+        for (entry, (seq, hash)) in future.iter().zip([3, 4].iter().zip(&hashes[2..])) {
+            assert_eq!(*seq, entry["seq"]);
+            assert_eq!(serde_json::to_value(hash).unwrap(), entry["hash"]);
+            assert_eq!("seqGap", entry["reason"]["type"]);
+            assert_eq!(2, entry["reason"]["expectedSeq"]);
+        }
+    }
+
+    #[test]
+    fn pending_of_unknown_sender_is_empty() {
+        let client = Arc::new(TestBlockChainClient::new());
+        let mempool = MempoolClient::new(Arc::clone(&client));
+
+        let address = PlatformAddress::new_v1(client.network_id(), Address::random());
+        let pending = serde_json::to_value(mempool.get_pending_by_sender(address).unwrap()).unwrap();
+        assert_eq!(0, pending["current"].as_array().unwrap().len());
+        assert_eq!(0, pending["future"].as_array().unwrap().len());
+    }
+
+    #[test]
+    fn removing_local_transaction_moves_the_rest_to_future() {
+        let client = Arc::new(TestBlockChainClient::new());
+        let keypair = Random.generate().unwrap();
+        let hashes = queue_gapped_transactions(&client, &keypair);
+        let mempool = MempoolClient::new(Arc::clone(&client));
+
+        assert_eq!(Ok(true), mempool.remove_transaction(hashes[1]));
+        assert_eq!(Ok(false), mempool.remove_transaction(hashes[1]));
+
+        let entries = client.pending_transactions_by_signer(&keypair.address());
+        assert_eq!(vec![0, 3, 4], entries.iter().map(|entry| entry.seq).collect::<Vec<_>>());
+        assert_eq!(None, entries[0].future_reason);
+        assert_eq!(
+            Some(ccore::FutureReason::SeqGap {
+                expected_seq: 1
+            }),
+            entries[1].future_reason
+        );
+    }
+
+    #[test]
+    fn seq_gap_is_reported_and_the_orphans_are_dropped() {
+        let client = Arc::new(TestBlockChainClient::new());
+        let keypair = Random.generate().unwrap();
+        let hashes = queue_gapped_transactions(&client, &keypair);
+        let mempool = MempoolClient::new(Arc::clone(&client));
+        let address = PlatformAddress::new_v1(client.network_id(), keypair.address());
+
+        let info = serde_json::to_value(mempool.get_seq_gap_info(address).unwrap()).unwrap();
+        assert_eq!(0, info["chainSeq"]);
+        assert_eq!(0, info["readyFrom"]);
+        assert_eq!(2, info["readyTo"]);
+        assert_eq!(2, info["firstGap"]);
+        let orphans = info["orphans"].as_array().unwrap();
+        assert_eq!(2, orphans.len());
+        for (orphan, (seq, hash)) in orphans.iter().zip([3, 4].iter().zip(&hashes[2..])) {
+            assert_eq!(*seq, orphan["seq"]);
+            assert_eq!(serde_json::to_value(hash).unwrap(), orphan["hash"]);
+        }
+
+        assert_eq!(Ok(vec![hashes[3]]), mempool.drop_future_transactions(address, 4));
+        assert_eq!(Ok(vec![hashes[2]]), mempool.drop_future_transactions(address, 0));
+        assert_eq!(Ok(vec![]), mempool.drop_future_transactions(address, 0));
+
+        let entries = client.pending_transactions_by_signer(&keypair.address());
+        assert_eq!(vec![0, 1], entries.iter().map(|entry| entry.seq).collect::<Vec<_>>());
+        let info = client.seq_gap_info(&keypair.address());
+        assert_eq!(0..2, info.ready);
+        assert_eq!(None, info.first_gap);
+        assert!(info.orphans.is_empty());
+    }
+
+    #[test]
+    fn external_transaction_cannot_be_removed() {
+        let client = Arc::new(TestBlockChainClient::new());
+        client.miner.stop_sealing();
+        let hash = client.insert_transaction_to_pool();
+        let mempool = MempoolClient::new(Arc::clone(&client));
+
+        let err = mempool.remove_transaction(hash).unwrap_err();
+        assert_eq!(jsonrpc_core::ErrorCode::ServerError(-32054), err.code);
+        assert_eq!(1, client.count_pending_transactions(0..u64::max_value()));
+    }
+}
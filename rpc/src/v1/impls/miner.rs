@@ -16,7 +16,7 @@
 
 use super::super::errors;
 use super::super::traits::Miner;
-use super::super::types::Work;
+use super::super::types::{MiningStatus, PendingSeal, Work};
 use ccore::block::IsBlock;
 use ccore::{EngineClient, EngineInfo, MinerService, MiningBlockChainClient, TermInfo};
 use cjson::bytes::Bytes;
@@ -73,4 +73,34 @@ where
         let seal = seal.iter().cloned().map(Into::into).collect();
         Ok(self.miner.submit_seal(&*self.client, pow_hash, seal).is_ok())
     }
+
+    fn get_pending_seal(&self) -> Result<PendingSeal> {
+        if !self.miner.can_seal_externally() {
+            cwarn!(MINER, "Cannot give a pending seal - external sealing is disabled.");
+            return Err(errors::external_sealing_disabled())
+        }
+        if self.miner.authoring_params().author.is_zero() {
+            cwarn!(MINER, "Cannot give a pending seal - no author is configured. Use --engine-signer to configure!");
+            return Err(errors::no_author())
+        }
+        self.miner
+            .pending_seal(&*self.client)
+            .map(|seal| PendingSeal::from_core(seal, self.client.network_id()))
+            .ok_or_else(|| errors::internal("No work found.", ""))
+    }
+
+    fn submit_signed_seal(&self, block_hash: BlockHash, seal: Vec<Bytes>) -> Result<()> {
+        if !self.miner.can_seal_externally() {
+            cwarn!(MINER, "Cannot submit a seal - external sealing is disabled.");
+            return Err(errors::external_sealing_disabled())
+        }
+        let seal: Vec<_> = seal.into_iter().map(Into::into).collect();
+        self.miner.verify_external_seal(block_hash, &seal).map_err(errors::core)?;
+        self.client.submit_seal(block_hash, seal);
+        Ok(())
+    }
+
+    fn get_status(&self) -> Result<MiningStatus> {
+        Ok(MiningStatus::from_core(self.miner.status(), self.client.network_id()))
+    }
 }
@@ -15,17 +15,21 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod account;
+mod block_stream;
 mod chain;
 mod devel;
 mod engine;
 mod mempool;
 mod miner;
 mod net;
+mod subscription;
 
 pub use self::account::AccountClient;
+pub use self::block_stream::BlockStreamClient;
 pub use self::chain::ChainClient;
 pub use self::devel::DevelClient;
 pub use self::engine::EngineClient;
 pub use self::mempool::MempoolClient;
 pub use self::miner::MinerClient;
 pub use self::net::NetClient;
+pub use self::subscription::{SubscriptionClient, Subscriptions};
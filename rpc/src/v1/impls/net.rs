@@ -16,7 +16,7 @@
 
 use super::super::errors;
 use super::super::traits::Net;
-use super::super::types::FilterStatus;
+use super::super::types::{ExtensionState, FilterStatus};
 use cidr::IpCidr;
 use ckey::Public;
 use cnetwork::{NetworkControl, SocketAddr};
@@ -78,6 +78,21 @@ impl Net for NetClient {
         Ok(peers.into_iter().map(Into::into).collect())
     }
 
+    fn add_trusted_peer(&self, address: IpAddr, port: u16) -> Result<()> {
+        self.network_control.add_trusted_peer(SocketAddr::new(address, port)).map_err(|e| errors::network_control(&e))
+    }
+
+    fn remove_trusted_peer(&self, address: IpAddr, port: u16) -> Result<bool> {
+        self.network_control
+            .remove_trusted_peer(&SocketAddr::new(address, port))
+            .map_err(|e| errors::network_control(&e))
+    }
+
+    fn get_trusted_peers(&self) -> Result<Vec<net::SocketAddr>> {
+        let peers = self.network_control.trusted_peers().map_err(|e| errors::network_control(&e))?;
+        Ok(peers.into_iter().map(Into::into).collect())
+    }
+
     fn add_to_whitelist(&self, addr: IpCidr, tag: Option<String>) -> Result<()> {
         self.network_control.add_to_whitelist(addr, tag).map_err(|e| errors::network_control(&e))
     }
@@ -129,4 +144,17 @@ impl Net for NetClient {
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>> {
         Ok(self.network_control.recent_network_usage().map_err(|e| errors::network_control(&e))?)
     }
+
+    fn disable_extension(&self, name: String) -> Result<()> {
+        self.network_control.disable_extension(&name).map_err(|e| errors::network_control(&e))
+    }
+
+    fn enable_extension(&self, name: String) -> Result<()> {
+        self.network_control.enable_extension(&name).map_err(|e| errors::network_control(&e))
+    }
+
+    fn get_extensions(&self) -> Result<Vec<ExtensionState>> {
+        let states = self.network_control.extension_states().map_err(|e| errors::network_control(&e))?;
+        Ok(states.into_iter().map(From::from).collect())
+    }
 }
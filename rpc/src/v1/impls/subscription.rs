@@ -0,0 +1,370 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::super::errors;
+use super::super::traits::Subscription;
+use super::super::types::{Header, SubscriptionKind};
+use super::super::Metadata;
+use ccore::{BlockChainTrait, ChainNotify, EngineInfo};
+use ctypes::{BlockHash, TxHash};
+use jsonrpc_core::futures::Future;
+use jsonrpc_core::Result;
+use jsonrpc_pubsub::typed::{Sink, Subscriber};
+use jsonrpc_pubsub::{PubSubMetadata, Session, SubscriptionId};
+use parking_lot::Mutex;
+use serde_json::{self, Value};
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::thread;
+
+struct ActiveSubscription {
+    kind: SubscriptionKind,
+    /// The connection that made the subscription.
+    connection_id: u64,
+    sink: Sink<Value>,
+}
+
+/// Subscriptions of all connections.
+/// Register it with the client and the miner to feed the subscribers.
+pub struct Subscriptions<C> {
+    client: Arc<C>,
+    max_subscriptions_per_connection: usize,
+    next_id: Mutex<u64>,
+    active: Arc<Mutex<HashMap<SubscriptionId, ActiveSubscription>>>,
+    // Notifications are delivered by a dedicated thread so that a slow client doesn't block block import.
+    sender: Mutex<Sender<(Sink<Value>, Value)>>,
+}
+
+impl<C> Subscriptions<C>
+where
+    C: BlockChainTrait + EngineInfo,
+{
+    pub fn new(client: Arc<C>, max_subscriptions_per_connection: usize) -> Self {
+        let (sender, receiver) = channel::<(Sink<Value>, Value)>();
+        thread::Builder::new()
+            .name("RPC subscriptions".to_string())
+            .spawn(move || {
+                for (sink, value) in receiver {
+                    if sink.notify(Ok(value)).wait().is_err() {
+                        cdebug!(RPC, "Cannot deliver a notification to a closed connection");
+                    }
+                }
+            })
+            .expect("Cannot spawn the subscription thread");
+
+        Subscriptions {
+            client,
+            max_subscriptions_per_connection,
+            next_id: Mutex::new(0),
+            active: Arc::new(Mutex::new(HashMap::new())),
+            sender: Mutex::new(sender),
+        }
+    }
+
+    /// Notifies the subscribers of newly queued transactions.
+    pub fn notify_pending_transactions(&self, hashes: &[TxHash]) {
+        let sinks = self.sinks(SubscriptionKind::NewPendingTransactions);
+        for hash in hashes {
+            self.send(&sinks, serde_json::to_value(hash).expect("A hash can always be serialized"));
+        }
+    }
+
+    fn add(&self, session: &Session, connection_id: u64, subscriber: Subscriber<Value>, kind: SubscriptionKind) {
+        let mut active = self.active.lock();
+        let count = active.values().filter(|subscription| subscription.connection_id == connection_id).count();
+        if count >= self.max_subscriptions_per_connection {
+            let _ = subscriber.reject(errors::too_many_subscriptions(self.max_subscriptions_per_connection));
+            return
+        }
+
+        let id = {
+            let mut next_id = self.next_id.lock();
+            *next_id += 1;
+            SubscriptionId::Number(*next_id)
+        };
+        if let Ok(sink) = subscriber.assign_id(id.clone()) {
+            active.insert(id.clone(), ActiveSubscription {
+                kind,
+                connection_id,
+                sink,
+            });
+            // The session is dropped when the connection is closed.
+            let active = Arc::downgrade(&self.active);
+            session.on_drop(move || {
+                if let Some(active) = active.upgrade() {
+                    active.lock().remove(&id);
+                }
+            });
+        }
+    }
+
+    /// Removes the subscription if it belongs to the connection.
+    /// `None` is given when the session of the subscription is dropped.
+    fn remove(&self, id: &SubscriptionId, connection_id: Option<u64>) -> bool {
+        let mut active = self.active.lock();
+        match (active.get(id), connection_id) {
+            (Some(subscription), Some(connection_id)) if subscription.connection_id != connection_id => false,
+            (Some(_), _) => active.remove(id).is_some(),
+            (None, _) => false,
+        }
+    }
+
+    fn sinks(&self, kind: SubscriptionKind) -> Vec<Sink<Value>> {
+        self.active
+            .lock()
+            .values()
+            .filter(|subscription| subscription.kind == kind)
+            .map(|subscription| subscription.sink.clone())
+            .collect()
+    }
+
+    fn send(&self, sinks: &[Sink<Value>], value: Value) {
+        let sender = self.sender.lock();
+        for sink in sinks {
+            sender.send((sink.clone(), value.clone())).expect("The subscription thread lives as long as the sender");
+        }
+    }
+}
+
+impl<C> ChainNotify for Subscriptions<C>
+where
+    C: BlockChainTrait + EngineInfo + Send + Sync + 'static,
+{
+    fn new_blocks(
+        &self,
+        _imported: Vec<BlockHash>,
+        _invalid: Vec<BlockHash>,
+        enacted: Vec<BlockHash>,
+        _retracted: Vec<BlockHash>,
+        _sealed: Vec<BlockHash>,
+    ) {
+        let sinks = self.sinks(SubscriptionKind::NewHeads);
+        if sinks.is_empty() {
+            return
+        }
+        let network_id = self.client.network_id();
+        for hash in enacted {
+            if let Some(header) = self.client.block_header(&hash.into()) {
+                let header = Header::from_core(header.decode(), network_id);
+                self.send(&sinks, serde_json::to_value(header).expect("A header can always be serialized"));
+            }
+        }
+    }
+}
+
+pub struct SubscriptionClient<C> {
+    subscriptions: Arc<Subscriptions<C>>,
+}
+
+impl<C> SubscriptionClient<C> {
+    pub fn new(subscriptions: Arc<Subscriptions<C>>) -> Self {
+        SubscriptionClient {
+            subscriptions,
+        }
+    }
+}
+
+impl<C> Subscription for SubscriptionClient<C>
+where
+    C: BlockChainTrait + EngineInfo + Send + Sync + 'static,
+{
+    type Metadata = Metadata;
+
+    fn subscribe(&self, meta: Metadata, subscriber: Subscriber<Value>, kind: SubscriptionKind) {
+        match (meta.session(), meta.connection_id()) {
+            (Some(session), Some(connection_id)) => self.subscriptions.add(&session, connection_id, subscriber, kind),
+            _ => {
+                let _ = subscriber.reject(errors::subscriptions_unavailable());
+            }
+        }
+    }
+
+    fn unsubscribe(&self, meta: Option<Metadata>, id: SubscriptionId) -> Result<bool> {
+        match meta.map(|meta| meta.connection_id()) {
+            // jsonrpc-pubsub unsubscribes without the metadata when the session is dropped.
+            None => Ok(self.subscriptions.remove(&id, None)),
+            Some(Some(connection_id)) => Ok(self.subscriptions.remove(&id, Some(connection_id))),
+            Some(None) => Err(errors::subscriptions_unavailable()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc_server::start_ws;
+    use ccore::TestBlockChainClient;
+    use jsonrpc_core::MetaIoHandler;
+    use jsonrpc_ws_server::Server as WsServer;
+    use std::sync::mpsc::Receiver;
+    use std::time::{Duration, Instant};
+    use ws;
+
+    fn start_server(subscriptions: &Arc<Subscriptions<TestBlockChainClient>>) -> (WsServer, String) {
+        let mut handler: MetaIoHandler<Metadata> = MetaIoHandler::default();
+        handler.extend_with(SubscriptionClient::new(Arc::clone(subscriptions)).to_delegate());
+        let server = start_ws(&"127.0.0.1:0".parse().unwrap(), handler, 10, None, None).unwrap();
+        let url = format!("ws://{}", server.addr());
+        (server, url)
+    }
+
+    /// Sends the request on a new connection and returns the response.
+    fn request_once(url: &str, request: String) -> Value {
+        let (sender, receiver) = channel();
+        ws::connect(url, |out| {
+            out.send(request.clone()).unwrap();
+            let sender = sender.clone();
+            move |message: ws::Message| {
+                sender.send(serde_json::from_str::<Value>(message.as_text()?).unwrap()).unwrap();
+                out.close(ws::CloseCode::Normal)
+            }
+        })
+        .unwrap();
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    fn recv(receiver: &Receiver<Value>) -> Value {
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn new_heads_are_notified_over_ws() {
+        let client = Arc::new(TestBlockChainClient::new());
+        let subscriptions = Arc::new(Subscriptions::new(Arc::clone(&client), 10));
+        let notify: Arc<dyn ChainNotify> = subscriptions.clone();
+        client.add_notify(Arc::downgrade(&notify));
+        let (server, url) = start_server(&subscriptions);
+
+        let (sender, receiver) = channel();
+        let ws_client = thread::spawn(move || {
+            ws::connect(url, |out| {
+                out.send(r#"{"jsonrpc":"2.0","method":"subscribe","params":["newHeads"],"id":1}"#).unwrap();
+                let sender = sender.clone();
+                move |message: ws::Message| {
+                    let message: Value = serde_json::from_str(message.as_text()?).unwrap();
+                    let is_notification = message["method"] == "subscription";
+                    sender.send(message).unwrap();
+                    if is_notification {
+                        out.close(ws::CloseCode::Normal)
+                    } else {
+                        Ok(())
+                    }
+                }
+            })
+            .unwrap();
+        });
+
+        let response = recv(&receiver);
+        let subscription_id = response["result"].clone();
+        assert!(subscription_id.is_number(), "{}", response);
+
+        client.add_blocks(1, 0);
+        let notification = recv(&receiver);
+        assert_eq!(subscription_id, notification["params"]["subscription"]);
+        let best_block_hash = serde_json::to_value(client.chain_info().best_block_hash).unwrap();
+        assert_eq!(best_block_hash, notification["params"]["result"]["hash"]);
+
+        ws_client.join().unwrap();
+        // The server drops the session after the connection is closed.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !subscriptions.active.lock().is_empty() {
+            assert!(Instant::now() < deadline, "The subscription must be removed when the connection is closed");
+            thread::sleep(Duration::from_millis(10));
+        }
+        server.close_handle().close();
+    }
+
+    #[test]
+    fn subscriptions_are_limited_per_connection() {
+        let client = Arc::new(TestBlockChainClient::new());
+        let subscriptions = Arc::new(Subscriptions::new(client, 1));
+        let (server, url) = start_server(&subscriptions);
+
+        let (sender, receiver) = channel();
+        let ws_client = thread::spawn(move || {
+            ws::connect(url, |out| {
+                for (id, kind) in [(1, "newHeads"), (2, "newPendingTransactions")].iter() {
+                    let request =
+                        format!(r#"{{"jsonrpc":"2.0","method":"subscribe","params":["{}"],"id":{}}}"#, kind, id);
+                    out.send(request).unwrap();
+                }
+                let sender = sender.clone();
+                move |message: ws::Message| {
+                    let message: Value = serde_json::from_str(message.as_text()?).unwrap();
+                    let is_last = message["id"] == 2;
+                    sender.send(message).unwrap();
+                    if is_last {
+                        out.close(ws::CloseCode::Normal)
+                    } else {
+                        Ok(())
+                    }
+                }
+            })
+            .unwrap();
+        });
+
+        let first = recv(&receiver);
+        assert!(first["result"].is_number(), "{}", first);
+        let second = recv(&receiver);
+        assert_eq!(-32052, second["error"]["code"]);
+
+        ws_client.join().unwrap();
+        server.close_handle().close();
+    }
+
+    #[test]
+    fn a_connection_cannot_cancel_the_subscriptions_of_another() {
+        let client = Arc::new(TestBlockChainClient::new());
+        let subscriptions = Arc::new(Subscriptions::new(client, 10));
+        let (server, url) = start_server(&subscriptions);
+
+        // The owner hands out its connection, so that it stays open while the other connection tries to cancel.
+        let (sender, receiver) = channel();
+        let owner_url = url.clone();
+        let owner = thread::spawn(move || {
+            ws::connect(owner_url, |out| {
+                out.send(r#"{"jsonrpc":"2.0","method":"subscribe","params":["newHeads"],"id":1}"#).unwrap();
+                let sender = sender.clone();
+                move |message: ws::Message| {
+                    let message: Value = serde_json::from_str(message.as_text()?).unwrap();
+                    sender.send((out.clone(), message)).unwrap();
+                    Ok(())
+                }
+            })
+            .unwrap();
+        });
+
+        let (owner_out, response) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        let subscription_id = response["result"].clone();
+        assert!(subscription_id.is_number(), "{}", response);
+        let unsubscribe =
+            format!(r#"{{"jsonrpc":"2.0","method":"unsubscribe","params":[{}],"id":2}}"#, subscription_id);
+
+        let response = request_once(&url, unsubscribe.clone());
+        assert_eq!(Value::Bool(false), response["result"], "{}", response);
+        assert_eq!(1, subscriptions.active.lock().len());
+
+        owner_out.send(unsubscribe).unwrap();
+        let (_, response) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(Value::Bool(true), response["result"], "{}", response);
+        assert!(subscriptions.active.lock().is_empty());
+
+        owner_out.close(ws::CloseCode::Normal).unwrap();
+        owner.join().unwrap();
+        server.close_handle().close();
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use jsonrpc_core;
+use jsonrpc_pubsub::{PubSubMetadata, Session};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// RPC metadata. It carries the session of the connection if the transport supports subscriptions.
+/// The clones of a metadata share the authentication state,
+/// so a connection that calls `auth` stays authenticated on the transports that extract the metadata per connection.
+#[derive(Clone, Default)]
+pub struct Metadata {
+    session: Option<Arc<Session>>,
+    /// Identifies the connection of the session. Unlike the address of the session, it is never reused.
+    connection_id: Option<u64>,
+    authenticated: Arc<AtomicBool>,
+}
+
+impl Metadata {
+    pub fn new(session: Session) -> Self {
+        Metadata {
+            session: Some(Arc::new(session)),
+            connection_id: Some(NEXT_CONNECTION_ID.fetch_add(1, Ordering::SeqCst)),
+            authenticated: Default::default(),
+        }
+    }
+
+    /// The id of the connection. It is `None` on the transports without sessions.
+    pub fn connection_id(&self) -> Option<u64> {
+        self.connection_id
+    }
+
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated.load(Ordering::SeqCst)
+    }
+
+    pub fn authenticate(&self) {
+        self.authenticated.store(true, Ordering::SeqCst);
+    }
+}
+
+impl jsonrpc_core::Metadata for Metadata {}
+
+impl PubSubMetadata for Metadata {
+    fn session(&self) -> Option<Arc<Session>> {
+        self.session.clone()
+    }
+}
@@ -14,10 +14,14 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-mod errors;
+pub(crate) mod errors;
 mod impls;
+mod metadata;
+mod read_only;
 mod traits;
 mod types;
 
 pub use self::impls::*;
+pub use self::metadata::Metadata;
+pub use self::read_only::reject_writes;
 pub use self::traits::*;
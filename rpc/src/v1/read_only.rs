@@ -0,0 +1,79 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::errors;
+use jsonrpc_core::{MetaIoHandler, Metadata, Middleware, Params};
+
+/// The methods that change the chain, the mem pool or the sealing.
+const WRITE_METHODS: &[&str] = &[
+    "mempool_sendSignedTransaction",
+    "mempool_sendSignedTransactionWithExpiry",
+    "mempool_deleteAllPendingTransactions",
+    "mempool_removeTransaction",
+    "mempool_dropFutureTransactions",
+    "mempool_banAccounts",
+    "mempool_unbanAccounts",
+    "mempool_registerImmuneAccounts",
+    "miner_getWork",
+    "miner_submitWork",
+    "miner_getPendingSeal",
+    "miner_submitSignedSeal",
+    "account_sendTransaction",
+];
+
+const DEVEL_WRITE_METHODS: &[&str] =
+    &["devel_startSealing", "devel_stopSealing", "devel_testTPS", "devel_restoreFromBackup"];
+
+/// Replaces the methods that change the state with the ones failing with the read-only error.
+pub fn reject_writes<T: Metadata, S: Middleware<T>>(handler: &mut MetaIoHandler<T, S>, enable_devel_api: bool) {
+    let devel_methods: &[&str] = if enable_devel_api {
+        DEVEL_WRITE_METHODS
+    } else {
+        &[]
+    };
+    for method in WRITE_METHODS.iter().chain(devel_methods) {
+        handler.add_method(method, |_params: Params| Err(errors::read_only()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_core::Value;
+
+    #[test]
+    fn only_write_methods_are_rejected() {
+        let mut handler = MetaIoHandler::<()>::default();
+        handler.add_method("chain_getBestBlockNumber", |_params: Params| Ok(Value::from(1)));
+        handler.add_method("mempool_sendSignedTransaction", |_params: Params| Ok(Value::from(2)));
+        reject_writes(&mut handler, false);
+
+        let request = |method: &str| {
+            let request = format!(r#"{{"jsonrpc":"2.0","method":"{}","params":[],"id":1}}"#, method);
+            handler.handle_request_sync(&request, ()).unwrap()
+        };
+        assert_eq!(r#"{"jsonrpc":"2.0","result":1,"id":1}"#, request("chain_getBestBlockNumber"));
+        assert_eq!(
+            r#"{"jsonrpc":"2.0","error":{"code":-32059,"message":"Unsupported in read-only mode"},"id":1}"#,
+            request("mempool_sendSignedTransaction")
+        );
+        // The devel API is not added when it's disabled.
+        assert_eq!(
+            r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":1}"#,
+            request("devel_startSealing")
+        );
+    }
+}
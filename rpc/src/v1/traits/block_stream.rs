@@ -0,0 +1,33 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::super::types::BlockStreamItem;
+use jsonrpc_core::Result;
+use jsonrpc_pubsub::typed::Subscriber;
+use jsonrpc_pubsub::SubscriptionId;
+
+#[rpc(server)]
+pub trait BlockStream {
+    type Metadata;
+
+    /// Streams the canonical blocks `from..=to` as the notifications of the subscription.
+    #[pubsub(subscription = "devel_blocks", subscribe, name = "devel_streamBlocks")]
+    fn stream_blocks(&self, meta: Self::Metadata, subscriber: Subscriber<BlockStreamItem>, from: u64, to: u64);
+
+    /// Stops the stream with given id.
+    #[pubsub(subscription = "devel_blocks", unsubscribe, name = "devel_stopStreamBlocks")]
+    fn stop_stream_blocks(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool>;
+}
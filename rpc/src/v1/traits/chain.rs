@@ -14,13 +14,19 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::{AssetScheme, Block, BlockNumberAndHash, OwnedAsset, Text, Transaction, UnsignedTransaction};
+use super::super::types::{
+    AssetLog, AssetLogFilter, AssetScheme, Block, BlockIdOrTag, BlockNumberAndHash, BlockNumberOrTag, DryRun,
+    FeeDistribution, Finalization, IndexedText, OrphanedProposal, OwnedAsset, SyncStatus, Text, Transaction,
+    TransactionAttempt, TransferInputCheck, TreeRoute, UnsignedTransaction, UnspentAsset, Validator, ValidatorLiveness,
+};
 use cjson::scheme::Params;
 use cjson::uint::Uint;
 use ckey::{NetworkId, PlatformAddress, Public};
 use ctypes::{BlockHash, BlockNumber, ShardId, Tracker, TxHash};
-use jsonrpc_core::Result;
+use jsonrpc_core::{BoxFuture, Result};
 use primitives::{Bytes as BytesArray, H160, H256};
+use serde_json::Value;
+use std::collections::BTreeMap;
 
 #[rpc(server)]
 pub trait Chain {
@@ -43,6 +49,30 @@ pub trait Chain {
     #[rpc(name = "chain_getTransactionByTracker")]
     fn get_transaction_by_tracker(&self, tracker: Tracker) -> Result<Option<Transaction>>;
 
+    /// Gets all the transactions tried with given transaction tracker, including the failed ones.
+    #[rpc(name = "chain_getTransactionsByTracker")]
+    fn get_transactions_by_tracker(&self, tracker: Tracker) -> Result<Vec<TransactionAttempt>>;
+
+    /// Gets the transactions sent by or paying to the given address in the given block range.
+    #[rpc(name = "chain_getTransactionsByAddress")]
+    fn get_transactions_by_address(
+        &self,
+        address: PlatformAddress,
+        from_block: u64,
+        to_block: Option<u64>,
+        skip: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Transaction>>;
+
+    /// Gets the mints, transfers and burns of the assets that match the filter.
+    #[rpc(name = "chain_getAssetLogs")]
+    fn get_asset_logs(
+        &self,
+        filter: AssetLogFilter,
+        skip: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<Vec<AssetLog>>;
+
     /// Gets asset scheme with given transaction tracker.
     #[rpc(name = "chain_getAssetSchemeByTracker")]
     fn get_asset_scheme_by_tracker(
@@ -65,6 +95,19 @@ pub trait Chain {
     #[rpc(name = "chain_getText")]
     fn get_text(&self, transaction_hash: TxHash, block_number: Option<u64>) -> Result<Option<Text>>;
 
+    /// Gets the texts certified by the address.
+    #[rpc(name = "chain_getTextsByCertifier")]
+    fn get_texts_by_certifier(
+        &self,
+        certifier: PlatformAddress,
+        skip: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<Vec<IndexedText>>;
+
+    /// Gets a text whose content hash is the given hash.
+    #[rpc(name = "chain_getTextByContentHash")]
+    fn get_text_by_content_hash(&self, content_hash: H256) -> Result<Option<IndexedText>>;
+
     /// Gets asset with given asset type.
     #[rpc(name = "chain_getAsset")]
     fn get_asset(
@@ -75,6 +118,16 @@ pub trait Chain {
         block_number: Option<u64>,
     ) -> Result<Option<OwnedAsset>>;
 
+    /// Gets the unspent assets in the shard that are locked by the lock script hash.
+    #[rpc(name = "chain_getOwnedAssetsByLockScriptHash")]
+    fn get_owned_assets_by_lock_script_hash(
+        &self,
+        shard_id: ShardId,
+        lock_script_hash: H160,
+        skip: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<Vec<UnspentAsset>>;
+
     /// Checks whether an asset is spent or not.
     #[rpc(name = "chain_isAssetSpent")]
     fn is_asset_spent(
@@ -87,11 +140,11 @@ pub trait Chain {
 
     /// Gets seq with given account.
     #[rpc(name = "chain_getSeq")]
-    fn get_seq(&self, address: PlatformAddress, block_number: Option<u64>) -> Result<Option<u64>>;
+    fn get_seq(&self, address: PlatformAddress, block_number: Option<BlockNumberOrTag>) -> Result<Option<u64>>;
 
     /// Gets balance with given account.
     #[rpc(name = "chain_getBalance")]
-    fn get_balance(&self, address: PlatformAddress, block_number: Option<u64>) -> Result<Option<Uint>>;
+    fn get_balance(&self, address: PlatformAddress, block_number: Option<BlockNumberOrTag>) -> Result<Option<Uint>>;
 
     /// Gets regular key with given account
     #[rpc(name = "chain_getRegularKey")]
@@ -129,10 +182,22 @@ pub trait Chain {
     #[rpc(name = "chain_getBestBlockNumber")]
     fn get_best_block_number(&self) -> Result<BlockNumber>;
 
+    /// Gets the best imported block number and the best block number known from the peers.
+    #[rpc(name = "chain_getSyncStatus")]
+    fn get_sync_status(&self) -> Result<SyncStatus>;
+
     /// Gets the number and the hash of the best block.
     #[rpc(name = "chain_getBestBlockId")]
     fn get_best_block_id(&self) -> Result<BlockNumberAndHash>;
 
+    /// Gets the number and the hash of the highest finalized block.
+    #[rpc(name = "chain_getFinalizedBlockId")]
+    fn get_finalized_block_id(&self) -> Result<BlockNumberAndHash>;
+
+    /// Gets whether the block with given hash is finalized and how many confirmations it has.
+    #[rpc(name = "chain_isFinalized")]
+    fn is_finalized(&self, block_hash: BlockHash) -> Result<Option<Finalization>>;
+
     /// Gets the hash of the block with given number.
     #[rpc(name = "chain_getBlockHash")]
     fn get_block_hash(&self, block_number: u64) -> Result<Option<BlockHash>>;
@@ -145,14 +210,47 @@ pub trait Chain {
     #[rpc(name = "chain_getBlockByHash")]
     fn get_block_by_hash(&self, block_hash: BlockHash) -> Result<Option<Block>>;
 
+    ///Gets the count of transactions in a block with given number.
+    #[rpc(name = "chain_getBlockTransactionCountByNumber")]
+    fn get_block_transaction_count_by_number(&self, block_number: u64) -> Result<Option<usize>>;
+
     ///Gets the count of transactions in a block with given hash.
     #[rpc(name = "chain_getBlockTransactionCountByHash")]
     fn get_block_transaction_count_by_hash(&self, block_hash: BlockHash) -> Result<Option<usize>>;
 
+    /// Gets the error hints of the transactions in a block with given hash.
+    #[rpc(name = "chain_getBlockErrorHints")]
+    fn get_block_error_hints(&self, block_hash: BlockHash) -> Result<Option<Vec<(TxHash, Option<String>)>>>;
+
+    /// Gets who received how much of the fees when the block with given hash was closed.
+    #[rpc(name = "chain_getBlockFeeDistribution")]
+    fn get_block_fee_distribution(&self, block_hash: BlockHash) -> Result<Option<FeeDistribution>>;
+
+    /// Gets the common ancestor of the given blocks and the blocks retracted and enacted to move from one to the other.
+    #[rpc(name = "chain_getTreeRoute")]
+    fn get_tree_route(&self, from: BlockHash, to: BlockHash) -> Result<Option<TreeRoute>>;
+
+    /// Gets the headers of consecutive blocks from the given block.
+    /// The headers are returned as the JSON values of `Header`.
+    #[rpc(name = "chain_getHeadersInRange")]
+    fn get_headers_in_range(&self, from: BlockIdOrTag, count: u64, reverse: bool) -> Result<Vec<Value>>;
+
+    /// Gets the proposal block of the given height and view that the consensus engine is voting on.
+    #[rpc(name = "chain_getProposalBlock")]
+    fn get_proposal_block(&self, block_number: u64, view: u64) -> Result<Option<Block>>;
+
     ///Gets the minimum transaction fee of the given name.
     #[rpc(name = "chain_getMinTransactionFee")]
     fn get_min_transaction_fee(&self, action_type: String, block_number: Option<u64>) -> Result<Option<u64>>;
 
+    /// Gets the minimum transaction fees of all action types.
+    #[rpc(name = "chain_getMinTransactionFees")]
+    fn get_min_transaction_fees(&self, block_number: Option<u64>) -> Result<Option<BTreeMap<String, u64>>>;
+
+    /// Suggests a fee per byte with which a transaction goes into one of the next `target_blocks` blocks.
+    #[rpc(name = "chain_estimateFee")]
+    fn estimate_fee(&self, target_blocks: u64) -> Result<u64>;
+
     /// Gets the mining given block number
     #[rpc(name = "chain_getMiningReward")]
     fn get_mining_reward(&self, block_number: u64) -> Result<Option<u64>>;
@@ -177,9 +275,26 @@ pub trait Chain {
     #[rpc(name = "chain_getPossibleAuthors")]
     fn get_possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>>;
 
-    /// Execute Transactions
+    /// Return the validators of the given block with their stakes
+    #[rpc(name = "chain_getValidatorSet")]
+    fn get_validator_set(&self, block_number: Option<u64>) -> Result<Option<Vec<Validator>>>;
+
+    /// Return the precommits of the validators counted over the given term
+    #[rpc(name = "chain_getValidatorLiveness")]
+    fn get_validator_liveness(&self, term_id: Option<u64>) -> Result<Option<Vec<ValidatorLiveness>>>;
+
+    /// Return the proposals of the given heights that are not committed
+    #[rpc(name = "chain_getOrphanedProposals")]
+    fn get_orphaned_proposals(&self, from_height: u64, to_height: u64) -> Result<Vec<OrphanedProposal>>;
+
+    /// Execute the transaction on the state of the given block without committing it
     #[rpc(name = "chain_executeTransaction")]
-    fn execute_transaction(&self, tx: UnsignedTransaction, sender: PlatformAddress) -> Result<Option<String>>;
+    fn execute_transaction(
+        &self,
+        tx: UnsignedTransaction,
+        sender: Public,
+        block_number: Option<u64>,
+    ) -> BoxFuture<DryRun>;
 
     /// Execute AssetTransfer transaction inputs in VM
     #[rpc(name = "chain_executeVM")]
@@ -189,4 +304,16 @@ pub trait Chain {
         params: Vec<Vec<BytesArray>>,
         indices: Vec<usize>,
     ) -> Result<Vec<String>>;
+
+    /// Return the tracker of the shard transaction
+    #[rpc(name = "chain_computeTracker")]
+    fn compute_tracker(&self, tx: UnsignedTransaction) -> Result<Option<Tracker>>;
+
+    /// Resolve the inputs and the burns of AssetTransfer transaction against the state without executing them
+    #[rpc(name = "chain_checkTransferInputs")]
+    fn check_transfer_inputs(
+        &self,
+        tx: UnsignedTransaction,
+        block_number: Option<u64>,
+    ) -> BoxFuture<Vec<TransferInputCheck>>;
 }
@@ -14,21 +14,29 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::TPSTestSetting;
+use super::super::types::{ImportMetrics, ShardEntries, Snapshot, StakeAudit, StateCacheStats, TPSTestSetting, Timer};
 use cjson::bytes::Bytes;
-use ctypes::BlockHash;
-use jsonrpc_core::Result;
+use ctypes::{BlockHash, ShardId};
+use jsonrpc_core::{BoxFuture, Result};
 use primitives::H256;
 use std::net::SocketAddr;
 
 #[rpc(server)]
 pub trait Devel {
     #[rpc(name = "devel_getStateTrieKeys")]
-    fn get_state_trie_keys(&self, offset: usize, limit: usize) -> Result<Vec<H256>>;
+    fn get_state_trie_keys(&self, offset: usize, limit: usize) -> BoxFuture<Vec<H256>>;
 
     #[rpc(name = "devel_getStateTrieValue")]
     fn get_state_trie_value(&self, key: H256) -> Result<Vec<Bytes>>;
 
+    #[rpc(name = "devel_getShardStateEntries")]
+    fn get_shard_state_entries(
+        &self,
+        shard_id: ShardId,
+        from_key: Option<H256>,
+        limit: usize,
+    ) -> Result<Option<ShardEntries>>;
+
     #[rpc(name = "devel_startSealing")]
     fn start_sealing(&self) -> Result<()>;
 
@@ -44,6 +52,27 @@ pub trait Devel {
     #[rpc(name = "devel_getTargetBlockHashes")]
     fn get_target_block_hashes(&self) -> Result<Vec<BlockHash>>;
 
+    #[rpc(name = "devel_getImportMetrics")]
+    fn get_import_metrics(&self) -> Result<Option<ImportMetrics>>;
+
+    #[rpc(name = "devel_getTimers")]
+    fn get_timers(&self) -> Result<Vec<Timer>>;
+
+    #[rpc(name = "devel_getStateCacheStats")]
+    fn get_state_cache_stats(&self) -> Result<Vec<StateCacheStats>>;
+
+    #[rpc(name = "devel_auditStake")]
+    fn audit_stake(&self, block_number: Option<u64>) -> Result<Option<StakeAudit>>;
+
+    #[rpc(name = "devel_getSnapshotList")]
+    fn get_snapshot_list(&self) -> Result<Vec<Snapshot>>;
+
     #[rpc(name = "devel_testTPS")]
     fn test_tps(&self, setting: TPSTestSetting) -> Result<f64>;
+
+    #[rpc(name = "devel_createBackup")]
+    fn create_backup(&self, path: String) -> BoxFuture<BlockHash>;
+
+    #[rpc(name = "devel_restoreFromBackup")]
+    fn restore_from_backup(&self, path: String) -> BoxFuture<BlockHash>;
 }
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::{MemPoolMinFees, PendingTransactions};
+use super::super::types::{MemPoolMinFees, PendingBySender, PendingTransactions, ReadyTransactions, SeqGapInfo};
 use cjson::bytes::Bytes;
 use ckey::PlatformAddress;
 use ctypes::{Tracker, TxHash};
@@ -26,6 +26,10 @@ pub trait Mempool {
     #[rpc(name = "mempool_sendSignedTransaction")]
     fn send_signed_transaction(&self, raw: Bytes) -> Result<TxHash>;
 
+    /// Sends signed transaction which is dropped from the mem pool after the expiry, returning its hash.
+    #[rpc(name = "mempool_sendSignedTransactionWithExpiry")]
+    fn send_signed_transaction_with_expiry(&self, raw: Bytes, expiry: u64) -> Result<TxHash>;
+
     /// Gets transaction results with given transaction tracker.
     #[rpc(name = "mempool_getTransactionResultsByTracker")]
     fn get_transaction_results_by_tracker(&self, tracker: Tracker) -> Result<Vec<bool>>;
@@ -47,6 +51,15 @@ pub trait Mempool {
         future_included: Option<bool>,
     ) -> Result<PendingTransactions>;
 
+    /// Gets a page of the transactions in the current mem pool in the order of block building.
+    #[rpc(name = "mempool_getReadyTransactions")]
+    fn get_ready_transactions(
+        &self,
+        max_count: Option<usize>,
+        max_size: Option<usize>,
+        cursor: Option<Bytes>,
+    ) -> Result<ReadyTransactions>;
+
     /// Gets the count of transactions in the current mem pool.
     #[rpc(name = "mempool_getPendingTransactionsCount")]
     fn get_pending_transactions_count(
@@ -56,6 +69,22 @@ pub trait Mempool {
         future_included: Option<bool>,
     ) -> Result<usize>;
 
+    /// Gets the transactions of the sender in the current and future queues.
+    #[rpc(name = "mempool_getPendingBySender")]
+    fn get_pending_by_sender(&self, address: PlatformAddress) -> Result<PendingBySender>;
+
+    /// Removes a transaction submitted to this node from the mem pool.
+    #[rpc(name = "mempool_removeTransaction")]
+    fn remove_transaction(&self, transaction_hash: TxHash) -> Result<bool>;
+
+    /// Gets the seq the future transactions of the sender are waiting for.
+    #[rpc(name = "mempool_getSeqGapInfo")]
+    fn get_seq_gap_info(&self, address: PlatformAddress) -> Result<SeqGapInfo>;
+
+    /// Removes the future transactions of the sender from the given seq, if they were submitted to this node.
+    #[rpc(name = "mempool_dropFutureTransactions")]
+    fn drop_future_transactions(&self, address: PlatformAddress, from_seq: u64) -> Result<Vec<TxHash>>;
+
     #[rpc(name = "mempool_getBannedAccounts")]
     fn get_banned_accounts(&self) -> Result<Vec<PlatformAddress>>;
 
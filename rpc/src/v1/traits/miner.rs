@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::Work;
+use super::super::types::{MiningStatus, PendingSeal, Work};
 use cjson::bytes::Bytes;
 use ctypes::BlockHash;
 use jsonrpc_core::Result;
@@ -26,4 +26,13 @@ pub trait Miner {
 
     #[rpc(name = "miner_submitWork")]
     fn submit_work(&self, pow_hash: BlockHash, seal: Vec<Bytes>) -> Result<bool>;
+
+    #[rpc(name = "miner_getPendingSeal")]
+    fn get_pending_seal(&self) -> Result<PendingSeal>;
+
+    #[rpc(name = "miner_submitSignedSeal")]
+    fn submit_signed_seal(&self, block_hash: BlockHash, seal: Vec<Bytes>) -> Result<()>;
+
+    #[rpc(name = "mining_getStatus")]
+    fn get_status(&self) -> Result<MiningStatus>;
 }
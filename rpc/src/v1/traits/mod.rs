@@ -15,17 +15,21 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod account;
+mod block_stream;
 mod chain;
 mod devel;
 mod engine;
 mod mempool;
 mod miner;
 mod net;
+mod subscription;
 
 pub use self::account::Account;
+pub use self::block_stream::BlockStream;
 pub use self::chain::Chain;
 pub use self::devel::Devel;
 pub use self::engine::Engine;
 pub use self::mempool::Mempool;
 pub use self::miner::Miner;
 pub use self::net::Net;
+pub use self::subscription::Subscription;
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::FilterStatus;
+use super::super::types::{ExtensionState, FilterStatus};
 use cidr::IpCidr;
 use ckey::Public;
 use jsonrpc_core::Result;
@@ -47,6 +47,15 @@ pub trait Net {
     #[rpc(name = "net_getEstablishedPeers")]
     fn get_established_peers(&self) -> Result<Vec<SocketAddr>>;
 
+    #[rpc(name = "net_addTrustedPeer")]
+    fn add_trusted_peer(&self, addr: IpAddr, port: u16) -> Result<()>;
+
+    #[rpc(name = "net_removeTrustedPeer")]
+    fn remove_trusted_peer(&self, addr: IpAddr, port: u16) -> Result<bool>;
+
+    #[rpc(name = "net_getTrustedPeers")]
+    fn get_trusted_peers(&self) -> Result<Vec<SocketAddr>>;
+
     #[rpc(name = "net_addToWhitelist")]
     fn add_to_whitelist(&self, addr: IpCidr, tag: Option<String>) -> Result<()>;
 
@@ -79,4 +88,13 @@ pub trait Net {
 
     #[rpc(name = "net_recentNetworkUsage")]
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>>;
+
+    #[rpc(name = "net_disableExtension")]
+    fn disable_extension(&self, name: String) -> Result<()>;
+
+    #[rpc(name = "net_enableExtension")]
+    fn enable_extension(&self, name: String) -> Result<()>;
+
+    #[rpc(name = "net_getExtensions")]
+    fn get_extensions(&self) -> Result<Vec<ExtensionState>>;
 }
@@ -0,0 +1,34 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::super::types::SubscriptionKind;
+use jsonrpc_core::Result;
+use jsonrpc_pubsub::typed::Subscriber;
+use jsonrpc_pubsub::SubscriptionId;
+use serde_json::Value;
+
+#[rpc(server)]
+pub trait Subscription {
+    type Metadata;
+
+    /// Subscribes to new best blocks or newly queued transactions.
+    #[pubsub(subscription = "subscription", subscribe, name = "subscribe")]
+    fn subscribe(&self, meta: Self::Metadata, subscriber: Subscriber<Value>, kind: SubscriptionKind);
+
+    /// Cancels the subscription with given id.
+    #[pubsub(subscription = "subscription", unsubscribe, name = "unsubscribe")]
+    fn unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool>;
+}
@@ -22,7 +22,7 @@ use ctypes::transaction::{Action as ActionType, AssetMintOutput as AssetMintOutp
 use ctypes::{ShardId, Tracker, TxHash};
 use primitives::{Bytes, H160};
 use rustc_serialize::hex::{FromHex, ToHex};
-use std::convert::TryFrom;
+use std::convert::{TryFrom, TryInto};
 
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase", tag = "type")]
@@ -83,10 +83,12 @@ pub enum Action {
     Pay {
         receiver: PlatformAddress,
         quantity: Uint,
+        memo: Option<String>,
     },
     SetRegularKey {
         key: Public,
     },
+    RevokeRegularKey,
     CreateShard {
         users: Vec<PlatformAddress>,
     },
@@ -96,11 +98,30 @@ pub enum Action {
         owners: Vec<PlatformAddress>,
     },
     #[serde(rename_all = "camelCase")]
+    OfferShardOwnership {
+        shard_id: ShardId,
+        new_owners: Vec<PlatformAddress>,
+        expiry: Uint,
+    },
+    #[serde(rename_all = "camelCase")]
+    AcceptShardOwnership {
+        shard_id: ShardId,
+    },
+    #[serde(rename_all = "camelCase")]
+    CancelShardOwnershipOffer {
+        shard_id: ShardId,
+    },
+    #[serde(rename_all = "camelCase")]
     SetShardUsers {
         shard_id: ShardId,
         users: Vec<PlatformAddress>,
     },
     #[serde(rename_all = "camelCase")]
+    CreateApproverGroup {
+        members: Vec<PlatformAddress>,
+        threshold: u8,
+    },
+    #[serde(rename_all = "camelCase")]
     WrapCCC {
         shard_id: ShardId,
         lock_script_hash: H160,
@@ -197,10 +218,12 @@ pub enum ActionWithTracker {
     Pay {
         receiver: PlatformAddress,
         quantity: Uint,
+        memo: Option<String>,
     },
     SetRegularKey {
         key: Public,
     },
+    RevokeRegularKey,
     CreateShard {
         users: Vec<PlatformAddress>,
     },
@@ -210,11 +233,30 @@ pub enum ActionWithTracker {
         owners: Vec<PlatformAddress>,
     },
     #[serde(rename_all = "camelCase")]
+    OfferShardOwnership {
+        shard_id: ShardId,
+        new_owners: Vec<PlatformAddress>,
+        expiry: Uint,
+    },
+    #[serde(rename_all = "camelCase")]
+    AcceptShardOwnership {
+        shard_id: ShardId,
+    },
+    #[serde(rename_all = "camelCase")]
+    CancelShardOwnershipOffer {
+        shard_id: ShardId,
+    },
+    #[serde(rename_all = "camelCase")]
     SetShardUsers {
         shard_id: ShardId,
         users: Vec<PlatformAddress>,
     },
     #[serde(rename_all = "camelCase")]
+    CreateApproverGroup {
+        members: Vec<PlatformAddress>,
+        threshold: u8,
+    },
+    #[serde(rename_all = "camelCase")]
     WrapCCC {
         shard_id: ShardId,
         lock_script_hash: H160,
@@ -333,15 +375,18 @@ impl ActionWithTracker {
             ActionType::Pay {
                 receiver,
                 quantity,
+                memo,
             } => ActionWithTracker::Pay {
                 receiver: PlatformAddress::new_v1(network_id, receiver),
                 quantity: quantity.into(),
+                memo: memo.map(|memo| memo.to_hex()),
             },
             ActionType::SetRegularKey {
                 key,
             } => ActionWithTracker::SetRegularKey {
                 key,
             },
+            ActionType::RevokeRegularKey => ActionWithTracker::RevokeRegularKey,
             ActionType::CreateShard {
                 users,
             } => {
@@ -357,6 +402,25 @@ impl ActionWithTracker {
                 shard_id,
                 owners: owners.into_iter().map(|owner| PlatformAddress::new_v1(network_id, owner)).collect(),
             },
+            ActionType::OfferShardOwnership {
+                shard_id,
+                new_owners,
+                expiry,
+            } => ActionWithTracker::OfferShardOwnership {
+                shard_id,
+                new_owners: new_owners.into_iter().map(|owner| PlatformAddress::new_v1(network_id, owner)).collect(),
+                expiry: expiry.into(),
+            },
+            ActionType::AcceptShardOwnership {
+                shard_id,
+            } => ActionWithTracker::AcceptShardOwnership {
+                shard_id,
+            },
+            ActionType::CancelShardOwnershipOffer {
+                shard_id,
+            } => ActionWithTracker::CancelShardOwnershipOffer {
+                shard_id,
+            },
             ActionType::SetShardUsers {
                 shard_id,
                 users,
@@ -364,6 +428,13 @@ impl ActionWithTracker {
                 shard_id,
                 users: users.into_iter().map(|user| PlatformAddress::new_v1(network_id, user)).collect(),
             },
+            ActionType::CreateApproverGroup {
+                members,
+                threshold,
+            } => ActionWithTracker::CreateApproverGroup {
+                members: members.into_iter().map(|member| PlatformAddress::new_v1(network_id, member)).collect(),
+                threshold,
+            },
             ActionType::WrapCCC {
                 shard_id,
                 lock_script_hash,
@@ -455,12 +526,12 @@ impl TryFrom<Action> for ActionType {
                 let outputs = outputs.into_iter().map(TryFrom::try_from).collect::<Result<_, _>>()?;
                 ActionType::TransferAsset {
                     network_id,
-                    burns: burns.into_iter().map(From::from).collect(),
-                    inputs: inputs.into_iter().map(From::from).collect(),
+                    burns: burns.into_iter().map(TryFrom::try_from).collect::<Result<_, _>>()?,
+                    inputs: inputs.into_iter().map(TryFrom::try_from).collect::<Result<_, _>>()?,
                     outputs,
                     metadata,
                     approvals,
-                    expiration: expiration.map(From::from),
+                    expiration: expiration.map(u64::try_from).transpose()?,
                 }
             }
             Action::ChangeAssetScheme {
@@ -519,21 +590,24 @@ impl TryFrom<Action> for ActionType {
                 receiver,
             } => ActionType::UnwrapCCC {
                 network_id,
-                burn: burn.into(),
+                burn: burn.try_into()?,
                 receiver: receiver.try_into_address()?,
             },
             Action::Pay {
                 receiver,
                 quantity,
+                memo,
             } => ActionType::Pay {
                 receiver: receiver.try_into_address()?,
-                quantity: quantity.into(),
+                quantity: quantity.try_into()?,
+                memo: memo.map(|memo| memo.from_hex()).transpose()?,
             },
             Action::SetRegularKey {
                 key,
             } => ActionType::SetRegularKey {
                 key,
             },
+            Action::RevokeRegularKey => ActionType::RevokeRegularKey,
             Action::CreateShard {
                 users,
             } => {
@@ -552,6 +626,28 @@ impl TryFrom<Action> for ActionType {
                     owners: owners?,
                 }
             }
+            Action::OfferShardOwnership {
+                shard_id,
+                new_owners,
+                expiry,
+            } => {
+                let new_owners: Result<_, _> = new_owners.into_iter().map(PlatformAddress::try_into_address).collect();
+                ActionType::OfferShardOwnership {
+                    shard_id,
+                    new_owners: new_owners?,
+                    expiry: expiry.try_into()?,
+                }
+            }
+            Action::AcceptShardOwnership {
+                shard_id,
+            } => ActionType::AcceptShardOwnership {
+                shard_id,
+            },
+            Action::CancelShardOwnershipOffer {
+                shard_id,
+            } => ActionType::CancelShardOwnershipOffer {
+                shard_id,
+            },
             Action::SetShardUsers {
                 shard_id,
                 users,
@@ -562,6 +658,16 @@ impl TryFrom<Action> for ActionType {
                     users: users?,
                 }
             }
+            Action::CreateApproverGroup {
+                members,
+                threshold,
+            } => {
+                let members: Result<_, _> = members.into_iter().map(PlatformAddress::try_into_address).collect();
+                ActionType::CreateApproverGroup {
+                    members: members?,
+                    threshold,
+                }
+            }
             Action::WrapCCC {
                 shard_id,
                 lock_script_hash,
@@ -574,7 +680,7 @@ impl TryFrom<Action> for ActionType {
                     shard_id,
                     lock_script_hash,
                     parameters,
-                    quantity: quantity.into(),
+                    quantity: quantity.try_into()?,
                     payer: payer.try_into_address()?,
                 }
             }
@@ -598,7 +704,7 @@ impl TryFrom<Action> for ActionType {
                 handler_id,
                 bytes,
             } => ActionType::Custom {
-                handler_id: handler_id.into(),
+                handler_id: handler_id.try_into()?,
                 bytes,
             },
         })
@@ -707,4 +813,53 @@ mod tests {
         let expected = r#"{"type":"mintAsset","networkId":"ab","shardId":0,"metadata":"string with 'an apostrophe’","approver":null,"registrar":null,"allowedScriptHashes":[],"output":{"lockScriptHash":"0x0000000000000000000000000000000000000000","parameters":[],"supply":"0x1"},"approvals":[],"tracker":"0x0000000000000000000000000000000000000000000000000000000000000000"}"#;
         assert_eq!(&s, expected);
     }
+
+    #[test]
+    fn serialize_revoke_regular_key() {
+        let s = to_string(&ActionWithTracker::RevokeRegularKey).unwrap();
+        assert_eq!(&s, r#"{"type":"revokeRegularKey"}"#);
+    }
+
+    #[test]
+    fn parse_revoke_regular_key() {
+        let action: Action = from_str(r#"{"type":"revokeRegularKey"}"#).unwrap();
+        assert_eq!(Action::RevokeRegularKey, action);
+        assert_eq!(Some(ActionType::RevokeRegularKey), ActionType::try_from(action).ok());
+    }
+
+    #[test]
+    fn overflowing_handler_id_is_rejected() {
+        let action: Action = from_str(r#"{"type":"custom","handlerId":"0xffffffffffffffff","bytes":[]}"#).unwrap();
+        assert!(ActionType::try_from(action).is_ok());
+
+        let action: Action = from_str(r#"{"type":"custom","handlerId":"0x10000000000000000","bytes":[]}"#).unwrap();
+        match ActionType::try_from(action) {
+            Err(ConversionError::Overflow(err)) => assert_eq!("u64", err.type_name),
+            Err(_) => panic!("Unexpected error"),
+            Ok(action) => panic!("{:?} must be rejected", action),
+        }
+    }
+
+    #[test]
+    fn parse_offer_shard_ownership() {
+        let action: Action =
+            from_str(r#"{"type":"offerShardOwnership","shardId":1,"newOwners":[],"expiry":"0x64"}"#).unwrap();
+        assert_eq!(
+            Some(ActionType::OfferShardOwnership {
+                shard_id: 1,
+                new_owners: vec![],
+                expiry: 100,
+            }),
+            ActionType::try_from(action).ok()
+        );
+    }
+
+    #[test]
+    fn serialize_accept_shard_ownership() {
+        let s = to_string(&ActionWithTracker::AcceptShardOwnership {
+            shard_id: 2,
+        })
+        .unwrap();
+        assert_eq!(&s, r#"{"type":"acceptShardOwnership","shardId":2}"#);
+    }
 }
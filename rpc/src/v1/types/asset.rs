@@ -16,6 +16,7 @@
 
 use cjson::uint::Uint;
 use cstate::{Asset as AssetType, OwnedAsset as OwnedAssetType};
+use ctypes::Tracker;
 use primitives::H160;
 use rustc_serialize::hex::ToHex;
 use std::ops::Deref;
@@ -36,6 +37,15 @@ pub struct OwnedAsset {
     parameters: Vec<String>,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnspentAsset {
+    tracker: Tracker,
+    index: usize,
+    #[serde(flatten)]
+    asset: OwnedAsset,
+}
+
 impl From<AssetType> for Asset {
     fn from(asset: AssetType) -> Self {
         Self {
@@ -57,3 +67,13 @@ impl From<OwnedAssetType> for OwnedAsset {
         }
     }
 }
+
+impl From<(Tracker, usize, OwnedAssetType)> for UnspentAsset {
+    fn from((tracker, index, asset): (Tracker, usize, OwnedAssetType)) -> Self {
+        Self {
+            tracker,
+            index,
+            asset: asset.into(),
+        }
+    }
+}
@@ -14,10 +14,11 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use cjson::uint::Uint;
+use cjson::uint::{OverflowError, Uint};
 use ctypes::transaction::{AssetOutPoint as AssetOutPointType, AssetTransferInput as AssetTransferInputType, Timelock};
 use ctypes::{ShardId, Tracker};
 use primitives::{Bytes, H160};
+use std::convert::{TryFrom, TryInto};
 
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -41,15 +42,16 @@ impl From<AssetOutPointType> for AssetOutPoint {
     }
 }
 
-impl From<AssetOutPoint> for AssetOutPointType {
-    fn from(from: AssetOutPoint) -> Self {
-        AssetOutPointType {
+impl TryFrom<AssetOutPoint> for AssetOutPointType {
+    type Error = OverflowError;
+    fn try_from(from: AssetOutPoint) -> Result<Self, Self::Error> {
+        Ok(AssetOutPointType {
             tracker: from.tracker,
             index: from.index,
             asset_type: from.asset_type,
             shard_id: from.shard_id,
-            quantity: from.quantity.into(),
-        }
+            quantity: from.quantity.try_into()?,
+        })
     }
 }
 
@@ -73,13 +75,14 @@ impl From<AssetTransferInputType> for AssetTransferInput {
     }
 }
 
-impl From<AssetTransferInput> for AssetTransferInputType {
-    fn from(from: AssetTransferInput) -> Self {
-        AssetTransferInputType {
-            prev_out: from.prev_out.into(),
+impl TryFrom<AssetTransferInput> for AssetTransferInputType {
+    type Error = OverflowError;
+    fn try_from(from: AssetTransferInput) -> Result<Self, Self::Error> {
+        Ok(AssetTransferInputType {
+            prev_out: from.prev_out.try_into()?,
             timelock: from.timelock,
             lock_script: from.lock_script,
             unlock_script: from.unlock_script,
-        }
+        })
     }
 }
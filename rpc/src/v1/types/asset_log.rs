@@ -0,0 +1,70 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{AssetLogFilter as AssetLogFilterType, LocalizedAssetLog};
+use cjson::uint::Uint;
+use ctypes::{BlockHash, BlockNumber, ShardId, Tracker};
+use primitives::H160;
+
+/// A mint, a transfer or a burn of an asset. A mint has no `from` and a burn has no `to`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetLog {
+    block_number: BlockNumber,
+    block_hash: BlockHash,
+    tracker: Tracker,
+    shard_id: ShardId,
+    asset_type: H160,
+    from: Vec<H160>,
+    to: Option<H160>,
+    quantity: Uint,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AssetLogFilter {
+    asset_type: Option<H160>,
+    shard_id: Option<ShardId>,
+    from_block: BlockNumber,
+    to_block: Option<BlockNumber>,
+}
+
+impl From<LocalizedAssetLog> for AssetLog {
+    fn from(log: LocalizedAssetLog) -> Self {
+        Self {
+            block_number: log.block_number,
+            block_hash: log.block_hash,
+            tracker: log.log.tracker,
+            shard_id: log.log.shard_id,
+            asset_type: log.log.asset_type,
+            from: log.log.from,
+            to: log.log.to,
+            quantity: log.log.quantity.into(),
+        }
+    }
+}
+
+impl AssetLogFilter {
+    /// The filter ends at the given best block number if `toBlock` is not given.
+    pub fn into_filter(self, best_block_number: BlockNumber) -> AssetLogFilterType {
+        AssetLogFilterType {
+            asset_type: self.asset_type,
+            shard_id: self.shard_id,
+            from_block: self.from_block,
+            to_block: self.to_block.unwrap_or(best_block_number),
+        }
+    }
+}
@@ -15,12 +15,13 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 extern crate rustc_serialize;
 
+use super::super::errors::ConversionError;
 use cjson::uint::Uint;
 use ctypes::transaction::{AssetMintOutput as AssetMintOutputType, AssetTransferOutput as AssetTransferOutputType};
 use ctypes::ShardId;
 use primitives::H160;
-use rustc_serialize::hex::{FromHex, FromHexError, ToHex};
-use std::convert::TryFrom;
+use rustc_serialize::hex::{FromHex, ToHex};
+use std::convert::{TryFrom, TryInto};
 use std::iter::FromIterator;
 use std::ops::Deref;
 
@@ -47,14 +48,14 @@ impl From<AssetTransferOutputType> for AssetTransferOutput {
 }
 
 impl TryFrom<AssetTransferOutput> for AssetTransferOutputType {
-    type Error = FromHexError;
+    type Error = ConversionError;
     fn try_from(from: AssetTransferOutput) -> Result<Self, Self::Error> {
         Ok(AssetTransferOutputType {
             lock_script_hash: from.lock_script_hash,
             parameters: Result::from_iter(from.parameters.iter().map(Deref::deref).map(FromHex::from_hex))?,
             asset_type: from.asset_type,
             shard_id: from.shard_id,
-            quantity: from.quantity.into(),
+            quantity: from.quantity.try_into()?,
         })
     }
 }
@@ -78,12 +79,12 @@ impl From<AssetMintOutputType> for AssetMintOutput {
 }
 
 impl TryFrom<AssetMintOutput> for AssetMintOutputType {
-    type Error = FromHexError;
+    type Error = ConversionError;
     fn try_from(from: AssetMintOutput) -> Result<Self, Self::Error> {
         Ok(AssetMintOutputType {
             lock_script_hash: from.lock_script_hash,
             parameters: Result::from_iter(from.parameters.iter().map(Deref::deref).map(FromHex::from_hex))?,
-            supply: from.supply.into(),
+            supply: from.supply.try_into()?,
         })
     }
 }
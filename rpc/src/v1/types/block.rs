@@ -15,9 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::Transaction;
-use ccore::{Block as CoreBlock, LocalizedTransaction};
+use ccore::{encoded, Block as CoreBlock, LocalizedTransaction};
 use ckey::{NetworkId, PlatformAddress};
-use ctypes::{BlockHash, BlockNumber};
+use ctypes::{BlockHash, BlockNumber, Header as CoreHeader};
 use primitives::{H256, U256};
 
 #[derive(Debug, Serialize)]
@@ -38,6 +38,8 @@ pub struct Block {
 
     hash: BlockHash,
     transactions: Vec<Transaction>,
+
+    size: Option<usize>,
 }
 
 impl Block {
@@ -68,6 +70,57 @@ impl Block {
 
             hash: block.header.hash(),
             transactions: transactions.map(From::from).collect(),
+
+            size: None,
+        }
+    }
+
+    /// Creates the response from the stored RLP, which also gives the encoded size of the block.
+    pub fn from_encoded(block: encoded::Block, network_id: NetworkId) -> Self {
+        let size = block.rlp().as_raw().len();
+        Block {
+            size: Some(size),
+            ..Self::from_core(block.decode(), network_id)
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Header {
+    parent_hash: BlockHash,
+    timestamp: u64,
+    number: u64,
+    author: PlatformAddress,
+
+    extra_data: Vec<u8>,
+
+    transactions_root: H256,
+    state_root: H256,
+
+    score: U256,
+    seal: Vec<Vec<u8>>,
+
+    hash: BlockHash,
+}
+
+impl Header {
+    pub fn from_core(header: CoreHeader, network_id: NetworkId) -> Self {
+        Header {
+            parent_hash: *header.parent_hash(),
+            timestamp: header.timestamp(),
+            number: header.number(),
+            author: PlatformAddress::new_v1(network_id, *header.author()),
+
+            extra_data: header.extra_data().clone(),
+
+            transactions_root: *header.transactions_root(),
+            state_root: *header.state_root(),
+
+            score: *header.score(),
+            seal: header.seal().to_vec(),
+
+            hash: header.hash(),
         }
     }
 }
@@ -78,3 +131,11 @@ pub struct BlockNumberAndHash {
     pub number: BlockNumber,
     pub hash: BlockHash,
 }
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finalization {
+    pub finalized: bool,
+    /// The number of the canonical blocks from the block to the best block, including both.
+    pub confirmations: u64,
+}
@@ -0,0 +1,125 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ccore::BlockId;
+use ctypes::BlockHash;
+use serde::de::{Error, IntoDeserializer, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+
+/// The block number parameter of the RPCs that query the state.
+///
+/// It is either a block number, `"latest"` or `"pending"`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockNumberOrTag {
+    Number(u64),
+    Latest,
+    /// The state of the block being prepared, including the pending transactions.
+    Pending,
+}
+
+impl<'a> Deserialize<'a> for BlockNumberOrTag {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'a>, {
+        deserializer.deserialize_any(BlockNumberOrTagVisitor)
+    }
+}
+
+struct BlockNumberOrTagVisitor;
+
+impl<'a> Visitor<'a> for BlockNumberOrTagVisitor {
+    type Value = BlockNumberOrTag;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a block number, `latest` or `pending`")
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: Error, {
+        Ok(BlockNumberOrTag::Number(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: Error, {
+        match value {
+            "latest" => Ok(BlockNumberOrTag::Latest),
+            "pending" => Ok(BlockNumberOrTag::Pending),
+            _ => Err(Error::custom(format!("Invalid block number: {}", value))),
+        }
+    }
+}
+
+/// The block parameter of the RPCs that take any known block, including the ones off the canonical chain.
+///
+/// It is either a block number, a block hash, `"earliest"` or `"latest"`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockIdOrTag {
+    Number(u64),
+    Hash(BlockHash),
+    Earliest,
+    Latest,
+}
+
+impl From<BlockIdOrTag> for BlockId {
+    fn from(id: BlockIdOrTag) -> Self {
+        match id {
+            BlockIdOrTag::Number(number) => BlockId::Number(number),
+            BlockIdOrTag::Hash(hash) => BlockId::Hash(hash),
+            BlockIdOrTag::Earliest => BlockId::Earliest,
+            BlockIdOrTag::Latest => BlockId::Latest,
+        }
+    }
+}
+
+impl<'a> Deserialize<'a> for BlockIdOrTag {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'a>, {
+        deserializer.deserialize_any(BlockIdOrTagVisitor)
+    }
+}
+
+struct BlockIdOrTagVisitor;
+
+impl<'a> Visitor<'a> for BlockIdOrTagVisitor {
+    type Value = BlockIdOrTag;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a block number, a block hash, `earliest` or `latest`")
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: Error, {
+        Ok(BlockIdOrTag::Number(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: Error, {
+        match value {
+            "earliest" => Ok(BlockIdOrTag::Earliest),
+            "latest" => Ok(BlockIdOrTag::Latest),
+            _ => BlockHash::deserialize(value.into_deserializer())
+                .map(BlockIdOrTag::Hash)
+                .map_err(|_: E| Error::custom(format!("Invalid block id: {}", value))),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::BlockRangeError;
+use cjson::bytes::Bytes;
+
+/// A notification of `devel_streamBlocks`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum BlockStreamItem {
+    /// `count` blocks from block `from`.
+    /// Each block is its RLP prefixed with the length as a 4-byte big-endian integer.
+    Blocks {
+        from: u64,
+        count: usize,
+        data: Bytes,
+    },
+    /// All the blocks in the range have been sent.
+    End,
+    /// The stream stopped at block `number`, since it's not available.
+    Error {
+        reason: BlockStreamErrorReason,
+        number: u64,
+        message: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BlockStreamErrorReason {
+    BodyPruned,
+    NotFound,
+}
+
+impl From<BlockRangeError> for BlockStreamItem {
+    fn from(error: BlockRangeError) -> Self {
+        let reason = match error {
+            BlockRangeError::BodyPruned {
+                ..
+            } => BlockStreamErrorReason::BodyPruned,
+            BlockRangeError::NotFound {
+                ..
+            } => BlockStreamErrorReason::NotFound,
+        };
+        BlockStreamItem::Error {
+            reason,
+            number: error.number(),
+            message: error.to_string(),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::OwnedAsset;
+use cjson::uint::Uint;
+use ckey::{NetworkId, PlatformAddress};
+use cstate::{AccountChange as CoreAccountChange, AssetChange as CoreAssetChange, DryRun as CoreDryRun};
+use ctypes::{ShardId, Tracker};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRun {
+    success: bool,
+    error_hint: Option<String>,
+    accounts: Vec<AccountChange>,
+    assets: Vec<AssetChange>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountChange {
+    address: PlatformAddress,
+    prev_balance: Uint,
+    balance: Uint,
+    prev_seq: u64,
+    seq: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetChange {
+    shard_id: ShardId,
+    tracker: Tracker,
+    index: usize,
+    /// None if the asset is created by the transaction.
+    prev: Option<OwnedAsset>,
+    /// None if the asset is spent by the transaction.
+    current: Option<OwnedAsset>,
+}
+
+impl DryRun {
+    pub fn from_core(dry_run: CoreDryRun, network_id: NetworkId) -> Self {
+        Self {
+            success: dry_run.error.is_none(),
+            error_hint: dry_run.error.map(|err| err.to_string()),
+            accounts: dry_run.accounts.into_iter().map(|change| AccountChange::from_core(change, network_id)).collect(),
+            assets: dry_run.assets.into_iter().map(AssetChange::from).collect(),
+        }
+    }
+
+    /// The transaction is rejected before it is executed.
+    pub fn rejected(error_hint: String) -> Self {
+        Self {
+            success: false,
+            error_hint: Some(error_hint),
+            accounts: vec![],
+            assets: vec![],
+        }
+    }
+}
+
+impl AccountChange {
+    fn from_core(change: CoreAccountChange, network_id: NetworkId) -> Self {
+        Self {
+            address: PlatformAddress::new_v1(network_id, change.address),
+            prev_balance: change.prev_balance.into(),
+            balance: change.balance.into(),
+            prev_seq: change.prev_seq,
+            seq: change.seq,
+        }
+    }
+}
+
+impl From<CoreAssetChange> for AssetChange {
+    fn from(change: CoreAssetChange) -> Self {
+        Self {
+            shard_id: change.shard_id,
+            tracker: change.tracker,
+            index: change.index,
+            prev: change.prev.map(From::from),
+            current: change.current.map(From::from),
+        }
+    }
+}
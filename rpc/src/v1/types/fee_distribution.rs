@@ -0,0 +1,59 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::stake;
+use ckey::{NetworkId, PlatformAddress};
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StakeholderShare {
+    address: PlatformAddress,
+    share: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeDistribution {
+    author: PlatformAddress,
+    author_reward: u64,
+    treasury: PlatformAddress,
+    total_min_fee: u64,
+    author_share: u64,
+    treasury_share: u64,
+    stakeholder_shares: Vec<StakeholderShare>,
+}
+
+impl FeeDistribution {
+    pub fn from_core(distribution: stake::FeeDistribution, network_id: NetworkId) -> Self {
+        Self {
+            author: PlatformAddress::new_v1(network_id, distribution.author),
+            author_reward: distribution.author_reward,
+            treasury: PlatformAddress::new_v1(network_id, distribution.treasury),
+            total_min_fee: distribution.shares.total_min_fee,
+            author_share: distribution.shares.author,
+            treasury_share: distribution.shares.treasury,
+            stakeholder_shares: distribution
+                .shares
+                .stakeholders
+                .into_iter()
+                .map(|stakeholder| StakeholderShare {
+                    address: PlatformAddress::new_v1(network_id, stakeholder.address),
+                    share: stakeholder.share,
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageTimings {
+    count: u64,
+    total_micros: u64,
+    histogram: Vec<u64>,
+}
+
+impl From<ccore::StageTimings> for StageTimings {
+    fn from(timings: ccore::StageTimings) -> Self {
+        Self {
+            count: timings.count,
+            total_micros: timings.total.as_micros() as u64,
+            histogram: timings.histogram,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueTimings {
+    queued: StageTimings,
+    verification: StageTimings,
+    verified: StageTimings,
+    commit: StageTimings,
+    total: StageTimings,
+}
+
+impl From<ccore::QueueTimings> for QueueTimings {
+    fn from(timings: ccore::QueueTimings) -> Self {
+        Self {
+            queued: timings.queued.into(),
+            verification: timings.verification.into(),
+            verified: timings.verified.into(),
+            commit: timings.commit.into(),
+            total: timings.total.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportMetrics {
+    histogram_bounds_micros: Vec<u64>,
+    headers: QueueTimings,
+    blocks: QueueTimings,
+}
+
+impl From<ccore::ImportMetrics> for ImportMetrics {
+    fn from(metrics: ccore::ImportMetrics) -> Self {
+        Self {
+            histogram_bounds_micros: ccore::HISTOGRAM_BOUNDS_MICROS.to_vec(),
+            headers: metrics.headers.into(),
+            blocks: metrics.blocks.into(),
+        }
+    }
+}
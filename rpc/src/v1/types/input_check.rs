@@ -0,0 +1,89 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use super::OwnedAsset;
+use cjson::uint::Uint;
+use cstate::{InputCheck as InputCheckType, InputMismatch as InputMismatchType};
+use ctypes::transaction::AssetOutPoint;
+use ctypes::{ShardId, Tracker};
+use primitives::H160;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferInputCheck {
+    shard_id: ShardId,
+    tracker: Tracker,
+    index: usize,
+    burn: bool,
+    found: bool,
+    duplicated: bool,
+    /// None if the asset is already spent or never existed.
+    asset: Option<OwnedAsset>,
+    mismatches: Vec<InputMismatch>,
+}
+
+/// The expected values are the ones in the state, and the found values are the ones in the input.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "field", rename_all = "camelCase")]
+pub enum InputMismatch {
+    AssetType {
+        expected: H160,
+        found: H160,
+    },
+    Quantity {
+        expected: Uint,
+        found: Uint,
+    },
+    LockScriptHash {
+        expected: H160,
+        found: H160,
+    },
+}
+
+impl TransferInputCheck {
+    pub fn from_core(prev_out: &AssetOutPoint, burn: bool, check: InputCheckType) -> Self {
+        Self {
+            shard_id: prev_out.shard_id,
+            tracker: prev_out.tracker,
+            index: prev_out.index,
+            burn,
+            found: check.asset.is_some(),
+            duplicated: check.duplicated,
+            asset: check.asset.map(From::from),
+            mismatches: check.mismatches.into_iter().map(From::from).collect(),
+        }
+    }
+}
+
+impl From<InputMismatchType> for InputMismatch {
+    fn from(mismatch: InputMismatchType) -> Self {
+        match mismatch {
+            InputMismatchType::AssetType(mismatch) => InputMismatch::AssetType {
+                expected: mismatch.expected,
+                found: mismatch.found,
+            },
+            InputMismatchType::Quantity(mismatch) => InputMismatch::Quantity {
+                expected: mismatch.expected.into(),
+                found: mismatch.found.into(),
+            },
+            InputMismatchType::LockScriptHash(mismatch) => InputMismatch::LockScriptHash {
+                expected: mismatch.expected,
+                found: mismatch.found,
+            },
+        }
+    }
+}
@@ -14,6 +14,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use cjson::uint::Uint;
+use ctypes::TxHash;
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MemPoolMinFees {
@@ -31,6 +34,7 @@ pub struct MemPoolMinFees {
     min_asset_scheme_change_cost: u64,
     min_asset_supply_increase_cost: u64,
     min_asset_unwrap_ccc_cost: u64,
+    min_pay_memo_byte_cost: u64,
 }
 
 impl From<ccore::MemPoolMinFees> for MemPoolMinFees {
@@ -50,6 +54,131 @@ impl From<ccore::MemPoolMinFees> for MemPoolMinFees {
             min_asset_scheme_change_cost: fees.min_asset_scheme_change_cost,
             min_asset_supply_increase_cost: fees.min_asset_supply_increase_cost,
             min_asset_unwrap_ccc_cost: fees.min_asset_unwrap_ccc_cost,
+            min_pay_memo_byte_cost: fees.min_pay_memo_byte_cost,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TxOrigin {
+    Local,
+    External,
+    RetractedBlock,
+}
+
+impl From<ccore::TxOrigin> for TxOrigin {
+    fn from(origin: ccore::TxOrigin) -> Self {
+        match origin {
+            ccore::TxOrigin::Local => TxOrigin::Local,
+            ccore::TxOrigin::External => TxOrigin::External,
+            ccore::TxOrigin::RetractedBlock => TxOrigin::RetractedBlock,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FutureReason {
+    #[serde(rename_all = "camelCase")]
+    SeqGap {
+        expected_seq: u64,
+    },
+    Timelock,
+}
+
+impl From<ccore::FutureReason> for FutureReason {
+    fn from(reason: ccore::FutureReason) -> Self {
+        match reason {
+            ccore::FutureReason::SeqGap {
+                expected_seq,
+            } => FutureReason::SeqGap {
+                expected_seq,
+            },
+            ccore::FutureReason::Timelock => FutureReason::Timelock,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemPoolEntry {
+    hash: TxHash,
+    seq: u64,
+    fee: Uint,
+    origin: TxOrigin,
+    inserted_block_number: u64,
+    inserted_timestamp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<FutureReason>,
+    below_fee_floor: bool,
+}
+
+impl From<ccore::MemPoolEntry> for MemPoolEntry {
+    fn from(entry: ccore::MemPoolEntry) -> Self {
+        Self {
+            hash: entry.hash,
+            seq: entry.seq,
+            fee: entry.fee.into(),
+            origin: entry.origin.into(),
+            inserted_block_number: entry.inserted_block_number,
+            inserted_timestamp: entry.inserted_timestamp,
+            reason: entry.future_reason.map(Into::into),
+            below_fee_floor: entry.below_fee_floor,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingBySender {
+    current: Vec<MemPoolEntry>,
+    future: Vec<MemPoolEntry>,
+}
+
+impl From<Vec<ccore::MemPoolEntry>> for PendingBySender {
+    fn from(entries: Vec<ccore::MemPoolEntry>) -> Self {
+        let (future, current): (Vec<_>, Vec<_>) = entries.into_iter().partition(|entry| entry.future_reason.is_some());
+        Self {
+            current: current.into_iter().map(Into::into).collect(),
+            future: future.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeqGapOrphan {
+    seq: u64,
+    hash: TxHash,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeqGapInfo {
+    chain_seq: u64,
+    /// The seqs from `readyFrom` to `readyTo`, excluding `readyTo`, are ready in the pool.
+    ready_from: u64,
+    ready_to: u64,
+    first_gap: Option<u64>,
+    orphans: Vec<SeqGapOrphan>,
+}
+
+impl From<ccore::SeqGapInfo> for SeqGapInfo {
+    fn from(info: ccore::SeqGapInfo) -> Self {
+        Self {
+            chain_seq: info.chain_seq,
+            ready_from: info.ready.start,
+            ready_to: info.ready.end,
+            first_gap: info.first_gap,
+            orphans: info
+                .orphans
+                .into_iter()
+                .map(|(seq, hash)| SeqGapOrphan {
+                    seq,
+                    hash,
+                })
+                .collect(),
         }
     }
 }
@@ -0,0 +1,56 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::MinerStatus;
+use ckey::{NetworkId, PlatformAddress};
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MiningStatus {
+    sealing_enabled: bool,
+    author: PlatformAddress,
+    /// The UNIX timestamp in seconds
+    last_sealed_at: Option<u64>,
+    pending_transactions: usize,
+    future_transactions: usize,
+    local_transactions: usize,
+    transactions_in_pending_block: usize,
+    effective_min_fee: u64,
+    /// Milliseconds until a new transaction can trigger a reseal
+    min_reseal_period_left: u64,
+    /// Milliseconds until a block is sealed without transactions
+    max_reseal_period_left: u64,
+}
+
+impl MiningStatus {
+    pub fn from_core(status: MinerStatus, network_id: NetworkId) -> Self {
+        Self {
+            sealing_enabled: status.sealing_enabled,
+            author: PlatformAddress::new_v1(network_id, status.author),
+            last_sealed_at: status
+                .last_sealed_at
+                .map(|at| at.duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs()),
+            pending_transactions: status.transactions_in_pending_queue,
+            future_transactions: status.transactions_in_future_queue,
+            local_transactions: status.local_transactions_in_queue,
+            transactions_in_pending_block: status.tranasction_in_pending_block,
+            effective_min_fee: status.effective_minimum_fee,
+            min_reseal_period_left: status.reseal_timers.min_period_left.as_millis() as u64,
+            max_reseal_period_left: status.reseal_timers.max_period_left.as_millis() as u64,
+        }
+    }
+}
@@ -17,13 +17,31 @@
 mod action;
 mod asset;
 mod asset_input;
+mod asset_log;
 mod asset_output;
 mod asset_scheme;
 mod block;
+mod block_number;
+mod block_stream;
+mod dry_run;
+mod fee_distribution;
+mod import_metrics;
+mod input_check;
 mod mem_pool;
+mod mining_status;
+mod orphaned_proposal;
+mod shard_entry;
+mod snapshot;
+mod stake_audit;
+mod state_cache;
+mod subscription;
+mod sync_status;
 mod text;
+mod timer;
 mod transaction;
+mod tree_route;
 mod unsigned_transaction;
+mod validator;
 mod work;
 
 use self::asset::Asset;
@@ -31,15 +49,35 @@ use self::asset_input::AssetTransferInput;
 use self::asset_output::{AssetMintOutput, AssetTransferOutput};
 
 pub use self::action::{Action, ActionWithTracker};
-pub use self::asset::OwnedAsset;
+pub use self::asset::{OwnedAsset, UnspentAsset};
+pub use self::asset_log::{AssetLog, AssetLogFilter};
 pub use self::asset_scheme::AssetScheme;
 pub use self::block::Block;
 pub use self::block::BlockNumberAndHash;
-pub use self::mem_pool::MemPoolMinFees;
-pub use self::text::Text;
-pub use self::transaction::{PendingTransactions, Transaction};
+pub use self::block::Finalization;
+pub use self::block::Header;
+pub use self::block_number::{BlockIdOrTag, BlockNumberOrTag};
+pub use self::block_stream::BlockStreamItem;
+pub use self::dry_run::DryRun;
+pub use self::fee_distribution::FeeDistribution;
+pub use self::import_metrics::ImportMetrics;
+pub use self::input_check::TransferInputCheck;
+pub use self::mem_pool::{MemPoolMinFees, PendingBySender, SeqGapInfo};
+pub use self::mining_status::MiningStatus;
+pub use self::orphaned_proposal::OrphanedProposal;
+pub use self::shard_entry::ShardEntries;
+pub use self::snapshot::Snapshot;
+pub use self::stake_audit::StakeAudit;
+pub use self::state_cache::StateCacheStats;
+pub use self::subscription::SubscriptionKind;
+pub use self::sync_status::SyncStatus;
+pub use self::text::{IndexedText, Text};
+pub use self::timer::Timer;
+pub use self::transaction::{PendingTransactions, ReadyTransactions, Transaction, TransactionAttempt};
+pub use self::tree_route::TreeRoute;
 pub use self::unsigned_transaction::UnsignedTransaction;
-pub use self::work::Work;
+pub use self::validator::{Validator, ValidatorLiveness};
+pub use self::work::{PendingSeal, Work};
 
 use ctypes::TxHash;
 use serde::de::{self, Deserialize, Deserializer};
@@ -50,6 +88,24 @@ pub struct FilterStatus {
     pub enabled: bool,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionState {
+    pub name: String,
+    pub enabled: bool,
+    pub dropped_messages: usize,
+}
+
+impl From<::cnetwork::ExtensionState> for ExtensionState {
+    fn from(state: ::cnetwork::ExtensionState) -> Self {
+        Self {
+            name: state.name,
+            enabled: state.enabled,
+            dropped_messages: state.dropped_messages,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SendTransactionResult {
     pub hash: TxHash,
@@ -0,0 +1,41 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::OrphanedProposal as CoreOrphanedProposal;
+use ckey::{NetworkId, PlatformAddress};
+use ctypes::{BlockHash, BlockNumber};
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedProposal {
+    height: BlockNumber,
+    view: u64,
+    hash: BlockHash,
+    author: PlatformAddress,
+    is_own: bool,
+}
+
+impl OrphanedProposal {
+    pub fn from_core(proposal: CoreOrphanedProposal, network_id: NetworkId) -> Self {
+        Self {
+            height: proposal.height,
+            view: proposal.view,
+            hash: proposal.hash,
+            author: PlatformAddress::new_v1(network_id, proposal.author),
+            is_own: proposal.is_own,
+        }
+    }
+}
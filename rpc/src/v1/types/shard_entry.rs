@@ -0,0 +1,74 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{AssetScheme, OwnedAsset, Text};
+use cjson::bytes::Bytes;
+use ckey::NetworkId;
+use cstate::ShardEntry as ShardEntryType;
+use primitives::H256;
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum ShardEntryValue {
+    AssetScheme(AssetScheme),
+    OwnedAsset(OwnedAsset),
+    Text(Text),
+    Opaque(Bytes),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardEntry {
+    key: H256,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    value: ShardEntryValue,
+}
+
+impl ShardEntry {
+    pub fn from_core(key: H256, entry: ShardEntryType, network_id: NetworkId) -> Self {
+        let (kind, value) = match entry {
+            ShardEntryType::AssetScheme(scheme) => {
+                ("assetScheme", ShardEntryValue::AssetScheme(AssetScheme::from_core(scheme, network_id)))
+            }
+            ShardEntryType::OwnedAsset(asset) => ("ownedAsset", ShardEntryValue::OwnedAsset(asset.into())),
+            ShardEntryType::Text(text) => ("text", ShardEntryValue::Text(Text::from_core(text, network_id))),
+            ShardEntryType::Opaque(bytes) => ("opaque", ShardEntryValue::Opaque(bytes.into())),
+        };
+        Self {
+            key,
+            kind,
+            value,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardEntries {
+    entries: Vec<ShardEntry>,
+    /// The key to continue from. `None` if there are no more entries.
+    next_key: Option<H256>,
+}
+
+impl ShardEntries {
+    pub fn from_core(entries: Vec<(H256, ShardEntryType)>, next_key: Option<H256>, network_id: NetworkId) -> Self {
+        Self {
+            entries: entries.into_iter().map(|(key, entry)| ShardEntry::from_core(key, entry, network_id)).collect(),
+            next_key,
+        }
+    }
+}
@@ -0,0 +1,39 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use csync::SnapshotManifest;
+use ctypes::BlockHash;
+use primitives::H256;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    block_hash: BlockHash,
+    block_number: u64,
+    state_root: H256,
+    chunks: Vec<H256>,
+}
+
+impl From<SnapshotManifest> for Snapshot {
+    fn from(manifest: SnapshotManifest) -> Self {
+        Self {
+            block_hash: manifest.block_hash,
+            block_number: manifest.block_number,
+            state_root: manifest.state_root,
+            chunks: manifest.chunks,
+        }
+    }
+}
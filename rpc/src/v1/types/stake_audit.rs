@@ -0,0 +1,39 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::stake;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StakeAudit {
+    balances: u64,
+    delegations: u64,
+    total_ccs: u64,
+    candidate_deposits: u64,
+    prisoner_deposits: u64,
+}
+
+impl From<stake::CcsAudit> for StakeAudit {
+    fn from(audit: stake::CcsAudit) -> Self {
+        Self {
+            balances: audit.balances,
+            delegations: audit.delegations,
+            total_ccs: audit.total_ccs(),
+            candidate_deposits: audit.candidate_deposits,
+            prisoner_deposits: audit.prisoner_deposits,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ctypes::BlockNumber;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    best_block_number: BlockNumber,
+    /// The highest block number whose header is downloaded from the peers
+    best_known_number: BlockNumber,
+    is_syncing: bool,
+    estimated_remaining_blocks: u64,
+}
+
+impl SyncStatus {
+    pub fn new(best_block_number: BlockNumber, best_known_number: BlockNumber) -> Self {
+        let best_known_number = best_known_number.max(best_block_number);
+        Self {
+            best_block_number,
+            best_known_number,
+            is_syncing: best_block_number < best_known_number,
+            estimated_remaining_blocks: best_known_number - best_block_number,
+        }
+    }
+}
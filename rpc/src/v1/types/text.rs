@@ -16,6 +16,7 @@
 
 use ckey::{NetworkId, PlatformAddress};
 use cstate::Text as TextType;
+use ctypes::TxHash;
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -32,3 +33,22 @@ impl Text {
         }
     }
 }
+
+/// A text found by the text index, with the hash of the transaction that stored it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedText {
+    pub transaction_hash: TxHash,
+    pub content: String,
+    pub certifier: PlatformAddress,
+}
+
+impl IndexedText {
+    pub fn from_core(transaction_hash: TxHash, from: TextType, network_id: NetworkId) -> Self {
+        Self {
+            transaction_hash,
+            content: from.content().to_string(),
+            certifier: PlatformAddress::new_v1(network_id, *from.certifier()),
+        }
+    }
+}
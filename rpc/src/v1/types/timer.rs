@@ -0,0 +1,39 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ctimer::ScheduledTimer;
+use std::time::Instant;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Timer {
+    name: String,
+    token: usize,
+    due_in_millis: u64,
+    repeat_millis: Option<u64>,
+}
+
+impl Timer {
+    pub fn new(timer: ScheduledTimer, now: Instant) -> Self {
+        Self {
+            name: timer.name.to_string(),
+            token: timer.token,
+            due_in_millis: timer.at.saturating_duration_since(now).as_millis() as u64,
+            repeat_millis: timer.repeat.map(|repeat| repeat.as_millis() as u64),
+        }
+    }
+}
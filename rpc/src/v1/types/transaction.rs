@@ -15,7 +15,11 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::ActionWithTracker;
-use ccore::{LocalizedTransaction, PendingSignedTransactions, SignedTransaction};
+use ccore::{
+    LocalizedTransaction, PendingSignedTransactions, ReadyTransactionsPage, SignedTransaction,
+    TransactionAttempt as CoreTransactionAttempt,
+};
+use cjson::bytes::Bytes;
 use cjson::uint::Uint;
 use ckey::{NetworkId, Signature};
 use ctypes::{BlockHash, TxHash};
@@ -35,6 +39,40 @@ pub struct Transaction {
     pub sig: Signature,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionAttempt {
+    pub hash: TxHash,
+    pub result: bool,
+    pub error_hint: Option<String>,
+    pub transaction: Option<Transaction>,
+}
+
+impl From<CoreTransactionAttempt> for TransactionAttempt {
+    fn from(attempt: CoreTransactionAttempt) -> Self {
+        match attempt {
+            CoreTransactionAttempt::Included(tx) => Self {
+                hash: tx.hash(),
+                result: true,
+                error_hint: None,
+                transaction: Some(Transaction {
+                    result: Some(true),
+                    ..Transaction::from(tx)
+                }),
+            },
+            CoreTransactionAttempt::Failed {
+                hash,
+                error_hint,
+            } => Self {
+                hash,
+                result: false,
+                error_hint: Some(error_hint),
+                transaction: None,
+            },
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PendingTransactions {
@@ -52,6 +90,24 @@ impl From<PendingSignedTransactions> for PendingTransactions {
     }
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadyTransactions {
+    transactions: Vec<Transaction>,
+    cursor: Option<Bytes>,
+    has_more: bool,
+}
+
+impl From<ReadyTransactionsPage> for ReadyTransactions {
+    fn from(page: ReadyTransactionsPage) -> Self {
+        Self {
+            transactions: page.transactions.into_iter().map(From::from).collect(),
+            cursor: page.cursor.map(|cursor| rlp::encode(&cursor).into()),
+            has_more: page.has_more,
+        }
+    }
+}
+
 impl From<LocalizedTransaction> for Transaction {
     fn from(p: LocalizedTransaction) -> Self {
         let sig = p.signature();
@@ -0,0 +1,41 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::TreeRoute as CoreTreeRoute;
+use ctypes::BlockHash;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeRoute {
+    ancestor: BlockHash,
+    /// From the `from` block to the child of the ancestor.
+    retracted: Vec<BlockHash>,
+    /// From the child of the ancestor to the `to` block.
+    enacted: Vec<BlockHash>,
+    /// True if the bodies of some blocks in the route have been pruned.
+    crosses_pruned_boundary: bool,
+}
+
+impl TreeRoute {
+    pub fn from_core(route: CoreTreeRoute, crosses_pruned_boundary: bool) -> Self {
+        Self {
+            ancestor: route.ancestor,
+            retracted: route.retracted,
+            enacted: route.enacted,
+            crosses_pruned_boundary,
+        }
+    }
+}
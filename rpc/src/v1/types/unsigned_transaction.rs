@@ -36,7 +36,7 @@ impl TryFrom<UnsignedTransaction> for (IncompleteTransaction, Option<u64>) {
     fn try_from(tx: UnsignedTransaction) -> Result<Self, Self::Error> {
         Ok((
             IncompleteTransaction {
-                fee: tx.fee.into(),
+                fee: tx.fee.try_into().map_err(errors::conversion)?,
                 network_id: tx.network_id,
                 action: tx.action.try_into().map_err(errors::conversion)?,
             },
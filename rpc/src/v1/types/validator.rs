@@ -0,0 +1,136 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::stake;
+use ckey::{public_to_address, NetworkId, PlatformAddress, Public};
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Validator {
+    address: PlatformAddress,
+    public_key: Public,
+    weight: u64,
+    delegation: u64,
+    deposit: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidatorLiveness {
+    address: PlatformAddress,
+    expected: u64,
+    signed: u64,
+    missed: u64,
+}
+
+impl ValidatorLiveness {
+    pub fn from_core(liveness: &stake::ValidatorLiveness, network_id: NetworkId) -> Self {
+        Self {
+            address: PlatformAddress::new_v1(network_id, liveness.address),
+            expected: liveness.expected,
+            signed: liveness.signed,
+            missed: liveness.missed(),
+        }
+    }
+}
+
+impl Validator {
+    pub fn from_core(validator: &stake::Validator, network_id: NetworkId) -> Self {
+        Self {
+            address: PlatformAddress::new_v1(network_id, public_to_address(validator.pubkey())),
+            public_key: *validator.pubkey(),
+            weight: validator.weight(),
+            delegation: validator.delegation(),
+            deposit: validator.deposit(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstate::tests::helpers;
+    use cstate::{ActionHandler, TopState, TopStateView};
+    use ctypes::CommonParams;
+    use rlp::Encodable;
+    use std::collections::HashMap;
+
+    #[test]
+    fn elected_validators_are_serialized_with_their_stakes() {
+        let mut state = helpers::get_temp_state_with_metadata();
+        let mut params = CommonParams::default_for_test();
+        params.set_dynamic_validator_params_for_test(30, 10, 3, 20, 30, 1, 1, 100, 100);
+        state.update_params(0, params).unwrap();
+
+        let candidate = Public::random();
+        let candidate_address = public_to_address(&candidate);
+        let delegator = Public::random();
+        let delegator_address = public_to_address(&delegator);
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator_address, 100);
+            stake::Stake::new(genesis_stakes)
+        };
+        stake.init(&mut state).unwrap();
+        state.add_balance(&candidate_address, 1000).unwrap();
+
+        let self_nominate = stake::Action::SelfNominate {
+            deposit: 200,
+            metadata: Default::default(),
+        };
+        stake.execute(&self_nominate.rlp_bytes(), &mut state, &candidate_address, &candidate).unwrap();
+        let delegate = stake::Action::DelegateCCS {
+            address: candidate_address,
+            quantity: 40,
+        };
+        stake.execute(&delegate.rlp_bytes(), &mut state, &delegator_address, &delegator).unwrap();
+
+        stake::on_term_close(&mut state, 1, &[]).unwrap();
+
+        let network_id = "tc".into();
+        let validators: Vec<_> =
+            stake::get_validators(&state).unwrap().iter().map(|v| Validator::from_core(v, network_id)).collect();
+        let expected = serde_json::json!([{
+            "address": PlatformAddress::new_v1(network_id, candidate_address),
+            "publicKey": candidate,
+            "weight": 40,
+            "delegation": 40,
+            "deposit": 200,
+        }]);
+        assert_eq!(expected, serde_json::to_value(&validators).unwrap());
+    }
+
+    #[test]
+    fn liveness_is_serialized_with_the_missed_precommits() {
+        let network_id = "tc".into();
+        let address = public_to_address(&Public::random());
+        let liveness = ValidatorLiveness::from_core(
+            &stake::ValidatorLiveness {
+                address,
+                expected: 10,
+                signed: 7,
+            },
+            network_id,
+        );
+        let expected = serde_json::json!({
+            "address": PlatformAddress::new_v1(network_id, address),
+            "expected": 10,
+            "signed": 7,
+            "missed": 3,
+        });
+        assert_eq!(expected, serde_json::to_value(&liveness).unwrap());
+    }
+}
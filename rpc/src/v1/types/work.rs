@@ -14,6 +14,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use ccore::PendingSeal as CorePendingSeal;
+use ckey::{NetworkId, PlatformAddress};
+use ctypes::{BlockHash, BlockNumber};
 use primitives::{H256, U256};
 
 #[derive(Debug, Serialize)]
@@ -22,3 +25,24 @@ pub struct Work {
     pub pow_hash: H256,
     pub target: U256,
 }
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingSeal {
+    /// The hash of the header without the seal, which the seal signs.
+    pub block_hash: BlockHash,
+    pub block_number: BlockNumber,
+    pub parent_hash: BlockHash,
+    pub author: PlatformAddress,
+}
+
+impl PendingSeal {
+    pub fn from_core(seal: CorePendingSeal, network_id: NetworkId) -> Self {
+        Self {
+            block_hash: seal.block_hash,
+            block_number: seal.number,
+            parent_hash: seal.parent_hash,
+            author: PlatformAddress::new_v1(network_id, seal.author),
+        }
+    }
+}
@@ -15,8 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::lru_cache::LruCache;
+use super::stats::{CacheKind, CacheSizes, CacheStats};
 use super::{ShardCache, TopCache};
-use crate::{Account, ActionData, AssetScheme, Metadata, OwnedAsset, RegularAccount, Shard, Text};
+use crate::{Account, ActionData, ApproverGroup, AssetScheme, Metadata, OwnedAsset, RegularAccount, Shard, Text};
 use ctypes::ShardId;
 use std::collections::{HashMap, HashSet};
 
@@ -27,34 +28,58 @@ pub struct GlobalCache {
     shard: LruCache<Shard>,
     text: LruCache<Text>,
     action_data: LruCache<ActionData>,
+    approver_group: LruCache<ApproverGroup>,
 
     asset_scheme: LruCache<AssetScheme>,
     asset: LruCache<OwnedAsset>,
+
+    stats: CacheStats,
 }
 
 impl GlobalCache {
-    pub fn new(
-        account: usize,
-        regular_account: usize,
-        shard: usize,
-        text: usize,
-        action_data: usize,
-        asset_scheme: usize,
-        asset: usize,
-    ) -> Self {
+    pub fn new(sizes: CacheSizes) -> Self {
+        Self::with_stats(sizes, CacheStats::default())
+    }
+
+    fn with_stats(sizes: CacheSizes, stats: CacheStats) -> Self {
         Self {
-            account: LruCache::new(account),
-            regular_account: LruCache::new(regular_account),
-            metadata: LruCache::new(1),
-            shard: LruCache::new(shard),
-            text: LruCache::new(text),
-            action_data: LruCache::new(action_data),
-
-            asset_scheme: LruCache::new(asset_scheme),
-            asset: LruCache::new(asset),
+            account: LruCache::new(sizes.account, stats.counters(CacheKind::Account)),
+            regular_account: LruCache::new(sizes.regular_account, stats.counters(CacheKind::RegularAccount)),
+            metadata: LruCache::new(1, stats.counters(CacheKind::Metadata)),
+            shard: LruCache::new(sizes.shard, stats.counters(CacheKind::Shard)),
+            text: LruCache::new(sizes.text, stats.counters(CacheKind::Text)),
+            action_data: LruCache::new(sizes.action_data, stats.counters(CacheKind::ActionData)),
+            approver_group: LruCache::new(sizes.approver_group, stats.counters(CacheKind::ApproverGroup)),
+
+            asset_scheme: LruCache::new(sizes.asset_scheme, stats.counters(CacheKind::AssetScheme)),
+            asset: LruCache::new(sizes.asset, stats.counters(CacheKind::Asset)),
+
+            stats,
+        }
+    }
+
+    /// Returns an empty cache that has the same capacities and shares the counters.
+    pub fn empty_clone(&self) -> Self {
+        Self::with_stats(self.sizes(), self.stats.clone())
+    }
+
+    fn sizes(&self) -> CacheSizes {
+        CacheSizes {
+            account: self.account.capacity(),
+            regular_account: self.regular_account.capacity(),
+            shard: self.shard.capacity(),
+            text: self.text.capacity(),
+            action_data: self.action_data.capacity(),
+            asset_scheme: self.asset_scheme.capacity(),
+            asset: self.asset.capacity(),
+            approver_group: self.approver_group.capacity(),
         }
     }
 
+    pub fn stats(&self) -> &CacheStats {
+        &self.stats
+    }
+
     pub fn top_cache(&self) -> TopCache {
         TopCache::new(
             self.account.iter().map(|(addr, item)| (*addr, item.clone())),
@@ -63,6 +88,8 @@ impl GlobalCache {
             self.shard.iter().map(|(addr, item)| (*addr, item.clone())),
             self.text.iter().map(|(addr, item)| (*addr, item.clone())),
             self.action_data.iter().map(|(addr, item)| (*addr, item.clone())),
+            self.approver_group.iter().map(|(addr, item)| (*addr, item.clone())),
+            &self.stats,
         )
     }
 
@@ -73,6 +100,7 @@ impl GlobalCache {
                 .filter(|(addr, _)| addr.shard_id() == shard_id)
                 .map(|(addr, item)| (*addr, item.clone())),
             self.asset.iter().filter(|(addr, _)| addr.shard_id() == shard_id).map(|(addr, item)| (*addr, item.clone())),
+            &self.stats,
         )
     }
 
@@ -127,6 +155,12 @@ impl GlobalCache {
                 None => self.action_data.remove(&addr),
             };
         }
+        for (addr, item) in top_cache.cached_approver_groups().into_iter() {
+            match item {
+                Some(item) => self.approver_group.insert(addr, item),
+                None => self.approver_group.remove(&addr),
+            };
+        }
 
         let mut cached_asset_schemes: Vec<_> =
             shard_caches.iter().flat_map(|(_, shard_cache)| shard_cache.cached_asset_schemes().into_iter()).collect();
@@ -156,6 +190,7 @@ impl GlobalCache {
         self.shard.clear();
         self.text.clear();
         self.action_data.clear();
+        self.approver_group.clear();
         self.asset_scheme.clear();
         self.asset.clear();
     }
@@ -163,15 +198,7 @@ impl GlobalCache {
 
 impl Default for GlobalCache {
     fn default() -> Self {
-        // FIXME: Set the right number
-        const N_ACCOUNT: usize = 100;
-        const N_REGULAR_ACCOUNT: usize = 100;
-        const N_SHARD: usize = 100;
-        const N_TEXT: usize = 100;
-        const N_ACTION_DATA: usize = 10;
-        const N_ASSET_SCHEME: usize = 100;
-        const N_ASSET: usize = 1000;
-        Self::new(N_ACCOUNT, N_REGULAR_ACCOUNT, N_SHARD, N_TEXT, N_ACTION_DATA, N_ASSET_SCHEME, N_ASSET)
+        Self::new(CacheSizes::default())
     }
 }
 
@@ -184,9 +211,12 @@ impl Clone for GlobalCache {
             shard: self.shard.clone(),
             text: self.text.clone(),
             action_data: self.action_data.clone(),
+            approver_group: self.approver_group.clone(),
 
             asset_scheme: self.asset_scheme.clone(),
             asset: self.asset.clone(),
+
+            stats: self.stats.clone(),
         }
     }
 }
@@ -14,20 +14,28 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::stats::CacheCounters;
 use crate::CacheableItem;
 use lru_cache::LruCache as LruCacheImpl;
+use std::sync::Arc;
 
 pub struct LruCache<Item: CacheableItem> {
     cache: LruCacheImpl<Item::Address, Item>,
+    counters: Arc<CacheCounters>,
 }
 
 impl<Item: CacheableItem> LruCache<Item> {
-    pub fn new(capacity: usize) -> Self {
+    pub fn new(capacity: usize, counters: Arc<CacheCounters>) -> Self {
         Self {
             cache: LruCacheImpl::new(capacity),
+            counters,
         }
     }
 
+    pub fn capacity(&self) -> usize {
+        self.cache.capacity()
+    }
+
     pub fn clear(&mut self) {
         self.cache.clear()
     }
@@ -38,6 +46,9 @@ impl<Item: CacheableItem> LruCache<Item> {
     }
 
     pub fn insert(&mut self, k: Item::Address, v: Item) -> Option<Item> {
+        if self.cache.len() == self.cache.capacity() && !self.cache.contains_key(&k) {
+            self.counters.evict();
+        }
         self.cache.insert(k, v)
     }
 
@@ -50,6 +61,7 @@ impl<Item: CacheableItem> Clone for LruCache<Item> {
     fn clone(&self) -> Self {
         Self {
             cache: self.cache.clone(),
+            counters: Arc::clone(&self.counters),
         }
     }
 }
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::stats::{CacheKind, CacheStats};
 use super::WriteBack;
 use crate::{AssetScheme, AssetSchemeAddress, OwnedAsset, OwnedAssetAddress};
 use merkle_trie::{Result as TrieResult, Trie, TrieMut};
@@ -22,19 +23,26 @@ use std::cell::RefMut;
 pub struct ShardCache {
     asset_scheme: WriteBack<AssetScheme>,
     asset: WriteBack<OwnedAsset>,
+    stats: CacheStats,
 }
 
 impl ShardCache {
     pub fn new(
         asset_schemes: impl Iterator<Item = (AssetSchemeAddress, AssetScheme)>,
         assets: impl Iterator<Item = (OwnedAssetAddress, OwnedAsset)>,
+        stats: &CacheStats,
     ) -> Self {
         Self {
-            asset_scheme: WriteBack::new_with_iter(asset_schemes),
-            asset: WriteBack::new_with_iter(assets),
+            asset_scheme: WriteBack::new_with_iter(asset_schemes, stats.counters(CacheKind::AssetScheme)),
+            asset: WriteBack::new_with_iter(assets, stats.counters(CacheKind::Asset)),
+            stats: stats.clone(),
         }
     }
 
+    pub fn empty(stats: &CacheStats) -> Self {
+        Self::new(::std::iter::empty(), ::std::iter::empty(), stats)
+    }
+
     pub fn checkpoint(&mut self) {
         self.asset_scheme.checkpoint();
         self.asset.checkpoint();
@@ -80,7 +88,6 @@ impl ShardCache {
         self.asset.create(a, f)
     }
 
-
     pub fn remove_asset(&self, address: &OwnedAssetAddress) {
         self.asset.remove(address)
     }
@@ -89,6 +96,10 @@ impl ShardCache {
         self.asset.items()
     }
 
+    pub fn stats(&self) -> &CacheStats {
+        &self.stats
+    }
+
     pub fn cached_asset_schemes(&self) -> Vec<(usize, AssetSchemeAddress, Option<AssetScheme>)> {
         self.asset_scheme.items()
     }
@@ -99,12 +110,46 @@ impl Clone for ShardCache {
         Self {
             asset_scheme: self.asset_scheme.clone(),
             asset: self.asset.clone(),
+            stats: self.stats.clone(),
         }
     }
 }
 
 impl Default for ShardCache {
     fn default() -> Self {
-        Self::new(::std::iter::empty(), ::std::iter::empty())
+        Self::empty(&CacheStats::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::helpers::get_temp_state_db;
+    use crate::CacheCounts;
+    use cdb::AsHashDB;
+    use merkle_trie::TrieFactory;
+    use primitives::{H160, H256};
+
+    #[test]
+    fn clone_shares_the_stats() {
+        let stats = CacheStats::default();
+        let cache = ShardCache::empty(&stats);
+        let clone = cache.clone();
+
+        let mut db = get_temp_state_db();
+        let mut root = H256::new();
+        let _ = TrieFactory::create(db.as_hashdb_mut(), &mut root);
+        let trie = TrieFactory::readonly(db.as_hashdb(), &root).unwrap();
+        let address = AssetSchemeAddress::new(H160::random(), 0);
+        assert_eq!(Ok(None), clone.asset_scheme(&address, &trie));
+        assert_eq!(Ok(None), clone.asset_scheme(&address, &trie));
+
+        let counts = CacheCounts {
+            hits: 1,
+            misses: 1,
+            evictions: 0,
+        };
+        assert_eq!(counts, cache.stats().counts(CacheKind::AssetScheme));
+        assert_eq!(counts, stats.counts(CacheKind::AssetScheme));
     }
 }
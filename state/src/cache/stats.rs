@@ -0,0 +1,147 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// The types of the items cached by `StateDB`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CacheKind {
+    Account = 0,
+    RegularAccount = 1,
+    Metadata = 2,
+    Shard = 3,
+    Text = 4,
+    ActionData = 5,
+    AssetScheme = 6,
+    Asset = 7,
+    ApproverGroup = 8,
+}
+
+impl CacheKind {
+    pub const ALL: [CacheKind; 9] = [
+        CacheKind::Account,
+        CacheKind::RegularAccount,
+        CacheKind::Metadata,
+        CacheKind::Shard,
+        CacheKind::Text,
+        CacheKind::ActionData,
+        CacheKind::AssetScheme,
+        CacheKind::Asset,
+        CacheKind::ApproverGroup,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CacheKind::Account => "account",
+            CacheKind::RegularAccount => "regular_account",
+            CacheKind::Metadata => "metadata",
+            CacheKind::Shard => "shard",
+            CacheKind::Text => "text",
+            CacheKind::ActionData => "action_data",
+            CacheKind::AssetScheme => "asset_scheme",
+            CacheKind::Asset => "asset",
+            CacheKind::ApproverGroup => "approver_group",
+        }
+    }
+}
+
+/// The capacities of the caches in `StateDB`, in the number of items.
+/// There is only one metadata, so its cache is not configurable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CacheSizes {
+    pub account: usize,
+    pub regular_account: usize,
+    pub shard: usize,
+    pub text: usize,
+    pub action_data: usize,
+    pub asset_scheme: usize,
+    pub asset: usize,
+    pub approver_group: usize,
+}
+
+impl Default for CacheSizes {
+    fn default() -> Self {
+        // FIXME: Set the right number
+        Self {
+            account: 100,
+            regular_account: 100,
+            shard: 100,
+            text: 100,
+            action_data: 10,
+            asset_scheme: 100,
+            asset: 1000,
+            approver_group: 100,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheCounts {
+    /// The number of reads served by the cache.
+    pub hits: usize,
+    /// The number of reads that went to the trie.
+    pub misses: usize,
+    /// The number of items dropped from a full cache.
+    pub evictions: usize,
+}
+
+#[derive(Default)]
+pub struct CacheCounters {
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    evictions: AtomicUsize,
+}
+
+impl CacheCounters {
+    pub fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn evict(&self) {
+        self.evictions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn counts(&self) -> CacheCounts {
+        CacheCounts {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The counters of a `StateDB` and the states created from it.
+/// Clones share the counters.
+#[derive(Clone, Default)]
+pub struct CacheStats {
+    counters: [Arc<CacheCounters>; 8],
+}
+
+impl CacheStats {
+    pub fn counters(&self, kind: CacheKind) -> Arc<CacheCounters> {
+        Arc::clone(&self.counters[kind as usize])
+    }
+
+    pub fn counts(&self, kind: CacheKind) -> CacheCounts {
+        self.counters[kind as usize].counts()
+    }
+}
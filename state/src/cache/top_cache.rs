@@ -14,9 +14,11 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::stats::{CacheKind, CacheStats};
 use super::WriteBack;
 use crate::{
-    Account, ActionData, Metadata, MetadataAddress, RegularAccount, RegularAccountAddress, Shard, ShardAddress, Text,
+    Account, ActionData, ApproverGroup, ApproverGroupAddress, Metadata, MetadataAddress, RegularAccount,
+    RegularAccountAddress, Shard, ShardAddress, Text,
 };
 use ckey::Address;
 use merkle_trie::{Result as TrieResult, Trie, TrieMut};
@@ -30,6 +32,7 @@ pub struct TopCache {
     shard: WriteBack<Shard>,
     text: WriteBack<Text>,
     action_data: WriteBack<ActionData>,
+    approver_group: WriteBack<ApproverGroup>,
 }
 
 impl TopCache {
@@ -40,14 +43,17 @@ impl TopCache {
         shards: impl Iterator<Item = (ShardAddress, Shard)>,
         text: impl Iterator<Item = (H256, Text)>,
         action_data: impl Iterator<Item = (H256, ActionData)>,
+        approver_groups: impl Iterator<Item = (ApproverGroupAddress, ApproverGroup)>,
+        stats: &CacheStats,
     ) -> Self {
         Self {
-            account: WriteBack::new_with_iter(accounts),
-            regular_account: WriteBack::new_with_iter(regular_accounts),
-            metadata: WriteBack::new_with_iter(metadata),
-            shard: WriteBack::new_with_iter(shards),
-            text: WriteBack::new_with_iter(text),
-            action_data: WriteBack::new_with_iter(action_data),
+            account: WriteBack::new_with_iter(accounts, stats.counters(CacheKind::Account)),
+            regular_account: WriteBack::new_with_iter(regular_accounts, stats.counters(CacheKind::RegularAccount)),
+            metadata: WriteBack::new_with_iter(metadata, stats.counters(CacheKind::Metadata)),
+            shard: WriteBack::new_with_iter(shards, stats.counters(CacheKind::Shard)),
+            text: WriteBack::new_with_iter(text, stats.counters(CacheKind::Text)),
+            action_data: WriteBack::new_with_iter(action_data, stats.counters(CacheKind::ActionData)),
+            approver_group: WriteBack::new_with_iter(approver_groups, stats.counters(CacheKind::ApproverGroup)),
         }
     }
 
@@ -58,6 +64,7 @@ impl TopCache {
         self.shard.checkpoint();
         self.text.checkpoint();
         self.action_data.checkpoint();
+        self.approver_group.checkpoint();
     }
 
     pub fn discard_checkpoint(&mut self) {
@@ -67,6 +74,7 @@ impl TopCache {
         self.shard.discard_checkpoint();
         self.text.discard_checkpoint();
         self.action_data.discard_checkpoint();
+        self.approver_group.discard_checkpoint();
     }
 
     pub fn revert_to_checkpoint(&mut self) {
@@ -76,6 +84,7 @@ impl TopCache {
         self.shard.revert_to_checkpoint();
         self.text.revert_to_checkpoint();
         self.action_data.revert_to_checkpoint();
+        self.approver_group.revert_to_checkpoint();
     }
 
     pub fn commit<'db>(&mut self, trie: &mut (dyn TrieMut + 'db)) -> TrieResult<()> {
@@ -85,9 +94,15 @@ impl TopCache {
         self.shard.commit(trie)?;
         self.text.commit(trie)?;
         self.action_data.commit(trie)?;
+        self.approver_group.commit(trie)?;
         Ok(())
     }
 
+    /// Returns the accounts changed since the last checkpoint with their values at the checkpoint.
+    pub fn accounts_at_checkpoint(&self) -> Vec<(Address, Option<Account>)> {
+        self.account.items_at_checkpoint()
+    }
+
     pub fn account(&self, a: &Address, db: &dyn Trie) -> TrieResult<Option<Account>> {
         self.account.get(a, db)
     }
@@ -153,6 +168,14 @@ impl TopCache {
         self.action_data.remove(address)
     }
 
+    pub fn approver_group(&self, a: &ApproverGroupAddress, db: &dyn Trie) -> TrieResult<Option<ApproverGroup>> {
+        self.approver_group.get(a, db)
+    }
+
+    pub fn approver_group_mut(&self, a: &ApproverGroupAddress, db: &dyn Trie) -> TrieResult<RefMut<ApproverGroup>> {
+        self.approver_group.get_mut(a, db)
+    }
+
     pub fn cached_accounts(&self) -> Vec<(Address, Option<Account>)> {
         let mut items = self.account.items();
         items.sort_unstable_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
@@ -188,6 +211,12 @@ impl TopCache {
         items.sort_unstable_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
         items.into_iter().map(|(_, addr, item)| (addr, item)).collect()
     }
+
+    pub fn cached_approver_groups(&self) -> Vec<(ApproverGroupAddress, Option<ApproverGroup>)> {
+        let mut items = self.approver_group.items();
+        items.sort_unstable_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+        items.into_iter().map(|(_, addr, item)| (addr, item)).collect()
+    }
 }
 
 impl Clone for TopCache {
@@ -199,6 +228,7 @@ impl Clone for TopCache {
             shard: self.shard.clone(),
             text: self.text.clone(),
             action_data: self.action_data.clone(),
+            approver_group: self.approver_group.clone(),
         }
     }
 }
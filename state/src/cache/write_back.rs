@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::stats::CacheCounters;
 use super::CacheableItem;
 use merkle_trie::{Result as TrieResult, Trie, TrieMut};
 use std::cell::{RefCell, RefMut};
@@ -22,6 +23,7 @@ use std::collections::HashMap;
 use std::convert::AsRef;
 use std::fmt;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::vec::Vec;
 
 static TOUCHED_COUNT: AtomicUsize = AtomicUsize::new(0);
@@ -76,21 +78,23 @@ where
     cache: RefCell<HashMap<Item::Address, Entry<Item>>>,
     // The original item is preserved in
     checkpoints: RefCell<CheckPoints<Item::Address, Item>>,
+    counters: Arc<CacheCounters>,
 }
 
 impl<Item> WriteBack<Item>
 where
     Item: CacheableItem,
 {
-    pub fn new() -> Self {
+    pub fn new(counters: Arc<CacheCounters>) -> Self {
         Self {
             cache: Default::default(),
             checkpoints: Default::default(),
+            counters,
         }
     }
 
-    pub fn new_with_iter(items: impl Iterator<Item = (Item::Address, Item)>) -> Self {
-        let cache = Self::new();
+    pub fn new_with_iter(items: impl Iterator<Item = (Item::Address, Item)>, counters: Arc<CacheCounters>) -> Self {
+        let cache = Self::new(counters);
         // lru_cache::iter() returns the least-recently-used to the most-recently-used
         for (touched, (addr, item)) in items.enumerate() {
             cache.insert(&addr, Entry::new_clean_with_touched(Some(item), touched))
@@ -148,6 +152,19 @@ where
         }
     }
 
+    /// Returns the items changed since the last checkpoint with their values at the checkpoint.
+    pub fn items_at_checkpoint(&self) -> Vec<(Item::Address, Option<Item>)> {
+        let checkpoints = self.checkpoints.borrow();
+        let checkpoint = match checkpoints.last() {
+            Some(checkpoint) => checkpoint,
+            None => return vec![],
+        };
+        checkpoint
+            .iter()
+            .map(|(address, entry)| (*address, entry.as_ref().and_then(|entry| entry.item.clone())))
+            .collect()
+    }
+
     fn insert(&self, address: &Item::Address, item: Entry<Item>) {
         // Dirty item which is not in the cache means this is a new item.
         // It goes directly into the checkpoint as there's nothing to revert to.
@@ -197,8 +214,10 @@ where
         // check local cache first
         if let Some(cached_item) = self.cache.borrow_mut().get_mut(a) {
             cached_item.touched = touched_count();
+            self.counters.hit();
             return Ok(cached_item.item.clone())
         }
+        self.counters.miss();
 
         // not found in the cache, get from the DB and insert into cache
         let maybe_item = db.get(a.as_ref())?.map(|bytes| ::rlp::decode::<Item>(&bytes).unwrap());
@@ -210,7 +229,10 @@ where
     /// If it doesn't exist, make item equal the evaluation of `default`.
     pub fn get_mut(&self, a: &Item::Address, db: &dyn Trie) -> TrieResult<RefMut<Item>> {
         let contains_key = self.cache.borrow().contains_key(a);
-        if !contains_key {
+        if contains_key {
+            self.counters.hit();
+        } else {
+            self.counters.miss();
             let maybe_item = db.get(a.as_ref())?.map(|bytes| ::rlp::decode::<Item>(&bytes).unwrap());
             self.insert(a, Entry::<Item>::new_clean(maybe_item));
         }
@@ -280,6 +302,7 @@ where
         Self {
             cache: self.cache.clone(),
             checkpoints: RefCell::new(vec![]),
+            counters: Arc::clone(&self.counters),
         }
     }
 }
@@ -14,13 +14,128 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
 pub type CheckpointId = usize;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheckpointError {
+    /// The checkpoint is already active.
+    AlreadyExists(CheckpointId),
+    /// Only the latest checkpoint can be discarded or reverted.
+    NotLatest {
+        id: CheckpointId,
+        latest: Option<CheckpointId>,
+    },
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckpointError::AlreadyExists(id) => write!(f, "Checkpoint({}) is already active", id),
+            CheckpointError::NotLatest {
+                id,
+                latest: Some(latest),
+            } => write!(f, "Checkpoint({}) is not the latest one, Checkpoint({}) is", id, latest),
+            CheckpointError::NotLatest {
+                id,
+                latest: None,
+            } => write!(f, "Checkpoint({}) is not active", id),
+        }
+    }
+}
+
 pub trait StateWithCheckpoint {
     /// Create a recoverable checkpoint of this state.
-    fn create_checkpoint(&mut self, id: CheckpointId);
+    fn create_checkpoint(&mut self, id: CheckpointId) -> Result<(), CheckpointError>;
     /// Merge last checkpoint with previous.
-    fn discard_checkpoint(&mut self, id: CheckpointId);
+    fn discard_checkpoint(&mut self, id: CheckpointId) -> Result<(), CheckpointError>;
     /// Revert to the last checkpoint and discard it.
-    fn revert_to_checkpoint(&mut self, id: CheckpointId);
+    fn revert_to_checkpoint(&mut self, id: CheckpointId) -> Result<(), CheckpointError>;
+
+    /// Create a checkpoint that is reverted when the guard is dropped, unless the guard is committed.
+    fn checkpoint_guard(&mut self, id: CheckpointId) -> Result<CheckpointGuard<Self>, CheckpointError>
+    where
+        Self: Sized, {
+        self.create_checkpoint(id)?;
+        Ok(CheckpointGuard {
+            state: self,
+            id,
+            committed: false,
+        })
+    }
+}
+
+/// The ids of the active checkpoints, the latest last.
+#[derive(Clone, Debug, Default)]
+pub struct CheckpointStack(Vec<CheckpointId>);
+
+impl CheckpointStack {
+    pub fn push(&mut self, id: CheckpointId) -> Result<(), CheckpointError> {
+        if self.0.contains(&id) {
+            return Err(CheckpointError::AlreadyExists(id))
+        }
+        self.0.push(id);
+        Ok(())
+    }
+
+    /// Pops the latest checkpoint if it is `id`.
+    pub fn pop(&mut self, id: CheckpointId) -> Result<(), CheckpointError> {
+        match self.latest() {
+            Some(latest) if latest == id => {
+                self.0.pop();
+                Ok(())
+            }
+            latest => Err(CheckpointError::NotLatest {
+                id,
+                latest,
+            }),
+        }
+    }
+
+    pub fn latest(&self) -> Option<CheckpointId> {
+        self.0.last().cloned()
+    }
+}
+
+/// A checkpoint that is reverted on drop unless `commit` is called.
+/// The guard dereferences to the state, so the changes are made through it.
+pub struct CheckpointGuard<'a, S: StateWithCheckpoint> {
+    state: &'a mut S,
+    id: CheckpointId,
+    committed: bool,
+}
+
+impl<'a, S: StateWithCheckpoint> CheckpointGuard<'a, S> {
+    /// Keeps the changes made after the checkpoint.
+    pub fn commit(mut self) -> Result<(), CheckpointError> {
+        self.committed = true;
+        self.state.discard_checkpoint(self.id)
+    }
+}
+
+impl<'a, S: StateWithCheckpoint> Deref for CheckpointGuard<'a, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        self.state
+    }
+}
+
+impl<'a, S: StateWithCheckpoint> DerefMut for CheckpointGuard<'a, S> {
+    fn deref_mut(&mut self) -> &mut S {
+        self.state
+    }
+}
+
+impl<'a, S: StateWithCheckpoint> Drop for CheckpointGuard<'a, S> {
+    fn drop(&mut self) {
+        if self.committed {
+            return
+        }
+        if let Err(err) = self.state.revert_to_checkpoint(self.id) {
+            cerror!(STATE, "Cannot revert the guarded checkpoint: {}", err);
+        }
+    }
 }
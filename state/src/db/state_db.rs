@@ -30,7 +30,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::cache::{GlobalCache, ShardCache, TopCache};
+use crate::cache::{CacheSizes, CacheStats, GlobalCache, ShardCache, TopCache};
 use crate::impls::TopLevelState;
 use cdb::{new_journaldb, Algorithm, AsHashDB, DatabaseError, HashDB, JournalDB};
 use ctypes::ShardId;
@@ -51,9 +51,14 @@ pub struct StateDB {
 impl StateDB {
     /// Create a new instance wrapping `JournalDB`
     pub fn new(db: Box<dyn JournalDB>) -> StateDB {
+        Self::new_with_cache_sizes(db, CacheSizes::default())
+    }
+
+    /// Create a new instance wrapping `JournalDB` with the given cache capacities
+    pub fn new_with_cache_sizes(db: Box<dyn JournalDB>, cache_sizes: CacheSizes) -> StateDB {
         StateDB {
             db,
-            cache: Default::default(),
+            cache: GlobalCache::new(cache_sizes),
             current_hash: None,
         }
     }
@@ -76,6 +81,17 @@ impl StateDB {
         self.db.is_empty()
     }
 
+    /// Check if the state with the given root is available, without opening the trie.
+    /// Both the journaled and the not yet journaled states are counted.
+    pub fn contains_root(&self, root: &H256) -> bool {
+        self.db.as_hashdb().contains(root)
+    }
+
+    /// The era of the latest journaled state.
+    pub fn latest_era(&self) -> Option<u64> {
+        self.db.latest_era()
+    }
+
     pub fn top_cache(&self) -> TopCache {
         self.cache.top_cache()
     }
@@ -84,6 +100,11 @@ impl StateDB {
         self.cache.shard_caches()
     }
 
+    /// The counters shared by this database, its clones and the states created from them.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats().clone()
+    }
+
     pub fn override_state(&mut self, state: &TopLevelState) {
         self.cache.override_cache(state.top_cache(), state.shard_caches());
         self.current_hash = Some(state.root());
@@ -93,7 +114,7 @@ impl StateDB {
         let (cache, current_hash) = if self.current_hash.as_ref() == Some(hash) {
             (self.cache.clone(), self.current_hash)
         } else {
-            (Default::default(), None)
+            (self.cache.empty_clone(), None)
         };
 
         Self {
@@ -103,8 +124,17 @@ impl StateDB {
         }
     }
 
-    pub fn clear_cache(&mut self) {
+    /// Replaces the backing database, which is reopened after the database is restored.
+    /// The caches are dropped, and the latest era is read from the new one.
+    pub fn reopen(&mut self, db: Box<dyn JournalDB>) {
+        self.db = db;
+        self.clear_caches();
+    }
+
+    /// Drops every cached item, so that the next states read them from the trie.
+    pub fn clear_caches(&mut self) {
         self.cache.clear();
+        self.current_hash = None;
     }
 }
 
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::checkpoint::CheckpointError;
 use ctypes::errors::RuntimeError;
 use merkle_trie::TrieError;
 use std::fmt;
@@ -22,6 +23,7 @@ use std::fmt;
 pub enum Error {
     Trie(TrieError),
     Runtime(RuntimeError),
+    Checkpoint(CheckpointError),
 }
 
 impl fmt::Display for Error {
@@ -29,6 +31,7 @@ impl fmt::Display for Error {
         match self {
             Error::Trie(err) => err.fmt(f),
             Error::Runtime(err) => err.fmt(f),
+            Error::Checkpoint(err) => err.fmt(f),
         }
     }
 }
@@ -44,3 +47,9 @@ impl From<RuntimeError> for Error {
         Error::Runtime(err)
     }
 }
+
+impl From<CheckpointError> for Error {
+    fn from(err: CheckpointError) -> Self {
+        Error::Checkpoint(err)
+    }
+}
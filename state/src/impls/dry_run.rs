@@ -0,0 +1,101 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::OwnedAsset;
+use ckey::Address;
+use ctypes::errors::RuntimeError;
+use ctypes::transaction::{Action, ShardTransaction, Transaction};
+use ctypes::{ShardId, Tracker};
+
+/// The result of `TopLevelState::dry_run`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DryRun {
+    /// The reason why the transaction failed. The changes are empty if it's given.
+    pub error: Option<RuntimeError>,
+    pub accounts: Vec<AccountChange>,
+    pub assets: Vec<AssetChange>,
+}
+
+/// The balance and the seq of an account changed by a transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountChange {
+    pub address: Address,
+    pub prev_balance: u64,
+    pub balance: u64,
+    pub prev_seq: u64,
+    pub seq: u64,
+}
+
+/// An asset created or spent by a transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetChange {
+    pub shard_id: ShardId,
+    pub tracker: Tracker,
+    pub index: usize,
+    /// None if the asset is created by the transaction.
+    pub prev: Option<OwnedAsset>,
+    /// None if the asset is spent by the transaction.
+    pub current: Option<OwnedAsset>,
+}
+
+/// The assets that the transaction can create or spend.
+pub(super) fn assets_touched_by(tx: &Transaction) -> Vec<(ShardId, Tracker, usize)> {
+    if let Action::WrapCCC {
+        shard_id,
+        ..
+    } = &tx.action
+    {
+        let tracker = tx.tracker().expect("WrapCCC has a tracker");
+        return vec![(*shard_id, tracker, 0)]
+    }
+    let transaction = match Option::<ShardTransaction>::from(tx.action.clone()) {
+        Some(transaction) => transaction,
+        None => return vec![],
+    };
+    let tracker = transaction.tracker();
+    match &transaction {
+        ShardTransaction::MintAsset {
+            shard_id,
+            ..
+        }
+        | ShardTransaction::IncreaseAssetSupply {
+            shard_id,
+            ..
+        }
+        | ShardTransaction::WrapCCC {
+            shard_id,
+            ..
+        } => vec![(*shard_id, tracker, 0)],
+        ShardTransaction::TransferAsset {
+            burns,
+            inputs,
+            outputs,
+            ..
+        } => burns
+            .iter()
+            .chain(inputs.iter())
+            .map(|input| (input.prev_out.shard_id, input.prev_out.tracker, input.prev_out.index))
+            .chain(outputs.iter().enumerate().map(|(index, output)| (output.shard_id, tracker, index)))
+            .collect(),
+        ShardTransaction::UnwrapCCC {
+            burn,
+            ..
+        } => vec![(burn.prev_out.shard_id, burn.prev_out.tracker, burn.prev_out.index)],
+        ShardTransaction::ChangeAssetScheme {
+            ..
+        } => vec![],
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use crate::OwnedAsset;
+use ccrypto::Blake;
+use ctypes::transaction::AssetTransferInput;
+use ctypes::util::unexpected::Mismatch;
+use primitives::H160;
+
+/// How an input of a transfer resolves against the state.
+/// The scripts are not executed, so an input that passes every check can still fail to unlock.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputCheck {
+    /// The unspent asset at the prev-out. None if it's already spent or never existed.
+    pub asset: Option<OwnedAsset>,
+    /// True if an earlier input of the same transaction spends the same prev-out.
+    pub duplicated: bool,
+    /// The fields of the input that differ from the asset. The expected values are the ones in the state.
+    pub mismatches: Vec<InputMismatch>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputMismatch {
+    AssetType(Mismatch<H160>),
+    Quantity(Mismatch<u64>),
+    LockScriptHash(Mismatch<H160>),
+}
+
+impl InputCheck {
+    pub fn new(input: &AssetTransferInput, asset: Option<OwnedAsset>) -> Self {
+        let mismatches = match &asset {
+            Some(asset) => {
+                let mut mismatches = Vec::new();
+                if *asset.asset_type() != input.prev_out.asset_type {
+                    mismatches.push(InputMismatch::AssetType(Mismatch {
+                        expected: *asset.asset_type(),
+                        found: input.prev_out.asset_type,
+                    }));
+                }
+                if asset.quantity() != input.prev_out.quantity {
+                    mismatches.push(InputMismatch::Quantity(Mismatch {
+                        expected: asset.quantity(),
+                        found: input.prev_out.quantity,
+                    }));
+                }
+                let lock_script_hash = Blake::blake(&input.lock_script);
+                if *asset.lock_script_hash() != lock_script_hash {
+                    mismatches.push(InputMismatch::LockScriptHash(Mismatch {
+                        expected: *asset.lock_script_hash(),
+                        found: lock_script_hash,
+                    }));
+                }
+                mismatches
+            }
+            None => Vec::new(),
+        };
+        Self {
+            asset,
+            duplicated: false,
+            mismatches,
+        }
+    }
+
+    /// True if the input can be spent as far as the state is concerned.
+    pub fn is_valid(&self) -> bool {
+        self.asset.is_some() && !self.duplicated && self.mismatches.is_empty()
+    }
+}
@@ -17,8 +17,14 @@
 #[cfg(test)]
 mod test_helper; // It must be placed above other modules
 
+mod dry_run;
+mod input_check;
+mod shard_entries;
 mod shard_level;
 mod top_level;
 
+pub use self::dry_run::{AccountChange, AssetChange, DryRun};
+pub use self::input_check::{InputCheck, InputMismatch};
+pub use self::shard_entries::ShardEntry;
 pub use self::shard_level::ShardLevelState;
 pub use self::top_level::TopLevelState;
@@ -0,0 +1,156 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{AssetScheme, OwnedAsset, Text};
+use ccrypto::BLAKE_NULL_RLP;
+use cdb::HashDB;
+use merkle_trie::{Node, Result as TrieResult, TrieError};
+use primitives::{Bytes, H256};
+
+/// A value stored in a shard-level trie.
+/// The values that cannot be decoded are kept as they are.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShardEntry {
+    AssetScheme(AssetScheme),
+    OwnedAsset(OwnedAsset),
+    Text(Text),
+    Opaque(Bytes),
+}
+
+impl ShardEntry {
+    pub fn decode(value: &[u8]) -> Self {
+        // The decoders check the prefix of each item, so at most one of them succeeds.
+        if let Ok(asset_scheme) = rlp::decode(value) {
+            return ShardEntry::AssetScheme(asset_scheme)
+        }
+        if let Ok(asset) = rlp::decode(value) {
+            return ShardEntry::OwnedAsset(asset)
+        }
+        if let Ok(text) = rlp::decode(value) {
+            return ShardEntry::Text(text)
+        }
+        ShardEntry::Opaque(value.to_vec())
+    }
+}
+
+/// Collects at most `limit` entries of the trie whose paths are not less than `from`, in the order of the paths.
+/// Returns the entries and the path of the next entry if there are more.
+pub fn shard_entries(
+    db: &dyn HashDB,
+    root: &H256,
+    from: &H256,
+    limit: usize,
+) -> TrieResult<(Vec<(H256, ShardEntry)>, Option<H256>)> {
+    let from = to_nibbles(from);
+    let mut leaves = Vec::new();
+    if *root != BLAKE_NULL_RLP {
+        // Collect one more leaf to find where the next page starts.
+        collect_leaves(db, root, &mut Vec::new(), &from, limit + 1, &mut leaves)?;
+    }
+    let next = if leaves.len() > limit {
+        leaves.pop().map(|(path, _)| path)
+    } else {
+        None
+    };
+    let entries = leaves.into_iter().map(|(path, value)| (path, ShardEntry::decode(&value))).collect();
+    Ok((entries, next))
+}
+
+fn collect_leaves(
+    db: &dyn HashDB,
+    hash: &H256,
+    path: &mut Vec<u8>,
+    from: &[u8],
+    max: usize,
+    leaves: &mut Vec<(H256, Bytes)>,
+) -> TrieResult<()> {
+    if leaves.len() >= max {
+        return Ok(())
+    }
+    let node = db.get(hash).ok_or_else(|| TrieError::IncompleteDatabase(*hash))?;
+    let depth = path.len();
+    match Node::decoded(&node) {
+        Some(Node::Leaf(partial, value)) => {
+            path.extend((0..partial.len()).map(|i| partial.at(i)));
+            if path.as_slice() >= from {
+                leaves.push((from_nibbles(path), value.to_vec()));
+            }
+        }
+        Some(Node::Branch(partial, children)) => {
+            path.extend((0..partial.len()).map(|i| partial.at(i)));
+            // Every path in the subtree is less than `from` if the prefix is.
+            let skip = path.as_slice() < &from[..path.len().min(from.len())];
+            if !skip {
+                for (index, child) in children.iter().enumerate() {
+                    if let Some(child) = child {
+                        path.push(index as u8);
+                        collect_leaves(db, child, path, from, max, leaves)?;
+                        path.pop();
+                    }
+                }
+            }
+        }
+        None => {}
+    }
+    path.truncate(depth);
+    Ok(())
+}
+
+fn to_nibbles(key: &H256) -> Vec<u8> {
+    key.iter().flat_map(|byte| vec![byte >> 4, byte & 0x0f]).collect()
+}
+
+fn from_nibbles(nibbles: &[u8]) -> H256 {
+    let mut key = H256::zero();
+    for (byte, pair) in key.iter_mut().zip(nibbles.chunks(2)) {
+        *byte = (pair[0] << 4) | pair.get(1).unwrap_or(&0);
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckey::Address;
+    use primitives::H160;
+    use rlp::Encodable;
+
+    #[test]
+    fn decode_known_items() {
+        let scheme = AssetScheme::new("metadata".to_string(), 100, None, None, vec![]);
+        assert_eq!(ShardEntry::AssetScheme(scheme.clone()), ShardEntry::decode(&scheme.rlp_bytes()));
+
+        let asset = OwnedAsset::new(H160::random(), H160::random(), vec![], 10);
+        assert_eq!(ShardEntry::OwnedAsset(asset.clone()), ShardEntry::decode(&asset.rlp_bytes()));
+
+        let text = Text::new("content", &Address::random());
+        assert_eq!(ShardEntry::Text(text.clone()), ShardEntry::decode(&text.rlp_bytes()));
+    }
+
+    #[test]
+    fn undecodable_values_are_opaque() {
+        let value = vec![0xc2, 0x01, 0x02];
+        assert_eq!(ShardEntry::Opaque(value.clone()), ShardEntry::decode(&value));
+        assert_eq!(ShardEntry::Opaque(vec![]), ShardEntry::decode(&[]));
+    }
+
+    #[test]
+    fn nibbles_round_trip() {
+        let key = H256::random();
+        assert_eq!(64, to_nibbles(&key).len());
+        assert_eq!(key, from_nibbles(&to_nibbles(&key)));
+    }
+}
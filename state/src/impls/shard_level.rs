@@ -14,13 +14,17 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::shard_entries::shard_entries;
 use crate::cache::ShardCache;
-use crate::checkpoint::{CheckpointId, StateWithCheckpoint};
+use crate::checkpoint::{CheckpointError, CheckpointId, CheckpointStack, StateWithCheckpoint};
 use crate::traits::{ShardState, ShardStateView};
-use crate::{Asset, AssetScheme, AssetSchemeAddress, OwnedAsset, OwnedAssetAddress, StateDB, StateResult};
+use crate::{
+    Asset, AssetScheme, AssetSchemeAddress, OwnedAsset, OwnedAssetAddress, ShardEntry, StateDB, StateError, StateResult,
+};
 use ccrypto::{Blake, BLAKE_NULL_RLP};
 use cdb::AsHashDB;
 use ckey::Address;
+use creactor::spawn_cpu;
 use ctypes::errors::{RuntimeError, UnlockFailureReason};
 use ctypes::transaction::{
     AssetMintOutput, AssetOutPoint, AssetTransferInput, AssetTransferOutput, AssetWrapCCCOutput, PartialHashing,
@@ -28,19 +32,20 @@ use ctypes::transaction::{
 };
 use ctypes::util::unexpected::Mismatch;
 use ctypes::{BlockNumber, ShardId, Tracker};
-use cvm::{decode, execute, ChainTimeInfo, ScriptResult, VMConfig};
+use cvm::{decode, execute, ChainTimeInfo, Instruction, ScriptResult, VMConfig};
 use merkle_trie::{Result as TrieResult, TrieError, TrieFactory};
 use primitives::{Bytes, H160, H256};
 use std::cell::{RefCell, RefMut};
 use std::collections::HashSet;
 use std::iter::{once, FromIterator};
+use std::sync::Arc;
 
 
 pub struct ShardLevelState<'db> {
     db: &'db mut RefCell<StateDB>,
     root: H256,
     cache: &'db mut ShardCache,
-    id_of_checkpoints: Vec<CheckpointId>,
+    id_of_checkpoints: CheckpointStack,
     shard_id: ShardId,
 }
 
@@ -276,25 +281,16 @@ impl<'db> ShardLevelState<'db> {
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> StateResult<()> {
-        for (input, transaction, burn) in inputs
-            .iter()
-            .map(|input| (input, transaction, false))
-            .chain(burns.iter().map(|input| (input, transaction, true)))
-        {
-            if input.prev_out.shard_id != self.shard_id {
-                continue
-            }
-            self.check_and_run_input_script(
-                input,
-                transaction,
-                burn,
-                sender,
-                approvers,
-                client,
-                parent_block_number,
-                parent_block_timestamp,
-            )?;
-        }
+        self.check_and_run_input_scripts(
+            transaction,
+            burns,
+            inputs,
+            sender,
+            approvers,
+            client,
+            parent_block_number,
+            parent_block_timestamp,
+        )?;
 
         let mut deleted_asset = Vec::with_capacity(inputs.len() + burns.len());
         for input in inputs.iter().chain(burns) {
@@ -366,7 +362,11 @@ impl<'db> ShardLevelState<'db> {
         allowed_script_hashes: &[H160],
     ) -> StateResult<()> {
         if !self.approved_by_registrar(*asset_type, sender, approvers)? {
-            return Err(RuntimeError::InsufficientPermission.into())
+            return Err(RuntimeError::NotRegistrar {
+                asset_type: *asset_type,
+                shard_id: self.shard_id,
+            }
+            .into())
         }
 
         let mut asset_scheme = self.get_asset_scheme_mut(self.shard_id, *asset_type)?;
@@ -527,13 +527,92 @@ impl<'db> ShardLevelState<'db> {
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> StateResult<()> {
+        let script = match self.check_input_script(input, burn, sender, approvers)? {
+            Some(script) => script,
+            None => return Ok(()),
+        };
+        script
+            .run(transaction, client, parent_block_number, parent_block_timestamp)
+            .map_err(|reason| self.unlock_failure(input, reason))
+    }
+
+    /// Runs the scripts of the inputs in parallel on the shared CPU pool.
+    /// It fails with the error of the first failing input, as running them one by one does.
+    fn check_and_run_input_scripts<C: ChainTimeInfo>(
+        &self,
+        transaction: &ShardTransaction,
+        burns: &[AssetTransferInput],
+        inputs: &[AssetTransferInput],
+        sender: &Address,
+        approvers: &[Address],
+        client: &C,
+        parent_block_number: BlockNumber,
+        parent_block_timestamp: u64,
+    ) -> StateResult<()> {
+        let mut scripts = Vec::with_capacity(inputs.len() + burns.len());
+        // The scripts of the inputs after it don't run, since it fails anyway.
+        let mut check_error = None;
+        for (input, burn) in inputs.iter().map(|input| (input, false)).chain(burns.iter().map(|input| (input, true))) {
+            if input.prev_out.shard_id != self.shard_id {
+                continue
+            }
+            match self.check_input_script(input, burn, sender, approvers) {
+                Ok(Some(script)) => scripts.push(script),
+                Ok(None) => {}
+                Err(err) => {
+                    check_error = Some(err);
+                    break
+                }
+            }
+        }
+
+        if scripts.len() == 1 {
+            let script = &scripts[0];
+            script
+                .run(transaction, client, parent_block_number, parent_block_timestamp)
+                .map_err(|reason| self.unlock_failure(&script.input, reason))?;
+        } else if scripts.len() > 1 {
+            let transaction = Arc::new(transaction.clone());
+            let runs: Vec<_> = scripts
+                .into_iter()
+                .map(|script| {
+                    let input = script.input.clone();
+                    let ages = InputAges::new(&script, client, parent_block_number, parent_block_timestamp);
+                    let transaction = Arc::clone(&transaction);
+                    let result = spawn_cpu(move || {
+                        script.run(&*transaction, &ages, parent_block_number, parent_block_timestamp)
+                    });
+                    (input, result)
+                })
+                .collect();
+            for (input, result) in runs {
+                let result = result.recv().unwrap_or_else(|_| {
+                    cerror!(TX, "The script of {}:{} panicked", input.prev_out.tracker, input.prev_out.index);
+                    Err(UnlockFailureReason::ScriptError)
+                });
+                result.map_err(|reason| self.unlock_failure(&input, reason))?;
+            }
+        }
+
+        match check_error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the input and decodes its scripts. Returns None if the scripts don't need to run.
+    fn check_input_script(
+        &self,
+        input: &AssetTransferInput,
+        burn: bool,
+        sender: &Address,
+        approvers: &[Address],
+    ) -> StateResult<Option<InputScript>> {
         let (asset, from_regulator) = self.check_input_asset(input, sender, approvers)?;
         if from_regulator {
-            return Ok(()) // Don't execute scripts when regulator sends the transaction.
+            return Ok(None) // Don't execute scripts when regulator sends the transaction.
         }
 
-        let to_hash: &dyn PartialHashing = transaction;
-
         if *asset.lock_script_hash() != Blake::blake(&input.lock_script) {
             return Err(RuntimeError::ScriptHashMismatch(Mismatch {
                 expected: *asset.lock_script_hash(),
@@ -542,40 +621,28 @@ impl<'db> ShardLevelState<'db> {
             .into())
         }
 
-        let script_result = match (decode(&input.lock_script), decode(&input.unlock_script)) {
-            (Ok(lock_script), Ok(unlock_script)) => execute(
-                &unlock_script,
-                &asset.parameters(),
-                &lock_script,
-                to_hash,
-                VMConfig::default(),
-                input,
+        match (decode(&input.lock_script), decode(&input.unlock_script)) {
+            (Ok(lock_script), Ok(unlock_script)) => Ok(Some(InputScript {
+                lock_script,
+                unlock_script,
+                parameters: asset.parameters().to_vec(),
+                input: input.clone(),
                 burn,
-                client,
-                parent_block_number,
-                parent_block_timestamp,
-            ),
+            })),
             // FIXME : Deliver full decode error
-            _ => return Err(RuntimeError::InvalidScript.into()),
-        };
+            _ => Err(RuntimeError::InvalidScript.into()),
+        }
+    }
 
-        match (script_result, burn) {
-            (Ok(ScriptResult::Burnt), true) => Ok(()),
-            (Ok(ScriptResult::Burnt), false) => Err(UnlockFailureReason::ScriptShouldBeBurnt),
-            (Ok(ScriptResult::Unlocked), false) => Ok(()),
-            (Ok(ScriptResult::Unlocked), true) => Err(UnlockFailureReason::ScriptShouldNotBeBurnt),
-            (Ok(ScriptResult::Fail), _) | (Err(_), _) => Err(UnlockFailureReason::ScriptError),
+    fn unlock_failure(&self, input: &AssetTransferInput, reason: UnlockFailureReason) -> StateError {
+        ctrace!(TX, "Cannot run unlock/lock script {:?}", reason);
+        RuntimeError::FailedToUnlock {
+            shard_id: self.shard_id,
+            tracker: input.prev_out.tracker,
+            index: input.prev_out.index,
+            reason,
         }
-        .map_err(|reason| {
-            ctrace!(TX, "Cannot run unlock/lock script {:?}", reason);
-            RuntimeError::FailedToUnlock {
-                shard_id: self.shard_id,
-                tracker: input.prev_out.tracker,
-                index: input.prev_out.index,
-                reason,
-            }
-            .into()
-        })
+        .into()
     }
 
     fn wrap_ccc(
@@ -691,6 +758,105 @@ impl<'db> ShardLevelState<'db> {
     }
 }
 
+/// The decoded scripts of an input, which can run on another thread.
+struct InputScript {
+    lock_script: Vec<Instruction>,
+    unlock_script: Vec<Instruction>,
+    parameters: Vec<Bytes>,
+    input: AssetTransferInput,
+    burn: bool,
+}
+
+impl InputScript {
+    fn has_timelock(&self) -> bool {
+        self.lock_script.iter().chain(&self.unlock_script).any(|instruction| match instruction {
+            Instruction::ChkTimelock(_) => true,
+            _ => false,
+        })
+    }
+
+    fn has_multisig(&self) -> bool {
+        self.lock_script.iter().chain(&self.unlock_script).any(|instruction| match instruction {
+            Instruction::ChkMultiSig => true,
+            _ => false,
+        })
+    }
+
+    fn run<C: ChainTimeInfo>(
+        &self,
+        transaction: &dyn PartialHashing,
+        client: &C,
+        parent_block_number: BlockNumber,
+        parent_block_timestamp: u64,
+    ) -> Result<(), UnlockFailureReason> {
+        let script_result = execute(
+            &self.unlock_script,
+            &self.parameters,
+            &self.lock_script,
+            transaction,
+            VMConfig::default(),
+            &self.input,
+            self.burn,
+            client,
+            parent_block_number,
+            parent_block_timestamp,
+        );
+        match (script_result, self.burn) {
+            (Ok(ScriptResult::Burnt), true) => Ok(()),
+            (Ok(ScriptResult::Burnt), false) => Err(UnlockFailureReason::ScriptShouldBeBurnt),
+            (Ok(ScriptResult::Unlocked), false) => Ok(()),
+            (Ok(ScriptResult::Unlocked), true) => Err(UnlockFailureReason::ScriptShouldNotBeBurnt),
+            (Ok(ScriptResult::Fail), _) | (Err(_), _) => Err(UnlockFailureReason::ScriptError),
+        }
+    }
+}
+
+/// The ages of the transaction that created the input, read from the client before the script runs on another thread.
+/// The cap on the public keys of CHKMULTISIG is read with them.
+struct InputAges {
+    block_age: Option<u64>,
+    time_age: Option<u64>,
+    caps_multisig_pubkeys: bool,
+}
+
+impl InputAges {
+    fn new<C: ChainTimeInfo>(
+        script: &InputScript,
+        client: &C,
+        parent_block_number: BlockNumber,
+        parent_block_timestamp: u64,
+    ) -> Self {
+        let caps_multisig_pubkeys = script.has_multisig() && client.caps_multisig_pubkeys(parent_block_number);
+        if !script.has_timelock() {
+            return Self {
+                block_age: None,
+                time_age: None,
+                caps_multisig_pubkeys,
+            }
+        }
+        let tracker = &script.input.prev_out.tracker;
+        Self {
+            block_age: client.transaction_block_age(tracker, parent_block_number),
+            time_age: client.transaction_time_age(tracker, parent_block_timestamp),
+            caps_multisig_pubkeys,
+        }
+    }
+}
+
+impl ChainTimeInfo for InputAges {
+    fn transaction_block_age(&self, _tracker: &Tracker, _parent_block_number: BlockNumber) -> Option<u64> {
+        self.block_age
+    }
+
+    fn transaction_time_age(&self, _tracker: &Tracker, _parent_timestamp: u64) -> Option<u64> {
+        self.time_age
+    }
+
+    fn caps_multisig_pubkeys(&self, _parent_block_number: BlockNumber) -> bool {
+        self.caps_multisig_pubkeys
+    }
+}
+
 impl<'db> ShardStateView for ShardLevelState<'db> {
     fn asset_scheme(&self, asset_type: H160) -> TrieResult<Option<AssetScheme>> {
         let db = self.db.borrow();
@@ -703,29 +869,35 @@ impl<'db> ShardStateView for ShardLevelState<'db> {
         let trie = TrieFactory::readonly(db.as_hashdb(), &self.root)?;
         self.cache.asset(&OwnedAssetAddress::new(tracker, index, self.shard_id), &trie)
     }
+
+    fn entries(&self, from: &H256, limit: usize) -> TrieResult<(Vec<(H256, ShardEntry)>, Option<H256>)> {
+        let db = self.db.borrow();
+        shard_entries(db.as_hashdb(), &self.root, from, limit)
+    }
 }
 
 impl<'db> StateWithCheckpoint for ShardLevelState<'db> {
-    fn create_checkpoint(&mut self, id: CheckpointId) {
+    fn create_checkpoint(&mut self, id: CheckpointId) -> Result<(), CheckpointError> {
+        self.id_of_checkpoints.push(id)?;
         ctrace!(STATE, "Checkpoint({}) for shard({}) is created", id, self.shard_id);
-        self.id_of_checkpoints.push(id);
         self.cache.checkpoint();
+        Ok(())
     }
 
-    fn discard_checkpoint(&mut self, id: CheckpointId) {
-        let expected = self.id_of_checkpoints.pop().expect("The checkpoint must exist");
-        assert_eq!(expected, id);
+    fn discard_checkpoint(&mut self, id: CheckpointId) -> Result<(), CheckpointError> {
+        self.id_of_checkpoints.pop(id)?;
 
         ctrace!(STATE, "Checkpoint({}) for shard({}) is discarded", id, self.shard_id);
         self.cache.discard_checkpoint();
+        Ok(())
     }
 
-    fn revert_to_checkpoint(&mut self, id: CheckpointId) {
-        let expected = self.id_of_checkpoints.pop().expect("The checkpoint must exist");
-        assert_eq!(expected, id);
+    fn revert_to_checkpoint(&mut self, id: CheckpointId) -> Result<(), CheckpointError> {
+        self.id_of_checkpoints.pop(id)?;
 
         ctrace!(STATE, "Checkpoint({}) for shard({}) is reverted", id, self.shard_id);
         self.cache.revert_to_checkpoint();
+        Ok(())
     }
 }
 
@@ -744,7 +916,7 @@ impl<'db> ShardState for ShardLevelState<'db> {
     ) -> StateResult<()> {
         ctrace!(TX, "Execute InnerTx {:?}(InnerTxHash:{:?})", transaction, transaction.tracker());
 
-        self.create_checkpoint(TRANSACTION_CHECKPOINT);
+        self.create_checkpoint(TRANSACTION_CHECKPOINT)?;
         let result = self.apply_internal(
             transaction,
             sender,
@@ -756,11 +928,11 @@ impl<'db> ShardState for ShardLevelState<'db> {
         );
         match result {
             Ok(_) => {
-                self.discard_checkpoint(TRANSACTION_CHECKPOINT);
+                self.discard_checkpoint(TRANSACTION_CHECKPOINT)?;
                 Ok(())
             }
             Err(err) => {
-                self.revert_to_checkpoint(TRANSACTION_CHECKPOINT);
+                self.revert_to_checkpoint(TRANSACTION_CHECKPOINT)?;
                 Err(err)
             }
         }
@@ -786,6 +958,11 @@ impl<'db> ShardStateView for ReadOnlyShardLevelState<'db> {
         let trie = TrieFactory::readonly(db.as_hashdb(), &self.root)?;
         self.cache.asset(&OwnedAssetAddress::new(tracker, index, self.shard_id), &trie)
     }
+
+    fn entries(&self, from: &H256, limit: usize) -> TrieResult<(Vec<(H256, ShardEntry)>, Option<H256>)> {
+        let db = self.db.borrow();
+        shard_entries(db.as_hashdb(), &self.root, from, limit)
+    }
 }
 
 #[cfg(test)]
@@ -980,6 +1157,62 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn transfer_fails_with_the_first_failing_input() {
+        let sender = address();
+        let mut state_db = RefCell::new(get_temp_state_db());
+        let mut shard_cache = ShardCache::default();
+        let mut state = get_temp_shard_state(&mut state_db, SHARD_ID, &mut shard_cache);
+
+        let lock_script_hash = H160::from("b042ad154a3359d276835c903587ebafefea22af");
+        let failing_lock_script = vec![0x30, 0x00];
+        let failing_lock_script_hash = Blake::blake(&failing_lock_script);
+
+        let mut mints = Vec::new();
+        for (index, hash) in
+            [lock_script_hash, failing_lock_script_hash, lock_script_hash, failing_lock_script_hash].iter().enumerate()
+        {
+            let mint = asset_mint!(asset_mint_output!(*hash, supply: 10), format!("metadata{}", index));
+            assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+            mints.push((mint.tracker(), Blake::blake(*mint.tracker())));
+        }
+        let (tracker0, asset_type0) = mints[0];
+        let (tracker1, asset_type1) = mints[1];
+        let (tracker2, asset_type2) = mints[2];
+        let (tracker3, asset_type3) = mints[3];
+
+        let transfer = asset_transfer!(
+            inputs: asset_transfer_inputs![
+                (asset_out_point!(tracker0, 0, asset_type0, 10), vec![0x30, 0x01]),
+                (asset_out_point!(tracker1, 0, asset_type1, 10), failing_lock_script.clone()),
+                (asset_out_point!(tracker2, 0, asset_type2, 10), vec![0x30, 0x01]),
+                (asset_out_point!(tracker3, 0, asset_type3, 10), failing_lock_script.clone()),
+            ],
+            asset_transfer_outputs![
+                (lock_script_hash, asset_type0, 10),
+                (lock_script_hash, asset_type1, 10),
+                (lock_script_hash, asset_type2, 10),
+                (lock_script_hash, asset_type3, 10),
+            ]
+        );
+        assert_eq!(
+            Err(StateError::Runtime(RuntimeError::FailedToUnlock {
+                shard_id: SHARD_ID,
+                tracker: tracker1,
+                index: 0,
+                reason: UnlockFailureReason::ScriptError,
+            })),
+            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), 0, 0)
+        );
+
+        check_shard_level_state!(state, [
+            (asset: (tracker0, 0) => { asset_type: asset_type0, quantity: 10 }),
+            (asset: (tracker1, 0) => { asset_type: asset_type1, quantity: 10 }),
+            (asset: (tracker2, 0) => { asset_type: asset_type2, quantity: 10 }),
+            (asset: (tracker3, 0) => { asset_type: asset_type3, quantity: 10 })
+        ]);
+    }
+
     #[test]
     fn mint_and_transfer_allowed() {
         let sender = address();
@@ -1738,6 +1971,111 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn only_registrar_can_change_asset_scheme() {
+        let sender = address();
+        let mut state_db = RefCell::new(get_temp_state_db());
+        let mut shard_cache = ShardCache::default();
+        let mut state = get_temp_shard_state(&mut state_db, SHARD_ID, &mut shard_cache);
+
+        let metadata = "metadata".to_string();
+        let amount = 100;
+        let registrar = Address::random();
+        let mint =
+            asset_mint!(asset_mint_output!(H160::random(), supply: amount), metadata.clone(), registrar: registrar);
+        let mint_tracker = mint.tracker();
+        let asset_type = Blake::blake(*mint_tracker);
+
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+
+        let change_asset_scheme = |seq: usize, metadata: &str| ShardTransaction::ChangeAssetScheme {
+            network_id: "tc".into(),
+            shard_id: SHARD_ID,
+            asset_type,
+            seq,
+            metadata: metadata.to_string(),
+            approver: None,
+            registrar: Some(registrar),
+            allowed_script_hashes: Vec::new(),
+        };
+        let not_registrar = Err(StateError::Runtime(RuntimeError::NotRegistrar {
+            asset_type,
+            shard_id: SHARD_ID,
+        }));
+
+        // Neither the minter nor the approvers other than the registrar can change it.
+        let typo_fixed = change_asset_scheme(0, "typo fixed");
+        assert_eq!(not_registrar, state.apply(&typo_fixed, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            not_registrar,
+            state.apply(&typo_fixed, &sender, &[sender], &[Address::random()], &get_test_client(), 0, 0)
+        );
+        check_shard_level_state!(state, [
+            (scheme: (asset_type) => { metadata: metadata, supply: amount, registrar: registrar })
+        ]);
+
+        assert_eq!(Ok(()), state.apply(&typo_fixed, &registrar, &[], &[], &get_test_client(), 0, 0));
+        check_shard_level_state!(state, [
+            (scheme: (asset_type) => { metadata: "typo fixed".to_string(), supply: amount, registrar: registrar })
+        ]);
+    }
+
+    #[test]
+    fn change_asset_scheme_after_assets_are_transferred_to_third_parties() {
+        let sender = address();
+        let mut state_db = RefCell::new(get_temp_state_db());
+        let mut shard_cache = ShardCache::default();
+        let mut state = get_temp_shard_state(&mut state_db, SHARD_ID, &mut shard_cache);
+
+        let registrar = address();
+        let metadata = "metadata".to_string();
+        let amount = 30;
+        let mint =
+            asset_mint!(asset_mint_output!(H160::random(), supply: amount), metadata.clone(), registrar: registrar);
+        let mint_tracker = mint.tracker();
+        let asset_type = Blake::blake(*mint_tracker);
+
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+
+        let third_party = address();
+        let third_party_lock_script_hash = H160::random();
+        let transfer = asset_transfer!(
+            inputs: asset_transfer_inputs![(asset_out_point!(mint_tracker, 0, asset_type, amount))],
+            asset_transfer_outputs![(third_party_lock_script_hash, asset_type, amount)]
+        );
+        let transfer_tracker = transfer.tracker();
+        assert_eq!(Ok(()), state.apply(&transfer, &registrar, &[sender], &[], &get_test_client(), 0, 0));
+
+        let change_asset_scheme = ShardTransaction::ChangeAssetScheme {
+            network_id: "tc".into(),
+            shard_id: SHARD_ID,
+            asset_type,
+            seq: 0,
+            metadata: "rotated url".to_string(),
+            approver: None,
+            registrar: Some(registrar),
+            allowed_script_hashes: Vec::new(),
+        };
+        // Holding the assets doesn't give the permission to change the scheme.
+        assert_eq!(
+            Err(StateError::Runtime(RuntimeError::NotRegistrar {
+                asset_type,
+                shard_id: SHARD_ID,
+            })),
+            state.apply(&change_asset_scheme, &third_party, &[sender], &[], &get_test_client(), 0, 0)
+        );
+        assert_eq!(
+            Ok(()),
+            state.apply(&change_asset_scheme, &sender, &[sender], &[registrar], &get_test_client(), 0, 0)
+        );
+
+        check_shard_level_state!(state, [
+            (scheme: (asset_type) => { metadata: "rotated url".to_string(), supply: amount, registrar: registrar }),
+            (asset: (mint_tracker, 0)),
+            (asset: (transfer_tracker, 0) => { asset_type: asset_type, quantity: amount })
+        ]);
+    }
+
     #[test]
     fn increase_asset_amount() {
         let sender = address();
@@ -1788,4 +2126,75 @@ mod tests {
             (asset: (supply_tracker, 0) => { asset_type: asset_type, quantity: new_supply })
         ]);
     }
+
+    fn commit(state: &mut ShardLevelState) {
+        let mut db = state.db.borrow_mut();
+        let mut trie = TrieFactory::from_existing(db.as_hashdb_mut(), &mut state.root).unwrap();
+        state.cache.commit(&mut trie).unwrap();
+    }
+
+    fn mint_assets(state: &mut ShardLevelState, count: usize) {
+        let sender = address();
+        for i in 0..count {
+            let transaction = asset_mint!(asset_mint_output!(H160::random(), supply: 100), format!("metadata {}", i));
+            assert_eq!(Ok(()), state.apply(&transaction, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        }
+        commit(state);
+    }
+
+    #[test]
+    fn entries_are_decoded() {
+        let mut state_db = RefCell::new(get_temp_state_db());
+        let mut shard_cache = ShardCache::default();
+        let mut state = get_temp_shard_state(&mut state_db, SHARD_ID, &mut shard_cache);
+        mint_assets(&mut state, 3);
+
+        let (entries, next) = state.entries(&H256::zero(), 100).unwrap();
+        assert_eq!(None, next);
+        assert_eq!(6, entries.len());
+        let mut metadata: Vec<_> = entries
+            .iter()
+            .filter_map(|(_, entry)| match entry {
+                ShardEntry::AssetScheme(scheme) => Some(scheme.metadata().clone()),
+                _ => None,
+            })
+            .collect();
+        metadata.sort();
+        assert_eq!(vec!["metadata 0", "metadata 1", "metadata 2"], metadata);
+        let assets = entries
+            .iter()
+            .filter(|(_, entry)| match entry {
+                ShardEntry::OwnedAsset(asset) => asset.quantity() == 100,
+                _ => false,
+            })
+            .count();
+        assert_eq!(3, assets);
+        assert!(entries.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn entries_are_paginated() {
+        let mut state_db = RefCell::new(get_temp_state_db());
+        let mut shard_cache = ShardCache::default();
+        let mut state = get_temp_shard_state(&mut state_db, SHARD_ID, &mut shard_cache);
+        mint_assets(&mut state, 4);
+        let (all, _) = state.entries(&H256::zero(), 100).unwrap();
+        assert_eq!(8, all.len());
+
+        let (first, next) = state.entries(&H256::zero(), 5).unwrap();
+        assert_eq!(all[..5], first[..]);
+        assert_eq!(Some(all[5].0), next);
+
+        let (second, next) = state.entries(&all[5].0, 5).unwrap();
+        assert_eq!(all[5..], second[..]);
+        assert_eq!(None, next);
+    }
+
+    #[test]
+    fn entries_of_empty_shard() {
+        let mut state_db = RefCell::new(get_temp_state_db());
+        let mut shard_cache = ShardCache::default();
+        let state = get_temp_shard_state(&mut state_db, SHARD_ID, &mut shard_cache);
+        assert_eq!((vec![], None), state.entries(&H256::zero(), 10).unwrap());
+    }
 }
@@ -324,6 +324,7 @@ macro_rules! pay {
         $crate::ctypes::transaction::Action::Pay {
             receiver: $receiver,
             quantity: $quantity,
+            memo: None,
         }
     };
 }
@@ -336,6 +337,12 @@ macro_rules! set_regular_key {
     };
 }
 
+macro_rules! revoke_regular_key {
+    () => {
+        $crate::ctypes::transaction::Action::RevokeRegularKey
+    };
+}
+
 macro_rules! wrap_ccc {
     ($lock_script_hash:expr, $quantity:expr) => {
         $crate::ctypes::transaction::Action::WrapCCC {
@@ -382,6 +389,32 @@ macro_rules! set_shard_owners {
     };
 }
 
+macro_rules! offer_shard_ownership {
+    ($new_owners:expr, $expiry:expr) => {
+        $crate::ctypes::transaction::Action::OfferShardOwnership {
+            shard_id: $crate::impls::test_helper::SHARD_ID,
+            new_owners: $new_owners,
+            expiry: $expiry,
+        }
+    };
+}
+
+macro_rules! accept_shard_ownership {
+    () => {
+        $crate::ctypes::transaction::Action::AcceptShardOwnership {
+            shard_id: $crate::impls::test_helper::SHARD_ID,
+        }
+    };
+}
+
+macro_rules! cancel_shard_ownership_offer {
+    () => {
+        $crate::ctypes::transaction::Action::CancelShardOwnershipOffer {
+            shard_id: $crate::impls::test_helper::SHARD_ID,
+        }
+    };
+}
+
 macro_rules! set_shard_users {
     ($users:expr) => {
         $crate::ctypes::transaction::Action::SetShardUsers {
@@ -391,6 +424,15 @@ macro_rules! set_shard_users {
     };
 }
 
+macro_rules! create_approver_group {
+    ($members:expr, $threshold:expr) => {
+        $crate::ctypes::transaction::Action::CreateApproverGroup {
+            members: $members,
+            threshold: $threshold,
+        }
+    };
+}
+
 macro_rules! transaction {
     (fee: $fee:expr, $action:expr) => {
         transaction!(seq: 0, fee: $fee, $action)
@@ -35,14 +35,16 @@
 //! Unconfirmed sub-states are managed with `checkpoint`s which may be canonicalized
 //! or rolled back.
 
+use super::dry_run::assets_touched_by;
 use crate::cache::{ShardCache, TopCache};
-use crate::checkpoint::{CheckpointId, StateWithCheckpoint};
+use crate::checkpoint::{CheckpointError, CheckpointId, CheckpointStack, StateWithCheckpoint};
 use crate::traits::{ShardState, ShardStateView, StateWithCache, TopState, TopStateView};
 #[cfg(test)]
 use crate::Asset;
 use crate::{
-    Account, ActionData, FindActionHandler, Metadata, MetadataAddress, RegularAccount, RegularAccountAddress, Shard,
-    ShardAddress, ShardLevelState, StateDB, StateResult, Text,
+    Account, AccountChange, ActionData, ApproverGroup, ApproverGroupAddress, AssetChange, DryRun, FindActionHandler,
+    Metadata, MetadataAddress, RegularAccount, RegularAccountAddress, Shard, ShardAddress, ShardLevelState,
+    ShardOwnershipOffer, StateDB, StateError, StateResult, Text,
 };
 use ccrypto::BLAKE_NULL_RLP;
 use cdb::{AsHashDB, DatabaseError};
@@ -92,7 +94,7 @@ pub struct TopLevelState {
 
     top_cache: TopCache,
     shard_caches: HashMap<ShardId, ShardCache>,
-    id_of_checkpoints: Vec<CheckpointId>,
+    id_of_checkpoints: CheckpointStack,
 }
 
 impl TopStateView for TopLevelState {
@@ -130,7 +132,11 @@ impl TopStateView for TopLevelState {
         match self.shard_root(shard_id)? {
             // FIXME: Find a way to use stored cache.
             Some(shard_root) => {
-                let shard_cache = self.shard_caches.get(&shard_id).cloned().unwrap_or_default();
+                let shard_cache = self
+                    .shard_caches
+                    .get(&shard_id)
+                    .cloned()
+                    .unwrap_or_else(|| ShardCache::empty(&self.db.borrow().cache_stats()));
                 Ok(Some(Box::new(ShardLevelState::read_only(shard_id, &self.db, shard_root, shard_cache)?)))
             }
             None => Ok(None),
@@ -148,6 +154,12 @@ impl TopStateView for TopLevelState {
         let trie = TrieFactory::readonly(db.as_hashdb(), &self.root)?;
         Ok(self.top_cache.action_data(key, &trie)?.map(Into::into))
     }
+
+    fn approver_group(&self, address: &Address) -> TrieResult<Option<ApproverGroup>> {
+        let db = self.db.borrow();
+        let trie = TrieFactory::readonly(db.as_hashdb(), &self.root)?;
+        self.top_cache.approver_group(&ApproverGroupAddress::new(address), &trie)
+    }
 }
 
 impl StateWithCache for TopLevelState {
@@ -190,21 +202,24 @@ impl StateWithCache for TopLevelState {
 
 const FEE_CHECKPOINT: CheckpointId = 123;
 const ACTION_CHECKPOINT: CheckpointId = 130;
+const BATCH_CHECKPOINT: CheckpointId = 140;
+const BATCHED_TX_CHECKPOINT: CheckpointId = 141;
+const DRY_RUN_CHECKPOINT: CheckpointId = 150;
 
 impl StateWithCheckpoint for TopLevelState {
-    fn create_checkpoint(&mut self, id: CheckpointId) {
+    fn create_checkpoint(&mut self, id: CheckpointId) -> Result<(), CheckpointError> {
+        self.id_of_checkpoints.push(id)?;
         ctrace!(STATE, "Checkpoint({}) for top level is created", id);
-        self.id_of_checkpoints.push(id);
         self.top_cache.checkpoint();
 
         for (_, cache) in self.shard_caches.iter_mut() {
             cache.checkpoint()
         }
+        Ok(())
     }
 
-    fn discard_checkpoint(&mut self, id: CheckpointId) {
-        let expected = self.id_of_checkpoints.pop().expect("The checkpoint must exist");
-        assert_eq!(expected, id);
+    fn discard_checkpoint(&mut self, id: CheckpointId) -> Result<(), CheckpointError> {
+        self.id_of_checkpoints.pop(id)?;
 
         ctrace!(STATE, "Checkpoint({}) for top level is discarded", id);
         self.top_cache.discard_checkpoint();
@@ -212,11 +227,11 @@ impl StateWithCheckpoint for TopLevelState {
         for (_, cache) in self.shard_caches.iter_mut() {
             cache.discard_checkpoint();
         }
+        Ok(())
     }
 
-    fn revert_to_checkpoint(&mut self, id: CheckpointId) {
-        let expected = self.id_of_checkpoints.pop().expect("The checkpoint must exist");
-        assert_eq!(expected, id);
+    fn revert_to_checkpoint(&mut self, id: CheckpointId) -> Result<(), CheckpointError> {
+        self.id_of_checkpoints.pop(id)?;
 
         ctrace!(STATE, "Checkpoint({}) for top level is reverted", id);
         self.top_cache.revert_to_checkpoint();
@@ -224,6 +239,7 @@ impl StateWithCheckpoint for TopLevelState {
         for (_, cache) in self.shard_caches.iter_mut() {
             cache.revert_to_checkpoint();
         }
+        Ok(())
     }
 }
 
@@ -260,7 +276,14 @@ impl TopLevelState {
         parent_block_timestamp: u64,
         current_block_timestamp: u64,
     ) -> StateResult<()> {
-        self.create_checkpoint(FEE_CHECKPOINT);
+        // Inside a batch, a failed transaction is rolled back as a whole, so
+        // the action doesn't need a checkpoint of its own.
+        let (checkpoint, isolate_action) = if self.is_in_batch() {
+            (BATCHED_TX_CHECKPOINT, false)
+        } else {
+            (FEE_CHECKPOINT, true)
+        };
+        self.create_checkpoint(checkpoint)?;
         let result = self.apply_internal(
             tx,
             signed_hash,
@@ -269,18 +292,149 @@ impl TopLevelState {
             parent_block_number,
             parent_block_timestamp,
             current_block_timestamp,
+            isolate_action,
         );
         match result {
             Ok(()) => {
-                self.discard_checkpoint(FEE_CHECKPOINT);
+                self.discard_checkpoint(checkpoint)?;
             }
             Err(_) => {
-                self.revert_to_checkpoint(FEE_CHECKPOINT);
+                self.revert_to_checkpoint(checkpoint)?;
             }
         }
         result
     }
 
+    /// Execute a given transaction as `apply` does and return the changes it makes.
+    /// All the changes are reverted afterwards, so nothing is written even if the state is committed.
+    pub fn dry_run<C: ChainTimeInfo + FindActionHandler>(
+        &mut self,
+        tx: &Transaction,
+        signed_hash: &TxHash,
+        signer_public: &Public,
+        client: &C,
+        parent_block_number: BlockNumber,
+        parent_block_timestamp: u64,
+        current_block_timestamp: u64,
+    ) -> StateResult<DryRun> {
+        let touched_assets = assets_touched_by(tx);
+        let prev_assets = touched_assets
+            .iter()
+            .map(|(shard_id, tracker, index)| self.asset(*shard_id, *tracker, *index))
+            .collect::<TrieResult<Vec<_>>>()?;
+
+        self.create_checkpoint(DRY_RUN_CHECKPOINT)?;
+        let result = self.apply(
+            tx,
+            signed_hash,
+            signer_public,
+            client,
+            parent_block_number,
+            parent_block_timestamp,
+            current_block_timestamp,
+        );
+        let changes = result.and_then(|()| {
+            let mut accounts = Vec::new();
+            for (address, prev) in self.top_cache.accounts_at_checkpoint() {
+                let (prev_balance, prev_seq) = prev.map_or((0, 0), |account| (account.balance(), account.seq()));
+                let (balance, seq) = (self.balance(&address)?, self.seq(&address)?);
+                if (prev_balance, prev_seq) != (balance, seq) {
+                    accounts.push(AccountChange {
+                        address,
+                        prev_balance,
+                        balance,
+                        prev_seq,
+                        seq,
+                    });
+                }
+            }
+            accounts.sort_unstable_by_key(|change| change.address);
+
+            let mut assets = Vec::new();
+            for ((shard_id, tracker, index), prev) in touched_assets.into_iter().zip(prev_assets) {
+                let current = self.asset(shard_id, tracker, index)?;
+                if prev != current {
+                    assets.push(AssetChange {
+                        shard_id,
+                        tracker,
+                        index,
+                        prev,
+                        current,
+                    });
+                }
+            }
+            Ok((accounts, assets))
+        });
+        self.revert_to_checkpoint(DRY_RUN_CHECKPOINT)?;
+
+        Ok(match changes {
+            Ok((accounts, assets)) => DryRun {
+                error: None,
+                accounts,
+                assets,
+            },
+            Err(StateError::Runtime(err)) => DryRun {
+                error: Some(err),
+                accounts: vec![],
+                assets: vec![],
+            },
+            // The failures to read the state are not the results of the transaction.
+            Err(err) => return Err(err),
+        })
+    }
+
+    /// Execute the given transactions in order as a single batch.
+    /// The result is the same as calling `apply` for each transaction,
+    /// but only the changes of a failed transaction are reverted.
+    pub fn apply_batch<'a, C, I>(
+        &mut self,
+        transactions: I,
+        client: &C,
+        parent_block_number: BlockNumber,
+        parent_block_timestamp: u64,
+        current_block_timestamp: u64,
+    ) -> Vec<StateResult<()>>
+    where
+        C: ChainTimeInfo + FindActionHandler,
+        I: IntoIterator<Item = (&'a Transaction, &'a TxHash, &'a Public)>, {
+        if let Err(err) = self.begin_batch() {
+            return transactions.into_iter().map(|_| Err(err.into())).collect()
+        }
+        let results: Vec<_> = transactions
+            .into_iter()
+            .map(|(tx, signed_hash, signer_public)| {
+                self.apply(
+                    tx,
+                    signed_hash,
+                    signer_public,
+                    client,
+                    parent_block_number,
+                    parent_block_timestamp,
+                    current_block_timestamp,
+                )
+            })
+            .collect();
+        if let Err(err) = self.end_batch() {
+            return results.into_iter().map(|_| Err(err.into())).collect()
+        }
+        results
+    }
+
+    /// Start a batch. Until `end_batch` is called, every `apply` shares one
+    /// outer checkpoint and takes a single checkpoint per transaction.
+    pub fn begin_batch(&mut self) -> Result<(), CheckpointError> {
+        self.create_checkpoint(BATCH_CHECKPOINT)
+    }
+
+    /// Finish the batch started by `begin_batch`, keeping its changes.
+    pub fn end_batch(&mut self) -> Result<(), CheckpointError> {
+        self.discard_checkpoint(BATCH_CHECKPOINT)
+    }
+
+    fn is_in_batch(&self) -> bool {
+        self.id_of_checkpoints.latest() == Some(BATCH_CHECKPOINT)
+    }
+
     // Change the public to an owner address if it is a regular key.
     fn public_to_owner_address(&self, public: &Public) -> StateResult<Address> {
         Ok(if self.regular_account_exists_and_not_null(public)? {
@@ -300,6 +454,7 @@ impl TopLevelState {
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
         current_block_timestamp: u64,
+        isolate_action: bool,
     ) -> StateResult<()> {
         let fee_payer = if self.regular_account_exists_and_not_null(signer_public)? {
             let regular_account = self.get_regular_account_mut(signer_public)?;
@@ -330,7 +485,22 @@ impl TopLevelState {
         self.inc_seq(&fee_payer)?;
         self.sub_balance(&fee_payer, fee)?;
 
-        self.create_checkpoint(ACTION_CHECKPOINT);
+        if !isolate_action {
+            return self.apply_action(
+                &tx.action,
+                tx.network_id,
+                tx.hash(),
+                signed_hash,
+                &fee_payer,
+                signer_public,
+                client,
+                parent_block_number,
+                parent_block_timestamp,
+                current_block_timestamp,
+            )
+        }
+
+        self.create_checkpoint(ACTION_CHECKPOINT)?;
         let result = self.apply_action(
             &tx.action,
             tx.network_id,
@@ -345,10 +515,10 @@ impl TopLevelState {
         );
         match &result {
             Ok(()) => {
-                self.discard_checkpoint(ACTION_CHECKPOINT);
+                self.discard_checkpoint(ACTION_CHECKPOINT)?;
             }
             Err(_) => {
-                self.revert_to_checkpoint(ACTION_CHECKPOINT);
+                self.revert_to_checkpoint(ACTION_CHECKPOINT)?;
             }
         }
         result
@@ -382,7 +552,7 @@ impl TopLevelState {
                 ..
             } => {
                 if let Some(approver) = approver {
-                    if !is_active_account(self, approver)? {
+                    if !is_active_account(self, approver)? && self.approver_group(approver)?.is_none() {
                         return Err(RuntimeError::NonActiveAccount {
                             address: *approver,
                             name: "approver of asset".to_string(),
@@ -438,6 +608,7 @@ impl TopLevelState {
                     |public| self.public_to_owner_address(public),
                     &transaction_tracker,
                 )?;
+                let approvers = self.add_satisfied_approver_groups(&transaction, approvers)?;
                 (transaction, approvers)
             }
             Action::UnwrapCCC {
@@ -472,6 +643,7 @@ impl TopLevelState {
             Action::Pay {
                 receiver,
                 quantity,
+                ..
             } => {
                 self.transfer_balance(fee_payer, receiver, *quantity)?;
                 return Ok(())
@@ -482,6 +654,14 @@ impl TopLevelState {
                 self.set_regular_key(signer_public, key)?;
                 return Ok(())
             }
+            Action::RevokeRegularKey => {
+                // Only the master key can revoke the regular key.
+                if public_to_address(signer_public) != *fee_payer {
+                    return Err(RuntimeError::InsufficientPermission.into())
+                }
+                self.revoke_regular_key(fee_payer)?;
+                return Ok(())
+            }
             Action::CreateShard {
                 users,
             } => {
@@ -495,6 +675,26 @@ impl TopLevelState {
                 self.change_shard_owners(*shard_id, owners, fee_payer)?;
                 return Ok(())
             }
+            Action::OfferShardOwnership {
+                shard_id,
+                new_owners,
+                expiry,
+            } => {
+                self.offer_shard_ownership(*shard_id, new_owners, *expiry, fee_payer, parent_block_number + 1)?;
+                return Ok(())
+            }
+            Action::AcceptShardOwnership {
+                shard_id,
+            } => {
+                self.accept_shard_ownership(*shard_id, fee_payer, parent_block_number + 1)?;
+                return Ok(())
+            }
+            Action::CancelShardOwnershipOffer {
+                shard_id,
+            } => {
+                self.cancel_shard_ownership_offer(*shard_id, fee_payer)?;
+                return Ok(())
+            }
             Action::SetShardUsers {
                 shard_id,
                 users,
@@ -502,6 +702,13 @@ impl TopLevelState {
                 self.change_shard_users(*shard_id, users, fee_payer)?;
                 return Ok(())
             }
+            Action::CreateApproverGroup {
+                members,
+                threshold,
+            } => {
+                self.create_approver_group(members.clone(), *threshold)?;
+                return Ok(())
+            }
             Action::WrapCCC {
                 shard_id,
                 lock_script_hash,
@@ -558,6 +765,43 @@ impl TopLevelState {
         )
     }
 
+    /// Adds the approver groups of the spent asset schemes that the approvers satisfy,
+    /// so the shard level state accepts them as if the groups approved the transaction.
+    fn add_satisfied_approver_groups(
+        &self,
+        transaction: &ShardTransaction,
+        mut approvers: Vec<Address>,
+    ) -> StateResult<Vec<Address>> {
+        if approvers.is_empty() {
+            return Ok(approvers)
+        }
+        let (burns, inputs) = match transaction {
+            ShardTransaction::TransferAsset {
+                burns,
+                inputs,
+                ..
+            } => (burns, inputs),
+            _ => return Ok(approvers),
+        };
+        let mut groups = Vec::new();
+        for input in burns.iter().chain(inputs.iter()) {
+            let prev_out = &input.prev_out;
+            let asset_scheme = self.asset_scheme(prev_out.shard_id, prev_out.asset_type)?;
+            if let Some(approver) = asset_scheme.and_then(|asset_scheme| *asset_scheme.approver()) {
+                if groups.contains(&approver) {
+                    continue
+                }
+                if let Some(group) = self.approver_group(&approver)? {
+                    if group.is_approved_by(&approvers) {
+                        groups.push(approver);
+                    }
+                }
+            }
+        }
+        approvers.extend(groups);
+        Ok(approvers)
+    }
+
     pub fn apply_shard_transaction<C: ChainTimeInfo>(
         &mut self,
         transaction: &ShardTransaction,
@@ -594,7 +838,8 @@ impl TopLevelState {
         let shard_root = self.shard_root(shard_id)?.ok_or_else(|| RuntimeError::InvalidShardId(shard_id))?;
         let shard_users = self.shard_users(shard_id)?.expect("Shard must exist");
 
-        let shard_cache = self.shard_caches.entry(shard_id).or_default();
+        let cache_stats = self.db.borrow().cache_stats();
+        let shard_cache = self.shard_caches.entry(shard_id).or_insert_with(|| ShardCache::empty(&cache_stats));
         let mut shard_level_state = ShardLevelState::from_existing(shard_id, &mut self.db, shard_root, shard_cache)?;
         shard_level_state.apply(
             &transaction,
@@ -615,7 +860,8 @@ impl TopLevelState {
     ) -> StateResult<()> {
         const DEFAULT_SHARD_ROOT: H256 = BLAKE_NULL_RLP;
         {
-            let shard_cache = self.shard_caches.entry(shard_id).or_default();
+            let cache_stats = self.db.borrow().cache_stats();
+            let shard_cache = self.shard_caches.entry(shard_id).or_insert_with(|| ShardCache::empty(&cache_stats));
             ShardLevelState::from_existing(shard_id, &mut self.db, DEFAULT_SHARD_ROOT, shard_cache)?;
         }
 
@@ -656,6 +902,12 @@ impl TopLevelState {
         self.top_cache.shard_mut(&shard_address, &trie)
     }
 
+    fn get_approver_group_mut(&self, address: &Address) -> TrieResult<RefMut<ApproverGroup>> {
+        let db = self.db.borrow();
+        let trie = TrieFactory::readonly(db.as_hashdb(), &self.root)?;
+        self.top_cache.approver_group_mut(&ApproverGroupAddress::new(address), &trie)
+    }
+
     fn get_text(&self, key: &TxHash) -> TrieResult<Option<Text>> {
         let db = self.db.borrow();
         let trie = TrieFactory::readonly(db.as_hashdb(), &self.root)?;
@@ -872,6 +1124,13 @@ impl TopState for TopLevelState {
         Ok(())
     }
 
+    fn revoke_regular_key(&mut self, owner_address: &Address) -> StateResult<()> {
+        let regular_key = self.regular_key(owner_address)?.ok_or(RuntimeError::RegularKeyNotSet)?;
+        self.kill_regular_account(&regular_key);
+        self.get_account_mut(owner_address)?.remove_regular_key();
+        Ok(())
+    }
+
     fn create_shard(&mut self, fee_payer: &Address, tx_hash: TxHash, users: Vec<Address>) -> StateResult<()> {
         let shard_id = {
             let mut metadata = self.get_metadata_mut()?;
@@ -891,7 +1150,71 @@ impl TopState for TopLevelState {
             return Err(RuntimeError::NewOwnersMustContainSender.into())
         }
 
-        self.set_shard_owners(shard_id, owners.to_vec())
+        self.set_shard_owners(shard_id, owners.to_vec())?;
+        // The offer was made by the previous owners.
+        self.get_shard_mut(shard_id)?.set_ownership_offer(None);
+        Ok(())
+    }
+
+    fn offer_shard_ownership(
+        &mut self,
+        shard_id: ShardId,
+        new_owners: &[Address],
+        expiry: u64,
+        sender: &Address,
+        block_number: BlockNumber,
+    ) -> StateResult<()> {
+        let owners = self.shard_owners(shard_id)?.ok_or_else(|| RuntimeError::InvalidShardId(shard_id))?;
+        if !owners.contains(sender) {
+            return Err(RuntimeError::InsufficientPermission.into())
+        }
+
+        let mut shard = self.get_shard_mut(shard_id)?;
+        shard.set_ownership_offer(Some(ShardOwnershipOffer {
+            new_owners: new_owners.to_vec(),
+            expiration: block_number.saturating_add(expiry),
+        }));
+        Ok(())
+    }
+
+    fn accept_shard_ownership(
+        &mut self,
+        shard_id: ShardId,
+        sender: &Address,
+        block_number: BlockNumber,
+    ) -> StateResult<()> {
+        if self.shard(shard_id)?.is_none() {
+            return Err(RuntimeError::InvalidShardId(shard_id).into())
+        }
+        let offer =
+            self.shard_ownership_offer(shard_id)?.ok_or_else(|| RuntimeError::ShardOwnershipOfferNotFound(shard_id))?;
+        if block_number > offer.expiration {
+            return Err(RuntimeError::ShardOwnershipOfferExpired {
+                shard_id,
+                expiration: offer.expiration,
+            }
+            .into())
+        }
+        if !offer.new_owners.contains(sender) {
+            return Err(RuntimeError::InsufficientPermission.into())
+        }
+
+        self.set_shard_owners(shard_id, offer.new_owners)?;
+        self.get_shard_mut(shard_id)?.set_ownership_offer(None);
+        Ok(())
+    }
+
+    fn cancel_shard_ownership_offer(&mut self, shard_id: ShardId, sender: &Address) -> StateResult<()> {
+        let owners = self.shard_owners(shard_id)?.ok_or_else(|| RuntimeError::InvalidShardId(shard_id))?;
+        if !owners.contains(sender) {
+            return Err(RuntimeError::InsufficientPermission.into())
+        }
+        if self.shard_ownership_offer(shard_id)?.is_none() {
+            return Err(RuntimeError::ShardOwnershipOfferNotFound(shard_id).into())
+        }
+
+        self.get_shard_mut(shard_id)?.set_ownership_offer(None);
+        Ok(())
     }
 
     fn change_shard_users(&mut self, shard_id: ShardId, users: &[Address], sender: &Address) -> StateResult<()> {
@@ -939,6 +1262,17 @@ impl TopState for TopLevelState {
         Ok(())
     }
 
+    fn create_approver_group(&mut self, members: Vec<Address>, threshold: u8) -> StateResult<Address> {
+        let group = ApproverGroup::new(members, threshold);
+        let address = group.address();
+        if self.approver_group(&address)?.is_some() {
+            return Err(RuntimeError::ApproverGroupAlreadyExists(address).into())
+        }
+        ctrace!(STATE, "approver group({}) created. members: {:?}, threshold: {}", address, group.members(), threshold);
+        *self.get_approver_group_mut(&address)? = group;
+        Ok(address)
+    }
+
     fn store_text(&mut self, key: &TxHash, text: Text, sig: &Signature) -> StateResult<()> {
         match verify_address(text.certifier(), sig, &text.content_hash()) {
             Ok(false) => {
@@ -1012,6 +1346,7 @@ mod tests_state {
 
     use super::*;
     use crate::tests::helpers::{empty_top_state, get_memory_db, get_temp_state, get_temp_state_db};
+    use crate::{CacheCounts, CacheKind, CacheSizes};
 
     #[test]
     fn work_when_cloned() {
@@ -1073,11 +1408,11 @@ mod tests_state {
     #[test]
     fn get_from_database() {
         let memory_db = get_memory_db();
-        let jorunal = new_journaldb(Arc::clone(&memory_db), Algorithm::Archive, Some(0));
-        let db = StateDB::new(jorunal.boxed_clone());
+        let journal = new_journaldb(Arc::clone(&memory_db), Algorithm::Archive, Some(0));
+        let db = StateDB::new(journal.boxed_clone());
         let a = Address::default();
         let root = {
-            let mut state = empty_top_state(StateDB::new(jorunal));
+            let mut state = empty_top_state(StateDB::new(journal));
             assert_eq!(Ok(()), state.inc_seq(&a));
             assert_eq!(Ok(()), state.add_balance(&a, 100));
             assert_eq!(Ok(100), state.balance(&a));
@@ -1101,14 +1436,89 @@ mod tests_state {
         assert_eq!(Ok(1), state.seq(&a));
     }
 
+    #[test]
+    fn contains_root_of_unflushed_and_flushed_states() {
+        let memory_db = get_memory_db();
+        let journal = new_journaldb(Arc::clone(&memory_db), Algorithm::Archive, Some(0));
+        let other_db = StateDB::new(journal.boxed_clone());
+        assert_eq!(None, other_db.latest_era());
+
+        let mut state = empty_top_state(StateDB::new(journal));
+        assert_eq!(Ok(()), state.add_balance(&Address::random(), 100));
+        let (mut db, root) = state.commit_and_into_db().unwrap();
+        assert!(db.contains_root(&root), "The root is in the overlay");
+        assert!(!other_db.contains_root(&root), "The overlay is not shared");
+
+        let mut transaction = memory_db.transaction();
+        db.journal_under(&mut transaction, 1, root).unwrap();
+        memory_db.write_buffered(transaction);
+        assert!(db.contains_root(&root));
+        assert!(other_db.contains_root(&root), "The root is in the backing database");
+
+        let reopened_db = StateDB::new(new_journaldb(Arc::clone(&memory_db), Algorithm::Archive, Some(0)));
+        assert!(reopened_db.contains_root(&root));
+        assert_eq!(Some(1), reopened_db.latest_era());
+        assert!(!reopened_db.contains_root(&H256::random()));
+    }
+
+    #[test]
+    fn cache_stats_count_hits_misses_and_evictions() {
+        let memory_db = get_memory_db();
+        let journal = new_journaldb(Arc::clone(&memory_db), Algorithm::Archive, Some(0));
+        let cache_sizes = CacheSizes {
+            account: 2,
+            ..Default::default()
+        };
+        let mut db = StateDB::new_with_cache_sizes(journal.boxed_clone(), cache_sizes);
+        let stats = db.cache_stats();
+        let addresses: Vec<Address> = (0..3).map(|_| Address::random()).collect();
+        let root = {
+            let mut state = empty_top_state(StateDB::new(journal));
+            for address in &addresses {
+                assert_eq!(Ok(()), state.add_balance(address, 100));
+            }
+            let root = state.commit().unwrap();
+
+            let mut transaction = memory_db.transaction();
+            state.journal_under(&mut transaction, 1).unwrap();
+            memory_db.write_buffered(transaction);
+
+            db.override_state(&state);
+            root
+        };
+        // Only the two most recently used accounts are kept.
+        let counts = |hits, misses, evictions| CacheCounts {
+            hits,
+            misses,
+            evictions,
+        };
+        assert_eq!(counts(0, 0, 1), stats.counts(CacheKind::Account));
+
+        let state = TopLevelState::from_existing(db.clone(&root), root).unwrap();
+        for _ in 0..3 {
+            assert_eq!(Ok(100), state.balance(&addresses[2]));
+        }
+        assert_eq!(counts(3, 0, 1), stats.counts(CacheKind::Account));
+
+        // The evicted account is read from the trie once, and then it is served by the cache.
+        assert_eq!(Ok(100), state.balance(&addresses[0]));
+        assert_eq!(Ok(100), state.balance(&addresses[0]));
+        assert_eq!(counts(4, 1, 1), stats.counts(CacheKind::Account));
+
+        db.clear_caches();
+        let state = TopLevelState::from_existing(db.clone(&root), root).unwrap();
+        assert_eq!(Ok(100), state.balance(&addresses[2]));
+        assert_eq!(counts(4, 2, 1), stats.counts(CacheKind::Account));
+    }
+
     #[test]
     fn get_from_cache() {
         let memory_db = get_memory_db();
-        let jorunal = new_journaldb(Arc::clone(&memory_db), Algorithm::Archive, Some(0));
-        let mut db = StateDB::new(jorunal.boxed_clone());
+        let journal = new_journaldb(Arc::clone(&memory_db), Algorithm::Archive, Some(0));
+        let mut db = StateDB::new(journal.boxed_clone());
         let a = Address::default();
         let root = {
-            let mut state = empty_top_state(StateDB::new(jorunal));
+            let mut state = empty_top_state(StateDB::new(journal));
             assert_eq!(Ok(()), state.inc_seq(&a));
             assert_eq!(Ok(()), state.add_balance(&a, 69));
             assert_eq!(Ok(69), state.balance(&a));
@@ -1176,10 +1586,10 @@ mod tests_state {
     fn remove_from_database() {
         let a = Address::default();
         let memory_db = get_memory_db();
-        let jorunal = new_journaldb(Arc::clone(&memory_db), Algorithm::Archive, Some(0));
-        let mut db = StateDB::new(jorunal.boxed_clone());
+        let journal = new_journaldb(Arc::clone(&memory_db), Algorithm::Archive, Some(0));
+        let mut db = StateDB::new(journal.boxed_clone());
         let root = {
-            let mut state = empty_top_state(StateDB::new(jorunal));
+            let mut state = empty_top_state(StateDB::new(journal));
             assert_eq!(Ok(()), state.inc_seq(&a));
             let root = state.commit();
             assert!(root.is_ok(), "{:?}", root);
@@ -1298,12 +1708,12 @@ mod tests_state {
         state.create_checkpoint(0);
         assert_eq!(Ok(()), state.add_balance(&a, 100));
         assert_eq!(Ok(100), state.balance(&a));
-        state.discard_checkpoint(0);
+        assert_eq!(Ok(()), state.discard_checkpoint(0));
         assert_eq!(Ok(100), state.balance(&a));
-        state.create_checkpoint(1);
+        assert_eq!(Ok(()), state.create_checkpoint(1));
         assert_eq!(Ok(()), state.add_balance(&a, 1));
         assert_eq!(Ok(100 + 1), state.balance(&a));
-        state.revert_to_checkpoint(1);
+        assert_eq!(Ok(()), state.revert_to_checkpoint(1));
         assert_eq!(Ok(100), state.balance(&a));
     }
 
@@ -1311,14 +1721,14 @@ mod tests_state {
     fn checkpoint_nested() {
         let mut state = get_temp_state();
         let a = Address::default();
-        state.create_checkpoint(0);
+        assert_eq!(Ok(()), state.create_checkpoint(0));
         assert_eq!(Ok(()), state.add_balance(&a, 100));
-        state.create_checkpoint(1);
+        assert_eq!(Ok(()), state.create_checkpoint(1));
         assert_eq!(Ok(()), state.add_balance(&a, 120));
         assert_eq!(Ok(100 + 120), state.balance(&a));
-        state.revert_to_checkpoint(1);
+        assert_eq!(Ok(()), state.revert_to_checkpoint(1));
         assert_eq!(Ok(100), state.balance(&a));
-        state.revert_to_checkpoint(0);
+        assert_eq!(Ok(()), state.revert_to_checkpoint(0));
         assert_eq!(Ok(0), state.balance(&a));
     }
 
@@ -1326,21 +1736,82 @@ mod tests_state {
     fn checkpoint_discard() {
         let mut state = get_temp_state();
         let a = Address::default();
-        state.create_checkpoint(0);
+        assert_eq!(Ok(()), state.create_checkpoint(0));
         assert_eq!(Ok(()), state.add_balance(&a, 100));
-        state.create_checkpoint(1);
+        assert_eq!(Ok(()), state.create_checkpoint(1));
         assert_eq!(Ok(()), state.add_balance(&a, 123));
         assert_eq!(Ok(()), state.inc_seq(&a));
         assert_eq!(Ok(100 + 123), state.balance(&a));
         assert_eq!(Ok(1), state.seq(&a));
-        state.discard_checkpoint(1);
+        assert_eq!(Ok(()), state.discard_checkpoint(1));
         assert_eq!(Ok(100 + 123), state.balance(&a));
         assert_eq!(Ok(1), state.seq(&a));
-        state.revert_to_checkpoint(0);
+        assert_eq!(Ok(()), state.revert_to_checkpoint(0));
         assert_eq!(Ok(0), state.balance(&a));
         assert_eq!(Ok(0), state.seq(&a));
     }
 
+    #[test]
+    fn checkpoint_must_not_be_created_twice() {
+        let mut state = get_temp_state();
+        assert_eq!(Ok(()), state.create_checkpoint(0));
+        assert_eq!(Err(CheckpointError::AlreadyExists(0)), state.create_checkpoint(0));
+        assert_eq!(Ok(()), state.discard_checkpoint(0));
+        assert_eq!(Ok(()), state.create_checkpoint(0));
+    }
+
+    #[test]
+    fn only_the_latest_checkpoint_can_be_discarded() {
+        let mut state = get_temp_state();
+        let a = Address::default();
+        assert_eq!(Ok(()), state.create_checkpoint(0));
+        assert_eq!(Ok(()), state.add_balance(&a, 100));
+        assert_eq!(Ok(()), state.create_checkpoint(1));
+        assert_eq!(Ok(()), state.add_balance(&a, 20));
+
+        let not_latest = Err(CheckpointError::NotLatest {
+            id: 0,
+            latest: Some(1),
+        });
+        assert_eq!(not_latest, state.discard_checkpoint(0));
+        assert_eq!(not_latest, state.revert_to_checkpoint(0));
+        assert_eq!(Ok(100 + 20), state.balance(&a), "A rejected checkpoint operation doesn't change the state");
+
+        assert_eq!(Ok(()), state.revert_to_checkpoint(1));
+        assert_eq!(Ok(100), state.balance(&a));
+        assert_eq!(Ok(()), state.revert_to_checkpoint(0));
+        assert_eq!(Ok(0), state.balance(&a));
+        assert_eq!(
+            Err(CheckpointError::NotLatest {
+                id: 0,
+                latest: None,
+            }),
+            state.revert_to_checkpoint(0)
+        );
+    }
+
+    #[test]
+    fn checkpoint_guard_reverts_unless_committed() {
+        let mut state = get_temp_state();
+        let a = Address::default();
+        {
+            let mut guard = state.checkpoint_guard(0).unwrap();
+            assert_eq!(Ok(()), guard.add_balance(&a, 100));
+        }
+        assert_eq!(Ok(0), state.balance(&a));
+
+        {
+            let mut guard = state.checkpoint_guard(0).unwrap();
+            assert_eq!(Ok(()), guard.add_balance(&a, 100));
+            assert_eq!(Ok(()), guard.commit());
+        }
+        assert_eq!(Ok(100), state.balance(&a));
+
+        assert_eq!(Ok(()), state.create_checkpoint(0));
+        assert_eq!(Err(CheckpointError::AlreadyExists(0)), state.checkpoint_guard(0).map(|_| ()));
+        assert_eq!(Ok(()), state.discard_checkpoint(0));
+    }
+
     #[test]
     fn create_empty() {
         let mut state = get_temp_state();
@@ -1358,7 +1829,7 @@ mod tests_tx {
 
     use super::*;
     use crate::tests::helpers::{get_temp_state, get_test_client};
-    use crate::StateError;
+    use crate::{InputMismatch, OwnedAsset, StateError};
 
     fn address() -> (Address, Public, Private) {
         let keypair = Random.generate().unwrap();
@@ -1442,6 +1913,87 @@ mod tests_tx {
         ]);
     }
 
+    #[test]
+    fn revoked_regular_key_cannot_be_used() {
+        let mut state = get_temp_state();
+
+        let (sender, sender_public, _) = address();
+        let (regular_address, regular_public, _) = address();
+        set_top_level_state!(state, [(account: sender => balance: 20)]);
+
+        let tx = transaction!(fee: 5, set_regular_key!(regular_public));
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(Ok(Some(sender)), state.regular_key_owner(&regular_address));
+
+        let tx = transaction!(seq: 1, fee: 5, revoke_regular_key!());
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(Ok(None), state.regular_key_owner(&regular_address));
+        check_top_level_state!(state, [
+            (account: sender => (seq: 2, balance: 10, key))
+        ]);
+
+        // The revoked key signs for its own account, whose seq doesn't match.
+        let tx = transaction!(seq: 2, fee: 5, pay!(1u64.into(), 5));
+        assert_eq!(
+            Err(StateError::Runtime(RuntimeError::InvalidSeq(Mismatch {
+                expected: 0,
+                found: 2
+            }))),
+            state.apply(&tx, &H256::random().into(), &regular_public, &get_test_client(), 0, 0, 0)
+        );
+        check_top_level_state!(state, [
+            (account: sender => (seq: 2, balance: 10, key)),
+            (account: regular_address => (seq: 0, balance: 0))
+        ]);
+
+        // The address of the revoked key can receive CCC again.
+        let tx = transaction!(seq: 2, fee: 5, pay!(regular_address, 5));
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        check_top_level_state!(state, [
+            (account: sender => (seq: 3, balance: 0, key)),
+            (account: regular_address => (seq: 0, balance: 5))
+        ]);
+    }
+
+    #[test]
+    fn regular_key_cannot_revoke_itself() {
+        let mut state = get_temp_state();
+
+        let (sender, sender_public, _) = address();
+        let (regular_address, regular_public, _) = address();
+        set_top_level_state!(state, [(account: sender => balance: 20)]);
+
+        let tx = transaction!(fee: 5, set_regular_key!(regular_public));
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+
+        let tx = transaction!(seq: 1, fee: 5, revoke_regular_key!());
+        assert_eq!(
+            Err(RuntimeError::InsufficientPermission.into()),
+            state.apply(&tx, &H256::random().into(), &regular_public, &get_test_client(), 0, 0, 0)
+        );
+        assert_eq!(Ok(Some(sender)), state.regular_key_owner(&regular_address));
+        check_top_level_state!(state, [
+            (account: sender => (seq: 1, balance: 15, key: regular_public))
+        ]);
+    }
+
+    #[test]
+    fn cannot_revoke_regular_key_that_is_not_set() {
+        let mut state = get_temp_state();
+
+        let (sender, sender_public, _) = address();
+        set_top_level_state!(state, [(account: sender => balance: 20)]);
+
+        let tx = transaction!(fee: 5, revoke_regular_key!());
+        assert_eq!(
+            Err(RuntimeError::RegularKeyNotSet.into()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+        );
+        check_top_level_state!(state, [
+            (account: sender => (seq: 0, balance: 20, key))
+        ]);
+    }
+
     #[test]
     fn cannot_pay_to_regular_account() {
         let mut state = get_temp_state();
@@ -1695,16 +2247,137 @@ mod tests_tx {
         ]);
     }
 
-    #[test]
-    fn use_deleted_regular_key_as_owner_key() {
+    /// Applies a transfer of an asset whose approver is `group`, approved by `approval_keys`.
+    fn transfer_approved_by_group(
+        state: &mut TopLevelState,
+        group: Address,
+        approval_keys: &[Private],
+    ) -> StateResult<()> {
         let (sender, sender_public, _) = address();
-        let (regular_address, regular_public, _) = address();
-        let (_, regular_public2, _) = address();
 
-        let mut state = get_temp_state();
-        set_top_level_state!(state, [
-            (account: sender => balance: 20),
-            (regular_key: sender_public => regular_public),
+        let shard_id = 0x0;
+        let mint_tracker = Tracker::from(H256::random());
+        let metadata = "metadata".to_string();
+        let lock_script_hash = H160::from("0xb042ad154a3359d276835c903587ebafefea22af");
+        let amount = 30;
+        let asset_type = Blake::blake(*mint_tracker);
+
+        set_top_level_state!(state, [
+            (account: sender => balance: 25),
+            (shard: shard_id => owners: [sender]),
+            (metadata: shards: 1),
+            (scheme: (shard_id, asset_type) => { supply: amount, metadata: metadata, approver: Some(group) }),
+            (asset: (shard_id, mint_tracker, 0) => { asset_type: asset_type, quantity: amount, lock_script_hash: lock_script_hash })
+        ]);
+
+        let transfer = transfer_asset!(
+            inputs: asset_transfer_inputs![(asset_out_point!(mint_tracker, 0, asset_type, 30), vec![0x30, 0x01])],
+            asset_transfer_outputs![(lock_script_hash, vec![vec![1]], asset_type, 30)]
+        );
+        let tracker = transfer.tracker().unwrap();
+        let approvals = approval_keys.iter().map(|key| sign(key, &tracker).unwrap()).collect();
+        let transfer = transfer_asset!(
+            inputs: asset_transfer_inputs![(asset_out_point!(mint_tracker, 0, asset_type, 30), vec![0x30, 0x01])],
+            asset_transfer_outputs![(lock_script_hash, vec![vec![1]], asset_type, 30)],
+            approvals: approvals
+        );
+        let transfer_tx = transaction!(seq: 0, fee: 11, transfer);
+        state.apply(&transfer_tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+    }
+
+    #[test]
+    fn create_approver_group() {
+        let (sender, sender_public, _) = address();
+        let members = vec![Address::random(), Address::random()];
+
+        let mut state = get_temp_state();
+        set_top_level_state!(state, [
+            (account: sender => balance: 20)
+        ]);
+
+        let tx = transaction!(fee: 5, create_approver_group!(members.clone(), 2));
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+
+        let group = ApproverGroup::new(members.clone(), 2);
+        assert_eq!(Ok(Some(group.clone())), state.approver_group(&group.address()));
+
+        let tx = transaction!(seq: 1, fee: 5, create_approver_group!(members, 2));
+        assert_eq!(
+            Err(RuntimeError::ApproverGroupAlreadyExists(group.address()).into()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+        );
+        check_top_level_state!(state, [
+            (account: sender => (seq: 1, balance: 20 - 5))
+        ]);
+    }
+
+    #[test]
+    fn transfer_approved_by_exactly_the_threshold_of_a_group() {
+        let (a, _, a_private) = address();
+        let (b, _, b_private) = address();
+        let (c, ..) = address();
+
+        let mut state = get_temp_state();
+        let group = state.create_approver_group(vec![a, b, c], 2).unwrap();
+
+        assert_eq!(Ok(()), transfer_approved_by_group(&mut state, group, &[a_private, b_private]));
+    }
+
+    #[test]
+    fn transfer_approved_below_the_threshold_of_a_group_fails() {
+        let (a, _, a_private) = address();
+        let (b, ..) = address();
+        let (c, ..) = address();
+
+        let mut state = get_temp_state();
+        let group = state.create_approver_group(vec![a, b, c], 2).unwrap();
+
+        assert_eq!(
+            Err(RuntimeError::NotApproved(group).into()),
+            transfer_approved_by_group(&mut state, group, &[a_private])
+        );
+    }
+
+    #[test]
+    fn duplicated_approvals_count_once_for_a_group() {
+        let (a, _, a_private) = address();
+        let (b, ..) = address();
+        let (c, ..) = address();
+
+        let mut state = get_temp_state();
+        let group = state.create_approver_group(vec![a, b, c], 2).unwrap();
+
+        assert_eq!(
+            Err(RuntimeError::NotApproved(group).into()),
+            transfer_approved_by_group(&mut state, group, &[a_private, a_private])
+        );
+    }
+
+    #[test]
+    fn group_member_approves_with_a_regular_key() {
+        let (a, a_public, _) = address();
+        let (b, _, b_private) = address();
+        let (_, regular_public, regular_private) = address();
+
+        let mut state = get_temp_state();
+        set_top_level_state!(state, [
+            (regular_key: a_public => regular_public)
+        ]);
+        let group = state.create_approver_group(vec![a, b], 2).unwrap();
+
+        assert_eq!(Ok(()), transfer_approved_by_group(&mut state, group, &[regular_private, b_private]));
+    }
+
+    #[test]
+    fn use_deleted_regular_key_as_owner_key() {
+        let (sender, sender_public, _) = address();
+        let (regular_address, regular_public, _) = address();
+        let (_, regular_public2, _) = address();
+
+        let mut state = get_temp_state();
+        set_top_level_state!(state, [
+            (account: sender => balance: 20),
+            (regular_key: sender_public => regular_public),
             (regular_key: sender_public => regular_public2),
             (account: regular_address => balance: 20)
         ]);
@@ -1795,6 +2468,141 @@ mod tests_tx {
         ]);
     }
 
+    #[test]
+    fn dry_run_pay() {
+        let mut state = get_temp_state();
+        let (sender, sender_public, _) = address();
+        set_top_level_state!(state, [
+            (account: sender => balance: 20)
+        ]);
+        let root = state.commit().unwrap();
+
+        let receiver = 1u64.into();
+        let tx = transaction!(fee: 5, pay!(receiver, 10));
+        let result = state.dry_run(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0).unwrap();
+
+        let mut expected = vec![
+            AccountChange {
+                address: sender,
+                prev_balance: 20,
+                balance: 5,
+                prev_seq: 0,
+                seq: 1,
+            },
+            AccountChange {
+                address: receiver,
+                prev_balance: 0,
+                balance: 10,
+                prev_seq: 0,
+                seq: 0,
+            },
+        ];
+        expected.sort_unstable_by_key(|change| change.address);
+        assert_eq!(
+            DryRun {
+                error: None,
+                accounts: expected,
+                assets: vec![],
+            },
+            result
+        );
+
+        check_top_level_state!(state, [
+            (account: sender => (seq: 0, balance: 20)),
+            (account: receiver => (seq: 0, balance: 0))
+        ]);
+        assert_eq!(root, state.commit().unwrap());
+    }
+
+    #[test]
+    fn dry_run_returns_the_error_of_failed_transaction() {
+        let mut state = get_temp_state();
+        let (sender, sender_public, _) = address();
+        set_top_level_state!(state, [
+            (account: sender => balance: 20)
+        ]);
+
+        let receiver = 1u64.into();
+        let tx = transaction!(fee: 5, pay!(receiver, 30));
+        let result = state.dry_run(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0).unwrap();
+
+        assert_eq!(
+            DryRun {
+                error: Some(RuntimeError::InsufficientBalance {
+                    address: sender,
+                    balance: 15,
+                    cost: 30,
+                }),
+                accounts: vec![],
+                assets: vec![],
+            },
+            result
+        );
+
+        check_top_level_state!(state, [
+            (account: sender => (seq: 0, balance: 20)),
+            (account: receiver => (seq: 0, balance: 0))
+        ]);
+    }
+
+    #[test]
+    fn apply_batch_matches_sequential_apply() {
+        let (a, a_public, _) = address();
+        let (b, b_public, _) = address();
+        let (c, c_public, _) = address();
+        let receiver: Address = 1u64.into();
+
+        let transactions = vec![
+            (transaction!(fee: 5, pay!(receiver, 10)), a_public),
+            (transaction!(seq: 3, fee: 5, pay!(receiver, 10)), b_public),
+            (transaction!(fee: 5, pay!(receiver, 30)), c_public),
+            (transaction!(seq: 1, fee: 5, pay!(b, 3)), a_public),
+            (transaction!(fee: 5, pay!(receiver, 1)), b_public),
+            (transaction!(seq: 2, fee: 50, pay!(receiver, 1)), a_public),
+        ];
+        let hashes: Vec<TxHash> = transactions.iter().map(|_| H256::random().into()).collect();
+        let client = get_test_client();
+
+        let mut sequential = get_temp_state();
+        set_top_level_state!(sequential, [
+            (account: a => balance: 30),
+            (account: b => balance: 20),
+            (account: c => balance: 20)
+        ]);
+        let sequential_results: Vec<_> = transactions
+            .iter()
+            .zip(&hashes)
+            .map(|((tx, public), hash)| sequential.apply(tx, hash, public, &client, 0, 0, 0))
+            .collect();
+
+        let mut batched = get_temp_state();
+        set_top_level_state!(batched, [
+            (account: a => balance: 30),
+            (account: b => balance: 20),
+            (account: c => balance: 20)
+        ]);
+        let batched_results = batched.apply_batch(
+            transactions.iter().zip(&hashes).map(|((tx, public), hash)| (tx, hash, public)),
+            &client,
+            0,
+            0,
+            0,
+        );
+
+        assert_eq!(sequential_results, batched_results);
+        assert_eq!(
+            vec![true, false, false, true, true, false],
+            batched_results.iter().map(Result::is_ok).collect::<Vec<_>>()
+        );
+        check_top_level_state!(batched, [
+            (account: a => (seq: 2, balance: 7)),
+            (account: b => (seq: 1, balance: 17)),
+            (account: c => (seq: 0, balance: 20)),
+            (account: receiver => (seq: 0, balance: 11))
+        ]);
+        assert_eq!(sequential.commit(), batched.commit());
+    }
+
     #[test]
     fn mint_permissioned_asset() {
         let (sender, sender_public, _) = address();
@@ -1927,6 +2735,173 @@ mod tests_tx {
         ]);
     }
 
+    #[test]
+    fn dry_run_transfer_asset() {
+        let (sender, sender_public, _) = address();
+
+        let shard_id = 0x00;
+
+        let mut state = get_temp_state();
+        set_top_level_state!(state, [
+            (account: sender => balance: 120),
+            (shard: shard_id => owners: [sender]),
+            (metadata: shards: 1)
+        ]);
+
+        let lock_script_hash = H160::from("b042ad154a3359d276835c903587ebafefea22af");
+        let mint = mint_asset!(Box::new(asset_mint_output!(lock_script_hash, supply: 30)), "metadata".to_string());
+        let mint_tracker = mint.tracker().unwrap();
+        let mint_tx = transaction!(fee: 20, mint);
+        let asset_type = Blake::blake(*mint_tracker);
+
+        assert_eq!(Ok(()), state.apply(&mint_tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        let root = state.commit().unwrap();
+
+        let transfer = transfer_asset!(
+            inputs: vec![asset_transfer_input!(asset_out_point!(mint_tracker, 0, asset_type, 30), vec![0x30, 0x01])],
+            vec![
+                asset_transfer_output!(lock_script_hash, asset_type, 10),
+                asset_transfer_output!(lock_script_hash, asset_type, 20),
+            ]
+        );
+        let transfer_tracker = transfer.tracker().unwrap();
+        let transfer_tx = transaction!(seq: 1, fee: 30, transfer);
+
+        let result =
+            state.dry_run(&transfer_tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0).unwrap();
+
+        assert_eq!(None, result.error);
+        assert_eq!(
+            vec![AccountChange {
+                address: sender,
+                prev_balance: 100,
+                balance: 70,
+                prev_seq: 1,
+                seq: 2,
+            }],
+            result.accounts
+        );
+        assert_eq!(
+            vec![
+                (mint_tracker, 0, Some(30), None),
+                (transfer_tracker, 0, None, Some(10)),
+                (transfer_tracker, 1, None, Some(20)),
+            ],
+            result
+                .assets
+                .iter()
+                .map(|change| (
+                    change.tracker,
+                    change.index,
+                    change.prev.as_ref().map(OwnedAsset::quantity),
+                    change.current.as_ref().map(OwnedAsset::quantity)
+                ))
+                .collect::<Vec<_>>()
+        );
+
+        check_top_level_state!(state, [
+            (account: sender => (seq: 1, balance: 100)),
+            (asset: (mint_tracker, 0, shard_id) => { asset_type: asset_type, quantity: 30 }),
+            (asset: (transfer_tracker, 0, shard_id)),
+            (asset: (transfer_tracker, 1, shard_id))
+        ]);
+        assert_eq!(root, state.commit().unwrap());
+    }
+
+    fn mint_for_input_checks(state: &mut TopLevelState, sender_public: &Public) -> (Tracker, H160, H160) {
+        let sender = public_to_address(sender_public);
+        set_top_level_state!(state, [
+            (account: sender => balance: 100),
+            (shard: 0 => owners: [sender]),
+            (metadata: shards: 1)
+        ]);
+
+        let lock_script_hash = H160::from("b042ad154a3359d276835c903587ebafefea22af");
+        let mint = mint_asset!(Box::new(asset_mint_output!(lock_script_hash, supply: 30)), "metadata".to_string());
+        let mint_tracker = mint.tracker().unwrap();
+        let mint_tx = transaction!(fee: 20, mint);
+        assert_eq!(Ok(()), state.apply(&mint_tx, &H256::random().into(), sender_public, &get_test_client(), 0, 0, 0));
+        (mint_tracker, Blake::blake(*mint_tracker), lock_script_hash)
+    }
+
+    #[test]
+    fn check_inputs_of_valid_transfer() {
+        let (_, sender_public, _) = address();
+        let mut state = get_temp_state();
+        let (mint_tracker, asset_type, lock_script_hash) = mint_for_input_checks(&mut state, &sender_public);
+
+        let inputs = vec![asset_transfer_input!(asset_out_point!(mint_tracker, 0, asset_type, 30), vec![0x30, 0x01])];
+        let checks = state.check_transfer_inputs(&inputs).unwrap();
+        assert_eq!(1, checks.len());
+        assert!(checks[0].is_valid(), "{:?}", checks[0]);
+        let asset = checks[0].asset.as_ref().unwrap();
+        assert_eq!(30, asset.quantity());
+        assert_eq!(&lock_script_hash, asset.lock_script_hash());
+    }
+
+    #[test]
+    fn check_inputs_with_wrong_prev_outs() {
+        let (_, sender_public, _) = address();
+        let mut state = get_temp_state();
+        let (mint_tracker, asset_type, lock_script_hash) = mint_for_input_checks(&mut state, &sender_public);
+
+        let wrong_lock_script = vec![0x30, 0x02];
+        let inputs = vec![
+            asset_transfer_input!(asset_out_point!(mint_tracker, 1, asset_type, 30), vec![0x30, 0x01]),
+            asset_transfer_input!(asset_out_point!(mint_tracker, 0, asset_type, 20), wrong_lock_script.clone()),
+        ];
+        let checks = state.check_transfer_inputs(&inputs).unwrap();
+
+        assert_eq!(None, checks[0].asset);
+        assert_eq!(Vec::<InputMismatch>::new(), checks[0].mismatches);
+        assert!(!checks[0].is_valid());
+
+        assert!(checks[1].asset.is_some());
+        assert_eq!(
+            vec![
+                InputMismatch::Quantity(Mismatch {
+                    expected: 30,
+                    found: 20,
+                }),
+                InputMismatch::LockScriptHash(Mismatch {
+                    expected: lock_script_hash,
+                    found: Blake::blake(&wrong_lock_script),
+                }),
+            ],
+            checks[1].mismatches
+        );
+        assert!(!checks[1].is_valid());
+    }
+
+    #[test]
+    fn check_inputs_spent_twice() {
+        let (_, sender_public, _) = address();
+        let mut state = get_temp_state();
+        let (mint_tracker, asset_type, lock_script_hash) = mint_for_input_checks(&mut state, &sender_public);
+
+        let input = asset_transfer_input!(asset_out_point!(mint_tracker, 0, asset_type, 30), vec![0x30, 0x01]);
+        let checks = state.check_transfer_inputs(&[input.clone(), input.clone()]).unwrap();
+        assert!(checks[0].is_valid());
+        assert!(checks[1].asset.is_some());
+        assert!(checks[1].duplicated);
+        assert!(!checks[1].is_valid());
+
+        let transfer = transfer_asset!(
+            inputs: vec![input.clone()],
+            vec![asset_transfer_output!(lock_script_hash, asset_type, 30)]
+        );
+        let transfer_tx = transaction!(seq: 1, fee: 30, transfer);
+        assert_eq!(
+            Ok(()),
+            state.apply(&transfer_tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+        );
+
+        let checks = state.check_transfer_inputs(&[input]).unwrap();
+        assert_eq!(None, checks[0].asset);
+        assert!(!checks[0].duplicated);
+        assert!(!checks[0].is_valid());
+    }
+
     #[test]
     fn cannot_mint_twice_in_different_transaction() {
         let (sender, sender_public, _) = address();
@@ -2632,6 +3607,174 @@ mod tests_tx {
         ]);
     }
 
+    #[test]
+    fn one_of_the_offered_owners_accepts_the_ownership() {
+        let (sender, sender_public, _) = address();
+        let (new_owner1, ..) = address();
+        let (new_owner2, new_owner2_public, _) = address();
+        let shard_id = 0;
+
+        let mut state = get_temp_state();
+        set_top_level_state!(state, [
+            (account: sender => balance: 100),
+            (account: new_owner1 => balance: 100),
+            (account: new_owner2 => balance: 100),
+            (shard: shard_id => owners: [sender]),
+            (metadata: shards: 1)
+        ]);
+
+        let new_owners = vec![new_owner1, new_owner2];
+        let tx = transaction!(fee: 5, offer_shard_ownership!(new_owners.clone(), 10));
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(Some(ShardOwnershipOffer {
+                new_owners: new_owners.clone(),
+                expiration: 11,
+            })),
+            state.shard_ownership_offer(shard_id)
+        );
+        check_top_level_state!(state, [
+            (account: sender => (seq: 1, balance: 100 - 5)),
+            (shard: 0 => owners: [sender])
+        ]);
+
+        let tx = transaction!(fee: 5, accept_shard_ownership!());
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &new_owner2_public, &get_test_client(), 10, 0, 0));
+        assert_eq!(Ok(None), state.shard_ownership_offer(shard_id));
+        check_top_level_state!(state, [
+            (account: new_owner2 => (seq: 1, balance: 100 - 5)),
+            (shard: 0 => owners: new_owners)
+        ]);
+    }
+
+    #[test]
+    fn only_the_offered_owners_can_accept_the_ownership() {
+        let (sender, sender_public, _) = address();
+        let (new_owner, ..) = address();
+        let (stranger, stranger_public, _) = address();
+        let shard_id = 0;
+
+        let mut state = get_temp_state();
+        set_top_level_state!(state, [
+            (account: sender => balance: 100),
+            (account: new_owner => balance: 100),
+            (account: stranger => balance: 100),
+            (shard: shard_id => owners: [sender]),
+            (metadata: shards: 1)
+        ]);
+
+        let tx = transaction!(fee: 5, offer_shard_ownership!(vec![new_owner], 10));
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+
+        let tx = transaction!(fee: 5, accept_shard_ownership!());
+        assert_eq!(
+            Err(RuntimeError::InsufficientPermission.into()),
+            state.apply(&tx, &H256::random().into(), &stranger_public, &get_test_client(), 0, 0, 0)
+        );
+        check_top_level_state!(state, [
+            (account: stranger => (seq: 0, balance: 100)),
+            (shard: 0 => owners: [sender])
+        ]);
+    }
+
+    #[test]
+    fn expired_ownership_offer_cannot_be_accepted() {
+        let (sender, sender_public, _) = address();
+        let (new_owner, new_owner_public, _) = address();
+        let shard_id = 0;
+
+        let mut state = get_temp_state();
+        set_top_level_state!(state, [
+            (account: sender => balance: 100),
+            (account: new_owner => balance: 100),
+            (shard: shard_id => owners: [sender]),
+            (metadata: shards: 1)
+        ]);
+
+        let tx = transaction!(fee: 5, offer_shard_ownership!(vec![new_owner], 10));
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+
+        let tx = transaction!(fee: 5, accept_shard_ownership!());
+        assert_eq!(
+            Err(RuntimeError::ShardOwnershipOfferExpired {
+                shard_id,
+                expiration: 11,
+            }
+            .into()),
+            state.apply(&tx, &H256::random().into(), &new_owner_public, &get_test_client(), 11, 0, 0)
+        );
+        check_top_level_state!(state, [
+            (account: new_owner => (seq: 0, balance: 100)),
+            (shard: 0 => owners: [sender])
+        ]);
+    }
+
+    #[test]
+    fn owner_cancels_the_ownership_offer() {
+        let (sender, sender_public, _) = address();
+        let (new_owner, new_owner_public, _) = address();
+        let shard_id = 0;
+
+        let mut state = get_temp_state();
+        set_top_level_state!(state, [
+            (account: sender => balance: 100),
+            (account: new_owner => balance: 100),
+            (shard: shard_id => owners: [sender]),
+            (metadata: shards: 1)
+        ]);
+
+        let tx = transaction!(fee: 5, offer_shard_ownership!(vec![new_owner], 10));
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+
+        let tx = transaction!(fee: 5, cancel_shard_ownership_offer!());
+        assert_eq!(
+            Err(RuntimeError::InsufficientPermission.into()),
+            state.apply(&tx, &H256::random().into(), &new_owner_public, &get_test_client(), 0, 0, 0)
+        );
+
+        let tx = transaction!(seq: 1, fee: 5, cancel_shard_ownership_offer!());
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 1, 0, 0));
+        assert_eq!(Ok(None), state.shard_ownership_offer(shard_id));
+
+        let tx = transaction!(fee: 5, accept_shard_ownership!());
+        assert_eq!(
+            Err(RuntimeError::ShardOwnershipOfferNotFound(shard_id).into()),
+            state.apply(&tx, &H256::random().into(), &new_owner_public, &get_test_client(), 2, 0, 0)
+        );
+        check_top_level_state!(state, [
+            (account: sender => (seq: 2, balance: 100 - 10)),
+            (account: new_owner => (seq: 0, balance: 100)),
+            (shard: 0 => owners: [sender])
+        ]);
+    }
+
+    #[test]
+    fn set_shard_owners_discards_the_ownership_offer() {
+        let (sender, sender_public, _) = address();
+        let (new_owner, ..) = address();
+        let shard_id = 0;
+
+        let mut state = get_temp_state();
+        set_top_level_state!(state, [
+            (account: sender => balance: 100),
+            (account: new_owner => balance: 100),
+            (shard: shard_id => owners: [sender]),
+            (metadata: shards: 1)
+        ]);
+
+        let tx = transaction!(fee: 5, offer_shard_ownership!(vec![new_owner], 10));
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+
+        let owners = vec![new_owner, sender];
+        let tx = transaction!(seq: 1, fee: 5, set_shard_owners!(owners.clone()));
+        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 1, 0, 0));
+        assert_eq!(Ok(None), state.shard_ownership_offer(shard_id));
+        check_top_level_state!(state, [
+            (account: sender => (seq: 2, balance: 100 - 10)),
+            (shard: 0 => owners: owners)
+        ]);
+    }
+
     #[test]
     fn user_cannot_set_owners() {
         let (original_owner, ..) = address();
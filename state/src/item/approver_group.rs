@@ -0,0 +1,138 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::CacheableItem;
+use ccrypto::Blake;
+use ckey::Address;
+use primitives::{H160, H256};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+
+/// A set of addresses that approves asset transactions together.
+/// An asset scheme uses a group by setting the group's address as its approver.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ApproverGroup {
+    members: Vec<Address>,
+    threshold: u8,
+}
+
+impl ApproverGroup {
+    pub fn new(mut members: Vec<Address>, threshold: u8) -> Self {
+        members.sort();
+        Self {
+            members,
+            threshold,
+        }
+    }
+
+    pub fn members(&self) -> &[Address] {
+        &self.members
+    }
+
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// The address that refers to this group. It depends only on the members and the threshold.
+    pub fn address(&self) -> Address {
+        let mut s = RlpStream::new_list(3);
+        s.append(&PREFIX).append_list(&self.members).append(&self.threshold);
+        H160::blake(s.out()).into()
+    }
+
+    /// Returns true if at least `threshold` distinct members are in the approvers.
+    pub fn is_approved_by(&self, approvers: &[Address]) -> bool {
+        let approved = self.members.iter().filter(|member| approvers.contains(member)).count();
+        approved >= self.threshold as usize
+    }
+}
+
+impl CacheableItem for ApproverGroup {
+    type Address = ApproverGroupAddress;
+
+    fn is_null(&self) -> bool {
+        self.members.is_empty()
+    }
+}
+
+const PREFIX: u8 = super::Prefix::ApproverGroup as u8;
+
+impl Encodable for ApproverGroup {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3).append(&PREFIX).append_list(&self.members).append(&self.threshold);
+    }
+}
+
+impl Decodable for ApproverGroup {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 3 {
+            return Err(DecoderError::RlpInvalidLength {
+                got: item_count,
+                expected: 3,
+            })
+        }
+        let prefix = rlp.val_at::<u8>(0)?;
+        if PREFIX != prefix {
+            cdebug!(STATE, "{} is not an expected prefix for approver group", prefix);
+            return Err(DecoderError::Custom("Unexpected prefix"))
+        }
+        Ok(Self {
+            members: rlp.list_at(1)?,
+            threshold: rlp.val_at(2)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ApproverGroupAddress(H256);
+
+impl_address!(TOP, ApproverGroupAddress, PREFIX);
+
+impl ApproverGroupAddress {
+    pub fn new(address: &Address) -> Self {
+        Self::from_transaction_hash(::ccrypto::blake256(address), 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rlp::rlp_encode_and_decode_test;
+
+    use super::*;
+
+    #[test]
+    fn rlp_encode_and_decode() {
+        let group = ApproverGroup::new(vec![Address::random(), Address::random()], 1);
+        rlp_encode_and_decode_test!(group);
+    }
+
+    #[test]
+    fn address_does_not_depend_on_the_order_of_members() {
+        let a = Address::random();
+        let b = Address::random();
+        assert_eq!(ApproverGroup::new(vec![a, b], 1).address(), ApproverGroup::new(vec![b, a], 1).address());
+        assert_ne!(ApproverGroup::new(vec![a, b], 1).address(), ApproverGroup::new(vec![a, b], 2).address());
+    }
+
+    #[test]
+    fn duplicated_approvers_are_counted_once() {
+        let a = Address::random();
+        let b = Address::random();
+        let group = ApproverGroup::new(vec![a, b, Address::random()], 2);
+        assert!(group.is_approved_by(&[a, b]));
+        assert!(!group.is_approved_by(&[a, a]));
+    }
+}
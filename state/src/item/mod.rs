@@ -19,6 +19,7 @@ mod address;
 
 pub mod account;
 pub mod action_data;
+pub mod approver_group;
 pub mod asset;
 pub mod asset_scheme;
 pub mod metadata;
@@ -31,6 +32,7 @@ pub mod text;
 enum Prefix {
     OwnedAsset = b'A',
     Account = b'C',
+    ApproverGroup = b'G',
     Shard = b'H',
     Metadata = b'M',
     RegularAccount = b'R',
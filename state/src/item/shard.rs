@@ -17,15 +17,24 @@
 use crate::CacheableItem;
 use ccrypto::BLAKE_NULL_RLP;
 use ckey::Address;
-use ctypes::ShardId;
+use ctypes::{BlockNumber, ShardId};
 use primitives::H256;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
+/// The owners proposed by the current owners. They become the owners when one of them accepts it.
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct ShardOwnershipOffer {
+    pub new_owners: Vec<Address>,
+    /// The last block in which the offer can be accepted.
+    pub expiration: BlockNumber,
+}
+
 #[derive(Clone, Debug)]
 pub struct Shard {
     root: H256,
     owners: Vec<Address>,
     users: Vec<Address>,
+    ownership_offer: Option<ShardOwnershipOffer>,
 }
 
 impl Shard {
@@ -34,6 +43,7 @@ impl Shard {
             root: shard_root,
             owners,
             users,
+            ownership_offer: None,
         }
     }
 
@@ -62,6 +72,14 @@ impl Shard {
     pub fn set_users(&mut self, users: Vec<Address>) {
         self.users = users;
     }
+
+    pub fn ownership_offer(&self) -> Option<&ShardOwnershipOffer> {
+        self.ownership_offer.as_ref()
+    }
+
+    pub fn set_ownership_offer(&mut self, ownership_offer: Option<ShardOwnershipOffer>) {
+        self.ownership_offer = ownership_offer;
+    }
 }
 
 impl Default for Shard {
@@ -80,16 +98,25 @@ impl CacheableItem for Shard {
 
 const PREFIX: u8 = super::Prefix::Shard as u8;
 
+/// The ownership offer is appended only if it exists, so the shards without it are encoded as before.
 impl Encodable for Shard {
     fn rlp_append(&self, s: &mut RlpStream) {
-        s.begin_list(4).append(&PREFIX).append(&self.root).append_list(&self.owners).append_list(&self.users);
+        let item_count = if self.ownership_offer.is_some() {
+            5
+        } else {
+            4
+        };
+        s.begin_list(item_count).append(&PREFIX).append(&self.root).append_list(&self.owners).append_list(&self.users);
+        if let Some(ownership_offer) = &self.ownership_offer {
+            s.append(ownership_offer);
+        }
     }
 }
 
 impl Decodable for Shard {
     fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
         let item_count = rlp.item_count()?;
-        if item_count != 4 {
+        if item_count != 4 && item_count != 5 {
             return Err(DecoderError::RlpInvalidLength {
                 expected: 4,
                 got: item_count,
@@ -104,6 +131,11 @@ impl Decodable for Shard {
             root: rlp.val_at(1)?,
             owners: rlp.list_at(2)?,
             users: rlp.list_at(3)?,
+            ownership_offer: if item_count == 5 {
+                Some(rlp.val_at(4)?)
+            } else {
+                None
+            },
         })
     }
 }
@@ -159,4 +191,27 @@ mod tests {
         let address = ShardAddress::from_hash(hash);
         assert_eq!(Some(ShardAddress(hash)), address);
     }
+
+    #[test]
+    fn shard_without_ownership_offer_has_four_items() {
+        let shard = Shard::new(H256::random(), vec![Address::random()], vec![]);
+        assert_eq!(4, Rlp::new(&rlp::encode(&shard)).item_count().unwrap());
+        let decoded: Shard = rlp::decode(&rlp::encode(&shard)).unwrap();
+        assert_eq!(None, decoded.ownership_offer());
+    }
+
+    #[test]
+    fn encode_and_decode_shard_with_ownership_offer() {
+        let mut shard = Shard::new(H256::random(), vec![Address::random()], vec![Address::random()]);
+        let offer = ShardOwnershipOffer {
+            new_owners: vec![Address::random(), Address::random()],
+            expiration: 10,
+        };
+        shard.set_ownership_offer(Some(offer.clone()));
+        let decoded: Shard = rlp::decode(&rlp::encode(&shard)).unwrap();
+        assert_eq!(shard.root(), decoded.root());
+        assert_eq!(shard.owners(), decoded.owners());
+        assert_eq!(shard.users(), decoded.users());
+        assert_eq!(Some(&offer), decoded.ownership_offer());
+    }
 }
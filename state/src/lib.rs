@@ -19,6 +19,7 @@ extern crate codechain_db as cdb;
 #[macro_use]
 extern crate codechain_logger as clogger;
 extern crate codechain_key as ckey;
+extern crate codechain_reactor as creactor;
 extern crate codechain_types as ctypes;
 extern crate codechain_vm as cvm;
 extern crate kvdb;
@@ -46,17 +47,21 @@ mod traits;
 pub mod tests;
 
 pub use crate::action_handler::{ActionDataKeyBuilder, ActionHandler, FindActionHandler, HitHandler};
-pub use crate::checkpoint::{CheckpointId, StateWithCheckpoint};
+pub use crate::cache::{CacheCounts, CacheKind, CacheSizes, CacheStats};
+pub use crate::checkpoint::{CheckpointError, CheckpointGuard, CheckpointId, StateWithCheckpoint};
 pub use crate::db::StateDB;
 pub use crate::error::Error as StateError;
-pub use crate::impls::{ShardLevelState, TopLevelState};
+pub use crate::impls::{
+    AccountChange, AssetChange, DryRun, InputCheck, InputMismatch, ShardEntry, ShardLevelState, TopLevelState,
+};
 pub use crate::item::account::Account;
 pub use crate::item::action_data::ActionData;
+pub use crate::item::approver_group::{ApproverGroup, ApproverGroupAddress};
 pub use crate::item::asset::{Asset, OwnedAsset, OwnedAssetAddress};
 pub use crate::item::asset_scheme::{AssetScheme, AssetSchemeAddress};
 pub use crate::item::metadata::{Metadata, MetadataAddress};
 pub use crate::item::regular_account::{RegularAccount, RegularAccountAddress};
-pub use crate::item::shard::{Shard, ShardAddress};
+pub use crate::item::shard::{Shard, ShardAddress, ShardOwnershipOffer};
 pub use crate::item::text::Text;
 pub use crate::traits::{ShardState, ShardStateView, StateWithCache, TopState, TopStateView};
 
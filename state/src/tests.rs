@@ -40,6 +40,10 @@ pub mod helpers {
         fn transaction_time_age(&self, _: &Tracker, _parent_block_timestamp: u64) -> Option<u64> {
             Some(0)
         }
+
+        fn caps_multisig_pubkeys(&self, _parent_block_number: BlockNumber) -> bool {
+            false
+        }
     }
 
     impl FindActionHandler for TestClient {}
@@ -15,15 +15,16 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{
-    Account, ActionData, AssetScheme, CacheableItem, Metadata, OwnedAsset, RegularAccount, Shard, StateDB, StateResult,
-    Text,
+    Account, ActionData, ApproverGroup, AssetScheme, CacheableItem, InputCheck, Metadata, OwnedAsset, RegularAccount,
+    Shard, ShardEntry, ShardOwnershipOffer, StateDB, StateResult, Text,
 };
 use ckey::{public_to_address, Address, Public, Signature};
-use ctypes::transaction::ShardTransaction;
+use ctypes::transaction::{AssetOutPoint, AssetTransferInput, ShardTransaction};
 use ctypes::{BlockNumber, CommonParams, ShardId, Tracker, TxHash};
 use cvm::ChainTimeInfo;
 use merkle_trie::Result as TrieResult;
 use primitives::{Bytes, H160, H256};
+use std::collections::HashSet;
 
 
 pub trait TopStateView {
@@ -106,6 +107,11 @@ pub trait TopStateView {
         Ok(self.shard(shard_id)?.map(|shard| shard.users().to_vec()))
     }
 
+    /// Get the ownership offer of the shard, which may be expired.
+    fn shard_ownership_offer(&self, shard_id: ShardId) -> TrieResult<Option<ShardOwnershipOffer>> {
+        Ok(self.shard(shard_id)?.and_then(|shard| shard.ownership_offer().cloned()))
+    }
+
     /// Get the asset scheme.
     fn asset_scheme(&self, shard_id: ShardId, asset_type: H160) -> TrieResult<Option<AssetScheme>> {
         match self.shard_state(shard_id)? {
@@ -122,9 +128,34 @@ pub trait TopStateView {
         }
     }
 
+    /// Resolve the prev-outs of the inputs of a transfer and compare them with the assets.
+    /// An input whose shard doesn't exist is reported as not found.
+    fn check_transfer_inputs(&self, inputs: &[AssetTransferInput]) -> TrieResult<Vec<InputCheck>> {
+        let mut spent = HashSet::new();
+        let mut checks = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let AssetOutPoint {
+                shard_id,
+                tracker,
+                index,
+                ..
+            } = input.prev_out;
+            let mut check = match self.shard_state(shard_id)? {
+                None => InputCheck::new(input, None),
+                Some(state) => state.check_input(input)?,
+            };
+            check.duplicated = !spent.insert((shard_id, tracker, index));
+            checks.push(check);
+        }
+        Ok(checks)
+    }
+
     fn text(&self, key: &H256) -> TrieResult<Option<Text>>;
 
     fn action_data(&self, key: &H256) -> TrieResult<Option<ActionData>>;
+
+    /// Returns the approver group that the address refers to.
+    fn approver_group(&self, address: &Address) -> TrieResult<Option<ApproverGroup>>;
 }
 
 pub trait ShardStateView {
@@ -132,6 +163,15 @@ pub trait ShardStateView {
     fn asset_scheme(&self, asset_type: H160) -> TrieResult<Option<AssetScheme>>;
     /// Get the asset.
     fn asset(&self, tracker: Tracker, index: usize) -> TrieResult<Option<OwnedAsset>>;
+    /// Get at most `limit` committed entries whose trie paths are not less than `from`,
+    /// and the path of the next entry if there are more.
+    fn entries(&self, from: &H256, limit: usize) -> TrieResult<(Vec<(H256, ShardEntry)>, Option<H256>)>;
+
+    /// Compare the asset at the prev-out of the input with the input, without running the scripts.
+    fn check_input(&self, input: &AssetTransferInput) -> TrieResult<InputCheck> {
+        let asset = self.asset(input.prev_out.tracker, input.prev_out.index)?;
+        Ok(InputCheck::new(input, asset))
+    }
 }
 
 pub trait ShardState {
@@ -164,15 +204,35 @@ pub trait TopState {
 
     /// Set the regular key of account `owner_public`
     fn set_regular_key(&mut self, owner_public: &Public, key: &Public) -> StateResult<()>;
+    /// Remove the regular key of account `owner_address` and the mapping from the key to the account
+    fn revoke_regular_key(&mut self, owner_address: &Address) -> StateResult<()>;
 
     fn create_shard(&mut self, fee_payer: &Address, tx_hash: TxHash, users: Vec<Address>) -> StateResult<()>;
     fn change_shard_owners(&mut self, shard_id: ShardId, owners: &[Address], sender: &Address) -> StateResult<()>;
     fn change_shard_users(&mut self, shard_id: ShardId, users: &[Address], sender: &Address) -> StateResult<()>;
+    /// Replaces the ownership offer of the shard. It can be accepted until `expiry` blocks after `block_number`.
+    fn offer_shard_ownership(
+        &mut self,
+        shard_id: ShardId,
+        new_owners: &[Address],
+        expiry: u64,
+        sender: &Address,
+        block_number: BlockNumber,
+    ) -> StateResult<()>;
+    fn accept_shard_ownership(
+        &mut self,
+        shard_id: ShardId,
+        sender: &Address,
+        block_number: BlockNumber,
+    ) -> StateResult<()>;
+    fn cancel_shard_ownership_offer(&mut self, shard_id: ShardId, sender: &Address) -> StateResult<()>;
 
     fn set_shard_root(&mut self, shard_id: ShardId, new_root: H256) -> StateResult<()>;
     fn set_shard_owners(&mut self, shard_id: ShardId, new_owners: Vec<Address>) -> StateResult<()>;
     fn set_shard_users(&mut self, shard_id: ShardId, new_users: Vec<Address>) -> StateResult<()>;
 
+    fn create_approver_group(&mut self, members: Vec<Address>, threshold: u8) -> StateResult<Address>;
+
     fn store_text(&mut self, key: &TxHash, text: Text, sig: &Signature) -> StateResult<()>;
     fn remove_text(&mut self, key: &TxHash, sig: &Signature) -> StateResult<()>;
 
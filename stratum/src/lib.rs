@@ -457,6 +457,23 @@ mod tests {
         assert_eq!(1, stratum.implementation.workers.read().len());
     }
 
+    #[test]
+    fn authorize_with_secret() {
+        let addr = get_available_test_addr(19700, 19800);
+        let stratum = Stratum::start(&addr, DummyManager::new(), Some(blake256("secret")))
+            .expect("There should be no error starting stratum");
+
+        let request = r#"{"jsonrpc": "2.0", "method": "mining.authorize", "params": ["miner1", "wrong"], "id": 1}"#;
+        let response = String::from_utf8(dummy_request(&addr, request)).unwrap();
+        assert_eq!(terminated_str(r#"{"jsonrpc":"2.0","result":false,"id":1}"#), response);
+        assert_eq!(0, stratum.implementation.workers.read().len());
+
+        let request = r#"{"jsonrpc": "2.0", "method": "mining.authorize", "params": ["miner1", "secret"], "id": 2}"#;
+        let response = String::from_utf8(dummy_request(&addr, request)).unwrap();
+        assert_eq!(terminated_str(r#"{"jsonrpc":"2.0","result":true,"id":2}"#), response);
+        assert_eq!(1, stratum.implementation.workers.read().len());
+    }
+
     #[test]
     fn push_work() {
         let addr = get_available_test_addr(19400, 19500);
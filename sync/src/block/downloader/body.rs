@@ -14,9 +14,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::message::RequestMessage;
+use super::super::message::{RequestMessage, MAX_INFLATED_BODIES_SIZE};
 use ccore::UnverifiedTransaction;
-use ctypes::{BlockHash, Header};
+use ctypes::BlockHash;
+use std::cmp;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::mem::replace;
@@ -50,8 +51,12 @@ pub struct BodyDownloader {
 }
 
 impl BodyDownloader {
-    pub fn create_request(&mut self) -> Option<RequestMessage> {
+    /// Requests as many bodies as the response can carry even if all of them are as large as `max_body_size`.
+    pub fn create_request(&mut self, max_body_size: usize) -> Option<RequestMessage> {
         const MAX_BODY_REQEUST_LENGTH: usize = 128;
+        // The list of the bodies has a header of at most 9 bytes.
+        let fitting_bodies = (MAX_INFLATED_BODIES_SIZE - 9) / cmp::max(max_body_size, 1);
+        let max_length = cmp::max(cmp::min(fitting_bodies, MAX_BODY_REQEUST_LENGTH), 1);
         let mut hashes = Vec::new();
         for t in &self.targets {
             let state = self.states.entry(t.hash).or_default();
@@ -60,7 +65,7 @@ impl BodyDownloader {
             }
             *state = State::Downloading;
             hashes.push(t.hash);
-            if hashes.len() >= MAX_BODY_REQEUST_LENGTH {
+            if hashes.len() >= max_length {
                 break
             }
         }
@@ -89,11 +94,11 @@ impl BodyDownloader {
         self.targets.iter().map(|t| t.hash).collect()
     }
 
-    pub fn add_target(&mut self, header: &Header, is_empty: bool) {
-        cdebug!(SYNC, "Add download target: {}", header.hash());
-        self.states.insert(header.hash(), State::Queued);
+    pub fn add_target(&mut self, hash: BlockHash, is_empty: bool) {
+        cdebug!(SYNC, "Add download target: {}", hash);
+        self.states.insert(hash, State::Queued);
         self.targets.push(Target {
-            hash: header.hash(),
+            hash,
             is_empty,
         });
     }
@@ -15,8 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::super::message::RequestMessage;
+use ccore::encoded::HeaderFields;
 use ccore::{BlockChainClient, BlockId};
-use ctypes::{BlockHash, Header};
+use ctypes::{BlockHash, BlockNumber, Header};
 use primitives::U256;
 use std::cmp::Ordering;
 use std::collections::HashMap;
@@ -105,13 +106,14 @@ impl HeaderDownloader {
     }
 
     /// Find header from queued headers, downloaded cache and then from blockchain
+    /// Only the fixed-size fields are copied, since the seal and the extra data are not used.
     /// Panics if header dosn't exist
-    fn pivot_header(&self) -> Header {
+    fn pivot_header(&self) -> HeaderFields {
         match self.queued.get(&self.pivot.hash) {
-            Some(header) => header.clone(),
+            Some(header) => header.into(),
             None => match self.downloaded.get(&self.pivot.hash) {
-                Some(header) => header.clone(),
-                None => self.client.block_header(&BlockId::Hash(self.pivot.hash)).unwrap().decode(),
+                Some(header) => header.into(),
+                None => self.client.block_header(&BlockId::Hash(self.pivot.hash)).unwrap().decode_fields(),
             },
         }
     }
@@ -124,6 +126,11 @@ impl HeaderDownloader {
         self.best_hash
     }
 
+    /// The number of the last header downloaded from this peer.
+    pub fn pivot_number(&self) -> BlockNumber {
+        self.pivot_header().number
+    }
+
     pub fn is_idle(&self) -> bool {
         let can_request = self.request_time.is_none() && self.total_score > self.pivot.total_score;
 
@@ -138,7 +145,7 @@ impl HeaderDownloader {
             return None
         }
 
-        let pivot_number = self.pivot_header().number();
+        let pivot_number = self.pivot_header().number;
 
         self.request_time = Some(Instant::now());
 
@@ -170,16 +177,16 @@ impl HeaderDownloader {
                 hash: headers.last().expect("Last downloaded header must exist").hash(),
                 total_score: self.pivot.total_score + new_scores,
             }
-        } else if first_header_number < pivot_header.number() {
+        } else if first_header_number < pivot_header.number {
             ctrace!(
                 SYNC,
                 "Ignore received headers, pivot is already updated since headers are imported by other peers"
             );
-        } else if first_header_number == pivot_header.number() {
-            if pivot_header.number() != 0 {
+        } else if first_header_number == pivot_header.number {
+            if pivot_header.number != 0 {
                 self.pivot = Pivot {
-                    hash: *pivot_header.parent_hash(),
-                    total_score: self.pivot.total_score - pivot_header.score(),
+                    hash: pivot_header.parent_hash,
+                    total_score: self.pivot.total_score - pivot_header.score,
                 }
             }
         } else {
@@ -15,13 +15,14 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::downloader::{BodyDownloader, HeaderDownloader};
-use super::message::{Message, RequestMessage, ResponseMessage};
-use ccore::encoded::Header as EncodedHeader;
+use super::message::{Message, MessageID, RequestMessage, ResponseMessage};
+use super::spot_check::{SpotCheck, SpotCheckConfig, Verdict, HEADERS_PER_SAMPLE};
+use super::status::SyncStatus;
 use ccore::{
     Block, BlockChainClient, BlockChainTrait, BlockId, BlockImportError, ChainNotify, Client, ImportBlock, ImportError,
     UnverifiedTransaction,
 };
-use cnetwork::{Api, EventSender, IntoSocketAddr, NetworkExtension, NodeId};
+use cnetwork::{decode_message, Api, EventSender, IntoSocketAddr, NetworkExtension, NodeId, DECODER_PANICKED};
 use cstate::FindActionHandler;
 use ctimer::TimerToken;
 use ctypes::header::{Header, Seal};
@@ -32,11 +33,12 @@ use primitives::{H256, U256};
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
 use rlp::{Encodable, Rlp};
+use std::cmp;
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use token_generator::TokenGenerator;
 
 const SYNC_TIMER_TOKEN: TimerToken = 0;
@@ -49,6 +51,9 @@ const SYNC_EXPIRE_REQUEST_INTERVAL: u64 = 15000;
 
 const SNAPSHOT_PERIOD: u64 = (1 << 14);
 
+/// The peers of this version or later understand `ResponseMessage::CompressedHeaders`.
+const COMPRESSED_HEADERS_VERSION: u64 = 1;
+
 #[derive(Debug, PartialEq)]
 pub struct TokenInfo {
     node_id: NodeId,
@@ -58,7 +63,13 @@ pub struct TokenInfo {
 pub struct Extension {
     requests: HashMap<NodeId, Vec<(u64, RequestMessage)>>,
     connected_nodes: HashSet<NodeId>,
+    peer_versions: HashMap<NodeId, u64>,
+    /// The peers that sent malformed data. They are excluded from the sync until they reconnect.
+    penalized_nodes: HashSet<NodeId>,
     header_downloaders: HashMap<NodeId, HeaderDownloader>,
+    /// The new peers whose chains are being spot-checked. They are not used as sync sources until they pass.
+    spot_checks: HashMap<NodeId, SpotCheck>,
+    spot_check_config: SpotCheckConfig,
     body_downloader: BodyDownloader,
     tokens: HashMap<NodeId, TimerToken>,
     tokens_info: HashMap<TimerToken, TokenInfo>,
@@ -66,33 +77,45 @@ pub struct Extension {
     client: Arc<Client>,
     api: Box<dyn Api>,
     last_request: u64,
+    status: Arc<SyncStatus>,
 }
 
 impl Extension {
-    pub fn new(client: Arc<Client>, api: Box<dyn Api>) -> Extension {
+    pub fn new(
+        client: Arc<Client>,
+        api: Box<dyn Api>,
+        status: Arc<SyncStatus>,
+        spot_check_config: SpotCheckConfig,
+    ) -> Extension {
         api.set_timer(SYNC_TIMER_TOKEN, Duration::from_millis(SYNC_TIMER_INTERVAL)).expect("Timer set succeeds");
 
         let mut header = client.best_header();
-        let mut hollow_headers = vec![header.decode()];
-        while client.block_body(&BlockId::Hash(header.hash())).is_none() {
+        let mut hash = header.hash();
+        let mut hollow_headers = vec![(hash, header.clone())];
+        while client.block_body(&BlockId::Hash(hash)).is_none() {
             header = client
                 .block_header(&BlockId::Hash(header.parent_hash()))
                 .expect("Every imported header must have parent");
-            hollow_headers.push(header.decode());
+            hash = header.hash();
+            hollow_headers.push((hash, header.clone()));
         }
         let mut body_downloader = BodyDownloader::default();
         for neighbors in hollow_headers.windows(2).rev() {
-            let child = &neighbors[0];
-            let parent = &neighbors[1];
-            cdebug!(SYNC, "Adding block #{} (hash: {}) for initial body download target", child.number(), child.hash());
-            let is_empty = child.transactions_root() == parent.transactions_root();
-            body_downloader.add_target(child, is_empty);
+            let (child_hash, child) = &neighbors[0];
+            let (_, parent) = &neighbors[1];
+            cdebug!(SYNC, "Adding block #{} (hash: {}) for initial body download target", child.number(), child_hash);
+            let is_empty = child.transactions_root_ref() == parent.transactions_root_ref();
+            body_downloader.add_target(*child_hash, is_empty);
         }
         cinfo!(SYNC, "Sync extension initialized");
-        Extension {
+        let extension = Extension {
             requests: Default::default(),
             connected_nodes: Default::default(),
+            peer_versions: Default::default(),
+            penalized_nodes: Default::default(),
             header_downloaders: Default::default(),
+            spot_checks: Default::default(),
+            spot_check_config,
             body_downloader,
             tokens: Default::default(),
             tokens_info: Default::default(),
@@ -100,7 +123,10 @@ impl Extension {
             client,
             api,
             last_request: Default::default(),
-        }
+            status,
+        };
+        extension.update_sync_status();
+        extension
     }
 
     fn dismiss_request(&mut self, id: &NodeId, request_id: u64) {
@@ -133,7 +159,11 @@ impl Extension {
                 return
             }
 
-            if let Some(request) = self.body_downloader.create_request() {
+            // The bodies of the blocks after the best block may be larger if the params change, but the limit
+            // of the best block is used since the others are not imported yet.
+            let max_body_size =
+                self.client.common_params(BlockId::Latest).expect("The best block exists").max_body_size();
+            if let Some(request) = self.body_downloader.create_request(max_body_size) {
                 cdebug!(SYNC, "Request body to {} {:?}", id, request);
                 let request_id = self.last_request;
                 self.last_request += 1;
@@ -196,8 +226,18 @@ impl Extension {
         debug_assert!(!has_error);
     }
 
+    fn penalize(&mut self, id: &NodeId) {
+        cwarn!(SYNC, "Peer #{} is excluded from the sync", id);
+        self.header_downloaders.remove(id);
+        self.penalized_nodes.insert(*id);
+        self.update_sync_status();
+    }
+
     fn send_body_requests(&mut self, peer_ids: &[NodeId], best_score: &U256) {
         for id in peer_ids {
+            if self.spot_checks.contains_key(id) {
+                continue
+            }
             let peer_score = self.header_downloaders.get(id).map_or_else(Default::default, |peer| peer.total_score());
             if peer_score <= *best_score {
                 continue
@@ -216,12 +256,13 @@ impl NetworkExtension<Event> for Extension {
     }
 
     fn versions() -> &'static [u64] {
-        const VERSIONS: &[u64] = &[0];
+        const VERSIONS: &[u64] = &[0, COMPRESSED_HEADERS_VERSION];
         &VERSIONS
     }
 
-    fn on_node_added(&mut self, id: &NodeId, _version: u64) {
+    fn on_node_added(&mut self, id: &NodeId, version: u64) {
         cinfo!(SYNC, "New peer detected #{}", id);
+        self.peer_versions.insert(*id, version);
         let chain_info = self.client.chain_info();
         self.api.send(
             id,
@@ -256,7 +297,11 @@ impl NetworkExtension<Event> for Extension {
         if self.connected_nodes.remove(id) {
             cinfo!(SYNC, "Peer removed #{}", id);
 
+            self.peer_versions.remove(id);
+            self.penalized_nodes.remove(id);
             self.header_downloaders.remove(id);
+            self.spot_checks.remove(id);
+            self.update_sync_status();
 
             for (_, request) in self.requests.remove(id).into_iter().flatten() {
                 if let RequestMessage::Bodies(hashes) = request {
@@ -279,29 +324,38 @@ impl NetworkExtension<Event> for Extension {
             return
         }
 
-        if let Ok(received_message) = Rlp::new(data).as_val() {
-            match received_message {
-                Message::Status {
-                    total_score,
-                    best_hash,
-                    genesis_hash,
-                } => self.on_peer_status(id, total_score, best_hash, genesis_hash),
-                Message::Request(request_id, request) => self.on_peer_request(id, request_id, request),
-                Message::Response(request_id, response) => self.on_peer_response(id, request_id, response),
+        match decode_message(data) {
+            Ok(Message::Status {
+                total_score,
+                best_hash,
+                genesis_hash,
+            }) => self.on_peer_status(id, total_score, best_hash, genesis_hash),
+            Ok(Message::Request(request_id, request)) => self.on_peer_request(id, request_id, request),
+            Ok(Message::Response(request_id, response)) => self.on_peer_response(id, request_id, response),
+            Err(err) => {
+                cinfo!(SYNC, "Invalid message from peer {}: {:?}", id, err);
+                if err == DECODER_PANICKED {
+                    self.penalize(id);
+                } else if let Ok(MessageID::CompressedHeaders) = Rlp::new(data).val_at(0) {
+                    // A compressed batch is rejected as a whole if any of its headers doesn't follow the previous one.
+                    self.penalize(id);
+                }
             }
-        } else {
-            cinfo!(SYNC, "Invalid message from peer {}", id);
         }
     }
 
     fn on_timeout(&mut self, token: TimerToken) {
         match token {
             SYNC_TIMER_TOKEN => {
+                self.expire_spot_checks();
                 let best_proposal_score = self.client.chain_info().best_proposal_score;
                 let mut peer_ids: Vec<_> = self.header_downloaders.keys().cloned().collect();
                 peer_ids.shuffle(&mut thread_rng());
 
                 for id in &peer_ids {
+                    if self.spot_checks.contains_key(id) {
+                        continue
+                    }
                     let request = self.header_downloaders.get_mut(id).and_then(HeaderDownloader::create_request);
                     if let Some(request) = request {
                         self.send_header_request(id, request);
@@ -405,25 +459,26 @@ impl Extension {
             }
         }
 
+        // The hashes are kept with the headers, so that they are not calculated again.
         let mut headers_to_download: Vec<_> = enacted
             .into_iter()
-            .map(|hash| self.client.block_header(&BlockId::Hash(hash)).expect("Enacted header must exist"))
+            .map(|hash| (hash, self.client.block_header(&BlockId::Hash(hash)).expect("Enacted header must exist")))
             .collect();
-        headers_to_download.sort_unstable_by_key(EncodedHeader::number);
-        headers_to_download.dedup_by_key(|h| h.hash());
+        headers_to_download.sort_unstable_by_key(|(_, header)| header.number());
+        headers_to_download.dedup_by_key(|(hash, _)| *hash);
 
         let headers: Vec<_> = headers_to_download
             .into_iter()
-            .filter(|header| self.client.block_body(&BlockId::Hash(header.hash())).is_none())
+            .filter(|(hash, _)| self.client.block_body(&BlockId::Hash(*hash)).is_none())
             .collect(); // FIXME: No need to collect here if self is not borrowed.
 
-        for header in headers {
+        for (hash, header) in headers {
             let parent = self
                 .client
                 .block_header(&BlockId::Hash(header.parent_hash()))
                 .expect("Enacted header must have parent");
-            let is_empty = header.transactions_root() == parent.transactions_root();
-            self.body_downloader.add_target(&header.decode(), is_empty);
+            let is_empty = header.transactions_root_ref() == parent.transactions_root_ref();
+            self.body_downloader.add_target(hash, is_empty);
         }
         self.body_downloader.remove_targets(&retracted);
     }
@@ -453,6 +508,10 @@ impl Extension {
 
 impl Extension {
     fn on_peer_status(&mut self, from: &NodeId, total_score: U256, best_hash: BlockHash, genesis_hash: BlockHash) {
+        if self.penalized_nodes.contains(from) {
+            cdebug!(SYNC, "Status of the penalized peer #{} is ignored", from);
+            return
+        }
         // Validity check
         if genesis_hash != self.client.chain_info().genesis_hash {
             cinfo!(SYNC, "Genesis hash mismatch with peer {}", from);
@@ -469,9 +528,74 @@ impl Extension {
             }
             Entry::Vacant(e) => {
                 e.insert(HeaderDownloader::new(self.client.clone(), total_score, best_hash));
+                let best_proposal_score = self.client.chain_info().best_proposal_score;
+                if self.spot_check_config.needs_check(total_score, best_proposal_score) {
+                    self.start_spot_check(from, best_hash);
+                }
             }
         }
         cinfo!(SYNC, "Peer #{} status update: total_score: {}, best_hash: {}", from, total_score, best_hash);
+        self.update_sync_status();
+    }
+
+    /// Requests the sampled headers of the peer's chain before trusting its score.
+    fn start_spot_check(&mut self, from: &NodeId, best_hash: BlockHash) {
+        cinfo!(SYNC, "Peer #{} claims a higher score. Its chain is spot-checked", from);
+        let best_block_number = self.client.chain_info().best_block_number;
+        let mut check = SpotCheck::new(best_hash, Instant::now() + self.spot_check_config.timeout);
+        for number in self.spot_check_config.sample_numbers(best_block_number) {
+            let request_id = self.last_request;
+            self.send_header_request(from, RequestMessage::Headers {
+                start_number: number,
+                max_count: HEADERS_PER_SAMPLE,
+            });
+            check.add_request(request_id, number);
+        }
+        self.spot_checks.insert(*from, check);
+    }
+
+    fn on_spot_check_response(&mut self, from: &NodeId, request_id: u64, headers: &[Header]) {
+        let verdict = {
+            let check = self.spot_checks.get_mut(from).expect("Only the responses to the spot checks are handled");
+            let engine = self.client.engine();
+            let client = &self.client;
+            check.on_response(
+                request_id,
+                headers,
+                |header| engine.verify_header_basic(header).is_ok() && engine.verify_block_seal(header).is_ok(),
+                |hash| client.block_header(&BlockId::Hash(*hash)).is_some(),
+            )
+        };
+        match verdict {
+            Verdict::Pending => {}
+            Verdict::Passed => {
+                cinfo!(SYNC, "Peer #{} passed the spot check", from);
+                self.spot_checks.remove(from);
+            }
+            Verdict::Failed(reason) => {
+                cwarn!(SYNC, "Peer #{} failed the spot check: {}", from, reason);
+                self.fail_spot_check(from);
+            }
+        }
+    }
+
+    fn expire_spot_checks(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<_> =
+            self.spot_checks.iter().filter(|(_, check)| check.is_expired(now)).map(|(id, _)| *id).collect();
+        for id in expired {
+            cwarn!(SYNC, "Peer #{} failed the spot check: timed out", id);
+            self.fail_spot_check(&id);
+        }
+    }
+
+    fn fail_spot_check(&mut self, id: &NodeId) {
+        if let Some(check) = self.spot_checks.remove(id) {
+            for request_id in check.request_ids() {
+                self.dismiss_request(id, *request_id);
+            }
+        }
+        self.penalize(id);
     }
 
     fn on_peer_request(&self, from: &NodeId, id: u64, request: RequestMessage) {
@@ -491,7 +615,7 @@ impl Extension {
                 max_count,
             } => {
                 ctrace!(SYNC, "Received header request from {}", from);
-                self.create_headers_response(start_number, max_count)
+                self.create_headers_response(from, start_number, max_count)
             }
             RequestMessage::Bodies(hashes) => {
                 ctrace!(SYNC, "Received body request from {}", from);
@@ -512,7 +636,11 @@ impl Extension {
             RequestMessage::Headers {
                 ..
             } => true,
-            RequestMessage::Bodies(hashes) => !hashes.is_empty(),
+            // Blocks whose bodies are pruned cannot be served, so the requests for them are ignored.
+            // Their headers are kept, so the header requests are still served.
+            RequestMessage::Bodies(hashes) => {
+                !hashes.is_empty() && hashes.iter().all(|hash| !self.client.is_block_body_pruned(&BlockId::Hash(*hash)))
+            }
             RequestMessage::StateHead(hash) => match self.client.block_number(&BlockId::Hash(*hash)) {
                 Some(number) if number % SNAPSHOT_PERIOD == 0 => true,
                 _ => false,
@@ -531,7 +659,7 @@ impl Extension {
         }
     }
 
-    fn create_headers_response(&self, start_number: BlockNumber, max_count: u64) -> ResponseMessage {
+    fn create_headers_response(&self, from: &NodeId, start_number: BlockNumber, max_count: u64) -> ResponseMessage {
         let best_proposal_header = self.client.best_proposal_header();
         let headers = (0..max_count)
             .map(|number| {
@@ -552,7 +680,10 @@ impl Extension {
             .take_while(Option::is_some)
             .map(|block| block.expect("take_while guarantees existance of item").header().decode())
             .collect();
-        ResponseMessage::Headers(headers)
+        match self.peer_versions.get(from) {
+            Some(version) if *version >= COMPRESSED_HEADERS_VERSION => ResponseMessage::CompressedHeaders(headers),
+            _ => ResponseMessage::Headers(headers),
+        }
     }
 
     fn create_bodies_response(&self, hashes: Vec<BlockHash>) -> ResponseMessage {
@@ -573,13 +704,30 @@ impl Extension {
         unimplemented!()
     }
 
-    fn on_peer_response(&mut self, from: &NodeId, id: u64, mut response: ResponseMessage) {
+    fn on_peer_response(&mut self, from: &NodeId, id: u64, response: ResponseMessage) {
         let last_request = self.requests[from].iter().find(|(i, _)| *i == id).cloned();
         if let Some((_, request)) = last_request {
+            let mut response = match response {
+                ResponseMessage::CompressedHeaders(headers) => ResponseMessage::Headers(headers),
+                response => response,
+            };
             if let ResponseMessage::Headers(headers) = &mut response {
                 headers.sort_unstable_by_key(Header::number);
             }
 
+            let is_spot_check = self.spot_checks.get(from).map_or(false, |check| check.is_waiting_for(id));
+            if is_spot_check {
+                self.dismiss_request(from, id);
+                match &response {
+                    ResponseMessage::Headers(headers) => self.on_spot_check_response(from, id, headers),
+                    _ => {
+                        cwarn!(SYNC, "Peer #{} failed the spot check: invalid response type", from);
+                        self.fail_spot_check(from);
+                    }
+                }
+                return
+            }
+
             if !self.is_valid_response(&request, &response) {
                 return
             }
@@ -697,9 +845,13 @@ impl Extension {
             match self.client.import_header(header) {
                 Err(BlockImportError::Import(ImportError::AlreadyInChain)) => exists.push(hash),
                 Err(BlockImportError::Import(ImportError::AlreadyQueued)) => queued.push(hash),
-                // FIXME: handle import errors
+                Err(err) if err.is_invalid_block() => {
+                    cwarn!(SYNC, "Peer #{} sent an invalid header({}): {:?}", from, hash, err);
+                    self.penalize(from);
+                    break
+                }
                 Err(err) => {
-                    cwarn!(SYNC, "Cannot import header({}): {:?}", hash, err);
+                    cinfo!(SYNC, "Cannot import header({}) yet: {:?}", hash, err);
                     break
                 }
                 _ => {}
@@ -716,6 +868,16 @@ impl Extension {
                 self.send_header_request(from, request);
             }
         }
+        self.update_sync_status();
+    }
+
+    /// Sets the best known number to the highest header downloaded from the connected peers.
+    /// It goes down when the peer that sent the highest header leaves.
+    fn update_sync_status(&self) {
+        let best_block_number = self.client.chain_info().best_block_number;
+        let best_known_number =
+            self.header_downloaders.values().map(HeaderDownloader::pivot_number).fold(best_block_number, cmp::max);
+        self.status.set_best_known_number(best_known_number);
     }
 
     fn import_blocks(&mut self, blocks: Vec<(BlockHash, Vec<UnverifiedTransaction>)>) {
@@ -755,9 +917,13 @@ impl Extension {
                 Err(BlockImportError::Import(ImportError::AlreadyQueued)) => {
                     cwarn!(SYNC, "Downloaded already queued in the verification queue({})", hash)
                 }
+                // The bodies come from several peers, so nobody is penalized here.
+                Err(err) if err.is_invalid_block() => {
+                    cwarn!(SYNC, "Cannot import the invalid block({}): {:?}", hash, err);
+                    break
+                }
                 Err(err) => {
-                    // FIXME: handle import errors
-                    cwarn!(SYNC, "Cannot import block({}): {:?}", hash, err);
+                    cinfo!(SYNC, "Cannot import block({}) yet: {:?}", hash, err);
                     break
                 }
                 Ok(_) => {
@@ -828,3 +994,110 @@ impl ChainNotify for BlockSyncSender {
             .unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ccore::{ClientConfig, ClientService, Miner, Scheme, NUM_COLUMNS};
+    use cnetwork::{NetworkExtensionResult, RequestId};
+    use ctimer::TimerLoop;
+    use primitives::Bytes;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct TestApi {
+        sent: Arc<Mutex<Vec<(NodeId, Message)>>>,
+    }
+
+    impl TestApi {
+        fn take_sent(&self) -> Vec<(NodeId, Message)> {
+            std::mem::replace(&mut *self.sent.lock().unwrap(), Vec::new())
+        }
+    }
+
+    impl Api for TestApi {
+        fn send(&self, node: &NodeId, message: Arc<Bytes>) {
+            self.sent.lock().unwrap().push((*node, decode_message(&message).unwrap()));
+        }
+
+        fn request(&self, _node: &NodeId, _message: Arc<Bytes>, _timeout: Duration) -> RequestId {
+            unimplemented!()
+        }
+
+        fn respond(&self, _node: &NodeId, _request: RequestId, _message: Arc<Bytes>) {
+            unimplemented!()
+        }
+
+        fn set_timer(&self, _timer: TimerToken, _duration: Duration) -> NetworkExtensionResult<()> {
+            Ok(())
+        }
+
+        fn set_timer_once(&self, _timer: TimerToken, _duration: Duration) -> NetworkExtensionResult<()> {
+            Ok(())
+        }
+
+        fn clear_timer(&self, _timer: TimerToken) -> NetworkExtensionResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn peer_failing_the_spot_check_is_penalized() {
+        let scheme = Scheme::new_test_solo();
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let miner = Arc::new(Miner::with_scheme_for_test(&scheme, db.clone()));
+        let timer_loop = TimerLoop::new(1);
+        let reseal_timer = timer_loop.new_timer_with_name("Client reseal timer");
+        let service = ClientService::start(&ClientConfig::default(), &scheme, db, miner, reseal_timer).unwrap();
+        let client = service.client();
+        let api = TestApi::default();
+        let mut extension =
+            Extension::new(Arc::clone(&client), Box::new(api.clone()), Default::default(), SpotCheckConfig::default());
+
+        let peer = NodeId::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 3485);
+        extension.on_node_added(&peer, COMPRESSED_HEADERS_VERSION);
+        let chain_info = client.chain_info();
+        let status = Message::Status {
+            total_score: chain_info.best_proposal_score + U256::from(1_000_000),
+            best_hash: H256::random().into(),
+            genesis_hash: chain_info.genesis_hash,
+        };
+        extension.on_message(&peer, &status.rlp_bytes());
+
+        // The peer is sampled from the block after our best block.
+        let sampled: Vec<_> = api
+            .take_sent()
+            .into_iter()
+            .filter_map(|(_, message)| match message {
+                Message::Request(
+                    request_id,
+                    RequestMessage::Headers {
+                        start_number,
+                        ..
+                    },
+                ) => Some((request_id, start_number)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![(0, 1), (1, 2), (2, 4), (3, 8)], sampled);
+        assert!(extension.header_downloaders.contains_key(&peer));
+
+        // The peer answers the first sample with a header of another number.
+        let mut header = Header::default();
+        header.set_number(5);
+        let response = Message::Response(0, ResponseMessage::Headers(vec![header]));
+        extension.on_message(&peer, &response.rlp_bytes());
+
+        assert!(extension.penalized_nodes.contains(&peer));
+        assert!(!extension.header_downloaders.contains_key(&peer));
+        assert!(!extension.spot_checks.contains_key(&peer));
+        assert_eq!(Vec::<(u64, RequestMessage)>::new(), extension.requests[&peer]);
+
+        // The status of the penalized peer is ignored.
+        extension.on_message(&peer, &status.rlp_bytes());
+        assert!(!extension.header_downloaders.contains_key(&peer));
+        assert_eq!(0, api.take_sent().len());
+        service.stop();
+    }
+}
@@ -0,0 +1,309 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Encodes a batch of headers, storing each header as the difference from the previous one.
+//!
+//! A delta entry is `[flags, timestamp delta, author?, extra data?, state root, transactions root?, score?, seal]`.
+//! Its parent hash is the hash of the previous header and its number is the next number.
+//! The fields marked with `?` are omitted if the flags say that they're the same as the previous header's.
+//! A header that isn't a child of the previous header is stored as `[RAW, header]`.
+
+use ctypes::Header;
+use rlp::{DecoderError, Rlp, RlpStream};
+
+const RAW: u8 = 0x80;
+const SAME_AUTHOR: u8 = 0x01;
+const SAME_EXTRA_DATA: u8 = 0x02;
+const SAME_TRANSACTIONS_ROOT: u8 = 0x04;
+const SAME_SCORE: u8 = 0x08;
+const DELTA_FLAGS: u8 = SAME_AUTHOR | SAME_EXTRA_DATA | SAME_TRANSACTIONS_ROOT | SAME_SCORE;
+
+pub fn compress(headers: &[Header], s: &mut RlpStream) {
+    s.begin_list(headers.len());
+    let mut prev: Option<&Header> = None;
+    for header in headers {
+        match prev {
+            Some(prev) if is_delta_applicable(prev, header) => append_delta(prev, header, s),
+            _ => {
+                s.begin_list(2);
+                s.append(&RAW);
+                s.append(header);
+            }
+        }
+        prev = Some(header);
+    }
+}
+
+fn is_delta_applicable(prev: &Header, header: &Header) -> bool {
+    *header.parent_hash() == prev.hash()
+        && Some(header.number()) == prev.number().checked_add(1)
+        && header.timestamp() >= prev.timestamp()
+}
+
+fn append_delta(prev: &Header, header: &Header, s: &mut RlpStream) {
+    let mut flags = 0;
+    if header.author() == prev.author() {
+        flags |= SAME_AUTHOR;
+    }
+    if header.extra_data() == prev.extra_data() {
+        flags |= SAME_EXTRA_DATA;
+    }
+    if header.transactions_root() == prev.transactions_root() {
+        flags |= SAME_TRANSACTIONS_ROOT;
+    }
+    if header.score() == prev.score() {
+        flags |= SAME_SCORE;
+    }
+
+    s.begin_list(delta_item_count(flags));
+    s.append(&flags);
+    s.append(&(header.timestamp() - prev.timestamp()));
+    if flags & SAME_AUTHOR == 0 {
+        s.append(header.author());
+    }
+    if flags & SAME_EXTRA_DATA == 0 {
+        s.append(header.extra_data());
+    }
+    s.append(header.state_root());
+    if flags & SAME_TRANSACTIONS_ROOT == 0 {
+        s.append(header.transactions_root());
+    }
+    if flags & SAME_SCORE == 0 {
+        s.append(header.score());
+    }
+    s.begin_list(header.seal().len());
+    for field in header.seal() {
+        s.append_raw(field, 1);
+    }
+}
+
+fn delta_item_count(flags: u8) -> usize {
+    // The flags, the timestamp delta, the state root and the seal are always given.
+    4 + (DELTA_FLAGS & !flags).count_ones() as usize
+}
+
+/// Fails if any entry is malformed, so the batch is accepted entirely or not at all.
+pub fn decompress(rlp: &Rlp) -> Result<Vec<Header>, DecoderError> {
+    let mut headers: Vec<Header> = Vec::with_capacity(rlp.item_count()?);
+    for entry in rlp.iter() {
+        let flags: u8 = entry.val_at(0)?;
+        let header = if flags == RAW {
+            let item_count = entry.item_count()?;
+            if item_count != 2 {
+                return Err(DecoderError::RlpIncorrectListLen {
+                    got: item_count,
+                    expected: 2,
+                })
+            }
+            entry.val_at(1)?
+        } else {
+            let prev = headers.last().ok_or(DecoderError::Custom("The first header is not a raw header"))?;
+            decode_delta(prev, flags, &entry)?
+        };
+        headers.push(header);
+    }
+    Ok(headers)
+}
+
+fn decode_delta(prev: &Header, flags: u8, entry: &Rlp) -> Result<Header, DecoderError> {
+    if flags & !DELTA_FLAGS != 0 {
+        return Err(DecoderError::Custom("Unknown header delta flags"))
+    }
+    let item_count = entry.item_count()?;
+    let expected = delta_item_count(flags);
+    if item_count != expected {
+        return Err(DecoderError::RlpIncorrectListLen {
+            got: item_count,
+            expected,
+        })
+    }
+
+    let mut header = Header::new();
+    header.set_parent_hash(prev.hash());
+    header.set_number(prev.number().checked_add(1).ok_or(DecoderError::Custom("Block number overflow"))?);
+    let timestamp_delta: u64 = entry.val_at(1)?;
+    header.set_timestamp(
+        prev.timestamp().checked_add(timestamp_delta).ok_or(DecoderError::Custom("Timestamp overflow"))?,
+    );
+
+    let mut index = 2;
+    let mut next = || {
+        index += 1;
+        entry.at(index - 1)
+    };
+    header.set_author(if flags & SAME_AUTHOR == 0 {
+        next()?.as_val()?
+    } else {
+        *prev.author()
+    });
+    header.set_extra_data(if flags & SAME_EXTRA_DATA == 0 {
+        next()?.as_val()?
+    } else {
+        prev.extra_data().clone()
+    });
+    header.set_state_root(next()?.as_val()?);
+    header.set_transactions_root(if flags & SAME_TRANSACTIONS_ROOT == 0 {
+        next()?.as_val()?
+    } else {
+        *prev.transactions_root()
+    });
+    header.set_score(if flags & SAME_SCORE == 0 {
+        next()?.as_val()?
+    } else {
+        *prev.score()
+    });
+    let seal = next()?;
+    header.set_seal(seal.iter().map(|field| field.as_raw().to_vec()).collect());
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckey::Address;
+    use primitives::{H256, U256};
+    use rlp::Encodable;
+
+    fn compressed_bytes(headers: &[Header]) -> Vec<u8> {
+        let mut s = RlpStream::new();
+        compress(headers, &mut s);
+        s.out()
+    }
+
+    /// A PoW-like chain: one author, a new state root in every block and transactions in every third block.
+    fn linear_chain(len: usize) -> Vec<Header> {
+        let author = Address::random();
+        let mut genesis = Header::new();
+        genesis.set_author(author);
+        genesis.set_score(U256::from(1_000_000));
+        genesis.set_timestamp(1_580_000_000);
+        genesis.set_state_root(H256::random());
+        let mut headers = vec![genesis];
+        for n in 1..len {
+            let prev = &headers[n - 1];
+            let mut header = Header::new();
+            header.set_parent_hash(prev.hash());
+            header.set_number(prev.number() + 1);
+            header.set_timestamp(prev.timestamp() + 10 + (n as u64 % 7));
+            header.set_author(author);
+            header.set_state_root(H256::random());
+            header.set_transactions_root(if n % 3 == 0 {
+                H256::random()
+            } else {
+                *prev.transactions_root()
+            });
+            header.set_score(U256::from(1_000_000 + n as u64 / 10));
+            header.set_seal(vec![rlp::encode(&(n as u64 * 7919))]);
+            headers.push(header);
+        }
+        headers
+    }
+
+    fn assert_same_headers(expected: &[Header], actual: &[Header]) {
+        assert_eq!(expected.len(), actual.len());
+        for (expected, actual) in expected.iter().zip(actual) {
+            assert_eq!(expected.hash(), actual.hash());
+            assert_eq!(expected.rlp_bytes(), actual.rlp_bytes());
+        }
+    }
+
+    #[test]
+    fn linear_chain_round_trip() {
+        let headers = linear_chain(100);
+        let decoded = decompress(&Rlp::new(&compressed_bytes(&headers))).unwrap();
+        assert_same_headers(&headers, &decoded);
+    }
+
+    #[test]
+    fn linear_chain_is_at_least_twice_smaller() {
+        let headers = linear_chain(1000);
+        let raw = {
+            let mut s = RlpStream::new();
+            s.append_list(&headers);
+            s.out().len()
+        };
+        let compressed = compressed_bytes(&headers).len();
+        assert!(compressed * 2 <= raw, "raw: {}, compressed: {}", raw, compressed);
+    }
+
+    #[test]
+    fn headers_out_of_chain_fall_back_to_raw_entries() {
+        let mut headers = linear_chain(5);
+        headers.extend(linear_chain(5));
+        // The timestamp goes back.
+        headers[2].set_timestamp(headers[1].timestamp() - 1);
+
+        let bytes = compressed_bytes(&headers);
+        let rlp = Rlp::new(&bytes);
+        let raw_entries: Vec<_> =
+            rlp.iter().enumerate().filter(|(_, entry)| entry.val_at::<u8>(0).unwrap() == RAW).map(|(i, _)| i).collect();
+        assert_eq!(vec![0, 2, 3, 5], raw_entries);
+        assert_same_headers(&headers, &decompress(&rlp).unwrap());
+    }
+
+    #[test]
+    fn batch_starting_with_delta_is_rejected() {
+        let headers = linear_chain(3);
+        let bytes = compressed_bytes(&headers);
+        let rlp = Rlp::new(&bytes);
+        let mut s = RlpStream::new_list(2);
+        s.append_raw(rlp.at(1).unwrap().as_raw(), 1);
+        s.append_raw(rlp.at(2).unwrap().as_raw(), 1);
+        assert!(decompress(&Rlp::new(&s.out())).is_err());
+    }
+
+    #[test]
+    fn broken_delta_rejects_the_whole_batch() {
+        let headers = linear_chain(3);
+        let bytes = compressed_bytes(&headers);
+        let rlp = Rlp::new(&bytes);
+
+        // The flags claim that the score is given, but it's not.
+        let delta = rlp.at(2).unwrap();
+        let flags: u8 = delta.val_at(0).unwrap();
+        assert_ne!(0, flags & SAME_SCORE);
+        let mut broken = RlpStream::new_list(delta.item_count().unwrap());
+        broken.append(&(flags & !SAME_SCORE));
+        for i in 1..delta.item_count().unwrap() {
+            broken.append_raw(delta.at(i).unwrap().as_raw(), 1);
+        }
+
+        let mut s = RlpStream::new_list(3);
+        s.append_raw(rlp.at(0).unwrap().as_raw(), 1);
+        s.append_raw(rlp.at(1).unwrap().as_raw(), 1);
+        s.append_raw(&broken.out(), 1);
+        assert!(decompress(&Rlp::new(&s.out())).is_err());
+    }
+
+    #[test]
+    fn unknown_flags_are_rejected() {
+        let headers = linear_chain(2);
+        let bytes = compressed_bytes(&headers);
+        let rlp = Rlp::new(&bytes);
+        let delta = rlp.at(1).unwrap();
+        let flags: u8 = delta.val_at(0).unwrap();
+
+        let mut broken = RlpStream::new_list(delta.item_count().unwrap());
+        broken.append(&(flags | 0x10));
+        for i in 1..delta.item_count().unwrap() {
+            broken.append_raw(delta.at(i).unwrap().as_raw(), 1);
+        }
+        let mut s = RlpStream::new_list(2);
+        s.append_raw(rlp.at(0).unwrap().as_raw(), 1);
+        s.append_raw(&broken.out(), 1);
+        assert!(decompress(&Rlp::new(&s.out())).is_err());
+    }
+}
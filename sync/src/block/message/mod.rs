@@ -18,11 +18,12 @@ use ctypes::BlockHash;
 use primitives::U256;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
+mod compressed_headers;
 mod request;
 mod response;
 
 pub use self::request::RequestMessage;
-pub use self::response::ResponseMessage;
+pub use self::response::{ResponseMessage, MAX_INFLATED_BODIES_SIZE};
 
 #[derive(Clone, Copy)]
 #[repr(u8)]
@@ -36,6 +37,7 @@ pub enum MessageID {
     StateHead = 0x07,
     GetStateChunk = 0x08,
     StateChunk = 0x09,
+    CompressedHeaders = 0x0a,
 }
 
 impl Encodable for MessageID {
@@ -57,6 +59,7 @@ impl Decodable for MessageID {
             0x07 => Ok(MessageID::StateHead),
             0x08 => Ok(MessageID::GetStateChunk),
             0x09 => Ok(MessageID::StateChunk),
+            0x0a => Ok(MessageID::CompressedHeaders),
             _ => Err(DecoderError::Custom("Unexpected MessageID Value")),
         }
     }
@@ -150,7 +153,11 @@ impl Decodable for Message {
                         Ok(Message::Request(request_id, RequestMessage::decode(id, &message)?))
                     }
 
-                    MessageID::Headers | MessageID::Bodies | MessageID::StateHead | MessageID::StateChunk => {
+                    MessageID::Headers
+                    | MessageID::Bodies
+                    | MessageID::StateHead
+                    | MessageID::StateChunk
+                    | MessageID::CompressedHeaders => {
                         Ok(Message::Response(request_id, ResponseMessage::decode(id, &message)?))
                     }
                     _ => Err(DecoderError::Custom("Unknown message id detected")),
@@ -14,18 +14,24 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::MessageID;
+use super::{compressed_headers, MessageID};
 use ccore::UnverifiedTransaction;
-use ctypes::Header;
+use ctypes::{Header, MAX_BODY_SIZE_LIMIT};
 use rlp::{DecoderError, Encodable, Rlp, RlpStream};
 use snap;
 
+/// The bodies in a response are rejected without inflating them if they inflate to more than this.
+/// It holds a few bodies of the largest size that the params allow.
+pub const MAX_INFLATED_BODIES_SIZE: usize = 2 * MAX_BODY_SIZE_LIMIT;
+
 #[derive(Debug)]
 pub enum ResponseMessage {
     Headers(Vec<Header>),
     Bodies(Vec<Vec<UnverifiedTransaction>>),
     StateHead(Vec<u8>),
     StateChunk(Vec<u8>),
+    /// The same as `Headers`, but encoded as the differences between the consecutive headers.
+    CompressedHeaders(Vec<Header>),
 }
 
 impl Encodable for ResponseMessage {
@@ -61,6 +67,9 @@ impl Encodable for ResponseMessage {
                 s.begin_list(1);
                 s.append(bytes);
             }
+            ResponseMessage::CompressedHeaders(headers) => {
+                compressed_headers::compress(headers, s);
+            }
         };
     }
 }
@@ -76,6 +85,7 @@ impl ResponseMessage {
             ResponseMessage::StateChunk {
                 ..
             } => MessageID::StateChunk,
+            ResponseMessage::CompressedHeaders(..) => MessageID::CompressedHeaders,
         }
     }
 
@@ -92,6 +102,14 @@ impl ResponseMessage {
                 }
 
                 let compressed: Vec<u8> = rlp.val_at(0)?;
+                let inflated_size = snap::decompress_len(&compressed).map_err(|err| {
+                    cwarn!(SYNC, "Decompression failed while decoding a body response: {}", err);
+                    DecoderError::Custom("Invalid compression format")
+                })?;
+                if inflated_size > MAX_INFLATED_BODIES_SIZE {
+                    cwarn!(SYNC, "Bodies in a response inflate to {} bytes", inflated_size);
+                    return Err(DecoderError::Custom("Too large bodies"))
+                }
                 let uncompressed = {
                     // TODO: Cache the Decoder object
                     let mut snappy_decoder = snap::Decoder::new();
@@ -129,6 +147,7 @@ impl ResponseMessage {
                 }
                 ResponseMessage::StateChunk(rlp.val_at(0)?)
             }
+            MessageID::CompressedHeaders => ResponseMessage::CompressedHeaders(compressed_headers::decompress(rlp)?),
             _ => return Err(DecoderError::Custom("Unknown message id detected")),
         };
 
@@ -138,7 +157,7 @@ impl ResponseMessage {
 
 #[cfg(test)]
 mod tests {
-    use rlp::{Encodable, Rlp};
+    use rlp::{DecoderError, Encodable, Rlp, RlpStream};
 
     use ccore::UnverifiedTransaction;
     use ckey::{Address, Signature};
@@ -168,6 +187,22 @@ mod tests {
         assert_eq_by_debug(&message, &decode_bytes(message.message_id(), message.rlp_bytes().as_ref()))
     }
 
+    #[test]
+    fn compressed_headers_message_rlp() {
+        let mut child = Header::default();
+        child.set_parent_hash(Header::default().hash());
+        child.set_number(1);
+        let hashes = vec![Header::default().hash(), child.hash()];
+
+        let message = ResponseMessage::CompressedHeaders(vec![Header::default(), child]);
+        match decode_bytes(message.message_id(), message.rlp_bytes().as_ref()) {
+            ResponseMessage::CompressedHeaders(headers) => {
+                assert_eq!(hashes, headers.iter().map(Header::hash).collect::<Vec<_>>())
+            }
+            decoded => panic!("Unexpected message: {:?}", decoded),
+        }
+    }
+
     #[test]
     fn bodies_message_rlp() {
         let message = ResponseMessage::Bodies(vec![vec![]]);
@@ -189,6 +224,19 @@ mod tests {
         assert_eq_by_debug(&message, &decode_bytes(message.message_id(), message.rlp_bytes().as_ref()));
     }
 
+    #[test]
+    fn bodies_inflating_too_much_are_rejected() {
+        // A snappy block starts with the inflated size in varint, 0x7fff_ffff here.
+        let compressed = vec![0xff, 0xff, 0xff, 0xff, 0x07, 0x00];
+        let mut s = RlpStream::new_list(1);
+        s.append(&compressed);
+        let bytes = s.out();
+        match ResponseMessage::decode(MessageID::Bodies, &Rlp::new(&bytes)) {
+            Err(DecoderError::Custom("Too large bodies")) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
     #[test]
     fn state_head_message_rlp() {
         let message = ResponseMessage::StateHead(vec![]);
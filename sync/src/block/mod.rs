@@ -17,5 +17,9 @@
 mod downloader;
 mod extension;
 mod message;
+mod spot_check;
+mod status;
 
 pub use self::extension::{BlockSyncSender, Event as BlockSyncEvent, Extension as BlockSyncExtension};
+pub use self::spot_check::SpotCheckConfig;
+pub use self::status::SyncStatus;
@@ -0,0 +1,276 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use ctypes::{BlockHash, BlockNumber, Header};
+use primitives::U256;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// The number of headers requested at each sampled block number.
+/// The second one must follow the first one.
+pub const HEADERS_PER_SAMPLE: u64 = 2;
+
+/// When a new peer claims a total score higher than ours by more than `score_threshold`,
+/// its chain is spot-checked before the peer is used as a sync source.
+#[derive(Clone, Copy, Debug)]
+pub struct SpotCheckConfig {
+    pub score_threshold: U256,
+    /// The number of sampled block numbers. Zero disables the spot check.
+    pub samples: usize,
+    /// A peer that doesn't answer all the samples in time fails the spot check.
+    pub timeout: Duration,
+}
+
+impl Default for SpotCheckConfig {
+    fn default() -> Self {
+        Self {
+            score_threshold: U256::zero(),
+            samples: 4,
+            timeout: Duration::from_secs(15),
+        }
+    }
+}
+
+impl SpotCheckConfig {
+    pub fn needs_check(&self, peer_score: U256, best_score: U256) -> bool {
+        self.samples > 0 && peer_score > best_score && peer_score - best_score > self.score_threshold
+    }
+
+    /// Samples the block numbers exponentially spaced from the block after our best block.
+    /// The headers are requested by their numbers, and the peer's best block number is unknown,
+    /// so the samples beyond the peer's chain are answered with no headers.
+    pub fn sample_numbers(&self, best_block_number: BlockNumber) -> Vec<BlockNumber> {
+        (0..self.samples.min(64)).map(|i| best_block_number.saturating_add(1 << i)).collect()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Pending,
+    Passed,
+    Failed(&'static str),
+}
+
+/// The spot check of a peer that is waiting for the responses to its samples.
+pub struct SpotCheck {
+    /// The sampled block numbers by the ids of their requests.
+    requests: HashMap<u64, BlockNumber>,
+    /// The number of the samples answered with headers.
+    answered: usize,
+    claimed_best_hash: BlockHash,
+    deadline: Instant,
+}
+
+impl SpotCheck {
+    pub fn new(claimed_best_hash: BlockHash, deadline: Instant) -> Self {
+        Self {
+            requests: HashMap::new(),
+            answered: 0,
+            claimed_best_hash,
+            deadline,
+        }
+    }
+
+    pub fn add_request(&mut self, request_id: u64, number: BlockNumber) {
+        self.requests.insert(request_id, number);
+    }
+
+    pub fn is_waiting_for(&self, request_id: u64) -> bool {
+        self.requests.contains_key(&request_id)
+    }
+
+    pub fn request_ids(&self) -> impl Iterator<Item = &u64> {
+        self.requests.keys()
+    }
+
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now >= self.deadline
+    }
+
+    /// Checks the headers answered to a sample. `verify` checks the seal of a header,
+    /// and `is_known` tells whether we have the header of the hash.
+    ///
+    /// A peer claiming a higher score must have a block after our best block,
+    /// or its best block must be the one we know.
+    pub fn on_response<V, K>(&mut self, request_id: u64, headers: &[Header], verify: V, is_known: K) -> Verdict
+    where
+        V: Fn(&Header) -> bool,
+        K: Fn(&BlockHash) -> bool, {
+        let number = match self.requests.remove(&request_id) {
+            Some(number) => number,
+            None => return Verdict::Pending,
+        };
+
+        if !headers.is_empty() {
+            if headers.len() as u64 > HEADERS_PER_SAMPLE {
+                return Verdict::Failed("too many headers")
+            }
+            if headers[0].number() != number {
+                return Verdict::Failed("unexpected block number")
+            }
+            for neighbors in headers.windows(2) {
+                let (parent, child) = (&neighbors[0], &neighbors[1]);
+                if child.number() != parent.number() + 1 || *child.parent_hash() != parent.hash() {
+                    return Verdict::Failed("headers not linked to their parents")
+                }
+            }
+            if !headers.iter().all(verify) {
+                return Verdict::Failed("implausible seal")
+            }
+            self.answered += 1;
+        }
+
+        if !self.requests.is_empty() {
+            return Verdict::Pending
+        }
+        if self.answered == 0 && !is_known(&self.claimed_best_hash) {
+            return Verdict::Failed("no header after our best block")
+        }
+        Verdict::Passed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitives::H256;
+
+    fn chain(from: BlockNumber, len: usize) -> Vec<Header> {
+        let mut headers: Vec<Header> = Vec::with_capacity(len);
+        for i in 0..len {
+            let mut header = Header::default();
+            header.set_number(from + i as BlockNumber);
+            if let Some(parent) = headers.last() {
+                header.set_parent_hash(parent.hash());
+            }
+            headers.push(header);
+        }
+        headers
+    }
+
+    fn start(config: &SpotCheckConfig, best_block_number: BlockNumber, claimed_best_hash: BlockHash) -> SpotCheck {
+        let mut check = SpotCheck::new(claimed_best_hash, Instant::now() + config.timeout);
+        for (request_id, number) in config.sample_numbers(best_block_number).into_iter().enumerate() {
+            check.add_request(request_id as u64, number);
+        }
+        check
+    }
+
+    #[test]
+    fn samples_are_exponentially_spaced() {
+        let config = SpotCheckConfig::default();
+        assert_eq!(vec![11, 12, 14, 18], config.sample_numbers(10));
+        assert!(config.needs_check(10.into(), 9.into()));
+        assert!(!config.needs_check(10.into(), 10.into()));
+
+        let config = SpotCheckConfig {
+            score_threshold: 5.into(),
+            ..Default::default()
+        };
+        assert!(!config.needs_check(10.into(), 5.into()));
+        assert!(config.needs_check(11.into(), 5.into()));
+
+        let config = SpotCheckConfig {
+            samples: 0,
+            ..Default::default()
+        };
+        assert!(!config.needs_check(10.into(), 5.into()));
+    }
+
+    #[test]
+    fn lying_peer_fails_and_honest_peer_passes_in_one_round() {
+        let config = SpotCheckConfig::default();
+        let best_block_number = 10;
+        let honest_chain = chain(0, 16);
+        let verify = |_: &Header| true;
+        let is_known = |_: &BlockHash| false;
+
+        // The liar claims a high score, but has nothing after our best block.
+        let mut liar = start(&config, best_block_number, H256::random().into());
+        // The honest peer has 5 blocks after our best block.
+        let mut honest = start(&config, best_block_number, honest_chain[15].hash());
+
+        let mut liar_verdicts = Vec::new();
+        let mut honest_verdicts = Vec::new();
+        for (request_id, number) in config.sample_numbers(best_block_number).into_iter().enumerate() {
+            let request_id = request_id as u64;
+            liar_verdicts.push(liar.on_response(request_id, &[], verify, is_known));
+
+            let from = number as usize;
+            let to = (from + HEADERS_PER_SAMPLE as usize).min(honest_chain.len());
+            let headers = honest_chain.get(from..to).unwrap_or(&[]);
+            honest_verdicts.push(honest.on_response(request_id, headers, verify, is_known));
+        }
+        assert_eq!(
+            vec![
+                Verdict::Pending,
+                Verdict::Pending,
+                Verdict::Pending,
+                Verdict::Failed("no header after our best block")
+            ],
+            liar_verdicts
+        );
+        assert_eq!(vec![Verdict::Pending, Verdict::Pending, Verdict::Pending, Verdict::Passed], honest_verdicts);
+    }
+
+    #[test]
+    fn peer_at_a_known_best_block_passes() {
+        let config = SpotCheckConfig {
+            samples: 1,
+            ..Default::default()
+        };
+        let best_hash = H256::random().into();
+        let mut check = start(&config, 10, best_hash);
+        assert_eq!(Verdict::Passed, check.on_response(0, &[], |_| true, |hash| *hash == best_hash));
+    }
+
+    #[test]
+    fn forged_headers_fail() {
+        let config = SpotCheckConfig {
+            samples: 1,
+            ..Default::default()
+        };
+        let headers = chain(11, 2);
+
+        let mut check = start(&config, 10, headers[1].hash());
+        assert_eq!(Verdict::Failed("implausible seal"), check.on_response(0, &headers, |_| false, |_| false));
+
+        let mut check = start(&config, 10, headers[1].hash());
+        let unlinked = vec![headers[0].clone(), chain(12, 1).remove(0)];
+        assert_eq!(
+            Verdict::Failed("headers not linked to their parents"),
+            check.on_response(0, &unlinked, |_| true, |_| false)
+        );
+
+        let mut check = start(&config, 10, headers[1].hash());
+        assert_eq!(
+            Verdict::Failed("unexpected block number"),
+            check.on_response(0, &headers[1..], |_| true, |_| false)
+        );
+    }
+
+    #[test]
+    fn unknown_request_is_ignored() {
+        let config = SpotCheckConfig::default();
+        let mut check = start(&config, 10, H256::random().into());
+        assert!(check.is_waiting_for(0));
+        assert!(!check.is_waiting_for(100));
+        assert_eq!(Verdict::Pending, check.on_response(100, &[], |_| true, |_| false));
+        assert!(!check.is_expired(Instant::now()));
+        assert!(check.is_expired(Instant::now() + config.timeout));
+    }
+}
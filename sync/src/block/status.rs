@@ -0,0 +1,54 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ctypes::BlockNumber;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// What the block sync extension knows about the chain of the peers.
+/// It is shared with the RPC to tell a block that isn't imported yet from a block that doesn't exist.
+#[derive(Default)]
+pub struct SyncStatus {
+    best_known_number: AtomicU64,
+}
+
+impl SyncStatus {
+    /// The highest block number whose header is downloaded from the connected peers,
+    /// or the best block number if it's higher. It is zero until the sync extension starts.
+    pub fn best_known_number(&self) -> BlockNumber {
+        self.best_known_number.load(Ordering::SeqCst)
+    }
+
+    /// Sets the best known number, which is recomputed from the connected peers.
+    pub fn set_best_known_number(&self, number: BlockNumber) {
+        self.best_known_number.store(number, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn best_known_number_follows_the_peers() {
+        let status = SyncStatus::default();
+        assert_eq!(0, status.best_known_number());
+        status.set_best_known_number(10);
+        assert_eq!(10, status.best_known_number());
+        // The peer that had the block 10 left.
+        status.set_best_known_number(5);
+        assert_eq!(5, status.best_known_number());
+    }
+}
@@ -43,8 +43,8 @@ mod block;
 mod snapshot;
 mod transaction;
 
-pub use crate::block::{BlockSyncEvent, BlockSyncExtension, BlockSyncSender};
-pub use crate::snapshot::SnapshotService;
+pub use crate::block::{BlockSyncEvent, BlockSyncExtension, BlockSyncSender, SpotCheckConfig, SyncStatus};
+pub use crate::snapshot::{SnapshotManifest, SnapshotService};
 pub use crate::transaction::TransactionSyncExtension;
 
 #[cfg(test)]
@@ -0,0 +1,178 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::error::Error;
+use ctypes::BlockHash;
+use primitives::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::fs::{read_dir, remove_dir_all, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+const MANIFEST_FILE: &str = "manifest";
+
+/// Describes a complete snapshot.
+///
+/// The manifest is written after all the chunks, so a snapshot directory without it is incomplete.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub block_hash: BlockHash,
+    pub block_number: u64,
+    pub state_root: H256,
+    /// The chunk files are named by the hashes of their root nodes.
+    pub chunks: Vec<H256>,
+}
+
+impl Manifest {
+    pub fn write<P: AsRef<Path>>(&self, dir: P) -> Result<(), Error> {
+        let mut file = File::create(dir.as_ref().join(MANIFEST_FILE))?;
+        file.write_all(&rlp::encode(self))?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    pub fn read<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let mut buf = Vec::new();
+        File::open(dir.as_ref().join(MANIFEST_FILE))?.read_to_end(&mut buf)?;
+        rlp::decode(&buf).map_err(|err| Error::SyncError(format!("Invalid snapshot manifest: {}", err)))
+    }
+}
+
+impl Encodable for Manifest {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4).append(&self.block_hash).append(&self.block_number).append(&self.state_root);
+        s.append_list(&self.chunks);
+    }
+}
+
+impl Decodable for Manifest {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 4 {
+            return Err(DecoderError::RlpInvalidLength {
+                got: item_count,
+                expected: 4,
+            })
+        }
+        Ok(Self {
+            block_hash: rlp.val_at(0)?,
+            block_number: rlp.val_at(1)?,
+            state_root: rlp.val_at(2)?,
+            chunks: rlp.list_at(3)?,
+        })
+    }
+}
+
+/// Returns the manifests of the complete snapshots under the root directory in the order of the block numbers.
+pub fn read_manifests<P: AsRef<Path>>(root_dir: P) -> Vec<Manifest> {
+    let entries = match read_dir(root_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut manifests: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| Manifest::read(entry.path()).ok())
+        .collect();
+    manifests.sort_by_key(|manifest| manifest.block_number);
+    manifests
+}
+
+/// Removes the snapshot directories without a manifest, which were left by an interrupted snapshot.
+/// It must be called before any snapshot is written.
+pub fn remove_incomplete_snapshots<P: AsRef<Path>>(root_dir: P) -> Result<Vec<PathBuf>, Error> {
+    let entries = match read_dir(root_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut removed = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() && Manifest::read(&path).is_err() {
+            remove_dir_all(&path)?;
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
+
+/// Removes the complete snapshots except the latest `keep` ones.
+/// The incomplete ones are left alone, because they can be being written.
+pub fn remove_old_snapshots<P: AsRef<Path>>(root_dir: P, keep: usize) -> Result<Vec<Manifest>, Error> {
+    let root_dir = root_dir.as_ref();
+    let manifests = read_manifests(root_dir);
+    let removed = manifests.len().saturating_sub(keep);
+    for manifest in &manifests[..removed] {
+        remove_dir_all(root_dir.join(format!("{:x}", *manifest.block_hash)))?;
+    }
+    Ok(manifests[..removed].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::rlp_encode_and_decode_test;
+    use std::fs::create_dir_all;
+    use tempfile::tempdir;
+
+    fn manifest(block_number: u64) -> Manifest {
+        Manifest {
+            block_hash: H256::random().into(),
+            block_number,
+            state_root: H256::random(),
+            chunks: vec![H256::random(), H256::random()],
+        }
+    }
+
+    #[test]
+    fn encode_and_decode() {
+        rlp_encode_and_decode_test!(manifest(3));
+    }
+
+    #[test]
+    fn old_snapshots_are_removed() {
+        let root_dir = tempdir().unwrap();
+        let manifests: Vec<_> = [2, 0, 1].iter().map(|number| manifest(*number)).collect();
+        for manifest in &manifests {
+            let dir = root_dir.path().join(format!("{:x}", *manifest.block_hash));
+            create_dir_all(&dir).unwrap();
+            manifest.write(&dir).unwrap();
+        }
+        let incomplete = root_dir.path().join("incomplete");
+        create_dir_all(&incomplete).unwrap();
+
+        let removed = remove_old_snapshots(root_dir.path(), 2).unwrap();
+        assert_eq!(vec![manifests[1].clone()], removed);
+        assert_eq!(vec![manifests[2].clone(), manifests[0].clone()], read_manifests(root_dir.path()));
+        assert!(incomplete.exists());
+    }
+
+    #[test]
+    fn incomplete_snapshots_are_removed() {
+        let root_dir = tempdir().unwrap();
+        let complete = manifest(0);
+        let complete_dir = root_dir.path().join(format!("{:x}", *complete.block_hash));
+        create_dir_all(&complete_dir).unwrap();
+        complete.write(&complete_dir).unwrap();
+        let incomplete = root_dir.path().join(format!("{:x}", H256::random()));
+        create_dir_all(&incomplete).unwrap();
+        File::create(incomplete.join(format!("{:x}", H256::random()))).unwrap();
+
+        assert_eq!(vec![incomplete.clone()], remove_incomplete_snapshots(root_dir.path()).unwrap());
+        assert!(!incomplete.exists());
+        assert_eq!(vec![complete], read_manifests(root_dir.path()));
+    }
+}
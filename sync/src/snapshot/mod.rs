@@ -15,8 +15,10 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod error;
+mod manifest;
 mod service;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod snapshot;
 
+pub use self::manifest::Manifest as SnapshotManifest;
 pub use self::service::Service as SnapshotService;
@@ -14,31 +14,133 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::error::Error;
+use super::manifest::{read_manifests, remove_incomplete_snapshots, remove_old_snapshots, Manifest};
 use super::snapshot::{Snapshot, WriteSnapshot};
 use ccore::{BlockChainClient, BlockChainTrait, BlockId, ChainNotify, Client, DatabaseClient};
 use ctypes::BlockHash;
-use std::io::ErrorKind;
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::thread::spawn;
+use kvdb::KeyValueDB;
+use primitives::H256;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{Builder, JoinHandle};
 
 pub struct Service {
     client: Arc<Client>,
     /// Snapshot root directory
-    root_dir: String,
+    root_dir: PathBuf,
     /// Snapshot creation period in unit of block numbers
     period: u64,
+    sender: Mutex<Option<SyncSender<Target>>>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+struct Target {
+    block_hash: BlockHash,
+    block_number: u64,
+    state_root: H256,
 }
 
 impl Service {
-    pub fn new(client: Arc<Client>, root_dir: String, period: u64) -> Arc<Self> {
+    /// Keeps the latest `keep` snapshots under the root directory and removes the older ones.
+    /// The incomplete snapshots left by the previous run are removed when the service starts.
+    pub fn new<P: Into<PathBuf>>(client: Arc<Client>, root_dir: P, period: u64, keep: usize) -> Arc<Self> {
+        let root_dir = root_dir.into();
+        // At most one snapshot waits for the one being written. The others are skipped, so that they don't pile up.
+        let (sender, receiver) = sync_channel(1);
+        let worker = {
+            let db = client.database();
+            let root_dir = root_dir.clone();
+            Builder::new()
+                .name("snapshot".to_string())
+                .spawn(move || run_worker(db, &root_dir, keep, receiver))
+                .expect("Cannot spawn the snapshot thread")
+        };
         Arc::new(Self {
             client,
             root_dir,
             period,
+            sender: Mutex::new(Some(sender)),
+            worker: Mutex::new(Some(worker)),
         })
     }
+
+    /// Returns the manifests of the complete snapshots in the order of the block numbers.
+    pub fn snapshots(&self) -> Vec<Manifest> {
+        read_manifests(&self.root_dir)
+    }
+
+    fn request(&self, target: Target) {
+        let sender = self.sender.lock().unwrap();
+        let sender = match sender.as_ref() {
+            Some(sender) => sender,
+            None => return,
+        };
+        match sender.try_send(target) {
+            Ok(()) => {}
+            Err(TrySendError::Full(target)) => cwarn!(
+                SNAPSHOT,
+                "Skip the snapshot of block #{}, because the previous snapshots are still being written",
+                target.block_number
+            ),
+            Err(TrySendError::Disconnected(_)) => cerror!(SNAPSHOT, "The snapshot thread is not running"),
+        }
+    }
+}
+
+impl Drop for Service {
+    /// Waits for the snapshot being written, so that the process doesn't exit in the middle of it.
+    fn drop(&mut self) {
+        self.sender.lock().unwrap().take();
+        if let Some(worker) = self.worker.lock().unwrap().take() {
+            worker.join().unwrap_or_else(|_| cerror!(SNAPSHOT, "The snapshot thread panicked"));
+        }
+    }
+}
+
+fn run_worker(db: Arc<dyn KeyValueDB>, root_dir: &Path, keep: usize, receiver: Receiver<Target>) {
+    // Nothing is being written yet, so the directories without a manifest are left by an interrupted snapshot.
+    match remove_incomplete_snapshots(root_dir) {
+        Ok(removed) => {
+            for path in removed {
+                cinfo!(SNAPSHOT, "Removed the incomplete snapshot in {:?}", path);
+            }
+        }
+        Err(err) => cerror!(SNAPSHOT, "Cannot remove the incomplete snapshots: {}", err),
+    }
+    for target in receiver {
+        let path = root_dir.join(format!("{:x}", *target.block_hash));
+        if Manifest::read(&path).is_ok() {
+            continue
+        }
+        let chunks = match Snapshot::try_new(&path).and_then(|s| s.write_snapshot(db.as_ref(), &target.state_root)) {
+            Ok(chunks) => chunks,
+            Err(err) => {
+                cerror!(SNAPSHOT, "Cannot take the snapshot of block #{}: {}", target.block_number, err);
+                continue
+            }
+        };
+        let manifest = Manifest {
+            block_hash: target.block_hash,
+            block_number: target.block_number,
+            state_root: target.state_root,
+            chunks,
+        };
+        if let Err(err) = manifest.write(&path) {
+            cerror!(SNAPSHOT, "Cannot write the snapshot manifest of block #{}: {}", target.block_number, err);
+            continue
+        }
+        cinfo!(SNAPSHOT, "Took the snapshot of block #{} in {:?}", target.block_number, path);
+
+        match remove_old_snapshots(root_dir, keep) {
+            Ok(removed) => {
+                for manifest in removed {
+                    cinfo!(SNAPSHOT, "Removed the snapshot of block #{}", manifest.block_number);
+                }
+            }
+            Err(err) => cerror!(SNAPSHOT, "Cannot remove the old snapshots: {}", err),
+        }
+    }
 }
 
 impl ChainNotify for Service {
@@ -57,18 +159,87 @@ impl ChainNotify for Service {
             .map(|hash| self.client.block_number(&BlockId::Hash(*hash)).expect("Enacted block must exist"))
             .any(|number| number % self.period == 0);
         if is_checkpoint && best_number > self.period {
+            // The snapshot is taken from a block a period behind the best block, whose state doesn't change.
             let number = (best_number / self.period - 1) * self.period;
             let header = self.client.block_header(&BlockId::Number(number)).expect("Snapshot target must exist");
 
-            let db = self.client.database();
-            let path: PathBuf = [self.root_dir.clone(), format!("{:x}", *header.hash())].iter().collect();
             let root = header.state_root();
-            // FIXME: The db can be corrupted because the CodeChain doesn't wait child threads end on exit.
-            spawn(move || match Snapshot::try_new(path).map(|s| s.write_snapshot(db.as_ref(), &root)) {
-                Ok(_) => {}
-                Err(Error::FileError(ErrorKind::AlreadyExists)) => {}
-                Err(e) => cerror!(SNAPSHOT, "{}", e),
+            if !self.client.state_db().read().contains_root(&root) {
+                cwarn!(SNAPSHOT, "Cannot take the snapshot of block #{}, whose state is not available", number);
+                return
+            }
+
+            self.request(Target {
+                block_hash: header.hash(),
+                block_number: number,
+                state_root: root,
             });
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ccore::{AccountProvider, ClientConfig, ClientService, Miner, MinerService, Scheme};
+    use ckey::Address;
+    use ctimer::TimerLoop;
+    use std::sync::Weak;
+    use std::thread::sleep;
+    use std::time::{Duration, Instant};
+    use tempfile::tempdir;
+
+    fn wait_until<F: Fn() -> bool>(condition: F, reason: &str) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !condition() {
+            assert!(Instant::now() < deadline, "{}", reason);
+            sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn snapshots_are_taken_periodically_and_the_old_ones_are_removed() {
+        let snapshot_dir = tempdir().unwrap();
+        let scheme = Scheme::new_test_solo();
+        let db = Arc::new(kvdb_memorydb::create(ccore::NUM_COLUMNS.unwrap()));
+        let miner = Miner::new(Default::default(), &scheme, AccountProvider::transient_provider(), Arc::clone(&db));
+        miner.set_author(Address::default()).unwrap();
+        let timer_loop = TimerLoop::new(1);
+        let reseal_timer = timer_loop.new_timer_with_name("Client reseal timer");
+        let client_service =
+            ClientService::start(&ClientConfig::default(), &scheme, db, Arc::clone(&miner), reseal_timer.clone())
+                .unwrap();
+        let client = client_service.client();
+        reseal_timer.set_handler(Arc::downgrade(&client));
+        let service = Service::new(Arc::clone(&client), snapshot_dir.path(), 2, 2);
+        client.add_notify(Arc::downgrade(&service) as Weak<dyn ChainNotify>);
+
+        let snapshot_numbers = || service.snapshots().iter().map(|manifest| manifest.block_number).collect::<Vec<_>>();
+        for number in 1..=10 {
+            client.update_sealing(BlockId::Latest, true);
+            wait_until(|| client.chain_info().best_block_number == number, "The block is not mined");
+            if number > 2 && number % 2 == 0 {
+                // Mining waits for the snapshot, so that none of them is skipped.
+                wait_until(|| snapshot_numbers().contains(&(number - 2)), "The snapshot is not taken");
+            }
+        }
+        wait_until(|| snapshot_numbers() == vec![6, 8], "The old snapshots are not removed");
+
+        let manifest = &service.snapshots()[1];
+        let header = client.block_header(&BlockId::Number(8)).unwrap();
+        assert_eq!(header.hash(), manifest.block_hash);
+        assert_eq!(header.state_root(), manifest.state_root);
+        assert_eq!(header.state_root(), manifest.chunks[0]);
+        for chunk in &manifest.chunks {
+            assert!(snapshot_dir
+                .path()
+                .join(format!("{:x}", *manifest.block_hash))
+                .join(format!("{:x}", chunk))
+                .exists());
+        }
+
+        drop(service);
+        miner.stop_sealing();
+        client_service.stop();
+    }
+}
@@ -183,7 +183,8 @@ impl Chunk {
 }
 
 pub trait WriteSnapshot {
-    fn write_snapshot(&self, db: &dyn KeyValueDB, root: &H256) -> Result<(), Error>;
+    /// Returns the roots of the written chunks. The head chunk comes first.
+    fn write_snapshot(&self, db: &dyn KeyValueDB, root: &H256) -> Result<Vec<H256>, Error>;
 }
 
 pub trait ReadSnapshot {
@@ -191,7 +192,7 @@ pub trait ReadSnapshot {
 }
 
 impl WriteSnapshot for Snapshot {
-    fn write_snapshot(&self, db: &dyn KeyValueDB, root: &H256) -> Result<(), Error> {
+    fn write_snapshot(&self, db: &dyn KeyValueDB, root: &H256) -> Result<Vec<H256>, Error> {
         let root_val = match db.get(COL_STATE, root) {
             Ok(Some(value)) => value.to_vec(),
             Ok(None) => return Err(Error::SyncError("Invalid state root, or the database is empty".to_string())),
@@ -205,12 +206,14 @@ impl WriteSnapshot for Snapshot {
         }
 
         self.write_nodes(root, once(&(*root, root_val)).chain(&children))?;
+        let mut chunks = vec![*root];
         for (grandchild, _) in &grandchildren {
             let nodes = enumerate_subtree(db, grandchild)?;
             self.write_nodes(grandchild, &nodes)?;
+            chunks.push(*grandchild);
         }
 
-        Ok(())
+        Ok(chunks)
     }
 }
 
@@ -16,11 +16,11 @@
 
 use super::message::Message;
 use ccore::{BlockChainClient, UnverifiedTransaction};
-use cnetwork::{Api, NetworkExtension, NodeId};
+use cnetwork::{decode_message, Api, NetworkExtension, NodeId};
 use ctimer::TimerToken;
 use ctypes::TxHash;
 use never_type::Never;
-use rlp::{Encodable, Rlp};
+use rlp::Encodable;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::Duration;
@@ -91,7 +91,7 @@ impl NetworkExtension<Never> for Extension {
     }
 
     fn on_message(&mut self, token: &NodeId, data: &[u8]) {
-        if let Ok(received_message) = Rlp::new(data).as_val() {
+        if let Ok(received_message) = decode_message(data) {
             match received_message {
                 Message::Transactions(transactions) => {
                     let transactions: Vec<_> = {
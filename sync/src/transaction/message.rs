@@ -17,6 +17,9 @@
 use ccore::UnverifiedTransaction;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
+/// The transactions in a message are rejected without inflating them if they inflate to more than this.
+const MAX_INFLATED_TRANSACTIONS_SIZE: usize = 64 * 1024 * 1024;
+
 #[derive(Debug, PartialEq)]
 pub enum Message {
     Transactions(Vec<UnverifiedTransaction>),
@@ -47,6 +50,14 @@ impl Encodable for Message {
 impl Decodable for Message {
     fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
         let compressed: Vec<u8> = rlp.as_val()?;
+        let inflated_size = snap::decompress_len(&compressed).map_err(|err| {
+            cwarn!(SYNC_TX, "Decompression failed with decoding a transactions: {}", err);
+            DecoderError::Custom("Invalid compression format")
+        })?;
+        if inflated_size > MAX_INFLATED_TRANSACTIONS_SIZE {
+            cwarn!(SYNC_TX, "Transactions in a message inflate to {} bytes", inflated_size);
+            return Err(DecoderError::Custom("Too large transactions"))
+        }
         let uncompressed = {
             // TODO: Cache the Decoder object
             let mut snappy_decoder = snap::Decoder::new();
@@ -82,6 +93,14 @@ mod tests {
         assert_eq_by_debug(&message, &decoded);
     }
 
+    #[test]
+    fn transactions_inflating_too_much_are_rejected() {
+        // A snappy block starts with the inflated size in varint, 0x7fff_ffff here.
+        let compressed = vec![0xffu8, 0xff, 0xff, 0xff, 0x07, 0x00];
+        let encoded = rlp::encode(&compressed);
+        assert_eq!(Err(rlp::DecoderError::Custom("Too large transactions")), rlp::decode::<Message>(&encoded));
+    }
+
     #[test]
     fn transactions_message_rlp_with_tx() {
         let tx = UnverifiedTransaction::new(
@@ -14,9 +14,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::transaction::ActionType;
 use cjson::scheme::Params;
-use ckey::NetworkId;
+use cjson::uint::{OverflowError, Uint};
+use ckey::{Address, NetworkId, PlatformAddress};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::convert::{TryFrom, TryInto};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct CommonParams {
@@ -66,8 +69,56 @@ pub struct CommonParams {
     max_candidate_metadata_size: usize,
 
     era: u64,
+
+    /// RevokeRegularKey is not allowed if it's not given.
+    min_revoke_regular_key_transaction_cost: u64,
+
+    /// Maximum size of the memo of Pay. The memo is not allowed if it's not given.
+    max_pay_memo_size: usize,
+    /// Additional cost of Pay for each byte of the memo.
+    min_pay_memo_byte_cost: u64,
+
+    /// The costs of the shard ownership offer fall back to the cost of SetShardOwners if they're not given.
+    min_offer_shard_ownership_transaction_cost: u64,
+    min_accept_shard_ownership_transaction_cost: u64,
+    min_cancel_shard_ownership_offer_transaction_cost: u64,
+    /// Maximum number of blocks that an ownership offer stays open. Offers are not allowed if it's not given.
+    max_shard_ownership_offer_period: u64,
+
+    /// The shares of the minimum fees that go to the block author and the treasury, in basis points.
+    /// All of the minimum fees go to the stakeholders if they're not given.
+    fee_author_share: u64,
+    fee_treasury_share: u64,
+    fee_treasury: Address,
+    /// 0 gives the fee left over by rounding to the block author and 1 gives it to the treasury.
+    fee_remainder_rule: u64,
+
+    /// Falls back to the cost of ChangeAssetScheme if it's not given.
+    min_create_approver_group_transaction_cost: u64,
+    /// Maximum number of the members of an approver group. The groups are not allowed if it's not given.
+    max_approver_group_size: usize,
+
+    /// A validator who missed more than this percentage of its precommits in a term is inactive.
+    /// The validators who didn't propose any block in the term are inactive if it's not given.
+    max_missed_precommit_percentage: u64,
 }
 
+/// The denominator of the fee shares.
+pub const FEE_SHARE_BASIS_POINTS: u64 = 10_000;
+/// The largest `max_body_size` that can be set. The sync messages are sized to carry a body of this size.
+pub const MAX_BODY_SIZE_LIMIT: usize = 32 * 1024 * 1024;
+/// `MAX_BODY_SIZE_LIMIT` is a consensus rule from this era.
+/// The chains of the earlier eras may have set a larger `max_body_size`, so their params keep being valid.
+pub const MAX_BODY_SIZE_LIMIT_ERA: u64 = 1;
+/// The stake action that changes the consensus key of a candidate is allowed from this era.
+pub const CHANGE_CONSENSUS_KEY_ERA: u64 = 2;
+/// The timelocks can be combined with `All` and `Any` from this era.
+pub const TIMELOCK_COMBINATION_ERA: u64 = 2;
+/// CHKMULTISIG rejects too many public keys from this era.
+pub const MULTISIG_PUBKEY_CAP_ERA: u64 = 2;
+const FEE_REMAINDER_TO_AUTHOR: u64 = 0;
+const FEE_REMAINDER_TO_TREASURY: u64 = 1;
+
 impl CommonParams {
     pub fn max_extra_data_size(&self) -> usize {
         self.max_extra_data_size
@@ -108,6 +159,30 @@ impl CommonParams {
     pub fn min_store_transaction_cost(&self) -> u64 {
         self.min_store_transaction_cost
     }
+    /// The minimum fee of the action type. The pay action costs more by the length of its memo.
+    pub fn min_transaction_cost(&self, action_type: ActionType) -> u64 {
+        match action_type {
+            ActionType::MintAsset => self.min_asset_mint_cost,
+            ActionType::TransferAsset => self.min_asset_transfer_cost,
+            ActionType::ChangeAssetScheme => self.min_asset_scheme_change_cost,
+            ActionType::IncreaseAssetSupply => self.min_asset_supply_increase_cost,
+            ActionType::UnwrapCCC => self.min_asset_unwrap_ccc_cost,
+            ActionType::Pay => self.min_pay_transaction_cost,
+            ActionType::SetRegularKey => self.min_set_regular_key_transaction_cost,
+            ActionType::RevokeRegularKey => self.min_revoke_regular_key_transaction_cost,
+            ActionType::CreateShard => self.min_create_shard_transaction_cost,
+            ActionType::SetShardOwners => self.min_set_shard_owners_transaction_cost,
+            ActionType::OfferShardOwnership => self.min_offer_shard_ownership_transaction_cost,
+            ActionType::AcceptShardOwnership => self.min_accept_shard_ownership_transaction_cost,
+            ActionType::CancelShardOwnershipOffer => self.min_cancel_shard_ownership_offer_transaction_cost,
+            ActionType::SetShardUsers => self.min_set_shard_users_transaction_cost,
+            ActionType::CreateApproverGroup => self.min_create_approver_group_transaction_cost,
+            ActionType::WrapCCC => self.min_wrap_ccc_transaction_cost,
+            ActionType::Store => self.min_store_transaction_cost,
+            ActionType::Remove => self.min_remove_transaction_cost,
+            ActionType::Custom => self.min_custom_transaction_cost,
+        }
+    }
     pub fn set_min_store_transaction_cost(&mut self, new_value: u64) {
         self.min_store_transaction_cost = new_value;
     }
@@ -176,6 +251,62 @@ impl CommonParams {
         self.era
     }
 
+    pub fn min_revoke_regular_key_transaction_cost(&self) -> u64 {
+        self.min_revoke_regular_key_transaction_cost
+    }
+    /// RevokeRegularKey is allowed once the params carry its cost.
+    pub fn is_revoke_regular_key_enabled(&self) -> bool {
+        self.size >= REVOKE_REGULAR_KEY_PARAM_SIZE
+    }
+
+    pub fn max_pay_memo_size(&self) -> usize {
+        self.max_pay_memo_size
+    }
+    pub fn min_pay_memo_byte_cost(&self) -> u64 {
+        self.min_pay_memo_byte_cost
+    }
+
+    pub fn min_offer_shard_ownership_transaction_cost(&self) -> u64 {
+        self.min_offer_shard_ownership_transaction_cost
+    }
+    pub fn min_accept_shard_ownership_transaction_cost(&self) -> u64 {
+        self.min_accept_shard_ownership_transaction_cost
+    }
+    pub fn min_cancel_shard_ownership_offer_transaction_cost(&self) -> u64 {
+        self.min_cancel_shard_ownership_offer_transaction_cost
+    }
+    pub fn max_shard_ownership_offer_period(&self) -> u64 {
+        self.max_shard_ownership_offer_period
+    }
+
+    pub fn fee_author_share(&self) -> u64 {
+        self.fee_author_share
+    }
+    pub fn fee_treasury_share(&self) -> u64 {
+        self.fee_treasury_share
+    }
+    pub fn fee_treasury(&self) -> Address {
+        self.fee_treasury
+    }
+    pub fn fee_remainder_to_treasury(&self) -> bool {
+        self.fee_remainder_rule == FEE_REMAINDER_TO_TREASURY
+    }
+
+    pub fn min_create_approver_group_transaction_cost(&self) -> u64 {
+        self.min_create_approver_group_transaction_cost
+    }
+    pub fn max_approver_group_size(&self) -> usize {
+        self.max_approver_group_size
+    }
+
+    pub fn max_missed_precommit_percentage(&self) -> Option<u64> {
+        if self.size >= LIVENESS_PARAM_SIZE {
+            Some(self.max_missed_precommit_percentage)
+        } else {
+            None
+        }
+    }
+
     pub fn verify(&self) -> Result<(), String> {
         if self.term_seconds != 0 {
             if self.nomination_expiration == 0 {
@@ -218,6 +349,28 @@ impl CommonParams {
                 ))
             }
         }
+        if self.era >= MAX_BODY_SIZE_LIMIT_ERA && self.max_body_size > MAX_BODY_SIZE_LIMIT {
+            return Err(format!("The maximum body size({}) exceeds {}", self.max_body_size, MAX_BODY_SIZE_LIMIT))
+        }
+        if self.fee_author_share.saturating_add(self.fee_treasury_share) > FEE_SHARE_BASIS_POINTS {
+            return Err(format!(
+                "The sum of the author share({}) and the treasury share({}) of the fee exceeds {}",
+                self.fee_author_share, self.fee_treasury_share, FEE_SHARE_BASIS_POINTS
+            ))
+        }
+        if self.max_missed_precommit_percentage > 100 {
+            return Err(format!(
+                "The maximum missed precommit percentage({}) exceeds 100",
+                self.max_missed_precommit_percentage
+            ))
+        }
+        if self.fee_remainder_rule != FEE_REMAINDER_TO_AUTHOR && self.fee_remainder_rule != FEE_REMAINDER_TO_TREASURY {
+            return Err(format!("Unknown fee remainder rule({})", self.fee_remainder_rule))
+        }
+        if (self.fee_treasury_share != 0 || self.fee_remainder_to_treasury()) && self.fee_treasury == Address::default()
+        {
+            return Err("You should set the fee treasury".to_string())
+        }
         Ok(())
     }
 
@@ -241,59 +394,134 @@ impl CommonParams {
 const DEFAULT_PARAMS_SIZE: usize = 23;
 const NUMBER_OF_STAKE_PARAMS: usize = 9;
 const NUMBER_OF_ERA_PARAMS: usize = 1;
+const NUMBER_OF_REVOKE_REGULAR_KEY_PARAMS: usize = 1;
+const NUMBER_OF_PAY_MEMO_PARAMS: usize = 2;
+const NUMBER_OF_SHARD_OWNERSHIP_OFFER_PARAMS: usize = 4;
+const NUMBER_OF_FEE_DISTRIBUTION_PARAMS: usize = 4;
+const NUMBER_OF_APPROVER_GROUP_PARAMS: usize = 2;
+const NUMBER_OF_LIVENESS_PARAMS: usize = 1;
 const STAKE_PARAM_SIZE: usize = DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS;
 const ERA_PARAM_SIZE: usize = STAKE_PARAM_SIZE + NUMBER_OF_ERA_PARAMS;
+const REVOKE_REGULAR_KEY_PARAM_SIZE: usize = ERA_PARAM_SIZE + NUMBER_OF_REVOKE_REGULAR_KEY_PARAMS;
+const PAY_MEMO_PARAM_SIZE: usize = REVOKE_REGULAR_KEY_PARAM_SIZE + NUMBER_OF_PAY_MEMO_PARAMS;
+const SHARD_OWNERSHIP_OFFER_PARAM_SIZE: usize = PAY_MEMO_PARAM_SIZE + NUMBER_OF_SHARD_OWNERSHIP_OFFER_PARAMS;
+const FEE_DISTRIBUTION_PARAM_SIZE: usize = SHARD_OWNERSHIP_OFFER_PARAM_SIZE + NUMBER_OF_FEE_DISTRIBUTION_PARAMS;
+const APPROVER_GROUP_PARAM_SIZE: usize = FEE_DISTRIBUTION_PARAM_SIZE + NUMBER_OF_APPROVER_GROUP_PARAMS;
+const LIVENESS_PARAM_SIZE: usize = APPROVER_GROUP_PARAM_SIZE + NUMBER_OF_LIVENESS_PARAMS;
+
+const VALID_SIZE: &[usize] = &[
+    DEFAULT_PARAMS_SIZE,
+    STAKE_PARAM_SIZE,
+    ERA_PARAM_SIZE,
+    REVOKE_REGULAR_KEY_PARAM_SIZE,
+    PAY_MEMO_PARAM_SIZE,
+    SHARD_OWNERSHIP_OFFER_PARAM_SIZE,
+    FEE_DISTRIBUTION_PARAM_SIZE,
+    APPROVER_GROUP_PARAM_SIZE,
+    LIVENESS_PARAM_SIZE,
+];
 
-const VALID_SIZE: &[usize] = &[DEFAULT_PARAMS_SIZE, STAKE_PARAM_SIZE, ERA_PARAM_SIZE];
+impl TryFrom<Params> for CommonParams {
+    type Error = OverflowError;
 
-impl From<Params> for CommonParams {
-    fn from(p: Params) -> Self {
-        let size = if p.era.is_some() {
+    fn try_from(p: Params) -> Result<Self, Self::Error> {
+        let size = if p.max_missed_precommit_percentage.is_some() {
+            LIVENESS_PARAM_SIZE
+        } else if p.min_create_approver_group_cost.is_some() || p.max_approver_group_size.is_some() {
+            APPROVER_GROUP_PARAM_SIZE
+        } else if p.fee_author_share.is_some()
+            || p.fee_treasury_share.is_some()
+            || p.fee_treasury.is_some()
+            || p.fee_remainder_rule.is_some()
+        {
+            FEE_DISTRIBUTION_PARAM_SIZE
+        } else if p.min_offer_shard_ownership_cost.is_some()
+            || p.min_accept_shard_ownership_cost.is_some()
+            || p.min_cancel_shard_ownership_offer_cost.is_some()
+            || p.max_shard_ownership_offer_period.is_some()
+        {
+            SHARD_OWNERSHIP_OFFER_PARAM_SIZE
+        } else if p.max_pay_memo_size.is_some() || p.min_pay_memo_byte_cost.is_some() {
+            PAY_MEMO_PARAM_SIZE
+        } else if p.min_revoke_regular_key_cost.is_some() {
+            REVOKE_REGULAR_KEY_PARAM_SIZE
+        } else if p.era.is_some() {
             ERA_PARAM_SIZE
         } else if p.term_seconds.is_some() {
             STAKE_PARAM_SIZE
         } else {
             DEFAULT_PARAMS_SIZE
         };
-        Self {
+        Ok(Self {
             size,
-            max_extra_data_size: p.max_extra_data_size.into(),
-            max_asset_scheme_metadata_size: p.max_asset_scheme_metadata_size.into(),
-            max_transfer_metadata_size: p.max_transfer_metadata_size.into(),
-            max_text_content_size: p.max_text_content_size.into(),
+            max_extra_data_size: p.max_extra_data_size.try_into()?,
+            max_asset_scheme_metadata_size: p.max_asset_scheme_metadata_size.try_into()?,
+            max_transfer_metadata_size: p.max_transfer_metadata_size.try_into()?,
+            max_text_content_size: p.max_text_content_size.try_into()?,
             network_id: p.network_id,
-            min_pay_transaction_cost: p.min_pay_cost.into(),
-            min_set_regular_key_transaction_cost: p.min_set_regular_key_cost.into(),
-            min_create_shard_transaction_cost: p.min_create_shard_cost.into(),
-            min_set_shard_owners_transaction_cost: p.min_set_shard_owners_cost.into(),
-            min_set_shard_users_transaction_cost: p.min_set_shard_users_cost.into(),
-            min_wrap_ccc_transaction_cost: p.min_wrap_ccc_cost.into(),
-            min_custom_transaction_cost: p.min_custom_cost.into(),
-            min_store_transaction_cost: p.min_store_cost.into(),
-            min_remove_transaction_cost: p.min_remove_cost.into(),
-            min_asset_mint_cost: p.min_mint_asset_cost.into(),
-            min_asset_transfer_cost: p.min_transfer_asset_cost.into(),
-            min_asset_scheme_change_cost: p.min_change_asset_scheme_cost.into(),
-            min_asset_supply_increase_cost: p.min_increase_asset_supply_cost.into(),
-            min_asset_compose_cost: p.min_compose_asset_cost.into(),
-            min_asset_decompose_cost: p.min_decompose_asset_cost.into(),
-            min_asset_unwrap_ccc_cost: p.min_unwrap_ccc_cost.into(),
-            max_body_size: p.max_body_size.into(),
-            snapshot_period: p.snapshot_period.into(),
-            term_seconds: p.term_seconds.map(From::from).unwrap_or_default(),
-            nomination_expiration: p.nomination_expiration.map(From::from).unwrap_or_default(),
-            custody_period: p.custody_period.map(From::from).unwrap_or_default(),
-            release_period: p.release_period.map(From::from).unwrap_or_default(),
-            max_num_of_validators: p.max_num_of_validators.map(From::from).unwrap_or_default(),
-            min_num_of_validators: p.min_num_of_validators.map(From::from).unwrap_or_default(),
-            delegation_threshold: p.delegation_threshold.map(From::from).unwrap_or_default(),
-            min_deposit: p.min_deposit.map(From::from).unwrap_or_default(),
-            max_candidate_metadata_size: p.max_candidate_metadata_size.map(From::from).unwrap_or_default(),
-            era: p.era.map(From::from).unwrap_or_default(),
-        }
+            min_pay_transaction_cost: p.min_pay_cost.try_into()?,
+            min_set_regular_key_transaction_cost: p.min_set_regular_key_cost.try_into()?,
+            min_create_shard_transaction_cost: p.min_create_shard_cost.try_into()?,
+            min_set_shard_owners_transaction_cost: p.min_set_shard_owners_cost.try_into()?,
+            min_set_shard_users_transaction_cost: p.min_set_shard_users_cost.try_into()?,
+            min_wrap_ccc_transaction_cost: p.min_wrap_ccc_cost.try_into()?,
+            min_custom_transaction_cost: p.min_custom_cost.try_into()?,
+            min_store_transaction_cost: p.min_store_cost.try_into()?,
+            min_remove_transaction_cost: p.min_remove_cost.try_into()?,
+            min_asset_mint_cost: p.min_mint_asset_cost.try_into()?,
+            min_asset_transfer_cost: p.min_transfer_asset_cost.try_into()?,
+            min_asset_scheme_change_cost: p.min_change_asset_scheme_cost.try_into()?,
+            min_asset_supply_increase_cost: p.min_increase_asset_supply_cost.try_into()?,
+            min_asset_compose_cost: p.min_compose_asset_cost.try_into()?,
+            min_asset_decompose_cost: p.min_decompose_asset_cost.try_into()?,
+            min_asset_unwrap_ccc_cost: p.min_unwrap_ccc_cost.try_into()?,
+            max_body_size: p.max_body_size.try_into()?,
+            snapshot_period: p.snapshot_period.try_into()?,
+            term_seconds: or_default(p.term_seconds)?,
+            nomination_expiration: or_default(p.nomination_expiration)?,
+            custody_period: or_default(p.custody_period)?,
+            release_period: or_default(p.release_period)?,
+            max_num_of_validators: or_default(p.max_num_of_validators)?,
+            min_num_of_validators: or_default(p.min_num_of_validators)?,
+            delegation_threshold: or_default(p.delegation_threshold)?,
+            min_deposit: or_default(p.min_deposit)?,
+            max_candidate_metadata_size: or_default(p.max_candidate_metadata_size)?,
+            era: or_default(p.era)?,
+            min_revoke_regular_key_transaction_cost: or_default(p.min_revoke_regular_key_cost)?,
+            max_pay_memo_size: or_default(p.max_pay_memo_size)?,
+            min_pay_memo_byte_cost: or_default(p.min_pay_memo_byte_cost)?,
+            min_offer_shard_ownership_transaction_cost: p
+                .min_offer_shard_ownership_cost
+                .unwrap_or(p.min_set_shard_owners_cost)
+                .try_into()?,
+            min_accept_shard_ownership_transaction_cost: p
+                .min_accept_shard_ownership_cost
+                .unwrap_or(p.min_set_shard_owners_cost)
+                .try_into()?,
+            min_cancel_shard_ownership_offer_transaction_cost: p
+                .min_cancel_shard_ownership_offer_cost
+                .unwrap_or(p.min_set_shard_owners_cost)
+                .try_into()?,
+            max_shard_ownership_offer_period: or_default(p.max_shard_ownership_offer_period)?,
+            fee_author_share: or_default(p.fee_author_share)?,
+            fee_treasury_share: or_default(p.fee_treasury_share)?,
+            fee_treasury: p.fee_treasury.map(PlatformAddress::into_address).unwrap_or_default(),
+            fee_remainder_rule: or_default(p.fee_remainder_rule)?,
+            min_create_approver_group_transaction_cost: p
+                .min_create_approver_group_cost
+                .unwrap_or(p.min_change_asset_scheme_cost)
+                .try_into()?,
+            max_approver_group_size: or_default(p.max_approver_group_size)?,
+            max_missed_precommit_percentage: or_default(p.max_missed_precommit_percentage)?,
+        })
     }
 }
 
+/// Returns the default if the param is omitted.
+fn or_default<T: TryFrom<Uint, Error = OverflowError> + Default>(value: Option<Uint>) -> Result<T, OverflowError> {
+    value.map_or_else(|| Ok(T::default()), T::try_from)
+}
+
 impl From<CommonParams> for Params {
     fn from(p: CommonParams) -> Params {
         #[allow(deprecated)]
@@ -337,6 +565,33 @@ impl From<CommonParams> for Params {
         if p.size >= ERA_PARAM_SIZE {
             result.era = Some(p.era().into());
         }
+        if p.size >= REVOKE_REGULAR_KEY_PARAM_SIZE {
+            result.min_revoke_regular_key_cost = Some(p.min_revoke_regular_key_transaction_cost().into());
+        }
+        if p.size >= PAY_MEMO_PARAM_SIZE {
+            result.max_pay_memo_size = Some(p.max_pay_memo_size().into());
+            result.min_pay_memo_byte_cost = Some(p.min_pay_memo_byte_cost().into());
+        }
+        if p.size >= SHARD_OWNERSHIP_OFFER_PARAM_SIZE {
+            result.min_offer_shard_ownership_cost = Some(p.min_offer_shard_ownership_transaction_cost().into());
+            result.min_accept_shard_ownership_cost = Some(p.min_accept_shard_ownership_transaction_cost().into());
+            result.min_cancel_shard_ownership_offer_cost =
+                Some(p.min_cancel_shard_ownership_offer_transaction_cost().into());
+            result.max_shard_ownership_offer_period = Some(p.max_shard_ownership_offer_period().into());
+        }
+        if p.size >= FEE_DISTRIBUTION_PARAM_SIZE {
+            result.fee_author_share = Some(p.fee_author_share().into());
+            result.fee_treasury_share = Some(p.fee_treasury_share().into());
+            result.fee_treasury = Some(PlatformAddress::new_v1(p.network_id(), p.fee_treasury()));
+            result.fee_remainder_rule = Some(p.fee_remainder_rule.into());
+        }
+        if p.size >= APPROVER_GROUP_PARAM_SIZE {
+            result.min_create_approver_group_cost = Some(p.min_create_approver_group_transaction_cost().into());
+            result.max_approver_group_size = Some(p.max_approver_group_size().into());
+        }
+        if p.size >= LIVENESS_PARAM_SIZE {
+            result.max_missed_precommit_percentage = Some(p.max_missed_precommit_percentage.into());
+        }
         result
     }
 }
@@ -382,6 +637,30 @@ impl Encodable for CommonParams {
         if self.size >= ERA_PARAM_SIZE {
             s.append(&self.era);
         }
+        if self.size >= REVOKE_REGULAR_KEY_PARAM_SIZE {
+            s.append(&self.min_revoke_regular_key_transaction_cost);
+        }
+        if self.size >= PAY_MEMO_PARAM_SIZE {
+            s.append(&self.max_pay_memo_size).append(&self.min_pay_memo_byte_cost);
+        }
+        if self.size >= SHARD_OWNERSHIP_OFFER_PARAM_SIZE {
+            s.append(&self.min_offer_shard_ownership_transaction_cost)
+                .append(&self.min_accept_shard_ownership_transaction_cost)
+                .append(&self.min_cancel_shard_ownership_offer_transaction_cost)
+                .append(&self.max_shard_ownership_offer_period);
+        }
+        if self.size >= FEE_DISTRIBUTION_PARAM_SIZE {
+            s.append(&self.fee_author_share)
+                .append(&self.fee_treasury_share)
+                .append(&self.fee_treasury)
+                .append(&self.fee_remainder_rule);
+        }
+        if self.size >= APPROVER_GROUP_PARAM_SIZE {
+            s.append(&self.min_create_approver_group_transaction_cost).append(&self.max_approver_group_size);
+        }
+        if self.size >= LIVENESS_PARAM_SIZE {
+            s.append(&self.max_missed_precommit_percentage);
+        }
     }
 }
 
@@ -451,6 +730,54 @@ impl Decodable for CommonParams {
             Default::default()
         };
 
+        let min_revoke_regular_key_transaction_cost = if size >= REVOKE_REGULAR_KEY_PARAM_SIZE {
+            rlp.val_at(33)?
+        } else {
+            Default::default()
+        };
+
+        let (max_pay_memo_size, min_pay_memo_byte_cost) = if size >= PAY_MEMO_PARAM_SIZE {
+            (rlp.val_at(34)?, rlp.val_at(35)?)
+        } else {
+            Default::default()
+        };
+
+        let (
+            min_offer_shard_ownership_transaction_cost,
+            min_accept_shard_ownership_transaction_cost,
+            min_cancel_shard_ownership_offer_transaction_cost,
+            max_shard_ownership_offer_period,
+        ) = if size >= SHARD_OWNERSHIP_OFFER_PARAM_SIZE {
+            (rlp.val_at(36)?, rlp.val_at(37)?, rlp.val_at(38)?, rlp.val_at(39)?)
+        } else {
+            (
+                min_set_shard_owners_transaction_cost,
+                min_set_shard_owners_transaction_cost,
+                min_set_shard_owners_transaction_cost,
+                0,
+            )
+        };
+
+        let (fee_author_share, fee_treasury_share, fee_treasury, fee_remainder_rule) =
+            if size >= FEE_DISTRIBUTION_PARAM_SIZE {
+                (rlp.val_at(40)?, rlp.val_at(41)?, rlp.val_at(42)?, rlp.val_at(43)?)
+            } else {
+                Default::default()
+            };
+
+        let (min_create_approver_group_transaction_cost, max_approver_group_size) = if size >= APPROVER_GROUP_PARAM_SIZE
+        {
+            (rlp.val_at(44)?, rlp.val_at(45)?)
+        } else {
+            (min_asset_scheme_change_cost, 0)
+        };
+
+        let max_missed_precommit_percentage = if size >= LIVENESS_PARAM_SIZE {
+            rlp.val_at(46)?
+        } else {
+            0
+        };
+
         Ok(Self {
             size,
             max_extra_data_size,
@@ -486,13 +813,27 @@ impl Decodable for CommonParams {
             min_deposit,
             max_candidate_metadata_size,
             era,
+            min_revoke_regular_key_transaction_cost,
+            max_pay_memo_size,
+            min_pay_memo_byte_cost,
+            min_offer_shard_ownership_transaction_cost,
+            min_accept_shard_ownership_transaction_cost,
+            min_cancel_shard_ownership_offer_transaction_cost,
+            max_shard_ownership_offer_period,
+            fee_author_share,
+            fee_treasury_share,
+            fee_treasury,
+            fee_remainder_rule,
+            min_create_approver_group_transaction_cost,
+            max_approver_group_size,
+            max_missed_precommit_percentage,
         })
     }
 }
 
 impl CommonParams {
     pub fn default_for_test() -> Self {
-        Self::from(Params::default())
+        Self::try_from(Params::default()).unwrap()
     }
 
     #[cfg(test)]
@@ -510,6 +851,59 @@ impl CommonParams {
         self.max_text_content_size = max_text_content_size;
     }
 
+    pub fn set_era_for_test(&mut self, era: u64) {
+        self.size = self.size.max(ERA_PARAM_SIZE);
+        self.era = era;
+    }
+
+    pub fn set_revoke_regular_key_params_for_test(&mut self, min_cost: u64) {
+        self.size = self.size.max(REVOKE_REGULAR_KEY_PARAM_SIZE);
+        self.min_revoke_regular_key_transaction_cost = min_cost;
+    }
+
+    pub fn set_pay_memo_params_for_test(&mut self, max_pay_memo_size: usize, min_pay_memo_byte_cost: u64) {
+        self.size = PAY_MEMO_PARAM_SIZE;
+        self.max_pay_memo_size = max_pay_memo_size;
+        self.min_pay_memo_byte_cost = min_pay_memo_byte_cost;
+    }
+
+    pub fn set_shard_ownership_offer_params_for_test(&mut self, min_cost: u64, max_shard_ownership_offer_period: u64) {
+        self.size = SHARD_OWNERSHIP_OFFER_PARAM_SIZE;
+        self.min_offer_shard_ownership_transaction_cost = min_cost;
+        self.min_accept_shard_ownership_transaction_cost = min_cost;
+        self.min_cancel_shard_ownership_offer_transaction_cost = min_cost;
+        self.max_shard_ownership_offer_period = max_shard_ownership_offer_period;
+    }
+
+    pub fn set_fee_distribution_params_for_test(
+        &mut self,
+        fee_author_share: u64,
+        fee_treasury_share: u64,
+        fee_treasury: Address,
+        fee_remainder_to_treasury: bool,
+    ) {
+        self.size = FEE_DISTRIBUTION_PARAM_SIZE;
+        self.fee_author_share = fee_author_share;
+        self.fee_treasury_share = fee_treasury_share;
+        self.fee_treasury = fee_treasury;
+        self.fee_remainder_rule = if fee_remainder_to_treasury {
+            FEE_REMAINDER_TO_TREASURY
+        } else {
+            FEE_REMAINDER_TO_AUTHOR
+        };
+    }
+
+    pub fn set_approver_group_params_for_test(&mut self, min_cost: u64, max_approver_group_size: usize) {
+        self.size = APPROVER_GROUP_PARAM_SIZE;
+        self.min_create_approver_group_transaction_cost = min_cost;
+        self.max_approver_group_size = max_approver_group_size;
+    }
+
+    pub fn set_max_missed_precommit_percentage_for_test(&mut self, max_missed_precommit_percentage: u64) {
+        self.size = LIVENESS_PARAM_SIZE;
+        self.max_missed_precommit_percentage = max_missed_precommit_percentage;
+    }
+
     pub fn set_dynamic_validator_params_for_test(
         &mut self,
         term_seconds: u64,
@@ -539,6 +933,7 @@ impl CommonParams {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use primitives::U256;
     use rlp::rlp_encode_and_decode_test;
 
     #[test]
@@ -571,6 +966,232 @@ mod tests {
         assert_ne!(rlp::encode(&origin), rlp::encode(&params));
     }
 
+    #[test]
+    fn revoke_regular_key_is_enabled_only_if_its_cost_is_given() {
+        let params = CommonParams::try_from(Params {
+            min_set_regular_key_cost: 11.into(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(params.size, DEFAULT_PARAMS_SIZE);
+        assert!(!params.is_revoke_regular_key_enabled());
+        assert_eq!(params.min_revoke_regular_key_transaction_cost(), 0);
+        let decoded: CommonParams = rlp::decode(&rlp::encode(&params)).unwrap();
+        assert!(!decoded.is_revoke_regular_key_enabled());
+        assert_eq!(decoded.min_revoke_regular_key_transaction_cost(), 0);
+
+        let params = CommonParams::try_from(Params {
+            min_set_regular_key_cost: 11.into(),
+            min_revoke_regular_key_cost: Some(7.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(params.size, REVOKE_REGULAR_KEY_PARAM_SIZE);
+        assert!(params.is_revoke_regular_key_enabled());
+        assert_eq!(params.min_revoke_regular_key_transaction_cost(), 7);
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn pay_memo_params_are_encoded_only_if_they_are_given() {
+        let origin = CommonParams::default_for_test();
+        assert_eq!(origin.max_pay_memo_size(), 0);
+        assert_eq!(origin.min_pay_memo_byte_cost(), 0);
+
+        let params = CommonParams::try_from(Params {
+            max_pay_memo_size: Some(64.into()),
+            min_pay_memo_byte_cost: Some(3.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(params.size, PAY_MEMO_PARAM_SIZE);
+        assert_eq!(params.max_pay_memo_size(), 64);
+        assert_eq!(params.min_pay_memo_byte_cost(), 3);
+        assert_ne!(rlp::encode(&origin), rlp::encode(&params));
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn shard_ownership_offer_params_are_encoded_only_if_they_are_given() {
+        let origin = CommonParams::try_from(Params {
+            min_set_shard_owners_cost: 13.into(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(origin.size, DEFAULT_PARAMS_SIZE);
+        assert_eq!(origin.min_transaction_cost(ActionType::OfferShardOwnership), 13);
+        assert_eq!(origin.min_transaction_cost(ActionType::AcceptShardOwnership), 13);
+        assert_eq!(origin.min_transaction_cost(ActionType::CancelShardOwnershipOffer), 13);
+        assert_eq!(origin.max_shard_ownership_offer_period(), 0);
+        let decoded: CommonParams = rlp::decode(&rlp::encode(&origin)).unwrap();
+        assert_eq!(origin, decoded);
+
+        let params = CommonParams::try_from(Params {
+            min_set_shard_owners_cost: 13.into(),
+            min_accept_shard_ownership_cost: Some(3.into()),
+            max_shard_ownership_offer_period: Some(100.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(params.size, SHARD_OWNERSHIP_OFFER_PARAM_SIZE);
+        assert_eq!(params.min_transaction_cost(ActionType::OfferShardOwnership), 13);
+        assert_eq!(params.min_transaction_cost(ActionType::AcceptShardOwnership), 3);
+        assert_eq!(params.min_transaction_cost(ActionType::CancelShardOwnershipOffer), 13);
+        assert_eq!(params.max_shard_ownership_offer_period(), 100);
+        assert_ne!(rlp::encode(&origin), rlp::encode(&params));
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn fee_distribution_params_are_encoded_only_if_they_are_given() {
+        let origin = CommonParams::default_for_test();
+        assert_eq!(origin.fee_author_share(), 0);
+        assert_eq!(origin.fee_treasury_share(), 0);
+        assert!(!origin.fee_remainder_to_treasury());
+
+        let treasury = Address::random();
+        let params = CommonParams::try_from(Params {
+            fee_author_share: Some(1_000.into()),
+            fee_treasury_share: Some(500.into()),
+            fee_treasury: Some(PlatformAddress::new_v1(origin.network_id(), treasury)),
+            fee_remainder_rule: Some(1.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(params.size, FEE_DISTRIBUTION_PARAM_SIZE);
+        assert_eq!(params.fee_author_share(), 1_000);
+        assert_eq!(params.fee_treasury_share(), 500);
+        assert_eq!(params.fee_treasury(), treasury);
+        assert!(params.fee_remainder_to_treasury());
+        assert_eq!(Ok(()), params.verify());
+        assert_ne!(rlp::encode(&origin), rlp::encode(&params));
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn approver_group_params_are_encoded_only_if_they_are_given() {
+        let origin = CommonParams::try_from(Params {
+            min_change_asset_scheme_cost: 17.into(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(origin.size, DEFAULT_PARAMS_SIZE);
+        assert_eq!(origin.min_transaction_cost(ActionType::CreateApproverGroup), 17);
+        assert_eq!(origin.max_approver_group_size(), 0);
+        let decoded: CommonParams = rlp::decode(&rlp::encode(&origin)).unwrap();
+        assert_eq!(origin, decoded);
+
+        let params = CommonParams::try_from(Params {
+            min_change_asset_scheme_cost: 17.into(),
+            max_approver_group_size: Some(5.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(params.size, APPROVER_GROUP_PARAM_SIZE);
+        assert_eq!(params.min_transaction_cost(ActionType::CreateApproverGroup), 17);
+        assert_eq!(params.max_approver_group_size(), 5);
+        assert_eq!(Ok(()), params.verify());
+        assert_ne!(rlp::encode(&origin), rlp::encode(&params));
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn max_missed_precommit_percentage_is_encoded_only_if_it_is_given() {
+        let origin = CommonParams::try_from(Params::default()).unwrap();
+        assert_eq!(origin.max_missed_precommit_percentage(), None);
+
+        let params = CommonParams::try_from(Params {
+            max_missed_precommit_percentage: Some(30.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(params.size, LIVENESS_PARAM_SIZE);
+        assert_eq!(params.max_missed_precommit_percentage(), Some(30));
+        assert_eq!(Ok(()), params.verify());
+        assert_ne!(rlp::encode(&origin), rlp::encode(&params));
+        rlp_encode_and_decode_test!(params);
+        assert_eq!(params, CommonParams::try_from(Params::from(params)).unwrap());
+
+        let params = CommonParams::try_from(Params {
+            max_missed_precommit_percentage: Some(101.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(params.verify().is_err());
+    }
+
+    #[test]
+    fn fee_shares_must_not_exceed_the_whole() {
+        let mut params = CommonParams::default_for_test();
+        params.set_fee_distribution_params_for_test(6_000, 4_000, Address::random(), false);
+        assert_eq!(Ok(()), params.verify());
+
+        params.set_fee_distribution_params_for_test(6_000, 4_001, Address::random(), false);
+        assert!(params.verify().is_err());
+    }
+
+    #[test]
+    fn fee_treasury_is_required_if_it_takes_a_share() {
+        let mut params = CommonParams::default_for_test();
+        params.set_fee_distribution_params_for_test(1_000, 0, Address::default(), false);
+        assert_eq!(Ok(()), params.verify());
+
+        params.set_fee_distribution_params_for_test(1_000, 1, Address::default(), false);
+        assert_eq!(Err("You should set the fee treasury".to_string()), params.verify());
+
+        params.set_fee_distribution_params_for_test(1_000, 0, Address::default(), true);
+        assert_eq!(Err("You should set the fee treasury".to_string()), params.verify());
+    }
+
+    #[test]
+    fn max_body_size_must_not_exceed_the_limit() {
+        let params = CommonParams::try_from(Params {
+            max_body_size: MAX_BODY_SIZE_LIMIT.into(),
+            era: Some(MAX_BODY_SIZE_LIMIT_ERA.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(Ok(()), params.verify());
+
+        let params = CommonParams::try_from(Params {
+            max_body_size: (MAX_BODY_SIZE_LIMIT + 1).into(),
+            era: Some(MAX_BODY_SIZE_LIMIT_ERA.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(params.verify().is_err());
+    }
+
+    #[test]
+    fn max_body_size_is_not_limited_before_the_era() {
+        let params = CommonParams::try_from(Params {
+            max_body_size: (MAX_BODY_SIZE_LIMIT + 1).into(),
+            era: Some((MAX_BODY_SIZE_LIMIT_ERA - 1).into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(Ok(()), params.verify());
+
+        // The era cannot be raised without lowering the body size.
+        let next_era = CommonParams::try_from(Params {
+            max_body_size: (MAX_BODY_SIZE_LIMIT + 1).into(),
+            era: Some(MAX_BODY_SIZE_LIMIT_ERA.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(next_era.verify_change(&params).is_err());
+    }
+
+    #[test]
+    fn params_overflowing_their_types_are_rejected() {
+        let err = CommonParams::try_from(Params {
+            max_body_size: Uint(U256::from(u64::max_value()) + 1),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert_eq!("Integer overflow when casting 18446744073709551616 to usize", err.to_string());
+    }
+
     #[test]
     #[allow(clippy::cognitive_complexity)]
     fn params_from_json() {
@@ -601,7 +1222,7 @@ mod tests {
         }"#;
 
         let params = serde_json::from_str::<Params>(s).unwrap();
-        let deserialized = CommonParams::from(params.clone());
+        let deserialized = CommonParams::try_from(params.clone()).unwrap();
         assert_eq!(deserialized.max_extra_data_size, 0x20);
         assert_eq!(deserialized.max_asset_scheme_metadata_size, 0x0400);
         assert_eq!(deserialized.max_transfer_metadata_size, 0x0100);
@@ -670,7 +1291,7 @@ mod tests {
         }"#;
 
         let params = serde_json::from_str::<Params>(s).unwrap();
-        let deserialized = CommonParams::from(params.clone());
+        let deserialized = CommonParams::try_from(params.clone()).unwrap();
         assert_eq!(deserialized.size, STAKE_PARAM_SIZE);
         assert_eq!(deserialized.max_extra_data_size, 0x20);
         assert_eq!(deserialized.max_asset_scheme_metadata_size, 0x0400);
@@ -762,7 +1383,7 @@ mod tests {
             "maxCandidateMetadataSize": 33
         }"#;
         let params = serde_json::from_str::<Params>(s).unwrap();
-        let deserialized = CommonParams::from(params.clone());
+        let deserialized = CommonParams::try_from(params.clone()).unwrap();
         assert_eq!(deserialized.size, STAKE_PARAM_SIZE);
         assert_eq!(deserialized.max_extra_data_size, 0x20);
         assert_eq!(deserialized.max_asset_scheme_metadata_size, 0x0400);
@@ -840,7 +1461,7 @@ mod tests {
             "era": 34
         }"#;
         let params = serde_json::from_str::<Params>(s).unwrap();
-        let deserialized = CommonParams::from(params.clone());
+        let deserialized = CommonParams::try_from(params.clone()).unwrap();
         assert_eq!(deserialized.size, ERA_PARAM_SIZE);
         assert_eq!(deserialized.max_extra_data_size, 0x20);
         assert_eq!(deserialized.max_asset_scheme_metadata_size, 0x0400);
@@ -40,6 +40,11 @@ pub enum Error {
         expiration: u64,
         timestamp: u64,
     },
+    /// The expiry given to the mem pool has passed
+    Expired {
+        expiry: u64,
+        timestamp: u64,
+    },
 }
 
 #[derive(Clone, Copy)]
@@ -51,6 +56,7 @@ enum ErrorID {
     TooCheapToReplace = 6,
     TxAlreadyImported = 7,
     TransferExpired = 8,
+    Expired = 9,
 }
 
 impl Encodable for ErrorID {
@@ -69,6 +75,7 @@ impl Decodable for ErrorID {
             6 => Ok(ErrorID::TooCheapToReplace),
             7 => Ok(ErrorID::TxAlreadyImported),
             8 => Ok(ErrorID::TransferExpired),
+            9 => Ok(ErrorID::Expired),
             _ => Err(DecoderError::Custom("Unexpected ErrorID Value")),
         }
     }
@@ -86,6 +93,7 @@ impl TaggedRlp for RlpHelper {
             ErrorID::TooCheapToReplace => 1,
             ErrorID::TxAlreadyImported => 1,
             ErrorID::TransferExpired => 3,
+            ErrorID::Expired => 3,
         })
     }
 }
@@ -105,6 +113,10 @@ impl Encodable for Error {
                 expiration,
                 timestamp,
             } => RlpHelper::new_tagged_list(s, ErrorID::TransferExpired).append(expiration).append(timestamp),
+            Error::Expired {
+                expiry,
+                timestamp,
+            } => RlpHelper::new_tagged_list(s, ErrorID::Expired).append(expiry).append(timestamp),
         };
     }
 }
@@ -125,6 +137,10 @@ impl Decodable for Error {
                 expiration: rlp.val_at(1)?,
                 timestamp: rlp.val_at(2)?,
             },
+            ErrorID::Expired => Error::Expired {
+                expiry: rlp.val_at(1)?,
+                timestamp: rlp.val_at(2)?,
+            },
         };
         RlpHelper::check_size(rlp, tag)?;
         Ok(error)
@@ -154,6 +170,14 @@ impl Display for Error {
                 "The TransferAsset transaction is expired. Expiration: {}, Block timestamp: {}",
                 expiration, timestamp
             ),
+            Error::Expired {
+                expiry,
+                timestamp,
+            } => write!(
+                f,
+                "The transaction expired before being mined. Expiry: {}, Block timestamp: {}",
+                expiry, timestamp
+            ),
         }
     }
 }
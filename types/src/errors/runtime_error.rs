@@ -16,7 +16,7 @@
 
 use super::TaggedRlp;
 use crate::util::unexpected::Mismatch;
-use crate::{ShardId, Tracker};
+use crate::{BlockNumber, ShardId, Tracker};
 use ckey::Address;
 use primitives::H160;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
@@ -85,8 +85,15 @@ pub enum Error {
     InvalidTransferDestination,
     NewOwnersMustContainSender,
     NotApproved(Address),
+    /// Only the registrar of the asset scheme can change it
+    NotRegistrar {
+        asset_type: H160,
+        shard_id: ShardId,
+    },
     RegularKeyAlreadyInUse,
     RegularKeyAlreadyInUseAsPlatformAccount,
+    /// Tried to revoke the regular key of an account that has none
+    RegularKeyNotSet,
     /// Script hash does not match with provided lock script
     ScriptHashMismatch(Mismatch<H160>),
     ScriptNotAllowed(H160),
@@ -105,6 +112,14 @@ pub enum Error {
         idx: usize,
         parent_height: u64,
     },
+    /// The shard has no ownership offer to accept or cancel.
+    ShardOwnershipOfferNotFound(ShardId),
+    ShardOwnershipOfferExpired {
+        shard_id: ShardId,
+        expiration: BlockNumber,
+    },
+    /// The same members with the same threshold already make the approver group.
+    ApproverGroupAlreadyExists(Address),
 }
 
 #[derive(Clone, Copy)]
@@ -144,6 +159,11 @@ enum ErrorID {
     SignatureOfInvalid = 32,
     InsufficientStakes = 33,
     InvalidValidatorIndex = 34,
+    RegularKeyNotSet = 35,
+    NotRegistrar = 36,
+    ShardOwnershipOfferNotFound = 37,
+    ShardOwnershipOfferExpired = 38,
+    ApproverGroupAlreadyExists = 39,
 }
 
 impl Encodable for ErrorID {
@@ -185,6 +205,11 @@ impl Decodable for ErrorID {
             32 => Ok(ErrorID::SignatureOfInvalid),
             33 => Ok(ErrorID::InsufficientStakes),
             34 => Ok(ErrorID::InvalidValidatorIndex),
+            35 => Ok(ErrorID::RegularKeyNotSet),
+            36 => Ok(ErrorID::NotRegistrar),
+            37 => Ok(ErrorID::ShardOwnershipOfferNotFound),
+            38 => Ok(ErrorID::ShardOwnershipOfferExpired),
+            39 => Ok(ErrorID::ApproverGroupAlreadyExists),
             _ => Err(DecoderError::Custom("Unexpected ActionTag Value")),
         }
     }
@@ -225,6 +250,11 @@ impl TaggedRlp for RlpHelper {
             ErrorID::SignatureOfInvalid => 2,
             ErrorID::InsufficientStakes => 3,
             ErrorID::InvalidValidatorIndex => 3,
+            ErrorID::RegularKeyNotSet => 1,
+            ErrorID::NotRegistrar => 3,
+            ErrorID::ShardOwnershipOfferNotFound => 2,
+            ErrorID::ShardOwnershipOfferExpired => 3,
+            ErrorID::ApproverGroupAlreadyExists => 2,
         })
     }
 }
@@ -300,7 +330,12 @@ impl Encodable for Error {
             Error::InvalidTransferDestination => RlpHelper::new_tagged_list(s, ErrorID::InvalidTransferDestination),
             Error::NewOwnersMustContainSender => RlpHelper::new_tagged_list(s, ErrorID::NewOwnersMustContainSender),
             Error::NotApproved(address) => RlpHelper::new_tagged_list(s, ErrorID::NotApproved).append(address),
+            Error::NotRegistrar {
+                asset_type,
+                shard_id,
+            } => RlpHelper::new_tagged_list(s, ErrorID::NotRegistrar).append(asset_type).append(shard_id),
             Error::RegularKeyAlreadyInUse => RlpHelper::new_tagged_list(s, ErrorID::RegularKeyAlreadyInUse),
+            Error::RegularKeyNotSet => RlpHelper::new_tagged_list(s, ErrorID::RegularKeyNotSet),
             Error::RegularKeyAlreadyInUseAsPlatformAccount => {
                 RlpHelper::new_tagged_list(s, ErrorID::RegularKeyAlreadyInUseAsPlatform)
             }
@@ -328,6 +363,16 @@ impl Encodable for Error {
                 idx,
                 parent_height,
             } => RlpHelper::new_tagged_list(s, ErrorID::InvalidValidatorIndex).append(idx).append(parent_height),
+            Error::ShardOwnershipOfferNotFound(shard_id) => {
+                RlpHelper::new_tagged_list(s, ErrorID::ShardOwnershipOfferNotFound).append(shard_id)
+            }
+            Error::ShardOwnershipOfferExpired {
+                shard_id,
+                expiration,
+            } => RlpHelper::new_tagged_list(s, ErrorID::ShardOwnershipOfferExpired).append(shard_id).append(expiration),
+            Error::ApproverGroupAlreadyExists(address) => {
+                RlpHelper::new_tagged_list(s, ErrorID::ApproverGroupAlreadyExists).append(address)
+            }
         };
     }
 }
@@ -387,7 +432,12 @@ impl Decodable for Error {
             ErrorID::InvalidTransferDestination => Error::InvalidTransferDestination,
             ErrorID::NewOwnersMustContainSender => Error::NewOwnersMustContainSender,
             ErrorID::NotApproved => Error::NotApproved(rlp.val_at(1)?),
+            ErrorID::NotRegistrar => Error::NotRegistrar {
+                asset_type: rlp.val_at(1)?,
+                shard_id: rlp.val_at(2)?,
+            },
             ErrorID::RegularKeyAlreadyInUse => Error::RegularKeyAlreadyInUse,
+            ErrorID::RegularKeyNotSet => Error::RegularKeyNotSet,
             ErrorID::RegularKeyAlreadyInUseAsPlatform => Error::RegularKeyAlreadyInUseAsPlatformAccount,
             ErrorID::ScriptHashMismatch => Error::ScriptHashMismatch(rlp.val_at(1)?),
             ErrorID::ScriptNotAllowed => Error::ScriptNotAllowed(rlp.val_at(1)?),
@@ -407,6 +457,12 @@ impl Decodable for Error {
                 idx: rlp.val_at(1)?,
                 parent_height: rlp.val_at(2)?,
             },
+            ErrorID::ShardOwnershipOfferNotFound => Error::ShardOwnershipOfferNotFound(rlp.val_at(1)?),
+            ErrorID::ShardOwnershipOfferExpired => Error::ShardOwnershipOfferExpired {
+                shard_id: rlp.val_at(1)?,
+                expiration: rlp.val_at(2)?,
+            },
+            ErrorID::ApproverGroupAlreadyExists => Error::ApproverGroupAlreadyExists(rlp.val_at(1)?),
         };
         RlpHelper::check_size(rlp, tag)?;
         Ok(error)
@@ -461,7 +517,12 @@ impl Display for Error {
             Error::InvalidTransferDestination => write!(f, "Transfer receiver is not valid account"),
             Error::NewOwnersMustContainSender => write!(f, "New owners must contain the sender"),
             Error::NotApproved(address) => write!(f, "{} should approve it.", address),
+            Error::NotRegistrar {
+                asset_type,
+                shard_id,
+            } => write!(f, "Only the registrar can change the asset scheme {}:{}", asset_type, shard_id),
             Error::RegularKeyAlreadyInUse => write!(f, "The regular key is already registered to another account"),
+            Error::RegularKeyNotSet => write!(f, "The account doesn't have a regular key"),
             Error::RegularKeyAlreadyInUseAsPlatformAccount => {
                 write!(f, "The regular key is already used as a platform account")
             }
@@ -486,6 +547,13 @@ impl Display for Error {
             Error::InvalidValidatorIndex {
                 idx, parent_height,
             } =>  write!(f, "The validator index {} is invalid at the parent hash {}", idx, parent_height),
+            Error::ShardOwnershipOfferNotFound(shard_id) =>
+                write!(f, "Shard({}) has no ownership offer", shard_id),
+            Error::ShardOwnershipOfferExpired {
+                shard_id, expiration,
+            } => write!(f, "The ownership offer of shard({}) has expired at block {}", shard_id, expiration),
+            Error::ApproverGroupAlreadyExists(address) =>
+                write!(f, "Approver group({}) already exists", address),
         }
     }
 }
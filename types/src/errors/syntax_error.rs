@@ -16,7 +16,7 @@
 
 use super::TaggedRlp;
 use crate::{ShardId, Tracker};
-use ckey::NetworkId;
+use ckey::{Address, NetworkId};
 use primitives::H160;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use std::fmt::{Display, Formatter, Result as FormatResult};
@@ -55,6 +55,17 @@ pub enum Error {
     CannotChangeWcccAssetScheme,
     DisabledTransaction,
     InvalidSignerOfWrapCCC,
+    /// A timelock is nested too deeply, has an empty combination, or is a combination before its era.
+    InvalidTimelock,
+    /// The memo of Pay exceeds the maximum size.
+    PayMemoTooBig,
+    /// The expiry of a shard ownership offer is zero or longer than the maximum period.
+    InvalidShardOwnershipOfferExpiry(u64),
+    /// An approver group has more members than the maximum size.
+    ApproverGroupTooBig(usize),
+    /// The threshold of an approver group is zero or larger than the number of the members.
+    InvalidApproverGroupThreshold(u8),
+    DuplicatedApproverGroupMember(Address),
 }
 
 #[derive(Clone, Copy)]
@@ -82,6 +93,12 @@ enum ErrorID {
     DisabledTransaction = 30,
     InvalidSignerOfWRAPCCC = 31,
     InvalidCustomAction = 32,
+    InvalidTimelock = 33,
+    PayMemoTooBig = 34,
+    InvalidShardOwnershipOfferExpiry = 35,
+    ApproverGroupTooBig = 36,
+    InvalidApproverGroupThreshold = 37,
+    DuplicatedApproverGroupMember = 38,
 }
 
 impl Encodable for ErrorID {
@@ -110,6 +127,12 @@ impl Decodable for ErrorID {
             30 => Ok(ErrorID::DisabledTransaction),
             31 => Ok(ErrorID::InvalidSignerOfWRAPCCC),
             32 => Ok(ErrorID::InvalidCustomAction),
+            33 => Ok(ErrorID::InvalidTimelock),
+            34 => Ok(ErrorID::PayMemoTooBig),
+            35 => Ok(ErrorID::InvalidShardOwnershipOfferExpiry),
+            36 => Ok(ErrorID::ApproverGroupTooBig),
+            37 => Ok(ErrorID::InvalidApproverGroupThreshold),
+            38 => Ok(ErrorID::DuplicatedApproverGroupMember),
             _ => Err(DecoderError::Custom("Unexpected ErrorID Value")),
         }
     }
@@ -138,6 +161,12 @@ impl TaggedRlp for RlpHelper {
             ErrorID::CannotChangeWCCCAssetScheme => 1,
             ErrorID::DisabledTransaction => 1,
             ErrorID::InvalidSignerOfWRAPCCC => 1,
+            ErrorID::InvalidTimelock => 1,
+            ErrorID::PayMemoTooBig => 1,
+            ErrorID::InvalidShardOwnershipOfferExpiry => 2,
+            ErrorID::ApproverGroupTooBig => 2,
+            ErrorID::InvalidApproverGroupThreshold => 2,
+            ErrorID::DuplicatedApproverGroupMember => 2,
         })
     }
 }
@@ -171,6 +200,20 @@ impl Encodable for Error {
             Error::CannotChangeWcccAssetScheme => RlpHelper::new_tagged_list(s, ErrorID::CannotChangeWCCCAssetScheme),
             Error::DisabledTransaction => RlpHelper::new_tagged_list(s, ErrorID::DisabledTransaction),
             Error::InvalidSignerOfWrapCCC => RlpHelper::new_tagged_list(s, ErrorID::InvalidSignerOfWRAPCCC),
+            Error::InvalidTimelock => RlpHelper::new_tagged_list(s, ErrorID::InvalidTimelock),
+            Error::PayMemoTooBig => RlpHelper::new_tagged_list(s, ErrorID::PayMemoTooBig),
+            Error::InvalidShardOwnershipOfferExpiry(expiry) => {
+                RlpHelper::new_tagged_list(s, ErrorID::InvalidShardOwnershipOfferExpiry).append(expiry)
+            }
+            Error::ApproverGroupTooBig(size) => {
+                RlpHelper::new_tagged_list(s, ErrorID::ApproverGroupTooBig).append(size)
+            }
+            Error::InvalidApproverGroupThreshold(threshold) => {
+                RlpHelper::new_tagged_list(s, ErrorID::InvalidApproverGroupThreshold).append(threshold)
+            }
+            Error::DuplicatedApproverGroupMember(member) => {
+                RlpHelper::new_tagged_list(s, ErrorID::DuplicatedApproverGroupMember).append(member)
+            }
         };
     }
 }
@@ -201,6 +244,12 @@ impl Decodable for Error {
             ErrorID::CannotChangeWCCCAssetScheme => Error::CannotChangeWcccAssetScheme,
             ErrorID::DisabledTransaction => Error::DisabledTransaction,
             ErrorID::InvalidSignerOfWRAPCCC => Error::InvalidSignerOfWrapCCC,
+            ErrorID::InvalidTimelock => Error::InvalidTimelock,
+            ErrorID::PayMemoTooBig => Error::PayMemoTooBig,
+            ErrorID::InvalidShardOwnershipOfferExpiry => Error::InvalidShardOwnershipOfferExpiry(rlp.val_at(1)?),
+            ErrorID::ApproverGroupTooBig => Error::ApproverGroupTooBig(rlp.val_at(1)?),
+            ErrorID::InvalidApproverGroupThreshold => Error::InvalidApproverGroupThreshold(rlp.val_at(1)?),
+            ErrorID::DuplicatedApproverGroupMember => Error::DuplicatedApproverGroupMember(rlp.val_at(1)?),
         };
         RlpHelper::check_size(rlp, tag)?;
         Ok(error)
@@ -234,6 +283,20 @@ impl Display for Error {
             Error::CannotChangeWcccAssetScheme => write!(f, "Cannot change the asset scheme of WCCC"),
             Error::DisabledTransaction => write!(f, "Used the disabled transaction"),
             Error::InvalidSignerOfWrapCCC => write!(f, "The signer of WrapCCC must be matched"),
+            Error::InvalidTimelock => {
+                write!(f, "The timelock is nested too deeply, has an empty combination, or is combined before its era")
+            }
+            Error::PayMemoTooBig => write!(f, "The memo of the payment is too big"),
+            Error::InvalidShardOwnershipOfferExpiry(expiry) => {
+                write!(f, "{} is an invalid expiry of the shard ownership offer", expiry)
+            }
+            Error::ApproverGroupTooBig(size) => write!(f, "The approver group has too many members: {}", size),
+            Error::InvalidApproverGroupThreshold(threshold) => {
+                write!(f, "{} is an invalid threshold of the approver group", threshold)
+            }
+            Error::DuplicatedApproverGroupMember(member) => {
+                write!(f, "{} is duplicated in the members of the approver group", member)
+            }
         }
     }
 }
@@ -16,6 +16,7 @@
 
 use crate::{BlockHash, BlockNumber};
 use ccrypto::{blake256, BLAKE_NULL_RLP};
+use cjson::uint::saturating_into_u64;
 use ckey::Address;
 use primitives::{Bytes, H256, U256};
 use rlp::*;
@@ -285,7 +286,7 @@ impl Decodable for Header {
             transactions_root: r.val_at(3)?,
             score: r.val_at(4)?,
             number: r.val_at(5)?,
-            timestamp: cmp::min(r.val_at::<U256>(6)?, u64::max_value().into()).as_u64(),
+            timestamp: saturating_into_u64(r.val_at(6)?),
             extra_data: r.val_at(7)?,
             seal: vec![],
             hash: RefCell::new(Some(blake256(r.as_raw()))),
@@ -41,7 +41,10 @@ pub type BlockNumber = u64;
 pub type ShardId = u16;
 
 pub use block_hash::BlockHash;
-pub use common_params::CommonParams;
+pub use common_params::{
+    CommonParams, CHANGE_CONSENSUS_KEY_ERA, FEE_SHARE_BASIS_POINTS, MAX_BODY_SIZE_LIMIT, MULTISIG_PUBKEY_CAP_ERA,
+    TIMELOCK_COMBINATION_ERA,
+};
 pub use header::Header;
 pub use tracker::Tracker;
 pub use tx_hash::TxHash;
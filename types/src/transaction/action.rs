@@ -15,8 +15,10 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::errors::SyntaxError;
-use crate::transaction::{AssetMintOutput, AssetTransferInput, AssetTransferOutput, ShardTransaction};
-use crate::{CommonParams, ShardId, Tracker, TxHash};
+use crate::transaction::{
+    ActionType, AssetMintOutput, AssetTransferInput, AssetTransferOutput, ShardTransaction, Timelock,
+};
+use crate::{CommonParams, ShardId, Tracker, TxHash, TIMELOCK_COMBINATION_ERA};
 use ccrypto::Blake;
 use ckey::{recover, Address, NetworkId, Public, Signature};
 use primitives::{Bytes, H160, H256};
@@ -34,6 +36,11 @@ enum ActionTag {
     WrapCcc = 0x07,
     Store = 0x08,
     Remove = 0x09,
+    RevokeRegularKey = 0x0A,
+    OfferShardOwnership = 0x0B,
+    AcceptShardOwnership = 0x0C,
+    CancelShardOwnershipOffer = 0x0D,
+    CreateApproverGroup = 0x0E,
     UnwrapCcc = 0x11,
     MintAsset = 0x13,
     TransferAsset = 0x14,
@@ -64,6 +71,11 @@ impl Decodable for ActionTag {
             0x07u8 => Ok(Self::WrapCcc),
             0x08u8 => Ok(Self::Store),
             0x09u8 => Ok(Self::Remove),
+            0x0Au8 => Ok(Self::RevokeRegularKey),
+            0x0Bu8 => Ok(Self::OfferShardOwnership),
+            0x0Cu8 => Ok(Self::AcceptShardOwnership),
+            0x0Du8 => Ok(Self::CancelShardOwnershipOffer),
+            0x0Eu8 => Ok(Self::CreateApproverGroup),
             0x11u8 => Ok(Self::UnwrapCcc),
             0x13u8 => Ok(Self::MintAsset),
             0x14u8 => Ok(Self::TransferAsset),
@@ -124,10 +136,13 @@ pub enum Action {
         receiver: Address,
         /// Transferred quantity.
         quantity: u64,
+        /// Arbitrary data for the receiver, e.g. the deposit ID of a user.
+        memo: Option<Bytes>,
     },
     SetRegularKey {
         key: Public,
     },
+    RevokeRegularKey,
     CreateShard {
         users: Vec<Address>,
     },
@@ -135,10 +150,29 @@ pub enum Action {
         shard_id: ShardId,
         owners: Vec<Address>,
     },
+    /// Proposes new owners of the shard. The owners change when one of them accepts it.
+    OfferShardOwnership {
+        shard_id: ShardId,
+        new_owners: Vec<Address>,
+        /// The number of blocks that the offer stays open.
+        expiry: u64,
+    },
+    AcceptShardOwnership {
+        shard_id: ShardId,
+    },
+    CancelShardOwnershipOffer {
+        shard_id: ShardId,
+    },
     SetShardUsers {
         shard_id: ShardId,
         users: Vec<Address>,
     },
+    /// Creates a group whose approval needs the signatures of `threshold` members.
+    /// An asset scheme uses the group by setting the address of the group as its approver.
+    CreateApproverGroup {
+        members: Vec<Address>,
+        threshold: u8,
+    },
     WrapCCC {
         shard_id: ShardId,
         lock_script_hash: H160,
@@ -192,6 +226,81 @@ impl Action {
         self.asset_transaction().map(|tx| tx.tracker())
     }
 
+    pub fn action_type(&self) -> ActionType {
+        match self {
+            Action::MintAsset {
+                ..
+            } => ActionType::MintAsset,
+            Action::TransferAsset {
+                ..
+            } => ActionType::TransferAsset,
+            Action::ChangeAssetScheme {
+                ..
+            } => ActionType::ChangeAssetScheme,
+            Action::IncreaseAssetSupply {
+                ..
+            } => ActionType::IncreaseAssetSupply,
+            Action::UnwrapCCC {
+                ..
+            } => ActionType::UnwrapCCC,
+            Action::Pay {
+                ..
+            } => ActionType::Pay,
+            Action::SetRegularKey {
+                ..
+            } => ActionType::SetRegularKey,
+            Action::RevokeRegularKey => ActionType::RevokeRegularKey,
+            Action::CreateShard {
+                ..
+            } => ActionType::CreateShard,
+            Action::SetShardOwners {
+                ..
+            } => ActionType::SetShardOwners,
+            Action::OfferShardOwnership {
+                ..
+            } => ActionType::OfferShardOwnership,
+            Action::AcceptShardOwnership {
+                ..
+            } => ActionType::AcceptShardOwnership,
+            Action::CancelShardOwnershipOffer {
+                ..
+            } => ActionType::CancelShardOwnershipOffer,
+            Action::SetShardUsers {
+                ..
+            } => ActionType::SetShardUsers,
+            Action::CreateApproverGroup {
+                ..
+            } => ActionType::CreateApproverGroup,
+            Action::WrapCCC {
+                ..
+            } => ActionType::WrapCCC,
+            Action::Custom {
+                ..
+            } => ActionType::Custom,
+            Action::Store {
+                ..
+            } => ActionType::Store,
+            Action::Remove {
+                ..
+            } => ActionType::Remove,
+        }
+    }
+
+    /// The minimum fee of the transaction with this action.
+    pub fn min_cost(&self, params: &CommonParams) -> u64 {
+        let min_cost = params.min_transaction_cost(self.action_type());
+        match self {
+            Action::Pay {
+                memo,
+                ..
+            } => {
+                let memo_len = memo.as_ref().map_or(0, Vec::len) as u64;
+                min_cost.saturating_add(memo_len.saturating_mul(params.min_pay_memo_byte_cost()))
+            }
+            _ => min_cost,
+        }
+    }
+
     pub fn verify(&self) -> Result<(), SyntaxError> {
         match self {
             Action::MintAsset {
@@ -221,6 +330,9 @@ impl Action {
                     return Err(SyntaxError::ZeroQuantity)
                 }
                 check_duplication_in_prev_out(burns, inputs)?;
+                if burns.iter().chain(inputs).any(|input| !has_valid_timelock(input)) {
+                    return Err(SyntaxError::InvalidTimelock)
+                }
 
                 if outputs.iter().any(|output| output.quantity == 0) {
                     return Err(SyntaxError::ZeroQuantity)
@@ -256,6 +368,9 @@ impl Action {
                 if !burn.prev_out.asset_type.is_zero() {
                     return Err(SyntaxError::InvalidAssetType(burn.prev_out.asset_type))
                 }
+                if !has_valid_timelock(burn) {
+                    return Err(SyntaxError::InvalidTimelock)
+                }
             }
             Action::WrapCCC {
                 quantity,
@@ -265,6 +380,27 @@ impl Action {
                     return Err(SyntaxError::ZeroQuantity)
                 }
             }
+            Action::OfferShardOwnership {
+                shard_id,
+                new_owners,
+                ..
+            } => {
+                if new_owners.is_empty() {
+                    return Err(SyntaxError::EmptyShardOwners(*shard_id))
+                }
+            }
+            Action::CreateApproverGroup {
+                members,
+                threshold,
+            } => {
+                if *threshold == 0 || usize::from(*threshold) > members.len() {
+                    return Err(SyntaxError::InvalidApproverGroupThreshold(*threshold))
+                }
+                let mut unique_members = HashSet::new();
+                if let Some(member) = members.iter().find(|member| !unique_members.insert(*member)) {
+                    return Err(SyntaxError::DuplicatedApproverGroupMember(*member))
+                }
+            }
             Action::Store {
                 ..
             } => {}
@@ -293,12 +429,17 @@ impl Action {
             }
             Action::TransferAsset {
                 metadata,
+                burns,
+                inputs,
                 ..
             } => {
                 let max_transfer_metadata_size = common_params.max_transfer_metadata_size();
                 if metadata.len() > max_transfer_metadata_size {
                     return Err(SyntaxError::MetadataTooBig)
                 }
+                if burns.iter().chain(inputs).any(|input| !is_timelock_allowed(input, common_params)) {
+                    return Err(SyntaxError::InvalidTimelock)
+                }
             }
             Action::ChangeAssetScheme {
                 metadata,
@@ -313,8 +454,13 @@ impl Action {
                 ..
             } => {}
             Action::UnwrapCCC {
+                burn,
                 ..
-            } => {}
+            } => {
+                if !is_timelock_allowed(burn, common_params) {
+                    return Err(SyntaxError::InvalidTimelock)
+                }
+            }
             Action::WrapCCC {
                 ..
             } => {}
@@ -327,6 +473,40 @@ impl Action {
                     return Err(SyntaxError::TextContentTooBig)
                 }
             }
+            Action::Pay {
+                memo: Some(memo),
+                ..
+            } => {
+                // The memo is disabled until the common params give its limit.
+                let max_pay_memo_size = common_params.max_pay_memo_size();
+                if max_pay_memo_size == 0 || memo.len() > max_pay_memo_size {
+                    return Err(SyntaxError::PayMemoTooBig)
+                }
+            }
+            Action::RevokeRegularKey => {
+                // It's disabled until the common params give its cost.
+                if !common_params.is_revoke_regular_key_enabled() {
+                    return Err(SyntaxError::DisabledTransaction)
+                }
+            }
+            Action::OfferShardOwnership {
+                expiry,
+                ..
+            } => {
+                // The offers are disabled until the common params give the maximum period.
+                if *expiry == 0 || *expiry > common_params.max_shard_ownership_offer_period() {
+                    return Err(SyntaxError::InvalidShardOwnershipOfferExpiry(*expiry))
+                }
+            }
+            Action::CreateApproverGroup {
+                members,
+                ..
+            } => {
+                // The groups are disabled until the common params give the maximum size.
+                if members.len() > common_params.max_approver_group_size() {
+                    return Err(SyntaxError::ApproverGroupTooBig(members.len()))
+                }
+            }
             _ => {}
         }
         Ok(())
@@ -582,11 +762,20 @@ impl Encodable for Action {
             Action::Pay {
                 receiver,
                 quantity,
+                memo,
             } => {
-                s.begin_list(3);
-                s.append(&ActionTag::Pay);
-                s.append(receiver);
-                s.append(quantity);
+                if let Some(memo) = memo {
+                    s.begin_list(4);
+                    s.append(&ActionTag::Pay);
+                    s.append(receiver);
+                    s.append(quantity);
+                    s.append(memo);
+                } else {
+                    s.begin_list(3);
+                    s.append(&ActionTag::Pay);
+                    s.append(receiver);
+                    s.append(quantity);
+                }
             }
             Action::SetRegularKey {
                 key,
@@ -595,6 +784,10 @@ impl Encodable for Action {
                 s.append(&ActionTag::SetRegularKey);
                 s.append(key);
             }
+            Action::RevokeRegularKey => {
+                s.begin_list(1);
+                s.append(&ActionTag::RevokeRegularKey);
+            }
             Action::CreateShard {
                 users,
             } => {
@@ -611,6 +804,31 @@ impl Encodable for Action {
                 s.append(shard_id);
                 s.append_list(owners);
             }
+            Action::OfferShardOwnership {
+                shard_id,
+                new_owners,
+                expiry,
+            } => {
+                s.begin_list(4);
+                s.append(&ActionTag::OfferShardOwnership);
+                s.append(shard_id);
+                s.append_list(new_owners);
+                s.append(expiry);
+            }
+            Action::AcceptShardOwnership {
+                shard_id,
+            } => {
+                s.begin_list(2);
+                s.append(&ActionTag::AcceptShardOwnership);
+                s.append(shard_id);
+            }
+            Action::CancelShardOwnershipOffer {
+                shard_id,
+            } => {
+                s.begin_list(2);
+                s.append(&ActionTag::CancelShardOwnershipOffer);
+                s.append(shard_id);
+            }
             Action::SetShardUsers {
                 shard_id,
                 users,
@@ -620,6 +838,15 @@ impl Encodable for Action {
                 s.append(shard_id);
                 s.append_list(users);
             }
+            Action::CreateApproverGroup {
+                members,
+                threshold,
+            } => {
+                s.begin_list(3);
+                s.append(&ActionTag::CreateApproverGroup);
+                s.append_list(members);
+                s.append(threshold);
+            }
             Action::WrapCCC {
                 shard_id,
                 lock_script_hash,
@@ -769,15 +996,26 @@ impl Decodable for Action {
             }
             ActionTag::Pay => {
                 let item_count = rlp.item_count()?;
-                if item_count != 3 {
+                if item_count != 3 && item_count != 4 {
                     return Err(DecoderError::RlpIncorrectListLen {
                         got: item_count,
-                        expected: 3,
+                        expected: if item_count < 3 {
+                            3
+                        } else {
+                            4
+                        },
                     })
                 }
+                // The memo is omitted in the transactions created before it was introduced.
+                let memo = if item_count == 4 {
+                    Some(rlp.val_at(3)?)
+                } else {
+                    None
+                };
                 Ok(Action::Pay {
                     receiver: rlp.val_at(1)?,
                     quantity: rlp.val_at(2)?,
+                    memo,
                 })
             }
             ActionTag::SetRegularKey => {
@@ -792,6 +1030,16 @@ impl Decodable for Action {
                     key: rlp.val_at(1)?,
                 })
             }
+            ActionTag::RevokeRegularKey => {
+                let item_count = rlp.item_count()?;
+                if item_count != 1 {
+                    return Err(DecoderError::RlpIncorrectListLen {
+                        got: item_count,
+                        expected: 1,
+                    })
+                }
+                Ok(Action::RevokeRegularKey)
+            }
             ActionTag::CreateShard => {
                 let item_count = rlp.item_count()?;
                 if item_count != 2 {
@@ -817,6 +1065,44 @@ impl Decodable for Action {
                     owners: rlp.list_at(2)?,
                 })
             }
+            ActionTag::OfferShardOwnership => {
+                let item_count = rlp.item_count()?;
+                if item_count != 4 {
+                    return Err(DecoderError::RlpIncorrectListLen {
+                        got: item_count,
+                        expected: 4,
+                    })
+                }
+                Ok(Action::OfferShardOwnership {
+                    shard_id: rlp.val_at(1)?,
+                    new_owners: rlp.list_at(2)?,
+                    expiry: rlp.val_at(3)?,
+                })
+            }
+            ActionTag::AcceptShardOwnership => {
+                let item_count = rlp.item_count()?;
+                if item_count != 2 {
+                    return Err(DecoderError::RlpIncorrectListLen {
+                        got: item_count,
+                        expected: 2,
+                    })
+                }
+                Ok(Action::AcceptShardOwnership {
+                    shard_id: rlp.val_at(1)?,
+                })
+            }
+            ActionTag::CancelShardOwnershipOffer => {
+                let item_count = rlp.item_count()?;
+                if item_count != 2 {
+                    return Err(DecoderError::RlpIncorrectListLen {
+                        got: item_count,
+                        expected: 2,
+                    })
+                }
+                Ok(Action::CancelShardOwnershipOffer {
+                    shard_id: rlp.val_at(1)?,
+                })
+            }
             ActionTag::SetShardUsers => {
                 let item_count = rlp.item_count()?;
                 if item_count != 3 {
@@ -830,6 +1116,19 @@ impl Decodable for Action {
                     users: rlp.list_at(2)?,
                 })
             }
+            ActionTag::CreateApproverGroup => {
+                let item_count = rlp.item_count()?;
+                if item_count != 3 {
+                    return Err(DecoderError::RlpIncorrectListLen {
+                        got: item_count,
+                        expected: 3,
+                    })
+                }
+                Ok(Action::CreateApproverGroup {
+                    members: rlp.list_at(1)?,
+                    threshold: rlp.val_at(2)?,
+                })
+            }
             ActionTag::WrapCcc => {
                 let item_count = rlp.item_count()?;
                 if item_count != 6 {
@@ -933,6 +1232,15 @@ fn check_duplication_in_prev_out(
     Ok(())
 }
 
+fn has_valid_timelock(input: &AssetTransferInput) -> bool {
+    input.timelock.as_ref().map_or(true, Timelock::is_valid)
+}
+
+/// The combinations of the timelocks are not allowed until their era.
+fn is_timelock_allowed(input: &AssetTransferInput, common_params: &CommonParams) -> bool {
+    common_params.era() >= TIMELOCK_COMBINATION_ERA || !input.timelock.as_ref().map_or(false, Timelock::is_combination)
+}
+
 #[cfg(test)]
 mod tests {
     use rlp::rlp_encode_and_decode_test;
@@ -1035,9 +1343,104 @@ mod tests {
         rlp_encode_and_decode_test!(Action::Pay {
             receiver: Address::random(),
             quantity: 300,
+            memo: None,
         });
     }
 
+    #[test]
+    fn encode_and_decode_pay_action_with_memo() {
+        rlp_encode_and_decode_test!(Action::Pay {
+            receiver: Address::random(),
+            quantity: 300,
+            memo: Some(b"deposit-1234".to_vec()),
+        });
+    }
+
+    #[test]
+    fn decode_pay_action_without_memo_field() {
+        let receiver = Address::random();
+        let mut s = RlpStream::new_list(3);
+        s.append(&ActionTag::Pay);
+        s.append(&receiver);
+        s.append(&300u64);
+        let encoded = s.out();
+
+        let action: Action = rlp::decode(&encoded).unwrap();
+        assert_eq!(
+            Action::Pay {
+                receiver,
+                quantity: 300,
+                memo: None,
+            },
+            action
+        );
+        assert_eq!(encoded, rlp::encode(&action));
+    }
+
+    #[test]
+    fn decode_pay_action_with_wrong_item_count() {
+        let pay = |item_count| {
+            let mut s = RlpStream::new_list(item_count);
+            s.append(&ActionTag::Pay);
+            for _ in 1..item_count {
+                s.append(&300u64);
+            }
+            rlp::decode::<Action>(&s.out())
+        };
+        assert_eq!(
+            Err(DecoderError::RlpIncorrectListLen {
+                got: 2,
+                expected: 3,
+            }),
+            pay(2)
+        );
+        assert_eq!(
+            Err(DecoderError::RlpIncorrectListLen {
+                got: 5,
+                expected: 4,
+            }),
+            pay(5)
+        );
+    }
+
+    #[test]
+    fn verify_pay_memo_size() {
+        let mut common_params = CommonParams::default_for_test();
+        assert_eq!(
+            Err(SyntaxError::PayMemoTooBig),
+            Action::Pay {
+                receiver: Address::random(),
+                quantity: 300,
+                memo: Some(vec![]),
+            }
+            .verify_with_params(&common_params)
+        );
+
+        common_params.set_pay_memo_params_for_test(4, 0);
+
+        let pay_with_memo = |memo: &[u8]| Action::Pay {
+            receiver: Address::random(),
+            quantity: 300,
+            memo: Some(memo.to_vec()),
+        };
+        assert_eq!(Ok(()), pay_with_memo(b"1234").verify_with_params(&common_params));
+        assert_eq!(Err(SyntaxError::PayMemoTooBig), pay_with_memo(b"12345").verify_with_params(&common_params));
+    }
+
+    #[test]
+    fn revoke_regular_key_is_disabled_until_its_cost_is_given() {
+        let mut common_params = CommonParams::default_for_test();
+        assert_eq!(Err(SyntaxError::DisabledTransaction), Action::RevokeRegularKey.verify_with_params(&common_params));
+
+        common_params.set_revoke_regular_key_params_for_test(10);
+        assert_eq!(Ok(()), Action::RevokeRegularKey.verify_with_params(&common_params));
+    }
+
+    #[test]
+    fn encode_and_decode_revoke_regular_key() {
+        rlp_encode_and_decode_test!(Action::RevokeRegularKey);
+    }
+
     #[test]
     fn encode_and_decode_set_shard_owners() {
         rlp_encode_and_decode_test!(Action::SetShardOwners {
@@ -1046,6 +1449,56 @@ mod tests {
         });
     }
 
+    #[test]
+    fn encode_and_decode_offer_shard_ownership() {
+        rlp_encode_and_decode_test!(Action::OfferShardOwnership {
+            shard_id: 1,
+            new_owners: vec![Address::random(), Address::random()],
+            expiry: 100,
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_accept_shard_ownership() {
+        rlp_encode_and_decode_test!(Action::AcceptShardOwnership {
+            shard_id: 1,
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_cancel_shard_ownership_offer() {
+        rlp_encode_and_decode_test!(Action::CancelShardOwnershipOffer {
+            shard_id: 1,
+        });
+    }
+
+    #[test]
+    fn shard_ownership_offer_expiry_is_limited_by_the_params() {
+        let offer = |expiry| Action::OfferShardOwnership {
+            shard_id: 0,
+            new_owners: vec![Address::random()],
+            expiry,
+        };
+        let mut params = CommonParams::default_for_test();
+        assert_eq!(Err(SyntaxError::InvalidShardOwnershipOfferExpiry(1)), offer(1).verify_with_params(&params));
+
+        params.set_shard_ownership_offer_params_for_test(0, 10);
+        assert_eq!(Ok(()), offer(1).verify_with_params(&params));
+        assert_eq!(Ok(()), offer(10).verify_with_params(&params));
+        assert_eq!(Err(SyntaxError::InvalidShardOwnershipOfferExpiry(0)), offer(0).verify_with_params(&params));
+        assert_eq!(Err(SyntaxError::InvalidShardOwnershipOfferExpiry(11)), offer(11).verify_with_params(&params));
+    }
+
+    #[test]
+    fn shard_ownership_offer_must_have_new_owners() {
+        let offer = Action::OfferShardOwnership {
+            shard_id: 3,
+            new_owners: vec![],
+            expiry: 1,
+        };
+        assert_eq!(Err(SyntaxError::EmptyShardOwners(3)), offer.verify());
+    }
+
     #[test]
     fn encode_and_decode_set_shard_users() {
         rlp_encode_and_decode_test!(Action::SetShardUsers {
@@ -1054,6 +1507,52 @@ mod tests {
         });
     }
 
+    #[test]
+    fn encode_and_decode_create_approver_group() {
+        rlp_encode_and_decode_test!(Action::CreateApproverGroup {
+            members: vec![Address::random(), Address::random(), Address::random()],
+            threshold: 2,
+        });
+    }
+
+    #[test]
+    fn approver_group_threshold_must_be_reachable() {
+        let group = |threshold| Action::CreateApproverGroup {
+            members: vec![Address::random(), Address::random()],
+            threshold,
+        };
+        assert_eq!(Ok(()), group(1).verify());
+        assert_eq!(Ok(()), group(2).verify());
+        assert_eq!(Err(SyntaxError::InvalidApproverGroupThreshold(0)), group(0).verify());
+        assert_eq!(Err(SyntaxError::InvalidApproverGroupThreshold(3)), group(3).verify());
+    }
+
+    #[test]
+    fn approver_group_members_must_be_unique() {
+        let member = Address::random();
+        let group = Action::CreateApproverGroup {
+            members: vec![member, Address::random(), member],
+            threshold: 2,
+        };
+        assert_eq!(Err(SyntaxError::DuplicatedApproverGroupMember(member)), group.verify());
+    }
+
+    #[test]
+    fn approver_group_size_is_limited_by_the_params() {
+        let group = Action::CreateApproverGroup {
+            members: vec![Address::random(), Address::random(), Address::random()],
+            threshold: 2,
+        };
+        let mut params = CommonParams::default_for_test();
+        assert_eq!(Err(SyntaxError::ApproverGroupTooBig(3)), group.verify_with_params(&params));
+
+        params.set_approver_group_params_for_test(0, 3);
+        assert_eq!(Ok(()), group.verify_with_params(&params));
+
+        params.set_approver_group_params_for_test(0, 2);
+        assert_eq!(Err(SyntaxError::ApproverGroupTooBig(3)), group.verify_with_params(&params));
+    }
+
     #[test]
     fn encode_and_decode_store() {
         rlp_encode_and_decode_test!(Action::Store {
@@ -1126,6 +1625,35 @@ mod tests {
         assert_eq!(tx_invalid_asset_type.verify(), Err(SyntaxError::InvalidAssetType(invalid_asset_type)));
     }
 
+    #[test]
+    fn timelock_combination_is_rejected_before_its_era() {
+        let unwrap_ccc = |timelock| Action::UnwrapCCC {
+            network_id: NetworkId::default(),
+            burn: AssetTransferInput {
+                prev_out: AssetOutPoint {
+                    tracker: Default::default(),
+                    index: 0,
+                    asset_type: H160::zero(),
+                    shard_id: 0,
+                    quantity: 1,
+                },
+                timelock: Some(timelock),
+                lock_script: vec![0x30, 0x01],
+                unlock_script: vec![],
+            },
+            receiver: Address::random(),
+        };
+        let combined = unwrap_ccc(Timelock::Any(vec![Timelock::Block(10), Timelock::Time(20)]));
+        let single = unwrap_ccc(Timelock::Block(10));
+
+        let mut common_params = CommonParams::default_for_test();
+        assert_eq!(Err(SyntaxError::InvalidTimelock), combined.verify_with_params(&common_params));
+        assert_eq!(Ok(()), single.verify_with_params(&common_params));
+
+        common_params.set_era_for_test(TIMELOCK_COMBINATION_ERA);
+        assert_eq!(Ok(()), combined.verify_with_params(&common_params));
+    }
+
     #[test]
     fn verify_wrap_ccc_transaction_should_fail() {
         let tx_zero_quantity = Action::WrapCCC {
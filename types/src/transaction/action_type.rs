@@ -0,0 +1,122 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The kind of an `Action`. Each kind has its own minimum fee in `CommonParams`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ActionType {
+    MintAsset,
+    TransferAsset,
+    ChangeAssetScheme,
+    IncreaseAssetSupply,
+    UnwrapCCC,
+    Pay,
+    SetRegularKey,
+    RevokeRegularKey,
+    CreateShard,
+    SetShardOwners,
+    OfferShardOwnership,
+    AcceptShardOwnership,
+    CancelShardOwnershipOffer,
+    SetShardUsers,
+    CreateApproverGroup,
+    WrapCCC,
+    Store,
+    Remove,
+    Custom,
+}
+
+impl ActionType {
+    pub const ALL: [ActionType; 19] = [
+        ActionType::MintAsset,
+        ActionType::TransferAsset,
+        ActionType::ChangeAssetScheme,
+        ActionType::IncreaseAssetSupply,
+        ActionType::UnwrapCCC,
+        ActionType::Pay,
+        ActionType::SetRegularKey,
+        ActionType::RevokeRegularKey,
+        ActionType::CreateShard,
+        ActionType::SetShardOwners,
+        ActionType::OfferShardOwnership,
+        ActionType::AcceptShardOwnership,
+        ActionType::CancelShardOwnershipOffer,
+        ActionType::SetShardUsers,
+        ActionType::CreateApproverGroup,
+        ActionType::WrapCCC,
+        ActionType::Store,
+        ActionType::Remove,
+        ActionType::Custom,
+    ];
+
+    /// The name used in the JSON-RPC.
+    pub fn name(self) -> &'static str {
+        match self {
+            ActionType::MintAsset => "mintAsset",
+            ActionType::TransferAsset => "transferAsset",
+            ActionType::ChangeAssetScheme => "changeAssetScheme",
+            ActionType::IncreaseAssetSupply => "increaseAssetSupply",
+            ActionType::UnwrapCCC => "unwrapCCC",
+            ActionType::Pay => "pay",
+            ActionType::SetRegularKey => "setRegularKey",
+            ActionType::RevokeRegularKey => "revokeRegularKey",
+            ActionType::CreateShard => "createShard",
+            ActionType::SetShardOwners => "setShardOwners",
+            ActionType::OfferShardOwnership => "offerShardOwnership",
+            ActionType::AcceptShardOwnership => "acceptShardOwnership",
+            ActionType::CancelShardOwnershipOffer => "cancelShardOwnershipOffer",
+            ActionType::SetShardUsers => "setShardUsers",
+            ActionType::CreateApproverGroup => "createApproverGroup",
+            ActionType::WrapCCC => "wrapCCC",
+            ActionType::Store => "store",
+            ActionType::Remove => "remove",
+            ActionType::Custom => "custom",
+        }
+    }
+}
+
+impl fmt::Display for ActionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ActionType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ActionType::ALL
+            .iter()
+            .find(|action_type| action_type.name() == s)
+            .cloned()
+            .ok_or_else(|| format!("Unknown action type: {}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_parsed_back() {
+        for action_type in ActionType::ALL.iter() {
+            assert_eq!(Ok(*action_type), action_type.name().parse());
+        }
+        assert!("composeAsset".parse::<ActionType>().is_err());
+    }
+}
@@ -0,0 +1,448 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{Action, AssetMintOutput, AssetTransferInput, AssetTransferOutput, Transaction};
+use crate::errors::SyntaxError;
+use crate::{CommonParams, ShardId};
+use ckey::{Address, NetworkId};
+use primitives::Bytes;
+use std::fmt::{Display, Formatter, Result as FormatResult};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    MissingSeq,
+    MissingFee,
+    MissingNetworkId,
+    /// The field is set on a transaction whose action doesn't have it.
+    UnexpectedField(&'static str),
+    Syntax(SyntaxError),
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        match self {
+            BuildError::MissingSeq => write!(f, "The seq is not set"),
+            BuildError::MissingFee => write!(f, "The fee is not set"),
+            BuildError::MissingNetworkId => write!(f, "The network id is not set"),
+            BuildError::UnexpectedField(field) => write!(f, "The action doesn't have the {} field", field),
+            BuildError::Syntax(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<SyntaxError> for BuildError {
+    fn from(err: SyntaxError) -> Self {
+        BuildError::Syntax(err)
+    }
+}
+
+/// Builds a transaction checking the things that can be checked without the state.
+///
+/// ```ignore
+/// let tx = TransactionBuilder::pay(receiver, 100).fee(10).seq(0).network(network_id).build_with_params(&params)?;
+/// ```
+pub struct TransactionBuilder {
+    seq: Option<u64>,
+    fee: Option<u64>,
+    network_id: Option<NetworkId>,
+    action: Action,
+    /// The first error of the setters, which is returned when building.
+    error: Option<BuildError>,
+}
+
+impl TransactionBuilder {
+    fn new(action: Action) -> Self {
+        Self {
+            seq: None,
+            fee: None,
+            network_id: None,
+            action,
+            error: None,
+        }
+    }
+
+    pub fn pay(receiver: Address, quantity: u64) -> Self {
+        Self::new(Action::Pay {
+            receiver,
+            quantity,
+            memo: None,
+        })
+    }
+
+    /// The network id of the action is the one of the transaction.
+    pub fn mint_asset(shard_id: ShardId, output: AssetMintOutput) -> Self {
+        Self::new(Action::MintAsset {
+            network_id: NetworkId::default(),
+            shard_id,
+            metadata: String::new(),
+            approver: None,
+            registrar: None,
+            allowed_script_hashes: Vec::new(),
+            output: Box::new(output),
+            approvals: Vec::new(),
+        })
+    }
+
+    /// The network id of the action is the one of the transaction.
+    pub fn transfer_asset(
+        burns: Vec<AssetTransferInput>,
+        inputs: Vec<AssetTransferInput>,
+        outputs: Vec<AssetTransferOutput>,
+    ) -> Self {
+        Self::new(Action::TransferAsset {
+            network_id: NetworkId::default(),
+            burns,
+            inputs,
+            outputs,
+            metadata: String::new(),
+            approvals: Vec::new(),
+            expiration: None,
+        })
+    }
+
+    pub fn custom(handler_id: u64, bytes: Bytes) -> Self {
+        Self::new(Action::Custom {
+            handler_id,
+            bytes,
+        })
+    }
+
+    pub fn seq(mut self, seq: u64) -> Self {
+        self.seq = Some(seq);
+        self
+    }
+
+    pub fn fee(mut self, fee: u64) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    pub fn network(mut self, network_id: NetworkId) -> Self {
+        self.network_id = Some(network_id);
+        self
+    }
+
+    /// Only for the pay transactions.
+    pub fn memo(mut self, new_memo: Bytes) -> Self {
+        match &mut self.action {
+            Action::Pay {
+                memo,
+                ..
+            } => *memo = Some(new_memo),
+            _ => self.fail(BuildError::UnexpectedField("memo")),
+        }
+        self
+    }
+
+    /// Only for the asset mint and transfer transactions.
+    pub fn metadata(mut self, new_metadata: String) -> Self {
+        match &mut self.action {
+            Action::MintAsset {
+                metadata,
+                ..
+            }
+            | Action::TransferAsset {
+                metadata,
+                ..
+            } => *metadata = new_metadata,
+            _ => self.fail(BuildError::UnexpectedField("metadata")),
+        }
+        self
+    }
+
+    /// Only for the asset mint transactions.
+    pub fn approver(mut self, new_approver: Address) -> Self {
+        match &mut self.action {
+            Action::MintAsset {
+                approver,
+                ..
+            } => *approver = Some(new_approver),
+            _ => self.fail(BuildError::UnexpectedField("approver")),
+        }
+        self
+    }
+
+    /// Only for the asset mint transactions.
+    pub fn registrar(mut self, new_registrar: Address) -> Self {
+        match &mut self.action {
+            Action::MintAsset {
+                registrar,
+                ..
+            } => *registrar = Some(new_registrar),
+            _ => self.fail(BuildError::UnexpectedField("registrar")),
+        }
+        self
+    }
+
+    /// Only for the asset transfer transactions.
+    pub fn expiration(mut self, new_expiration: u64) -> Self {
+        match &mut self.action {
+            Action::TransferAsset {
+                expiration,
+                ..
+            } => *expiration = Some(new_expiration),
+            _ => self.fail(BuildError::UnexpectedField("expiration")),
+        }
+        self
+    }
+
+    /// Builds the transaction with the checks that don't need the common params.
+    pub fn build(mut self) -> Result<Transaction, BuildError> {
+        if let Some(err) = self.error {
+            return Err(err)
+        }
+        let seq = self.seq.ok_or(BuildError::MissingSeq)?;
+        let fee = self.fee.ok_or(BuildError::MissingFee)?;
+        let network_id = self.network_id.ok_or(BuildError::MissingNetworkId)?;
+        match &mut self.action {
+            Action::MintAsset {
+                network_id: action_network_id,
+                ..
+            }
+            | Action::TransferAsset {
+                network_id: action_network_id,
+                ..
+            } => *action_network_id = network_id,
+            _ => {}
+        }
+        self.action.verify()?;
+        Ok(Transaction {
+            seq,
+            fee,
+            network_id,
+            action: self.action,
+        })
+    }
+
+    /// Builds the transaction with the checks that `CodeChainMachine::verify_transaction_with_params` does,
+    /// except the size of the body, which depends on the signature.
+    pub fn build_with_params(self, params: &CommonParams) -> Result<Transaction, BuildError> {
+        let tx = self.build()?;
+        if tx.network_id != params.network_id() {
+            return Err(SyntaxError::InvalidNetworkId(tx.network_id).into())
+        }
+        let min_cost = tx.action.min_cost(params);
+        if tx.fee < min_cost {
+            return Err(SyntaxError::InsufficientFee {
+                minimal: min_cost,
+                got: tx.fee,
+            }
+            .into())
+        }
+        tx.action.verify_with_params(params)?;
+        Ok(tx)
+    }
+
+    fn fail(&mut self, err: BuildError) {
+        if self.error.is_none() {
+            self.error = Some(err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::AssetOutPoint;
+    use crate::Tracker;
+    use primitives::{H160, H256};
+
+    fn params() -> CommonParams {
+        let mut params = CommonParams::default_for_test();
+        params.set_max_transfer_metadata_size(4);
+        params
+    }
+
+    fn input(quantity: u64) -> AssetTransferInput {
+        AssetTransferInput {
+            prev_out: AssetOutPoint {
+                tracker: Tracker::from(H256::from(1)),
+                index: 0,
+                asset_type: H160::from(2),
+                shard_id: 0,
+                quantity,
+            },
+            timelock: None,
+            lock_script: vec![],
+            unlock_script: vec![],
+        }
+    }
+
+    fn output(quantity: u64) -> AssetTransferOutput {
+        AssetTransferOutput {
+            lock_script_hash: H160::from(3),
+            parameters: vec![],
+            asset_type: H160::from(2),
+            shard_id: 0,
+            quantity,
+        }
+    }
+
+    #[test]
+    fn built_pay_is_encoded_as_the_hand_constructed_one() {
+        let receiver = Address::from(7);
+        let network_id = params().network_id();
+        let built =
+            TransactionBuilder::pay(receiver, 100).fee(10).seq(3).network(network_id).memo(vec![1, 2]).build().unwrap();
+        let constructed = Transaction {
+            seq: 3,
+            fee: 10,
+            network_id,
+            action: Action::Pay {
+                receiver,
+                quantity: 100,
+                memo: Some(vec![1, 2]),
+            },
+        };
+        assert_eq!(constructed, built);
+        assert_eq!(rlp::encode(&constructed.action), rlp::encode(&built.action));
+        assert_eq!(constructed.hash(), built.hash());
+    }
+
+    #[test]
+    fn built_transfer_has_the_network_id_of_the_transaction() {
+        let network_id = params().network_id();
+        let built = TransactionBuilder::transfer_asset(vec![], vec![input(5)], vec![output(5)])
+            .fee(10)
+            .seq(0)
+            .network(network_id)
+            .metadata("meta".to_string())
+            .build_with_params(&params())
+            .unwrap();
+        let constructed = Transaction {
+            seq: 0,
+            fee: 10,
+            network_id,
+            action: Action::TransferAsset {
+                network_id,
+                burns: vec![],
+                inputs: vec![input(5)],
+                outputs: vec![output(5)],
+                metadata: "meta".to_string(),
+                approvals: vec![],
+                expiration: None,
+            },
+        };
+        assert_eq!(constructed, built);
+        assert_eq!(constructed.hash(), built.hash());
+    }
+
+    #[test]
+    fn missing_fields() {
+        let network_id = params().network_id();
+        let builder = || TransactionBuilder::custom(1, vec![]);
+        assert_eq!(Err(BuildError::MissingSeq), builder().fee(0).network(network_id).build());
+        assert_eq!(Err(BuildError::MissingFee), builder().seq(0).network(network_id).build());
+        assert_eq!(Err(BuildError::MissingNetworkId), builder().seq(0).fee(0).build());
+    }
+
+    #[test]
+    fn field_of_another_action() {
+        let network_id = params().network_id();
+        assert_eq!(
+            Err(BuildError::UnexpectedField("memo")),
+            TransactionBuilder::custom(1, vec![]).memo(vec![]).seq(0).fee(0).network(network_id).build()
+        );
+        assert_eq!(
+            Err(BuildError::UnexpectedField("expiration")),
+            TransactionBuilder::pay(Address::default(), 1).expiration(0).seq(0).fee(0).network(network_id).build()
+        );
+    }
+
+    #[test]
+    fn network_id_mismatch() {
+        let network_id: NetworkId = "zz".into();
+        assert_ne!(network_id, params().network_id());
+        assert_eq!(
+            Err(BuildError::Syntax(SyntaxError::InvalidNetworkId(network_id))),
+            TransactionBuilder::pay(Address::default(), 1)
+                .seq(0)
+                .fee(10)
+                .network(network_id)
+                .build_with_params(&params())
+        );
+    }
+
+    #[test]
+    fn insufficient_fee() {
+        let mut params = params();
+        params.set_pay_memo_params_for_test(10, 2);
+        let min_cost = params.min_pay_transaction_cost() + 3 * 2;
+        assert_eq!(
+            Err(BuildError::Syntax(SyntaxError::InsufficientFee {
+                minimal: min_cost,
+                got: min_cost - 1,
+            })),
+            TransactionBuilder::pay(Address::default(), 1)
+                .memo(vec![0; 3])
+                .seq(0)
+                .fee(min_cost - 1)
+                .network(params.network_id())
+                .build_with_params(&params)
+        );
+    }
+
+    #[test]
+    fn metadata_too_big() {
+        assert_eq!(
+            Err(BuildError::Syntax(SyntaxError::MetadataTooBig)),
+            TransactionBuilder::transfer_asset(vec![], vec![input(5)], vec![output(5)])
+                .metadata("12345".to_string())
+                .seq(0)
+                .fee(10)
+                .network(params().network_id())
+                .build_with_params(&params())
+        );
+    }
+
+    #[test]
+    fn pay_memo_too_big() {
+        // The memo is disabled by the default params.
+        assert_eq!(
+            Err(BuildError::Syntax(SyntaxError::PayMemoTooBig)),
+            TransactionBuilder::pay(Address::default(), 1)
+                .memo(vec![0])
+                .seq(0)
+                .fee(10)
+                .network(params().network_id())
+                .build_with_params(&params())
+        );
+    }
+
+    #[test]
+    fn zero_supply() {
+        assert_eq!(
+            Err(BuildError::Syntax(SyntaxError::ZeroQuantity)),
+            TransactionBuilder::mint_asset(0, AssetMintOutput::default())
+                .seq(0)
+                .fee(10)
+                .network(params().network_id())
+                .build()
+        );
+    }
+
+    #[test]
+    fn inconsistent_transfer() {
+        assert_eq!(
+            Err(BuildError::Syntax(SyntaxError::InconsistentTransactionInOut)),
+            TransactionBuilder::transfer_asset(vec![], vec![input(5)], vec![output(4)])
+                .seq(0)
+                .fee(10)
+                .network(params().network_id())
+                .build()
+        );
+    }
+}
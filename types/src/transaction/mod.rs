@@ -15,7 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod action;
+mod action_type;
 mod asset_out_point;
+mod builder;
 mod incomplete_transaction;
 mod input;
 mod output;
@@ -26,7 +28,9 @@ mod timelock;
 mod transaction;
 
 pub use self::action::Action;
+pub use self::action_type::ActionType;
 pub use self::asset_out_point::AssetOutPoint;
+pub use self::builder::{BuildError, TransactionBuilder};
 pub use self::incomplete_transaction::IncompleteTransaction;
 pub use self::input::AssetTransferInput;
 pub use self::output::{AssetMintOutput, AssetTransferOutput};
@@ -249,7 +249,7 @@ fn apply_input_scheme(
             .iter()
             .map(|input| AssetTransferInput {
                 prev_out: input.prev_out.clone(),
-                timelock: input.timelock,
+                timelock: input.timelock.clone(),
                 lock_script: Vec::new(),
                 unlock_script: Vec::new(),
             })
@@ -259,7 +259,7 @@ fn apply_input_scheme(
     if is_sign_single {
         return vec![AssetTransferInput {
             prev_out: cur.prev_out.clone(),
-            timelock: cur.timelock,
+            timelock: cur.timelock.clone(),
             lock_script: Vec::new(),
             unlock_script: Vec::new(),
         }]
@@ -311,7 +311,7 @@ impl PartialHashing for ShardTransaction {
                         network_id: *network_id,
                         burn: AssetTransferInput {
                             prev_out: burn.prev_out.clone(),
-                            timelock: burn.timelock,
+                            timelock: burn.timelock.clone(),
                             lock_script: Vec::new(),
                             unlock_script: Vec::new(),
                         },
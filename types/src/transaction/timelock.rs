@@ -17,13 +17,20 @@
 
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Serialize)]
+/// The maximum depth of a timelock. A single condition has the depth 1.
+pub const MAX_TIMELOCK_DEPTH: usize = 3;
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase", tag = "type", content = "value")]
 pub enum Timelock {
     Block(u64),
     BlockAge(u64),
     Time(u64),
     TimeAge(u64),
+    /// Satisfied when all of the timelocks are satisfied.
+    All(Vec<Timelock>),
+    /// Satisfied when any of the timelocks is satisfied.
+    Any(Vec<Timelock>),
 }
 
 type TimelockType = u8;
@@ -31,6 +38,69 @@ const BLOCK: TimelockType = 0x01;
 const BLOCK_AGE: TimelockType = 0x02;
 const TIME: TimelockType = 0x03;
 const TIME_AGE: TimelockType = 0x04;
+const ALL: TimelockType = 0x05;
+const ANY: TimelockType = 0x06;
+
+impl Timelock {
+    pub fn depth(&self) -> usize {
+        match self {
+            Timelock::All(timelocks) | Timelock::Any(timelocks) => {
+                1 + timelocks.iter().map(Timelock::depth).max().unwrap_or_default()
+            }
+            _ => 1,
+        }
+    }
+
+    pub fn is_combination(&self) -> bool {
+        match self {
+            Timelock::All(_) | Timelock::Any(_) => true,
+            _ => false,
+        }
+    }
+
+    /// A timelock is valid if it isn't deeper than `MAX_TIMELOCK_DEPTH`
+    /// and none of its combinations is empty.
+    pub fn is_valid(&self) -> bool {
+        self.depth() <= MAX_TIMELOCK_DEPTH && self.has_no_empty_combination()
+    }
+
+    fn has_no_empty_combination(&self) -> bool {
+        match self {
+            Timelock::All(timelocks) | Timelock::Any(timelocks) => {
+                !timelocks.is_empty() && timelocks.iter().all(Timelock::has_no_empty_combination)
+            }
+            _ => true,
+        }
+    }
+
+    /// Evaluates the timelock.
+    /// `remaining` returns how long a single condition has to wait, or 0 if it is satisfied.
+    /// On failure, returns an unsatisfied condition with its remaining time.
+    /// If no alternative of `Any` is satisfied, the one of the first alternative is returned.
+    pub fn evaluate<F>(&self, remaining: &mut F) -> Result<(), (Timelock, u64)>
+    where
+        F: FnMut(&Timelock) -> u64, {
+        match self {
+            Timelock::All(timelocks) => timelocks.iter().try_for_each(|timelock| timelock.evaluate(remaining)),
+            Timelock::Any(timelocks) => {
+                let mut first_failure = None;
+                for timelock in timelocks {
+                    match timelock.evaluate(remaining) {
+                        Ok(()) => return Ok(()),
+                        Err(failure) => {
+                            first_failure.get_or_insert(failure);
+                        }
+                    }
+                }
+                Err(first_failure.unwrap_or_else(|| (self.clone(), u64::max_value())))
+            }
+            condition => match remaining(condition) {
+                0 => Ok(()),
+                remaining_time => Err((condition.clone(), remaining_time)),
+            },
+        }
+    }
+}
 
 impl Encodable for Timelock {
     fn rlp_append(&self, s: &mut RlpStream) {
@@ -39,12 +109,19 @@ impl Encodable for Timelock {
             Timelock::BlockAge(val) => s.begin_list(2).append(&BLOCK_AGE).append(val),
             Timelock::Time(val) => s.begin_list(2).append(&TIME).append(val),
             Timelock::TimeAge(val) => s.begin_list(2).append(&TIME_AGE).append(val),
+            Timelock::All(timelocks) => s.begin_list(2).append(&ALL).append_list(timelocks),
+            Timelock::Any(timelocks) => s.begin_list(2).append(&ANY).append_list(timelocks),
         };
     }
 }
 
-impl Decodable for Timelock {
-    fn decode(d: &Rlp) -> Result<Self, DecoderError> {
+impl Timelock {
+    /// Decodes a timelock at `depth`, failing as soon as it goes deeper than `MAX_TIMELOCK_DEPTH`,
+    /// so that a deeply nested input can't exhaust the stack.
+    fn decode_at(d: &Rlp, depth: usize) -> Result<Self, DecoderError> {
+        if depth > MAX_TIMELOCK_DEPTH {
+            return Err(DecoderError::Custom("Too deep timelock"))
+        }
         let item_count = d.item_count()?;
         if item_count != 2 {
             return Err(DecoderError::RlpIncorrectListLen {
@@ -52,12 +129,110 @@ impl Decodable for Timelock {
                 expected: 2,
             })
         }
-        match d.val_at(0)? {
-            BLOCK => Ok(Timelock::Block(d.val_at(1)?)),
-            BLOCK_AGE => Ok(Timelock::BlockAge(d.val_at(1)?)),
-            TIME => Ok(Timelock::Time(d.val_at(1)?)),
-            TIME_AGE => Ok(Timelock::TimeAge(d.val_at(1)?)),
-            _ => Err(DecoderError::Custom("Unexpected timelock type")),
+        let decode_list = |list: Rlp| -> Result<Vec<Timelock>, DecoderError> {
+            list.iter().map(|item| Self::decode_at(&item, depth + 1)).collect()
+        };
+        Ok(match d.val_at(0)? {
+            BLOCK => Timelock::Block(d.val_at(1)?),
+            BLOCK_AGE => Timelock::BlockAge(d.val_at(1)?),
+            TIME => Timelock::Time(d.val_at(1)?),
+            TIME_AGE => Timelock::TimeAge(d.val_at(1)?),
+            ALL => Timelock::All(decode_list(d.at(1)?)?),
+            ANY => Timelock::Any(decode_list(d.at(1)?)?),
+            _ => return Err(DecoderError::Custom("Unexpected timelock type")),
+        })
+    }
+}
+
+impl Decodable for Timelock {
+    fn decode(d: &Rlp) -> Result<Self, DecoderError> {
+        let timelock = Self::decode_at(d, 1)?;
+        if !timelock.is_valid() {
+            return Err(DecoderError::Custom("Invalid timelock combination"))
         }
+        Ok(timelock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::rlp_encode_and_decode_test;
+
+    #[test]
+    fn decode_single_condition_encoding() {
+        let mut s = RlpStream::new_list(2);
+        s.append(&BLOCK_AGE).append(&10u64);
+        assert_eq!(Ok(Timelock::BlockAge(10)), rlp::decode(&s.out()));
+    }
+
+    #[test]
+    fn encode_and_decode_combination() {
+        rlp_encode_and_decode_test!(Timelock::Any(vec![
+            Timelock::All(vec![Timelock::Block(10), Timelock::Time(20)]),
+            Timelock::TimeAge(30),
+        ]));
+    }
+
+    #[test]
+    fn reject_too_deep_timelock() {
+        let too_deep = Timelock::All(vec![Timelock::Any(vec![Timelock::All(vec![Timelock::Block(1)])])]);
+        assert_eq!(4, too_deep.depth());
+        assert!(!too_deep.is_valid());
+        assert!(rlp::decode::<Timelock>(&rlp::encode(&too_deep)).is_err());
+    }
+
+    #[test]
+    fn stop_decoding_at_the_depth_limit() {
+        let mut nested = rlp::encode(&Timelock::Block(1));
+        for _ in 0..1000 {
+            let mut s = RlpStream::new_list(2);
+            s.append(&ALL).begin_list(1).append_raw(&nested, 1);
+            nested = s.out();
+        }
+        assert_eq!(Err(DecoderError::Custom("Too deep timelock")), rlp::decode::<Timelock>(&nested));
+    }
+
+    #[test]
+    fn reject_empty_combination() {
+        let empty = Timelock::All(vec![Timelock::Any(vec![])]);
+        assert!(!empty.is_valid());
+        assert!(rlp::decode::<Timelock>(&rlp::encode(&empty)).is_err());
+    }
+
+    // Block(0) and Time(0) are satisfied while Block(1) and Time(1) are not.
+    fn evaluate(timelock: &Timelock) -> Result<(), (Timelock, u64)> {
+        timelock.evaluate(&mut |condition: &Timelock| match condition {
+            Timelock::Block(value) | Timelock::Time(value) => *value,
+            _ => unreachable!(),
+        })
+    }
+
+    #[test]
+    fn evaluate_all() {
+        let all = |a, b| Timelock::All(vec![Timelock::Block(a), Timelock::Time(b)]);
+        assert_eq!(Ok(()), evaluate(&all(0, 0)));
+        assert_eq!(Err((Timelock::Time(1), 1)), evaluate(&all(0, 1)));
+        assert_eq!(Err((Timelock::Block(1), 1)), evaluate(&all(1, 0)));
+        assert_eq!(Err((Timelock::Block(1), 1)), evaluate(&all(1, 1)));
+    }
+
+    #[test]
+    fn evaluate_any() {
+        let any = |a, b| Timelock::Any(vec![Timelock::Block(a), Timelock::Time(b)]);
+        assert_eq!(Ok(()), evaluate(&any(0, 0)));
+        assert_eq!(Ok(()), evaluate(&any(0, 1)));
+        assert_eq!(Ok(()), evaluate(&any(1, 0)));
+        assert_eq!(Err((Timelock::Block(1), 1)), evaluate(&any(1, 1)));
+    }
+
+    #[test]
+    fn evaluate_nested() {
+        let timelock = |a, b, c| {
+            Timelock::Any(vec![Timelock::All(vec![Timelock::Block(a), Timelock::Time(b)]), Timelock::Block(c)])
+        };
+        assert_eq!(Ok(()), evaluate(&timelock(0, 0, 1)));
+        assert_eq!(Ok(()), evaluate(&timelock(1, 0, 0)));
+        assert_eq!(Err((Timelock::Time(1), 1)), evaluate(&timelock(0, 1, 1)));
     }
 }
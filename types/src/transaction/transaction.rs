@@ -76,7 +76,8 @@ impl Transaction {
         match self.action {
             Action::SetRegularKey {
                 ..
-            } => true,
+            }
+            | Action::RevokeRegularKey => true,
             _ => false,
         }
     }
@@ -459,7 +459,12 @@ where
         })
     }
 
+    /// Stops the event loop and joins its thread. Calling it again does nothing.
     pub fn stop(&self) {
+        let thread = match self.thread.lock().take() {
+            Some(thread) => thread,
+            None => return,
+        };
         ctrace!(SHUTDOWN, "[IoService] Closing...");
         // Clear handler so that shared pointers are not stuck on stack
         // in Channel::send_sync
@@ -468,11 +473,9 @@ where
             .lock()
             .send(IoMessage::Shutdown)
             .unwrap_or_else(|e| cerror!(IO, "Error on IO service shutdown: {:?}", e));
-        if let Some(thread) = self.thread.lock().take() {
-            thread.join().unwrap_or_else(|e| {
-                cdebug!(SHUTDOWN, "Error joining IO service event loop thread: {:?}", e);
-            });
-        }
+        thread.join().unwrap_or_else(|e| {
+            cdebug!(SHUTDOWN, "Error joining IO service event loop thread: {:?}", e);
+        });
         ctrace!(SHUTDOWN, "[IoService] Closed.");
     }
 
@@ -549,4 +552,15 @@ mod tests {
 
         event_loop.register(&stream, Token(2), Ready::empty(), PollOpt::edge()).unwrap();
     }
+
+    #[test]
+    fn stop_twice() {
+        let service = IoService::<()>::start("Test").unwrap();
+        service.stop();
+        service.stop();
+        assert!(service.thread.lock().is_none());
+        // Messages sent after stop are dropped without panicking.
+        let _ = service.send_message(());
+        let _ = service.channel().send(());
+    }
 }
@@ -29,6 +29,7 @@ extern crate time;
 mod email;
 mod logger;
 mod macros;
+pub mod metric;
 mod structured_logger;
 
 use log::SetLoggerError;
@@ -40,6 +40,9 @@ macro_rules! log_target {
     (MEM_POOL) => {
         "mem_pool"
     };
+    (METRICS) => {
+        "metrics"
+    };
     (MINER) => {
         "miner"
     };
@@ -55,12 +58,18 @@ macro_rules! log_target {
     (OWN_TX) => {
         "own_tx"
     };
+    (REACTOR) => {
+        "reactor"
+    };
     (ROUTING_TABLE) => {
         "routing_table"
     };
     (RPC) => {
         "rpc"
     };
+    (RPC_TIMING) => {
+        "rpc_timing"
+    };
     (SHUTDOWN) => {
         "shutdown"
     };
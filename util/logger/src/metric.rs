@@ -0,0 +1,213 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+lazy_static! {
+    /// The registry rendered by the metrics endpoint.
+    pub static ref METRICS: Registry = Registry::default();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetricKind {
+    /// A value that only increases.
+    Counter,
+    /// A value that can go up and down.
+    Gauge,
+}
+
+impl MetricKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+pub struct Metric {
+    kind: MetricKind,
+    help: &'static str,
+    value: AtomicI64,
+}
+
+impl Metric {
+    pub fn kind(&self) -> MetricKind {
+        self.kind
+    }
+
+    pub fn get(&self) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, value: i64) {
+        debug_assert_eq!(MetricKind::Gauge, self.kind, "Only gauges can be set");
+        self.value.store(value, Ordering::Relaxed);
+    }
+
+    pub fn inc_by(&self, delta: u64) {
+        self.value.fetch_add(delta as i64, Ordering::Relaxed);
+    }
+
+    /// Raises a counter to `total`, a cumulative count kept elsewhere. A smaller total is ignored.
+    pub fn raise_to(&self, total: u64) {
+        debug_assert_eq!(MetricKind::Counter, self.kind, "Only counters can be raised");
+        let total = total as i64;
+        let mut current = self.value.load(Ordering::Relaxed);
+        while current < total {
+            match self.value.compare_exchange_weak(current, total, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+/// Named counters and gauges, rendered in the Prometheus text exposition format.
+#[derive(Default)]
+pub struct Registry {
+    metrics: RwLock<BTreeMap<&'static str, Arc<Metric>>>,
+}
+
+impl Registry {
+    /// Returns the counter registered with `name`, registering it if it doesn't exist.
+    pub fn counter(&self, name: &'static str, help: &'static str) -> Arc<Metric> {
+        self.register(name, help, MetricKind::Counter)
+    }
+
+    /// Returns the gauge registered with `name`, registering it if it doesn't exist.
+    pub fn gauge(&self, name: &'static str, help: &'static str) -> Arc<Metric> {
+        self.register(name, help, MetricKind::Gauge)
+    }
+
+    fn register(&self, name: &'static str, help: &'static str, kind: MetricKind) -> Arc<Metric> {
+        debug_assert!(is_valid_name(name), "{} is not a valid metric name", name);
+        let registered = self.metrics.read().get(name).cloned();
+        let metric = registered.unwrap_or_else(|| {
+            let mut metrics = self.metrics.write();
+            let metric = metrics.entry(name).or_insert_with(|| {
+                Arc::new(Metric {
+                    kind,
+                    help,
+                    value: AtomicI64::new(0),
+                })
+            });
+            Arc::clone(metric)
+        });
+        assert_eq!(kind, metric.kind, "{} is already registered as a {}", name, metric.kind.as_str());
+        metric
+    }
+
+    /// Renders every registered metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        for (name, metric) in self.metrics.read().iter() {
+            writeln!(output, "# HELP {} {}", name, escape_help(metric.help)).expect("Writing to a string never fails");
+            writeln!(output, "# TYPE {} {}", name, metric.kind.as_str()).expect("Writing to a string never fails");
+            writeln!(output, "{} {}", name, metric.get()).expect("Writing to a string never fails");
+        }
+        output
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == ':' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_empty_registry() {
+        let registry = Registry::default();
+        assert_eq!("", registry.render());
+    }
+
+    #[test]
+    fn render_sorted_by_name() {
+        let registry = Registry::default();
+        registry.gauge("codechain_peers", "The number of peers").set(3);
+        registry.counter("codechain_blocks_imported_total", "The number of imported blocks").inc_by(2);
+        assert_eq!(
+            "# HELP codechain_blocks_imported_total The number of imported blocks\n\
+             # TYPE codechain_blocks_imported_total counter\n\
+             codechain_blocks_imported_total 2\n\
+             # HELP codechain_peers The number of peers\n\
+             # TYPE codechain_peers gauge\n\
+             codechain_peers 3\n",
+            registry.render()
+        );
+    }
+
+    #[test]
+    fn registering_twice_returns_the_same_metric() {
+        let registry = Registry::default();
+        registry.gauge("height", "The height").set(10);
+        assert_eq!(10, registry.gauge("height", "The height").get());
+    }
+
+    #[test]
+    fn counters_are_raised_but_never_lowered() {
+        let registry = Registry::default();
+        let counter = registry.counter("hits_total", "The hits");
+        counter.raise_to(5);
+        counter.raise_to(3);
+        assert_eq!(5, counter.get());
+        counter.inc_by(1);
+        counter.raise_to(6);
+        assert_eq!(6, counter.get());
+    }
+
+    #[test]
+    #[should_panic]
+    fn kind_mismatch() {
+        let registry = Registry::default();
+        registry.gauge("height", "The height");
+        registry.counter("height", "The height");
+    }
+
+    #[test]
+    fn help_is_escaped() {
+        let registry = Registry::default();
+        registry.gauge("g", "a\\b\nc");
+        assert!(registry.render().starts_with("# HELP g a\\\\b\\nc\n"));
+    }
+
+    #[test]
+    fn names() {
+        assert!(is_valid_name("codechain_block_height"));
+        assert!(is_valid_name("a:b_1"));
+        assert!(!is_valid_name("1a"));
+        assert!(!is_valid_name("a-b"));
+        assert!(!is_valid_name(""));
+    }
+}
@@ -23,9 +23,21 @@ extern crate my_internet_ip;
 
 use backtrace::Backtrace;
 use clogger::EmailAlarm;
-use std::panic::{self, PanicInfo};
+use std::any::Any;
+use std::cell::Cell;
+use std::panic::{self, PanicInfo, UnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
 
+/// The instance id of the node written in the reports. `NO_INSTANCE_ID` until it's set.
+static INSTANCE_ID: AtomicUsize = AtomicUsize::new(NO_INSTANCE_ID);
+const NO_INSTANCE_ID: usize = usize::max_value();
+
+thread_local! {
+    /// Set while `catch_panic` runs, so that the hook doesn't treat the panic as a crash.
+    static CATCHING: Cell<bool> = Cell::new(false);
+}
+
 /// Set the panic hook
 pub fn set() {
     panic::set_hook(Box::new(panic_hook));
@@ -35,6 +47,26 @@ pub fn set_with_email_alarm(email_alarm: clogger::EmailAlarm) {
     panic::set_hook(Box::new(move |info| panic_hook_with_email_alarm(&email_alarm, info)));
 }
 
+/// Set the instance id of the node, so that the reports tell which instance panicked.
+pub fn set_instance_id(instance_id: usize) {
+    INSTANCE_ID.store(instance_id, Ordering::Relaxed);
+}
+
+/// Run `f` and return the panic message instead of crashing if it panics.
+/// Use it only where a panic can be caused by untrusted input, e.g. decoding a message from a peer.
+pub fn catch_panic<F, R>(f: F) -> Result<R, String>
+where
+    F: FnOnce() -> R + UnwindSafe, {
+    let was_catching = CATCHING.with(|catching| catching.replace(true));
+    let result = panic::catch_unwind(f);
+    CATCHING.with(|catching| catching.set(was_catching));
+    result.map_err(|payload| payload_message(&*payload).to_string())
+}
+
+fn is_catching() -> bool {
+    CATCHING.with(Cell::get)
+}
+
 static ABOUT_PANIC: &str = "
 This is a bug. Please report it at:
 
@@ -42,12 +74,18 @@ This is a bug. Please report it at:
 ";
 
 fn panic_hook(info: &PanicInfo) {
+    if is_catching() {
+        return
+    }
     let message = panic_message(info);
     eprintln!("{}", message);
     exit_on_debug_or_env_set_on_release();
 }
 
 fn panic_hook_with_email_alarm(email_alarm: &EmailAlarm, info: &PanicInfo) {
+    if is_catching() {
+        return
+    }
     let message = panic_message(info);
     eprintln!("{}", message);
     let ip_addresses = get_ip_addresses();
@@ -62,32 +100,41 @@ fn panic_message(info: &PanicInfo) -> String {
     let file = location.as_ref().map(|l| l.file()).unwrap_or("<unknown>");
     let line = location.as_ref().map(|l| l.line()).unwrap_or(0);
 
-    let msg = match info.payload().downcast_ref::<&'static str>() {
-        Some(s) => *s,
-        None => match info.payload().downcast_ref::<String>() {
-            Some(s) => &s[..],
-            None => "Box<Any>",
-        },
-    };
+    let msg = payload_message(info.payload());
 
     let thread = thread::current();
     let name = thread.name().unwrap_or("<unnamed>");
 
     let backtrace = Backtrace::new();
 
+    let instance = match INSTANCE_ID.load(Ordering::Relaxed) {
+        NO_INSTANCE_ID => String::new(),
+        instance_id => format!("Instance #{}: ", instance_id),
+    };
+
     let lines = [
         "".to_string(),
         "====================".to_string(),
         "".to_string(),
         format!("{:?}", backtrace),
         "".to_string(),
-        format!("Thread '{}' panicked at '{}', {}:{}", name, msg, file, line),
+        format!("{}Thread '{}' panicked at '{}', {}:{}", instance, name, msg, file, line),
         ABOUT_PANIC.to_string(),
     ];
 
     lines.join("\n")
 }
 
+fn payload_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&'static str>() {
+        Some(s) => *s,
+        None => match payload.downcast_ref::<String>() {
+            Some(s) => &s[..],
+            None => "Box<Any>",
+        },
+    }
+}
+
 #[cfg(debug_assertions)]
 fn exit_on_debug_or_env_set_on_release() {
     std::process::exit(-1);
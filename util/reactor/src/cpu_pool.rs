@@ -0,0 +1,162 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use panic_hook::catch_panic;
+use parking_lot::Mutex;
+use std::cmp;
+use std::panic::AssertUnwindSafe;
+use std::thread::{self, JoinHandle};
+
+type Task = Box<dyn FnOnce() + Send>;
+
+lazy_static! {
+    static ref SHARED_POOL: CpuPool = CpuPool::new(default_size());
+}
+
+/// Runs `f` on the pool shared by the whole process, and returns the receiver of its result.
+/// The receiver is disconnected without a result if `f` panics.
+pub fn spawn_cpu<F, T>(f: F) -> Receiver<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static, {
+    SHARED_POOL.spawn(f)
+}
+
+/// Leaves a core to the threads that don't run on the pool.
+fn default_size() -> usize {
+    cmp::max(num_cpus::get(), 2) - 1
+}
+
+/// A fixed number of threads running CPU-bound tasks.
+/// A task must not wait for another task of the same pool, since all workers can be waiting.
+pub struct CpuPool {
+    sender: Mutex<Option<Sender<Task>>>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl CpuPool {
+    pub fn new(size: usize) -> Self {
+        assert_ne!(0, size, "A pool needs at least one worker");
+        let (sender, receiver) = unbounded::<Task>();
+        let workers = (0..size)
+            .map(|i| {
+                let receiver = receiver.clone();
+                thread::Builder::new().name(format!("reactor.cpu.{}", i)).spawn(move || worker_loop(&receiver)).unwrap()
+            })
+            .collect();
+        Self {
+            sender: Mutex::new(Some(sender)),
+            workers: Mutex::new(workers),
+        }
+    }
+
+    /// Runs `f` on a worker, and returns the receiver of its result.
+    /// The receiver is disconnected without a result if `f` panics or the pool is shut down.
+    pub fn spawn<F, T>(&self, f: F) -> Receiver<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static, {
+        let (result_sender, result_receiver) = bounded(1);
+        let task: Task = Box::new(move || {
+            // The caller may have dropped the receiver.
+            let _ = result_sender.send(f());
+        });
+        match &*self.sender.lock() {
+            Some(sender) => sender.send(task).expect("Workers run until the sender is dropped"),
+            None => cwarn!(REACTOR, "A task is spawned after the pool is shut down"),
+        }
+        result_receiver
+    }
+
+    /// Waits for the tasks spawned so far and stops the workers.
+    pub fn shutdown(&self) {
+        // The workers stop when the channel is empty and disconnected.
+        self.sender.lock().take();
+        for worker in self.workers.lock().drain(..) {
+            worker.join().expect("Workers don't panic");
+        }
+    }
+}
+
+impl Drop for CpuPool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn worker_loop(receiver: &Receiver<Task>) {
+    while let Ok(task) = receiver.recv() {
+        if let Err(message) = catch_panic(AssertUnwindSafe(task)) {
+            cerror!(REACTOR, "A task panicked: {}", message);
+        }
+    }
+    ctrace!(REACTOR, "Worker has been stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn result_is_received() {
+        let pool = CpuPool::new(2);
+        let receivers: Vec<_> = (0..10u64).map(|i| pool.spawn(move || i * i)).collect();
+        let results: Vec<_> = receivers.into_iter().map(|receiver| receiver.recv().unwrap()).collect();
+        assert_eq!((0..10u64).map(|i| i * i).collect::<Vec<_>>(), results);
+    }
+
+    #[test]
+    fn panic_is_isolated_to_the_task() {
+        let pool = CpuPool::new(1);
+        let panicked = pool.spawn(|| -> u32 { panic!("Intended panic") });
+        assert!(panicked.recv().is_err());
+        assert_eq!(Ok(3), pool.spawn(|| 3).recv());
+    }
+
+    #[test]
+    fn shutdown_runs_pending_tasks() {
+        let pool = CpuPool::new(1);
+        let finished = Arc::new(AtomicUsize::new(0));
+        let receivers: Vec<_> = (0..5)
+            .map(|i| {
+                let finished = Arc::clone(&finished);
+                pool.spawn(move || {
+                    thread::sleep(Duration::from_millis(50));
+                    finished.fetch_add(1, Ordering::SeqCst);
+                    i
+                })
+            })
+            .collect();
+
+        pool.shutdown();
+        assert_eq!(5, finished.load(Ordering::SeqCst));
+        for (i, receiver) in receivers.into_iter().enumerate() {
+            assert_eq!(Ok(i), receiver.recv());
+        }
+
+        assert!(pool.spawn(|| 0).recv().is_err());
+    }
+
+    #[test]
+    fn shared_pool_has_a_worker_at_least() {
+        assert!(default_size() >= 1);
+        assert_eq!(Ok(7), spawn_cpu(|| 7).recv());
+    }
+}
@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 extern crate parking_lot;
+extern crate rand;
 #[macro_use]
 extern crate log;
 #[macro_use]
@@ -23,5 +24,6 @@ extern crate codechain_logger as clogger;
 mod timer;
 
 pub use crate::timer::{
-    ScheduleError as TimerScheduleError, TimeoutHandler, TimerApi, TimerLoop, TimerName, TimerToken,
+    PeriodicSchedule, ScheduleError as TimerScheduleError, ScheduledTimer, TimeoutHandler, TimerApi, TimerLoop,
+    TimerMonitor, TimerName, TimerToken,
 };
@@ -15,10 +15,11 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use parking_lot::{Condvar, Mutex, RwLock};
+use rand::Rng;
 use std::cmp::Reverse;
 use std::collections::binary_heap::BinaryHeap;
 use std::collections::hash_map::{Entry, HashMap};
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::string::ToString;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
@@ -29,6 +30,11 @@ pub type TimerName = &'static str;
 const TIMER_NAME_DEFAULT: TimerName = "UNNAMED_TIMER";
 pub type TimerToken = usize;
 
+/// Timers due within this window of each other fire in one wakeup of the scheduler.
+const DEFAULT_COALESCING_WINDOW: Duration = Duration::from_millis(2);
+/// The live timers are logged at this interval while there are any.
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
 pub trait TimeoutHandler: Send + Sync {
     fn on_timeout(&self, _token: TimerToken);
 }
@@ -45,7 +51,14 @@ pub struct TimerLoop {
 
 impl TimerLoop {
     pub fn new(worker_size: usize) -> TimerLoop {
-        let scheduler = Arc::new(Scheduler::new());
+        Self::with_coalescing_window(worker_size, DEFAULT_COALESCING_WINDOW)
+    }
+
+    /// Creates a loop that delays a timeout up to `coalescing_window`,
+    /// so that the timers due within the window fire in one wakeup instead of many.
+    /// A timer never fires before its scheduled time.
+    pub fn with_coalescing_window(worker_size: usize, coalescing_window: Duration) -> TimerLoop {
+        let scheduler = Arc::new(Scheduler::new(coalescing_window));
 
         let worker_queue = Arc::new(WorkerQueue::new());
         let worker_join_handles = spawn_workers(worker_size, &worker_queue);
@@ -84,6 +97,64 @@ impl TimerLoop {
         timer.set_name(name);
         timer
     }
+
+    pub fn monitor(&self) -> TimerMonitor {
+        TimerMonitor {
+            scheduler: Arc::downgrade(&self.scheduler),
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<ScheduledTimer> {
+        self.scheduler.snapshot()
+    }
+
+    pub fn periodic_schedules(&self) -> Vec<PeriodicSchedule> {
+        self.scheduler.periodic_schedules()
+    }
+}
+
+/// A timer that is scheduled and has neither fired nor been cancelled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduledTimer {
+    pub name: TimerName,
+    pub token: TimerToken,
+    pub at: Instant,
+    pub repeat: Option<Duration>,
+}
+
+/// A schedule registered by `TimerApi::schedule_periodic` and not cancelled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeriodicSchedule {
+    pub name: TimerName,
+    pub timer_name: TimerName,
+    pub token: TimerToken,
+    pub interval: Duration,
+    pub jitter: Duration,
+    /// The next firing, including the jitter.
+    pub at: Instant,
+    pub fired: usize,
+    /// The firings skipped because the previous callback was still running or the scheduler woke up too late.
+    pub missed: usize,
+}
+
+/// Inspects the live timers of a `TimerLoop` without keeping it alive.
+#[derive(Clone)]
+pub struct TimerMonitor {
+    scheduler: Weak<Scheduler>,
+}
+
+impl TimerMonitor {
+    /// Returns the live timers in the order they fire.
+    /// It is empty if the `TimerLoop` is dropped.
+    pub fn snapshot(&self) -> Vec<ScheduledTimer> {
+        self.scheduler.upgrade().map(|scheduler| scheduler.snapshot()).unwrap_or_default()
+    }
+
+    /// Returns the periodic schedules in the order they fire.
+    /// It is empty if the `TimerLoop` is dropped.
+    pub fn periodic_schedules(&self) -> Vec<PeriodicSchedule> {
+        self.scheduler.upgrade().map(|scheduler| scheduler.periodic_schedules()).unwrap_or_default()
+    }
 }
 
 impl Drop for TimerLoop {
@@ -113,6 +184,8 @@ pub struct TimerApi {
 pub enum ScheduleError {
     TokenAlreadyScheduled,
     TimerLoopDropped,
+    /// The interval of a periodic schedule is zero or not longer than its jitter.
+    InvalidPeriod,
 }
 
 impl TimerApi {
@@ -132,12 +205,36 @@ impl TimerApi {
 
     pub fn schedule_once(&self, after: Duration, timer_token: TimerToken) -> Result<(), ScheduleError> {
         let scheduler = self.scheduler.upgrade().ok_or(ScheduleError::TimerLoopDropped)?;
-        scheduler.schedule(self, timer_token, after, None)
+        scheduler.schedule(self, timer_token, after, None, None)
     }
 
     pub fn schedule_repeat(&self, after: Duration, timer_token: TimerToken) -> Result<(), ScheduleError> {
         let scheduler = self.scheduler.upgrade().ok_or(ScheduleError::TimerLoopDropped)?;
-        scheduler.schedule(self, timer_token, after, Some(after))
+        scheduler.schedule(self, timer_token, after, Some(after), None)
+    }
+
+    /// Fires every `interval` counted from now. The next firing is computed from the previous scheduled one,
+    /// so neither a late wakeup nor a slow callback pushes the following firings back.
+    /// Each firing is delayed by a random duration up to `jitter`,
+    /// so that the schedules sharing an interval don't fire all at once.
+    ///
+    /// `on_timeout` runs on the workers shared by all timers of the `TimerLoop`,
+    /// so the handler should hand the token over to its own thread and return.
+    /// The schedule skips a firing while its previous callback is still running,
+    /// so a slow handler occupies at most one worker.
+    pub fn schedule_periodic(
+        &self,
+        name: TimerName,
+        interval: Duration,
+        jitter: Duration,
+        timer_token: TimerToken,
+    ) -> Result<(), ScheduleError> {
+        if interval == Duration::default() || jitter >= interval {
+            return Err(ScheduleError::InvalidPeriod)
+        }
+        let scheduler = self.scheduler.upgrade().ok_or(ScheduleError::TimerLoopDropped)?;
+        let periodic = Periodic::new(name, interval, jitter, Instant::now());
+        scheduler.schedule(self, timer_token, interval, Some(interval), Some(periodic))
     }
 
     pub fn cancel(&self, timer_token: TimerToken) -> Result<bool, ScheduleError> {
@@ -153,9 +250,9 @@ struct Scheduler {
 }
 
 impl Scheduler {
-    fn new() -> Scheduler {
+    fn new(coalescing_window: Duration) -> Scheduler {
         Scheduler {
-            inner: Mutex::new(SchedulerInner::new()),
+            inner: Mutex::new(SchedulerInner::new(coalescing_window)),
             condvar: Condvar::new(),
         }
     }
@@ -166,9 +263,10 @@ impl Scheduler {
         timer_token: TimerToken,
         after: Duration,
         repeat: Option<Duration>,
+        periodic: Option<Periodic>,
     ) -> Result<(), ScheduleError> {
         let mut scheduler = self.inner.lock();
-        scheduler.schedule(requested_timer, timer_token, after, repeat)?;
+        scheduler.schedule(requested_timer, timer_token, after, repeat, periodic)?;
         self.condvar.notify_one();
         Ok(())
     }
@@ -186,13 +284,26 @@ impl Scheduler {
         self.condvar.notify_all();
     }
 
+    fn snapshot(&self) -> Vec<ScheduledTimer> {
+        self.inner.lock().snapshot()
+    }
+
+    fn periodic_schedules(&self) -> Vec<PeriodicSchedule> {
+        self.inner.lock().periodic_schedules()
+    }
+
     fn run(&self, worker_queue: &WorkerQueue) {
         let mut scheduler = self.inner.lock();
+        let mut last_report = Instant::now();
         while !scheduler.stop {
             let wait_for = scheduler.handle_timeout(worker_queue);
+            if last_report.elapsed() >= REPORT_INTERVAL {
+                scheduler.report();
+                last_report = Instant::now();
+            }
             match wait_for {
                 Some(timeout) => {
-                    self.condvar.wait_for(&mut scheduler, timeout);
+                    self.condvar.wait_for(&mut scheduler, timeout.min(REPORT_INTERVAL));
                 }
                 None => self.condvar.wait(&mut scheduler),
             }
@@ -202,6 +313,8 @@ impl Scheduler {
     }
 }
 
+/// 'self.states' is also the registry of the live timers. See 'SchedulerInner::snapshot'.
+///
 /// Def 1. A 'state_control' for a 'schedule' that contained in 'self.heap' have two implicit states.
 ///     Attached: It is also contained in 'self.states'.
 ///     Detached: It is not contained in 'self.states'.
@@ -233,17 +346,31 @@ impl Scheduler {
 /// Note 2. Timeout, Cancelled states never revive. (to ease the complexity)
 
 struct SchedulerInner {
-    states: HashMap<ScheduleId, Arc<ScheduleStateControl>>,
+    states: HashMap<ScheduleId, Attached>,
     heap: BinaryHeap<Reverse<TimeOrdered<Schedule>>>,
     stop: bool,
+    coalescing_window: Duration,
+    /// The number of wakeups that fired at least one schedule.
+    wakeups: usize,
+}
+
+/// The 'state_control' attached to a ScheduleId and what is known about its schedule.
+struct Attached {
+    state_control: Arc<ScheduleStateControl>,
+    timer_name: TimerName,
+    at: Instant,
+    repeat: Option<Duration>,
+    periodic: Option<Periodic>,
 }
 
 impl SchedulerInner {
-    fn new() -> SchedulerInner {
+    fn new(coalescing_window: Duration) -> SchedulerInner {
         SchedulerInner {
             states: HashMap::new(),
             heap: BinaryHeap::new(),
             stop: false,
+            coalescing_window,
+            wakeups: 0,
         }
     }
 
@@ -253,6 +380,7 @@ impl SchedulerInner {
         timer_token: TimerToken,
         after: Duration,
         repeat: Option<Duration>,
+        periodic: Option<Periodic>,
     ) -> Result<(), ScheduleError> {
         if self.stop {
             cdebug!(TIMER, "schedule: TimerLoop has been finished");
@@ -266,26 +394,42 @@ impl SchedulerInner {
             Weak::clone(handler)
         };
 
+        let timer_name = requested_timer.timer_name.read().unwrap_or(TIMER_NAME_DEFAULT);
+        let at = match &periodic {
+            Some(periodic) => periodic.at,
+            None => Instant::now() + after,
+        };
         let state_control = match self.states.entry(schedule_id) {
             Entry::Vacant(entry) => {
                 // unique one(Rule 2). it is going to be attached.
                 let state_control = Arc::new(ScheduleStateControl::new_auto(repeat));
-                entry.insert(Arc::clone(&state_control));
+                entry.insert(Attached {
+                    state_control: Arc::clone(&state_control),
+                    timer_name,
+                    at,
+                    repeat,
+                    periodic: periodic.clone(),
+                });
                 state_control
             }
             Entry::Occupied(mut entry) => {
-                if !entry.get().is_cancelled() {
+                if !entry.get().state_control.is_cancelled() {
                     // Prevents violation of Rule 1. We can't detach it.
                     return Err(ScheduleError::TokenAlreadyScheduled)
                 }
                 // Detach the entry (Corollary 1) before it become garbage (Note 1),
                 // create a unique one (Rule 2). it is going to be attached.
                 let state_control = Arc::new(ScheduleStateControl::new_auto(repeat));
-                *entry.get_mut() = Arc::clone(&state_control);
+                *entry.get_mut() = Attached {
+                    state_control: Arc::clone(&state_control),
+                    timer_name,
+                    at,
+                    repeat,
+                    periodic: periodic.clone(),
+                };
                 state_control
             }
         };
-        let timer_name = requested_timer.timer_name.read().unwrap_or(TIMER_NAME_DEFAULT);
 
         ctrace!(
             TIMER,
@@ -297,12 +441,13 @@ impl SchedulerInner {
         );
 
         let schedule = Schedule {
-            at: Instant::now() + after,
+            at,
             schedule_id,
             repeat,
             state_control,
             handler,
             timer_name,
+            periodic,
         };
         // state_control become an attached one (Def 1)
         self.heap.push(Reverse(TimeOrdered(schedule)));
@@ -310,7 +455,11 @@ impl SchedulerInner {
     }
 
     fn try_reschedule(&mut self, mut schedule: Schedule) -> bool {
-        schedule.at = Instant::now() + schedule.repeat.expect("Schedule should have repeat interval");
+        let now = Instant::now();
+        schedule.at = match &mut schedule.periodic {
+            Some(periodic) => periodic.advance(now),
+            None => now + schedule.repeat.expect("Schedule should have repeat interval"),
+        };
         match self.states.entry(schedule.schedule_id) {
             Entry::Vacant(_) => {
                 // 'schedule.state_control' was detached one (Def 1).
@@ -318,13 +467,15 @@ impl SchedulerInner {
                 // schedule is going to be removed
                 false
             }
-            Entry::Occupied(entry) => {
-                if Arc::ptr_eq(entry.get(), &schedule.state_control) {
+            Entry::Occupied(mut entry) => {
+                if Arc::ptr_eq(&entry.get().state_control, &schedule.state_control) {
                     // schedule.state_control was attached one, (Def 1)
                     // just re-push to heap.
+                    entry.get_mut().at = schedule.at;
+                    entry.get_mut().periodic = schedule.periodic.clone();
                     self.heap.push(Reverse(TimeOrdered(schedule)));
                     true
-                } else if entry.get().is_cancelled() {
+                } else if entry.get().state_control.is_cancelled() {
                     // Detach the entry (Corollary 1) before it become garbage (Note 1),
                     entry.remove();
                     // 'schedule.state_control' was detached one (Def 1).
@@ -347,12 +498,78 @@ impl SchedulerInner {
                 // Detach and cancel it (Rule 1)
                 let timer_name = requested_timer.timer_name.read().unwrap_or(TIMER_NAME_DEFAULT);
                 ctrace!(TIMER, "cancel(TimerName({}), {:?})", timer_name, schedule_id);
-                let state_control = entry.remove();
-                state_control.cancel()
+                let attached = entry.remove();
+                attached.state_control.cancel()
             }
         }
     }
 
+    /// Detaches and cancels the schedule if it is the attached one (Rule 1).
+    fn detach(&mut self, schedule: &Schedule) {
+        if let Entry::Occupied(entry) = self.states.entry(schedule.schedule_id) {
+            if Arc::ptr_eq(&entry.get().state_control, &schedule.state_control) {
+                entry.remove();
+                schedule.state_control.cancel();
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Vec<ScheduledTimer> {
+        let mut timers: Vec<_> = self
+            .states
+            .iter()
+            .map(|(ScheduleId(_, token), attached)| ScheduledTimer {
+                name: attached.timer_name,
+                token: *token,
+                at: attached.at,
+                repeat: attached.repeat,
+            })
+            .collect();
+        timers.sort_by_key(|timer| timer.at);
+        timers
+    }
+
+    fn periodic_schedules(&self) -> Vec<PeriodicSchedule> {
+        let mut schedules: Vec<_> = self
+            .states
+            .iter()
+            .filter_map(|(ScheduleId(_, token), attached)| {
+                let periodic = attached.periodic.as_ref()?;
+                Some(PeriodicSchedule {
+                    name: periodic.name,
+                    timer_name: attached.timer_name,
+                    token: *token,
+                    interval: periodic.interval,
+                    jitter: periodic.jitter,
+                    at: attached.at,
+                    fired: periodic.stats.fired.load(Ordering::SeqCst),
+                    missed: periodic.stats.missed.load(Ordering::SeqCst),
+                })
+            })
+            .collect();
+        schedules.sort_by_key(|schedule| schedule.at);
+        schedules
+    }
+
+    fn report(&self) {
+        if self.states.is_empty() {
+            return
+        }
+        let mut counts: BTreeMap<TimerName, usize> = BTreeMap::new();
+        for attached in self.states.values() {
+            *counts.entry(attached.timer_name).or_default() += 1;
+        }
+        let counts: Vec<_> = counts.iter().map(|(name, count)| format!("{}: {}", name, count)).collect();
+        cdebug!(
+            TIMER,
+            "{} live timers ({}), {} schedules in the heap, {} wakeups",
+            self.states.len(),
+            counts.join(", "),
+            self.heap.len(),
+            self.wakeups
+        );
+    }
+
     fn clear_and_stop(&mut self) {
         self.states.clear();
         for Reverse(TimeOrdered(schedule)) in self.heap.drain() {
@@ -362,14 +579,22 @@ impl SchedulerInner {
     }
 
     fn handle_timeout(&mut self, worker_queue: &WorkerQueue) -> Option<Duration> {
+        let mut fired = false;
         loop {
             let now = Instant::now();
             match self.heap.peek() {
                 None => return None,
-                Some(Reverse(TimeOrdered(earliest))) if now < earliest.at => return Some(earliest.at - now),
+                Some(Reverse(TimeOrdered(earliest))) if now < earliest.at => {
+                    // Wait a little longer so the schedules due shortly after it fire in the same wakeup.
+                    return Some(earliest.at - now + self.coalescing_window)
+                }
                 _ => { /* lifetime prevents modifying heap from here. */ }
             }
             let Reverse(TimeOrdered(timed_out)) = self.heap.pop().expect("It always have an item");
+            if !fired {
+                fired = true;
+                self.wakeups += 1;
+            }
 
             let timer_name = timed_out.timer_name;
             let schedule_id = timed_out.schedule_id;
@@ -377,7 +602,14 @@ impl SchedulerInner {
             if timed_out.repeat.is_some() {
                 if let Some(callback) = Callback::from_schedule(&timed_out) {
                     // timed_out.state_control is re-pushed only after it is popped (Rule 3)
-                    if self.try_reschedule(timed_out.clone()) {
+                    if !self.try_reschedule(timed_out.clone()) {
+                        ctrace!(
+                            TIMER,
+                            "handle_timeout(TimerName({}), {:?}, repeat): Cancelled schedule",
+                            timer_name,
+                            schedule_id
+                        );
+                    } else if timed_out.periodic.as_ref().map_or(true, Periodic::try_start) {
                         ctrace!(
                             TIMER,
                             "handle_timeout(TimerName({}), {:?}, repeat): Enqueue to worker queue",
@@ -388,12 +620,14 @@ impl SchedulerInner {
                     } else {
                         ctrace!(
                             TIMER,
-                            "handle_timeout(TimerName({}), {:?}, repeat): Cancelled schedule",
+                            "handle_timeout(TimerName({}), {:?}, periodic): The previous callback is still running",
                             timer_name,
                             schedule_id
                         );
                     }
                 } else {
+                    // It will never fire again, so remove it from the registry.
+                    self.detach(&timed_out);
                     ctrace!(
                         TIMER,
                         "handle_timeout(TimerName({}), {:?}, repeat): Dropped TimeoutHandler",
@@ -404,7 +638,7 @@ impl SchedulerInner {
             } else {
                 let enqueue = match self.states.entry(timed_out.schedule_id) {
                     Entry::Occupied(entry) => {
-                        if Arc::ptr_eq(entry.get(), &timed_out.state_control) {
+                        if Arc::ptr_eq(&entry.get().state_control, &timed_out.state_control) {
                             // 'timed_out.state_control' was attached one. (Def 1)
                             entry.remove();
                             !timed_out.state_control.is_cancelled()
@@ -534,6 +768,73 @@ struct Schedule {
     state_control: Arc<ScheduleStateControl>,
     handler: Weak<dyn TimeoutHandler>,
     timer_name: TimerName,
+    periodic: Option<Periodic>,
+}
+
+/// The points of time a periodic schedule fires at.
+/// The n-th firing is due at `interval * n` after it is scheduled, plus a jitter drawn for each firing.
+#[derive(Clone)]
+struct Periodic {
+    name: TimerName,
+    interval: Duration,
+    jitter: Duration,
+    /// The current firing without the jitter.
+    nominal: Instant,
+    /// The current firing.
+    at: Instant,
+    stats: Arc<PeriodicStats>,
+}
+
+#[derive(Default)]
+struct PeriodicStats {
+    /// Whether a callback of the schedule is queued or running.
+    busy: AtomicBool,
+    fired: AtomicUsize,
+    missed: AtomicUsize,
+}
+
+impl Periodic {
+    fn new(name: TimerName, interval: Duration, jitter: Duration, now: Instant) -> Periodic {
+        let nominal = now + interval;
+        Periodic {
+            name,
+            interval,
+            jitter,
+            nominal,
+            at: nominal + random_jitter(jitter),
+            stats: Default::default(),
+        }
+    }
+
+    /// Moves to the next firing and returns when it is due.
+    fn advance(&mut self, now: Instant) -> Instant {
+        self.nominal += self.interval;
+        if self.nominal < now {
+            // The scheduler woke up too late. Skip the firings that already passed instead of catching up.
+            let missed = ((now - self.nominal).as_nanos() / self.interval.as_nanos()) as u32 + 1;
+            self.nominal += self.interval * missed;
+            self.stats.missed.fetch_add(missed as usize, Ordering::SeqCst);
+        }
+        self.at = self.nominal + random_jitter(self.jitter);
+        self.at
+    }
+
+    /// Returns false if the previous callback has not finished yet.
+    fn try_start(&self) -> bool {
+        if self.stats.busy.swap(true, Ordering::SeqCst) {
+            self.stats.missed.fetch_add(1, Ordering::SeqCst);
+            return false
+        }
+        self.stats.fired.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+}
+
+fn random_jitter(jitter: Duration) -> Duration {
+    if jitter == Duration::default() {
+        return jitter
+    }
+    Duration::from_nanos(rand::thread_rng().gen_range(0, jitter.as_nanos() as u64 + 1))
 }
 
 struct TimeOrdered<T>(T);
@@ -570,6 +871,7 @@ struct Callback {
     state_control: Arc<ScheduleStateControl>,
     handler: Arc<dyn TimeoutHandler>,
     timer_name: TimerName,
+    periodic: Option<Arc<PeriodicStats>>,
 }
 
 impl Callback {
@@ -581,6 +883,7 @@ impl Callback {
                 state_control: Arc::clone(&schedule.state_control),
                 handler,
                 timer_name: schedule.timer_name,
+                periodic: schedule.periodic.as_ref().map(|periodic| Arc::clone(&periodic.stats)),
             })
         } else {
             None
@@ -625,6 +928,9 @@ fn worker_loop(queue: &Arc<WorkerQueue>) {
                 );
             }
         });
+        if let Some(stats) = &callback.periodic {
+            stats.busy.store(false, Ordering::SeqCst);
+        }
     }
     ctrace!(TIMER, "Worker loop has been stopped");
 }
@@ -968,4 +1274,238 @@ mod tests {
 
         assert_eq!(timer.schedule_once(tick(), timer_token), Err(ScheduleError::TimerLoopDropped));
     }
+
+    #[test]
+    fn test_snapshot() {
+        let timer_loop = TimerLoop::new(1);
+        let handler = Arc::new(CallbackHandler(|_| {}));
+        let timer = new_timer(&timer_loop, "test", Arc::downgrade(&handler));
+
+        let begin = Instant::now();
+        timer.schedule_repeat(long_tick(), 2).unwrap();
+        timer.schedule_once(tick(), 1).unwrap();
+
+        let snapshot = timer_loop.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!((snapshot[0].name, snapshot[0].token, snapshot[0].repeat), ("test", 1, None));
+        assert_eq!((snapshot[1].name, snapshot[1].token, snapshot[1].repeat), ("test", 2, Some(long_tick())));
+        assert!(snapshot[0].at >= begin + tick());
+        assert_eq!(timer_loop.monitor().snapshot(), snapshot);
+    }
+
+    #[test]
+    fn test_registry_is_empty_after_cancel() {
+        const TIMER_COUNT: usize = 1000;
+        let timer_loop = TimerLoop::new(1);
+        let handler = Arc::new(CallbackHandler(|_| {}));
+        let timer = new_timer(&timer_loop, "test", Arc::downgrade(&handler));
+
+        for token in 0..TIMER_COUNT {
+            if token % 2 == 0 {
+                timer.schedule_once(tick(), token).unwrap();
+            } else {
+                timer.schedule_repeat(tick(), token).unwrap();
+            }
+        }
+        assert_eq!(timer_loop.snapshot().len(), TIMER_COUNT);
+
+        for token in 0..TIMER_COUNT {
+            assert_eq!(timer.cancel(token), Ok(true));
+        }
+        assert_eq!(timer_loop.snapshot(), vec![]);
+    }
+
+    #[test]
+    fn test_registry_is_empty_after_timeout() {
+        const TIMER_COUNT: usize = 100;
+        let timer_loop = TimerLoop::new(1);
+        let handler = Arc::new(CallbackHandler(|_| {}));
+        let timer = new_timer(&timer_loop, "test", Arc::downgrade(&handler));
+
+        for token in 0..TIMER_COUNT {
+            timer.schedule_once(tick(), token).unwrap();
+        }
+        assert_eq!(timer_loop.snapshot().len(), TIMER_COUNT);
+
+        thread::sleep(long_tick());
+        assert_eq!(timer_loop.snapshot(), vec![]);
+    }
+
+    #[test]
+    fn test_registry_drops_repeat_of_dropped_handler() {
+        let timer_loop = TimerLoop::new(1);
+        let handler = Arc::new(CallbackHandler(|_| {}));
+        let timer = new_timer(&timer_loop, "test", Arc::downgrade(&handler));
+
+        timer.schedule_repeat(tick(), 100).unwrap();
+        drop(handler);
+
+        thread::sleep(long_tick());
+        assert_eq!(timer_loop.snapshot(), vec![]);
+    }
+
+    #[test]
+    fn test_monitor_after_timerloop_drop() {
+        let timer_loop = TimerLoop::new(1);
+        let handler = Arc::new(CallbackHandler(|_| {}));
+        let timer = new_timer(&timer_loop, "test", Arc::downgrade(&handler));
+        timer.schedule_once(tick(), 100).unwrap();
+
+        let monitor = timer_loop.monitor();
+        assert_eq!(monitor.snapshot().len(), 1);
+        drop(timer_loop);
+        assert_eq!(monitor.snapshot(), vec![]);
+    }
+
+    #[test]
+    fn test_coalesce() {
+        const TIMER_COUNT: usize = 10;
+        let coalescing_window = Duration::from_millis(100);
+        let timer_loop = TimerLoop::with_coalescing_window(1, coalescing_window);
+        let pair = Arc::new((Condvar::new(), Mutex::new(vec![])));
+        let handler = {
+            let pair = Arc::clone(&pair);
+            Arc::new(CallbackHandler(move |token| {
+                let (ref condvar, ref mutex) = *pair;
+                let mut value = mutex.lock();
+                value.push((Instant::now(), token));
+                condvar.notify_all();
+            }))
+        };
+        let timer = new_timer(&timer_loop, "test", Arc::downgrade(&handler));
+
+        let begin = Instant::now();
+        for token in 0..TIMER_COUNT {
+            // Due within 10 ms of each other.
+            timer.schedule_once(tick() + Duration::from_millis(token as u64), token).unwrap();
+        }
+
+        let (ref condvar, ref mutex) = *pair;
+        let mut value = mutex.lock();
+        while value.len() < TIMER_COUNT {
+            assert!(!condvar.wait_for(&mut value, long_tick()).timed_out(), "Only {} timers fired", value.len());
+        }
+        let mut tokens: Vec<_> = value.iter().map(|(_, token)| *token).collect();
+        tokens.sort();
+        assert_eq!(tokens, (0..TIMER_COUNT).collect::<Vec<_>>());
+        for (called_at, token) in value.iter() {
+            let at = begin + tick() + Duration::from_millis(*token as u64);
+            assert!(*called_at >= at, "{:?} >= {:?}", called_at, at);
+        }
+        assert_eq!(timer_loop.scheduler.inner.lock().wakeups, 1);
+        assert_eq!(timer_loop.snapshot(), vec![]);
+    }
+
+    #[test]
+    fn test_periodic_does_not_drift() {
+        let begin = Instant::now();
+        let mut periodic = Periodic::new("test", tick(), Duration::default(), begin);
+        assert_eq!(periodic.at, begin + tick());
+        for n in 2..100u32 {
+            // Every callback finishes 300 ms after it is due.
+            let now = periodic.at + Duration::from_millis(300);
+            assert_eq!(periodic.advance(now), begin + tick() * n);
+        }
+        assert_eq!(periodic.stats.missed.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_periodic_skips_passed_firings() {
+        let begin = Instant::now();
+        let mut periodic = Periodic::new("test", tick(), Duration::default(), begin);
+        // The scheduler wakes up 3.5 ticks after the first firing is due.
+        let now = periodic.at + tick() * 7 / 2;
+        assert_eq!(periodic.advance(now), begin + tick() * 5);
+        assert_eq!(periodic.stats.missed.load(Ordering::SeqCst), 3);
+        assert_eq!(periodic.advance(periodic.at), begin + tick() * 6);
+    }
+
+    #[test]
+    fn test_periodic_jitter_bounds() {
+        let jitter = Duration::from_millis(100);
+        let begin = Instant::now();
+        let mut periodic = Periodic::new("test", tick(), jitter, begin);
+        let mut offsets = vec![periodic.at - (begin + tick())];
+        for n in 2..1000u32 {
+            let at = periodic.advance(periodic.at);
+            let nominal = begin + tick() * n;
+            assert!(nominal <= at && at <= nominal + jitter, "{:?} <= {:?} <= {:?}", nominal, at, nominal + jitter);
+            offsets.push(at - nominal);
+        }
+        offsets.sort();
+        assert_ne!(offsets.first(), offsets.last(), "The jitter is always {:?}", offsets[0]);
+        assert_eq!(periodic.stats.missed.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_invalid_period() {
+        let timer_loop = TimerLoop::new(1);
+        let handler = Arc::new(CallbackHandler(|_| {}));
+        let timer = new_timer(&timer_loop, "test", Arc::downgrade(&handler));
+
+        assert_eq!(
+            timer.schedule_periodic("zero", Duration::default(), Duration::default(), 1),
+            Err(ScheduleError::InvalidPeriod)
+        );
+        assert_eq!(timer.schedule_periodic("jitter", tick(), tick(), 1), Err(ScheduleError::InvalidPeriod));
+        assert_eq!(timer_loop.periodic_schedules(), vec![]);
+    }
+
+    #[test]
+    fn test_periodic_schedules() {
+        let timer_loop = TimerLoop::new(1);
+        let handler = Arc::new(CallbackHandler(|_| {}));
+        let timer = new_timer(&timer_loop, "test", Arc::downgrade(&handler));
+
+        let begin = Instant::now();
+        let jitter = Duration::from_millis(100);
+        timer.schedule_once(tick(), 1).unwrap();
+        timer.schedule_periodic("long", long_tick(), jitter, 2).unwrap();
+        timer.schedule_periodic("short", tick(), Duration::default(), 3).unwrap();
+
+        let schedules = timer_loop.periodic_schedules();
+        assert_eq!(schedules.len(), 2);
+        assert_eq!((schedules[0].name, schedules[0].timer_name, schedules[0].token), ("short", "test", 3));
+        assert_eq!((schedules[0].interval, schedules[0].jitter), (tick(), Duration::default()));
+        assert_eq!((schedules[1].name, schedules[1].timer_name, schedules[1].token), ("long", "test", 2));
+        assert_eq!((schedules[1].interval, schedules[1].jitter), (long_tick(), jitter));
+        assert!(schedules[1].at >= begin + long_tick());
+        assert_eq!(timer_loop.snapshot().len(), 3);
+        assert_eq!(timer_loop.monitor().periodic_schedules(), schedules);
+
+        assert_eq!(timer.cancel(3), Ok(true));
+        assert_eq!(timer_loop.periodic_schedules().len(), 1);
+    }
+
+    #[test]
+    fn test_periodic_skips_while_callback_runs() {
+        let interval = Duration::from_millis(100);
+        let timer_loop = TimerLoop::new(2);
+        let running = Arc::new(AtomicBool::new(false));
+        let overlapped = Arc::new(AtomicBool::new(false));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler = {
+            let running = Arc::clone(&running);
+            let overlapped = Arc::clone(&overlapped);
+            let calls = Arc::clone(&calls);
+            Arc::new(CallbackHandler(move |_| {
+                if running.swap(true, Ordering::SeqCst) {
+                    overlapped.store(true, Ordering::SeqCst);
+                }
+                calls.fetch_add(1, Ordering::SeqCst);
+                thread::sleep(interval * 5 / 2);
+                running.store(false, Ordering::SeqCst);
+            }))
+        };
+        let timer = new_timer(&timer_loop, "test", Arc::downgrade(&handler));
+
+        timer.schedule_periodic("slow", interval, Duration::default(), 1).unwrap();
+        thread::sleep(interval * 10);
+        assert_eq!(timer.cancel(1), Ok(true));
+
+        assert!(!overlapped.load(Ordering::SeqCst), "Callbacks of a schedule overlapped");
+        let calls = calls.load(Ordering::SeqCst);
+        assert!(2 <= calls && calls <= 4, "{} calls", calls);
+        assert!(timer_loop.scheduler.inner.lock().wakeups > calls);
+    }
 }
@@ -23,6 +23,8 @@ use primitives::{H160, H256};
 use crate::instruction::{has_expensive_opcodes, is_valid_unlock_script, Instruction};
 
 const DEFAULT_MAX_MEMORY: usize = 1024;
+/// The maximum n of an m-of-n CHKMULTISIG, once the chain caps it.
+pub const MAX_MULTISIG_PUBKEYS: usize = 16;
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(u8)]
@@ -257,8 +259,12 @@ where
                 stack.push(Item(vec![result]))?;
             }
             Instruction::ChkMultiSig => {
-                // Get n pubkey. If there are more than six pubkeys, return error.
+                // Get n pubkey. If there are more than MAX_MULTISIG_PUBKEYS pubkeys after the cap is activated,
+                // return error.
                 let n = stack.pop()?.assert_len(1)?.as_ref()[0] as usize;
+                if n > MAX_MULTISIG_PUBKEYS && client.caps_multisig_pubkeys(parent_block_number) {
+                    return Err(RuntimeError::InvalidSigCount)
+                }
 
                 let mut pubkey: Vec<Public> = Vec::with_capacity(n);
                 for _ in 0..n {
@@ -375,6 +381,9 @@ pub trait ChainTimeInfo {
 
     /// Get the how many seconds elapsed since transaction is confirmed, according to block timestamp.
     fn transaction_time_age(&self, tracker: &Tracker, parent_timestamp: u64) -> Option<u64>;
+
+    /// Whether CHKMULTISIG rejects more than `MAX_MULTISIG_PUBKEYS` public keys in the child of the parent block.
+    fn caps_multisig_pubkeys(&self, parent_block_number: BlockNumber) -> bool;
 }
 
 #[cfg(test)]
@@ -29,5 +29,7 @@ mod instruction;
 mod opcode;
 
 pub use crate::decoder::{decode, DecoderError};
-pub use crate::executor::{execute, ChainTimeInfo, Config as VMConfig, RuntimeError, ScriptResult, TimelockType};
+pub use crate::executor::{
+    execute, ChainTimeInfo, Config as VMConfig, RuntimeError, ScriptResult, TimelockType, MAX_MULTISIG_PUBKEYS,
+};
 pub use crate::instruction::Instruction;
@@ -748,3 +748,118 @@ fn invalid_multi_sig_with_too_many_arg() {
         Err(RuntimeError::InvalidSigCount)
     );
 }
+
+#[test]
+fn invalid_multi_sig_2_of_3_wrong_order() {
+    let client = TestClient::default();
+    let transaction = ShardTransaction::TransferAsset {
+        network_id: NetworkId::default(),
+        burns: Vec::new(),
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+    };
+    let outpoint = AssetTransferInput {
+        prev_out: AssetOutPoint {
+            tracker: Default::default(),
+            index: 0,
+            asset_type: H160::default(),
+            shard_id: 0,
+            quantity: 0,
+        },
+        timelock: None,
+        lock_script: Vec::new(),
+        unlock_script: Vec::new(),
+    };
+    let keypair1 = KeyPair::from_private(Private::from(ONE_KEY)).unwrap();
+    let keypair2 = KeyPair::from_private(Private::from(MINUS_ONE_KEY)).unwrap();
+    let keypair3 = KeyPair::from_private(Private::from(TWO_KEY)).unwrap();
+    let pubkey1 = <&[u8]>::from(keypair1.public()).to_vec();
+    let pubkey2 = <&[u8]>::from(keypair2.public()).to_vec();
+    let pubkey3 = <&[u8]>::from(keypair3.public()).to_vec();
+    let message = blake256_with_key(
+        &ShardTransaction::TransferAsset {
+            network_id: NetworkId::default(),
+            burns: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+        .rlp_bytes(),
+        &blake128(&[0b11 as u8]),
+    );
+    let signature1 = sign(keypair1.private(), &message).unwrap().to_vec();
+    let signature2 = sign(keypair2.private(), &message).unwrap().to_vec();
+
+    let unlock_script =
+        vec![Instruction::PushB(vec![0b11 as u8]), Instruction::PushB(signature2), Instruction::PushB(signature1)];
+    let lock_script = vec![
+        Instruction::PushB(vec![2]),
+        Instruction::PushB(pubkey1),
+        Instruction::PushB(pubkey2),
+        Instruction::PushB(pubkey3),
+        Instruction::PushB(vec![3]),
+        Instruction::ChkMultiSig,
+    ];
+
+    assert_eq!(
+        execute(&unlock_script, &[], &lock_script, &transaction, VMConfig::default(), &outpoint, false, &client, 0, 0),
+        Ok(ScriptResult::Fail)
+    );
+}
+
+#[test]
+fn too_many_pubkeys_are_rejected_once_capped() {
+    let client = TestClient::capping_multisig_pubkeys();
+    let transaction = ShardTransaction::TransferAsset {
+        network_id: NetworkId::default(),
+        burns: Vec::new(),
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+    };
+    let outpoint = AssetTransferInput {
+        prev_out: AssetOutPoint {
+            tracker: Default::default(),
+            index: 0,
+            asset_type: H160::default(),
+            shard_id: 0,
+            quantity: 0,
+        },
+        timelock: None,
+        lock_script: Vec::new(),
+        unlock_script: Vec::new(),
+    };
+    let keypair1 = KeyPair::from_private(Private::from(ONE_KEY)).unwrap();
+    let pubkey1 = <&[u8]>::from(keypair1.public()).to_vec();
+    let message = blake256_with_key(
+        &ShardTransaction::TransferAsset {
+            network_id: NetworkId::default(),
+            burns: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+        .rlp_bytes(),
+        &blake128(&[0b11 as u8]),
+    );
+    let signature1 = sign(keypair1.private(), &message).unwrap().to_vec();
+
+    let unlock_script = vec![Instruction::PushB(vec![0b11 as u8]), Instruction::PushB(signature1)];
+    let lock_script = vec![
+        Instruction::PushB(vec![1]),
+        Instruction::PushB(pubkey1.clone()),
+        Instruction::PushB(pubkey1.clone()),
+        Instruction::PushB(pubkey1),
+        Instruction::PushB(vec![17]),
+        Instruction::ChkMultiSig,
+    ];
+
+    assert_eq!(
+        execute(&unlock_script, &[], &lock_script, &transaction, VMConfig::default(), &outpoint, false, &client, 0, 0),
+        Err(RuntimeError::InvalidSigCount)
+    );
+
+    // Before the cap, the 17 public keys are popped, and m is popped as a public key.
+    let client = TestClient::default();
+    assert_eq!(
+        execute(&unlock_script, &[], &lock_script, &transaction, VMConfig::default(), &outpoint, false, &client, 0, 0),
+        Err(RuntimeError::TypeMismatch)
+    );
+}
@@ -20,6 +20,7 @@ use cvm::ChainTimeInfo;
 pub struct TestClient {
     block_age: Option<u64>,
     time_age: Option<u64>,
+    caps_multisig_pubkeys: bool,
 }
 
 impl TestClient {
@@ -27,6 +28,15 @@ impl TestClient {
         TestClient {
             block_age,
             time_age,
+            caps_multisig_pubkeys: false,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn capping_multisig_pubkeys() -> Self {
+        TestClient {
+            caps_multisig_pubkeys: true,
+            ..Self::default()
         }
     }
 }
@@ -45,4 +55,8 @@ impl ChainTimeInfo for TestClient {
     fn transaction_time_age(&self, _: &Tracker, _parent_timestamp: u64) -> Option<u64> {
         self.time_age
     }
+
+    fn caps_multisig_pubkeys(&self, _parent_block_number: BlockNumber) -> bool {
+        self.caps_multisig_pubkeys
+    }
 }